@@ -9,14 +9,15 @@ use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criteri
 use std::collections::HashMap;
 
 // Import from the search crate
-use search::{generate_trigrams, tokenize, ContentIndex, Posting, TrigramIndex};
+use search::corpus::{generate_corpus, CorpusLanguage, CorpusSpec};
+use search::{generate_trigrams, tokenize, ContentIndex, Posting, SkippedFiles, Token, TokenizerSettings, TrigramIndex};
 
 // ─── Helpers ─────────────────────────────────────────────────────────
 
 /// Build a synthetic ContentIndex with N files, each containing a set of tokens.
 fn build_synthetic_index(num_files: usize, tokens_per_file: usize) -> ContentIndex {
     let mut files = Vec::with_capacity(num_files);
-    let mut index: HashMap<String, Vec<Posting>> = HashMap::new();
+    let mut index: HashMap<Token, Vec<Posting>> = HashMap::new();
     let mut file_token_counts = Vec::with_capacity(num_files);
     let mut total_tokens: u64 = 0;
 
@@ -31,7 +32,7 @@ fn build_synthetic_index(num_files: usize, tokens_per_file: usize) -> ContentInd
             let line = (t + 1) as u32;
 
             index
-                .entry(token)
+                .entry(Token::from(token))
                 .or_default()
                 .push(Posting {
                     file_id: file_id as u32,
@@ -45,7 +46,7 @@ fn build_synthetic_index(num_files: usize, tokens_per_file: usize) -> ContentInd
             total_tokens += 1;
             count += 1;
             index
-                .entry(token)
+                .entry(Token::from(token))
                 .or_default()
                 .push(Posting {
                     file_id: file_id as u32,
@@ -59,7 +60,7 @@ fn build_synthetic_index(num_files: usize, tokens_per_file: usize) -> ContentInd
             total_tokens += 1;
             count += 1;
             index
-                .entry(token)
+                .entry(Token::from(token))
                 .or_default()
                 .push(Posting {
                     file_id: file_id as u32,
@@ -83,6 +84,15 @@ fn build_synthetic_index(num_files: usize, tokens_per_file: usize) -> ContentInd
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        ticket_refs: Vec::new(),
+        revision: None,
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     }
 }
 
@@ -289,7 +299,7 @@ fn bench_regex_scan(c: &mut Criterion) {
             |b, index| {
                 let re = regex::Regex::new("(?i)^token_4.*$").unwrap();
                 b.iter(|| {
-                    let matches: Vec<&String> =
+                    let matches: Vec<&Token> =
                         index.index.keys().filter(|k| re.is_match(k)).collect();
                     black_box(matches);
                 })
@@ -302,7 +312,7 @@ fn bench_regex_scan(c: &mut Criterion) {
             |b, index| {
                 let re = regex::Regex::new("(?i)^class$").unwrap();
                 b.iter(|| {
-                    let matches: Vec<&String> =
+                    let matches: Vec<&Token> =
                         index.index.keys().filter(|k| re.is_match(k)).collect();
                     black_box(matches);
                 })
@@ -334,6 +344,50 @@ fn bench_index_build(c: &mut Criterion) {
     group.finish();
 }
 
+// ─── Generated Corpus Benchmarks ─────────────────────────────────────
+
+/// Read and tokenize every file of a `gen-corpus` codebase into an inverted index:
+/// the per-file work of a content index build, on realistic token frequencies.
+fn bench_corpus_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("corpus_index");
+    group.sample_size(10);
+
+    for &num_files in &[200, 2_000] {
+        let tmp = tempfile::tempdir().unwrap();
+        let spec = CorpusSpec {
+            files: num_files,
+            languages: vec![CorpusLanguage::CSharp, CorpusLanguage::TypeScript],
+            ..CorpusSpec::default()
+        };
+        generate_corpus(tmp.path(), &spec).unwrap();
+        let paths: Vec<std::path::PathBuf> = std::fs::read_dir(tmp.path()).unwrap()
+            .flat_map(|module| std::fs::read_dir(module.unwrap().path()).unwrap())
+            .map(|file| file.unwrap().path())
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("tokenize_files", num_files), &paths, |b, paths| {
+            b.iter(|| {
+                let mut index: HashMap<String, Vec<Posting>> = HashMap::new();
+                for (file_id, path) in paths.iter().enumerate() {
+                    let text = std::fs::read_to_string(path).unwrap();
+                    for (line_no, line) in text.lines().enumerate() {
+                        for token in tokenize(line, 2) {
+                            let postings = index.entry(token).or_default();
+                            match postings.last_mut() {
+                                Some(p) if p.file_id == file_id as u32 => p.lines.push(line_no as u32 + 1),
+                                _ => postings.push(Posting { file_id: file_id as u32, lines: vec![line_no as u32 + 1] }),
+                            }
+                        }
+                    }
+                }
+                black_box(index.len());
+            })
+        });
+    }
+
+    group.finish();
+}
+
 // ─── Serialization Benchmarks ────────────────────────────────────────
 
 fn bench_serialization(c: &mut Criterion) {
@@ -372,8 +426,8 @@ fn bench_serialization(c: &mut Criterion) {
 // ─── Trigram / Substring Benchmarks ─────────────────────────────────
 
 /// Build a TrigramIndex from an inverted index (mirrors build_trigram_index in index.rs)
-fn build_trigram_for_bench(inverted: &HashMap<String, Vec<Posting>>) -> TrigramIndex {
-    let mut tokens: Vec<String> = inverted.keys().cloned().collect();
+fn build_trigram_for_bench(inverted: &HashMap<Token, Vec<Posting>>) -> TrigramIndex {
+    let mut tokens: Vec<Token> = inverted.keys().cloned().collect();
     tokens.sort();
 
     let mut trigram_map: HashMap<String, Vec<u32>> = HashMap::new();
@@ -458,7 +512,7 @@ fn bench_substring_search(c: &mut Criterion) {
                     let verified: Vec<&str> = candidates.unwrap_or_default().iter()
                         .filter_map(|&idx| trigram.tokens.get(idx as usize))
                         .filter(|t| t.contains(&query_lower))
-                        .map(|t| t.as_str())
+                        .map(|t| &**t)
                         .collect();
 
                     // Look up in main index
@@ -479,7 +533,7 @@ fn bench_substring_search(c: &mut Criterion) {
                     let query = "cl"; // 2 chars — falls back to linear scan
                     let matches: Vec<&str> = trigram.tokens.iter()
                         .filter(|t| t.contains(query))
-                        .map(|t| t.as_str())
+                        .map(|t| &**t)
                         .collect();
                     black_box(matches.len());
                 })
@@ -516,7 +570,7 @@ fn bench_substring_vs_regex(c: &mut Criterion) {
             let verified: Vec<&str> = candidates.unwrap_or_default().iter()
                 .filter_map(|&idx| trigram.tokens.get(idx as usize))
                 .filter(|t| t.contains(&query_lower))
-                .map(|t| t.as_str())
+                .map(|t| &**t)
                 .collect();
             black_box(verified.len());
         })
@@ -526,7 +580,7 @@ fn bench_substring_vs_regex(c: &mut Criterion) {
     group.bench_function("regex_scan_all_keys", |b| {
         let re = regex::Regex::new("(?i).*rarehttpclie.*").unwrap();
         b.iter(|| {
-            let matches: Vec<&String> = index.index.keys()
+            let matches: Vec<&Token> = index.index.keys()
                 .filter(|k| re.is_match(k))
                 .collect();
             black_box(matches.len());
@@ -537,7 +591,7 @@ fn bench_substring_vs_regex(c: &mut Criterion) {
     group.bench_function("linear_contains_scan", |b| {
         b.iter(|| {
             let query = "rarehttpclie";
-            let matches: Vec<&String> = index.index.keys()
+            let matches: Vec<&Token> = index.index.keys()
                 .filter(|k| k.contains(query))
                 .collect();
             black_box(matches.len());
@@ -554,6 +608,7 @@ criterion_group!(
     bench_tfidf_scoring,
     bench_regex_scan,
     bench_index_build,
+    bench_corpus_index,
     bench_serialization,
     bench_trigram_build,
     bench_substring_search,
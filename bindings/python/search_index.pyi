@@ -0,0 +1,41 @@
+from os import PathLike
+from typing import Any
+
+class Index:
+    def __init__(
+        self,
+        root: str | PathLike[str],
+        extensions: list[str] | None = None,
+        hidden: bool = False,
+        ignore_files: bool = True,
+        max_file_size: int | None = None,
+    ) -> None: ...
+    def grep(
+        self, terms: str, mode: str = "substring", all_terms: bool = False, max_results: int = 50
+    ) -> dict[str, Any]: ...
+    def rebuild(self) -> None: ...
+    def stats(self) -> dict[str, Any]: ...
+    @property
+    def files(self) -> list[str]: ...
+    @property
+    def root(self) -> str: ...
+    def __len__(self) -> int: ...
+
+class Definitions:
+    def __init__(self, path: str | PathLike[str]) -> None: ...
+    def find(
+        self,
+        name: str | None = None,
+        kind: str | None = None,
+        parent: str | None = None,
+        file: str | None = None,
+        namespace: str | None = None,
+        attribute: str | None = None,
+        base_type: str | None = None,
+        exclude_dir: list[str] | None = None,
+        regex: bool = False,
+        max_results: int = 100,
+    ) -> list[dict[str, Any]]: ...
+    @property
+    def files(self) -> list[str]: ...
+    def __len__(self) -> int: ...
@@ -0,0 +1,240 @@
+//! `search_index`: Python bindings for the library's content index (see
+//! [`search::IndexBuilder`] and [`search::query`]) and for definition indexes built by
+//! `search def-index` (see [`search::definition_index`]).
+//!
+//! Builds and searches release the GIL. Results are plain dicts and lists in the
+//! shape of the MCP `search_grep` tool result, so scripts written against the server
+//! read them unchanged.
+
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::IntoPyObjectExt;
+use pyo3::types::{PyDict, PyList};
+use serde_json::{json, Value};
+
+use search::definition_index::{self, DefinitionIndex, DefinitionQuery};
+use search::{GrepQuery, IndexBuilder, MatchMode, Searcher};
+
+/// A content index of one directory tree, held in memory.
+#[pyclass(module = "search_index", frozen)]
+struct Index {
+    builder: IndexBuilder,
+    searcher: Searcher,
+}
+
+#[pymethods]
+impl Index {
+    /// Walk `root` and index the files with one of `extensions` (all when None).
+    #[new]
+    #[pyo3(signature = (root, extensions=None, hidden=false, ignore_files=true, max_file_size=None))]
+    fn new(
+        py: Python<'_>,
+        root: PathBuf,
+        extensions: Option<Vec<String>>,
+        hidden: bool,
+        ignore_files: bool,
+        max_file_size: Option<u64>,
+    ) -> PyResult<Self> {
+        let extensions = extensions.unwrap_or_default();
+        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        let mut builder = IndexBuilder::new(root).extensions(&extensions).hidden(hidden).ignore_files(ignore_files);
+        if let Some(max) = max_file_size {
+            builder = builder.max_file_size(max);
+        }
+        let index = py.allow_threads(|| builder.build()).map_err(|e| PyOSError::new_err(e.to_string()))?;
+        Ok(Index { builder, searcher: Searcher::new(index) })
+    }
+
+    /// Search file contents. `terms` is comma-separated, or one phrase for
+    /// mode="phrase"; mode is "substring", "exact", "regex" or "phrase". Returns
+    /// {"files": [...], "summary": {...}} as search_grep does.
+    #[pyo3(signature = (terms, mode="substring", all_terms=false, max_results=50))]
+    fn grep(&self, py: Python<'_>, terms: &str, mode: &str, all_terms: bool, max_results: usize) -> PyResult<PyObject> {
+        let mode: MatchMode = mode.parse().map_err(PyValueError::new_err)?;
+        let query = match mode {
+            MatchMode::Phrase => GrepQuery::phrase(terms),
+            mode => GrepQuery::new(terms).mode(mode),
+        }.all_terms(all_terms).max_results(max_results);
+        let response = py.allow_threads(|| self.searcher.grep_response(&query))
+            .map_err(|e: regex::Error| PyValueError::new_err(e.to_string()))?;
+        to_python(py, &response)
+    }
+
+    /// Walk the tree again and swap the new index in.
+    fn rebuild(&self, py: Python<'_>) -> PyResult<()> {
+        let index = py.allow_threads(|| self.builder.build()).map_err(|e| PyOSError::new_err(e.to_string()))?;
+        self.searcher.replace(index);
+        Ok(())
+    }
+
+    /// Indexed file paths, by file id.
+    #[getter]
+    fn files(&self) -> Vec<String> {
+        self.searcher.snapshot().files.clone()
+    }
+
+    #[getter]
+    fn root(&self) -> String {
+        self.searcher.snapshot().root.clone()
+    }
+
+    /// Index size: files, unique and total tokens, and the files the build skipped.
+    fn stats(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let index = self.searcher.snapshot();
+        to_python(py, &serde_json::json!({
+            "files": index.files.len(),
+            "uniqueTokens": index.index.len(),
+            "totalTokens": index.total_tokens,
+            "skipped": { "tooLarge": index.skipped.too_large, "binary": index.skipped.binary },
+        }))
+    }
+
+    fn __len__(&self) -> usize {
+        self.searcher.snapshot().files.len()
+    }
+
+    fn __repr__(&self) -> String {
+        let index = self.searcher.snapshot();
+        format!("Index(root={:?}, files={})", index.root, index.files.len())
+    }
+}
+
+/// The classes, methods, functions and SQL objects of a codebase, loaded from a
+/// `.code-structure` file that `search def-index` saved.
+#[pyclass(module = "search_index", frozen)]
+struct Definitions {
+    index: DefinitionIndex,
+}
+
+#[pymethods]
+impl Definitions {
+    #[new]
+    fn new(py: Python<'_>, path: PathBuf) -> PyResult<Self> {
+        let index = py.allow_threads(|| definition_index::load(&path)).map_err(|e| PyOSError::new_err(e.to_string()))?;
+        Ok(Definitions { index })
+    }
+
+    /// Definitions matching every filter given, in index order, as search_definitions
+    /// lists them. `name` is comma-separated substrings, or a regex with regex=True;
+    /// the other text filters are substrings; all ignore case.
+    #[pyo3(signature = (name=None, kind=None, parent=None, file=None, namespace=None, attribute=None,
+        base_type=None, exclude_dir=None, regex=false, max_results=100))]
+    #[allow(clippy::too_many_arguments)]
+    fn find(
+        &self,
+        py: Python<'_>,
+        name: Option<&str>,
+        kind: Option<&str>,
+        parent: Option<&str>,
+        file: Option<&str>,
+        namespace: Option<&str>,
+        attribute: Option<&str>,
+        base_type: Option<&str>,
+        exclude_dir: Option<Vec<String>>,
+        regex: bool,
+        max_results: usize,
+    ) -> PyResult<PyObject> {
+        let mut query = DefinitionQuery::new().regex(regex).exclude_dir(exclude_dir.unwrap_or_default());
+        if let Some(kind) = kind {
+            query = query.kind(kind.parse().map_err(PyValueError::new_err)?);
+        }
+        if let Some(name) = name {
+            query = query.name(name);
+        }
+        if let Some(parent) = parent {
+            query = query.parent(parent);
+        }
+        if let Some(file) = file {
+            query = query.file(file);
+        }
+        if let Some(namespace) = namespace {
+            query = query.namespace(namespace);
+        }
+        if let Some(attribute) = attribute {
+            query = query.attribute(attribute);
+        }
+        if let Some(base_type) = base_type {
+            query = query.base_type(base_type);
+        }
+        let found = py.allow_threads(|| self.index.find(&query))
+            .map_err(|e: regex::Error| PyValueError::new_err(e.to_string()))?;
+        let limit = if max_results == 0 { found.len() } else { max_results };
+        let definitions: Vec<Value> = found.iter().take(limit).map(|&i| self.to_json(i)).collect();
+        to_python(py, &Value::Array(definitions))
+    }
+
+    /// Indexed file paths, by file id.
+    #[getter]
+    fn files(&self) -> Vec<String> {
+        self.index.files.clone()
+    }
+
+    fn __len__(&self) -> usize {
+        self.index.definitions.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Definitions(root={:?}, definitions={})", self.index.root, self.index.definitions.len())
+    }
+}
+
+impl Definitions {
+    fn to_json(&self, def_idx: u32) -> Value {
+        let def = &self.index.definitions[def_idx as usize];
+        let mut obj = json!({
+            "name": def.name,
+            "kind": def.kind.as_str(),
+            "file": self.index.files.get(def.file_id as usize).map_or("", String::as_str),
+            "lines": format!("{}-{}", def.line_start, def.line_end),
+        });
+        if let Some(parent) = &def.parent {
+            obj["parent"] = json!(parent);
+        }
+        if let Some(namespace) = definition_index::namespace_of(&self.index, def) {
+            obj["namespace"] = json!(namespace);
+        }
+        if let Some(signature) = &def.signature {
+            obj["signature"] = json!(signature);
+        }
+        for (key, list) in [("modifiers", &def.modifiers), ("attributes", &def.attributes), ("baseTypes", &def.base_types)] {
+            if !list.is_empty() {
+                obj[key] = json!(list);
+            }
+        }
+        obj
+    }
+}
+
+/// `value` as Python objects: dicts, lists, str, int, float, bool and None.
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    match value {
+        Value::Null => Ok(py.None()),
+        Value::Bool(b) => b.into_py_any(py),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => u.into_py_any(py),
+            (None, Some(i)) => i.into_py_any(py),
+            (None, None) => n.as_f64().unwrap_or(f64::NAN).into_py_any(py),
+        },
+        Value::String(s) => s.into_py_any(py),
+        Value::Array(items) => {
+            let items = items.iter().map(|item| to_python(py, item)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_py_any(py)
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key, to_python(py, item)?)?;
+            }
+            dict.into_py_any(py)
+        }
+    }
+}
+
+#[pymodule]
+fn search_index(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Index>()?;
+    m.add_class::<Definitions>()?;
+    Ok(())
+}
@@ -0,0 +1,120 @@
+"""Smoke test of the built extension: imports search_index, indexes a small tree and
+checks each method returns what search_index.pyi and README.md describe.
+
+Run after `maturin develop` or installing the wheel:
+    python -m unittest discover -s bindings/python/tests
+
+The Definitions test needs a definition index, which only the CLI builds: set
+SEARCH_BIN to the `search` executable (or put it on PATH), or the test is skipped.
+"""
+
+import glob
+import os
+import shutil
+import subprocess
+import tempfile
+import unittest
+
+import search_index
+
+
+class IndexTest(unittest.TestCase):
+    def setUp(self):
+        self.tmp = tempfile.TemporaryDirectory()
+        self.root = self.tmp.name
+        self.write("a.cs", "class UserService {}\nIUserService service;\nvar user = new UserService();\n")
+        self.write("b.cs", "class Order { UserService owner; }\nstring note = \"new HttpClient\";\n")
+        self.write("notes.txt", "UserService\n")
+
+    def tearDown(self):
+        self.tmp.cleanup()
+
+    def write(self, name, text):
+        with open(os.path.join(self.root, name), "w", encoding="utf-8") as f:
+            f.write(text)
+
+    def names(self, result):
+        return [os.path.basename(f["path"]) for f in result["files"]]
+
+    def test_build_and_grep(self):
+        index = search_index.Index(self.root, extensions=["cs"])
+        self.assertEqual(len(index), 2)
+        self.assertEqual(sorted(os.path.basename(p) for p in index.files), ["a.cs", "b.cs"])
+        self.assertIn("Index(root=", repr(index))
+
+        result = index.grep("userservice")
+        self.assertEqual(self.names(result), ["a.cs", "b.cs"])
+        top = result["files"][0]
+        self.assertEqual(top["lines"], [1, 2, 3])
+        self.assertGreater(top["score"], 0)
+        self.assertEqual(result["summary"]["totalFiles"], 2)
+
+        self.assertEqual(self.names(index.grep("order,userservice", mode="exact", all_terms=True)), ["b.cs"])
+        self.assertEqual(self.names(index.grep("new HttpClient", mode="phrase")), ["b.cs"])
+        self.assertEqual(self.names(index.grep("i.*service", mode="regex")), ["a.cs"])
+
+    def test_invalid_arguments_raise(self):
+        index = search_index.Index(self.root, extensions=["cs"])
+        with self.assertRaises(ValueError):
+            index.grep("(", mode="regex")
+        with self.assertRaises(ValueError):
+            index.grep("user", mode="fuzzy")
+        with self.assertRaises(OSError):
+            search_index.Index(os.path.join(self.root, "missing"))
+
+    def test_rebuild_and_stats(self):
+        index = search_index.Index(self.root, extensions=["cs"], max_file_size=70)
+        stats = index.stats()
+        self.assertEqual(stats["files"], 1)
+        self.assertEqual(stats["skipped"]["tooLarge"], 1)
+
+        self.write("c.cs", "class Invoice {}\n")
+        index.rebuild()
+        self.assertEqual(len(index), 2)
+        self.assertEqual(self.names(index.grep("invoice", mode="exact")), ["c.cs"])
+
+
+SEARCH_BIN = os.environ.get("SEARCH_BIN") or shutil.which("search")
+
+
+@unittest.skipUnless(SEARCH_BIN, "needs the search CLI (SEARCH_BIN) to build a definition index")
+class DefinitionsTest(unittest.TestCase):
+    def setUp(self):
+        self.tmp = tempfile.TemporaryDirectory()
+        root = os.path.join(self.tmp.name, "repo")
+        os.mkdir(root)
+        with open(os.path.join(root, "Billing.cs"), "w", encoding="utf-8") as f:
+            f.write("namespace Shop.Billing\n{\n    public class InvoiceService : IInvoiceService\n    {\n"
+                    "        public void CreateInvoice(int id) { }\n        private void Audit() { }\n    }\n}\n")
+        # def-index saves under the platform data directory: point it into the temp dir
+        data = os.path.join(self.tmp.name, "data")
+        env = dict(os.environ, XDG_DATA_HOME=data, LOCALAPPDATA=data, HOME=data)
+        subprocess.run([SEARCH_BIN, "def-index", "--dir", root, "--ext", "cs"], env=env, check=True, capture_output=True)
+        [self.path] = glob.glob(os.path.join(data, "**", "*.code-structure"), recursive=True)
+
+    def tearDown(self):
+        self.tmp.cleanup()
+
+    def test_load_and_find(self):
+        defs = search_index.Definitions(self.path)
+        self.assertEqual(len(defs), 3)
+        self.assertEqual([os.path.basename(p) for p in defs.files], ["Billing.cs"])
+        self.assertIn("Definitions(root=", repr(defs))
+
+        [cls] = defs.find(name="invoiceservice", kind="class")
+        self.assertEqual((cls["name"], cls["namespace"], cls["baseTypes"]), ("InvoiceService", "Shop.Billing", ["IInvoiceService"]))
+        self.assertEqual([d["name"] for d in defs.find(parent="InvoiceService")], ["CreateInvoice", "Audit"])
+        self.assertEqual([d["name"] for d in defs.find(name="^aud", regex=True)], ["Audit"])
+        self.assertEqual(len(defs.find(max_results=1)), 1)
+        self.assertEqual(defs.find(name="create")[0]["lines"], "5-5")
+
+        with self.assertRaises(ValueError):
+            defs.find(kind="gadget")
+        with self.assertRaises(ValueError):
+            defs.find(name="(", regex=True)
+        with self.assertRaises(OSError):
+            search_index.Definitions(os.path.join(self.tmp.name, "missing.code-structure"))
+
+
+if __name__ == "__main__":
+    unittest.main()
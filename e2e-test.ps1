@@ -140,8 +140,8 @@ catch {
 
 
 # T21-T23: error handling
-Run-Test "T21 invalid-regex"       "$Binary grep `"[invalid`" -d $TestDir -e $TestExt --regex" -ExpectedExit 1
-Run-Test "T22 nonexistent-dir"     "$Binary find test -d /nonexistent/path/xyz" -ExpectedExit 1
+Run-Test "T21 invalid-regex"       "$Binary grep `"[invalid`" -d $TestDir -e $TestExt --regex" -ExpectedExit 2
+Run-Test "T22 nonexistent-dir"     "$Binary find test -d /nonexistent/path/xyz" -ExpectedExit 2
 
 # T42/T42b: tips
 Run-Test "T42 tips-strategy-recipes" "$Binary tips | Select-String 'STRATEGY RECIPES'"
@@ -152,17 +152,17 @@ $ErrorActionPreference = "Continue"
 Invoke-Expression "$Binary content-index -d $TestDir -e $TestExt 2>&1" | Out-Null
 $ErrorActionPreference = "Stop"
 
-# T54: grep with non-existent term should return 0 matches gracefully (not crash)
-Run-Test "T54 grep-nonexistent-term" "$Binary grep ZZZNonExistentXYZ123 -d $TestDir -e $TestExt"
+# T54: grep with non-existent term should return 0 matches gracefully (not crash), exit 1 like grep
+Run-Test "T54 grep-nonexistent-term" "$Binary grep ZZZNonExistentXYZ123 -d $TestDir -e $TestExt" -ExpectedExit 1
 
-# T65: fast with invalid regex should return error (exit 1)
-Run-Test "T65 fast-invalid-regex"    "$Binary fast `"[invalid`" -d $TestDir --regex" -ExpectedExit 1
+# T65: fast with invalid regex should return error (exit 2)
+Run-Test "T65 fast-invalid-regex"    "$Binary fast `"[invalid`" -d $TestDir --regex" -ExpectedExit 2
 
 # T76: fast with empty pattern
 Run-Test "T76 fast-empty-pattern"    "$Binary fast `"`" -d $TestDir -e $TestExt" -ExpectedExit 2
 
 # T80: grep with non-existent directory should return error (no index found)
-Run-Test "T80 grep-nonexistent-dir"  "$Binary grep fn -d C:\nonexistent\fakepath123 -e $TestExt" -ExpectedExit 1
+Run-Test "T80 grep-nonexistent-dir"  "$Binary grep fn -d C:\nonexistent\fakepath123 -e $TestExt" -ExpectedExit 2
 
 # T82: grep with --max-results 0 should work (0 means unlimited)
 Run-Test "T82 grep-max-results-zero" "$Binary grep fn -d $TestDir -e $TestExt --max-results 0"
@@ -0,0 +1,342 @@
+//! Async wrappers for services that embed the engine.
+//!
+//! Index builds and searches are CPU-bound and read files, so an async caller must not
+//! run them on its runtime's worker threads. [`spawn_blocking`] queues such a call on a
+//! pool of worker threads, one per CPU and started as calls arrive, and returns a
+//! [`BlockingTask`] future that wakes the caller when the result is ready. It needs
+//! nothing from the runtime, so it works the same under tokio, async-std or smol.
+//! [`IndexBuilder::build_async`] and [`Searcher::grep_async`] are built on it; a phrase
+//! search reads its candidate files in batches queued on the pool as well, one per
+//! worker, so a large phrase search keeps every worker busy.
+//!
+//! [`Searcher`] is `Send + Sync` and cheap to clone: share one across request handlers
+//! and swap in a rebuilt index with [`Searcher::replace`] while queries run.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::query::{best_first, PhraseSearch};
+use crate::{ContentIndex, FileMatch, GrepQuery, IndexBuilder, MatchMode, Searcher};
+
+/// The result of a call queued on the worker pool (see [`spawn_blocking`]). Resolves to
+/// an error when no worker thread could be started to run it; a panic in the call
+/// resumes in the task that awaits it.
+#[must_use = "the call runs anyway; await the task for its result"]
+pub struct BlockingTask<T> {
+    shared: Arc<Mutex<Slot<T>>>,
+}
+
+struct Slot<T> {
+    result: Option<thread::Result<io::Result<T>>>,
+    waker: Option<Waker>,
+}
+
+/// Hands a call's result to its task. Dropped without one (the pool gave up on the
+/// call), it resolves the task to an error instead of leaving it pending.
+struct Completer<T> {
+    shared: Arc<Mutex<Slot<T>>>,
+    result: Option<thread::Result<io::Result<T>>>,
+}
+
+impl<T> Completer<T> {
+    fn complete(mut self, result: thread::Result<io::Result<T>>) {
+        self.result = Some(result);
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        let result = self.result.take().unwrap_or_else(|| {
+            Ok(Err(io::Error::other("no worker thread could be started for the blocking call")))
+        });
+        let waker = {
+            let mut slot = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+            slot.result = Some(result);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Queue `call` on the worker pool; await the returned task for its result.
+pub fn spawn_blocking<T, F>(call: F) -> BlockingTask<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    spawn_fallible(move || Ok(call()))
+}
+
+/// [`spawn_blocking`] for a call that returns an `io::Result` of its own, merged with
+/// the pool's error.
+fn spawn_fallible<T, F>(call: F) -> BlockingTask<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Slot { result: None, waker: None }));
+    let completer = Completer { shared: Arc::clone(&shared), result: None };
+    pool().submit(Box::new(move || completer.complete(panic::catch_unwind(AssertUnwindSafe(call)))));
+    BlockingTask { shared }
+}
+
+impl<T> Future for BlockingTask<T> {
+    type Output = io::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<T>> {
+        let mut slot = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        match slot.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(payload)) => {
+                drop(slot);
+                panic::resume_unwind(payload)
+            }
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Worker threads shared by every blocking call in the process. Each call reserves an
+/// idle worker, or starts one when none is left, up to `max_workers`; past that, calls
+/// queue for the next worker to finish.
+struct Pool {
+    state: Mutex<PoolState>,
+    work: Condvar,
+    max_workers: usize,
+}
+
+#[derive(Default)]
+struct PoolState {
+    jobs: VecDeque<Job>,
+    workers: usize,
+    /// Waiting workers not yet reserved by a call
+    idle: usize,
+    /// Reservations not yet taken up by a waiting worker
+    wakeups: usize,
+}
+
+fn pool() -> &'static Pool {
+    static POOL: OnceLock<Pool> = OnceLock::new();
+    POOL.get_or_init(|| Pool::new(thread::available_parallelism().map_or(4, |n| n.get())))
+}
+
+impl Pool {
+    fn new(max_workers: usize) -> Self {
+        Pool { state: Mutex::new(PoolState::default()), work: Condvar::new(), max_workers: max_workers.max(1) }
+    }
+
+    fn submit(&'static self, job: Job) {
+        let mut state = self.lock();
+        state.jobs.push_back(job);
+        if state.idle > 0 {
+            // Reserve the worker, so the next call does not count on it too
+            state.idle -= 1;
+            state.wakeups += 1;
+            self.work.notify_one();
+            return;
+        }
+        if state.workers >= self.max_workers {
+            return;
+        }
+        state.workers += 1;
+        drop(state);
+        let started = thread::Builder::new()
+            .name("search-blocking".to_string())
+            .spawn(move || self.run());
+        if started.is_err() {
+            let mut state = self.lock();
+            state.workers -= 1;
+            // With no worker left the queue would never drain: drop its calls, which
+            // resolves their tasks to an error. Otherwise the running workers take it.
+            let orphaned = if state.workers == 0 { std::mem::take(&mut state.jobs) } else { VecDeque::new() };
+            drop(state);
+            drop(orphaned);
+        }
+    }
+
+    fn run(&self) {
+        let mut state = self.lock();
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                drop(state);
+                job();
+                state = self.lock();
+            } else {
+                // The call that wakes a worker takes it off `idle`; a spurious wakeup waits on
+                state.idle += 1;
+                while state.wakeups == 0 {
+                    state = self.work.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+                state.wakeups -= 1;
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl IndexBuilder {
+    /// [`IndexBuilder::build`] on the worker pool.
+    pub fn build_async(&self) -> BlockingTask<ContentIndex> {
+        let builder = self.clone();
+        spawn_fallible(move || builder.build())
+    }
+}
+
+impl Searcher {
+    /// [`Searcher::grep`] on the worker pool, against the index current when it starts.
+    /// A phrase search reads its candidate files in one call per worker. The outer error
+    /// is the pool's, the inner one the query's.
+    pub fn grep_async(&self, query: GrepQuery)
+        -> impl Future<Output = io::Result<Result<Vec<FileMatch>, regex::Error>>> + Send + 'static
+    {
+        let searcher = self.clone();
+        async move {
+            if query.mode != MatchMode::Phrase {
+                return spawn_blocking(move || searcher.grep(&query)).await;
+            }
+            let phrase = query.terms.first().cloned().unwrap_or_default();
+            let search = Arc::new(spawn_blocking(move || PhraseSearch::new(&searcher.snapshot(), &phrase)).await?);
+            let batch = search.paths.len().div_ceil(pool().max_workers).max(1);
+            let reads: Vec<_> = (0..search.paths.len()).step_by(batch).map(|start| {
+                let search = Arc::clone(&search);
+                spawn_blocking(move || {
+                    let end = (start + batch).min(search.paths.len());
+                    search.paths[start..end].iter().filter_map(|path| search.verify(path)).collect::<Vec<_>>()
+                })
+            }).collect();
+            let mut results = Vec::new();
+            for read in reads {
+                results.extend(read.await?);
+            }
+            best_first(&mut results);
+            if query.max_results > 0 {
+                results.truncate(query.max_results);
+            }
+            Ok(Ok(results))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::Wake;
+
+    /// Wakes a parked thread: the smallest executor there is.
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_async_build_and_grep_share_a_searcher_across_threads() {
+        assert_send_sync::<Searcher>();
+        assert_send_sync::<BlockingTask<ContentIndex>>();
+
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("a.rs"), "fn retry_payment() {}\n").unwrap();
+        let searcher = Searcher::new(block_on(IndexBuilder::new(tmp.path()).build_async()).unwrap());
+
+        let handlers: Vec<_> = (0..4).map(|_| {
+            let searcher = searcher.clone();
+            thread::spawn(move || block_on(searcher.grep_async(GrepQuery::new("payment"))).unwrap().unwrap().len())
+        }).collect();
+        assert!(handlers.into_iter().all(|h| h.join().unwrap() == 1));
+        assert_eq!(block_on(searcher.grep_async(GrepQuery::phrase("retry payment"))).unwrap().unwrap().len(), 0,
+            "retry_payment is one token: no whitespace between the words");
+    }
+
+    #[test]
+    fn test_async_phrase_search_matches_the_blocking_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        for i in 0..20 {
+            let body = "retry the payment\n".repeat(i % 3) + "payment retry\n";
+            std::fs::write(tmp.path().join(format!("f{i:02}.rs")), body).unwrap();
+        }
+        let searcher = Searcher::new(IndexBuilder::new(tmp.path()).build().unwrap());
+        let query = GrepQuery::phrase("Retry  the payment").max_results(10);
+        let paths = |matches: Vec<FileMatch>| matches.into_iter().map(|m| (m.path, m.lines)).collect::<Vec<_>>();
+        let expected = paths(searcher.grep(&query).unwrap());
+        assert_eq!(expected.len(), 10, "13 of the files hold the phrase");
+        assert_eq!(paths(block_on(searcher.grep_async(query)).unwrap().unwrap()), expected);
+    }
+
+    #[test]
+    fn test_blocking_task_resumes_a_panic_in_the_awaiting_task() {
+        let task = spawn_blocking(|| -> u32 { panic!("index build failed") });
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| block_on(task))).unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"index build failed"));
+    }
+
+    #[test]
+    fn test_blocking_calls_share_a_bounded_pool_of_workers() {
+        let tasks: Vec<_> = (0..64).map(|i| spawn_blocking(move || (i, thread::current().id()))).collect();
+        let mut workers = std::collections::HashSet::new();
+        for (i, task) in tasks.into_iter().enumerate() {
+            let (value, worker) = block_on(task).unwrap();
+            assert_eq!(value, i);
+            workers.insert(worker);
+        }
+        assert!(workers.len() <= pool().max_workers, "{} threads for {} workers", workers.len(), pool().max_workers);
+    }
+
+    #[test]
+    fn test_call_reserves_the_idle_worker() {
+        let pool: &'static Pool = Box::leak(Box::new(Pool::new(2)));
+        let (done, finished) = std::sync::mpsc::channel();
+        pool.submit(Box::new(move || done.send(()).unwrap()));
+        finished.recv().unwrap();
+        while pool.lock().idle == 0 {
+            thread::yield_now();
+        }
+        // The first call blocks its worker until the second runs: queued behind it on
+        // the one idle worker, the second would never run
+        let (unblock, blocked) = std::sync::mpsc::channel();
+        let (ran, first_ran) = std::sync::mpsc::channel();
+        pool.submit(Box::new(move || ran.send(blocked.recv_timeout(std::time::Duration::from_secs(10)).is_ok()).unwrap()));
+        pool.submit(Box::new(move || unblock.send(()).unwrap()));
+        assert!(first_ran.recv().unwrap(), "the second call waited for the first one's worker");
+        assert_eq!(pool.lock().workers, 2);
+    }
+
+    #[test]
+    fn test_call_the_pool_gave_up_on_resolves_to_an_error() {
+        let shared = Arc::new(Mutex::new(Slot::<u32> { result: None, waker: None }));
+        drop(Completer { shared: Arc::clone(&shared), result: None });
+        let err = block_on(BlockingTask { shared }).unwrap_err();
+        assert!(err.to_string().contains("no worker thread"), "{err}");
+    }
+}
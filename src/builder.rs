@@ -0,0 +1,356 @@
+//! Building a [`ContentIndex`] in-process, for programs that embed the engine.
+//!
+//! [`IndexBuilder`] walks a directory the way `search content-index` does (gitignore
+//! rules, hidden files, extension filter, size limit) and tokenizes every file into
+//! one index, in a single thread and in file-name order, so file ids are repeatable.
+//! The CLI's build is this builder run in parallel: it takes the walk
+//! ([`IndexBuilder::walker`]), the size and binary checks ([`IndexBuilder::read`]),
+//! the per-file tokenization ([`file_tokens`]) and the final assembly ([`assemble`])
+//! from here, and adds spilling, checkpoints and `.search-index.toml` on top.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ignore::WalkBuilder;
+
+use crate::{
+    clean_path, extract_comment_markers, extract_ticket_refs, generate_trigrams, looks_binary, path_to_string,
+    read_file_lossy, top_level_dir, CommentMarker, ContentIndex, Posting, Searcher, SkippedFiles, TicketRef, Token,
+    TokenBloom, TokenizerSettings, TrigramIndex, WalkLimits, DEFAULT_STOP_TOKEN_PCT,
+};
+
+/// Age after which an index built by [`IndexBuilder`] counts as stale, as the CLI's default.
+const DEFAULT_MAX_AGE_SECS: u64 = 24 * 3600;
+
+/// What [`IndexBuilder::read`] made of a file.
+#[derive(Debug, PartialEq)]
+pub enum FileRead {
+    /// Contents to index, lossily decoded as UTF-8
+    Text(String),
+    /// Over the size limit; counted in [`SkippedFiles::too_large`]
+    TooLarge,
+    /// NUL bytes near the start; counted in [`SkippedFiles::binary`]
+    Binary,
+    /// Gone or not readable since the walk saw it
+    Unreadable,
+}
+
+/// Builds a [`ContentIndex`] of a directory tree.
+///
+/// ```no_run
+/// use search::{GrepQuery, IndexBuilder};
+///
+/// let searcher = IndexBuilder::new("src").extensions(&["rs"]).build_searcher()?;
+/// for file in searcher.grep(&GrepQuery::new("tokenize"))? {
+///     println!("{} {:.3} {:?}", file.path, file.score, file.lines);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct IndexBuilder {
+    root: PathBuf,
+    extensions: Vec<String>,
+    hidden: bool,
+    ignore_files: bool,
+    max_file_size: Option<u64>,
+    tokenizer: TokenizerSettings,
+    stop_token_pct: u8,
+}
+
+impl IndexBuilder {
+    /// A builder for `root`, indexing every extension, skipping hidden files and
+    /// following `.gitignore` rules.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        IndexBuilder {
+            root: root.as_ref().to_path_buf(),
+            extensions: Vec::new(),
+            hidden: false,
+            ignore_files: true,
+            max_file_size: None,
+            tokenizer: TokenizerSettings::default(),
+            stop_token_pct: DEFAULT_STOP_TOKEN_PCT,
+        }
+    }
+
+    /// Index only files with these extensions (without the dot, any case). Empty = all.
+    pub fn extensions(mut self, extensions: &[&str]) -> Self {
+        self.extensions = extensions.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect();
+        self
+    }
+
+    /// Also index hidden files and directories.
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Follow `.gitignore`, global gitignore and `.git/info/exclude` rules (default: true).
+    pub fn ignore_files(mut self, ignore_files: bool) -> Self {
+        self.ignore_files = ignore_files;
+        self
+    }
+
+    /// Skip files larger than `bytes`; counted in [`ContentIndex::skipped`].
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    pub fn tokenizer(mut self, settings: TokenizerSettings) -> Self {
+        self.tokenizer = settings;
+        self
+    }
+
+    /// Stop-token cutoff of the index (see [`ContentIndex::is_stop_token`]); 0 = none.
+    pub fn stop_token_pct(mut self, pct: u8) -> Self {
+        self.stop_token_pct = pct;
+        self
+    }
+
+    /// Walk the tree and build the index. Fails only when the root is not a readable
+    /// directory; unreadable files are left out.
+    pub fn build(&self) -> io::Result<ContentIndex> {
+        let root = fs::canonicalize(&self.root)?;
+        if !root.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a directory", root.display())));
+        }
+        let mut walk = self.walker(&root);
+        walk.sort_by_file_name(|a, b| a.cmp(b));
+
+        let mut files = Vec::new();
+        let mut file_token_counts = Vec::new();
+        let mut postings: HashMap<String, Vec<Posting>> = HashMap::new();
+        let mut comment_markers = Vec::new();
+        let mut ticket_refs = Vec::new();
+        let mut skipped = SkippedFiles::default();
+        for entry in walk.build().flatten() {
+            if !entry.file_type().is_some_and(|ft| ft.is_file()) || !self.wants(entry.path()) {
+                continue;
+            }
+            let content = match self.read(entry.path(), entry.metadata().ok().map(|m| m.len())) {
+                FileRead::Text(content) => content,
+                FileRead::TooLarge => {
+                    skipped.too_large += 1;
+                    continue;
+                }
+                FileRead::Binary => {
+                    skipped.binary += 1;
+                    continue;
+                }
+                FileRead::Unreadable => continue,
+            };
+
+            // Files are visited in id order, so every posting list stays sorted by file id
+            let file_id = files.len() as u32;
+            let (tokens, count) = file_tokens(&content, &self.tokenizer);
+            for (token, lines) in tokens {
+                postings.entry(token).or_default().push(Posting { file_id, lines });
+            }
+            comment_markers.extend(extract_comment_markers(&content).into_iter().map(
+                |(line, marker, text)| CommentMarker { file_id, line, marker: marker.to_string(), text },
+            ));
+            ticket_refs.extend(extract_ticket_refs(&content).into_iter().map(
+                |(line, ticket)| TicketRef { file_id, line, ticket },
+            ));
+            files.push(clean_path(&path_to_string(entry.path())));
+            file_token_counts.push(count);
+        }
+
+        let index: HashMap<Token, Vec<Posting>> = postings.into_iter().map(|(t, p)| (Token::from(t), p)).collect();
+        let mut index = assemble(clean_path(&root.to_string_lossy()), files, file_token_counts, index, comment_markers, ticket_refs);
+        index.extensions = self.extensions.clone();
+        index.skipped = skipped;
+        index.tokenizer = self.tokenizer;
+        index.stop_token_pct = self.stop_token_pct;
+        index.max_file_size = self.max_file_size.unwrap_or(0);
+        Ok(index)
+    }
+
+    /// The walk of `root` this builder makes: hidden files and ignore rules as set,
+    /// no extension filter (see [`IndexBuilder::build`]).
+    pub fn walker(&self, root: &Path) -> WalkBuilder {
+        let mut walk = WalkBuilder::new(root);
+        walk.hidden(!self.hidden)
+            .git_ignore(self.ignore_files)
+            .git_global(self.ignore_files)
+            .git_exclude(self.ignore_files);
+        walk
+    }
+
+    /// Read a walked file of `len` bytes (from its metadata, when known) for indexing,
+    /// unless the size limit or the binary check leaves it out.
+    pub fn read(&self, path: &Path, len: Option<u64>) -> FileRead {
+        if let Some(max) = self.max_file_size
+            && len.is_some_and(|len| len > max) {
+            return FileRead::TooLarge;
+        }
+        match read_file_lossy(path) {
+            Ok((content, _)) if looks_binary(&content) => FileRead::Binary,
+            Ok((content, _)) => FileRead::Text(content),
+            Err(_) => FileRead::Unreadable,
+        }
+    }
+
+    /// [`IndexBuilder::build`], wrapped in a [`Searcher`] to query it.
+    pub fn build_searcher(&self) -> io::Result<Searcher> {
+        self.build().map(Searcher::new)
+    }
+
+    fn wants(&self, path: &Path) -> bool {
+        self.extensions.is_empty() || path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| self.extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+    }
+}
+
+/// The tokens of one file's `content` with their 1-based line numbers, and the
+/// file's token count (the TF denominator).
+pub fn file_tokens(content: &str, settings: &TokenizerSettings) -> (HashMap<String, Vec<u32>>, u32) {
+    let mut tokens: HashMap<String, Vec<u32>> = HashMap::new();
+    let mut total: u32 = 0;
+    for (line_num, line) in content.lines().enumerate() {
+        for token in settings.tokenize(line) {
+            total += 1;
+            tokens.entry(token).or_default().push((line_num + 1) as u32);
+        }
+    }
+    (tokens, total)
+}
+
+/// A content index of `root` from its files (by file id), their token counts, the
+/// inverted index and the annotations, with the trigram index and directory blooms
+/// derived from them. Build settings are left at their defaults for the caller to set.
+pub fn assemble(
+    root: String,
+    files: Vec<String>,
+    file_token_counts: Vec<u32>,
+    index: HashMap<Token, Vec<Posting>>,
+    comment_markers: Vec<CommentMarker>,
+    ticket_refs: Vec<TicketRef>,
+) -> ContentIndex {
+    let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    ContentIndex {
+        trigram: build_trigram_index(&index),
+        dir_blooms: build_dir_blooms(&index, &files, &root),
+        root,
+        created_at,
+        max_age_secs: DEFAULT_MAX_AGE_SECS,
+        total_tokens: file_token_counts.iter().map(|&c| c as u64).sum(),
+        files,
+        index,
+        extensions: Vec::new(),
+        file_token_counts,
+        trigram_dirty: false,
+        forward: None,
+        path_to_id: None,
+        comment_markers,
+        ticket_refs,
+        revision: None,
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: WalkLimits::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
+    }
+}
+
+/// Build a trigram index from the inverted index's token keys.
+pub fn build_trigram_index(inverted: &HashMap<Token, Vec<Posting>>) -> TrigramIndex {
+    let mut tokens: Vec<Token> = inverted.keys().cloned().collect();
+    tokens.sort();
+
+    let mut trigram_map: HashMap<String, Vec<u32>> = HashMap::new();
+
+    for (idx, token) in tokens.iter().enumerate() {
+        let trigrams = generate_trigrams(token);
+        for trigram in trigrams {
+            trigram_map.entry(trigram).or_default().push(idx as u32);
+        }
+    }
+
+    // Sort and dedup posting lists
+    for list in trigram_map.values_mut() {
+        list.sort();
+        list.dedup();
+    }
+
+    TrigramIndex { tokens, trigram_map }
+}
+
+/// Build one token bloom filter per top-level directory (see `ContentIndex::dir_blooms`),
+/// each sized for the number of distinct tokens in that directory.
+pub fn build_dir_blooms(inverted: &HashMap<Token, Vec<Posting>>, files: &[String], root: &str) -> HashMap<String, TokenBloom> {
+    let mut key_ids: HashMap<String, usize> = HashMap::new();
+    let file_dir: Vec<Option<usize>> = files.iter()
+        .map(|f| top_level_dir(root, f, false).map(|key| {
+            let next = key_ids.len();
+            *key_ids.entry(key).or_insert(next)
+        }))
+        .collect();
+    if key_ids.is_empty() {
+        return HashMap::new();
+    }
+
+    let mut counts = vec![0usize; key_ids.len()];
+    for_each_dir_token(inverted, &file_dir, key_ids.len(), |dir, _| counts[dir] += 1);
+    let mut blooms: Vec<TokenBloom> = counts.iter().map(|&n| TokenBloom::with_capacity(n)).collect();
+    for_each_dir_token(inverted, &file_dir, key_ids.len(), |dir, token| blooms[dir].insert(token));
+
+    let mut by_key: Vec<(String, usize)> = key_ids.into_iter().collect();
+    by_key.sort_by_key(|(_, id)| *id);
+    by_key.into_iter().zip(blooms).map(|((key, _), bloom)| (key, bloom)).collect()
+}
+
+/// Call `visit(dir, token)` once per distinct (directory, token) pair, where `file_dir`
+/// maps file_id to directory number.
+fn for_each_dir_token(
+    inverted: &HashMap<Token, Vec<Posting>>,
+    file_dir: &[Option<usize>],
+    dirs: usize,
+    mut visit: impl FnMut(usize, &str),
+) {
+    // seen[dir] = number of the last token visited for that directory
+    let mut seen = vec![usize::MAX; dirs];
+    for (n, (token, postings)) in inverted.iter().enumerate() {
+        for p in postings {
+            if let Some(&Some(dir)) = file_dir.get(p.file_id as usize)
+                && seen[dir] != n
+            {
+                seen[dir] = n;
+                visit(dir, token);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_indexes_matching_files_in_name_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join("src")).unwrap();
+        fs::write(tmp.path().join("src/b.rs"), "fn parse_args() {}\n// TODO: more args\n").unwrap();
+        fs::write(tmp.path().join("src/a.rs"), "fn main() {\n    parse_args();\n}\n").unwrap();
+        fs::write(tmp.path().join("notes.md"), "parse_args").unwrap();
+        fs::write(tmp.path().join("src/blob.rs"), "a\0b").unwrap();
+
+        let index = IndexBuilder::new(tmp.path()).extensions(&[".RS"]).build().unwrap();
+        let names: Vec<&str> = index.files.iter().map(|f| f.rsplit('/').next().unwrap()).collect();
+        assert_eq!(names, vec!["a.rs", "b.rs"]);
+        assert_eq!(index.skipped.binary, 1);
+        let postings = &index.index["parse_args"];
+        assert_eq!((postings[0].file_id, &postings[0].lines), (0, &vec![2]));
+        assert_eq!((postings[1].file_id, &postings[1].lines), (1, &vec![1]));
+        assert_eq!(index.file_token_counts, vec![3, 5]);
+        assert_eq!(index.comment_markers.len(), 1);
+        assert!(!index.trigram.tokens.is_empty());
+        assert!(index.dir_may_contain(&format!("{}/src", index.root), "parse_args"));
+
+        assert!(IndexBuilder::new(tmp.path().join("missing")).build().is_err());
+    }
+}
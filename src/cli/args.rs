@@ -1,7 +1,17 @@
 //! CLI argument structs for all subcommands.
 
+use std::path::PathBuf;
+
 use clap::Parser;
 
+use crate::cli::output::OutputFormat;
+use crate::cli::report::Severity;
+use crate::config::RankingBoosts;
+use crate::mcp::handlers::utils::{GrepGroup, GrepSort};
+use crate::mcp::watcher::WatchMode;
+use crate::priority::BuildPriority;
+use crate::sqlite::Storage;
+
 #[derive(Parser, Debug)]
 pub struct FindArgs {
     /// Search pattern (substring or regex with --regex)
@@ -19,6 +29,14 @@ pub struct FindArgs {
     #[arg(long)]
     pub contents: bool,
 
+    /// With --contents: print each match instead of its line, as path:line:column: match
+    #[arg(short = 'o', long, requires = "contents")]
+    pub only_matching: bool,
+
+    /// With --only-matching: also print up to N characters on each side of a match
+    #[arg(long, default_value = "0", value_name = "N", requires = "only_matching")]
+    pub context_chars: usize,
+
     /// Show hidden files
     #[arg(long)]
     pub hidden: bool,
@@ -46,6 +64,10 @@ pub struct FindArgs {
     /// File extension filter
     #[arg(short, long)]
     pub ext: Option<String>,
+
+    /// Also write the summary (counts, timing, exit code) as JSON to this file, for CI
+    #[arg(long, value_name = "PATH")]
+    pub summary_json: Option<String>,
 }
 
 #[derive(Parser, Debug)]
@@ -69,6 +91,21 @@ pub struct IndexArgs {
     /// Number of parallel threads (0 = auto)
     #[arg(short, long, default_value = "0")]
     pub threads: usize,
+
+    /// Walk the tree and print estimated entry count and index size without building
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Build priority: "low" caps worker threads to ~25% of cores and runs them
+    /// at background OS priority so the machine stays responsive
+    #[arg(long, value_enum, default_value = "normal")]
+    pub build_priority: BuildPriority,
+
+    /// Store the index as one bincode file, or in a SQLite database that saves only
+    /// the entries that changed and can be queried with SQL. Omitted: keep the
+    /// current format (bincode for a new index)
+    #[arg(long, value_enum)]
+    pub storage: Option<Storage>,
 }
 
 #[derive(Parser, Debug)]
@@ -115,15 +152,28 @@ pub struct FastArgs {
     /// Maximum file size in bytes
     #[arg(long)]
     pub max_size: Option<u64>,
+
+    /// Also write the summary (counts, timing, exit code) as JSON to this file, for CI
+    #[arg(long, value_name = "PATH")]
+    pub summary_json: Option<String>,
 }
 
 #[derive(Parser, Debug)]
+pub struct InfoArgs {
+    /// Also list the N tokens found in the most files of each content index, with the
+    /// share of files and whether grep treats them as stop tokens (default N: 20)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "20")]
+    pub top_tokens: Option<usize>,
+}
+
+#[derive(Parser, Debug, Clone)]
 pub struct ContentIndexArgs {
     /// Directory to index
     #[arg(short, long, default_value = ".")]
     pub dir: String,
 
-    /// File extensions to index (comma-separated, e.g. "cs,rs,py,js")
+    /// File extensions to index (comma-separated, e.g. "cs,rs,py,js"), or "auto" to
+    /// pick the most common text extensions from a sample of the tree
     #[arg(short, long, default_value = "cs")]
     pub ext: String,
 
@@ -139,6 +189,16 @@ pub struct ContentIndexArgs {
     #[arg(long)]
     pub no_ignore: bool,
 
+    /// Walk at most N levels below --dir, counting files directly in it as level 1
+    /// (0 = unlimited). Recorded in the index: rebuilds and --watch keep to it.
+    #[arg(long, default_value = "0")]
+    pub max_depth: usize,
+
+    /// Skip a directory and everything below it: a directory name (e.g. node_modules)
+    /// or a path relative to --dir (e.g. src/legacy). Repeatable; recorded in the index.
+    #[arg(long, value_name = "DIR")]
+    pub prune_dir: Vec<String>,
+
     /// Number of parallel threads (0 = auto)
     #[arg(short, long, default_value = "0")]
     pub threads: usize,
@@ -146,6 +206,72 @@ pub struct ContentIndexArgs {
     /// Minimum token length to index (default: 2)
     #[arg(long, default_value = "2")]
     pub min_token_len: usize,
+
+    /// Maximum token length to index, e.g. to keep hashes and base64 blobs out of the
+    /// index (0 = no limit). Both limits are stored in the index; grep warns about
+    /// terms outside them and scans file contents for those instead.
+    #[arg(long, default_value = "0")]
+    pub max_token_len: usize,
+
+    /// Tokens in more than this percentage of files (e.g. `public`, `var`) are stop
+    /// tokens: grep leaves them out of multi-term OR token queries with a warning.
+    /// Stored in the index; applies to indexes of 100 files or more (0 = off)
+    #[arg(long, default_value = "50", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub stop_token_pct: u8,
+
+    /// Skip files larger than this many bytes; K, M and G suffixes are accepted
+    /// (e.g. 512K, 2M). 0 = no limit unless .search-index.toml sets max-file-size-kb.
+    /// Files with a NUL byte in their first 8000 bytes are always skipped as binary.
+    #[arg(long, default_value = "0", value_parser = parse_size)]
+    pub max_file_size: u64,
+
+    /// Spill partial postings to temp files once the build holds more than N MB of
+    /// them, and merge the files at the end (0 = keep everything in memory). For
+    /// monorepos whose build would otherwise run out of RAM.
+    #[arg(long, default_value = "0")]
+    pub spill_mb: u64,
+
+    /// Split the index into N shard files by top-level directory, built in parallel
+    /// and saved independently: a save rewrites only the shards whose files changed.
+    /// 1 converts a sharded index back to one file; 0 keeps the current layout.
+    #[arg(long, default_value = "0")]
+    pub shards: usize,
+
+    /// Store the index as one bincode file, or in a SQLite database: saves rewrite
+    /// only the files that changed, grep loads only the tokens it searches for, and
+    /// the postings can be queried with SQL. Omitted: keep the current format
+    /// (bincode for a new index)
+    #[arg(long, value_enum, conflicts_with = "shards")]
+    pub storage: Option<Storage>,
+
+    /// Save the finished part of the build to disk every N seconds, so a build that is
+    /// interrupted (crash, Ctrl+C) resumes from there on the next run instead of
+    /// starting over. Files changed since their checkpoint are re-read (0 = off).
+    #[arg(long, default_value = "60")]
+    pub checkpoint_secs: u64,
+
+    /// Walk the tree and print file counts, estimated tokens, index size and RAM,
+    /// and the largest contributors without building anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Build priority: "low" caps worker threads to ~25% of cores and runs them
+    /// at background OS priority so the machine stays responsive
+    #[arg(long, value_enum, default_value = "normal")]
+    pub build_priority: BuildPriority,
+
+    /// Throttle file reads during the build to N MB/s (0 = unlimited)
+    #[arg(long, default_value = "0")]
+    pub io_limit_mb: u64,
+
+    /// Keep running after the build: watch the directory, apply file changes
+    /// incrementally and save the index periodically. Stop with Ctrl+C.
+    #[arg(long)]
+    pub watch: bool,
+
+    /// With --watch, save the index at most every N seconds when files changed (default: 60)
+    #[arg(long, default_value = "60")]
+    pub save_interval_secs: u64,
 }
 
 #[derive(Parser, Debug)]
@@ -155,6 +281,294 @@ pub struct CleanupArgs {
     pub dir: Option<String>,
 }
 
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"EXAMPLES:
+  Default (1000 C# files):  search gen-corpus --out /tmp/corpus
+  Large mixed corpus:       search gen-corpus --out /tmp/big --files 50000 --lang cs,ts
+  Flatter vocabulary:       search gen-corpus --out /tmp/flat --zipf 0.5 --vocabulary 50000
+
+The same options and seed always produce byte-identical files."#)]
+pub struct GenCorpusArgs {
+    /// Directory to write the corpus to (must be empty or missing unless --force)
+    #[arg(short, long)]
+    pub out: PathBuf,
+
+    /// Number of files to generate (one class each)
+    #[arg(long, default_value = "1000")]
+    pub files: usize,
+
+    /// Number of distinct vocabulary words identifiers are built from
+    #[arg(long, default_value = "5000")]
+    pub vocabulary: usize,
+
+    /// Zipf exponent of word frequencies (0 = uniform, 1 = typical code, higher = more skewed)
+    #[arg(long, default_value = "1.0")]
+    pub zipf: f64,
+
+    /// Languages to generate, assigned to files round-robin (comma-separated: cs, ts)
+    #[arg(long, default_value = "cs")]
+    pub lang: String,
+
+    /// Methods per class
+    #[arg(long, default_value = "8")]
+    pub methods: usize,
+
+    /// Average statements per method body
+    #[arg(long, default_value = "6")]
+    pub statements: usize,
+
+    /// Files per directory
+    #[arg(long, default_value = "100")]
+    pub files_per_dir: usize,
+
+    /// Random seed
+    #[arg(long, default_value = "42")]
+    pub seed: u64,
+
+    /// Write into a non-empty directory, overwriting files with the same names
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"EXAMPLES:
+  Record a session:  search serve --dir . --ext cs --definitions --audit-log audit.jsonl
+  Replay it:         search replay --log audit.jsonl --dir . --ext cs
+  JSON report:       search replay --log audit.jsonl --json
+  Ignore a field:    search replay --log audit.jsonl --ignore-field branchWarning
+
+Exits with status 1 when any replayed call returns a different result."#)]
+pub struct ReplayArgs {
+    /// Audit log written by `search serve --audit-log`
+    #[arg(long)]
+    pub log: PathBuf,
+
+    /// Directory the session was recorded against
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions of the content index to load (comma-separated)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Response size limit the session was recorded with, as `serve --max-response-kb`
+    #[arg(long, default_value = "16")]
+    pub max_response_kb: usize,
+
+    /// Also ignore this response field when comparing (repeatable). Fields ending in
+    /// `Ms`, `ageHours`, `sizeMb` and `_meta` are always ignored.
+    #[arg(long)]
+    pub ignore_field: Vec<String>,
+
+    /// Differences to print per changed call
+    #[arg(long, default_value = "5")]
+    pub max_diffs: usize,
+
+    /// Print a JSON report instead of text
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"EXAMPLES:
+  List suites:       search audit --dir .
+  Run a suite:       search audit release --dir . --ext cs
+  JSON report:       search --format json audit release
+
+SUITES (.search-index.toml in --dir):
+  [[audit.release]]
+  name = "No Thread.Sleep"
+  tool = "grep"                       # any MCP tool, "search_" optional
+  args = { terms = "thread.sleep", phrase = true }
+  expect = "zero"                     # default; "nonzero" requires a match
+
+  [[audit.release]]
+  name = "Controllers are authorized"
+  any = [{ tool = "grep", args = { terms = "authorize" } },
+         { tool = "definitions", args = { name = "AuthorizeAttribute" } }]
+  expect = "nonzero"
+
+NOTES:
+  - A check's count is the sum of its queries' totals (totalResults, totalFiles, ...)
+  - Uses the indexes on disk (content, and definitions when present); builds nothing
+
+Exits with status 1 when any check fails or errors."#)]
+pub struct AuditArgs {
+    /// Suite to run. Omit to list the configured suites
+    pub suite: Option<String>,
+
+    /// Directory whose config and indexes to use
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions of the content index to load (comma-separated)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"EXAMPLES:
+  8 agents at once:     search bench --dir . --ext cs --concurrent 8
+  While files change:   search bench --concurrent 8 --write-interval-ms 50
+  Own query terms:      search bench --concurrent 4 --terms "HttpClient,OrderService"
+  JSON report:          search --format json bench --concurrent 8
+
+Uses the indexes on disk (content, and definitions when present); builds nothing."#)]
+pub struct BenchArgs {
+    /// Directory whose indexes to query
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions of the content index to load (comma-separated)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Threads issuing calls at the same time
+    #[arg(long, default_value = "1")]
+    pub concurrent: usize,
+
+    /// Calls per thread
+    #[arg(long, default_value = "200")]
+    pub queries: usize,
+
+    /// Query terms (comma-separated). Default: the 20 tokens found in the most files
+    #[arg(long)]
+    pub terms: Option<String>,
+
+    /// Also take the index write locks this often, like watcher batches (0 = never)
+    #[arg(long, default_value = "0")]
+    pub write_interval_ms: u64,
+
+    /// Response size limit, as `serve --max-response-kb`
+    #[arg(long, default_value = "16")]
+    pub max_response_kb: usize,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"EXAMPLES:
+  To stdout:         search brief -d . -e cs
+  To a file:         search brief -d . -e cs,ts --out brief.md
+  Last quarter:      search brief -d . -e cs --days 90 --top 15
+  JSON:              search brief -d . -e cs --format json
+
+SECTIONS:
+  Structure          Files and definitions per top-level directory, extension and kind
+  Key types          Classes and interfaces referenced from the most files (content index)
+  Entry points       main functions, Program/Startup, controllers, serverless functions
+  Hotspots           Files ranked by git churn x complexity
+  Complexity         Methods with the highest cyclomatic complexity
+  Recent activity    Most changed files and most active authors in the last --days
+
+NOTES:
+  - Requires a definition index (search def-index); a content index for the same
+    --dir and --ext adds usage counts to key types
+  - Git sections use the git history cache and are skipped outside a git repository
+"#)]
+pub struct BriefArgs {
+    /// Repository root whose indexes to use.
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// Extensions of the definition (and content) index to use.
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Write the brief to this file instead of stdout.
+    #[arg(short, long)]
+    pub out: Option<String>,
+
+    /// Entries per ranked section.
+    #[arg(long, default_value = "10")]
+    pub top: usize,
+
+    /// Days of git history in the recent activity section.
+    #[arg(long, default_value = "30")]
+    pub days: u64,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"EXAMPLES:
+  To stdout:         search metrics -d . -e cs
+  Per subdirectory:  search metrics -d . -e cs,ts --depth 2 --top 20
+  HTML report:       search metrics -d . -e cs --format html --out metrics.html
+  JSON:              search metrics -d . -e cs --format json
+
+SECTIONS:
+  Totals             Files, lines, definitions, methods, average/max complexity
+  Directories        The same per directory, down to --depth levels
+  Largest classes    Classes by lines, with member count, WMC and LCOM
+  Most complex       Methods with the highest cyclomatic complexity
+  Most called        Methods with the most call sites in the index
+
+NOTES:
+  - Requires a definition index (search def-index); nothing is rebuilt
+  - Lines are the physical lines of the indexed files, read from disk
+"#)]
+pub struct MetricsArgs {
+    /// Repository root whose definition index to use.
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// Extensions of the definition index to use.
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Write the report to this file instead of stdout.
+    #[arg(short, long)]
+    pub out: Option<String>,
+
+    /// Entries per ranked section.
+    #[arg(long, default_value = "10")]
+    pub top: usize,
+
+    /// Directory levels below the root to aggregate by.
+    #[arg(long, default_value = "1")]
+    pub depth: usize,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"EXAMPLES:
+  To stdout:     search dump-schema
+  To a file:     search dump-schema -o schema.json
+
+Prints the input schema of every MCP tool and the layout of every index file
+(struct fields in on-disk order, with their Rust types), generated from the code."#)]
+pub struct DumpSchemaArgs {
+    /// Write the schema to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"KEYS:
+  typing             Edit the query; results update as you type
+  Up/Down, Ctrl-P/N  Select a file (PgUp/PgDn, Home/End to jump)
+  Left/Right         Step through the matches of the selected file
+  Tab                Cycle query mode: substring, exact, phrase, regex
+  Ctrl-W / Ctrl-U    Delete a word / clear the query
+  Enter              Open the file at the match in $VISUAL or $EDITOR
+  Esc, Ctrl-C        Quit
+
+EXAMPLES:
+  search tui -d C:\Projects\MyApp -e cs
+  search tui HttpClient"#)]
+pub struct TuiArgs {
+    /// Initial query
+    pub query: Option<String>,
+
+    /// Directory whose content index to search
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// Extensions of the content index to load (comma-separated; default: any index of the directory)
+    #[arg(short, long, default_value = "")]
+    pub ext: String,
+
+    /// Files to list per query
+    #[arg(long, default_value = "200")]
+    pub max_results: usize,
+}
+
 #[derive(Parser, Debug)]
 #[command(after_long_help = r#"WHAT IS MCP:
   Model Context Protocol (MCP) is a JSON-RPC 2.0 protocol over stdio that
@@ -169,6 +583,7 @@ EXAMPLES:
   With defs:      search serve --dir C:\Projects --ext cs --watch --definitions
   TS defs:        search serve --dir C:\Projects --ext ts,tsx --watch --definitions
   Custom debounce: search serve --dir . --ext rs --watch --debounce-ms 1000
+  Laptop-friendly: search serve --dir . --ext cs --watch --build-priority low --io-limit-mb 50
 
 VS CODE CONFIGURATION (.vscode/mcp.json):
   {
@@ -195,13 +610,36 @@ AVAILABLE TOOLS (exposed via MCP):
   search_find        -- Live filesystem search (no index, slow for large dirs)
   search_fast        -- Search file name index (instant)
   search_info        -- Show all indexes
-  search_reindex     -- Force rebuild + reload index
+  search_metrics     -- Call counts, latency histograms, watcher activity (JSON or Prometheus)
+  search_history     -- Recent calls of this session with result counts (empty ones to refine)
+  search_reindex     -- Rebuild the content index in the background, swap it in when done
+  search_reindex_status -- Progress and result of a background search_reindex job
   search_git_history -- Commit history for a file (cached or git CLI)
   search_git_diff    -- Commit history with full diff/patch for a file
   search_git_authors -- Top authors for a file ranked by commit count
   search_git_activity-- Repo-wide activity (all changed files) for a date range
   search_git_blame   -- Line-by-line git blame for a file or line range
+  search_git_grep    -- Commits whose message or diff contains a term (which commit introduced X)
+  search_git_added_lines -- Lines added since a ref that contain a term (release audits)
   search_branch_status-- Show current git branch status, behind/ahead counts, dirty files
+  search_read_file   -- Read a line/byte range or a definition body (budgeted)
+  search_outline     -- Nested definition tree (classes, members, lines) of one file
+  search_references  -- All usages of a symbol, classified (call/instantiation/inheritance/type)
+  search_symbol_at   -- Identifier at file:line:column with its definition and references
+  search_type_hierarchy -- Base types and subclasses/implementations of a type (tree)
+  search_dependencies -- What a file depends on, what depends on it, and import cycles
+  search_impact      -- Test methods that reach a method or class through its callers
+  search_di_registrations -- C# DI registrations: lifetime, service, implementation
+  search_endpoints   -- ASP.NET routes ([Route]/[HttpGet], MapGet) and their handler methods
+  search_namespaces  -- Namespace tree with file/type counts; types declared in a namespace
+  search_dupes       -- Near-duplicate method bodies (clone pairs with line ranges)
+  search_vocabulary  -- Most widespread identifiers, filterable, to check names before grepping
+  search_todos       -- TODO/FIXME/HACK/BUG comments, filterable by marker, dir, author, age
+  search_hotspots    -- Files/methods ranked by git churn x complexity, with top authors
+  search_tickets     -- Code comments and commits that mention a ticket ID (PAY-812, #77)
+  search_density     -- Hits of a term per line region of each file, densest regions first
+  search_audit       -- Run a saved [audit] suite of .search-index.toml: pass/fail per check
+  search_replace_preview -- Diff preview of a search-and-replace (writes nothing)
   search_help        -- Show tips and best practices for effective search tool usage
   search_reindex_definitions -- Re-index code definitions (tree-sitter). Requires --definitions
 
@@ -219,7 +657,8 @@ pub struct ServeArgs {
     #[arg(short, long, default_value = ".")]
     pub dir: String,
 
-    /// File extensions to index, comma-separated.
+    /// File extensions to index, comma-separated, or "auto" to pick the most common
+    /// text extensions from a sample of the tree.
     #[arg(short, long, default_value = "cs")]
     pub ext: String,
 
@@ -236,9 +675,20 @@ pub struct ServeArgs {
     pub log_level: String,
 
     /// If more than N files change in one debounce window, do a full reindex.
+    /// When HEAD moved (checkout, pull), only the files changed between the two commits are reindexed.
     #[arg(long, default_value = "100")]
     pub bulk_threshold: usize,
 
+    /// How --watch detects changes: "native" OS notifications, "poll" (rescan sizes and
+    /// mtimes every --poll-interval-ms, for SMB/NFS shares and Docker volumes where
+    /// notifications are unreliable), or "auto" (poll on those file systems, native elsewhere).
+    #[arg(long, value_enum, default_value = "auto")]
+    pub watch_mode: WatchMode,
+
+    /// Rescan interval in ms for --watch-mode poll.
+    #[arg(long, default_value = "2000")]
+    pub poll_interval_ms: u64,
+
     /// Also load (or build) a code definition index using tree-sitter.
     #[arg(long)]
     pub definitions: bool,
@@ -247,6 +697,11 @@ pub struct ServeArgs {
     #[arg(long)]
     pub metrics: bool,
 
+    /// Record how long each query waits for the content, definition and git history
+    /// locks, and report p95/max wait per lock in search_info (`lockWait`). Diagnostic.
+    #[arg(long)]
+    pub lock_metrics: bool,
+
     /// Maximum response size in KB before truncation (0 = no limit, default: 16).
     /// Prevents large search results from filling the LLM context window.
     #[arg(long, default_value = "16")]
@@ -256,6 +711,47 @@ pub struct ServeArgs {
     /// in the index directory (memory.log). Useful for diagnosing memory spikes.
     #[arg(long)]
     pub memory_log: bool,
+
+    /// Priority for startup index builds (when no index is cached on disk).
+    /// "low" caps worker threads to ~25% of cores at background OS priority.
+    #[arg(long, value_enum, default_value = "normal")]
+    pub build_priority: BuildPriority,
+
+    /// Priority for full rebuilds triggered by the file watcher (--bulk-threshold exceeded).
+    #[arg(long, value_enum, default_value = "low")]
+    pub watch_build_priority: BuildPriority,
+
+    /// Throttle file reads during background builds to N MB/s (0 = unlimited).
+    #[arg(long, default_value = "0")]
+    pub io_limit_mb: u64,
+
+    /// Skip files larger than this many bytes in startup builds (when no index is
+    /// cached on disk); K, M and G suffixes are accepted. A loaded index keeps the
+    /// limit it was built with, for watcher updates and reindexes too.
+    #[arg(long, default_value = "0", value_parser = parse_size)]
+    pub max_file_size: u64,
+
+    /// Ownership file for annotating results with owners (CODEOWNERS syntax, or a flat
+    /// `pattern: owner` owners.yaml). Default: CODEOWNERS discovered under --dir
+    /// (.github/, root, docs/, .gitlab/).
+    #[arg(long)]
+    pub owners: Option<String>,
+
+    /// WebAssembly ranking policy: re-scores search_grep and search_definitions results
+    /// after the built-in ranking (ABI: user-stories/wasm-ranking-hook-design.md)
+    #[arg(long)]
+    pub rank_hook: Option<PathBuf>,
+
+    /// Escape all non-ASCII characters in responses as \uXXXX, for clients that read
+    /// stdout in a legacy code page. A request can override this with
+    /// `params._meta.asciiOutput`.
+    #[arg(long)]
+    pub ascii_json: bool,
+
+    /// Append every tools/call (tool, arguments and result) to this file as JSON lines,
+    /// for `search replay`
+    #[arg(long)]
+    pub audit_log: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -266,11 +762,15 @@ pub struct ServeArgs {
   Regex:           search grep "i.*cache" -d C:\Projects -e cs --regex
   Regex + lines:   search grep ".*factory" -d C:\Projects -e cs --regex --show-lines
   Top 10 results:  search grep "HttpClient" -d C:\Projects --max-results 10
+  Why it ranked:   search grep "HttpClient" -d . -e cs --max-results 5 --explain
   Exclude dirs:    search grep "HttpClient" -d . -e cs --exclude-dir test --exclude-dir E2E
   Exclude files:   search grep "HttpClient" -d . -e cs --exclude Mock
   Context lines:   search grep "HttpClient" -d . -e cs --show-lines -C 3
   Before/after:    search grep "HttpClient" -d . -e cs --show-lines -B 2 -A 5
   Exact tokens:    search grep "UserService" -d C:\Projects -e cs --exact
+  Old revision:    search grep "UserService" -d C:\Projects -e cs --rev v1.2.0
+  Vim quickfix:    search grep "Sleep" -d . -e cs --exact --format quickfix > qf.txt
+  CI report:       search grep "ServicePointManager" -d . -e cs --format junit --rule no-spm --severity error
 
 NOTES:
   - Requires a content index. Build one first:
@@ -283,6 +783,11 @@ NOTES:
   - Use --show-lines to see actual source code lines from matching files
   - --exclude-dir and --exclude filter results by path substring (case-insensitive)
   - Context lines (-C/-B/-A) show surrounding code, like grep -C
+  - --sort path|mtime|matches overrides TF-IDF order (path = deterministic for CI diffs)
+  - --rev indexes the files of that commit from git (cached per commit), so results
+    and --show-lines reflect the historical code, not the working tree
+  - --format quickfix prints one match per line for vim (:cfile) and editors;
+    --format junit prints one failing test case per matching file for CI
 "#)]
 pub struct GrepArgs {
     /// Search term(s). Comma-separated for multi-term.
@@ -300,6 +805,10 @@ pub struct GrepArgs {
     #[arg(long)]
     pub show_lines: bool,
 
+    /// Wrap matches in `<mark>`...`</mark>` in --show-lines output.
+    #[arg(long)]
+    pub highlight: bool,
+
     /// Automatically rebuild index if stale.
     #[arg(long, default_value = "true")]
     pub auto_reindex: bool,
@@ -350,4 +859,348 @@ pub struct GrepArgs {
     /// Use --exact to search for exact tokens only.
     #[arg(long)]
     pub exact: bool,
-}
\ No newline at end of file
+
+    /// Match terms in the case typed (`ID` but not `id`). The index is case-insensitive,
+    /// so candidate lines are read and checked. Not with --regex.
+    #[arg(long, conflicts_with = "regex")]
+    pub case_sensitive: bool,
+
+    /// Substring matches only from one identifier-part boundary to another (camelCase
+    /// hump, `_`, digit, start/end): `cat` finds `CatName` but not `concatenate`.
+    #[arg(long, conflicts_with_all = ["exact", "regex", "phrase"])]
+    pub word_boundary: bool,
+
+    /// Result order: score (TF-IDF, default), path (stable for diffs), mtime (newest first), matches
+    #[arg(long, value_enum, default_value = "score")]
+    pub sort: GrepSort,
+
+    /// Aggregate matches per directory instead of listing files: files, occurrences and
+    /// the top files of each, most occurrences first. --max-results limits the groups.
+    #[arg(long, value_enum, conflicts_with = "count")]
+    pub group_by: Option<GrepGroup>,
+
+    /// With --group-by dir: group by the first N directory levels under the index root
+    /// (0 = each file's own directory).
+    #[arg(long, default_value = "0", requires = "group_by")]
+    pub group_depth: usize,
+
+    /// Show why each file ranked where it did: per-token tf, idf and contribution to
+    /// the TF-IDF score. Not with --phrase.
+    #[arg(long, conflicts_with = "phrase")]
+    pub explain: bool,
+
+    /// Rank by plain TF-IDF, without the file name, test and generated-code factors
+    /// of `[ranking]` in .search-index.toml
+    #[arg(long)]
+    pub no_boost: bool,
+
+    /// WebAssembly ranking policy to re-score the ranked files with, as `serve
+    /// --rank-hook` does. Not with --phrase.
+    #[arg(long, conflicts_with = "phrase")]
+    pub rank_hook: Option<PathBuf>,
+
+    /// Score factors, from `[ranking]` in .search-index.toml
+    #[arg(skip)]
+    pub ranking: RankingBoosts,
+
+    /// Search the code as of this git revision (commit, branch, tag) instead of the
+    /// working tree. Requires --ext; the index for each commit is built once and cached.
+    #[arg(long)]
+    pub rev: Option<String>,
+
+    /// Output format, set by the global `--format` (which also takes quickfix and junit
+    /// for grep).
+    #[arg(skip)]
+    pub format: OutputFormat,
+
+    /// Rule name reported with quickfix/junit findings (default: the pattern).
+    #[arg(long)]
+    pub rule: Option<String>,
+
+    /// Severity reported with quickfix/junit findings.
+    #[arg(long, value_enum, default_value = "warning")]
+    pub severity: Severity,
+
+    /// Also write the summary (counts, timing, exit code) as JSON to this file, for CI
+    #[arg(long, value_name = "PATH")]
+    pub summary_json: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Lists TODO, FIXME, HACK and BUG comments recorded when the content index was built.
+  With --blame, adds author, date and age from git blame (one blame per file).
+
+EXAMPLES:
+  All markers:       search todos -d C:\Projects -e cs
+  FIXMEs in a dir:   search todos -d . -e rs --marker FIXME --path src/mcp
+  Old debt:          search todos -d . -e cs --min-age-days 365 --sort age
+  By author:         search todos -d . -e cs --author alice
+  JSON output:       search todos -d . -e rs --blame --json
+
+NOTES:
+  - Requires a content index built by this version (older indexes record no markers)
+  - Markers count only inside comments and as whole upper-case words
+  - --author, --min-age-days and --max-age-days imply --blame
+"#)]
+pub struct TodosArgs {
+    /// Directory whose content index to read.
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// Extensions of the content index to use.
+    #[arg(short, long)]
+    pub ext: Option<String>,
+
+    /// Comma-separated markers to include: TODO, FIXME, HACK, BUG (default: all).
+    #[arg(long)]
+    pub marker: Option<String>,
+
+    /// Only files under this directory (absolute, or relative to --dir).
+    #[arg(long)]
+    pub path: Option<String>,
+
+    /// Exclude directories by substring.
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub exclude_dir: Vec<String>,
+
+    /// Add author, date and age from git blame.
+    #[arg(long)]
+    pub blame: bool,
+
+    /// Only markers whose author name or email contains this.
+    #[arg(long)]
+    pub author: Option<String>,
+
+    /// Only markers at least this many days old.
+    #[arg(long)]
+    pub min_age_days: Option<u64>,
+
+    /// Only markers at most this many days old.
+    #[arg(long)]
+    pub max_age_days: Option<u64>,
+
+    /// Order: path (default) or age (oldest first, needs blame).
+    #[arg(long, default_value = "path")]
+    pub sort: String,
+
+    /// Maximum markers to display (0 = all).
+    #[arg(long, default_value = "0")]
+    pub max_results: usize,
+
+    /// Print the search_todos JSON response.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Ranks files (or methods) by churn x complexity: commits touching the file in git
+  history times cyclomatic (or cognitive) complexity from the definition index.
+  Each hotspot lists its top authors. High churn on complex code is where
+  refactoring pays off first.
+
+EXAMPLES:
+  Top files:         search hotspots -d C:\Projects\MyApp -e cs
+  Top methods:       search hotspots -d . -e cs --level method --metric cognitive
+  Last year only:    search hotspots -d . -e ts --from 2025-10-01
+  One area:          search hotspots -d . -e cs --path src/Payments --max-results 10
+  JSON output:       search hotspots -d . -e cs --json
+
+NOTES:
+  - Requires a definition index with code stats (search def-index) and --dir at a git repository root
+  - Uses the git history cache shared with 'search serve'; builds it on first use
+  - Method churn is the churn of the containing file (history is recorded per file)
+  - CODEOWNERS under --dir adds owners to each hotspot and enables --owner
+"#)]
+pub struct HotspotsArgs {
+    /// Repository root whose definition index and git history to use.
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// Extensions of the definition index to use.
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Rank "file" (default) or "method".
+    #[arg(long, default_value = "file")]
+    pub level: String,
+
+    /// Complexity metric: cyclomatic (default) or cognitive.
+    #[arg(long, default_value = "cyclomatic")]
+    pub metric: String,
+
+    /// Count commits from this date (YYYY-MM-DD).
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Count commits up to this date (YYYY-MM-DD).
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Only files under this directory (absolute, or relative to --dir).
+    #[arg(long)]
+    pub path: Option<String>,
+
+    /// Exclude directories by substring.
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub exclude_dir: Vec<String>,
+
+    /// Only files owned by this team/user per CODEOWNERS.
+    #[arg(long)]
+    pub owner: Option<String>,
+
+    /// Authors listed per hotspot.
+    #[arg(long, default_value = "3")]
+    pub top_authors: usize,
+
+    /// Maximum hotspots to display (0 = all).
+    #[arg(long, default_value = "20")]
+    pub max_results: usize,
+
+    /// Print the search_hotspots JSON response.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Maps an issue-tracker ID to the code and commits that mention it: comments
+  referencing the ticket (recorded when the content index was built) and commits
+  whose subject references it, with the files each commit changed.
+
+EXAMPLES:
+  JIRA key:          search tickets PAY-812 -d C:\Projects\MyApp -e cs
+  GitHub issue:      search tickets 77 -d . -e rs
+  One area:          search tickets PAY-812 -d . -e cs --path src/Payments
+  JSON output:       search tickets '#77' -d . -e rs --json
+
+NOTES:
+  - Recognizes upper-case tracker keys (PAY-812) and issue numbers (#77) in comments
+  - Keys are matched case-insensitively; 77 and #77 are the same ticket
+  - Requires a content index built by this version (older indexes record no tickets)
+  - Commits come from the git history cache shared with 'search serve'; skipped outside a git repository
+"#)]
+pub struct TicketsArgs {
+    /// Ticket ID: a tracker key like PAY-812 or an issue number like #77.
+    pub ticket: String,
+
+    /// Directory whose content index and git history to use.
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// Extensions of the content index to use.
+    #[arg(short, long)]
+    pub ext: Option<String>,
+
+    /// Only code under this directory (absolute, or relative to --dir).
+    #[arg(long)]
+    pub path: Option<String>,
+
+    /// Exclude directories by substring.
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub exclude_dir: Vec<String>,
+
+    /// Only code owned by this team/user per CODEOWNERS.
+    #[arg(long)]
+    pub owner: Option<String>,
+
+    /// Maximum files and commits to display (0 = all).
+    #[arg(long, default_value = "50")]
+    pub max_results: usize,
+
+    /// Print the search_tickets JSON response.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Replaces text across the files of a content index. The index picks the
+  candidate files; each match is then verified against the current file text.
+  Without --yes it only prints a unified diff of what would change.
+
+  --yes writes the changes. Every file is backed up first into a journal under
+  the index directory, and each file is replaced atomically (temp file + rename).
+  Files that changed since the preview are skipped. --undo restores the files of
+  the latest journal for --dir, unless they were edited again after the replace.
+
+EXAMPLES:
+  Preview:           search replace OldName NewName -d C:\Projects\MyApp -e cs
+  Apply:             search replace OldName NewName -d . -e cs --yes
+  Whole words:       search replace Id Key -d . -e cs -w --path src/Models
+  Regex groups:      search replace 'Get(\w+)Async' 'Fetch${1}Async' -d . -e cs --regex
+  Undo:              search replace --undo -d .
+
+NOTES:
+  - A literal pattern is matched case-sensitively unless -i is given
+  - --regex scans every indexed file instead of using the index to narrow them
+  - The index is not updated: 'search serve' picks the edits up through its watcher,
+    otherwise rebuild with 'search content-index'
+"#)]
+pub struct ReplaceArgs {
+    /// Text to replace (literal unless --regex).
+    #[arg(required_unless_present = "undo")]
+    pub pattern: Option<String>,
+
+    /// Replacement text. With --regex, $1 and ${name} insert capture groups.
+    #[arg(required_unless_present = "undo")]
+    pub replacement: Option<String>,
+
+    /// Directory whose content index to use.
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// Extensions of the content index to use, and of the files to edit.
+    #[arg(short, long)]
+    pub ext: Option<String>,
+
+    /// Only files under this directory (absolute, or relative to --dir).
+    #[arg(long)]
+    pub path: Option<String>,
+
+    /// Treat the pattern as a regular expression.
+    #[arg(long)]
+    pub regex: bool,
+
+    /// Case-insensitive match.
+    #[arg(short, long)]
+    pub ignore_case: bool,
+
+    /// Only whole-word matches.
+    #[arg(short, long)]
+    pub word: bool,
+
+    /// Unchanged lines shown around each change in the diff.
+    #[arg(short = 'C', long, default_value = "3")]
+    pub context: usize,
+
+    /// Write the changes (default: preview only).
+    #[arg(long, conflicts_with = "undo")]
+    pub yes: bool,
+
+    /// Restore the files changed by the latest applied replace in --dir.
+    #[arg(long)]
+    pub undo: bool,
+}
+
+/// Parse a byte count with an optional K/M/G suffix (powers of 1024, case-insensitive).
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let multiplier = match c.to_ascii_uppercase() {
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                _ => return Err(format!("unknown size suffix '{}' (use K, M or G)", c)),
+            };
+            (&s[..i], multiplier)
+        }
+        _ => (s, 1),
+    };
+    digits.trim().parse::<u64>()
+        .map(|n| n.saturating_mul(multiplier))
+        .map_err(|_| format!("invalid size '{}'", s))
+}
+
@@ -0,0 +1,69 @@
+//! `search audit`: run an `[audit]` suite of `.search-index.toml` against the indexes
+//! on disk and print a pass/fail report, for CI.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::config::RepoConfig;
+use crate::mcp::handlers::{list_suites, run_suite, unknown_suite};
+use crate::{index_dir, SearchError, SystemClock};
+
+use super::args::AuditArgs;
+use super::output::{print_json, OutputFormat};
+use super::replay::replay_context;
+
+pub(crate) fn cmd_audit(args: AuditArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let root = std::fs::canonicalize(&args.dir).unwrap_or_else(|_| PathBuf::from(&args.dir));
+    let config = RepoConfig::load(&root).map_err(SearchError::InvalidArgs)?;
+    let Some(name) = args.suite.as_deref() else {
+        let suites = list_suites(&config);
+        if format.is_json() {
+            print_json(format, &suites, &["suites"]);
+        } else {
+            for suite in suites["suites"].as_array().into_iter().flatten() {
+                println!("{} ({} checks)", suite["name"].as_str().unwrap_or_default(), suite["checks"].as_array().map_or(0, |c| c.len()));
+            }
+            if let Some(hint) = suites["hint"].as_str() {
+                eprintln!("{}", hint);
+            }
+        }
+        return Ok(());
+    };
+    let suite = config.audit_suite(name).ok_or_else(|| SearchError::InvalidArgs(unknown_suite(&config, name)))?;
+
+    let ctx = replay_context(&args.dir, &args.ext, 16, index_dir(), Arc::new(SystemClock))?;
+    let (report, passed) = run_suite(&ctx, suite);
+    if format.is_json() {
+        print_json(format, &report, &["checks"]);
+    } else {
+        for check in report["checks"].as_array().into_iter().flatten() {
+            print_check(check);
+        }
+    }
+    let summary = &report["summary"];
+    eprintln!("Suite {}: {} passed, {} failed, {} errors in {}ms",
+        suite.name, summary["passed"], summary["failed"], summary["errors"], summary["elapsedMs"]);
+    if !passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `PASS  name (expect zero, found 0)`, and each query's count or error when it fails.
+fn print_check(check: &Value) {
+    let status = check["status"].as_str().unwrap_or_default();
+    println!("{:<5} {} (expect {}, found {})", status.to_uppercase(), check["name"].as_str().unwrap_or_default(),
+        check["expect"].as_str().unwrap_or_default(), check["count"]);
+    if status == "pass" {
+        return;
+    }
+    for query in check["queries"].as_array().into_iter().flatten() {
+        let outcome = match query["error"].as_str() {
+            Some(error) => format!("error: {}", error),
+            None => format!("{}", query["count"]),
+        };
+        println!("      {} {}: {}", query["tool"].as_str().unwrap_or_default(), query["arguments"], outcome);
+    }
+}
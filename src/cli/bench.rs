@@ -0,0 +1,203 @@
+//! `search bench`: drive MCP tool calls from several threads at once against the
+//! indexes on disk, and report throughput, latency per tool and lock wait per query.
+//!
+//! Each of `--concurrent` threads runs `--queries` calls from the same mix (grep,
+//! substring grep and, with a definition index, definitions and callers), starting at a
+//! different point of it. `--write-interval-ms` adds a thread that takes the write locks
+//! the way watcher batches do, so the numbers show what agents see while files change.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::mcp::handlers::{self, HandlerContext};
+use crate::{index_dir, SearchError, SystemClock};
+use search::lock_stats;
+
+use super::args::BenchArgs;
+use super::output::{print_json, OutputFormat};
+use super::replay::replay_context;
+
+/// Index tokens used as query terms when `--terms` is not given
+const DEFAULT_TERMS: usize = 20;
+
+pub(crate) struct BenchReport {
+    pub threads: usize,
+    pub elapsed: Duration,
+    /// Latency of every call in ms, per tool
+    pub latencies: BTreeMap<String, Vec<f64>>,
+    pub errors: usize,
+    pub writes: usize,
+}
+
+pub(crate) fn cmd_bench(args: BenchArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let ctx = replay_context(&args.dir, &args.ext, args.max_response_kb, index_dir(), Arc::new(SystemClock))?;
+    let terms: Vec<String> = match &args.terms {
+        Some(terms) => terms.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+        None => widespread_tokens(&ctx, DEFAULT_TERMS),
+    };
+    if terms.is_empty() {
+        return Err(SearchError::InvalidArgs("No query terms: the index is empty and --terms was not given".to_string()));
+    }
+    let mix = query_mix(&terms, ctx.def_index.is_some());
+    lock_stats::enable();
+    eprintln!("Running {} calls on each of {} threads...", args.queries, args.concurrent.max(1));
+    let report = run_bench(&ctx, &mix, args.concurrent, args.queries, args.write_interval_ms);
+    let output = report_json(&report, lock_stats::summary());
+
+    if format.is_json() {
+        print_json(format, &output, &["tools"]);
+        return Ok(());
+    }
+    println!("{} calls on {} threads in {:.2}s: {:.0} calls/s, {} errors, {} writes",
+        output["calls"], report.threads, report.elapsed.as_secs_f64(), output["callsPerSec"].as_f64().unwrap_or(0.0),
+        report.errors, report.writes);
+    for tool in output["tools"].as_array().into_iter().flatten() {
+        println!("  {:<20} p50 {:>8.2}ms  p95 {:>8.2}ms  max {:>8.2}ms  ({} calls)",
+            tool["tool"].as_str().unwrap_or_default(), tool["p50Ms"].as_f64().unwrap_or(0.0),
+            tool["p95Ms"].as_f64().unwrap_or(0.0), tool["maxMs"].as_f64().unwrap_or(0.0), tool["calls"]);
+    }
+    println!("Lock wait per call:");
+    for (lock, wait) in output["lockWait"].as_object().into_iter().flatten() {
+        println!("  {:<20} p95 {:>8.2}ms  max {:>8.2}ms  ({} calls)", lock,
+            wait["p95Ms"].as_f64().unwrap_or(0.0), wait["maxMs"].as_f64().unwrap_or(0.0), wait["queries"]);
+    }
+    Ok(())
+}
+
+/// The `count` tokens found in the most files.
+fn widespread_tokens(ctx: &HandlerContext, count: usize) -> Vec<String> {
+    let index = ctx.index.snapshot();
+    let mut tokens: Vec<(&str, usize)> = index.index.iter().map(|(t, postings)| (&**t, postings.len())).collect();
+    tokens.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    tokens.into_iter().take(count).map(|(t, _)| t.to_string()).collect()
+}
+
+/// `(tool, arguments)` calls the threads cycle through.
+fn query_mix(terms: &[String], definitions: bool) -> Vec<(&'static str, Value)> {
+    let mut mix = Vec::new();
+    for term in terms {
+        mix.push(("search_grep", json!({ "terms": term })));
+        mix.push(("search_grep", json!({ "terms": term, "substring": true })));
+        if definitions {
+            mix.push(("search_definitions", json!({ "name": term })));
+            mix.push(("search_callers", json!({ "method": term, "depth": 2 })));
+        }
+    }
+    mix
+}
+
+/// Run `queries` calls of `mix` on each of `threads` threads (at least one). With a
+/// `write_interval_ms`, another thread marks the content index changed and takes the
+/// definition write lock that often until the calls are done.
+pub(crate) fn run_bench(ctx: &HandlerContext, mix: &[(&str, Value)], threads: usize, queries: usize, write_interval_ms: u64) -> BenchReport {
+    let threads = threads.max(1);
+    let done = AtomicBool::new(false);
+    let start = Instant::now();
+    let (results, writes) = std::thread::scope(|scope| {
+        let writer = (write_interval_ms > 0).then(|| scope.spawn(|| {
+            let mut writes = 0;
+            while !done.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(write_interval_ms));
+                ctx.index.update(|idx| idx.trigram_dirty = true);
+                if let Some(def) = &ctx.def_index {
+                    drop(lock_stats::timed(lock_stats::Lock::Definitions, || def.write()));
+                }
+                writes += 1;
+            }
+            writes
+        }));
+        let workers: Vec<_> = (0..threads).map(|t| scope.spawn(move || {
+            (0..queries).map(|i| {
+                let (tool, arguments) = &mix[(t * mix.len() / threads + i) % mix.len()];
+                let call_start = Instant::now();
+                let result = handlers::dispatch_tool(ctx, tool, arguments);
+                (*tool, call_start.elapsed().as_secs_f64() * 1000.0, result.is_error)
+            }).collect::<Vec<_>>()
+        })).collect();
+        let results: Vec<_> = workers.into_iter().flat_map(|w| w.join().unwrap()).collect();
+        done.store(true, Ordering::Relaxed);
+        (results, writer.map_or(0, |w| w.join().unwrap()))
+    });
+
+    let mut latencies: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut errors = 0;
+    for (tool, ms, is_error) in results {
+        latencies.entry(tool.to_string()).or_default().push(ms);
+        errors += is_error as usize;
+    }
+    BenchReport { threads, elapsed: start.elapsed(), latencies, errors, writes }
+}
+
+fn report_json(report: &BenchReport, lock_wait: Option<Value>) -> Value {
+    let calls: usize = report.latencies.values().map(Vec::len).sum();
+    let round = |ms: f64| (ms * 100.0).round() / 100.0;
+    let tools: Vec<Value> = report.latencies.iter().map(|(tool, latencies)| {
+        let mut sorted = latencies.clone();
+        sorted.sort_unstable_by(f64::total_cmp);
+        let at = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        json!({
+            "tool": tool,
+            "calls": sorted.len(),
+            "p50Ms": round(at(50)),
+            "p95Ms": round(at(95)),
+            "maxMs": round(sorted[sorted.len() - 1]),
+        })
+    }).collect();
+    json!({
+        "threads": report.threads,
+        "calls": calls,
+        "errors": report.errors,
+        "writes": report.writes,
+        "elapsedMs": round(report.elapsed.as_secs_f64() * 1000.0),
+        "callsPerSec": round(calls as f64 / report.elapsed.as_secs_f64().max(f64::EPSILON)),
+        "tools": tools,
+        "lockWait": lock_wait.unwrap_or_else(|| json!({})),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_runs_concurrent_calls_alongside_writes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let idx_base = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("a.cs"), "class Alpha { HttpClient client; }\n").unwrap();
+        std::fs::write(tmp.path().join("b.cs"), "class Beta { HttpClient other; }\n").unwrap();
+        let dir = tmp.path().to_string_lossy().to_string();
+        let index = crate::build_content_index(&crate::ContentIndexArgs {
+            dir: dir.clone(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        });
+        crate::save_content_index(&index, idx_base.path()).unwrap();
+        let ctx = replay_context(&dir, "cs", 16, idx_base.path().to_path_buf(), Arc::new(SystemClock)).unwrap();
+
+        let terms = widespread_tokens(&ctx, 2);
+        assert_eq!(terms, vec!["class", "httpclient"], "in both files, then alphabetical");
+        let mix = query_mix(&terms, false);
+        assert_eq!(mix.len(), 4);
+        let report = run_bench(&ctx, &mix, 3, 20, 1);
+        assert_eq!(report.errors, 0);
+        let output = report_json(&report, None);
+        assert_eq!(output["calls"], 60);
+        assert_eq!(output["tools"][0]["tool"], "search_grep");
+        assert!(output["tools"][0]["p50Ms"].as_f64().unwrap() <= output["tools"][0]["maxMs"].as_f64().unwrap());
+    }
+}
@@ -0,0 +1,388 @@
+//! `search brief`: a generated project brief for onboarding (people and agents),
+//! assembled from the definition index, its code stats, the content index and the
+//! git history cache.
+//!
+//! [`brief_json`] collects every section as JSON; [`render_markdown`] turns that into
+//! the Markdown document, so `--format json` and the default output carry the same data.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::definitions::{self, DefinitionIndex, DefinitionKind};
+use crate::git::cache::GitHistoryCache;
+use crate::mcp::handlers::{format_timestamp, search_hotspots_json};
+use crate::{index_dir, load_content_index, relative_path, ContentIndex, SearchError};
+
+use super::args::BriefArgs;
+use super::output::OutputFormat;
+
+/// Kinds listed as key types
+const TYPE_KINDS: &[DefinitionKind] = &[DefinitionKind::Class, DefinitionKind::Interface, DefinitionKind::Struct, DefinitionKind::Record];
+
+/// Attributes (without arguments) that mark a method as an HTTP endpoint
+const ENDPOINT_ATTRIBUTES: &[&str] = &["HttpGet", "HttpPost", "HttpPut", "HttpPatch", "HttpDelete", "Route", "Get", "Post", "Put", "Patch", "Delete"];
+
+pub(crate) fn cmd_brief(args: BriefArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+    let index = definitions::load_definition_index(&args.dir, &exts, &idx_base)
+        .map_err(|_| SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext)))?;
+    let content = load_content_index(&args.dir, &exts, &idx_base).ok();
+    let git = GitHistoryCache::load_or_build(&args.dir, &idx_base);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+
+    let brief = brief_json(&index, content.as_ref(), git.as_ref(), args.top, args.days, now);
+    let text = if format.is_json() {
+        serde_json::to_string_pretty(&brief).unwrap() + "\n"
+    } else {
+        render_markdown(&brief)
+    };
+    match &args.out {
+        Some(path) => {
+            std::fs::write(path, text)?;
+            eprintln!("Wrote the brief of {} ({} files, {} definitions) to {}",
+                index.root, index.files.len(), index.definitions.len(), path);
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Every section of the brief. `git` is the history cache, or why it is unavailable;
+/// `now` (Unix seconds) anchors the recent activity window of `days`.
+pub(crate) fn brief_json(
+    index: &DefinitionIndex,
+    content: Option<&ContentIndex>,
+    git: Result<&GitHistoryCache, &String>,
+    top: usize,
+    days: u64,
+    now: i64,
+) -> Value {
+    let mut notes = Vec::new();
+    if content.is_none() {
+        notes.push("No content index for this --dir and --ext: key types are ranked by implementors only. Run 'search content-index' for usage counts.".to_string());
+    }
+    let mut brief = json!({
+        "root": index.root,
+        "generatedAt": now,
+        "structure": structure_json(index, top),
+        "keyTypes": key_types_json(index, content, top),
+        "entryPoints": entry_points_json(index, top),
+        "complexityOutliers": complexity_json(index, top),
+    });
+    match git {
+        Ok(cache) => {
+            let query = json!({ "level": "file", "maxResults": top, "topAuthors": 2 });
+            match search_hotspots_json(index, cache, &query, None) {
+                Ok(mut hotspots) => {
+                    for hotspot in hotspots["hotspots"].as_array_mut().into_iter().flatten() {
+                        let file = relative_path(&index.root, text(&hotspot["file"])).to_string();
+                        hotspot["file"] = json!(file);
+                    }
+                    brief["hotspots"] = hotspots["hotspots"].take();
+                }
+                Err(e) => notes.push(format!("Hotspots skipped: {}", e)),
+            }
+            brief["recentActivity"] = activity_json(cache, top, days, now);
+        }
+        Err(e) => notes.push(format!("Git sections skipped: {}", e)),
+    }
+    brief["notes"] = json!(notes);
+    brief
+}
+
+fn file_of(index: &DefinitionIndex, file_id: u32) -> &str {
+    index.files.get(file_id as usize).map_or("", |f| relative_path(&index.root, f))
+}
+
+fn structure_json(index: &DefinitionIndex, top: usize) -> Value {
+    let mut dirs: HashMap<&str, (usize, usize)> = HashMap::new();
+    let mut extensions: HashMap<String, usize> = HashMap::new();
+    for (file_id, path) in index.files.iter().enumerate() {
+        let rel = relative_path(&index.root, path);
+        let dir = rel.split_once('/').map_or(".", |(first, _)| first);
+        let entry = dirs.entry(dir).or_default();
+        entry.0 += 1;
+        entry.1 += index.file_index.get(&(file_id as u32)).map_or(0, Vec::len);
+        let ext = Path::new(rel).extension().and_then(|e| e.to_str()).unwrap_or("(none)").to_lowercase();
+        *extensions.entry(ext).or_default() += 1;
+    }
+    let mut dirs: Vec<_> = dirs.into_iter().collect();
+    dirs.sort_by(|a, b| b.1.0.cmp(&a.1.0).then_with(|| a.0.cmp(b.0)));
+    let mut extensions: Vec<_> = extensions.into_iter().collect();
+    extensions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let kinds: BTreeMap<&str, usize> = index.kind_index.iter()
+        .filter(|(_, ids)| !ids.is_empty())
+        .map(|(kind, ids)| (kind.as_str(), ids.len()))
+        .collect();
+    json!({
+        "files": index.files.len(),
+        "definitions": index.definitions.len(),
+        "topDirectories": dirs.iter().take(top).map(|(dir, (files, defs))| json!({ "dir": dir, "files": files, "definitions": defs })).collect::<Vec<_>>(),
+        "extensions": extensions.iter().map(|(ext, files)| json!({ "ext": ext, "files": files })).collect::<Vec<_>>(),
+        "kinds": kinds,
+    })
+}
+
+/// Types referenced from the most other files (by the content index), then by
+/// implementors; a partial type is listed once.
+fn key_types_json(index: &DefinitionIndex, content: Option<&ContentIndex>, top: usize) -> Value {
+    let mut seen = HashSet::new();
+    let mut types: Vec<(usize, usize, &definitions::DefinitionEntry)> = index.definitions.iter()
+        .filter(|d| TYPE_KINDS.contains(&d.kind) && seen.insert(d.name.as_str()))
+        .map(|d| {
+            let lower = d.name.to_lowercase();
+            let referenced_from = content.and_then(|c| c.index.get(lower.as_str())).map_or(0, |p| p.len().saturating_sub(1));
+            let implementors = index.base_type_index.get(&lower).map_or(0, Vec::len);
+            (referenced_from, implementors, d)
+        })
+        .collect();
+    types.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then_with(|| a.2.name.cmp(&b.2.name)));
+    types.iter().take(top).map(|(referenced_from, implementors, d)| {
+        let mut entry = json!({
+            "name": d.name,
+            "kind": d.kind.as_str(),
+            "file": file_of(index, d.file_id),
+            "line": d.line_start,
+            "implementors": implementors,
+        });
+        if content.is_some() {
+            entry["referencedFromFiles"] = json!(referenced_from);
+        }
+        entry
+    }).collect()
+}
+
+/// Name of an attribute without `@`, arguments or an `Attribute` suffix.
+fn attribute_name(attribute: &str) -> &str {
+    let name = attribute.trim_start_matches(['@', '[']).split(['(', ']']).next().unwrap_or("").trim();
+    name.strip_suffix("Attribute").unwrap_or(name)
+}
+
+/// `main` functions, host startup classes, HTTP controllers (with their endpoint
+/// count) and serverless functions.
+fn entry_points_json(index: &DefinitionIndex, top: usize) -> Value {
+    let mut entries = Vec::new();
+    for (def_idx, d) in index.definitions.iter().enumerate() {
+        let attributes: Vec<&str> = d.attributes.iter().map(|a| attribute_name(a)).collect();
+        let reason = match d.kind {
+            DefinitionKind::Method | DefinitionKind::Function if d.name.eq_ignore_ascii_case("main") => "main function".to_string(),
+            DefinitionKind::Class if d.name == "Program" || d.name == "Startup" => "host startup".to_string(),
+            DefinitionKind::Class if d.name.ends_with("Controller") || attributes.contains(&"ApiController") => {
+                let endpoints = index.file_index.get(&d.file_id).into_iter().flatten()
+                    .filter(|&&i| i as usize != def_idx)
+                    .filter_map(|&i| index.definitions.get(i as usize))
+                    .filter(|m| m.parent.as_deref() == Some(d.name.as_str())
+                        && m.attributes.iter().any(|a| ENDPOINT_ATTRIBUTES.contains(&attribute_name(a))))
+                    .count();
+                format!("HTTP controller, {} endpoints", endpoints)
+            }
+            _ if attributes.iter().any(|a| *a == "Function" || *a == "FunctionName") => "serverless function".to_string(),
+            _ => continue,
+        };
+        entries.push(json!({
+            "name": d.name,
+            "kind": d.kind.as_str(),
+            "file": file_of(index, d.file_id),
+            "line": d.line_start,
+            "reason": reason,
+        }));
+    }
+    entries.truncate(top);
+    json!(entries)
+}
+
+fn complexity_json(index: &DefinitionIndex, top: usize) -> Value {
+    let mut methods: Vec<(&u32, &definitions::CodeStats)> = index.code_stats.iter().collect();
+    methods.sort_by(|a, b| b.1.cyclomatic_complexity.cmp(&a.1.cyclomatic_complexity)
+        .then(b.1.cognitive_complexity.cmp(&a.1.cognitive_complexity))
+        .then(a.0.cmp(b.0)));
+    methods.into_iter().take(top).filter_map(|(&def_idx, stats)| {
+        let d = index.definitions.get(def_idx as usize)?;
+        let name = match &d.parent {
+            Some(parent) => format!("{}.{}", parent, d.name),
+            None => d.name.clone(),
+        };
+        Some(json!({
+            "name": name,
+            "file": file_of(index, d.file_id),
+            "line": d.line_start,
+            "lines": d.line_end.saturating_sub(d.line_start) + 1,
+            "cyclomatic": stats.cyclomatic_complexity,
+            "cognitive": stats.cognitive_complexity,
+        }))
+    }).collect()
+}
+
+fn activity_json(cache: &GitHistoryCache, top: usize, days: u64, now: i64) -> Value {
+    let from = now - days as i64 * 86_400;
+    let mut files = cache.query_activity("", Some(from), None, None, None);
+    files.sort_by(|a, b| b.commit_count.cmp(&a.commit_count).then_with(|| a.file_path.cmp(&b.file_path)));
+    let mut authors = cache.query_authors("", None, None, Some(from), None);
+    authors.sort_by(|a, b| b.commit_count.cmp(&a.commit_count).then_with(|| a.name.cmp(&b.name)));
+    json!({
+        "days": days,
+        "commits": cache.commits.iter().filter(|c| c.timestamp >= from).count(),
+        "files": files.iter().take(top).map(|f| json!({
+            "file": f.file_path,
+            "commits": f.commit_count,
+            "lastModified": format_timestamp(f.last_modified),
+        })).collect::<Vec<_>>(),
+        "authors": authors.iter().take(top).map(|a| json!({ "name": a.name, "commits": a.commit_count })).collect::<Vec<_>>(),
+    })
+}
+
+/// The Markdown document for [`brief_json`]'s output. Sections with nothing to show
+/// are left out.
+pub(crate) fn render_markdown(brief: &Value) -> String {
+    let mut out = String::new();
+    let root = brief["root"].as_str().unwrap_or("");
+    let name = root.trim_end_matches('/').rsplit('/').next().unwrap_or(root);
+    let _ = writeln!(out, "# Project brief: {}\n", name);
+    let _ = writeln!(out, "Generated by `search brief` from the indexes of `{}`.\n", root);
+
+    let structure = &brief["structure"];
+    let _ = writeln!(out, "## Structure\n");
+    let _ = writeln!(out, "{} files, {} definitions.\n", structure["files"], structure["definitions"]);
+    let extensions: Vec<String> = rows(&structure["extensions"]).iter()
+        .map(|e| format!("`.{}` {}", text(&e["ext"]), e["files"])).collect();
+    if !extensions.is_empty() {
+        let _ = writeln!(out, "- Extensions: {}", extensions.join(", "));
+    }
+    if let Some(kinds) = structure["kinds"].as_object().filter(|k| !k.is_empty()) {
+        let kinds: Vec<String> = kinds.iter().map(|(kind, n)| format!("{} {}", n, kind)).collect();
+        let _ = writeln!(out, "- Definitions: {}", kinds.join(", "));
+    }
+    out.push('\n');
+    table(&mut out, None, &["Directory", "Files", "Definitions"], &structure["topDirectories"], |d| {
+        vec![format!("`{}`", text(&d["dir"])), d["files"].to_string(), d["definitions"].to_string()]
+    });
+
+    let with_usage = rows(&brief["keyTypes"]).first().is_some_and(|t| t.get("referencedFromFiles").is_some());
+    let mut headers = vec!["Type", "Kind", "File"];
+    if with_usage {
+        headers.push("Referenced from");
+    }
+    headers.push("Implementors");
+    table(&mut out, Some("Key types"), &headers, &brief["keyTypes"], |t| {
+        let mut row = vec![format!("`{}`", text(&t["name"])), text(&t["kind"]).to_string(), location(t)];
+        if with_usage {
+            row.push(format!("{} files", t["referencedFromFiles"]));
+        }
+        row.push(t["implementors"].to_string());
+        row
+    });
+    table(&mut out, Some("Entry points"), &["Name", "Kind", "File", "Why"], &brief["entryPoints"], |e| {
+        vec![format!("`{}`", text(&e["name"])), text(&e["kind"]).to_string(), location(e), text(&e["reason"]).to_string()]
+    });
+    table(&mut out, Some("Hotspots (churn × complexity)"), &["File", "Score", "Churn", "Complexity", "Top authors"], &brief["hotspots"], |h| {
+        let authors: Vec<&str> = rows(&h["topAuthors"]).iter().filter_map(|a| a["name"].as_str()).collect();
+        vec![format!("`{}`", text(&h["file"])), h["score"].to_string(), h["churn"].to_string(), h["complexity"].to_string(), authors.join(", ")]
+    });
+    table(&mut out, Some("Complexity outliers"), &["Method", "File", "Lines", "Cyclomatic", "Cognitive"], &brief["complexityOutliers"], |m| {
+        vec![format!("`{}`", text(&m["name"])), location(m), m["lines"].to_string(), m["cyclomatic"].to_string(), m["cognitive"].to_string()]
+    });
+
+    let activity = &brief["recentActivity"];
+    if activity.is_object() {
+        let _ = writeln!(out, "## Recent activity (last {} days)\n", activity["days"]);
+        let _ = writeln!(out, "{} commits.\n", activity["commits"]);
+        table(&mut out, None, &["File", "Commits", "Last change"], &activity["files"], |f| {
+            vec![format!("`{}`", text(&f["file"])), f["commits"].to_string(), text(&f["lastModified"]).to_string()]
+        });
+        table(&mut out, None, &["Author", "Commits"], &activity["authors"], |a| {
+            vec![text(&a["name"]).to_string(), a["commits"].to_string()]
+        });
+    }
+
+    let notes = rows(&brief["notes"]);
+    if !notes.is_empty() {
+        let _ = writeln!(out, "## Notes\n");
+        for note in notes {
+            let _ = writeln!(out, "- {}", text(note));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn rows(value: &Value) -> &[Value] {
+    value.as_array().map_or(&[], Vec::as_slice)
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or("")
+}
+
+fn location(entry: &Value) -> String {
+    format!("`{}:{}`", text(&entry["file"]), entry["line"])
+}
+
+/// A Markdown table of `values`, under a `##` heading when given; nothing when empty.
+fn table(out: &mut String, heading: Option<&str>, headers: &[&str], values: &Value, row: impl Fn(&Value) -> Vec<String>) {
+    let values = rows(values);
+    if values.is_empty() {
+        return;
+    }
+    if let Some(heading) = heading {
+        let _ = writeln!(out, "## {}\n", heading);
+    }
+    let _ = writeln!(out, "| {} |", headers.join(" | "));
+    let _ = writeln!(out, "|{}", " --- |".repeat(headers.len()));
+    for value in values {
+        let _ = writeln!(out, "| {} |", row(value).join(" | ").replace('\n', " "));
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::{build_definition_index, DefIndexArgs};
+
+    #[test]
+    fn test_brief_ranks_key_types_and_finds_entry_points() {
+        let tmp = tempfile::tempdir().unwrap();
+        let write = |rel: &str, text: &str| {
+            let path = tmp.path().join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        };
+        write("src/Program.cs", "public class Program { public static void Main(string[] args) { var s = new OrderService(); } }");
+        write("src/Orders/OrderService.cs", "public interface IOrderService { }\npublic class OrderService : IOrderService {\n  public int Total(int a) { if (a > 0) { return 1; } else if (a < 0) { return 2; } return 0; }\n}");
+        write("src/Api/OrdersController.cs", "[ApiController]\npublic class OrdersController {\n  [HttpGet]\n  public string Get() { return new OrderService().ToString(); }\n  [HttpPost(\"x\")]\n  public void Post() { }\n}");
+        let dir = tmp.path().to_string_lossy().to_string();
+        let index = build_definition_index(&DefIndexArgs {
+            dir: dir.clone(), ext: "cs".to_string(), threads: 1, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new(),
+        });
+        let content = crate::build_content_index(&crate::ContentIndexArgs {
+            dir, ext: "cs".to_string(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+            dry_run: false, build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, watch: false,
+            save_interval_secs: 60, max_file_size: 0, spill_mb: 0, shards: 0, max_token_len: 0, stop_token_pct: 0, checkpoint_secs: 0,
+            max_depth: 0, prune_dir: Vec::new(), storage: None,
+        });
+
+        let no_git = "not a git repository".to_string();
+        let brief = brief_json(&index, Some(&content), Err(&no_git), 5, 30, 0);
+        assert_eq!(brief["structure"]["files"], 3);
+        assert_eq!(brief["keyTypes"][0]["name"], "OrderService", "{}", brief["keyTypes"]);
+        assert_eq!(brief["keyTypes"][0]["referencedFromFiles"], 2);
+        let reasons: Vec<&str> = rows(&brief["entryPoints"]).iter().filter_map(|e| e["reason"].as_str()).collect();
+        assert!(reasons.contains(&"main function") && reasons.contains(&"host startup"), "{:?}", reasons);
+        assert!(reasons.contains(&"HTTP controller, 2 endpoints"), "{:?}", reasons);
+        assert_eq!(brief["complexityOutliers"][0]["name"], "OrderService.Total");
+        assert!(brief.get("recentActivity").is_none());
+
+        let markdown = render_markdown(&brief);
+        assert!(markdown.contains("## Key types\n"), "{}", markdown);
+        assert!(markdown.contains("| `OrderService` | class | `src/Orders/OrderService.cs:2` | 2 files | 0 |"), "{}", markdown);
+        assert!(markdown.contains("- Git sections skipped: not a git repository"));
+        assert!(!markdown.contains("## Recent activity"));
+    }
+}
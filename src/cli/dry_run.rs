@@ -0,0 +1,468 @@
+//! `--dry-run` support for index builders: walk the tree, estimate index size, build nothing.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use ignore::WalkBuilder;
+
+use crate::config::RepoConfig;
+use crate::index::recover_mutex;
+use crate::{clean_path, read_file_lossy, tokenize, WalkLimits};
+
+/// Number of files sampled (evenly spaced by size rank) to measure token density.
+const SAMPLE_FILES: usize = 200;
+
+/// Number of entries shown in each "largest contributors" list.
+const TOP_N: usize = 10;
+
+/// Heaps' law exponent used to extrapolate unique tokens from the sample.
+/// Source code vocabularies typically grow with β ≈ 0.5–0.6.
+const HEAPS_BETA: f64 = 0.55;
+
+/// Average number of definitions per parsed file, measured on a 48K-file C# repo
+/// (846K definitions). Used only for def-index estimates.
+const DEFS_PER_FILE: f64 = 17.4;
+
+/// Average in-memory cost of one definition including secondary indexes, call sites
+/// and code stats (bytes).
+const BYTES_PER_DEF_RAM: f64 = 420.0;
+
+/// Average on-disk (LZ4-compressed) cost of one definition (bytes).
+const BYTES_PER_DEF_DISK: f64 = 280.0;
+
+/// A single file discovered during the dry-run walk.
+#[derive(Debug, Clone)]
+pub(crate) struct ScannedFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Result of walking a directory with the same rules the real builder uses.
+#[derive(Debug, Default)]
+pub(crate) struct DryRunScan {
+    pub root: String,
+    pub files: Vec<ScannedFile>,
+    /// Directories encountered (only tracked for the file-list index).
+    pub dir_count: usize,
+}
+
+/// Estimates for a content (`.word-search`) index.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ContentEstimate {
+    pub sampled_files: usize,
+    pub total_tokens: u64,
+    pub unique_tokens: u64,
+    pub disk_bytes: u64,
+    pub ram_bytes: u64,
+}
+
+impl DryRunScan {
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    /// Per-extension (lowercased) file count and total bytes, largest first.
+    pub fn by_extension(&self) -> Vec<(String, usize, u64)> {
+        let mut map: HashMap<String, (usize, u64)> = HashMap::new();
+        for f in &self.files {
+            let ext = std::path::Path::new(&f.path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase())
+                .unwrap_or_else(|| "(none)".to_string());
+            let entry = map.entry(ext).or_default();
+            entry.0 += 1;
+            entry.1 += f.size;
+        }
+        let mut out: Vec<_> = map.into_iter().map(|(e, (c, b))| (e, c, b)).collect();
+        out.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        out
+    }
+
+    /// Top-level directories under the root ranked by total bytes of matching files.
+    /// Files directly in the root are grouped under ".".
+    pub fn largest_dirs(&self, n: usize) -> Vec<(String, usize, u64)> {
+        let prefix = format!("{}/", self.root.trim_end_matches('/'));
+        let mut map: HashMap<String, (usize, u64)> = HashMap::new();
+        for f in &self.files {
+            let rel = f.path.strip_prefix(&prefix).unwrap_or(&f.path);
+            let top = match rel.split_once('/') {
+                Some((first, _)) => first.to_string(),
+                None => ".".to_string(),
+            };
+            let entry = map.entry(top).or_default();
+            entry.0 += 1;
+            entry.1 += f.size;
+        }
+        let mut out: Vec<_> = map.into_iter().map(|(d, (c, b))| (d, c, b)).collect();
+        out.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        out.truncate(n);
+        out
+    }
+
+    /// Largest individual files, biggest first.
+    pub fn largest_files(&self, n: usize) -> Vec<&ScannedFile> {
+        let mut sorted: Vec<&ScannedFile> = self.files.iter().collect();
+        sorted.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        sorted.truncate(n);
+        sorted
+    }
+}
+
+/// Walk `dir` with the same ignore rules and `limits` as the index builders.
+/// `extensions = None` keeps every entry (file-list index); otherwise only files
+/// whose extension matches (case-insensitive) and fit `max_file_size` (0 = the
+/// config's limit, if any) are kept.
+pub(crate) fn scan_tree(
+    dir: &str,
+    extensions: Option<&[String]>,
+    hidden: bool,
+    no_ignore: bool,
+    max_file_size: u64,
+    threads: usize,
+    limits: &WalkLimits,
+) -> DryRunScan {
+    let root = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
+    let root_str = clean_path(&root.to_string_lossy());
+
+    let mut builder = WalkBuilder::new(&root);
+    builder.hidden(!hidden);
+    builder.git_ignore(!no_ignore);
+    builder.git_global(!no_ignore);
+    builder.git_exclude(!no_ignore);
+    if extensions.is_some() {
+        let config = RepoConfig::load_or_default(&root);
+        config.apply_to_walk(&mut builder, &root, limits);
+        builder.max_filesize(config.effective_max_file_size(max_file_size));
+    } else {
+        limits.apply_to_walk(&mut builder, &root);
+    }
+    if threads > 0 {
+        builder.threads(threads);
+    }
+
+    let files: Mutex<Vec<ScannedFile>> = Mutex::new(Vec::new());
+    let dirs: Mutex<usize> = Mutex::new(0);
+
+    builder.build_parallel().run(|| {
+        let files = &files;
+        let dirs = &dirs;
+        Box::new(move |result| {
+            let entry = match result {
+                Ok(e) => e,
+                Err(_) => return ignore::WalkState::Continue,
+            };
+            let is_file = entry.file_type().is_some_and(|ft| ft.is_file());
+            if !is_file {
+                if extensions.is_none() && entry.depth() > 0 {
+                    *dirs.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+                }
+                return ignore::WalkState::Continue;
+            }
+            if let Some(exts) = extensions {
+                let ext_match = entry
+                    .path()
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| exts.iter().any(|x| x.eq_ignore_ascii_case(e)));
+                if !ext_match {
+                    return ignore::WalkState::Continue;
+                }
+            }
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            let path = clean_path(&entry.path().to_string_lossy());
+            files.lock().unwrap_or_else(|e| e.into_inner()).push(ScannedFile { path, size });
+            ignore::WalkState::Continue
+        })
+    });
+
+    DryRunScan {
+        root: root_str,
+        files: recover_mutex(files, "dry-run"),
+        dir_count: recover_mutex(dirs, "dry-run"),
+    }
+}
+
+/// Estimate content index size by tokenizing a size-stratified sample of files
+/// and extrapolating to the whole tree.
+pub(crate) fn estimate_content_index(scan: &DryRunScan, min_token_len: usize) -> ContentEstimate {
+    if scan.files.is_empty() {
+        return ContentEstimate::default();
+    }
+
+    // Evenly spaced picks over files ordered by size, so the sample covers
+    // both the long tail of tiny files and the few huge ones.
+    let mut by_size: Vec<&ScannedFile> = scan.files.iter().collect();
+    by_size.sort_by(|a, b| a.size.cmp(&b.size).then_with(|| a.path.cmp(&b.path)));
+    let step = (by_size.len() as f64 / SAMPLE_FILES as f64).max(1.0);
+    let mut sample: Vec<&ScannedFile> = Vec::new();
+    let mut pos = 0.0;
+    while (pos as usize) < by_size.len() && sample.len() < SAMPLE_FILES {
+        sample.push(by_size[pos as usize]);
+        pos += step;
+    }
+
+    let mut sample_bytes: u64 = 0;
+    let mut sample_tokens: u64 = 0;
+    let mut sample_postings: u64 = 0;
+    let mut vocab: std::collections::HashSet<String> = std::collections::HashSet::new();
+    for f in &sample {
+        let (content, _) = match read_file_lossy(std::path::Path::new(&f.path)) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        sample_bytes += content.len() as u64;
+        let mut file_vocab: std::collections::HashSet<String> = std::collections::HashSet::new();
+        for line in content.lines() {
+            for token in tokenize(line, min_token_len) {
+                sample_tokens += 1;
+                file_vocab.insert(token);
+            }
+        }
+        sample_postings += file_vocab.len() as u64;
+        vocab.extend(file_vocab);
+    }
+
+    let total_bytes = scan.total_bytes();
+    let scale = if sample_bytes > 0 { total_bytes as f64 / sample_bytes as f64 } else { 0.0 };
+    let total_tokens = (sample_tokens as f64 * scale) as u64;
+    let postings = (sample_postings as f64 * scale) as u64;
+    let unique_tokens = if sample_tokens > 0 {
+        ((vocab.len() as f64) * scale.max(1.0).powf(HEAPS_BETA)) as u64
+    } else {
+        0
+    };
+
+    // RAM model: each token occurrence stores one u32 line number; each posting
+    // carries a file_id + Vec header; each unique token costs a String + HashMap
+    // slot + its trigram entries; each file costs its path + token count.
+    let avg_path = scan.files.iter().map(|f| f.path.len() as u64).sum::<u64>() / scan.files.len() as u64;
+    let ram_bytes = total_tokens * 4
+        + postings * 32
+        + unique_tokens * 120
+        + scan.files.len() as u64 * (avg_path + 32);
+    // On disk postings are bincode-encoded and LZ4-compressed; measured ratio ≈ 0.35.
+    let disk_bytes = (ram_bytes as f64 * 0.35) as u64;
+
+    ContentEstimate {
+        sampled_files: sample.len(),
+        total_tokens,
+        unique_tokens,
+        disk_bytes,
+        ram_bytes,
+    }
+}
+
+fn fmt_mb(bytes: u64) -> String {
+    if bytes < 1_048_576 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / 1_048_576.0)
+    }
+}
+
+fn print_scan_summary(label: &str, scan: &DryRunScan, elapsed_secs: f64) {
+    println!("[dry-run] {} for {}", label, scan.root);
+    println!(
+        "[dry-run] {} files, {} total (walk took {:.2}s)",
+        scan.files.len(),
+        fmt_mb(scan.total_bytes()),
+        elapsed_secs
+    );
+    let by_ext = scan.by_extension();
+    if !by_ext.is_empty() {
+        println!("[dry-run] By extension:");
+        for (ext, count, bytes) in by_ext.iter().take(TOP_N) {
+            println!("    .{:<12} {:>8} files  {:>10}", ext, count, fmt_mb(*bytes));
+        }
+    }
+    let dirs = scan.largest_dirs(TOP_N);
+    if !dirs.is_empty() {
+        println!("[dry-run] Largest top-level directories:");
+        for (dir, count, bytes) in &dirs {
+            println!("    {:<40} {:>8} files  {:>10}", dir, count, fmt_mb(*bytes));
+        }
+    }
+    let files = scan.largest_files(TOP_N);
+    if !files.is_empty() {
+        println!("[dry-run] Largest files:");
+        for f in files {
+            println!("    {:>10}  {}", fmt_mb(f.size), f.path);
+        }
+    }
+}
+
+/// `search index --dry-run`
+pub(crate) fn dry_run_file_index(args: &crate::IndexArgs) {
+    let start = Instant::now();
+    let scan = scan_tree(&args.dir, None, args.hidden, args.no_ignore, 0, args.threads, &WalkLimits::default());
+    let elapsed = start.elapsed().as_secs_f64();
+    print_scan_summary("File index", &scan, elapsed);
+
+    let entries = (scan.files.len() + scan.dir_count) as u64;
+    let avg_path = if scan.files.is_empty() {
+        0
+    } else {
+        scan.files.iter().map(|f| f.path.len() as u64).sum::<u64>() / scan.files.len() as u64
+    };
+    // FileEntry = path String (24 + len) + size + modified + is_dir
+    let ram = entries * (avg_path + 48);
+    println!(
+        "[dry-run] Estimated: {} entries ({} dirs), ~{} RAM, ~{} on disk",
+        entries, scan.dir_count, fmt_mb(ram), fmt_mb((ram as f64 * 0.3) as u64)
+    );
+    println!("[dry-run] Nothing was written.");
+}
+
+/// `search content-index --dry-run`
+pub(crate) fn dry_run_content_index(args: &crate::ContentIndexArgs) {
+    let extensions: Vec<String> = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect();
+    let start = Instant::now();
+    let scan = scan_tree(&args.dir, Some(&extensions), args.hidden, args.no_ignore, args.max_file_size, args.threads,
+        &WalkLimits::new(args.max_depth, &args.prune_dir));
+    let elapsed = start.elapsed().as_secs_f64();
+    print_scan_summary("Content index", &scan, elapsed);
+
+    let est = estimate_content_index(&scan, args.min_token_len);
+    println!(
+        "[dry-run] Estimated: ~{} tokens, ~{} unique tokens (sampled {} files)",
+        est.total_tokens, est.unique_tokens, est.sampled_files
+    );
+    println!(
+        "[dry-run] Estimated index size: ~{} on disk, ~{} RAM when loaded",
+        fmt_mb(est.disk_bytes), fmt_mb(est.ram_bytes)
+    );
+    println!("[dry-run] Nothing was written.");
+}
+
+/// `search def-index --dry-run`
+pub(crate) fn dry_run_definition_index(args: &crate::definitions::DefIndexArgs) {
+    let extensions: Vec<String> = args.ext.split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let start = Instant::now();
+    // def-index always respects .gitignore and skips hidden files (see build_definition_index)
+    let scan = scan_tree(&args.dir, Some(&extensions), false, false, 0, args.threads,
+        &WalkLimits::new(args.max_depth, &args.prune_dir));
+    let elapsed = start.elapsed().as_secs_f64();
+    print_scan_summary("Definition index", &scan, elapsed);
+
+    let defs = (scan.files.len() as f64 * DEFS_PER_FILE) as u64;
+    println!(
+        "[dry-run] Estimated: ~{} definitions, ~{} on disk, ~{} RAM when loaded",
+        defs,
+        fmt_mb((defs as f64 * BYTES_PER_DEF_DISK) as u64),
+        fmt_mb((defs as f64 * BYTES_PER_DEF_RAM) as u64)
+    );
+    println!("[dry-run] Nothing was written.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &std::path::Path, rel: &str, content: &str) {
+        let p = dir.join(rel);
+        std::fs::create_dir_all(p.parent().unwrap()).unwrap();
+        std::fs::write(p, content).unwrap();
+    }
+
+    #[test]
+    fn test_scan_tree_filters_extensions() {
+        let tmp = tempfile::tempdir().unwrap();
+        write(tmp.path(), "a.cs", "class A {}");
+        write(tmp.path(), "src/b.cs", "class B {}");
+        write(tmp.path(), "src/c.txt", "ignored");
+
+        let exts = vec!["cs".to_string()];
+        let scan = scan_tree(tmp.path().to_str().unwrap(), Some(&exts), false, false, 0, 1, &WalkLimits::default());
+        assert_eq!(scan.files.len(), 2);
+        assert_eq!(scan.total_bytes(), 20);
+        assert_eq!(scan.dir_count, 0, "dirs are only counted for the file-list index");
+    }
+
+    #[test]
+    fn test_scan_tree_counts_dirs_without_ext_filter() {
+        let tmp = tempfile::tempdir().unwrap();
+        write(tmp.path(), "x/y/z.txt", "hi");
+        let scan = scan_tree(tmp.path().to_str().unwrap(), None, false, false, 0, 1, &WalkLimits::default());
+        assert_eq!(scan.files.len(), 1);
+        assert_eq!(scan.dir_count, 2);
+    }
+
+    #[test]
+    fn test_largest_dirs_groups_by_top_level() {
+        let scan = DryRunScan {
+            root: "/repo".to_string(),
+            files: vec![
+                ScannedFile { path: "/repo/src/a.cs".into(), size: 100 },
+                ScannedFile { path: "/repo/src/deep/b.cs".into(), size: 50 },
+                ScannedFile { path: "/repo/tests/c.cs".into(), size: 500 },
+                ScannedFile { path: "/repo/root.cs".into(), size: 1 },
+            ],
+            dir_count: 0,
+        };
+        let dirs = scan.largest_dirs(10);
+        assert_eq!(dirs[0], ("tests".to_string(), 1, 500));
+        assert_eq!(dirs[1], ("src".to_string(), 2, 150));
+        assert_eq!(dirs[2], (".".to_string(), 1, 1));
+    }
+
+    #[test]
+    fn test_largest_files_sorted_desc() {
+        let scan = DryRunScan {
+            root: "/r".to_string(),
+            files: vec![
+                ScannedFile { path: "/r/a".into(), size: 1 },
+                ScannedFile { path: "/r/b".into(), size: 3 },
+                ScannedFile { path: "/r/c".into(), size: 2 },
+            ],
+            dir_count: 0,
+        };
+        let top: Vec<&str> = scan.largest_files(2).iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(top, vec!["/r/b", "/r/c"]);
+    }
+
+    #[test]
+    fn test_by_extension_lowercases() {
+        let scan = DryRunScan {
+            root: "/r".to_string(),
+            files: vec![
+                ScannedFile { path: "/r/a.CS".into(), size: 10 },
+                ScannedFile { path: "/r/b.cs".into(), size: 10 },
+                ScannedFile { path: "/r/Makefile".into(), size: 5 },
+            ],
+            dir_count: 0,
+        };
+        let by_ext = scan.by_extension();
+        assert_eq!(by_ext[0], ("cs".to_string(), 2, 20));
+        assert_eq!(by_ext[1], ("(none)".to_string(), 1, 5));
+    }
+
+    #[test]
+    fn test_estimate_content_index_exact_when_fully_sampled() {
+        let tmp = tempfile::tempdir().unwrap();
+        write(tmp.path(), "a.cs", "public class UserService { }\n");
+        write(tmp.path(), "b.cs", "public class OrderService { }\n");
+        let exts = vec!["cs".to_string()];
+        let scan = scan_tree(tmp.path().to_str().unwrap(), Some(&exts), false, false, 0, 1, &WalkLimits::default());
+
+        let est = estimate_content_index(&scan, 2);
+        assert_eq!(est.sampled_files, 2);
+        // Every file was sampled, so the estimate is exact: 3 tokens per file.
+        assert_eq!(est.total_tokens, 6);
+        assert_eq!(est.unique_tokens, 4); // public, class, userservice, orderservice
+        assert!(est.ram_bytes > 0);
+        assert!(est.disk_bytes < est.ram_bytes);
+    }
+
+    #[test]
+    fn test_estimate_content_index_empty_scan() {
+        let scan = DryRunScan::default();
+        assert_eq!(estimate_content_index(&scan, 2), ContentEstimate::default());
+    }
+}
@@ -0,0 +1,148 @@
+//! `--ext auto`: pick the extensions to index from a sample of the tree, so a first
+//! build needs no knowledge of the repo's languages.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+use crate::config::RepoConfig;
+use crate::{SearchError, WalkLimits};
+
+/// Value of `--ext` that asks for discovery
+pub(crate) const AUTO: &str = "auto";
+
+/// Files looked at before the walk stops
+const MAX_SAMPLED_FILES: usize = 20_000;
+
+/// Files per extension read to tell text from binary
+const PROBES_PER_EXTENSION: usize = 3;
+
+/// Bytes read from each probed file
+const PROBE_BYTES: usize = 8192;
+
+/// Extensions picked at most
+const MAX_EXTENSIONS: usize = 8;
+
+/// Share of the sampled text files, in percent, an extension needs to be picked
+const MIN_SHARE_PERCENT: usize = 2;
+
+/// Extensions found in the sample, most files first.
+#[derive(Debug, Default)]
+pub(crate) struct ExtensionSample {
+    pub sampled_files: usize,
+    /// `(extension, files, binary)`
+    pub extensions: Vec<(String, usize, bool)>,
+}
+
+impl ExtensionSample {
+    /// The most common text extensions: at most [`MAX_EXTENSIONS`], each with at
+    /// least [`MIN_SHARE_PERCENT`] of the text files, and always the most common one.
+    pub fn picked(&self) -> Vec<&str> {
+        let text_files: usize = self.extensions.iter().filter(|e| !e.2).map(|e| e.1).sum();
+        self.extensions.iter()
+            .filter(|(_, _, binary)| !binary)
+            .enumerate()
+            .take_while(|(i, (_, files, _))| *i == 0 || files * 100 >= text_files * MIN_SHARE_PERCENT)
+            .take(MAX_EXTENSIONS)
+            .map(|(_, (ext, _, _))| ext.as_str())
+            .collect()
+    }
+}
+
+/// `ext` unchanged, or for [`AUTO`] the extensions picked from a sample of `dir`,
+/// comma-separated. The sample and the pick are reported on stderr.
+pub(crate) fn resolve_ext(dir: &str, ext: &str, hidden: bool, no_ignore: bool, limits: &WalkLimits) -> Result<String, SearchError> {
+    if !ext.trim().eq_ignore_ascii_case(AUTO) {
+        return Ok(ext.to_string());
+    }
+    let sample = sample_extensions(dir, hidden, no_ignore, limits);
+    let picked = sample.picked();
+    if picked.is_empty() {
+        return Err(SearchError::InvalidArgs(format!(
+            "--ext auto found no text files with an extension under {}. Pass --ext explicitly.", dir)));
+    }
+    eprintln!("[ext auto] Sampled {} files under {}", sample.sampled_files, dir);
+    for (ext, files, binary) in sample.extensions.iter().take(MAX_EXTENSIONS * 2) {
+        let note = if *binary { "  (binary, skipped)" } else if picked.contains(&ext.as_str()) { "  (indexed)" } else { "" };
+        eprintln!("    .{:<12} {:>8} files{}", ext, files, note);
+    }
+    let ext = picked.join(",");
+    eprintln!("[ext auto] Indexing --ext {}", ext);
+    Ok(ext)
+}
+
+/// Count file extensions (lowercased) in the first [`MAX_SAMPLED_FILES`] files of the
+/// walk the index builders would do, and probe a few files of each for binary content.
+pub(crate) fn sample_extensions(dir: &str, hidden: bool, no_ignore: bool, limits: &WalkLimits) -> ExtensionSample {
+    let root = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
+    let mut builder = WalkBuilder::new(&root);
+    builder.hidden(!hidden);
+    builder.git_ignore(!no_ignore);
+    builder.git_global(!no_ignore);
+    builder.git_exclude(!no_ignore);
+    RepoConfig::load_or_default(&root).apply_to_walk(&mut builder, &root, limits);
+
+    let mut counts: HashMap<String, (usize, usize, bool)> = HashMap::new();
+    let mut sampled_files = 0;
+    for entry in builder.build().flatten() {
+        if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+            continue;
+        }
+        sampled_files += 1;
+        if let Some(ext) = entry.path().extension().and_then(|e| e.to_str()) {
+            let (files, probed, binary) = counts.entry(ext.to_lowercase()).or_default();
+            *files += 1;
+            if !*binary && *probed < PROBES_PER_EXTENSION {
+                *probed += 1;
+                *binary = looks_binary(entry.path());
+            }
+        }
+        if sampled_files == MAX_SAMPLED_FILES {
+            break;
+        }
+    }
+    let mut extensions: Vec<(String, usize, bool)> = counts.into_iter()
+        .map(|(ext, (files, _, binary))| (ext, files, binary))
+        .collect();
+    extensions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ExtensionSample { sampled_files, extensions }
+}
+
+/// A NUL byte in the first [`PROBE_BYTES`], as git decides.
+fn looks_binary(path: &Path) -> bool {
+    let mut head = Vec::with_capacity(PROBE_BYTES);
+    fs::File::open(path)
+        .and_then(|f| f.take(PROBE_BYTES as u64).read_to_end(&mut head))
+        .is_ok_and(|_| head.contains(&0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_ext_picks_common_text_extensions_and_skips_binaries() {
+        let tmp = tempfile::tempdir().unwrap();
+        for i in 0..60 {
+            fs::write(tmp.path().join(format!("f{}.cs", i)), "class A {}").unwrap();
+        }
+        for i in 0..20 {
+            fs::write(tmp.path().join(format!("f{}.ts", i)), "export class B {}").unwrap();
+            fs::write(tmp.path().join(format!("f{}.png", i)), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        }
+        fs::write(tmp.path().join("README.md"), "# rare").unwrap();
+        fs::write(tmp.path().join("Makefile"), "all:").unwrap();
+
+        let dir = tmp.path().to_str().unwrap();
+        let sample = sample_extensions(dir, false, false, &WalkLimits::default());
+        assert_eq!(sample.sampled_files, 102);
+        assert_eq!(sample.extensions[0], ("cs".to_string(), 60, false));
+        assert!(sample.extensions.contains(&("png".to_string(), 20, true)));
+        // .md is 1 of 81 text files, under the 2% share
+        assert_eq!(resolve_ext(dir, "auto", false, false, &WalkLimits::default()).unwrap(), "cs,ts");
+        assert_eq!(resolve_ext(dir, "rs", false, false, &WalkLimits::default()).unwrap(), "rs");
+    }
+}
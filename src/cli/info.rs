@@ -3,9 +3,17 @@
 use std::fs;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::{index_dir, index::load_compressed, ContentIndex, FileIndex};
+use crate::{index_dir, index::load_compressed, ContentIndex, FileIndex, SkippedFiles, SystemClock, TokenizerSettings, WalkLimits, STOP_TOKEN_MIN_FILES};
 
-pub fn cmd_info() {
+use super::args::InfoArgs;
+use super::output::{print_json, OutputFormat};
+
+pub fn cmd_info(args: InfoArgs, format: OutputFormat) {
+    let top_tokens = args.top_tokens.unwrap_or(0);
+    if format.is_json() {
+        print_json(format, &info_json(top_tokens), &["indexes"]);
+        return;
+    }
     let dir = index_dir();
     if !dir.exists() {
         eprintln!("No indexes found. Use 'search index -d <dir>' to create one.");
@@ -41,7 +49,7 @@ pub fn cmd_info() {
                         .saturating_sub(index.created_at);
                     let age_hours = age_secs as f64 / 3600.0;
                     let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
-                    let stale = if index.is_stale() { " [STALE]" } else { "" };
+                    let stale = if index.is_stale(&SystemClock) { " [STALE]" } else { "" };
                     println!(
                         "  [FILE] {} -- {} entries, {:.1} MB, {:.1}h ago{} ({})",
                         index.root, index.entries.len(),
@@ -63,18 +71,82 @@ pub fn cmd_info() {
                         .saturating_sub(index.created_at);
                     let age_hours = age_secs as f64 / 3600.0;
                     let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
-                    let stale = if index.is_stale() { " [STALE]" } else { "" };
+                    let stale = if index.is_stale(&SystemClock) { " [STALE]" } else { "" };
                     println!(
                         "  [CONTENT] {} -- {} files, {} tokens, exts: [{}], {:.1} MB, {:.1}h ago{} ({})",
                         index.root, index.files.len(), index.total_tokens,
                         index.extensions.join(", "),
                         size as f64 / 1_048_576.0, age_hours, stale, filename
                     );
+                    if index.skipped != SkippedFiles::default() {
+                        println!(
+                            "            skipped {} oversized, {} binary files",
+                            index.skipped.too_large, index.skipped.binary
+                        );
+                    }
+                    if index.tokenizer != TokenizerSettings::default() {
+                        println!(
+                            "            tokens of {}..{} bytes",
+                            index.tokenizer.min_token_len,
+                            if index.tokenizer.max_token_len == 0 { "any".to_string() } else { index.tokenizer.max_token_len.to_string() }
+                        );
+                    }
+                    if !index.walk_limits.is_unlimited() {
+                        println!("            walk: {}", walk_limits_text(&index.walk_limits));
+                    }
+                    if top_tokens > 0 {
+                        print_top_tokens(&index, top_tokens);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("  Warning: failed to load {}: {}", path.display(), e);
+                }
+            }
+        } else if ext == Some(crate::shards::MANIFEST_EXTENSION) {
+            match load_compressed::<crate::shards::ShardManifest>(&path, "content-shards") {
+                Ok(manifest) => {
+                    found = true;
+                    let age_secs = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or(Duration::ZERO)
+                        .as_secs()
+                        .saturating_sub(manifest.created_at);
+                    let count = manifest.fingerprints.len();
+                    let exts = manifest.extensions.join(",");
+                    let size: u64 = (0..count)
+                        .filter_map(|i| fs::metadata(crate::shards::shard_path_for(&manifest.root, &exts, i, count, &dir)).ok())
+                        .map(|m| m.len())
+                        .sum();
+                    println!(
+                        "  [CONTENT] {} -- {} shards, exts: [{}], {:.1} MB, {:.1}h ago ({})",
+                        manifest.root, count, manifest.extensions.join(", "),
+                        size as f64 / 1_048_576.0, age_secs as f64 / 3600.0, filename
+                    );
                 }
                 Err(e) => {
                     eprintln!("  Warning: failed to load {}: {}", path.display(), e);
                 }
             }
+        } else if ext == Some(crate::sqlite::EXTENSION) {
+            if let Some(summary) = crate::sqlite::read_summary(&path) {
+                found = true;
+                let age_secs = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or(Duration::ZERO)
+                    .as_secs()
+                    .saturating_sub(summary.created_at);
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                let (label, unit) = match summary.kind.as_str() {
+                    "files" => ("FILE", "entries"),
+                    "content" => ("CONTENT", "files"),
+                    _ => ("DEF", "files"),
+                };
+                println!(
+                    "  [{}] {} -- {} {}, exts: [{}], SQLite, {:.1} MB, {:.1}h ago ({})",
+                    label, summary.root, summary.files, unit, summary.extensions.join(", "),
+                    size as f64 / 1_048_576.0, age_secs as f64 / 3600.0, filename
+                );
+            }
         } else if ext == Some("git-history") {
             if let Ok(cache) = crate::git::cache::GitHistoryCache::load_from_disk(&path) {
                 found = true;
@@ -97,6 +169,47 @@ pub fn cmd_info() {
                     filename
                 );
             }
+        } else if ext == Some("git-grep") {
+            if let Ok(index) = crate::git::commit_index::CommitIndex::load_from_disk(&path) {
+                found = true;
+                let age_secs = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or(Duration::ZERO)
+                    .as_secs()
+                    .saturating_sub(index.built_at);
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                println!(
+                    "  [GIT-GREP] {} -- {} commits, {} tokens, patches={}, HEAD={}, {:.1} MB, {:.1}h ago ({})",
+                    index.repo,
+                    index.commits.len(),
+                    index.tokens.len(),
+                    index.include_patches,
+                    &index.head_hash[..index.head_hash.len().min(8)],
+                    size as f64 / 1_048_576.0,
+                    age_secs as f64 / 3600.0,
+                    filename
+                );
+            }
+        } else if ext == Some("semantic") {
+            if let Ok(index) = crate::semantic::SemanticIndex::load_from_disk(&path) {
+                found = true;
+                let age_secs = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or(Duration::ZERO)
+                    .as_secs()
+                    .saturating_sub(index.built_at);
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                println!(
+                    "  [SEMANTIC] {} -- {} chunks, {} dims, embedder={}, {:.1} MB, {:.1}h ago ({})",
+                    index.root,
+                    index.chunks.len(),
+                    index.dimensions,
+                    index.embedder,
+                    size as f64 / 1_048_576.0,
+                    age_secs as f64 / 3600.0,
+                    filename
+                );
+            }
         }
     }
 
@@ -107,6 +220,11 @@ pub fn cmd_info() {
 
 /// Return index info as JSON value (for MCP handler)
 pub fn cmd_info_json() -> serde_json::Value {
+    info_json(0)
+}
+
+/// Index info as JSON, with the `top_tokens` most common tokens of each content index.
+fn info_json(top_tokens: usize) -> serde_json::Value {
     let dir = index_dir();
     if !dir.exists() {
         return serde_json::json!({ "indexes": [], "directory": dir.display().to_string() });
@@ -134,7 +252,7 @@ pub fn cmd_info_json() -> serde_json::Value {
                         "entries": index.entries.len(),
                         "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
                         "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
-                        "stale": index.is_stale(),
+                        "stale": index.is_stale(&SystemClock),
                         "filename": filename,
                     }));
                 }
@@ -146,7 +264,7 @@ pub fn cmd_info_json() -> serde_json::Value {
                         .as_secs()
                         .saturating_sub(index.created_at);
                     let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
-                    indexes.push(serde_json::json!({
+                    let mut content_info = serde_json::json!({
                         "type": "content",
                         "root": index.root,
                         "files": index.files.len(),
@@ -154,12 +272,30 @@ pub fn cmd_info_json() -> serde_json::Value {
                         "extensions": index.extensions,
                         "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
                         "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
-                        "stale": index.is_stale(),
-                        "filename": filename,
-                    }));
+                        "stale": index.is_stale(&SystemClock),
+                        "tokenizer": {
+                            "minTokenLen": index.tokenizer.min_token_len,
+                            "maxTokenLen": index.tokenizer.max_token_len,
+                            "stopTokenPct": index.stop_token_pct,
+                        },
+                    });
+                    if !index.walk_limits.is_unlimited() {
+                        content_info["walkLimits"] = walk_limits_json(&index.walk_limits);
+                    }
+                    if index.skipped != SkippedFiles::default() {
+                        content_info["skippedFiles"] = serde_json::json!({
+                            "tooLarge": index.skipped.too_large,
+                            "binary": index.skipped.binary,
+                        });
+                    }
+                    if top_tokens > 0 {
+                        content_info["topTokens"] = top_tokens_json(&index, top_tokens);
+                    }
+                    content_info["filename"] = serde_json::json!(filename);
+                    indexes.push(content_info);
                 }
             } else if ext == Some("code-structure") {
-                if let Ok(index) = load_compressed::<crate::definitions::DefinitionIndex>(&path, "definition-index") {
+                if let Ok(index) = crate::definitions::load_definition_index_file(&path) {
                     let age_secs = SystemTime::now()
                         .duration_since(UNIX_EPOCH)
                         .unwrap_or(Duration::ZERO)
@@ -183,9 +319,17 @@ pub fn cmd_info_json() -> serde_json::Value {
                     if index.lossy_file_count > 0 {
                         def_info["lossyUtf8Files"] = serde_json::json!(index.lossy_file_count);
                     }
+                    if !index.walk_limits.is_unlimited() {
+                        def_info["walkLimits"] = walk_limits_json(&index.walk_limits);
+                    }
                     def_info["filename"] = serde_json::json!(filename);
                     indexes.push(def_info);
                 }
+            } else if ext == Some(crate::sqlite::EXTENSION) {
+                if let Some(summary) = crate::sqlite::read_summary(&path) {
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    indexes.push(database_json(&summary, size, filename));
+                }
             } else if ext == Some("git-history") {
                 if let Ok(cache) = crate::git::cache::GitHistoryCache::load_from_disk(&path) {
                     let age_secs = SystemTime::now()
@@ -206,6 +350,45 @@ pub fn cmd_info_json() -> serde_json::Value {
                         "filename": filename,
                     }));
                 }
+            } else if ext == Some("git-grep") {
+                if let Ok(index) = crate::git::commit_index::CommitIndex::load_from_disk(&path) {
+                    let age_secs = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or(Duration::ZERO)
+                        .as_secs()
+                        .saturating_sub(index.built_at);
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    indexes.push(serde_json::json!({
+                        "type": "git-grep",
+                        "repo": index.repo,
+                        "commits": index.commits.len(),
+                        "tokens": index.tokens.len(),
+                        "includesPatches": index.include_patches,
+                        "headHash": index.head_hash,
+                        "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
+                        "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
+                        "filename": filename,
+                    }));
+                }
+            } else if ext == Some("semantic") {
+                if let Ok(index) = crate::semantic::SemanticIndex::load_from_disk(&path) {
+                    let age_secs = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or(Duration::ZERO)
+                        .as_secs()
+                        .saturating_sub(index.built_at);
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    indexes.push(serde_json::json!({
+                        "type": "semantic",
+                        "root": index.root,
+                        "chunks": index.chunks.len(),
+                        "dimensions": index.dimensions,
+                        "embedder": index.embedder,
+                        "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
+                        "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
+                        "filename": filename,
+                    }));
+                }
             }
         }
     }
@@ -216,8 +399,81 @@ pub fn cmd_info_json() -> serde_json::Value {
     })
 }
 
+/// Percentage of the index's files holding a token, to one decimal.
+/// Info of an index stored in SQLite, from its `meta` table (see [`crate::sqlite`]).
+fn database_json(summary: &crate::sqlite::DatabaseSummary, size: u64, filename: String) -> serde_json::Value {
+    let age_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+        .saturating_sub(summary.created_at);
+    let (kind, count) = match summary.kind.as_str() {
+        "files" => ("file", "entries"),
+        "content" => ("content", "files"),
+        _ => ("definition", "files"),
+    };
+    let mut info = serde_json::json!({
+        "type": kind,
+        "root": summary.root,
+        "storage": "sqlite",
+        "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
+        "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
+        "filename": filename,
+    });
+    info[count] = serde_json::json!(summary.files);
+    if kind != "file" {
+        info["extensions"] = serde_json::json!(summary.extensions);
+    }
+    info
+}
+
+fn file_share(index: &ContentIndex, files: usize) -> f64 {
+    (files as f64 * 1000.0 / index.files.len().max(1) as f64).round() / 10.0
+}
+
+/// `--top-tokens` rows of one content index: `{token, files, pct, stop}`.
+fn top_tokens_json(index: &ContentIndex, limit: usize) -> serde_json::Value {
+    let rows: Vec<serde_json::Value> = index.top_tokens(limit).into_iter().map(|(token, files)| serde_json::json!({
+        "token": &**token,
+        "files": files,
+        "pct": file_share(index, files),
+        "stop": index.is_stop_token(token),
+    })).collect();
+    serde_json::json!(rows)
+}
+
+/// Text form of [`top_tokens_json`], under the content index's line.
+fn print_top_tokens(index: &ContentIndex, limit: usize) {
+    let cutoff = match index.stop_token_doc_freq() {
+        Some(_) => format!("stop tokens above {}% of files", index.stop_token_pct),
+        None if index.stop_token_pct == 0 => "no stop-token cutoff".to_string(),
+        None => format!("no stop tokens under {} files", STOP_TOKEN_MIN_FILES),
+    };
+    println!("            top tokens ({}):", cutoff);
+    for (token, files) in index.top_tokens(limit) {
+        println!("            {:>6.1}% {:>8} files  {}{}", file_share(index, files), files, token,
+            if index.is_stop_token(token) { "  [stop]" } else { "" });
+    }
+}
+
 /// Return index info as JSON value for a specific directory (for testing).
 /// This is like `cmd_info_json` but operates on a given directory path.
+/// `max depth 2, pruned: node_modules, src/legacy`
+fn walk_limits_text(limits: &WalkLimits) -> String {
+    let mut parts = Vec::new();
+    if limits.max_depth > 0 {
+        parts.push(format!("max depth {}", limits.max_depth));
+    }
+    if !limits.prune_dirs.is_empty() {
+        parts.push(format!("pruned: {}", limits.prune_dirs.join(", ")));
+    }
+    parts.join(", ")
+}
+
+fn walk_limits_json(limits: &WalkLimits) -> serde_json::Value {
+    serde_json::json!({ "maxDepth": limits.max_depth, "pruneDirs": limits.prune_dirs })
+}
+
 #[cfg(test)]
 pub(crate) fn cmd_info_json_for_dir(dir: &std::path::Path) -> serde_json::Value {
     if !dir.exists() {
@@ -246,7 +502,7 @@ pub(crate) fn cmd_info_json_for_dir(dir: &std::path::Path) -> serde_json::Value
                         "entries": index.entries.len(),
                         "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
                         "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
-                        "stale": index.is_stale(),
+                        "stale": index.is_stale(&SystemClock),
                         "filename": filename,
                     }));
                 }
@@ -258,7 +514,7 @@ pub(crate) fn cmd_info_json_for_dir(dir: &std::path::Path) -> serde_json::Value
                         .as_secs()
                         .saturating_sub(index.created_at);
                     let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
-                    indexes.push(serde_json::json!({
+                    let mut content_info = serde_json::json!({
                         "type": "content",
                         "root": index.root,
                         "files": index.files.len(),
@@ -266,12 +522,27 @@ pub(crate) fn cmd_info_json_for_dir(dir: &std::path::Path) -> serde_json::Value
                         "extensions": index.extensions,
                         "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
                         "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
-                        "stale": index.is_stale(),
-                        "filename": filename,
-                    }));
+                        "stale": index.is_stale(&SystemClock),
+                        "tokenizer": {
+                            "minTokenLen": index.tokenizer.min_token_len,
+                            "maxTokenLen": index.tokenizer.max_token_len,
+                            "stopTokenPct": index.stop_token_pct,
+                        },
+                    });
+                    if !index.walk_limits.is_unlimited() {
+                        content_info["walkLimits"] = walk_limits_json(&index.walk_limits);
+                    }
+                    if index.skipped != SkippedFiles::default() {
+                        content_info["skippedFiles"] = serde_json::json!({
+                            "tooLarge": index.skipped.too_large,
+                            "binary": index.skipped.binary,
+                        });
+                    }
+                    content_info["filename"] = serde_json::json!(filename);
+                    indexes.push(content_info);
                 }
             } else if ext == Some("code-structure") {
-                if let Ok(index) = load_compressed::<crate::definitions::DefinitionIndex>(&path, "definition-index") {
+                if let Ok(index) = crate::definitions::load_definition_index_file(&path) {
                     let age_secs = SystemTime::now()
                         .duration_since(UNIX_EPOCH)
                         .unwrap_or(Duration::ZERO)
@@ -295,9 +566,17 @@ pub(crate) fn cmd_info_json_for_dir(dir: &std::path::Path) -> serde_json::Value
                     if index.lossy_file_count > 0 {
                         def_info["lossyUtf8Files"] = serde_json::json!(index.lossy_file_count);
                     }
+                    if !index.walk_limits.is_unlimited() {
+                        def_info["walkLimits"] = walk_limits_json(&index.walk_limits);
+                    }
                     def_info["filename"] = serde_json::json!(filename);
                     indexes.push(def_info);
                 }
+            } else if ext == Some(crate::sqlite::EXTENSION) {
+                if let Some(summary) = crate::sqlite::read_summary(&path) {
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    indexes.push(database_json(&summary, size, filename));
+                }
             } else if ext == Some("git-history") {
                 if let Ok(cache) = crate::git::cache::GitHistoryCache::load_from_disk(&path) {
                     let age_secs = SystemTime::now()
@@ -318,6 +597,45 @@ pub(crate) fn cmd_info_json_for_dir(dir: &std::path::Path) -> serde_json::Value
                         "filename": filename,
                     }));
                 }
+            } else if ext == Some("git-grep") {
+                if let Ok(index) = crate::git::commit_index::CommitIndex::load_from_disk(&path) {
+                    let age_secs = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or(Duration::ZERO)
+                        .as_secs()
+                        .saturating_sub(index.built_at);
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    indexes.push(serde_json::json!({
+                        "type": "git-grep",
+                        "repo": index.repo,
+                        "commits": index.commits.len(),
+                        "tokens": index.tokens.len(),
+                        "includesPatches": index.include_patches,
+                        "headHash": index.head_hash,
+                        "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
+                        "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
+                        "filename": filename,
+                    }));
+                }
+            } else if ext == Some("semantic") {
+                if let Ok(index) = crate::semantic::SemanticIndex::load_from_disk(&path) {
+                    let age_secs = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or(Duration::ZERO)
+                        .as_secs()
+                        .saturating_sub(index.built_at);
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    indexes.push(serde_json::json!({
+                        "type": "semantic",
+                        "root": index.root,
+                        "chunks": index.chunks.len(),
+                        "dimensions": index.dimensions,
+                        "embedder": index.embedder,
+                        "sizeMb": (size as f64 / 1_048_576.0 * 10.0).round() / 10.0,
+                        "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
+                        "filename": filename,
+                    }));
+                }
             }
         }
     }
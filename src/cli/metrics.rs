@@ -0,0 +1,389 @@
+//! `search metrics`: a project metrics report from the definition index and its code
+//! stats. Totals and per-directory lines and complexity, the largest classes and the
+//! most-called methods, as text, JSON or a standalone HTML page.
+//!
+//! [`metrics_json`] collects the report; [`render_text`] and [`render_html`] format the
+//! same data. Nothing is rebuilt: only the physical line counts are read from disk.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{self, class_metrics, primary_part, DefinitionIndex, DefinitionKind};
+use crate::{index_dir, read_file_lossy, relative_path, SearchError};
+
+use super::args::MetricsArgs;
+use super::output::{print_json, OutputFormat};
+use super::report::xml_escape;
+
+/// Kinds measured as classes
+const CLASS_KINDS: &[DefinitionKind] = &[DefinitionKind::Class, DefinitionKind::Struct, DefinitionKind::Record];
+
+pub(crate) fn cmd_metrics(args: MetricsArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+    let index = definitions::load_definition_index(&args.dir, &exts, &idx_base)
+        .map_err(|_| SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext)))?;
+    if index.code_stats.is_empty() {
+        eprintln!("Warning: the definition index has no code stats; complexity columns are empty. Rebuild it with 'search def-index'.");
+    }
+
+    let report = metrics_json(&index, &file_lines(&index), args.top, args.depth);
+    let text = match format {
+        OutputFormat::Json | OutputFormat::Jsonl if args.out.is_none() => {
+            print_json(format, &report, &["directories"]);
+            return Ok(());
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => serde_json::to_string_pretty(&report).unwrap() + "\n",
+        OutputFormat::Html => render_html(&report),
+        _ => render_text(&report),
+    };
+    match &args.out {
+        Some(path) => {
+            std::fs::write(path, text)?;
+            eprintln!("Wrote the metrics of {} ({} files, {} definitions) to {}",
+                index.root, index.files.len(), index.definitions.len(), path);
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Physical lines of every indexed file; 0 for files that can no longer be read.
+fn file_lines(index: &DefinitionIndex) -> Vec<usize> {
+    index.files.iter()
+        .map(|f| read_file_lossy(Path::new(f)).map_or(0, |(content, _)| content.lines().count()))
+        .collect()
+}
+
+/// The first `depth` directories of `rel`, `.` for files at the root.
+fn directory_of(rel: &str, depth: usize) -> String {
+    let dirs: Vec<&str> = rel.split('/').collect();
+    let dirs = &dirs[..dirs.len() - 1];
+    if dirs.is_empty() || depth == 0 {
+        return ".".to_string();
+    }
+    dirs[..depth.min(dirs.len())].join("/")
+}
+
+/// Counts of one directory or of the whole project.
+#[derive(Debug, Default)]
+struct Totals {
+    files: usize,
+    lines: usize,
+    definitions: usize,
+    classes: usize,
+    /// Definitions with code stats: methods, constructors, functions, property bodies
+    methods: usize,
+    complexity: u64,
+    max_complexity: u16,
+    maintainability: u64,
+}
+
+impl Totals {
+    fn add_file(&mut self, index: &DefinitionIndex, file_id: u32, lines: usize) {
+        self.files += 1;
+        self.lines += lines;
+        for &def_id in index.file_index.get(&file_id).into_iter().flatten() {
+            self.definitions += 1;
+            if CLASS_KINDS.contains(&index.definitions[def_id as usize].kind) {
+                self.classes += 1;
+            }
+            if let Some(stats) = index.code_stats.get(&def_id) {
+                self.methods += 1;
+                self.complexity += stats.cyclomatic_complexity as u64;
+                self.max_complexity = self.max_complexity.max(stats.cyclomatic_complexity);
+                self.maintainability += stats.maintainability_index as u64;
+            }
+        }
+    }
+
+    fn json(&self) -> Value {
+        let average = |sum: u64| if self.methods == 0 { 0.0 } else { (sum as f64 / self.methods as f64 * 100.0).round() / 100.0 };
+        json!({
+            "files": self.files,
+            "lines": self.lines,
+            "definitions": self.definitions,
+            "classes": self.classes,
+            "methods": self.methods,
+            "avgComplexity": average(self.complexity),
+            "maxComplexity": self.max_complexity,
+            "avgMaintainability": average(self.maintainability),
+        })
+    }
+}
+
+/// The report: `totals`, `directories` down to `depth` levels (largest first), and
+/// the `top` largest classes, most complex and most called methods. `lines` holds the
+/// line count of each file of the index.
+pub(crate) fn metrics_json(index: &DefinitionIndex, lines: &[usize], top: usize, depth: usize) -> Value {
+    let mut totals = Totals::default();
+    let mut directories: BTreeMap<String, Totals> = BTreeMap::new();
+    for (file_id, path) in index.files.iter().enumerate() {
+        let file_lines = lines.get(file_id).copied().unwrap_or(0);
+        totals.add_file(index, file_id as u32, file_lines);
+        directories.entry(directory_of(relative_path(&index.root, path), depth)).or_default()
+            .add_file(index, file_id as u32, file_lines);
+    }
+    let mut directories: Vec<(String, Totals)> = directories.into_iter().collect();
+    directories.sort_by(|a, b| b.1.lines.cmp(&a.1.lines).then_with(|| a.0.cmp(&b.0)));
+
+    json!({
+        "root": index.root,
+        "totals": totals.json(),
+        "directories": directories.iter().map(|(dir, t)| {
+            let mut entry = t.json();
+            entry["dir"] = json!(dir);
+            entry
+        }).collect::<Vec<_>>(),
+        "largestClasses": largest_classes_json(index, top),
+        "mostComplex": most_complex_json(index, top),
+        "mostCalled": most_called_json(index, top),
+    })
+}
+
+fn file_of(index: &DefinitionIndex, file_id: u32) -> &str {
+    index.files.get(file_id as usize).map_or("", |f| relative_path(&index.root, f))
+}
+
+fn qualified(def: &definitions::DefinitionEntry) -> String {
+    match &def.parent {
+        Some(parent) => format!("{}.{}", parent, def.name),
+        None => def.name.clone(),
+    }
+}
+
+/// Classes, structs and records by lines; a partial type is listed once with the
+/// lines of all its parts.
+fn largest_classes_json(index: &DefinitionIndex, top: usize) -> Value {
+    let mut classes: Vec<(u32, u32)> = CLASS_KINDS.iter()
+        .flat_map(|kind| index.kind_index.get(kind).into_iter().flatten().copied())
+        .filter(|&id| primary_part(index, id) == id)
+        .map(|id| {
+            let parts = index.partial_types.get(&id).map_or_else(|| vec![id], |p| p.parts.clone());
+            let lines = parts.iter().map(|&p| {
+                let d = &index.definitions[p as usize];
+                d.line_end.saturating_sub(d.line_start) + 1
+            }).sum();
+            (id, lines)
+        })
+        .collect();
+    classes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| index.definitions[a.0 as usize].name.cmp(&index.definitions[b.0 as usize].name)));
+    classes.into_iter().take(top).map(|(id, lines)| {
+        let d = &index.definitions[id as usize];
+        let mut entry = json!({
+            "name": qualified(d),
+            "kind": d.kind.as_str(),
+            "file": file_of(index, d.file_id),
+            "line": d.line_start,
+            "lines": lines,
+        });
+        if let Some(m) = class_metrics(index, id) {
+            entry["members"] = json!(m.members);
+            entry["wmc"] = json!(m.wmc);
+            entry["lcom"] = json!(m.lcom);
+        }
+        entry
+    }).collect()
+}
+
+fn most_complex_json(index: &DefinitionIndex, top: usize) -> Value {
+    let mut methods: Vec<(&u32, &definitions::CodeStats)> = index.code_stats.iter().collect();
+    methods.sort_by(|a, b| b.1.cyclomatic_complexity.cmp(&a.1.cyclomatic_complexity)
+        .then(b.1.cognitive_complexity.cmp(&a.1.cognitive_complexity))
+        .then(a.0.cmp(b.0)));
+    methods.into_iter().take(top).filter_map(|(&def_idx, stats)| {
+        let d = index.definitions.get(def_idx as usize)?;
+        Some(json!({
+            "name": qualified(d),
+            "file": file_of(index, d.file_id),
+            "line": d.line_start,
+            "lines": d.line_end.saturating_sub(d.line_start) + 1,
+            "cyclomatic": stats.cyclomatic_complexity,
+            "cognitive": stats.cognitive_complexity,
+            "maintainability": stats.maintainability_index,
+        }))
+    }).collect()
+}
+
+/// Methods and functions by call sites in the index. A call counts for the methods of
+/// that name declared in its receiver type (the caller's own type for an unqualified
+/// call); an unqualified call also counts for a top-level function of that name.
+fn most_called_json(index: &DefinitionIndex, top: usize) -> Value {
+    // (declaring type, method name), both lowercase → (calls, calling definitions)
+    let mut calls: HashMap<(Option<String>, String), (usize, HashSet<u32>)> = HashMap::new();
+    for (&caller, sites) in &index.method_calls {
+        let caller_type = index.definitions.get(caller as usize).and_then(|d| d.parent.as_deref());
+        for site in sites {
+            let name = site.method_name.to_lowercase();
+            let receiver = site.receiver_type.as_deref().or(caller_type)
+                .map(|t| t.split('<').next().unwrap_or(t).to_lowercase());
+            let mut keys = vec![(receiver, name.clone())];
+            if site.receiver.is_none() && site.receiver_type.is_none() {
+                keys.push((None, name));
+            }
+            for key in keys {
+                let entry = calls.entry(key).or_default();
+                entry.0 += 1;
+                entry.1.insert(caller);
+            }
+        }
+    }
+
+    let mut methods: Vec<(u32, usize, usize)> = [DefinitionKind::Method, DefinitionKind::Function].iter()
+        .flat_map(|kind| index.kind_index.get(kind).into_iter().flatten().copied())
+        .filter_map(|id| {
+            let d = &index.definitions[id as usize];
+            let key = (d.parent.as_deref().map(str::to_lowercase), d.name.to_lowercase());
+            calls.get(&key).map(|(count, callers)| (id, *count, callers.len()))
+        })
+        .collect();
+    methods.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+    methods.into_iter().take(top).map(|(id, count, callers)| {
+        let d = &index.definitions[id as usize];
+        json!({
+            "name": qualified(d),
+            "file": file_of(index, d.file_id),
+            "line": d.line_start,
+            "calls": count,
+            "callers": callers,
+        })
+    }).collect()
+}
+
+/// A section of the report: title, column headers and one row per entry.
+type Section = (&'static str, Vec<&'static str>, Vec<Vec<String>>);
+
+fn rows(value: &Value) -> &[Value] {
+    value.as_array().map_or(&[], Vec::as_slice)
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().map_or_else(|| value.to_string(), str::to_string)
+}
+
+fn location(entry: &Value) -> String {
+    format!("{}:{}", text(&entry["file"]), entry["line"])
+}
+
+fn totals_row(t: &Value) -> Vec<String> {
+    ["files", "lines", "definitions", "classes", "methods", "avgComplexity", "maxComplexity", "avgMaintainability"]
+        .iter().map(|k| text(&t[*k])).collect()
+}
+
+const TOTALS_HEADERS: [&str; 8] = ["Files", "Lines", "Definitions", "Classes", "Methods", "Avg CC", "Max CC", "Avg MI"];
+
+/// The sections of [`metrics_json`]'s output, shared by the text and HTML renderers.
+fn sections(report: &Value) -> Vec<Section> {
+    let mut dir_headers = vec!["Directory"];
+    dir_headers.extend(TOTALS_HEADERS);
+    vec![
+        ("Totals", TOTALS_HEADERS.to_vec(), vec![totals_row(&report["totals"])]),
+        ("Directories", dir_headers, rows(&report["directories"]).iter().map(|d| {
+            let mut row = vec![text(&d["dir"])];
+            row.extend(totals_row(d));
+            row
+        }).collect()),
+        ("Largest classes", vec!["Class", "File", "Lines", "Members", "WMC", "LCOM"], rows(&report["largestClasses"]).iter().map(|c| {
+            vec![text(&c["name"]), location(c), text(&c["lines"]),
+                 c.get("members").map_or(String::new(), text), c.get("wmc").map_or(String::new(), text), c.get("lcom").map_or(String::new(), text)]
+        }).collect()),
+        ("Most complex methods", vec!["Method", "File", "Lines", "Cyclomatic", "Cognitive", "MI"], rows(&report["mostComplex"]).iter().map(|m| {
+            vec![text(&m["name"]), location(m), text(&m["lines"]), text(&m["cyclomatic"]), text(&m["cognitive"]), text(&m["maintainability"])]
+        }).collect()),
+        ("Most called methods", vec!["Method", "File", "Calls", "Callers"], rows(&report["mostCalled"]).iter().map(|m| {
+            vec![text(&m["name"]), location(m), text(&m["calls"]), text(&m["callers"])]
+        }).collect()),
+    ]
+}
+
+/// Plain-text report: one aligned table per section; empty sections are left out.
+pub(crate) fn render_text(report: &Value) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Metrics of {}\n", text(&report["root"]));
+    for (title, headers, rows) in sections(report) {
+        if rows.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "{}", title);
+        let widths: Vec<usize> = (0..headers.len())
+            .map(|i| rows.iter().map(|r| r[i].chars().count()).chain([headers[i].len()]).max().unwrap_or(0))
+            .collect();
+        let line = |cells: Vec<&str>| -> String {
+            cells.iter().zip(&widths).map(|(c, w)| format!("{:<w$}", c, w = w)).collect::<Vec<_>>().join("  ").trim_end().to_string()
+        };
+        let _ = writeln!(out, "  {}", line(headers.clone()));
+        for row in &rows {
+            let _ = writeln!(out, "  {}", line(row.iter().map(String::as_str).collect()));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Standalone HTML page with one table per section.
+pub(crate) fn render_html(report: &Value) -> String {
+    let root = xml_escape(&text(&report["root"]));
+    let mut out = String::new();
+    let _ = writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Metrics of {}</title>", root);
+    out.push_str("<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left}th{background:#f0f0f0}</style>\n</head>\n<body>\n");
+    let _ = writeln!(out, "<h1>Metrics of {}</h1>", root);
+    for (title, headers, rows) in sections(report) {
+        if rows.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "<h2>{}</h2>\n<table>", title);
+        let _ = writeln!(out, "<tr>{}</tr>", headers.iter().map(|h| format!("<th>{}</th>", h)).collect::<String>());
+        for row in rows {
+            let _ = writeln!(out, "<tr>{}</tr>", row.iter().map(|c| format!("<td>{}</td>", xml_escape(c))).collect::<String>());
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::{build_definition_index, DefIndexArgs};
+
+    #[test]
+    fn test_metrics_report_aggregates_directories_classes_and_calls() {
+        let tmp = tempfile::tempdir().unwrap();
+        let write = |rel: &str, text: &str| {
+            let path = tmp.path().join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        };
+        write("src/Orders/OrderService.cs", "public class OrderService\n{\n    private int _count;\n    public int Total(int a) { if (a > 0) { return Count(); } return 0; }\n    public int Count() { return _count; }\n}\n");
+        write("src/Api/OrdersController.cs", "public class OrdersController\n{\n    private OrderService _orders;\n    public int Get() { return _orders.Count(); }\n}\n");
+        let index = build_definition_index(&DefIndexArgs {
+            dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new(),
+        });
+
+        let report = metrics_json(&index, &file_lines(&index), 5, 2);
+        assert_eq!(report["totals"]["files"], 2);
+        assert_eq!(report["totals"]["lines"], 11);
+        assert_eq!(report["totals"]["methods"], 3);
+        assert_eq!(report["totals"]["maxComplexity"], 2);
+        let dirs: Vec<&str> = rows(&report["directories"]).iter().filter_map(|d| d["dir"].as_str()).collect();
+        assert_eq!(dirs, ["src/Orders", "src/Api"]);
+        assert_eq!(report["largestClasses"][0]["name"], "OrderService");
+        assert_eq!(report["largestClasses"][0]["wmc"], 3);
+        assert_eq!(report["mostCalled"][0]["name"], "OrderService.Count", "{}", report["mostCalled"]);
+        assert_eq!(report["mostCalled"][0]["calls"], 2);
+        assert_eq!(report["mostCalled"][0]["callers"], 2);
+
+        let text = render_text(&report);
+        assert!(text.contains("Most called methods\n  Method"), "{}", text);
+        let html = render_html(&report);
+        assert!(html.contains("<td>OrderService.Count</td>"), "{}", html);
+        assert!(html.ends_with("</html>\n"));
+    }
+}
@@ -1,15 +1,33 @@
 //! CLI layer: argument parsing, command dispatch, and subcommand implementations.
 
 pub mod args;
+mod audit;
+mod bench;
+mod brief;
+mod dry_run;
+mod ext_auto;
 mod info;
+mod metrics;
+mod output;
+mod replace;
+mod replay;
+mod report;
+mod sarif;
+mod schema;
 mod serve;
+mod tui;
+mod watch;
 
 pub use args::*;
+use output::{print_json, OutputFormat};
+use report::{search_exit, SearchOutcome, EXIT_ERROR};
 pub use info::cmd_info_json;
 
-use clap::{Parser, Subcommand};
-use std::collections::HashMap;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::borrow::Cow;
 use std::fs;
+use std::io::IsTerminal;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
@@ -20,11 +38,21 @@ use regex::Regex;
 use crate::{
     build_content_index, build_index, cleanup_indexes_for_dir, cleanup_orphaned_indexes,
     content_index_path_for, find_content_index_for_dir,
-    index_dir, index_path_for, load_content_index, load_index,
-    save_content_index, save_index, tokenize,
-    SearchError, DEFAULT_MIN_TOKEN_LEN,
+    index_dir, index_path_for, load_content_index, load_index, load_or_build_content_index_at_rev,
+    read_indexed_file, save_content_index, save_index,
+    SearchError, SystemClock, WalkLimits, DEFAULT_STOP_TOKEN_PCT,
 };
+use crate::config::RepoConfig;
+use search::corpus::{generate_corpus, CorpusLanguage, CorpusSpec};
 use crate::definitions;
+use crate::mcp::handlers::utils::{
+    boosted, explain_json, explain_lines, group_by_dir, highlight_columns, match_fragment, skip_stop_terms, Boost, BoundaryFilter, CaseFilter, MatchLocator, Ranking, TermScore,
+    EXPLAIN_FORMULA,
+};
+use crate::priority::BuildPriority;
+use crate::rank_hook::{repo_path, Candidate, RankHook, RankTool};
+use crate::shards;
+use crate::sqlite;
 
 // ─── CLI ─────────────────────────────────────────────────────────────
 
@@ -45,6 +73,17 @@ Run 'search <COMMAND> --help' for detailed options and examples.\n\
 Common options: -d <DIR> (directory), -e <EXT> (extension filter), -c (count only)"
 )]
 pub(crate) struct Cli {
+    /// Output format: text (default), json (one document shaped like the MCP tool
+    /// response) or jsonl (one record per line, then the summary). grep also takes
+    /// quickfix and junit.
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+
+    /// Hide the live progress line (phase, files/sec, ETA) of index builds. It is
+    /// only drawn when stderr is a terminal.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -61,7 +100,7 @@ pub(crate) enum Commands {
     Fast(FastArgs),
 
     /// Show index info or list indexed directories
-    Info,
+    Info(InfoArgs),
 
     /// Build an inverted (content) index for text/code files
     ContentIndex(ContentIndexArgs),
@@ -69,6 +108,24 @@ pub(crate) enum Commands {
     /// Search file contents using inverted index (instant grep).
     Grep(GrepArgs),
 
+    /// List TODO/FIXME/HACK/BUG comments, optionally with git blame age and author
+    Todos(TodosArgs),
+
+    /// Rank files or methods by git churn x complexity, with top authors per hotspot
+    Hotspots(HotspotsArgs),
+
+    /// Generate a project brief: structure, key types, entry points, hotspots, recent activity
+    Brief(BriefArgs),
+
+    /// Project metrics report: lines and complexity per directory, largest classes, most-called methods
+    Metrics(MetricsArgs),
+
+    /// Find the code comments and commits that mention a ticket ID (PAY-812, #77)
+    Tickets(TicketsArgs),
+
+    /// Replace text across indexed files: diff preview, --yes to apply with a backup journal, --undo
+    Replace(ReplaceArgs),
+
     /// Start MCP (Model Context Protocol) server over stdio.
     Serve(ServeArgs),
 
@@ -78,9 +135,46 @@ pub(crate) enum Commands {
     /// Audit definition index coverage (load from disk, no rebuild)
     DefAudit(definitions::DefAuditArgs),
 
+    /// Show the definition tree (classes, members, line ranges) of one file
+    Outline(definitions::OutlineArgs),
+
+    /// Search code definitions with a key:value query (kind:class base:X attr:Y file:Z)
+    Defs(definitions::DefsArgs),
+
+    /// Find near-duplicate method bodies (token shingle similarity)
+    Dupes(definitions::DupesArgs),
+
+    /// Find private methods and unexported functions that nothing references
+    #[command(name = "deadcode")]
+    DeadCode(definitions::DeadCodeArgs),
+
+    /// Export the call graph of classes or namespaces as DOT, Mermaid or JSON
+    Callgraph(definitions::CallgraphArgs),
+
+    /// Export the definition index as SCIP or LSIF for code-intelligence tools, or as SQL
+    Export(definitions::ExportArgs),
+
     /// Remove orphaned index files, or indexes for a specific directory.
     Cleanup(CleanupArgs),
 
+    /// Re-run the tool calls of a `serve --audit-log` file and report changed results
+    Replay(ReplayArgs),
+
+    /// Run a saved audit suite from .search-index.toml and report pass/fail per check
+    Audit(AuditArgs),
+
+    /// Run MCP tool calls from many threads at once; report latency and lock wait
+    Bench(BenchArgs),
+
+    /// Print the MCP tool schemas and index file layouts, generated from the code
+    DumpSchema(DumpSchemaArgs),
+
+    /// Interactive search: results update as you type, with a preview and $EDITOR integration
+    Tui(TuiArgs),
+
+    /// Generate a synthetic C#/TypeScript codebase for benchmarks and tests
+    GenCorpus(GenCorpusArgs),
+
     /// Show best practices and tips.
     Tips,
 }
@@ -88,21 +182,76 @@ pub(crate) enum Commands {
 // ─── Main entry point ───────────────────────────────────────────────
 
 pub fn run() {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // Flags typed on the command line win over .search-index.toml
+    let sub_matches = matches.subcommand().map(|(_, m)| m);
+    let explicit = |id: &str| from_command_line(sub_matches, id);
+    let format = cli.format;
+    if let Err(e) = check_format(format, matches.subcommand_name().unwrap_or_default()) {
+        eprintln!("Error: {}", e);
+        std::process::exit(EXIT_ERROR);
+    }
+
+    if crate::progress::wanted(cli.quiet, matches!(cli.command, Commands::Serve(_)), std::io::stderr().is_terminal()) {
+        crate::progress::enable();
+    }
 
     let result = match cli.command {
-        Commands::Find(args) => cmd_find(args),
-        Commands::Index(args) => cmd_index(args),
-        Commands::Fast(args) => cmd_fast(args),
-        Commands::Info => { info::cmd_info(); Ok(()) },
-        Commands::ContentIndex(args) => cmd_content_index(args),
-        Commands::Grep(args) => cmd_grep(args),
-        Commands::Serve(args) => { serve::cmd_serve(args); Ok(()) },
-        Commands::DefIndex(args) => cmd_def_index(args),
-        Commands::DefAudit(args) => cmd_def_audit(args),
+        Commands::Find(args) => {
+            let summary_json = args.summary_json.clone();
+            std::process::exit(search_exit("find", summary_json.as_deref(), cmd_find(args, format)))
+        }
+        Commands::Index(args) => cmd_index(args, format),
+        Commands::Fast(args) => {
+            let summary_json = args.summary_json.clone();
+            std::process::exit(search_exit("fast", summary_json.as_deref(), cmd_fast(args, format)))
+        }
+        Commands::Info(args) => { info::cmd_info(args, format); Ok(()) },
+        Commands::ContentIndex(mut args) => RepoConfig::load(Path::new(&args.dir))
+            .map_err(SearchError::InvalidArgs)
+            .and_then(|config| {
+                config.apply_to_content_index(&mut args, explicit);
+                args.ext = ext_auto::resolve_ext(&args.dir, &args.ext, args.hidden, args.no_ignore,
+                    &WalkLimits::new(args.max_depth, &args.prune_dir))?;
+                cmd_content_index(args, format)
+            }),
+        Commands::Grep(mut args) => {
+            let summary_json = args.summary_json.clone();
+            let outcome = RepoConfig::load(Path::new(&args.dir))
+                .map_err(SearchError::InvalidArgs)
+                .and_then(|config| {
+                    config.apply_to_grep(&mut args, explicit);
+                    args.format = format;
+                    cmd_grep(args)
+                });
+            std::process::exit(search_exit("grep", summary_json.as_deref(), outcome))
+        }
+        Commands::Todos(args) => cmd_todos(args, format),
+        Commands::Hotspots(args) => cmd_hotspots(args, format),
+        Commands::Brief(args) => brief::cmd_brief(args, format),
+        Commands::Metrics(args) => metrics::cmd_metrics(args, format),
+        Commands::Tickets(args) => cmd_tickets(args, format),
+        Commands::Replace(args) => replace::cmd_replace(args, format),
+        Commands::Serve(mut args) => RepoConfig::load(Path::new(&args.dir))
+            .map_err(SearchError::InvalidArgs)
+            .and_then(|config| {
+                config.apply_to_serve(&mut args, explicit);
+                args.ext = ext_auto::resolve_ext(&args.dir, &args.ext, false, false, &WalkLimits::default())?;
+                serve::cmd_serve(args);
+                Ok(())
+            }),
+        Commands::DefIndex(args) => cmd_def_index(args, format),
+        Commands::DefAudit(args) => cmd_def_audit(args, format),
+        Commands::Outline(args) => cmd_outline(args, format),
+        Commands::Defs(args) => cmd_defs(args, format),
+        Commands::Dupes(args) => cmd_dupes(args, format),
+        Commands::DeadCode(args) => cmd_dead_code(args, format),
+        Commands::Callgraph(args) => cmd_callgraph(args, format),
+        Commands::Export(args) => cmd_export(args),
         Commands::Cleanup(args) => {
             let idx_base = index_dir();
-            if let Some(ref dir) = args.dir {
+            let removed = if let Some(ref dir) = args.dir {
                 eprintln!("Removing indexes for directory '{}' from {}...", dir, idx_base.display());
                 let removed = cleanup_indexes_for_dir(dir, &idx_base);
                 if removed == 0 {
@@ -110,6 +259,7 @@ pub fn run() {
                 } else {
                     eprintln!("Removed {} index file(s) for '{}'.", removed, dir);
                 }
+                removed
             } else {
                 eprintln!("Scanning for orphaned indexes in {}...", idx_base.display());
                 let removed = cleanup_orphaned_indexes(&idx_base);
@@ -118,9 +268,19 @@ pub fn run() {
                 } else {
                     eprintln!("Removed {} orphaned index file(s).", removed);
                 }
+                removed
+            };
+            if format.is_json() {
+                print_json(format, &serde_json::json!({ "status": "ok", "removed": removed }), &[]);
             }
             Ok(())
         },
+        Commands::Replay(args) => replay::cmd_replay(args, format),
+        Commands::Audit(args) => audit::cmd_audit(args, format),
+        Commands::Bench(args) => bench::cmd_bench(args, format),
+        Commands::DumpSchema(args) => schema::cmd_dump_schema(args),
+        Commands::Tui(args) => tui::cmd_tui(args),
+        Commands::GenCorpus(args) => cmd_gen_corpus(args),
         Commands::Tips => { print!("{}", crate::tips::render_cli()); Ok(()) },
     };
 
@@ -130,47 +290,168 @@ pub fn run() {
     }
 }
 
+/// "Did you mean" tokens printed for each term of a grep that found nothing.
+const GREP_SUGGESTIONS: usize = 3;
+
+/// Commands that only report progress or print prose, with no structured form.
+const TEXT_ONLY_COMMANDS: &[&str] = &["serve", "tui", "gen-corpus", "tips", "export"];
+
+/// Reject a global `--format` that `command` cannot print.
+fn check_format(format: OutputFormat, command: &str) -> Result<(), SearchError> {
+    format.check_supported(command)?;
+    if format.is_json() && TEXT_ONLY_COMMANDS.contains(&command) {
+        return Err(SearchError::InvalidArgs(format!("--format {} is not supported by {}", format.as_str(), command)));
+    }
+    Ok(())
+}
+
+/// Whether flag `id` (the args field name) was typed on the command line rather than
+/// taken from its default.
+fn from_command_line(matches: Option<&ArgMatches>, id: &str) -> bool {
+    matches.is_some_and(|m| m.value_source(id) == Some(ValueSource::CommandLine))
+}
+
 // ─── Small commands ─────────────────────────────────────────────────
 
-fn cmd_index(args: IndexArgs) -> Result<(), SearchError> {
+fn cmd_index(args: IndexArgs, format: OutputFormat) -> Result<(), SearchError> {
+    if args.dry_run {
+        dry_run::dry_run_file_index(&args);
+        return Ok(());
+    }
     let idx_base = index_dir();
     let index = build_index(&args);
-    save_index(&index, &idx_base)?;
-    let path = index_path_for(&args.dir, &idx_base);
+    crate::index::save_index_as(&index, &idx_base, args.storage)?;
+    let path = sqlite::stored_path(&index_path_for(&args.dir, &idx_base));
     let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
     eprintln!(
         "Index saved to {} ({:.1} MB)",
         path.display(),
         size as f64 / 1_048_576.0
     );
+    if format.is_json() {
+        print_json(format, &serde_json::json!({
+            "status": "ok",
+            "entries": index.entries.len(),
+            "path": path.display().to_string(),
+            "sizeMb": size_mb(size),
+        }), &[]);
+    }
     Ok(())
 }
 
-fn cmd_content_index(args: ContentIndexArgs) -> Result<(), SearchError> {
+/// Bytes as MB rounded to one decimal, as the MCP tools report sizes.
+fn size_mb(bytes: u64) -> f64 {
+    (bytes as f64 / 1_048_576.0 * 10.0).round() / 10.0
+}
+
+/// `--format json|jsonl` summary of a saved content index.
+fn print_content_index_summary(format: OutputFormat, index: &crate::ContentIndex, path: &Path, shards: usize, size: u64) {
+    print_json(format, &serde_json::json!({
+        "status": "ok",
+        "files": index.files.len(),
+        "uniqueTokens": index.index.len(),
+        "totalTokens": index.total_tokens,
+        "shards": shards,
+        "path": path.display().to_string(),
+        "sizeMb": size_mb(size),
+    }), &[]);
+}
+
+/// Warn when a running `serve` keeps the index at `index_path` up to date: its next save
+/// replaces what this command writes.
+fn warn_if_served(index_path: &Path) {
+    if let Some(holder) = crate::lock::serving_process(index_path) {
+        eprintln!("Warning: search serve ({}) is serving this index and will save over this build. \
+            Use its search_reindex tool instead, or stop it first.", holder);
+    }
+}
+
+fn cmd_content_index(args: ContentIndexArgs, format: OutputFormat) -> Result<(), SearchError> {
+    if args.dry_run {
+        dry_run::dry_run_content_index(&args);
+        return Ok(());
+    }
     let idx_base = index_dir();
     let exts_str = args.ext.clone();
+    warn_if_served(&content_index_path_for(&args.dir, &exts_str, &idx_base));
+    let shard_count = match args.shards {
+        // An explicit --storage is one file or one database
+        0 if args.storage.is_some() => 1,
+        0 => shards::shard_count_for(&args.dir, &exts_str, &idx_base).unwrap_or(1),
+        n => n,
+    };
+    if shard_count > 1 {
+        let built = shards::build_content_index_sharded(&args, shard_count);
+        let written = shards::save_content_shards(&built, &idx_base)?;
+        crate::index::remove_checkpoints(&args.dir, &exts_str, &idx_base);
+        let manifest = shards::manifest_path_for(&args.dir, &exts_str, &idx_base);
+        eprintln!(
+            "Content index saved as {} shards ({} rewritten) to {}",
+            shard_count, written, manifest.display()
+        );
+        let merged = shards::merge_content_shards(built);
+        if format.is_json() {
+            let size = (0..shard_count)
+                .filter_map(|i| fs::metadata(shards::shard_path_for(&args.dir, &exts_str, i, shard_count, &idx_base)).ok())
+                .map(|m| m.len())
+                .sum();
+            print_content_index_summary(format, &merged, &manifest, shard_count, size);
+        }
+        if args.watch {
+            return watch::watch_content_index(&args, merged, idx_base);
+        }
+        return Ok(());
+    }
+    if shards::remove_content_shards(&args.dir, &exts_str, &idx_base) > 0 {
+        eprintln!("Removed the shard files of the previous sharded index");
+    }
     let index = build_content_index(&args);
-    save_content_index(&index, &idx_base)?;
-    let path = content_index_path_for(&args.dir, &exts_str, &idx_base);
+    crate::index::save_content_index_as(&index, &idx_base, args.storage)?;
+    crate::index::remove_checkpoints(&args.dir, &exts_str, &idx_base);
+    let path = sqlite::stored_path(&content_index_path_for(&args.dir, &exts_str, &idx_base));
     let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
     eprintln!(
         "Content index saved to {} ({:.1} MB)",
         path.display(),
         size as f64 / 1_048_576.0
     );
+    if format.is_json() {
+        print_content_index_summary(format, &index, &path, 1, size);
+    }
+    if args.watch {
+        return watch::watch_content_index(&args, index, idx_base);
+    }
     Ok(())
 }
 
-fn cmd_def_index(args: definitions::DefIndexArgs) -> Result<(), SearchError> {
+fn cmd_def_index(args: definitions::DefIndexArgs, format: OutputFormat) -> Result<(), SearchError> {
+    if args.dry_run {
+        dry_run::dry_run_definition_index(&args);
+        return Ok(());
+    }
+    let start = Instant::now();
     let idx_base = index_dir();
     let index = definitions::build_definition_index(&args);
-    definitions::save_definition_index(&index, &idx_base)?;
+    definitions::save_definition_index_as(&index, &idx_base, args.storage)?;
     eprintln!("[def-index] Done! {} definitions from {} files",
         index.definitions.len(), index.files.len());
+    if format.is_json() {
+        // Same shape as search_reindex_definitions
+        let path = sqlite::stored_path(&definitions::definition_index_path_for(&index.root, &index.extensions.join(","), &idx_base));
+        print_json(format, &serde_json::json!({
+            "status": "ok",
+            "files": index.files.len(),
+            "definitions": index.definitions.len(),
+            "callSites": index.method_calls.values().map(|v| v.len()).sum::<usize>(),
+            "codeStatsEntries": index.code_stats.len(),
+            "sizeMb": size_mb(fs::metadata(path).map(|m| m.len()).unwrap_or(0)),
+            "rebuildTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+        }), &[]);
+    }
     Ok(())
 }
 
-fn cmd_def_audit(args: definitions::DefAuditArgs) -> Result<(), SearchError> {
+fn cmd_def_audit(args: definitions::DefAuditArgs, format: OutputFormat) -> Result<(), SearchError> {
     let idx_base = index_dir();
     let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
 
@@ -195,6 +476,26 @@ fn cmd_def_audit(args: definitions::DefAuditArgs) -> Result<(), SearchError> {
         .filter(|(_, size)| *size > args.min_bytes)
         .collect();
 
+    if format.is_json() {
+        let suspicious: Vec<serde_json::Value> = suspicious.iter().map(|(fid, size)| serde_json::json!({
+            "path": index.files.get(*fid as usize).map(|s| s.as_str()).unwrap_or("?"),
+            "bytes": size,
+        })).collect();
+        print_json(format, &serde_json::json!({
+            "suspicious": suspicious,
+            "summary": {
+                "totalFiles": total_files,
+                "filesWithDefinitions": files_with_defs,
+                "filesWithoutDefinitions": files_without_defs,
+                "definitions": index.definitions.len(),
+                "readErrors": index.parse_errors,
+                "lossyFiles": index.lossy_file_count,
+                "minBytes": args.min_bytes,
+            },
+        }), &["suspicious"]);
+        return Ok(());
+    }
+
     if suspicious.is_empty() {
         eprintln!("[def-audit] No suspicious files (all files >{}B have definitions). ✓", args.min_bytes);
     } else {
@@ -217,9 +518,319 @@ fn cmd_def_audit(args: definitions::DefAuditArgs) -> Result<(), SearchError> {
     Ok(())
 }
 
+fn cmd_gen_corpus(args: GenCorpusArgs) -> Result<(), SearchError> {
+    let languages = args.lang.split(',').filter(|l| !l.trim().is_empty())
+        .map(|l| CorpusLanguage::from_ext(l).ok_or_else(|| SearchError::InvalidArgs(
+            format!("Unsupported corpus language '{}': use cs, ts", l.trim()))))
+        .collect::<Result<Vec<_>, _>>()?;
+    if languages.is_empty() {
+        return Err(SearchError::InvalidArgs("--lang must name at least one language (cs, ts)".to_string()));
+    }
+    if !args.force && fs::read_dir(&args.out).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(SearchError::InvalidArgs(format!(
+            "{} is not empty; pass --force to write into it anyway", args.out.display())));
+    }
+    let spec = CorpusSpec {
+        files: args.files,
+        vocabulary: args.vocabulary,
+        zipf_exponent: args.zipf,
+        languages,
+        methods_per_class: args.methods,
+        statements_per_method: args.statements,
+        files_per_dir: args.files_per_dir,
+        seed: args.seed,
+    };
+
+    let start = Instant::now();
+    let stats = generate_corpus(&args.out, &spec)?;
+    eprintln!("[gen-corpus] {} files, {} classes, {} methods, {} lines ({:.1} MB) written to {} in {:.2}s",
+        stats.files, stats.classes, stats.methods, stats.lines,
+        stats.bytes as f64 / 1_048_576.0, args.out.display(), start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+// ─── cmd_outline ────────────────────────────────────────────────────
+
+fn cmd_outline(args: definitions::OutlineArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+
+    let index = match definitions::load_definition_index(&args.dir, &exts, &idx_base) {
+        Ok(idx) => idx,
+        Err(_) => return Err(SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext))),
+    };
+
+    let file_id = definitions::resolve_file_id(&index, &args.file).map_err(SearchError::InvalidArgs)?;
+    let nodes = definitions::build_outline(&index, file_id);
+    let path = index.files.get(file_id as usize).map(|s| s.as_str()).unwrap_or("?");
+
+    let format = format.or_json_flag(args.json);
+    if format.is_json() {
+        let output = serde_json::json!({
+            "file": path,
+            "outline": definitions::outline_to_json(&nodes),
+        });
+        print_json(format, &output, &["outline"]);
+    } else {
+        println!("{}", path);
+        print!("{}", definitions::render_outline_text(&nodes));
+        eprintln!("[outline] {} definitions", definitions::outline_len(&nodes));
+    }
+    Ok(())
+}
+
+// ─── cmd_defs ───────────────────────────────────────────────────────
+
+fn cmd_defs(args: definitions::DefsArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+
+    let query = match &args.query {
+        Some(q) => Some(crate::mcp::handlers::parse_def_query(q).map_err(SearchError::InvalidArgs)?),
+        None => None,
+    };
+    let index = match definitions::load_definition_index(&args.dir, &exts, &idx_base) {
+        Ok(idx) => idx,
+        Err(_) => return Err(SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext))),
+    };
+    let format = format.or_json_flag(args.json);
+    let Some(query) = query else {
+        if format == OutputFormat::Sarif {
+            return Err(SearchError::InvalidArgs("--format sarif reports code-stats findings of a query, not --implementations-of".to_string()));
+        }
+        let type_name = args.implementations_of.as_deref().unwrap_or_default();
+        return print_implementations(&index, type_name, args.depth, format);
+    };
+    // CODEOWNERS discovered under the index root annotates JSON output and enables owner:
+    let owners = crate::owners::Owners::load_for(Path::new(&index.root), None).ok().flatten();
+    let output = crate::mcp::handlers::search_definitions_json(&index, &query, owners.as_ref(), None, None)
+        .map_err(SearchError::InvalidArgs)?;
+
+    if format == OutputFormat::Sarif {
+        let log = sarif::definitions_sarif(&index.root, &query, &output)?;
+        println!("{}", serde_json::to_string_pretty(&log).unwrap());
+        return Ok(());
+    }
+    if format.is_json() {
+        print_json(format, &output, &["definitions", "containingDefinitions"]);
+        return Ok(());
+    }
+
+    // containsLine responses use a different key than regular searches
+    let defs = output.get("definitions").or_else(|| output.get("containingDefinitions"))
+        .and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    for def in &defs {
+        let field = |k: &str| def.get(k).and_then(|v| v.as_str()).unwrap_or("");
+        let first_line = field("lines").split('-').next().unwrap_or("");
+        let parent = def.get("parent").and_then(|v| v.as_str())
+            .map(|p| format!(" (in {})", p)).unwrap_or_default();
+        println!("{}:{}: {} {}{}", field("file"), first_line, field("kind"), field("name"), parent);
+        if let Some(body) = def.get("body").and_then(|v| v.as_array()) {
+            for line in body.iter().filter_map(|l| l.as_str()) {
+                println!("    {}", line);
+            }
+        }
+    }
+    let total = output["summary"]["totalResults"].as_u64().unwrap_or(defs.len() as u64);
+    eprintln!("\n{} definitions ({} shown)", total, defs.len());
+    Ok(())
+}
+
+/// `search defs --implementations-of`: the subtype tree of one type.
+fn print_implementations(
+    index: &definitions::DefinitionIndex, type_name: &str, depth: usize, format: OutputFormat,
+) -> Result<(), SearchError> {
+    if depth == 0 {
+        return Err(SearchError::InvalidArgs("--depth must be >= 1".to_string()));
+    }
+    let root = definitions::find_type_definitions(index, type_name).first().copied();
+    let mut walker = definitions::HierarchyWalker::new(index, depth.min(10), usize::MAX);
+    let nodes = walker.subtypes(type_name, root);
+
+    if format.is_json() {
+        let output = serde_json::json!({
+            "name": type_name,
+            "subtypes": definitions::hierarchy_to_json(index, &nodes),
+        });
+        print_json(format, &output, &["subtypes"]);
+    } else {
+        println!("{}", type_name);
+        print!("{}", definitions::render_hierarchy_text(index, &nodes));
+        eprintln!("\n{} implementations", definitions::hierarchy_len(&nodes));
+    }
+    Ok(())
+}
+
+// ─── cmd_dupes ──────────────────────────────────────────────────────
+
+fn cmd_dupes(args: definitions::DupesArgs, format: OutputFormat) -> Result<(), SearchError> {
+    if args.threshold <= 0.0 || args.threshold > 1.0 {
+        return Err(SearchError::InvalidArgs(format!("--threshold must be in (0, 1], got {}", args.threshold)));
+    }
+    if args.shingle_size == 0 {
+        return Err(SearchError::InvalidArgs("--shingle-size must be >= 1".to_string()));
+    }
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+    let index = match definitions::load_definition_index(&args.dir, &exts, &idx_base) {
+        Ok(idx) => idx,
+        Err(_) => return Err(SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext))),
+    };
+
+    let start = Instant::now();
+    let opts = definitions::DupeOptions {
+        shingle_size: args.shingle_size,
+        min_lines: args.min_lines,
+        min_similarity: args.threshold,
+        file_filter: args.file,
+        exclude_dir: args.exclude_dir,
+        ..Default::default()
+    };
+    let report = definitions::find_duplicates(&index, &opts);
+    let shown = if args.max_results == 0 { report.pairs.len() } else { report.pairs.len().min(args.max_results) };
+
+    let format = format.or_json_flag(args.json);
+    if format == OutputFormat::Sarif {
+        println!("{}", serde_json::to_string_pretty(&sarif::dupes_sarif(&index, &report.pairs[..shown])).unwrap());
+    } else if format.is_json() {
+        let pairs: Vec<serde_json::Value> = report.pairs[..shown].iter()
+            .map(|p| definitions::clone_pair_to_json(&index, p))
+            .collect();
+        print_json(format, &serde_json::json!({ "pairs": pairs }), &["pairs"]);
+    } else {
+        for pair in &report.pairs[..shown] {
+            let (a, b) = (&index.definitions[pair.a as usize], &index.definitions[pair.b as usize]);
+            println!("{:.0}%  {}:{}-{} {}", pair.similarity * 100.0,
+                index.files[a.file_id as usize], a.line_start, a.line_end, a.name);
+            println!("      {}:{}-{} {}",
+                index.files[b.file_id as usize], b.line_start, b.line_end, b.name);
+        }
+    }
+    eprintln!("\n{} clone pairs ({} shown) among {} bodies in {:.1}s",
+        report.pairs.len(), shown, report.bodies_scanned, start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+// ─── cmd_dead_code ──────────────────────────────────────────────────
+
+fn cmd_dead_code(args: definitions::DeadCodeArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+    let index = match definitions::load_definition_index(&args.dir, &exts, &idx_base) {
+        Ok(idx) => idx,
+        Err(_) => return Err(SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext))),
+    };
+
+    let start = Instant::now();
+    let opts = definitions::DeadCodeOptions { file_filter: args.file, exclude_dir: args.exclude_dir };
+    let report = definitions::find_dead_code(&index, &opts);
+    let shown = if args.max_results == 0 { report.dead.len() } else { report.dead.len().min(args.max_results) };
+
+    let format = format.or_json_flag(args.json);
+    if format == OutputFormat::Sarif {
+        println!("{}", serde_json::to_string_pretty(&sarif::dead_code_sarif(&index, &report.dead[..shown])).unwrap());
+    } else if format.is_json() {
+        let defs: Vec<serde_json::Value> = report.dead[..shown].iter()
+            .map(|&d| definitions::dead_code_to_json(&index, d))
+            .collect();
+        print_json(format, &serde_json::json!({ "definitions": defs }), &["definitions"]);
+    } else {
+        for &def_idx in &report.dead[..shown] {
+            let def = &index.definitions[def_idx as usize];
+            let name = def.parent.as_ref().map_or_else(|| def.name.clone(), |p| format!("{}.{}", p, def.name));
+            println!("{}:{}-{} {} {}", index.files[def.file_id as usize], def.line_start, def.line_end, def.kind.as_str(), name);
+        }
+    }
+    eprintln!("\n{} unreferenced definitions ({} shown) among {} candidates in {:.1}s",
+        report.dead.len(), shown, report.candidates_scanned, start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+// ─── cmd_callgraph ──────────────────────────────────────────────────
+
+fn cmd_callgraph(args: definitions::CallgraphArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let scope = definitions::GraphScope {
+        classes: args.class.iter().map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect(),
+        namespaces: args.namespace.iter().map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect(),
+        internal_only: args.internal,
+    };
+    if scope.is_empty() {
+        return Err(SearchError::InvalidArgs("callgraph needs --class or --namespace".to_string()));
+    }
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+    let index = match definitions::load_definition_index(&args.dir, &exts, &idx_base) {
+        Ok(idx) => idx,
+        Err(_) => return Err(SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext))),
+    };
+
+    let start = Instant::now();
+    let edges = definitions::scope_call_graph(&index, &scope);
+    let export = if format.is_json() { definitions::GraphFormat::Json } else { args.export };
+    match definitions::render_call_graph(&edges, export) {
+        Some(text) => print!("{}", text),
+        None => print_json(format, &definitions::call_graph_to_json(&edges), &["edges"]),
+    }
+    eprintln!("\n{} call edges in {:.1}s", edges.len(), start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+// ─── cmd_export ─────────────────────────────────────────────────────
+
+fn cmd_export(args: definitions::ExportArgs) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+    let index = match definitions::load_definition_index(&args.dir, &exts, &idx_base) {
+        Ok(idx) => idx,
+        Err(_) => return Err(SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext))),
+    };
+
+    let start = Instant::now();
+    let out = args.out.unwrap_or_else(|| args.to.default_file().to_string());
+    if args.to == definitions::ExportFormat::Sql {
+        std::fs::write(&out, definitions::to_sql(&index))?;
+        eprintln!("Wrote {} (sql, {} files, {} definitions) in {:.1}s",
+            out, index.file_index.len(), index.file_index.values().map(Vec::len).sum::<usize>(), start.elapsed().as_secs_f64());
+        return Ok(());
+    }
+    let export = definitions::build_export_index(&index);
+    let bytes = match args.to {
+        definitions::ExportFormat::Lsif => definitions::to_lsif(&export).into_bytes(),
+        _ => definitions::to_scip(&export),
+    };
+    std::fs::write(&out, &bytes)?;
+    let occurrences: usize = export.documents.iter().map(|d| d.occurrences.len()).sum();
+    eprintln!("Wrote {} ({}, {} documents, {} symbols, {} occurrences) in {:.1}s",
+        out, args.to.as_str(), export.documents.len(), export.symbols.len(), occurrences, start.elapsed().as_secs_f64());
+    Ok(())
+}
+
 // ─── cmd_find ───────────────────────────────────────────────────────
 
-fn cmd_find(args: FindArgs) -> Result<(), SearchError> {
+/// JSON record of one line matched by `find --contents`. `matchColumns` are the 1-based
+/// byte `[start, end]` (end inclusive) of each match in the untrimmed line, the same
+/// column `-o` prints; with `only_matching`, `fragments` replaces `text`.
+fn find_line_record(line_num: usize, line: &str, columns: &[(usize, usize)], only_matching: bool, context_chars: usize) -> serde_json::Value {
+    let mut record = serde_json::json!({
+        "line": line_num,
+        "matchColumns": columns.iter().map(|&(s, e)| [s + 1, e]).collect::<Vec<_>>(),
+    });
+    if only_matching {
+        record["fragments"] = serde_json::json!(columns.iter()
+            .map(|&(s, e)| match_fragment(line, s, e, context_chars)).collect::<Vec<_>>());
+    } else {
+        record["text"] = serde_json::json!(line);
+    }
+    record
+}
+
+fn cmd_find(args: FindArgs, format: OutputFormat) -> Result<SearchOutcome, SearchError> {
     let start = Instant::now();
 
     let pattern = if args.ignore_case {
@@ -251,6 +862,8 @@ fn cmd_find(args: FindArgs) -> Result<(), SearchError> {
 
     let match_count = AtomicUsize::new(0);
     let file_count = AtomicUsize::new(0);
+    // --format json|jsonl: records in the shape of search_find, sorted once the walk ends
+    let records: std::sync::Mutex<Vec<serde_json::Value>> = std::sync::Mutex::new(Vec::new());
 
     let mut builder = WalkBuilder::new(root);
     builder.hidden(!args.hidden);
@@ -272,14 +885,23 @@ fn cmd_find(args: FindArgs) -> Result<(), SearchError> {
     builder.threads(thread_count);
 
     if args.contents {
+        // Where the matches fall in a line: the regex, or the literal pattern
+        let finder = match &re {
+            Some(re) => re.clone(),
+            None => Regex::new(&format!("{}{}", if args.ignore_case { "(?i)" } else { "" }, regex::escape(&args.pattern)))
+                .map_err(|e| SearchError::InvalidRegex { pattern: args.pattern.clone(), source: e })?,
+        };
         builder.build_parallel().run(|| {
             let pattern = pattern.clone();
             let re = re.clone();
+            let finder = finder.clone();
             let ignore_case = args.ignore_case;
             let count_only = args.count;
+            let (only_matching, context_chars) = (args.only_matching, args.context_chars);
             let ext_filter = args.ext.clone();
             let match_count = &match_count;
             let file_count = &file_count;
+            let records = &records;
 
             Box::new(move |entry| {
                 let entry = match entry {
@@ -310,6 +932,7 @@ fn cmd_find(args: FindArgs) -> Result<(), SearchError> {
                 if matched {
                     match_count.fetch_add(1, Ordering::Relaxed);
                     if !count_only {
+                        let mut lines = Vec::new();
                         for (line_num, line) in content.lines().enumerate() {
                             let line_matched = if let Some(ref re) = re {
                                 re.is_match(line)
@@ -318,10 +941,29 @@ fn cmd_find(args: FindArgs) -> Result<(), SearchError> {
                             } else {
                                 line.contains(&pattern)
                             };
-                            if line_matched {
+                            if !line_matched {
+                                continue;
+                            }
+                            let columns: Vec<(usize, usize)> = finder.find_iter(line)
+                                .filter(|m| !m.is_empty())
+                                .map(|m| (m.start(), m.end()))
+                                .collect();
+                            if format.is_json() {
+                                lines.push(find_line_record(line_num + 1, line, &columns, only_matching, context_chars));
+                            } else if only_matching {
+                                for &(s, e) in &columns {
+                                    println!("{}:{}:{}: {}", entry.path().display(), line_num + 1, s + 1, match_fragment(line, s, e, context_chars));
+                                }
+                            } else {
                                 println!("{}:{}: {}", entry.path().display(), line_num + 1, line.trim());
                             }
                         }
+                        if format.is_json() {
+                            records.lock().unwrap_or_else(|e| e.into_inner()).push(serde_json::json!({
+                                "path": entry.path().display().to_string(),
+                                "matches": lines,
+                            }));
+                        }
                     }
                 }
                 ignore::WalkState::Continue
@@ -336,6 +978,7 @@ fn cmd_find(args: FindArgs) -> Result<(), SearchError> {
             let ext_filter = args.ext.clone();
             let match_count = &match_count;
             let file_count = &file_count;
+            let records = &records;
 
             Box::new(move |entry| {
                 let entry = match entry {
@@ -361,7 +1004,9 @@ fn cmd_find(args: FindArgs) -> Result<(), SearchError> {
                 };
                 if matched {
                     match_count.fetch_add(1, Ordering::Relaxed);
-                    if !count_only {
+                    if !count_only && format.is_json() {
+                        records.lock().unwrap_or_else(|e| e.into_inner()).push(serde_json::json!({ "path": entry.path().display().to_string() }));
+                    } else if !count_only {
                         println!("{}", entry.path().display());
                     }
                 }
@@ -373,31 +1018,44 @@ fn cmd_find(args: FindArgs) -> Result<(), SearchError> {
     let elapsed = start.elapsed();
     let matches = match_count.load(Ordering::Relaxed);
     let files = file_count.load(Ordering::Relaxed);
+    let summary = serde_json::json!({
+        "totalMatches": matches,
+        "totalFilesScanned": files,
+        "searchTimeMs": elapsed.as_secs_f64() * 1000.0,
+    });
+    if format.is_json() {
+        let mut records = crate::index::recover_mutex(records, "find");
+        records.sort_by(|a, b| a["path"].as_str().cmp(&b["path"].as_str()));
+        print_json(format, &serde_json::json!({ "files": records, "summary": summary }), &["files"]);
+    }
     eprintln!("\n{} matches found among {} entries in {:.3}s ({} threads)",
         matches, files, elapsed.as_secs_f64(), thread_count);
-    Ok(())
+    Ok(SearchOutcome { matched: matches > 0, summary })
 }
 
 // ─── cmd_fast ───────────────────────────────────────────────────────
 
-fn cmd_fast(args: FastArgs) -> Result<(), SearchError> {
+fn cmd_fast(args: FastArgs, format: OutputFormat) -> Result<SearchOutcome, SearchError> {
     let start = Instant::now();
     let idx_base = index_dir();
 
     let index = match load_index(&args.dir, &idx_base) {
         Ok(idx) => {
-            if idx.is_stale() && args.auto_reindex {
+            if idx.is_stale(&SystemClock) && args.auto_reindex {
                 eprintln!("Index is stale, rebuilding...");
                 let new_index = build_index(&IndexArgs {
                     dir: args.dir.clone(), max_age_hours: idx.max_age_secs / 3600,
                     hidden: false, no_ignore: false, threads: 0,
+                    dry_run: false,
+                    build_priority: BuildPriority::Normal,
+                    storage: None,
                 });
                 if let Err(e) = save_index(&new_index, &idx_base) {
                     eprintln!("Warning: failed to save updated index: {}", e);
                 }
                 new_index
             } else {
-                if idx.is_stale() {
+                if idx.is_stale(&SystemClock) {
                     eprintln!("Warning: index is stale (use 'search index -d {}' to rebuild)", args.dir);
                 }
                 idx
@@ -408,6 +1066,9 @@ fn cmd_fast(args: FastArgs) -> Result<(), SearchError> {
             let new_index = build_index(&IndexArgs {
                 dir: args.dir.clone(), max_age_hours: 24,
                 hidden: false, no_ignore: false, threads: 0,
+                dry_run: false,
+                build_priority: BuildPriority::Normal,
+                storage: None,
             });
             if let Err(e) = save_index(&new_index, &idx_base) {
                 eprintln!("Warning: failed to save index: {}", e);
@@ -432,6 +1093,7 @@ fn cmd_fast(args: FastArgs) -> Result<(), SearchError> {
 
     let search_start = Instant::now();
     let mut match_count = 0usize;
+    let mut records: Vec<serde_json::Value> = Vec::new();
 
     for entry in &index.entries {
         if args.dirs_only && !entry.is_dir { continue; }
@@ -451,48 +1113,355 @@ fn cmd_fast(args: FastArgs) -> Result<(), SearchError> {
         let matched = if let Some(ref re) = re { re.is_match(&search_name) } else { search_name.contains(&pattern) };
         if matched {
             match_count += 1;
-            if !args.count {
-                if entry.is_dir { println!("[DIR]  {}", entry.path); }
-                else { println!("       {}", entry.path); }
+            if args.count {
+                continue;
+            }
+            if format.is_json() {
+                records.push(serde_json::json!({ "path": entry.path, "size": entry.size, "isDir": entry.is_dir }));
+            } else if entry.is_dir {
+                println!("[DIR]  {}", entry.path);
+            } else {
+                println!("       {}", entry.path);
             }
         }
     }
 
     let search_elapsed = search_start.elapsed();
     let total_elapsed = start.elapsed();
+    let summary = serde_json::json!({
+        "totalMatches": match_count,
+        "totalIndexed": index.entries.len(),
+        "searchTimeMs": search_elapsed.as_secs_f64() * 1000.0,
+    });
+    if format.is_json() {
+        print_json(format, &serde_json::json!({ "files": records, "summary": summary }), &["files"]);
+    }
     eprintln!("\n{} matches found among {} indexed entries", match_count, index.entries.len());
     eprintln!("Index load: {:.3}s | Search: {:.6}s | Total: {:.3}s",
         load_elapsed.as_secs_f64(), search_elapsed.as_secs_f64(), total_elapsed.as_secs_f64());
+    Ok(SearchOutcome { matched: match_count > 0, summary })
+}
+
+// ─── cmd_todos ──────────────────────────────────────────────────────
+
+fn cmd_todos(args: TodosArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.clone().unwrap_or_default();
+    let index = match load_content_index(&args.dir, &exts, &idx_base) {
+        Ok(idx) => idx,
+        Err(_) => find_content_index_for_dir(&args.dir, &idx_base)
+            .ok_or_else(|| SearchError::IndexNotFound { dir: args.dir.clone() })?,
+    };
+
+    let mut query = serde_json::json!({
+        "blame": args.blame,
+        "excludeDir": args.exclude_dir,
+        "sortBy": args.sort,
+        "maxResults": args.max_results,
+    });
+    for (key, value) in [("marker", &args.marker), ("dir", &args.path), ("author", &args.author)] {
+        if let Some(v) = value {
+            query[key] = serde_json::json!(v);
+        }
+    }
+    for (key, value) in [("minAgeDays", args.min_age_days), ("maxAgeDays", args.max_age_days)] {
+        if let Some(v) = value {
+            query[key] = serde_json::json!(v);
+        }
+    }
+    let output = crate::mcp::handlers::search_todos_json(&index, &query)
+        .map_err(SearchError::InvalidArgs)?;
+
+    let format = format.or_json_flag(args.json);
+    if format.is_json() {
+        print_json(format, &output, &["todos"]);
+        return Ok(());
+    }
+    let todos = output["todos"].as_array().cloned().unwrap_or_default();
+    for todo in &todos {
+        let field = |k: &str| todo.get(k).and_then(|v| v.as_str()).unwrap_or("");
+        let blame = match todo.get("ageDays") {
+            Some(age) => format!("  ({}, {}, {}d)", field("author"), field("date"), age),
+            None => String::new(),
+        };
+        println!("{}:{}: {} {}{}", field("file"), todo["line"], field("marker"), field("text"), blame);
+    }
+    let summary = &output["summary"];
+    eprintln!("\n{} markers ({} shown)", summary["totalMatching"], todos.len());
+    if let Some(hint) = summary.get("hint").and_then(|v| v.as_str()) {
+        eprintln!("{}", hint);
+    }
+    Ok(())
+}
+
+// ─── cmd_hotspots ───────────────────────────────────────────────────
+
+fn cmd_hotspots(args: HotspotsArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect::<Vec<_>>().join(",");
+    let index = definitions::load_definition_index(&args.dir, &exts, &idx_base)
+        .map_err(|_| SearchError::InvalidArgs(format!(
+            "No definition index found for dir='{}' ext='{}'. Run 'search def-index' first.", args.dir, args.ext)))?;
+    let cache = crate::git::cache::GitHistoryCache::load_or_build(&args.dir, &idx_base)
+        .map_err(SearchError::Git)?;
+    let owners = crate::owners::Owners::load_for(Path::new(&index.root), None).ok().flatten();
+
+    let mut query = serde_json::json!({
+        "level": args.level,
+        "metric": args.metric,
+        "excludeDir": args.exclude_dir,
+        "topAuthors": args.top_authors,
+        "maxResults": args.max_results,
+    });
+    for (key, value) in [("from", &args.from), ("to", &args.to), ("dir", &args.path), ("owner", &args.owner)] {
+        if let Some(v) = value {
+            query[key] = serde_json::json!(v);
+        }
+    }
+    let output = crate::mcp::handlers::search_hotspots_json(&index, &cache, &query, owners.as_ref())
+        .map_err(SearchError::InvalidArgs)?;
+
+    let format = format.or_json_flag(args.json);
+    if format.is_json() {
+        print_json(format, &output, &["hotspots"]);
+        return Ok(());
+    }
+    let hotspots = output["hotspots"].as_array().cloned().unwrap_or_default();
+    for hotspot in &hotspots {
+        let field = |k: &str| hotspot.get(k).and_then(|v| v.as_str()).unwrap_or("");
+        let location = match hotspot.get("name").and_then(|v| v.as_str()) {
+            Some(name) => format!("{}:{} {}", field("file"), field("lines").split('-').next().unwrap_or(""), name),
+            None => field("file").to_string(),
+        };
+        let authors: Vec<&str> = hotspot["topAuthors"].as_array().map(|a| a.iter()
+            .filter_map(|a| a["name"].as_str()).collect()).unwrap_or_default();
+        println!("{:>8}  churn {:>4} x complexity {:>4}  {}  [{}]",
+            hotspot["score"], hotspot["churn"], hotspot["complexity"], location, authors.join(", "));
+    }
+    let summary = &output["summary"];
+    eprintln!("\n{} {}s ranked ({} shown, {} complexity)", summary["totalCandidates"],
+        summary["level"].as_str().unwrap_or(""), hotspots.len(), summary["metric"].as_str().unwrap_or(""));
+    if let Some(hint) = summary.get("hint").and_then(|v| v.as_str()) {
+        eprintln!("{}", hint);
+    }
+    Ok(())
+}
+
+// ─── cmd_tickets ────────────────────────────────────────────────────
+
+fn cmd_tickets(args: TicketsArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let idx_base = index_dir();
+    let exts = args.ext.clone().unwrap_or_default();
+    let index = match load_content_index(&args.dir, &exts, &idx_base) {
+        Ok(idx) => idx,
+        Err(_) => find_content_index_for_dir(&args.dir, &idx_base)
+            .ok_or_else(|| SearchError::IndexNotFound { dir: args.dir.clone() })?,
+    };
+    let cache = match crate::git::cache::GitHistoryCache::load_or_build(&args.dir, &idx_base) {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            eprintln!("Commits skipped: {}", e);
+            None
+        }
+    };
+    let owners = crate::owners::Owners::load_for(Path::new(&index.root), None).ok().flatten();
+
+    let mut query = serde_json::json!({
+        "ticket": args.ticket,
+        "excludeDir": args.exclude_dir,
+        "maxResults": args.max_results,
+    });
+    for (key, value) in [("dir", &args.path), ("owner", &args.owner)] {
+        if let Some(v) = value {
+            query[key] = serde_json::json!(v);
+        }
+    }
+    let output = crate::mcp::handlers::search_tickets_json(&index, cache.as_ref(), &query, owners.as_ref())
+        .map_err(SearchError::InvalidArgs)?;
+
+    let format = format.or_json_flag(args.json);
+    if format.is_json() {
+        print_json(format, &output, &["code", "commits"]);
+        return Ok(());
+    }
+    let code = output["code"].as_array().cloned().unwrap_or_default();
+    for entry in &code {
+        let lines: Vec<String> = entry["lines"].as_array().map(|a| a.iter().map(|l| l.to_string()).collect()).unwrap_or_default();
+        println!("{}:{}", entry["file"].as_str().unwrap_or(""), lines.join(","));
+    }
+    let commits = output["commits"].as_array().cloned().unwrap_or_default();
+    for commit in &commits {
+        let field = |k: &str| commit.get(k).and_then(|v| v.as_str()).unwrap_or("");
+        println!("{} {} {}  {}", &field("hash")[..field("hash").len().min(10)],
+            field("date").split(' ').next().unwrap_or(""), field("author"), field("subject"));
+    }
+    let summary = &output["summary"];
+    eprintln!("\n{}: {} references in {} files, {} commits", summary["ticket"].as_str().unwrap_or(""),
+        summary["codeRefs"], summary["codeFiles"], summary["commits"]);
+    if let Some(hint) = summary.get("hint").and_then(|v| v.as_str()) {
+        eprintln!("{}", hint);
+    }
     Ok(())
 }
 
 // ─── cmd_grep ───────────────────────────────────────────────────────
 
-fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
+/// Print `--format quickfix|junit` output for grep findings to stdout.
+fn print_grep_report(args: &GrepArgs, findings: &[report::Finding], files_total: usize) {
+    let rule = args.rule.as_deref().unwrap_or(&args.pattern);
+    match args.format {
+        OutputFormat::Quickfix => print!("{}", report::render_quickfix(findings, rule, args.severity)),
+        OutputFormat::Junit => print!("{}", report::render_junit(findings, rule, args.severity, files_total)),
+        OutputFormat::Text | OutputFormat::Json | OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Sarif => {}
+    }
+}
+
+/// A grep result for JSON output: path, score (none for phrase search), matched lines
+/// and the `--explain` breakdown.
+type GrepJsonFile<'a> = (&'a str, Option<f64>, &'a [u32], Option<serde_json::Value>);
+
+/// Print grep results as `json`/`jsonl` in the shape of MCP `search_grep`: a `files`
+/// array (dropped by `--count`) and a `summary`. `files` holds the file entries; with
+/// `--show-lines` their `lineContent` carries the `matchColumns` `locator` finds.
+fn print_grep_json(args: &GrepArgs, index: &crate::ContentIndex, files: Vec<GrepJsonFile>,
+    locator: &MatchLocator, summary: serde_json::Value) {
+    if args.count {
+        print_json(args.format, &serde_json::json!({ "summary": summary }), &[]);
+        return;
+    }
+    let context = args.context.max(args.before).max(args.after);
+    let contents = if args.show_lines {
+        crate::mcp::handlers::utils::read_indexed_files(index, &files.iter().map(|(path, _, _, _)| *path).collect::<Vec<_>>())
+    } else {
+        Vec::new()
+    };
+    let files: Vec<serde_json::Value> = files.into_iter().enumerate().map(|(i, (path, score, lines, explain))| {
+        let mut entry = serde_json::json!({ "path": path, "occurrences": lines.len(), "lines": lines });
+        if let Some(score) = score {
+            entry["score"] = serde_json::json!((score * 10000.0).round() / 10000.0);
+        }
+        if let Some(explain) = explain {
+            entry["explain"] = explain;
+        }
+        if let Some(Some(content)) = contents.get(i) {
+            entry["lineContent"] = crate::mcp::handlers::utils::build_line_content_from_matches(content, lines, context, Some(locator), args.highlight);
+        }
+        entry
+    }).collect();
+    print_json(args.format, &serde_json::json!({ "files": files, "summary": summary }), &["files"]);
+}
+
+/// Print `--group-by dir` output: one line per directory, or a `groups` array in the
+/// shape of MCP `search_grep` with `groupBy`. `files` are (path, occurrences) in result
+/// order.
+fn print_grep_groups<'a>(args: &GrepArgs, index: &crate::ContentIndex, files: impl IntoIterator<Item = (&'a str, usize)>,
+    mut summary: serde_json::Value) {
+    let mut groups = group_by_dir(&index.root, files, args.group_depth);
+    summary["groupedBy"] = serde_json::json!("dir");
+    summary["totalGroups"] = serde_json::json!(groups.len());
+    if args.max_results > 0 {
+        groups.truncate(args.max_results);
+    }
+    if args.format.is_json() {
+        print_json(args.format, &serde_json::json!({ "groups": groups, "summary": summary }), &["groups"]);
+        return;
+    }
+    for group in &groups {
+        let top: Vec<&str> = group["topFiles"].as_array().into_iter().flatten()
+            .filter_map(|f| f["path"].as_str())
+            .map(|p| p.rsplit(['/', '\\']).next().unwrap_or(p))
+            .collect();
+        println!("{:>7} occurrences {:>5} files  {}  ({})",
+            group["occurrences"].as_u64().unwrap_or(0), group["files"].as_u64().unwrap_or(0),
+            group["dir"].as_str().unwrap_or(""), top.join(", "));
+    }
+}
+
+/// A `--show-lines` line, with its matches marked when `highlight` is set.
+fn shown_line<'a>(line: &'a str, highlight: bool, locator: &MatchLocator) -> std::borrow::Cow<'a, str> {
+    if highlight {
+        highlight_columns(line, &locator.columns(line)).into()
+    } else {
+        line.into()
+    }
+}
+
+/// Build arguments that rebuild the stale `idx` of `dir` for `grep --auto-reindex`,
+/// with the extensions, size limit, token length limits and walk limits it was built with.
+fn auto_reindex_args(dir: &str, idx: &crate::ContentIndex) -> ContentIndexArgs {
+    ContentIndexArgs {
+        dir: dir.to_string(), ext: idx.extensions.join(","), max_age_hours: idx.max_age_secs / 3600,
+        hidden: false, no_ignore: false, threads: 0, min_token_len: idx.tokenizer.min_token_len,
+        dry_run: false,
+        build_priority: BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: idx.max_file_size,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: idx.tokenizer.max_token_len,
+        stop_token_pct: DEFAULT_STOP_TOKEN_PCT,
+        checkpoint_secs: 0,
+        max_depth: idx.walk_limits.max_depth,
+        prune_dir: idx.walk_limits.prune_dirs.clone(),
+        storage: None,
+    }
+}
+
+fn cmd_grep(args: GrepArgs) -> Result<SearchOutcome, SearchError> {
     let start = Instant::now();
+    let rank_hook = args.rank_hook.as_deref().map(RankHook::load).transpose().map_err(SearchError::InvalidArgs)?;
     let idx_base = index_dir();
     let exts_for_load = args.ext.clone().unwrap_or_default();
-
-    let index = match load_content_index(&args.dir, &exts_for_load, &idx_base) {
-        Ok(idx) => {
-            if idx.is_stale() && args.auto_reindex {
-                eprintln!("Content index is stale, rebuilding...");
-                let ext_str = idx.extensions.join(",");
-                let new_idx = build_content_index(&ContentIndexArgs {
-                    dir: args.dir.clone(), ext: ext_str, max_age_hours: idx.max_age_secs / 3600,
-                    hidden: false, no_ignore: false, threads: 0, min_token_len: DEFAULT_MIN_TOKEN_LEN,
-                });
-                let _ = save_content_index(&new_idx, &idx_base);
-                new_idx
-            } else {
-                if idx.is_stale() { eprintln!("Warning: content index is stale"); }
-                idx
-            }
+    // Exact and substring searches need only the postings of their terms: an index
+    // stored in SQLite loads just those
+    let pattern_terms: Vec<String> = args.pattern.split(',')
+        .map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect();
+    let token_filter = if args.regex || args.phrase || args.rev.is_some() {
+        None
+    } else if args.exact {
+        Some(sqlite::TokenFilter::Exact(&pattern_terms))
+    } else {
+        Some(sqlite::TokenFilter::Containing(&pattern_terms))
+    };
+    let database = token_filter.as_ref().and_then(|_| crate::index::content_database_for(&args.dir, &exts_for_load, &idx_base));
+    // A sharded index looks the terms up in each shard and merges just those postings
+    let shard_set = token_filter.as_ref().filter(|_| database.is_none())
+        .and_then(|_| shards::ShardSet::open_for(&args.dir, &exts_for_load, &idx_base));
+    let mut partial = false;
+
+    let index = if let Some(ref rev) = args.rev {
+        if exts_for_load.is_empty() {
+            return Err(SearchError::InvalidArgs("--rev requires --ext (extensions to index at that revision)".to_string()));
         }
-        Err(_) => {
-            match find_content_index_for_dir(&args.dir, &idx_base) {
-                Some(idx) => idx,
-                None => return Err(SearchError::IndexNotFound { dir: args.dir.clone() }),
+        let idx = load_or_build_content_index_at_rev(&args.dir, &exts_for_load, rev, &idx_base)?;
+        eprintln!("Searching revision {}", idx.revision.as_deref().unwrap_or(rev));
+        idx
+    } else {
+        let loaded = match (&token_filter, &database, &shard_set) {
+            (Some(filter), Some(database), _) => sqlite::load_content_tokens(database, filter),
+            (Some(filter), None, Some(shard_set)) => shard_set.load_tokens(filter),
+            _ => load_content_index(&args.dir, &exts_for_load, &idx_base),
+        };
+        match loaded {
+            Ok(idx) => {
+                if idx.is_stale(&SystemClock) && args.auto_reindex {
+                    eprintln!("Content index is stale, rebuilding...");
+                    let new_idx = build_content_index(&auto_reindex_args(&args.dir, &idx));
+                    let _ = save_content_index(&new_idx, &idx_base);
+                    new_idx
+                } else {
+                    if idx.is_stale(&SystemClock) { eprintln!("Warning: content index is stale"); }
+                    partial = database.is_some() || shard_set.is_some();
+                    idx
+                }
+            }
+            Err(_) => {
+                match find_content_index_for_dir(&args.dir, &idx_base) {
+                    Some(idx) => idx,
+                    None => return Err(SearchError::IndexNotFound { dir: args.dir.clone() }),
+                }
             }
         }
     };
@@ -508,14 +1477,23 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
     if args.phrase {
         let phrase = &args.pattern;
         let phrase_lower = phrase.to_lowercase();
-        let phrase_tokens = tokenize(&phrase_lower, DEFAULT_MIN_TOKEN_LEN);
+        let phrase_tokens = index.tokenizer.tokenize(&phrase_lower);
         if phrase_tokens.is_empty() {
-            return Err(SearchError::EmptyPhrase { phrase: phrase.to_string() });
+            return Err(SearchError::EmptyPhrase { phrase: phrase.to_string(), min_token_len: index.tokenizer.min_token_len });
         }
 
-        let phrase_regex_pattern = phrase_tokens.iter()
+        // Case-sensitive: the phrase's own words, as typed, matched without (?i)
+        let pattern_words: Vec<String> = if args.case_sensitive {
+            phrase.split(|c: char| !c.is_alphanumeric() && c != '_')
+                .filter(|word| !index.tokenizer.tokenize(word).is_empty())
+                .map(str::to_string)
+                .collect()
+        } else {
+            phrase_tokens.clone()
+        };
+        let phrase_regex_pattern = pattern_words.iter()
             .map(|t| regex::escape(t)).collect::<Vec<_>>().join(r"\s+");
-        let phrase_re_pat = format!("(?i){}", phrase_regex_pattern);
+        let phrase_re_pat = format!("{}{}", if args.case_sensitive { "" } else { "(?i)" }, phrase_regex_pattern);
         let phrase_re = match Regex::new(&phrase_re_pat) {
             Ok(r) => r,
             Err(e) => return Err(SearchError::InvalidRegex { pattern: phrase_re_pat, source: e }),
@@ -563,7 +1541,7 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
                 Some(p) => p,
                 None => continue,
             };
-            if let Ok(content) = fs::read_to_string(file_path) && phrase_re.is_match(&content) {
+            if let Some(content) = read_indexed_file(&index, file_path) && phrase_re.is_match(&content) {
                 let mut matching_lines = Vec::new();
                 for (line_num, line) in content.lines().enumerate() {
                     if phrase_re.is_match(line) { matching_lines.push((line_num + 1) as u32); }
@@ -573,6 +1551,9 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
                 }
             }
         }
+        // Default phrase order: most matching lines first
+        results.sort_by(|a, b| b.lines.len().cmp(&a.lines.len()).then_with(|| a.file_path.cmp(&b.file_path)));
+        crate::mcp::handlers::utils::apply_grep_sort(&mut results, args.sort, |r| r.file_path.as_str(), |r| r.lines.len());
 
         let search_elapsed = search_start.elapsed();
         let total_elapsed = start.elapsed();
@@ -582,11 +1563,29 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
         let display_results = if args.max_results > 0 { &results[..results.len().min(args.max_results)] } else { &results };
         let ctx_before = if args.context > 0 { args.context } else { args.before };
         let ctx_after = if args.context > 0 { args.context } else { args.after };
+        let locator = MatchLocator::Regex(phrase_re.clone());
+
+        let summary = serde_json::json!({
+            "totalFiles": match_count, "totalOccurrences": line_count, "termsSearched": phrase_tokens,
+            "searchMode": "phrase", "indexFiles": index.files.len(),
+            "searchTimeMs": search_elapsed.as_secs_f64() * 1000.0,
+        });
 
-        if !args.count {
+        if args.group_by.is_some() && !matches!(args.format, OutputFormat::Quickfix | OutputFormat::Junit) {
+            print_grep_groups(&args, &index, results.iter().map(|r| (r.file_path.as_str(), r.lines.len())), summary.clone());
+        } else if args.format.is_json() {
+            let files = display_results.iter().map(|r| (r.file_path.as_str(), None, r.lines.as_slice(), None)).collect();
+            print_grep_json(&args, &index, files, &locator, summary.clone());
+        } else if matches!(args.format, OutputFormat::Quickfix | OutputFormat::Junit) {
+            let findings: Vec<report::Finding> = display_results.iter().flat_map(|r| {
+                let content = read_indexed_file(&index, &r.file_path);
+                report::findings_for_file(&r.file_path, &r.lines, content.as_deref(), |l| phrase_re.find(l).map(|m| m.start()))
+            }).collect();
+            print_grep_report(&args, &findings, match_count);
+        } else if !args.count {
             for result in display_results {
                 if args.show_lines {
-                    if let Ok(content) = fs::read_to_string(&result.file_path) {
+                    if let Some(content) = read_indexed_file(&index, &result.file_path) {
                         let lines_vec: Vec<&str> = content.lines().collect();
                         let total_lines = lines_vec.len();
                         let mut lines_to_show: std::collections::BTreeSet<usize> = std::collections::BTreeSet::new();
@@ -604,7 +1603,8 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
                         for &idx in &lines_to_show {
                             if let Some(p) = prev && idx > p + 1 { println!("--"); }
                             let marker = if match_lines_set.contains(&idx) { ">" } else { " " };
-                            println!("{}{}:{}: {}", marker, result.file_path, idx + 1, lines_vec[idx]);
+                            println!("{}{}:{}: {}", marker, result.file_path, idx + 1,
+                                shown_line(lines_vec[idx], args.highlight && marker == ">", &locator));
                             prev = Some(idx);
                         }
                         if !lines_to_show.is_empty() { println!(); }
@@ -620,48 +1620,29 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
             match_count, line_count, phrase, candidates.len(), index.files.len());
         eprintln!("Index load: {:.3}s | Search+Verify: {:.6}s | Total: {:.3}s",
             load_elapsed.as_secs_f64(), search_elapsed.as_secs_f64(), total_elapsed.as_secs_f64());
-        return Ok(());
+        return Ok(SearchOutcome { matched: match_count > 0, summary });
     }
 
     // ─── Normal token search ────────────────────────────────
-    let raw_terms: Vec<String> = args.pattern.split(',')
-        .map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect();
+    let mut raw_terms = pattern_terms.clone();
+    for reason in raw_terms.iter().filter_map(|t| index.tokenizer.unanswerable(t, use_substring)) {
+        eprintln!("Warning: {}; it cannot match through the index", reason);
+    }
+    if !args.regex && !use_substring {
+        for warning in skip_stop_terms(&index, &mut raw_terms, args.all) {
+            eprintln!("Warning: {}", warning);
+        }
+    }
 
     let terms: Vec<String> = if use_substring {
         // Expand terms using trigram index: find all tokens containing each term as a substring
         let trigram_idx = &index.trigram;
         let mut expanded = Vec::new();
         for term in &raw_terms {
-            let matched_tokens: Vec<String> = if term.len() < 3 {
-                // Linear scan for very short terms (no trigrams possible)
-                trigram_idx.tokens.iter()
-                    .filter(|tok| tok.contains(term.as_str()))
-                    .cloned()
-                    .collect()
-            } else {
-                let trigrams = search::generate_trigrams(term);
-                if trigrams.is_empty() {
-                    Vec::new()
-                } else {
-                    let mut candidates: Option<Vec<u32>> = None;
-                    for tri in &trigrams {
-                        if let Some(posting_list) = trigram_idx.trigram_map.get(tri) {
-                            candidates = Some(match candidates {
-                                None => posting_list.clone(),
-                                Some(prev) => crate::mcp::handlers::utils::sorted_intersect(&prev, posting_list),
-                            });
-                        } else {
-                            candidates = Some(Vec::new());
-                            break;
-                        }
-                    }
-                    candidates.unwrap_or_default().into_iter()
-                        .filter_map(|idx| trigram_idx.tokens.get(idx as usize))
-                        .filter(|tok| tok.contains(term.as_str()))
-                        .cloned()
-                        .collect()
-                }
-            };
+            let matched_tokens: Vec<String> = search::query::substring_token_ids(trigram_idx, term).into_iter()
+                .filter_map(|idx| trigram_idx.tokens.get(idx as usize))
+                .map(|tok| tok.to_string())
+                .collect();
             if matched_tokens.is_empty() {
                 eprintln!("Warning: substring '{}' matched 0 tokens", term);
             } else {
@@ -674,9 +1655,9 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
     } else if args.regex {
         let mut expanded = Vec::new();
         for pat in &raw_terms {
-            match Regex::new(&format!("(?i)^{}$", pat)) {
+            match search::query::token_regex(pat) {
                 Ok(re) => {
-                    let matching: Vec<String> = index.index.keys().filter(|k| re.is_match(k)).cloned().collect();
+                    let matching: Vec<String> = index.index.keys().filter(|k| re.is_match(k)).map(|k| k.to_string()).collect();
                     if matching.is_empty() { eprintln!("Warning: regex '{}' matched 0 tokens", pat); }
                     else { eprintln!("Regex '{}' matched {} tokens", pat, matching.len()); }
                     expanded.extend(matching);
@@ -689,18 +1670,31 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
         raw_terms.clone()
     };
 
-    let total_docs = index.files.len() as f64;
     let mode_str = if use_substring { if args.all { "SUBSTRING-AND" } else { "SUBSTRING-OR" } }
         else if args.regex { "REGEX" } else if args.all { "AND" } else { "OR" };
 
-    struct FileScore { file_path: String, lines: Vec<u32>, tf_idf: f64, occurrences: usize, terms_matched: usize }
-    let mut file_scores: HashMap<u32, FileScore> = HashMap::new();
+    struct FileScore { file_id: u32, file_path: String, lines: Vec<u32>, tf_idf: f64, occurrences: usize, terms_matched: usize, explain: Vec<TermScore>, boosts: Vec<Boost> }
+    let ranking = if args.no_boost {
+        Ranking::unboosted(args.explain)
+    } else {
+        Ranking::new(args.ranking, if args.regex { &[] } else { &raw_terms }, args.explain)
+    };
+    // Definitions describe the working tree: a --rev search is not boosted by them
+    let ranking = if ranking.wants_definitions() && args.rev.is_none()
+        && let Ok(defs) = definitions::load_definition_index(&args.dir, &index.extensions.join(","), &idx_base)
+    {
+        let files = search::query::defining_files(&defs, ranking.terms());
+        ranking.with_defining_files(files)
+    } else {
+        ranking
+    };
+    let mut scorer = ranking.scorer(&index);
     let term_count_for_all = if args.regex || use_substring { raw_terms.len() } else { terms.len() };
+    let mut case_filter = args.case_sensitive.then(|| CaseFilter::new(&index, &args.pattern));
+    let mut boundary_filter = (args.word_boundary && use_substring).then(|| BoundaryFilter::new(&index));
 
-    for term in &terms {
+    for (term_no, term) in terms.iter().enumerate() {
         if let Some(postings) = index.index.get(term.as_str()) {
-            let doc_freq = postings.len() as f64;
-            let idf = (total_docs / doc_freq).ln();
             for posting in postings {
                 let file_path = match index.files.get(posting.file_id as usize) {
                     Some(p) => p,
@@ -714,29 +1708,49 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
                 if args.exclude_dir.iter().any(|excl| file_path.to_lowercase().contains(&excl.to_lowercase())) { continue; }
                 if args.exclude.iter().any(|excl| file_path.to_lowercase().contains(&excl.to_lowercase())) { continue; }
 
-                let occurrences = posting.lines.len();
-                let file_total = if (posting.file_id as usize) < index.file_token_counts.len() {
-                    index.file_token_counts[posting.file_id as usize] as f64
-                } else { 1.0 };
-                let tf = occurrences as f64 / file_total;
-                let tf_idf = tf * idf;
-
-                let entry = file_scores.entry(posting.file_id).or_insert(FileScore {
-                    file_path: file_path.clone(), lines: Vec::new(), tf_idf: 0.0, occurrences: 0, terms_matched: 0,
-                });
-                entry.tf_idf += tf_idf;
-                entry.occurrences += occurrences;
-                entry.lines.extend_from_slice(&posting.lines);
-                entry.terms_matched += 1;
+                // A substring token is checked for the query terms it contains
+                let query_terms: Vec<&str> = if use_substring {
+                    raw_terms.iter().filter(|t| term.contains(t.as_str())).map(String::as_str).collect()
+                } else {
+                    vec![term.as_str()]
+                };
+                let mut lines = Cow::Borrowed(posting.lines.as_slice());
+                if let Some(filter) = case_filter.as_mut() {
+                    lines = Cow::Owned(filter.retain(posting.file_id, &lines, &query_terms, use_substring));
+                }
+                if let Some(filter) = boundary_filter.as_mut() {
+                    lines = Cow::Owned(filter.retain(posting.file_id, &lines, term, &query_terms));
+                }
+                if lines.is_empty() { continue; }
+                // Substring tokens are credited to the first query term they contain
+                let query_term = if use_substring { raw_terms.iter().find(|t| term.contains(t.as_str())) } else { None };
+                scorer.add(posting.file_id, term_no, query_term.unwrap_or(term), term, postings.len(), &lines);
             }
         }
     }
 
-    let mut results: Vec<FileScore> = file_scores.into_values()
-        .filter(|fs| !args.all || fs.terms_matched >= term_count_for_all).collect();
+    let mut results: Vec<FileScore> = scorer.finish().into_iter()
+        .filter(|fs| !args.all || fs.terms_matched >= term_count_for_all)
+        .map(|fs| FileScore {
+            file_id: fs.file_id, file_path: index.files[fs.file_id as usize].clone(), lines: fs.lines, tf_idf: fs.tf_idf,
+            occurrences: fs.occurrences, terms_matched: fs.terms_matched, explain: fs.explain,
+            boosts: ranking.boosts(&index.files[fs.file_id as usize], fs.definition.as_ref()),
+        })
+        .collect();
 
-    for result in &mut results { result.lines.sort(); result.lines.dedup(); }
-    results.sort_by(|a, b| b.tf_idf.partial_cmp(&a.tf_idf).unwrap_or(std::cmp::Ordering::Equal));
+    for result in &mut results {
+        result.tf_idf = boosted(result.tf_idf, &result.boosts);
+    }
+    results.sort_by(|a, b| crate::mcp::handlers::utils::cmp_ranked(
+        (a.tf_idf, &a.file_path, a.file_id), (b.tf_idf, &b.file_path, b.file_id)));
+    let hooked = rank_hook.map(|hook| hook.apply(RankTool::Grep, &mut results, |_, r| Candidate {
+        path: repo_path(&index.root, &r.file_path),
+        name: "",
+        kind: u32::MAX,
+        line: r.lines.first().copied().unwrap_or(0),
+        score: r.tf_idf as f32,
+    }));
+    crate::mcp::handlers::utils::apply_grep_sort(&mut results, args.sort, |r| r.file_path.as_str(), |r| r.occurrences);
 
     let match_count = results.len();
     let line_count: usize = results.iter().map(|r| r.lines.len()).sum();
@@ -744,11 +1758,56 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
 
     let ctx_before = if args.context > 0 { args.context } else { args.before };
     let ctx_after = if args.context > 0 { args.context } else { args.after };
+    // Substring columns cover the term inside the token, not the whole token
+    let locator = if use_substring {
+        MatchLocator::substrings(&raw_terms).unwrap_or_else(|| MatchLocator::Tokens(Default::default()))
+    } else {
+        MatchLocator::Tokens(terms.iter().cloned().collect())
+    };
 
-    if !args.count {
+    let mut summary = serde_json::json!({
+        "totalFiles": match_count, "totalOccurrences": line_count, "termsSearched": terms,
+        "searchMode": mode_str.to_lowercase(), "indexFiles": index.files.len(), "indexTokens": index.index.len(),
+        "searchTimeMs": search_start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if args.explain {
+        summary["scoring"] = serde_json::json!(EXPLAIN_FORMULA);
+    }
+    if let Some(outcome) = &hooked {
+        outcome.inject(&mut summary);
+        if let Some(error) = &outcome.error {
+            eprintln!("Warning: rank hook failed, results keep the built-in order: {}", error);
+        }
+    }
+
+    if args.group_by.is_some() && !matches!(args.format, OutputFormat::Quickfix | OutputFormat::Junit) {
+        print_grep_groups(&args, &index, results.iter().map(|r| (r.file_path.as_str(), r.occurrences)), summary.clone());
+    } else if args.format.is_json() {
+        let files = display_results.iter().map(|r| {
+            let explain = args.explain.then(|| explain_json(r.tf_idf, &r.explain, &r.boosts));
+            (r.file_path.as_str(), Some(r.tf_idf), r.lines.as_slice(), explain)
+        }).collect();
+        print_grep_json(&args, &index, files, &locator, summary.clone());
+    } else if matches!(args.format, OutputFormat::Quickfix | OutputFormat::Junit) {
+        // Columns come from the first matched token; ASCII lowercasing keeps byte offsets
+        let findings: Vec<report::Finding> = display_results.iter().flat_map(|r| {
+            let content = read_indexed_file(&index, &r.file_path);
+            report::findings_for_file(&r.file_path, &r.lines, content.as_deref(), |l| {
+                let lower = l.to_ascii_lowercase();
+                terms.iter().filter_map(|t| lower.find(t.as_str())).min()
+            })
+        }).collect();
+        print_grep_report(&args, &findings, match_count);
+    } else if !args.count {
         for result in display_results {
             if args.show_lines {
-                if let Ok(content) = fs::read_to_string(&result.file_path) {
+                if args.explain {
+                    println!("[{:.4}] {}", result.tf_idf, result.file_path);
+                    for line in explain_lines(&result.explain, &result.boosts, index.files.len()) {
+                        println!("{}", line);
+                    }
+                }
+                if let Some(content) = read_indexed_file(&index, &result.file_path) {
                     let lines_vec: Vec<&str> = content.lines().collect();
                     let total_lines = lines_vec.len();
                     let mut lines_to_show: std::collections::BTreeSet<usize> = std::collections::BTreeSet::new();
@@ -766,7 +1825,8 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
                     for &idx in &lines_to_show {
                         if let Some(prev) = prev_idx && idx > prev + 1 { println!("--"); }
                         let marker = if match_lines.contains(&idx) { ">" } else { " " };
-                        println!("{}{}:{}: {}", marker, result.file_path, idx + 1, lines_vec[idx]);
+                        println!("{}{}:{}: {}", marker, result.file_path, idx + 1,
+                            shown_line(lines_vec[idx], args.highlight && marker == ">", &locator));
                         prev_idx = Some(idx);
                     }
                     if !lines_to_show.is_empty() { println!(); }
@@ -776,6 +1836,11 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
                     result.tf_idf, result.file_path, result.occurrences,
                     result.terms_matched, terms.len(),
                     result.lines.iter().take(10).map(|n| n.to_string()).collect::<Vec<_>>().join(", "));
+                if args.explain {
+                    for line in explain_lines(&result.explain, &result.boosts, index.files.len()) {
+                        println!("{}", line);
+                    }
+                }
             }
         }
     }
@@ -784,7 +1849,65 @@ fn cmd_grep(args: GrepArgs) -> Result<(), SearchError> {
     let total_elapsed = start.elapsed();
     eprintln!("\n{} files, {} occurrences matching {} terms [{}]: '{}' (index: {} files, {} unique tokens)",
         match_count, line_count, terms.len(), mode_str, args.pattern, index.files.len(), index.index.len());
+    if match_count == 0 && !args.regex {
+        // A partly loaded index holds only the searched tokens: suggest from all of them
+        let whole = if partial { load_content_index(&args.dir, &exts_for_load, &idx_base).ok() } else { None };
+        for term in &raw_terms {
+            let similar = whole.as_ref().unwrap_or(&index).spelling_suggestions(term, GREP_SUGGESTIONS);
+            if !similar.is_empty() {
+                eprintln!("Did you mean {} (for '{}')?",
+                    similar.iter().map(|t| format!("`{}`", t)).collect::<Vec<_>>().join(", "), term);
+            }
+        }
+    }
     eprintln!("Index load: {:.3}s | Search+Rank: {:.6}s | Total: {:.3}s",
         load_elapsed.as_secs_f64(), search_elapsed.as_secs_f64(), total_elapsed.as_secs_f64());
-    Ok(())
-}
\ No newline at end of file
+    Ok(SearchOutcome { matched: match_count > 0, summary })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_line_record_columns_are_one_based_in_the_untrimmed_line() {
+        let line = "    let user = get_user();";
+        let record = find_line_record(3, line, &[(8, 12), (15, 19)], false, 0);
+        assert_eq!(record["matchColumns"], serde_json::json!([[9, 12], [16, 19]]));
+        assert_eq!(record["text"], line);
+        let record = find_line_record(3, line, &[(8, 12)], true, 2);
+        assert_eq!(record["fragments"], serde_json::json!(["t user ="]));
+        assert!(record.get("text").is_none());
+
+        assert!(FindArgs::try_parse_from(["find", "user", "--contents", "--context-chars", "5"]).is_err());
+        assert!(FindArgs::try_parse_from(["find", "user", "--contents", "-o", "--context-chars", "5"]).is_ok());
+    }
+
+    #[test]
+    fn test_auto_reindex_keeps_the_build_settings_of_the_stale_index() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("a.cs"), "class Ok { int id; }\n").unwrap();
+        let dir = tmp.path().to_string_lossy().to_string();
+        let built = build_content_index(&ContentIndexArgs {
+            dir: dir.clone(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 3,
+            dry_run: false,
+            build_priority: BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 4096,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 12,
+            stop_token_pct: DEFAULT_STOP_TOKEN_PCT,
+            checkpoint_secs: 0,
+            max_depth: 2,
+            prune_dir: Vec::new(),
+            storage: None,
+        });
+        let args = auto_reindex_args(&dir, &built);
+        assert_eq!((args.ext.as_str(), args.min_token_len, args.max_token_len), ("cs", 3, 12));
+        assert_eq!((args.max_file_size, args.max_depth), (4096, 2));
+        assert_eq!(build_content_index(&args).tokenizer, built.tokenizer);
+    }
+}
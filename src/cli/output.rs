@@ -0,0 +1,134 @@
+//! Global `--format`: text for people, `json` and `jsonl` for scripts.
+//!
+//! JSON output has the shape of the matching MCP tool response where one exists
+//! (`{"files": [...], "summary": {...}}` for find, fast and grep). JSONL prints one
+//! record per line, each element of the response's record list, and then whatever else
+//! the response holds (usually `{"summary": ...}`) as a final line, so a script can
+//! stream results line by line. Progress and diagnostics stay on stderr in every format.
+
+use serde_json::{Map, Value};
+
+use crate::SearchError;
+
+/// Output format of CLI commands. `quickfix` and `junit` are grep reports (see `report`),
+/// `html` is the metrics report and `sarif` the findings of defs, dupes and deadcode
+/// (see `sarif`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable listing (default)
+    #[default]
+    Text,
+    /// One JSON document, shaped like the MCP tool response
+    Json,
+    /// One JSON record per line, then the summary
+    Jsonl,
+    /// grep only: `file:line:col: severity: [rule] text`, one line per match (vim `:cfile`)
+    Quickfix,
+    /// grep only: JUnit XML, one failing test case per matching file
+    Junit,
+    /// metrics only: a standalone HTML page
+    Html,
+    /// defs, dupes and deadcode only: SARIF 2.1.0 for code scanning
+    Sarif,
+}
+
+impl OutputFormat {
+    /// `json` or `jsonl`.
+    pub fn is_json(self) -> bool {
+        matches!(self, Self::Json | Self::Jsonl)
+    }
+
+    /// The format for a command that also has its own `--json` flag: the flag means `json`.
+    pub fn or_json_flag(self, json: bool) -> Self {
+        if json && self == Self::Text { Self::Json } else { self }
+    }
+
+    /// Reject the report formats of other commands for `command`.
+    pub fn check_supported(self, command: &str) -> Result<(), SearchError> {
+        let only: &[&str] = match self {
+            Self::Quickfix | Self::Junit => &["grep"],
+            Self::Html => &["metrics"],
+            Self::Sarif => &["defs", "dupes", "deadcode"],
+            _ => return Ok(()),
+        };
+        if only.contains(&command) {
+            return Ok(());
+        }
+        Err(SearchError::InvalidArgs(format!(
+            "--format {} is only supported by {}, not {}", self.as_str(), only.join(" and "), command)))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+            Self::Quickfix => "quickfix",
+            Self::Junit => "junit",
+            Self::Html => "html",
+            Self::Sarif => "sarif",
+        }
+    }
+}
+
+/// Print a response in `json` or `jsonl` (see the module docs). `records` names the
+/// arrays whose elements become JSONL lines; with several, each element is wrapped
+/// as `{"<name>": element}` so a reader can tell them apart.
+pub fn print_json(format: OutputFormat, output: &Value, records: &[&str]) {
+    if format != OutputFormat::Jsonl {
+        println!("{}", serde_json::to_string_pretty(output).unwrap());
+        return;
+    }
+    for line in jsonl_lines(output, records) {
+        println!("{}", line);
+    }
+}
+
+/// The JSONL lines of `output`: record elements, then the remaining fields.
+fn jsonl_lines(output: &Value, records: &[&str]) -> Vec<String> {
+    let Some(object) = output.as_object() else {
+        return vec![output.to_string()];
+    };
+    let mut lines = Vec::new();
+    for &name in records {
+        for element in object.get(name).and_then(|v| v.as_array()).into_iter().flatten() {
+            lines.push(if records.len() > 1 {
+                serde_json::json!({ name: element }).to_string()
+            } else {
+                element.to_string()
+            });
+        }
+    }
+    let rest: Map<String, Value> = object.iter()
+        .filter(|(key, _)| !records.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if !rest.is_empty() {
+        lines.push(Value::Object(rest).to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_jsonl_lines_records_then_summary() {
+        let output = json!({ "files": [{ "path": "a" }, { "path": "b" }], "summary": { "totalFiles": 2 } });
+        assert_eq!(jsonl_lines(&output, &["files"]), vec![
+            r#"{"path":"a"}"#, r#"{"path":"b"}"#, r#"{"summary":{"totalFiles":2}}"#,
+        ]);
+        let tickets = json!({ "code": [{ "file": "a" }], "commits": [{ "hash": "h" }] });
+        assert_eq!(jsonl_lines(&tickets, &["code", "commits"]), vec![
+            r#"{"code":{"file":"a"}}"#, r#"{"commits":{"hash":"h"}}"#,
+        ]);
+        assert_eq!(jsonl_lines(&json!({ "status": "ok" }), &[]), vec![r#"{"status":"ok"}"#]);
+        assert!(OutputFormat::Junit.check_supported("find").is_err());
+        assert!(OutputFormat::Html.check_supported("grep").is_err() && OutputFormat::Html.check_supported("metrics").is_ok());
+        assert!(OutputFormat::Sarif.check_supported("dupes").is_ok() && OutputFormat::Sarif.check_supported("find").is_err());
+        assert_eq!(OutputFormat::Text.or_json_flag(true), OutputFormat::Json);
+        assert_eq!(OutputFormat::Jsonl.or_json_flag(true), OutputFormat::Jsonl);
+    }
+}
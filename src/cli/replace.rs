@@ -0,0 +1,279 @@
+//! `search replace`: apply a replacement planned by `search_replace_preview`, with a
+//! backup journal for `--undo`.
+//!
+//! Applying happens in two passes. First every file is checked against the content it
+//! was planned from and backed up into `<index dir>/replace-journal/<unix ms>/`, and the
+//! journal is written. Only then are the files replaced, each atomically (temp file +
+//! rename). A run that stops half-way leaves a journal naming every file it may have
+//! touched; `--undo` restores only files whose content is still what the replace wrote.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::args::ReplaceArgs;
+use super::output::{print_json, OutputFormat};
+use crate::mcp::handlers::{plan_replacements, replace_plan_json};
+use crate::{clean_path, find_content_index_for_dir, index_dir, load_content_index, SearchError};
+
+const JOURNAL_DIR: &str = "replace-journal";
+const JOURNAL_FILE: &str = "journal.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Journal {
+    /// Canonical `--dir` of the replace
+    dir: String,
+    pattern: String,
+    replacement: String,
+    files: Vec<JournalEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct JournalEntry {
+    path: String,
+    /// Backup file name inside the journal directory
+    backup: String,
+    before_hash: String,
+    after_hash: String,
+}
+
+pub(crate) fn cmd_replace(args: ReplaceArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let root = fs::canonicalize(&args.dir).map_err(|_| SearchError::DirNotFound(args.dir.clone()))?;
+    let root = clean_path(&root.to_string_lossy());
+    let journal_base = index_dir().join(JOURNAL_DIR);
+    if args.undo {
+        let (restored, skipped) = undo_latest(&root, &journal_base)?;
+        if format.is_json() {
+            print_json(format, &json!({ "restored": restored, "skipped": skipped }), &["restored"]);
+        } else {
+            for path in &restored {
+                println!("restored {}", path);
+            }
+            for reason in &skipped {
+                eprintln!("skipped {}", reason);
+            }
+            eprintln!("{} files restored, {} skipped", restored.len(), skipped.len());
+        }
+        return Ok(());
+    }
+
+    let (pattern, replacement) = (args.pattern.unwrap_or_default(), args.replacement.unwrap_or_default());
+    let exts = args.ext.clone().unwrap_or_default();
+    let index_base = index_dir();
+    let index = match load_content_index(&args.dir, &exts, &index_base) {
+        Ok(idx) => idx,
+        Err(_) => find_content_index_for_dir(&args.dir, &index_base)
+            .ok_or_else(|| SearchError::IndexNotFound { dir: args.dir.clone() })?,
+    };
+    let mut query = json!({
+        "pattern": pattern,
+        "replacement": replacement,
+        "regex": args.regex,
+        "ignoreCase": args.ignore_case,
+        "wordBoundary": args.word,
+        "contextLines": args.context,
+        "dir": args.path.clone().unwrap_or_else(|| root.clone()),
+    });
+    if let Some(ext) = &args.ext {
+        query["ext"] = json!(ext);
+    }
+    let plan = plan_replacements(&index, &query).map_err(SearchError::InvalidArgs)?;
+    let mut output = replace_plan_json(&plan, usize::MAX);
+
+    let applied = if args.yes && !plan.files.is_empty() {
+        let journal = Journal { dir: root, pattern, replacement, files: Vec::new() };
+        let edits: Vec<(&str, &str, &str)> = plan.files.iter()
+            .map(|f| (f.path.as_str(), f.original.as_str(), f.updated.as_str()))
+            .collect();
+        Some(apply_with_journal(journal, &edits, &journal_base)?)
+    } else {
+        None
+    };
+    if let Some((journal_dir, skipped)) = &applied {
+        output["summary"]["journal"] = json!(journal_dir.display().to_string());
+        output["summary"]["skippedChanged"] = json!(skipped);
+    }
+
+    if format.is_json() {
+        print_json(format, &output, &["files"]);
+        return Ok(());
+    }
+    for file in &plan.files {
+        print!("{}", file.diff);
+    }
+    let summary = &output["summary"];
+    eprintln!("\n{} replacements in {} files ({} candidate files checked)",
+        summary["totalReplacements"], summary["totalFiles"], summary["candidateFiles"]);
+    match &applied {
+        Some((journal_dir, skipped)) => {
+            for path in skipped {
+                eprintln!("skipped {}: changed since it was read", path);
+            }
+            eprintln!("Applied to {} files. Backups: {}", plan.files.len() - skipped.len(), journal_dir.display());
+            eprintln!("Undo with: search replace --undo -d {}", args.dir);
+        }
+        None if !plan.files.is_empty() => eprintln!("Preview only. Re-run with --yes to apply."),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Back up and journal every `(path, original, updated)` edit whose file still holds
+/// `original`, then write them. Returns the journal directory and the skipped paths.
+fn apply_with_journal(
+    mut journal: Journal,
+    edits: &[(&str, &str, &str)],
+    journal_base: &Path,
+) -> Result<(PathBuf, Vec<String>), SearchError> {
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    let journal_dir = journal_base.join(stamp.to_string());
+    fs::create_dir_all(&journal_dir)?;
+
+    let mut skipped = Vec::new();
+    let mut writes = Vec::new();
+    for &(path, original, updated) in edits {
+        if fs::read_to_string(path).ok().as_deref() != Some(original) {
+            skipped.push(path.to_string());
+            continue;
+        }
+        let backup = format!("{}.orig", journal.files.len());
+        fs::write(journal_dir.join(&backup), original)?;
+        journal.files.push(JournalEntry {
+            path: path.to_string(),
+            backup,
+            before_hash: content_hash(original),
+            after_hash: content_hash(updated),
+        });
+        writes.push((path, updated));
+    }
+    write_atomic(&journal_dir.join(JOURNAL_FILE), &serde_json::to_string_pretty(&journal).unwrap())?;
+
+    for (path, updated) in writes {
+        write_atomic(Path::new(path), updated)?;
+    }
+    Ok((journal_dir, skipped))
+}
+
+/// Restore the files of the newest journal for `root`, then delete the journal. Files
+/// edited since the replace are left alone. Returns the restored paths and the reasons
+/// files were skipped.
+fn undo_latest(root: &str, journal_base: &Path) -> Result<(Vec<String>, Vec<String>), SearchError> {
+    let mut journals: Vec<(u128, PathBuf, Journal)> = fs::read_dir(journal_base).into_iter().flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let stamp = path.file_name()?.to_str()?.parse().ok()?;
+            let journal = serde_json::from_str(&fs::read_to_string(path.join(JOURNAL_FILE)).ok()?).ok()?;
+            Some((stamp, path, journal))
+        })
+        .filter(|(_, _, journal): &(u128, PathBuf, Journal)| journal.dir == root)
+        .collect();
+    journals.sort_by_key(|(stamp, _, _)| *stamp);
+    let (_, journal_dir, journal) = journals.pop()
+        .ok_or_else(|| SearchError::InvalidArgs(format!("No replace to undo in {}", root)))?;
+
+    let mut restored = Vec::new();
+    let mut skipped = Vec::new();
+    for entry in &journal.files {
+        let current = fs::read_to_string(&entry.path).ok().map(|c| content_hash(&c));
+        if current.as_deref() == Some(entry.before_hash.as_str()) {
+            continue; // never written
+        }
+        if current.as_deref() != Some(entry.after_hash.as_str()) {
+            skipped.push(format!("{}: edited after the replace", entry.path));
+            continue;
+        }
+        let original = fs::read_to_string(journal_dir.join(&entry.backup))?;
+        write_atomic(Path::new(&entry.path), &original)?;
+        restored.push(entry.path.clone());
+    }
+    if skipped.is_empty() {
+        fs::remove_dir_all(&journal_dir)?;
+    }
+    Ok((restored, skipped))
+}
+
+/// Replace `path` with `content` through a temp file in the same directory, keeping
+/// the permissions of the file it replaces.
+fn write_atomic(path: &Path, content: &str) -> Result<(), SearchError> {
+    let tmp = PathBuf::from(format!("{}.search-replace.tmp", path.display()));
+    fs::write(&tmp, content)?;
+    if let Ok(meta) = fs::metadata(path) {
+        fs::set_permissions(&tmp, meta.permissions())?;
+    }
+    fs::rename(&tmp, path).inspect_err(|_| { let _ = fs::remove_file(&tmp); })?;
+    Ok(())
+}
+
+fn content_hash(content: &str) -> String {
+    format!("{:016x}", search::stable_hash(&[content.as_bytes()]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_content_index, ContentIndexArgs};
+
+    #[test]
+    fn test_replace_plan_apply_and_undo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("src");
+        fs::create_dir_all(&root).unwrap();
+        let a = root.join("a.cs");
+        let b = root.join("b.cs");
+        let a_text = "class OrderService {\n  // OrderServiceFactory\n  int x;\n  OrderService s;\n}\n";
+        fs::write(&a, a_text).unwrap();
+        fs::write(&b, "class Other {}\n").unwrap();
+        let index = build_content_index(&ContentIndexArgs {
+            dir: root.to_string_lossy().to_string(), ext: "cs".to_string(),
+            max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        });
+
+        let plan = plan_replacements(&index, &json!({
+            "pattern": "OrderService", "replacement": "OrderManager", "wordBoundary": true, "contextLines": 1,
+        })).unwrap();
+        assert_eq!(plan.candidate_files, 1, "b.cs has no OrderService token");
+        assert_eq!(plan.files.len(), 1);
+        let file = &plan.files[0];
+        assert_eq!(file.replacements, 2);
+        assert_eq!(file.updated, a_text.replace("class OrderService", "class OrderManager").replace("  OrderService s", "  OrderManager s"));
+        // Changes two lines apart share a hunk once their context touches
+        assert_eq!(file.diff, "--- a/a.cs\n+++ b/a.cs\n@@ -1,5 +1,5 @@\n\
+            -class OrderService {\n+class OrderManager {\n   // OrderServiceFactory\n   int x;\n\
+            -  OrderService s;\n+  OrderManager s;\n }\n");
+
+        let regex = plan_replacements(&index, &json!({
+            "pattern": r"Order(\w+)", "replacement": "${1}Order", "regex": true,
+        })).unwrap();
+        assert!(regex.files[0].updated.starts_with("class ServiceOrder {\n  // ServiceFactoryOrder\n"));
+
+        let root_str = clean_path(&root.canonicalize().unwrap().to_string_lossy());
+        let journal_base = tmp.path().join(JOURNAL_DIR);
+        let journal = Journal { dir: root_str.clone(), pattern: String::new(), replacement: String::new(), files: Vec::new() };
+        let (_, skipped) = apply_with_journal(journal,
+            &[(&file.path, &file.original, &file.updated), (&file.path, "stale", "x")], &journal_base).unwrap();
+        assert_eq!(skipped, vec![file.path.clone()]);
+        assert_eq!(fs::read_to_string(&a).unwrap(), file.updated);
+
+        let (restored, skipped) = undo_latest(&root_str, &journal_base).unwrap();
+        assert_eq!((restored.len(), skipped.len()), (1, 0));
+        assert_eq!(fs::read_to_string(&a).unwrap(), a_text);
+        assert!(undo_latest(&root_str, &journal_base).is_err(), "journal is consumed");
+    }
+}
@@ -0,0 +1,258 @@
+//! `search replay`: run the tool calls recorded by `serve --audit-log` again against
+//! the current build and indexes, and report every result that changed.
+//!
+//! Text results that are JSON are compared field by field. Timings (any field ending
+//! in `Ms`), index age and size, and `_meta` are ignored, so a replay of an unchanged
+//! build on an unchanged tree reports no differences. Calls that rebuild the index are
+//! skipped. Each call runs with the clock set to the time it was recorded, so index
+//! staleness comes out as it did then.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, RwLock};
+
+use serde_json::{json, Value};
+
+use crate::config::RepoConfig;
+use crate::definitions;
+use crate::git::cache::GitHistoryCache;
+use crate::mcp::audit_log::{read_audit_log, AuditRecord};
+use crate::mcp::handlers::{self, HandlerContext};
+use crate::owners::Owners;
+use crate::{clean_path, find_content_index_for_dir, index_dir, load_content_index, SearchError, Searcher};
+use search::clock::{Clock, Generation, ManualClock};
+
+use super::args::ReplayArgs;
+use super::output::{print_json, OutputFormat};
+
+/// Fields that differ between runs without the results changing.
+const VOLATILE_FIELDS: &[&str] = &["_meta", "ageHours", "sizeMb"];
+
+/// Tools that modify the index, so replaying them would change what later calls see,
+/// and `search_metrics` and `search_history`, whose output differs on every run.
+const SKIPPED_TOOLS: &[&str] = &["search_reindex", "search_reindex_status", "search_reindex_definitions", "search_metrics", "search_history"];
+
+/// Outcome of one replayed call.
+pub(crate) struct ReplayedCall {
+    /// 1-based position in the log
+    pub number: usize,
+    pub tool: String,
+    pub arguments: Value,
+    /// `None` when the call was skipped
+    pub differences: Option<Vec<String>>,
+}
+
+pub(crate) fn cmd_replay(args: ReplayArgs, format: OutputFormat) -> Result<(), SearchError> {
+    let records = read_audit_log(&args.log).map_err(SearchError::InvalidArgs)?;
+    let clock = Arc::new(ManualClock::default());
+    let ctx = replay_context(&args.dir, &args.ext, args.max_response_kb, index_dir(), clock.clone())?;
+    let calls = replay(&ctx, &clock, &records, &args.ignore_field);
+
+    let changed: Vec<&ReplayedCall> = calls.iter().filter(|c| c.differences.as_ref().is_some_and(|d| !d.is_empty())).collect();
+    let skipped = calls.iter().filter(|c| c.differences.is_none()).count();
+    let unchanged = calls.len() - changed.len() - skipped;
+
+    let format = format.or_json_flag(args.json);
+    if format.is_json() {
+        let changed_json: Vec<Value> = changed.iter().map(|c| json!({
+            "call": c.number,
+            "tool": c.tool,
+            "arguments": c.arguments,
+            "differences": c.differences,
+        })).collect();
+        print_json(format, &json!({
+            "calls": calls.len(),
+            "unchanged": unchanged,
+            "skipped": skipped,
+            "changed": changed_json,
+        }), &["changed"]);
+    } else {
+        for call in &changed {
+            let diffs = call.differences.as_deref().unwrap_or_default();
+            println!("#{} {} {}: {} difference{}", call.number, call.tool, call.arguments,
+                diffs.len(), if diffs.len() == 1 { "" } else { "s" });
+            for diff in diffs.iter().take(args.max_diffs) {
+                println!("    {}", diff);
+            }
+            if diffs.len() > args.max_diffs {
+                println!("    ... {} more", diffs.len() - args.max_diffs);
+            }
+        }
+    }
+    eprintln!("Replayed {} calls: {} unchanged, {} changed, {} skipped",
+        calls.len(), unchanged, changed.len(), skipped);
+    if !changed.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Handler context over the indexes on disk for `dir`, set up like `serve` but without
+/// building anything: the definition index and git history cache are used if present.
+/// Index staleness is judged by `clock`.
+pub(crate) fn replay_context(dir: &str, ext: &str, max_response_kb: usize, index_base: PathBuf, clock: Arc<dyn Clock>) -> Result<HandlerContext, SearchError> {
+    let index = load_content_index(dir, ext, &index_base)
+        .ok()
+        .or_else(|| find_content_index_for_dir(dir, &index_base))
+        .ok_or_else(|| SearchError::IndexNotFound { dir: dir.to_string() })?;
+    let def_index = definitions::find_definition_index_for_dir(dir, &index_base);
+    let git_cache = Some(GitHistoryCache::cache_path_for(dir, &index_base))
+        .filter(|p| p.exists())
+        .and_then(|p| GitHistoryCache::load_from_disk(&p).ok());
+    let root = std::fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
+    let owners = Owners::load_for(&root, None).ok().flatten().map(Arc::new);
+
+    Ok(HandlerContext {
+        index: Searcher::new(index),
+        def_index: def_index.map(|d| Arc::new(RwLock::new(d))),
+        server_dir: clean_path(&root.to_string_lossy()),
+        server_ext: ext.to_string(),
+        metrics: false,
+        index_base,
+        max_response_bytes: max_response_kb * 1024,
+        content_ready: Arc::new(AtomicBool::new(true)),
+        def_ready: Arc::new(AtomicBool::new(true)),
+        git_cache: Arc::new(RwLock::new(git_cache)),
+        git_cache_ready: Arc::new(AtomicBool::new(true)),
+        current_branch: super::serve::detect_current_branch(dir),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners,
+        generation: Arc::new(Generation::with_clock(clock)),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: RepoConfig::load_or_default(&root).ranking,
+        rank_hook: None,
+    })
+}
+
+/// Dispatch every record against `ctx`, with `clock` set to the record's time, and
+/// compare with the recorded result.
+pub(crate) fn replay(ctx: &HandlerContext, clock: &ManualClock, records: &[AuditRecord], ignore: &[String]) -> Vec<ReplayedCall> {
+    records.iter().enumerate().map(|(i, record)| {
+        let differences = (!SKIPPED_TOOLS.contains(&record.tool.as_str())).then(|| {
+            clock.set(record.ts);
+            let result = serde_json::to_value(handlers::dispatch_tool(ctx, &record.tool, &record.arguments)).unwrap();
+            diff_results(&record.result, &result, ignore)
+        });
+        ReplayedCall { number: i + 1, tool: record.tool.clone(), arguments: record.arguments.clone(), differences }
+    }).collect()
+}
+
+/// Differences between two tool results as `path: recorded -> replayed` lines.
+pub(crate) fn diff_results(recorded: &Value, replayed: &Value, ignore: &[String]) -> Vec<String> {
+    let mut diffs = Vec::new();
+    diff_values("", &parse_text_content(recorded), &parse_text_content(replayed), ignore, &mut diffs);
+    diffs
+}
+
+/// The result with each `content[].text` that holds JSON replaced by the parsed value.
+fn parse_text_content(result: &Value) -> Value {
+    let mut result = result.clone();
+    if let Some(content) = result.get_mut("content").and_then(|c| c.as_array_mut()) {
+        for item in content {
+            let parsed = item.get("text").and_then(|t| t.as_str()).and_then(|t| serde_json::from_str::<Value>(t).ok());
+            if let Some(parsed) = parsed {
+                item["text"] = parsed;
+            }
+        }
+    }
+    result
+}
+
+fn is_volatile(key: &str, ignore: &[String]) -> bool {
+    key.ends_with("Ms") || VOLATILE_FIELDS.contains(&key) || ignore.iter().any(|f| f == key)
+}
+
+fn diff_values(path: &str, recorded: &Value, replayed: &Value, ignore: &[String], out: &mut Vec<String>) {
+    match (recorded, replayed) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys.into_iter().filter(|k| !is_volatile(k, ignore)) {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_values(&child, x, y, ignore, out),
+                    (Some(x), None) => out.push(format!("{}: {} -> (missing)", child, brief(x))),
+                    (None, Some(y)) => out.push(format!("{}: (missing) -> {}", child, brief(y))),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                diff_values(&format!("{}[{}]", path, i), x, y, ignore, out);
+            }
+            if a.len() != b.len() {
+                out.push(format!("{}: {} items -> {} items", path, a.len(), b.len()));
+            }
+        }
+        (x, y) if x != y => out.push(format!("{}: {} -> {}", path, brief(x), brief(y))),
+        _ => {}
+    }
+}
+
+/// A value as JSON, cut to 80 characters.
+fn brief(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(80) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_reports_changed_results_and_ignores_timings() {
+        let tmp = tempfile::tempdir().unwrap();
+        let idx_base = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("a.cs"), "class Alpha { HttpClient client; }\n").unwrap();
+        std::fs::write(tmp.path().join("b.cs"), "class Beta { HttpClient other; }\n").unwrap();
+        let dir = tmp.path().to_string_lossy().to_string();
+        let index = crate::build_content_index(&crate::ContentIndexArgs {
+            dir: dir.clone(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        });
+        crate::save_content_index(&index, idx_base.path()).unwrap();
+        let clock = Arc::new(ManualClock::default());
+        let ctx = replay_context(&dir, "cs", 16, idx_base.path().to_path_buf(), clock.clone()).unwrap();
+
+        let arguments = json!({ "terms": "httpclient" });
+        let result = serde_json::to_value(handlers::dispatch_tool(&ctx, "search_grep", &arguments)).unwrap();
+        let mut same = result.clone();
+        let mut output: Value = serde_json::from_str(same["content"][0]["text"].as_str().unwrap()).unwrap();
+        output["summary"]["searchTimeMs"] = json!(12345.0);
+        same["content"][0]["text"] = json!(output.to_string());
+        same["_meta"] = json!({ "indexGeneration": 7 });
+        let mut changed = same.clone();
+        output["summary"]["totalFiles"] = json!(3);
+        changed["content"][0]["text"] = json!(output.to_string());
+
+        let record = |result: &Value, tool: &str| AuditRecord { ts: 1_700_000_000, tool: tool.to_string(), arguments: arguments.clone(), result: result.clone() };
+        let calls = replay(&ctx, &clock, &[record(&same, "search_grep"), record(&changed, "search_grep"), record(&json!({}), "search_reindex")], &[]);
+        assert_eq!(clock.now_secs(), 1_700_000_000, "calls run at their recorded time");
+        assert_eq!(calls[0].differences, Some(vec![]));
+        assert_eq!(calls[1].differences, Some(vec!["content[0].text.summary.totalFiles: 3 -> 2".to_string()]));
+        assert_eq!(calls[2].differences, None, "reindex is skipped");
+
+        // --ignore-field drops a field everywhere
+        let calls = replay(&ctx, &clock, &[record(&changed, "search_grep")], &["totalFiles".to_string()]);
+        assert_eq!(calls[0].differences, Some(vec![]));
+    }
+}
@@ -0,0 +1,229 @@
+//! `search grep --format`: vim quickfix lines and JUnit XML for editors and CI.
+//!
+//! Each matching line becomes one finding tagged with a rule name (the pattern by
+//! default) and a severity. Quickfix prints one `file:line:col: severity: [rule] text`
+//! line per finding. JUnit prints one failing test case per file, so CI shows every
+//! file that still contains the pattern. With no matches it prints a single passing case.
+//!
+//! find, fast and grep also exit like grep (see [`search_exit`]), and `--summary-json`
+//! leaves their summary in a file, so a pipeline can gate on a pattern without parsing
+//! the output.
+
+use std::fs;
+
+use crate::SearchError;
+
+/// Severity attached to each finding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Severity {
+    Error,
+    #[default]
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Info => "info",
+        }
+    }
+}
+
+/// One matching line.
+pub(crate) struct Finding {
+    pub path: String,
+    /// 1-based line number
+    pub line: u32,
+    /// 1-based byte column of the first match on the line (1 when unknown)
+    pub col: usize,
+    pub text: String,
+}
+
+/// Build findings for `lines` of `path`, reading the file once. `locate` returns the
+/// byte offset of the match within a line. A file that can't be read still yields
+/// its findings, with column 1 and empty text.
+pub(crate) fn findings_for_file(
+    path: &str,
+    lines: &[u32],
+    content: Option<&str>,
+    locate: impl Fn(&str) -> Option<usize>,
+) -> Vec<Finding> {
+    let file_lines: Vec<&str> = content.map(|c| c.lines().collect()).unwrap_or_default();
+    lines.iter().map(|&line| {
+        let text = file_lines.get((line as usize).saturating_sub(1)).copied().unwrap_or("");
+        Finding {
+            path: path.to_string(),
+            line,
+            col: locate(text).map(|offset| offset + 1).unwrap_or(1),
+            text: text.trim().to_string(),
+        }
+    }).collect()
+}
+
+/// Quickfix lines, one per finding.
+pub(crate) fn render_quickfix(findings: &[Finding], rule: &str, severity: Severity) -> String {
+    findings.iter()
+        .map(|f| format!("{}:{}:{}: {}: [{}] {}\n", f.path, f.line, f.col, severity.as_str(), rule, f.text))
+        .collect()
+}
+
+/// JUnit XML document with one `<testsuite>` named after the rule. Findings are
+/// grouped by file in first-seen order; `files_total` counts matching files, including
+/// any cut off by `--max-results`.
+pub(crate) fn render_junit(findings: &[Finding], rule: &str, severity: Severity, files_total: usize) -> String {
+    let mut files: Vec<(&str, Vec<&Finding>)> = Vec::new();
+    for f in findings {
+        match files.iter_mut().find(|(path, _)| *path == f.path) {
+            Some((_, group)) => group.push(f),
+            None => files.push((&f.path, vec![f])),
+        }
+    }
+    let rule_xml = xml_escape(rule);
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!("<testsuites name=\"search\" tests=\"{}\" failures=\"{}\">\n", files.len().max(1), files.len()));
+    out.push_str(&format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n", rule_xml, files.len().max(1), files.len()));
+    out.push_str(&format!("    <properties>\n      <property name=\"rule\" value=\"{}\"/>\n      <property name=\"severity\" value=\"{}\"/>\n      <property name=\"matchingFiles\" value=\"{}\"/>\n    </properties>\n",
+        rule_xml, severity.as_str(), files_total));
+    if files.is_empty() {
+        out.push_str(&format!("    <testcase classname=\"{}\" name=\"no matches\"/>\n", rule_xml));
+    }
+    for (path, group) in &files {
+        let path_xml = xml_escape(path);
+        out.push_str(&format!("    <testcase classname=\"{}\" name=\"{}\" file=\"{}\" line=\"{}\">\n",
+            rule_xml, path_xml, path_xml, group[0].line));
+        out.push_str(&format!("      <failure type=\"{}\" message=\"{} match{} of {}\">",
+            severity.as_str(), group.len(), if group.len() == 1 { "" } else { "es" }, rule_xml));
+        for f in group {
+            out.push_str(&xml_escape(&format!("\n{}:{}:{}: {}", f.path, f.line, f.col, f.text)));
+        }
+        out.push_str("\n      </failure>\n    </testcase>\n");
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+/// Escape text for XML attributes and element content. Control characters other
+/// than tab and newline are not allowed in XML 1.0 and are dropped.
+pub(crate) fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Exit code of find, fast and grep when something matched.
+pub(crate) const EXIT_MATCH: i32 = 0;
+/// Exit code of find, fast and grep when nothing matched.
+pub(crate) const EXIT_NO_MATCH: i32 = 1;
+/// Exit code of find, fast and grep on error, and of any command given a bad `--format`.
+pub(crate) const EXIT_ERROR: i32 = 2;
+
+/// What a find, fast or grep run found.
+pub(crate) struct SearchOutcome {
+    pub matched: bool,
+    /// Counts and timing, as in the `summary` of `--format json`
+    pub summary: serde_json::Value,
+}
+
+/// Exit code for the outcome of `command`. Reports an error on stderr, and with
+/// `--summary-json` writes the summary, plus the command and exit code, to that file.
+pub(crate) fn search_exit(command: &str, summary_json: Option<&str>, outcome: Result<SearchOutcome, SearchError>) -> i32 {
+    let (code, mut summary) = match outcome {
+        Ok(outcome) => (if outcome.matched { EXIT_MATCH } else { EXIT_NO_MATCH }, outcome.summary),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            (EXIT_ERROR, serde_json::json!({ "error": e.to_string() }))
+        }
+    };
+    let Some(path) = summary_json else { return code };
+    summary["command"] = serde_json::json!(command);
+    summary["exitCode"] = serde_json::json!(code);
+    match fs::write(path, serde_json::to_string_pretty(&summary).unwrap()) {
+        Ok(()) => code,
+        Err(e) => {
+            eprintln!("Error: failed to write summary to {}: {}", path, e);
+            EXIT_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(path: &str, line: u32, col: usize, text: &str) -> Finding {
+        Finding { path: path.to_string(), line, col, text: text.to_string() }
+    }
+
+    #[test]
+    fn test_findings_and_quickfix() {
+        let content = "fn a() {}\n    let t = Thread.Sleep(5);\n";
+        let found = findings_for_file("src/a.rs", &[2, 9], Some(content), |l| l.to_lowercase().find("sleep"));
+        assert_eq!(found[0].col, 20);
+        assert_eq!(found[0].text, "let t = Thread.Sleep(5);");
+        // Line past the end of the file keeps its position, without text
+        assert_eq!((found[1].col, found[1].text.as_str()), (1, ""));
+
+        let out = render_quickfix(&found[..1], "sleep", Severity::Error);
+        assert_eq!(out, "src/a.rs:2:20: error: [sleep] let t = Thread.Sleep(5);\n");
+    }
+
+    #[test]
+    fn test_junit_groups_by_file_and_escapes() {
+        let findings = vec![
+            finding("src/a.cs", 3, 5, "if (a < b && c) Sleep();"),
+            finding("src/b.cs", 1, 1, "Sleep();"),
+            finding("src/a.cs", 9, 1, "Sleep(\"x\");"),
+        ];
+        let xml = render_junit(&findings, "no-sleep", Severity::Warning, 2);
+        assert!(xml.contains("<testsuite name=\"no-sleep\" tests=\"2\" failures=\"2\">"));
+        assert!(xml.contains("<property name=\"severity\" value=\"warning\"/>"));
+        assert!(xml.contains("name=\"src/a.cs\" file=\"src/a.cs\" line=\"3\""));
+        assert!(xml.contains("message=\"2 matches of no-sleep\""));
+        assert!(xml.contains("src/a.cs:3:5: if (a &lt; b &amp;&amp; c) Sleep();"));
+        assert!(xml.contains("src/a.cs:9:1: Sleep(&quot;x&quot;);"));
+        assert_eq!(xml.matches("<testcase ").count(), 2);
+
+        let empty = render_junit(&[], "no-sleep", Severity::Error, 0);
+        assert!(empty.contains("tests=\"1\" failures=\"0\""));
+        assert!(empty.contains("<testcase classname=\"no-sleep\" name=\"no matches\"/>"));
+    }
+
+    #[test]
+    fn test_search_exit_codes_and_summary_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("summary.json");
+        let path = path.to_str().unwrap();
+        let read = || serde_json::from_str::<serde_json::Value>(&fs::read_to_string(path).unwrap()).unwrap();
+
+        let found = SearchOutcome { matched: true, summary: serde_json::json!({ "totalFiles": 3 }) };
+        assert_eq!(search_exit("grep", None, Ok(found)), EXIT_MATCH);
+
+        let none = SearchOutcome { matched: false, summary: serde_json::json!({ "totalMatches": 0 }) };
+        assert_eq!(search_exit("find", Some(path), Ok(none)), EXIT_NO_MATCH);
+        assert_eq!(read(), serde_json::json!({ "totalMatches": 0, "command": "find", "exitCode": 1 }));
+
+        let err = Err(SearchError::DirNotFound("/nope".to_string()));
+        assert_eq!(search_exit("fast", Some(path), err), EXIT_ERROR);
+        assert_eq!(read()["exitCode"], 2);
+        assert!(read()["error"].as_str().unwrap().contains("/nope"));
+
+        let unwritable = tmp.path().join("missing").join("summary.json");
+        let found = SearchOutcome { matched: true, summary: serde_json::json!({}) };
+        assert_eq!(search_exit("grep", unwritable.to_str(), Ok(found)), EXIT_ERROR,
+            "a summary CI asked for but did not get is an error");
+    }
+}
@@ -0,0 +1,192 @@
+//! `--format sarif`: SARIF 2.1.0 logs that GitHub code scanning and Azure DevOps
+//! ingest directly.
+//!
+//! `defs` reports every returned definition once per code-stats threshold of the query
+//! (`complexity:20` is rule `cyclomatic-complexity`); `dupes` reports each clone pair
+//! at its first copy, with the second copy as a related location; `deadcode` reports
+//! each unreferenced definition. Paths are relative to the index root, declared as
+//! `%SRCROOT%`.
+
+use serde_json::{json, Value};
+
+use crate::definitions::{root_uri, ClonePair, DefinitionIndex};
+use crate::{relative_path, SearchError};
+
+/// Query argument, `codeStats` key, rule id and rule description of each threshold.
+const THRESHOLDS: &[(&str, &str, &str, &str)] = &[
+    ("minComplexity", "cyclomaticComplexity", "cyclomatic-complexity", "Cyclomatic complexity at or above the threshold"),
+    ("minCognitive", "cognitiveComplexity", "cognitive-complexity", "Cognitive complexity at or above the threshold"),
+    ("minNesting", "maxNestingDepth", "nesting-depth", "Nesting depth at or above the threshold"),
+    ("minParams", "paramCount", "parameter-count", "Parameter count at or above the threshold"),
+    ("minReturns", "returnCount", "return-count", "Return and throw statements at or above the threshold"),
+    ("minCalls", "callCount", "call-count", "Calls (fan-out) at or above the threshold"),
+    ("minHalsteadVolume", "halsteadVolume", "halstead-volume", "Halstead volume at or above the threshold"),
+    ("minHalsteadDifficulty", "halsteadDifficulty", "halstead-difficulty", "Halstead difficulty at or above the threshold"),
+    ("maxMaintainabilityIndex", "maintainabilityIndex", "maintainability-index", "Maintainability index at or below the threshold"),
+    ("minWmc", "wmc", "class-wmc", "Weighted methods per class at or above the threshold"),
+    ("minLcom", "lcom", "class-lcom", "Lack of cohesion (LCOM4) at or above the threshold"),
+];
+
+const DUPLICATE_RULE: (&str, &str) = ("duplicate-code", "Near-duplicate method bodies");
+const DEAD_CODE_RULE: (&str, &str) = ("dead-code", "Private method or function that is never referenced");
+
+fn location(root: &str, file: &str, start_line: u32, end_line: u32) -> Value {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": relative_path(root, file), "uriBaseId": "%SRCROOT%" },
+            "region": { "startLine": start_line, "endLine": end_line.max(start_line) },
+        }
+    })
+}
+
+/// `(start, end)` of a `"12-40"` line range.
+fn line_range(lines: &str) -> (u32, u32) {
+    let mut parts = lines.split('-').map(|n| n.trim().parse::<u32>().unwrap_or(1));
+    let start = parts.next().unwrap_or(1);
+    (start, parts.next().unwrap_or(start))
+}
+
+/// A SARIF log with one run of `search`, declaring the rules that have results.
+fn sarif_log(root: &str, rules: &[(&str, &str)], results: Vec<Value>) -> Value {
+    let used: Vec<&(&str, &str)> = rules.iter().filter(|(id, _)| results.iter().any(|r| r["ruleId"] == *id)).collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": { "driver": {
+                "name": "search",
+                "version": env!("CARGO_PKG_VERSION"),
+                "rules": used.iter().map(|(id, description)| json!({
+                    "id": id,
+                    "shortDescription": { "text": description },
+                })).collect::<Vec<_>>(),
+            }},
+            "originalUriBaseIds": { "%SRCROOT%": { "uri": root_uri(root) } },
+            "results": results,
+        }]
+    })
+}
+
+/// SARIF log of a `search_definitions` response: one result per returned definition
+/// and threshold of `query`. Errors when the query has no code-stats threshold.
+pub(crate) fn definitions_sarif(root: &str, query: &Value, output: &Value) -> Result<Value, SearchError> {
+    let thresholds: Vec<_> = THRESHOLDS.iter().filter(|(arg, ..)| query.get(*arg).is_some()).collect();
+    if thresholds.is_empty() {
+        return Err(SearchError::InvalidArgs(
+            "--format sarif needs a code-stats threshold in the query (complexity:, cognitive:, nesting:, params:, returns:, calls:, volume:, difficulty:, mi:, wmc:, lcom:)".to_string()));
+    }
+    let defs = output["definitions"].as_array().map_or(&[][..], Vec::as_slice);
+    let mut results = Vec::new();
+    for def in defs {
+        let name = match def["parent"].as_str() {
+            Some(parent) => format!("{}.{}", parent, def["name"].as_str().unwrap_or("")),
+            None => def["name"].as_str().unwrap_or("").to_string(),
+        };
+        let (start, end) = line_range(def["lines"].as_str().unwrap_or(""));
+        for (arg, stat, rule, _) in &thresholds {
+            let Some(value) = def["codeStats"].get(*stat) else { continue };
+            results.push(json!({
+                "ruleId": rule,
+                "level": "warning",
+                "message": { "text": format!("{} `{}`: {} is {} (threshold {})",
+                    def["kind"].as_str().unwrap_or(""), name, stat, value, query[*arg]) },
+                "locations": [location(root, def["file"].as_str().unwrap_or(""), start, end)],
+            }));
+        }
+    }
+    let rules: Vec<(&str, &str)> = THRESHOLDS.iter().map(|(_, _, id, description)| (*id, *description)).collect();
+    Ok(sarif_log(root, &rules, results))
+}
+
+/// SARIF log of clone pairs: each at its first copy, the second as a related location.
+pub(crate) fn dupes_sarif(index: &DefinitionIndex, pairs: &[ClonePair]) -> Value {
+    let root = index.root.as_str();
+    let results = pairs.iter().map(|pair| {
+        let (a, b) = (&index.definitions[pair.a as usize], &index.definitions[pair.b as usize]);
+        let file = |file_id: u32| index.files.get(file_id as usize).map_or("", |s| s.as_str());
+        let mut related = location(root, file(b.file_id), b.line_start, b.line_end);
+        related["id"] = json!(1);
+        related["message"] = json!({ "text": b.name });
+        json!({
+            "ruleId": DUPLICATE_RULE.0,
+            "level": "warning",
+            "message": { "text": format!("`{}` is {:.0}% similar to [{}](1)", a.name, pair.similarity * 100.0, b.name) },
+            "locations": [location(root, file(a.file_id), a.line_start, a.line_end)],
+            "relatedLocations": [related],
+        })
+    }).collect();
+    sarif_log(root, &[DUPLICATE_RULE], results)
+}
+
+/// SARIF log of unreferenced definitions: one `dead-code` result each.
+pub(crate) fn dead_code_sarif(index: &DefinitionIndex, dead: &[u32]) -> Value {
+    let root = index.root.as_str();
+    let results = dead.iter().map(|&def_idx| {
+        let def = &index.definitions[def_idx as usize];
+        let file = index.files.get(def.file_id as usize).map_or("", |s| s.as_str());
+        json!({
+            "ruleId": DEAD_CODE_RULE.0,
+            "level": "note",
+            "message": { "text": format!("{} `{}` is never referenced", def.kind.as_str(), def.name) },
+            "locations": [location(root, file, def.line_start, def.line_end)],
+        })
+    }).collect();
+    sarif_log(root, &[DEAD_CODE_RULE], results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definitions_sarif_one_result_per_threshold() {
+        let query = json!({ "minComplexity": 10, "minParams": 4 });
+        let output = json!({ "definitions": [{
+            "name": "Process", "kind": "method", "parent": "OrderService",
+            "file": "/repo/src/OrderService.cs", "lines": "12-40",
+            "codeStats": { "cyclomaticComplexity": 14, "paramCount": 5 },
+        }]});
+        let log = definitions_sarif("/repo", &query, &output).unwrap();
+        let run = &log["runs"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(run["originalUriBaseIds"]["%SRCROOT%"]["uri"], "file:///repo/");
+        let rules: Vec<&str> = run["tool"]["driver"]["rules"].as_array().unwrap().iter().filter_map(|r| r["id"].as_str()).collect();
+        assert_eq!(rules, ["cyclomatic-complexity", "parameter-count"]);
+        let result = &run["results"][0];
+        assert_eq!(result["message"]["text"], "method `OrderService.Process`: cyclomaticComplexity is 14 (threshold 10)");
+        assert_eq!(result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "src/OrderService.cs");
+        assert_eq!(result["locations"][0]["physicalLocation"]["region"], json!({ "startLine": 12, "endLine": 40 }));
+        assert_eq!(run["results"].as_array().unwrap().len(), 2);
+
+        assert!(definitions_sarif("/repo", &json!({ "name": "Process" }), &output).is_err());
+        assert_eq!(root_uri("C:/Projects/App"), "file:///C:/Projects/App/");
+    }
+
+    #[test]
+    fn test_dead_code_sarif_paths_match_whole_segments() {
+        let index = DefinitionIndex {
+            root: "/repo".to_string(),
+            files: vec!["/repo/src/Orders.cs".to_string(), "/repository/Other.cs".to_string()],
+            definitions: (0..2).map(|file_id| crate::definitions::DefinitionEntry {
+                file_id,
+                name: "Unused".to_string(),
+                kind: crate::definitions::DefinitionKind::Method,
+                line_start: 5,
+                line_end: 7,
+                parent: None,
+                signature: None,
+                modifiers: vec!["private".to_string()],
+                attributes: Vec::new(),
+                base_types: Vec::new(),
+            }).collect(),
+            ..Default::default()
+        };
+        let log = dead_code_sarif(&index, &[0, 1]);
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "dead-code");
+        assert_eq!(run["results"][0]["message"]["text"], "method `Unused` is never referenced");
+        let uri = |i: usize| run["results"][i]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].clone();
+        assert_eq!(uri(0), "src/Orders.cs");
+        assert_eq!(uri(1), "/repository/Other.cs");
+    }
+}
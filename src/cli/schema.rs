@@ -0,0 +1,76 @@
+//! `search dump-schema`: the contracts integrators build against, generated from the
+//! code. Tool schemas come from the same definitions `serve` answers `tools/list` with;
+//! index layouts are traced from the index types (see `search::schema`).
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::definitions::{DefinitionIndex, DEFINITION_INDEX_VERSION};
+use crate::git::cache::{self, GitHistoryCache};
+use crate::git::commit_index::{self, CommitIndex};
+use crate::index::INDEX_FORMAT_VERSION;
+use crate::mcp::handlers::tool_definitions;
+use crate::semantic::{self, SemanticIndex};
+use crate::{ContentIndex, FileIndex, SearchError};
+use search::schema;
+
+use super::args::DumpSchemaArgs;
+
+pub(crate) fn cmd_dump_schema(args: DumpSchemaArgs) -> Result<(), SearchError> {
+    let output = schema_json()?;
+    let text = serde_json::to_string_pretty(&output).unwrap();
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, text + "\n")?;
+            eprintln!("Wrote {} tool schemas and {} index layouts to {}",
+                output["tools"].as_array().map_or(0, Vec::len), output["indexes"].as_array().map_or(0, Vec::len), path);
+        }
+        None => println!("{}", text),
+    }
+    Ok(())
+}
+
+/// `{version, tools: [{name, description, inputSchema}], indexes: [{file, formatVersion, root, types}]}`.
+pub(crate) fn schema_json() -> Result<Value, SearchError> {
+    let indexes = vec![
+        index_layout::<FileIndex>("file-list", INDEX_FORMAT_VERSION)?,
+        index_layout::<ContentIndex>("word-search", INDEX_FORMAT_VERSION)?,
+        index_layout::<DefinitionIndex>("code-structure", DEFINITION_INDEX_VERSION)?,
+        index_layout::<GitHistoryCache>("git-history", cache::FORMAT_VERSION)?,
+        index_layout::<CommitIndex>("git-grep", commit_index::FORMAT_VERSION)?,
+        index_layout::<SemanticIndex>("semantic", semantic::FORMAT_VERSION)?,
+    ];
+    Ok(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "tools": tool_definitions(),
+        "indexes": indexes,
+    }))
+}
+
+fn index_layout<T: DeserializeOwned>(file: &str, format_version: u32) -> Result<Value, SearchError> {
+    let mut layout = schema::layout::<T>()
+        .map_err(|e| SearchError::InvalidArgs(format!("Cannot trace the layout of .{} files: {}", file, e)))?;
+    layout["file"] = json!(file);
+    layout["formatVersion"] = json!(format_version);
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_covers_tools_and_index_layouts() {
+        let output = schema_json().unwrap();
+        assert_eq!(output["tools"].as_array().unwrap().len(), tool_definitions().len());
+        assert!(output["tools"][0]["inputSchema"]["properties"].is_object());
+
+        let content = output["indexes"].as_array().unwrap().iter().find(|i| i["file"] == "word-search").unwrap();
+        assert_eq!(content["root"], "ContentIndex");
+        assert_eq!(content["types"]["ContentIndex"]["fields"][0], json!({ "name": "root", "type": "String" }));
+        assert!(content["types"]["Posting"]["fields"].is_array(), "reached through the token map");
+        let defs = output["indexes"].as_array().unwrap().iter().find(|i| i["file"] == "code-structure").unwrap();
+        assert_eq!(defs["formatVersion"], DEFINITION_INDEX_VERSION);
+        assert!(defs["types"]["DefinitionKind"]["variants"].is_array());
+    }
+}
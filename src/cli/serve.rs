@@ -1,19 +1,23 @@
 //! MCP server startup and configuration.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
 
 use tracing::{info, warn};
 
 use crate::{
     build_content_index, save_content_index, load_content_index, find_content_index_for_dir,
-    index_dir, ContentIndex, TrigramIndex, DEFAULT_MIN_TOKEN_LEN,
+    index_dir, ContentIndex, Searcher, SkippedFiles, TokenizerSettings, TrigramIndex, DEFAULT_MIN_TOKEN_LEN, DEFAULT_STOP_TOKEN_PCT,
 };
+use crate::config::RepoConfig;
 use crate::definitions;
 use crate::git::cache::GitHistoryCache;
-use crate::mcp;
+use crate::mcp::{self, handlers::HandlerContext};
+use crate::owners::Owners;
+use crate::rank_hook::RankHook;
+use search::clock::Generation;
 
 use super::args::{ServeArgs, ContentIndexArgs};
 
@@ -37,9 +41,29 @@ pub fn cmd_serve(args: ServeArgs) {
         .init();
 
     info!(dir = %dir_str, ext = %exts_for_load, "Starting MCP server");
+    search::metrics::start();
+    if args.lock_metrics {
+        search::lock_stats::enable();
+    }
 
     let idx_base = index_dir();
 
+    // Held until the server exits; a second serve of this index still works, and the
+    // index write lock keeps their saves apart
+    let index_path = crate::content_index_path_for(&dir_str, &exts_for_load, &idx_base);
+    let _daemon_lock = match crate::lock::DaemonLock::try_acquire(&index_path) {
+        Ok(Some(lock)) => Some(lock),
+        Ok(None) => {
+            warn!(holder = %crate::lock::serving_process(&index_path).unwrap_or_default(),
+                "Another search serve is already serving this index; both will save it, one at a time");
+            None
+        }
+        Err(e) => {
+            warn!(error = %e, "Cannot take the serve lock of the index");
+            None
+        }
+    };
+
     // Enable memory diagnostics if --memory-log was passed
     if args.memory_log {
         crate::index::enable_memory_log(&idx_base);
@@ -51,6 +75,8 @@ pub fn cmd_serve(args: ServeArgs) {
 
     let content_ready = Arc::new(AtomicBool::new(false));
     let def_ready = Arc::new(AtomicBool::new(false));
+    // Bumped whenever the in-memory indexes change (background build, watcher batch, reindex)
+    let generation = Arc::new(Generation::default());
 
     // Create an empty ContentIndex so the event loop can start immediately
     let empty_index = ContentIndex {
@@ -66,8 +92,17 @@ pub fn cmd_serve(args: ServeArgs) {
         trigram_dirty: false,
         forward: None,  // forward index eliminated — saves ~1.5 GB RAM
         path_to_id: if args.watch { Some(HashMap::new()) } else { None },
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
-    let index = Arc::new(RwLock::new(empty_index));
+    let index = Searcher::new(empty_index);
 
     // Try fast load from disk (typically < 3s)
     let start = Instant::now();
@@ -89,29 +124,31 @@ pub fn cmd_serve(args: ServeArgs) {
         } else {
             idx
         };
-        *index.write().unwrap_or_else(|e| e.into_inner()) = idx;
+        index.replace(idx);
         content_ready.store(true, Ordering::Release);
         crate::index::log_memory("serve: content ready");
 
         // Pre-warm trigram index in background to eliminate cold-start penalty
-        let warmup_index = Arc::clone(&index);
+        let warmup_index = index.clone();
         std::thread::spawn(move || {
             eprintln!("[warmup] Starting trigram pre-warm...");
             let start = Instant::now();
-            let (trigrams, tokens) = warmup_index.read()
-                .unwrap_or_else(|e| e.into_inner())
-                .warm_up();
+            let (trigrams, tokens) = warmup_index.snapshot().warm_up();
             eprintln!("[warmup] Trigram pre-warm completed in {:.1}ms ({} trigrams, {} tokens)",
                 start.elapsed().as_secs_f64() * 1000.0, trigrams, tokens);
         });
     } else {
         // Build in background — don't block the event loop
-        let bg_index: Arc<RwLock<ContentIndex>> = Arc::clone(&index);
+        let bg_index = index.clone();
         let bg_ready = Arc::clone(&content_ready);
+        let bg_generation = Arc::clone(&generation);
         let bg_dir = dir_str.clone();
         let bg_ext = exts_for_load.clone();
         let bg_idx_base = idx_base.clone();
         let bg_watch = args.watch;
+        let bg_priority = args.build_priority;
+        let bg_io_limit = args.io_limit_mb;
+        let bg_max_file_size = args.max_file_size;
 
         std::thread::spawn(move || {
             info!("Building content index in background...");
@@ -125,6 +162,20 @@ pub fn cmd_serve(args: ServeArgs) {
                 no_ignore: false,
                 threads: 0,
                 min_token_len: DEFAULT_MIN_TOKEN_LEN,
+                dry_run: false,
+                build_priority: bg_priority,
+                io_limit_mb: bg_io_limit,
+                watch: false,
+                save_interval_secs: 60,
+                max_file_size: bg_max_file_size,
+                spill_mb: 0,
+                shards: 0,
+                max_token_len: 0,
+                stop_token_pct: DEFAULT_STOP_TOKEN_PCT,
+                checkpoint_secs: 0,
+                max_depth: 0,
+                prune_dir: Vec::new(),
+                storage: None,
             });
             crate::index::log_memory("content-build: finished");
             if let Err(e) = save_content_index(&new_idx, &bg_idx_base) {
@@ -147,6 +198,20 @@ pub fn cmd_serve(args: ServeArgs) {
                         dir: bg_dir, ext: bg_ext,
                         max_age_hours: 24, hidden: false, no_ignore: false,
                         threads: 0, min_token_len: DEFAULT_MIN_TOKEN_LEN,
+                        dry_run: false,
+                        build_priority: bg_priority,
+                        io_limit_mb: bg_io_limit,
+                        watch: false,
+                        save_interval_secs: 60,
+                        max_file_size: bg_max_file_size,
+                        spill_mb: 0,
+                        shards: 0,
+                        max_token_len: 0,
+                        stop_token_pct: DEFAULT_STOP_TOKEN_PCT,
+                        checkpoint_secs: 0,
+                        max_depth: 0,
+                        prune_dir: Vec::new(),
+                        storage: None,
                     })
                 });
 
@@ -163,16 +228,15 @@ pub fn cmd_serve(args: ServeArgs) {
                 tokens = token_count,
                 "Content index ready (background build complete)"
             );
-            *bg_index.write().unwrap_or_else(|e| e.into_inner()) = new_idx;
+            bg_index.replace(new_idx);
             bg_ready.store(true, Ordering::Release);
+            bg_generation.bump();
             crate::index::log_memory("serve: content ready");
 
             // Pre-warm trigram index after background build
             eprintln!("[warmup] Starting trigram pre-warm...");
             let warmup_start = Instant::now();
-            let (trigrams, tokens) = bg_index.read()
-                .unwrap_or_else(|e| e.into_inner())
-                .warm_up();
+            let (trigrams, tokens) = bg_index.snapshot().warm_up();
             eprintln!("[warmup] Trigram pre-warm completed in {:.1}ms ({} trigrams, {} tokens)",
                 warmup_start.elapsed().as_secs_f64() * 1000.0, trigrams, tokens);
         });
@@ -210,6 +274,13 @@ pub fn cmd_serve(args: ServeArgs) {
             extension_methods: HashMap::new(),
             selector_index: HashMap::new(),
             template_children: HashMap::new(),
+            walk_limits: Default::default(),
+            file_imports: HashMap::new(),
+            di_registrations: Vec::new(),
+            endpoints: Vec::new(),
+            partial_types: HashMap::new(),
+            doc_comments: HashMap::new(),
+            imports: Vec::new(),
         };
         let def_arc = Arc::new(RwLock::new(empty_def));
 
@@ -234,9 +305,12 @@ pub fn cmd_serve(args: ServeArgs) {
             // Build in background
             let bg_def = Arc::clone(&def_arc);
             let bg_def_ready = Arc::clone(&def_ready);
+            let bg_generation = Arc::clone(&generation);
             let bg_dir = dir_str.clone();
             let bg_def_exts = def_exts.clone();
             let bg_idx_base = idx_base.clone();
+            let bg_priority = args.build_priority;
+            let bg_io_limit = args.io_limit_mb;
 
             std::thread::spawn(move || {
                 info!("Building definition index in background...");
@@ -246,6 +320,13 @@ pub fn cmd_serve(args: ServeArgs) {
                     dir: bg_dir.clone(),
                     ext: bg_def_exts.clone(),
                     threads: 0,
+                    dry_run: false,
+                    build_priority: bg_priority,
+                    io_limit_mb: bg_io_limit,
+                    max_depth: 0,
+                    prune_dir: Vec::new(),
+                    storage: None,
+                    import: Vec::new(),
                 });
                 crate::index::log_memory("def-build: finished");
                 if let Err(e) = definitions::save_definition_index(&new_idx, &bg_idx_base) {
@@ -264,6 +345,13 @@ pub fn cmd_serve(args: ServeArgs) {
                         warn!(error = %e, "Failed to reload definition index from disk, rebuilding");
                         definitions::build_definition_index(&definitions::DefIndexArgs {
                             dir: bg_dir, ext: bg_def_exts, threads: 0,
+                            dry_run: false,
+                            build_priority: bg_priority,
+                            io_limit_mb: bg_io_limit,
+                            max_depth: 0,
+                            prune_dir: Vec::new(),
+                            storage: None,
+                            import: Vec::new(),
                         })
                     });
 
@@ -277,6 +365,7 @@ pub fn cmd_serve(args: ServeArgs) {
                 );
                 *bg_def.write().unwrap_or_else(|e| e.into_inner()) = new_idx;
                 bg_def_ready.store(true, Ordering::Release);
+                bg_generation.bump();
                 crate::index::log_memory("serve: def ready");
             });
         }
@@ -294,13 +383,20 @@ pub fn cmd_serve(args: ServeArgs) {
         let watch_dir = std::fs::canonicalize(&dir_str)
             .unwrap_or_else(|_| PathBuf::from(&dir_str));
         if let Err(e) = mcp::watcher::start_watcher(
-            Arc::clone(&index),
+            index.clone(),
             def_index.as_ref().map(Arc::clone),
             watch_dir,
             extensions,
-            args.debounce_ms,
-            args.bulk_threshold,
             idx_base.clone(),
+            mcp::watcher::WatcherOptions {
+                debounce_ms: args.debounce_ms,
+                bulk_threshold: args.bulk_threshold,
+                rebuild_priority: args.watch_build_priority,
+                io_limit_mb: args.io_limit_mb,
+                mode: args.watch_mode,
+                poll_interval_ms: args.poll_interval_ms,
+            },
+            Arc::clone(&generation),
         ) {
             warn!(error = %e, "Failed to start file watcher");
         }
@@ -448,25 +544,83 @@ pub fn cmd_serve(args: ServeArgs) {
     }
 
     // ─── Detect current branch ───
-    let current_branch = std::process::Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "HEAD"])
-        .current_dir(&dir_str)
-        .output()
-        .ok()
-        .and_then(|o| if o.status.success() {
-            String::from_utf8(o.stdout).ok().map(|s| s.trim().to_string())
-        } else { None });
+    let current_branch = detect_current_branch(&dir_str);
 
     if let Some(ref branch) = current_branch {
         info!(branch = %branch, "Detected current branch");
     }
 
+    // ─── Load ownership rules (explicit --owners, else discovered CODEOWNERS) ───
+    let owners_root = std::fs::canonicalize(&dir_str).unwrap_or_else(|_| PathBuf::from(&dir_str));
+    let owners = match Owners::load_for(&owners_root, args.owners.as_deref().map(Path::new)) {
+        Ok(Some(owners)) => {
+            info!(source = %owners.source, rules = owners.rule_count(), "Loaded ownership rules");
+            Some(Arc::new(owners))
+        }
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let rank_hook = match args.rank_hook.as_deref().map(RankHook::load) {
+        Some(Ok(hook)) => {
+            info!(path = %args.rank_hook.as_ref().unwrap().display(), "Loaded rank hook");
+            Some(Arc::new(hook))
+        }
+        Some(Err(e)) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    let audit_log = match args.audit_log.as_deref().map(mcp::audit_log::AuditLog::open) {
+        Some(Ok(log)) => Some(log),
+        Some(Err(e)) => {
+            eprintln!("Error: cannot open audit log: {}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
     let max_response_bytes = if args.max_response_kb == 0 { 0 } else { args.max_response_kb * 1024 };
-    mcp::server::run_server(
-        index, def_index, dir_str, exts_for_load,
-        args.metrics, idx_base, max_response_bytes,
-        content_ready, def_ready,
-        git_cache, git_cache_ready,
+    let ranking = RepoConfig::load_or_default(Path::new(&dir_str)).ranking;
+    let ctx = HandlerContext {
+        index,
+        def_index,
+        server_dir: dir_str,
+        server_ext: exts_for_load,
+        metrics: args.metrics,
+        index_base: idx_base,
+        max_response_bytes,
+        content_ready,
+        def_ready,
+        git_cache,
+        git_cache_ready,
         current_branch,
-    );
-}
\ No newline at end of file
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners,
+        generation,
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking,
+        rank_hook,
+    };
+    mcp::server::run_server(ctx, args.ascii_json, audit_log);
+}
+
+/// Branch checked out in `dir`, for the `branchWarning` of index-based tools.
+pub(crate) fn detect_current_branch(dir: &str) -> Option<String> {
+    std::process::Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .and_then(|o| if o.status.success() {
+            String::from_utf8(o.stdout).ok().map(|s| s.trim().to_string())
+        } else { None })
+}
@@ -0,0 +1,793 @@
+//! `search tui`: interactive search over the content index.
+//!
+//! Every edit of the query re-runs `search_grep` against the in-memory index (the
+//! handler `serve` uses, so results and ranking match the MCP tool). The left pane lists
+//! the ranked files; the right pane previews the selected file around its current match,
+//! with the query highlighted. Enter opens the file at that line in `$VISUAL`/`$EDITOR`.
+//!
+//! There is no terminal library: raw mode is set through the C runtime on Unix and the
+//! console API on Windows, and the screen is drawn with ANSI escapes.
+
+use std::io::IsTerminal;
+use std::sync::Arc;
+use std::time::Duration;
+
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::mcp::handlers::{self, HandlerContext};
+use crate::{index_dir, path_from_string, read_indexed_file, SearchError, SystemClock};
+
+use super::args::TuiArgs;
+
+/// How long to wait for input before checking the terminal size again.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Width of the line-number gutter in the preview.
+const GUTTER: usize = 6;
+
+/// How the query is passed to `search_grep`; Tab cycles through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueryMode {
+    Substring,
+    Exact,
+    Phrase,
+    Regex,
+}
+
+impl QueryMode {
+    fn next(self) -> Self {
+        match self {
+            Self::Substring => Self::Exact,
+            Self::Exact => Self::Phrase,
+            Self::Phrase => Self::Regex,
+            Self::Regex => Self::Substring,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Substring => "substring",
+            Self::Exact => "exact",
+            Self::Phrase => "phrase",
+            Self::Regex => "regex",
+        }
+    }
+
+    /// Regex for the query's matches on a line, for the preview. `None` for an empty
+    /// query or an invalid regex.
+    fn highlighter(self, query: &str) -> Option<Regex> {
+        let terms: Vec<&str> = query.split(',').map(str::trim).filter(|t| !t.is_empty()).collect();
+        let pattern = match self {
+            Self::Substring => terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|"),
+            Self::Exact => format!(r"\b(?:{})\b", terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|")),
+            Self::Phrase => query.split_whitespace().map(regex::escape).collect::<Vec<_>>().join(r"\s+"),
+            Self::Regex => format!(r"\b(?:{})\b", terms.join("|")),
+        };
+        if terms.is_empty() || pattern.is_empty() {
+            return None;
+        }
+        Regex::new(&format!("(?i){}", pattern)).ok()
+    }
+}
+
+/// A key press, decoded from terminal input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Backspace,
+    /// Ctrl-W
+    DeleteWord,
+    /// Ctrl-U
+    Clear,
+    Enter,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    /// Esc or Ctrl-C
+    Quit,
+}
+
+/// The key for a control character, `None` for ones without a binding.
+fn control_key(byte: u8) -> Option<Key> {
+    match byte {
+        0x03 | 0x1b => Some(Key::Quit),
+        0x08 | 0x7f => Some(Key::Backspace),
+        b'\t' => Some(Key::Tab),
+        b'\r' | b'\n' => Some(Key::Enter),
+        0x0e => Some(Key::Down),
+        0x10 => Some(Key::Up),
+        0x15 => Some(Key::Clear),
+        0x17 => Some(Key::DeleteWord),
+        _ => None,
+    }
+}
+
+/// Decode one read from a terminal in raw mode. A lone ESC is Quit; ESC followed by
+/// `[` or `O` starts an escape sequence, and unknown sequences are dropped whole.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == 0x1b && matches!(bytes.get(i + 1), Some(b'[' | b'O')) {
+            let start = i + 2;
+            let end = bytes[start..].iter().position(|b| (0x40..=0x7e).contains(b)).map(|p| start + p);
+            let Some(end) = end else { break };
+            let key = match (&bytes[start..end], bytes[end]) {
+                (_, b'A') => Some(Key::Up),
+                (_, b'B') => Some(Key::Down),
+                (_, b'C') => Some(Key::Right),
+                (_, b'D') => Some(Key::Left),
+                (_, b'H') | (b"1" | b"7", b'~') => Some(Key::Home),
+                (_, b'F') | (b"4" | b"8", b'~') => Some(Key::End),
+                (b"5", b'~') => Some(Key::PageUp),
+                (b"6", b'~') => Some(Key::PageDown),
+                _ => None,
+            };
+            keys.extend(key);
+            i = end + 1;
+        } else if byte < 0x20 || byte == 0x7f {
+            keys.extend(control_key(byte));
+            i += 1;
+        } else {
+            let end = bytes[i..].iter().position(|&b| b < 0x20 || b == 0x7f).map_or(bytes.len(), |p| i + p);
+            keys.extend(String::from_utf8_lossy(&bytes[i..end]).chars().map(Key::Char));
+            i = end;
+        }
+    }
+    keys
+}
+
+/// One ranked file from `search_grep`.
+#[derive(Debug)]
+struct Hit {
+    path: String,
+    lines: Vec<u32>,
+}
+
+/// What the event loop does after a key.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    None,
+    Search,
+    Open,
+    Quit,
+}
+
+/// Query, results and selection. Terminal-independent, so key handling is testable.
+struct App {
+    query: String,
+    mode: QueryMode,
+    hits: Vec<Hit>,
+    selected: usize,
+    /// First list row on screen
+    offset: usize,
+    /// Index into the selected hit's `lines`
+    match_index: usize,
+    highlighter: Option<Regex>,
+    status: String,
+    /// Path and lines of the file in the preview
+    preview: Option<(String, Vec<String>)>,
+}
+
+impl App {
+    fn new() -> Self {
+        App {
+            query: String::new(),
+            mode: QueryMode::Substring,
+            hits: Vec::new(),
+            selected: 0,
+            offset: 0,
+            match_index: 0,
+            highlighter: None,
+            status: "type to search".to_string(),
+            preview: None,
+        }
+    }
+
+    /// Apply `key`; `page` is the number of list rows on screen.
+    fn handle_key(&mut self, key: Key, page: usize) -> Action {
+        let last = self.hits.len().saturating_sub(1);
+        let select = |app: &mut App, index: usize| {
+            app.selected = index.min(last);
+            app.match_index = 0;
+        };
+        match key {
+            Key::Char(c) => {
+                self.query.push(c);
+                return Action::Search;
+            }
+            Key::Backspace => return if self.query.pop().is_some() { Action::Search } else { Action::None },
+            Key::DeleteWord => {
+                let kept = self.query.trim_end().rfind(|c: char| c.is_whitespace() || c == ',').map_or(0, |i| i + 1);
+                self.query.truncate(kept);
+                return Action::Search;
+            }
+            Key::Clear => {
+                self.query.clear();
+                return Action::Search;
+            }
+            Key::Tab => {
+                self.mode = self.mode.next();
+                return Action::Search;
+            }
+            Key::Enter => return if self.hits.is_empty() { Action::None } else { Action::Open },
+            Key::Quit => return Action::Quit,
+            Key::Up => select(self, self.selected.saturating_sub(1)),
+            Key::Down => select(self, self.selected + 1),
+            Key::PageUp => select(self, self.selected.saturating_sub(page.max(1))),
+            Key::PageDown => select(self, self.selected + page.max(1)),
+            Key::Home => select(self, 0),
+            Key::End => select(self, last),
+            Key::Left => self.match_index = self.match_index.saturating_sub(1),
+            Key::Right => {
+                let matches = self.hits.get(self.selected).map_or(0, |h| h.lines.len());
+                self.match_index = (self.match_index + 1).min(matches.saturating_sub(1));
+            }
+        }
+        self.scroll_to_selection(page);
+        Action::None
+    }
+
+    fn scroll_to_selection(&mut self, page: usize) {
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if page > 0 && self.selected >= self.offset + page {
+            self.offset = self.selected + 1 - page;
+        }
+    }
+
+    /// Run the query through `search_grep` and replace the results.
+    fn search(&mut self, ctx: &HandlerContext, max_results: usize) {
+        self.hits.clear();
+        self.selected = 0;
+        self.offset = 0;
+        self.match_index = 0;
+        self.highlighter = self.mode.highlighter(&self.query);
+        if self.query.trim().is_empty() {
+            self.status = "type to search".to_string();
+            return;
+        }
+        let mut args = json!({ "terms": self.query, "maxResults": max_results });
+        match self.mode {
+            QueryMode::Substring => {}
+            QueryMode::Exact => args["substring"] = json!(false),
+            QueryMode::Phrase => args["phrase"] = json!(true),
+            QueryMode::Regex => args["regex"] = json!(true),
+        }
+        let result = handlers::dispatch_tool(ctx, "search_grep", &args);
+        let text = result.content.first().map(|c| c.text.as_str()).unwrap_or_default();
+        if result.is_error {
+            self.status = text.lines().next().unwrap_or("search failed").to_string();
+            return;
+        }
+        let output: Value = serde_json::from_str(text).unwrap_or_default();
+        self.hits = output["files"].as_array().into_iter().flatten().filter_map(|f| Some(Hit {
+            path: f["path"].as_str()?.to_string(),
+            lines: f["lines"].as_array()?.iter().filter_map(|l| l.as_u64().map(|l| l as u32)).collect(),
+        })).collect();
+        let summary = &output["summary"];
+        self.status = format!("{} files, {} matches, {:.1} ms",
+            summary["totalFiles"].as_u64().unwrap_or(0),
+            summary["totalOccurrences"].as_u64().unwrap_or(0),
+            summary["searchTimeMs"].as_f64().unwrap_or(0.0));
+        if let Some(warning) = summary["warnings"].as_array().and_then(|w| w.first()).and_then(|w| w.as_str()) {
+            self.status = format!("{} | {}", self.status, warning);
+        }
+    }
+
+    /// The selected file and the line of its current match.
+    fn current_match(&self) -> Option<(&str, u32)> {
+        let hit = self.hits.get(self.selected)?;
+        Some((hit.path.as_str(), hit.lines.get(self.match_index).copied().unwrap_or(1)))
+    }
+}
+
+/// Program and arguments that open `path` at `line` in `editor` (a command line such as
+/// `code --wait`). Editors that take `+N` get that; the VS Code family gets `--goto`.
+fn editor_command(editor: &str, path: &str, line: u32) -> (String, Vec<String>) {
+    let mut words = editor.split_whitespace().map(str::to_string);
+    let program = words.next().unwrap_or_default();
+    let mut args: Vec<String> = words.collect();
+    let name = program.rsplit(['/', '\\']).next().unwrap_or_default().to_lowercase();
+    match name.trim_end_matches(".exe").trim_end_matches(".cmd") {
+        "code" | "code-insiders" | "codium" | "cursor" => args.extend(["--goto".to_string(), format!("{}:{}", path, line)]),
+        "subl" | "sublime_text" | "hx" | "zed" => args.push(format!("{}:{}", path, line)),
+        "notepad" => args.push(path.to_string()),
+        _ => args.extend([format!("+{}", line), path.to_string()]),
+    }
+    (program, args)
+}
+
+/// `$VISUAL`, then `$EDITOR`, then the platform default.
+fn editor() -> String {
+    std::env::var("VISUAL").ok().filter(|e| !e.trim().is_empty())
+        .or_else(|| std::env::var("EDITOR").ok().filter(|e| !e.trim().is_empty()))
+        .unwrap_or_else(|| if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() })
+}
+
+/// `line` with tabs expanded and control characters blanked, cut to `width` characters,
+/// with the highlighter's matches in bold yellow.
+fn styled_line(line: &str, highlighter: Option<&Regex>, width: usize) -> String {
+    let clean: String = line.replace('\t', "    ").chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+    let ranges: Vec<(usize, usize)> = highlighter.map(|re| re.find_iter(&clean).map(|m| (m.start(), m.end())).collect()).unwrap_or_default();
+    let mut out = String::new();
+    let mut highlighted = false;
+    for (count, (i, c)) in clean.char_indices().enumerate() {
+        if count >= width {
+            break;
+        }
+        let inside = ranges.iter().any(|&(s, e)| i >= s && i < e);
+        if inside != highlighted {
+            out.push_str(if inside { "\x1b[1;33m" } else { "\x1b[0m" });
+            highlighted = inside;
+        }
+        out.push(c);
+    }
+    if highlighted {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+/// `text` cut to `width` characters, keeping the end (for paths).
+fn fit_left(text: &str, width: usize) -> String {
+    let count = text.chars().count();
+    if count <= width {
+        return text.to_string();
+    }
+    let kept: String = text.chars().skip(count + 1 - width.max(1)).collect();
+    format!("…{}", kept)
+}
+
+/// Draw the whole screen: query line, results and preview, key help.
+fn render(app: &mut App, index_root: &str, read: impl Fn(&str) -> Option<String>, (width, height): (usize, usize)) -> String {
+    let mut out = String::from("\x1b[?25l");
+    let row = |out: &mut String, y: usize, text: &str| {
+        out.push_str(&format!("\x1b[{};1H{}\x1b[0m\x1b[K", y + 1, text));
+    };
+    let relative = |path: &str| -> String {
+        path.strip_prefix(index_root).map_or(path, |p| p.trim_start_matches('/')).to_string()
+    };
+
+    let prompt = format!("[{}] > {}", app.mode.as_str(), app.query);
+    let status = format!("{}  ", app.status);
+    let gap = width.saturating_sub(prompt.chars().count() + status.chars().count());
+    row(&mut out, 0, &format!("\x1b[1m{}\x1b[0m{}\x1b[2m{}", prompt, " ".repeat(gap), fit_left(&status, width.saturating_sub(prompt.chars().count()))));
+    row(&mut out, 1, &"─".repeat(width));
+
+    let body = height.saturating_sub(3);
+    let list_width = (width * 2 / 5).clamp(20.min(width), 60);
+    let preview_width = width.saturating_sub(list_width + 1);
+    app.scroll_to_selection(body);
+
+    // Preview: the selected file centered a third of the way down on its current match
+    let mut preview_rows: Vec<String> = Vec::new();
+    if let Some((path, line)) = app.current_match().map(|(p, l)| (p.to_string(), l)) {
+        if app.preview.as_ref().is_none_or(|(p, _)| *p != path) {
+            let lines = read(&path).map(|c| c.lines().map(str::to_string).collect()).unwrap_or_default();
+            app.preview = Some((path.clone(), lines));
+        }
+        let hit = &app.hits[app.selected];
+        let lines = app.preview.as_ref().map(|(_, l)| l.as_slice()).unwrap_or_default();
+        preview_rows.push(fit_left(&format!("\x1b[1m{}:{}\x1b[0m  match {}/{}",
+            relative(&path), line, app.match_index + 1, hit.lines.len()), preview_width + 8));
+        let first = (line as usize).saturating_sub(body / 3).max(1);
+        for number in first..first + body.saturating_sub(1) {
+            let Some(text) = lines.get(number - 1) else { break };
+            let marker = if hit.lines.contains(&(number as u32)) { '>' } else { ' ' };
+            preview_rows.push(format!("\x1b[2m{:>w$}{}\x1b[0m{}", number, marker, styled_line(text, app.highlighter.as_ref(),
+                preview_width.saturating_sub(GUTTER)), w = GUTTER - 1));
+        }
+    }
+
+    for y in 0..body {
+        let list = match app.hits.get(app.offset + y) {
+            Some(hit) => {
+                let label = fit_left(&format!("{} ({})", relative(&hit.path), hit.lines.len()), list_width);
+                let pad = " ".repeat(list_width.saturating_sub(label.chars().count()));
+                if app.offset + y == app.selected { format!("\x1b[7m{}{}\x1b[0m", label, pad) } else { format!("{}{}", label, pad) }
+            }
+            None => " ".repeat(list_width),
+        };
+        let preview = preview_rows.get(y).map(String::as_str).unwrap_or_default();
+        row(&mut out, 2 + y, &format!("{}\x1b[2m│\x1b[0m{}", list, preview));
+    }
+    row(&mut out, height.saturating_sub(1), &format!("\x1b[2m{}",
+        fit_left("↑↓ file  ←→ match  PgUp/PgDn page  Tab mode  Enter open  Ctrl-U clear  Esc quit", width)));
+    // Cursor back to the end of the query
+    out.push_str(&format!("\x1b[1;{}H\x1b[?25h", prompt.chars().count() + 1));
+    out
+}
+
+pub(crate) fn cmd_tui(args: TuiArgs) -> Result<(), SearchError> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err(SearchError::InvalidArgs("search tui needs an interactive terminal".to_string()));
+    }
+    let mut ctx = super::replay::replay_context(&args.dir, &args.ext, 0, index_dir(), Arc::new(SystemClock))?;
+    let (root, extensions) = {
+        let index = ctx.index.snapshot();
+        (index.root.clone(), index.extensions.join(","))
+    };
+    if ctx.server_ext.is_empty() {
+        ctx.server_ext = extensions;
+    }
+
+    let mut app = App::new();
+    if let Some(query) = args.query {
+        app.query = query;
+        app.search(&ctx, args.max_results);
+    }
+    let mut terminal = term::Terminal::enter()?;
+    let mut size = (0, 0);
+    loop {
+        let keys = terminal.read_keys(POLL_INTERVAL)?;
+        let current = term::size();
+        if keys.is_empty() && current == size {
+            continue;
+        }
+        size = current;
+        let page = size.1.saturating_sub(3);
+        let mut search = false;
+        for key in keys {
+            match app.handle_key(key, page) {
+                Action::None => {}
+                Action::Search => search = true,
+                Action::Quit => return Ok(()),
+                Action::Open => {
+                    if search {
+                        app.search(&ctx, args.max_results);
+                        search = false;
+                    }
+                    let Some((path, line)) = app.current_match() else { continue };
+                    let (program, editor_args) = editor_command(&editor(), &path_from_string(path).to_string_lossy(), line);
+                    terminal.suspend();
+                    let status = std::process::Command::new(&program).args(&editor_args).status();
+                    terminal.resume()?;
+                    if let Err(e) = status {
+                        app.status = format!("cannot run editor '{}': {} (set $EDITOR)", program, e);
+                    }
+                }
+            }
+        }
+        if search {
+            app.search(&ctx, args.max_results);
+        }
+        let frame = {
+            let index = ctx.index.snapshot();
+            render(&mut app, &root, |path| read_indexed_file(&index, path), size)
+        };
+        terminal.write(&frame)?;
+    }
+}
+
+/// Raw mode, the alternate screen, and key input, per platform.
+mod term {
+    use std::io::{self, Write};
+    use std::time::Duration;
+
+    use super::Key;
+
+    /// Switches the terminal to raw mode on the alternate screen; dropping it restores both.
+    pub(super) struct Terminal {
+        saved: sys::Saved,
+    }
+
+    impl Terminal {
+        pub(super) fn enter() -> io::Result<Self> {
+            let terminal = Terminal { saved: sys::enable_raw()? };
+            terminal.write("\x1b[?1049h\x1b[2J")?;
+            Ok(terminal)
+        }
+
+        pub(super) fn write(&self, text: &str) -> io::Result<()> {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(text.as_bytes())?;
+            stdout.flush()
+        }
+
+        /// Hand the terminal to a child process.
+        pub(super) fn suspend(&self) {
+            let _ = self.write("\x1b[?25h\x1b[?1049l");
+            sys::restore(&self.saved);
+        }
+
+        pub(super) fn resume(&mut self) -> io::Result<()> {
+            self.saved = sys::enable_raw()?;
+            self.write("\x1b[?1049h\x1b[2J")
+        }
+
+        /// Keys pressed within `timeout`; empty when there were none.
+        pub(super) fn read_keys(&self, timeout: Duration) -> io::Result<Vec<Key>> {
+            sys::read_keys(timeout)
+        }
+    }
+
+    impl Drop for Terminal {
+        fn drop(&mut self) {
+            self.suspend();
+        }
+    }
+
+    /// Columns and rows, 80x24 when unknown.
+    pub(super) fn size() -> (usize, usize) {
+        sys::size().filter(|&(w, h)| w > 0 && h > 0).unwrap_or((80, 24))
+    }
+
+    #[cfg(unix)]
+    mod sys {
+        use std::ffi::{c_int, c_short, c_ulong, c_void};
+        use std::io;
+        use std::time::Duration;
+
+        use super::super::{parse_keys, Key};
+
+        /// `struct termios`, kept opaque: its layout differs between platforms, and it
+        /// is only copied and handed to `cfmakeraw`.
+        pub(super) type Saved = [u64; 32];
+
+        #[repr(C)]
+        struct PollFd {
+            fd: c_int,
+            events: c_short,
+            revents: c_short,
+        }
+
+        #[repr(C)]
+        #[derive(Default)]
+        struct WinSize {
+            rows: u16,
+            cols: u16,
+            x_pixels: u16,
+            y_pixels: u16,
+        }
+
+        #[cfg(target_os = "linux")]
+        type NFds = c_ulong;
+        #[cfg(not(target_os = "linux"))]
+        type NFds = std::ffi::c_uint;
+
+        #[cfg(target_os = "linux")]
+        const TIOCGWINSZ: c_ulong = 0x5413;
+        #[cfg(not(target_os = "linux"))]
+        const TIOCGWINSZ: c_ulong = 0x4008_7468;
+
+        const POLLIN: c_short = 1;
+        const TCSANOW: c_int = 0;
+
+        unsafe extern "C" {
+            fn tcgetattr(fd: c_int, termios: *mut Saved) -> c_int;
+            fn tcsetattr(fd: c_int, action: c_int, termios: *const Saved) -> c_int;
+            fn cfmakeraw(termios: *mut Saved);
+            fn poll(fds: *mut PollFd, nfds: NFds, timeout: c_int) -> c_int;
+            fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+            fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+        }
+
+        pub(super) fn enable_raw() -> io::Result<Saved> {
+            let mut saved: Saved = [0; 32];
+            if unsafe { tcgetattr(0, &mut saved) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = saved;
+            unsafe { cfmakeraw(&mut raw) };
+            if unsafe { tcsetattr(0, TCSANOW, &raw) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(saved)
+        }
+
+        pub(super) fn restore(saved: &Saved) {
+            unsafe { tcsetattr(0, TCSANOW, saved) };
+        }
+
+        pub(super) fn read_keys(timeout: Duration) -> io::Result<Vec<Key>> {
+            let mut fd = PollFd { fd: 0, events: POLLIN, revents: 0 };
+            let ready = unsafe { poll(&mut fd, 1, timeout.as_millis() as c_int) };
+            if ready < 0 {
+                let err = io::Error::last_os_error();
+                // A resize (SIGWINCH) interrupts the wait
+                return if err.kind() == io::ErrorKind::Interrupted { Ok(Vec::new()) } else { Err(err) };
+            }
+            if ready == 0 {
+                return Ok(Vec::new());
+            }
+            let mut buf = [0u8; 256];
+            let n = unsafe { read(0, buf.as_mut_ptr().cast(), buf.len()) };
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if n == 0 {
+                return Ok(vec![Key::Quit]);
+            }
+            Ok(parse_keys(&buf[..n as usize]))
+        }
+
+        pub(super) fn size() -> Option<(usize, usize)> {
+            let mut ws = WinSize::default();
+            (unsafe { ioctl(1, TIOCGWINSZ, &mut ws) } == 0).then_some((ws.cols as usize, ws.rows as usize))
+        }
+    }
+
+    #[cfg(windows)]
+    mod sys {
+        use std::io;
+        use std::time::Duration;
+
+        use super::super::{control_key, Key};
+
+        /// Input and output console modes before raw mode.
+        pub(super) type Saved = (u32, u32);
+
+        const STD_INPUT_HANDLE: u32 = -10i32 as u32;
+        const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+        const ENABLE_PROCESSED_INPUT: u32 = 0x1;
+        const ENABLE_LINE_INPUT: u32 = 0x2;
+        const ENABLE_ECHO_INPUT: u32 = 0x4;
+        const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x4;
+        const WAIT_OBJECT_0: u32 = 0;
+        const KEY_EVENT: u32 = 1;
+
+        unsafe extern "system" {
+            fn GetStdHandle(handle: u32) -> isize;
+            fn GetConsoleMode(console: isize, mode: *mut u32) -> i32;
+            fn SetConsoleMode(console: isize, mode: u32) -> i32;
+            /// `info` is a CONSOLE_SCREEN_BUFFER_INFO: eleven 16-bit fields
+            fn GetConsoleScreenBufferInfo(console: isize, info: *mut [i16; 11]) -> i32;
+            fn WaitForSingleObject(handle: isize, millis: u32) -> u32;
+            /// `records` are INPUT_RECORDs: a 32-bit event type, then a 16-byte event
+            fn ReadConsoleInputW(console: isize, records: *mut [u32; 5], length: u32, read: *mut u32) -> i32;
+        }
+
+        pub(super) fn enable_raw() -> io::Result<Saved> {
+            let (input, output) = unsafe { (GetStdHandle(STD_INPUT_HANDLE), GetStdHandle(STD_OUTPUT_HANDLE)) };
+            let (mut in_mode, mut out_mode) = (0u32, 0u32);
+            if unsafe { GetConsoleMode(input, &mut in_mode) == 0 || GetConsoleMode(output, &mut out_mode) == 0 } {
+                return Err(io::Error::last_os_error());
+            }
+            let raw_in = in_mode & !(ENABLE_PROCESSED_INPUT | ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT);
+            if unsafe { SetConsoleMode(input, raw_in) == 0 || SetConsoleMode(output, out_mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) == 0 } {
+                return Err(io::Error::last_os_error());
+            }
+            Ok((in_mode, out_mode))
+        }
+
+        pub(super) fn restore(saved: &Saved) {
+            unsafe {
+                SetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), saved.0);
+                SetConsoleMode(GetStdHandle(STD_OUTPUT_HANDLE), saved.1);
+            }
+        }
+
+        pub(super) fn read_keys(timeout: Duration) -> io::Result<Vec<Key>> {
+            let input = unsafe { GetStdHandle(STD_INPUT_HANDLE) };
+            if unsafe { WaitForSingleObject(input, timeout.as_millis() as u32) } != WAIT_OBJECT_0 {
+                return Ok(Vec::new());
+            }
+            let mut records = [[0u32; 5]; 32];
+            let mut count = 0u32;
+            if unsafe { ReadConsoleInputW(input, records.as_mut_ptr(), records.len() as u32, &mut count) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut keys = Vec::new();
+            for record in &records[..count as usize] {
+                // KEY_EVENT_RECORD: bKeyDown, wRepeatCount | wVirtualKeyCode << 16,
+                // wVirtualScanCode | UnicodeChar << 16, dwControlKeyState
+                if record[0] & 0xffff != KEY_EVENT || record[1] == 0 {
+                    continue;
+                }
+                let repeat = (record[2] & 0xffff).max(1) as usize;
+                let key = match record[2] >> 16 {
+                    0x21 => Some(Key::PageUp),
+                    0x22 => Some(Key::PageDown),
+                    0x23 => Some(Key::End),
+                    0x24 => Some(Key::Home),
+                    0x25 => Some(Key::Left),
+                    0x26 => Some(Key::Up),
+                    0x27 => Some(Key::Right),
+                    0x28 => Some(Key::Down),
+                    _ => match record[3] >> 16 {
+                        0 => None,
+                        c if c < 0x20 || c == 0x7f => control_key(c as u8),
+                        c => char::from_u32(c).map(Key::Char),
+                    },
+                };
+                keys.extend(std::iter::repeat_n(key, repeat).flatten());
+            }
+            Ok(keys)
+        }
+
+        pub(super) fn size() -> Option<(usize, usize)> {
+            let mut info = [0i16; 11];
+            let ok = unsafe { GetConsoleScreenBufferInfo(GetStdHandle(STD_OUTPUT_HANDLE), &mut info) };
+            // srWindow: Left, Top, Right, Bottom
+            (ok != 0).then(|| ((info[7] - info[5] + 1) as usize, (info[8] - info[6] + 1) as usize))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tui_keys_highlighting_and_editor_command() {
+        assert_eq!(parse_keys(b"ab\x1b[A\x1b[6~\x7f\r"), vec![
+            Key::Char('a'), Key::Char('b'), Key::Up, Key::PageDown, Key::Backspace, Key::Enter,
+        ]);
+        assert_eq!(parse_keys(b"\x1b"), vec![Key::Quit]);
+        assert_eq!(parse_keys("é\x1bOB\x1b[1;5C".as_bytes()), vec![Key::Char('é'), Key::Down, Key::Right]);
+
+        let re = QueryMode::Substring.highlighter("client, Factory").unwrap();
+        assert_eq!(styled_line("HttpClient f = factory;", Some(&re), 80),
+            "Http\x1b[1;33mClient\x1b[0m f = \x1b[1;33mfactory\x1b[0m;");
+        assert_eq!(styled_line("\tabcdef", None, 6), "    ab");
+        let exact = QueryMode::Exact.highlighter("client").unwrap();
+        assert!(!exact.is_match("HttpClient") && exact.is_match("a client."));
+        assert!(QueryMode::Regex.highlighter("(").is_none());
+        assert!(QueryMode::Phrase.highlighter("  ").is_none());
+
+        assert_eq!(editor_command("vim", "a.rs", 7), ("vim".to_string(), vec!["+7".to_string(), "a.rs".to_string()]));
+        assert_eq!(editor_command("code --wait", "a.rs", 7).1, vec!["--wait", "--goto", "a.rs:7"]);
+        assert_eq!(editor_command(r"C:\Tools\Notepad.exe", "a.rs", 7).1, vec!["a.rs"]);
+    }
+
+    #[test]
+    fn test_tui_search_and_selection() {
+        let tmp = tempfile::tempdir().unwrap();
+        let idx_base = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("a.cs"), "class Alpha {\n    HttpClient client;\n}\n").unwrap();
+        std::fs::write(tmp.path().join("b.cs"), "class Beta { HttpClient one; HttpClient two; }\n").unwrap();
+        let dir = tmp.path().to_string_lossy().to_string();
+        let index = crate::build_content_index(&crate::ContentIndexArgs {
+            dir: dir.clone(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        });
+        crate::save_content_index(&index, idx_base.path()).unwrap();
+        let ctx = super::super::replay::replay_context(&dir, "cs", 0, idx_base.path().to_path_buf(), Arc::new(SystemClock)).unwrap();
+
+        let mut app = App::new();
+        for c in "httpcl".chars() {
+            assert_eq!(app.handle_key(Key::Char(c), 10), Action::Search);
+        }
+        app.search(&ctx, 50);
+        assert_eq!(app.hits.len(), 2, "{}", app.status);
+        assert!(app.status.starts_with("2 files, 3 matches"), "{}", app.status);
+        assert_eq!(app.handle_key(Key::End, 10), Action::None);
+        assert_eq!(app.selected, 1);
+        assert_eq!(app.handle_key(Key::Down, 10), Action::None);
+        assert_eq!(app.selected, 1, "selection stops at the last file");
+        let (path, line) = app.current_match().unwrap();
+        assert!(path.ends_with(if line == 2 { "a.cs" } else { "b.cs" }));
+        assert_eq!(app.handle_key(Key::Enter, 10), Action::Open);
+
+        let frame = render(&mut app, &index.root, |p| std::fs::read_to_string(p).ok(), (100, 12));
+        assert!(frame.contains("\x1b[1;33mHttpCl\x1b[0mient"), "preview highlights the query");
+
+        assert_eq!(app.handle_key(Key::Tab, 10), Action::Search);
+        app.search(&ctx, 50);
+        assert!(app.hits.is_empty(), "exact mode: 'httpcl' is not a token");
+        assert_eq!(app.handle_key(Key::Clear, 10), Action::Search);
+        app.search(&ctx, 50);
+        assert_eq!(app.status, "type to search");
+    }
+}
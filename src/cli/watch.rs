@@ -0,0 +1,94 @@
+//! `search content-index --watch`: keeps the on-disk content index current for
+//! CLI-only users, without running the MCP server.
+//!
+//! After the normal build, the index is handed to the same file watcher that
+//! `serve --watch` uses. Changes are applied in memory. The index is saved at most
+//! every `--save-interval-secs` when a batch was applied, and once more on Ctrl+C.
+//! Later `search grep` runs then load a fresh index instead of rebuilding.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::mcp::watcher::{self, WatchMode, WatcherOptions};
+use crate::priority::BuildPriority;
+use crate::index::build_trigram_index;
+use crate::{save_content_index, ContentIndex, ContentIndexArgs, SearchError, Searcher};
+use search::clock::Generation;
+
+/// Same as the `serve --debounce-ms` / `--bulk-threshold` / `--poll-interval-ms` defaults.
+const DEBOUNCE_MS: u64 = 500;
+const BULK_THRESHOLD: usize = 100;
+const POLL_INTERVAL_MS: u64 = 2000;
+/// How often the loop checks for Ctrl+C and the save deadline.
+const TICK: Duration = Duration::from_millis(200);
+
+pub(crate) fn watch_content_index(
+    args: &ContentIndexArgs,
+    index: ContentIndex,
+    idx_base: PathBuf,
+) -> Result<(), SearchError> {
+    // The watcher reports batches through tracing
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .with_writer(std::io::stderr)
+        .init();
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&shutdown);
+    if let Err(e) = ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst)) {
+        eprintln!("Warning: failed to set Ctrl+C handler: {}", e);
+    }
+
+    let watch_dir = std::fs::canonicalize(&args.dir).unwrap_or_else(|_| PathBuf::from(&args.dir));
+    let extensions: Vec<String> = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect();
+    let index = Searcher::new(watcher::build_watch_index_from(index));
+    let batches = watcher::start_watcher(
+        index.clone(),
+        None,
+        watch_dir,
+        extensions,
+        idx_base.clone(),
+        WatcherOptions {
+            debounce_ms: DEBOUNCE_MS,
+            bulk_threshold: BULK_THRESHOLD,
+            rebuild_priority: BuildPriority::Low,
+            io_limit_mb: args.io_limit_mb,
+            mode: WatchMode::Auto,
+            poll_interval_ms: POLL_INTERVAL_MS,
+        },
+        Arc::new(Generation::default()),
+    ).map_err(|e| SearchError::Io(std::io::Error::other(format!("failed to start file watcher: {}", e))))?;
+
+    eprintln!("Watching {} for changes (saving every {}s when changed). Press Ctrl+C to stop.",
+        args.dir, args.save_interval_secs);
+
+    let interval = Duration::from_secs(args.save_interval_secs.max(1));
+    let mut saved_batches = 0;
+    let mut last_save = Instant::now();
+    loop {
+        std::thread::sleep(TICK);
+        let stopping = shutdown.load(Ordering::SeqCst);
+        let current = batches.get();
+        if current != saved_batches && (stopping || last_save.elapsed() >= interval) {
+            // CLI substring search reads the saved trigrams as-is, so bring them up to date
+            index.update(|idx| {
+                if idx.trigram_dirty {
+                    idx.trigram = build_trigram_index(&idx.index);
+                    idx.trigram_dirty = false;
+                }
+            });
+            let idx = index.snapshot();
+            save_content_index(&idx, &idx_base)?;
+            eprintln!("Content index saved ({} files)", idx.files.len());
+            saved_batches = current;
+            last_save = Instant::now();
+        }
+        if stopping {
+            eprintln!("Stopped watching {}", args.dir);
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Time source for index staleness, and the index generation it also goes by.
+//!
+//! Index types take a [`Clock`] instead of reading the system clock, so staleness can
+//! be tested and `search replay` can run each call at the time it was recorded. The
+//! server and CLI pass [`SystemClock`]; tests and replay use a [`ManualClock`].
+//!
+//! Staleness compares the distance between the build time and now with the index's
+//! max age, in either direction: a build stamp from a clock that ran ahead (or a
+//! clock that was set back since) makes the index stale instead of fresh for as long
+//! as the skew lasts. A live index kept current by the watcher or `search_reindex` is
+//! measured from its last change, recorded by [`Generation`], when that is later than
+//! its build.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source of the current time, in seconds since the Unix epoch.
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now_secs(&self) -> u64;
+}
+
+/// The system clock. Times before the epoch read as 0.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_secs(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+    }
+}
+
+/// A clock that only moves when it is set or advanced.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    secs: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn new(secs: u64) -> Self {
+        ManualClock { secs: AtomicU64::new(secs) }
+    }
+
+    pub fn set(&self, secs: u64) {
+        self.secs.store(secs, Ordering::Release);
+    }
+
+    pub fn advance(&self, secs: u64) {
+        self.secs.fetch_add(secs, Ordering::AcqRel);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_secs(&self) -> u64 {
+        self.secs.load(Ordering::Acquire)
+    }
+}
+
+/// Whether an index stamped `refreshed_at` is past `max_age_secs` at `now`. A stamp
+/// more than `max_age_secs` in the future is stale too: the clock was wrong when it was
+/// taken, or is wrong now.
+pub fn is_stale(refreshed_at: u64, max_age_secs: u64, now: u64) -> bool {
+    refreshed_at.abs_diff(now) > max_age_secs
+}
+
+/// Counter of changes to a live index (background build, watcher batch, reindex), with
+/// the time of the last change. The MCP server returns it as `_meta.indexGeneration`.
+#[derive(Debug)]
+pub struct Generation {
+    value: AtomicU64,
+    /// Clock time of the last [`Generation::bump`]; meaningless while `value` is 0
+    changed_at: AtomicU64,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for Generation {
+    fn default() -> Self {
+        Generation::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl Generation {
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Generation { value: AtomicU64::new(0), changed_at: AtomicU64::new(0), clock }
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Acquire)
+    }
+
+    /// Record a change now. Returns the new generation.
+    pub fn bump(&self) -> u64 {
+        // Stored first, so a reader that sees the new generation sees its time
+        self.changed_at.store(self.clock.now_secs(), Ordering::Release);
+        self.value.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    /// Clock time of the last change; `None` before the first.
+    pub fn changed_at(&self) -> Option<u64> {
+        (self.get() > 0).then(|| self.changed_at.load(Ordering::Acquire))
+    }
+
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Whether an index built at `created_at` is stale, measured from the later of its
+    /// build and the last change.
+    pub fn is_stale(&self, created_at: u64, max_age_secs: u64) -> bool {
+        let refreshed_at = self.changed_at().map_or(created_at, |changed| changed.max(created_at));
+        is_stale(refreshed_at, max_age_secs, self.clock.now_secs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staleness_follows_clock_and_generation() {
+        let clock = Arc::new(ManualClock::new(10_000));
+        assert!(!is_stale(10_000, 3600, clock.now_secs()));
+        assert!(is_stale(6_000, 3600, clock.now_secs()));
+        // A build stamp far in the future is not trusted
+        assert!(!is_stale(12_000, 3600, clock.now_secs()));
+        assert!(is_stale(20_000, 3600, clock.now_secs()));
+
+        let generation = Generation::with_clock(clock.clone());
+        assert_eq!(generation.changed_at(), None);
+        clock.advance(4000);
+        assert!(generation.is_stale(10_000, 3600));
+        assert_eq!(generation.bump(), 1);
+        assert_eq!(generation.changed_at(), Some(14_000));
+        assert!(!generation.is_stale(10_000, 3600));
+        clock.advance(3601);
+        assert!(generation.is_stale(10_000, 3600));
+    }
+}
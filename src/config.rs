@@ -0,0 +1,519 @@
+//! Per-repository configuration: `.search-index.toml` in the indexed root.
+//!
+//! Teams commit shared defaults here instead of repeating flags. Keys use the CLI flag
+//! names, and a flag given on the command line always wins over the file:
+//!
+//! ```toml
+//! [index]
+//! ext = ["cs", "ts"]              # content-index / serve --ext
+//! exclude = ["**/Generated/**"]   # gitignore-style globs, config only
+//! min-token-len = 3               # content-index --min-token-len
+//! max-token-len = 64              # content-index --max-token-len
+//! max-file-size-kb = 512          # content-index --max-file-size, in KB
+//! stop-token-pct = 60             # content-index --stop-token-pct
+//!
+//! [search]
+//! sort = "path"                   # grep --sort
+//! max-results = 100               # grep --max-results
+//! exclude-dir = ["test"]          # grep --exclude-dir
+//!
+//! [watch]
+//! debounce-ms = 1000              # serve --debounce-ms
+//! bulk-threshold = 200            # serve --bulk-threshold
+//! mode = "poll"                   # serve --watch-mode
+//! poll-interval-ms = 5000         # serve --poll-interval-ms
+//!
+//! [languages]
+//! "scripts/*" = "sh"              # treat matching files as this extension
+//!
+//! [ranking]                       # grep score factors, 1.0 turns one off
+//! filename-boost = 2.0            # file name contains a query term
+//! definition-boost = 1.5          # file defines a symbol named like a query term
+//! test-penalty = 0.5              # test directories and *Tests / *.spec files
+//! generated-penalty = 0.5         # obj/, bin/, generated dirs, *.g.cs, *.designer.cs
+//!
+//! [semantic]                      # search_semantic embeddings
+//! embeddings = "http://localhost:11434/v1/embeddings"   # or "hash" (default, no model)
+//! model = "nomic-embed-text"      # model name sent to the endpoint
+//! api-key-env = "EMBEDDINGS_KEY"  # environment variable with a bearer token
+//!
+//! [[audit.release]]               # a check of suite "release" (search audit release)
+//! name = "No Thread.Sleep"
+//! tool = "grep"                   # any MCP tool, "search_" optional
+//! args = { terms = "thread.sleep", phrase = true }
+//! expect = "zero"                 # or "nonzero"
+//! ```
+//!
+//! `exclude` and `languages` have no flag, so every build reads them directly,
+//! including the ones the MCP server and the watcher start. They also take
+//! `max-file-size-kb` from here. `[audit]` suites are read when they are run.
+
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use serde_json::Value as Json;
+use toml_edit::{Document, Item, TableLike, Value};
+
+use crate::cli::args::{ContentIndexArgs, GrepArgs, ServeArgs};
+use crate::language::LanguageMap;
+use crate::mcp::handlers::utils::GrepSort;
+use crate::mcp::watcher::WatchMode;
+use crate::semantic::SemanticConfig;
+use crate::WalkLimits;
+
+/// File name looked up in the indexed root.
+pub const CONFIG_FILE_NAME: &str = ".search-index.toml";
+
+/// Settings read from `.search-index.toml`. `None` / empty means "not set".
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RepoConfig {
+    /// File the settings came from (`None` when the root has no config file).
+    pub source: Option<PathBuf>,
+    pub ext: Option<String>,
+    pub exclude: Vec<String>,
+    pub min_token_len: Option<usize>,
+    pub max_token_len: Option<usize>,
+    pub max_file_size_kb: Option<u64>,
+    pub stop_token_pct: Option<u8>,
+    pub sort: Option<GrepSort>,
+    pub max_results: Option<usize>,
+    pub exclude_dir: Vec<String>,
+    pub debounce_ms: Option<u64>,
+    pub bulk_threshold: Option<usize>,
+    pub watch_mode: Option<WatchMode>,
+    pub poll_interval_ms: Option<u64>,
+    /// `[languages]`: (glob, extension) in file order.
+    pub languages: Vec<(String, String)>,
+    /// `[audit]`: suites in file order.
+    pub audit_suites: Vec<AuditSuite>,
+    /// `[ranking]`: grep score factors.
+    pub ranking: RankingBoosts,
+    /// `[semantic]`: embedding backend of search_semantic.
+    pub semantic: SemanticConfig,
+}
+
+/// Factors grep multiplies a file's TF-IDF score by. 1.0 leaves the score alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankingBoosts {
+    /// The file name contains a query term: `UserService.cs` for `UserService`
+    pub filename: f64,
+    /// The file defines a symbol named like a query term (needs the definition index)
+    pub definition: f64,
+    /// The file is a test: under a test directory, or named `*Test(s)` / `*.spec.*` / `*.test.*`
+    pub test: f64,
+    /// The file is generated: under `obj`, `bin` or a generated directory, or `*.g.cs` and the like
+    pub generated: f64,
+}
+
+impl Default for RankingBoosts {
+    fn default() -> Self {
+        Self { filename: 2.0, definition: 1.5, test: 0.5, generated: 0.5 }
+    }
+}
+
+/// A named list of checks, run by `search audit <suite>` and the `search_audit` tool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditSuite {
+    pub name: String,
+    pub checks: Vec<AuditCheck>,
+}
+
+/// One assertion of a suite: the summed result count of its queries must be zero or
+/// not. A check is one query (`tool` and `args`), or several OR-ed together (`any`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditCheck {
+    pub name: String,
+    pub queries: Vec<AuditQuery>,
+    pub expect: AuditExpect,
+}
+
+/// An MCP tool call, e.g. `search_grep` with `{"terms": "thread.sleep"}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditQuery {
+    pub tool: String,
+    pub args: Json,
+}
+
+/// What an audit check asserts about its result count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuditExpect {
+    /// Nothing may match (a forbidden pattern)
+    Zero,
+    /// Something must match (a required pattern)
+    Nonzero,
+}
+
+impl AuditExpect {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditExpect::Zero => "zero",
+            AuditExpect::Nonzero => "nonzero",
+        }
+    }
+
+    pub fn holds(self, count: u64) -> bool {
+        match self {
+            AuditExpect::Zero => count == 0,
+            AuditExpect::Nonzero => count > 0,
+        }
+    }
+}
+
+impl RepoConfig {
+    /// Read `.search-index.toml` from `root`. A missing file is an empty config; a file
+    /// that does not parse, or has an unknown key or a mistyped value, is an error.
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let path = root.join(CONFIG_FILE_NAME);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&text, &path)
+    }
+
+    /// Like [`RepoConfig::load`], but a broken file only logs a warning. For builds
+    /// started by the server and the watcher, where there is nobody to report to.
+    pub fn load_or_default(root: &Path) -> Self {
+        Self::load(root).unwrap_or_else(|e| {
+            eprintln!("[config] Warning: {}", e);
+            Self::default()
+        })
+    }
+
+    fn parse(text: &str, path: &Path) -> Result<Self, String> {
+        let doc = Document::parse(text).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        let mut config = Self { source: Some(path.to_path_buf()), ..Self::default() };
+        for (section, item) in doc.as_table().iter() {
+            let table = item.as_table_like()
+                .ok_or_else(|| format!("Invalid {}: '{}' must be a table", path.display(), section))?;
+            for (key, value) in table.iter() {
+                let name = format!("{}.{}", section, key);
+                let err = |expected: &str| format!("Invalid {}: '{}' must be {}", path.display(), name, expected);
+                match (section, key) {
+                    ("index", "ext") => config.ext = Some(string_list(value).ok_or_else(|| err("a list of strings"))?.join(",")),
+                    ("index", "exclude") => config.exclude = string_list(value).ok_or_else(|| err("a list of strings"))?,
+                    ("index", "min-token-len") => config.min_token_len = Some(uint(value).ok_or_else(|| err("a non-negative integer"))? as usize),
+                    ("index", "max-token-len") => config.max_token_len = Some(uint(value).ok_or_else(|| err("a non-negative integer"))? as usize),
+                    ("index", "max-file-size-kb") => config.max_file_size_kb = Some(uint(value).ok_or_else(|| err("a non-negative integer"))?),
+                    ("index", "stop-token-pct") => config.stop_token_pct = Some(uint(value).filter(|&n| n <= 100).ok_or_else(|| err("a percentage from 0 to 100"))? as u8),
+                    ("search", "sort") => config.sort = Some(enum_value(value).ok_or_else(|| err("one of score, path, mtime, matches"))?),
+                    ("search", "max-results") => config.max_results = Some(uint(value).ok_or_else(|| err("a non-negative integer"))? as usize),
+                    ("search", "exclude-dir") => config.exclude_dir = string_list(value).ok_or_else(|| err("a list of strings"))?,
+                    ("watch", "debounce-ms") => config.debounce_ms = Some(uint(value).ok_or_else(|| err("a non-negative integer"))?),
+                    ("watch", "bulk-threshold") => config.bulk_threshold = Some(uint(value).ok_or_else(|| err("a non-negative integer"))? as usize),
+                    ("watch", "mode") => config.watch_mode = Some(enum_value(value).ok_or_else(|| err("one of auto, native, poll"))?),
+                    ("watch", "poll-interval-ms") => config.poll_interval_ms = Some(uint(value).ok_or_else(|| err("a non-negative integer"))?),
+                    ("languages", glob) => config.languages.push((glob.to_string(), value.as_str().ok_or_else(|| err("an extension string"))?.to_string())),
+                    ("ranking", "filename-boost") => config.ranking.filename = factor(value).ok_or_else(|| err("a number above 0"))?,
+                    ("ranking", "definition-boost") => config.ranking.definition = factor(value).ok_or_else(|| err("a number above 0"))?,
+                    ("ranking", "test-penalty") => config.ranking.test = factor(value).ok_or_else(|| err("a number above 0"))?,
+                    ("ranking", "generated-penalty") => config.ranking.generated = factor(value).ok_or_else(|| err("a number above 0"))?,
+                    ("semantic", "embeddings") => config.semantic.embeddings = Some(value.as_str().ok_or_else(|| err("\"hash\" or an http:// URL"))?.to_string()),
+                    ("semantic", "model") => config.semantic.model = Some(value.as_str().ok_or_else(|| err("a string"))?.to_string()),
+                    ("semantic", "api-key-env") => config.semantic.api_key_env = Some(value.as_str().ok_or_else(|| err("a string"))?.to_string()),
+                    ("audit", suite) => config.audit_suites.push(AuditSuite {
+                        name: suite.to_string(),
+                        checks: audit_checks(value, &name).map_err(|e| format!("Invalid {}: {}", path.display(), e))?,
+                    }),
+                    _ => return Err(format!("Invalid {}: unknown key '{}'", path.display(), name)),
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// The `[audit]` suite called `name`, case-insensitively.
+    pub fn audit_suite(&self, name: &str) -> Option<&AuditSuite> {
+        self.audit_suites.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Matcher for `exclude`, rooted at `root`. `None` when nothing is excluded.
+    pub fn exclude_matcher(&self, root: &Path) -> Option<Gitignore> {
+        if self.exclude.is_empty() {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(root);
+        for glob in &self.exclude {
+            if let Err(e) = builder.add_line(None, glob) {
+                eprintln!("[config] Warning: invalid exclude glob '{}': {}", glob, e);
+            }
+        }
+        builder.build().map_err(|e| eprintln!("[config] Warning: invalid exclude globs: {}", e)).ok()
+    }
+
+    /// Matchers for `[languages]`, rooted at `root`.
+    pub fn language_map(&self, root: &Path) -> LanguageMap {
+        LanguageMap::new(root, &self.languages)
+    }
+
+    /// `max-file-size-kb` in bytes; `None` (or 0) means no limit.
+    pub fn max_file_size(&self) -> Option<u64> {
+        self.max_file_size_kb.filter(|&kb| kb > 0).map(|kb| kb * 1024)
+    }
+
+    /// Size limit for a build: `--max-file-size` when set (non-zero), else the config's.
+    /// Builds the server and the watcher start pass 0, so the config applies to them.
+    pub fn effective_max_file_size(&self, flag: u64) -> Option<u64> {
+        if flag > 0 { Some(flag) } else { self.max_file_size() }
+    }
+
+    /// Apply `exclude` and `limits` to a content walk rooted at `root`.
+    pub fn apply_to_walk(&self, builder: &mut WalkBuilder, root: &Path, limits: &WalkLimits) {
+        limits.apply_to_walk(builder, root);
+        if let Some(excludes) = self.exclude_matcher(root) {
+            // One entry filter per walk: it replaces the one pruning directories
+            let (root, limits) = (root.to_path_buf(), limits.clone());
+            builder.filter_entry(move |entry| {
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                let pruned = is_dir && limits.prunes(&root, entry.path());
+                !pruned && !excludes.matched_path_or_any_parents(entry.path(), is_dir).is_ignore()
+            });
+        }
+    }
+
+    /// Fill `content-index` flags that were not given on the command line.
+    pub fn apply_to_content_index(&self, args: &mut ContentIndexArgs, explicit: impl Fn(&str) -> bool) {
+        merge(&mut args.ext, &self.ext, explicit("ext"));
+        merge(&mut args.min_token_len, &self.min_token_len, explicit("min_token_len"));
+        merge(&mut args.max_token_len, &self.max_token_len, explicit("max_token_len"));
+        merge(&mut args.stop_token_pct, &self.stop_token_pct, explicit("stop_token_pct"));
+        merge(&mut args.max_file_size, &self.max_file_size(), explicit("max_file_size"));
+    }
+
+    /// Fill `serve` flags that were not given on the command line.
+    pub fn apply_to_serve(&self, args: &mut ServeArgs, explicit: impl Fn(&str) -> bool) {
+        merge(&mut args.ext, &self.ext, explicit("ext"));
+        merge(&mut args.debounce_ms, &self.debounce_ms, explicit("debounce_ms"));
+        merge(&mut args.bulk_threshold, &self.bulk_threshold, explicit("bulk_threshold"));
+        merge(&mut args.watch_mode, &self.watch_mode, explicit("watch_mode"));
+        merge(&mut args.poll_interval_ms, &self.poll_interval_ms, explicit("poll_interval_ms"));
+    }
+
+    /// Fill `grep` flags that were not given on the command line.
+    pub fn apply_to_grep(&self, args: &mut GrepArgs, explicit: impl Fn(&str) -> bool) {
+        merge(&mut args.sort, &self.sort, explicit("sort"));
+        merge(&mut args.max_results, &self.max_results, explicit("max_results"));
+        args.ranking = self.ranking;
+        if !explicit("exclude_dir") && !self.exclude_dir.is_empty() {
+            args.exclude_dir = self.exclude_dir.clone();
+        }
+    }
+}
+
+fn merge<T: Clone>(field: &mut T, configured: &Option<T>, explicit: bool) {
+    if let Some(value) = configured.as_ref().filter(|_| !explicit) {
+        *field = value.clone();
+    }
+}
+
+/// An array of strings, or one comma-separated string like the CLI takes.
+fn string_list(item: &Item) -> Option<Vec<String>> {
+    let value = item.as_value()?;
+    if let Some(s) = value.as_str() {
+        return Some(s.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect());
+    }
+    value.as_array()?.iter().map(|v| v.as_str().map(str::to_string)).collect()
+}
+
+/// The checks of `[[audit.<suite>]]` tables, or an inline array of tables.
+fn audit_checks(item: &Item, name: &str) -> Result<Vec<AuditCheck>, String> {
+    let tables: Vec<&dyn TableLike> = if let Some(tables) = item.as_array_of_tables() {
+        tables.iter().map(|t| t as &dyn TableLike).collect()
+    } else if let Some(array) = item.as_array() {
+        array.iter().map(|v| v.as_inline_table().map(|t| t as &dyn TableLike))
+            .collect::<Option<_>>()
+            .ok_or_else(|| format!("'{}' must be a list of tables", name))?
+    } else {
+        return Err(format!("'{}' must be a list of tables ([[{}]])", name, name));
+    };
+    tables.into_iter().enumerate().map(|(i, table)| {
+        let name = format!("{}[{}]", name, i);
+        let mut check = AuditCheck { name: format!("check {}", i + 1), queries: Vec::new(), expect: AuditExpect::Zero };
+        let mut single = (None, None);
+        for (key, value) in table.iter() {
+            let err = |expected: &str| format!("'{}.{}' must be {}", name, key, expected);
+            match key {
+                "name" => check.name = value.as_str().ok_or_else(|| err("a string"))?.to_string(),
+                "expect" => check.expect = enum_value(value).ok_or_else(|| err("one of zero, nonzero"))?,
+                "tool" => single.0 = Some(value.as_str().ok_or_else(|| err("a tool name"))?),
+                "args" => single.1 = Some(value),
+                "any" => {
+                    let queries = value.as_array().ok_or_else(|| err("a list of {tool, args} tables"))?;
+                    for query in queries {
+                        let query = query.as_inline_table().ok_or_else(|| err("a list of {tool, args} tables"))?;
+                        let tool = TableLike::get(query, "tool").and_then(|t| t.as_str())
+                            .ok_or_else(|| format!("every '{}.any' entry needs a tool", name))?;
+                        check.queries.push(audit_query(tool, TableLike::get(query, "args"), &name)?);
+                    }
+                }
+                _ => return Err(format!("unknown key '{}.{}'", name, key)),
+            }
+        }
+        match single {
+            (Some(tool), args) if check.queries.is_empty() => check.queries.push(audit_query(tool, args, &name)?),
+            (None, None) if !check.queries.is_empty() => {}
+            _ => return Err(format!("'{}' needs either tool (and args) or any", name)),
+        }
+        Ok(check)
+    }).collect()
+}
+
+/// A query of `tool` (`search_` prefix optional) with `args` as its JSON arguments.
+fn audit_query(tool: &str, args: Option<&Item>, name: &str) -> Result<AuditQuery, String> {
+    let tool = if tool.starts_with("search_") { tool.to_string() } else { format!("search_{}", tool) };
+    let args = match args {
+        Some(item) => match item.as_table_like() {
+            Some(table) => table_json(table),
+            None => return Err(format!("'{}.args' must be a table", name)),
+        },
+        None => Json::Object(Default::default()),
+    };
+    Ok(AuditQuery { tool, args })
+}
+
+fn table_json(table: &dyn TableLike) -> Json {
+    Json::Object(table.iter().filter_map(|(key, item)| Some((key.to_string(), item_json(item)?))).collect())
+}
+
+fn item_json(item: &Item) -> Option<Json> {
+    match item {
+        Item::Value(value) => Some(value_json(value)),
+        Item::Table(table) => Some(table_json(table)),
+        Item::ArrayOfTables(tables) => Some(Json::Array(tables.iter().map(|t| table_json(t)).collect())),
+        Item::None => None,
+    }
+}
+
+fn value_json(value: &Value) -> Json {
+    match value {
+        Value::String(s) => Json::from(s.value().as_str()),
+        Value::Integer(n) => Json::from(*n.value()),
+        Value::Float(f) => Json::from(*f.value()),
+        Value::Boolean(b) => Json::from(*b.value()),
+        Value::Datetime(d) => Json::from(d.value().to_string()),
+        Value::Array(array) => Json::Array(array.iter().map(value_json).collect()),
+        Value::InlineTable(table) => table_json(table),
+    }
+}
+
+fn uint(item: &Item) -> Option<u64> {
+    item.as_integer().and_then(|n| u64::try_from(n).ok())
+}
+
+/// A positive number; integers are taken as floats.
+fn factor(item: &Item) -> Option<f64> {
+    let value = item.as_float().or_else(|| item.as_integer().map(|n| n as f64))?;
+    (value > 0.0 && value.is_finite()).then_some(value)
+}
+
+fn enum_value<T: ValueEnum>(item: &Item) -> Option<T> {
+    T::from_str(item.as_str()?, true).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_repo_config_parses_and_merges_under_cli_flags() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(RepoConfig::load(tmp.path()).unwrap(), RepoConfig::default());
+
+        std::fs::write(tmp.path().join(CONFIG_FILE_NAME), r#"
+[index]
+ext = ["cs", "ts"]
+exclude = ["**/Generated/**"]
+min-token-len = 3
+max-token-len = 64
+max-file-size-kb = 512
+stop-token-pct = 80
+
+[search]
+sort = "path"
+exclude-dir = "test, e2e"
+
+[watch]
+mode = "poll"
+debounce-ms = 1000
+
+[languages]
+"scripts/*" = "sh"
+
+[ranking]
+filename-boost = 3
+test-penalty = 1.0
+
+[semantic]
+embeddings = "http://localhost:11434/v1/embeddings"
+model = "nomic-embed-text"
+"#).unwrap();
+        let config = RepoConfig::load(tmp.path()).unwrap();
+        assert_eq!(config.ext.as_deref(), Some("cs,ts"));
+        assert_eq!(config.languages, vec![("scripts/*".to_string(), "sh".to_string())]);
+        assert_eq!(config.exclude_dir, vec!["test", "e2e"]);
+        assert_eq!(config.max_file_size(), Some(512 * 1024));
+        assert_eq!(config.semantic.model.as_deref(), Some("nomic-embed-text"));
+        let matcher = config.exclude_matcher(tmp.path()).unwrap();
+        assert!(matcher.matched_path_or_any_parents(tmp.path().join("src/Generated/A.cs"), false).is_ignore());
+
+        // Flags given on the command line win, the rest come from the file
+        let mut args = ContentIndexArgs::parse_from(["content-index", "--ext", "rs"]);
+        config.apply_to_content_index(&mut args, |id| id == "ext");
+        assert_eq!((args.ext.as_str(), args.min_token_len, args.max_file_size), ("rs", 3, 512 * 1024));
+        assert_eq!((args.max_token_len, args.stop_token_pct), (64, 80));
+        assert_eq!(config.effective_max_file_size(100), Some(100));
+        let args = ContentIndexArgs::parse_from(["content-index", "--max-file-size", "2M"]);
+        assert_eq!(config.effective_max_file_size(args.max_file_size), Some(2 << 20));
+        assert!(ContentIndexArgs::try_parse_from(["content-index", "--max-file-size", "2X"]).is_err());
+        let mut args = ServeArgs::parse_from(["serve"]);
+        config.apply_to_serve(&mut args, |_| false);
+        assert_eq!((args.watch_mode, args.debounce_ms, args.bulk_threshold), (WatchMode::Poll, 1000, 100));
+        let mut args = GrepArgs::parse_from(["grep", "x", "--exclude-dir", "bin"]);
+        config.apply_to_grep(&mut args, |id| id == "exclude_dir");
+        assert_eq!((args.sort, args.exclude_dir), (GrepSort::Path, vec!["bin".to_string()]));
+        assert_eq!(args.ranking, RankingBoosts { filename: 3.0, definition: 1.5, test: 1.0, generated: 0.5 });
+
+        std::fs::write(tmp.path().join(CONFIG_FILE_NAME), "[index]\nmin_token_len = 3\n").unwrap();
+        assert!(RepoConfig::load(tmp.path()).unwrap_err().contains("unknown key 'index.min_token_len'"));
+        std::fs::write(tmp.path().join(CONFIG_FILE_NAME), "[watch]\nmode = \"inotify\"\n").unwrap();
+        assert!(RepoConfig::load(tmp.path()).unwrap_err().contains("one of auto, native, poll"));
+        std::fs::write(tmp.path().join(CONFIG_FILE_NAME), "[languages]\nbuild = 3\n").unwrap();
+        assert!(RepoConfig::load(tmp.path()).unwrap_err().contains("'languages.build' must be an extension string"));
+        std::fs::write(tmp.path().join(CONFIG_FILE_NAME), "[ranking]\ntest-penalty = 0\n").unwrap();
+        assert!(RepoConfig::load(tmp.path()).unwrap_err().contains("a number above 0"));
+    }
+
+    #[test]
+    fn test_audit_suites_parse_single_and_any_queries() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join(CONFIG_FILE_NAME), r#"
+[[audit.release]]
+name = "No Thread.Sleep"
+tool = "grep"
+args = { terms = "thread.sleep", phrase = true, excludeDir = ["test"] }
+
+[[audit.release]]
+any = [{ tool = "search_definitions", args = { name = "Startup" } }, { tool = "find" }]
+expect = "nonzero"
+"#).unwrap();
+        let config = RepoConfig::load(tmp.path()).unwrap();
+        let suite = config.audit_suite("RELEASE").unwrap();
+        assert_eq!(suite.checks[0].queries, vec![AuditQuery {
+            tool: "search_grep".to_string(),
+            args: serde_json::json!({ "terms": "thread.sleep", "phrase": true, "excludeDir": ["test"] }),
+        }]);
+        assert_eq!(suite.checks[0].expect, AuditExpect::Zero);
+        assert_eq!((suite.checks[1].name.as_str(), suite.checks[1].expect), ("check 2", AuditExpect::Nonzero));
+        let tools: Vec<&str> = suite.checks[1].queries.iter().map(|q| q.tool.as_str()).collect();
+        assert_eq!(tools, vec!["search_definitions", "search_find"]);
+
+        std::fs::write(tmp.path().join(CONFIG_FILE_NAME), "[[audit.ci]]\nname = \"x\"\n").unwrap();
+        assert!(RepoConfig::load(tmp.path()).unwrap_err().contains("'audit.ci[0]' needs either tool (and args) or any"));
+        std::fs::write(tmp.path().join(CONFIG_FILE_NAME), "[[audit.ci]]\ntool = \"grep\"\nexpect = \"some\"\n").unwrap();
+        assert!(RepoConfig::load(tmp.path()).unwrap_err().contains("one of zero, nonzero"));
+    }
+}
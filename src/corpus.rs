@@ -0,0 +1,373 @@
+//! Synthetic codebase generator for benchmarks and tests (`search gen-corpus`).
+//!
+//! Produces a deterministic fake C# and/or TypeScript codebase: one class per file,
+//! fields holding other generated classes, and methods whose bodies call those classes'
+//! methods. Identifiers are drawn from a synthetic vocabulary with a Zipf distribution,
+//! so token frequencies (a few very common tokens, a long tail of rare ones) resemble
+//! real code. The same spec and seed always produce byte-identical files, which makes
+//! performance numbers reproducible without access to a private monorepo.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Language of generated files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorpusLanguage {
+    CSharp,
+    TypeScript,
+}
+
+impl CorpusLanguage {
+    /// Parse a file extension (`cs`, `ts`).
+    pub fn from_ext(ext: &str) -> Option<Self> {
+        match ext.trim().to_ascii_lowercase().as_str() {
+            "cs" => Some(Self::CSharp),
+            "ts" => Some(Self::TypeScript),
+            _ => None,
+        }
+    }
+
+    pub fn ext(self) -> &'static str {
+        match self {
+            Self::CSharp => "cs",
+            Self::TypeScript => "ts",
+        }
+    }
+}
+
+/// Shape of the generated codebase.
+#[derive(Debug, Clone)]
+pub struct CorpusSpec {
+    /// Number of files (one class each)
+    pub files: usize,
+    /// Number of distinct vocabulary words identifiers are built from
+    pub vocabulary: usize,
+    /// Zipf exponent of word frequencies: 0 = uniform, 1 = classic Zipf, higher = more skewed
+    pub zipf_exponent: f64,
+    /// Languages, assigned to files round-robin
+    pub languages: Vec<CorpusLanguage>,
+    pub methods_per_class: usize,
+    /// Statements per method body, on average
+    pub statements_per_method: usize,
+    /// Files per directory (`mod000/`, `mod001/`, ...)
+    pub files_per_dir: usize,
+    pub seed: u64,
+}
+
+impl Default for CorpusSpec {
+    fn default() -> Self {
+        Self {
+            files: 1_000,
+            vocabulary: 5_000,
+            zipf_exponent: 1.0,
+            languages: vec![CorpusLanguage::CSharp],
+            methods_per_class: 8,
+            statements_per_method: 6,
+            files_per_dir: 100,
+            seed: 42,
+        }
+    }
+}
+
+/// What [`generate_corpus`] wrote.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorpusStats {
+    pub files: usize,
+    pub classes: usize,
+    pub methods: usize,
+    pub lines: usize,
+    pub bytes: u64,
+}
+
+/// Consonant-vowel syllables: concatenations of fixed-width syllables never collide,
+/// so every vocabulary index maps to a distinct word.
+const CONSONANTS: &[u8] = b"bcdfghklmnprstvz";
+const VOWELS: &[u8] = b"aeiou";
+
+/// Vocabulary words that are keywords in C# or TypeScript.
+const RESERVED: &[&str] = &["base", "case", "goto", "delete", "module"];
+
+const CLASS_SUFFIXES: &[&str] = &["Service", "Repository", "Controller", "Handler", "Manager", "Client", "Validator", "Provider"];
+const VERBS: &[&str] = &["Get", "Create", "Update", "Delete", "Process", "Validate", "Load", "Save", "Find", "Handle"];
+
+/// SplitMix64: tiny, fast and stable across platforms and releases.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n` (`n > 0`).
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn pick<'a>(&mut self, items: &'a [&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+/// The first `size` vocabulary words, most frequent first: lowercase, at least two
+/// syllables, no keywords.
+pub fn vocabulary(size: usize) -> Vec<String> {
+    let syllables = CONSONANTS.len() * VOWELS.len();
+    (syllables..)
+        .map(|mut n| {
+            let mut word = Vec::new();
+            while n > 0 {
+                let s = n % syllables;
+                word.push(CONSONANTS[s / VOWELS.len()]);
+                word.push(VOWELS[s % VOWELS.len()]);
+                n /= syllables;
+            }
+            String::from_utf8(word).unwrap()
+        })
+        .filter(|w| !RESERVED.contains(&w.as_str()))
+        .take(size)
+        .collect()
+}
+
+/// Samples vocabulary words with Zipf-distributed frequencies.
+struct Words {
+    words: Vec<String>,
+    /// Cumulative distribution over `words`
+    cumulative: Vec<f64>,
+}
+
+impl Words {
+    fn new(size: usize, exponent: f64) -> Self {
+        let words = vocabulary(size.max(1));
+        let mut total = 0.0;
+        let mut cumulative: Vec<f64> = (1..=words.len())
+            .map(|rank| {
+                total += 1.0 / (rank as f64).powf(exponent);
+                total
+            })
+            .collect();
+        for c in &mut cumulative {
+            *c /= total;
+        }
+        Self { words, cumulative }
+    }
+
+    fn sample(&self, rng: &mut Rng) -> &str {
+        let u = rng.unit();
+        let i = self.cumulative.partition_point(|&c| c < u).min(self.words.len() - 1);
+        &self.words[i]
+    }
+
+    fn pascal(&self, rng: &mut Rng) -> String {
+        pascal(self.sample(rng))
+    }
+}
+
+fn pascal(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+}
+
+fn camel(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map(|c| c.to_ascii_lowercase().to_string() + chars.as_str()).unwrap_or_default()
+}
+
+/// A generated class other classes can depend on.
+struct ClassInfo {
+    name: String,
+    dir: String,
+    language: CorpusLanguage,
+    methods: Vec<String>,
+}
+
+/// Write the corpus described by `spec` under `dir`, creating directories as needed.
+/// Existing files with the same names are overwritten.
+pub fn generate_corpus(dir: &Path, spec: &CorpusSpec) -> io::Result<CorpusStats> {
+    let languages = if spec.languages.is_empty() { vec![CorpusLanguage::CSharp] } else { spec.languages.clone() };
+    let words = Words::new(spec.vocabulary, spec.zipf_exponent);
+    let mut rng = Rng(spec.seed);
+    let mut classes: Vec<ClassInfo> = Vec::with_capacity(spec.files);
+    let mut stats = CorpusStats::default();
+
+    for i in 0..spec.files {
+        let language = languages[i % languages.len()];
+        let module = format!("mod{:03}", i / spec.files_per_dir.max(1));
+        let name = format!("{}{}{}", words.pascal(&mut rng), rng.pick(CLASS_SUFFIXES), i);
+        let methods: Vec<String> = (0..spec.methods_per_class)
+            .map(|_| format!("{}{}", rng.pick(VERBS), words.pascal(&mut rng)))
+            .collect();
+
+        // Up to three earlier classes of the same language, referenced as fields
+        let candidates: Vec<&ClassInfo> = classes.iter().filter(|c| c.language == language).collect();
+        let mut deps: Vec<&ClassInfo> = Vec::new();
+        if !candidates.is_empty() {
+            for _ in 0..rng.below(4) {
+                let dep = candidates[rng.below(candidates.len())];
+                if !deps.iter().any(|d| d.name == dep.name) {
+                    deps.push(dep);
+                }
+            }
+        }
+
+        let text = match language {
+            CorpusLanguage::CSharp => render_csharp(&mut rng, &words, spec, &module, &name, &methods, &deps),
+            CorpusLanguage::TypeScript => render_typescript(&mut rng, &words, spec, &name, &methods, &deps),
+        };
+        let module_dir = dir.join(&module);
+        fs::create_dir_all(&module_dir)?;
+        fs::write(module_dir.join(format!("{}.{}", name, language.ext())), &text)?;
+
+        stats.files += 1;
+        stats.classes += 1;
+        stats.methods += methods.len();
+        stats.lines += text.lines().count();
+        stats.bytes += text.len() as u64;
+        classes.push(ClassInfo { name, dir: module, language, methods });
+    }
+    Ok(stats)
+}
+
+/// Method body statements, with `{indent}` before each line.
+fn render_statements(rng: &mut Rng, words: &Words, spec: &CorpusSpec, language: CorpusLanguage, deps: &[&ClassInfo], param: &str, indent: &str) -> String {
+    let count = 1 + rng.below(spec.statements_per_method.max(1) * 2);
+    let mut out = String::new();
+    for _ in 0..count {
+        let local = words.sample(rng).to_string();
+        let line = match (rng.below(4), language) {
+            (0, _) if !deps.is_empty() => {
+                let dep = deps[rng.below(deps.len())];
+                let method = dep.methods.get(rng.below(dep.methods.len().max(1))).cloned().unwrap_or_else(|| "ToString".to_string());
+                match language {
+                    CorpusLanguage::CSharp => format!("var {} = _{}.{}({});", local, camel(&dep.name), method, param),
+                    CorpusLanguage::TypeScript => format!("const {} = this.{}.{}({});", local, camel(&dep.name), camel(&method), param),
+                }
+            }
+            (1, CorpusLanguage::CSharp) => format!("if ({} == null) throw new ArgumentNullException(nameof({}));", param, param),
+            (1, CorpusLanguage::TypeScript) => format!("if (!{}) {{ throw new Error('{} {}'); }}", param, words.sample(rng), words.sample(rng)),
+            (2, _) => format!("// {} {} {} {}", words.pascal(rng), words.sample(rng), words.sample(rng), words.sample(rng)),
+            (_, CorpusLanguage::CSharp) => format!("var {} = {}.{}({}, {});", local, words.pascal(rng), words.pascal(rng), param, words.sample(rng)),
+            (_, CorpusLanguage::TypeScript) => format!("const {} = {}.{}({}, {});", local, words.sample(rng), words.sample(rng), param, words.sample(rng)),
+        };
+        out.push_str(indent);
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn render_csharp(rng: &mut Rng, words: &Words, spec: &CorpusSpec, module: &str, name: &str, methods: &[String], deps: &[&ClassInfo]) -> String {
+    let mut out = String::from("using System;\nusing System.Collections.Generic;\n\n");
+    out.push_str(&format!("namespace Corpus.{}\n{{\n", pascal(module)));
+    out.push_str(&format!("    /// <summary>{} {} {}.</summary>\n", words.pascal(rng), words.sample(rng), words.sample(rng)));
+    out.push_str(&format!("    public class {}\n    {{\n", name));
+    for dep in deps {
+        out.push_str(&format!("        private readonly Corpus.{}.{} _{} = new Corpus.{}.{}();\n",
+            pascal(&dep.dir), dep.name, camel(&dep.name), pascal(&dep.dir), dep.name));
+    }
+    for method in methods {
+        let param = words.sample(rng).to_string();
+        let visibility = if rng.below(4) == 0 { "private" } else { "public" };
+        out.push_str(&format!("\n        {} string {}(string {})\n        {{\n", visibility, method, param));
+        out.push_str(&render_statements(rng, words, spec, CorpusLanguage::CSharp, deps, &param, "            "));
+        out.push_str(&format!("            return {};\n        }}\n", param));
+    }
+    out.push_str("    }\n}\n");
+    out
+}
+
+fn render_typescript(rng: &mut Rng, words: &Words, spec: &CorpusSpec, name: &str, methods: &[String], deps: &[&ClassInfo]) -> String {
+    let mut out = String::new();
+    for dep in deps {
+        out.push_str(&format!("import {{ {} }} from '../{}/{}';\n", dep.name, dep.dir, dep.name));
+    }
+    if !deps.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&format!("/** {} {} {}. */\n", words.pascal(rng), words.sample(rng), words.sample(rng)));
+    out.push_str(&format!("export class {} {{\n", name));
+    for dep in deps {
+        out.push_str(&format!("    private {} = new {}();\n", camel(&dep.name), dep.name));
+    }
+    for method in methods {
+        let param = words.sample(rng).to_string();
+        let visibility = if rng.below(4) == 0 { "private" } else { "public" };
+        out.push_str(&format!("\n    {} {}({}: string): string {{\n", visibility, camel(method), param));
+        out.push_str(&render_statements(rng, words, spec, CorpusLanguage::TypeScript, deps, &param, "        "));
+        out.push_str(&format!("        return {};\n    }}\n", param));
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_vocabulary_words_are_distinct_and_not_keywords() {
+        let words = vocabulary(10_000);
+        assert_eq!(words.len(), 10_000);
+        let distinct: std::collections::HashSet<&String> = words.iter().collect();
+        assert_eq!(distinct.len(), words.len());
+        assert!(words.iter().all(|w| w.len() >= 4 && !RESERVED.contains(&w.as_str())));
+    }
+
+    #[test]
+    fn test_generate_corpus_is_deterministic_and_zipf_skewed() {
+        let spec = CorpusSpec {
+            files: 40,
+            vocabulary: 500,
+            languages: vec![CorpusLanguage::CSharp, CorpusLanguage::TypeScript],
+            files_per_dir: 10,
+            ..CorpusSpec::default()
+        };
+        let a = tempfile::tempdir().unwrap();
+        let b = tempfile::tempdir().unwrap();
+        let stats = generate_corpus(a.path(), &spec).unwrap();
+        assert_eq!(generate_corpus(b.path(), &spec).unwrap(), stats);
+        assert_eq!((stats.files, stats.classes, stats.methods), (40, 40, 320));
+
+        let read_all = |root: &Path| {
+            let mut files: Vec<(String, String)> = Vec::new();
+            for module in fs::read_dir(root).unwrap() {
+                for file in fs::read_dir(module.unwrap().path()).unwrap() {
+                    let path = file.unwrap().path();
+                    files.push((path.strip_prefix(root).unwrap().to_string_lossy().to_string(), fs::read_to_string(&path).unwrap()));
+                }
+            }
+            files.sort();
+            files
+        };
+        let files = read_all(a.path());
+        assert_eq!(files, read_all(b.path()), "same seed, same bytes");
+        assert_eq!(files.len(), 40);
+        assert_eq!(files.iter().filter(|(p, _)| p.ends_with(".ts")).count(), 20);
+        assert!(files.iter().all(|(p, _)| p.starts_with("mod00") || p.starts_with("mod01") || p.starts_with("mod02") || p.starts_with("mod03")));
+
+        // The most frequent vocabulary word dwarfs a mid-ranked one
+        let vocab = vocabulary(500);
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (_, text) in &files {
+            for token in text.split(|c: char| !c.is_ascii_alphanumeric()) {
+                *counts.entry(token).or_default() += 1;
+            }
+        }
+        let top = counts.get(vocab[0].as_str()).copied().unwrap_or(0);
+        let mid = counts.get(vocab[100].as_str()).copied().unwrap_or(0);
+        assert!(top > 10 * mid.max(1), "top={} mid={}", top, mid);
+
+        let other = generate_corpus(b.path(), &CorpusSpec { seed: 7, ..spec }).unwrap();
+        assert_ne!(other, stats, "a different seed gives a different corpus");
+    }
+}
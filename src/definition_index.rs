@@ -0,0 +1,811 @@
+//! Definition index: the classes, methods, functions and SQL objects of a codebase,
+//! and queries over them.
+//!
+//! The CLI builds a [`DefinitionIndex`] with its tree-sitter parsers (`search
+//! def-index`) and saves it next to the content index. Other programs [`load`] that
+//! file and look definitions up with a [`DefinitionQuery`], as the MCP server's
+//! `search_definitions` tool does:
+//!
+//! ```no_run
+//! use search::definition_index::{self, DefinitionKind, DefinitionQuery};
+//!
+//! let index = definition_index::load("repo.code-structure".as_ref())?;
+//! let query = DefinitionQuery::new().name("UserService").kind(DefinitionKind::Class);
+//! for idx in index.find(&query)? {
+//!     let def = &index.definitions[idx as usize];
+//!     println!("{}:{} {}", index.files[def.file_id as usize], def.line_start, def.name);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Payload;
+use crate::WalkLimits;
+
+// ─── Definition Kind ─────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefinitionKind {
+    // C# kinds
+    Class,
+    Interface,
+    Enum,
+    Struct,
+    Record,
+    Method,
+    Property,
+    Field,
+    Constructor,
+    Delegate,
+    Event,
+    EnumMember,
+    // TypeScript kinds
+    Function,
+    TypeAlias,
+    Variable,
+    // SQL kinds
+    StoredProcedure,
+    Table,
+    View,
+    SqlFunction,
+    UserDefinedType,
+    Column,
+    SqlIndex,
+}
+
+impl DefinitionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Class => "class",
+            Self::Interface => "interface",
+            Self::Enum => "enum",
+            Self::Struct => "struct",
+            Self::Record => "record",
+            Self::Method => "method",
+            Self::Property => "property",
+            Self::Field => "field",
+            Self::Constructor => "constructor",
+            Self::Delegate => "delegate",
+            Self::Event => "event",
+            Self::EnumMember => "enumMember",
+            Self::Function => "function",
+            Self::TypeAlias => "typeAlias",
+            Self::Variable => "variable",
+            Self::StoredProcedure => "storedProcedure",
+            Self::Table => "table",
+            Self::View => "view",
+            Self::SqlFunction => "sqlFunction",
+            Self::UserDefinedType => "userDefinedType",
+            Self::Column => "column",
+            Self::SqlIndex => "sqlIndex",
+        }
+    }
+}
+
+impl std::fmt::Display for DefinitionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for DefinitionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "class" => Ok(Self::Class),
+            "interface" => Ok(Self::Interface),
+            "enum" => Ok(Self::Enum),
+            "struct" => Ok(Self::Struct),
+            "record" => Ok(Self::Record),
+            "method" => Ok(Self::Method),
+            "property" => Ok(Self::Property),
+            "field" => Ok(Self::Field),
+            "constructor" => Ok(Self::Constructor),
+            "delegate" => Ok(Self::Delegate),
+            "event" => Ok(Self::Event),
+            "enummember" => Ok(Self::EnumMember),
+            "function" => Ok(Self::Function),
+            "typealias" => Ok(Self::TypeAlias),
+            "variable" => Ok(Self::Variable),
+            "storedprocedure" => Ok(Self::StoredProcedure),
+            "table" => Ok(Self::Table),
+            "view" => Ok(Self::View),
+            "sqlfunction" => Ok(Self::SqlFunction),
+            "userdefinedtype" => Ok(Self::UserDefinedType),
+            "column" => Ok(Self::Column),
+            "sqlindex" => Ok(Self::SqlIndex),
+            other => Err(format!("Unknown definition kind: '{}'", other)),
+        }
+    }
+}
+
+// ─── Definition Entry ────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DefinitionEntry {
+    pub file_id: u32,
+    pub name: String,
+    pub kind: DefinitionKind,
+    pub line_start: u32,
+    pub line_end: u32,
+    pub parent: Option<String>,
+    pub signature: Option<String>,
+    pub modifiers: Vec<String>,
+    pub attributes: Vec<String>,
+    pub base_types: Vec<String>,
+}
+
+// ─── Code Stats ──────────────────────────────────────────────────────
+
+/// Code complexity metrics computed during AST walk.
+/// Only populated for Method, Constructor, Function, Property (expression body).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CodeStats {
+    /// Cyclomatic complexity: number of linearly independent execution paths.
+    /// Base = 1, each branching node adds +1.
+    pub cyclomatic_complexity: u16,
+    /// SonarSource Cognitive Complexity: penalizes nesting depth.
+    /// Measures code readability, not just structural complexity.
+    pub cognitive_complexity: u16,
+    /// Maximum nesting depth of control flow structures.
+    pub max_nesting_depth: u8,
+    /// Number of parameters in the method/function signature.
+    pub param_count: u8,
+    /// Number of return + throw statements (exit points).
+    pub return_count: u8,
+    /// Number of method/function calls in the body (fan-out).
+    pub call_count: u16,
+    /// Number of lambda/arrow function expressions in the body.
+    pub lambda_count: u8,
+    /// Halstead volume: total operators and operands × log2 of the distinct ones.
+    #[serde(default)]
+    pub halstead_volume: f32,
+    /// Halstead difficulty: distinct operators / 2 × total operands / distinct operands.
+    #[serde(default)]
+    pub halstead_difficulty: f32,
+    /// Maintainability index (0–100) from Halstead volume, cyclomatic complexity and lines.
+    #[serde(default)]
+    pub maintainability_index: u8,
+    /// Fields, properties and methods of the enclosing class used in the body (for LCOM).
+    #[serde(default)]
+    pub members_used: Vec<String>,
+}
+
+/// A call site found in a method/constructor body via AST analysis.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CallSite {
+    /// Name of the method being called, e.g., "GetUser"
+    pub method_name: String,
+    /// Resolved type of the receiver, e.g., "IUserService".
+    /// None for simple calls like Foo() where receiver type is unknown.
+    pub receiver_type: Option<String>,
+    /// Line of the called name (1-based). In a chain split over several lines
+    /// (`_service\n    .GetUser()`), the line of `.GetUser`, not of `_service`.
+    pub line: u32,
+    /// Whether the receiver type at the call site had generic parameters,
+    /// e.g., `new List<int>()` → true, `new List()` → false.
+    /// Used to filter out name collisions with non-generic classes.
+    #[serde(default)]
+    pub receiver_is_generic: bool,
+    /// 1-based character column of the called name on `line`; 0 if unknown
+    /// (call sites migrated from a definition index built before columns were kept).
+    #[serde(default)]
+    pub column: u32,
+    /// Character column just past the called name (end exclusive); 0 if unknown.
+    #[serde(default)]
+    pub end_column: u32,
+    /// Receiver expression as written, normalized by [`normalize_receiver`]:
+    /// `_userService`, `this.repo`, `GetFactory()`. None for unqualified calls and `new`.
+    #[serde(default)]
+    pub receiver: Option<String>,
+    /// Number of arguments passed; None if unknown (a spread argument, or a call site
+    /// from a definition index built before argument counts were kept).
+    #[serde(default)]
+    pub arg_count: Option<u8>,
+}
+
+/// Receiver text longer than this is cut (long lambdas and fluent chains).
+const MAX_RECEIVER_CHARS: usize = 120;
+
+/// Receiver expression text on one line: whitespace (line breaks in fluent chains)
+/// is dropped, except a single space between two identifier characters
+/// (`await client`), and text over 120 characters is cut with `...`.
+pub fn normalize_receiver(text: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(text.len());
+    let mut pending_space = false;
+    for c in text.trim().chars() {
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space && out.chars().next_back().is_some_and(is_word) && is_word(c) {
+            out.push(' ');
+        }
+        pending_space = false;
+        out.push(c);
+    }
+    match out.char_indices().nth(MAX_RECEIVER_CHARS) {
+        Some((cut, _)) => format!("{}...", &out[..cut]),
+        None => out,
+    }
+}
+
+// ─── File Imports ────────────────────────────────────────────────────
+
+/// What one file imports and declares, as written in the source.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct FileImports {
+    /// C# `using` namespaces and types, or TypeScript module specifiers (`./user.service`)
+    pub imports: Vec<String>,
+    /// Namespaces the file declares (C# `namespace`, TypeScript `namespace`), by full name
+    pub namespaces: Vec<String>,
+    /// Line ranges of the declared namespaces, in declaration order
+    #[serde(default)]
+    pub scopes: Vec<NamespaceScope>,
+}
+
+/// A namespace declaration and the lines it covers. File-scoped C# namespaces and
+/// unclosed blocks extend to the end of the file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NamespaceScope {
+    /// Full dotted name, including enclosing namespaces (`Shop.Billing`)
+    pub name: String,
+    pub line_start: u32,
+    pub line_end: u32,
+}
+
+impl FileImports {
+    pub fn is_empty(&self) -> bool {
+        self.imports.is_empty() && self.namespaces.is_empty() && self.scopes.is_empty()
+    }
+}
+
+// ─── DI Registrations ────────────────────────────────────────────────
+
+/// One `services.Add{Lifetime}(...)` call: a service type and what resolves it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DiRegistration {
+    /// Service type as written (`IRepository<User>`, `Shop.IClock`)
+    pub service: String,
+    /// Implementation type as written; `None` for a factory that constructs no type itself
+    pub implementation: Option<String>,
+    /// `scoped`, `transient` or `singleton`
+    pub lifetime: String,
+    pub file_id: u32,
+    pub line: u32,
+}
+
+// ─── Endpoints ───────────────────────────────────────────────────────
+
+/// An HTTP endpoint and the code that handles it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    /// `GET`, `POST`, ...; `ANY` for an action with only `[Route]`
+    pub http_method: String,
+    /// Route template with a leading slash (`/api/Orders/{id:int}`)
+    pub route: String,
+    /// `Class.Method` of an action, or the method group passed to `Map*`; `None` for lambdas
+    pub handler: Option<String>,
+    pub file_id: u32,
+    /// Line of the action, or of the `Map*` call
+    pub line: u32,
+}
+
+// ─── Partial Types ───────────────────────────────────────────────────
+
+/// A C# type declared `partial` in more than one place, seen as one logical type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PartialType {
+    /// def_idx of every declaration, by file path and line; the first is the primary
+    pub parts: Vec<u32>,
+    /// Union of the parts' attributes, in part order
+    pub attributes: Vec<String>,
+    /// Union of the parts' base types, in part order
+    pub base_types: Vec<String>,
+}
+
+// ─── Doc Comments ────────────────────────────────────────────────────
+
+/// The `///` XML documentation comment of a C# definition.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct DocComment {
+    /// `line_start` of the definitions it documents
+    pub line: u32,
+    pub summary: Option<String>,
+    /// `<param name="...">` descriptions, in order
+    pub params: Vec<DocParam>,
+    pub returns: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocParam {
+    pub name: String,
+    pub description: String,
+}
+
+// ─── Definition Index ────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DefinitionIndex {
+    pub root: String,
+    pub created_at: u64,
+    pub extensions: Vec<String>,
+    /// file_id -> file path
+    pub files: Vec<String>,
+    /// All definitions
+    pub definitions: Vec<DefinitionEntry>,
+    /// name (lowercased) -> Vec<index into definitions>
+    pub name_index: HashMap<String, Vec<u32>>,
+    /// kind -> Vec<index into definitions>
+    pub kind_index: HashMap<DefinitionKind, Vec<u32>>,
+    /// attribute name (lowercased) -> Vec<index into definitions>
+    pub attribute_index: HashMap<String, Vec<u32>>,
+    /// base type name (lowercased) -> Vec<index into definitions>
+    pub base_type_index: HashMap<String, Vec<u32>>,
+    /// file_id -> Vec<index into definitions>
+    pub file_index: HashMap<u32, Vec<u32>>,
+    /// Path -> file_id lookup (for watcher)
+    pub path_to_id: HashMap<PathBuf, u32>,
+    /// def_idx -> list of call sites found in that method/constructor body.
+    /// Only populated for Method and Constructor kinds.
+    #[serde(default)]
+    pub method_calls: HashMap<u32, Vec<CallSite>>,
+    /// Number of files that could not be read (IO errors) during index build.
+    #[serde(default)]
+    pub parse_errors: usize,
+    /// Number of files that contained non-UTF8 bytes and were read with lossy conversion.
+    #[serde(default)]
+    pub lossy_file_count: usize,
+    /// Files that were read and parsed but produced 0 definitions.
+    /// Each entry is (file_id, byte_size). Files >500 bytes with 0 defs are suspicious.
+    #[serde(default)]
+    pub empty_file_ids: Vec<(u32, u64)>,
+    /// def_idx -> CodeStats for methods/constructors/functions.
+    /// Always populated when --definitions is used.
+    #[serde(default)]
+    pub code_stats: HashMap<u32, CodeStats>,
+    /// Extension method name → Vec of static class names containing the extension.
+    /// Populated during C# parsing by detecting static classes with `this` parameter methods.
+    #[serde(default)]
+    pub extension_methods: HashMap<String, Vec<String>>,
+    /// Angular component selector → def_idx of the @Component class.
+    /// Example: "datahub-compact-view" → [idx of DatahubCompactViewComponent]
+    #[serde(default)]
+    pub selector_index: HashMap<String, Vec<u32>>,
+    /// def_idx of component → child selectors from HTML template.
+    /// Example: idx of DatahubEmbedComponent → ["datahub-compact-view", "pbi-spinner"]
+    #[serde(default)]
+    pub template_children: HashMap<u32, Vec<String>>,
+    /// Depth and directory limits of the walk the index was built from
+    #[serde(default)]
+    pub walk_limits: WalkLimits,
+    /// file_id -> imports and declared namespaces, for the file dependency graph.
+    /// Files with neither have no entry.
+    #[serde(default)]
+    pub file_imports: HashMap<u32, FileImports>,
+    /// C# DI container registrations (`services.AddScoped<IFoo, Foo>()`)
+    #[serde(default)]
+    pub di_registrations: Vec<DiRegistration>,
+    /// ASP.NET endpoints: attribute-routed actions and minimal-API mappings
+    #[serde(default)]
+    pub endpoints: Vec<Endpoint>,
+    /// def_idx of every part of a partial C# type -> the merged type
+    #[serde(default)]
+    pub partial_types: HashMap<u32, PartialType>,
+    /// file_id -> XML doc comments of its definitions, by line
+    #[serde(default)]
+    pub doc_comments: HashMap<u32, Vec<DocComment>>,
+    /// ctags and SCIP files whose definitions were imported (`def-index --import`),
+    /// imported again by rebuilds
+    #[serde(default)]
+    pub imports: Vec<String>,
+}
+
+// ─── Namespaces ──────────────────────────────────────────────────────
+
+/// Whether namespace `ns` is `outer` or nested in it (case-insensitive).
+pub fn is_within_namespace(ns: &str, outer: &str) -> bool {
+    ns.eq_ignore_ascii_case(outer)
+        || (ns.len() > outer.len() && ns.as_bytes()[outer.len()] == b'.' && ns[..outer.len()].eq_ignore_ascii_case(outer))
+}
+
+/// Innermost namespace declared around `def`, if any.
+pub fn namespace_of<'a>(index: &'a DefinitionIndex, def: &DefinitionEntry) -> Option<&'a str> {
+    index.file_imports.get(&def.file_id)?.scopes.iter()
+        .filter(|s| s.line_start <= def.line_start && def.line_start <= s.line_end)
+        // Scopes opening on the same line nest in declaration order
+        .max_by_key(|s| s.line_start)
+        .map(|s| s.name.as_str())
+}
+
+/// `Namespace.Parent.Name`, leaving out the parts `def` does not have.
+pub fn qualified_name(index: &DefinitionIndex, def: &DefinitionEntry) -> String {
+    let mut parts: Vec<&str> = Vec::with_capacity(3);
+    parts.extend(namespace_of(index, def));
+    parts.extend(def.parent.as_deref());
+    parts.push(&def.name);
+    parts.join(".")
+}
+
+// ─── Queries ─────────────────────────────────────────────────────────
+
+/// A definition lookup: what [`DefinitionIndex::find`] selects. Every filter set must
+/// match; text filters are case-insensitive.
+#[derive(Debug, Clone, Default)]
+pub struct DefinitionQuery {
+    /// Comma-separated names, any of which may be a substring of the definition's
+    /// name; a dotted term (`Billing.InvoiceService`) may also match its qualified
+    /// name. A regular expression over names with [`DefinitionQuery::regex`].
+    pub name: Option<String>,
+    pub regex: bool,
+    pub kind: Option<DefinitionKind>,
+    /// Attribute or decorator, by name
+    pub attribute: Option<String>,
+    /// Base type or implemented interface, by name
+    pub base_type: Option<String>,
+    /// Substring of the file path
+    pub file: Option<String>,
+    /// Substring of the enclosing type's name
+    pub parent: Option<String>,
+    /// Namespace the definition is declared in, or one nested in it
+    pub namespace: Option<String>,
+    /// Substrings of file paths to leave out
+    pub exclude_dir: Vec<String>,
+}
+
+impl DefinitionQuery {
+    /// A query matching every definition.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string()).filter(|n| !n.is_empty());
+        self
+    }
+
+    pub fn regex(mut self, regex: bool) -> Self {
+        self.regex = regex;
+        self
+    }
+
+    pub fn kind(mut self, kind: DefinitionKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn attribute(mut self, attribute: &str) -> Self {
+        self.attribute = Some(attribute.to_string());
+        self
+    }
+
+    pub fn base_type(mut self, base_type: &str) -> Self {
+        self.base_type = Some(base_type.to_string());
+        self
+    }
+
+    pub fn file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    pub fn parent(mut self, parent: &str) -> Self {
+        self.parent = Some(parent.to_string());
+        self
+    }
+
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.trim().to_string()).filter(|n| !n.is_empty());
+        self
+    }
+
+    pub fn exclude_dir(mut self, dirs: Vec<String>) -> Self {
+        self.exclude_dir = dirs;
+        self
+    }
+}
+
+impl DefinitionIndex {
+    /// Indices into [`DefinitionIndex::definitions`] of the definitions matching
+    /// `query`, ascending. Fails only on an invalid name regex.
+    pub fn find(&self, query: &DefinitionQuery) -> Result<Vec<u32>, regex::Error> {
+        // Candidates from the kind, attribute and base type indexes, then the names
+        let mut candidates: Option<Vec<u32>> = None;
+        let mut narrow = |found: Vec<u32>| {
+            candidates = Some(match candidates.take() {
+                Some(existing) => {
+                    let set: HashSet<u32> = found.into_iter().collect();
+                    existing.into_iter().filter(|i| set.contains(i)).collect()
+                }
+                None => found,
+            });
+        };
+        if let Some(kind) = query.kind {
+            narrow(self.kind_index.get(&kind).cloned().unwrap_or_default());
+        }
+        if let Some(attr) = &query.attribute {
+            narrow(self.attribute_index.get(&attr.to_lowercase()).cloned().unwrap_or_default());
+        }
+        if let Some(bt) = &query.base_type {
+            narrow(self.base_type_index.get(&bt.to_lowercase()).cloned().unwrap_or_default());
+        }
+        if let Some(name) = &query.name {
+            narrow(self.find_names(name, query.regex)?);
+        }
+
+        // If no filters applied, every definition
+        let mut candidates = candidates.unwrap_or_else(|| (0..self.definitions.len() as u32).collect());
+        // A definition appears more than once if e.g. several attributes normalize to
+        // the same name
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let file_filter = query.file.as_ref().map(|f| f.replace('\\', "/").to_lowercase());
+        let parent_filter = query.parent.as_ref().map(|p| p.to_lowercase());
+        let exclude_dir: Vec<String> = query.exclude_dir.iter().map(|d| d.to_lowercase()).collect();
+        candidates.retain(|&idx| {
+            let Some(def) = self.definitions.get(idx as usize) else { return false };
+            let Some(file_path) = self.files.get(def.file_id as usize) else { return false };
+            let file_lower = file_path.to_lowercase();
+            // Separators normalized for cross-platform matching
+            file_filter.as_ref().is_none_or(|ff| file_lower.replace('\\', "/").contains(ff.as_str()))
+                && parent_filter.as_ref().is_none_or(|pf| def.parent.as_ref().is_some_and(|p| p.to_lowercase().contains(pf.as_str())))
+                && query.namespace.as_ref().is_none_or(|nf| namespace_of(self, def).is_some_and(|ns| is_within_namespace(ns, nf)))
+                && !exclude_dir.iter().any(|excl| file_lower.contains(excl.as_str()))
+        });
+        Ok(candidates)
+    }
+
+    /// Definitions whose names match the `name` filter of a [`DefinitionQuery`].
+    fn find_names(&self, name: &str, regex: bool) -> Result<Vec<u32>, regex::Error> {
+        let mut matching = Vec::new();
+        if regex {
+            let re = regex::Regex::new(&format!("(?i){}", name))?;
+            for (n, indices) in &self.name_index {
+                if re.is_match(n) {
+                    matching.extend(indices);
+                }
+            }
+            return Ok(matching);
+        }
+        let terms: Vec<String> = name.split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+        for (n, indices) in &self.name_index {
+            if terms.iter().any(|t| n.contains(t)) {
+                matching.extend(indices);
+                continue;
+            }
+            let qualified: Vec<&String> = terms.iter()
+                .filter(|t| t.contains('.') && n.contains(t.rsplit('.').next().unwrap_or(t)))
+                .collect();
+            if !qualified.is_empty() {
+                matching.extend(indices.iter().filter(|&&idx| {
+                    let name = qualified_name(self, &self.definitions[idx as usize]).to_lowercase();
+                    qualified.iter().any(|t| name.contains(t.as_str()))
+                }));
+            }
+        }
+        Ok(matching)
+    }
+}
+
+// ─── Storage ─────────────────────────────────────────────────────────
+
+/// Payload layout of definition index files, stored in the file header.
+///
+/// - 1 (and headerless files): call sites hold a line only.
+/// - 2: call sites add `column`, `end_column` and `receiver`.
+///
+/// Older files are migrated on load; fields they lack are left unknown.
+pub const DEFINITION_INDEX_VERSION: u32 = 2;
+
+/// Load a definition index file of any [`DEFINITION_INDEX_VERSION`] up to the current one.
+pub fn load(path: &Path) -> io::Result<DefinitionIndex> {
+    crate::storage::read_index_file(path, DEFINITION_INDEX_VERSION, decode)
+}
+
+/// Decode the payload of a definition index file of format `version`, migrating
+/// older layouts.
+pub fn decode(version: u32, payload: Payload) -> bincode::Result<DefinitionIndex> {
+    if version >= 2 {
+        payload.deserialize()
+    } else {
+        let legacy: DefinitionIndexV1 = payload.deserialize()?;
+        Ok(legacy.into())
+    }
+}
+
+/// [`CallSite`] as stored before version 2. bincode is positional, so old files need
+/// the old field list.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct CallSiteV1 {
+    method_name: String,
+    receiver_type: Option<String>,
+    line: u32,
+    receiver_is_generic: bool,
+}
+
+/// [`DefinitionIndex`] as stored before version 2: identical but for its call sites.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct DefinitionIndexV1 {
+    root: String,
+    created_at: u64,
+    extensions: Vec<String>,
+    files: Vec<String>,
+    definitions: Vec<DefinitionEntry>,
+    name_index: HashMap<String, Vec<u32>>,
+    kind_index: HashMap<DefinitionKind, Vec<u32>>,
+    attribute_index: HashMap<String, Vec<u32>>,
+    base_type_index: HashMap<String, Vec<u32>>,
+    file_index: HashMap<u32, Vec<u32>>,
+    path_to_id: HashMap<PathBuf, u32>,
+    method_calls: HashMap<u32, Vec<CallSiteV1>>,
+    parse_errors: usize,
+    lossy_file_count: usize,
+    empty_file_ids: Vec<(u32, u64)>,
+    code_stats: HashMap<u32, CodeStats>,
+    extension_methods: HashMap<String, Vec<String>>,
+    selector_index: HashMap<String, Vec<u32>>,
+    template_children: HashMap<u32, Vec<String>>,
+}
+
+impl From<CallSiteV1> for CallSite {
+    fn from(c: CallSiteV1) -> Self {
+        CallSite {
+            method_name: c.method_name,
+            receiver_type: c.receiver_type,
+            line: c.line,
+            receiver_is_generic: c.receiver_is_generic,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
+        }
+    }
+}
+
+impl From<DefinitionIndexV1> for DefinitionIndex {
+    fn from(v1: DefinitionIndexV1) -> Self {
+        DefinitionIndex {
+            root: v1.root,
+            created_at: v1.created_at,
+            extensions: v1.extensions,
+            files: v1.files,
+            definitions: v1.definitions,
+            name_index: v1.name_index,
+            kind_index: v1.kind_index,
+            attribute_index: v1.attribute_index,
+            base_type_index: v1.base_type_index,
+            file_index: v1.file_index,
+            path_to_id: v1.path_to_id,
+            method_calls: v1.method_calls.into_iter()
+                .map(|(di, calls)| (di, calls.into_iter().map(CallSite::from).collect()))
+                .collect(),
+            parse_errors: v1.parse_errors,
+            lossy_file_count: v1.lossy_file_count,
+            empty_file_ids: v1.empty_file_ids,
+            code_stats: v1.code_stats,
+            extension_methods: v1.extension_methods,
+            selector_index: v1.selector_index,
+            template_children: v1.template_children,
+            walk_limits: Default::default(),
+            file_imports: HashMap::new(),
+            di_registrations: Vec::new(),
+            endpoints: Vec::new(),
+            partial_types: HashMap::new(),
+            doc_comments: HashMap::new(),
+            imports: Vec::new(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def(file_id: u32, name: &str, kind: DefinitionKind, parent: Option<&str>, line: u32) -> DefinitionEntry {
+        DefinitionEntry {
+            file_id, name: name.to_string(), kind, line_start: line, line_end: line + 5,
+            parent: parent.map(str::to_string), signature: None, modifiers: Vec::new(),
+            attributes: Vec::new(), base_types: Vec::new(),
+        }
+    }
+
+    /// Two files: `Shop.Billing` with a class and its method, and a test class.
+    fn sample_index() -> DefinitionIndex {
+        let definitions = vec![
+            def(0, "InvoiceService", DefinitionKind::Class, None, 3),
+            def(0, "CreateInvoice", DefinitionKind::Method, Some("InvoiceService"), 5),
+            def(1, "InvoiceServiceTests", DefinitionKind::Class, None, 1),
+        ];
+        let mut index = DefinitionIndex {
+            files: vec!["C:/repo/src/Billing/InvoiceService.cs".to_string(), "C:/repo/tests/InvoiceServiceTests.cs".to_string()],
+            ..Default::default()
+        };
+        for (idx, d) in definitions.iter().enumerate() {
+            index.name_index.entry(d.name.to_lowercase()).or_default().push(idx as u32);
+            index.kind_index.entry(d.kind).or_default().push(idx as u32);
+        }
+        index.attribute_index.insert("testclass".to_string(), vec![2]);
+        index.base_type_index.insert("iinvoiceservice".to_string(), vec![0]);
+        index.file_imports.insert(0, FileImports {
+            scopes: vec![NamespaceScope { name: "Shop.Billing".to_string(), line_start: 1, line_end: 20 }],
+            ..Default::default()
+        });
+        index.definitions = definitions;
+        index
+    }
+
+    #[test]
+    fn test_find_intersects_filters() {
+        let index = sample_index();
+        let find = |query: DefinitionQuery| index.find(&query).unwrap();
+
+        assert_eq!(find(DefinitionQuery::new()), vec![0, 1, 2]);
+        assert_eq!(find(DefinitionQuery::new().name("invoiceservice")), vec![0, 2]);
+        assert_eq!(find(DefinitionQuery::new().name("invoiceservice").kind(DefinitionKind::Class).attribute("TestClass")), vec![2]);
+        assert_eq!(find(DefinitionQuery::new().base_type("IInvoiceService")), vec![0]);
+        assert_eq!(find(DefinitionQuery::new().kind(DefinitionKind::Enum)), Vec::<u32>::new());
+        assert_eq!(find(DefinitionQuery::new().name("^create").regex(true)), vec![1]);
+        assert_eq!(find(DefinitionQuery::new().name("nothing, createinvoice")), vec![1]);
+        assert_eq!(find(DefinitionQuery::new().parent("invoiceservice")), vec![1]);
+        assert_eq!(find(DefinitionQuery::new().file("src\\billing")), vec![0, 1]);
+        assert_eq!(find(DefinitionQuery::new().exclude_dir(vec!["Tests".to_string()])), vec![0, 1]);
+        assert!(index.find(&DefinitionQuery::new().name("(").regex(true)).is_err());
+    }
+
+    #[test]
+    fn test_find_by_namespace_and_qualified_name() {
+        let index = sample_index();
+        let find = |query: DefinitionQuery| index.find(&query).unwrap();
+
+        assert_eq!(qualified_name(&index, &index.definitions[1]), "Shop.Billing.InvoiceService.CreateInvoice");
+        assert_eq!(find(DefinitionQuery::new().namespace("shop")), vec![0, 1]);
+        assert_eq!(find(DefinitionQuery::new().namespace("Shop.Bill")), Vec::<u32>::new());
+        assert_eq!(find(DefinitionQuery::new().name("Billing.InvoiceService")), vec![0]);
+        assert_eq!(find(DefinitionQuery::new().name("InvoiceService.CreateInvoice")), vec![1]);
+        assert_eq!(find(DefinitionQuery::new().name("Sales.InvoiceService")), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_load_migrates_version_1_call_sites() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("old.code-structure");
+        let call = CallSiteV1 { method_name: "GetUser".to_string(), receiver_type: Some("IUserService".to_string()), line: 7, receiver_is_generic: false };
+        let v1 = DefinitionIndexV1 {
+            root: "C:/repo".to_string(), created_at: 1, extensions: vec!["cs".to_string()],
+            files: vec!["C:/repo/a.cs".to_string()], definitions: Vec::new(),
+            name_index: HashMap::new(), kind_index: HashMap::new(), attribute_index: HashMap::new(),
+            base_type_index: HashMap::new(), file_index: HashMap::new(), path_to_id: HashMap::new(),
+            method_calls: HashMap::from([(3, vec![call])]),
+            parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), code_stats: HashMap::new(),
+            extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        };
+        crate::storage::write_index_file(&path, &v1, 1).unwrap();
+
+        let index = load(&path).unwrap();
+        assert_eq!(index.root, "C:/repo");
+        let migrated = &index.method_calls[&3][0];
+        assert_eq!((migrated.method_name.as_str(), migrated.line, migrated.column, migrated.receiver.as_deref()), ("GetUser", 7, 0, None));
+
+        // Saved again in the current version, and a newer one is refused
+        let current = tmp.path().join("new.code-structure");
+        crate::storage::write_index_file(&current, &index, DEFINITION_INDEX_VERSION).unwrap();
+        assert_eq!(load(&current).unwrap().method_calls[&3][0].line, 7);
+        crate::storage::write_index_file(&current, &index, DEFINITION_INDEX_VERSION + 1).unwrap();
+        let err = load(&current).unwrap_err().to_string();
+        assert!(err.contains("format version"), "got: {}", err);
+    }
+}
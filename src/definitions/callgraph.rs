@@ -0,0 +1,231 @@
+//! Call graph export: the call edges of whole classes or namespaces, or of a
+//! `search_callers` tree, as Graphviz DOT, Mermaid or a flat JSON edge list.
+//!
+//! Edges come from the call sites recorded per method (`method_calls`), resolved to
+//! definitions the same way `search_callers` direction=down resolves them. A file is in
+//! a namespace when it declares it (`namespace Billing.Invoices` or
+//! `namespace Billing.Invoices;`), as recorded in the index or, for files without
+//! an entry, read from disk.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+
+use serde_json::{json, Value};
+
+use super::deps::file_namespaces;
+use super::namespaces::is_within_namespace;
+use super::types::{DefinitionEntry, DefinitionIndex, DefinitionKind};
+use crate::mcp::handlers::resolve_call_site;
+use crate::read_file_lossy;
+
+/// Output of a call graph export (`--export` on the CLI, `exportFormat` in MCP).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz `digraph`
+    #[default]
+    Dot,
+    /// Mermaid `flowchart LR`
+    Mermaid,
+    /// `{nodes, edges}` with one edge per caller/callee pair
+    Json,
+}
+
+impl GraphFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dot => "dot",
+            Self::Mermaid => "mermaid",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            "json" => Ok(Self::Json),
+            other => Err(format!("Invalid exportFormat '{}'. Valid values: dot, mermaid, json", other)),
+        }
+    }
+}
+
+/// Calls from one method to another: `Class.Method` names, the number of call sites,
+/// and the first of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEdge {
+    pub caller: String,
+    pub callee: String,
+    pub calls: usize,
+    pub file: String,
+    pub line: u32,
+}
+
+/// Which methods [`scope_call_graph`] takes the calls of.
+#[derive(Debug, Clone, Default)]
+pub struct GraphScope {
+    /// Class names, case-insensitive
+    pub classes: Vec<String>,
+    /// Namespaces; nested namespaces are included (`Billing` covers `Billing.Invoices`)
+    pub namespaces: Vec<String>,
+    /// Keep only calls to methods that are in scope too
+    pub internal_only: bool,
+}
+
+impl GraphScope {
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty() && self.namespaces.is_empty()
+    }
+}
+
+/// Methods, constructors and functions: the definitions that make calls.
+pub(crate) fn is_callable(kind: &DefinitionKind) -> bool {
+    matches!(kind, DefinitionKind::Method | DefinitionKind::Constructor | DefinitionKind::Function)
+}
+
+/// `Class.Method`, or the bare name of a free function.
+pub(crate) fn qualified_name(def: &DefinitionEntry) -> String {
+    match &def.parent {
+        Some(parent) => format!("{}.{}", parent, def.name),
+        None => def.name.clone(),
+    }
+}
+
+fn in_namespace(declared: &[String], wanted: &[String]) -> bool {
+    declared.iter().any(|ns| wanted.iter().any(|w| is_within_namespace(ns, w)))
+}
+
+/// Call edges out of every method, constructor and function in `scope`, sorted by
+/// caller and callee.
+pub fn scope_call_graph(index: &DefinitionIndex, scope: &GraphScope) -> Vec<CallEdge> {
+    let classes: HashSet<String> = scope.classes.iter().map(|c| c.to_lowercase()).collect();
+    let mut namespace_files: HashMap<u32, bool> = HashMap::new();
+    let mut in_scope = |def: &DefinitionEntry| -> bool {
+        if def.parent.as_ref().is_some_and(|p| classes.contains(&p.to_lowercase())) {
+            return true;
+        }
+        !scope.namespaces.is_empty() && *namespace_files.entry(def.file_id).or_insert_with(|| {
+            if let Some(imports) = index.file_imports.get(&def.file_id) {
+                return in_namespace(&imports.namespaces, &scope.namespaces);
+            }
+            index.files.get(def.file_id as usize)
+                .and_then(|path| read_file_lossy(std::path::Path::new(path)).ok())
+                .is_some_and(|(content, _)| in_namespace(&file_namespaces(&content), &scope.namespaces))
+        })
+    };
+    let members: HashSet<u32> = index.definitions.iter().enumerate()
+        .filter(|(_, def)| is_callable(&def.kind) && in_scope(def))
+        .map(|(i, _)| i as u32)
+        .collect();
+
+    let mut edges: BTreeMap<(String, String), CallEdge> = BTreeMap::new();
+    for &caller_id in &members {
+        let caller = &index.definitions[caller_id as usize];
+        let Some(calls) = index.method_calls.get(&caller_id) else { continue };
+        let file = index.files.get(caller.file_id as usize).cloned().unwrap_or_default();
+        for call in calls {
+            for callee_id in resolve_call_site(call, index, caller.parent.as_deref()) {
+                if scope.internal_only && !members.contains(&callee_id) {
+                    continue;
+                }
+                let Some(callee) = index.definitions.get(callee_id as usize) else { continue };
+                let key = (qualified_name(caller), qualified_name(callee));
+                let edge = edges.entry(key.clone()).or_insert_with(|| CallEdge {
+                    caller: key.0, callee: key.1, calls: 0, file: file.clone(), line: call.line,
+                });
+                edge.calls += 1;
+                edge.line = edge.line.min(call.line);
+            }
+        }
+    }
+    edges.into_values().collect()
+}
+
+/// Edges of a `search_callers` `callTree` below `root`. Up trees point from each
+/// caller to the method it calls; down trees from the method to its callees.
+pub fn edges_from_call_tree(root: &str, tree: &[Value], up: bool) -> Vec<CallEdge> {
+    fn walk(parent: &str, nodes: &[Value], up: bool, edges: &mut Vec<CallEdge>) {
+        for node in nodes {
+            let Some(method) = node["method"].as_str() else { continue };
+            let name = match node["class"].as_str() {
+                Some(class) => format!("{}.{}", class, method),
+                None => method.to_string(),
+            };
+            let (caller, callee) = if up { (name.clone(), parent.to_string()) } else { (parent.to_string(), name.clone()) };
+            let line = node["callSite"].as_u64().or_else(|| node["callSiteLine"].as_u64()).unwrap_or(0) as u32;
+            match edges.iter_mut().find(|e| e.caller == caller && e.callee == callee) {
+                Some(edge) => edge.calls += 1,
+                None => edges.push(CallEdge {
+                    caller, callee, calls: 1, file: node["file"].as_str().unwrap_or_default().to_string(), line,
+                }),
+            }
+            let children = node[if up { "callers" } else { "callees" }].as_array();
+            walk(&name, children.map_or(&[][..], Vec::as_slice), up, edges);
+        }
+    }
+    let mut edges = Vec::new();
+    walk(root, tree, up, &mut edges);
+    edges
+}
+
+/// Node names in first-seen order.
+fn graph_nodes(edges: &[CallEdge]) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    edges.iter()
+        .flat_map(|e| [e.caller.as_str(), e.callee.as_str()])
+        .filter(|n| seen.insert(*n))
+        .collect()
+}
+
+/// `edges` as a Graphviz digraph. Repeated calls are labelled with their count.
+pub fn render_dot(edges: &[CallEdge]) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut out = String::from("digraph calls {\n  rankdir=LR;\n  node [shape=box];\n");
+    for edge in edges {
+        let _ = write!(out, "  {} -> {}", quote(&edge.caller), quote(&edge.callee));
+        if edge.calls > 1 {
+            let _ = write!(out, " [label=\"{}\"]", edge.calls);
+        }
+        out.push_str(";\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// `edges` as a Mermaid flowchart. Nodes get generated ids, since Mermaid ids cannot
+/// hold the `.` and generic brackets of method names.
+pub fn render_mermaid(edges: &[CallEdge]) -> String {
+    let nodes = graph_nodes(edges);
+    let ids: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+    let mut out = String::from("flowchart LR\n");
+    for (i, node) in nodes.iter().enumerate() {
+        let _ = writeln!(out, "  n{}[\"{}\"]", i, node.replace('"', "#quot;"));
+    }
+    for edge in edges {
+        let label = if edge.calls > 1 { format!("|{}|", edge.calls) } else { String::new() };
+        let _ = writeln!(out, "  n{} -->{} n{}", ids[edge.caller.as_str()], label, ids[edge.callee.as_str()]);
+    }
+    out
+}
+
+/// `edges` as `{nodes, edges}`: the flat edge list for other tools.
+pub fn call_graph_to_json(edges: &[CallEdge]) -> Value {
+    json!({
+        "nodes": graph_nodes(edges),
+        "edges": edges.iter().map(|e| json!({
+            "from": e.caller, "to": e.callee, "calls": e.calls, "file": e.file, "line": e.line,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// DOT or Mermaid text of `edges`; `None` for [`GraphFormat::Json`].
+pub fn render_call_graph(edges: &[CallEdge], format: GraphFormat) -> Option<String> {
+    match format {
+        GraphFormat::Dot => Some(render_dot(edges)),
+        GraphFormat::Mermaid => Some(render_mermaid(edges)),
+        GraphFormat::Json => None,
+    }
+}
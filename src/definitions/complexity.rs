@@ -0,0 +1,166 @@
+//! Halstead metrics, maintainability index and class-level aggregates (WMC, LCOM).
+//!
+//! Halstead counts come from the leaf tokens of a method body: named leaves
+//! (identifiers, literals, `this`) are operands, anonymous ones (keywords,
+//! punctuation, operators) are operators. String literals count as one operand, and a
+//! closing bracket is part of the operator its opening bracket starts.
+//!
+//! Class aggregates are computed on demand from the members' [`CodeStats`]: WMC is the
+//! sum of their cyclomatic complexities, LCOM the number of groups of methods that
+//! share no field, property or call (LCOM4; 1 is a cohesive class).
+
+use std::collections::{HashMap, HashSet};
+
+use super::types::{CodeStats, DefinitionIndex, DefinitionKind};
+
+/// Operators and operands of a body, distinct and total.
+#[derive(Debug, Default)]
+struct HalsteadCounts<'a> {
+    operators: HashMap<&'a str, u32>,
+    operands: HashMap<&'a str, u32>,
+}
+
+impl HalsteadCounts<'_> {
+    fn volume(&self) -> f32 {
+        let total: u32 = self.operators.values().sum::<u32>() + self.operands.values().sum::<u32>();
+        let distinct = self.operators.len() + self.operands.len();
+        if distinct < 2 {
+            return 0.0;
+        }
+        total as f32 * (distinct as f32).log2()
+    }
+
+    fn difficulty(&self) -> f32 {
+        if self.operands.is_empty() {
+            return 0.0;
+        }
+        let total_operands: u32 = self.operands.values().sum();
+        self.operators.len() as f32 / 2.0 * total_operands as f32 / self.operands.len() as f32
+    }
+}
+
+fn is_atomic_literal(kind: &str) -> bool {
+    kind.contains("string") || kind.ends_with("_literal")
+}
+
+fn walk_tokens<'a>(node: tree_sitter::Node, source: &'a [u8], counts: &mut HalsteadCounts<'a>) {
+    let kind = node.kind();
+    if kind == "comment" {
+        return;
+    }
+    if node.child_count() == 0 || (node.is_named() && is_atomic_literal(kind)) {
+        if node.is_named() {
+            let text = std::str::from_utf8(&source[node.start_byte()..node.end_byte()]).unwrap_or("");
+            *counts.operands.entry(text).or_default() += 1;
+        } else if !matches!(kind, ")" | "]" | "}") {
+            *counts.operators.entry(kind).or_default() += 1;
+        }
+        return;
+    }
+    for i in 0..node.child_count() {
+        if let Some(child) = node.child(i) {
+            walk_tokens(child, source, counts);
+        }
+    }
+}
+
+/// Maintainability index on the 0–100 scale: `(171 − 5.2·ln V − 0.23·CC − 16.2·ln LOC) · 100 / 171`,
+/// clamped at 0. Below 10 is hard to maintain, 10–19 moderate.
+pub fn maintainability_index(volume: f32, cyclomatic: u16, lines: u32) -> u8 {
+    let raw = 171.0 - 5.2 * volume.max(1.0).ln() - 0.23 * cyclomatic as f32 - 16.2 * (lines.max(1) as f32).ln();
+    (raw * 100.0 / 171.0).clamp(0.0, 100.0).round() as u8
+}
+
+/// Fill the Halstead volume and difficulty of `body` and the maintainability index of
+/// a definition spanning `lines` into `stats` (cyclomatic complexity already counted).
+/// Returns the operands of the body, for [`members_used`].
+pub(crate) fn record_halstead<'a>(stats: &mut CodeStats, body: Option<tree_sitter::Node>, source: &'a [u8], lines: u32) -> HashSet<&'a str> {
+    let mut counts = HalsteadCounts::default();
+    if let Some(body) = body {
+        walk_tokens(body, source, &mut counts);
+    }
+    stats.halstead_volume = counts.volume();
+    stats.halstead_difficulty = counts.difficulty();
+    stats.maintainability_index = maintainability_index(stats.halstead_volume, stats.cyclomatic_complexity, lines);
+    counts.operands.into_keys().collect()
+}
+
+/// The `members` of the enclosing class (fields, properties, methods) other than
+/// `name` itself that occur among `operands`, sorted.
+pub(crate) fn members_used(operands: &HashSet<&str>, members: &HashSet<&str>, name: &str) -> Vec<String> {
+    let mut used: Vec<String> = members.iter()
+        .filter(|m| **m != name && operands.contains(*m))
+        .map(|m| m.to_string())
+        .collect();
+    used.sort();
+    used
+}
+
+/// Class-level aggregates of one type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassMetrics {
+    /// Members with code stats: methods, constructors, properties with bodies
+    pub members: u32,
+    /// Weighted methods per class: the sum of the members' cyclomatic complexities
+    pub wmc: u32,
+    /// Lack of cohesion (LCOM4): connected groups of methods; 0 without methods
+    pub lcom: u32,
+}
+
+/// WMC and LCOM of the class, struct or record `def_id`, over all parts of a partial
+/// type. None for other kinds and types without members that have code stats.
+pub fn class_metrics(index: &DefinitionIndex, def_id: u32) -> Option<ClassMetrics> {
+    let def = index.definitions.get(def_id as usize)?;
+    if !matches!(def.kind, DefinitionKind::Class | DefinitionKind::Struct | DefinitionKind::Record) {
+        return None;
+    }
+    let parts: Vec<u32> = index.partial_types.get(&def_id).map_or_else(|| vec![def_id], |p| p.parts.clone());
+    let mut members: Vec<u32> = Vec::new();
+    for &part in &parts {
+        let part = &index.definitions[part as usize];
+        members.extend(index.file_index.get(&part.file_id).into_iter().flatten().copied().filter(|&id| {
+            let m = &index.definitions[id as usize];
+            m.parent.as_deref() == Some(def.name.as_str())
+                && m.line_start >= part.line_start && m.line_end <= part.line_end
+        }));
+    }
+
+    let mut metrics = ClassMetrics { members: 0, wmc: 0, lcom: 0 };
+    let mut methods: Vec<(&str, &[String])> = Vec::new();
+    for &id in &members {
+        let Some(stats) = index.code_stats.get(&id) else { continue };
+        metrics.members += 1;
+        metrics.wmc += stats.cyclomatic_complexity as u32;
+        let m = &index.definitions[id as usize];
+        if m.kind == DefinitionKind::Method {
+            methods.push((m.name.as_str(), &stats.members_used));
+        }
+    }
+    if metrics.members == 0 {
+        return None;
+    }
+
+    // Union-find over methods: linked by a shared member or a call to each other
+    let mut group: Vec<usize> = (0..methods.len()).collect();
+    fn root(group: &mut [usize], mut i: usize) -> usize {
+        while group[i] != i {
+            group[i] = group[group[i]];
+            i = group[i];
+        }
+        i
+    }
+    let mut first_user: HashMap<&str, usize> = HashMap::new();
+    for (i, (name, used)) in methods.iter().enumerate() {
+        for key in used.iter().map(String::as_str).chain(std::iter::once(*name)) {
+            match first_user.get(key) {
+                Some(&j) => {
+                    let (a, b) = (root(&mut group, i), root(&mut group, j));
+                    group[a] = b;
+                }
+                None => { first_user.insert(key, i); }
+            }
+        }
+    }
+    metrics.lcom = (0..methods.len()).filter(|&i| root(&mut group, i) == i).count() as u32;
+    Some(metrics)
+}
@@ -0,0 +1,172 @@
+//! Dead-code detection: private methods and unexported functions that nothing references.
+//!
+//! Such a definition can only be called from its own file (or, for a C# partial type,
+//! from the files of the other parts), so those files are all that is read. A candidate
+//! is dead when its name appears as a whole word nowhere but on its declaration line.
+//! Mentions in comments and strings count as references: the check errs on the side of
+//! not reporting.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use super::types::{DefinitionEntry, DefinitionIndex, DefinitionKind};
+
+/// Filters for [`find_dead_code`].
+#[derive(Debug, Clone, Default)]
+pub struct DeadCodeOptions {
+    /// Only scan definitions whose file path contains this substring.
+    pub file_filter: Option<String>,
+    /// Skip definitions whose file path contains any of these substrings.
+    pub exclude_dir: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct DeadCodeReport {
+    /// def_idx of each unreferenced definition, by file path and line.
+    pub dead: Vec<u32>,
+    pub candidates_scanned: usize,
+    /// Candidates skipped because one of their files could not be read
+    pub files_unreadable: usize,
+}
+
+/// Modifiers that make a method reachable from outside its file, or from the runtime.
+const REACHABLE_MODIFIERS: &[&str] = &["public", "protected", "internal", "override", "virtual", "abstract", "extern", "partial", "export"];
+
+/// Whether only its own file (or partial type) can call `def`: a `private` method, a C#
+/// method with no access modifier in a class, struct or record, or a top-level function
+/// that is not exported. Attributed definitions are skipped, since attributes usually
+/// mean a framework calls them, as are C# entry points and explicit interface members.
+fn is_candidate(index: &DefinitionIndex, def_idx: u32, path: &str) -> bool {
+    let def = &index.definitions[def_idx as usize];
+    if !def.attributes.is_empty() || def.modifiers.iter().any(|m| REACHABLE_MODIFIERS.contains(&m.as_str())) {
+        return false;
+    }
+    match def.kind {
+        DefinitionKind::Function => def.parent.is_none(),
+        DefinitionKind::Method if def.modifiers.iter().any(|m| m == "private") => true,
+        DefinitionKind::Method if path.to_lowercase().ends_with(".cs") => {
+            let explicit_interface = def.signature.as_ref().is_some_and(|s| s.contains(&format!(".{}(", def.name)));
+            def.name != "Main" && !explicit_interface
+                && enclosing_type(index, def_idx).is_some_and(|t| matches!(
+                    index.definitions[t as usize].kind,
+                    DefinitionKind::Class | DefinitionKind::Struct | DefinitionKind::Record))
+        }
+        _ => false,
+    }
+}
+
+/// def_idx of the innermost type declared around `def_idx` in the same file.
+fn enclosing_type(index: &DefinitionIndex, def_idx: u32) -> Option<u32> {
+    let def = &index.definitions[def_idx as usize];
+    index.file_index.get(&def.file_id)?.iter().copied()
+        .filter(|&t| t != def_idx)
+        .filter(|&t| {
+            let outer = &index.definitions[t as usize];
+            matches!(outer.kind, DefinitionKind::Class | DefinitionKind::Struct | DefinitionKind::Record | DefinitionKind::Interface)
+                && outer.line_start <= def.line_start && outer.line_end >= def.line_end
+        })
+        .max_by_key(|&t| index.definitions[t as usize].line_start)
+}
+
+/// file_ids that can reference `def_idx`: its own file, plus the files of the other
+/// parts of its enclosing partial type.
+fn scope_files(index: &DefinitionIndex, def_idx: u32) -> Vec<u32> {
+    let file_id = index.definitions[def_idx as usize].file_id;
+    let mut files = vec![file_id];
+    if let Some(partial) = enclosing_type(index, def_idx).and_then(|t| index.partial_types.get(&t)) {
+        files.extend(partial.parts.iter().map(|&p| index.definitions[p as usize].file_id));
+    }
+    files.sort_unstable();
+    files.dedup();
+    files
+}
+
+/// Text of a file and its whole-word counts.
+type FileWords = (String, HashMap<String, usize>);
+
+/// Whole-word identifier occurrences in `text`, by word.
+fn word_counts(text: &str) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for word in text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).filter(|w| !w.is_empty()) {
+        *counts.entry(word).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Occurrences of `name` on the first line of `def` that declares it.
+fn declaration_occurrences(lines: &[&str], def: &DefinitionEntry) -> usize {
+    let start = (def.line_start as usize).saturating_sub(1);
+    let end = (def.line_end as usize).min(lines.len());
+    lines.get(start..end).unwrap_or(&[]).iter()
+        .map(|line| word_counts(line).get(def.name.as_str()).copied().unwrap_or(0))
+        .find(|&n| n > 0)
+        .unwrap_or(0)
+}
+
+/// Find private methods and unexported functions whose name is not mentioned outside
+/// their declaration line in any file that could call them. Reads each of those files
+/// once; candidates whose files cannot all be read are counted and skipped.
+pub fn find_dead_code(index: &DefinitionIndex, opts: &DeadCodeOptions) -> DeadCodeReport {
+    let file_filter = opts.file_filter.as_ref().map(|f| f.replace('\\', "/").to_lowercase());
+    let exclude: Vec<String> = opts.exclude_dir.iter().map(|e| e.to_lowercase()).collect();
+
+    let mut candidates: Vec<u32> = (0..index.definitions.len() as u32)
+        .filter(|&i| {
+            let Some(path) = index.files.get(index.definitions[i as usize].file_id as usize) else { return false };
+            let lower = path.replace('\\', "/").to_lowercase();
+            file_filter.as_ref().is_none_or(|f| lower.contains(f.as_str()))
+                && !exclude.iter().any(|e| lower.contains(e.as_str()))
+                && is_candidate(index, i, path)
+        })
+        .collect();
+    candidates.sort_by(|&a, &b| {
+        let (da, db) = (&index.definitions[a as usize], &index.definitions[b as usize]);
+        index.files[da.file_id as usize].cmp(&index.files[db.file_id as usize])
+            .then(da.line_start.cmp(&db.line_start))
+    });
+
+    let mut report = DeadCodeReport { candidates_scanned: candidates.len(), ..Default::default() };
+    // file_id -> None when unreadable
+    let mut files_read: HashMap<u32, Option<FileWords>> = HashMap::new();
+    for &def_idx in &candidates {
+        let def = &index.definitions[def_idx as usize];
+        let files = scope_files(index, def_idx);
+        for &file_id in &files {
+            files_read.entry(file_id).or_insert_with(|| {
+                crate::read_file_lossy(Path::new(&index.files[file_id as usize])).ok().map(|(text, _)| {
+                    let counts = word_counts(&text).into_iter().map(|(w, n)| (w.to_string(), n)).collect();
+                    (text, counts)
+                })
+            });
+        }
+        if files.iter().any(|f| files_read[f].is_none()) {
+            report.files_unreadable += 1;
+            continue;
+        }
+        let mentions: usize = files.iter()
+            .filter_map(|f| files_read[f].as_ref()?.1.get(&def.name))
+            .sum();
+        let own: Vec<&str> = files_read[&def.file_id].as_ref().map_or("", |(text, _)| text.as_str()).lines().collect();
+        if mentions <= declaration_occurrences(&own, def) {
+            report.dead.push(def_idx);
+        }
+    }
+    report
+}
+
+/// JSON of one unreferenced definition, as the `search deadcode --json` output lists it.
+pub fn dead_code_to_json(index: &DefinitionIndex, def_idx: u32) -> Value {
+    let def = &index.definitions[def_idx as usize];
+    let mut obj = json!({
+        "name": def.name,
+        "kind": def.kind.as_str(),
+        "file": index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or(""),
+        "lines": format!("{}-{}", def.line_start, def.line_end),
+    });
+    if let Some(ref parent) = def.parent {
+        obj["parent"] = json!(parent);
+    }
+    obj
+}
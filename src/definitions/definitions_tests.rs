@@ -64,7 +64,7 @@ fn test_definition_index_build_and_search() {
     std::fs::write(dir.join("test.cs"), "public class TestClass : BaseClass { public void TestMethod() {} }").unwrap();
     std::fs::write(dir.join("test.sql"), "CREATE TABLE TestTable (Id INT NOT NULL)").unwrap();
 
-    let args = DefIndexArgs { dir: dir.to_string_lossy().to_string(), ext: "cs,sql".to_string(), threads: 1 };
+    let args = DefIndexArgs { dir: dir.to_string_lossy().to_string(), ext: "cs,sql".to_string(), threads: 1, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new() };
     let index = build_definition_index(&args);
 
     assert_eq!(index.files.len(), 2);
@@ -91,6 +91,13 @@ fn test_definition_index_serialization() {
         attribute_index: HashMap::new(), base_type_index: HashMap::new(),
         file_index: { let mut m = HashMap::new(); m.insert(0, vec![0]); m },
         path_to_id: HashMap::new(), method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let encoded = bincode::serialize(&index).unwrap();
@@ -131,6 +138,24 @@ fn test_read_file_lossy_with_non_utf8_byte() {
     assert!(result.contains('\u{FFFD}'), "Should contain replacement character");
 }
 
+#[test]
+fn test_looks_binary_after_lossy_read() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::write(dir.join("blob.cs"), b"MZ\x90\x00\x03\x00class").unwrap();
+    // UTF-16LE with a BOM: every other raw byte is 0, but it decodes to text
+    let mut utf16 = vec![0xFF, 0xFE];
+    utf16.extend("public class Wide {}".encode_utf16().flat_map(|u| u.to_le_bytes()));
+    std::fs::write(dir.join("wide.cs"), &utf16).unwrap();
+
+    let (blob, _) = search::read_file_lossy(&dir.join("blob.cs")).unwrap();
+    let (wide, _) = search::read_file_lossy(&dir.join("wide.cs")).unwrap();
+    assert!(search::looks_binary(&blob));
+    assert!(!search::looks_binary(&wide), "decoded UTF-16 is text");
+    // Only the first BINARY_SNIFF_BYTES are inspected
+    assert!(!search::looks_binary(&format!("{}\0", "x".repeat(search::BINARY_SNIFF_BYTES))));
+}
+
 
 // ─── Lazy Parser Init & Extension Filtering Tests ─────────────────────
 
@@ -148,6 +173,13 @@ fn test_build_def_index_cs_only_no_ts_parsers() {
         dir: dir.to_string_lossy().to_string(),
         ext: "cs".to_string(), // only C#
         threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
     });
 
     // Should find the C# class and method but NOT the TypeScript function
@@ -156,6 +188,69 @@ fn test_build_def_index_cs_only_no_ts_parsers() {
     assert!(!idx.name_index.contains_key("helper"), "Should NOT find TS function when ext=cs");
 }
 
+#[test]
+fn test_build_def_index_parses_extensionless_scripts_by_detected_language() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::create_dir_all(dir.join("tools")).unwrap();
+    std::fs::write(dir.join("serve"), "#!/usr/bin/env -S deno run\nexport function startServer(): void {}\n").unwrap();
+    std::fs::write(dir.join("tools/gen"), "export function generateClient(): void {}\n").unwrap();
+    std::fs::write(dir.join("notes"), "export function notCode(): void {}\n").unwrap();
+    std::fs::write(dir.join(crate::config::CONFIG_FILE_NAME), "[languages]\n\"tools/*\" = \"ts\"\n").unwrap();
+
+    let idx = build_definition_index(&DefIndexArgs {
+        dir: dir.to_string_lossy().to_string(),
+        ext: "ts".to_string(),
+        threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+
+    assert!(idx.name_index.contains_key("startserver"), "deno shebang is parsed as TypeScript");
+    assert!(idx.name_index.contains_key("generateclient"), "config override applies");
+    assert!(!idx.name_index.contains_key("notcode"), "no shebang, no override");
+}
+
+#[test]
+fn test_generated_corpus_parses_completely() {
+    // Every class and method gen-corpus writes is found, and dependency calls resolve
+    let tmp = tempfile::tempdir().unwrap();
+    let spec = search::corpus::CorpusSpec {
+        files: 30,
+        vocabulary: 300,
+        languages: vec![search::corpus::CorpusLanguage::CSharp, search::corpus::CorpusLanguage::TypeScript],
+        files_per_dir: 10,
+        ..Default::default()
+    };
+    let stats = search::corpus::generate_corpus(tmp.path(), &spec).unwrap();
+
+    let idx = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(),
+        ext: "cs,ts".to_string(),
+        threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+
+    let count = |kind: DefinitionKind| idx.kind_index.get(&kind).map_or(0, |ids| ids.len());
+    assert_eq!(idx.files.len(), stats.files);
+    assert!(idx.empty_file_ids.is_empty());
+    assert_eq!(count(DefinitionKind::Class), stats.classes);
+    assert_eq!(count(DefinitionKind::Method), stats.methods);
+    assert!(idx.method_calls.values().map(Vec::len).sum::<usize>() > stats.methods,
+        "method bodies make calls");
+}
+
 #[test]
 fn test_build_def_index_cs_and_ts() {
     // When ext="cs,ts", both C# and TS files should be parsed
@@ -169,6 +264,13 @@ fn test_build_def_index_cs_and_ts() {
         dir: dir.to_string_lossy().to_string(),
         ext: "cs,ts".to_string(),
         threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
     });
 
     assert!(idx.name_index.contains_key("userservice"), "Should find C# class");
@@ -188,9 +290,609 @@ fn test_build_def_index_ts_only() {
         dir: dir.to_string_lossy().to_string(),
         ext: "ts".to_string(),
         threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
     });
 
     assert!(!idx.name_index.contains_key("userservice"), "Should NOT find C# class when ext=ts");
     assert!(idx.name_index.contains_key("appcontroller"), "Should find TS class");
     assert!(idx.name_index.contains_key("run"), "Should find TS method");
 }
+
+// ─── Outline Tests ──────────────────────────────────────────────────
+
+fn make_outline_index() -> DefinitionIndex {
+    let mut index = DefinitionIndex {
+        files: vec!["C:/repo/src/Services/UserService.cs".to_string(), "C:/repo/tests/UserService.cs".to_string()],
+        ..Default::default()
+    };
+    // Deliberately out of line order: outline must sort by position
+    for (name, kind, start, end) in [
+        ("GetUser", DefinitionKind::Method, 10, 15),
+        ("UserService", DefinitionKind::Class, 3, 40),
+        ("Cache", DefinitionKind::Class, 20, 35),
+        ("_db", DefinitionKind::Field, 5, 5),
+        ("Get", DefinitionKind::Method, 22, 30),
+        ("Helper", DefinitionKind::Class, 45, 50),
+    ] {
+        let idx = index.definitions.len() as u32;
+        index.definitions.push(DefinitionEntry {
+            file_id: 0, name: name.to_string(), kind, line_start: start, line_end: end,
+            parent: None, signature: None, modifiers: Vec::new(), attributes: Vec::new(), base_types: Vec::new(),
+        });
+        index.file_index.entry(0).or_default().push(idx);
+    }
+    index
+}
+
+#[test]
+fn test_build_outline_nests_by_line_range() {
+    let index = make_outline_index();
+    let nodes = build_outline(&index, 0);
+    let names: Vec<&str> = nodes.iter().map(|n| n.def.name.as_str()).collect();
+    assert_eq!(names, vec!["UserService", "Helper"]);
+    let members: Vec<&str> = nodes[0].children.iter().map(|n| n.def.name.as_str()).collect();
+    assert_eq!(members, vec!["_db", "GetUser", "Cache"]);
+    assert_eq!(nodes[0].children[2].children[0].def.name, "Get");
+    assert_eq!(outline_len(&nodes), 6);
+}
+
+#[test]
+fn test_outline_text_and_json() {
+    let index = make_outline_index();
+    let nodes = build_outline(&index, 0);
+    let text = render_outline_text(&nodes);
+    assert!(text.starts_with("class UserService [3-40]\n  field _db [5-5]\n"), "{}", text);
+    assert!(text.contains("\n    method Get [22-30]\n"), "{}", text);
+
+    let json = outline_to_json(&nodes);
+    assert_eq!(json[0]["lines"], "3-40");
+    assert_eq!(json[0]["children"][2]["children"][0]["name"], "Get");
+    assert!(json[1].get("children").is_none());
+}
+
+#[test]
+fn test_resolve_file_id_suffix_and_ambiguity() {
+    let index = make_outline_index();
+    assert_eq!(resolve_file_id(&index, "Services/UserService.cs"), Ok(0));
+    assert_eq!(resolve_file_id(&index, r"tests\userservice.cs"), Ok(1));
+    assert_eq!(resolve_file_id(&index, "C:/repo/src/Services/UserService.cs"), Ok(0));
+    let err = resolve_file_id(&index, "UserService.cs").unwrap_err();
+    assert!(err.contains("matches 2 indexed files"), "{}", err);
+    assert!(resolve_file_id(&index, "Missing.cs").unwrap_err().contains("not in the definition index"));
+}
+
+#[test]
+fn test_build_outline_file_without_definitions() {
+    let index = make_outline_index();
+    assert!(build_outline(&index, 1).is_empty());
+}
+
+// ─── Call Graph Tests ───────────────────────────────────────────────
+
+#[test]
+fn test_scope_call_graph_for_classes_and_namespaces() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::write(dir.join("Orders.cs"), r#"namespace Shop.Orders
+{
+    public class OrderService
+    {
+        private readonly OrderRepository _repo;
+        public void Place(Order o)
+        {
+            Validate(o);
+            _repo.Save(o);
+            _repo.Save(o);
+        }
+        private void Validate(Order o) { }
+    }
+}
+"#).unwrap();
+    std::fs::write(dir.join("Repo.cs"), "namespace Shop.Data;\n\npublic class OrderRepository\n{\n    public void Save(Order o) { Flush(); }\n    private void Flush() { }\n}\n").unwrap();
+    let index = build_definition_index(&DefIndexArgs {
+        dir: dir.to_string_lossy().to_string(),
+        ext: "cs".to_string(),
+        threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+
+    let scope = GraphScope { classes: vec!["orderservice".to_string()], ..Default::default() };
+    let edges = scope_call_graph(&index, &scope);
+    let pairs: Vec<(&str, &str, usize)> = edges.iter().map(|e| (e.caller.as_str(), e.callee.as_str(), e.calls)).collect();
+    assert_eq!(pairs, [("OrderService.Place", "OrderRepository.Save", 2), ("OrderService.Place", "OrderService.Validate", 1)]);
+    assert_eq!(edges[0].line, 9);
+
+    let internal = GraphScope { internal_only: true, ..scope };
+    assert_eq!(scope_call_graph(&index, &internal).len(), 1);
+    // 'Shop' covers both files; 'Shop.Data' only the repository
+    let shop = GraphScope { namespaces: vec!["Shop".to_string()], ..Default::default() };
+    assert_eq!(scope_call_graph(&index, &shop).len(), 3);
+    let data = GraphScope { namespaces: vec!["Shop.Data".to_string()], ..Default::default() };
+    let data_edges = scope_call_graph(&index, &data);
+    assert_eq!(data_edges.len(), 1);
+    assert_eq!(data_edges[0].callee, "OrderRepository.Flush");
+    assert!(scope_call_graph(&index, &GraphScope { namespaces: vec!["Sho".to_string()], ..Default::default() }).is_empty());
+
+    assert_eq!(render_dot(&edges), "digraph calls {\n  rankdir=LR;\n  node [shape=box];\n  \"OrderService.Place\" -> \"OrderRepository.Save\" [label=\"2\"];\n  \"OrderService.Place\" -> \"OrderService.Validate\";\n}\n");
+    assert_eq!(render_mermaid(&edges), "flowchart LR\n  n0[\"OrderService.Place\"]\n  n1[\"OrderRepository.Save\"]\n  n2[\"OrderService.Validate\"]\n  n0 -->|2| n1\n  n0 --> n2\n");
+    assert_eq!(call_graph_to_json(&edges)["edges"][0]["from"], "OrderService.Place");
+}
+
+#[test]
+fn test_test_matcher_attributes_and_paths() {
+    let def = |attributes: &[&str]| DefinitionEntry {
+        file_id: 0, name: "Saves".to_string(), kind: DefinitionKind::Method, line_start: 1, line_end: 1,
+        parent: Some("OrderTests".to_string()), signature: None, modifiers: vec![],
+        attributes: attributes.iter().map(|a| a.to_string()).collect(), base_types: vec![],
+    };
+    let matcher = TestMatcher::default();
+    assert!(matcher.is_test(&def(&["TestCase(1, 2)"]), "src/Orders/OrderTests.cs"));
+    assert!(matcher.is_test(&def(&["FactAttribute"]), "src/Orders/Checks.cs"));
+    assert!(matcher.is_test(&def(&[]), "tests/Orders/Checks.cs"));
+    assert!(!matcher.is_test(&def(&["HttpGet"]), "src/Orders/Checks.cs"));
+
+    let custom = TestMatcher { attributes: vec!["Scenario".to_string()], paths: vec!["Specs\\".to_string()] };
+    assert!(custom.is_test(&def(&["scenario"]), "src/Checks.cs"));
+    assert!(custom.is_test(&def(&[]), "app/specs/Checks.cs"));
+    // Custom paths replace the built-in heuristic
+    assert!(!custom.is_test(&def(&["Fact"]), "tests/Checks.cs"));
+}
+
+#[test]
+fn test_file_dependency_graph_imports_and_cycles() {
+    let cs = extract_file_imports("cs", "global using System;\nusing static Shop.Util.Math;\nusing Db = Shop.Data;\nnamespace Shop.Orders;\n");
+    assert_eq!(cs.imports, ["System", "Shop.Util.Math", "Shop.Data"]);
+    assert_eq!(cs.namespaces, ["Shop.Orders"]);
+    let ts = extract_file_imports("ts", "import { A } from './a';\nimport type { B } from \"../b/b\";\nimport './side-effect';\nexport * from './c';\nconst x = 'from';\n");
+    assert_eq!(ts.imports, ["./a", "../b/b", "./side-effect", "./c"]);
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::create_dir(dir.join("a")).unwrap();
+    std::fs::write(dir.join("a/orders.ts"), "import { Repo } from '../repo';\nimport * as _ from 'lodash';\nexport class Orders { }\n").unwrap();
+    std::fs::write(dir.join("repo.ts"), "import { Orders } from './a/orders';\nexport class Repo { }\n").unwrap();
+    std::fs::write(dir.join("util.ts"), "import { Repo } from './repo';\nexport function helper() { }\n").unwrap();
+    let mut index = build_definition_index(&DefIndexArgs {
+        dir: dir.to_string_lossy().to_string(),
+        ext: "ts".to_string(),
+        threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let id = |name: &str| index.files.iter().position(|f| f.ends_with(name)).unwrap() as u32;
+    let (orders, repo, util) = (id("orders.ts"), id("repo.ts"), id("util.ts"));
+
+    let graph = DependencyGraph::build(&index);
+    assert_eq!(graph.dependencies(orders).iter().map(|(f, _)| *f).collect::<Vec<_>>(), [repo]);
+    assert!(graph.dependencies(orders)[0].1.imports);
+    assert_eq!(graph.external[&orders], ["lodash"]);
+    let mut dependents: Vec<u32> = graph.dependents(repo).iter().map(|(f, _)| *f).collect();
+    dependents.sort_unstable();
+    let mut expected = vec![orders, util];
+    expected.sort_unstable();
+    assert_eq!(dependents, expected);
+    assert_eq!(graph.cycle_through(repo), Some(vec![repo, orders, repo]));
+    assert_eq!(graph.cycle_through(util), None);
+    let mut group = vec![orders, repo];
+    group.sort_unstable();
+    assert_eq!(graph.cycles(), [group]);
+
+    // Removing a file drops its imports, and the cycle with them
+    remove_file_from_def_index(&mut index, &dir.join("repo.ts"));
+    assert!(!index.file_imports.contains_key(&repo));
+    assert!(DependencyGraph::build(&index).cycles().is_empty());
+}
+
+// ─── Type Hierarchy Tests ───────────────────────────────────────────
+
+fn make_hierarchy_index() -> DefinitionIndex {
+    let mut index = DefinitionIndex {
+        files: vec!["C:/repo/src/Repos.cs".to_string()],
+        ..Default::default()
+    };
+    for (name, kind, bases) in [
+        ("IRepository", DefinitionKind::Interface, vec![]),
+        ("BaseRepository", DefinitionKind::Class, vec!["Data.IRepository<T>", "IDisposable"]),
+        ("UserRepository", DefinitionKind::Class, vec!["BaseRepository<User>"]),
+        ("CachedUserRepository", DefinitionKind::Class, vec!["UserRepository"]),
+        ("OrderRepository", DefinitionKind::Class, vec!["BaseRepository<Order>"]),
+        // Inheritance cycle (only possible via name collisions): walks must terminate
+        ("LoopA", DefinitionKind::Class, vec!["LoopB"]),
+        ("LoopB", DefinitionKind::Class, vec!["LoopA"]),
+        ("UserRepository", DefinitionKind::Method, vec![]),
+    ] {
+        let idx = index.definitions.len() as u32;
+        let line = idx * 10 + 1;
+        index.definitions.push(DefinitionEntry {
+            file_id: 0, name: name.to_string(), kind, line_start: line, line_end: line + 5,
+            parent: None, signature: None, modifiers: Vec::new(), attributes: Vec::new(),
+            base_types: bases.iter().map(|s| s.to_string()).collect(),
+        });
+        index.name_index.entry(name.to_lowercase()).or_default().push(idx);
+        for bt in bases {
+            index.base_type_index.entry(bt.to_lowercase()).or_default().push(idx);
+        }
+    }
+    index
+}
+
+#[test]
+fn test_base_type_name_strips_generics_and_namespace() {
+    assert_eq!(base_type_name("IRepository<User>"), "IRepository");
+    assert_eq!(base_type_name("Data.Core.IRepository<Dictionary<string, int>>"), "IRepository");
+    assert_eq!(base_type_name("ControllerBase"), "ControllerBase");
+}
+
+#[test]
+fn test_hierarchy_subtypes_recursive_with_depth() {
+    let index = make_hierarchy_index();
+    let root = find_type_definitions(&index, "IRepository");
+    assert_eq!(root, vec![0]);
+
+    let mut walker = HierarchyWalker::new(&index, 10, 100);
+    let nodes = walker.subtypes("IRepository", Some(0));
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].name, "BaseRepository");
+    let second: Vec<&str> = nodes[0].children.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(second, vec!["OrderRepository", "UserRepository"]);
+    assert_eq!(nodes[0].children[1].children[0].name, "CachedUserRepository");
+    assert_eq!(hierarchy_len(&nodes), 4);
+    assert!(!walker.truncated);
+
+    let mut shallow = HierarchyWalker::new(&index, 2, 100);
+    assert_eq!(hierarchy_len(&shallow.subtypes("IRepository", Some(0))), 3);
+
+    let mut limited = HierarchyWalker::new(&index, 10, 2);
+    assert_eq!(hierarchy_len(&limited.subtypes("IRepository", Some(0))), 2);
+    assert!(limited.truncated);
+}
+
+#[test]
+fn test_hierarchy_supertypes_and_external_bases() {
+    let index = make_hierarchy_index();
+    let mut walker = HierarchyWalker::new(&index, 10, 100);
+    let nodes = walker.supertypes(3);
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].name, "UserRepository");
+    let base = &nodes[0].children[0];
+    assert_eq!(base.name, "BaseRepository");
+    let bases: Vec<(&str, bool)> = base.children.iter().map(|n| (n.name.as_str(), n.def.is_some())).collect();
+    assert_eq!(bases, vec![("IRepository", true), ("IDisposable", false)]);
+
+    let json = hierarchy_to_json(&index, &nodes);
+    assert_eq!(json[0]["children"][0]["children"][1]["external"], true);
+    assert_eq!(json[0]["kind"], "class");
+    let text = render_hierarchy_text(&index, &nodes);
+    assert!(text.starts_with("  class UserRepository  C:/repo/src/Repos.cs:21\n"), "{}", text);
+    assert!(text.contains("      IDisposable (external)\n"), "{}", text);
+
+    let cycle = walker.supertypes(5);
+    assert_eq!(hierarchy_len(&cycle), 1);
+    assert_eq!(cycle[0].name, "LoopB");
+}
+
+// ─── Duplicate Detection Tests ──────────────────────────────────────
+
+const DUPE_BODY: &str = "
+    {
+        var total = 0;
+        foreach (var order in orders)
+        {
+            if (order.IsPaid && order.Amount > 0)
+                total += order.Amount * rate;
+        }
+        logger.Info(\"Computed total \" + total);
+        return total;
+    }
+";
+
+fn build_dupes_index(tmp: &std::path::Path) -> DefinitionIndex {
+    std::fs::write(tmp.join("Billing.cs"), format!(
+        "class Billing\n{{\n    int Sum(List<Order> orders, int rate){}\n    void Other() {{ Console.WriteLine(1); }}\n}}\n", DUPE_BODY)).unwrap();
+    // Same body, different name and formatting
+    std::fs::write(tmp.join("Invoices.cs"), format!(
+        "class Invoices\n{{\n    int   Total( List<Order> orders,int rate ){}\n}}\n", DUPE_BODY.replace("        ", "            "))).unwrap();
+    std::fs::write(tmp.join("Unrelated.cs"),
+        "class Unrelated\n{\n    void Run()\n    {\n        var client = new HttpClient();\n        client.Timeout = TimeSpan.FromSeconds(30);\n        client.Send(request);\n        Dispose();\n    }\n}\n").unwrap();
+    build_definition_index(&DefIndexArgs {
+        dir: tmp.to_string_lossy().to_string(),
+        ext: "cs".to_string(),
+        threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    })
+}
+
+#[test]
+fn test_body_shingles() {
+    assert_eq!(body_shingles("a b c d", 2).len(), 3);
+    assert_eq!(body_shingles("a b\nc d", 2), body_shingles("a  b c,d", 2));
+    assert!(body_shingles("a b", 3).is_empty());
+}
+
+#[test]
+fn test_find_duplicates_reports_clone_pair() {
+    let tmp = tempfile::tempdir().unwrap();
+    let index = build_dupes_index(tmp.path());
+    let report = find_duplicates(&index, &DupeOptions::default());
+    assert_eq!(report.pairs.len(), 1, "{:?}", report.pairs);
+    let pair = &report.pairs[0];
+    let names = (index.definitions[pair.a as usize].name.as_str(), index.definitions[pair.b as usize].name.as_str());
+    // Location order: Billing.cs before Invoices.cs
+    assert_eq!(names, ("Sum", "Total"));
+    assert!(pair.similarity > 0.8, "{}", pair.similarity);
+
+    let json = clone_pair_to_json(&index, pair);
+    assert!(json["a"]["file"].as_str().unwrap().ends_with("Billing.cs"));
+    assert_eq!(json["a"]["parent"], "Billing");
+    assert!(json["b"]["lines"].as_str().unwrap().starts_with("3-"));
+}
+
+#[test]
+fn test_find_duplicates_filters() {
+    let tmp = tempfile::tempdir().unwrap();
+    let index = build_dupes_index(tmp.path());
+    let only_billing = DupeOptions { file_filter: Some("Billing".to_string()), ..Default::default() };
+    assert!(find_duplicates(&index, &only_billing).pairs.is_empty());
+    let excluded = DupeOptions { exclude_dir: vec!["invoices".to_string()], ..Default::default() };
+    assert!(find_duplicates(&index, &excluded).pairs.is_empty());
+    let long_only = DupeOptions { min_lines: 50, ..Default::default() };
+    assert_eq!(find_duplicates(&index, &long_only).bodies_scanned, 0);
+}
+
+#[test]
+fn test_find_dead_code() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Orders.cs"), "\
+class Orders
+{
+    public void Run() { Validate(); }
+    private void Validate() { }
+    private void Unused() { }
+    void Legacy() { }
+    [Fact]
+    private void Attributed() { }
+    static void Main() { }
+}
+partial class Cart
+{
+    private void Recalculate() { }
+}
+").unwrap();
+    std::fs::write(tmp.path().join("Cart.Part.cs"), "partial class Cart\n{\n    public void Add() { Recalculate(); }\n}\n").unwrap();
+    std::fs::write(tmp.path().join("util.ts"), "\
+export function format(x: number) { return pad(x); }
+function pad(x: number) { return x; }
+function orphan() { }
+class Widget {
+    private draw() { }
+    render() { this.draw(); }
+}
+").unwrap();
+    let index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(),
+        ext: "cs,ts".to_string(),
+        threads: 1,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+
+    let report = find_dead_code(&index, &DeadCodeOptions::default());
+    let names: Vec<&str> = report.dead.iter().map(|&d| index.definitions[d as usize].name.as_str()).collect();
+    // Location order: Orders.cs before util.ts; Recalculate is called from the other part
+    assert_eq!(names, ["Unused", "Legacy", "orphan"]);
+    assert_eq!(report.files_unreadable, 0);
+
+    let json = dead_code_to_json(&index, report.dead[0]);
+    assert_eq!(json["parent"], "Orders");
+    assert_eq!(json["lines"], "5-5");
+
+    let only_ts = DeadCodeOptions { file_filter: Some("util".to_string()), ..Default::default() };
+    assert_eq!(find_dead_code(&index, &only_ts).dead.len(), 1);
+    let excluded = DeadCodeOptions { exclude_dir: vec!["orders".to_string()], ..Default::default() };
+    assert_eq!(find_dead_code(&index, &excluded).dead.len(), 1);
+}
+
+#[test]
+fn test_namespace_scopes_nested_file_scoped_and_literals() {
+    let scopes = namespace_scopes("\
+namespace Shop
+{
+    // namespace Commented { }
+    namespace Billing.Invoices
+    {
+        class A { string s = \"namespace Fake {\"; char c = '}'; }
+    }
+    class B { string v = @\"a \"\"}\"\" b\"; }
+}
+");
+    let found: Vec<(&str, u32, u32)> = scopes.iter().map(|s| (s.name.as_str(), s.line_start, s.line_end)).collect();
+    assert_eq!(found, [("Shop", 1, 9), ("Shop.Billing.Invoices", 4, 7)]);
+
+    let file_scoped = namespace_scopes("using System;\nnamespace Shop.Orders;\n\npublic class Order { }\n");
+    assert_eq!(file_scoped, [NamespaceScope { name: "Shop.Orders".to_string(), line_start: 2, line_end: 5 }]);
+    let ts = namespace_scopes("export namespace App {\n  export module Util { export function f() { return `}`; } }\n}\nconst module = 1;\n");
+    let found: Vec<(&str, u32, u32)> = ts.iter().map(|s| (s.name.as_str(), s.line_start, s.line_end)).collect();
+    assert_eq!(found, [("App", 1, 3), ("App.Util", 2, 2)]);
+
+    assert!(is_within_namespace("Shop.Billing", "shop"));
+    assert!(!is_within_namespace("ShopBilling", "Shop"));
+}
+
+#[test]
+fn test_signature_parameters_arity_and_type_matching() {
+    let def = |name: &str, signature: &str| DefinitionEntry {
+        file_id: 0, name: name.to_string(), kind: DefinitionKind::Method, line_start: 1, line_end: 1,
+        parent: None, signature: Some(signature.to_string()), modifiers: Vec::new(), attributes: Vec::new(), base_types: Vec::new(),
+    };
+
+    let cs = def("Find", "[HttpGet(\"a,b\")] public static IList<T> Find<T>(this IQueryable<T> source, [FromQuery] Dictionary<string, int> map, string sep = \",\", params T[] keys) where T : class");
+    let params = parameters(&cs);
+    let found: Vec<(&str, Option<&str>, bool, bool)> = params.iter()
+        .map(|p| (p.name.as_str(), p.type_name.as_deref(), p.optional, p.variadic)).collect();
+    assert_eq!(found, [
+        ("map", Some("Dictionary<string, int>"), false, false),
+        ("sep", Some("string"), true, false),
+        ("keys", Some("T[]"), false, true),
+    ]);
+    assert_eq!(arity(&cs), Arity { min: 1, max: None });
+    assert!(matches_param_types(&cs, &["System.Collections.Generic.Dictionary<string,int>".into(), "String".into(), "int[]".into()], &[]));
+    assert!(!matches_param_types(&cs, &["Dictionary<string, int>".into(), "int".into(), "int[]".into()], &[]));
+
+    let ts = def("load", "async load(this: Window, id: number, opts?: { a: string }, cb: (e: Error) => void = noop): Promise<void>");
+    assert_eq!(parameters(&ts).iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), ["id", "opts", "cb"]);
+    assert_eq!(arity(&ts), Arity { min: 1, max: Some(3) });
+    assert!(arity(&def("constructor", "constructor(private readonly http: HttpClient, ...rest: string[])")).accepts(4));
+
+    assert!(type_matches("List<T>", "list<string>", &["T".to_string()]));
+    assert!(type_matches("List<T>", "List", &[]));
+    assert!(!type_matches("List<T>", "List<string>", &[]));
+}
+
+#[test]
+fn test_call_argument_types() {
+    assert_eq!(call_arguments("<int>(1, \"a, b\", Map(x, y))").unwrap(), ["1", "\"a, b\"", "Map(x, y)"]);
+    assert_eq!(call_arguments(" ()").unwrap(), Vec::<String>::new());
+    assert_eq!(call_arguments("(1,\n  2"), None);
+    assert_eq!(call_arguments(" = 1"), None);
+
+    let cs = |arg: &str| literal_type(arg, false);
+    assert_eq!([cs("\"a\""), cs("$\"{x}\""), cs("'c'"), cs("true"), cs("-1"), cs("10L"), cs("0xFFu"), cs("1.5"), cs("2m"), cs("new List<int>()")],
+        ["string", "string", "char", "bool", "int", "long", "uint", "double", "decimal", "List<int>"].map(|t| Some(t.to_string())));
+    assert_eq!([cs("null"), cs("\"a\".Length"), cs("x + 1"), cs("new()")], [None, None, None, None]);
+    assert_eq!(literal_type("'a'", true).as_deref(), Some("string"));
+    assert_eq!(literal_type("1.5", true).as_deref(), Some("number"));
+
+    let body = "void Run(string name, int count)\n{\n    var id = 5;\n    foreach (var item in items)\n    List<int> ids = new();\n    if (count == limit)";
+    assert_eq!(declared_type(body, "name", false), Some(Some("string".to_string())));
+    assert_eq!(declared_type(body, "id", false), Some(Some("int".to_string())));
+    assert_eq!(declared_type(body, "ids", false), Some(Some("List<int>".to_string())));
+    assert_eq!(declared_type(body, "item", false), Some(None));
+    assert_eq!(declared_type(body, "limit", false), None);
+    assert_eq!(declared_type("load(id: number, name?: string) {\n  let n = 'x'", "name", true), Some(Some("string".to_string())));
+    assert_eq!(declared_type("load(id: number) {\n  let n = 'x'", "n", true), Some(Some("string".to_string())));
+
+    assert_eq!(argument_fit("string", "String", &[]), Some(true));
+    assert_eq!(argument_fit("long", "int", &[]), Some(true));
+    assert_eq!(argument_fit("int?", "int", &[]), Some(true));
+    assert_eq!(argument_fit("T", "string", &["T".to_string()]), Some(true));
+    assert_eq!(argument_fit("object", "User", &[]), Some(true));
+    assert_eq!(argument_fit("int", "string", &[]), Some(false));
+    assert_eq!(argument_fit("System.Int32", "bool", &[]), Some(false));
+    assert_eq!(argument_fit("byte", "int", &[]), None);
+    assert_eq!(argument_fit("User", "Customer", &[]), None);
+}
+
+#[test]
+fn test_parse_ctags_classic_and_json() {
+    let tags = concat!(
+        "!_TAG_FILE_FORMAT\t2\t/extended format/\n",
+        "Repo\tsrc/repo.py\t/^class Repo(Base):$/;\"\tkind:class\tline:3\tend:20\tinherits:Base\n",
+        "load\tsrc/repo.py\t/^    def load(self, id):$/;\"\tkind:member\tline:5\tclass:Repo\tsignature:(self, id)\taccess:public\n",
+        "helper\tsrc/util.py\t12;\"\tf\n",
+        "no_line\tsrc/util.py\t/^no_line = 1$/;\"\tv\n",
+        "{\"_type\": \"tag\", \"name\": \"Parse\", \"path\": \"cmd/main.go\", \"line\": 8, \"end\": 14, \"kind\": \"func\", \"signature\": \"(s string)\"}\n",
+        "{\"_type\": \"ptag\", \"name\": \"JSON_OUTPUT_VERSION\"}\n",
+    );
+    let files = parse_ctags(tags);
+    assert_eq!(files.keys().collect::<Vec<_>>(), ["cmd/main.go", "src/repo.py", "src/util.py"]);
+
+    let repo = &files["src/repo.py"];
+    assert_eq!((repo[0].kind, repo[0].line_start, repo[0].line_end), (DefinitionKind::Class, 3, 20));
+    assert_eq!(repo[0].base_types, ["Base"]);
+    assert_eq!((repo[1].kind, repo[1].parent.as_deref()), (DefinitionKind::Method, Some("Repo")));
+    assert_eq!(repo[1].signature.as_deref(), Some("load(self, id)"));
+    assert_eq!(repo[1].modifiers, ["public"]);
+    assert_eq!((files["src/util.py"].len(), files["src/util.py"][0].kind), (1, DefinitionKind::Function));
+    assert_eq!(files["cmd/main.go"][0].line_end, 14);
+}
+
+#[test]
+fn test_import_scip_export_into_index() {
+    let src = tempfile::tempdir().unwrap();
+    std::fs::write(src.path().join("Shapes.cs"), concat!(
+        "namespace Geo\n{\n    public interface IShape { double Area(); }\n\n",
+        "    public class Circle : IShape\n    {\n        public double R;\n        public double Area() { return R * R; }\n    }\n}\n",
+    )).unwrap();
+    let built = build_definition_index(&DefIndexArgs {
+        dir: src.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new(),
+    });
+    let scip = src.path().join("index.scip");
+    std::fs::write(&scip, to_scip(&build_export_index(&built))).unwrap();
+
+    // An index of another extension: Shapes.cs comes only from the SCIP file
+    let mut index = build_definition_index(&DefIndexArgs {
+        dir: src.path().to_string_lossy().to_string(), ext: "ts".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new(),
+    });
+    let summary = import_external_definitions(&mut index, &[scip.to_string_lossy().to_string()]);
+    assert_eq!(summary, ImportSummary { definitions: built.definitions.len(), files: 1, skipped_files: 0 });
+    assert_eq!(index.imports.len(), 1);
+
+    let find = |name: &str| index.definitions[index.name_index[name][0] as usize].clone();
+    let original = |name: &str| built.definitions[built.name_index[name][0] as usize].clone();
+    let circle = find("circle");
+    assert_eq!((circle.kind, circle.base_types.clone()), (DefinitionKind::Class, vec!["IShape".to_string()]));
+    assert_eq!((circle.line_start, circle.line_end), (original("circle").line_start, original("circle").line_end));
+    let area: Vec<_> = index.name_index["area"].iter().map(|&i| index.definitions[i as usize].parent.clone()).collect();
+    assert_eq!(area, [Some("IShape".to_string()), Some("Circle".to_string())]);
+    assert_eq!(find("r").kind, DefinitionKind::Field);
+    assert_eq!(find("circle").signature, original("circle").signature);
+    assert!(index.files[circle.file_id as usize].ends_with("Shapes.cs"));
+
+    // Files the built-in parsers handled keep their own definitions
+    let mut native = built;
+    let count = native.definitions.len();
+    assert_eq!(import_external_definitions(&mut native, &[scip.to_string_lossy().to_string()]).skipped_files, 1);
+    assert_eq!(native.definitions.len(), count);
+}
+
+#[test]
+fn test_sql_dump_tables_rows_and_quoting() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Notes.cs"), concat!(
+        "public class Notes\n{\n    [Obsolete(\"it's old\")]\n    public void Save(int id) { Store(id); }\n",
+        "    private void Store(int id) { }\n}\n",
+    )).unwrap();
+    let index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new(),
+    });
+    let save = index.name_index["save"][0];
+
+    let sql = to_sql(&index);
+    assert!(sql.starts_with("BEGIN TRANSACTION;\nCREATE TABLE files"));
+    assert!(sql.ends_with("CREATE INDEX call_sites_caller ON call_sites(caller_id);\nCOMMIT;\n"));
+    assert!(sql.contains(&format!("({},0,'Save','method',3,4,'Notes',", save)), "{}", sql);
+    assert!(sql.contains(r#"'["Obsolete(\"it''s old\")"]'"#));
+    assert!(sql.contains(&format!("({},'Store',NULL,NULL,4,", save)));
+    assert_eq!(sql.matches("INSERT INTO code_stats").count(), 1);
+}
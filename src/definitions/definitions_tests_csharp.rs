@@ -258,7 +258,7 @@ public class MyService { }
 public class OtherService { }
 "#).unwrap();
 
-    let args = DefIndexArgs { dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1 };
+    let args = DefIndexArgs { dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new() };
     let index = build_definition_index(&args);
 
     let attr_indices = index.attribute_index.get("obsolete").expect("should have 'obsolete'");
@@ -282,6 +282,13 @@ fn test_incremental_update_new_file() {
         kind_index: HashMap::new(), attribute_index: HashMap::new(),
         base_type_index: HashMap::new(), file_index: HashMap::new(),
         path_to_id: HashMap::new(), method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let clean = PathBuf::from(crate::clean_path(&test_file.to_string_lossy()));
@@ -316,6 +323,13 @@ fn test_incremental_update_existing_file() {
         file_index: { let mut m = HashMap::new(); m.insert(0, vec![0]); m },
         path_to_id: { let mut m = HashMap::new(); m.insert(clean.clone(), 0u32); m },
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     std::fs::write(&test_file, "public class UpdatedClass { public int Value { get; set; } }").unwrap();
@@ -341,6 +355,13 @@ fn test_remove_file_from_def_index() {
         file_index: { let mut m = HashMap::new(); m.insert(0, vec![0]); m.insert(1, vec![1]); m },
         path_to_id: { let mut m = HashMap::new(); m.insert(PathBuf::from("file0.cs"), 0); m.insert(PathBuf::from("file1.cs"), 1); m },
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     remove_file_from_def_index(&mut index, &PathBuf::from("file0.cs"));
@@ -424,6 +445,42 @@ public class Factory { public void Create() { var obj = new OrderValidator(); }
     assert_eq!(nc.unwrap().receiver_type.as_deref(), Some("OrderValidator"));
 }
 
+#[test] fn test_call_site_columns_and_receiver_text() {
+    let source = "
+public class OrderService {
+    private readonly IUserService _userService;
+    public void Process(int id) {
+        var é = _userService?.GetUser(id);
+        var list = new List<int>();
+        _userService
+            .Find<User>(id)
+            .Save();
+        Validate(Validate(id));
+    }
+}
+";
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&tree_sitter_c_sharp::LANGUAGE.into()).unwrap();
+    let (defs, cs, _, _) = parse_csharp_definitions(&mut parser, source, 0);
+    let pi = defs.iter().position(|d| d.name == "Process").unwrap();
+    let calls = &cs.iter().find(|(i, _)| *i == pi).unwrap().1;
+    let span = |name: &str| -> Vec<(u32, u32, u32, Option<&str>)> {
+        calls.iter().filter(|c| c.method_name == name)
+            .map(|c| (c.line, c.column, c.end_column, c.receiver.as_deref())).collect()
+    };
+
+    // Character columns: `é` is two bytes but one column
+    assert_eq!(span("GetUser"), vec![(5, 31, 38, Some("_userService"))]);
+    assert_eq!(calls.iter().find(|c| c.method_name == "GetUser").unwrap().receiver_type.as_deref(), Some("IUserService"));
+    // `new List<int>()` spans the type name only
+    assert_eq!(span("List"), vec![(6, 24, 28, None)]);
+    // A chain split over lines: each call is on the line of its name, receivers joined
+    assert_eq!(span("Find"), vec![(8, 14, 18, Some("_userService"))]);
+    assert_eq!(span("Save"), vec![(9, 14, 18, Some("_userService.Find<User>(id)"))]);
+    // Same name twice on a line: two call sites
+    assert_eq!(span("Validate"), vec![(10, 9, 17, None), (10, 18, 26, None)]);
+}
+
 #[test] fn test_call_site_extraction_this_and_static() {
     let source = r#"
 public class MyClass {
@@ -560,6 +617,13 @@ public class MyService {
         kind_index: HashMap::new(), attribute_index: HashMap::new(),
         base_type_index: HashMap::new(), file_index: HashMap::new(),
         path_to_id: HashMap::new(), method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let clean = PathBuf::from(crate::clean_path(&test_file.to_string_lossy()));
@@ -1962,3 +2026,280 @@ fn test_csharp_chained_member_access_receiver_not_resolved() {
         receiver
     );
 }
+
+#[test]
+fn test_extract_di_registrations_forms() {
+    let source = r#"public static class ServiceRegistration
+{
+    public static void AddShop(this IServiceCollection services)
+    {
+        services.AddScoped<IOrderService, OrderService>();
+        services.TryAddSingleton<Shop.IClock, SystemClock>();
+        services.AddTransient<Mailer>();
+        services.AddScoped(typeof(IRepository<>), typeof(Repository<>));
+        services.AddSingleton<IDictionary<string, int>, Dictionary<string, int>>();
+        services.AddScoped<IOrderStore>(sp => new SqlOrderStore(sp.GetRequiredService<IConfig>()));
+        services.AddScoped<IPayments>(sp => sp.GetRequiredService<PaymentFactory>().Create());
+    }
+}
+"#;
+    let found = extract_di_registrations(source, 4);
+    let triples: Vec<(&str, Option<&str>, &str)> = found.iter()
+        .map(|r| (r.service.as_str(), r.implementation.as_deref(), r.lifetime.as_str()))
+        .collect();
+    assert_eq!(triples, [
+        ("IOrderService", Some("OrderService"), "scoped"),
+        ("Shop.IClock", Some("SystemClock"), "singleton"),
+        ("Mailer", Some("Mailer"), "transient"),
+        ("IRepository<>", Some("Repository<>"), "scoped"),
+        ("IDictionary<string, int>", Some("Dictionary<string, int>"), "singleton"),
+        ("IOrderStore", Some("SqlOrderStore"), "scoped"),
+        ("IPayments", None, "scoped"),
+    ]);
+    assert_eq!(found[0].line, 5);
+    assert!(found.iter().all(|r| r.file_id == 4));
+
+    let index = DefinitionIndex { di_registrations: found, ..Default::default() };
+    assert_eq!(di_resolves(&index, "IClock", "SystemClock"), Some(true));
+    assert_eq!(di_resolves(&index, "IOrderService", "LegacyOrderService"), Some(false));
+    assert_eq!(di_resolves(&index, "IPayments", "StripePayments"), None);
+    assert_eq!(di_resolves(&index, "IUnregistered", "Unregistered"), None);
+    assert_eq!(di_services_of(&index, "Repository<User>"), ["irepository"]);
+}
+
+#[test]
+fn test_extract_endpoints_attribute_routes_and_minimal_apis() {
+    let source = r#"[ApiController]
+[Route("api/[controller]")]
+public class OrdersController : ControllerBase
+{
+    [HttpGet]
+    public IActionResult List() => Ok();
+
+    [HttpGet("{id:int}")]
+    public IActionResult Get(int id) => Ok();
+
+    [HttpPost, Route("~/legacy/orders")]
+    public IActionResult Create() => Ok();
+
+    [Route("[action]")]
+    public IActionResult Export() => Ok();
+
+    public IActionResult Helper() => Ok();
+}
+
+public static class Endpoints
+{
+    public static void Map(WebApplication app)
+    {
+        app.MapGet("/health", () => "ok");
+        var api = app.MapGroup("/api/v2");
+        var orders = api.MapGroup("orders");
+        orders.MapDelete("/{id}", OrderHandlers.Delete).RequireAuthorization();
+    }
+}
+"#;
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&tree_sitter_c_sharp::LANGUAGE.into()).unwrap();
+    let (defs, _, _, _) = parse_csharp_definitions(&mut parser, source, 7);
+    let endpoints = extract_endpoints(&defs, source, 7);
+    let found: Vec<(&str, &str, Option<&str>)> = endpoints.iter()
+        .map(|e| (e.http_method.as_str(), e.route.as_str(), e.handler.as_deref()))
+        .collect();
+    assert_eq!(found, [
+        ("GET", "/api/Orders", Some("OrdersController.List")),
+        ("GET", "/api/Orders/{id:int}", Some("OrdersController.Get")),
+        ("POST", "/legacy/orders", Some("OrdersController.Create")),
+        ("ANY", "/api/Orders/Export", Some("OrdersController.Export")),
+        ("GET", "/health", None),
+        ("DELETE", "/api/v2/orders/{id}", Some("OrderHandlers.Delete")),
+    ]);
+    assert_eq!((endpoints[1].line, endpoints[4].line), (8, 24));
+
+    assert!(route_matches("/api/Orders/{id:int}", "/api/orders/42?expand=lines"));
+    assert!(!route_matches("/api/Orders/{id:int}", "/api/orders"));
+    assert!(!route_matches("/api/Orders/{id:int}", "/api/orders/42/lines"));
+    assert!(route_matches("/api/Orders/{id?}", "/api/orders"));
+    assert!(route_matches("/files/{*path}", "/files/a/b/c.txt"));
+    assert_eq!(route_specificity("/api/Orders/{id:int}"), 2);
+}
+
+#[test]
+fn test_partial_types_merge_parts_and_resolve_sibling_fields() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::write(dir.join("OrderService.cs"), r#"namespace Shop
+{
+    [Serializable]
+    public partial class OrderService : IOrderService
+    {
+        private readonly IOrderRepository _repo;
+    }
+}
+"#).unwrap();
+    std::fs::write(dir.join("OrderService.Queries.cs"), r#"namespace Shop
+{
+    public partial class OrderService : IDisposable
+    {
+        public void Find() { _repo.Load(); this._repo.Save(); }
+        public void Dispose() { }
+    }
+}
+"#).unwrap();
+    // Same name in another namespace: a different type
+    std::fs::write(dir.join("Legacy.cs"), "namespace Legacy { public partial class OrderService { } }\n").unwrap();
+
+    let args = DefIndexArgs { dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new() };
+    let mut index = build_definition_index(&args);
+
+    assert_eq!(index.partial_types.len(), 2);
+    let (&part, partial) = index.partial_types.iter().next().unwrap();
+    assert_eq!(partial.parts.len(), 2);
+    assert!(partial.parts.contains(&part));
+    assert!(index.files[index.definitions[partial.parts[0] as usize].file_id as usize].ends_with("OrderService.Queries.cs"));
+    assert_eq!(partial.base_types, ["IDisposable", "IOrderService"]);
+    assert_eq!(partial.attributes, ["Serializable"]);
+    assert_eq!(merged_base_types(&index, partial.parts[1]), ["IDisposable", "IOrderService"]);
+
+    let find = index.name_index["find"][0];
+    let receivers: Vec<Option<&str>> = index.method_calls[&find].iter().map(|c| c.receiver_type.as_deref()).collect();
+    assert_eq!(receivers, [Some("IOrderRepository"), Some("IOrderRepository")]);
+
+    remove_file_from_def_index(&mut index, &dir.join("OrderService.cs"));
+    assert!(index.partial_types.is_empty());
+}
+
+#[test]
+fn test_doc_comments_extracted_per_definition() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::write(dir.join("UserService.cs"), r#"/// <summary>Loads and caches users.</summary>
+public class UserService
+{
+    /// <summary>
+    /// Loads a user by <paramref name="id"/>, see <see cref="M:Shop.UserCache.Get(System.Int32)"/>.
+    /// </summary>
+    /// <param name="id">The user id &amp; tenant.</param>
+    /// <returns>The user, or <c>null</c>.</returns>
+    [HttpGet]
+    [Obsolete]
+    public User Get(int id) { return null; }
+
+    // Not a doc comment
+    public void Save() { }
+}
+"#).unwrap();
+
+    let args = DefIndexArgs { dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new() };
+    let mut index = build_definition_index(&args);
+    let def = |index: &DefinitionIndex, name: &str| index.definitions[index.name_index[name][0] as usize].clone();
+
+    let doc = doc_comment(&index, &def(&index, "get")).unwrap();
+    assert_eq!(doc.summary.as_deref(), Some("Loads a user by id, see Get."));
+    assert_eq!(doc.params, [DocParam { name: "id".to_string(), description: "The user id & tenant.".to_string() }]);
+    assert_eq!(doc.returns.as_deref(), Some("The user, or null."));
+    assert_eq!(doc_comment(&index, &def(&index, "userservice")).unwrap().summary.as_deref(), Some("Loads and caches users."));
+    assert!(doc_comment(&index, &def(&index, "save")).is_none());
+
+    std::fs::write(dir.join("UserService.cs"), "public class UserService { }\n").unwrap();
+    update_file_definitions(&mut index, &dir.join("UserService.cs"));
+    assert!(index.doc_comments.is_empty());
+}
+
+#[test]
+fn test_halstead_maintainability_and_class_cohesion() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::write(dir.join("Report.cs"), r#"public class Report
+{
+    private int _total;
+    private string _title;
+    public void Add(int n) { _total += n; }
+    public int Total() { return _total; }
+    public void Rename(string title) { _title = title.Trim(); }
+    public string Title() { return _title; }
+}
+
+public class Summary
+{
+    private int _count;
+    public int Count() { return _count; }
+    public string Describe() { return "Count: " + Count(); }
+}
+"#).unwrap();
+
+    let args = DefIndexArgs { dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new() };
+    let index = build_definition_index(&args);
+    let id = |name: &str| index.name_index[name][0];
+
+    // `{ _total += n; }`: operators `{` `+=` `;`, operands `_total` `n`
+    let add = &index.code_stats[&id("add")];
+    assert!((add.halstead_volume - 5.0 * 5f32.log2()).abs() < 0.01, "{}", add.halstead_volume);
+    assert!((add.halstead_difficulty - 1.5).abs() < 0.01, "{}", add.halstead_difficulty);
+    assert_eq!(add.maintainability_index, maintainability_index(add.halstead_volume, 1, 1));
+    assert_eq!(add.members_used, ["_total"]);
+    assert_eq!(index.code_stats[&id("describe")].members_used, ["Count"]);
+    assert!(maintainability_index(20000.0, 60, 600) < 10);
+
+    // Add/Total and Rename/Title share no field: two groups
+    assert_eq!(class_metrics(&index, id("report")), Some(ClassMetrics { members: 4, wmc: 4, lcom: 2 }));
+    assert_eq!(class_metrics(&index, id("summary")), Some(ClassMetrics { members: 2, wmc: 2, lcom: 1 }));
+    assert_eq!(class_metrics(&index, id("add")), None);
+}
+
+#[test]
+fn test_export_scip_and_lsif_symbols_and_references() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::write(dir.join("Orders.cs"), r#"namespace Shop.Orders
+{
+    public interface IOrderStore { void Save(Order order); }
+
+    public class OrderStore : IOrderStore
+    {
+        public void Save(Order order) { }
+        public void Save(Order order, bool flush) { }
+    }
+
+    public class OrderService
+    {
+        private readonly OrderStore _store;
+        public void Place(Order order) { _store.Save(order); }
+    }
+}
+"#).unwrap();
+
+    let args = DefIndexArgs { dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(), storage: None, import: Vec::new() };
+    let index = build_definition_index(&args);
+    let export = build_export_index(&index);
+
+    let symbols: Vec<&str> = export.symbols.iter().map(|s| s.symbol.as_str()).collect();
+    assert!(symbols.contains(&"search . . . Shop/Orders/OrderStore#"), "{:?}", symbols);
+    assert!(symbols.contains(&"search . . . Shop/Orders/OrderStore#Save()."));
+    assert!(symbols.contains(&"search . . . Shop/Orders/OrderStore#Save(+1)."));
+    let store = symbols.iter().position(|s| *s == "search . . . Shop/Orders/OrderStore#").unwrap();
+    let iface = symbols.iter().position(|s| *s == "search . . . Shop/Orders/IOrderStore#").unwrap();
+    assert_eq!(export.symbols[store].implements, [iface]);
+
+    assert_eq!(export.documents.len(), 1);
+    let doc = &export.documents[0];
+    assert_eq!(doc.path, "Orders.cs");
+    let def = doc.occurrences.iter().find(|o| o.definition && o.symbol == store).unwrap();
+    assert_eq!((def.line, def.start, def.end, def.enclosing), (4, 17, 27, Some((4, 8))));
+    // `_store.Save(order)` on line 14 references a Save of OrderStore
+    let call = doc.occurrences.iter().find(|o| !o.definition).unwrap();
+    assert_eq!((call.line, call.start, call.end), (13, 48, 52));
+    assert!(export.symbols[call.symbol].symbol.starts_with("search . . . Shop/Orders/OrderStore#Save("));
+
+    let scip = to_scip(&export);
+    assert_eq!(scip[0], 0x0a); // Index.metadata
+    assert!(scip.windows(9).any(|w| w == b"Orders.cs"));
+
+    let lsif: Vec<serde_json::Value> = to_lsif(&export).lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lsif[0]["label"], "metaData");
+    assert!(lsif.iter().enumerate().all(|(i, v)| v["id"] == i as u64 + 1));
+    let count = |label: &str| lsif.iter().filter(|v| v["label"] == label).count();
+    assert_eq!(count("resultSet"), export.symbols.len());
+    assert_eq!(count("range"), doc.occurrences.len());
+    assert_eq!(count("next"), doc.occurrences.len());
+}
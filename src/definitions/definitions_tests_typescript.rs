@@ -266,6 +266,13 @@ fn test_ts_incremental_update() {
         kind_index: HashMap::new(), attribute_index: HashMap::new(),
         base_type_index: HashMap::new(), file_index: HashMap::new(),
         path_to_id: HashMap::new(), method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let clean = PathBuf::from(crate::clean_path(&test_file.to_string_lossy()));
@@ -323,6 +330,31 @@ fn test_ts_this_method_call() {
     assert_eq!(ds.unwrap().receiver_type.as_deref(), Some("OrderService"));
 }
 
+#[test]
+fn test_ts_call_site_columns_and_receiver_text() {
+    let source = r#"class OrderController {
+    constructor(private userService: UserService) {}
+    handle(): void {
+        this.userService
+            .getUser();
+        const m = new Map<string, number>();
+    }
+}"#;
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()).unwrap();
+    let (defs, call_sites, _) = parse_typescript_definitions(&mut parser, source, 0);
+
+    let hi = defs.iter().position(|d| d.name == "handle").unwrap();
+    let calls = &call_sites.iter().find(|(i, _)| *i == hi).unwrap().1;
+    let gu = calls.iter().find(|c| c.method_name == "getUser").unwrap();
+    assert_eq!((gu.line, gu.column, gu.end_column), (5, 14, 21));
+    assert_eq!(gu.receiver.as_deref(), Some("this.userService"));
+    assert_eq!(gu.receiver_type.as_deref(), Some("UserService"));
+    let map = calls.iter().find(|c| c.method_name == "Map").unwrap();
+    assert_eq!((map.line, map.column, map.end_column, map.receiver.as_deref()), (6, 23, 26, None));
+    assert!(map.receiver_is_generic);
+}
+
 #[test]
 fn test_ts_this_field_method_call() {
     let source = r#"class OrderController {
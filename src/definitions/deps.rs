@@ -0,0 +1,250 @@
+//! File dependency graph: which files a file depends on and which depend on it.
+//!
+//! A file depends on another when it imports it or calls into it. Imports are
+//! recorded per file at index time ([`FileImports`]): a C# `using Shop.Data;`
+//! depends on every file declaring `namespace Shop.Data`, a TypeScript
+//! `import ... from './user.service'` on the file the relative specifier resolves to.
+//! Package and path-alias specifiers are external. Call edges come from the recorded
+//! call sites, resolved the way `search_callers` direction=down resolves them.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::namespaces::namespace_scopes;
+use super::types::{DefinitionIndex, FileImports, NamespaceScope};
+use crate::mcp::handlers::resolve_call_site;
+
+static CS_USING_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*(?:global\s+)?using\s+(?:static\s+)?(?:\w+\s*=\s*)?([A-Za-z_][\w.]*)\s*;").unwrap()
+});
+static TS_IMPORT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?m)^\s*(?:import\s+(?:[^'";]*?\bfrom\s*)?|export\s[^'";]*?\bfrom\s*)['"]([^'"]+)['"]"#).unwrap()
+});
+/// Namespaces a source file declares, by full name.
+pub fn file_namespaces(content: &str) -> Vec<String> {
+    scope_names(&namespace_scopes(content))
+}
+
+fn scope_names(scopes: &[NamespaceScope]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for scope in scopes {
+        if !names.contains(&scope.name) {
+            names.push(scope.name.clone());
+        }
+    }
+    names
+}
+
+/// Imports and declared namespaces of a file in `language` (`cs`, `ts`, `tsx`).
+pub fn extract_file_imports(language: &str, content: &str) -> FileImports {
+    let re = match language {
+        "cs" => &CS_USING_RE,
+        "ts" | "tsx" => &TS_IMPORT_RE,
+        _ => return FileImports::default(),
+    };
+    let mut imports: Vec<String> = Vec::new();
+    for cap in re.captures_iter(content) {
+        if !imports.iter().any(|i| i == &cap[1]) {
+            imports.push(cap[1].to_string());
+        }
+    }
+    let scopes = namespace_scopes(content);
+    FileImports { imports, namespaces: scope_names(&scopes), scopes }
+}
+
+/// Why one file depends on another.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DependencyEdge {
+    /// The file imports the other (or its namespace)
+    pub imports: bool,
+    /// Call sites resolved to definitions in the other file
+    pub calls: usize,
+}
+
+/// File-level dependency graph of a definition index.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    /// file_id -> the files it depends on
+    pub depends_on: HashMap<u32, BTreeMap<u32, DependencyEdge>>,
+    /// file_id -> import specifiers that resolve to no indexed file
+    pub external: HashMap<u32, Vec<String>>,
+}
+
+/// `a/b/../c/./d` -> `a/c/d`, with `/` separators.
+fn normalize_path(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "." => {}
+            ".." if parts.last().is_some_and(|p| !p.is_empty() && *p != "..") => { parts.pop(); }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+impl DependencyGraph {
+    pub fn build(index: &DefinitionIndex) -> Self {
+        let mut graph = Self::default();
+        // Removed files keep their slot in `files` but lose both entries
+        let live = |file_id: u32| index.file_index.contains_key(&file_id) || index.file_imports.contains_key(&file_id);
+
+        let mut namespace_files: HashMap<&str, Vec<u32>> = HashMap::new();
+        let mut path_ids: HashMap<String, u32> = HashMap::new();
+        for (file_id, path) in index.files.iter().enumerate() {
+            if !live(file_id as u32) { continue; }
+            path_ids.insert(path.replace('\\', "/").to_lowercase(), file_id as u32);
+            if let Some(fi) = index.file_imports.get(&(file_id as u32)) {
+                for ns in &fi.namespaces {
+                    namespace_files.entry(ns.as_str()).or_default().push(file_id as u32);
+                }
+            }
+        }
+
+        for (&file_id, fi) in &index.file_imports {
+            let path = index.files[file_id as usize].replace('\\', "/");
+            let dir = path.rsplit_once('/').map_or("", |(d, _)| d);
+            for spec in &fi.imports {
+                let targets: Vec<u32> = if spec.starts_with('.') {
+                    let base = normalize_path(&format!("{}/{}", dir, spec)).to_lowercase();
+                    ["", ".ts", ".tsx", ".d.ts", "/index.ts", "/index.tsx"].iter()
+                        .find_map(|suffix| path_ids.get(&format!("{}{}", base, suffix)).copied())
+                        .into_iter().collect()
+                } else {
+                    namespace_files.get(spec.as_str()).cloned().unwrap_or_default()
+                };
+                if targets.is_empty() {
+                    graph.external.entry(file_id).or_default().push(spec.clone());
+                }
+                for target in targets.into_iter().filter(|&t| t != file_id) {
+                    graph.depends_on.entry(file_id).or_default().entry(target).or_default().imports = true;
+                }
+            }
+        }
+
+        for (&file_id, def_ids) in &index.file_index {
+            for &def_id in def_ids {
+                let Some(calls) = index.method_calls.get(&def_id) else { continue };
+                let parent = index.definitions[def_id as usize].parent.as_deref();
+                for call in calls {
+                    let mut callee_files: Vec<u32> = resolve_call_site(call, index, parent).into_iter()
+                        .filter_map(|id| index.definitions.get(id as usize).map(|d| d.file_id))
+                        .filter(|&f| f != file_id)
+                        .collect();
+                    callee_files.sort_unstable();
+                    callee_files.dedup();
+                    for target in callee_files {
+                        graph.depends_on.entry(file_id).or_default().entry(target).or_default().calls += 1;
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Files `file_id` depends on, by file id.
+    pub fn dependencies(&self, file_id: u32) -> Vec<(u32, &DependencyEdge)> {
+        self.depends_on.get(&file_id)
+            .map(|deps| deps.iter().map(|(&f, e)| (f, e)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Files that depend on `file_id`, by file id.
+    pub fn dependents(&self, file_id: u32) -> Vec<(u32, &DependencyEdge)> {
+        let mut result: Vec<(u32, &DependencyEdge)> = self.depends_on.iter()
+            .filter_map(|(&from, deps)| deps.get(&file_id).map(|e| (from, e)))
+            .collect();
+        result.sort_by_key(|(f, _)| *f);
+        result
+    }
+
+    /// Shortest dependency cycle through `file_id`, starting and ending with it;
+    /// `None` when the file is in no cycle.
+    pub fn cycle_through(&self, file_id: u32) -> Option<Vec<u32>> {
+        let mut previous: HashMap<u32, u32> = HashMap::new();
+        let mut queue = VecDeque::from([file_id]);
+        while let Some(current) = queue.pop_front() {
+            for (next, _) in self.dependencies(current) {
+                if next == file_id {
+                    // Back along the BFS tree; it ends at the direct dependency of file_id
+                    let mut cycle = vec![current];
+                    while let Some(&p) = previous.get(cycle.last().unwrap()) {
+                        cycle.push(p);
+                    }
+                    cycle.reverse();
+                    cycle.push(file_id);
+                    return Some(cycle);
+                }
+                if let std::collections::hash_map::Entry::Vacant(e) = previous.entry(next) {
+                    e.insert(current);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Groups of files that depend on each other (strongly connected components of
+    /// two or more files), largest first.
+    pub fn cycles(&self) -> Vec<Vec<u32>> {
+        // Tarjan, iterative so deep dependency chains cannot overflow the stack
+        let nodes: BTreeSet<u32> = self.depends_on.iter()
+            .flat_map(|(&f, deps)| std::iter::once(f).chain(deps.keys().copied()))
+            .collect();
+        let mut index_of: HashMap<u32, usize> = HashMap::new();
+        let mut low: HashMap<u32, usize> = HashMap::new();
+        let mut on_stack: BTreeSet<u32> = BTreeSet::new();
+        let mut stack: Vec<u32> = Vec::new();
+        let mut groups: Vec<Vec<u32>> = Vec::new();
+        let mut counter = 0;
+
+        for &start in &nodes {
+            if index_of.contains_key(&start) { continue; }
+            let mut work: Vec<(u32, Vec<u32>)> = Vec::new();
+            let push = |node: u32, work: &mut Vec<(u32, Vec<u32>)>, index_of: &mut HashMap<u32, usize>,
+                        low: &mut HashMap<u32, usize>, stack: &mut Vec<u32>, on_stack: &mut BTreeSet<u32>, counter: &mut usize| {
+                index_of.insert(node, *counter);
+                low.insert(node, *counter);
+                *counter += 1;
+                stack.push(node);
+                on_stack.insert(node);
+                let next: Vec<u32> = self.depends_on.get(&node).map(|d| d.keys().rev().copied().collect()).unwrap_or_default();
+                work.push((node, next));
+            };
+            push(start, &mut work, &mut index_of, &mut low, &mut stack, &mut on_stack, &mut counter);
+            while let Some((node, pending)) = work.last_mut() {
+                let node = *node;
+                if let Some(next) = pending.pop() {
+                    if !index_of.contains_key(&next) {
+                        push(next, &mut work, &mut index_of, &mut low, &mut stack, &mut on_stack, &mut counter);
+                    } else if on_stack.contains(&next) {
+                        let l = low[&node].min(index_of[&next]);
+                        low.insert(node, l);
+                    }
+                    continue;
+                }
+                work.pop();
+                if let Some((parent, _)) = work.last() {
+                    let l = low[parent].min(low[&node]);
+                    low.insert(*parent, l);
+                }
+                if low[&node] == index_of[&node] {
+                    let mut group = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(&member);
+                        group.push(member);
+                        if member == node { break; }
+                    }
+                    if group.len() > 1 {
+                        group.sort_unstable();
+                        groups.push(group);
+                    }
+                }
+            }
+        }
+        groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        groups
+    }
+}
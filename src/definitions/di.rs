@@ -0,0 +1,155 @@
+//! Dependency injection registrations of C# code (`Startup.cs`, `Program.cs` and
+//! extension methods on `IServiceCollection`).
+//!
+//! Recognized forms, each with a `TryAdd` variant:
+//!
+//! ```text
+//! services.AddScoped<IOrderService, OrderService>();
+//! services.AddSingleton<Clock>();
+//! services.AddTransient(typeof(IRepository<>), typeof(Repository<>));
+//! services.AddScoped<IOrderStore>(sp => new SqlOrderStore(...));
+//! ```
+//!
+//! A factory registration records the type it constructs with `new` as the
+//! implementation, or none when the factory does not construct one itself.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::types::{DefinitionIndex, DiRegistration};
+
+static ADD_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:TryAdd|Add)(Scoped|Transient|Singleton)\s*([<(])").unwrap()
+});
+static TYPEOF_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\(\s*typeof\s*\(([^()]+)\)\s*(?:,\s*typeof\s*\(([^()]+)\))?").unwrap()
+});
+static NEW_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\bnew\s+([A-Za-z_][\w.]*(?:<[^()]*>)?)\s*\(").unwrap()
+});
+
+/// `Shop.Data.IRepository<User>` -> `irepository`: how the definition index and call
+/// sites name a type.
+pub fn di_type_key(name: &str) -> String {
+    let base = name.split('<').next().unwrap_or(name).trim();
+    base.rsplit('.').next().unwrap_or(base).to_lowercase()
+}
+
+/// The text between the `<` at the start of `s` and its matching `>`, split at
+/// top-level commas.
+fn generic_args(s: &str) -> Option<(Vec<String>, usize)> {
+    let mut depth = 0usize;
+    let mut args = Vec::new();
+    let mut start = 1;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    args.push(s[start..i].trim().to_string());
+                    return Some((args, i + 1));
+                }
+            }
+            ',' if depth == 1 => {
+                args.push(s[start..i].trim().to_string());
+                start = i + 1;
+            }
+            '(' | ')' | ';' | '{' | '}' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The argument list starting at the `(` at the start of `s`, without the parentheses.
+fn call_args(s: &str) -> &str {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &s[1..i];
+                }
+            }
+            _ => {}
+        }
+    }
+    s.get(1..).unwrap_or_default()
+}
+
+/// DI registrations in the C# source of file `file_id`.
+pub fn extract_di_registrations(content: &str, file_id: u32) -> Vec<DiRegistration> {
+    let mut registrations = Vec::new();
+    for cap in ADD_RE.captures_iter(content) {
+        let lifetime = cap[1].to_lowercase();
+        let open = cap.get(2).unwrap();
+        let rest = &content[open.start()..];
+        let (service, implementation) = if open.as_str() == "<" {
+            let Some((args, end)) = generic_args(rest) else { continue };
+            let after = rest[end..].trim_start();
+            match args.as_slice() {
+                [service, implementation] => (service.clone(), Some(implementation.clone())),
+                // One type argument: the type itself, or a factory that builds it
+                [service] if after.starts_with('(') => {
+                    let factory = call_args(after);
+                    let built = NEW_RE.captures(factory).map(|c| c[1].to_string());
+                    match (factory.trim().is_empty(), built) {
+                        (true, _) => (service.clone(), Some(service.clone())),
+                        (false, built) => (service.clone(), built),
+                    }
+                }
+                _ => continue,
+            }
+        } else {
+            let Some(types) = TYPEOF_RE.captures(rest) else { continue };
+            let service = types[1].trim().to_string();
+            let implementation = types.get(2).map_or(service.clone(), |m| m.as_str().trim().to_string());
+            (service, Some(implementation))
+        };
+        let line = content[..cap.get(0).unwrap().start()].matches('\n').count() as u32 + 1;
+        registrations.push(DiRegistration { service, implementation, lifetime, file_id, line });
+    }
+    registrations
+}
+
+/// Whether the registrations resolve `service` to `implementation`: `None` when
+/// `service` is not registered, or only through factories that may build anything,
+/// so callers fall back to their own matching.
+pub fn di_resolves(index: &DefinitionIndex, service: &str, implementation: &str) -> Option<bool> {
+    let service = di_type_key(service);
+    let implementation = di_type_key(implementation);
+    let mut registered = index.di_registrations.iter()
+        .filter(|r| di_type_key(&r.service) == service)
+        .peekable();
+    registered.peek()?;
+    if service == implementation {
+        return Some(true);
+    }
+    let mut factory = false;
+    for r in registered {
+        match r.implementation.as_deref() {
+            Some(i) if di_type_key(i) == implementation => return Some(true),
+            Some(_) => {}
+            // A factory may build anything
+            None => factory = true,
+        }
+    }
+    if factory { None } else { Some(false) }
+}
+
+/// Lowercased service types `implementation` is registered as.
+pub fn di_services_of(index: &DefinitionIndex, implementation: &str) -> Vec<String> {
+    let implementation = di_type_key(implementation);
+    let mut services: Vec<String> = index.di_registrations.iter()
+        .filter(|r| r.implementation.as_deref().is_some_and(|i| di_type_key(i) == implementation))
+        .map(|r| di_type_key(&r.service))
+        .filter(|s| *s != implementation)
+        .collect();
+    services.sort();
+    services.dedup();
+    services
+}
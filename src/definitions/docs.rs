@@ -0,0 +1,111 @@
+//! XML documentation comments: the `///` block above a C# definition, reduced to
+//! its summary, parameter descriptions and return value.
+//!
+//! Only the text is kept: `<see cref="T:Ns.Type"/>` and `<paramref name="x"/>`
+//! become the name they point to, other tags are dropped and whitespace is collapsed.
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::types::{DefinitionEntry, DefinitionIndex, DocComment, DocParam};
+
+static SECTION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?s)<(summary|returns|param)(?:\s+name\s*=\s*"([^"]*)")?\s*>(.*?)</\s*(?:summary|returns|param)\s*>"#).unwrap()
+});
+
+/// Self-closing references: `<see cref="..."/>`, `<paramref name="..."/>`, `<typeparamref name="..."/>`
+static REFERENCE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"<\w+\s+(?:cref|name|langword|href)\s*=\s*"([^"]*)"\s*/>"#).unwrap()
+});
+
+static TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"</?[A-Za-z][^>]*>").unwrap());
+
+/// Space left before punctuation by a dropped tag: `<c>null</c>.`
+static SPACE_BEFORE_PUNCT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r" ([.,;:!?)])").unwrap());
+
+/// Text of a doc section: references resolved, tags dropped, entities decoded.
+fn section_text(xml: &str) -> String {
+    let text = REFERENCE_RE.replace_all(xml, |cap: &regex::Captures| {
+        // `T:Namespace.Type` → `Type`, `M:Type.Method(System.Int32)` → `Method`
+        let target = cap[1].split_once(':').map_or(&cap[1], |(_, t)| t);
+        let target = target.split('(').next().unwrap_or(target);
+        target.rsplit('.').next().unwrap_or(target).to_string()
+    });
+    let text = TAG_RE.replace_all(&text, " ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    SPACE_BEFORE_PUNCT_RE.replace_all(&text, "$1")
+        .replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+/// Parse the XML of a doc comment, `///` markers already removed.
+pub fn parse_doc_comment(xml: &str, line: u32) -> DocComment {
+    let mut doc = DocComment { line, ..Default::default() };
+    for cap in SECTION_RE.captures_iter(xml) {
+        let text = section_text(&cap[3]);
+        match &cap[1] {
+            "summary" => doc.summary = Some(text).filter(|t| !t.is_empty()),
+            "returns" => doc.returns = Some(text).filter(|t| !t.is_empty()),
+            _ => if let Some(name) = cap.get(2) {
+                doc.params.push(DocParam { name: name.as_str().to_string(), description: text });
+            }
+        }
+    }
+    doc
+}
+
+/// Doc comments of the C# definitions `defs` parsed from `content`. A comment
+/// belongs to the definitions starting right below it; attribute lines in between
+/// are skipped.
+pub fn extract_doc_comments(defs: &[DefinitionEntry], content: &str) -> Vec<DocComment> {
+    let lines: Vec<&str> = content.lines().collect();
+    let starts: HashSet<u32> = defs.iter().map(|d| d.line_start).collect();
+    let mut starts: Vec<u32> = starts.into_iter().collect();
+    starts.sort_unstable();
+
+    let mut docs = Vec::new();
+    for line_start in starts {
+        let mut i = (line_start as usize).saturating_sub(1).min(lines.len());
+        // The definition's own line may carry its attributes; skip the ones above it
+        while i > 0 && lines[i - 1].trim_start().starts_with('[') && !lines[i - 1].trim_start().starts_with("[assembly:") {
+            i -= 1;
+        }
+        let end = i;
+        while i > 0 && lines[i - 1].trim_start().starts_with("///") {
+            i -= 1;
+        }
+        if i == end {
+            continue;
+        }
+        let xml: Vec<&str> = lines[i..end].iter()
+            .map(|l| { let l = &l.trim_start()[3..]; l.strip_prefix(' ').unwrap_or(l) })
+            .collect();
+        let doc = parse_doc_comment(&xml.join("\n"), line_start);
+        if doc.summary.is_some() || doc.returns.is_some() || !doc.params.is_empty() {
+            docs.push(doc);
+        }
+    }
+    docs
+}
+
+/// The doc comment of `def`, if it has one.
+pub fn doc_comment<'a>(index: &'a DefinitionIndex, def: &DefinitionEntry) -> Option<&'a DocComment> {
+    index.doc_comments.get(&def.file_id)?.iter().find(|d| d.line == def.line_start)
+}
+
+/// All text of a doc comment, lowercased, for `docs` searches.
+pub fn doc_text(doc: &DocComment) -> String {
+    let mut text = doc.summary.clone().unwrap_or_default();
+    for param in &doc.params {
+        text.push(' ');
+        text.push_str(&param.name);
+        text.push(' ');
+        text.push_str(&param.description);
+    }
+    if let Some(ref returns) = doc.returns {
+        text.push(' ');
+        text.push_str(returns);
+    }
+    text.to_lowercase()
+}
@@ -0,0 +1,197 @@
+//! Near-duplicate detection: Jaccard similarity of token w-shingles per method body.
+//!
+//! Bodies come from the definition index's line ranges; tokens come from the same
+//! tokenizer as the content index, so renamed identifiers lower similarity while
+//! formatting and punctuation changes do not.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use super::types::{DefinitionIndex, DefinitionKind};
+
+/// Tuning knobs for [`find_duplicates`].
+#[derive(Debug, Clone)]
+pub struct DupeOptions {
+    /// Tokens per shingle (w). Larger values demand longer identical runs.
+    pub shingle_size: usize,
+    /// Skip bodies shorter than this many lines.
+    pub min_lines: u32,
+    /// Report pairs with Jaccard similarity at or above this value (0.0-1.0).
+    pub min_similarity: f64,
+    /// Shingles shared by more bodies than this are boilerplate and are not used to
+    /// find candidate pairs (keeps pair counting from going quadratic).
+    pub max_shingle_freq: usize,
+    /// Only scan definitions whose file path contains this substring.
+    pub file_filter: Option<String>,
+    /// Skip definitions whose file path contains any of these substrings.
+    pub exclude_dir: Vec<String>,
+}
+
+impl Default for DupeOptions {
+    fn default() -> Self {
+        Self {
+            shingle_size: 5,
+            min_lines: 5,
+            min_similarity: 0.8,
+            max_shingle_freq: 100,
+            file_filter: None,
+            exclude_dir: Vec::new(),
+        }
+    }
+}
+
+/// Two definitions (indices into `DefinitionIndex::definitions`) with similar bodies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClonePair {
+    pub a: u32,
+    pub b: u32,
+    pub similarity: f64,
+    pub shared_shingles: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct DupeReport {
+    /// Sorted by similarity (highest first), then by location of `a`.
+    pub pairs: Vec<ClonePair>,
+    pub bodies_scanned: usize,
+    pub files_unreadable: usize,
+}
+
+/// Hashes of every run of `shingle_size` consecutive tokens in `text`.
+/// Bodies with fewer tokens than `shingle_size` produce an empty set.
+pub fn body_shingles(text: &str, shingle_size: usize) -> HashSet<u64> {
+    let tokens: Vec<String> = text.lines().flat_map(|l| crate::tokenize(l, 1)).collect();
+    tokens.windows(shingle_size.max(1))
+        .map(|w| {
+            let mut h = DefaultHasher::new();
+            w.hash(&mut h);
+            h.finish()
+        })
+        .collect()
+}
+
+fn is_body_kind(kind: &DefinitionKind) -> bool {
+    matches!(kind, DefinitionKind::Method | DefinitionKind::Constructor | DefinitionKind::Function)
+}
+
+/// Find pairs of method/constructor/function bodies whose shingle sets overlap by at
+/// least `opts.min_similarity`. Reads each file once; unreadable files are counted and
+/// skipped. Nested definitions are never paired with the definition that contains them.
+pub fn find_duplicates(index: &DefinitionIndex, opts: &DupeOptions) -> DupeReport {
+    let file_filter = opts.file_filter.as_ref().map(|f| f.replace('\\', "/").to_lowercase());
+    let exclude: Vec<String> = opts.exclude_dir.iter().map(|e| e.to_lowercase()).collect();
+
+    // Candidates ordered by location so pair ids and output are stable across index builds
+    let mut candidates: Vec<u32> = (0..index.definitions.len() as u32)
+        .filter(|&i| {
+            let def = &index.definitions[i as usize];
+            if !is_body_kind(&def.kind) || def.line_end + 1 < def.line_start + opts.min_lines {
+                return false;
+            }
+            let Some(path) = index.files.get(def.file_id as usize) else { return false };
+            let lower = path.replace('\\', "/").to_lowercase();
+            file_filter.as_ref().is_none_or(|f| lower.contains(f.as_str()))
+                && !exclude.iter().any(|e| lower.contains(e.as_str()))
+        })
+        .collect();
+    candidates.sort_by(|&a, &b| {
+        let (da, db) = (&index.definitions[a as usize], &index.definitions[b as usize]);
+        index.files[da.file_id as usize].cmp(&index.files[db.file_id as usize])
+            .then(da.line_start.cmp(&db.line_start))
+    });
+
+    let mut report = DupeReport::default();
+    let mut bodies: Vec<(u32, HashSet<u64>)> = Vec::new();
+    let mut current_file: Option<(u32, Option<Vec<String>>)> = None;
+    for &def_idx in &candidates {
+        let def = &index.definitions[def_idx as usize];
+        if current_file.as_ref().is_none_or(|(id, _)| *id != def.file_id) {
+            let lines = crate::read_file_lossy(Path::new(&index.files[def.file_id as usize]))
+                .ok()
+                .map(|(content, _)| content.lines().map(|l| l.to_string()).collect());
+            if lines.is_none() {
+                report.files_unreadable += 1;
+            }
+            current_file = Some((def.file_id, lines));
+        }
+        let Some((_, Some(lines))) = &current_file else { continue };
+        let start = (def.line_start as usize).saturating_sub(1);
+        let end = (def.line_end as usize).min(lines.len());
+        if start >= end {
+            continue;
+        }
+        let shingles = body_shingles(&lines[start..end].join("\n"), opts.shingle_size);
+        if !shingles.is_empty() {
+            bodies.push((def_idx, shingles));
+        }
+    }
+    report.bodies_scanned = bodies.len();
+
+    // shingle -> bodies containing it
+    let mut postings: HashMap<u64, Vec<u32>> = HashMap::new();
+    for (body_id, (_, shingles)) in bodies.iter().enumerate() {
+        for &s in shingles {
+            postings.entry(s).or_default().push(body_id as u32);
+        }
+    }
+    let mut shared: HashMap<(u32, u32), usize> = HashMap::new();
+    for ids in postings.values() {
+        if ids.len() < 2 || ids.len() > opts.max_shingle_freq {
+            continue;
+        }
+        for (i, &x) in ids.iter().enumerate() {
+            for &y in &ids[i + 1..] {
+                *shared.entry((x, y)).or_default() += 1;
+            }
+        }
+    }
+
+    for ((x, y), common) in shared {
+        let (def_a, set_a) = &bodies[x as usize];
+        let (def_b, set_b) = &bodies[y as usize];
+        let similarity = common as f64 / (set_a.len() + set_b.len() - common) as f64;
+        if similarity < opts.min_similarity {
+            continue;
+        }
+        let (a, b) = (&index.definitions[*def_a as usize], &index.definitions[*def_b as usize]);
+        let nested = a.file_id == b.file_id && a.line_start <= b.line_end && b.line_start <= a.line_end;
+        if nested {
+            continue;
+        }
+        report.pairs.push(ClonePair { a: *def_a, b: *def_b, similarity, shared_shingles: common });
+    }
+    // Body ids follow location order, so (a, b) ordering doubles as a location tie-breaker
+    let position: HashMap<u32, usize> = bodies.iter().enumerate().map(|(i, (d, _))| (*d, i)).collect();
+    report.pairs.sort_by(|p, q| {
+        q.similarity.total_cmp(&p.similarity)
+            .then(position[&p.a].cmp(&position[&q.a]))
+            .then(position[&p.b].cmp(&position[&q.b]))
+    });
+    report
+}
+
+/// JSON form used by the `search_dupes` MCP tool and `search dupes --json`.
+pub fn clone_pair_to_json(index: &DefinitionIndex, pair: &ClonePair) -> Value {
+    let side = |def_idx: u32| {
+        let def = &index.definitions[def_idx as usize];
+        let mut obj = json!({
+            "name": def.name,
+            "file": index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or(""),
+            "lines": format!("{}-{}", def.line_start, def.line_end),
+        });
+        if let Some(ref parent) = def.parent {
+            obj["parent"] = json!(parent);
+        }
+        obj
+    };
+    json!({
+        "similarity": (pair.similarity * 1000.0).round() / 1000.0,
+        "sharedShingles": pair.shared_shingles,
+        "a": side(pair.a),
+        "b": side(pair.b),
+    })
+}
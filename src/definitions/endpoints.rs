@@ -0,0 +1,152 @@
+//! ASP.NET endpoints: attribute-routed controller actions and minimal-API `Map*` calls.
+//!
+//! Attribute routes come from the parsed definitions: a controller's `[Route]`
+//! prefix combined with each action's `[HttpGet("{id}")]`-style template or
+//! `[Route]`, with `[controller]` and `[action]` replaced. Templates starting with
+//! `/` or `~/` ignore the prefix. Minimal APIs are read from the source:
+//! `app.MapGet("/orders/{id}", handler)`, including prefixes of `MapGroup` variables
+//! declared in the same file.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::types::{DefinitionEntry, DefinitionKind, Endpoint};
+
+const HTTP_ATTRIBUTES: &[(&str, &str)] = &[
+    ("HttpGet", "GET"), ("HttpPost", "POST"), ("HttpPut", "PUT"), ("HttpDelete", "DELETE"),
+    ("HttpPatch", "PATCH"), ("HttpHead", "HEAD"), ("HttpOptions", "OPTIONS"),
+];
+
+static STRING_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"@?"([^"]*)""#).unwrap());
+static MAP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:\b(\w+)\s*)?\.Map(Get|Post|Put|Delete|Patch)\s*\(\s*@?"([^"]*)"\s*,\s*([A-Za-z_][\w.]*\s*\)|)"#).unwrap()
+});
+static GROUP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\b(?:var\s+)?(\w+)\s*=\s*(?:(\w+)\s*)?\.MapGroup\s*\(\s*@?"([^"]*)"\s*\)"#).unwrap()
+});
+
+/// `Route("api/[controller]")` -> (`Route`, `api/[controller]`).
+fn split_attribute(attr: &str) -> (&str, Option<String>) {
+    let name = attr.split('(').next().unwrap_or(attr).trim();
+    let name = name.strip_suffix("Attribute").unwrap_or(name);
+    let template = attr.find('(').and_then(|i| STRING_RE.captures(&attr[i..])).map(|c| c[1].to_string());
+    (name, template)
+}
+
+/// `/`-joined route with a leading slash and no empty segments.
+pub fn normalize_route(route: &str) -> String {
+    let segments: Vec<&str> = route.split('/').filter(|s| !s.is_empty() && *s != "~").collect();
+    format!("/{}", segments.join("/"))
+}
+
+fn combine(prefix: Option<&str>, template: &str) -> String {
+    match prefix {
+        Some(prefix) if !template.starts_with('/') && !template.starts_with("~/") => normalize_route(&format!("{}/{}", prefix, template)),
+        _ => normalize_route(template),
+    }
+}
+
+/// Endpoints of one C# file: its attribute-routed actions (`defs` are the file's
+/// definitions) and its minimal-API mappings.
+pub fn extract_endpoints(defs: &[DefinitionEntry], content: &str, file_id: u32) -> Vec<Endpoint> {
+    let mut endpoints = Vec::new();
+
+    let class_routes: HashMap<&str, Vec<String>> = defs.iter()
+        .filter(|d| d.kind == DefinitionKind::Class)
+        .map(|d| (d.name.as_str(), d.attributes.iter()
+            .map(|a| split_attribute(a))
+            .filter(|(name, _)| *name == "Route")
+            .filter_map(|(_, template)| template)
+            .collect()))
+        .collect();
+    for def in defs.iter().filter(|d| d.kind == DefinitionKind::Method) {
+        let Some(class) = def.parent.as_deref() else { continue };
+        let attributes: Vec<(&str, Option<String>)> = def.attributes.iter().map(|a| split_attribute(a)).collect();
+        let routes: Vec<&String> = attributes.iter().filter(|(n, _)| *n == "Route").filter_map(|(_, t)| t.as_ref()).collect();
+        let mut verbs: Vec<(&str, Option<&String>)> = attributes.iter()
+            .filter_map(|(name, template)| HTTP_ATTRIBUTES.iter().find(|(a, _)| a == name).map(|(_, verb)| (*verb, template.as_ref())))
+            .collect();
+        if verbs.is_empty() && !routes.is_empty() {
+            verbs.push(("ANY", None));
+        }
+        let controller = class.strip_suffix("Controller").unwrap_or(class);
+        let prefixes: Vec<Option<&str>> = match class_routes.get(class) {
+            Some(p) if !p.is_empty() => p.iter().map(|s| Some(s.as_str())).collect(),
+            _ => vec![None],
+        };
+        for (verb, template) in verbs {
+            let templates: Vec<&str> = match template {
+                Some(t) => vec![t.as_str()],
+                None if !routes.is_empty() => routes.iter().map(|r| r.as_str()).collect(),
+                None => vec![""],
+            };
+            for template in templates {
+                for prefix in &prefixes {
+                    let route = combine(*prefix, template)
+                        .replace("[controller]", controller)
+                        .replace("[action]", &def.name);
+                    endpoints.push(Endpoint {
+                        http_method: verb.to_string(),
+                        route,
+                        handler: Some(format!("{}.{}", class, def.name)),
+                        file_id,
+                        line: def.line_start,
+                    });
+                }
+            }
+        }
+    }
+
+    // MapGroup variables: name -> full prefix. Unlike attribute routes, a leading `/`
+    // under a group is still relative to it
+    let mut groups: HashMap<String, String> = HashMap::new();
+    let in_group = |groups: &HashMap<String, String>, receiver: Option<regex::Match>, template: &str| {
+        let prefix = receiver.and_then(|m| groups.get(m.as_str())).map_or("", String::as_str);
+        normalize_route(&format!("{}/{}", prefix, template))
+    };
+    for cap in GROUP_RE.captures_iter(content) {
+        let prefix = in_group(&groups, cap.get(2), &cap[3]);
+        groups.insert(cap[1].to_string(), prefix);
+    }
+    for cap in MAP_RE.captures_iter(content) {
+        let handler = cap[4].trim_end_matches(')').trim();
+        endpoints.push(Endpoint {
+            http_method: cap[2].to_uppercase(),
+            route: in_group(&groups, cap.get(1), &cap[3]),
+            handler: (!handler.is_empty()).then(|| handler.to_string()),
+            file_id,
+            line: content[..cap.get(0).unwrap().start()].matches('\n').count() as u32 + 1,
+        });
+    }
+    endpoints
+}
+
+/// Whether the concrete `path` (`/api/orders/42?x=1`) is served by `template`
+/// (`/api/orders/{id:int}`). Parameters match one segment, optional ones may be
+/// missing, and catch-alls (`{*rest}`) match the rest. Literals are case-insensitive.
+pub fn route_matches(template: &str, path: &str) -> bool {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let template: Vec<&str> = template.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let mut i = 0;
+    for (t, segment) in template.iter().enumerate() {
+        let is_param = segment.starts_with('{') && segment.ends_with('}');
+        if is_param && (segment.starts_with("{*") || segment.starts_with("{**")) {
+            return true;
+        }
+        match path.get(i) {
+            Some(p) if is_param || segment.eq_ignore_ascii_case(p) => i += 1,
+            // Optional parameters (and the ones after them) may be missing at the end
+            None if is_param && template[t..].iter().all(|s| s.ends_with("?}") || s.contains('=')) => return true,
+            _ => return false,
+        }
+    }
+    i == path.len()
+}
+
+/// Literal (non-parameter) segments of a template: the more, the more specific.
+pub fn route_specificity(template: &str) -> usize {
+    template.split('/').filter(|s| !s.is_empty() && !s.starts_with('{')).count()
+}
@@ -0,0 +1,504 @@
+//! Code-intelligence export: the definition index as a SCIP index or an LSIF dump, for
+//! Sourcegraph and other tools that read them.
+//!
+//! Every definition gets a SCIP symbol built from its namespace, parent and name
+//! (`search . . . Shop/OrderService#Save().`); the parts of a partial type share one, and
+//! overloads are told apart by a `(+1)` disambiguator. Definition occurrences are the
+//! name on the definition's lines, found by reading the file; reference occurrences are
+//! the call sites that resolve to a definition, as `search_callers` direction=down
+//! resolves them. Base types in the index become implementation relationships.
+//! Call sites recorded without columns have no range and are left out.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use super::docs::doc_comment;
+use super::namespaces::namespace_of;
+use super::types::{DefinitionEntry, DefinitionIndex, DefinitionKind};
+use crate::mcp::handlers::resolve_call_site;
+use crate::read_file_lossy;
+
+/// Target format of `search export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// SCIP protobuf index (`index.scip`)
+    Scip,
+    /// LSIF JSON lines dump (`dump.lsif`)
+    Lsif,
+    /// SQL script for SQLite (`index.sql`)
+    Sql,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Scip => "scip",
+            Self::Lsif => "lsif",
+            Self::Sql => "sql",
+        }
+    }
+
+    /// Default output file; the names the Sourcegraph uploaders look for.
+    pub fn default_file(&self) -> &'static str {
+        match self {
+            Self::Scip => "index.scip",
+            Self::Lsif => "dump.lsif",
+            Self::Sql => "index.sql",
+        }
+    }
+}
+
+/// One symbol: a definition, or all parts of a partial type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSymbol {
+    /// SCIP symbol string; also the LSIF moniker
+    pub symbol: String,
+    pub kind: DefinitionKind,
+    pub name: String,
+    /// Signature (as a code block) and doc comment summary
+    pub documentation: Vec<String>,
+    /// Base types and interfaces defined in the index, as symbol indexes
+    pub implements: Vec<usize>,
+}
+
+/// A range of a document that defines or references a symbol. Lines and characters
+/// are 0-based, the end exclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportOccurrence {
+    pub line: u32,
+    pub start: u32,
+    pub end: u32,
+    pub symbol: usize,
+    pub definition: bool,
+    /// Lines of the whole definition (0-based, inclusive), for definitions
+    pub enclosing: Option<(u32, u32)>,
+}
+
+/// One indexed file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportDocument {
+    /// Path relative to the index root, with `/` separators
+    pub path: String,
+    pub language: Language,
+    pub occurrences: Vec<ExportOccurrence>,
+    /// Symbols first defined in this document
+    pub symbols: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    CSharp,
+    TypeScript,
+    TypeScriptReact,
+    Sql,
+}
+
+impl Language {
+    fn of(path: &str) -> Self {
+        match path.rsplit('.').next().map(str::to_ascii_lowercase).as_deref() {
+            Some("ts") => Self::TypeScript,
+            Some("tsx") => Self::TypeScriptReact,
+            Some("sql") => Self::Sql,
+            _ => Self::CSharp,
+        }
+    }
+
+    /// SCIP `Language` enum name.
+    fn scip_name(self) -> &'static str {
+        match self {
+            Self::CSharp => "CSharp",
+            Self::TypeScript => "TypeScript",
+            Self::TypeScriptReact => "TypeScriptReact",
+            Self::Sql => "SQL",
+        }
+    }
+
+    /// LSP language id.
+    fn language_id(self) -> &'static str {
+        match self {
+            Self::CSharp => "csharp",
+            Self::TypeScript => "typescript",
+            Self::TypeScriptReact => "typescriptreact",
+            Self::Sql => "sql",
+        }
+    }
+}
+
+/// Symbols and documents of a whole definition index, ready to encode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportIndex {
+    /// `file:///` URI of the index root, with a trailing slash
+    pub project_root: String,
+    pub symbols: Vec<ExportSymbol>,
+    pub documents: Vec<ExportDocument>,
+}
+
+/// `file:///` URI of a root directory, with a trailing slash.
+pub(crate) fn root_uri(root: &str) -> String {
+    let root = root.replace('\\', "/");
+    format!("file:///{}/", root.trim_start_matches('/').trim_end_matches('/'))
+}
+
+fn is_type(kind: DefinitionKind) -> bool {
+    matches!(kind, DefinitionKind::Class | DefinitionKind::Interface | DefinitionKind::Enum
+        | DefinitionKind::Struct | DefinitionKind::Record | DefinitionKind::Delegate | DefinitionKind::TypeAlias)
+}
+
+fn is_callable(kind: DefinitionKind) -> bool {
+    matches!(kind, DefinitionKind::Method | DefinitionKind::Constructor | DefinitionKind::Function
+        | DefinitionKind::StoredProcedure | DefinitionKind::SqlFunction)
+}
+
+/// A SCIP descriptor name, backtick-quoted unless it is a plain identifier.
+fn escape(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '+' | '-' | '$')) {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+/// SCIP symbol of `def` without an overload disambiguator.
+fn symbol_of(index: &DefinitionIndex, def: &DefinitionEntry) -> String {
+    let mut symbol = "search . . . ".to_string();
+    for part in namespace_of(index, def).into_iter().flat_map(|ns| ns.split('.')) {
+        symbol.push_str(&escape(part));
+        symbol.push('/');
+    }
+    if let Some(ref parent) = def.parent {
+        symbol.push_str(&escape(parent));
+        symbol.push('#');
+    }
+    symbol.push_str(&escape(&def.name));
+    symbol.push_str(if is_type(def.kind) { "#" } else if is_callable(def.kind) { "()." } else { "." });
+    symbol
+}
+
+/// 0-based character column of the first whole-word `name` in `line`.
+fn name_column(line: &str, name: &str) -> Option<u32> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(name).find(|(at, _)| {
+        !line[..*at].chars().next_back().is_some_and(is_ident)
+            && !line[at + name.len()..].chars().next().is_some_and(is_ident)
+    }).map(|(at, _)| line[..at].chars().count() as u32)
+}
+
+/// Path of `file_id` relative to the index root, with `/` separators.
+fn relative_file(index: &DefinitionIndex, file_id: u32) -> String {
+    let path = index.files.get(file_id as usize).map_or("", |s| s.as_str()).replace('\\', "/");
+    crate::relative_path(&index.root, &path).to_string()
+}
+
+/// Collect the symbols and occurrences of `index`. Files are read from disk to place
+/// the definition names; definitions in unreadable files keep their symbol but get no
+/// definition occurrence.
+pub fn build_export_index(index: &DefinitionIndex) -> ExportIndex {
+    let mut symbols: Vec<ExportSymbol> = Vec::new();
+    let mut by_symbol: HashMap<String, usize> = HashMap::new();
+    let mut overloads: HashMap<String, u32> = HashMap::new();
+    let mut def_symbol: Vec<usize> = Vec::with_capacity(index.definitions.len());
+    let mut documents: BTreeMap<u32, ExportDocument> = BTreeMap::new();
+
+    // Definitions removed by incremental updates stay in `definitions` but leave `file_index`
+    let live: HashSet<u32> = index.file_index.values().flatten().copied().collect();
+    for (def_id, def) in index.definitions.iter().enumerate() {
+        if !live.contains(&(def_id as u32)) {
+            def_symbol.push(usize::MAX);
+            continue;
+        }
+        let mut symbol = symbol_of(index, def);
+        if is_callable(def.kind) {
+            let seen = overloads.entry(symbol.clone()).or_insert(0);
+            if *seen > 0 {
+                symbol = format!("{}(+{}).", &symbol[..symbol.len() - 3], seen);
+            }
+            *seen += 1;
+        }
+        let id = *by_symbol.entry(symbol.clone()).or_insert_with(|| {
+            let language = Language::of(index.files.get(def.file_id as usize).map_or("", |s| s.as_str()));
+            let mut documentation = Vec::new();
+            if let Some(ref signature) = def.signature {
+                documentation.push(format!("```{}\n{}\n```", language.language_id(), signature));
+            }
+            documentation.extend(doc_comment(index, def).and_then(|d| d.summary.clone()));
+            symbols.push(ExportSymbol { symbol, kind: def.kind, name: def.name.clone(), documentation, implements: Vec::new() });
+            documents.entry(def.file_id).or_insert_with(|| document(index, def.file_id)).symbols.push(symbols.len() - 1);
+            symbols.len() - 1
+        });
+        def_symbol.push(id);
+    }
+
+    for (def_id, def) in index.definitions.iter().enumerate() {
+        if def_symbol[def_id] == usize::MAX {
+            continue;
+        }
+        for base in &def.base_types {
+            let base = base.split('<').next().unwrap_or(base).rsplit('.').next().unwrap_or(base).trim();
+            let targets = index.name_index.get(&base.to_lowercase()).into_iter().flatten()
+                .filter(|&&id| is_type(index.definitions[id as usize].kind))
+                .map(|&id| def_symbol[id as usize]);
+            for target in targets {
+                let implements = &mut symbols[def_symbol[def_id]].implements;
+                if !implements.contains(&target) {
+                    implements.push(target);
+                }
+            }
+        }
+    }
+
+    for (&file_id, defs) in &index.file_index {
+        let Some(path) = index.files.get(file_id as usize) else { continue };
+        let Ok((content, _)) = read_file_lossy(std::path::Path::new(path)) else { continue };
+        let lines: Vec<&str> = content.lines().collect();
+        let doc = documents.entry(file_id).or_insert_with(|| document(index, file_id));
+        for &def_id in defs {
+            let def = &index.definitions[def_id as usize];
+            let found = (def.line_start..=def.line_end.max(def.line_start))
+                .filter_map(|n| Some((n - 1, name_column(lines.get(n.checked_sub(1)? as usize)?, &def.name)?)))
+                .next();
+            if let Some((line, start)) = found {
+                doc.occurrences.push(ExportOccurrence {
+                    line, start, end: start + def.name.chars().count() as u32,
+                    symbol: def_symbol[def_id as usize], definition: true,
+                    enclosing: Some((def.line_start.saturating_sub(1), def.line_end.saturating_sub(1))),
+                });
+            }
+        }
+    }
+
+    let mut callers: Vec<&u32> = index.method_calls.keys().collect();
+    callers.sort_unstable();
+    for &caller_id in callers {
+        let Some(caller) = index.definitions.get(caller_id as usize) else { continue };
+        let doc = documents.entry(caller.file_id).or_insert_with(|| document(index, caller.file_id));
+        for call in &index.method_calls[&caller_id] {
+            if call.column == 0 || call.end_column <= call.column {
+                continue;
+            }
+            for target in resolve_call_site(call, index, caller.parent.as_deref()) {
+                doc.occurrences.push(ExportOccurrence {
+                    line: call.line.saturating_sub(1), start: call.column - 1, end: call.end_column - 1,
+                    symbol: def_symbol[target as usize], definition: false, enclosing: None,
+                });
+            }
+        }
+    }
+
+    let mut documents: Vec<ExportDocument> = documents.into_values().collect();
+    for doc in &mut documents {
+        doc.occurrences.sort_by_key(|o| (o.line, o.start, !o.definition, o.symbol));
+        doc.occurrences.dedup();
+    }
+    documents.sort_by(|a, b| a.path.cmp(&b.path));
+    ExportIndex { project_root: root_uri(&index.root), symbols, documents }
+}
+
+fn document(index: &DefinitionIndex, file_id: u32) -> ExportDocument {
+    let path = relative_file(index, file_id);
+    ExportDocument { language: Language::of(&path), path, occurrences: Vec::new(), symbols: Vec::new() }
+}
+
+// ─── SCIP ────────────────────────────────────────────────────────────
+
+/// SCIP `SymbolInformation.Kind` of a definition kind.
+fn scip_kind(kind: DefinitionKind) -> u64 {
+    match kind {
+        DefinitionKind::Class | DefinitionKind::Record => 7,
+        DefinitionKind::Constructor => 9,
+        DefinitionKind::Enum => 11,
+        DefinitionKind::EnumMember => 12,
+        DefinitionKind::Event => 13,
+        DefinitionKind::Field | DefinitionKind::Column => 15,
+        DefinitionKind::Function | DefinitionKind::SqlFunction | DefinitionKind::StoredProcedure => 17,
+        DefinitionKind::Interface => 21,
+        DefinitionKind::Method => 26,
+        DefinitionKind::Property => 41,
+        DefinitionKind::Struct => 49,
+        DefinitionKind::Delegate | DefinitionKind::UserDefinedType | DefinitionKind::Table | DefinitionKind::View => 54,
+        DefinitionKind::TypeAlias => 55,
+        DefinitionKind::Variable => 61,
+        DefinitionKind::SqlIndex => 0,
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Varint field; proto3 leaves out zero values.
+fn put_uint(buf: &mut Vec<u8>, field: u64, value: u64) {
+    if value != 0 {
+        put_varint(buf, field << 3);
+        put_varint(buf, value);
+    }
+}
+
+/// Length-delimited field: a string, a nested message or a packed repeated field.
+fn put_bytes(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    put_varint(buf, (field << 3) | 2);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn packed(values: &[u32]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for &v in values {
+        put_varint(&mut buf, v as u64);
+    }
+    buf
+}
+
+/// The SCIP protobuf encoding of `export` (scip.proto `Index`).
+pub fn to_scip(export: &ExportIndex) -> Vec<u8> {
+    let mut tool = Vec::new();
+    put_bytes(&mut tool, 1, b"search");
+    put_bytes(&mut tool, 2, env!("CARGO_PKG_VERSION").as_bytes());
+    let mut metadata = Vec::new();
+    put_bytes(&mut metadata, 2, &tool);
+    put_bytes(&mut metadata, 3, export.project_root.as_bytes());
+    put_uint(&mut metadata, 4, 1); // TextEncoding.UTF8
+
+    let mut out = Vec::new();
+    put_bytes(&mut out, 1, &metadata);
+    for doc in &export.documents {
+        let mut document = Vec::new();
+        put_bytes(&mut document, 1, doc.path.as_bytes());
+        for occ in &doc.occurrences {
+            let mut occurrence = Vec::new();
+            put_bytes(&mut occurrence, 1, &packed(&[occ.line, occ.start, occ.end]));
+            put_bytes(&mut occurrence, 2, export.symbols[occ.symbol].symbol.as_bytes());
+            put_uint(&mut occurrence, 3, occ.definition as u64); // SymbolRole.Definition
+            if let Some((first, last)) = occ.enclosing {
+                put_bytes(&mut occurrence, 7, &packed(&[first, 0, last + 1, 0]));
+            }
+            put_bytes(&mut document, 2, &occurrence);
+        }
+        for &id in &doc.symbols {
+            let symbol = &export.symbols[id];
+            let mut info = Vec::new();
+            put_bytes(&mut info, 1, symbol.symbol.as_bytes());
+            for text in &symbol.documentation {
+                put_bytes(&mut info, 3, text.as_bytes());
+            }
+            for &base in &symbol.implements {
+                let mut relationship = Vec::new();
+                put_bytes(&mut relationship, 1, export.symbols[base].symbol.as_bytes());
+                put_uint(&mut relationship, 3, 1); // is_implementation
+                put_bytes(&mut info, 4, &relationship);
+            }
+            put_uint(&mut info, 5, scip_kind(symbol.kind));
+            put_bytes(&mut info, 6, symbol.name.as_bytes());
+            put_bytes(&mut document, 3, &info);
+        }
+        put_bytes(&mut document, 4, doc.language.scip_name().as_bytes());
+        put_uint(&mut document, 6, 3); // PositionEncoding.UTF32CodeUnitOffsetFromLineStart
+        put_bytes(&mut out, 2, &document);
+    }
+    out
+}
+
+// ─── LSIF ────────────────────────────────────────────────────────────
+
+/// Vertices and edges of an LSIF dump, numbered in emission order.
+struct LsifWriter {
+    lines: Vec<String>,
+}
+
+impl LsifWriter {
+    fn emit(&mut self, kind: &str, label: &str, mut fields: Value) -> u64 {
+        let id = self.lines.len() as u64 + 1;
+        fields["id"] = json!(id);
+        fields["type"] = json!(kind);
+        fields["label"] = json!(label);
+        self.lines.push(fields.to_string());
+        id
+    }
+
+    fn vertex(&mut self, label: &str, fields: Value) -> u64 {
+        self.emit("vertex", label, fields)
+    }
+
+    fn edge(&mut self, label: &str, out_v: u64, in_v: u64) {
+        self.emit("edge", label, json!({ "outV": out_v, "inV": in_v }));
+    }
+}
+
+/// `export` as an LSIF 0.6 dump, one JSON object per line. A call site resolving to
+/// several overloads references the first of them, as an LSIF range has one result set.
+pub fn to_lsif(export: &ExportIndex) -> String {
+    let mut w = LsifWriter { lines: Vec::new() };
+    w.vertex("metaData", json!({
+        "version": "0.6.0",
+        "projectRoot": export.project_root,
+        "positionEncoding": "utf-16",
+        "toolInfo": { "name": "search", "version": env!("CARGO_PKG_VERSION") },
+    }));
+    let kind = export.documents.first().map_or("csharp", |d| d.language.language_id());
+    let project = w.vertex("project", json!({ "kind": kind }));
+
+    // Per symbol: result set, definition and reference results
+    let mut results: Vec<(u64, u64, u64)> = Vec::with_capacity(export.symbols.len());
+    for symbol in &export.symbols {
+        let result_set = w.vertex("resultSet", json!({}));
+        let moniker = w.vertex("moniker", json!({ "scheme": "search", "identifier": symbol.symbol, "kind": "export", "unique": "workspace" }));
+        w.edge("moniker", result_set, moniker);
+        if !symbol.documentation.is_empty() {
+            let hover = w.vertex("hoverResult", json!({ "result": { "contents": symbol.documentation.iter()
+                .map(|text| json!({ "kind": "markdown", "value": text })).collect::<Vec<_>>() } }));
+            w.edge("textDocument/hover", result_set, hover);
+        }
+        let definitions = w.vertex("definitionResult", json!({}));
+        w.edge("textDocument/definition", result_set, definitions);
+        let references = w.vertex("referenceResult", json!({}));
+        w.edge("textDocument/references", result_set, references);
+        results.push((result_set, definitions, references));
+    }
+
+    let mut documents = Vec::with_capacity(export.documents.len());
+    for doc in &export.documents {
+        let document = w.vertex("document", json!({
+            "uri": format!("{}{}", export.project_root, doc.path),
+            "languageId": doc.language.language_id(),
+        }));
+        documents.push(document);
+        let mut ranges = Vec::new();
+        // (symbol, is definition) → ranges, for the item edges
+        let mut items: BTreeMap<(usize, bool), Vec<u64>> = BTreeMap::new();
+        let mut last: Option<(u32, u32)> = None;
+        for occ in &doc.occurrences {
+            if last == Some((occ.line, occ.start)) {
+                continue;
+            }
+            last = Some((occ.line, occ.start));
+            let range = w.vertex("range", json!({
+                "start": { "line": occ.line, "character": occ.start },
+                "end": { "line": occ.line, "character": occ.end },
+            }));
+            w.edge("next", range, results[occ.symbol].0);
+            ranges.push(range);
+            items.entry((occ.symbol, occ.definition)).or_default().push(range);
+        }
+        if !ranges.is_empty() {
+            w.emit("edge", "contains", json!({ "outV": document, "inVs": ranges }));
+        }
+        for ((symbol, definition), ranges) in items {
+            let (_, definitions, references) = results[symbol];
+            if definition {
+                w.emit("edge", "item", json!({ "outV": definitions, "inVs": ranges, "shard": document }));
+            }
+            let property = if definition { "definitions" } else { "references" };
+            w.emit("edge", "item", json!({ "outV": references, "inVs": ranges, "shard": document, "property": property }));
+        }
+    }
+    if !documents.is_empty() {
+        w.emit("edge", "contains", json!({ "outV": project, "inVs": documents }));
+    }
+    let mut out = w.lines.join("\n");
+    out.push('\n');
+    out
+}
@@ -0,0 +1,495 @@
+//! External symbol data: definitions imported from ctags tags files or SCIP indexes,
+//! for languages without a built-in parser (`def-index --import`).
+//!
+//! Tags files may be classic (`name<TAB>file<TAB>excmd;"<TAB>fields`) or Universal
+//! Ctags JSON lines (`--output-format=json`); entries need a line number (`--fields=+n`,
+//! plus `+e` for end lines). A SCIP index contributes its definition occurrences, named
+//! and nested by their symbol's descriptors. Relative paths are resolved against the
+//! indexed directory. Files the built-in parsers already produced definitions for keep
+//! those; imported entries carry no call sites or code stats.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::incremental::add_definition;
+use super::types::{DefinitionEntry, DefinitionIndex, DefinitionKind};
+use crate::clean_path;
+
+/// Definitions of one file of an external symbol source, keyed by the path as written.
+type ImportedFiles = BTreeMap<String, Vec<DefinitionEntry>>;
+
+/// Counts of one `--import` run.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ImportSummary {
+    pub definitions: usize,
+    pub files: usize,
+    /// Files skipped because a built-in parser already handled them
+    pub skipped_files: usize,
+}
+
+fn entry(name: &str, kind: DefinitionKind, line_start: u32, line_end: u32, parent: Option<String>) -> DefinitionEntry {
+    DefinitionEntry {
+        file_id: 0,
+        name: name.to_string(),
+        kind,
+        line_start,
+        line_end: line_end.max(line_start),
+        parent,
+        signature: None,
+        modifiers: Vec::new(),
+        attributes: Vec::new(),
+        base_types: Vec::new(),
+    }
+}
+
+// ─── ctags ───────────────────────────────────────────────────────────
+
+fn is_type_scope(scope_kind: &str) -> bool {
+    matches!(scope_kind, "class" | "struct" | "interface" | "trait" | "protocol" | "enum" | "record" | "union" | "object" | "implementation")
+}
+
+/// Definition kind of a ctags kind, full name or one-letter; `in_type` when the tag is
+/// scoped to a class-like type. None for kinds with no counterpart (modules, macros...).
+fn ctags_kind(kind: &str, in_type: bool, has_signature: bool) -> Option<DefinitionKind> {
+    Some(match kind {
+        "class" | "c" => DefinitionKind::Class,
+        "interface" | "i" | "trait" | "protocol" => DefinitionKind::Interface,
+        "struct" | "s" => DefinitionKind::Struct,
+        "enum" | "g" => DefinitionKind::Enum,
+        "enumerator" | "e" | "enumConstant" => DefinitionKind::EnumMember,
+        "record" => DefinitionKind::Record,
+        "method" | "singletonMethod" => DefinitionKind::Method,
+        "constructor" => DefinitionKind::Constructor,
+        "function" | "f" | "func" | "subroutine" | "procedure" if in_type => DefinitionKind::Method,
+        "function" | "f" | "func" | "subroutine" | "procedure" => DefinitionKind::Function,
+        "member" | "m" if has_signature => DefinitionKind::Method,
+        "member" | "m" | "field" => DefinitionKind::Field,
+        "property" => DefinitionKind::Property,
+        "event" => DefinitionKind::Event,
+        "delegate" => DefinitionKind::Delegate,
+        "variable" | "v" | "var" | "constant" | "const" if in_type => DefinitionKind::Field,
+        "variable" | "v" | "var" | "constant" | "const" => DefinitionKind::Variable,
+        "typedef" | "t" | "type" | "alias" | "typealias" => DefinitionKind::TypeAlias,
+        _ => return None,
+    })
+}
+
+/// Innermost name of a ctags scope (`Outer.Inner`, `ns::Outer`).
+fn scope_name(scope: &str) -> String {
+    scope.rsplit(['.', ':']).find(|s| !s.is_empty()).unwrap_or(scope).to_string()
+}
+
+/// One tag: its name, file and extension fields (`kind`, `line`, `end`, `scope`,
+/// `scopeKind`, `signature`, `access`, `inherits`).
+fn ctags_entry(name: &str, fields: &BTreeMap<String, String>) -> Option<DefinitionEntry> {
+    let line: u32 = fields.get("line")?.parse().ok()?;
+    let scope_kind = fields.get("scopeKind").map_or("", String::as_str);
+    let parent = fields.get("scope").map(|s| scope_name(s));
+    let signature = fields.get("signature");
+    let kind = ctags_kind(fields.get("kind").map_or("", String::as_str), is_type_scope(scope_kind), signature.is_some())?;
+    let end = fields.get("end").and_then(|e| e.parse().ok()).unwrap_or(line);
+    let mut def = entry(name, kind, line, end, parent);
+    def.signature = signature.map(|s| format!("{}{}", name, s));
+    def.modifiers.extend(fields.get("access").cloned());
+    if let Some(inherits) = fields.get("inherits") {
+        def.base_types = inherits.split(',').map(str::trim).filter(|b| !b.is_empty()).map(String::from).collect();
+    }
+    Some(def)
+}
+
+/// Definitions of a tags file, classic or JSON lines. Tags without a line number or
+/// with an unsupported kind are skipped.
+pub fn parse_ctags(content: &str) -> ImportedFiles {
+    let mut files = ImportedFiles::new();
+    for line in content.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() || line.starts_with("!_TAG_") {
+            continue;
+        }
+        let parsed = if line.starts_with('{') {
+            ctags_json_line(line)
+        } else {
+            ctags_classic_line(line)
+        };
+        if let Some((path, def)) = parsed {
+            files.entry(path).or_default().push(def);
+        }
+    }
+    files
+}
+
+fn ctags_json_line(line: &str) -> Option<(String, DefinitionEntry)> {
+    let tag: Value = serde_json::from_str(line).ok()?;
+    if tag["_type"] != "tag" {
+        return None;
+    }
+    let fields: BTreeMap<String, String> = tag.as_object()?.iter()
+        .filter_map(|(k, v)| Some((k.clone(), match v { Value::String(s) => s.clone(), Value::Number(n) => n.to_string(), _ => return None })))
+        .collect();
+    Some((fields.get("path")?.clone(), ctags_entry(fields.get("name")?, &fields)?))
+}
+
+fn ctags_classic_line(line: &str) -> Option<(String, DefinitionEntry)> {
+    let mut parts = line.splitn(3, '\t');
+    let (name, path, rest) = (parts.next()?, parts.next()?, parts.next()?);
+    // The ex command (a line number or a search pattern) ends at `;"`
+    let (excmd, extension) = rest.split_once(";\"").unwrap_or((rest, ""));
+    let mut fields = BTreeMap::new();
+    if let Ok(n) = excmd.trim().parse::<u32>() {
+        fields.insert("line".to_string(), n.to_string());
+    }
+    for field in extension.split('\t').filter(|f| !f.is_empty()) {
+        match field.split_once(':') {
+            Some(("kind", v)) => { fields.insert("kind".to_string(), v.to_string()); }
+            Some(("scope", v)) => {
+                // Universal Ctags `scope:class:Outer`
+                let (kind, name) = v.split_once(':').unwrap_or(("", v));
+                fields.insert("scopeKind".to_string(), kind.to_string());
+                fields.insert("scope".to_string(), name.to_string());
+            }
+            Some((key, v)) if is_type_scope(key) || matches!(key, "namespace" | "module" | "package") => {
+                fields.insert("scopeKind".to_string(), key.to_string());
+                fields.insert("scope".to_string(), v.to_string());
+            }
+            Some((key, v)) => { fields.insert(key.to_string(), v.to_string()); }
+            // Bare one-letter kind of the default field set
+            None => { fields.insert("kind".to_string(), field.to_string()); }
+        }
+    }
+    Some((path.to_string(), ctags_entry(name, &fields)?))
+}
+
+// ─── SCIP ────────────────────────────────────────────────────────────
+
+/// Field of a protobuf message: a varint, or the bytes of a length-delimited field.
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Fields of one protobuf message, in order. Fixed-width fields are skipped; a
+/// truncated message ends the iteration.
+struct Fields<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.buf.get(self.pos)?;
+            self.pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = (u64, Field<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let key = self.varint()?;
+            let field = match key & 7 {
+                0 => Field::Varint(self.varint()?),
+                2 => {
+                    let len = self.varint()? as usize;
+                    let bytes = self.buf.get(self.pos..self.pos.checked_add(len)?)?;
+                    self.pos += len;
+                    Field::Bytes(bytes)
+                }
+                1 => { self.pos += 8; continue; }
+                5 => { self.pos += 4; continue; }
+                _ => return None,
+            };
+            return Some((key >> 3, field));
+        }
+    }
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Values of a packed (or a single unpacked) repeated int32 field.
+fn int_list(field: Field, out: &mut Vec<u32>) {
+    match field {
+        Field::Varint(v) => out.push(v as u32),
+        Field::Bytes(bytes) => {
+            let mut values = Fields::new(bytes);
+            while let Some(v) = values.varint() {
+                out.push(v as u32);
+            }
+        }
+    }
+}
+
+/// Descriptor suffix of a SCIP symbol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Suffix {
+    Namespace,
+    Type,
+    Term,
+    Method,
+    Other,
+}
+
+/// Descriptors of a global SCIP symbol (`scheme manager package version descriptors`).
+/// None for local and malformed symbols.
+fn symbol_descriptors(symbol: &str) -> Option<Vec<(String, Suffix)>> {
+    if symbol.starts_with("local ") {
+        return None;
+    }
+    // Skip the four space-separated header parts; `  ` is an escaped space
+    let bytes = symbol.as_bytes();
+    let mut i = 0;
+    for _ in 0..4 {
+        loop {
+            match bytes.get(i)? {
+                b' ' if bytes.get(i + 1) == Some(&b' ') => i += 2,
+                b' ' => { i += 1; break; }
+                _ => i += 1,
+            }
+        }
+    }
+
+    let mut chars = symbol[i..].chars().peekable();
+    let mut out = Vec::new();
+    while let Some(&c) = chars.peek() {
+        if c == '(' || c == '[' {
+            // Parameter or type parameter descriptor
+            let close = if c == '(' { ')' } else { ']' };
+            for c in chars.by_ref() {
+                if c == close { break; }
+            }
+            out.push((String::new(), Suffix::Other));
+            continue;
+        }
+        let mut name = String::new();
+        if c == '`' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                if c == '`' {
+                    if chars.peek() == Some(&'`') { chars.next(); } else { break; }
+                }
+                name.push(c);
+            }
+        } else {
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || matches!(c, '_' | '+' | '-' | '$')) {
+                name.push(c);
+                chars.next();
+            }
+        }
+        let suffix = match chars.next()? {
+            '/' => Suffix::Namespace,
+            '#' => Suffix::Type,
+            '.' => Suffix::Term,
+            ':' | '!' => Suffix::Other,
+            '(' => {
+                // Method disambiguator, then `.`
+                for c in chars.by_ref() {
+                    if c == ')' { break; }
+                }
+                if chars.next()? != '.' { return None; }
+                Suffix::Method
+            }
+            _ => return None,
+        };
+        out.push((name, suffix));
+    }
+    Some(out)
+}
+
+/// Definition kind of a SCIP `SymbolInformation.Kind`, if it has one.
+fn kind_from_scip(kind: u64) -> Option<DefinitionKind> {
+    Some(match kind {
+        7 => DefinitionKind::Class,
+        9 => DefinitionKind::Constructor,
+        11 => DefinitionKind::Enum,
+        12 => DefinitionKind::EnumMember,
+        13 => DefinitionKind::Event,
+        15 => DefinitionKind::Field,
+        17 => DefinitionKind::Function,
+        21 | 42 | 53 => DefinitionKind::Interface,
+        26 => DefinitionKind::Method,
+        41 => DefinitionKind::Property,
+        49 => DefinitionKind::Struct,
+        54 | 55 => DefinitionKind::TypeAlias,
+        8 | 61 => DefinitionKind::Variable,
+        _ => return None,
+    })
+}
+
+/// `SymbolInformation` fields used on import.
+#[derive(Default)]
+struct SymbolInfo {
+    kind: u64,
+    signature: Option<String>,
+    implements: Vec<String>,
+}
+
+/// Definitions of a SCIP index: one per definition occurrence of a global symbol.
+pub fn parse_scip(bytes: &[u8]) -> ImportedFiles {
+    let mut files = ImportedFiles::new();
+    for (field, value) in Fields::new(bytes) {
+        let (2, Field::Bytes(document)) = (field, value) else { continue };
+        let mut path = String::new();
+        let mut occurrences: Vec<(Vec<u32>, String, Vec<u32>)> = Vec::new();
+        let mut symbols: BTreeMap<String, SymbolInfo> = BTreeMap::new();
+        for (field, value) in Fields::new(document) {
+            match (field, value) {
+                (1, Field::Bytes(p)) => path = text(p),
+                (2, Field::Bytes(occurrence)) => {
+                    let (mut range, mut symbol, mut roles, mut enclosing) = (Vec::new(), String::new(), 0, Vec::new());
+                    for (field, value) in Fields::new(occurrence) {
+                        match (field, value) {
+                            (1, v) => int_list(v, &mut range),
+                            (2, Field::Bytes(s)) => symbol = text(s),
+                            (3, Field::Varint(r)) => roles = r,
+                            (7, v) => int_list(v, &mut enclosing),
+                            _ => {}
+                        }
+                    }
+                    // SymbolRole.Definition
+                    if roles & 1 != 0 && range.len() >= 3 {
+                        occurrences.push((range, symbol, enclosing));
+                    }
+                }
+                (3, Field::Bytes(information)) => {
+                    let (mut symbol, mut info) = (String::new(), SymbolInfo::default());
+                    for (field, value) in Fields::new(information) {
+                        match (field, value) {
+                            (1, Field::Bytes(s)) => symbol = text(s),
+                            (3, Field::Bytes(doc)) if info.signature.is_none() => {
+                                let doc = text(doc);
+                                if let Some(code) = doc.strip_prefix("```") {
+                                    let code = code.split_once('\n').map_or("", |(_, c)| c);
+                                    info.signature = Some(code.trim_end().trim_end_matches("```").trim().to_string());
+                                }
+                            }
+                            (4, Field::Bytes(relationship)) => {
+                                let (mut target, mut implementation) = (String::new(), false);
+                                for (field, value) in Fields::new(relationship) {
+                                    match (field, value) {
+                                        (1, Field::Bytes(s)) => target = text(s),
+                                        (3, Field::Varint(v)) => implementation = v != 0,
+                                        _ => {}
+                                    }
+                                }
+                                if implementation {
+                                    info.implements.push(target);
+                                }
+                            }
+                            (5, Field::Varint(k)) => info.kind = k,
+                            _ => {}
+                        }
+                    }
+                    symbols.insert(symbol, info);
+                }
+                _ => {}
+            }
+        }
+
+        for (range, symbol, enclosing) in occurrences {
+            let Some(descriptors) = symbol_descriptors(&symbol) else { continue };
+            let Some(((name, suffix), outer)) = descriptors.split_last() else { continue };
+            let parent = outer.last().filter(|(_, s)| *s == Suffix::Type).map(|(n, _)| n.clone());
+            let info = symbols.get(&symbol);
+            let kind = match (info.and_then(|i| kind_from_scip(i.kind)), suffix) {
+                (Some(kind), _) => kind,
+                (None, Suffix::Type) => DefinitionKind::Class,
+                (None, Suffix::Method) if parent.is_some() => DefinitionKind::Method,
+                (None, Suffix::Method) => DefinitionKind::Function,
+                (None, Suffix::Term) if parent.is_some() => DefinitionKind::Field,
+                (None, Suffix::Term) => DefinitionKind::Variable,
+                (None, Suffix::Namespace | Suffix::Other) => continue,
+            };
+            // Whole-definition lines from the enclosing range when there is one
+            let (line_start, line_end) = match enclosing.as_slice() {
+                [start, _, end, 0] if end > start => (start + 1, *end),
+                [start, _, end, _] => (start + 1, end + 1),
+                [line, _, _] => (line + 1, line + 1),
+                _ => (range[0] + 1, if range.len() == 4 { range[2] + 1 } else { range[0] + 1 }),
+            };
+            let mut def = entry(name, kind, line_start, line_end, parent);
+            if let Some(info) = info {
+                def.signature = info.signature.clone();
+                def.base_types = info.implements.iter()
+                    .filter_map(|s| symbol_descriptors(s)?.pop().map(|(n, _)| n))
+                    .collect();
+            }
+            files.entry(path.clone()).or_default().push(def);
+        }
+    }
+    files
+}
+
+// ─── Merge ───────────────────────────────────────────────────────────
+
+/// Add the definitions of the tags files and SCIP indexes in `sources` to `index`,
+/// and record the sources for rebuilds. A source is SCIP when it ends in `.scip` or
+/// is not text. Unreadable sources are reported and skipped.
+pub fn import_external_definitions(index: &mut DefinitionIndex, sources: &[String]) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    for source in sources {
+        let bytes = match std::fs::read(source) {
+            Ok(b) => b,
+            Err(e) => {
+                eprintln!("[def-index] WARNING: cannot read import file '{}': {}", source, e);
+                continue;
+            }
+        };
+        let files = match std::str::from_utf8(&bytes) {
+            Ok(content) if !source.to_lowercase().ends_with(".scip") => parse_ctags(content),
+            _ => parse_scip(&bytes),
+        };
+        let before = summary;
+        merge_files(index, files, &mut summary);
+        eprintln!("[def-index] Imported {} definitions in {} files from {} ({} files already parsed, skipped)",
+            summary.definitions - before.definitions, summary.files - before.files, source,
+            summary.skipped_files - before.skipped_files);
+        let absolute = std::fs::canonicalize(source).map_or_else(|_| source.clone(), |p| clean_path(&p.to_string_lossy()));
+        if !index.imports.contains(&absolute) {
+            index.imports.push(absolute);
+        }
+    }
+    summary
+}
+
+fn merge_files(index: &mut DefinitionIndex, files: ImportedFiles, summary: &mut ImportSummary) {
+    for (path, defs) in files {
+        let full = if Path::new(&path).is_absolute() { PathBuf::from(&path) } else { Path::new(&index.root).join(&path) };
+        let full = PathBuf::from(clean_path(&crate::path_to_string(&full)));
+        let file_id = match index.path_to_id.get(&full) {
+            Some(&id) if index.file_index.contains_key(&id) => {
+                summary.skipped_files += 1;
+                continue;
+            }
+            Some(&id) => id,
+            None => {
+                let id = index.files.len() as u32;
+                index.files.push(crate::path_to_string(&full));
+                index.path_to_id.insert(full, id);
+                id
+            }
+        };
+        index.empty_file_ids.retain(|(id, _)| *id != file_id);
+        summary.files += 1;
+        summary.definitions += defs.len();
+        for mut def in defs {
+            def.file_id = file_id;
+            add_definition(index, file_id, def);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Type hierarchy: walks `base_types` / `base_type_index` up (supertypes) and down
+//! (subclasses and implementations) from a class or interface. The parts of a partial
+//! type are one node, shown at the primary part, with the base types of all parts.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use super::partials::{merged_base_types, primary_part};
+use super::types::{DefinitionEntry, DefinitionIndex, DefinitionKind};
+
+/// A type in the hierarchy. `def` is `None` for base types that are not defined in the
+/// indexed code (e.g. framework classes such as `ControllerBase`).
+#[derive(Debug)]
+pub struct HierarchyNode<'a> {
+    pub name: String,
+    pub def: Option<&'a DefinitionEntry>,
+    pub children: Vec<HierarchyNode<'a>>,
+}
+
+/// Strip generic arguments and namespace qualification from a base type as written
+/// in source: `Foo.IRepository<User>` → `IRepository`.
+pub fn base_type_name(base_type: &str) -> &str {
+    let without_generics = base_type.split('<').next().unwrap_or(base_type).trim();
+    without_generics.rsplit('.').next().unwrap_or(without_generics)
+}
+
+fn is_type_kind(kind: &DefinitionKind) -> bool {
+    matches!(kind,
+        DefinitionKind::Class | DefinitionKind::Interface | DefinitionKind::Struct | DefinitionKind::Record)
+}
+
+/// Indices of class/interface/struct/record definitions with this name (case-insensitive).
+pub fn find_type_definitions(index: &DefinitionIndex, name: &str) -> Vec<u32> {
+    index.name_index.get(&name.to_lowercase())
+        .map(|ids| ids.iter().copied()
+            .filter(|&i| index.definitions.get(i as usize).is_some_and(|d| is_type_kind(&d.kind)))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Builds supertype/subtype trees with a shared depth limit and node budget.
+///
+/// Each type is expanded at most once per walk, which guards against cycles from
+/// name collisions (`class A : B` and `class B : A` in different namespaces).
+pub struct HierarchyWalker<'a> {
+    index: &'a DefinitionIndex,
+    /// base type name (lowercased, generics and namespace stripped) -> deriving definitions
+    derived: HashMap<String, Vec<u32>>,
+    max_depth: usize,
+    remaining: usize,
+    visited: HashSet<u32>,
+    /// Set when the node budget ran out before the walk finished.
+    pub truncated: bool,
+}
+
+impl<'a> HierarchyWalker<'a> {
+    pub fn new(index: &'a DefinitionIndex, max_depth: usize, max_nodes: usize) -> Self {
+        let mut derived: HashMap<String, Vec<u32>> = HashMap::new();
+        for (base, ids) in &index.base_type_index {
+            derived.entry(base_type_name(base).to_string()).or_default().extend(ids.iter().copied());
+        }
+        for ids in derived.values_mut() {
+            ids.sort_unstable();
+            ids.dedup();
+        }
+        Self { index, derived, max_depth, remaining: max_nodes, visited: HashSet::new(), truncated: false }
+    }
+
+    /// Types that list `name` as a base type, recursively (subclasses and implementations).
+    /// `root` is the definition of `name` itself, if it is in the index.
+    pub fn subtypes(&mut self, name: &str, root: Option<u32>) -> Vec<HierarchyNode<'a>> {
+        self.visited.clear();
+        self.visited.extend(root.map(|id| primary_part(self.index, id)));
+        self.subtypes_at(name, 0)
+    }
+
+    /// Base classes and implemented interfaces of the definition `root`, recursively.
+    pub fn supertypes(&mut self, root: u32) -> Vec<HierarchyNode<'a>> {
+        self.visited.clear();
+        self.visited.insert(primary_part(self.index, root));
+        if self.index.definitions.get(root as usize).is_none() {
+            return Vec::new();
+        }
+        self.supertypes_at(root, 0)
+    }
+
+    fn take_node(&mut self) -> bool {
+        if self.remaining == 0 {
+            self.truncated = true;
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+
+    fn subtypes_at(&mut self, name: &str, depth: usize) -> Vec<HierarchyNode<'a>> {
+        if depth >= self.max_depth {
+            return Vec::new();
+        }
+        let index = self.index;
+        let ids = self.derived.get(&name.to_lowercase()).cloned().unwrap_or_default();
+        let mut nodes = Vec::new();
+        for id in ids {
+            let id = primary_part(index, id);
+            let Some(def) = index.definitions.get(id as usize) else { continue };
+            if !is_type_kind(&def.kind) || !self.visited.insert(id) {
+                continue;
+            }
+            if !self.take_node() {
+                break;
+            }
+            let children = self.subtypes_at(&def.name, depth + 1);
+            nodes.push(HierarchyNode { name: def.name.clone(), def: Some(def), children });
+        }
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+        nodes
+    }
+
+    fn supertypes_at(&mut self, def_id: u32, depth: usize) -> Vec<HierarchyNode<'a>> {
+        if depth >= self.max_depth {
+            return Vec::new();
+        }
+        let index = self.index;
+        let mut nodes = Vec::new();
+        for base in merged_base_types(index, def_id) {
+            let name = base_type_name(base);
+            let ids = find_type_definitions(index, name);
+            if ids.is_empty() {
+                if !self.take_node() {
+                    break;
+                }
+                nodes.push(HierarchyNode { name: base.clone(), def: None, children: Vec::new() });
+                continue;
+            }
+            for id in ids {
+                let id = primary_part(index, id);
+                if !self.visited.insert(id) {
+                    continue;
+                }
+                if !self.take_node() {
+                    return nodes;
+                }
+                let base_def = &index.definitions[id as usize];
+                let children = self.supertypes_at(id, depth + 1);
+                nodes.push(HierarchyNode { name: base_def.name.clone(), def: Some(base_def), children });
+            }
+        }
+        nodes
+    }
+}
+
+/// Total number of nodes in the tree.
+pub fn hierarchy_len(nodes: &[HierarchyNode]) -> usize {
+    nodes.iter().map(|n| 1 + hierarchy_len(&n.children)).sum()
+}
+
+/// JSON form used by the `search_type_hierarchy` MCP tool.
+pub fn hierarchy_to_json(index: &DefinitionIndex, nodes: &[HierarchyNode]) -> Value {
+    Value::Array(nodes.iter().map(|n| {
+        let mut obj = match n.def {
+            Some(def) => json!({
+                "name": def.name,
+                "kind": def.kind.as_str(),
+                "file": index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or(""),
+                "lines": format!("{}-{}", def.line_start, def.line_end),
+            }),
+            None => json!({ "name": n.name, "external": true }),
+        };
+        if !n.children.is_empty() {
+            obj["children"] = hierarchy_to_json(index, &n.children);
+        }
+        obj
+    }).collect())
+}
+
+/// Indented text form for the CLI: `kind name  file:line`, one node per line.
+pub fn render_hierarchy_text(index: &DefinitionIndex, nodes: &[HierarchyNode]) -> String {
+    let mut out = String::new();
+    render_into(index, nodes, 0, &mut out);
+    out
+}
+
+fn render_into(index: &DefinitionIndex, nodes: &[HierarchyNode], depth: usize, out: &mut String) {
+    for n in nodes {
+        out.push_str(&"  ".repeat(depth + 1));
+        match n.def {
+            Some(def) => {
+                let file = index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or("?");
+                out.push_str(&format!("{} {}  {}:{}\n", def.kind, def.name, file, def.line_start));
+            }
+            None => out.push_str(&format!("{} (external)\n", n.name)),
+        }
+        render_into(index, &n.children, depth + 1, out);
+    }
+}
@@ -0,0 +1,132 @@
+//! Impact analysis: the test methods that reach a method through the caller graph.
+//!
+//! Callers are found by resolving every recorded call site once (the way
+//! `search_callers` direction=down resolves them) and reversing the edges. The walk
+//! goes up from the changed methods and stops at tests; what is above a test is not
+//! walked.
+
+use std::collections::{HashMap, VecDeque};
+
+use super::types::{DefinitionEntry, DefinitionIndex};
+use crate::mcp::handlers::resolve_call_site;
+
+/// Attributes that mark a test method when none are given: NUnit, xUnit and MSTest.
+pub const DEFAULT_TEST_ATTRIBUTES: &[&str] = &["Test", "TestCase", "TestCaseSource", "Fact", "Theory", "TestMethod", "DataTestMethod"];
+
+/// What makes a definition a test.
+#[derive(Debug, Clone)]
+pub struct TestMatcher {
+    /// Attribute names, case-insensitive, without arguments or the `Attribute` suffix
+    pub attributes: Vec<String>,
+    /// Path substrings, case-insensitive. Empty: the built-in test path heuristic
+    pub paths: Vec<String>,
+}
+
+impl Default for TestMatcher {
+    fn default() -> Self {
+        Self { attributes: DEFAULT_TEST_ATTRIBUTES.iter().map(|a| a.to_string()).collect(), paths: Vec::new() }
+    }
+}
+
+impl TestMatcher {
+    /// A test method: a test attribute, or any callable in a test file.
+    pub fn is_test(&self, def: &DefinitionEntry, path: &str) -> bool {
+        let attribute = def.attributes.iter().any(|attr| {
+            let name = attr.split('(').next().unwrap_or(attr).trim();
+            let name = name.strip_suffix("Attribute").unwrap_or(name);
+            self.attributes.iter().any(|a| a.eq_ignore_ascii_case(name))
+        });
+        attribute || if self.paths.is_empty() {
+            crate::mcp::handlers::utils::is_test_path(path)
+        } else {
+            let lower = path.replace('\\', "/").to_lowercase();
+            self.paths.iter().any(|p| lower.contains(&p.replace('\\', "/").to_lowercase()))
+        }
+    }
+}
+
+/// A test reached from a changed method.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpactedTest {
+    pub def_id: u32,
+    /// Calls between the test and the changed method (1 = calls it directly)
+    pub depth: usize,
+    /// The methods in between, from the test's callee down to the changed method
+    pub via: Vec<u32>,
+}
+
+/// Result of [`affected_tests`].
+#[derive(Debug, Default)]
+pub struct ImpactReport {
+    pub tests: Vec<ImpactedTest>,
+    /// Methods the walk went through, the changed ones included
+    pub visited: usize,
+    /// The depth limit stopped the walk with callers left to visit
+    pub truncated: bool,
+}
+
+/// Callable definition -> the callables that call it.
+pub fn callers_by_callee(index: &DefinitionIndex) -> HashMap<u32, Vec<u32>> {
+    let mut callers: HashMap<u32, Vec<u32>> = HashMap::new();
+    for def_ids in index.file_index.values() {
+        for &def_id in def_ids {
+            let Some(calls) = index.method_calls.get(&def_id) else { continue };
+            let parent = index.definitions[def_id as usize].parent.as_deref();
+            for call in calls {
+                for callee in resolve_call_site(call, index, parent) {
+                    let list = callers.entry(callee).or_default();
+                    if !list.contains(&def_id) {
+                        list.push(def_id);
+                    }
+                }
+            }
+        }
+    }
+    callers
+}
+
+/// Tests that call `targets`, directly or through up to `max_depth` calls, nearest
+/// first. Tests among `targets` are reported at depth 0.
+pub fn affected_tests(index: &DefinitionIndex, targets: &[u32], matcher: &TestMatcher, max_depth: usize) -> ImpactReport {
+    let callers = callers_by_callee(index);
+    let is_test = |id: u32| {
+        let def = &index.definitions[id as usize];
+        matcher.is_test(def, index.files.get(def.file_id as usize).map_or("", |s| s.as_str()))
+    };
+
+    let mut report = ImpactReport::default();
+    // def_id -> (depth, the callee it was reached from)
+    let mut reached: HashMap<u32, (usize, Option<u32>)> = HashMap::new();
+    let mut queue: VecDeque<u32> = VecDeque::new();
+    for &target in targets {
+        if reached.insert(target, (0, None)).is_none() {
+            queue.push_back(target);
+        }
+    }
+    while let Some(id) = queue.pop_front() {
+        let (depth, _) = reached[&id];
+        if is_test(id) {
+            let mut via = Vec::new();
+            let mut next = reached[&id].1;
+            while let Some(callee) = next {
+                via.push(callee);
+                next = reached[&callee].1;
+            }
+            report.tests.push(ImpactedTest { def_id: id, depth, via });
+            continue;
+        }
+        let Some(ids) = callers.get(&id) else { continue };
+        if depth == max_depth {
+            report.truncated |= ids.iter().any(|c| !reached.contains_key(c));
+            continue;
+        }
+        for &caller in ids {
+            if let std::collections::hash_map::Entry::Vacant(e) = reached.entry(caller) {
+                e.insert((depth + 1, Some(id)));
+                queue.push_back(caller);
+            }
+        }
+    }
+    report.visited = reached.len();
+    report
+}
@@ -12,7 +12,7 @@ use super::parser_typescript::parse_typescript_definitions;
 /// Update definitions for a single file (incremental).
 /// Removes old definitions for the file, parses it again, adds new ones.
 pub fn update_file_definitions(index: &mut DefinitionIndex, path: &Path) {
-    let path_str = path.to_string_lossy().to_string();
+    let path_str = crate::path_to_string(path);
 
     let (content, was_lossy) = match read_file_lossy(path) {
         Ok(r) => r,
@@ -22,10 +22,6 @@ pub fn update_file_definitions(index: &mut DefinitionIndex, path: &Path) {
         warn!("File contains non-UTF8 bytes (lossy conversion applied): {}", path_str);
     }
 
-    let ext = path.extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
-
     // Get or assign file_id
     let file_id = if let Some(&id) = index.path_to_id.get(path) {
         // Existing file — remove old definitions
@@ -39,14 +35,20 @@ pub fn update_file_definitions(index: &mut DefinitionIndex, path: &Path) {
         id
     };
 
-    // Parse the file
-    let ext_lower = ext.to_lowercase();
+    // Parse the file; extensionless scripts are parsed as their detected language
+    let ext_lower = crate::language::file_language(path).unwrap_or_default();
     let (file_defs, file_calls, file_stats) = match ext_lower.as_str() {
         "cs" => {
+            index.di_registrations.extend(super::extract_di_registrations(&content, file_id));
             let mut cs_parser = tree_sitter::Parser::new();
             cs_parser.set_language(&tree_sitter_c_sharp::LANGUAGE.into()).ok();
             {
                 let (defs, calls, stats, _ext) = parse_csharp_definitions(&mut cs_parser, &content, file_id);
+                index.endpoints.extend(super::extract_endpoints(&defs, &content, file_id));
+                let docs = super::extract_doc_comments(&defs, &content);
+                if !docs.is_empty() {
+                    index.doc_comments.insert(file_id, docs);
+                }
                 (defs, calls, stats)
             }
         }
@@ -63,43 +65,16 @@ pub fn update_file_definitions(index: &mut DefinitionIndex, path: &Path) {
         _ => (Vec::new(), Vec::new(), Vec::new()),
     };
 
+    let imports = super::extract_file_imports(&ext_lower, &content);
+    if !imports.is_empty() {
+        index.file_imports.insert(file_id, imports);
+    }
+
     // Add new definitions to index
     let base_def_idx = index.definitions.len() as u32;
 
     for def in file_defs {
-        let def_idx = index.definitions.len() as u32;
-
-        index.name_index.entry(def.name.to_lowercase())
-            .or_default()
-            .push(def_idx);
-
-        index.kind_index.entry(def.kind)
-            .or_default()
-            .push(def_idx);
-
-        {
-            let mut seen_attrs = std::collections::HashSet::new();
-            for attr in &def.attributes {
-                let attr_name = attr.split('(').next().unwrap_or(attr).trim().to_lowercase();
-                if seen_attrs.insert(attr_name.clone()) {
-                    index.attribute_index.entry(attr_name)
-                        .or_default()
-                        .push(def_idx);
-                }
-            }
-        }
-
-        for bt in &def.base_types {
-            index.base_type_index.entry(bt.to_lowercase())
-                .or_default()
-                .push(def_idx);
-        }
-
-        index.file_index.entry(file_id)
-            .or_default()
-            .push(def_idx);
-
-        index.definitions.push(def);
+        add_definition(index, file_id, def);
     }
 
     // Add call sites for new definitions
@@ -115,10 +90,57 @@ pub fn update_file_definitions(index: &mut DefinitionIndex, path: &Path) {
         let global_idx = base_def_idx + local_idx as u32;
         index.code_stats.insert(global_idx, stats);
     }
+
+    super::link_partial_types(index);
+}
+
+/// Append `def` to the index of `file_id` and its name, kind, attribute and base-type
+/// lookups. Returns its def_idx.
+pub(crate) fn add_definition(index: &mut DefinitionIndex, file_id: u32, def: DefinitionEntry) -> u32 {
+    let def_idx = index.definitions.len() as u32;
+    index_definition(index, file_id, def_idx, &def);
+    index.definitions.push(def);
+    def_idx
+}
+
+/// Add the definition `def_idx` of `file_id` to the name, kind, attribute, base-type
+/// and file lookups.
+pub(crate) fn index_definition(index: &mut DefinitionIndex, file_id: u32, def_idx: u32, def: &DefinitionEntry) {
+    index.name_index.entry(def.name.to_lowercase())
+        .or_default()
+        .push(def_idx);
+
+    index.kind_index.entry(def.kind)
+        .or_default()
+        .push(def_idx);
+
+    let mut seen_attrs = std::collections::HashSet::new();
+    for attr in &def.attributes {
+        let attr_name = attr.split('(').next().unwrap_or(attr).trim().to_lowercase();
+        if seen_attrs.insert(attr_name.clone()) {
+            index.attribute_index.entry(attr_name)
+                .or_default()
+                .push(def_idx);
+        }
+    }
+
+    for bt in &def.base_types {
+        index.base_type_index.entry(bt.to_lowercase())
+            .or_default()
+            .push(def_idx);
+    }
+
+    index.file_index.entry(file_id)
+        .or_default()
+        .push(def_idx);
 }
 
 /// Remove all definitions for a file from the index
 pub fn remove_file_definitions(index: &mut DefinitionIndex, file_id: u32) {
+    index.file_imports.remove(&file_id);
+    index.di_registrations.retain(|r| r.file_id != file_id);
+    index.endpoints.retain(|e| e.file_id != file_id);
+    index.doc_comments.remove(&file_id);
     let def_indices = match index.file_index.remove(&file_id) {
         Some(indices) => indices,
         None => return,
@@ -206,5 +228,18 @@ pub fn remove_file_from_def_index(index: &mut DefinitionIndex, path: &Path) {
     if let Some(&file_id) = index.path_to_id.get(path) {
         remove_file_definitions(index, file_id);
         index.path_to_id.remove(path);
+        super::link_partial_types(index);
     }
-}
\ No newline at end of file
+}
+/// Point a file's definitions at its new path after a rename, without re-parsing.
+/// A file previously indexed at `new` is removed first (the rename replaced it).
+/// Returns `false` if `old` is not in the index.
+pub fn rename_file_in_def_index(index: &mut DefinitionIndex, old: &Path, new: &Path) -> bool {
+    let Some(file_id) = index.path_to_id.remove(old) else { return false };
+    remove_file_from_def_index(index, new);
+    if let Some(slot) = index.files.get_mut(file_id as usize) {
+        *slot = crate::path_to_string(new);
+    }
+    index.path_to_id.insert(new.to_path_buf(), file_id);
+    true
+}
@@ -7,11 +7,45 @@ mod parser_typescript;
 mod parser_sql;
 mod storage;
 mod incremental;
+mod outline;
+mod hierarchy;
+mod dupes;
+mod deadcode;
+mod callgraph;
+mod deps;
+mod di;
+mod endpoints;
+mod impact;
+mod namespaces;
+mod partials;
+mod signatures;
+mod docs;
+mod complexity;
+mod export;
+mod external;
+mod sql_dump;
 
 // Re-export all public types and functions
 pub use types::*;
 pub use storage::*;
 pub use incremental::*;
+pub use outline::*;
+pub use hierarchy::*;
+pub use dupes::*;
+pub use deadcode::*;
+pub use callgraph::*;
+pub use deps::*;
+pub use di::*;
+pub use endpoints::*;
+pub use impact::*;
+pub use namespaces::*;
+pub use partials::*;
+pub use signatures::*;
+pub use docs::*;
+pub use complexity::*;
+pub use export::*;
+pub use external::*;
+pub use sql_dump::*;
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -20,7 +54,10 @@ use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use ignore::WalkBuilder;
 
-use crate::{clean_path, read_file_lossy};
+use crate::config::RepoConfig;
+use crate::{clean_path, read_file_lossy, WalkLimits};
+use crate::priority::{apply_to_current_thread, resolve_threads, BuildPriority, IoThrottle};
+use crate::progress::Progress;
 use parser_typescript::extract_component_metadata;
 
 // ─── Index Build ─────────────────────────────────────────────────────
@@ -38,17 +75,24 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
     let start = Instant::now();
 
     // Collect all files
+    let priority = args.build_priority;
     let mut walker = WalkBuilder::new(&dir);
     walker.hidden(false).git_ignore(true);
-    if args.threads > 0 {
-        walker.threads(args.threads);
+    let walk_limits = WalkLimits::new(args.max_depth, &args.prune_dir);
+    walk_limits.apply_to_walk(&mut walker, &dir);
+    if args.threads > 0 || priority == BuildPriority::Low {
+        walker.threads(resolve_threads(args.threads, priority));
     }
 
+    let languages = RepoConfig::load_or_default(&dir).language_map(&dir);
     let file_count = AtomicUsize::new(0);
-    let all_files: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+    let progress = Progress::start("def-index", "scanning", "files", None);
+    // (path, language): the extension the file is parsed as, detected for extensionless files
+    let all_files: std::sync::Mutex<Vec<(String, String)>> = std::sync::Mutex::new(Vec::new());
 
     walker.build_parallel().run(|| {
         Box::new(|entry| {
+            apply_to_current_thread(priority);
             let entry = match entry {
                 Ok(e) => e,
                 Err(_) => return ignore::WalkState::Continue,
@@ -57,35 +101,30 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
                 return ignore::WalkState::Continue;
             }
             let path = entry.path();
-            let ext_match = path.extension()
-                .and_then(|e| e.to_str())
-                .is_some_and(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)));
-            if !ext_match {
+            let Some(language) = languages.language(path).filter(|l| extensions.contains(l)) else {
                 return ignore::WalkState::Continue;
-            }
-            let clean = clean_path(&path.to_string_lossy());
-            all_files.lock().unwrap_or_else(|e| e.into_inner()).push(clean);
+            };
+            let clean = clean_path(&crate::path_to_string(path));
+            all_files.lock().unwrap_or_else(|e| e.into_inner()).push((clean, language));
             file_count.fetch_add(1, Ordering::Relaxed);
+            progress.inc(1);
             ignore::WalkState::Continue
         })
     });
 
-    let files: Vec<String> = crate::index::recover_mutex(all_files, "def-index");
+    let (files, file_languages): (Vec<String>, Vec<String>) = crate::index::recover_mutex(all_files, "def-index").into_iter().unzip();
     let total_files = files.len();
+    progress.phase("parsing", "files", Some(total_files));
     eprintln!("[def-index] Found {} files to parse", total_files);
     crate::index::log_memory(&format!("def-build: after file walk ({} files)", total_files));
 
     // ─── Parallel parsing ─────────────────────────────────────
-    let num_threads = if args.threads > 0 {
-        args.threads
-    } else {
-        std::thread::available_parallelism()
-            .map(|n| n.get())
-            .unwrap_or(4)
-    };
+    let num_threads = resolve_threads(args.threads, priority);
+    let io_throttle = IoThrottle::new(args.io_limit_mb);
+    let io_throttle = &io_throttle;
     let chunk_size = total_files.div_ceil(num_threads);
-    let chunks: Vec<Vec<(u32, String)>> = files.iter().enumerate()
-        .map(|(i, f)| (i as u32, f.clone()))
+    let chunks: Vec<Vec<(u32, String, String)>> = files.iter().zip(file_languages).enumerate()
+        .map(|(i, (f, lang))| (i as u32, f.clone(), lang))
         .collect::<Vec<_>>()
         .chunks(chunk_size.max(1))
         .map(|c| c.to_vec())
@@ -98,7 +137,9 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
 
     let thread_results: Vec<_> = std::thread::scope(|s| {
         let handles: Vec<_> = chunks.into_iter().map(|chunk| {
+            let progress = &progress;
             s.spawn(move || {
+                apply_to_current_thread(priority);
                 let mut cs_parser = tree_sitter::Parser::new();
                 cs_parser.set_language(&tree_sitter_c_sharp::LANGUAGE.into())
                     .expect("Error loading C# grammar");
@@ -109,29 +150,41 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
 
                 let mut chunk_defs: Vec<(u32, Vec<DefinitionEntry>, Vec<(usize, Vec<CallSite>)>, Vec<(usize, CodeStats)>)> = Vec::new();
                 let mut chunk_ext_methods: HashMap<String, Vec<String>> = HashMap::new();
+                let mut chunk_imports: Vec<(u32, FileImports)> = Vec::new();
+                let mut chunk_di: Vec<DiRegistration> = Vec::new();
+                let mut chunk_endpoints: Vec<Endpoint> = Vec::new();
+                let mut chunk_docs: Vec<(u32, Vec<DocComment>)> = Vec::new();
                 let mut errors = 0usize;
                 let mut lossy_files: Vec<String> = Vec::new();
                 let mut empty_files: Vec<(u32, u64)> = Vec::new(); // (file_id, byte_size) for files with 0 defs
 
-                for (file_id, file_path) in &chunk {
+                for (file_id, file_path, language) in &chunk {
+                    progress.inc(1);
                     let (content, was_lossy) = match read_file_lossy(Path::new(file_path)) {
                         Ok(r) => r,
                         Err(_) => { errors += 1; continue; }
                     };
+                    io_throttle.consume(content.len() as u64);
                     if was_lossy {
                         lossy_files.push(file_path.clone());
                     }
 
                     let content_len = content.len() as u64;
 
-                    let ext = Path::new(file_path.as_str())
-                        .extension()
-                        .and_then(|e| e.to_str())
-                        .unwrap_or("");
+                    let imports = extract_file_imports(language, &content);
+                    if !imports.is_empty() {
+                        chunk_imports.push((*file_id, imports));
+                    }
 
-                    let (file_defs, file_calls, file_stats) = match ext.to_lowercase().as_str() {
+                    let (file_defs, file_calls, file_stats) = match language.as_str() {
                         "cs" => {
+                            chunk_di.extend(extract_di_registrations(&content, *file_id));
                             let (defs, calls, stats, ext_methods) = parser_csharp::parse_csharp_definitions(&mut cs_parser, &content, *file_id);
+                            chunk_endpoints.extend(extract_endpoints(&defs, &content, *file_id));
+                            let docs = extract_doc_comments(&defs, &content);
+                            if !docs.is_empty() {
+                                chunk_docs.push((*file_id, docs));
+                            }
                             // Merge extension methods from this file into chunk accumulator
                             for (method_name, classes) in ext_methods {
                                 chunk_ext_methods.entry(method_name).or_default().extend(classes);
@@ -166,15 +219,16 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
                     }
                 }
 
-                (chunk_defs, errors, lossy_files, empty_files, chunk_ext_methods)
+                (chunk_defs, errors, lossy_files, empty_files, chunk_ext_methods, chunk_imports, chunk_di, chunk_endpoints, chunk_docs)
             })
         }).collect();
 
         handles.into_iter().map(|h| h.join().unwrap_or_else(|_| {
             eprintln!("[WARN] Worker thread panicked during definition index building");
-            (Vec::new(), 0, Vec::new(), Vec::new(), HashMap::new())
+            (Vec::new(), 0, Vec::new(), Vec::new(), HashMap::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new())
         })).collect()
     });
+    drop(progress);
 
     // ─── Merge results ────────────────────────────────────────
     let mut definitions: Vec<DefinitionEntry> = Vec::new();
@@ -187,6 +241,10 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
     let mut method_calls: HashMap<u32, Vec<CallSite>> = HashMap::new();
     let mut code_stats: HashMap<u32, CodeStats> = HashMap::new();
     let mut extension_methods: HashMap<String, Vec<String>> = HashMap::new();
+    let mut file_imports: HashMap<u32, FileImports> = HashMap::new();
+    let mut di_registrations: Vec<DiRegistration> = Vec::new();
+    let mut endpoints: Vec<Endpoint> = Vec::new();
+    let mut doc_comments: HashMap<u32, Vec<DocComment>> = HashMap::new();
     let mut parse_errors = 0usize;
     let mut total_call_sites = 0usize;
 
@@ -197,7 +255,7 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
 
     let mut lossy_file_count = 0usize;
     let mut empty_file_ids: Vec<(u32, u64)> = Vec::new();
-    for (chunk_defs, errors, lossy_files, empty_files, chunk_ext_methods) in thread_results {
+    for (chunk_defs, errors, lossy_files, empty_files, chunk_ext_methods, chunk_imports, chunk_di, chunk_endpoints, chunk_docs) in thread_results {
         parse_errors += errors;
         for f in &lossy_files {
             eprintln!("[def-index] WARNING: file contains non-UTF8 bytes (lossy conversion applied): {}", f);
@@ -259,12 +317,20 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
             }
         }
 
+        file_imports.extend(chunk_imports);
+        di_registrations.extend(chunk_di);
+        endpoints.extend(chunk_endpoints);
+        doc_comments.extend(chunk_docs);
+
         // Merge extension methods from this chunk
         for (method_name, classes) in chunk_ext_methods {
             extension_methods.entry(method_name).or_default().extend(classes);
         }
     }
 
+    di_registrations.sort_by_key(|r| (r.file_id, r.line));
+    endpoints.sort_by_key(|e| (e.file_id, e.line));
+
     // ─── Angular template enrichment ──────────────────────────────────────
     let template_start = Instant::now();
     let mut selector_index: HashMap<String, Vec<u32>> = HashMap::new();
@@ -351,7 +417,7 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
         .unwrap_or(Duration::ZERO)
         .as_secs();
 
-    DefinitionIndex {
+    let mut index = DefinitionIndex {
         root: dir_str,
         created_at: now,
         extensions,
@@ -371,7 +437,19 @@ pub fn build_definition_index(args: &DefIndexArgs) -> DefinitionIndex {
         extension_methods,
         selector_index,
         template_children,
+        walk_limits,
+        file_imports,
+        di_registrations,
+        endpoints,
+        partial_types: HashMap::new(),
+        doc_comments,
+        imports: Vec::new(),
+    };
+    link_partial_types(&mut index);
+    if !args.import.is_empty() {
+        import_external_definitions(&mut index, &args.import);
     }
+    index
 }
 
 /// Extract custom element tag names from HTML content.
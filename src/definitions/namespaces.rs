@@ -0,0 +1,173 @@
+//! Namespaces: which namespace each definition lives in, fully-qualified names, and
+//! the namespace tree of an index.
+//!
+//! Scopes are recorded per file at index time ([`FileImports::scopes`]) by a light
+//! scan of the source that skips comments and string literals: C# block and
+//! file-scoped `namespace` declarations and TypeScript `namespace`/`module` blocks.
+//! Nested blocks get the full dotted name (`namespace Shop { namespace Billing {` is
+//! `Shop.Billing`). ES modules have no namespace; their definitions are addressed by
+//! name and file.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::types::{DefinitionIndex, DefinitionKind, NamespaceScope};
+
+pub use search::definition_index::{is_within_namespace, namespace_of};
+
+static DECLARATION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:namespace|module)\s+([A-Za-z_][\w.]*)\s*([{;])").unwrap()
+});
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80
+}
+
+/// Index just past the string, char or template literal opening at `start`.
+/// Regular literals end at a newline, so an unbalanced quote cannot swallow the file.
+fn skip_literal(bytes: &[u8], start: usize, line: &mut u32) -> usize {
+    let quote = bytes[start];
+    let before = |n: usize| start.checked_sub(n).map(|i| bytes[i]);
+    let verbatim = quote == b'"' && (before(1) == Some(b'@') || (before(1) == Some(b'$') && before(2) == Some(b'@')));
+    // C# raw string literal: closed by the same number of quotes
+    let run = bytes[start..].iter().take_while(|&&b| b == b'"').count();
+    if quote == b'"' && run >= 3 {
+        let close = &bytes[start..start + run];
+        let end = bytes[start + run..].windows(run).position(|w| w == close)
+            .map_or(bytes.len(), |p| start + run + p + run);
+        *line += bytes[start..end].iter().filter(|&&b| b == b'\n').count() as u32;
+        return end;
+    }
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if !verbatim => {
+                if bytes.get(i + 1) == Some(&b'\n') {
+                    *line += 1;
+                }
+                i += 1;
+            }
+            b'\n' if quote != b'`' && !verbatim => return i,
+            b'\n' => *line += 1,
+            b if b == quote => {
+                if verbatim && bytes.get(i + 1) == Some(&b'"') {
+                    i += 1;
+                } else {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+/// Namespace declarations of a C# or TypeScript source, in declaration order.
+pub fn namespace_scopes(content: &str) -> Vec<NamespaceScope> {
+    let bytes = content.as_bytes();
+    let mut scopes: Vec<NamespaceScope> = Vec::new();
+    // One entry per open `{`: the scope it opened, if any
+    let mut braces: Vec<Option<usize>> = Vec::new();
+    let mut file_scoped: Option<usize> = None;
+    let mut line = 1u32;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => line += 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |p| i + p);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = content[i + 2..].find("*/").map_or(bytes.len(), |p| i + 2 + p + 2);
+                line += bytes[i..end].iter().filter(|&&b| b == b'\n').count() as u32;
+                i = end;
+                continue;
+            }
+            b'"' | b'\'' | b'`' => {
+                i = skip_literal(bytes, i, &mut line);
+                continue;
+            }
+            b'{' => braces.push(None),
+            b'}' => {
+                if let Some(Some(scope)) = braces.pop() {
+                    scopes[scope].line_end = line;
+                }
+            }
+            b'n' | b'm' if i == 0 || !is_word_byte(bytes[i - 1]) => {
+                if let Some(cap) = DECLARATION_RE.captures(&content[i..]) {
+                    let enclosing = braces.iter().rev().find_map(|s| *s).or(file_scoped);
+                    let name = match enclosing {
+                        Some(outer) => format!("{}.{}", scopes[outer].name, &cap[1]),
+                        None => cap[1].to_string(),
+                    };
+                    let opener = cap.get(2).unwrap();
+                    let line_start = line;
+                    line += cap[0].matches('\n').count() as u32;
+                    scopes.push(NamespaceScope { name, line_start, line_end: 0 });
+                    if opener.as_str() == "{" {
+                        braces.push(Some(scopes.len() - 1));
+                    } else {
+                        file_scoped = Some(scopes.len() - 1);
+                    }
+                    i += opener.end();
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    for scope in scopes.iter_mut().filter(|s| s.line_end == 0) {
+        scope.line_end = line;
+    }
+    scopes
+}
+
+/// One namespace of the tree.
+#[derive(Debug, Default)]
+pub struct NamespaceNode {
+    /// Files declaring this namespace itself
+    pub files: BTreeSet<u32>,
+    /// Top-level types (and TypeScript functions) declared directly in it
+    pub types: Vec<u32>,
+}
+
+/// Every namespace of the index by full name, including the ones only implied by
+/// nested declarations (`Shop` for `namespace Shop.Billing`).
+pub fn namespace_tree(index: &DefinitionIndex) -> BTreeMap<String, NamespaceNode> {
+    let mut tree: BTreeMap<String, NamespaceNode> = BTreeMap::new();
+    for (&file_id, imports) in &index.file_imports {
+        for scope in &imports.scopes {
+            tree.entry(scope.name.clone()).or_default().files.insert(file_id);
+            let mut end = 0;
+            while let Some(dot) = scope.name[end..].find('.') {
+                end += dot;
+                tree.entry(scope.name[..end].to_string()).or_default();
+                end += 1;
+            }
+        }
+    }
+    for &file_id in index.file_imports.iter().filter(|(_, i)| !i.scopes.is_empty()).map(|(id, _)| id) {
+        for &def_id in index.file_index.get(&file_id).into_iter().flatten() {
+            let def = &index.definitions[def_id as usize];
+            let is_type = matches!(def.kind,
+                DefinitionKind::Class | DefinitionKind::Interface | DefinitionKind::Enum | DefinitionKind::Struct
+                | DefinitionKind::Record | DefinitionKind::Delegate | DefinitionKind::Function | DefinitionKind::TypeAlias);
+            if def.parent.is_none() && is_type
+                && let Some(ns) = namespace_of(index, def) {
+                    tree.entry(ns.to_string()).or_default().types.push(def_id);
+                }
+        }
+    }
+    for node in tree.values_mut() {
+        node.types.sort_by(|&a, &b| {
+            let (a, b) = (&index.definitions[a as usize], &index.definitions[b as usize]);
+            a.name.cmp(&b.name).then(a.file_id.cmp(&b.file_id)).then(a.line_start.cmp(&b.line_start))
+        });
+    }
+    tree
+}
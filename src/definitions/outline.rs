@@ -0,0 +1,126 @@
+//! Per-file outline: nests a file's definitions into a symbol tree by line-range containment.
+
+use serde_json::{json, Value};
+
+use super::types::{DefinitionEntry, DefinitionIndex};
+
+/// A definition and the definitions nested inside its line range.
+#[derive(Debug)]
+pub struct OutlineNode<'a> {
+    pub def: &'a DefinitionEntry,
+    pub children: Vec<OutlineNode<'a>>,
+}
+
+/// Resolve a user-supplied path to a file_id in the definition index.
+///
+/// Tries an exact (case-insensitive, separator-normalized) match on the canonical path
+/// first, then a unique suffix match on path components (e.g. `Services/UserService.cs`).
+/// Returns an error message listing candidates when the suffix is ambiguous.
+pub fn resolve_file_id(index: &DefinitionIndex, path: &str) -> Result<u32, String> {
+    let wanted = path.replace('\\', "/").to_lowercase();
+    let canonical = std::fs::canonicalize(path)
+        .map(|p| crate::clean_path(&p.to_string_lossy()).to_lowercase())
+        .ok();
+    let suffix = format!("/{}", wanted.trim_start_matches("./"));
+
+    let mut suffix_matches: Vec<u32> = Vec::new();
+    for (file_id, file) in index.files.iter().enumerate() {
+        let file_lower = file.replace('\\', "/").to_lowercase();
+        if file_lower == wanted || canonical.as_deref() == Some(file_lower.as_str()) {
+            return Ok(file_id as u32);
+        }
+        if file_lower.ends_with(&suffix) {
+            suffix_matches.push(file_id as u32);
+        }
+    }
+
+    match suffix_matches.len() {
+        0 => Err(format!("File '{}' is not in the definition index.", path)),
+        1 => Ok(suffix_matches[0]),
+        n => {
+            let candidates: Vec<&str> = suffix_matches.iter().take(10)
+                .filter_map(|&id| index.files.get(id as usize).map(|s| s.as_str()))
+                .collect();
+            Err(format!("'{}' matches {} indexed files; use a longer path. Candidates: {}",
+                path, n, candidates.join(", ")))
+        }
+    }
+}
+
+/// Build the symbol tree for one file.
+///
+/// Nesting is derived from line ranges rather than `parent` names, so nested types
+/// and same-named members in different classes land under the right node.
+/// Siblings are ordered by `line_start`.
+pub fn build_outline(index: &DefinitionIndex, file_id: u32) -> Vec<OutlineNode<'_>> {
+    let mut defs: Vec<&DefinitionEntry> = index.file_index.get(&file_id)
+        .map(|ids| ids.iter().filter_map(|&i| index.definitions.get(i as usize)).collect())
+        .unwrap_or_default();
+    // Outer ranges before inner ones that start on the same line (stable sort keeps index order)
+    defs.sort_by(|a, b| a.line_start.cmp(&b.line_start).then(b.line_end.cmp(&a.line_end)));
+
+    let mut roots: Vec<OutlineNode> = Vec::new();
+    let mut stack: Vec<OutlineNode> = Vec::new();
+    for def in defs {
+        while let Some(top) = stack.last() {
+            if def.line_start >= top.def.line_start && def.line_end <= top.def.line_end {
+                break;
+            }
+            let done = stack.pop().unwrap();
+            attach(&mut stack, &mut roots, done);
+        }
+        stack.push(OutlineNode { def, children: Vec::new() });
+    }
+    while let Some(done) = stack.pop() {
+        attach(&mut stack, &mut roots, done);
+    }
+    roots
+}
+
+fn attach<'a>(stack: &mut [OutlineNode<'a>], roots: &mut Vec<OutlineNode<'a>>, node: OutlineNode<'a>) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(node),
+        None => roots.push(node),
+    }
+}
+
+/// Total number of nodes in the tree.
+pub fn outline_len(nodes: &[OutlineNode]) -> usize {
+    nodes.iter().map(|n| 1 + outline_len(&n.children)).sum()
+}
+
+/// JSON form used by the `search_outline` MCP tool and `search outline --json`.
+pub fn outline_to_json(nodes: &[OutlineNode]) -> Value {
+    Value::Array(nodes.iter().map(|n| {
+        let mut obj = json!({
+            "name": n.def.name,
+            "kind": n.def.kind.as_str(),
+            "lines": format!("{}-{}", n.def.line_start, n.def.line_end),
+        });
+        if let Some(ref sig) = n.def.signature {
+            obj["signature"] = json!(sig);
+        }
+        if !n.def.modifiers.is_empty() {
+            obj["modifiers"] = json!(n.def.modifiers);
+        }
+        if !n.children.is_empty() {
+            obj["children"] = outline_to_json(&n.children);
+        }
+        obj
+    }).collect())
+}
+
+/// Indented text form for the CLI: `kind name [start-end]`, one node per line.
+pub fn render_outline_text(nodes: &[OutlineNode]) -> String {
+    let mut out = String::new();
+    render_into(nodes, 0, &mut out);
+    out
+}
+
+fn render_into(nodes: &[OutlineNode], depth: usize, out: &mut String) {
+    for n in nodes {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(&format!("{} {} [{}-{}]\n", n.def.kind, n.def.name, n.def.line_start, n.def.line_end));
+        render_into(&n.children, depth + 1, out);
+    }
+}
@@ -1,7 +1,8 @@
 //! C# AST parser using tree-sitter: extracts definitions and call sites.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use super::complexity::{members_used, record_halstead};
 use super::types::*;
 
 // ─── Main entry point ───────────────────────────────────────────────
@@ -115,10 +116,23 @@ pub(crate) fn parse_csharp_definitions(
         .map(|(idx, calls)| (*idx, calls.len() as u16))
         .collect();
 
+    // Members of each class, for the members a method uses (LCOM)
+    let mut class_members: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for def in &defs {
+        if let Some(ref parent) = def.parent
+            && matches!(def.kind, DefinitionKind::Field | DefinitionKind::Property | DefinitionKind::Method) {
+                class_members.entry(parent.as_str()).or_default().insert(def.name.as_str());
+            }
+    }
+
     let mut code_stats_entries: Vec<(usize, CodeStats)> = Vec::new();
     for &(def_local_idx, method_node) in &method_nodes {
-        let mut stats = compute_code_stats_csharp(method_node, source_bytes);
+        let (mut stats, operands) = compute_code_stats_csharp(method_node, source_bytes);
         stats.call_count = call_count_map.get(&def_local_idx).copied().unwrap_or(0);
+        let def = &defs[def_local_idx];
+        if let Some(members) = def.parent.as_deref().and_then(|p| class_members.get(p)) {
+            stats.members_used = members_used(&operands, members, &def.name);
+        }
         code_stats_entries.push((def_local_idx, stats));
     }
 
@@ -327,9 +341,11 @@ fn extract_call_sites(
     }
 
     calls.sort_by(|a, b| a.line.cmp(&b.line)
+        .then_with(|| a.column.cmp(&b.column))
         .then_with(|| a.method_name.cmp(&b.method_name))
         .then_with(|| a.receiver_type.cmp(&b.receiver_type)));
-    calls.dedup_by(|a, b| a.line == b.line && a.method_name == b.method_name && a.receiver_type == b.receiver_type);
+    calls.dedup_by(|a, b| a.line == b.line && a.column == b.column
+        && a.method_name == b.method_name && a.receiver_type == b.receiver_type);
 
     calls
 }
@@ -345,7 +361,7 @@ fn walk_for_invocations(
     match node.kind() {
         "invocation_expression" => {
             if let Some(call) = extract_invocation(node, source, class_name, field_types, base_types) {
-                calls.push(call);
+                calls.push(call.with_arguments(node));
             }
             // Recurse into ALL children — not just argument_list.
             // The expression child (first child, typically member_access_expression)
@@ -360,7 +376,7 @@ fn walk_for_invocations(
         }
         "object_creation_expression" => {
             if let Some(call) = extract_object_creation(node, source) {
-                calls.push(call);
+                calls.push(call.with_arguments(node));
             }
             // Same fix: recurse into all children to capture nested calls in arguments
             for i in 0..node.child_count() {
@@ -385,25 +401,24 @@ fn extract_invocation(
     base_types: &[String],
 ) -> Option<CallSite> {
     let expr = node.child(0)?;
-    let line = node.start_position().row as u32 + 1;
 
     match expr.kind() {
         "identifier" => {
             let method_name = node_text(expr, source).to_string();
-            Some(CallSite { method_name, receiver_type: None, line, receiver_is_generic: false })
+            Some(CallSite::at_node(method_name, None, expr, source))
         }
         "member_access_expression" => {
-            extract_member_access_call(expr, source, class_name, field_types, base_types, line)
+            extract_member_access_call(expr, source, class_name, field_types, base_types)
         }
         "conditional_access_expression" => {
-            extract_conditional_access_call(expr, source, class_name, field_types, base_types, line)
+            extract_conditional_access_call(expr, source, class_name, field_types, base_types)
         }
         "generic_name" => {
             let name_node = find_child_by_field(expr, "name")
-                .or_else(|| expr.child(0));
-            let method_name = name_node.map(|n| node_text(n, source)).unwrap_or("");
+                .or_else(|| expr.child(0))?;
+            let method_name = node_text(name_node, source);
             if !method_name.is_empty() {
-                Some(CallSite { method_name: method_name.to_string(), receiver_type: None, line, receiver_is_generic: false })
+                Some(CallSite::at_node(method_name.to_string(), None, name_node, source))
             } else {
                 None
             }
@@ -418,7 +433,6 @@ fn extract_member_access_call(
     class_name: &str,
     field_types: &HashMap<String, String>,
     base_types: &[String],
-    line: u32,
 ) -> Option<CallSite> {
     let name_node = find_child_by_field(node, "name")?;
     let method_name = extract_method_name_from_name_node(name_node, source);
@@ -427,7 +441,10 @@ fn extract_member_access_call(
         .or_else(|| node.child(0))?;
     let receiver_type = resolve_receiver_type(receiver_node, source, class_name, field_types, base_types);
 
-    Some(CallSite { method_name, receiver_type, line, receiver_is_generic: false })
+    Some(CallSite {
+        receiver: Some(normalize_receiver(node_text(receiver_node, source))),
+        ..CallSite::at_node(method_name, receiver_type, method_identifier(name_node), source)
+    })
 }
 
 fn extract_conditional_access_call(
@@ -436,7 +453,6 @@ fn extract_conditional_access_call(
     class_name: &str,
     field_types: &HashMap<String, String>,
     base_types: &[String],
-    line: u32,
 ) -> Option<CallSite> {
     let receiver_node = node.child(0)?;
 
@@ -456,7 +472,18 @@ fn extract_conditional_access_call(
 
     let receiver_type = resolve_receiver_type(receiver_node, source, class_name, field_types, base_types);
 
-    Some(CallSite { method_name, receiver_type, line, receiver_is_generic: false })
+    Some(CallSite {
+        receiver: Some(normalize_receiver(node_text(receiver_node, source))),
+        ..CallSite::at_node(method_name, receiver_type, method_identifier(name_node), source)
+    })
+}
+
+/// The identifier of a name node: `Method` of `Method<T>`, the node itself otherwise.
+fn method_identifier(name_node: tree_sitter::Node) -> tree_sitter::Node {
+    match name_node.child(0) {
+        Some(id) if name_node.kind() == "generic_name" && id.kind() == "identifier" => id,
+        _ => name_node,
+    }
 }
 
 /// Extract the method name from a name node, handling `generic_name` by stripping
@@ -489,11 +516,11 @@ fn extract_object_creation(
 
     if type_name.is_empty() { return None; }
 
+    let call = CallSite::at_node(type_name.to_string(), Some(type_name.to_string()), type_node, source);
     Some(CallSite {
-        method_name: type_name.to_string(),
-        receiver_type: Some(type_name.to_string()),
-        line: node.start_position().row as u32 + 1,
+        end_column: call.column + type_name.chars().count() as u32,
         receiver_is_generic: is_generic,
+        ..call
     })
 }
 
@@ -1188,10 +1215,11 @@ pub(crate) fn unwrap_task_type(type_name: &str) -> String {
 
 // ─── Code stats computation ─────────────────────────────────────────
 
-fn compute_code_stats_csharp(
+/// Code stats of a method node, and the operands of its body.
+fn compute_code_stats_csharp<'a>(
     method_node: tree_sitter::Node,
-    _source: &[u8],
-) -> CodeStats {
+    source: &'a [u8],
+) -> (CodeStats, HashSet<&'a str>) {
     let mut stats = CodeStats::default();
     stats.cyclomatic_complexity = 1; // base complexity
 
@@ -1205,9 +1233,11 @@ fn compute_code_stats_csharp(
     if let Some(body_node) = body {
         walk_code_stats_csharp(body_node, 0, &mut stats);
     }
+    let lines = (method_node.end_position().row - method_node.start_position().row + 1) as u32;
+    let operands = record_halstead(&mut stats, body, source, lines);
 
     // callCount is filled separately from method_calls after invocations walk
-    stats
+    (stats, operands)
 }
 
 pub(crate) fn count_parameters_csharp(method_node: tree_sitter::Node) -> u8 {
@@ -1,7 +1,8 @@
 //! TypeScript AST parser using tree-sitter: extracts definitions and call sites.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use super::complexity::{members_used, record_halstead};
 use super::types::*;
 
 // ─── Main entry point ───────────────────────────────────────────────
@@ -81,10 +82,23 @@ pub(crate) fn parse_typescript_definitions(
         .map(|(idx, calls)| (*idx, calls.len() as u16))
         .collect();
 
+    // Members of each class, for the members a method uses (LCOM)
+    let mut class_members: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for def in &defs {
+        if let Some(ref parent) = def.parent
+            && matches!(def.kind, DefinitionKind::Field | DefinitionKind::Property | DefinitionKind::Method) {
+                class_members.entry(parent.as_str()).or_default().insert(def.name.as_str());
+            }
+    }
+
     let mut code_stats_entries: Vec<(usize, CodeStats)> = Vec::new();
     for &(def_local_idx, method_node) in &method_nodes {
-        let mut stats = compute_code_stats_typescript(method_node, source);
+        let (mut stats, operands) = compute_code_stats_typescript(method_node, source);
         stats.call_count = call_count_map.get(&def_local_idx).copied().unwrap_or(0);
+        let def = &defs[def_local_idx];
+        if let Some(members) = def.parent.as_deref().and_then(|p| class_members.get(p)) {
+            stats.members_used = members_used(&operands, members, &def.name);
+        }
         code_stats_entries.push((def_local_idx, stats));
     }
 
@@ -1292,11 +1306,15 @@ fn extract_ts_call_sites(
     calls.sort_by(|a, b| {
         a.line
             .cmp(&b.line)
+            .then_with(|| a.column.cmp(&b.column))
             .then_with(|| a.method_name.cmp(&b.method_name))
             .then_with(|| a.receiver_type.cmp(&b.receiver_type))
     });
     calls.dedup_by(|a, b| {
-        a.line == b.line && a.method_name == b.method_name && a.receiver_type == b.receiver_type
+        a.line == b.line
+            && a.column == b.column
+            && a.method_name == b.method_name
+            && a.receiver_type == b.receiver_type
     });
 
     calls
@@ -1313,7 +1331,7 @@ fn walk_ts_for_invocations(
     match node.kind() {
         "call_expression" => {
             if let Some(call) = extract_ts_call(node, source, class_name, field_types) {
-                calls.push(call);
+                calls.push(call.with_arguments(node));
             }
             // Recurse into ALL children — not just arguments.
             // The function child (first child, typically member_expression)
@@ -1328,7 +1346,7 @@ fn walk_ts_for_invocations(
         }
         "new_expression" => {
             if let Some(call) = extract_ts_new_expression(node, source) {
-                calls.push(call);
+                calls.push(call.with_arguments(node));
             }
             // Same fix: recurse into all children to capture nested calls
             for i in 0..node.child_count() {
@@ -1356,20 +1374,14 @@ fn extract_ts_call(
     field_types: &HashMap<String, String>,
 ) -> Option<CallSite> {
     let func_node = find_child_by_field(node, "function").or_else(|| node.child(0))?;
-    let line = node.start_position().row as u32 + 1;
 
     match func_node.kind() {
         "identifier" => {
             let method_name = node_text(func_node, source).to_string();
-            Some(CallSite {
-                method_name,
-                receiver_type: None,
-                line,
-                receiver_is_generic: false,
-            })
+            Some(CallSite::at_node(method_name, None, func_node, source.as_bytes()))
         }
         "member_expression" => {
-            extract_ts_member_call(func_node, source, class_name, field_types, line)
+            extract_ts_member_call(func_node, source, class_name, field_types)
         }
         _ => None,
     }
@@ -1381,7 +1393,6 @@ fn extract_ts_member_call(
     source: &str,
     class_name: &str,
     field_types: &HashMap<String, String>,
-    line: u32,
 ) -> Option<CallSite> {
     let property_node = find_child_by_field(member_node, "property")?;
     let method_name = node_text(property_node, source).to_string();
@@ -1391,10 +1402,8 @@ fn extract_ts_member_call(
     let receiver_type = resolve_ts_receiver_type(object_node, source, class_name, field_types);
 
     Some(CallSite {
-        method_name,
-        receiver_type,
-        line,
-        receiver_is_generic: false,
+        receiver: Some(normalize_receiver(node_text(object_node, source))),
+        ..CallSite::at_node(method_name, receiver_type, property_node, source.as_bytes())
     })
 }
 
@@ -1453,10 +1462,11 @@ fn resolve_ts_receiver_type(
 
 // ─── Code stats computation ─────────────────────────────────────────
 
-fn compute_code_stats_typescript(
+/// Code stats of a method node, and the operands of its body.
+fn compute_code_stats_typescript<'a>(
     method_node: tree_sitter::Node,
-    _source: &str,
-) -> CodeStats {
+    source: &'a str,
+) -> (CodeStats, HashSet<&'a str>) {
     let mut stats = CodeStats::default();
     stats.cyclomatic_complexity = 1; // base complexity
 
@@ -1479,9 +1489,11 @@ fn compute_code_stats_typescript(
     if let Some(body_node) = body {
         walk_code_stats_typescript(body_node, 0, &mut stats);
     }
+    let lines = (method_node.end_position().row - method_node.start_position().row + 1) as u32;
+    let operands = record_halstead(&mut stats, body, source.as_bytes(), lines);
 
     // callCount is filled separately from call_sites after invocations walk
-    stats
+    (stats, operands)
 }
 
 pub(crate) fn count_parameters_typescript(method_node: tree_sitter::Node) -> u8 {
@@ -1632,10 +1644,10 @@ fn extract_ts_new_expression(node: tree_sitter::Node, source: &str) -> Option<Ca
         return None;
     }
 
+    let call = CallSite::at_node(type_name.to_string(), Some(type_name.to_string()), type_node, source.as_bytes());
     Some(CallSite {
-        method_name: type_name.to_string(),
-        receiver_type: Some(type_name.to_string()),
-        line: node.start_position().row as u32 + 1,
+        end_column: call.column + type_name.chars().count() as u32,
         receiver_is_generic: is_generic,
+        ..call
     })
 }
\ No newline at end of file
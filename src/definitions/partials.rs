@@ -0,0 +1,146 @@
+//! Partial C# types: the declarations of one `partial` type, linked into one
+//! logical type after parsing.
+//!
+//! Parts are grouped by namespace, enclosing type and name. Each part keeps its own
+//! attributes and base types; [`PartialType`] holds their union. Members already
+//! belong to the type through `parent`, but call sites are parsed one file at a time:
+//! a call through a field, property or injected constructor parameter declared in
+//! another part gets its receiver type here.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use super::namespaces::namespace_of;
+use super::parser_csharp::{extract_constructor_param_types, parse_field_signature};
+use super::types::{DefinitionIndex, DefinitionKind, PartialType};
+
+/// Namespace, enclosing type and name: the parts of one partial type share it.
+type PartialKey<'a> = (Option<&'a str>, Option<&'a str>, &'a str);
+
+fn push_unique(list: &mut Vec<String>, items: &[String]) {
+    for item in items {
+        if !list.contains(item) {
+            list.push(item.clone());
+        }
+    }
+}
+
+/// Rebuild [`DefinitionIndex::partial_types`] from the live definitions and resolve
+/// call-site receivers declared in sibling parts. Run after a build and after every
+/// incremental update.
+pub fn link_partial_types(index: &mut DefinitionIndex) {
+    let mut groups: HashMap<PartialKey, Vec<u32>> = HashMap::new();
+    for kind in [DefinitionKind::Class, DefinitionKind::Struct, DefinitionKind::Record, DefinitionKind::Interface] {
+        for &id in index.kind_index.get(&kind).into_iter().flatten() {
+            let def = &index.definitions[id as usize];
+            if def.modifiers.iter().any(|m| m == "partial") {
+                groups.entry((namespace_of(index, def), def.parent.as_deref(), def.name.as_str())).or_default().push(id);
+            }
+        }
+    }
+
+    let mut partial_types: HashMap<u32, PartialType> = HashMap::new();
+    let mut linked: Vec<(String, Vec<u32>)> = Vec::new();
+    for ((_, _, name), mut parts) in groups.into_iter().filter(|(_, parts)| parts.len() > 1) {
+        parts.sort_by(|&a, &b| {
+            let (a, b) = (&index.definitions[a as usize], &index.definitions[b as usize]);
+            index.files.get(a.file_id as usize).cmp(&index.files.get(b.file_id as usize))
+                .then(a.line_start.cmp(&b.line_start))
+        });
+        let mut merged = PartialType { parts: parts.clone(), attributes: Vec::new(), base_types: Vec::new() };
+        for &id in &parts {
+            let def = &index.definitions[id as usize];
+            push_unique(&mut merged.attributes, &def.attributes);
+            push_unique(&mut merged.base_types, &def.base_types);
+        }
+        for &id in &parts {
+            partial_types.insert(id, merged.clone());
+        }
+        linked.push((name.to_string(), parts));
+    }
+    index.partial_types = partial_types;
+
+    for (name, parts) in linked {
+        resolve_sibling_receivers(index, &name, &parts);
+    }
+}
+
+/// Give call sites in the members of a partial type the receiver types of fields,
+/// properties and constructor parameters declared in any of its parts.
+fn resolve_sibling_receivers(index: &mut DefinitionIndex, name: &str, parts: &[u32]) {
+    let files: HashSet<u32> = parts.iter().map(|&id| index.definitions[id as usize].file_id).collect();
+    let members: Vec<u32> = files.iter()
+        .flat_map(|f| index.file_index.get(f).into_iter().flatten().copied())
+        .filter(|&id| index.definitions[id as usize].parent.as_deref() == Some(name))
+        .collect();
+
+    // Declared fields and properties win over constructor parameters, as in the parser
+    let mut field_types: HashMap<String, String> = HashMap::new();
+    for &id in &members {
+        let def = &index.definitions[id as usize];
+        if matches!(def.kind, DefinitionKind::Field | DefinitionKind::Property)
+            && let Some((type_name, field)) = def.signature.as_deref().and_then(parse_field_signature) {
+                field_types.insert(field, type_name);
+            }
+    }
+    for &id in &members {
+        let def = &index.definitions[id as usize];
+        if def.kind == DefinitionKind::Constructor {
+            for (param, type_name) in def.signature.as_deref().map(extract_constructor_param_types).unwrap_or_default() {
+                field_types.entry(format!("_{}", param)).or_insert_with(|| type_name.clone());
+                field_types.entry(param).or_insert(type_name);
+            }
+        }
+    }
+    let base = index.partial_types.get(&parts[0])
+        .and_then(|p| p.base_types.first())
+        .map(|bt| bt.split('<').next().unwrap_or(bt).to_string());
+
+    for id in members {
+        let Some(calls) = index.method_calls.get_mut(&id) else { continue };
+        for call in calls {
+            let Some(receiver) = call.receiver.as_deref() else { continue };
+            if receiver == "base" {
+                if call.receiver_type.is_none() {
+                    call.receiver_type = base.clone();
+                }
+                continue;
+            }
+            let (member, via_this) = match receiver.strip_prefix("this.") {
+                Some(member) => (member, true),
+                None => (receiver, false),
+            };
+            // The parser keeps an unknown identifier as its own type, and `this.x` as the class
+            let unresolved = call.receiver_type.as_deref() == Some(member)
+                || (via_this && call.receiver_type.as_deref() == Some(name));
+            if unresolved && let Some(type_name) = field_types.get(member) {
+                call.receiver_type = Some(type_name.clone());
+            }
+        }
+    }
+}
+
+/// The primary part of `def_id` if it is part of a partial type, else `def_id`.
+pub fn primary_part(index: &DefinitionIndex, def_id: u32) -> u32 {
+    index.partial_types.get(&def_id).map_or(def_id, |p| p.parts[0])
+}
+
+/// Base types of `def_id`, merged over all parts of a partial type.
+pub fn merged_base_types(index: &DefinitionIndex, def_id: u32) -> &[String] {
+    match index.partial_types.get(&def_id) {
+        Some(p) => &p.base_types,
+        None => index.definitions.get(def_id as usize).map_or(&[], |d| &d.base_types),
+    }
+}
+
+/// `[{file, lines}]` of every part of a partial type, for JSON output.
+pub fn partial_parts_json(index: &DefinitionIndex, partial: &PartialType) -> Value {
+    Value::Array(partial.parts.iter().map(|&id| {
+        let def = &index.definitions[id as usize];
+        json!({
+            "file": index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or(""),
+            "lines": format!("{}-{}", def.line_start, def.line_end),
+        })
+    }).collect())
+}
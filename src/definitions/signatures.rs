@@ -0,0 +1,511 @@
+//! Method signatures: the parameter lists kept in [`DefinitionEntry::signature`],
+//! the argument counts an overload accepts, matching overloads against a list of
+//! parameter types, and the types of the arguments a call passes.
+//!
+//! Both C# (`int id, string name = null`, `params T[] items`) and TypeScript
+//! (`id: number, name?: string`, `...items: T[]`) parameter lists are read. The
+//! receiver of a C# extension method (`this`) and a TypeScript `this` parameter are
+//! not arguments and are left out.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::types::DefinitionEntry;
+
+/// `ns.` and `global::` qualifiers in front of a type name.
+static QUALIFIER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:global::)?(?:[A-Za-z_]\w*\.)+").unwrap()
+});
+
+/// One declared parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    /// Declared type as written; None for an untyped TypeScript parameter
+    pub type_name: Option<String>,
+    /// Has a default value (or `?` in TypeScript)
+    pub optional: bool,
+    /// `params` array or TypeScript rest parameter
+    pub variadic: bool,
+}
+
+/// Argument counts an overload accepts: `min..=max`, no upper bound with a variadic parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn of(params: &[Parameter]) -> Self {
+        let variadic = params.iter().any(|p| p.variadic);
+        Arity {
+            min: params.iter().filter(|p| !p.optional && !p.variadic).count(),
+            max: if variadic { None } else { Some(params.len()) },
+        }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+
+    /// How well `count` arguments fit, lower is better, as in C# overload resolution:
+    /// 0 passes every parameter, 1 leaves optional ones out, 2 expands a variadic one.
+    /// None if the overload does not take `count` arguments.
+    pub fn fit(&self, count: usize) -> Option<u8> {
+        if !self.accepts(count) {
+            return None;
+        }
+        Some(match self.max {
+            Some(max) if max == count => 0,
+            Some(_) => 1,
+            None => 2,
+        })
+    }
+}
+
+/// Split `s` at `sep` outside brackets, generics and string literals.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut prev = '\0';
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q && prev != '\\' => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' | '`' => quote = Some(c),
+                '(' | '[' | '{' | '<' => depth += 1,
+                // `=>` of a function type is not a closing generic
+                '>' if prev == '=' => {}
+                ')' | ']' | '}' | '>' => depth = depth.saturating_sub(1),
+                _ if c == sep && depth == 0 => {
+                    parts.push(&s[start..i]);
+                    start = i + c.len_utf8();
+                }
+                _ => {}
+            },
+        }
+        prev = c;
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Byte range of the `<...>` or `(...)` group opening at `start`, end exclusive.
+fn group_end(s: &str, start: usize) -> Option<usize> {
+    let open = s[start..].chars().next()?;
+    let close = if open == '<' { '>' } else { ')' };
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut prev = '\0';
+    for (i, c) in s[start..].char_indices() {
+        match quote {
+            Some(q) if c == q && prev != '\\' => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' || c == '`' => quote = Some(c),
+            None if c == open => depth += 1,
+            None if c == close && !(close == '>' && prev == '=') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start + i + 1);
+                }
+            }
+            None => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+/// `(type parameter list, parameter list)` of the declaration of `name` in `signature`,
+/// without their brackets: `Get<T>(T id)` → `(Some("T"), Some("T id"))`.
+fn declaration_lists<'a>(signature: &'a str, name: &str) -> (Option<&'a str>, Option<&'a str>) {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    for (pos, _) in signature.match_indices(name) {
+        if signature[..pos].chars().next_back().is_some_and(is_word) {
+            continue;
+        }
+        let mut i = pos + name.len();
+        let skip_ws = |i: usize| i + signature[i..].len() - signature[i..].trim_start().len();
+        i = skip_ws(i);
+        let mut type_params = None;
+        if signature[i..].starts_with('<') {
+            let Some(end) = group_end(signature, i) else { continue };
+            type_params = Some(&signature[i + 1..end - 1]);
+            i = skip_ws(end);
+        }
+        if signature[i..].starts_with('(') {
+            let params = group_end(signature, i).map(|end| &signature[i + 1..end - 1]);
+            return (type_params, params);
+        }
+        if type_params.is_some() {
+            // A type declaration has no parameter list
+            return (type_params, None);
+        }
+    }
+    (None, None)
+}
+
+/// Generic type parameters declared by `name` in `signature`: `T` and `U` of
+/// `Map<T, U>(...)` or `class Repository<T> where T : class`.
+pub fn type_parameters(signature: &str, name: &str) -> Vec<String> {
+    declaration_lists(signature, name).0.map_or_else(Vec::new, |list| {
+        split_top_level(list, ',').into_iter()
+            .filter_map(|p| {
+                // `in T`/`out T` variance, `T extends Base`, `T = Default`
+                let p = p.split(['=', ':']).next().unwrap_or(p);
+                p.split_whitespace().find(|t| !matches!(*t, "in" | "out" | "const")).map(str::to_string)
+            })
+            .collect()
+    })
+}
+
+/// Parameters of a C# or TypeScript parameter list, receiver parameters left out.
+fn parse_parameter_list(list: &str) -> Vec<Parameter> {
+    split_top_level(list, ',').into_iter()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .filter_map(|p| {
+            // TypeScript annotates with `name: Type`; C# only has `::` after `global`
+            let colon = split_top_level(p, ':');
+            let is_ts = colon.len() > 1 && !p.contains("::");
+            if is_ts { parse_ts_parameter(p) } else { parse_csharp_parameter(p) }
+        })
+        .collect()
+}
+
+fn parse_csharp_parameter(param: &str) -> Option<Parameter> {
+    // Parameter attributes: `[FromBody] User user`
+    let mut rest = param.trim_start();
+    while rest.starts_with('[') {
+        let mut depth = 0usize;
+        let end = rest.char_indices().find_map(|(i, c)| match c {
+            '[' => { depth += 1; None }
+            ']' => { depth -= 1; (depth == 0).then_some(i + 1) }
+            _ => None,
+        })?;
+        rest = rest[end..].trim_start();
+    }
+    let default = split_top_level(rest, '=');
+    let optional = default.len() > 1;
+    let declaration = default[0].trim();
+    let tokens: Vec<&str> = split_top_level(declaration, ' ').into_iter().filter(|t| !t.is_empty()).collect();
+    if tokens.first() == Some(&"this") {
+        return None;
+    }
+    let variadic = tokens.contains(&"params");
+    let (name, type_tokens) = tokens.split_last()?;
+    let type_tokens: Vec<&str> = type_tokens.iter().copied()
+        .filter(|t| !matches!(*t, "ref" | "out" | "in" | "params" | "scoped" | "readonly"))
+        .collect();
+    Some(Parameter {
+        name: name.to_string(),
+        type_name: (!type_tokens.is_empty()).then(|| type_tokens.join(" ")),
+        optional,
+        variadic,
+    })
+}
+
+fn parse_ts_parameter(param: &str) -> Option<Parameter> {
+    let parts = split_top_level(param, ':');
+    let name_part = parts[0];
+    let type_part = (parts.len() > 1).then(|| &param[name_part.len() + 1..]);
+    let (type_name, has_default) = match type_part {
+        Some(ty) => {
+            let parts = split_top_level(ty, '=');
+            (Some(parts[0].trim().to_string()), parts.len() > 1)
+        }
+        None => (None, false),
+    };
+    let name_default = split_top_level(name_part, '=');
+    let mut name = name_default[0].trim();
+    // Constructor parameter properties: `private readonly http: HttpClient`
+    while let Some((first, rest)) = name.split_once(char::is_whitespace) {
+        if !matches!(first, "public" | "private" | "protected" | "readonly" | "override") {
+            break;
+        }
+        name = rest.trim_start();
+    }
+    let variadic = name.starts_with("...");
+    let name = name.trim_start_matches("...");
+    let optional = has_default || name_default.len() > 1 || name.ends_with('?');
+    let name = name.trim_end_matches('?');
+    if name == "this" {
+        return None;
+    }
+    Some(Parameter { name: name.to_string(), type_name, optional, variadic })
+}
+
+/// Parameters of a method, constructor or function definition; empty when its
+/// signature has none (or no parameter list, like a property).
+pub fn parameters(def: &DefinitionEntry) -> Vec<Parameter> {
+    def.signature.as_deref()
+        .and_then(|sig| declaration_lists(sig, &def.name).1)
+        .map(parse_parameter_list)
+        .unwrap_or_default()
+}
+
+/// Argument counts `def` accepts.
+pub fn arity(def: &DefinitionEntry) -> Arity {
+    Arity::of(&parameters(def))
+}
+
+/// `type_name` without whitespace, namespace qualifiers and case.
+fn normalize_type(type_name: &str) -> String {
+    let compact: String = type_name.chars().filter(|c| !c.is_whitespace()).collect();
+    QUALIFIER_RE.replace_all(&compact, "").to_lowercase()
+}
+
+/// Whether the declared type `declared` matches the queried type `query`. A generic
+/// type parameter matches any type (`T` matches `int`, `List<T>` matches
+/// `List<string>`), and a query without type arguments matches any of them
+/// (`List` matches `List<T>`).
+pub fn type_matches(declared: &str, query: &str, generics: &[String]) -> bool {
+    let declared = normalize_type(declared);
+    let query = normalize_type(query);
+    if declared == query {
+        return true;
+    }
+    if !query.contains('<') && declared.split('<').next() == Some(query.as_str()) {
+        return true;
+    }
+    if generics.is_empty() {
+        return false;
+    }
+    let mut pattern = String::from("^");
+    let mut word = String::new();
+    let flush = |word: &mut String, pattern: &mut String| {
+        if generics.iter().any(|g| g.eq_ignore_ascii_case(word)) {
+            pattern.push_str(".+");
+        } else {
+            pattern.push_str(&regex::escape(word));
+        }
+        word.clear();
+    };
+    for c in declared.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut pattern);
+            pattern.push_str(&regex::escape(&c.to_string()));
+        }
+    }
+    flush(&mut word, &mut pattern);
+    pattern.push('$');
+    Regex::new(&pattern).is_ok_and(|re| re.is_match(&query))
+}
+
+/// Whether the parameters of `def` have exactly the types `query`, in order.
+/// `generics` are the type parameters in scope besides the method's own (those of
+/// its class).
+pub fn matches_param_types(def: &DefinitionEntry, query: &[String], generics: &[String]) -> bool {
+    let params = parameters(def);
+    if params.len() != query.len() {
+        return false;
+    }
+    let mut generics = generics.to_vec();
+    generics.extend(def.signature.as_deref().map(|s| type_parameters(s, &def.name)).unwrap_or_default());
+    params.iter().zip(query).all(|(param, q)| {
+        param.type_name.as_deref().is_some_and(|ty| type_matches(ty, q, &generics))
+    })
+}
+
+// ─── Argument types at call sites ───────────────────────────────────
+
+/// Arguments of a call, read from the text just past the called name:
+/// `<int>(1, "a, b")` → `["1", "\"a, b\""]`. None without an argument list, or if
+/// it does not close within `text`.
+pub fn call_arguments(text: &str) -> Option<Vec<String>> {
+    let skip_ws = |i: usize| i + text[i..].len() - text[i..].trim_start().len();
+    let mut i = skip_ws(0);
+    if text[i..].starts_with('<') {
+        i = skip_ws(group_end(text, i)?);
+    }
+    if !text[i..].starts_with('(') {
+        return None;
+    }
+    let end = group_end(text, i)?;
+    let inner = text[i + 1..end - 1].trim();
+    if inner.is_empty() {
+        return Some(Vec::new());
+    }
+    Some(split_top_level(inner, ',').into_iter().map(|a| a.trim().to_string()).collect())
+}
+
+/// Whether the quoted literal opening at the start of `s` ends at the end of `s`.
+fn is_whole_literal(s: &str, quote: char) -> bool {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return i + c.len_utf8() == s.len(),
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Type of a literal argument: `"a"` is `string`, `1L` is `long` in C# and `number`
+/// in TypeScript, `new User(...)` is `User`. None for anything else, `null` included.
+pub fn literal_type(arg: &str, typescript: bool) -> Option<String> {
+    let arg = arg.trim();
+    let first = arg.chars().next()?;
+    let ty = |t: &str| Some(t.to_string());
+    if typescript && matches!(first, '"' | '\'' | '`') {
+        return if is_whole_literal(arg, first) { ty("string") } else { None };
+    }
+    if !typescript {
+        // `@"..."`, `$"..."` and `$@"..."` are strings too
+        let quoted = arg.trim_start_matches(['@', '$']);
+        if quoted.starts_with('"') {
+            return if is_whole_literal(quoted, '"') { ty("string") } else { None };
+        }
+        if first == '\'' {
+            return if is_whole_literal(arg, '\'') { ty("char") } else { None };
+        }
+    }
+    match arg {
+        "true" | "false" => return ty(if typescript { "boolean" } else { "bool" }),
+        _ => {}
+    }
+    if let Some(created) = arg.strip_prefix("new ") {
+        // `new User(...)`, `new int[] { 1 }`; target-typed `new()` has no type
+        let name = created.split(['(', '{']).next().unwrap_or("").trim();
+        return (!name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || "_.<>[], ".contains(c)))
+            .then(|| name.to_string());
+    }
+    let number = arg.strip_prefix('-').unwrap_or(arg);
+    if !number.starts_with(|c: char| c.is_ascii_digit())
+        || !number.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_') {
+        return None;
+    }
+    if typescript {
+        return ty(if number.ends_with('n') { "bigint" } else { "number" });
+    }
+    let lower = number.to_ascii_lowercase();
+    let hex = lower.starts_with("0x") || lower.starts_with("0b");
+    ty(if lower.ends_with("ul") || lower.ends_with("lu") {
+        "ulong"
+    } else if lower.ends_with('l') {
+        "long"
+    } else if lower.ends_with('u') {
+        "uint"
+    } else if hex {
+        "int"
+    } else if lower.ends_with('m') {
+        "decimal"
+    } else if lower.ends_with('f') {
+        "float"
+    } else if lower.ends_with('d') || lower.contains(['.', 'e']) {
+        "double"
+    } else {
+        "int"
+    })
+}
+
+/// Words that can stand where a declaration's type does but are not types.
+const NOT_TYPES: &[&str] = &[
+    "return", "new", "throw", "await", "yield", "case", "else", "is", "as", "in", "out", "ref",
+    "using", "goto", "typeof", "sizeof", "default", "not", "and", "or", "when", "where", "select",
+    "from", "export", "import", "keyof", "readonly", "static", "public", "private", "protected",
+];
+
+/// Type of the last declaration of `name` in `text`: a parameter, local, field or
+/// property (`string name`, `name: string`, `name?: string = ""`). `var`, `let` and
+/// `const` take the type of a literal initializer. None if `text` does not declare
+/// `name`, Some(None) if it does but the type is not known (`var user = Load()`).
+pub fn declared_type(text: &str, name: &str, typescript: bool) -> Option<Option<String>> {
+    let name = regex::escape(name);
+    // `Type name` followed by an initializer, a separator or the end of the text
+    let typed = Regex::new(&format!(
+        r"([A-Za-z_][\w.]*(?:<[^;(){{}}=]*?>)?(?:\[\])*\??)\s+{}\s*(?:=([^=>;][^;\n]*)|[;,)]|\s+in\b|$)", name,
+    )).ok()?;
+    let mut found: Option<(usize, Option<String>)> = None;
+    for caps in typed.captures_iter(text) {
+        let ty = &caps[1];
+        let start = caps.get(0).map_or(0, |m| m.start());
+        if matches!(ty, "var" | "let" | "const") {
+            let init = caps.get(2).and_then(|m| literal_type(m.as_str(), typescript));
+            found = Some((start, init));
+        } else if !NOT_TYPES.contains(&ty) {
+            found = Some((start, Some(ty.to_string())));
+        }
+    }
+    if typescript {
+        let annotated = Regex::new(&format!(r"\b{}\s*\??\s*:\s*([^=;,)]+?)\s*(?:[=;,)]|$)", name)).ok()?;
+        if let Some(caps) = annotated.captures_iter(text).last()
+            && found.as_ref().is_none_or(|(start, _)| caps.get(0).is_some_and(|m| m.start() > *start)) {
+            found = Some((0, Some(caps[1].trim().to_string())));
+        }
+    }
+    found.map(|(_, ty)| ty)
+}
+
+/// C# and TypeScript built-in types, with the .NET names of the C# ones folded
+/// into their keywords.
+fn builtin_type(normalized: &str) -> Option<&'static str> {
+    Some(match normalized {
+        "string" => "string",
+        "char" => "char",
+        "bool" | "boolean" => "bool",
+        "byte" => "byte",
+        "sbyte" => "sbyte",
+        "short" | "int16" => "short",
+        "ushort" | "uint16" => "ushort",
+        "int" | "int32" => "int",
+        "uint" | "uint32" => "uint",
+        "long" | "int64" => "long",
+        "ulong" | "uint64" => "ulong",
+        "float" | "single" => "float",
+        "double" => "double",
+        "decimal" => "decimal",
+        "number" => "number",
+        "bigint" => "bigint",
+        _ => return None,
+    })
+}
+
+/// Numeric types a value of `from` converts to implicitly in C#.
+fn widens_to(from: &str) -> &'static [&'static str] {
+    match from {
+        "sbyte" => &["short", "int", "long", "float", "double", "decimal"],
+        "byte" => &["short", "ushort", "int", "uint", "long", "ulong", "float", "double", "decimal"],
+        "short" => &["int", "long", "float", "double", "decimal"],
+        "ushort" | "char" => &["int", "uint", "long", "ulong", "float", "double", "decimal"],
+        "int" => &["long", "float", "double", "decimal"],
+        "uint" => &["long", "ulong", "float", "double", "decimal"],
+        "long" | "ulong" => &["float", "double", "decimal"],
+        "float" => &["double"],
+        _ => &[],
+    }
+}
+
+/// Whether an argument of type `arg` can be passed to a parameter of type `param`:
+/// Some(true) if the types match (see [`type_matches`]), `param` is `object`/`any`,
+/// or C# widens the number; Some(false) if both are built-in types that do not
+/// convert; None when it cannot be told (two class types, or a narrowing that a
+/// constant argument may still fit).
+pub fn argument_fit(param: &str, arg: &str, generics: &[String]) -> Option<bool> {
+    let param = param.trim().trim_end_matches('?');
+    if type_matches(param, arg, generics) {
+        return Some(true);
+    }
+    let (param, arg) = (normalize_type(param), normalize_type(arg));
+    if matches!(param.as_str(), "object" | "any" | "unknown" | "dynamic") {
+        return Some(true);
+    }
+    let (Some(param), Some(arg)) = (builtin_type(&param), builtin_type(&arg)) else { return None };
+    if param == arg || widens_to(arg).contains(&param) {
+        return Some(true);
+    }
+    let numeric = |t: &str| !matches!(t, "string" | "bool" | "char" | "number" | "bigint");
+    if numeric(param) && numeric(arg) {
+        return None;
+    }
+    Some(false)
+}
@@ -0,0 +1,93 @@
+//! SQL dump of the definition index (`search export --to sql`): a script that creates
+//! and fills `files`, `definitions`, `call_sites` and `code_stats` tables with their
+//! lookup indexes, for ad-hoc SQL analysis in SQLite (`sqlite3 defs.db < index.sql`).
+//!
+//! Definition ids are the index's def_idx, so `call_sites.caller_id` and
+//! `code_stats.def_id` join on `definitions.id`. Modifiers, attributes and base types
+//! are JSON arrays (`json_each`). Definitions removed by incremental updates are left out.
+
+use std::fmt::Write as _;
+
+use super::types::DefinitionIndex;
+
+const SCHEMA: &str = "\
+CREATE TABLE files (id INTEGER PRIMARY KEY, path TEXT NOT NULL);
+CREATE TABLE definitions (id INTEGER PRIMARY KEY, file_id INTEGER NOT NULL REFERENCES files(id), name TEXT NOT NULL, kind TEXT NOT NULL, line_start INTEGER NOT NULL, line_end INTEGER NOT NULL, parent TEXT, signature TEXT, modifiers TEXT NOT NULL, attributes TEXT NOT NULL, base_types TEXT NOT NULL);
+CREATE TABLE call_sites (caller_id INTEGER NOT NULL REFERENCES definitions(id), method_name TEXT NOT NULL, receiver_type TEXT, receiver TEXT, line INTEGER NOT NULL, column INTEGER NOT NULL, end_column INTEGER NOT NULL, arg_count INTEGER);
+CREATE TABLE code_stats (def_id INTEGER PRIMARY KEY REFERENCES definitions(id), cyclomatic INTEGER NOT NULL, cognitive INTEGER NOT NULL, max_nesting INTEGER NOT NULL, params INTEGER NOT NULL, returns INTEGER NOT NULL, calls INTEGER NOT NULL, lambdas INTEGER NOT NULL, halstead_volume REAL NOT NULL, halstead_difficulty REAL NOT NULL, maintainability_index INTEGER NOT NULL);
+";
+
+/// Created after the rows are in, which loads faster than maintaining them per insert.
+const INDEXES: &str = "\
+CREATE INDEX definitions_name ON definitions(name COLLATE NOCASE);
+CREATE INDEX definitions_kind ON definitions(kind);
+CREATE INDEX definitions_file ON definitions(file_id);
+CREATE INDEX definitions_parent ON definitions(parent COLLATE NOCASE);
+CREATE INDEX call_sites_method ON call_sites(method_name COLLATE NOCASE);
+CREATE INDEX call_sites_caller ON call_sites(caller_id);
+";
+
+/// Rows per INSERT statement.
+const BATCH: usize = 500;
+
+/// A SQL string literal.
+fn text(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn opt_text(s: Option<&str>) -> String {
+    s.map_or_else(|| "NULL".to_string(), text)
+}
+
+fn json_list(items: &[String]) -> String {
+    text(&serde_json::to_string(items).unwrap_or_else(|_| "[]".to_string()))
+}
+
+/// Append `rows` as INSERT statements of up to [`BATCH`] rows each.
+fn insert(out: &mut String, table: &str, rows: impl Iterator<Item = String>) {
+    let mut rows = rows.peekable();
+    while rows.peek().is_some() {
+        let batch: Vec<String> = rows.by_ref().take(BATCH).collect();
+        let _ = writeln!(out, "INSERT INTO {} VALUES\n{};", table, batch.join(",\n"));
+    }
+}
+
+/// The SQL script of `index`, in one transaction.
+pub fn to_sql(index: &DefinitionIndex) -> String {
+    let mut out = String::from("BEGIN TRANSACTION;\n");
+    out.push_str(SCHEMA);
+
+    let mut file_ids: Vec<u32> = index.file_index.keys().copied().collect();
+    file_ids.sort_unstable();
+    insert(&mut out, "files", file_ids.iter().map(|&id| {
+        format!("({},{})", id, text(index.files.get(id as usize).map_or("", |s| s.as_str())))
+    }));
+
+    let mut def_ids: Vec<u32> = index.file_index.values().flatten().copied().collect();
+    def_ids.sort_unstable();
+    insert(&mut out, "definitions", def_ids.iter().map(|&id| {
+        let d = &index.definitions[id as usize];
+        format!("({},{},{},{},{},{},{},{},{},{},{})",
+            id, d.file_id, text(&d.name), text(d.kind.as_str()), d.line_start, d.line_end,
+            opt_text(d.parent.as_deref()), opt_text(d.signature.as_deref()),
+            json_list(&d.modifiers), json_list(&d.attributes), json_list(&d.base_types))
+    }));
+
+    insert(&mut out, "call_sites", def_ids.iter().flat_map(|&id| {
+        index.method_calls.get(&id).into_iter().flatten().map(move |c| format!("({},{},{},{},{},{},{},{})",
+            id, text(&c.method_name), opt_text(c.receiver_type.as_deref()), opt_text(c.receiver.as_deref()),
+            c.line, c.column, c.end_column, c.arg_count.map_or_else(|| "NULL".to_string(), |n| n.to_string())))
+    }));
+
+    insert(&mut out, "code_stats", def_ids.iter().filter_map(|&id| {
+        let s = index.code_stats.get(&id)?;
+        Some(format!("({},{},{},{},{},{},{},{},{:.2},{:.2},{})",
+            id, s.cyclomatic_complexity, s.cognitive_complexity, s.max_nesting_depth, s.param_count,
+            s.return_count, s.call_count, s.lambda_count, s.halstead_volume, s.halstead_difficulty,
+            s.maintainability_index))
+    }));
+
+    out.push_str(INDEXES);
+    out.push_str("COMMIT;\n");
+    out
+}
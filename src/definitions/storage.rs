@@ -1,11 +1,14 @@
 //! Persistence for DefinitionIndex: save/load/find on disk.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::clean_path;
+use crate::sqlite::{self, Storage};
 
 use super::types::DefinitionIndex;
 
+pub use search::definition_index::DEFINITION_INDEX_VERSION;
+
 pub fn definition_index_path_for(dir: &str, exts: &str, index_base: &std::path::Path) -> PathBuf {
     let canonical = std::fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
     let hash = search::stable_hash(&[
@@ -18,16 +21,47 @@ pub fn definition_index_path_for(dir: &str, exts: &str, index_base: &std::path::
 }
 
 pub fn save_definition_index(index: &DefinitionIndex, index_base: &std::path::Path) -> Result<(), crate::SearchError> {
+    save_definition_index_as(index, index_base, None)
+}
+
+/// Save a definition index in `storage`, removing its copy in the other format. `None`
+/// keeps the format it was saved in before.
+pub fn save_definition_index_as(index: &DefinitionIndex, index_base: &std::path::Path, storage: Option<Storage>) -> Result<(), crate::SearchError> {
     std::fs::create_dir_all(index_base)?;
     let exts_str = index.extensions.join(",");
     let path = definition_index_path_for(&index.root, &exts_str, index_base);
-    crate::index::save_compressed(&path, index, "definition-index")
+    let database = sqlite::database_path(&path);
+    if storage.unwrap_or_else(|| sqlite::storage_of(&path)) == Storage::Sqlite {
+        let _lock = crate::lock::IndexLock::acquire(&path)?;
+        sqlite::save_definition_index(&database, index)?;
+        let _ = std::fs::remove_file(&path);
+        return Ok(());
+    }
+    crate::index::save_compressed_versioned(&path, index, "definition-index", DEFINITION_INDEX_VERSION)?;
+    sqlite::remove_database(&database);
+    Ok(())
 }
 
 #[allow(dead_code)]
 pub fn load_definition_index(dir: &str, exts: &str, index_base: &std::path::Path) -> Result<DefinitionIndex, crate::SearchError> {
     let path = definition_index_path_for(dir, exts, index_base);
-    crate::index::load_compressed(&path, "definition-index")
+    load_definition_index_file(&sqlite::stored_path(&path))
+}
+
+/// Load a definition index file of any [`DEFINITION_INDEX_VERSION`] up to the current
+/// one, or a definition index database (see [`crate::sqlite`]).
+pub fn load_definition_index_file(path: &Path) -> Result<DefinitionIndex, crate::SearchError> {
+    if path.extension().is_some_and(|e| e == sqlite::EXTENSION) {
+        return sqlite::load_definition_index(path);
+    }
+    crate::index::load_compressed_versioned(path, "definition-index", DEFINITION_INDEX_VERSION, |version, payload| {
+        let index = search::definition_index::decode(version, payload)?;
+        if version < 2 {
+            eprintln!("[definition-index] Migrated {} from format version {} (call-site columns unknown until rebuilt)",
+                path.display(), version.max(1));
+        }
+        Ok(index)
+    })
 }
 
 /// Try to find any definition index for a directory (any extension combo)
@@ -39,8 +73,8 @@ pub fn find_definition_index_for_dir(dir: &str, index_base: &std::path::Path) ->
 
     for entry in entries.flatten() {
         let path = entry.path();
-        if path.extension().is_some_and(|e| e == "code-structure") {
-            match crate::index::load_compressed::<DefinitionIndex>(&path, "definition-index") {
+        if path.extension().is_some_and(|e| e == "code-structure") || sqlite::is_database_of(&path, "code-structure") {
+            match load_definition_index_file(&path) {
                 Ok(index) => {
                     let idx_root = std::fs::canonicalize(&index.root)
                         .map(|p| clean_path(&p.to_string_lossy()))
@@ -56,4 +90,4 @@ pub fn find_definition_index_for_dir(dir: &str, index_base: &std::path::Path) ->
         }
     }
     None
-}
\ No newline at end of file
+}
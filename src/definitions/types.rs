@@ -1,250 +1,74 @@
 //! Core data types for the definition index.
+//!
+//! The types and queries are in the library ([`search::definition_index`]), for
+//! programs that load an index without the parsers; this adds the CLI arguments and
+//! what the parsers need to fill them in.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
-
-use serde::{Deserialize, Serialize};
-
-// ─── Definition Kind ─────────────────────────────────────────────────
-
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum DefinitionKind {
-    // C# kinds
-    Class,
-    Interface,
-    Enum,
-    Struct,
-    Record,
-    Method,
-    Property,
-    Field,
-    Constructor,
-    Delegate,
-    Event,
-    EnumMember,
-    // TypeScript kinds
-    Function,
-    TypeAlias,
-    Variable,
-    // SQL kinds
-    StoredProcedure,
-    Table,
-    View,
-    SqlFunction,
-    UserDefinedType,
-    Column,
-    SqlIndex,
-}
+pub use search::definition_index::{
+    normalize_receiver, CallSite, CodeStats, DefinitionEntry, DefinitionIndex, DefinitionKind, DefinitionQuery, DiRegistration,
+    DocComment, DocParam, Endpoint, FileImports, NamespaceScope, PartialType,
+};
 
-impl DefinitionKind {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            Self::Class => "class",
-            Self::Interface => "interface",
-            Self::Enum => "enum",
-            Self::Struct => "struct",
-            Self::Record => "record",
-            Self::Method => "method",
-            Self::Property => "property",
-            Self::Field => "field",
-            Self::Constructor => "constructor",
-            Self::Delegate => "delegate",
-            Self::Event => "event",
-            Self::EnumMember => "enumMember",
-            Self::Function => "function",
-            Self::TypeAlias => "typeAlias",
-            Self::Variable => "variable",
-            Self::StoredProcedure => "storedProcedure",
-            Self::Table => "table",
-            Self::View => "view",
-            Self::SqlFunction => "sqlFunction",
-            Self::UserDefinedType => "userDefinedType",
-            Self::Column => "column",
-            Self::SqlIndex => "sqlIndex",
-        }
-    }
-}
+// ─── Call Sites ──────────────────────────────────────────────────────
 
-impl std::fmt::Display for DefinitionKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.as_str())
-    }
+/// Constructors of [`CallSite`] from the tree-sitter nodes of a parsed call.
+pub(crate) trait CallSiteExt: Sized {
+    fn at_node(method_name: String, receiver_type: Option<String>, name_node: tree_sitter::Node, source: &[u8]) -> Self;
+    fn with_arguments(self, call: tree_sitter::Node) -> Self;
 }
 
-impl std::str::FromStr for DefinitionKind {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "class" => Ok(Self::Class),
-            "interface" => Ok(Self::Interface),
-            "enum" => Ok(Self::Enum),
-            "struct" => Ok(Self::Struct),
-            "record" => Ok(Self::Record),
-            "method" => Ok(Self::Method),
-            "property" => Ok(Self::Property),
-            "field" => Ok(Self::Field),
-            "constructor" => Ok(Self::Constructor),
-            "delegate" => Ok(Self::Delegate),
-            "event" => Ok(Self::Event),
-            "enummember" => Ok(Self::EnumMember),
-            "function" => Ok(Self::Function),
-            "typealias" => Ok(Self::TypeAlias),
-            "variable" => Ok(Self::Variable),
-            "storedprocedure" => Ok(Self::StoredProcedure),
-            "table" => Ok(Self::Table),
-            "view" => Ok(Self::View),
-            "sqlfunction" => Ok(Self::SqlFunction),
-            "userdefinedtype" => Ok(Self::UserDefinedType),
-            "column" => Ok(Self::Column),
-            "sqlindex" => Ok(Self::SqlIndex),
-            other => Err(format!("Unknown definition kind: '{}'", other)),
+impl CallSiteExt for CallSite {
+    /// Call site of `method_name` whose called name is `name_node`: `line`, `column` and
+    /// `end_column` come from the node. `end_column` stops at the end of its first line.
+    fn at_node(method_name: String, receiver_type: Option<String>, name_node: tree_sitter::Node, source: &[u8]) -> Self {
+        let start = name_node.start_byte();
+        let line_start = start - name_node.start_position().column;
+        let end = if name_node.end_position().row == name_node.start_position().row {
+            name_node.end_byte()
+        } else {
+            start + source[start..].iter().position(|&b| b == b'\n').unwrap_or(source.len() - start)
+        };
+        let column = char_count(&source[line_start..start]) + 1;
+        Self {
+            method_name,
+            receiver_type,
+            line: name_node.start_position().row as u32 + 1,
+            receiver_is_generic: false,
+            column,
+            end_column: column + char_count(&source[start..end]),
+            receiver: None,
+            arg_count: None,
         }
     }
-}
-
-// ─── Definition Entry ────────────────────────────────────────────────
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct DefinitionEntry {
-    pub file_id: u32,
-    pub name: String,
-    pub kind: DefinitionKind,
-    pub line_start: u32,
-    pub line_end: u32,
-    pub parent: Option<String>,
-    pub signature: Option<String>,
-    pub modifiers: Vec<String>,
-    pub attributes: Vec<String>,
-    pub base_types: Vec<String>,
-}
-
-// ─── Code Stats ──────────────────────────────────────────────────────
-
-/// Code complexity metrics computed during AST walk.
-/// Only populated for Method, Constructor, Function, Property (expression body).
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
-pub struct CodeStats {
-    /// Cyclomatic complexity: number of linearly independent execution paths.
-    /// Base = 1, each branching node adds +1.
-    pub cyclomatic_complexity: u16,
-    /// SonarSource Cognitive Complexity: penalizes nesting depth.
-    /// Measures code readability, not just structural complexity.
-    pub cognitive_complexity: u16,
-    /// Maximum nesting depth of control flow structures.
-    pub max_nesting_depth: u8,
-    /// Number of parameters in the method/function signature.
-    pub param_count: u8,
-    /// Number of return + throw statements (exit points).
-    pub return_count: u8,
-    /// Number of method/function calls in the body (fan-out).
-    pub call_count: u16,
-    /// Number of lambda/arrow function expressions in the body.
-    pub lambda_count: u8,
-}
-
-/// A call site found in a method/constructor body via AST analysis.
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct CallSite {
-    /// Name of the method being called, e.g., "GetUser"
-    pub method_name: String,
-    /// Resolved type of the receiver, e.g., "IUserService".
-    /// None for simple calls like Foo() where receiver type is unknown.
-    pub receiver_type: Option<String>,
-    /// Line number where the call occurs (1-based)
-    pub line: u32,
-    /// Whether the receiver type at the call site had generic parameters,
-    /// e.g., `new List<int>()` → true, `new List()` → false.
-    /// Used to filter out name collisions with non-generic classes.
-    #[serde(default)]
-    pub receiver_is_generic: bool,
-}
 
-// ─── Definition Index ────────────────────────────────────────────────
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DefinitionIndex {
-    pub root: String,
-    pub created_at: u64,
-    pub extensions: Vec<String>,
-    /// file_id -> file path
-    pub files: Vec<String>,
-    /// All definitions
-    pub definitions: Vec<DefinitionEntry>,
-    /// name (lowercased) -> Vec<index into definitions>
-    pub name_index: HashMap<String, Vec<u32>>,
-    /// kind -> Vec<index into definitions>
-    pub kind_index: HashMap<DefinitionKind, Vec<u32>>,
-    /// attribute name (lowercased) -> Vec<index into definitions>
-    pub attribute_index: HashMap<String, Vec<u32>>,
-    /// base type name (lowercased) -> Vec<index into definitions>
-    pub base_type_index: HashMap<String, Vec<u32>>,
-    /// file_id -> Vec<index into definitions>
-    pub file_index: HashMap<u32, Vec<u32>>,
-    /// Path -> file_id lookup (for watcher)
-    pub path_to_id: HashMap<PathBuf, u32>,
-    /// def_idx -> list of call sites found in that method/constructor body.
-    /// Only populated for Method and Constructor kinds.
-    #[serde(default)]
-    pub method_calls: HashMap<u32, Vec<CallSite>>,
-    /// Number of files that could not be read (IO errors) during index build.
-    #[serde(default)]
-    pub parse_errors: usize,
-    /// Number of files that contained non-UTF8 bytes and were read with lossy conversion.
-    #[serde(default)]
-    pub lossy_file_count: usize,
-    /// Files that were read and parsed but produced 0 definitions.
-    /// Each entry is (file_id, byte_size). Files >500 bytes with 0 defs are suspicious.
-    #[serde(default)]
-    pub empty_file_ids: Vec<(u32, u64)>,
-    /// def_idx -> CodeStats for methods/constructors/functions.
-    /// Always populated when --definitions is used.
-    #[serde(default)]
-    pub code_stats: HashMap<u32, CodeStats>,
-    /// Extension method name → Vec of static class names containing the extension.
-    /// Populated during C# parsing by detecting static classes with `this` parameter methods.
-    #[serde(default)]
-    pub extension_methods: HashMap<String, Vec<String>>,
-    /// Angular component selector → def_idx of the @Component class.
-    /// Example: "datahub-compact-view" → [idx of DatahubCompactViewComponent]
-    #[serde(default)]
-    pub selector_index: HashMap<String, Vec<u32>>,
-    /// def_idx of component → child selectors from HTML template.
-    /// Example: idx of DatahubEmbedComponent → ["datahub-compact-view", "pbi-spinner"]
-    #[serde(default)]
-    pub template_children: HashMap<u32, Vec<String>>,
+    /// This call site with the argument count of `call`, the invocation, object
+    /// creation or `new` expression it was read from.
+    fn with_arguments(self, call: tree_sitter::Node) -> Self {
+        let arg_count = match call.child_by_field_name("arguments") {
+            Some(args) if matches!(args.kind(), "argument_list" | "arguments") => {
+                let mut cursor = args.walk();
+                let args: Vec<_> = args.named_children(&mut cursor).filter(|a| a.kind() != "comment").collect();
+                if args.iter().any(|a| a.kind() == "spread_element") { None } else { u8::try_from(args.len()).ok() }
+            }
+            // Tagged template
+            Some(_) => None,
+            // `new Foo { X = 1 }` and `new Foo` without an argument list
+            None => Some(0),
+        };
+        Self { arg_count, ..self }
+    }
 }
 
-impl Default for DefinitionIndex {
-    fn default() -> Self {
-        Self {
-            root: String::new(),
-            created_at: 0,
-            extensions: Vec::new(),
-            files: Vec::new(),
-            definitions: Vec::new(),
-            name_index: HashMap::new(),
-            kind_index: HashMap::new(),
-            attribute_index: HashMap::new(),
-            base_type_index: HashMap::new(),
-            file_index: HashMap::new(),
-            path_to_id: HashMap::new(),
-            method_calls: HashMap::new(),
-            code_stats: HashMap::new(),
-            parse_errors: 0,
-            lossy_file_count: 0,
-            empty_file_ids: Vec::new(),
-            extension_methods: HashMap::new(),
-            selector_index: HashMap::new(),
-            template_children: HashMap::new(),
-        }
-    }
+fn char_count(bytes: &[u8]) -> u32 {
+    String::from_utf8_lossy(bytes).chars().count() as u32
 }
 
 // ─── CLI Args ────────────────────────────────────────────────────────
 
 use clap::Parser;
 
+use crate::priority::BuildPriority;
+
 #[derive(Parser, Debug)]
 #[command(after_long_help = r#"WHAT IT DOES:
   Parses C#, TypeScript, and SQL files using tree-sitter to extract code structure:
@@ -260,12 +84,19 @@ use clap::Parser;
   The index is saved to disk as a .code-structure file and can be loaded instantly
   by 'search serve --definitions'.
 
+  Other languages can be added from external tools with --import: a ctags tags
+  file (classic or Universal Ctags JSON, with line numbers) or a SCIP index.
+  Relative paths in them are resolved against --dir.
+
 EXAMPLES:
   Index C# files:     search def-index --dir C:\Projects --ext cs
   Index TypeScript:   search def-index --dir C:\Projects --ext ts,tsx
   Index C# + SQL:     search def-index --dir C:\Projects --ext cs,sql
   Index all:          search def-index --dir C:\Projects --ext cs,sql,ts,tsx
   Custom threads:     search def-index --dir C:\Projects --ext cs --threads 8
+  Estimate only:      search def-index --dir C:\Projects --ext cs,ts,tsx --dry-run
+  Background build:   search def-index --dir C:\Projects --ext cs --build-priority low --io-limit-mb 50
+  Import ctags/SCIP:  search def-index --dir C:\Projects --ext cs --import tags --import index.scip
 
 PERFORMANCE:
   48,643 files -> 846,167 definitions in ~14s (24 threads)
@@ -287,6 +118,41 @@ pub struct DefIndexArgs {
     /// tree-sitter parser instance. 0 = auto-detect CPU cores.
     #[arg(short, long, default_value = "0")]
     pub threads: usize,
+
+    /// Walk at most N levels below --dir, counting files directly in it as level 1
+    /// (0 = unlimited). Recorded in the index.
+    #[arg(long, default_value = "0")]
+    pub max_depth: usize,
+
+    /// Skip a directory and everything below it: a directory name (e.g. node_modules)
+    /// or a path relative to --dir (e.g. src/legacy). Repeatable; recorded in the index.
+    #[arg(long, value_name = "DIR")]
+    pub prune_dir: Vec<String>,
+
+    /// Walk the tree and print file counts and estimated definition count,
+    /// index size and RAM without parsing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Build priority: "low" caps worker threads to ~25% of cores and runs them
+    /// at background OS priority so the machine stays responsive
+    #[arg(long, value_enum, default_value = "normal")]
+    pub build_priority: BuildPriority,
+
+    /// Throttle file reads during the build to N MB/s (0 = unlimited)
+    #[arg(long, default_value = "0")]
+    pub io_limit_mb: u64,
+
+    /// Import definitions from a ctags tags file (classic or JSON) or a SCIP index,
+    /// for files no built-in parser handles. Repeatable; recorded in the index.
+    #[arg(long, value_name = "FILE")]
+    pub import: Vec<String>,
+
+    /// Store the index as one bincode file, or in a SQLite database that saves only
+    /// the files that changed and holds definitions, call sites and code stats as
+    /// SQL tables. Omitted: keep the current format (bincode for a new index)
+    #[arg(long, value_enum)]
+    pub storage: Option<crate::sqlite::Storage>,
 }
 
 #[derive(Parser, Debug)]
@@ -320,4 +186,276 @@ pub struct DefAuditArgs {
     /// Also show files that required lossy UTF-8 conversion.
     #[arg(long)]
     pub show_lossy: bool,
-}
\ No newline at end of file
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Prints the nested definition structure of one file — classes, members, and
+  their line ranges — from a previously built definition index. The file is
+  not read or re-parsed.
+
+  FILE may be an absolute path or any unique path suffix within the index.
+
+EXAMPLES:
+  Outline a file:          search outline Services/UserService.cs --dir C:\Projects --ext cs
+  JSON output:             search outline UserService.cs --dir C:\Projects --ext cs --json
+"#)]
+pub struct OutlineArgs {
+    /// File to outline (absolute path or unique suffix, e.g. Services/UserService.cs)
+    pub file: String,
+
+    /// Directory that was indexed (must match the --dir used during def-index)
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions that were indexed (must match the --ext used during def-index)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Print JSON (same shape as the search_outline MCP tool) instead of an indented tree
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Exports the call graph of whole classes or namespaces: an edge from every method,
+  constructor and function in scope to each method it calls, with the number of
+  call sites. Calls are resolved like search_callers direction=down.
+
+  Namespaces are not in the definition index: a file is in a namespace when it
+  declares it (nested namespaces included), which is read from disk.
+
+EXAMPLES:
+  One class, Graphviz:      search callgraph --class OrderService --dir C:\Projects > orders.dot
+  Namespace, Mermaid:       search callgraph --namespace Billing.Invoices --export mermaid
+  Calls inside the scope:   search callgraph --class OrderService,OrderRepository --internal
+  Edge list for tooling:    search callgraph --namespace Billing --export json
+"#)]
+pub struct CallgraphArgs {
+    /// Directory that was indexed (must match the --dir used during def-index)
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions that were indexed (must match the --ext used during def-index)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Classes whose methods to include (comma-separated, case-insensitive)
+    #[arg(long, value_delimiter = ',')]
+    pub class: Vec<String>,
+
+    /// Namespaces whose methods to include (comma-separated; nested namespaces included)
+    #[arg(long, value_delimiter = ',')]
+    pub namespace: Vec<String>,
+
+    /// Only calls between methods in scope (drops calls out to other classes)
+    #[arg(long)]
+    pub internal: bool,
+
+    /// Output: dot (Graphviz), mermaid, or json ({nodes, edges}). A global
+    /// `--format json` implies json.
+    #[arg(long, value_enum, default_value = "dot")]
+    pub export: super::GraphFormat,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Converts a definition index into a SCIP index or an LSIF dump for
+  Sourcegraph-style code navigation: go to definition, find references and
+  hover signatures, without running a language server.
+
+  Definitions become symbols (namespace, parent and name); call sites that
+  resolve to a definition become references, resolved like search_callers
+  direction=down. The indexed files are read from disk to place the names.
+
+  --to sql writes a SQL script instead: files, definitions, call_sites and
+  code_stats tables with lookup indexes, for ad-hoc queries in SQLite.
+
+EXAMPLES:
+  SCIP for Sourcegraph:     search export --dir C:\Projects --ext cs
+  LSIF dump:                search export --dir C:\Projects --ext cs --to lsif
+  Custom output path:       search export --dir C:\Projects --ext cs,ts --out build/index.scip
+  SQLite database:          search export --dir C:\Projects --ext cs --to sql && sqlite3 defs.db < index.sql
+"#)]
+pub struct ExportArgs {
+    /// Directory that was indexed (must match the --dir used during def-index)
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions that were indexed (must match the --ext used during def-index)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Output format: scip (protobuf), lsif (JSON lines) or sql (SQLite script)
+    #[arg(long, value_enum, default_value = "scip")]
+    pub to: super::ExportFormat,
+
+    /// Output file (default: index.scip, dump.lsif or index.sql)
+    #[arg(short, long)]
+    pub out: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Finds near-duplicate (copy-pasted) methods, constructors, and functions.
+  Every body from a previously built definition index is tokenized, split into
+  runs of --shingle-size consecutive tokens, and compared by Jaccard similarity.
+  Pairs at or above --threshold are printed with both line ranges.
+
+  The files containing the scanned bodies are read from disk.
+
+EXAMPLES:
+  Whole index:             search dupes --dir C:\Projects --ext cs
+  Looser match, one area:  search dupes --dir C:\Projects --ext cs --threshold 0.6 --file Services
+  Only large bodies:       search dupes --dir C:\Projects --ext cs --min-lines 20 --json
+  Code scanning (SARIF):   search dupes --dir C:\Projects --ext cs --format sarif > dupes.sarif
+"#)]
+pub struct DupesArgs {
+    /// Directory that was indexed (must match the --dir used during def-index)
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions that were indexed (must match the --ext used during def-index)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Minimum similarity (0-1) for a pair to be reported
+    #[arg(long, default_value = "0.8")]
+    pub threshold: f64,
+
+    /// Ignore bodies shorter than this many lines
+    #[arg(long, default_value = "5")]
+    pub min_lines: u32,
+
+    /// Tokens per shingle; larger values require longer identical token runs
+    #[arg(long, default_value = "5")]
+    pub shingle_size: usize,
+
+    /// Only scan files whose path contains this substring
+    #[arg(long)]
+    pub file: Option<String>,
+
+    /// Skip paths containing this substring (repeatable)
+    #[arg(long)]
+    pub exclude_dir: Vec<String>,
+
+    /// Max pairs to print (0 = unlimited)
+    #[arg(long, default_value = "50")]
+    pub max_results: usize,
+
+    /// Print JSON (same shape as the search_dupes MCP tool)
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Finds private methods and unexported functions that nothing references.
+  Such a definition can only be called from its own file (or the other parts of
+  its partial class), so only those files are read: a candidate whose name
+  appears as a whole word nowhere but on its declaration line is reported.
+
+  Candidates: C# private methods (explicit or by default), TypeScript private
+  methods and top-level functions without `export`. Definitions with attributes,
+  override/virtual/abstract/extern/partial methods, C# Main and explicit interface
+  members are never reported. Mentions in comments and strings count as uses.
+
+EXAMPLES:
+  Whole index:             search deadcode --dir C:\Projects --ext cs
+  One area:                search deadcode --dir C:\Projects --ext cs,ts --file Services --json
+  Code scanning (SARIF):   search deadcode --dir C:\Projects --ext cs --format sarif > deadcode.sarif
+"#)]
+pub struct DeadCodeArgs {
+    /// Directory that was indexed (must match the --dir used during def-index)
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions that were indexed (must match the --ext used during def-index)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Only scan files whose path contains this substring
+    #[arg(long)]
+    pub file: Option<String>,
+
+    /// Skip paths containing this substring (repeatable)
+    #[arg(long)]
+    pub exclude_dir: Vec<String>,
+
+    /// Max definitions to print (0 = unlimited)
+    #[arg(long, default_value = "50")]
+    pub max_results: usize,
+
+    /// Print JSON ({"definitions": [...]})
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(after_long_help = r#"WHAT IT DOES:
+  Searches a previously built definition index with the same filters as the
+  search_definitions MCP tool, written as a key:value query. Bare words are
+  name filters (substring match; several words = OR).
+
+QUERY KEYS:
+  name:<text>       Name substring (same as a bare word)
+  kind:<kind>       class, interface, method, property, storedProcedure, ...
+  base:<type>       Base type / implemented interface
+  attr:<name>       Attribute, e.g. attr:Authorize
+  file:<substr>     File path substring
+  parent:<substr>   Containing type (alias: in:)
+  exclude:<substr>  Exclude paths containing substr (repeatable)
+  owner:<team>      Files owned by team per CODEOWNERS under the index root
+  line:<N>          Definitions containing line N (requires file:)
+  sort:<metric>     cyclomaticComplexity, cognitiveComplexity, lines, ...
+  limit:<N>         Max results (default: 100, 0 = unlimited)
+  regex:true        Treat the name as a regex
+  body:true         Include source bodies
+  stats:true        Include code stats
+  complexity:<N>, cognitive:<N>, nesting:<N>, params:<N>, returns:<N>, calls:<N>,
+  volume:<N>, difficulty:<N>
+                    Minimum code-stat thresholds
+  mi:<N>            Maximum maintainability index (0-100)
+  wmc:<N>, lcom:<N> Minimum class-level thresholds (classes only)
+
+  Quote values with spaces: file:"My Project/Controllers"
+
+TYPE HIERARCHY:
+  --implementations-of <TYPE> prints every class/interface deriving from TYPE,
+  recursively (same as search_type_hierarchy with direction=down). TYPE may be a
+  framework type that is not in the index, e.g. ControllerBase.
+
+EXAMPLES:
+  Controllers:             search defs "kind:class base:ControllerBase attr:Authorize file:Controllers"
+  By name:                 search defs "UserService kind:interface"
+  Complex methods:         search defs "kind:method complexity:20 sort:cyclomaticComplexity limit:10"
+  What's at a line:        search defs "file:UserService.cs line:120"
+  Implementations:         search defs --implementations-of IUserService --depth 5
+  Code scanning (SARIF):   search defs "kind:method complexity:20" --format sarif > complexity.sarif
+"#)]
+pub struct DefsArgs {
+    /// Query, e.g. "kind:class base:ControllerBase file:Controllers"
+    #[arg(required_unless_present = "implementations_of", conflicts_with = "implementations_of")]
+    pub query: Option<String>,
+
+    /// Print the subclass/implementation tree of this type instead of running a query
+    #[arg(long, value_name = "TYPE")]
+    pub implementations_of: Option<String>,
+
+    /// Max hierarchy depth for --implementations-of (max: 10)
+    #[arg(long, default_value = "3")]
+    pub depth: usize,
+
+    /// Directory that was indexed (must match the --dir used during def-index)
+    #[arg(short, long, default_value = ".")]
+    pub dir: String,
+
+    /// File extensions that were indexed (must match the --ext used during def-index)
+    #[arg(short, long, default_value = "cs")]
+    pub ext: String,
+
+    /// Print the JSON response (same shape as the search_definitions MCP tool)
+    #[arg(long)]
+    pub json: bool,
+}
@@ -42,8 +42,8 @@ pub enum SearchError {
     SaveFailed(String),
 
     /// Phrase has no indexable tokens
-    #[error("Phrase '{phrase}' has no indexable tokens (min length 2)")]
-    EmptyPhrase { phrase: String },
+    #[error("Phrase '{phrase}' has no indexable tokens (min length {min_token_len})")]
+    EmptyPhrase { phrase: String, min_token_len: usize },
 
     /// Mutually exclusive flags or other argument validation error
     #[error("{0}")]
@@ -55,6 +55,97 @@ pub enum SearchError {
         path: String,
         message: String,
     },
+
+    /// Another process kept an index file locked for writing (see `lock`)
+    #[error("Index file {path} is locked by {holder}; gave up after waiting {waited_secs}s. Retry when it has finished saving")]
+    IndexLocked {
+        path: String,
+        holder: String,
+        waited_secs: u64,
+    },
+
+    /// A git command failed or a revision could not be resolved
+    #[error("Git error: {0}")]
+    Git(String),
+
+    /// An index the operation needs is still being built; the message says which
+    #[error("{0}")]
+    IndexBuilding(String),
+}
+
+/// What a client can do about an error before calling again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuggestedAction {
+    /// Call again unchanged, optionally after a short pause
+    Retry,
+    /// Call again once the index build (or save) in progress has finished
+    WaitForBuild,
+    /// Rebuild the index (`search_reindex` or `search content-index`)
+    RebuildIndex,
+    /// Use a smaller or simpler regex
+    NarrowRegex,
+    /// Correct the arguments; the same call fails again
+    FixArguments,
+    /// Restart the server: its in-memory state is unusable
+    RestartServer,
+}
+
+impl SuggestedAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SuggestedAction::Retry => "retry",
+            SuggestedAction::WaitForBuild => "wait_for_build",
+            SuggestedAction::RebuildIndex => "rebuild_index",
+            SuggestedAction::NarrowRegex => "narrow_regex",
+            SuggestedAction::FixArguments => "fix_arguments",
+            SuggestedAction::RestartServer => "restart_server",
+        }
+    }
+}
+
+impl SearchError {
+    /// Stable machine-readable name of the variant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SearchError::Io(_) => "io",
+            SearchError::Serialization(_) => "serialization",
+            SearchError::InvalidRegex { .. } => "invalid_regex",
+            SearchError::DirNotFound(_) => "dir_not_found",
+            SearchError::IndexNotFound { .. } => "index_not_found",
+            SearchError::StaleIndex { .. } => "stale_index",
+            SearchError::LockPoisoned(_) => "lock_poisoned",
+            SearchError::SaveFailed(_) => "save_failed",
+            SearchError::EmptyPhrase { .. } => "empty_phrase",
+            SearchError::InvalidArgs(_) => "invalid_arguments",
+            SearchError::IndexLoad { .. } => "index_load",
+            SearchError::IndexLocked { .. } => "index_locked",
+            SearchError::Git(_) => "git",
+            SearchError::IndexBuilding(_) => "index_building",
+        }
+    }
+
+    /// Whether the same call can succeed later without any change on the caller's side.
+    pub fn retryable(&self) -> bool {
+        matches!(self.suggested_action(), SuggestedAction::Retry | SuggestedAction::WaitForBuild)
+    }
+
+    pub fn suggested_action(&self) -> SuggestedAction {
+        match self {
+            SearchError::Io(_) | SearchError::SaveFailed(_) => SuggestedAction::Retry,
+            SearchError::IndexBuilding(_) | SearchError::IndexLocked { .. } => SuggestedAction::WaitForBuild,
+            SearchError::Serialization(_)
+            | SearchError::IndexNotFound { .. }
+            | SearchError::StaleIndex { .. }
+            | SearchError::IndexLoad { .. } => SuggestedAction::RebuildIndex,
+            SearchError::InvalidRegex { source: regex::Error::CompiledTooBig(_), .. } => SuggestedAction::NarrowRegex,
+            SearchError::InvalidRegex { .. }
+            | SearchError::DirNotFound(_)
+            | SearchError::EmptyPhrase { .. }
+            | SearchError::InvalidArgs(_)
+            | SearchError::Git(_) => SuggestedAction::FixArguments,
+            SearchError::LockPoisoned(_) => SuggestedAction::RestartServer,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -101,11 +192,41 @@ mod tests {
     fn test_empty_phrase_display() {
         let err = SearchError::EmptyPhrase {
             phrase: "a b".to_string(),
+            min_token_len: 2,
         };
         assert!(err.to_string().contains("a b"));
         assert!(err.to_string().contains("no indexable tokens"));
     }
 
+    #[test]
+    fn test_index_locked_display() {
+        let err = SearchError::IndexLocked {
+            path: "a.word-search".to_string(),
+            holder: "pid 42: search serve".to_string(),
+            waited_secs: 30,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("a.word-search") && msg.contains("pid 42") && msg.contains("30s"));
+    }
+
+    #[test]
+    fn test_retry_hints() {
+        let building = SearchError::IndexBuilding("Content index is being built".to_string());
+        assert_eq!((building.code(), building.retryable(), building.suggested_action()),
+            ("index_building", true, SuggestedAction::WaitForBuild));
+        let missing = SearchError::IndexNotFound { dir: ".".to_string() };
+        assert!(!missing.retryable());
+        assert_eq!(missing.suggested_action().as_str(), "rebuild_index");
+
+        let unclosed = "(".to_string();
+        let invalid = regex::Regex::new(&unclosed).unwrap_err();
+        let err = SearchError::InvalidRegex { pattern: unclosed, source: invalid };
+        assert_eq!(err.suggested_action(), SuggestedAction::FixArguments);
+        let huge = regex::RegexBuilder::new(r"\w{500}").size_limit(1000).build().unwrap_err();
+        let err = SearchError::InvalidRegex { pattern: r"\w{500}".to_string(), source: huge };
+        assert_eq!(err.suggested_action(), SuggestedAction::NarrowRegex);
+    }
+
     #[test]
     fn test_io_error_from_conversion() {
         let io_err = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "access denied");
@@ -323,7 +323,7 @@ pub fn parse_git_log_stream(
     let progress_start = std::time::Instant::now();
     let mut last_progress = std::time::Instant::now();
 
-    for line_result in reader.lines() {
+    for line_result in super::escaped_lines(reader) {
         let line = line_result.map_err(|e| format!("IO error reading git log: {}", e))?;
 
         if line.starts_with(COMMIT_PREFIX) {
@@ -413,8 +413,8 @@ fn matches_path_prefix(file_path: &str, query_path: &str) -> bool {
 // ─── Public API ─────────────────────────────────────────────────────
 
 impl GitHistoryCache {
-    /// Save cache to disk using bincode + LZ4 compression (via save_compressed).
-    /// Uses atomic write: write to temp file, then rename.
+    /// Save cache to disk using bincode + LZ4 compression (via save_compressed,
+    /// which writes atomically: temp file, then rename).
     pub fn save_to_disk(&self, path: &std::path::Path) -> Result<(), String> {
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
@@ -422,17 +422,8 @@ impl GitHistoryCache {
                 .map_err(|e| format!("Failed to create cache directory: {}", e))?;
         }
 
-        // Atomic write: write to temp file first, then rename
-        let tmp_path_str = format!("{}.tmp", path.display());
-        let tmp_path = std::path::PathBuf::from(&tmp_path_str);
-
-        crate::index::save_compressed(&tmp_path, self, "git-history")
-            .map_err(|e| format!("Failed to save git cache: {}", e))?;
-
-        std::fs::rename(&tmp_path, path)
-            .map_err(|e| format!("Failed to rename temp cache file: {}", e))?;
-
-        Ok(())
+        crate::index::save_compressed(path, self, "git-history")
+            .map_err(|e| format!("Failed to save git cache: {}", e))
     }
 
     /// Load cache from disk using bincode + LZ4 decompression.
@@ -466,6 +457,28 @@ impl GitHistoryCache {
         index_base.join(format!("{}_{:08x}.git-history", prefix, hash as u32))
     }
 
+    /// Load the on-disk cache for `dir` if it matches the default branch HEAD,
+    /// otherwise build it from `git log` and save it.
+    ///
+    /// Synchronous counterpart of the server's background cache thread, for CLI
+    /// commands that need history without a running server.
+    pub fn load_or_build(dir: &str, index_base: &std::path::Path) -> Result<Self, String> {
+        let repo_path = Path::new(dir);
+        let branch = Self::detect_default_branch(repo_path)?;
+        let head = Self::get_branch_head(repo_path, &branch)?;
+        let cache_path = Self::cache_path_for(dir, index_base);
+        if let Ok(cache) = Self::load_from_disk(&cache_path)
+            && cache.is_valid_for(&head) {
+                return Ok(cache);
+            }
+        eprintln!("[git-cache] Building cache for branch '{}'...", branch);
+        let cache = Self::build(repo_path, &branch)?;
+        if let Err(e) = cache.save_to_disk(&cache_path) {
+            eprintln!("[git-cache] Warning: failed to save cache: {}", e);
+        }
+        Ok(cache)
+    }
+
     /// Check if the cached HEAD hash is an ancestor of the current HEAD.
     /// Used to decide between incremental update and full rebuild.
     pub fn is_ancestor(repo_path: &Path, old_head: &str, new_head: &str) -> bool {
@@ -849,6 +862,36 @@ impl GitHistoryCache {
         activities
     }
 
+    /// Commits whose subject references `ticket` (a canonical ID such as `PAY-812` or
+    /// `#77`, see [`crate::normalize_ticket`]), newest first, each with the files it
+    /// changed (sorted).
+    pub fn query_ticket(&self, ticket: &str) -> Vec<(CommitInfo, Vec<String>)> {
+        let mut ids: Vec<u32> = self.commits.iter().enumerate()
+            .filter(|(_, meta)| {
+                let subject = self.get_subject(meta);
+                subject.contains(ticket) && crate::ticket_refs_in_text(subject).iter().any(|t| t == ticket)
+            })
+            .map(|(i, _)| i as u32)
+            .collect();
+        if ids.is_empty() {
+            return Vec::new();
+        }
+        ids.sort_by_key(|&id| std::cmp::Reverse(self.commits[id as usize].timestamp));
+
+        let wanted: std::collections::HashSet<u32> = ids.iter().copied().collect();
+        let mut files: HashMap<u32, Vec<String>> = HashMap::new();
+        for (file_path, commit_ids) in &self.file_commits {
+            for id in commit_ids.iter().filter(|id| wanted.contains(id)) {
+                files.entry(*id).or_default().push(file_path.clone());
+            }
+        }
+        ids.into_iter().map(|id| {
+            let mut changed = files.remove(&id).unwrap_or_default();
+            changed.sort();
+            (self.commit_meta_to_info(&self.commits[id as usize]), changed)
+        }).collect()
+    }
+
     /// Check if cache is still valid for the given HEAD hash.
     pub fn is_valid_for(&self, head_hash: &str) -> bool {
         self.head_hash == head_hash && self.format_version == FORMAT_VERSION
@@ -1738,4 +1738,18 @@ fn test_query_file_history_total_count_nonexistent_file() {
 
     assert!(history.is_empty());
     assert_eq!(total_count, 0, "Nonexistent file should have 0 total");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_non_utf8_author_and_path() {
+    // Latin-1 author name and file path: the commit is kept instead of failing the parse
+    let mut input = b"COMMIT:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\xe2\x90\x9e1700000000\xe2\x90\x9ejose@example.com\xe2\x90\x9eJos\xe9\xe2\x90\x9eFix\n".to_vec();
+    input.extend_from_slice(b"src/caf\xe9.rs\n\n");
+    let mut builder = GitHistoryCache::builder();
+    parse_git_log_stream(Cursor::new(input), &mut builder).expect("parse should succeed");
+    let cache = GitHistoryCache::from_builder(builder, "a".repeat(40), "main".to_string());
+    let path = crate::bytes_to_string_escaped(b"src/caf\xe9.rs");
+    let (history, total) = cache.query_file_history(&path, None, None, None, None, None);
+    assert_eq!(total, 1);
+    assert!(history[0].author_name.starts_with("Jos"));
+}
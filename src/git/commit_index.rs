@@ -0,0 +1,476 @@
+//! Commit content index — inverted index over commit messages and patch lines.
+//!
+//! Answers "which commit introduced the string X" with a HashMap lookup instead of
+//! `git log -S`, which re-diffs every commit on every query.
+//!
+//! Tokens come from the same tokenizer as the content index. Each posting records
+//! where in the commit the token appeared (message, added lines, removed lines).
+//! Commit ids follow `git log --reverse` order, so postings are oldest-first and
+//! incremental updates (`old_head..new_head`) only append.
+//!
+//! ## Module isolation
+//!
+//! Like [`cache`](super::cache), this module does NOT import from `src/index.rs`
+//! (other than the shared save/load helpers), `src/definitions/`, or `src/mcp/`.
+
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use search::Clock;
+
+// ─── Constants ──────────────────────────────────────────────────────
+
+/// Index format version. Bump when struct layout changes incompatibly.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Token appeared in the commit message (subject or body).
+pub const IN_MESSAGE: u8 = 1;
+/// Token appeared on an added (`+`) patch line.
+pub const IN_ADDED: u8 = 2;
+/// Token appeared on a removed (`-`) patch line.
+pub const IN_REMOVED: u8 = 4;
+
+/// Field separator in git log format — same as [`cache`](super::cache).
+const FIELD_SEP: &str = "␞";
+
+/// Commit header prefix in git log output.
+const COMMIT_PREFIX: &str = "COMMIT:";
+
+/// Line emitted after the commit message; everything up to the next header is patch.
+const MESSAGE_END: &str = "␞END␞";
+
+/// Patch lines longer than this (minified bundles, generated data) are not tokenized.
+const MAX_PATCH_LINE_LEN: usize = 1000;
+
+// ─── Core types ─────────────────────────────────────────────────────
+
+/// Commit metadata kept for result display.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedCommit {
+    /// SHA-1 hash as 40-char hex string.
+    pub hash: String,
+    /// Unix timestamp (seconds since epoch).
+    pub timestamp: i64,
+    pub author_name: String,
+    pub author_email: String,
+    /// First line of the commit message.
+    pub subject: String,
+}
+
+/// One commit containing a token, with [`IN_MESSAGE`] / [`IN_ADDED`] / [`IN_REMOVED`] flags.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommitPosting {
+    pub commit_id: u32,
+    pub flags: u8,
+}
+
+/// Inverted index over commit messages and, optionally, patch hunks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitIndex {
+    /// Index format version. Mismatch → full rebuild.
+    pub format_version: u32,
+    /// Canonical repository path the index was built from.
+    pub repo: String,
+    /// SHA-1 hex of HEAD when the index was last built or updated.
+    pub head_hash: String,
+    /// Whether patch lines were indexed (message-only indexes build much faster).
+    pub include_patches: bool,
+    /// Timestamp when the index was last built or updated (seconds since epoch).
+    pub built_at: u64,
+    /// All commits, oldest first. Index into this vec = "commit ID" used in postings.
+    pub commits: Vec<IndexedCommit>,
+    /// token (lowercased) → commits containing it, ascending by commit id.
+    pub tokens: HashMap<String, Vec<CommitPosting>>,
+}
+
+/// Filters for [`CommitIndex::search`].
+#[derive(Clone, Debug, Default)]
+pub struct CommitQuery {
+    /// Search terms. A multi-token term (`Foo.Bar`) matches commits containing all its tokens.
+    pub terms: Vec<String>,
+    /// Require every term (true) or any term (false).
+    pub all_terms: bool,
+    /// Bitmask of [`IN_MESSAGE`] / [`IN_ADDED`] / [`IN_REMOVED`] to search in.
+    pub scope: u8,
+    /// Author name/email substring (case-insensitive).
+    pub author: Option<String>,
+    /// Inclusive timestamp bounds.
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+/// A commit matching a [`CommitQuery`], with the places its terms were found.
+#[derive(Clone, Debug)]
+pub struct CommitMatch<'a> {
+    pub commit: &'a IndexedCommit,
+    pub flags: u8,
+}
+
+/// Names for posting flags, in display order.
+pub fn flag_names(flags: u8) -> Vec<&'static str> {
+    [(IN_MESSAGE, "message"), (IN_ADDED, "added"), (IN_REMOVED, "removed")]
+        .iter()
+        .filter(|(f, _)| flags & f != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+// ─── Streaming parser ───────────────────────────────────────────────
+
+impl CommitIndex {
+    /// Empty index for `repo`; fill with [`ingest_log_stream`](Self::ingest_log_stream).
+    pub fn new(repo: String, include_patches: bool) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            repo,
+            head_hash: String::new(),
+            include_patches,
+            built_at: 0,
+            commits: Vec::new(),
+            tokens: HashMap::new(),
+        }
+    }
+
+    /// Parse `git log --reverse` output produced with [`log_format`] and append every
+    /// commit to the index. Returns the number of commits added.
+    ///
+    /// Per commit: the header line, the full message, the [`MESSAGE_END`] line, then
+    /// (with `-p`) the patch. Only lines inside `@@` hunks are tokenized, so file
+    /// headers (`--- a/x`, `+++ b/x`) are never mistaken for content.
+    pub fn ingest_log_stream(&mut self, reader: impl BufRead) -> Result<usize, String> {
+        let mut added = 0usize;
+        let mut current: Option<HashMap<String, u8>> = None;
+        let mut in_message = false;
+        let mut in_hunk = false;
+
+        for line_result in super::escaped_lines(reader) {
+            let line = line_result.map_err(|e| format!("IO error reading git log: {}", e))?;
+
+            if in_message {
+                if line == MESSAGE_END {
+                    in_message = false;
+                } else if let Some(tokens) = current.as_mut() {
+                    add_tokens(tokens, &line, IN_MESSAGE);
+                }
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix(COMMIT_PREFIX) {
+                if let Some(tokens) = current.take() {
+                    self.push_postings(tokens);
+                }
+                in_hunk = false;
+                in_message = true;
+                // COMMIT:<hash>␞<timestamp>␞<email>␞<name>␞<subject...>
+                let fields: Vec<&str> = header.split(FIELD_SEP).collect();
+                if fields.len() < 5 {
+                    eprintln!(
+                        "[git-grep] Warning: malformed commit line ({} fields, expected >=5): {}",
+                        fields.len(),
+                        &line[..line.len().min(100)]
+                    );
+                    continue;
+                }
+                let Ok(timestamp) = fields[1].trim().parse::<i64>() else {
+                    eprintln!("[git-grep] Warning: skipping commit with bad timestamp '{}'", fields[1]);
+                    continue;
+                };
+                self.commits.push(IndexedCommit {
+                    hash: fields[0].trim().to_string(),
+                    timestamp,
+                    author_email: fields[2].trim().to_string(),
+                    author_name: fields[3].trim().to_string(),
+                    subject: fields[4..].join(FIELD_SEP).trim().to_string(),
+                });
+                current = Some(HashMap::new());
+                added += 1;
+                continue;
+            }
+
+            let Some(tokens) = current.as_mut() else { continue };
+            if line.starts_with("diff ") {
+                in_hunk = false;
+            } else if line.starts_with("@@") {
+                in_hunk = true;
+            } else if in_hunk && line.len() <= MAX_PATCH_LINE_LEN {
+                if let Some(text) = line.strip_prefix('+') {
+                    add_tokens(tokens, text, IN_ADDED);
+                } else if let Some(text) = line.strip_prefix('-') {
+                    add_tokens(tokens, text, IN_REMOVED);
+                }
+            }
+        }
+        if let Some(tokens) = current.take() {
+            self.push_postings(tokens);
+        }
+        Ok(added)
+    }
+
+    /// Record postings for the most recently pushed commit.
+    fn push_postings(&mut self, tokens: HashMap<String, u8>) {
+        let commit_id = (self.commits.len() - 1) as u32;
+        for (token, flags) in tokens {
+            self.tokens.entry(token).or_default().push(CommitPosting { commit_id, flags });
+        }
+    }
+}
+
+fn add_tokens(tokens: &mut HashMap<String, u8>, text: &str, flag: u8) {
+    for token in crate::tokenize(text, crate::DEFAULT_MIN_TOKEN_LEN) {
+        *tokens.entry(token).or_default() |= flag;
+    }
+}
+
+/// `--format` value understood by [`CommitIndex::ingest_log_stream`].
+pub fn log_format() -> String {
+    format!("--format={}%H{}%at{}%aE{}%aN{}%s%n%B%n{}",
+        COMMIT_PREFIX, FIELD_SEP, FIELD_SEP, FIELD_SEP, FIELD_SEP, MESSAGE_END)
+}
+
+// ─── Build / update ─────────────────────────────────────────────────
+
+impl CommitIndex {
+    /// Index every commit reachable from HEAD, stamped with `clock`'s time.
+    pub fn build(repo_path: &Path, include_patches: bool, clock: &dyn Clock) -> Result<Self, String> {
+        let head_hash = super::resolve_commit(&repo_path.to_string_lossy(), "HEAD")?;
+        let repo = std::fs::canonicalize(repo_path)
+            .map(|p| crate::clean_path(&p.to_string_lossy()))
+            .unwrap_or_else(|_| repo_path.to_string_lossy().to_string());
+        let mut index = Self::new(repo, include_patches);
+        index.ingest_range(repo_path, &head_hash)?;
+        index.head_hash = head_hash;
+        index.built_at = clock.now_secs();
+        eprintln!(
+            "[git-grep] Built commit index: {} commits, {} tokens, patches={}",
+            index.commits.len(),
+            index.tokens.len(),
+            include_patches
+        );
+        Ok(index)
+    }
+
+    /// Append commits between the indexed HEAD and `new_head`.
+    /// Caller must check that the indexed HEAD is an ancestor of `new_head`
+    /// ([`GitHistoryCache::is_ancestor`](super::cache::GitHistoryCache::is_ancestor)).
+    pub fn update(&mut self, repo_path: &Path, new_head: &str, clock: &dyn Clock) -> Result<usize, String> {
+        let added = self.ingest_range(repo_path, &format!("{}..{}", self.head_hash, new_head))?;
+        self.head_hash = new_head.to_string();
+        self.built_at = clock.now_secs();
+        Ok(added)
+    }
+
+    fn ingest_range(&mut self, repo_path: &Path, range: &str) -> Result<usize, String> {
+        let mut cmd = Command::new("git");
+        cmd.args(["-c", "core.quotePath=false", "log", "--reverse", "--no-color", &log_format()]);
+        if self.include_patches {
+            cmd.args(["-p", "--unified=0", "--no-ext-diff", "--no-textconv", "--no-renames"]);
+        }
+        let mut child = cmd
+            .arg(range)
+            .current_dir(repo_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to spawn git log: {}", e))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| "Failed to capture git log stdout".to_string())?;
+        let added = self.ingest_log_stream(std::io::BufReader::new(stdout))?;
+        let status = child
+            .wait()
+            .map_err(|e| format!("Failed to wait for git log: {}", e))?;
+        if !status.success() {
+            return Err(format!("git log exited with status: {}", status));
+        }
+        Ok(added)
+    }
+
+    /// Whether this index can answer queries for `repo` at `head_hash`.
+    /// A message-only index cannot serve a request that needs patches.
+    pub fn is_valid_for(&self, head_hash: &str, include_patches: bool) -> bool {
+        self.head_hash == head_hash && (self.include_patches || !include_patches)
+    }
+}
+
+// ─── Query ──────────────────────────────────────────────────────────
+
+impl CommitIndex {
+    /// Commits matching `query`, oldest first. Every term must tokenize to at least one
+    /// token; a term matches a commit when all its tokens appear within `query.scope`.
+    pub fn search(&self, query: &CommitQuery) -> Result<Vec<CommitMatch<'_>>, String> {
+        let author = query.author.as_ref().map(|a| a.to_lowercase());
+        let mut combined: Option<HashMap<u32, u8>> = None;
+        for term in &query.terms {
+            let tokens = crate::tokenize(term, 1);
+            if tokens.is_empty() {
+                return Err(format!("Term '{}' contains no searchable tokens", term));
+            }
+            let term_hits = self.term_commits(&tokens, query.scope);
+            combined = Some(match combined {
+                None => term_hits,
+                Some(mut acc) if query.all_terms => {
+                    acc.retain(|id, _| term_hits.contains_key(id));
+                    for (id, flags) in acc.iter_mut() {
+                        *flags |= term_hits[id];
+                    }
+                    acc
+                }
+                Some(mut acc) => {
+                    for (id, flags) in term_hits {
+                        *acc.entry(id).or_default() |= flags;
+                    }
+                    acc
+                }
+            });
+        }
+
+        let mut matches: Vec<(u32, u8)> = combined.unwrap_or_default().into_iter().collect();
+        matches.sort_unstable_by_key(|&(id, _)| id);
+        Ok(matches.into_iter()
+            .filter_map(|(id, flags)| {
+                let commit = self.commits.get(id as usize)?;
+                let keep = query.from.is_none_or(|f| commit.timestamp >= f)
+                    && query.to.is_none_or(|t| commit.timestamp <= t)
+                    && author.as_ref().is_none_or(|a| {
+                        commit.author_name.to_lowercase().contains(a.as_str())
+                            || commit.author_email.to_lowercase().contains(a.as_str())
+                    });
+                keep.then_some(CommitMatch { commit, flags })
+            })
+            .collect())
+    }
+
+    /// commit id → flags (within `scope`) for commits containing every token.
+    fn term_commits(&self, tokens: &[String], scope: u8) -> HashMap<u32, u8> {
+        let mut result: Option<HashMap<u32, u8>> = None;
+        let unique: HashSet<&String> = tokens.iter().collect();
+        for token in unique {
+            let hits: HashMap<u32, u8> = self.tokens.get(token)
+                .map(|postings| postings.iter()
+                    .filter(|p| p.flags & scope != 0)
+                    .map(|p| (p.commit_id, p.flags & scope))
+                    .collect())
+                .unwrap_or_default();
+            result = Some(match result {
+                None => hits,
+                Some(mut acc) => {
+                    acc.retain(|id, _| hits.contains_key(id));
+                    for (id, flags) in acc.iter_mut() {
+                        *flags &= hits[id];
+                    }
+                    acc
+                }
+            });
+        }
+        result.unwrap_or_default()
+    }
+}
+
+// ─── Persistence ────────────────────────────────────────────────────
+
+impl CommitIndex {
+    /// Save index to disk using bincode + LZ4 compression (atomic: temp file, then rename).
+    pub fn save_to_disk(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create index directory: {}", e))?;
+        }
+        crate::index::save_compressed(path, self, "git-grep")
+            .map_err(|e| format!("Failed to save commit index: {}", e))
+    }
+
+    /// Load index from disk. Returns Err on any error (corrupt file, wrong version) —
+    /// caller does full rebuild.
+    pub fn load_from_disk(path: &Path) -> Result<Self, String> {
+        let index: Self = crate::index::load_compressed(path, "git-grep")
+            .map_err(|e| format!("Failed to load commit index: {}", e))?;
+        if index.format_version != FORMAT_VERSION {
+            return Err(format!(
+                "Commit index format version mismatch: file has {}, expected {}",
+                index.format_version, FORMAT_VERSION
+            ));
+        }
+        Ok(index)
+    }
+
+    /// Index file path for a repository: `<semantic_prefix>_<hash>.git-grep`.
+    pub fn index_path_for(dir: &str, index_base: &Path) -> PathBuf {
+        let canonical = std::fs::canonicalize(dir)
+            .unwrap_or_else(|_| PathBuf::from(dir));
+        let hash = search::stable_hash(&[
+            canonical.to_string_lossy().as_bytes(),
+            b"git-grep",
+        ]);
+        let prefix = search::extract_semantic_prefix(&canonical);
+        index_base.join(format!("{}_{:08x}.git-grep", prefix, hash as u32))
+    }
+}
+
+/// How [`load_or_update`] obtained the index it returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexRefresh {
+    /// Already up to date (in memory or on disk).
+    Current,
+    /// Appended commits since the indexed HEAD.
+    Updated(usize),
+    /// Built from scratch (no index, history rewritten, or patches newly requested).
+    Built,
+}
+
+impl IndexRefresh {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexRefresh::Current => "current",
+            IndexRefresh::Updated(_) => "updated",
+            IndexRefresh::Built => "built",
+        }
+    }
+}
+
+/// Bring `slot` up to date with the repository's HEAD, reusing (in order) the in-memory
+/// index, the on-disk index, or an incremental update, and building from scratch only
+/// when none of those apply. Saves to disk whenever the index changed.
+pub fn load_or_update(
+    slot: &mut Option<CommitIndex>,
+    repo_path: &Path,
+    include_patches: bool,
+    index_base: &Path,
+    clock: &dyn Clock,
+) -> Result<IndexRefresh, String> {
+    let repo_str = repo_path.to_string_lossy();
+    let head = super::resolve_commit(&repo_str, "HEAD")?;
+    let canonical = std::fs::canonicalize(repo_path)
+        .map(|p| crate::clean_path(&p.to_string_lossy()))
+        .unwrap_or_else(|_| repo_str.to_string());
+    let path = CommitIndex::index_path_for(&repo_str, index_base);
+
+    if slot.as_ref().is_none_or(|idx| idx.repo != canonical) {
+        *slot = CommitIndex::load_from_disk(&path).ok().filter(|idx| idx.repo == canonical);
+    }
+    if let Some(idx) = slot.as_ref()
+        && idx.is_valid_for(&head, include_patches) {
+            return Ok(IndexRefresh::Current);
+        }
+
+    let refresh = match slot.as_mut() {
+        Some(idx) if (idx.include_patches || !include_patches)
+            && super::cache::GitHistoryCache::is_ancestor(repo_path, &idx.head_hash, &head) =>
+        {
+            IndexRefresh::Updated(idx.update(repo_path, &head, clock)?)
+        }
+        _ => {
+            *slot = Some(CommitIndex::build(repo_path, include_patches, clock)?);
+            IndexRefresh::Built
+        }
+    };
+    if let Some(idx) = slot.as_ref()
+        && let Err(e) = idx.save_to_disk(&path) {
+            eprintln!("[git-grep] Warning: {}", e);
+        }
+    Ok(refresh)
+}
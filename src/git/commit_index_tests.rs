@@ -0,0 +1,144 @@
+//! Unit tests for the commit content index.
+//!
+//! Parser and query tests use mock `git log -p` output; one integration test
+//! builds, updates, and persists an index for a temp git repository.
+
+use super::commit_index::*;
+use std::io::Cursor;
+
+/// Two commits in `git log --reverse -p --unified=0` shape.
+fn mock_log() -> &'static str {
+    concat!(
+        "COMMIT:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa␞1700000000␞alice@example.com␞Alice␞Add retry policy\n",
+        "Add retry policy\n",
+        "\n",
+        "Wraps HttpClient calls.\n",
+        "\n",
+        "␞END␞\n",
+        "\n",
+        "diff --git a/src/client.rs b/src/client.rs\n",
+        "--- a/src/client.rs\n",
+        "+++ b/src/client.rs\n",
+        "@@ -0,0 +1,2 @@\n",
+        "+const MAX_RETRIES: u32 = 3;\n",
+        "+fn send_with_retry() {}\n",
+        "COMMIT:bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb␞1700001000␞bob@example.com␞Bob␞Drop retries\n",
+        "Drop retries\n",
+        "␞END␞\n",
+        "diff --git a/src/client.rs b/src/client.rs\n",
+        "--- a/src/client.rs\n",
+        "+++ b/src/client.rs\n",
+        "@@ -1 +0,0 @@\n",
+        "-const MAX_RETRIES: u32 = 3;\n",
+    )
+}
+
+fn parse_mock() -> CommitIndex {
+    let mut index = CommitIndex::new("/repo".to_string(), true);
+    let added = index.ingest_log_stream(Cursor::new(mock_log().as_bytes())).unwrap();
+    assert_eq!(added, 2);
+    index
+}
+
+fn query(terms: &[&str], scope: u8) -> CommitQuery {
+    CommitQuery {
+        terms: terms.iter().map(|t| t.to_string()).collect(),
+        scope,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_ingest_records_message_added_and_removed_tokens() {
+    let index = parse_mock();
+    assert_eq!(index.commits[0].subject, "Add retry policy");
+    assert_eq!(index.commits[1].author_name, "Bob");
+
+    let postings = &index.tokens["max_retries"];
+    assert_eq!(postings, &vec![
+        CommitPosting { commit_id: 0, flags: IN_ADDED },
+        CommitPosting { commit_id: 1, flags: IN_REMOVED },
+    ]);
+    assert_eq!(index.tokens["httpclient"], vec![CommitPosting { commit_id: 0, flags: IN_MESSAGE }]);
+    // File headers are outside hunks and must not be indexed as content
+    assert!(!index.tokens.contains_key("client"), "diff headers leaked into tokens");
+}
+
+#[test]
+fn test_search_scopes_terms_and_filters() {
+    let index = parse_mock();
+
+    let introduced = index.search(&query(&["MAX_RETRIES"], IN_ADDED)).unwrap();
+    assert_eq!(introduced.len(), 1);
+    assert_eq!(introduced[0].commit.hash, "a".repeat(40));
+
+    let anywhere = index.search(&query(&["MAX_RETRIES"], IN_MESSAGE | IN_ADDED | IN_REMOVED)).unwrap();
+    assert_eq!(anywhere.iter().map(|m| flag_names(m.flags)).collect::<Vec<_>>(),
+        vec![vec!["added"], vec!["removed"]]);
+
+    // Multi-token term: every token must be present in the commit
+    assert_eq!(index.search(&query(&["send_with_retry MAX_RETRIES"], IN_ADDED)).unwrap().len(), 1);
+    assert!(index.search(&query(&["retries drop nothing_here"], IN_MESSAGE)).unwrap().is_empty());
+
+    let mut both = query(&["retry", "retries"], IN_MESSAGE);
+    assert_eq!(index.search(&both).unwrap().len(), 2);
+    both.all_terms = true;
+    assert!(index.search(&both).unwrap().is_empty());
+
+    let mut by_bob = query(&["MAX_RETRIES"], IN_ADDED | IN_REMOVED);
+    by_bob.author = Some("BOB".to_string());
+    assert_eq!(index.search(&by_bob).unwrap()[0].commit.author_name, "Bob");
+    by_bob.author = None;
+    by_bob.to = Some(1700000500);
+    assert_eq!(index.search(&by_bob).unwrap().len(), 1);
+
+    assert!(index.search(&query(&["::"], IN_ADDED)).is_err());
+}
+
+#[test]
+fn test_load_or_update_builds_updates_and_persists() {
+    use std::process::Command;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = tmp.path().join("repo");
+    let index_base = tmp.path().join("idx");
+    std::fs::create_dir_all(&repo).unwrap();
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).current_dir(&repo)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null").env("GIT_CONFIG_SYSTEM", "/dev/null")
+            .output().expect("git");
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    };
+    let commit = |msg: &str| git(&["-c", "user.name=Ada", "-c", "user.email=ada@example.com", "commit", "-q", "-m", msg]);
+    git(&["init", "-q"]);
+    std::fs::write(repo.join("a.txt"), "legacyFlagName = 1\n").unwrap();
+    git(&["add", "."]);
+    commit("init");
+
+    let clock = search::clock::ManualClock::new(1_000);
+    let mut slot = None;
+    assert_eq!(load_or_update(&mut slot, &repo, true, &index_base, &clock).unwrap(), IndexRefresh::Built);
+    clock.advance(60);
+    assert_eq!(load_or_update(&mut slot, &repo, true, &index_base, &clock).unwrap(), IndexRefresh::Current);
+    assert_eq!(slot.as_ref().unwrap().built_at, 1_000, "a current index keeps its stamp");
+
+    std::fs::write(repo.join("a.txt"), "renamedFlag = 1\n").unwrap();
+    git(&["add", "."]);
+    commit("rename flag");
+    assert_eq!(load_or_update(&mut slot, &repo, true, &index_base, &clock).unwrap(), IndexRefresh::Updated(1));
+
+    let index = slot.as_ref().unwrap();
+    assert_eq!(index.built_at, 1_060);
+    let removed = index.search(&query(&["legacyFlagName"], IN_REMOVED)).unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].commit.subject, "rename flag");
+
+    // A fresh slot picks the saved index up from disk
+    let mut fresh = None;
+    assert_eq!(load_or_update(&mut fresh, &repo, true, &index_base, &clock).unwrap(), IndexRefresh::Current);
+    assert_eq!(fresh.unwrap().commits.len(), 2);
+    // A message-only index cannot serve patch queries
+    let mut messages_only = Some(CommitIndex::build(&repo, false, &clock).unwrap());
+    assert!(!messages_only.as_ref().unwrap().is_valid_for(&index.head_hash, true));
+    assert_eq!(load_or_update(&mut messages_only, &repo, true, &index_base, &clock).unwrap(), IndexRefresh::Built);
+}
@@ -470,3 +470,43 @@ fn test_file_exists_in_git_bad_repo() {
         "Bad repo should return false"
     );
 }
+
+// ─── parse_added_lines tests ────────────────────────────────────────
+
+#[test]
+fn test_parse_added_lines_numbers_and_filters() {
+    let diff = concat!(
+        "diff --git a/src/a.cs b/src/a.cs\n",
+        "index 1111111..2222222 100644\n",
+        "--- a/src/a.cs\n",
+        "+++ b/src/a.cs\n",
+        "@@ -3,0 +4,2 @@ class A\n",
+        "+    Foo();\n",
+        "+++counter;\n",
+        "@@ -10 +12 @@\n",
+        "-    Old();\n",
+        "+    Foo(Bar);\n",
+        "diff --git a/gone.cs b/gone.cs\n",
+        "deleted file mode 100644\n",
+        "--- a/gone.cs\n",
+        "+++ /dev/null\n",
+        "@@ -1 +0,0 @@\n",
+        "-Foo();\n",
+        "diff --git a/new.cs b/new.cs\n",
+        "new file mode 100644\n",
+        "--- /dev/null\n",
+        "+++ b/new.cs\n",
+        "@@ -0,0 +1 @@\n",
+        "+Baz();\n",
+        "\\ No newline at end of file\n",
+    );
+    let scan = parse_added_lines(std::io::Cursor::new(diff), |_, content| content.contains("Foo")).unwrap();
+    assert_eq!(scan.added_lines, 4);
+    assert_eq!(scan.files_changed, 2);
+    let found: Vec<(&str, usize, &str)> = scan.lines.iter().map(|l| (l.file.as_str(), l.line, l.content.as_str())).collect();
+    assert_eq!(found, vec![("src/a.cs", 4, "    Foo();"), ("src/a.cs", 12, "    Foo(Bar);")]);
+
+    // The added line "++counter;" is content, not a file header
+    let scan = parse_added_lines(std::io::Cursor::new(diff), |_, content| content.contains("counter")).unwrap();
+    assert_eq!((scan.lines[0].line, scan.lines[0].content.as_str()), (5, "++counter;"));
+}
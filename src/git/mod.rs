@@ -5,7 +5,8 @@
 //! See `cache.rs` for the pre-built in-memory cache path (sub-millisecond queries).
 
 use std::collections::HashMap;
-use std::process::Command;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
 
 // ─── Types ──────────────────────────────────────────────────────────
 
@@ -47,9 +48,32 @@ pub struct BlameLine {
     pub author_name: String,
     pub author_email: String,
     pub date: String,
+    /// Author time (Unix seconds, UTC)
+    pub timestamp: i64,
     pub content: String,
 }
 
+/// A line present in the newer revision but not the older one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddedLine {
+    /// Path relative to the repo path
+    pub file: String,
+    /// 1-based line number in the newer revision
+    pub line: usize,
+    pub content: String,
+}
+
+/// Result of scanning a diff for added lines.
+#[derive(Clone, Debug, Default)]
+pub struct AddedLinesScan {
+    /// Added lines that passed the filter, in diff order
+    pub lines: Vec<AddedLine>,
+    /// Files with at least one added line
+    pub files_changed: usize,
+    /// Added lines seen, matching or not
+    pub added_lines: usize,
+}
+
 // ─── Date helpers ───────────────────────────────────────────────────
 
 /// Validate a YYYY-MM-DD date string. Returns Ok(()) or Err with message.
@@ -179,8 +203,31 @@ fn run_git(cmd: &mut Command) -> Result<String, String> {
         return Err(format!("git command failed: {}", stderr.trim()));
     }
 
-    String::from_utf8(output.stdout)
-        .map_err(|e| format!("git output is not valid UTF-8: {}", e))
+    // Paths and author names are raw bytes; escape invalid UTF-8 instead of failing
+    Ok(crate::bytes_to_string_escaped(&output.stdout))
+}
+
+/// Lines of `reader` without the trailing `\n` / `\r\n`. Invalid UTF-8 (author names
+/// or paths in legacy encodings) is escaped with [`crate::bytes_to_string_escaped`]
+/// instead of failing the whole read, as `BufRead::lines` would.
+pub(crate) fn escaped_lines(mut reader: impl BufRead) -> impl Iterator<Item = std::io::Result<String>> {
+    let mut buf = Vec::new();
+    std::iter::from_fn(move || {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => None,
+            Ok(_) => {
+                if buf.last() == Some(&b'\n') {
+                    buf.pop();
+                    if buf.last() == Some(&b'\r') {
+                        buf.pop();
+                    }
+                }
+                Some(Ok(crate::bytes_to_string_escaped(&buf)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    })
 }
 
 /// Parse a git log record (using FIELD_SEP-separated fields) into CommitInfo.
@@ -239,7 +286,7 @@ pub fn file_history(
         cmd.arg(format!("--grep={}", message));
     }
 
-    cmd.arg("--").arg(file);
+    cmd.arg("--").arg(crate::path_from_string(file));
 
     let output = run_git(&mut cmd)?;
 
@@ -274,7 +321,7 @@ fn get_commit_diff(repo_path: &str, hash: &str, file: &str) -> Result<String, St
         .arg("diff")
         .arg(format!("{}^..{}", hash, hash))
         .arg("--")
-        .arg(file);
+        .arg(crate::path_from_string(file));
 
     let output = run_git(&mut cmd)?;
 
@@ -323,7 +370,7 @@ pub fn top_authors(
     }
 
     if !path.is_empty() {
-        cmd.arg("--").arg(path);
+        cmd.arg("--").arg(crate::path_from_string(path));
     }
 
     let output = run_git(&mut cmd)?;
@@ -397,7 +444,7 @@ pub fn repo_activity(
 
     let mut cmd = Command::new("git");
     cmd.current_dir(repo_path)
-        .arg("log")
+        .args(["-c", "core.quotePath=false", "log"]) // raw UTF-8 paths
         .arg(format!("--format={}", format))
         .arg("--name-only");
 
@@ -459,7 +506,7 @@ pub fn file_exists_in_git(repo: &str, file: &str) -> bool {
     cmd.current_dir(repo)
         .arg("ls-files")
         .arg("--")
-        .arg(file);
+        .arg(crate::path_from_string(file));
 
     match run_git(&mut cmd) {
         Ok(output) => !output.trim().is_empty(),
@@ -483,11 +530,11 @@ pub fn blame_lines(
 
     let mut cmd = Command::new("git");
     cmd.current_dir(repo_path)
-        .arg("blame")
+        .args(["-c", "core.quotePath=false", "blame"])
         .arg(format!("-L{},{}", start_line, end))
         .arg("--porcelain")
         .arg("--")
-        .arg(file);
+        .arg(crate::path_from_string(file));
 
     let output = run_git(&mut cmd)?;
     parse_blame_porcelain(&output)
@@ -611,6 +658,7 @@ pub(crate) fn parse_blame_porcelain(output: &str) -> Result<Vec<BlameLine>, Stri
             author_name,
             author_email,
             date,
+            timestamp: author_time,
             content,
         });
     }
@@ -658,7 +706,189 @@ pub(crate) fn format_blame_date(timestamp: i64, tz: &str) -> String {
     format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}", y, m, d, hours, minutes, seconds, tz)
 }
 
+// ─── Revisions ──────────────────────────────────────────────────────
+
+/// Resolve a revision (branch, tag, `HEAD~3`, short hash) to a full commit hash.
+pub fn resolve_commit(repo_path: &str, rev: &str) -> Result<String, String> {
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(format!("Invalid revision '{}'", rev));
+    }
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_path)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", rev));
+    match run_git(&mut cmd) {
+        Ok(out) if !out.trim().is_empty() => Ok(out.trim().to_string()),
+        _ => Err(format!("Unknown revision '{}' in {}", rev, repo_path)),
+    }
+}
+
+/// Files that differ between two commits, relative to `repo_path` and limited to it
+/// (`git diff --name-only --relative`). Renames are reported as delete + add, so both
+/// the old and the new path appear.
+pub fn changed_files_between(repo_path: &str, old: &str, new: &str) -> Result<Vec<String>, String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_path)
+        .args(["-c", "core.quotePath=false", "diff", "--name-only", "-z", "--no-renames", "--relative", old, new]);
+    let out = run_git(&mut cmd)?;
+    Ok(out.split('\0').filter(|p| !p.is_empty()).map(|p| p.to_string()).collect())
+}
+
+/// Added lines between two commits that pass `keep(file, content)`, from one
+/// `git diff --unified=0` streamed through [`parse_added_lines`]. Paths are relative to
+/// `repo_path` and limited to it; `pathspecs` narrow the diff further. Renames are
+/// detected, so a moved file only reports the lines that changed.
+pub fn added_lines_between(
+    repo_path: &str,
+    old: &str,
+    new: &str,
+    pathspecs: &[String],
+    keep: impl FnMut(&str, &str) -> bool,
+) -> Result<AddedLinesScan, String> {
+    let mut child = Command::new("git")
+        .current_dir(repo_path)
+        .args(["-c", "core.quotePath=false", "diff", "--unified=0", "--no-color", "--no-ext-diff",
+            "--find-renames", "--relative", "--src-prefix=a/", "--dst-prefix=b/", old, new, "--"])
+        .args(pathspecs)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute git: {}. Is git installed and in PATH?", e))?;
+    let stdout = child.stdout.take().ok_or("git diff: no stdout")?;
+    let scan = parse_added_lines(BufReader::new(stdout), keep);
+    let output = child.wait_with_output().map_err(|e| format!("git diff: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git command failed: {}", stderr.trim()));
+    }
+    scan
+}
+
+/// Parse `git diff` output and collect the added lines that pass `keep(file, content)`,
+/// numbered from the `+start` of each hunk header. Lines of deleted files are never
+/// added; lines before the first hunk (file headers) are never content.
+pub(crate) fn parse_added_lines(reader: impl BufRead, mut keep: impl FnMut(&str, &str) -> bool) -> Result<AddedLinesScan, String> {
+    let mut scan = AddedLinesScan::default();
+    let mut file: Option<String> = None;
+    let mut file_counted = false;
+    let mut in_hunk = false;
+    let mut next_line = 0usize;
+
+    for line_result in escaped_lines(reader) {
+        let line = line_result.map_err(|e| format!("IO error reading git diff: {}", e))?;
+        if line.starts_with("diff ") {
+            file = None;
+            file_counted = false;
+            in_hunk = false;
+        } else if !in_hunk && let Some(path) = line.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").map(|p| p.to_string());
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            // "@@ -12,3 +14,5 @@ context": the new side starts at 14
+            next_line = header.split(' ')
+                .find_map(|part| part.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(0);
+            in_hunk = true;
+        } else if in_hunk && let Some(content) = line.strip_prefix('+') {
+            let Some(path) = file.as_deref() else { continue };
+            scan.added_lines += 1;
+            if !file_counted {
+                scan.files_changed += 1;
+                file_counted = true;
+            }
+            if keep(path, content) {
+                scan.lines.push(AddedLine { file: path.to_string(), line: next_line, content: content.to_string() });
+            }
+            next_line += 1;
+        } else if in_hunk && line.starts_with(' ') {
+            next_line += 1;
+        }
+    }
+    Ok(scan)
+}
+
+/// Read every blob of `commit` under `repo_path` whose path passes `keep`.
+///
+/// Paths are relative to `repo_path` (which may be a subdirectory of the repository).
+/// Uses one `git ls-tree` and one `git cat-file --batch` process regardless of file
+/// count. Contents are decoded as lossy UTF-8 with any BOM stripped, like the
+/// working-tree indexer does.
+pub fn read_tree_blobs(
+    repo_path: &str,
+    commit: &str,
+    keep: impl Fn(&str) -> bool,
+) -> Result<Vec<(String, String)>, String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_path).args(["ls-tree", "-r", "-z", commit]);
+    let listing = run_git(&mut cmd)?;
+    // Each record: "<mode> <type> <hash>\t<path>"
+    let entries: Vec<(&str, &str)> = listing.split('\0')
+        .filter_map(|record| {
+            let (meta, path) = record.split_once('\t')?;
+            let mut parts = meta.split(' ');
+            let (_mode, kind, hash) = (parts.next()?, parts.next()?, parts.next()?);
+            (kind == "blob" && keep(path)).then_some((path, hash))
+        })
+        .collect();
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut child = Command::new("git")
+        .current_dir(repo_path)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to execute git: {}. Is git installed and in PATH?", e))?;
+    let mut stdin = child.stdin.take().ok_or("git cat-file: no stdin")?;
+    let request: String = entries.iter().map(|(_, hash)| format!("{}\n", hash)).collect();
+    // Write from another thread: git starts answering before it has read every request
+    let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+    let mut reader = BufReader::new(child.stdout.take().ok_or("git cat-file: no stdout")?);
+
+    let mut blobs = Vec::with_capacity(entries.len());
+    for (path, _) in &entries {
+        // "<hash> blob <size>" or "<hash> missing"
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| format!("git cat-file: {}", e))?;
+        let mut fields = header.split_whitespace().skip(1);
+        let size = match (fields.next(), fields.next().and_then(|n| n.parse::<usize>().ok())) {
+            (Some("blob"), Some(size)) => size,
+            _ => continue,
+        };
+        // Content is followed by a single LF
+        let mut buf = vec![0u8; size + 1];
+        reader.read_exact(&mut buf).map_err(|e| format!("git cat-file: {}", e))?;
+        buf.pop();
+        let text = String::from_utf8_lossy(&buf);
+        blobs.push((path.to_string(), text.strip_prefix('\u{feff}').unwrap_or(&text).to_string()));
+    }
+    let _ = writer.join();
+    let _ = child.wait();
+    Ok(blobs)
+}
+
+/// Contents of `path` (relative to `repo_path`) at `commit`, as lossy UTF-8.
+pub fn show_file_at(repo_path: &str, commit: &str, path: &str) -> Result<String, String> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .arg("show")
+        .arg(format!("{}:./{}", commit, path))
+        .output()
+        .map_err(|e| format!("Failed to execute git: {}. Is git installed and in PATH?", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git command failed: {}", stderr.trim()));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    Ok(text.strip_prefix('\u{feff}').unwrap_or(&text).to_string())
+}
+
 pub mod cache;
+pub mod commit_index;
 
 // ─── Tests ──────────────────────────────────────────────────────────
 
@@ -668,4 +898,8 @@ mod tests;
 
 #[cfg(test)]
 #[path = "cache_tests.rs"]
-mod cache_tests;
\ No newline at end of file
+mod cache_tests;
+
+#[cfg(test)]
+#[path = "commit_index_tests.rs"]
+mod commit_index_tests;
\ No newline at end of file
@@ -3,14 +3,22 @@
 use std::collections::HashMap;
 use std::fs;
 use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicUsize};
 use std::sync::Mutex;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use ignore::WalkBuilder;
 
+use crate::config::RepoConfig;
 use crate::error::SearchError;
-use search::{clean_path, extract_semantic_prefix, generate_trigrams, read_file_lossy, stable_hash, tokenize, ContentIndex, FileEntry, FileIndex, Posting, TrigramIndex};
+use crate::priority::{apply_to_current_thread, resolve_threads, BuildPriority, IoThrottle};
+use crate::progress::Progress;
+use crate::shards;
+use crate::sqlite::{self, Storage};
+use search::{clean_path, path_from_string, path_to_string, extract_comment_markers, extract_semantic_prefix, extract_ticket_refs, stable_hash, CommentMarker, ContentIndex, FileEntry, FileIndex, Posting, SkippedFiles, TicketRef, Token, TokenizerSettings, WalkLimits, DEFAULT_MIN_TOKEN_LEN, DEFAULT_STOP_TOKEN_PCT};
+
+use search::builder::{FileRead, IndexBuilder};
 
 use crate::{ContentIndexArgs, IndexArgs};
 
@@ -205,12 +213,15 @@ pub fn get_process_memory_info() -> serde_json::Value {
 
 /// Force mimalloc to collect and decommit all freed segments.
 /// This prevents abandoned thread heaps from inflating Working Set
-/// after the build+drop+reload pattern.
+/// after the build+drop+reload pattern. A no-op without the `mimalloc` feature.
 pub fn force_mimalloc_collect() {
-    unsafe extern "C" {
-        fn mi_collect(force: bool);
+    #[cfg(feature = "mimalloc")]
+    {
+        unsafe extern "C" {
+            fn mi_collect(force: bool);
+        }
+        unsafe { mi_collect(true); }
     }
-    unsafe { mi_collect(true); }
 }
 
 /// Estimate the in-memory size of a ContentIndex.
@@ -247,21 +258,15 @@ pub fn estimate_content_index_memory(idx: &ContentIndex) -> serde_json::Value {
     let full_total_postings: usize = idx.index.values().map(|v| v.len()).sum();
 
     // Inverted index estimate:
-    // Each HashMap entry: ~80 bytes overhead + key String (24 + len) + Vec<Posting> (24 + postings)
+    // Each HashMap entry: ~80 bytes overhead + key Arc<str> (16 + 16 counts + len) + Vec<Posting> (24 + postings)
     // Each Posting: 4 (file_id) + 24 (Vec header) + lines * 4 = 28 + avg_lines * 4
-    let per_entry = 80.0 + 24.0 + avg_key_len + 24.0;
+    let per_entry = 80.0 + 32.0 + avg_key_len + 24.0;
     let per_posting = 28.0 + avg_lines * 4.0;
     let inverted_mb = (idx.index.len() as f64 * per_entry + full_total_postings as f64 * per_posting) / 1_048_576.0;
 
-    // Trigram tokens estimate
-    let tri_sample_size = idx.trigram.tokens.len().min(1000);
-    let avg_token_len = if tri_sample_size > 0 {
-        let total: usize = idx.trigram.tokens.iter().take(tri_sample_size).map(|t| t.len()).sum();
-        total as f64 / tri_sample_size as f64
-    } else {
-        8.0
-    };
-    let trigram_tokens_mb = idx.trigram.tokens.len() as f64 * (24.0 + avg_token_len) / 1_048_576.0;
+    // Trigram tokens estimate: each token shares the inverted index key's string,
+    // so only the 16-byte pointer is counted
+    let trigram_tokens_mb = idx.trigram.tokens.len() as f64 * 16.0 / 1_048_576.0;
 
     // Trigram map estimate
     let total_tri_postings: usize = idx.trigram.trigram_map.values().map(|v| v.len()).sum();
@@ -376,22 +381,41 @@ pub(crate) fn recover_mutex<T>(mutex: std::sync::Mutex<T>, label: &str) -> T {
 
 // ─── LZ4 compression helpers ────────────────────────────────────────
 
-/// Magic bytes identifying LZ4-compressed index files.
-pub const LZ4_MAGIC: &[u8; 4] = b"LZ4S";
+// The file format itself lives in `search::storage`; this adds locking, atomic
+// replacement and logging.
+
+pub use search::storage::{Payload, INDEX_FORMAT_VERSION, INDEX_HEADER_LEN, INDEX_MAGIC, LZ4_MAGIC};
 
-/// Save a serializable value to a file with LZ4 frame compression.
-/// Writes magic bytes, then LZ4-compressed bincode data.
+/// Distinguishes temp files of concurrent saves within one process.
+static TEMP_FILE_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Save a serializable value to a file with LZ4 frame compression, atomically.
+/// Writes a header and the compressed bincode data to a temp file next to `path`,
+/// fsyncs it, then renames it over `path`: a crash mid-save leaves the previous file
+/// intact instead of a truncated one.
 /// Logs compression ratio and timing to stderr.
 pub fn save_compressed<T: serde::Serialize>(path: &std::path::Path, data: &T, label: &str) -> Result<(), SearchError> {
+    save_compressed_versioned(path, data, label, INDEX_FORMAT_VERSION)
+}
+
+/// [`save_compressed`] with `version` in the header instead of [`INDEX_FORMAT_VERSION`].
+/// Holds the write lock on `path` (see [`crate::lock`]) while it writes.
+pub fn save_compressed_versioned<T: serde::Serialize>(path: &std::path::Path, data: &T, label: &str, version: u32) -> Result<(), SearchError> {
     let start = Instant::now();
+    let _lock = crate::lock::IndexLock::acquire(path)?;
 
-    let file = std::fs::File::create(path)?;
-    let mut writer = BufWriter::new(file);
-    writer.write_all(LZ4_MAGIC)?;
-    let mut encoder = lz4_flex::frame::FrameEncoder::new(writer);
-    bincode::serialize_into(&mut encoder, data)?;
-    let mut writer = encoder.finish().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    writer.flush()?;
+    let n = TEMP_FILE_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let tmp_path = PathBuf::from(format!("{}.{}-{}.tmp", path.display(), std::process::id(), n));
+    let result = search::storage::write_index_file(&tmp_path, data, version).and_then(|()| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result?;
+    // Persist the rename itself (directories can't be opened for syncing on Windows)
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = fs::File::open(parent) {
+        let _ = dir.sync_all();
+    }
 
     let compressed_size = std::fs::metadata(path)?.len();
     let elapsed = start.elapsed();
@@ -405,57 +429,28 @@ pub fn save_compressed<T: serde::Serialize>(path: &std::path::Path, data: &T, la
     Ok(())
 }
 
-/// Load a deserializable value from a file, supporting both LZ4-compressed
-/// and legacy uncompressed formats (backward compatibility).
-/// Returns `Err(SearchError::IndexLoad)` with a descriptive message on failure.
+/// Load a deserializable value from a file, supporting the current format and the
+/// legacy headerless LZ4 and uncompressed formats (backward compatibility).
+/// Returns `Err(SearchError::IndexLoad)` with a descriptive message on failure: a
+/// truncated or corrupt file, or one from a newer format version, is reported as
+/// such, and callers rebuild the index.
 pub fn load_compressed<T: serde::de::DeserializeOwned>(path: &std::path::Path, label: &str) -> Result<T, SearchError> {
-    let path_str = path.display().to_string();
-    let start = Instant::now();
-    let compressed_size = std::fs::metadata(path)
-        .map_err(|e| SearchError::IndexLoad {
-            path: path_str.clone(),
-            message: format!("file not found or inaccessible: {}", e),
-        })?
-        .len();
-
-    let file = std::fs::File::open(path).map_err(|e| SearchError::IndexLoad {
-        path: path_str.clone(),
-        message: format!("cannot open file: {}", e),
-    })?;
-    let mut reader = BufReader::new(file);
+    load_compressed_versioned(path, label, INDEX_FORMAT_VERSION, |_, payload| payload.deserialize())
+}
 
-    let mut magic = [0u8; 4];
-    reader.read_exact(&mut magic).map_err(|e| SearchError::IndexLoad {
-        path: path_str.clone(),
-        message: format!("read error (magic bytes): {}", e),
-    })?;
-
-    let result = if &magic == LZ4_MAGIC {
-        // Compressed format
-        let decoder = lz4_flex::frame::FrameDecoder::new(reader);
-        bincode::deserialize_from(decoder).map_err(|e| SearchError::IndexLoad {
-            path: path_str.clone(),
-            message: format!("LZ4 deserialization failed: {}", e),
-        })?
-    } else {
-        // Legacy uncompressed format
-        reader.seek(SeekFrom::Start(0)).map_err(|e| SearchError::IndexLoad {
-            path: path_str.clone(),
-            message: format!("seek error: {}", e),
-        })?;
-        let data = {
-            let mut buf = Vec::new();
-            reader.read_to_end(&mut buf).map_err(|e| SearchError::IndexLoad {
-                path: path_str.clone(),
-                message: format!("read error: {}", e),
-            })?;
-            buf
-        };
-        bincode::deserialize(&data).map_err(|e| SearchError::IndexLoad {
-            path: path_str.clone(),
-            message: format!("deserialization failed: {}", e),
-        })?
-    };
+/// [`load_compressed`] for index types that changed their payload layout: files up to
+/// `max_version` are accepted and `decode` is given the file's version to pick the
+/// layout. Headerless legacy files have version 0.
+pub fn load_compressed_versioned<T>(
+    path: &std::path::Path,
+    label: &str,
+    max_version: u32,
+    decode: impl FnOnce(u32, Payload) -> bincode::Result<T>,
+) -> Result<T, SearchError> {
+    let start = Instant::now();
+    let result = search::storage::read_index_file(path, max_version, decode)
+        .map_err(|e| SearchError::IndexLoad { path: path.display().to_string(), message: e.to_string() })?;
+    let compressed_size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
 
     let elapsed = start.elapsed();
     eprintln!("[{}] Loaded {:.1} MB in {:.3}s",
@@ -483,71 +478,243 @@ pub fn index_path_for(dir: &str, index_base: &std::path::Path) -> PathBuf {
 }
 
 pub fn save_index(index: &FileIndex, index_base: &std::path::Path) -> Result<(), SearchError> {
+    save_index_as(index, index_base, None)
+}
+
+/// Save a file index in `storage`, removing its copy in the other format. `None` keeps
+/// the format it was saved in before.
+pub fn save_index_as(index: &FileIndex, index_base: &std::path::Path, storage: Option<Storage>) -> Result<(), SearchError> {
     fs::create_dir_all(index_base)?;
     let path = index_path_for(&index.root, index_base);
-    save_compressed(&path, index, "file-index")
+    let database = sqlite::database_path(&path);
+    if storage.unwrap_or_else(|| sqlite::storage_of(&path)) == Storage::Sqlite {
+        let _lock = crate::lock::IndexLock::acquire(&path)?;
+        sqlite::save_file_index(&database, index)?;
+        let _ = fs::remove_file(&path);
+        return Ok(());
+    }
+    save_compressed(&path, index, "file-index")?;
+    sqlite::remove_database(&database);
+    Ok(())
 }
 
 pub fn load_index(dir: &str, index_base: &std::path::Path) -> Result<FileIndex, SearchError> {
     let path = index_path_for(dir, index_base);
+    if sqlite::storage_of(&path) == Storage::Sqlite {
+        return sqlite::load_file_index(&sqlite::database_path(&path));
+    }
     load_compressed(&path, "file-index")
 }
 
+/// Canonical form of an extension list, used for index identity: trimmed, lowercased,
+/// sorted and deduplicated, so `cs,SQL` and `sql, cs` name the same index file.
+pub fn normalize_exts(exts: &str) -> String {
+    let mut list: Vec<String> = exts.split(',')
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect();
+    list.sort();
+    list.dedup();
+    list.join(",")
+}
+
 pub fn content_index_path_for(dir: &str, exts: &str, index_base: &std::path::Path) -> PathBuf {
     let canonical = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
-    let hash = stable_hash(&[canonical.to_string_lossy().as_bytes(), exts.as_bytes()]);
+    let hash = stable_hash(&[canonical.to_string_lossy().as_bytes(), normalize_exts(exts).as_bytes()]);
     let prefix = extract_semantic_prefix(&canonical);
     index_base.join(format!("{}_{:08x}.word-search", prefix, hash as u32))
 }
 
+/// Cache file for a revision index. Commit hashes make these immutable; the separate
+/// `.rev-search` extension keeps them out of [`find_content_index_for_dir`].
+pub fn rev_content_index_path_for(dir: &str, exts: &str, commit: &str, index_base: &std::path::Path) -> PathBuf {
+    let canonical = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
+    let hash = stable_hash(&[canonical.to_string_lossy().as_bytes(), normalize_exts(exts).as_bytes()]);
+    let prefix = extract_semantic_prefix(&canonical);
+    index_base.join(format!("{}_{:08x}_{}.rev-search", prefix, hash as u32, &commit[..12.min(commit.len())]))
+}
+
+/// Save a content index in the format it was saved in before. An index saved as shards
+/// (see [`crate::shards`]) is split again, and only its changed shards are rewritten;
+/// one saved in SQLite (see [`crate::sqlite`]) has only its changed files rewritten.
 pub fn save_content_index(index: &ContentIndex, index_base: &std::path::Path) -> Result<(), SearchError> {
+    save_content_index_as(index, index_base, None)
+}
+
+/// Save a content index in `storage`, removing its copies in the other formats. `None`
+/// keeps the format it was saved in before, as [`save_content_index`] does.
+pub fn save_content_index_as(index: &ContentIndex, index_base: &std::path::Path, storage: Option<Storage>) -> Result<(), SearchError> {
     fs::create_dir_all(index_base)?;
     let exts_str = index.extensions.join(",");
     let path = content_index_path_for(&index.root, &exts_str, index_base);
+    let database = sqlite::database_path(&path);
+    if storage.unwrap_or_else(|| sqlite::storage_of(&path)) == Storage::Sqlite {
+        let _lock = crate::lock::IndexLock::acquire(&path)?;
+        sqlite::save_content_index(&database, index)?;
+        let _ = fs::remove_file(&path);
+        shards::remove_content_shards(&index.root, &exts_str, index_base);
+        return Ok(());
+    }
+    sqlite::remove_database(&database);
+    if let Some(count) = shards::shard_count_for(&index.root, &exts_str, index_base) {
+        let written = shards::save_content_shards(&shards::split_content_index(index, count), index_base)?;
+        eprintln!("[content-shards] Rewrote {} of {} shards", written, count);
+        return Ok(());
+    }
     save_compressed(&path, index, "content-index")
 }
 
+/// Load the content index built for `dir` and `exts`. Without one, fall back to the
+/// broadest index of `dir` that covers every extension in `exts`: queries filter by
+/// extension anyway, so a `cs,sql` index answers a `cs` query without a second build.
 pub fn load_content_index(dir: &str, exts: &str, index_base: &std::path::Path) -> Result<ContentIndex, SearchError> {
     let path = content_index_path_for(dir, exts, index_base);
-    load_compressed(&path, "content-index")
+    let manifest = shards::manifest_path_for(dir, exts, index_base);
+    let loaded = match sqlite::storage_of(&path) {
+        Storage::Sqlite => sqlite::load_content_index(&sqlite::database_path(&path)),
+        Storage::Bincode => load_content_index_file(&path, "content-index"),
+    };
+    loaded
+        .or_else(|e| if manifest.exists() { shards::load_content_shards(&manifest) } else { Err(e) })
+        .or_else(|e| find_content_index_covering(dir, exts, index_base).ok_or(e))
+}
+
+/// The SQLite database (see [`crate::sqlite`]) of the content index for `dir` and
+/// `exts`, or without any index for them, the broadest database of `dir` covering
+/// `exts`. `None` when the index is stored in another format. A query that needs only
+/// some tokens loads them from it with [`sqlite::load_content_tokens`].
+pub fn content_database_for(dir: &str, exts: &str, index_base: &std::path::Path) -> Option<PathBuf> {
+    let path = content_index_path_for(dir, exts, index_base);
+    match sqlite::storage_of(&path) {
+        Storage::Sqlite => Some(sqlite::database_path(&path)),
+        Storage::Bincode if !path.exists() && !shards::manifest_path_for(dir, exts, index_base).exists() => {
+            find_content_database_covering(dir, exts, index_base)
+        }
+        Storage::Bincode => None,
+    }
 }
 
-/// Try to find any content index (.word-search) file matching the given directory
+/// Load a content index file, with its token strings shared between the inverted index
+/// and the trigram table (see [`ContentIndex::share_token_strings`]).
+pub fn load_content_index_file(path: &Path, label: &str) -> Result<ContentIndex, SearchError> {
+    let mut index: ContentIndex = load_compressed(path, label)?;
+    index.share_token_strings();
+    Ok(index)
+}
+
+/// Find the broadest content index (.word-search) for the given directory, whatever
+/// its extensions.
 pub fn find_content_index_for_dir(dir: &str, index_base: &std::path::Path) -> Option<ContentIndex> {
+    find_content_index_covering(dir, "", index_base)
+}
+
+/// Find the content index for `dir` whose extensions include all of `exts` (empty =
+/// any), preferring the one with the most extensions, then the newest. Roots are read
+/// from the file headers, so only indexes of `dir` are deserialized. Sharded indexes
+/// and SQLite databases are considered by their manifest or `meta` table and loaded
+/// only when they win.
+pub fn find_content_index_covering(dir: &str, exts: &str, index_base: &std::path::Path) -> Option<ContentIndex> {
     if !index_base.exists() {
         return None;
     }
     let canonical = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
     let clean = clean_path(&canonical.to_string_lossy());
+    let wanted = normalize_exts(exts);
 
+    let covers = |extensions: &[String]| wanted.split(',').filter(|e| !e.is_empty())
+        .all(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)));
+    let mut best: Option<ContentIndex> = None;
+    // (extension count, created_at, manifest or database)
+    let mut best_lazy: Option<(usize, u64, PathBuf)> = None;
     for entry in fs::read_dir(index_base).ok()?.flatten() {
         let path = entry.path();
-        if path.extension().is_some_and(|e| e == "word-search") {
-            match load_compressed::<ContentIndex>(&path, "content-index") {
-                Ok(index) => {
-                    if index.root == clean {
-                        return Some(index);
-                    }
-                }
-                Err(e) => {
-                    eprintln!("[find_content_index] Skipping {}: {}", path.display(), e);
+        let is_manifest = path.extension().is_some_and(|e| e == shards::MANIFEST_EXTENSION);
+        let is_database = sqlite::is_database_of(&path, "word-search");
+        if !is_manifest && !is_database && path.extension().is_none_or(|e| e != "word-search") {
+            continue;
+        }
+        if read_root_from_index_file(&path).is_some_and(|root| root != clean) {
+            continue;
+        }
+        let lazy = if is_manifest {
+            load_compressed::<shards::ShardManifest>(&path, "content-shards").ok()
+                .map(|m| (m.root, m.extensions, m.created_at))
+        } else if is_database {
+            sqlite::read_summary(&path).filter(|s| s.kind == "content")
+                .map(|s| (s.root, s.extensions, s.created_at))
+        } else {
+            None
+        };
+        if is_manifest || is_database {
+            if let Some((root, extensions, created_at)) = lazy
+                && root == clean && covers(&extensions)
+                && best_lazy.as_ref().is_none_or(|b| (extensions.len(), created_at) > (b.0, b.1)) {
+                best_lazy = Some((extensions.len(), created_at, path));
+            }
+            continue;
+        }
+        match load_content_index_file(&path, "content-index") {
+            Ok(index) => {
+                let broader = best.as_ref().is_none_or(|b| {
+                    (index.extensions.len(), index.created_at) > (b.extensions.len(), b.created_at)
+                });
+                if index.root == clean && covers(&index.extensions) && broader {
+                    best = Some(index);
                 }
             }
+            Err(e) => {
+                eprintln!("[find_content_index] Skipping {}: {}", path.display(), e);
+            }
+        }
+    }
+    if let Some((extension_count, created_at, path)) = best_lazy
+        && best.as_ref().is_none_or(|b| (extension_count, created_at) > (b.extensions.len(), b.created_at)) {
+        let loaded = if sqlite::is_database_of(&path, "word-search") {
+            sqlite::load_content_index(&path)
+        } else {
+            shards::load_content_shards(&path)
+        };
+        match loaded {
+            Ok(index) => return Some(index),
+            Err(e) => eprintln!("[find_content_index] Skipping {}: {}", path.display(), e),
         }
     }
-    None
+    best
+}
+
+/// The broadest SQLite content database of `dir` covering `exts`, as
+/// [`find_content_index_covering`] ranks them, read from the `meta` tables only.
+fn find_content_database_covering(dir: &str, exts: &str, index_base: &std::path::Path) -> Option<PathBuf> {
+    let canonical = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
+    let clean = clean_path(&canonical.to_string_lossy());
+    let wanted = normalize_exts(exts);
+    fs::read_dir(index_base).ok()?.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| sqlite::is_database_of(path, "word-search"))
+        .filter_map(|path| sqlite::read_summary(&path).map(|s| (s, path)))
+        .filter(|(s, _)| s.kind == "content" && s.root == clean
+            && wanted.split(',').filter(|e| !e.is_empty()).all(|e| s.extensions.iter().any(|x| x.eq_ignore_ascii_case(e))))
+        .max_by_key(|(s, _)| (s.extensions.len(), s.created_at))
+        .map(|(_, path)| path)
 }
 
 /// Read the root field from an index file without deserializing the whole file.
-/// Handles both LZ4-compressed and legacy uncompressed formats.
+/// Handles the current format, the legacy LZ4 and uncompressed ones, and SQLite
+/// databases (from their `meta` table).
 /// Bincode stores a String as: u64 (length) + bytes. Since `root` is the first field in
 /// FileIndex, ContentIndex, and DefinitionIndex, we can read just the first few bytes.
 fn read_root_from_index_file(path: &std::path::Path) -> Option<String> {
+    if path.extension().is_some_and(|e| e == sqlite::EXTENSION) {
+        return sqlite::read_summary(path).map(|s| s.root);
+    }
     let mut file = std::fs::File::open(path).ok()?;
     let mut magic = [0u8; 4];
     file.read_exact(&mut magic).ok()?;
 
-    let reader: Box<dyn Read> = if &magic == LZ4_MAGIC {
+    let reader: Box<dyn Read> = if &magic == INDEX_MAGIC {
+        file.seek(SeekFrom::Start(INDEX_HEADER_LEN)).ok()?;
+        Box::new(lz4_flex::frame::FrameDecoder::new(BufReader::new(file)))
+    } else if &magic == LZ4_MAGIC {
         Box::new(lz4_flex::frame::FrameDecoder::new(BufReader::new(file)))
     } else {
         file.seek(SeekFrom::Start(0)).ok()?;
@@ -585,13 +752,14 @@ pub fn cleanup_orphaned_indexes(index_base: &std::path::Path) -> usize {
         for entry in entries.flatten() {
             let path = entry.path();
             let ext = path.extension().and_then(|e| e.to_str());
-            if !matches!(ext, Some("file-list") | Some("word-search") | Some("code-structure")) {
+            if !matches!(ext, Some("file-list" | "word-search" | "word-shards" | "word-shard" | "rev-search" | "code-structure" | sqlite::EXTENSION)) {
                 continue;
             }
 
             if let Some(root) = read_root_from_index_file(&path) {
                 if !std::path::Path::new(&root).exists() {
-                    if std::fs::remove_file(&path).is_ok() {
+                    if remove_index_file(&path) {
+                        remove_lock_files(&path);
                         removed += 1;
                         eprintln!("  Removed orphaned index: {} (root: {})", path.display(), root);
                     }
@@ -603,7 +771,7 @@ pub fn cleanup_orphaned_indexes(index_base: &std::path::Path) -> usize {
     removed
 }
 
-/// Remove all index files (.file-list, .word-search, .code-structure) whose root matches the given directory.
+/// Remove all index files (.file-list, .word-search, .word-shards, .word-shard, .rev-search, .code-structure, .sqlite) whose root matches the given directory.
 /// Comparison is case-insensitive on the canonicalized paths (Windows-safe).
 /// Returns the number of files removed.
 pub fn cleanup_indexes_for_dir(dir: &str, index_base: &std::path::Path) -> usize {
@@ -621,7 +789,7 @@ pub fn cleanup_indexes_for_dir(dir: &str, index_base: &std::path::Path) -> usize
         for entry in entries.flatten() {
             let path = entry.path();
             let ext = path.extension().and_then(|e| e.to_str());
-            if !matches!(ext, Some("file-list") | Some("word-search") | Some("code-structure")) {
+            if !matches!(ext, Some("file-list" | "word-search" | "word-shards" | "word-shard" | "rev-search" | "code-structure" | sqlite::EXTENSION)) {
                 continue;
             }
 
@@ -630,7 +798,8 @@ pub fn cleanup_indexes_for_dir(dir: &str, index_base: &std::path::Path) -> usize
                     .map(|p| clean_path(&p.to_string_lossy()))
                     .unwrap_or_else(|_| clean_path(&root));
                 if root_canonical.eq_ignore_ascii_case(&target) {
-                    if std::fs::remove_file(&path).is_ok() {
+                    if remove_index_file(&path) {
+                        remove_lock_files(&path);
                         removed += 1;
                         eprintln!("  Removed index for dir '{}': {} ({})",
                             dir, path.display(), ext.unwrap_or("?"));
@@ -643,28 +812,44 @@ pub fn cleanup_indexes_for_dir(dir: &str, index_base: &std::path::Path) -> usize
     removed
 }
 
-// ─── Index building ──────────────────────────────────────────────────
-
-pub fn build_index(args: &IndexArgs) -> FileIndex {
-    let root = fs::canonicalize(&args.dir).unwrap_or_else(|_| PathBuf::from(&args.dir));
-    let root_str = clean_path(&root.to_string_lossy());
+/// Remove an index file, or a SQLite database with its WAL files.
+fn remove_index_file(path: &Path) -> bool {
+    if path.extension().is_some_and(|e| e == sqlite::EXTENSION) {
+        sqlite::remove_database(path)
+    } else {
+        fs::remove_file(path).is_ok()
+    }
+}
 
-    eprintln!("Indexing {}...", root_str);
-    let start = Instant::now();
+/// Remove the lock files of a removed index file. Locks still held (on Windows) stay.
+/// A database is locked through the index file it replaces.
+fn remove_lock_files(index_path: &Path) {
+    let index_path = match index_path.extension() {
+        Some(e) if e == sqlite::EXTENSION => index_path.with_extension(""),
+        _ => index_path.to_path_buf(),
+    };
+    for lock_path in crate::lock::lock_files_for(&index_path) {
+        let _ = std::fs::remove_file(lock_path);
+    }
+}
 
-    let mut builder = WalkBuilder::new(&root);
-    builder.hidden(!args.hidden);
-    builder.git_ignore(!args.no_ignore);
-    builder.git_global(!args.no_ignore);
-    builder.git_exclude(!args.no_ignore);
+// ─── Index building ──────────────────────────────────────────────────
 
-    let thread_count = if args.threads == 0 {
-        std::thread::available_parallelism()
-            .map(|n| n.get())
-            .unwrap_or(4)
-    } else {
-        args.threads
-    };
+/// Walk `root` and record every file and directory with its size and mtime.
+/// Shared by `search index` and the polling file watcher, which diffs two walks.
+pub(crate) fn walk_file_entries(
+    root: &Path,
+    hidden: bool,
+    no_ignore: bool,
+    thread_count: usize,
+    priority: BuildPriority,
+    progress: Option<&Progress>,
+) -> Vec<FileEntry> {
+    let mut builder = WalkBuilder::new(root);
+    builder.hidden(!hidden);
+    builder.git_ignore(!no_ignore);
+    builder.git_global(!no_ignore);
+    builder.git_exclude(!no_ignore);
     builder.threads(thread_count);
 
     let entries: Mutex<Vec<FileEntry>> = Mutex::new(Vec::new());
@@ -672,8 +857,9 @@ pub fn build_index(args: &IndexArgs) -> FileIndex {
     builder.build_parallel().run(|| {
         let entries = &entries;
         Box::new(move |result| {
+            apply_to_current_thread(priority);
             if let Ok(entry) = result {
-                let path = clean_path(&entry.path().to_string_lossy());
+                let path = clean_path(&path_to_string(entry.path()));
                 let metadata = entry.metadata().ok();
                 let (size, modified, is_dir) = if let Some(m) = metadata {
                     let mod_time = m
@@ -695,12 +881,28 @@ pub fn build_index(args: &IndexArgs) -> FileIndex {
                 };
 
                 entries.lock().unwrap_or_else(|e| e.into_inner()).push(fe);
+                if let Some(progress) = progress {
+                    progress.inc(1);
+                }
             }
             ignore::WalkState::Continue
         })
     });
 
-    let entries = recover_mutex(entries, "file-index");
+    recover_mutex(entries, "file-index")
+}
+
+pub fn build_index(args: &IndexArgs) -> FileIndex {
+    let root = fs::canonicalize(&args.dir).unwrap_or_else(|_| PathBuf::from(&args.dir));
+    let root_str = clean_path(&root.to_string_lossy());
+
+    eprintln!("Indexing {}...", root_str);
+    let start = Instant::now();
+
+    let thread_count = resolve_threads(args.threads, args.build_priority);
+    let progress = Progress::start("index", "scanning", "entries", None);
+    let entries = walk_file_entries(&root, args.hidden, args.no_ignore, thread_count, args.build_priority, Some(&progress));
+    drop(progress);
     let count = entries.len();
 
     let now = SystemTime::now()
@@ -728,225 +930,867 @@ pub fn build_index(args: &IndexArgs) -> FileIndex {
 // ─── Content index building ──────────────────────────────────────────
 
 pub fn build_content_index(args: &ContentIndexArgs) -> ContentIndex {
+    build_content_index_shard(args, None)
+}
+
+/// [`build_content_index`] over the files of one shard (`(shard, count)`, see
+/// [`crate::shards::shard_of`]); `None` indexes every file. Top-level directories of
+/// other shards are not walked.
+pub fn build_content_index_shard(args: &ContentIndexArgs, shard: Option<(usize, usize)>) -> ContentIndex {
+    let checkpoint_interval = (args.checkpoint_secs > 0).then(|| Duration::from_secs(args.checkpoint_secs));
+    build_walked(args, shard, &index_dir(), checkpoint_interval)
+}
+
+/// [`build_content_index_shard`] with the checkpoints (see [`Checkpoint`]) kept next to
+/// the index in `index_base`, every `checkpoint_interval`; `None` takes none.
+fn build_walked(
+    args: &ContentIndexArgs,
+    shard: Option<(usize, usize)>,
+    index_base: &Path,
+    checkpoint_interval: Option<Duration>,
+) -> ContentIndex {
     let root = fs::canonicalize(&args.dir).unwrap_or_else(|_| PathBuf::from(&args.dir));
     let root_str = clean_path(&root.to_string_lossy());
     let extensions: Vec<String> = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect();
 
-    eprintln!(
-        "Building content index for {} (extensions: {})...",
-        root_str,
-        extensions.join(", ")
-    );
+    match shard {
+        Some((shard, count)) => eprintln!(
+            "Building content index shard {}/{} for {} (extensions: {})...",
+            shard + 1, count, root_str, extensions.join(", ")
+        ),
+        None => eprintln!(
+            "Building content index for {} (extensions: {})...",
+            root_str,
+            extensions.join(", ")
+        ),
+    }
     let start = Instant::now();
 
-    let mut builder = WalkBuilder::new(&root);
-    builder.hidden(!args.hidden);
-    builder.git_ignore(!args.no_ignore);
-    builder.git_global(!args.no_ignore);
-    builder.git_exclude(!args.no_ignore);
+    let config = RepoConfig::load_or_default(&root);
+    let max_file_size = config.effective_max_file_size(args.max_file_size);
+    let mut files = IndexBuilder::new(&root).hidden(args.hidden).ignore_files(!args.no_ignore);
+    if let Some(max) = max_file_size {
+        files = files.max_file_size(max);
+    }
+    let mut builder = files.walker(&root);
+    let walk_limits = WalkLimits::new(args.max_depth, &args.prune_dir);
+    config.apply_to_walk(&mut builder, &root, &walk_limits);
+    let languages = config.language_map(&root);
 
-    let thread_count = if args.threads == 0 {
-        std::thread::available_parallelism()
-            .map(|n| n.get())
-            .unwrap_or(4)
-    } else {
-        args.threads
-    };
+    let priority = args.build_priority;
+    let thread_count = resolve_threads(args.threads, priority);
     builder.threads(thread_count);
+    let io_throttle = IoThrottle::new(args.io_limit_mb);
+    if priority == BuildPriority::Low || io_throttle.is_enabled() {
+        eprintln!(
+            "Build priority: {} ({} threads, IO limit: {})",
+            priority, thread_count,
+            if io_throttle.is_enabled() { format!("{} MB/s", args.io_limit_mb) } else { "none".to_string() }
+        );
+    }
 
-    let file_data: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
-
+    let shard_count = thread_count.max(1);
+    let tokenizer_settings = TokenizerSettings { min_token_len: args.min_token_len, max_token_len: args.max_token_len };
+    let checkpoint = checkpoint_interval.and_then(|interval| {
+        let config = format!("{:?}", (&extensions, tokenizer_settings, max_file_size, args.hidden, args.no_ignore, shard_count, &walk_limits));
+        let dir = checkpoint_path_for(&args.dir, &args.ext, index_base).join(match shard {
+            Some((shard, count)) => format!("{:02}of{:02}", shard + 1, count),
+            None => "all".to_string(),
+        });
+        Checkpoint::open(dir, interval, stable_hash(&[root_str.as_bytes(), config.as_bytes()]))
+    });
+    let checkpoint = checkpoint.as_ref();
+    let spill = Spill::new(args.spill_mb, shard_count, checkpoint);
+    let spill = spill.as_ref();
+    let next_file_id = AtomicU32::new(checkpoint.map_or(0, |c| c.next_file_id));
+    let outputs: Mutex<Vec<TokenizerOutput>> = Mutex::new(Vec::new());
+    let progress = Progress::start("content-index", "tokenizing", "files", None);
+    let skipped_large = AtomicUsize::new(0);
+    let skipped_binary = AtomicUsize::new(0);
+
+    // Each walker thread tokenizes the files it reads and drops their content right
+    // away: no file content is kept past its own tokenization.
     builder.build_parallel().run(|| {
         let extensions = extensions.clone();
-        let file_data = &file_data;
+        let languages = &languages;
+        let files = &files;
+        let io_throttle = &io_throttle;
+        let next_file_id = &next_file_id;
+        let (skipped_large, skipped_binary) = (&skipped_large, &skipped_binary);
+        let progress = &progress;
+        let mut tokenizer = ThreadTokenizer { out: Some(TokenizerOutput::new(shard_count)), sink: &outputs };
         Box::new(move |result| {
+            apply_to_current_thread(priority);
             if let Ok(entry) = result {
+                if let Some((shard, count)) = shard && entry.depth() == 1 {
+                    let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+                    let key = if is_dir { entry.file_name().to_string_lossy().to_lowercase() } else { String::new() };
+                    if crate::shards::shard_of_dir(&key, count) != shard {
+                        return ignore::WalkState::Skip;
+                    }
+                }
                 if !entry.file_type().is_some_and(|ft| ft.is_file()) {
                     return ignore::WalkState::Continue;
                 }
-                let ext_match = entry
-                    .path()
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .is_some_and(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)));
-                if !ext_match {
+                if !languages.matches(entry.path(), &extensions) {
+                    return ignore::WalkState::Continue;
+                }
+                let path = clean_path(&path_to_string(entry.path()));
+                let mtime = checkpoint.map_or(0, |_| modified_nanos(entry.metadata().ok()));
+                if checkpoint.is_some_and(|c| c.resume(&path, mtime)) {
                     return ignore::WalkState::Continue;
                 }
-                let path = clean_path(&entry.path().to_string_lossy());
-                match read_file_lossy(entry.path()) {
-                    Ok((content, _was_lossy)) => {
-                        file_data.lock().unwrap_or_else(|e| e.into_inner()).push((path, content));
+                match files.read(entry.path(), entry.metadata().ok().map(|m| m.len())) {
+                    FileRead::Text(content) => {
+                        io_throttle.consume(content.len() as u64);
+                        if let Some(out) = tokenizer.out.as_mut() {
+                            let file_id = next_file_id.fetch_add(1, Ordering::Relaxed);
+                            out.add_file(file_id, path, mtime, &content, &tokenizer_settings, spill);
+                            progress.inc(1);
+                            if let Some(checkpoint) = checkpoint
+                                && !out.checkpoint_failed
+                                && out.last_checkpoint.elapsed() >= checkpoint.interval
+                                && let Err(e) = checkpoint.save(out) {
+                                eprintln!("[WARN] Failed to write a build checkpoint to {}: {}; no more checkpoints from this thread",
+                                    checkpoint.dir.display(), e);
+                                out.checkpoint_failed = true;
+                            }
+                        }
                     }
-                    Err(_) => {}
+                    FileRead::TooLarge => { skipped_large.fetch_add(1, Ordering::Relaxed); }
+                    FileRead::Binary => { skipped_binary.fetch_add(1, Ordering::Relaxed); }
+                    FileRead::Unreadable => {}
                 }
             }
             ignore::WalkState::Continue
         })
     });
 
-    let file_data = recover_mutex(file_data, "content-index");
-    let file_count = file_data.len();
-    let min_len = args.min_token_len;
-    log_memory(&format!("content-build: after file walk ({} files)", file_count));
-
-    // ─── Parallel tokenization ──────────────────────────────────
-    let num_tok_threads = thread_count.max(1);
-    let tok_chunk_size = file_count.div_ceil(num_tok_threads).max(1);
-
-    let chunk_results: Vec<_> = std::thread::scope(|s| {
-        let handles: Vec<_> = file_data
-            .chunks(tok_chunk_size)
-            .enumerate()
-            .map(|(chunk_idx, chunk)| {
-                let base_file_id = (chunk_idx * tok_chunk_size) as u32;
-                s.spawn(move || {
-                    let mut local_files: Vec<String> = Vec::with_capacity(chunk.len());
-                    let mut local_counts: Vec<u32> = Vec::with_capacity(chunk.len());
-                    let mut local_index: HashMap<String, Vec<Posting>> = HashMap::new();
-                    let mut local_total: u64 = 0;
-
-                    for (i, (path, content)) in chunk.iter().enumerate() {
-                        let file_id = base_file_id + i as u32;
-                        local_files.push(path.clone());
-                        let mut file_tokens: HashMap<String, Vec<u32>> = HashMap::new();
-                        let mut file_total: u32 = 0;
-
-                        for (line_num, line) in content.lines().enumerate() {
-                            for token in tokenize(line, min_len) {
-                                local_total += 1;
-                                file_total += 1;
-                                file_tokens
-                                    .entry(token)
-                                    .or_default()
-                                    .push((line_num + 1) as u32);
-                            }
-                        }
+    let mut outputs = recover_mutex(outputs, "content-index");
+    if let Some(checkpoint) = checkpoint {
+        outputs.extend(checkpoint.outputs(shard_count));
+    }
+    let file_count = next_file_id.into_inner() as usize;
+    log_memory(&format!("content-build: after walk + tokenization ({} files)", file_count));
 
-                        local_counts.push(file_total);
+    progress.phase("merging", "shards", Some(outputs.first().map_or(1, |o| o.shards.len())));
+    let mut index = merge_tokenized(
+        outputs, file_count, root_str, extensions, args.max_age_hours * 3600, priority, Some(&progress),
+    );
+    drop(progress);
+    if let Some(spill) = spill {
+        eprintln!("Spilled {} runs to {} during the build", spill.runs_written(), spill.dir.display());
+    }
+    index.skipped = SkippedFiles {
+        too_large: skipped_large.into_inner(),
+        binary: skipped_binary.into_inner(),
+    };
+    index.tokenizer = tokenizer_settings;
+    index.walk_limits = walk_limits;
+    index.stop_token_pct = args.stop_token_pct;
+    index.max_file_size = max_file_size.unwrap_or(0);
+    eprintln!(
+        "Indexed {} files, {} unique tokens ({} total) in {:.3}s",
+        file_count, index.index.len(), index.total_tokens, start.elapsed().as_secs_f64()
+    );
+    if index.skipped != SkippedFiles::default() {
+        eprintln!(
+            "Skipped {} files over {} KB, {} binary files",
+            index.skipped.too_large, max_file_size.unwrap_or(0) / 1024, index.skipped.binary
+        );
+    }
+    index
+}
 
-                        for (token, lines) in file_tokens {
-                            local_index
-                                .entry(token)
-                                .or_default()
-                                .push(Posting { file_id, lines });
-                        }
-                    }
+/// Files handed to a tokenizer thread at a time by [`index_file_contents`]. Small
+/// enough that a few huge files do not leave the other threads idle at the end.
+const TOKENIZE_BATCH_FILES: usize = 64;
+
+/// Rough heap bytes of a posting or map entry beyond its line numbers and token
+/// text; only used to decide when to spill.
+const ENTRY_OVERHEAD_BYTES: usize = 48;
+
+/// Postings and per-file results of one tokenizer thread. `shards[s]` holds the tokens
+/// whose hash maps to shard `s`, so shards can be merged independently; shards
+/// written to disk by [`Spill`] are listed in `runs`.
+struct TokenizerOutput {
+    /// (file_id, path, token count)
+    files: Vec<(u32, String, u32)>,
+    /// Modification time of each of `files` when it was read (ns since the epoch; 0 =
+    /// unknown), recorded in checkpoints
+    mtimes: Vec<u64>,
+    shards: Vec<HashMap<String, Vec<Posting>>>,
+    total_tokens: u64,
+    markers: Vec<CommentMarker>,
+    tickets: Vec<TicketRef>,
+    /// Approximate bytes held by `shards`
+    shard_bytes: usize,
+    runs: Vec<SpillRun>,
+    /// Set after a failed spill: the rest stays in memory
+    spill_failed: bool,
+    last_checkpoint: Instant,
+    /// Set after a failed checkpoint: the thread stops taking them
+    checkpoint_failed: bool,
+}
+
+impl TokenizerOutput {
+    fn new(shard_count: usize) -> Self {
+        Self {
+            files: Vec::new(),
+            mtimes: Vec::new(),
+            shards: (0..shard_count).map(|_| HashMap::new()).collect(),
+            total_tokens: 0,
+            markers: Vec::new(),
+            tickets: Vec::new(),
+            shard_bytes: 0,
+            runs: Vec::new(),
+            spill_failed: false,
+            last_checkpoint: Instant::now(),
+            checkpoint_failed: false,
+        }
+    }
 
-                    (local_files, local_counts, local_index, local_total)
-                })
-            })
+    /// Tokenize one file into the shards, then spill them if they outgrew the budget.
+    fn add_file(&mut self, file_id: u32, path: String, mtime: u64, content: &str, settings: &TokenizerSettings, spill: Option<&Spill>) {
+        let (file_tokens, file_total) = search::builder::file_tokens(content, settings);
+
+        self.total_tokens += file_total as u64;
+        self.files.push((file_id, path, file_total));
+        self.mtimes.push(mtime);
+        self.markers.extend(extract_comment_markers(content).into_iter().map(
+            |(line, marker, text)| CommentMarker { file_id, line, marker: marker.to_string(), text },
+        ));
+        self.tickets.extend(extract_ticket_refs(content).into_iter().map(
+            |(line, ticket)| TicketRef { file_id, line, ticket },
+        ));
+
+        let shard_count = self.shards.len();
+        for (token, lines) in file_tokens {
+            self.shard_bytes += lines.len() * 4 + ENTRY_OVERHEAD_BYTES;
+            let shard = &mut self.shards[token_shard(&token, shard_count)];
+            if let Some(postings) = shard.get_mut(&token) {
+                postings.push(Posting { file_id, lines });
+            } else {
+                self.shard_bytes += token.len() + ENTRY_OVERHEAD_BYTES;
+                shard.insert(token, vec![Posting { file_id, lines }]);
+            }
+        }
+
+        if let Some(spill) = spill
+            && !self.spill_failed
+            && self.shard_bytes > spill.thread_budget_bytes {
+            match spill.write_run(&self.shards) {
+                Ok(run) => {
+                    self.runs.push(run);
+                    self.shards.iter_mut().for_each(|s| *s = HashMap::new());
+                    self.shard_bytes = 0;
+                }
+                Err(e) => {
+                    eprintln!("[WARN] Failed to spill postings to {}: {}; keeping them in memory", spill.dir.display(), e);
+                    self.spill_failed = true;
+                }
+            }
+        }
+    }
+}
+
+/// A walker thread's tokenizer. The walker drops it when the thread finishes, which
+/// hands its output to the build.
+struct ThreadTokenizer<'a> {
+    out: Option<TokenizerOutput>,
+    sink: &'a Mutex<Vec<TokenizerOutput>>,
+}
+
+impl Drop for ThreadTokenizer<'_> {
+    fn drop(&mut self) {
+        if let Some(out) = self.out.take() {
+            self.sink.lock().unwrap_or_else(|e| e.into_inner()).push(out);
+        }
+    }
+}
+
+fn token_shard(token: &str, shard_count: usize) -> usize {
+    (stable_hash(&[token.as_bytes()]) % shard_count as u64) as usize
+}
+
+/// Temp directory for postings spilled during a build (`--spill-mb`), removed on drop.
+/// A build with checkpoints spills into its checkpoint directory instead, so that
+/// checkpoints can refer to the runs; that directory is kept.
+struct Spill {
+    dir: PathBuf,
+    /// Postings a thread holds before writing them out
+    thread_budget_bytes: usize,
+    next_run: AtomicUsize,
+    /// Run file name prefix
+    prefix: String,
+    keep_dir: bool,
+}
+
+/// One spilled set of shards: bincode maps written back to back.
+struct SpillRun {
+    path: PathBuf,
+    /// Start of each shard's map in the file
+    offsets: Vec<u64>,
+}
+
+impl Spill {
+    /// `None` when `spill_mb` is 0 or the directory can't be created. The budget is
+    /// split evenly between `threads`.
+    fn new(spill_mb: u64, threads: usize, checkpoint: Option<&Checkpoint>) -> Option<Self> {
+        if spill_mb == 0 {
+            return None;
+        }
+        let thread_budget_bytes = (spill_mb as usize * 1_048_576 / threads.max(1)).max(1);
+        if let Some(checkpoint) = checkpoint {
+            return Some(Self {
+                dir: checkpoint.dir.clone(), thread_budget_bytes, next_run: AtomicUsize::new(0),
+                prefix: format!("run-{}-s", checkpoint.session), keep_dir: true,
+            });
+        }
+        let n = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("search-spill-{}-{}", std::process::id(), n));
+        if let Err(e) = fs::create_dir_all(&dir) {
+            eprintln!("[WARN] Cannot create spill directory {}: {}; building in memory", dir.display(), e);
+            return None;
+        }
+        Some(Self { dir, thread_budget_bytes, next_run: AtomicUsize::new(0), prefix: "run-".to_string(), keep_dir: false })
+    }
+
+    fn runs_written(&self) -> usize {
+        self.next_run.load(Ordering::Relaxed)
+    }
+
+    fn write_run(&self, shards: &[HashMap<String, Vec<Posting>>]) -> Result<SpillRun, SearchError> {
+        let n = self.next_run.fetch_add(1, Ordering::Relaxed);
+        write_run(self.dir.join(format!("{}{}.bin", self.prefix, n)), shards)
+    }
+}
+
+fn write_run(path: PathBuf, shards: &[HashMap<String, Vec<Posting>>]) -> Result<SpillRun, SearchError> {
+    let mut writer = BufWriter::new(fs::File::create(&path)?);
+    let mut offsets = Vec::with_capacity(shards.len());
+    let mut offset = 0u64;
+    for shard in shards {
+        offsets.push(offset);
+        offset += bincode::serialized_size(shard)?;
+        bincode::serialize_into(&mut writer, shard)?;
+    }
+    writer.flush()?;
+    Ok(SpillRun { path, offsets })
+}
+
+impl SpillRun {
+    fn read_shard(&self, shard: usize) -> Result<HashMap<String, Vec<Posting>>, SearchError> {
+        let mut reader = BufReader::new(fs::File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(self.offsets[shard]))?;
+        Ok(bincode::deserialize_from(&mut reader)?)
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        if !self.keep_dir {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+// ─── Build checkpoints ──────────────────────────────────────────────
+
+/// Directory extension of the checkpoints of an unfinished content index build.
+pub const CHECKPOINT_EXTENSION: &str = "word-checkpoint";
+
+/// Directory for the checkpoints of the content index build of `dir` and `exts`.
+/// `content-index` removes it once the index is saved.
+pub fn checkpoint_path_for(dir: &str, exts: &str, index_base: &Path) -> PathBuf {
+    content_index_path_for(dir, exts, index_base).with_extension(CHECKPOINT_EXTENSION)
+}
+
+/// Checkpoints of a content index build (`--checkpoint-secs`). Every interval, each
+/// walker thread writes what it has tokenized so far as a part: its postings as runs
+/// (the format of [`Spill`]) plus the files, markers and tickets they belong to. The
+/// part file is written last, under a temp name that is renamed into place, so a part
+/// on disk is always complete.
+///
+/// A build that finds parts from an interrupted build of the same configuration skips
+/// their files, unless a file's modification time changed, and merges the parts into
+/// its result. File ids of the parts are kept, so their runs need no rewriting; files
+/// that changed or disappeared leave gaps that the merge removes.
+struct Checkpoint {
+    dir: PathBuf,
+    interval: Duration,
+    /// Names this build's files apart from those of the build it resumes
+    session: u128,
+    next_part: AtomicUsize,
+    /// Parts found on disk when the build started
+    resumed: Vec<CheckpointPart>,
+    /// Path → (modification time, found unchanged by this build's walk) of the files
+    /// in `resumed`
+    done: HashMap<String, (u64, AtomicBool)>,
+    /// Parts written by this build
+    saved: Mutex<Vec<CheckpointPart>>,
+    /// First file id not used by `resumed`
+    next_file_id: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CheckpointPart {
+    /// (file_id, path, token count, modification time)
+    files: Vec<(u32, String, u32, u64)>,
+    markers: Vec<CommentMarker>,
+    tickets: Vec<TicketRef>,
+    /// Run file names in the checkpoint directory, with the offsets of their shards
+    runs: Vec<(String, Vec<u64>)>,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint directory `dir` for a build whose configuration hashes to
+    /// `key`, loading the parts left there by an earlier build of the same
+    /// configuration. Anything else in the directory is removed. `None` when the
+    /// directory can't be created.
+    fn open(dir: PathBuf, interval: Duration, key: u64) -> Option<Self> {
+        let key = format!("{:016x}", key);
+        let key_path = dir.join("key");
+        if fs::read_to_string(&key_path).ok().as_deref() != Some(key.as_str()) {
+            let _ = fs::remove_dir_all(&dir);
+        }
+        if let Err(e) = fs::create_dir_all(&dir).and_then(|()| fs::write(&key_path, &key)) {
+            eprintln!("[WARN] Cannot create checkpoint directory {}: {}; building without checkpoints", dir.display(), e);
+            return None;
+        }
+
+        let mut resumed = Vec::new();
+        let mut names: Vec<PathBuf> = fs::read_dir(&dir).into_iter().flatten().flatten().map(|e| e.path()).collect();
+        names.sort();
+        for path in &names {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if name.starts_with("part-") && name.ends_with(".bin") {
+                let part = fs::File::open(path).map_err(SearchError::from)
+                    .and_then(|f| Ok(bincode::deserialize_from::<_, CheckpointPart>(BufReader::new(f))?));
+                match part {
+                    Ok(part) => resumed.push(part),
+                    Err(e) => eprintln!("[WARN] Ignoring unreadable checkpoint {}: {}", path.display(), e),
+                }
+            }
+        }
+        // Runs spilled after the last part of the interrupted build, temp files and
+        // unreadable parts belong to no part
+        let referenced: std::collections::HashSet<&str> = resumed.iter()
+            .flat_map(|p| p.runs.iter().map(|(name, _)| name.as_str()))
             .collect();
+        for path in &names {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let used = name == "key" || (name.starts_with("part-") && name.ends_with(".bin")) || referenced.contains(name.as_ref());
+            if !used {
+                let _ = fs::remove_file(path);
+            }
+        }
 
-        handles.into_iter().map(|h| h.join().unwrap_or_else(|_| {
+        let done: HashMap<String, (u64, AtomicBool)> = resumed.iter()
+            .flat_map(|p| p.files.iter().map(|(_, path, _, mtime)| (path.clone(), (*mtime, AtomicBool::new(false)))))
+            .collect();
+        let next_file_id = resumed.iter().flat_map(|p| p.files.iter().map(|f| f.0 + 1)).max().unwrap_or(0);
+        if !done.is_empty() {
+            eprintln!("Resuming from checkpoint {}: {} files already indexed", dir.display(), done.len());
+        }
+        let session = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_nanos();
+        Some(Self { dir, interval, session, next_part: AtomicUsize::new(0), resumed, done, saved: Mutex::new(Vec::new()), next_file_id })
+    }
+
+    /// Whether `path`, modified at `mtime`, is already indexed by a resumed part.
+    fn resume(&self, path: &str, mtime: u64) -> bool {
+        match self.done.get(path) {
+            Some((done_mtime, found)) if *done_mtime == mtime && mtime != 0 => {
+                found.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Write everything `out` holds as a part and clear it.
+    fn save(&self, out: &mut TokenizerOutput) -> Result<(), SearchError> {
+        out.last_checkpoint = Instant::now();
+        if out.files.is_empty() {
+            return Ok(());
+        }
+        let n = self.next_part.fetch_add(1, Ordering::Relaxed);
+        if out.shards.iter().any(|s| !s.is_empty()) {
+            out.runs.push(write_run(self.dir.join(format!("run-{}-p{}.bin", self.session, n)), &out.shards)?);
+            out.shards.iter_mut().for_each(|s| *s = HashMap::new());
+            out.shard_bytes = 0;
+        }
+        let part = CheckpointPart {
+            files: out.files.iter().zip(&out.mtimes)
+                .map(|((id, path, count), mtime)| (*id, path.clone(), *count, *mtime))
+                .collect(),
+            markers: out.markers.clone(),
+            tickets: out.tickets.clone(),
+            runs: out.runs.iter()
+                .map(|r| (r.path.file_name().unwrap_or_default().to_string_lossy().to_string(), r.offsets.clone()))
+                .collect(),
+        };
+        let path = self.dir.join(format!("part-{}-{:06}.bin", self.session, n));
+        let tmp_path = path.with_extension("tmp");
+        let written = fs::File::create(&tmp_path).map_err(SearchError::from).and_then(|f| {
+            let mut writer = BufWriter::new(f);
+            bincode::serialize_into(&mut writer, &part)?;
+            writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            Ok(fs::rename(&tmp_path, &path)?)
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        written?;
+
+        out.files.clear();
+        out.mtimes.clear();
+        out.total_tokens = 0;
+        out.markers.clear();
+        out.tickets.clear();
+        out.runs.clear();
+        self.saved.lock().unwrap_or_else(|e| e.into_inner()).push(part);
+        Ok(())
+    }
+
+    /// The parts as tokenizer outputs for the merge. Files of resumed parts that the
+    /// walk did not find unchanged are left out; their ids become gaps.
+    fn outputs(&self, shard_count: usize) -> Vec<TokenizerOutput> {
+        let saved = self.saved.lock().unwrap_or_else(|e| e.into_inner());
+        let resumed = self.resumed.iter().map(|part| (part, true));
+        resumed.chain(saved.iter().map(|part| (part, false))).map(|(part, is_resumed)| {
+            let mut out = TokenizerOutput::new(shard_count);
+            for (id, path, count, mtime) in &part.files {
+                if is_resumed && !self.done.get(path).is_some_and(|(_, found)| found.load(Ordering::Relaxed)) {
+                    continue;
+                }
+                out.files.push((*id, path.clone(), *count));
+                out.mtimes.push(*mtime);
+                out.total_tokens += *count as u64;
+            }
+            out.markers = part.markers.clone();
+            out.tickets = part.tickets.clone();
+            out.runs = part.runs.iter()
+                .map(|(name, offsets)| SpillRun { path: self.dir.join(name), offsets: offsets.clone() })
+                .collect();
+            out
+        }).collect()
+    }
+}
+
+/// Remove the checkpoints of the content index build of `dir` and `exts`, after the
+/// index was saved. Returns whether there were any.
+pub fn remove_checkpoints(dir: &str, exts: &str, index_base: &Path) -> bool {
+    fs::remove_dir_all(checkpoint_path_for(dir, exts, index_base)).is_ok()
+}
+
+/// Modification time in ns since the epoch; 0 when unknown.
+fn modified_nanos(metadata: Option<fs::Metadata>) -> u64 {
+    metadata.and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// Tokenize `(path, content)` pairs in parallel into a content index.
+/// Used by [`build_content_index_at_rev`], whose blobs are already in memory;
+/// file_ids follow the order of `file_data`.
+///
+/// Threads take batches of [`TOKENIZE_BATCH_FILES`] files from a shared counter.
+fn index_file_contents(
+    file_data: Vec<(String, String)>,
+    root: String,
+    extensions: Vec<String>,
+    max_age_secs: u64,
+    settings: TokenizerSettings,
+    thread_count: usize,
+    priority: BuildPriority,
+) -> ContentIndex {
+    let file_count = file_data.len();
+    let shard_count = thread_count.max(1);
+
+    let batches: Vec<&[(String, String)]> = file_data.chunks(TOKENIZE_BATCH_FILES).collect();
+    let next_batch = AtomicUsize::new(0);
+    let outputs: Vec<TokenizerOutput> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..shard_count.min(batches.len()))
+            .map(|_| s.spawn(|| {
+                apply_to_current_thread(priority);
+                let mut out = TokenizerOutput::new(shard_count);
+                loop {
+                    let b = next_batch.fetch_add(1, Ordering::Relaxed);
+                    let Some(batch) = batches.get(b) else { break };
+                    for (i, (path, content)) in batch.iter().enumerate() {
+                        let file_id = (b * TOKENIZE_BATCH_FILES + i) as u32;
+                        out.add_file(file_id, path.clone(), 0, content, &settings, None);
+                    }
+                }
+                out
+            }))
+            .collect();
+        handles.into_iter().filter_map(|h| h.join().map_err(|_| {
             eprintln!("[WARN] Worker thread panicked during content index building");
-            (Vec::new(), Vec::new(), HashMap::new(), 0u64)
-        })).collect()
+        }).ok()).collect()
     });
 
-    log_memory("content-build: after tokenization (file_data + chunks alive)");
+    log_memory("content-build: after tokenization (file_data + shards alive)");
 
     // Free raw file contents — no longer needed after tokenization.
-    // This releases ~1.6 GB for large repos (80K files × ~20KB avg content).
-    // Without this drop, the file data stays alive until function return,
-    // causing peak memory to be ~1.6 GB higher during build vs. load-from-disk.
+    drop(batches);
     drop(file_data);
     log_memory("content-build: after drop(file_data)");
 
-    // ─── Merge per-thread results ───────────────────────────────
-    let mut files: Vec<String> = Vec::with_capacity(file_count);
-    let mut file_token_counts: Vec<u32> = Vec::with_capacity(file_count);
-    let mut index: HashMap<String, Vec<Posting>> = HashMap::new();
-    let mut total_tokens: u64 = 0;
+    let mut index = merge_tokenized(outputs, file_count, root, extensions, max_age_secs, priority, None);
+    index.tokenizer = settings;
+    index
+}
 
-    for (local_files, local_counts, local_index, local_total) in chunk_results {
-        files.extend(local_files);
-        file_token_counts.extend(local_counts);
-        total_tokens += local_total;
-        for (token, postings) in local_index {
-            index.entry(token).or_default().extend(postings);
+/// Merge the output of tokenizer threads into a content index. Each shard is merged
+/// by its own thread, from memory and from spilled runs, and its posting lists are
+/// sorted by file_id, so the result does not depend on which thread read which file.
+fn merge_tokenized(
+    outputs: Vec<TokenizerOutput>,
+    file_count: usize,
+    root: String,
+    extensions: Vec<String>,
+    max_age_secs: u64,
+    priority: BuildPriority,
+    progress: Option<&Progress>,
+) -> ContentIndex {
+    let shard_count = outputs.first().map_or(1, |o| o.shards.len());
+    let mut files: Vec<String> = vec![String::new(); file_count];
+    let mut file_token_counts: Vec<u32> = vec![0; file_count];
+    let mut total_tokens: u64 = 0;
+    let mut comment_markers: Vec<CommentMarker> = Vec::new();
+    let mut ticket_refs: Vec<TicketRef> = Vec::new();
+    let mut runs: Vec<SpillRun> = Vec::new();
+    let mut shard_parts: Vec<Vec<HashMap<String, Vec<Posting>>>> =
+        (0..shard_count).map(|_| Vec::with_capacity(outputs.len())).collect();
+
+    for out in outputs {
+        for (file_id, path, count) in out.files {
+            files[file_id as usize] = path;
+            file_token_counts[file_id as usize] = count;
+        }
+        total_tokens += out.total_tokens;
+        comment_markers.extend(out.markers);
+        ticket_refs.extend(out.tickets);
+        runs.extend(out.runs);
+        for (parts, shard) in shard_parts.iter_mut().zip(out.shards) {
+            parts.push(shard);
         }
     }
+    comment_markers.sort_by_key(|m| m.file_id);
+    ticket_refs.sort_by_key(|t| t.file_id);
+
+    let runs = &runs;
+    let merged: Vec<HashMap<String, Vec<Posting>>> = std::thread::scope(|s| {
+        let handles: Vec<_> = shard_parts.into_iter().enumerate()
+            .map(|(shard_idx, parts)| s.spawn(move || {
+                apply_to_current_thread(priority);
+                let spilled = runs.iter().filter_map(|run| run.read_shard(shard_idx).map_err(|e| {
+                    eprintln!("[ERROR] Failed to read spilled postings {}: {}; the index is incomplete", run.path.display(), e);
+                }).ok());
+                let mut parts = parts.into_iter().chain(spilled);
+                let mut shard = parts.next().unwrap_or_default();
+                for part in parts {
+                    for (token, mut postings) in part {
+                        match shard.get_mut(&token) {
+                            Some(merged) => {
+                                // Append the shorter list to the longer one, which
+                                // reallocates less; lists are sorted below
+                                if merged.len() < postings.len() {
+                                    std::mem::swap(merged, &mut postings);
+                                }
+                                merged.append(&mut postings);
+                            }
+                            None => { shard.insert(token, postings); }
+                        }
+                    }
+                }
+                for postings in shard.values_mut() {
+                    if !postings.is_sorted_by_key(|p| p.file_id) {
+                        postings.sort_unstable_by_key(|p| p.file_id);
+                    }
+                }
+                if let Some(progress) = progress {
+                    progress.inc(1);
+                }
+                shard
+            }))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap_or_else(|_| {
+            eprintln!("[WARN] Worker thread panicked during content index merge");
+            HashMap::new()
+        })).collect()
+    });
+    log_memory("content-build: after shard merge");
+    let mut index: HashMap<Token, Vec<Posting>> = HashMap::with_capacity(merged.iter().map(HashMap::len).sum());
+    for shard in merged {
+        index.extend(shard.into_iter().map(|(token, postings)| (Token::from(token), postings)));
+    }
+
+    if files.iter().any(String::is_empty) {
+        drop_missing_files(&mut files, &mut file_token_counts, &mut index, &mut comment_markers, &mut ticket_refs);
+        total_tokens = file_token_counts.iter().map(|&c| c as u64).sum();
+    }
 
     let unique_tokens = index.len();
     log_memory(&format!("content-build: after merge ({} tokens)", unique_tokens));
 
-    // Build trigram index from inverted index tokens
-    let trigram = build_trigram_index(&index);
+    let mut index = search::builder::assemble(root, files, file_token_counts, index, comment_markers, ticket_refs);
+    index.total_tokens = total_tokens;
+    index.max_age_secs = max_age_secs;
+    index.extensions = extensions;
     eprintln!(
         "Trigram index: {} trigrams, {} tokens",
-        trigram.trigram_map.len(),
-        trigram.tokens.len()
+        index.trigram.trigram_map.len(),
+        index.trigram.tokens.len()
     );
     log_memory("content-build: after trigram build");
+    index
+}
 
-    let elapsed = start.elapsed();
+/// Remove the files without a path, the ids a resumed build left unused (see
+/// [`Checkpoint`]), with their postings and annotations, and renumber the rest in order.
+fn drop_missing_files(
+    files: &mut Vec<String>,
+    file_token_counts: &mut Vec<u32>,
+    index: &mut HashMap<Token, Vec<Posting>>,
+    comment_markers: &mut Vec<CommentMarker>,
+    ticket_refs: &mut Vec<TicketRef>,
+) {
+    let mut next = 0u32;
+    let new_ids: Vec<Option<u32>> = files.iter()
+        .map(|path| (!path.is_empty()).then(|| { next += 1; next - 1 }))
+        .collect();
+    let renumber = |file_id: &mut u32| match new_ids.get(*file_id as usize).copied().flatten() {
+        Some(id) => { *file_id = id; true }
+        None => false,
+    };
+    index.retain(|_, postings| {
+        postings.retain_mut(|p| renumber(&mut p.file_id));
+        !postings.is_empty()
+    });
+    comment_markers.retain_mut(|m| renumber(&mut m.file_id));
+    ticket_refs.retain_mut(|t| renumber(&mut t.file_id));
+    let mut kept = new_ids.iter().map(Option::is_some);
+    file_token_counts.retain(|_| kept.next().unwrap_or(false));
+    files.retain(|path| !path.is_empty());
+}
 
+/// Build a content index from the files of a git revision instead of the working tree.
+///
+/// Reads blobs with `git ls-tree` + `git cat-file --batch` under `args.dir`, which may be
+/// a subdirectory of the repository. File paths are `<dir>/<path>` exactly as in a
+/// working-tree index, so dir/ext/exclude filters behave the same; `revision` holds the
+/// full commit hash and [`read_indexed_file`] reads line content back from git.
+pub fn build_content_index_at_rev(args: &ContentIndexArgs, rev: &str) -> Result<ContentIndex, SearchError> {
+    let root = fs::canonicalize(&args.dir).map_err(|_| SearchError::DirNotFound(args.dir.clone()))?;
+    let root_str = clean_path(&root.to_string_lossy());
+    let extensions: Vec<String> = args.ext.split(',').map(|s| s.trim().to_lowercase()).collect();
+    let commit = crate::git::resolve_commit(&root_str, rev).map_err(SearchError::Git)?;
+
+    eprintln!("Building content index for {} at {} (extensions: {})...",
+        root_str, &commit[..12.min(commit.len())], extensions.join(", "));
+    let start = Instant::now();
+    let blobs = crate::git::read_tree_blobs(&root_str, &commit, |path| {
+        std::path::Path::new(path).extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+    }).map_err(SearchError::Git)?;
+    let file_data: Vec<(String, String)> = blobs.into_iter()
+        .map(|(path, content)| (format!("{}/{}", root_str, path), content))
+        .collect();
+    let file_count = file_data.len();
+
+    let thread_count = resolve_threads(args.threads, args.build_priority);
+    // Commits are immutable: a revision index never goes stale
+    let mut index = index_file_contents(
+        file_data, root_str, extensions, u64::MAX,
+        TokenizerSettings { min_token_len: args.min_token_len, max_token_len: args.max_token_len },
+        thread_count, args.build_priority,
+    );
+    index.revision = Some(commit);
     eprintln!(
         "Indexed {} files, {} unique tokens ({} total) in {:.3}s",
-        file_count, unique_tokens, total_tokens, elapsed.as_secs_f64()
+        file_count, index.index.len(), index.total_tokens, start.elapsed().as_secs_f64()
     );
+    Ok(index)
+}
 
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or(Duration::ZERO)
-        .as_secs();
-
-    ContentIndex {
-        root: root_str,
-        created_at: now,
-        max_age_secs: args.max_age_hours * 3600,
-        files,
-        index,
-        total_tokens,
-        extensions,
-        file_token_counts,
-        trigram,
-        trigram_dirty: false,
-        forward: None,
-        path_to_id: None,
+/// Load the cached index for `rev` of `dir`, or build and cache it.
+/// `rev` is resolved first, so branch names always map to the commit they point at now.
+pub fn load_or_build_content_index_at_rev(
+    dir: &str,
+    exts: &str,
+    rev: &str,
+    index_base: &std::path::Path,
+) -> Result<ContentIndex, SearchError> {
+    let canonical = fs::canonicalize(dir).map_err(|_| SearchError::DirNotFound(dir.to_string()))?;
+    let commit = crate::git::resolve_commit(&clean_path(&canonical.to_string_lossy()), rev)
+        .map_err(SearchError::Git)?;
+    let path = rev_content_index_path_for(dir, exts, &commit, index_base);
+    if path.exists()
+        && let Ok(index) = load_content_index_file(&path, "rev-content-index") {
+            return Ok(index);
+        }
+    let index = build_content_index_at_rev(&ContentIndexArgs {
+        dir: dir.to_string(), ext: exts.to_string(), max_age_hours: 0,
+        hidden: false, no_ignore: false, threads: 0, min_token_len: DEFAULT_MIN_TOKEN_LEN,
+        dry_run: false,
+        build_priority: BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: 0,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: 0,
+        stop_token_pct: DEFAULT_STOP_TOKEN_PCT,
+        checkpoint_secs: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+    }, &commit)?;
+    fs::create_dir_all(index_base)?;
+    if let Err(e) = save_compressed(&path, &index, "rev-content-index") {
+        eprintln!("[rev-index] Failed to cache {}: {}", path.display(), e);
     }
+    Ok(index)
 }
 
-/// Build a trigram index from the inverted index's token keys.
-pub fn build_trigram_index(inverted: &HashMap<String, Vec<Posting>>) -> TrigramIndex {
-    let mut tokens: Vec<String> = inverted.keys().cloned().collect();
-    tokens.sort();
-
-    let mut trigram_map: HashMap<String, Vec<u32>> = HashMap::new();
-
-    for (idx, token) in tokens.iter().enumerate() {
-        let trigrams = generate_trigrams(token);
-        for trigram in trigrams {
-            trigram_map.entry(trigram).or_default().push(idx as u32);
+/// Read a file listed in `index`: from disk for a working-tree index, from git for a
+/// revision index. Used for showLines/context and phrase verification.
+pub fn read_indexed_file(index: &ContentIndex, path: &str) -> Option<String> {
+    match index.revision {
+        Some(ref commit) => {
+            let relative = path.strip_prefix(index.root.as_str())?.trim_start_matches('/');
+            crate::git::show_file_at(&index.root, commit, relative).ok()
         }
+        None => fs::read_to_string(path_from_string(path)).ok(),
     }
+}
 
-    // Sort and dedup posting lists
-    for list in trigram_map.values_mut() {
-        list.sort();
-        list.dedup();
-    }
+pub use search::builder::build_trigram_index;
 
-    TrigramIndex { tokens, trigram_map }
-}
 #[cfg(test)]
 mod index_tests {
     use std::collections::HashMap;
+    use search::{ContentIndex, SkippedFiles, Token, TokenizerSettings};
     use std::io::Write;
     use search::Posting;
     use crate::index::build_trigram_index;
 
     #[test]
     fn test_build_trigram_index_basic() {
-        let mut inverted: HashMap<String, Vec<Posting>> = HashMap::new();
-        inverted.insert("httpclient".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-        inverted.insert("httphandler".to_string(), vec![Posting { file_id: 1, lines: vec![5] }]);
-        inverted.insert("ab".to_string(), vec![Posting { file_id: 2, lines: vec![10] }]); // too short for trigrams
+        let mut inverted: HashMap<Token, Vec<Posting>> = HashMap::new();
+        inverted.insert("httpclient".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+        inverted.insert("httphandler".into(), vec![Posting { file_id: 1, lines: vec![5] }]);
+        inverted.insert("ab".into(), vec![Posting { file_id: 2, lines: vec![10] }]); // too short for trigrams
 
         let ti = build_trigram_index(&inverted);
 
         // Tokens should be sorted
-        assert_eq!(ti.tokens, vec!["ab", "httpclient", "httphandler"]);
+        assert_eq!(ti.tokens, vec!["ab".into(), "httpclient".into(), "httphandler".into()]);
 
         // "htt" should map to both http tokens
         let htt = ti.trigram_map.get("htt").unwrap();
@@ -958,12 +1802,47 @@ mod index_tests {
 
         // "ab" should not generate any trigrams (too short)
         // but "ab" should still be in tokens list
-        assert!(ti.tokens.contains(&"ab".to_string()));
+        assert!(ti.tokens.contains(&"ab".into()));
+    }
+
+    #[test]
+    fn test_build_dir_blooms_per_top_level_dir() {
+        use search::builder::build_dir_blooms;
+        let files: Vec<String> = ["/r/Services/a.cs", "/r/Services/Deep/b.cs", "/r/Web/c.cs", "/r/root.cs"]
+            .iter().map(|s| s.to_string()).collect();
+        let mut inverted: HashMap<Token, Vec<Posting>> = HashMap::new();
+        inverted.insert("orders".into(), vec![Posting { file_id: 0, lines: vec![1] }, Posting { file_id: 1, lines: vec![2] }]);
+        inverted.insert("page".into(), vec![Posting { file_id: 2, lines: vec![1] }]);
+        inverted.insert("program".into(), vec![Posting { file_id: 3, lines: vec![1] }]);
+
+        let blooms = build_dir_blooms(&inverted, &files, "/r");
+        let mut keys: Vec<&String> = blooms.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["services", "web"], "files directly in the root get no filter");
+        assert!(blooms["services"].may_contain("orders"));
+        assert!(blooms["web"].may_contain("page"));
+        assert!(!blooms["web"].may_contain("orders"));
+
+        let index = ContentIndex {
+            root: "/r".to_string(), created_at: 0, max_age_secs: 3600, files, index: inverted, total_tokens: 4,
+            extensions: vec!["cs".to_string()], file_token_counts: vec![1, 1, 1, 1],
+            trigram: search::TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+            comment_markers: Vec::new(), ticket_refs: Vec::new(), revision: None, dir_blooms: blooms,
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
+        };
+        assert!(index.dir_may_contain("/r/Services/Deep", "orders"));
+        assert!(!index.dir_may_contain("/r/Web", "orders"));
+        assert!(!index.dir_may_contain("/r/Docs", "orders"), "no indexed file under /r/Docs");
+        assert!(index.dir_may_contain("/r", "orders"));
     }
 
     #[test]
     fn test_build_trigram_index_empty() {
-        let inverted: HashMap<String, Vec<Posting>> = HashMap::new();
+        let inverted: HashMap<Token, Vec<Posting>> = HashMap::new();
         let ti = build_trigram_index(&inverted);
         assert!(ti.tokens.is_empty());
         assert!(ti.trigram_map.is_empty());
@@ -971,9 +1850,9 @@ mod index_tests {
 
     #[test]
     fn test_build_trigram_index_sorted_posting_lists() {
-        let mut inverted: HashMap<String, Vec<Posting>> = HashMap::new();
-        inverted.insert("abcdef".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-        inverted.insert("abcxyz".to_string(), vec![Posting { file_id: 1, lines: vec![2] }]);
+        let mut inverted: HashMap<Token, Vec<Posting>> = HashMap::new();
+        inverted.insert("abcdef".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+        inverted.insert("abcxyz".into(), vec![Posting { file_id: 1, lines: vec![2] }]);
 
         let ti = build_trigram_index(&inverted);
 
@@ -987,12 +1866,12 @@ mod index_tests {
 
     #[test]
     fn test_build_trigram_index_single_token() {
-        let mut inverted: HashMap<String, Vec<Posting>> = HashMap::new();
-        inverted.insert("foobar".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
+        let mut inverted: HashMap<Token, Vec<Posting>> = HashMap::new();
+        inverted.insert("foobar".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
 
         let ti = build_trigram_index(&inverted);
 
-        assert_eq!(ti.tokens, vec!["foobar"]);
+        assert_eq!(ti.tokens, vec!["foobar".into()]);
         // "foobar" has 4 trigrams: foo, oob, oba, bar
         assert_eq!(ti.trigram_map.len(), 4);
         assert!(ti.trigram_map.contains_key("foo"));
@@ -1004,9 +1883,9 @@ mod index_tests {
     #[test]
     fn test_build_trigram_index_deduplicates() {
         // Two tokens sharing the same trigram should appear once each in the posting list
-        let mut inverted: HashMap<String, Vec<Posting>> = HashMap::new();
-        inverted.insert("abc".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-        inverted.insert("abcdef".to_string(), vec![Posting { file_id: 1, lines: vec![2] }]);
+        let mut inverted: HashMap<Token, Vec<Posting>> = HashMap::new();
+        inverted.insert("abc".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+        inverted.insert("abcdef".into(), vec![Posting { file_id: 1, lines: vec![2] }]);
 
         let ti = build_trigram_index(&inverted);
 
@@ -1033,9 +1912,58 @@ mod index_tests {
         assert!(loaded.is_ok());
         assert_eq!(data, loaded.unwrap());
 
-        // Verify file starts with LZ4 magic bytes
+        // Verify file starts with the header magic and leaves no temp file behind (only
+        // the write lock file next to it)
         let raw = std::fs::read(&path).unwrap();
-        assert_eq!(&raw[..4], crate::index::LZ4_MAGIC);
+        assert_eq!(&raw[..4], crate::index::INDEX_MAGIC);
+        let names: Vec<String> = std::fs::read_dir(tmp.path()).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        assert_eq!(names.len(), 2, "{:?}", names);
+        assert!(names.contains(&"test.bin.lock".to_string()));
+    }
+
+    #[test]
+    fn test_load_compressed_detects_truncation_and_corruption() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("test.bin");
+        let data: Vec<String> = (0..200).map(|i| format!("token_{}", i)).collect();
+        crate::index::save_compressed(&path, &data, "test").unwrap();
+        let raw = std::fs::read(&path).unwrap();
+
+        // Interrupted write: the tail is missing
+        std::fs::write(&path, &raw[..raw.len() - 10]).unwrap();
+        let err = crate::index::load_compressed::<Vec<String>>(&path, "test").unwrap_err().to_string();
+        assert!(err.contains("truncated"), "got: {}", err);
+
+        // Same length, one flipped bit in the payload
+        let mut flipped = raw.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 0x01;
+        std::fs::write(&path, &flipped).unwrap();
+        let err = crate::index::load_compressed::<Vec<String>>(&path, "test").unwrap_err().to_string();
+        assert!(err.contains("checksum mismatch"), "got: {}", err);
+
+        // Written by a newer format version
+        let mut newer = raw.clone();
+        newer[4..8].copy_from_slice(&(crate::index::INDEX_FORMAT_VERSION + 1).to_le_bytes());
+        std::fs::write(&path, &newer).unwrap();
+        let err = crate::index::load_compressed::<Vec<String>>(&path, "test").unwrap_err().to_string();
+        assert!(err.contains("format version"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_load_compressed_headerless_lz4() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("lz4.bin");
+        let data = vec!["headerless".to_string()];
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        bincode::serialize_into(&mut encoder, &data).unwrap();
+        let mut raw = crate::index::LZ4_MAGIC.to_vec();
+        raw.extend(encoder.finish().unwrap());
+        std::fs::write(&path, &raw).unwrap();
+
+        let loaded: Vec<String> = crate::index::load_compressed(&path, "test").unwrap();
+        assert_eq!(loaded, data);
     }
 
     #[test]
@@ -1145,6 +2073,15 @@ mod index_tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
         let estimate = crate::index::estimate_content_index_memory(&idx);
         assert!(estimate.is_object());
@@ -1158,11 +2095,11 @@ mod index_tests {
     #[test]
     fn test_estimate_content_index_memory_nonempty() {
         let mut index = HashMap::new();
-        index.insert("httpclient".to_string(), vec![
+        index.insert("httpclient".into(), vec![
             Posting { file_id: 0, lines: vec![1, 5, 10] },
             Posting { file_id: 1, lines: vec![3] },
         ]);
-        index.insert("ilogger".to_string(), vec![
+        index.insert("ilogger".into(), vec![
             Posting { file_id: 0, lines: vec![2] },
         ]);
 
@@ -1179,6 +2116,15 @@ mod index_tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
         let estimate = crate::index::estimate_content_index_memory(&idx);
         assert!(estimate.is_object());
@@ -1217,6 +2163,13 @@ mod index_tests {
             extension_methods: std::collections::HashMap::new(),
             selector_index: std::collections::HashMap::new(),
             template_children: std::collections::HashMap::new(),
+            walk_limits: Default::default(),
+            file_imports: std::collections::HashMap::new(),
+            di_registrations: Vec::new(),
+            endpoints: Vec::new(),
+            partial_types: HashMap::new(),
+            doc_comments: HashMap::new(),
+            imports: Vec::new(),
         };
         let estimate = crate::index::estimate_definition_index_memory(&idx);
         assert!(estimate.is_object());
@@ -1245,4 +2198,386 @@ mod index_tests {
             "Compressed ({}) should be smaller than uncompressed ({})",
             compressed_size, uncompressed_size);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_index_file_contents_same_for_any_thread_count() {
+        // Several batches, tokens shared across them, a TODO marker in some files
+        let file_data: Vec<(String, String)> = (0..300).map(|i| (
+            format!("/repo/f{:03}.cs", i),
+            format!("class C{i} {{\n    Shared common{} = new Shared();\n    // TODO: item {i}\n}}\n", i % 7),
+        )).collect();
+        let build = |threads| super::index_file_contents(
+            file_data.clone(), "/repo".to_string(), vec!["cs".to_string()], 3600,
+            TokenizerSettings::default(), threads, crate::priority::BuildPriority::Normal,
+        );
+        let postings = |idx: &ContentIndex| -> std::collections::BTreeMap<String, Vec<(u32, Vec<u32>)>> {
+            idx.index.iter().map(|(t, ps)| (t.to_string(), ps.iter().map(|p| (p.file_id, p.lines.clone())).collect())).collect()
+        };
+
+        let single = build(1);
+        let parallel = build(4);
+        assert_eq!(parallel.files, single.files);
+        assert_eq!(parallel.file_token_counts, single.file_token_counts);
+        assert_eq!(parallel.total_tokens, single.total_tokens);
+        assert_eq!(postings(&parallel), postings(&single));
+        assert_eq!(parallel.comment_markers.iter().map(|m| (m.file_id, m.line)).collect::<Vec<_>>(),
+            single.comment_markers.iter().map(|m| (m.file_id, m.line)).collect::<Vec<_>>());
+
+        assert_eq!(parallel.files[137], "/repo/f137.cs");
+        let shared = &parallel.index["shared"];
+        assert_eq!(shared.len(), 300);
+        assert!(shared.windows(2).all(|w| w[0].file_id < w[1].file_id), "postings sorted by file_id");
+        assert_eq!(parallel.comment_markers.len(), 300);
+    }
+
+    #[test]
+    fn test_build_content_index_spill_matches_in_memory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let spec = search::corpus::CorpusSpec { files: 300, ..Default::default() };
+        search::corpus::generate_corpus(tmp.path(), &spec).unwrap();
+        let args = crate::ContentIndexArgs {
+            dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 2, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        };
+        // Postings keyed by path: walker threads hand out file_ids in any order
+        let by_path = |idx: &ContentIndex| -> std::collections::BTreeMap<String, Vec<(String, Vec<u32>)>> {
+            idx.index.iter().map(|(t, ps)| {
+                let mut list: Vec<(String, Vec<u32>)> = ps.iter().map(|p| (idx.files[p.file_id as usize].clone(), p.lines.clone())).collect();
+                list.sort();
+                (t.to_string(), list)
+            }).collect()
+        };
+
+        let in_memory = crate::build_content_index(&args);
+        // 1 MB over 2 threads spills every few dozen files
+        let spilled = crate::build_content_index(&crate::ContentIndexArgs { spill_mb: 1, ..args });
+        assert_eq!(spilled.files.len(), 300);
+        assert_eq!(spilled.total_tokens, in_memory.total_tokens);
+        assert!(spilled.index.values().all(|ps| ps.windows(2).all(|w| w[0].file_id < w[1].file_id)));
+        assert_eq!(by_path(&spilled), by_path(&in_memory));
+        let prefix = format!("search-spill-{}-", std::process::id());
+        assert!(!std::fs::read_dir(std::env::temp_dir()).unwrap().flatten()
+            .any(|e| e.file_name().to_string_lossy().starts_with(&prefix)), "spill directory removed");
+    }
+
+    #[test]
+    fn test_build_resumes_from_checkpoint() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("src");
+        let spec = search::corpus::CorpusSpec { files: 120, ..Default::default() };
+        search::corpus::generate_corpus(&root, &spec).unwrap();
+        let index_base = tmp.path().join("idx");
+        let args = crate::ContentIndexArgs {
+            dir: root.to_string_lossy().to_string(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 2, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 1,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        };
+        let by_path = |idx: &ContentIndex| -> std::collections::BTreeMap<String, Vec<(String, Vec<u32>)>> {
+            idx.index.iter().map(|(t, ps)| {
+                let mut list: Vec<(String, Vec<u32>)> = ps.iter().map(|p| (idx.files[p.file_id as usize].clone(), p.lines.clone())).collect();
+                list.sort();
+                (t.to_string(), list)
+            }).collect()
+        };
+
+        // A zero interval checkpoints after every file. Dropping every other part
+        // stands in for a build that was interrupted before writing them.
+        super::build_walked(&args, None, &index_base, Some(std::time::Duration::ZERO));
+        let dir = super::checkpoint_path_for(&args.dir, &args.ext, &index_base).join("all");
+        let mut parts: Vec<std::path::PathBuf> = std::fs::read_dir(&dir).unwrap().flatten().map(|e| e.path())
+            .filter(|p| p.file_name().unwrap().to_string_lossy().starts_with("part-"))
+            .collect();
+        parts.sort();
+        assert_eq!(parts.len(), 120);
+        for part in parts.iter().step_by(2) {
+            std::fs::remove_file(part).unwrap();
+        }
+        // Edit a file of a kept part and delete another one
+        let kept: super::CheckpointPart = bincode::deserialize(&std::fs::read(&parts[1]).unwrap()).unwrap();
+        let edited = kept.files[0].1.clone();
+        std::fs::write(&edited, "class Checkpointed { // TODO: resumed\n}\n").unwrap();
+        std::fs::File::options().write(true).open(&edited).unwrap()
+            .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000)).unwrap();
+        let kept: super::CheckpointPart = bincode::deserialize(&std::fs::read(&parts[3]).unwrap()).unwrap();
+        std::fs::remove_file(&kept.files[0].1).unwrap();
+
+        let resumed = super::build_walked(&args, None, &index_base, Some(std::time::Duration::ZERO));
+        // Only the 60 files of the dropped parts and the edited file were read again
+        let parts = std::fs::read_dir(&dir).unwrap().flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with("part-"))
+            .count();
+        assert_eq!(parts, 60 + 61);
+        let fresh = crate::build_content_index(&args);
+        assert_eq!(resumed.files.len(), 119);
+        assert_eq!(resumed.total_tokens, fresh.total_tokens);
+        assert_eq!(by_path(&resumed), by_path(&fresh));
+        assert_eq!(resumed.comment_markers.len(), fresh.comment_markers.len());
+        assert!(resumed.comment_markers.iter().all(|m| (m.file_id as usize) < resumed.files.len()));
+        let checkpointed = &resumed.index["checkpointed"];
+        assert_eq!((checkpointed.len(), resumed.files[checkpointed[0].file_id as usize].as_str()), (1, edited.as_str()));
+
+        assert!(super::remove_checkpoints(&args.dir, &args.ext, &index_base));
+    }
+
+    // ─── Revision index tests ────────────────────────────────────
+
+    fn git(dir: &std::path::Path, args: &[&str]) {
+        let output = std::process::Command::new("git").args(args).current_dir(dir).output().expect("git");
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+
+    /// Repo with two commits: `OldService` in the first, renamed to `NewService` in the second.
+    fn make_renaming_repo() -> tempfile::TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let commit = |msg: &str| git(dir, &["-c", "user.name=Test", "-c", "user.email=t@example.com", "commit", "-qam", msg]);
+        git(dir, &["init", "-q"]);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/service.rs"), "// TODO: rename\nstruct OldService;\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "OldService notes\n").unwrap();
+        git(dir, &["add", "."]);
+        commit("first");
+        std::fs::write(dir.join("src/service.rs"), "struct NewService;\n").unwrap();
+        commit("second");
+        tmp
+    }
+
+    #[test]
+    fn test_build_content_index_at_rev_reads_historical_blobs() {
+        let tmp = make_renaming_repo();
+        let dir = tmp.path().to_string_lossy().to_string();
+        let args = crate::ContentIndexArgs {
+            dir: dir.clone(), ext: "rs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        };
+        let old = super::build_content_index_at_rev(&args, "HEAD~1").unwrap();
+        assert!(old.index.contains_key("oldservice"));
+        assert!(!old.index.contains_key("newservice"));
+        // Extension filter applies to blobs: notes.txt is not indexed
+        assert_eq!(old.files.len(), 1);
+        assert!(old.files[0].ends_with("/src/service.rs"));
+        assert_eq!(old.comment_markers.len(), 1);
+        assert_eq!(old.revision.as_deref().map(str::len), Some(40));
+        assert!(!old.is_stale(&search::SystemClock));
+        // Line content comes from the commit, not the working tree
+        let content = super::read_indexed_file(&old, &old.files[0]).unwrap();
+        assert!(content.contains("OldService"));
+
+        let head = super::build_content_index_at_rev(&args, "HEAD").unwrap();
+        assert!(head.index.contains_key("newservice"));
+        assert!(super::build_content_index_at_rev(&args, "no-such-branch").is_err());
+        assert!(super::build_content_index_at_rev(&args, "--all").is_err());
+    }
+
+    #[test]
+    fn test_build_content_index_applies_repo_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("src/Generated")).unwrap();
+        std::fs::write(dir.join("src/app.cs"), "class App {}\n").unwrap();
+        std::fs::write(dir.join("src/Generated/Proxy.cs"), "class Proxy {}\n").unwrap();
+        std::fs::write(dir.join("src/huge.cs"), format!("class Huge {{}}\n{}", "// x\n".repeat(1000))).unwrap();
+        std::fs::write(dir.join(crate::config::CONFIG_FILE_NAME),
+            "[index]\nexclude = [\"**/Generated/**\"]\nmax-file-size-kb = 2\n").unwrap();
+        let args = crate::ContentIndexArgs {
+            dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        };
+        std::fs::write(dir.join("src/blob.cs"), b"class Blob {}\0\x01\x02").unwrap();
+        let index = super::build_content_index(&args);
+        assert_eq!(index.files.len(), 1, "{:?}", index.files);
+        assert!(index.files[0].ends_with("/src/app.cs"));
+        assert_eq!(index.skipped, SkippedFiles { too_large: 1, binary: 1 });
+        assert_eq!(index.max_file_size, 2048, "the limit is recorded for rebuilds and watcher updates");
+
+        // --max-file-size overrides the config limit
+        let index = super::build_content_index(&crate::ContentIndexArgs { max_file_size: 1 << 20, ..args.clone() });
+        assert_eq!(index.files.len(), 2, "{:?}", index.files);
+        assert_eq!(index.skipped, SkippedFiles { too_large: 0, binary: 1 });
+        assert_eq!(index.max_file_size, 1 << 20);
+
+        // Token length limits drop long tokens and are recorded for queries to check
+        let index = super::build_content_index(&crate::ContentIndexArgs { max_token_len: 4, ..args });
+        assert!(index.index.contains_key("app") && !index.index.contains_key("class"));
+        assert_eq!(index.tokenizer, TokenizerSettings { min_token_len: 2, max_token_len: 4 });
+    }
+
+    #[test]
+    fn test_build_content_index_honors_max_depth_and_prune_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for file in ["top.cs", "src/app.cs", "src/deep/inner.cs", "src/legacy/old.cs", "lib/node_modules/dep.cs", "src/Generated/Proxy.cs"] {
+            std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+            std::fs::write(dir.join(file), "class X {}\n").unwrap();
+        }
+        std::fs::write(dir.join(crate::config::CONFIG_FILE_NAME), "[index]\nexclude = [\"**/Generated/**\"]\n").unwrap();
+        let args = crate::ContentIndexArgs {
+            dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: vec!["node_modules".to_string(), "src\\legacy\\".to_string()],
+            storage: None,
+        };
+        let files = |index: &ContentIndex| {
+            let root = index.root.clone() + "/";
+            let mut files: Vec<String> = index.files.iter().map(|f| f.replace(&root, "")).collect();
+            files.sort();
+            files
+        };
+        // Pruned directories and config excludes both apply
+        let index = super::build_content_index(&args);
+        assert_eq!(files(&index), ["src/app.cs", "src/deep/inner.cs", "top.cs"]);
+        assert_eq!(index.walk_limits.prune_dirs, ["node_modules", "src/legacy"]);
+
+        let index = super::build_content_index(&crate::ContentIndexArgs { max_depth: 2, ..args });
+        assert_eq!(files(&index), ["src/app.cs", "top.cs"]);
+        let root = std::path::Path::new(&index.root);
+        assert!(index.walk_limits.covers(root, &root.join("src/new.cs")));
+        assert!(!index.walk_limits.covers(root, &root.join("src/deep/new.cs")));
+        assert!(!index.walk_limits.covers(root, &root.join("a/node_modules/new.cs")));
+    }
+
+    #[test]
+    fn test_build_content_index_includes_extensionless_scripts_by_language() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::write(dir.join("deploy"), "#!/bin/bash\nkubectl rollout restart\n").unwrap();
+        std::fs::write(dir.join("setup.sh"), "helm install\n").unwrap();
+        std::fs::write(dir.join("LICENSE"), "Permission is hereby granted\n").unwrap();
+        std::fs::write(dir.join("Makefile"), "build:\n\tcargo build\n").unwrap();
+        let index = super::build_content_index(&crate::ContentIndexArgs {
+            dir: dir.to_string_lossy().to_string(), ext: "sh,mk".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        });
+        let mut names: Vec<&str> = index.files.iter().filter_map(|f| f.rsplit('/').next()).collect();
+        names.sort();
+        assert_eq!(names, vec!["Makefile", "deploy", "setup.sh"]);
+        assert!(index.index.contains_key("kubectl"));
+    }
+
+    #[test]
+    fn test_superset_content_index_serves_subset_extensions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let idx_base = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_string_lossy().to_string();
+        std::fs::write(tmp.path().join("a.cs"), "class A {}\n").unwrap();
+        std::fs::write(tmp.path().join("b.sql"), "select 1\n").unwrap();
+        let build = |ext: &str| super::build_content_index(&crate::ContentIndexArgs {
+            dir: dir.clone(), ext: ext.to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        });
+        assert_eq!(super::normalize_exts(" SQL,cs,sql"), "cs,sql");
+        assert_eq!(super::content_index_path_for(&dir, "sql,cs", idx_base.path()),
+            super::content_index_path_for(&dir, "cs,sql", idx_base.path()));
+
+        super::save_content_index(&build("cs"), idx_base.path()).unwrap();
+        super::save_content_index(&build("sql,cs"), idx_base.path()).unwrap();
+        // No "sql" index of its own: the cs,sql one covers it
+        let sql = super::load_content_index(&dir, "sql", idx_base.path()).unwrap();
+        assert_eq!(sql.files.len(), 2);
+        // An exact match still wins over a broader one
+        assert_eq!(super::load_content_index(&dir, "cs", idx_base.path()).unwrap().files.len(), 1);
+        assert_eq!(super::find_content_index_for_dir(&dir, idx_base.path()).unwrap().extensions, ["sql", "cs"]);
+        assert!(super::load_content_index(&dir, "ts", idx_base.path()).is_err(), "nothing covers ts");
+    }
+
+    #[test]
+    fn test_rev_index_cached_per_commit() {
+        let tmp = make_renaming_repo();
+        let dir = tmp.path().to_string_lossy().to_string();
+        let idx_base = tempfile::tempdir().unwrap();
+
+        let first = super::load_or_build_content_index_at_rev(&dir, "rs", "HEAD~1", idx_base.path()).unwrap();
+        let commit = first.revision.clone().unwrap();
+        let cached = super::rev_content_index_path_for(&dir, "rs", &commit, idx_base.path());
+        // Mark the cached copy so a second load can be told apart from a rebuild
+        let mut marked: ContentIndex = super::load_compressed(&cached, "test").unwrap();
+        marked.created_at = 1;
+        super::save_compressed(&cached, &marked, "test").unwrap();
+        let again = super::load_or_build_content_index_at_rev(&dir, "rs", &commit[..10], idx_base.path()).unwrap();
+        assert_eq!(again.created_at, 1, "second load should come from the cache");
+        assert_eq!(again.files, first.files);
+        // Revision indexes never stand in for the working-tree index
+        assert!(super::find_content_index_for_dir(&dir, idx_base.path()).is_none());
+    }
+}
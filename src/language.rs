@@ -0,0 +1,185 @@
+//! Language detection for files without an extension.
+//!
+//! Indexing selects files, and parsers, by extension. Scripts such as `deploy`,
+//! `Makefile` or `Dockerfile` have none, so they are given one here: from a well-known
+//! file name, then from the interpreter on a shebang line, then from the first line of
+//! content. The result is matched against `--ext` like a real extension, so `--ext sh`
+//! picks up `#!/bin/bash` scripts. `[languages]` in `.search-index.toml` overrides
+//! detection for matching paths, including files that do have an extension:
+//!
+//! ```toml
+//! [languages]
+//! "scripts/*" = "sh"     # gitignore-style glob = extension to treat the file as
+//! "build" = "py"
+//! ```
+
+use std::io::Read;
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Bytes read from an extensionless file to look for a shebang or content marker.
+const HEAD_BYTES: usize = 256;
+
+/// Well-known extensionless file names, matched case-insensitively.
+const FILE_NAMES: &[(&str, &str)] = &[
+    ("makefile", "mk"),
+    ("gnumakefile", "mk"),
+    ("dockerfile", "dockerfile"),
+    ("containerfile", "dockerfile"),
+    ("jenkinsfile", "groovy"),
+    ("rakefile", "rb"),
+    ("gemfile", "rb"),
+    ("vagrantfile", "rb"),
+    ("podfile", "rb"),
+];
+
+/// Shebang interpreters; a trailing version (`python3.11`) is ignored.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("sh", "sh"),
+    ("bash", "sh"),
+    ("zsh", "sh"),
+    ("dash", "sh"),
+    ("ksh", "sh"),
+    ("python", "py"),
+    ("node", "js"),
+    ("nodejs", "js"),
+    ("bun", "js"),
+    ("deno", "ts"),
+    ("ts-node", "ts"),
+    ("tsx", "ts"),
+    ("pwsh", "ps1"),
+    ("powershell", "ps1"),
+    ("perl", "pl"),
+    ("ruby", "rb"),
+    ("php", "php"),
+    ("dotnet-script", "cs"),
+];
+
+/// Per-path language overrides from `.search-index.toml`, applied before detection.
+#[derive(Default)]
+pub struct LanguageMap {
+    /// Glob matcher and extension, in config order; the first match wins.
+    rules: Vec<(Gitignore, String)>,
+}
+
+impl LanguageMap {
+    /// Matchers for `overrides` (glob, extension), rooted at `root`. Invalid globs are
+    /// skipped with a warning.
+    pub fn new(root: &Path, overrides: &[(String, String)]) -> Self {
+        let rules = overrides.iter().filter_map(|(glob, ext)| {
+            let mut builder = GitignoreBuilder::new(root);
+            if let Err(e) = builder.add_line(None, glob) {
+                eprintln!("[config] Warning: invalid languages glob '{}': {}", glob, e);
+                return None;
+            }
+            builder.build().ok().map(|gi| (gi, ext.trim_start_matches('.').to_lowercase()))
+        }).collect();
+        LanguageMap { rules }
+    }
+
+    /// The extension `path` is indexed and parsed as: an override, the file's own
+    /// extension (lowercased), or [`detect_language`] for extensionless files.
+    pub fn language(&self, path: &Path) -> Option<String> {
+        let overridden = self.rules.iter().find(|(gi, _)| {
+            path.strip_prefix(gi.path()).is_ok_and(|rel| gi.matched_path_or_any_parents(rel, false).is_ignore())
+        });
+        if let Some((_, ext)) = overridden {
+            return Some(ext.clone());
+        }
+        file_language(path)
+    }
+
+    /// Whether `path` is indexed under `extensions` (lowercase).
+    pub fn matches(&self, path: &Path, extensions: &[String]) -> bool {
+        self.language(path).is_some_and(|lang| extensions.contains(&lang))
+    }
+}
+
+/// The file's extension (lowercased), or [`detect_language`] when it has none. No
+/// config overrides; for the watcher, which re-checks single files.
+pub fn file_language(path: &Path) -> Option<String> {
+    match path.extension() {
+        Some(ext) => ext.to_str().map(str::to_lowercase),
+        None => detect_language(path).map(str::to_string),
+    }
+}
+
+/// Extension for an extensionless file, from its name or its first bytes. `None` when
+/// nothing matches or the file cannot be read.
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    if let Some(&(_, ext)) = FILE_NAMES.iter().find(|(n, _)| *n == name) {
+        return Some(ext);
+    }
+    let mut head = Vec::with_capacity(HEAD_BYTES);
+    std::fs::File::open(path).ok()?.take(HEAD_BYTES as u64).read_to_end(&mut head).ok()?;
+    detect_from_content(&String::from_utf8_lossy(&head))
+}
+
+/// Extension from a shebang interpreter or a leading content marker.
+fn detect_from_content(head: &str) -> Option<&'static str> {
+    let first = head.lines().next()?.trim();
+    if let Some(shebang) = first.strip_prefix("#!") {
+        return shebang_interpreter(shebang).and_then(|name| {
+            let base = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+            INTERPRETERS.iter().find(|(n, _)| *n == base).map(|&(_, ext)| ext)
+        });
+    }
+    if first.starts_with("<?php") {
+        return Some("php");
+    }
+    if first.starts_with("<?xml") {
+        return Some("xml");
+    }
+    // A make rule: `target: deps` followed by a tab-indented recipe
+    let mut lines = head.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#'));
+    if let (Some(rule), Some(recipe)) = (lines.next(), lines.next())
+        && rule.split_once(':').is_some_and(|(target, rest)| !target.is_empty() && !target.contains(char::is_whitespace) && !rest.starts_with('='))
+        && recipe.starts_with('\t') {
+        return Some("mk");
+    }
+    None
+}
+
+/// Interpreter named by a shebang: the program's file name, or the first argument of
+/// `env` that is not an option (`#!/usr/bin/env -S deno run`).
+fn shebang_interpreter(shebang: &str) -> Option<&str> {
+    let mut words = shebang.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program != "env" {
+        return Some(program);
+    }
+    words.find(|w| !w.starts_with('-') && !w.contains('='))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language_from_names_shebangs_and_overrides() {
+        let tmp = tempfile::tempdir().unwrap();
+        let write = |name: &str, text: &str| {
+            let path = tmp.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, text).unwrap();
+            path
+        };
+        assert_eq!(detect_language(&write("Makefile", "")), Some("mk"));
+        assert_eq!(detect_language(&write("deploy", "#!/bin/bash\nset -e\n")), Some("sh"));
+        assert_eq!(detect_language(&write("tool", "#!/usr/bin/env python3.11\n")), Some("py"));
+        assert_eq!(detect_language(&write("serve", "#!/usr/bin/env -S deno run --allow-net\n")), Some("ts"));
+        assert_eq!(detect_language(&write("rules", "build: src\n\tcargo build\n")), Some("mk"));
+        assert_eq!(detect_language(&write("README", "Just text.\n")), None);
+        assert_eq!(detect_language(&tmp.path().join("missing")), None);
+
+        let plain = write("scripts/run", "echo hi\n");
+        let with_ext = write("scripts/old.txt", "echo hi\n");
+        let map = LanguageMap::new(tmp.path(), &[("scripts/*".to_string(), "sh".to_string())]);
+        assert_eq!(map.language(&plain).as_deref(), Some("sh"));
+        assert_eq!(map.language(&with_ext).as_deref(), Some("sh"), "overrides apply to extensions too");
+        assert!(map.matches(&write("src/a.CS", ""), &["cs".to_string()]));
+        assert_eq!(LanguageMap::default().language(&plain), None);
+    }
+}
@@ -6,18 +6,47 @@
 //! ## Library usage
 //!
 //! This crate is primarily a CLI tool / MCP server, but core types and functions
-//! are exposed as a library for benchmarking and integration testing.
+//! are exposed as a library for benchmarking and integration testing. Other programs
+//! can embed the content search: [`IndexBuilder`] indexes a directory and
+//! [`Searcher::grep`] runs [`GrepQuery`]s against it; async services use their
+//! non-blocking variants in [`blocking`]. A definition index built by `search
+//! def-index` is loaded and queried with [`definition_index`].
 
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
+pub mod blocking;
+pub mod builder;
+pub mod clock;
+pub mod corpus;
+pub mod definition_index;
+pub mod lock_stats;
+pub mod metrics;
+pub mod query;
+pub mod schema;
+pub mod searcher;
+pub mod storage;
+
+pub use blocking::{spawn_blocking, BlockingTask};
+pub use builder::IndexBuilder;
+pub use clock::{Clock, SystemClock};
+pub use query::{FileMatch, GrepQuery, MatchMode};
+pub use searcher::Searcher;
+
 /// Default minimum token length used for indexing and search.
 /// Tokens shorter than this are discarded during tokenization.
 pub const DEFAULT_MIN_TOKEN_LEN: usize = 2;
 
+/// Default `--stop-token-pct`: a token in more than half of the files is a stop token.
+pub const DEFAULT_STOP_TOKEN_PCT: u8 = 50;
+
+/// Files an index needs before any of its tokens counts as a stop token: in a small
+/// tree, a token's share of files says little about it.
+pub const STOP_TOKEN_MIN_FILES: usize = 100;
+
 // ─── Stable hashing ─────────────────────────────────────────────────
 
 /// Stable FNV-1a hash (deterministic across Rust versions, unlike `DefaultHasher`).
@@ -47,6 +76,131 @@ pub fn clean_path(p: &str) -> String {
     p.strip_prefix(r"\\?\").unwrap_or(p).replace('\\', "/")
 }
 
+/// `path` relative to `root`, both with `/` separators; `path` itself when it is not
+/// under `root`. Only whole segments match: `/repository/x` is not under `/repo`.
+#[must_use]
+pub fn relative_path<'a>(root: &str, path: &'a str) -> &'a str {
+    let root = root.trim_end_matches('/');
+    match path.strip_prefix(root) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest.trim_start_matches('/'),
+        _ => path,
+    }
+}
+
+// ─── Lossless path strings ───────────────────────────────────────────
+//
+// Index files, JSON responses and git output all carry paths as Rust `String`s.
+// A path that is not valid Unicode (non-UTF-8 bytes on Unix, unpaired UTF-16
+// surrogates on Windows) would lose data through `to_string_lossy`, leaving a
+// U+FFFD path that can no longer be opened. Instead, each offending byte or
+// surrogate is mapped to one character in Supplementary Private Use Area-B, so the
+// string stays valid UTF-8 (and valid JSON) and converts back to the exact path.
+
+/// Invalid byte `b` (0x80..=0xFF) is stored as `PATH_BYTE_ESCAPE + b`.
+const PATH_BYTE_ESCAPE: u32 = 0x10_FE00;
+/// Unpaired surrogate `s` (0xD800..=0xDFFF) is stored as `PATH_SURROGATE_ESCAPE + (s - 0xD800)`.
+const PATH_SURROGATE_ESCAPE: u32 = 0x10_F000;
+
+/// Bytes as UTF-8, escaping invalid sequences byte by byte (see [`path_to_string`]).
+/// Used for git output, whose paths and author names are raw bytes.
+#[must_use]
+pub fn bytes_to_string_escaped(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        out.push_str(chunk.valid());
+        for &b in chunk.invalid() {
+            out.extend(char::from_u32(PATH_BYTE_ESCAPE + b as u32));
+        }
+    }
+    out
+}
+
+/// A path as a `String` without data loss. Valid Unicode paths are returned
+/// unchanged; anything else is escaped reversibly (see [`path_from_string`]).
+#[must_use]
+pub fn path_to_string(path: &std::path::Path) -> String {
+    if let Some(s) = path.to_str() {
+        return s.to_string();
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        bytes_to_string_escaped(path.as_os_str().as_bytes())
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        char::decode_utf16(path.as_os_str().encode_wide())
+            .map(|r| r.unwrap_or_else(|e| {
+                char::from_u32(PATH_SURROGATE_ESCAPE + (e.unpaired_surrogate() as u32 - 0xD800)).unwrap_or('\u{FFFD}')
+            }))
+            .collect()
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        path.to_string_lossy().into_owned()
+    }
+}
+
+/// Inverse of [`path_to_string`]: the exact path a stored string refers to.
+#[must_use]
+pub fn path_from_string(s: &str) -> std::path::PathBuf {
+    let escaped = |c: char| (PATH_SURROGATE_ESCAPE..=PATH_BYTE_ESCAPE + 0xFF).contains(&(c as u32));
+    if !s.chars().any(escaped) {
+        return std::path::PathBuf::from(s);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        let mut bytes = Vec::with_capacity(s.len());
+        for c in s.chars() {
+            match (c as u32).checked_sub(PATH_BYTE_ESCAPE) {
+                Some(b @ 0x80..=0xFF) => bytes.push(b as u8),
+                _ => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        std::path::PathBuf::from(std::ffi::OsString::from_vec(bytes))
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        let mut wide = Vec::with_capacity(s.len());
+        for c in s.chars() {
+            match (c as u32).checked_sub(PATH_SURROGATE_ESCAPE) {
+                Some(offset @ 0..=0x7FF) => wide.push((0xD800 + offset) as u16),
+                _ => wide.extend_from_slice(c.encode_utf16(&mut [0; 2])),
+            }
+        }
+        std::path::PathBuf::from(std::ffi::OsString::from_wide(&wide))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        std::path::PathBuf::from(s)
+    }
+}
+
+/// Escape every non-ASCII character in serialized JSON as `\uXXXX` (surrogate pairs
+/// above U+FFFF), so the output survives clients that decode stdout in a legacy
+/// code page. Outside string literals JSON text is pure ASCII, so escaping every
+/// non-ASCII character is always valid.
+#[must_use]
+pub fn escape_non_ascii_json(json: &str) -> String {
+    if json.is_ascii() {
+        return json.to_string();
+    }
+    let mut out = String::with_capacity(json.len() + json.len() / 4);
+    for c in json.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                out.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    out
+}
+
 // ─── Index file naming ───────────────────────────────────────────────
 
 /// Windows reserved device names that cannot be used as filenames.
@@ -169,6 +323,16 @@ pub fn extract_semantic_prefix(canonical: &std::path::Path) -> String {
     }
 }
 
+/// How much of a file [`looks_binary`] inspects (git uses the same amount).
+pub const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// Whether decoded file content is binary: a NUL byte within the first
+/// [`BINARY_SNIFF_BYTES`]. Run it on [`read_file_lossy`] output, which has already
+/// decoded UTF-16 files with a BOM, so their zero high bytes don't count.
+pub fn looks_binary(content: &str) -> bool {
+    content.as_bytes().iter().take(BINARY_SNIFF_BYTES).any(|&b| b == 0)
+}
+
 /// Read a file as a String, handling BOM-detected encodings and lossy UTF-8 fallback.
 ///
 /// Encoding detection order:
@@ -179,9 +343,13 @@ pub fn extract_semantic_prefix(canonical: &std::path::Path) -> String {
 ///
 /// Returns `(content, was_lossy)` where `was_lossy` is true if replacement characters
 /// were inserted during lossy UTF-8 conversion. Files successfully decoded via BOM
-/// (UTF-16LE/BE/UTF-8 BOM) return `was_lossy = false`.
+/// (UTF-16LE/BE/UTF-8 BOM) return `was_lossy = false`. Escaped paths from
+/// [`path_to_string`] are opened as the original path.
 pub fn read_file_lossy(path: &std::path::Path) -> std::io::Result<(String, bool)> {
-    let raw = std::fs::read(path)?;
+    let raw = match path.to_str() {
+        Some(s) => std::fs::read(path_from_string(s))?,
+        None => std::fs::read(path)?,
+    };
 
     // UTF-16LE BOM: FF FE
     if raw.len() >= 2 && raw[0] == 0xFF && raw[1] == 0xFE {
@@ -251,13 +419,9 @@ pub struct FileIndex {
 }
 
 impl FileIndex {
-    /// Check if the index is older than its configured max age.
-    pub fn is_stale(&self) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or(std::time::Duration::ZERO)
-            .as_secs();
-        now.saturating_sub(self.created_at) > self.max_age_secs
+    /// Check if the index is older than its configured max age at `clock`'s time.
+    pub fn is_stale(&self, clock: &dyn Clock) -> bool {
+        clock::is_stale(self.created_at, self.max_age_secs, clock.now_secs())
     }
 }
 
@@ -270,16 +434,107 @@ pub struct Posting {
     pub lines: Vec<u32>,
 }
 
+/// A TODO/FIXME/HACK/BUG annotation found in a comment while building the content index.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommentMarker {
+    pub file_id: u32,
+    /// 1-based line number
+    pub line: u32,
+    /// One of [`COMMENT_MARKERS`]
+    pub marker: String,
+    /// Comment text after the marker (separators stripped, at most 200 chars)
+    pub text: String,
+}
+
+/// An issue-tracker reference (`JIRA-1234`, `#5678`) found in a comment while
+/// building the content index.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TicketRef {
+    pub file_id: u32,
+    /// 1-based line number
+    pub line: u32,
+    /// Canonical ticket ID (see [`normalize_ticket`])
+    pub ticket: String,
+}
+
+/// Annotation keywords recorded in [`ContentIndex::comment_markers`]. Matched
+/// upper-case and as whole words only, so `todoList` or `Debug` never count.
+pub const COMMENT_MARKERS: [&str; 4] = ["TODO", "FIXME", "HACK", "BUG"];
+
+/// An indexed token. The string is allocated once and shared: the inverted index key
+/// and the trigram token table hold the same `Arc`, so a token costs one heap string
+/// however many tables list it. Serialized as a plain string, so index files are
+/// unchanged; [`ContentIndex::share_token_strings`] re-links the tables after a load.
+pub type Token = Arc<str>;
+
 /// Trigram index for substring search.
 /// Maps 3-character sequences to tokens containing them.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct TrigramIndex {
-    /// All unique tokens from the inverted index, sorted alphabetically.
-    pub tokens: Vec<String>,
+    /// All unique tokens from the inverted index, sorted alphabetically. A token's
+    /// position is its u32 id in `trigram_map`.
+    pub tokens: Vec<Token>,
     /// Trigram → sorted vec of token indices (into `tokens` vec).
     pub trigram_map: HashMap<String, Vec<u32>>,
 }
 
+/// Trigram similarity (Dice coefficient) a token needs to be suggested for a term
+const MIN_TRIGRAM_SIMILARITY: f64 = 0.4;
+
+impl TrigramIndex {
+    /// Tokens close to `term`, closest first: at most `limit` of those sharing at least
+    /// [`MIN_TRIGRAM_SIMILARITY`] of their trigrams with it (Dice coefficient), ranked
+    /// by edit distance and then by that similarity. Candidates come from the posting
+    /// lists of the term's own trigrams, so the whole token list is never scanned.
+    /// Empty for terms shorter than 3 characters.
+    pub fn similar_tokens(&self, term: &str, limit: usize) -> Vec<&Token> {
+        let trigrams = generate_trigrams(term);
+        let mut shared: HashMap<u32, usize> = HashMap::new();
+        for trigram in trigrams.iter().collect::<std::collections::HashSet<_>>() {
+            for &id in self.trigram_map.get(trigram).map_or(&[][..], Vec::as_slice) {
+                *shared.entry(id).or_default() += 1;
+            }
+        }
+        let mut similar: Vec<(usize, f64, &Token)> = shared.into_iter()
+            .filter_map(|(id, shared)| {
+                let token = self.tokens.get(id as usize)?;
+                let token_trigrams = token.chars().count().saturating_sub(2);
+                let similarity = 2.0 * shared as f64 / (trigrams.len() + token_trigrams) as f64;
+                if similarity < MIN_TRIGRAM_SIMILARITY || &**token == term {
+                    return None;
+                }
+                Some((edit_distance_within(term, token, usize::MAX)?, similarity, token))
+            })
+            .collect();
+        similar.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.total_cmp(&a.1)).then_with(|| a.2.cmp(b.2)));
+        similar.into_iter().take(limit).map(|(_, _, token)| token).collect()
+    }
+}
+
+/// Levenshtein distance between `a` and `b` (in chars) if it is at most `max`; stops
+/// early once every alignment is further apart.
+pub fn edit_distance_within(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            current[j + 1] = (previous[j] + usize::from(ca != cb))
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        if current.iter().min().is_some_and(|&d| d > max) {
+            return None;
+        }
+        previous = current;
+    }
+    Some(previous[b.len()]).filter(|&d| d <= max)
+}
+
 /// Generate trigrams (3-char sliding windows) from a token.
 /// Uses char-based windows for Unicode correctness.
 /// Returns empty vec for tokens shorter than 3 chars.
@@ -294,11 +549,77 @@ pub fn generate_trigrams(token: &str) -> Vec<String> {
         .collect()
 }
 
+/// Bloom filter over the tokens of one directory subtree.
+///
+/// `may_contain` never returns `false` for an inserted token; it returns `true` for
+/// about 1% of other tokens when filled to the capacity it was sized for. Hashing uses
+/// [`stable_hash`], so filters saved to disk stay valid across Rust versions.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TokenBloom {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl TokenBloom {
+    /// Bits per expected token; with 7 hashes gives a ~1% false-positive rate.
+    const BITS_PER_TOKEN: usize = 10;
+    const HASHES: u32 = 7;
+
+    /// Empty filter sized for `tokens` distinct tokens (at least 64).
+    pub fn with_capacity(tokens: usize) -> Self {
+        let words = (tokens.max(64) * Self::BITS_PER_TOKEN).div_ceil(64);
+        TokenBloom { bits: vec![0; words], hashes: Self::HASHES }
+    }
+
+    pub fn insert(&mut self, token: &str) {
+        let len = self.bits.len() as u64 * 64;
+        if len == 0 {
+            return;
+        }
+        for bit in Self::bit_positions(token, self.hashes, len) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// `false` only if `token` was never inserted.
+    pub fn may_contain(&self, token: &str) -> bool {
+        let len = self.bits.len() as u64 * 64;
+        len == 0 || Self::bit_positions(token, self.hashes, len)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Double hashing (Kirsch–Mitzenmacher): bit i = h1 + i * h2 (mod len).
+    fn bit_positions(token: &str, hashes: u32, len: u64) -> impl Iterator<Item = u64> {
+        let h = stable_hash(&[token.as_bytes()]);
+        let h2 = h.rotate_left(32) | 1;
+        (0..hashes as u64).map(move |i| h.wrapping_add(i.wrapping_mul(h2)) % len)
+    }
+}
+
+/// Key of the top-level directory `path` is in, relative to `root`: its first path
+/// component, lowercased. `None` for `root` itself and, unless `is_dir`, for files
+/// directly in `root`.
+#[must_use]
+pub fn top_level_dir(root: &str, path: &str, is_dir: bool) -> Option<String> {
+    let root = root.replace('\\', "/").to_lowercase();
+    let path = path.replace('\\', "/").to_lowercase();
+    let rest = path.strip_prefix(root.trim_end_matches('/'))?.strip_prefix('/')?;
+    match rest.split_once('/') {
+        Some((first, _)) if !first.is_empty() => Some(first.to_string()),
+        None if is_dir && !rest.is_empty() => Some(rest.to_string()),
+        _ => None,
+    }
+}
+
+/// Capacity of a filter created by the watcher for a new top-level directory.
+/// Overfilling only raises the false-positive rate; a rebuild resizes it.
+const NEW_DIR_BLOOM_TOKENS: usize = 16 * 1024;
+
 /// Inverted index: token → list of postings.
 ///
 /// The core data structure for content search. Maps every token
 /// to the files and line numbers where it appears.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ContentIndex {
     pub root: String,
     pub created_at: u64,
@@ -306,7 +627,7 @@ pub struct ContentIndex {
     /// file_id → file path
     pub files: Vec<String>,
     /// token (lowercased) → postings
-    pub index: HashMap<String, Vec<Posting>>,
+    pub index: HashMap<Token, Vec<Posting>>,
     /// total tokens indexed
     pub total_tokens: u64,
     /// extensions that were indexed
@@ -328,16 +649,234 @@ pub struct ContentIndex {
     /// Path → file_id lookup (populated with --watch)
     #[serde(default)]
     pub path_to_id: Option<HashMap<PathBuf, u32>>,
+    /// Marker comments (see [`COMMENT_MARKERS`]), ordered by file_id then line
+    #[serde(default)]
+    pub comment_markers: Vec<CommentMarker>,
+    /// Ticket references in comments (see [`extract_ticket_refs`]), ordered by file_id then line
+    #[serde(default)]
+    pub ticket_refs: Vec<TicketRef>,
+    /// Commit hash the index was built from (`search grep --rev`); None = working tree
+    #[serde(default)]
+    pub revision: Option<String>,
+    /// Top-level directory (see [`top_level_dir`]) → bloom filter of its tokens, so a
+    /// query scoped to one directory can skip terms that can't occur there. Empty in
+    /// indexes built before the filters existed, which disables the check.
+    #[serde(default)]
+    pub dir_blooms: HashMap<String, TokenBloom>,
+    /// Files the build left out: over the size limit or binary
+    #[serde(default)]
+    pub skipped: SkippedFiles,
+    /// Token length limits the index was built with
+    #[serde(default)]
+    pub tokenizer: TokenizerSettings,
+    /// Depth and directory limits of the walk the index was built from
+    #[serde(default)]
+    pub walk_limits: WalkLimits,
+    /// Share of files, in percent, above which a token is a stop token (see
+    /// [`ContentIndex::is_stop_token`]); 0 = none, as in indexes built before the cutoff
+    #[serde(default)]
+    pub stop_token_pct: u8,
+    /// Size limit in bytes the build skipped larger files by (see [`SkippedFiles`]);
+    /// 0 = none. Rebuilds and watcher updates hold files to the same limit.
+    #[serde(default)]
+    pub max_file_size: u64,
+}
+
+/// Counts of files with a watched extension that the content build did not index.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SkippedFiles {
+    /// Larger than `--max-file-size`
+    pub too_large: usize,
+    /// Content with a NUL byte (see [`looks_binary`])
+    pub binary: usize,
+}
+
+/// Token length limits of a content index. Tokens outside them were never indexed,
+/// so a query term outside them cannot match through the index.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenizerSettings {
+    /// Shortest token indexed, in bytes
+    pub min_token_len: usize,
+    /// Longest token indexed, in bytes; 0 = no limit
+    pub max_token_len: usize,
+}
+
+impl Default for TokenizerSettings {
+    fn default() -> Self {
+        TokenizerSettings { min_token_len: DEFAULT_MIN_TOKEN_LEN, max_token_len: 0 }
+    }
+}
+
+/// The part of the tree an index build walks (`--max-depth`, `--prune-dir`). Stored in
+/// the index, so rebuilds and the watcher keep to the same part.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalkLimits {
+    /// Deepest level walked, counting files directly in the root as depth 1; 0 = no limit
+    pub max_depth: usize,
+    /// Directories skipped with everything below them, each a directory name or a path
+    /// relative to the root, with forward slashes; compared ignoring ASCII case
+    pub prune_dirs: Vec<String>,
+}
+
+impl WalkLimits {
+    pub fn new(max_depth: usize, prune_dirs: &[String]) -> Self {
+        let prune_dirs = prune_dirs.iter()
+            .map(|d| d.replace('\\', "/").trim_matches('/').to_string())
+            .filter(|d| !d.is_empty())
+            .collect();
+        WalkLimits { max_depth, prune_dirs }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_depth == 0 && self.prune_dirs.is_empty()
+    }
+
+    /// Limit a walk rooted at `root` to these bounds.
+    pub fn apply_to_walk(&self, builder: &mut ignore::WalkBuilder, root: &Path) {
+        if self.max_depth > 0 {
+            builder.max_depth(Some(self.max_depth));
+        }
+        if !self.prune_dirs.is_empty() {
+            let (root, limits) = (root.to_path_buf(), self.clone());
+            builder.filter_entry(move |entry| {
+                !(entry.file_type().is_some_and(|t| t.is_dir()) && limits.prunes(&root, entry.path()))
+            });
+        }
+    }
+
+    /// Whether the walk skips directory `dir` below `root`.
+    pub fn prunes(&self, root: &Path, dir: &Path) -> bool {
+        !self.prune_dirs.is_empty()
+            && dir.strip_prefix(root).is_ok_and(|rel| self.prunes_relative(&rel.to_string_lossy().replace('\\', "/")))
+    }
+
+    /// Whether the walk reaches file `path` below `root`.
+    pub fn covers(&self, root: &Path, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(root) else { return true };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        let parts: Vec<&str> = rel.split('/').filter(|p| !p.is_empty()).collect();
+        if self.max_depth > 0 && parts.len() > self.max_depth {
+            return false;
+        }
+        (1..parts.len()).all(|n| !self.prunes_relative(&parts[..n].join("/")))
+    }
+
+    fn prunes_relative(&self, rel: &str) -> bool {
+        let name = rel.rsplit('/').next().unwrap_or(rel);
+        self.prune_dirs.iter().any(|p| p.eq_ignore_ascii_case(name) || p.eq_ignore_ascii_case(rel))
+    }
+}
+
+impl TokenizerSettings {
+    /// Tokens of `line` within the limits.
+    #[must_use]
+    pub fn tokenize(&self, line: &str) -> Vec<String> {
+        let mut tokens = tokenize(line, self.min_token_len);
+        if self.max_token_len > 0 {
+            tokens.retain(|t| t.len() <= self.max_token_len);
+        }
+        tokens
+    }
+
+    /// Why the index cannot answer a search for `term` (lowercased), or `None` when it
+    /// can. An exact term must be a whole indexed token; a substring term only has to
+    /// fit inside one, so it may be shorter than the minimum.
+    #[must_use]
+    pub fn unanswerable(&self, term: &str, substring: bool) -> Option<String> {
+        if term.chars().any(|c| !c.is_alphanumeric() && c != '_') {
+            Some(format!("'{}' contains characters the tokenizer splits on", term))
+        } else if !substring && term.len() < self.min_token_len {
+            Some(format!("'{}' is shorter than the index's minimum token length ({})", term, self.min_token_len))
+        } else if self.max_token_len > 0 && term.len() > self.max_token_len {
+            Some(format!("'{}' is longer than the index's maximum token length ({})", term, self.max_token_len))
+        } else {
+            None
+        }
+    }
 }
 
 impl ContentIndex {
-    /// Check if the index is older than its configured max age.
-    pub fn is_stale(&self) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or(std::time::Duration::ZERO)
-            .as_secs();
-        now.saturating_sub(self.created_at) > self.max_age_secs
+    /// Whether `token` may occur in a file under `dir` (the root or a directory below
+    /// it). `false` only when the directory's bloom filter rules the token out, or
+    /// when the index has filters but no file in that top-level directory.
+    pub fn dir_may_contain(&self, dir: &str, token: &str) -> bool {
+        if self.dir_blooms.is_empty() {
+            return true;
+        }
+        match top_level_dir(&self.root, dir, true) {
+            Some(key) => self.dir_blooms.get(&key).is_some_and(|b| b.may_contain(token)),
+            None => true,
+        }
+    }
+
+    /// Add the tokens of the file at `path` to its top-level directory's filter
+    /// (watcher updates). No-op for indexes without filters.
+    pub fn add_to_dir_bloom<'a>(&mut self, path: &str, tokens: impl IntoIterator<Item = &'a str>) {
+        if self.dir_blooms.is_empty() {
+            return;
+        }
+        let Some(key) = top_level_dir(&self.root, path, false) else { return };
+        let bloom = self.dir_blooms.entry(key).or_insert_with(|| TokenBloom::with_capacity(NEW_DIR_BLOOM_TOKENS));
+        for token in tokens {
+            bloom.insert(token);
+        }
+    }
+
+    /// Check if the index is older than its configured max age at `clock`'s time.
+    pub fn is_stale(&self, clock: &dyn Clock) -> bool {
+        clock::is_stale(self.created_at, self.max_age_secs, clock.now_secs())
+    }
+
+    /// Number of files a token must exceed to be a stop token; `None` when the index
+    /// has no cutoff or fewer than [`STOP_TOKEN_MIN_FILES`] files.
+    pub fn stop_token_doc_freq(&self) -> Option<usize> {
+        (self.stop_token_pct > 0 && self.files.len() >= STOP_TOKEN_MIN_FILES)
+            .then(|| self.files.len() * self.stop_token_pct as usize / 100)
+    }
+
+    /// Whether `token` occurs in more than `stop_token_pct` percent of the files, like
+    /// `public` or `var`. Such tokens match most of the tree and barely move a score.
+    pub fn is_stop_token(&self, token: &str) -> bool {
+        self.stop_token_doc_freq()
+            .is_some_and(|cutoff| self.index.get(token).is_some_and(|postings| postings.len() > cutoff))
+    }
+
+    /// The `limit` tokens found in the most files, with their file counts; ties by token.
+    pub fn top_tokens(&self, limit: usize) -> Vec<(&Token, usize)> {
+        let mut tokens: Vec<(&Token, usize)> = self.index.iter().map(|(token, postings)| (token, postings.len())).collect();
+        tokens.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        tokens.truncate(limit);
+        tokens
+    }
+
+    /// "Did you mean" tokens for a lowercased grep term the index does not hold: the
+    /// most similar tokens by [`TrigramIndex::similar_tokens`]. Empty for known terms.
+    pub fn spelling_suggestions(&self, term: &str, limit: usize) -> Vec<&Token> {
+        if self.index.contains_key(term) {
+            return Vec::new();
+        }
+        // The trigram index lags behind watcher updates until it is rebuilt
+        self.trigram.similar_tokens(term, limit).into_iter()
+            .filter(|token| self.index.contains_key(&***token))
+            .collect()
+    }
+
+    /// Point every trigram token at the inverted index's copy of the same string.
+    ///
+    /// Deserialization allocates each occurrence of a token separately, so a freshly
+    /// loaded index holds every token twice; this drops the trigram copies. Returns the
+    /// number of tokens re-linked.
+    pub fn share_token_strings(&mut self) -> usize {
+        let mut shared = 0;
+        for token in &mut self.trigram.tokens {
+            if let Some((key, _)) = self.index.get_key_value(&**token)
+                && !Arc::ptr_eq(key, token)
+            {
+                *token = Arc::clone(key);
+                shared += 1;
+            }
+        }
+        shared
     }
 
     /// Pre-warm the trigram index by touching all data structures.
@@ -414,6 +953,165 @@ pub fn tokenize(line: &str, min_len: usize) -> Vec<String> {
         .collect()
 }
 
+const MAX_MARKER_TEXT_CHARS: usize = 200;
+
+/// Find TODO/FIXME/HACK/BUG annotations in comments.
+///
+/// A marker counts only as the first word of a comment (after `//`, `#`, `/*`, `--`,
+/// `<!--`, or a leading `*` inside a block comment), so string literals, identifiers
+/// like `TODO_LIMIT`, and prose that merely mentions a marker are ignored.
+/// Returns (1-based line, marker, text).
+///
+/// # Examples
+///
+/// ```
+/// use search::extract_comment_markers;
+///
+/// let found = extract_comment_markers("let x = 1; // TODO: remove once v2 ships\n");
+/// assert_eq!(found, vec![(1, "TODO", "remove once v2 ships".to_string())]);
+/// ```
+#[must_use]
+pub fn extract_comment_markers(content: &str) -> Vec<(u32, &'static str, String)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut found = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if !COMMENT_MARKERS.iter().any(|m| line.contains(m)) {
+            continue;
+        }
+        let hit = comment_starts(line).into_iter().find_map(|pos| {
+            let body = line[pos..].trim_start_matches(['/', '*', '!', '#', '-', '<']).trim_start();
+            COMMENT_MARKERS.iter()
+                .find(|m| body.starts_with(*m) && !body[m.len()..].starts_with(is_word))
+                .map(|&m| (m, &body[m.len()..]))
+        });
+        if let Some((marker, rest)) = hit {
+            found.push(((i + 1) as u32, marker, marker_text(rest)));
+        }
+    }
+    found
+}
+
+/// Byte offsets of comment openers in `line`, left to right. `#` and `--` need
+/// whitespace on both sides (`# x`, `x -- y`) so `#[attr]` and `--flag` don't count.
+fn comment_starts(line: &str) -> Vec<usize> {
+    let trimmed = line.trim_start();
+    let mut starts: Vec<usize> = Vec::new();
+    if trimmed.starts_with('*') {
+        starts.push(line.len() - trimmed.len());
+    }
+    for opener in ["//", "/*", "<!--"] {
+        starts.extend(line.match_indices(opener).map(|(pos, _)| pos));
+    }
+    for opener in ['#', '-'] {
+        for (pos, _) in line.match_indices(opener) {
+            let after = line[pos..].trim_start_matches(opener);
+            let repeated = line.len() - pos - after.len();
+            let spaced_before = line[..pos].chars().next_back().is_none_or(char::is_whitespace);
+            let spaced_after = after.is_empty() || after.starts_with(char::is_whitespace);
+            if spaced_before && spaced_after && (opener == '#' || repeated >= 2) {
+                starts.push(pos);
+            }
+        }
+    }
+    starts.sort_unstable();
+    starts
+}
+
+fn marker_text(rest: &str) -> String {
+    let text = rest.trim_end();
+    let text = text.strip_suffix("-->").or_else(|| text.strip_suffix("*/")).unwrap_or(text);
+    let text = text.trim_start_matches(|c: char| c == ':' || c == '-' || c.is_whitespace()).trim_end();
+    text.chars().take(MAX_MARKER_TEXT_CHARS).collect()
+}
+
+/// Uppercase project keys that look like `KEY-123` but are standards or versions.
+const NOT_TICKET_KEYS: [&str; 5] = ["UTF", "SHA", "ISO", "RFC", "CVE"];
+
+/// Find issue-tracker references in comments: JIRA-style `PROJ-123` (upper-case key of
+/// 2-10 letters/digits) and GitHub-style `#123`. Only text after the first comment
+/// opener on a line is scanned (same openers as [`extract_comment_markers`]).
+/// Returns (1-based line, canonical ticket), one entry per distinct ticket per line.
+///
+/// # Examples
+///
+/// ```
+/// use search::extract_ticket_refs;
+///
+/// let found = extract_ticket_refs("retry(); // PAY-812: see also #77\n");
+/// assert_eq!(found, vec![(1, "PAY-812".to_string()), (1, "#77".to_string())]);
+/// ```
+#[must_use]
+pub fn extract_ticket_refs(content: &str) -> Vec<(u32, String)> {
+    let mut found = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if !line.contains(['-', '#']) {
+            continue;
+        }
+        if let Some(&pos) = comment_starts(line).first() {
+            found.extend(ticket_refs_in_text(&line[pos..]).into_iter().map(|t| ((i + 1) as u32, t)));
+        }
+    }
+    found
+}
+
+/// Ticket references anywhere in `text` (e.g. a commit subject), canonical and deduplicated,
+/// in order of first appearance.
+#[must_use]
+pub fn ticket_refs_in_text(text: &str) -> Vec<String> {
+    let bytes = text.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let digits_from = |at: usize| bytes[at..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let mut found: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let boundary = i == 0 || !is_word(bytes[i - 1]);
+        let mut hit: Option<(String, usize)> = None;
+        if bytes[i] == b'#' && (i == 0 || !(is_word(bytes[i - 1]) || bytes[i - 1] == b'&')) {
+            // `#123`, but not `&#123;` entities or `#0` / hex colours like `#000`
+            let n = digits_from(i + 1);
+            let end = i + 1 + n;
+            if n > 0 && bytes[i + 1] != b'0' && (end == bytes.len() || !is_word(bytes[end])) {
+                hit = Some((text[i..end].to_string(), end));
+            }
+        } else if boundary && bytes[i].is_ascii_uppercase() {
+            let key_len = bytes[i..].iter().take_while(|b| b.is_ascii_uppercase() || b.is_ascii_digit()).count();
+            let dash = i + key_len;
+            if (2..=10).contains(&key_len) && bytes.get(dash) == Some(&b'-') {
+                let n = digits_from(dash + 1);
+                let end = dash + 1 + n;
+                let key = &text[i..dash];
+                if n > 0 && (end == bytes.len() || !is_word(bytes[end])) && !NOT_TICKET_KEYS.contains(&key) {
+                    hit = Some((text[i..end].to_string(), end));
+                }
+            }
+        }
+        match hit {
+            Some((ticket, end)) => {
+                if !found.contains(&ticket) {
+                    found.push(ticket);
+                }
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+    found
+}
+
+/// Canonical form of a ticket ID typed by a user: `pay-812` → `PAY-812`,
+/// `5678` / `#5678` → `#5678`. Returns None for anything else.
+#[must_use]
+pub fn normalize_ticket(input: &str) -> Option<String> {
+    let input = input.trim();
+    let number = input.strip_prefix('#').unwrap_or(input);
+    if !number.is_empty() && !number.starts_with('0') && number.bytes().all(|b| b.is_ascii_digit()) {
+        return Some(format!("#{}", number));
+    }
+    let upper = input.to_ascii_uppercase();
+    let refs = ticket_refs_in_text(&upper);
+    (refs.len() == 1 && refs[0] == upper).then_some(upper)
+}
+
 #[cfg(test)]
 mod lib_tests {
     use super::*;
@@ -439,11 +1137,32 @@ mod lib_tests {
         assert_eq!(tokens, vec!["bb", "ccc"]);
     }
 
+    #[test]
+    fn test_tokenizer_settings_limits_and_unanswerable_terms() {
+        let settings = TokenizerSettings { min_token_len: 2, max_token_len: 5 };
+        assert_eq!(settings.tokenize("a bb ccccc dddddd"), vec!["bb", "ccccc"]);
+        assert_eq!(settings.unanswerable("bb", false), None);
+        assert!(settings.unanswerable("x", false).unwrap().contains("shorter"));
+        assert_eq!(settings.unanswerable("x", true), None, "a short substring can sit inside a token");
+        assert!(settings.unanswerable("dddddd", true).unwrap().contains("longer"));
+        assert!(settings.unanswerable("a->b", true).unwrap().contains("splits on"));
+        assert_eq!(TokenizerSettings::default().unanswerable(&"z".repeat(500), false), None);
+    }
+
     #[test]
     fn test_clean_path_strips_prefix() {
         assert_eq!(clean_path(r"\\?\C:\Users\test"), "C:/Users/test");
     }
 
+    #[test]
+    fn test_relative_path_matches_whole_segments() {
+        assert_eq!(relative_path("/repo", "/repo/src/a.cs"), "src/a.cs");
+        assert_eq!(relative_path("/repo/", "/repo/src/a.cs"), "src/a.cs");
+        assert_eq!(relative_path("/repo", "/repo"), "");
+        assert_eq!(relative_path("/repo", "/repository/x"), "/repository/x");
+        assert_eq!(relative_path("/repo", "/other/a.cs"), "/other/a.cs");
+    }
+
     #[test]
     fn test_clean_path_no_prefix() {
         assert_eq!(clean_path(r"C:\Users\test"), "C:/Users/test");
@@ -528,8 +1247,18 @@ mod lib_tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
-        assert!(index.is_stale());
+        assert!(index.is_stale(&clock::ManualClock::new(3601)));
+        assert!(!index.is_stale(&clock::ManualClock::new(3600)));
     }
 
     // ─── warm_up tests ──────────────────────────────────────────
@@ -549,6 +1278,15 @@ mod lib_tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
         let (trigrams, tokens) = index.warm_up();
         assert_eq!(trigrams, 0);
@@ -564,8 +1302,8 @@ mod lib_tests {
         trigram_map.insert("han".to_string(), vec![1]);
 
         let mut inverted = HashMap::new();
-        inverted.insert("httpclient".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-        inverted.insert("httphandler".to_string(), vec![Posting { file_id: 1, lines: vec![5] }]);
+        inverted.insert("httpclient".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+        inverted.insert("httphandler".into(), vec![Posting { file_id: 1, lines: vec![5] }]);
 
         let index = ContentIndex {
             root: ".".to_string(),
@@ -577,12 +1315,21 @@ mod lib_tests {
             extensions: vec!["cs".to_string()],
             file_token_counts: vec![1, 1],
             trigram: TrigramIndex {
-                tokens: vec!["httpclient".to_string(), "httphandler".to_string()],
+                tokens: vec!["httpclient".into(), "httphandler".into()],
                 trigram_map,
             },
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
         let (trigrams, tokens) = index.warm_up();
         assert_eq!(trigrams, 4); // 4 trigram entries
@@ -604,12 +1351,21 @@ mod lib_tests {
             extensions: vec![],
             file_token_counts: vec![],
             trigram: TrigramIndex {
-                tokens: vec!["abcdef".to_string()],
+                tokens: vec!["abcdef".into()],
                 trigram_map,
             },
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
 
         // Call warm_up multiple times — should always return the same result
@@ -631,7 +1387,7 @@ mod lib_tests {
         trigram_map.insert("bar".to_string(), vec![0]);
 
         let mut inverted = HashMap::new();
-        inverted.insert("foobar".to_string(), vec![Posting { file_id: 0, lines: vec![1, 5] }]);
+        inverted.insert("foobar".into(), vec![Posting { file_id: 0, lines: vec![1, 5] }]);
 
         let index = ContentIndex {
             root: ".".to_string(),
@@ -643,12 +1399,21 @@ mod lib_tests {
             extensions: vec!["cs".to_string()],
             file_token_counts: vec![1],
             trigram: TrigramIndex {
-                tokens: vec!["foobar".to_string()],
+                tokens: vec!["foobar".into()],
                 trigram_map,
             },
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
 
         // Warm up should succeed
@@ -659,7 +1424,7 @@ mod lib_tests {
         // After warm_up, the trigram index should still be usable
         // Verify trigram map still contains expected data
         assert!(index.trigram.trigram_map.contains_key("foo"));
-        assert_eq!(index.trigram.tokens[0], "foobar");
+        assert_eq!(&*index.trigram.tokens[0], "foobar");
 
         // Verify inverted index still works
         let postings = index.index.get("foobar").unwrap();
@@ -678,6 +1443,122 @@ mod lib_tests {
         assert_eq!(decoded.file_id, 42);
         assert_eq!(decoded.lines, vec![1, 5, 10]);
     }
+
+    // ─── extract_comment_markers tests ──────────────────────────
+
+    #[test]
+    fn test_extract_comment_markers_comment_styles() {
+        let content = "\
+int x = 0; // TODO: remove after migration
+# FIXME - breaks on empty input
+/* HACK(ops): retry twice */
+ * BUG: off by one in paging
+<!-- TODO: localize -->
+SELECT 1 -- TODO drop this column
+/// TODO document the error cases
+  ## FIXME";
+        let found = extract_comment_markers(content);
+        assert_eq!(found, vec![
+            (1, "TODO", "remove after migration".to_string()),
+            (2, "FIXME", "breaks on empty input".to_string()),
+            (3, "HACK", "(ops): retry twice".to_string()),
+            (4, "BUG", "off by one in paging".to_string()),
+            (5, "TODO", "localize".to_string()),
+            (6, "TODO", "drop this column".to_string()),
+            (7, "TODO", "document the error cases".to_string()),
+            (8, "FIXME", String::new()),
+        ]);
+    }
+
+    #[test]
+    fn test_extract_comment_markers_ignores_code_and_prose() {
+        let content = "\
+let msg = \"TODO list\";
+const TODO_LIMIT: usize = 5;
+// Debug output, see todoList
+// TODOS are tracked elsewhere
+/// Lists TODO and FIXME comments
+run --marker FIXME
+#[cfg(TODO)]
+x = a--; // BUG: underflow";
+        let found = extract_comment_markers(content);
+        assert_eq!(found, vec![(8, "BUG", "underflow".to_string())]);
+
+        let long = format!("// TODO {}", "x".repeat(500));
+        assert_eq!(extract_comment_markers(&long)[0].2.len(), MAX_MARKER_TEXT_CHARS);
+    }
+
+    // ─── extract_ticket_refs tests ───────────────────────────────
+
+    #[test]
+    fn test_extract_ticket_refs_in_comments_only() {
+        let content = "\
+let url = \"/issues/PAY-1\"; // PAY-812, see #77 and PAY-812 again
+# OPS-42 follows RFC-2119 and UTF-8
+const ABC_DEF = X-1; // color #000, entity &#123;, issue#5, v2-3
+/* GH-9: pending */
+  # OPS-42 OPS-42
+";
+        let found = extract_ticket_refs(content);
+        assert_eq!(found, vec![
+            (1, "PAY-812".to_string()),
+            (1, "#77".to_string()),
+            (2, "OPS-42".to_string()),
+            (4, "GH-9".to_string()),
+            (5, "OPS-42".to_string()),
+        ]);
+
+        assert_eq!(ticket_refs_in_text("Fix PAY-8120 (#77), not pay-1 or PAY-"), vec!["PAY-8120", "#77"]);
+        assert_eq!(normalize_ticket(" pay-812 "), Some("PAY-812".to_string()));
+        assert_eq!(normalize_ticket("77"), Some("#77".to_string()));
+        assert_eq!(normalize_ticket("#77"), Some("#77".to_string()));
+        assert_eq!(normalize_ticket("#077"), None);
+        assert_eq!(normalize_ticket("PAY-812 PAY-9"), None);
+    }
+
+    // ─── lossless path string tests ──────────────────────────────
+
+    #[test]
+    fn test_non_utf8_paths_round_trip_and_ascii_json() {
+        let escaped = bytes_to_string_escaped(b"caf\xe9.cs");
+        assert!(escaped.starts_with("caf") && escaped.ends_with(".cs"));
+        assert!(!escaped.contains('\u{FFFD}'));
+        assert_eq!(bytes_to_string_escaped("café.cs".as_bytes()), "café.cs");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let raw = std::path::Path::new(std::ffi::OsStr::from_bytes(b"src/caf\xe9.cs"));
+            let s = path_to_string(raw);
+            assert_eq!(path_from_string(&s).as_path(), raw);
+        }
+
+        let json = serde_json::to_string(&serde_json::json!({"file": "café/😀.cs"})).unwrap();
+        let ascii = escape_non_ascii_json(&json);
+        assert_eq!(ascii, r#"{"file":"caf\u00e9/\ud83d\ude00.cs"}"#);
+        let back: serde_json::Value = serde_json::from_str(&ascii).unwrap();
+        assert_eq!(back["file"], "café/😀.cs");
+    }
+
+    #[test]
+    fn test_token_bloom_and_top_level_dir() {
+        let tokens: Vec<String> = (0..2000).map(|i| format!("token{}", i)).collect();
+        let mut bloom = TokenBloom::with_capacity(tokens.len());
+        for t in &tokens {
+            bloom.insert(t);
+        }
+        assert!(tokens.iter().all(|t| bloom.may_contain(t)), "no false negatives");
+        let false_positives = (0..2000).filter(|i| bloom.may_contain(&format!("other{}", i))).count();
+        assert!(false_positives < 60, "~1% expected, got {} of 2000", false_positives);
+        assert!(TokenBloom::default().may_contain("anything"), "an empty filter rules nothing out");
+
+        assert_eq!(top_level_dir("C:/Repo", r"C:\repo\Services\Orders\a.cs", false), Some("services".to_string()));
+        assert_eq!(top_level_dir("/r/", "/r/a.cs", false), None);
+        assert_eq!(top_level_dir("/r", "/r/Web", true), Some("web".to_string()));
+        assert_eq!(top_level_dir("/r", "/r", true), None);
+        assert_eq!(top_level_dir("/r", "/other/x/a.cs", false), None);
+    }
+
     // ─── sanitize_for_filename tests ─────────────────────────────
 
     #[test]
@@ -849,6 +1730,31 @@ mod lib_tests {
 mod trigram_tests {
     use super::*;
 
+    #[test]
+    fn test_edit_distance_within_stops_past_max() {
+        assert_eq!(edit_distance_within("ordersevice", "orderservice", 2), Some(1));
+        assert_eq!(edit_distance_within("handler", "handle", 1), Some(1));
+        assert_eq!(edit_distance_within("handler", "factory", 2), None);
+        assert_eq!(edit_distance_within("ab", "abcd", 1), None);
+    }
+
+    #[test]
+    fn test_similar_tokens_ranks_trigram_candidates_by_edit_distance() {
+        let tokens = ["token", "tokenizer", "tokenizers", "detokenize", "parser"];
+        let mut trigram = TrigramIndex { tokens: tokens.iter().map(|&t| Token::from(t)).collect(), trigram_map: HashMap::new() };
+        for (id, token) in tokens.iter().enumerate() {
+            for tri in generate_trigrams(token) {
+                let ids = trigram.trigram_map.entry(tri).or_default();
+                if ids.last() != Some(&(id as u32)) {
+                    ids.push(id as u32);
+                }
+            }
+        }
+        let similar: Vec<&str> = trigram.similar_tokens("tokenzier", 3).into_iter().map(|t| &**t).collect();
+        assert_eq!(similar, ["tokenizer", "tokenizers", "token"]);
+        assert!(trigram.similar_tokens("pa", 3).is_empty());
+    }
+
     #[test]
     fn test_generate_trigrams_basic() {
         // "httpclient" → ["htt","ttp","tpc","pcl","cli","lie","ien","ent"]
@@ -915,7 +1821,7 @@ mod trigram_tests {
         trigram_map.insert("abc".to_string(), vec![0, 1, 2]);
         trigram_map.insert("bcd".to_string(), vec![1, 2]);
         let ti = TrigramIndex {
-            tokens: vec!["abcdef".to_string(), "bcdefg".to_string(), "cdefgh".to_string()],
+            tokens: vec!["abcdef".into(), "bcdefg".into(), "cdefgh".into()],
             trigram_map,
         };
         let bytes = bincode::serialize(&ti).unwrap();
@@ -937,7 +1843,7 @@ mod trigram_tests {
             extensions: vec![],
             file_token_counts: vec![],
             trigram: TrigramIndex {
-                tokens: vec!["hello".to_string()],
+                tokens: vec!["hello".into()],
                 trigram_map: {
                     let mut m = HashMap::new();
                     m.insert("hel".to_string(), vec![0]);
@@ -949,12 +1855,65 @@ mod trigram_tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
         let bytes = bincode::serialize(&ci).unwrap();
         let ci2: ContentIndex = bincode::deserialize(&bytes).unwrap();
         assert_eq!(ci.trigram.tokens, ci2.trigram.tokens);
         assert_eq!(ci.trigram.trigram_map.len(), ci2.trigram.trigram_map.len());
     }
+
+    #[test]
+    fn test_share_token_strings_after_deserialize() {
+        let mut index = HashMap::new();
+        index.insert(Token::from("hello"), vec![Posting { file_id: 0, lines: vec![1] }]);
+        index.insert(Token::from("world"), vec![Posting { file_id: 0, lines: vec![2] }]);
+        let tokens: Vec<Token> = vec![index.get_key_value("hello").unwrap().0.clone(), index.get_key_value("world").unwrap().0.clone()];
+        let ci = ContentIndex {
+            root: ".".to_string(),
+            created_at: 0,
+            max_age_secs: 3600,
+            files: vec!["a.cs".to_string()],
+            index,
+            total_tokens: 2,
+            extensions: vec![],
+            file_token_counts: vec![2],
+            trigram: TrigramIndex { tokens, trigram_map: HashMap::new() },
+            trigram_dirty: false,
+            forward: None,
+            path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
+        };
+        assert!(Arc::ptr_eq(ci.index.get_key_value("hello").unwrap().0, &ci.trigram.tokens[0]));
+
+        // Tokens serialize as plain strings
+        assert_eq!(bincode::serialize(&ci.trigram.tokens).unwrap(),
+            bincode::serialize(&vec!["hello".to_string(), "world".to_string()]).unwrap());
+
+        let mut loaded: ContentIndex = bincode::deserialize(&bincode::serialize(&ci).unwrap()).unwrap();
+        assert!(!Arc::ptr_eq(loaded.index.get_key_value("hello").unwrap().0, &loaded.trigram.tokens[0]));
+        assert_eq!(loaded.share_token_strings(), 2);
+        for token in &loaded.trigram.tokens {
+            assert!(Arc::ptr_eq(loaded.index.get_key_value(&**token).unwrap().0, token));
+        }
+        assert_eq!(loaded.share_token_strings(), 0, "already shared");
+    }
 }
 
     // ─── read_file_lossy / BOM detection tests ───────────────────
@@ -1258,7 +2217,7 @@ mod property_tests {
             tokens_per_file in 1usize..50,
         ) {
             let mut files = Vec::new();
-            let mut index: HashMap<String, Vec<Posting>> = HashMap::new();
+            let mut index: HashMap<Token, Vec<Posting>> = HashMap::new();
             let mut file_token_counts = Vec::new();
 
             for file_id in 0..num_files {
@@ -1267,7 +2226,7 @@ mod property_tests {
                 for t in 0..tokens_per_file {
                     let token = format!("tok_{}", t % 10);
                     count += 1;
-                    index.entry(token).or_default().push(Posting {
+                    index.entry(token.into()).or_default().push(Posting {
                         file_id: file_id as u32,
                         lines: vec![(t + 1) as u32],
                     });
@@ -1291,7 +2250,7 @@ mod property_tests {
         #[test]
         fn content_index_roundtrip(num_files in 1usize..10) {
             let mut files = Vec::new();
-            let mut index: HashMap<String, Vec<Posting>> = HashMap::new();
+            let mut index: HashMap<Token, Vec<Posting>> = HashMap::new();
             let mut file_token_counts = Vec::new();
             let mut total_tokens = 0u64;
 
@@ -1300,7 +2259,7 @@ mod property_tests {
                 let token = format!("token_{}", file_id);
                 total_tokens += 1;
                 file_token_counts.push(1);
-                index.entry(token).or_default().push(Posting {
+                index.entry(token.into()).or_default().push(Posting {
                     file_id: file_id as u32,
                     lines: vec![1],
                 });
@@ -1319,6 +2278,15 @@ mod property_tests {
                 trigram_dirty: false,
                 forward: None,
                 path_to_id: None,
+                comment_markers: Vec::new(),
+                revision: None,
+                ticket_refs: Vec::new(),
+                dir_blooms: HashMap::new(),
+                skipped: SkippedFiles::default(),
+                tokenizer: TokenizerSettings::default(),
+                walk_limits: Default::default(),
+                stop_token_pct: 0,
+                max_file_size: 0,
             };
 
             let encoded = bincode::serialize(&ci).unwrap();
@@ -0,0 +1,167 @@
+//! Single-writer guarantee for index files.
+//!
+//! Saves replace index files atomically (temp file + rename), so a reader never sees a
+//! half-written file. Writers can still interleave: two `serve` instances on one root,
+//! or `serve` and a CLI rebuild, would each rename their own file over the other's, and
+//! a sharded save (shard files plus a manifest) could mix shards from both. Every save
+//! therefore holds an advisory exclusive lock on `<index file>.lock` while it writes.
+//! A second writer queues behind it, waiting up to [`LOCK_WAIT`], and then fails with
+//! [`SearchError::IndexLocked`], which names the process holding the lock.
+//!
+//! `serve` also holds a [`DaemonLock`] on its content index for as long as it runs, so
+//! a second `serve` or a CLI rebuild of the same index can say which process will keep
+//! saving over it. Locks are released by the OS when their process exits, so a crash
+//! never leaves a stale lock behind.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::SearchError;
+
+/// How long a save waits for another writer before giving up.
+pub const LOCK_WAIT: Duration = Duration::from_secs(30);
+
+/// Interval between attempts while waiting for a lock.
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Exclusive write lock on one index file, held until dropped.
+pub struct IndexLock {
+    _file: File,
+}
+
+impl IndexLock {
+    /// Lock `index_path` for writing, waiting up to [`LOCK_WAIT`] for another writer.
+    pub fn acquire(index_path: &Path) -> Result<Self, SearchError> {
+        Self::acquire_within(index_path, LOCK_WAIT)
+    }
+
+    pub fn acquire_within(index_path: &Path, wait: Duration) -> Result<Self, SearchError> {
+        let lock_path = lock_path_for(index_path, "lock");
+        let start = Instant::now();
+        let mut announced = false;
+        loop {
+            match try_lock(&lock_path)? {
+                Some(file) => return Ok(IndexLock { _file: file }),
+                None if start.elapsed() >= wait => {
+                    return Err(SearchError::IndexLocked {
+                        path: index_path.display().to_string(),
+                        holder: holder_of(&lock_path),
+                        waited_secs: wait.as_secs(),
+                    });
+                }
+                None => {
+                    if !announced {
+                        eprintln!("[index-lock] Waiting for {} to finish writing {}",
+                            holder_of(&lock_path), index_path.display());
+                        announced = true;
+                    }
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+/// Lock held by `serve` on the content index it keeps up to date.
+pub struct DaemonLock {
+    _file: File,
+}
+
+impl DaemonLock {
+    /// Take the daemon lock on `index_path` without waiting. `None` when another
+    /// process holds it (see [`serving_process`]).
+    pub fn try_acquire(index_path: &Path) -> Result<Option<Self>, SearchError> {
+        Ok(try_lock(&lock_path_for(index_path, "serve.lock"))?.map(|file| DaemonLock { _file: file }))
+    }
+}
+
+/// The process serving `index_path`, if a `serve` holds its daemon lock.
+pub fn serving_process(index_path: &Path) -> Option<String> {
+    let lock_path = lock_path_for(index_path, "serve.lock");
+    if !lock_path.exists() {
+        return None;
+    }
+    let file = OpenOptions::new().read(true).write(true).open(&lock_path).ok()?;
+    match file.try_lock() {
+        Ok(()) => None,
+        Err(_) => Some(holder_of(&lock_path)),
+    }
+}
+
+/// The lock files kept next to `index_path`, for cleanup.
+pub fn lock_files_for(index_path: &Path) -> [PathBuf; 2] {
+    [lock_path_for(index_path, "lock"), lock_path_for(index_path, "serve.lock")]
+}
+
+fn lock_path_for(index_path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}", index_path.display(), suffix))
+}
+
+/// Try to lock `lock_path` exclusively. `Ok(None)` when another handle holds it. The
+/// winner records its process in the file for the error messages of later writers.
+fn try_lock(lock_path: &Path) -> Result<Option<File>, SearchError> {
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(lock_path)?;
+    match file.try_lock() {
+        Ok(()) => {
+            let _ = file.set_len(0);
+            let _ = write!(file, "{}", describe_current_process());
+            Ok(Some(file))
+        }
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// `pid N: command line` of the process holding `lock_path`. Windows does not let
+/// other processes read a locked file, so there it is just "another process".
+fn holder_of(lock_path: &Path) -> String {
+    fs::read_to_string(lock_path).ok()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| "another process".to_string())
+}
+
+fn describe_current_process() -> String {
+    let args: Vec<String> = std::env::args().take(6).collect();
+    format!("pid {}: {}", std::process::id(), args.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_lock_is_exclusive_and_released_on_drop() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("a.word-search");
+        let held = IndexLock::acquire(&path).unwrap();
+        let err = IndexLock::acquire_within(&path, Duration::from_millis(120)).err().unwrap();
+        assert!(matches!(err, SearchError::IndexLocked { .. }));
+        if cfg!(unix) {
+            assert!(err.to_string().contains(&format!("pid {}", std::process::id())), "{}", err);
+        }
+        drop(held);
+        IndexLock::acquire_within(&path, Duration::ZERO).unwrap();
+
+        // A writer queued behind the lock gets it once the holder is done
+        let held = IndexLock::acquire(&path).unwrap();
+        let waiter = std::thread::spawn({
+            let path = path.clone();
+            move || IndexLock::acquire(&path).is_ok()
+        });
+        std::thread::sleep(Duration::from_millis(150));
+        drop(held);
+        assert!(waiter.join().unwrap());
+
+        assert_eq!(serving_process(&path), None);
+        let daemon = DaemonLock::try_acquire(&path).unwrap().unwrap();
+        assert!(DaemonLock::try_acquire(&path).unwrap().is_none());
+        assert!(serving_process(&path).is_some());
+        drop(daemon);
+        assert_eq!(serving_process(&path), None);
+    }
+}
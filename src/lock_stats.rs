@@ -0,0 +1,173 @@
+//! Lock-wait instrumentation for the content, definition and git history locks.
+//!
+//! Off unless [`enable`] is called (`serve --lock-metrics`, `search bench`). While on,
+//! every acquisition wrapped in [`timed`] adds its wait to the query running on that
+//! thread, and [`record_query`] keeps the total per lock of each query, for the last
+//! [`MAX_SAMPLES`] queries that took the lock. [`summary`] reports p95 and max of those.
+//! Off, a wrapped acquisition costs one atomic load.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Per-query samples kept for each lock
+pub const MAX_SAMPLES: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lock {
+    Content,
+    Definitions,
+    Git,
+}
+
+impl Lock {
+    const ALL: [Lock; 3] = [Lock::Content, Lock::Definitions, Lock::Git];
+
+    fn name(self) -> &'static str {
+        match self {
+            Lock::Content => "content",
+            Lock::Definitions => "definitions",
+            Lock::Git => "git",
+        }
+    }
+}
+
+struct Samples {
+    /// Wait of each recorded query in µs, oldest overwritten first
+    waits: Vec<u64>,
+    next: usize,
+    queries: u64,
+}
+
+static SAMPLES: Mutex<[Samples; 3]> = Mutex::new([const { Samples { waits: Vec::new(), next: 0, queries: 0 } }; 3]);
+static ACQUISITIONS: [AtomicU64; 3] = [const { AtomicU64::new(0) }; 3];
+
+thread_local! {
+    /// Wait per lock of the query running on this thread; `None` outside [`record_query`]
+    static QUERY: Cell<Option<[Option<Duration>; 3]>> = const { Cell::new(None) };
+}
+
+/// Record lock waits from now on.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Run `acquire` (a lock acquisition) and, while recording, count its wait against `lock`.
+pub fn timed<T>(lock: Lock, acquire: impl FnOnce() -> T) -> T {
+    if !is_enabled() {
+        return acquire();
+    }
+    let start = Instant::now();
+    let guard = acquire();
+    let wait = start.elapsed();
+    ACQUISITIONS[lock as usize].fetch_add(1, Ordering::Relaxed);
+    QUERY.with(|q| {
+        if let Some(mut waits) = q.get() {
+            let slot = &mut waits[lock as usize];
+            *slot = Some(slot.unwrap_or_default() + wait);
+            q.set(Some(waits));
+        }
+    });
+    guard
+}
+
+/// Run the query `f`, keeping its total wait on each lock it took.
+pub fn record_query<T>(f: impl FnOnce() -> T) -> T {
+    if !is_enabled() {
+        return f();
+    }
+    let outer = QUERY.with(|q| q.replace(Some([None; 3])));
+    let result = f();
+    let waits = QUERY.with(|q| q.replace(outer)).unwrap_or_default();
+    let mut samples = SAMPLES.lock().unwrap_or_else(|e| e.into_inner());
+    for (s, wait) in samples.iter_mut().zip(waits) {
+        let Some(wait) = wait else { continue };
+        let micros = wait.as_micros() as u64;
+        if s.waits.len() < MAX_SAMPLES {
+            s.waits.push(micros);
+        } else {
+            s.waits[s.next] = micros;
+        }
+        s.next = (s.next + 1) % MAX_SAMPLES;
+        s.queries += 1;
+    }
+    result
+}
+
+/// `{content: {queries, acquisitions, p95Ms, maxMs}, definitions: .., git: ..}`, or
+/// `None` when not recording.
+pub fn summary() -> Option<Value> {
+    if !is_enabled() {
+        return None;
+    }
+    let samples = SAMPLES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut out = json!({});
+    for lock in Lock::ALL {
+        let s = &samples[lock as usize];
+        let mut waits = s.waits.clone();
+        waits.sort_unstable();
+        let ms = |micros: u64| (micros as f64 / 10.0).round() / 100.0;
+        let p95 = if waits.is_empty() { 0 } else { waits[(waits.len() * 95).div_ceil(100) - 1] };
+        out[lock.name()] = json!({
+            "queries": s.queries,
+            "acquisitions": ACQUISITIONS[lock as usize].load(Ordering::Relaxed),
+            "p95Ms": ms(p95),
+            "maxMs": ms(waits.last().copied().unwrap_or(0)),
+        });
+    }
+    Some(out)
+}
+
+/// Forget everything recorded so far.
+pub fn reset() {
+    let mut samples = SAMPLES.lock().unwrap_or_else(|e| e.into_inner());
+    for (s, acquisitions) in samples.iter_mut().zip(&ACQUISITIONS) {
+        *s = Samples { waits: Vec::new(), next: 0, queries: 0 };
+        acquisitions.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, RwLock};
+
+    #[test]
+    fn test_lock_waits_recorded_per_query() {
+        assert!(summary().is_none());
+        enable();
+        reset();
+        let lock = Arc::new(RwLock::new(0));
+        let writer = lock.write().unwrap();
+        let reader = {
+            let lock = Arc::clone(&lock);
+            std::thread::spawn(move || record_query(|| {
+                // Two acquisitions, one blocked by the writer: one sample
+                let first = *timed(Lock::Git, || lock.read()).unwrap();
+                let second = *timed(Lock::Git, || lock.read()).unwrap();
+                first + second
+            }))
+        };
+        std::thread::sleep(Duration::from_millis(30));
+        drop(writer);
+        assert_eq!(reader.join().unwrap(), 0);
+        // Outside a query the wait is only counted as an acquisition
+        drop(timed(Lock::Definitions, || lock.read()));
+
+        let summary = summary().unwrap();
+        assert_eq!(summary["git"]["queries"], 1);
+        assert_eq!(summary["git"]["acquisitions"], 2);
+        assert!(summary["git"]["p95Ms"].as_f64().unwrap() >= 20.0, "{}", summary);
+        assert_eq!(summary["git"]["p95Ms"], summary["git"]["maxMs"]);
+        assert_eq!(summary["definitions"], json!({ "queries": 0, "acquisitions": 1, "p95Ms": 0.0, "maxMs": 0.0 }));
+    }
+}
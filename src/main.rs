@@ -4,25 +4,37 @@
 
 // Use mimalloc as global allocator — aggressively returns freed pages to the OS,
 // reducing memory fragmentation by ~70-80% compared to Windows HeapAlloc.
+#[cfg(feature = "mimalloc")]
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 // Re-export core types from library crate
-pub use search::{clean_path, read_file_lossy, tokenize, ContentIndex, FileEntry, FileIndex, Posting, TrigramIndex, DEFAULT_MIN_TOKEN_LEN};
+pub use search::{bytes_to_string_escaped, clean_path, escape_non_ascii_json, path_from_string, path_to_string, extract_comment_markers, extract_ticket_refs, normalize_ticket, read_file_lossy, relative_path, ticket_refs_in_text, tokenize, top_level_dir, looks_binary, CommentMarker, ContentIndex, FileEntry, FileIndex, Posting, Searcher, SkippedFiles, SystemClock, TicketRef, Token, TokenizerSettings, TrigramIndex, WalkLimits, COMMENT_MARKERS, DEFAULT_MIN_TOKEN_LEN, DEFAULT_STOP_TOKEN_PCT, STOP_TOKEN_MIN_FILES};
 
 mod cli;
+mod config;
 mod definitions;
 mod error;
 mod git;
 mod index;
+mod language;
+mod lock;
 mod mcp;
+mod owners;
+mod priority;
+mod progress;
+mod rank_hook;
+mod semantic;
+mod shards;
+mod sqlite;
 mod tips;
 
 pub use error::SearchError;
 pub use index::{
     build_content_index, build_index, cleanup_indexes_for_dir, cleanup_orphaned_indexes,
     content_index_path_for, find_content_index_for_dir, index_dir, index_path_for,
-    load_content_index, load_index, save_content_index, save_index,
+    load_content_index, load_index, load_or_build_content_index_at_rev, read_indexed_file,
+    save_content_index, save_index,
 };
 
 // Re-export CLI types used by other modules
@@ -52,7 +52,7 @@
             max_age_secs: 3600,
             entries: vec![],
         };
-        assert!(!index.is_stale());
+        assert!(!index.is_stale(&SystemClock));
     }
 
     #[test]
@@ -63,7 +63,7 @@
             max_age_secs: 3600,
             entries: vec![],
         };
-        assert!(index.is_stale());
+        assert!(index.is_stale(&SystemClock));
     }
 
     #[test]
@@ -86,8 +86,17 @@
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
-        assert!(!index.is_stale());
+        assert!(!index.is_stale(&SystemClock));
     }
 
     #[test]
@@ -105,8 +114,17 @@
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
-        assert!(index.is_stale());
+        assert!(index.is_stale(&SystemClock));
     }
 
     #[test]
@@ -130,6 +148,20 @@
             no_ignore: false,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         });
 
         assert_eq!(index.files.len(), 2);
@@ -153,10 +185,13 @@
             hidden: false,
             no_ignore: false,
             threads: 1,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            storage: None,
         });
 
         assert!(index.entries.len() >= 2, "Should find at least 2 files");
-        assert!(!index.is_stale());
+        assert!(!index.is_stale(&SystemClock));
 
         let names: Vec<&str> = index.entries.iter()
             .filter_map(|e| std::path::Path::new(&e.path).file_name().and_then(|n| n.to_str()))
@@ -202,7 +237,7 @@
     fn test_content_index_serialization_roundtrip() {
         let mut idx = HashMap::new();
         idx.insert(
-            "httpclient".to_string(),
+            "httpclient".into(),
             vec![Posting {
                 file_id: 0,
                 lines: vec![5, 12, 30],
@@ -221,6 +256,15 @@
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
         let encoded = bincode::serialize(&index).unwrap();
         let decoded: ContentIndex = bincode::deserialize(&encoded).unwrap();
@@ -297,6 +341,20 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
@@ -343,6 +401,20 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
@@ -382,12 +454,26 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
         // Regex "i.*cache" should match itenantcache, iusercache, isessioncache
         let re = Regex::new("(?i)^i.*cache$").unwrap();
-        let matching_tokens: Vec<&String> = index.index.keys()
+        let matching_tokens: Vec<&crate::Token> = index.index.keys()
             .filter(|k| re.is_match(k))
             .collect();
 
@@ -396,7 +482,7 @@
 
         // "inotamatch" should NOT match the cache regex
         assert!(
-            !matching_tokens.contains(&&"inotamatch".to_string()),
+            !matching_tokens.contains(&&"inotamatch".into()),
             "inotamatch should not match i.*cache pattern"
         );
     }
@@ -417,11 +503,25 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
         let re = Regex::new("(?i)^zzzznonexistent$").unwrap();
-        let matching: Vec<&String> = index.index.keys()
+        let matching: Vec<&crate::Token> = index.index.keys()
             .filter(|k| re.is_match(k))
             .collect();
 
@@ -444,18 +544,32 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
         // Pattern ".*async" should match getasync, postasync, sendasync
         let re = Regex::new("(?i)^.*async$").unwrap();
-        let matching: Vec<&String> = index.index.keys()
+        let matching: Vec<&crate::Token> = index.index.keys()
             .filter(|k| re.is_match(k))
             .collect();
 
         assert!(matching.len() >= 3, "Should match at least 3 async tokens, got {}: {:?}", matching.len(), matching);
         assert!(
-            !matching.contains(&&"syncmethod".to_string()),
+            !matching.contains(&&"syncmethod".into()),
             "syncmethod should not match .*async$ pattern"
         );
     }
@@ -487,6 +601,20 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
@@ -532,6 +660,20 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
@@ -632,6 +774,9 @@
         let file_idx = build_index(&IndexArgs {
             dir: root_str.clone(), max_age_hours: 24,
             hidden: false, no_ignore: false, threads: 1,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            storage: None,
         });
         save_index(&file_idx, &idx_base).unwrap();
 
@@ -639,6 +784,20 @@
         let content_idx = build_content_index(&ContentIndexArgs {
             dir: root_str.clone(), ext: "cs".to_string(), max_age_hours: 24,
             hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         });
         save_content_index(&content_idx, &idx_base).unwrap();
 
@@ -682,12 +841,18 @@
         let idx_a = build_index(&IndexArgs {
             dir: root_a.clone(), max_age_hours: 24,
             hidden: false, no_ignore: false, threads: 1,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            storage: None,
         });
         save_index(&idx_a, &idx_base).unwrap();
 
         let idx_b = build_index(&IndexArgs {
             dir: root_b.clone(), max_age_hours: 24,
             hidden: false, no_ignore: false, threads: 1,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            storage: None,
         });
         save_index(&idx_b, &idx_base).unwrap();
 
@@ -731,6 +896,20 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
@@ -790,6 +969,20 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
@@ -818,6 +1011,20 @@
             no_ignore: true,
             threads: 1,
             min_token_len: 2,
+            dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal,
+            io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
         };
         let index = build_content_index(&args);
 
@@ -0,0 +1,96 @@
+//! Tool call audit log: `serve --audit-log` records every `tools/call`, `search replay`
+//! runs the calls again against the current build.
+//!
+//! One JSON object per line, appended as calls complete:
+//!
+//! ```json
+//! {"ts":1760500000,"tool":"search_grep","arguments":{"terms":"HttpClient"},"result":{"content":[...]}}
+//! ```
+//!
+//! `result` is the `tools/call` result as sent to the client, `_meta` included.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One recorded tool call.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    /// Unix seconds when the call completed
+    pub ts: u64,
+    pub tool: String,
+    #[serde(default)]
+    pub arguments: Value,
+    pub result: Value,
+}
+
+/// Appends records to an audit log file.
+pub struct AuditLog {
+    file: File,
+}
+
+impl AuditLog {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    /// Append one call as a single line and flush, so a killed server loses nothing.
+    pub fn append(&mut self, tool: &str, arguments: &Value, result: &Value) -> io::Result<()> {
+        let record = AuditRecord {
+            ts: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            tool: tool.to_string(),
+            arguments: arguments.clone(),
+            result: result.clone(),
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&record).unwrap())?;
+        self.file.flush()
+    }
+}
+
+/// Read every record from `path`. Blank lines are skipped; a line that is not a record
+/// is an error naming its line number.
+pub fn read_audit_log(path: &Path) -> Result<Vec<AuditRecord>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut records = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line)
+            .map_err(|e| format!("{}:{}: not an audit record: {}", path.display(), i + 1, e))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_audit_log_appends_and_reads_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("audit.jsonl");
+        let mut log = AuditLog::open(&path).unwrap();
+        log.append("search_grep", &json!({ "terms": "foo" }), &json!({ "content": [] })).unwrap();
+        drop(log);
+        // Reopening appends rather than truncating
+        AuditLog::open(&path).unwrap().append("search_info", &json!({}), &json!({ "isError": true })).unwrap();
+
+        let records = read_audit_log(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].tool.as_str(), &records[0].arguments), ("search_grep", &json!({ "terms": "foo" })));
+        assert_eq!(records[1].result, json!({ "isError": true }));
+
+        std::fs::write(&path, "\n{\"tool\":\"x\"}\n").unwrap();
+        assert!(read_audit_log(&path).unwrap_err().contains("audit.jsonl:2:"));
+    }
+}
@@ -0,0 +1,157 @@
+//! search_audit handler: run a suite of checks from the `[audit]` section of
+//! `.search-index.toml` and report which pass.
+//!
+//! Each check is one or more tool calls whose result counts (`totalResults`,
+//! `totalFiles`, ...) add up, asserted to be zero or non-zero. The calls go through
+//! the normal dispatch, so a suite runs at index speed and checks whatever any search
+//! tool can express. Suites are read from the config on every call, so edits need no
+//! server restart.
+
+use std::path::Path;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::config::{AuditCheck, AuditSuite, RepoConfig};
+use crate::mcp::protocol::ToolCallResult;
+
+use super::history::result_count;
+use super::HandlerContext;
+
+/// Tools a check may not call: they rebuild indexes, report only on the server, or
+/// would run suites recursively.
+const DISALLOWED_TOOLS: &[&str] = &[
+    "search_reindex", "search_reindex_status", "search_reindex_definitions", "search_history", "search_metrics", "search_audit",
+];
+
+/// Outcome of one check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckStatus {
+    Pass,
+    Fail,
+    /// A query failed or reported no count
+    Error,
+}
+
+impl CheckStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Fail => "fail",
+            CheckStatus::Error => "error",
+        }
+    }
+}
+
+pub(crate) fn handle_search_audit(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let config = match RepoConfig::load(Path::new(&ctx.server_dir)) {
+        Ok(config) => config,
+        Err(e) => return ToolCallResult::error(e),
+    };
+    match args.get("suite").and_then(|v| v.as_str()) {
+        Some(name) => match config.audit_suite(name) {
+            Some(suite) => {
+                let (report, _) = run_suite(ctx, suite);
+                ToolCallResult::success(serde_json::to_string(&report).unwrap())
+            }
+            None => ToolCallResult::error(unknown_suite(&config, name)),
+        },
+        None => ToolCallResult::success(serde_json::to_string(&list_suites(&config)).unwrap()),
+    }
+}
+
+/// Run every check of `suite` against `ctx`. Returns the report and whether all passed.
+pub(crate) fn run_suite(ctx: &HandlerContext, suite: &AuditSuite) -> (Value, bool) {
+    let start = Instant::now();
+    let results: Vec<(CheckStatus, Value)> = suite.checks.iter().map(|check| run_check(ctx, check)).collect();
+    let count = |status: CheckStatus| results.iter().filter(|(s, _)| *s == status).count();
+    let (failed, errors) = (count(CheckStatus::Fail), count(CheckStatus::Error));
+    let passed = failed == 0 && errors == 0;
+    let report = json!({
+        "checks": results.iter().map(|(_, check)| check).collect::<Vec<_>>(),
+        "summary": {
+            "suite": suite.name,
+            "checks": suite.checks.len(),
+            "passed": count(CheckStatus::Pass),
+            "failed": failed,
+            "errors": errors,
+            "result": if passed { "pass" } else { "fail" },
+            "elapsedMs": (start.elapsed().as_secs_f64() * 1000.0 * 100.0).round() / 100.0,
+        }
+    });
+    (report, passed)
+}
+
+fn run_check(ctx: &HandlerContext, check: &AuditCheck) -> (CheckStatus, Value) {
+    let mut total = 0;
+    let mut error = None;
+    let queries: Vec<Value> = check.queries.iter().map(|query| {
+        let mut entry = json!({ "tool": query.tool, "arguments": query.args });
+        match run_query(ctx, &query.tool, &query.args) {
+            Ok(count) => {
+                total += count;
+                entry["count"] = json!(count);
+            }
+            Err(e) => {
+                entry["error"] = json!(e);
+                error.get_or_insert(e);
+            }
+        }
+        entry
+    }).collect();
+    let mut result = json!({
+        "name": check.name,
+        "expect": check.expect.as_str(),
+        "count": total,
+        "queries": queries,
+    });
+    let status = match error {
+        Some(e) => {
+            result["error"] = json!(e);
+            CheckStatus::Error
+        }
+        None if check.expect.holds(total) => CheckStatus::Pass,
+        None => CheckStatus::Fail,
+    };
+    result["status"] = json!(status.as_str());
+    (status, result)
+}
+
+/// Result count of one tool call. search_grep runs as `countOnly`: the summary is all
+/// a check reads.
+fn run_query(ctx: &HandlerContext, tool: &str, args: &Value) -> Result<u64, String> {
+    if DISALLOWED_TOOLS.contains(&tool) {
+        return Err(format!("{} cannot be used in an audit suite", tool));
+    }
+    let mut args = args.clone();
+    if tool == "search_grep" && args.get("countOnly").is_none() {
+        args["countOnly"] = json!(true);
+    }
+    let result = super::dispatch_tool_from(ctx, tool, &args, Instant::now());
+    if result.is_error {
+        return Err(result.content.first().map(|c| c.text.clone()).unwrap_or_default());
+    }
+    result_count(&result).ok_or_else(|| format!("{} reports no result count", tool))
+}
+
+/// The suites in `config` with their check names.
+pub(crate) fn list_suites(config: &RepoConfig) -> Value {
+    let suites: Vec<Value> = config.audit_suites.iter().map(|suite| json!({
+        "name": suite.name,
+        "checks": suite.checks.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+    })).collect();
+    let mut output = json!({ "suites": suites });
+    if config.audit_suites.is_empty() {
+        output["hint"] = json!("No [audit] suites are configured. Define them as [[audit.<suite>]] tables in .search-index.toml.");
+    }
+    output
+}
+
+pub(crate) fn unknown_suite(config: &RepoConfig, name: &str) -> String {
+    let known: Vec<&str> = config.audit_suites.iter().map(|s| s.name.as_str()).collect();
+    if known.is_empty() {
+        format!("Unknown audit suite '{}': no [audit] suites are configured in .search-index.toml", name)
+    } else {
+        format!("Unknown audit suite '{}'. Configured suites: {}", name, known.join(", "))
+    }
+}
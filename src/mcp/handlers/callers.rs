@@ -1,19 +1,24 @@
 //! search_callers handler: call tree building (up/down).
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use serde_json::{json, Value};
 
+use crate::mcp::progress;
 use crate::mcp::protocol::ToolCallResult;
-use crate::ContentIndex;
-use crate::definitions::{CallSite, DefinitionEntry, DefinitionIndex, DefinitionKind};
+use crate::SearchError;
+use crate::{ContentIndex, Posting};
+use crate::definitions::{argument_fit, arity, call_arguments, call_graph_to_json, declared_type, di_resolves, di_services_of, edges_from_call_tree, literal_type, matches_param_types, parameters, render_call_graph, type_parameters, Arity, CallSite, DefinitionEntry, DefinitionIndex, DefinitionKind, GraphFormat, Parameter};
+use crate::owners::Owners;
 use search::generate_trigrams;
+use search::lock_stats::{self, Lock};
 
 use super::HandlerContext;
-use super::utils::{inject_branch_warning, sorted_intersect};
+use super::utils::{inject_branch_warning, inject_owners, inject_owners_source, sorted_intersect, Page};
 
 /// Built-in JavaScript/TypeScript types whose methods should never be resolved
 /// to user-defined classes. When a call site has one of these as its receiver type,
@@ -89,6 +94,14 @@ pub(crate) fn handle_search_callers(ctx: &HandlerContext, args: &Value) -> ToolC
         let raw = args.get("maxTotalNodes").and_then(|v| v.as_u64()).unwrap_or(200) as usize;
         if raw == 0 { usize::MAX } else { raw }
     };
+    let page = match Page::from_args(ctx, "search_callers", args) {
+        Ok(page) => page,
+        Err(msg) => return ToolCallResult::error(msg),
+    };
+    let export_format = match args.get("exportFormat").and_then(|v| v.as_str()).map(str::parse::<GraphFormat>).transpose() {
+        Ok(format) => format,
+        Err(msg) => return ToolCallResult::error(msg),
+    };
     let exclude_dir: Vec<String> = args.get("excludeDir")
         .and_then(|v| v.as_array())
         .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
@@ -97,19 +110,35 @@ pub(crate) fn handle_search_callers(ctx: &HandlerContext, args: &Value) -> ToolC
         .and_then(|v| v.as_array())
         .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
         .unwrap_or_default();
+    let arg_count = args.get("argCount").and_then(|v| v.as_u64()).map(|n| n as usize);
+    let param_types: Option<Vec<String>> = match args.get("paramTypes") {
+        None | Some(Value::Null) => None,
+        Some(value) => match value.as_array().and_then(|arr| arr.iter().map(|v| v.as_str().map(str::to_string)).collect()) {
+            Some(types) => Some(types),
+            None => return ToolCallResult::error("paramTypes must be an array of type names, e.g. [\"int\", \"string\"]".to_string()),
+        },
+    };
 
     let search_start = Instant::now();
 
-    let content_index = match ctx.index.read() {
-        Ok(idx) => idx,
-        Err(e) => return ToolCallResult::error(format!("Failed to acquire content index lock: {}", e)),
-    };
-    let def_idx = match def_index.read() {
+    let content_index = ctx.index.snapshot();
+    let def_idx = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
         Ok(idx) => idx,
-        Err(e) => return ToolCallResult::error(format!("Failed to acquire definition index lock: {}", e)),
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
     };
 
-    let limits = CallerLimits { max_callers_per_level, max_total_nodes };
+    // A page walks the root level up to its end; one more root tells whether there are more
+    let max_root_callers = page.as_ref().map_or(max_callers_per_level, |p| p.offset + p.size + 1);
+    let overloads = if arg_count.is_some() || param_types.is_some() {
+        match select_overloads(&def_idx, &method_name, class_filter.as_deref(), arg_count, param_types.as_deref()) {
+            Ok(filter) => Some(filter),
+            Err(msg) => return ToolCallResult::error(msg),
+        }
+    } else {
+        None
+    };
+    let overloads_json = overloads.as_ref().map(|o| o.to_json(&def_idx));
+    let limits = CallerLimits { max_callers_per_level, max_root_callers, max_total_nodes, overloads, owners: ctx.owners.clone() };
     let node_count = AtomicUsize::new(0);
 
     // Check for ambiguous method names and generate warning
@@ -206,7 +235,8 @@ pub(crate) fn handle_search_callers(ctx: &HandlerContext, args: &Value) -> ToolC
         );
 
         // Dedup: remove duplicate nodes at root level (can happen with resolveInterfaces)
-        let tree = dedup_caller_tree(tree);
+        let mut tree = dedup_caller_tree(tree);
+        let page_info = page.as_ref().map(|p| p.apply(&mut tree));
 
         let total_nodes = node_count.load(std::sync::atomic::Ordering::Relaxed);
         let truncated = total_nodes >= max_total_nodes;
@@ -217,7 +247,11 @@ pub(crate) fn handle_search_callers(ctx: &HandlerContext, args: &Value) -> ToolC
             "truncated": truncated,
             "searchTimeMs": search_elapsed.as_secs_f64() * 1000.0,
         });
+        if let Some(info) = page_info {
+            info.inject(&mut summary);
+        }
         inject_branch_warning(&mut summary, ctx);
+        inject_owners_source(&mut summary, ctx.owners.as_deref());
         let mut output = json!({
             "callTree": tree,
             "query": {
@@ -235,9 +269,16 @@ pub(crate) fn handle_search_callers(ctx: &HandlerContext, args: &Value) -> ToolC
         if let Some(ref cls) = class_filter {
             output["query"]["class"] = json!(cls);
         }
+        inject_overload_query(&mut output, arg_count, param_types.as_deref(), overloads_json.as_ref());
+        if overloads_json.is_some() {
+            output["query"]["overloadMatching"] = json!(OVERLOAD_MATCHING);
+        }
+        if let Some(format) = export_format {
+            export_call_tree(&mut output, format, &method_name, class_filter.as_deref(), true);
+        }
         ToolCallResult::success(serde_json::to_string(&output).unwrap())
     } else {
-        let tree = build_callee_tree(
+        let mut tree = build_callee_tree(
             &method_name,
             class_filter.as_deref(),
             max_depth,
@@ -251,13 +292,19 @@ pub(crate) fn handle_search_callers(ctx: &HandlerContext, args: &Value) -> ToolC
             &node_count,
         );
 
+        let page_info = page.as_ref().map(|p| p.apply(&mut tree));
+
         let total_nodes = node_count.load(std::sync::atomic::Ordering::Relaxed);
         let search_elapsed = search_start.elapsed();
         let mut summary = json!({
             "totalNodes": total_nodes,
             "searchTimeMs": search_elapsed.as_secs_f64() * 1000.0,
         });
+        if let Some(info) = page_info {
+            info.inject(&mut summary);
+        }
         inject_branch_warning(&mut summary, ctx);
+        inject_owners_source(&mut summary, ctx.owners.as_deref());
         let mut output = json!({
             "callTree": tree,
             "query": {
@@ -275,12 +322,32 @@ pub(crate) fn handle_search_callers(ctx: &HandlerContext, args: &Value) -> ToolC
         if let Some(ref cls) = class_filter {
             output["query"]["class"] = json!(cls);
         }
+        inject_overload_query(&mut output, arg_count, param_types.as_deref(), overloads_json.as_ref());
+        if let Some(format) = export_format {
+            export_call_tree(&mut output, format, &method_name, class_filter.as_deref(), false);
+        }
         ToolCallResult::success(serde_json::to_string(&output).unwrap())
     }
 }
 
 // ─── Internal helpers ───────────────────────────────────────────────
 
+/// Replace `callTree` in `output` with its edges as `graph`: DOT or Mermaid text, or
+/// the `{nodes, edges}` JSON.
+fn export_call_tree(output: &mut Value, format: GraphFormat, method: &str, class: Option<&str>, up: bool) {
+    let root = class.map_or_else(|| method.to_string(), |class| format!("{}.{}", class, method));
+    let tree = output["callTree"].as_array().cloned().unwrap_or_default();
+    let edges = edges_from_call_tree(&root, &tree, up);
+    output["graph"] = match render_call_graph(&edges, format) {
+        Some(text) => json!(text),
+        None => call_graph_to_json(&edges),
+    };
+    output["query"]["exportFormat"] = json!(format.as_str());
+    if let Some(obj) = output.as_object_mut() {
+        obj.remove("callTree");
+    }
+}
+
 /// Remove duplicate nodes from the caller tree (can occur with resolveInterfaces
 /// when the same caller is found through multiple interface implementations).
 fn dedup_caller_tree(tree: Vec<Value>) -> Vec<Value> {
@@ -299,9 +366,285 @@ fn dedup_caller_tree(tree: Vec<Value>) -> Vec<Value> {
         .collect()
 }
 
+/// Settings of one caller or callee search, shared by every level of the tree.
 struct CallerLimits {
     max_callers_per_level: usize,
+    /// `max_callers_per_level` for the root level
+    max_root_callers: usize,
     max_total_nodes: usize,
+    /// Overloads of the searched method selected by `argCount`/`paramTypes`
+    overloads: Option<OverloadFilter>,
+    /// Ownership rules annotating each node with its `owners`
+    owners: Option<Arc<Owners>>,
+}
+
+impl CallerLimits {
+    fn per_level(&self, depth: usize) -> usize {
+        if depth == 0 { self.max_root_callers } else { self.max_callers_per_level }
+    }
+
+    /// The overload filter, if it is for `method_lower`.
+    fn overloads_of(&self, method_lower: &str) -> Option<&OverloadFilter> {
+        self.overloads.as_ref().filter(|o| o.method == method_lower)
+    }
+}
+
+/// The overloads of one method a search is limited to.
+struct OverloadFilter {
+    /// Lowercased method name
+    method: String,
+    /// Selected definitions, in file and line order
+    defs: Vec<u32>,
+    /// `argCount`: only calls passing exactly this many arguments
+    arg_count: Option<usize>,
+    arities: Vec<Arity>,
+    /// Arities of the other overloads, which win calls that fit them better
+    other_arities: Vec<Arity>,
+    /// Parameters of each selected overload, with the type parameters in scope
+    params: Vec<(Vec<Parameter>, Vec<String>)>,
+    /// Lines of the caller files read to type call arguments, by definition file id
+    sources: Mutex<HashMap<u32, Option<Arc<Vec<String>>>>>,
+}
+
+impl OverloadFilter {
+    /// Whether a call passing `arg_count` arguments can target a selected overload:
+    /// one takes that many arguments and no other overload fits them better.
+    /// A call whose argument count is unknown can.
+    fn accepts(&self, arg_count: Option<u8>) -> bool {
+        let Some(n) = arg_count.map(usize::from) else { return true };
+        let Some(best) = self.arities.iter().filter_map(|a| a.fit(n)).min() else { return false };
+        self.arg_count.is_none_or(|c| c == n)
+            && self.other_arities.iter().filter_map(|a| a.fit(n)).all(|other| other >= best)
+    }
+
+    /// Whether the arguments of `call`, made in caller `caller`, can have the
+    /// parameter types of a selected overload. An argument's type is known if it is
+    /// a literal, or a parameter, typed local, field or property of the caller;
+    /// other arguments fit any type, as do calls with named arguments.
+    fn accepts_types(&self, def_idx: &DefinitionIndex, caller: &DefinitionEntry, call: &CallSite) -> bool {
+        let Some(lines) = self.source(def_idx, caller.file_id) else { return true };
+        let Some(args) = call_text(&lines, call).and_then(|text| call_arguments(&text)) else { return true };
+        // An argument split inside a generic or comparison does not line up with the parameters
+        if call.arg_count.is_some_and(|n| usize::from(n) != args.len()) {
+            return true;
+        }
+        let typescript = def_idx.files.get(caller.file_id as usize)
+            .and_then(|f| Path::new(f).extension()).and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("ts") || e.eq_ignore_ascii_case("tsx"));
+        let is_named = |arg: &str| arg.split_once(':').is_some_and(|(name, value)| {
+            !value.starts_with(':') && !name.trim().is_empty() && name.trim().chars().all(|c| c.is_alphanumeric() || c == '_')
+        });
+        if !typescript && args.iter().any(|a| is_named(a)) {
+            return true;
+        }
+        let scope = caller_scope(&lines, caller.line_start, call);
+        let arg_types: Vec<Option<String>> = args.iter()
+            .map(|arg| argument_type(def_idx, caller, &scope, arg, typescript))
+            .collect();
+        self.params.iter().any(|(params, generics)| {
+            Arity::of(params).accepts(args.len()) && arg_types.iter().enumerate().all(|(i, ty)| {
+                let Some(ty) = ty else { return true };
+                let Some(param) = params.get(i).or_else(|| params.last().filter(|p| p.variadic)) else { return true };
+                let Some(declared) = param.type_name.as_deref() else { return true };
+                if !param.variadic {
+                    return argument_fit(declared, ty, generics) != Some(false);
+                }
+                // One argument may be the array itself, or each an element of it
+                let element = declared.trim().strip_suffix("[]").unwrap_or(declared);
+                argument_fit(declared, ty, generics) != Some(false) || argument_fit(element, ty, generics) != Some(false)
+            })
+        })
+    }
+
+    /// Lines of file `file_id`, read once per search; None if it cannot be read.
+    fn source(&self, def_idx: &DefinitionIndex, file_id: u32) -> Option<Arc<Vec<String>>> {
+        let mut sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+        sources.entry(file_id).or_insert_with(|| {
+            let path = def_idx.files.get(file_id as usize)?;
+            crate::read_file_lossy(Path::new(path)).ok()
+                .map(|(content, _)| Arc::new(content.lines().map(str::to_string).collect()))
+        }).clone()
+    }
+
+    fn to_json(&self, def_idx: &DefinitionIndex) -> Value {
+        json!(self.defs.iter().map(|&di| overload_json(def_idx, di)).collect::<Vec<_>>())
+    }
+}
+
+/// How callers are matched to the selected overloads, echoed in `query.overloadMatching`.
+const OVERLOAD_MATCHING: &str = "Calls are matched by argument count, and by argument type where it is known: \
+    literals, and the caller's parameters, typed locals, fields and properties. Other arguments (calls, \
+    expressions, `var` locals without a literal initializer) and calls with named arguments match any parameter type.";
+
+/// Calls spread over more lines than this are not typed.
+const MAX_CALL_LINES: usize = 20;
+
+/// Source text from just past the called name of `call` on, up to `MAX_CALL_LINES` lines.
+fn call_text(lines: &[String], call: &CallSite) -> Option<String> {
+    let first = (call.line as usize).checked_sub(1)?;
+    let line = lines.get(first)?;
+    let start = if call.end_column > 0 {
+        line.char_indices().nth(call.end_column as usize - 1).map_or(line.len(), |(i, _)| i)
+    } else {
+        line.find(&call.method_name)? + call.method_name.len()
+    };
+    let mut text = line[start..].to_string();
+    for next in lines.iter().skip(first + 1).take(MAX_CALL_LINES - 1) {
+        text.push('\n');
+        text.push_str(next);
+    }
+    Some(text)
+}
+
+/// The caller's source from its first line up to the called name of `call`, where
+/// its parameters and the locals in scope of the call are declared.
+fn caller_scope(lines: &[String], line_start: u32, call: &CallSite) -> String {
+    let first = (line_start as usize).saturating_sub(1);
+    let last = (call.line as usize).saturating_sub(1);
+    let mut scope: Vec<&str> = lines.iter().take(last).skip(first).map(String::as_str).collect();
+    if let Some(line) = lines.get(last) {
+        let end = if call.column > 0 {
+            line.char_indices().nth(call.column as usize - 1).map_or(line.len(), |(i, _)| i)
+        } else {
+            line.len()
+        };
+        scope.push(&line[..end]);
+    }
+    scope.join("\n")
+}
+
+/// Type of argument `arg` of a call in `caller`: a literal's, or the declared type of
+/// the caller's parameter, local, field or property it names. None if not known.
+fn argument_type(def_idx: &DefinitionIndex, caller: &DefinitionEntry, scope: &str, arg: &str, typescript: bool) -> Option<String> {
+    let mut arg = arg.trim();
+    for modifier in ["ref ", "in ", "out "] {
+        arg = arg.strip_prefix(modifier).unwrap_or(arg).trim_start();
+    }
+    if let Some(ty) = literal_type(arg, typescript) {
+        return Some(ty);
+    }
+    let member = arg.strip_prefix("this.");
+    let name = member.unwrap_or(arg);
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit())
+        || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+        return None;
+    }
+    if member.is_none()
+        && let Some(ty) = declared_type(scope, name, typescript) {
+            return ty;
+        }
+    // A field or property of the caller's class
+    let parent = caller.parent.as_deref()?;
+    def_idx.name_index.get(&name.to_lowercase()).into_iter().flatten()
+        .filter_map(|&di| def_idx.definitions.get(di as usize))
+        .find(|d| d.file_id == caller.file_id && d.name == name && d.parent.as_deref() == Some(parent)
+            && matches!(d.kind, DefinitionKind::Field | DefinitionKind::Property))
+        .and_then(|d| declared_type(d.signature.as_deref()?, name, typescript).flatten())
+}
+
+fn overload_json(def_idx: &DefinitionIndex, di: u32) -> Value {
+    let def = &def_idx.definitions[di as usize];
+    let mut obj = json!({
+        "signature": def.signature.as_deref().unwrap_or(&def.name),
+        "line": def.line_start,
+    });
+    if let Some(ref parent) = def.parent {
+        obj["class"] = json!(parent);
+    }
+    if let Some(fname) = def_idx.files.get(def.file_id as usize).and_then(|f| Path::new(f).file_name()).and_then(|f| f.to_str()) {
+        obj["file"] = json!(fname);
+    }
+    obj
+}
+
+/// Select the overloads of `method` (in `class`, if given) that accept `arg_count`
+/// arguments and have the parameter types `param_types`. Errors list the overloads
+/// there are when none matches.
+fn select_overloads(
+    def_idx: &DefinitionIndex,
+    method: &str,
+    class: Option<&str>,
+    arg_count: Option<usize>,
+    param_types: Option<&[String]>,
+) -> Result<OverloadFilter, String> {
+    let method_lower = method.to_lowercase();
+    let mut all: Vec<u32> = def_idx.name_index.get(&method_lower).into_iter().flatten().copied()
+        .filter(|&di| def_idx.definitions.get(di as usize).is_some_and(|d| {
+            matches!(d.kind, DefinitionKind::Method | DefinitionKind::Constructor | DefinitionKind::Function)
+                && class.is_none_or(|cls| d.parent.as_deref().is_some_and(|p| p.eq_ignore_ascii_case(cls)))
+        }))
+        .collect();
+    all.sort_by_key(|&di| {
+        let def = &def_idx.definitions[di as usize];
+        (def_idx.files.get(def.file_id as usize), def.line_start)
+    });
+
+    let defs: Vec<u32> = all.iter().copied().filter(|&di| {
+        let def = &def_idx.definitions[di as usize];
+        arg_count.is_none_or(|n| arity(def).accepts(n))
+            && param_types.is_none_or(|types| matches_param_types(def, types, &class_type_parameters(def_idx, def)))
+    }).collect();
+
+    if defs.is_empty() {
+        let mut wanted = Vec::new();
+        if let Some(n) = arg_count {
+            wanted.push(format!("takes {} argument{}", n, if n == 1 { "" } else { "s" }));
+        }
+        if let Some(types) = param_types {
+            wanted.push(format!("has parameter types ({})", types.join(", ")));
+        }
+        let listed: Vec<String> = all.iter().take(10).map(|&di| {
+            let def = &def_idx.definitions[di as usize];
+            def.signature.clone().unwrap_or_else(|| def.name.clone())
+        }).collect();
+        return Err(if listed.is_empty() {
+            format!("Method '{}' not found{}.", method, class.map(|c| format!(" in class '{}'", c)).unwrap_or_default())
+        } else {
+            format!("No overload of '{}' {}. Overloads: {}", method, wanted.join(" and "), listed.join("; "))
+        });
+    }
+    let arities = defs.iter().map(|&di| arity(&def_idx.definitions[di as usize])).collect();
+    let other_arities = all.iter().filter(|di| !defs.contains(di)).map(|&di| arity(&def_idx.definitions[di as usize])).collect();
+    let params = defs.iter().map(|&di| {
+        let def = &def_idx.definitions[di as usize];
+        let mut generics = class_type_parameters(def_idx, def);
+        generics.extend(def.signature.as_deref().map(|s| type_parameters(s, &def.name)).unwrap_or_default());
+        (parameters(def), generics)
+    }).collect();
+    Ok(OverloadFilter { method: method_lower, defs, arg_count, arities, other_arities, params, sources: Mutex::new(HashMap::new()) })
+}
+
+/// Type parameters of the class declaring `def` (`T` of `Repository<T>`).
+fn class_type_parameters(def_idx: &DefinitionIndex, def: &DefinitionEntry) -> Vec<String> {
+    let Some(parent) = def.parent.as_deref() else { return Vec::new() };
+    def_idx.name_index.get(&parent.to_lowercase()).into_iter().flatten()
+        .filter_map(|&di| def_idx.definitions.get(di as usize))
+        .filter(|d| d.file_id == def.file_id && d.kind != DefinitionKind::Method)
+        .flat_map(|d| d.signature.as_deref().map(|sig| type_parameters(sig, parent)).unwrap_or_default())
+        .collect()
+}
+
+/// Add `argCount`, `paramTypes` and the selected `overloads` to the query echo.
+fn inject_overload_query(output: &mut Value, arg_count: Option<usize>, param_types: Option<&[String]>, overloads: Option<&Value>) {
+    if let Some(n) = arg_count {
+        output["query"]["argCount"] = json!(n);
+    }
+    if let Some(types) = param_types {
+        output["query"]["paramTypes"] = json!(types);
+    }
+    if let Some(overloads) = overloads {
+        output["query"]["overloads"] = overloads.clone();
+    }
+}
+
+/// Whether a call of `method_lower` on `line` of caller `caller_di` passes arguments
+/// one of the selected overloads accepts: as many as it takes, of types it takes
+/// where they are known.
+fn call_matches_overloads(def_idx: &DefinitionIndex, caller_di: u32, line: u32, method_lower: &str, filter: &OverloadFilter) -> bool {
+    let Some(caller) = def_idx.definitions.get(caller_di as usize) else { return false };
+    def_idx.method_calls.get(&caller_di).into_iter().flatten()
+        .filter(|cs| cs.line == line && cs.method_name.eq_ignore_ascii_case(method_lower))
+        .any(|cs| filter.accepts(cs.arg_count) && filter.accepts_types(def_idx, caller, cs))
 }
 
 /// Find the containing method for a given file_id and line number in the definition index.
@@ -484,6 +827,14 @@ fn verify_call_site_target(
                 if rt_lower == target_lower {
                     return true;
                 }
+                // DI registrations are exact: a registered service resolves to its
+                // registered implementations only, whatever the names suggest
+                if let Some(resolves) = di_resolves(def_idx, rt, target_class) {
+                    if resolves {
+                        return true;
+                    }
+                    continue;
+                }
                 // Interface match: receiver is IFoo, target is Foo
                 if rt_lower == target_interface {
                     return true;
@@ -580,9 +931,11 @@ fn build_caller_tree(
     }
 
     let method_lower = method_name.to_lowercase();
+    let overloads = limits.overloads_of(&method_lower);
 
     // Find line_start of first matching method definition for overload disambiguation
-    let target_line = def_idx.name_index.get(&method_lower)
+    let target_line = overloads.and_then(|o| o.defs.first()).map(|&di| def_idx.definitions[di as usize].line_start)
+        .or_else(|| def_idx.name_index.get(&method_lower)
         .and_then(|indices| indices.iter().find_map(|&di| {
             def_idx.definitions.get(di as usize).and_then(|d| {
                 if matches!(d.kind, DefinitionKind::Method | DefinitionKind::Constructor | DefinitionKind::Function) {
@@ -596,7 +949,7 @@ fn build_caller_tree(
                 }
                 None
             })
-        }));
+        })));
 
     // Use class.method.line as visited key to distinguish overloads
     let visited_key = if let Some(cls) = parent_class {
@@ -608,10 +961,17 @@ fn build_caller_tree(
         return Vec::new();
     }
 
-    let postings = match content_index.index.get(&method_lower) {
+    let postings = match content_index.index.get(method_lower.as_str()) {
         Some(p) => p,
         None => return Vec::new(),
     };
+    // Visit files in path order so maxCallersPerLevel keeps the same callers on every run
+    let mut postings: Vec<&Posting> = postings.iter().collect();
+    postings.sort_by(|a, b| {
+        let path_a = content_index.files.get(a.file_id as usize);
+        let path_b = content_index.files.get(b.file_id as usize);
+        path_a.cmp(&path_b).then(a.file_id.cmp(&b.file_id))
+    });
 
     // Pre-compute: which content index file_ids contain the parent class token?
     // This filters out files that use the same method name but from a different class.
@@ -621,28 +981,35 @@ fn build_caller_tree(
         let mut file_ids: HashSet<u32> = HashSet::new();
 
         // Add files containing the class name directly
-        if let Some(postings) = content_index.index.get(&cls_lower) {
+        if let Some(postings) = content_index.index.get(cls_lower.as_str()) {
             file_ids.extend(postings.iter().map(|p| p.file_id));
         }
 
         // Also check for interface name (IClassName pattern for DI)
         let interface_name = format!("i{}", cls_lower);
-        if let Some(postings) = content_index.index.get(&interface_name) {
+        if let Some(postings) = content_index.index.get(interface_name.as_str()) {
             file_ids.extend(postings.iter().map(|p| p.file_id));
         }
 
+        // Service types the class is registered as in the DI container
+        for service in di_services_of(def_idx, cls) {
+            if let Some(postings) = content_index.index.get(service.as_str()) {
+                file_ids.extend(postings.iter().map(|p| p.file_id));
+            }
+        }
+
         // Fuzzy DI: find implementations of I{ClassName} via base_type_index
         // and add files containing those implementation class names
         let impls = find_implementations_of_interface(def_idx, &interface_name);
         for impl_lower in &impls {
-            if let Some(postings) = content_index.index.get(impl_lower) {
+            if let Some(postings) = content_index.index.get(impl_lower.as_str()) {
                 file_ids.extend(postings.iter().map(|p| p.file_id));
             }
         }
         // Also find implementations of the class itself (if cls IS an interface)
         let impls_of_cls = find_implementations_of_interface(def_idx, &cls_lower);
         for impl_lower in &impls_of_cls {
-            if let Some(postings) = content_index.index.get(impl_lower) {
+            if let Some(postings) = content_index.index.get(impl_lower.as_str()) {
                 file_ids.extend(postings.iter().map(|p| p.file_id));
             }
         }
@@ -675,7 +1042,7 @@ fn build_caller_tree(
     }
 
     for posting in postings {
-        if callers.len() >= limits.max_callers_per_level {
+        if callers.len() >= limits.per_level(current_depth) {
             break;
         }
         if node_count.load(std::sync::atomic::Ordering::Relaxed) >= limits.max_total_nodes {
@@ -712,7 +1079,7 @@ fn build_caller_tree(
         };
 
         for &line in &posting.lines {
-            if callers.len() >= limits.max_callers_per_level { break; }
+            if callers.len() >= limits.per_level(current_depth) { break; }
             if node_count.load(std::sync::atomic::Ordering::Relaxed) >= limits.max_total_nodes { break; }
 
             if definition_locations.contains(&(def_fid, line)) {
@@ -735,6 +1102,10 @@ fn build_caller_tree(
                         continue;
                     }
                 }
+                if let Some(filter) = overloads
+                    && !call_matches_overloads(def_idx, caller_di, line, &method_lower, filter) {
+                        continue;
+                    }
 
                 let caller_key = format!("{}.{}.{}",
                     caller_parent.as_deref().unwrap_or("?"),
@@ -747,7 +1118,8 @@ fn build_caller_tree(
                 }
                 seen_callers.insert(caller_key.clone());
 
-                node_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let nodes = node_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+                progress::report(nodes, None, "building caller tree");
 
                 // Recurse without parent_class filter. The parent_class
                 // disambiguation is most useful at the initial level to
@@ -781,9 +1153,13 @@ fn build_caller_tree(
                 if let Some(fname) = Path::new(file_path).file_name().and_then(|f| f.to_str()) {
                     node["file"] = json!(fname);
                 }
+                inject_owners(&mut node, limits.owners.as_deref(), file_path);
                 if !sub_callers.is_empty() {
                     node["callers"] = json!(sub_callers);
                 }
+                if current_depth == 0 {
+                    progress::partial(|| node.clone());
+                }
                 callers.push(node);
             }
         }
@@ -808,6 +1184,8 @@ fn build_caller_tree(
                 if pc_lower.starts_with('i') && pc_lower.len() > 1 {
                     related.insert(pc_lower[1..].to_string());
                 }
+                // Service types the target class is registered as
+                related.extend(di_services_of(def_idx, pc));
                 // Target class's own base_types (interfaces it implements)
                 if let Some(indices) = def_idx.name_index.get(&pc_lower) {
                     for &idx in indices {
@@ -1045,9 +1423,11 @@ fn build_callee_tree(
     }
 
     let method_lower = method_name.to_lowercase();
+    let overloads = limits.overloads_of(&method_lower);
 
     // Find line_start of first matching method definition for overload disambiguation
-    let target_line = def_idx.name_index.get(&method_lower)
+    let target_line = overloads.and_then(|o| o.defs.first()).map(|&di| def_idx.definitions[di as usize].line_start)
+        .or_else(|| def_idx.name_index.get(&method_lower)
         .and_then(|indices| indices.iter().find_map(|&di| {
             def_idx.definitions.get(di as usize).and_then(|d| {
                 if matches!(d.kind, DefinitionKind::Method | DefinitionKind::Constructor | DefinitionKind::Function) {
@@ -1061,7 +1441,7 @@ fn build_callee_tree(
                 }
                 None
             })
-        }));
+        })));
 
     // Use class.method.line as visit key to distinguish overloads
     let visit_key = if let Some(cls) = class_filter {
@@ -1074,7 +1454,7 @@ fn build_callee_tree(
     }
 
     // Find all definitions of this method (with their def_idx indices)
-    let method_def_indices: Vec<u32> = def_idx.name_index
+    let mut method_def_indices: Vec<u32> = def_idx.name_index
         .get(&method_lower)
         .map(|indices| {
             indices.iter()
@@ -1101,15 +1481,23 @@ fn build_callee_tree(
         })
         .unwrap_or_default();
 
+    if let Some(filter) = overloads {
+        method_def_indices.retain(|di| filter.defs.contains(di));
+    }
     if method_def_indices.is_empty() {
         return Vec::new();
     }
+    // Definition order follows parallel parse order; walk overloads by location instead
+    method_def_indices.sort_by_key(|&di| {
+        let def = &def_idx.definitions[di as usize];
+        (def_idx.files.get(def.file_id as usize), def.line_start)
+    });
 
     let mut callees: Vec<Value> = Vec::new();
     let mut seen_callees: HashSet<String> = HashSet::new();
 
     for &method_di in &method_def_indices {
-        if callees.len() >= limits.max_callers_per_level { break; }
+        if callees.len() >= limits.per_level(current_depth) { break; }
         if node_count.load(std::sync::atomic::Ordering::Relaxed) >= limits.max_total_nodes { break; }
 
         // Get pre-computed call sites for this method
@@ -1119,7 +1507,7 @@ fn build_callee_tree(
         };
 
         for call in call_sites {
-            if callees.len() >= limits.max_callers_per_level { break; }
+            if callees.len() >= limits.per_level(current_depth) { break; }
             if node_count.load(std::sync::atomic::Ordering::Relaxed) >= limits.max_total_nodes { break; }
 
             // Resolve this call site to actual definitions
@@ -1128,7 +1516,7 @@ fn build_callee_tree(
             let resolved = resolve_call_site(call, def_idx, caller_parent);
 
             for callee_di in resolved {
-                if callees.len() >= limits.max_callers_per_level { break; }
+                if callees.len() >= limits.per_level(current_depth) { break; }
                 if node_count.load(std::sync::atomic::Ordering::Relaxed) >= limits.max_total_nodes { break; }
 
                 let callee_def = match def_idx.definitions.get(callee_di as usize) {
@@ -1163,7 +1551,8 @@ fn build_callee_tree(
                 if seen_callees.contains(&callee_key) { continue; }
                 seen_callees.insert(callee_key.clone());
 
-                node_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let nodes = node_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+                progress::report(nodes, None, "building callee tree");
 
                 let sub_callees = build_callee_tree(
                     &callee_def.name,
@@ -1184,18 +1573,28 @@ fn build_callee_tree(
                     "line": callee_def.line_start,
                     "callSiteLine": call.line,
                 });
+                if call.column > 0 {
+                    node["callSiteColumn"] = json!(call.column);
+                }
                 if let Some(ref parent) = callee_def.parent {
                     node["class"] = json!(parent);
                 }
                 if let Some(fname) = Path::new(callee_file).file_name().and_then(|f| f.to_str()) {
                     node["file"] = json!(fname);
                 }
+                inject_owners(&mut node, limits.owners.as_deref(), callee_file);
                 if let Some(ref recv) = call.receiver_type {
                     node["receiverType"] = json!(recv);
                 }
+                if let Some(ref receiver) = call.receiver {
+                    node["receiver"] = json!(receiver);
+                }
                 if !sub_callees.is_empty() {
                     node["callees"] = json!(sub_callees);
                 }
+                if current_depth == 0 {
+                    progress::partial(|| node.clone());
+                }
                 callees.push(node);
             }
         }
@@ -1384,6 +1783,16 @@ pub(crate) fn resolve_call_site(call: &CallSite, def_idx: &DefinitionIndex, call
         }
     }
 
+    // Overloads: keep the candidates the call's arguments fit best
+    if let Some(n) = call.arg_count.map(usize::from)
+        && resolved.len() > 1 {
+            let fits: Vec<Option<u8>> = resolved.iter().map(|&di| arity(&def_idx.definitions[di as usize]).fit(n)).collect();
+            if let Some(best) = fits.iter().flatten().min().copied() {
+                let mut fits = fits.into_iter();
+                resolved.retain(|_| fits.next().flatten() == Some(best));
+            }
+        }
+
     resolved
 }
 
@@ -1391,6 +1800,7 @@ pub(crate) fn resolve_call_site(call: &CallSite, def_idx: &DefinitionIndex, call
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{SkippedFiles, TokenizerSettings};
     use crate::definitions::{CallSite, DefinitionEntry, DefinitionIndex, DefinitionKind};
     use std::collections::HashMap;
 
@@ -1429,6 +1839,13 @@ mod tests {
             lossy_file_count: 0,
             empty_file_ids: Vec::new(),
             extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+            walk_limits: Default::default(),
+            file_imports: HashMap::new(),
+            di_registrations: Vec::new(),
+            endpoints: Vec::new(),
+            partial_types: HashMap::new(),
+            doc_comments: HashMap::new(),
+            imports: Vec::new(),
         }
     }
 
@@ -1484,6 +1901,10 @@ mod tests {
                 receiver_type: Some("OrderValidator".to_string()),
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -1513,6 +1934,10 @@ mod tests {
                 receiver_type: Some("Path".to_string()),
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -1541,6 +1966,10 @@ mod tests {
                 receiver_type: None,
                 line: 55,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -1570,6 +1999,10 @@ mod tests {
                 receiver_type: None,
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -1595,6 +2028,10 @@ mod tests {
                 receiver_type: Some("SomeRandomClass".to_string()),
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -1643,6 +2080,10 @@ mod tests {
                 receiver_type: Some("IOrderValidator".to_string()),
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -1671,6 +2112,10 @@ mod tests {
                 receiver_type: Some("String".to_string()),
                 line: 10,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -1694,7 +2139,7 @@ mod tests {
         // We test this by running build_caller_tree and verifying that
         // only the file with the actual call is in the results.
 
-        use crate::{ContentIndex, Posting, TrigramIndex};
+        use crate::{ContentIndex, Posting, Token, TrigramIndex};
         use std::sync::atomic::AtomicUsize;
         use std::path::PathBuf;
 
@@ -1766,6 +2211,10 @@ mod tests {
                 receiver_type: Some("ResourceManager".to_string()),
                 line: 15,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -1814,22 +2263,29 @@ mod tests {
             lossy_file_count: 0,
             empty_file_ids: Vec::new(),
             extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+            walk_limits: Default::default(),
+            file_imports: HashMap::new(),
+            di_registrations: Vec::new(),
+            endpoints: Vec::new(),
+            partial_types: HashMap::new(),
+            doc_comments: HashMap::new(),
+            imports: Vec::new(),
         };
 
         // --- Content Index ---
         // Token "dispose" appears in file 0 (definition) and file 1 (actual call)
         // Token "idisposable" appears in files 2..11 (many files mentioning the interface)
         // Token "resourcemanager" appears only in file 0 and file 1
-        let mut index: HashMap<String, Vec<Posting>> = HashMap::new();
+        let mut index: HashMap<Token, Vec<Posting>> = HashMap::new();
 
         // "dispose" in file 0 (definition, line 10) and file 1 (call, line 15)
-        index.insert("dispose".to_string(), vec![
+        index.insert("dispose".into(), vec![
             Posting { file_id: 0, lines: vec![10] },
             Posting { file_id: 1, lines: vec![15] },
         ]);
 
         // "resourcemanager" in file 0 and file 1
-        index.insert("resourcemanager".to_string(), vec![
+        index.insert("resourcemanager".into(), vec![
             Posting { file_id: 0, lines: vec![1] },
             Posting { file_id: 1, lines: vec![15] },
         ]);
@@ -1838,7 +2294,7 @@ mod tests {
         let idisposable_postings: Vec<Posting> = (2..num_files)
             .map(|fid| Posting { file_id: fid, lines: vec![1, 5, 10] })
             .collect();
-        index.insert("idisposable".to_string(), idisposable_postings);
+        index.insert("idisposable".into(), idisposable_postings);
 
         let content_index = ContentIndex {
             root: ".".to_string(),
@@ -1853,13 +2309,25 @@ mod tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
 
         // --- Run build_caller_tree ---
         let mut visited = HashSet::new();
         let limits = CallerLimits {
             max_callers_per_level: 50,
+            max_root_callers: 50,
             max_total_nodes: 200,
+            overloads: None,
+            owners: None,
         };
         let node_count = AtomicUsize::new(0);
 
@@ -1906,6 +2374,10 @@ mod tests {
             receiver_type: None,
             line: 10,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         };
 
         let resolved_a = resolve_call_site(&call, &def_idx, Some("ClassA"));
@@ -1940,16 +2412,16 @@ mod tests {
 
         let mut method_calls: HashMap<u32, Vec<CallSite>> = HashMap::new();
         method_calls.insert(1, vec![
-            CallSite { method_name: "run".to_string(), receiver_type: Some("Helper".to_string()), line: 10, receiver_is_generic: false },
-            CallSite { method_name: "internalWork".to_string(), receiver_type: None, line: 15, receiver_is_generic: false },
+            CallSite { method_name: "run".to_string(), receiver_type: Some("Helper".to_string()), line: 10, receiver_is_generic: false, column: 0, end_column: 0, receiver: None, arg_count: None },
+            CallSite { method_name: "internalWork".to_string(), receiver_type: None, line: 15, receiver_is_generic: false, column: 0, end_column: 0, receiver: None, arg_count: None },
         ]);
         method_calls.insert(4, vec![
-            CallSite { method_name: "helperStep".to_string(), receiver_type: None, line: 12, receiver_is_generic: false },
+            CallSite { method_name: "helperStep".to_string(), receiver_type: None, line: 12, receiver_is_generic: false, column: 0, end_column: 0, receiver: None, arg_count: None },
         ]);
 
         let def_idx = make_def_index(definitions, method_calls);
         let mut visited = HashSet::new();
-        let limits = CallerLimits { max_callers_per_level: 50, max_total_nodes: 200 };
+        let limits = CallerLimits { max_callers_per_level: 50, max_root_callers: 50, max_total_nodes: 200, overloads: None, owners: None };
         let node_count = AtomicUsize::new(0);
 
         let callees = build_callee_tree("process", Some("ClassA"), 3, 0, &def_idx, "ts", &[], &[], &mut visited, &limits, &node_count);
@@ -2001,6 +2473,10 @@ mod tests {
             receiver_type: Some("DataList".to_string()),
             line: 252,
             receiver_is_generic: true, // <-- the key: call site had generics
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         };
 
         // Should NOT resolve because the only DataList class is non-generic
@@ -2014,6 +2490,10 @@ mod tests {
             receiver_type: Some("DataList".to_string()),
             line: 300,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         };
 
         // SHOULD resolve — both non-generic
@@ -2047,6 +2527,10 @@ mod tests {
                 receiver_type: Some("Promise".to_string()),
                 line: 10,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2058,6 +2542,10 @@ mod tests {
             receiver_type: Some("Promise".to_string()),
             line: 10,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         };
 
         let resolved = resolve_call_site(&call, &def_idx, Some("Worker"));
@@ -2086,6 +2574,10 @@ mod tests {
                 receiver_type: Some("Array".to_string()),
                 line: 10,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2096,6 +2588,10 @@ mod tests {
             receiver_type: Some("Array".to_string()),
             line: 10,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         };
 
         let resolved = resolve_call_site(&call, &def_idx, Some("Processor"));
@@ -2123,6 +2619,10 @@ mod tests {
                 receiver_type: Some("MyService".to_string()),
                 line: 10,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2133,6 +2633,10 @@ mod tests {
             receiver_type: Some("MyService".to_string()),
             line: 10,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         };
 
         let resolved = resolve_call_site(&call, &def_idx, Some("Controller"));
@@ -2206,6 +2710,10 @@ mod tests {
                 receiver_type: Some("IDataModelService".to_string()),
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2237,6 +2745,10 @@ mod tests {
                 receiver_type: Some("IService".to_string()),
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2272,6 +2784,10 @@ mod tests {
                 receiver_type: Some("IDataModelService".to_string()),
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2302,6 +2818,10 @@ mod tests {
                 receiver_type: Some("DataModelWebService".to_string()),
                 line: 25,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2312,6 +2832,49 @@ mod tests {
             "DataModelWebService → IDataModelService should match via reverse is_implementation_of");
     }
 
+    // ─── Test 22c: DI registrations replace the fuzzy interface match ──
+
+    #[test]
+    fn test_verify_di_registration_is_exact() {
+        let definitions = vec![
+            class_def(0, "SomeController", vec![]),                        // idx 0
+            method_def(0, "process", "SomeController", 20, 40),           // idx 1
+            class_def(1, "DataModelWebService", vec![]),                   // idx 2
+            method_def(1, "getData", "DataModelWebService", 10, 30),      // idx 3
+            class_def(2, "CachedDataModel", vec![]),                       // idx 4
+            method_def(2, "getData", "CachedDataModel", 10, 30),          // idx 5
+        ];
+        let mut method_calls = HashMap::new();
+        method_calls.insert(1u32, vec![
+            CallSite {
+                method_name: "getData".to_string(),
+                receiver_type: Some("IDataModelService".to_string()),
+                line: 25,
+                receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
+            },
+        ]);
+        let mut def_idx = make_def_index(definitions, method_calls);
+        def_idx.di_registrations.push(crate::definitions::DiRegistration {
+            service: "Shop.IDataModelService".to_string(),
+            implementation: Some("CachedDataModel".to_string()),
+            lifetime: "scoped".to_string(),
+            file_id: 3,
+            line: 12,
+        });
+
+        // The name matches the interface, but the container resolves it to CachedDataModel
+        assert!(!verify_call_site_target(&def_idx, 1, 25, "getData", Some("DataModelWebService")));
+        assert!(verify_call_site_target(&def_idx, 1, 25, "getData", Some("CachedDataModel")));
+
+        // A factory may build anything: back to the name-based match
+        def_idx.di_registrations[0].implementation = None;
+        assert!(verify_call_site_target(&def_idx, 1, 25, "getData", Some("DataModelWebService")));
+    }
+
     // ─── Test 22: find_implementations_of_interface via base_type_index ──
 
     #[test]
@@ -2356,6 +2919,10 @@ mod tests {
                 receiver_type: Some("TokenType".to_string()),
                 line: 15,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2388,6 +2955,10 @@ mod tests {
                 receiver_type: Some("TokenType".to_string()),
                 line: 15,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2422,6 +2993,10 @@ mod tests {
                 receiver_type: Some("ISearchService".to_string()),
                 line: 20,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             },
         ]);
 
@@ -2710,6 +3285,10 @@ mod tests {
             receiver_type: Some("IDataModelService".to_string()),
             line: 5,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         };
 
         let resolved = resolve_call_site(&call, &def_idx, None);
@@ -1,14 +1,21 @@
 //! search_definitions handler: AST-based code definition search.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
 use serde_json::{json, Value};
 
 use crate::mcp::protocol::ToolCallResult;
-use crate::definitions::{DefinitionEntry, DefinitionKind, CodeStats};
-
-use super::utils::{inject_body_into_obj, inject_branch_warning, best_match_tier};
+use crate::SearchError;
+use crate::definitions::{class_metrics, doc_comment, doc_text, namespace_of, partial_parts_json, DefinitionEntry, DefinitionIndex, DefinitionKind, DefinitionQuery, CodeStats, DocComment};
+use crate::owners::Owners;
+use crate::rank_hook::{repo_path, Candidate, RankHook, RankTool};
+use search::lock_stats::{self, Lock};
+
+use super::utils::{
+    inject_body_into_obj, inject_branch_warning, inject_owners, inject_owners_source, owner_allows,
+    parse_owner_filter, best_match_tier, Page,
+};
 use super::HandlerContext;
 
 /// Returns 0 for type-level definitions (class, interface, enum, struct, record),
@@ -32,11 +39,35 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
         ),
     };
 
-    let index = match def_index.read() {
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
         Ok(idx) => idx,
-        Err(e) => return ToolCallResult::error(format!("Failed to acquire definition index lock: {}", e)),
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let page = match Page::from_args(ctx, "search_definitions", args) {
+        Ok(page) => page,
+        Err(msg) => return ToolCallResult::error(msg),
     };
+    let rank_hook = ctx.rank_hook.as_deref()
+        .filter(|_| args.get("rankHook").and_then(|v| v.as_bool()).unwrap_or(true));
+    match search_definitions_json(&index, args, ctx.owners.as_deref(), page.as_ref(), rank_hook) {
+        Ok(mut output) => {
+            if let Some(summary) = output.get_mut("summary") {
+                inject_branch_warning(summary, ctx);
+            }
+            ToolCallResult::success(serde_json::to_string(&output).unwrap())
+        }
+        Err(msg) => ToolCallResult::error(msg),
+    }
+}
 
+/// Run a `search_definitions` query against an index and build the JSON response.
+/// Shared by the MCP handler and the `search defs` CLI command; `args` uses the
+/// MCP parameter names (see [`parse_def_query`] for the CLI grammar). With `owners`,
+/// results carry an `owners` array and the `owner` filter is available. A `page`
+/// replaces `maxResults`. A `rank_hook` re-scores the relevance order (not a `sortBy`
+/// order) before the cut.
+pub(crate) fn search_definitions_json(index: &DefinitionIndex, args: &Value, owners: Option<&Owners>, page: Option<&Page>, rank_hook: Option<&RankHook>) -> Result<Value, String> {
     let search_start = Instant::now();
 
     let name_filter = args.get("name").and_then(|v| v.as_str())
@@ -46,10 +77,17 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
     let base_type_filter = args.get("baseType").and_then(|v| v.as_str());
     let file_filter = args.get("file").and_then(|v| v.as_str());
     let parent_filter = args.get("parent").and_then(|v| v.as_str());
+    let namespace_filter = args.get("namespace").and_then(|v| v.as_str())
+        .map(str::trim).filter(|s| !s.is_empty());
+    let docs_terms: Option<Vec<String>> = args.get("docs").and_then(|v| v.as_str())
+        .map(|s| s.split_whitespace().map(str::to_lowercase).collect::<Vec<_>>())
+        .filter(|terms| !terms.is_empty());
+    // A docs search implies includeDocs
+    let include_docs = args.get("includeDocs").and_then(|v| v.as_bool()).unwrap_or(false) || docs_terms.is_some();
     let contains_line = match args.get("containsLine") {
         Some(v) if v.is_i64() || v.is_u64() => {
             match v.as_i64() {
-                Some(n) if n < 1 => return ToolCallResult::error(
+                Some(n) if n < 1 => return Err(
                     format!("containsLine must be >= 1, got {}", n)
                 ),
                 Some(n) => Some(n as u32),
@@ -70,6 +108,8 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
     let max_body_lines = args.get("maxBodyLines").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
     let max_total_body_lines = args.get("maxTotalBodyLines").and_then(|v| v.as_u64()).unwrap_or(500) as usize;
     let audit = args.get("audit").and_then(|v| v.as_bool()).unwrap_or(false);
+    let owner_filter = parse_owner_filter(owners, args)?;
+    let owner_filter = owner_filter.as_deref();
 
     // Code stats parameters
     let sort_by = args.get("sortBy").and_then(|v| v.as_str());
@@ -79,14 +119,24 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
     let min_params = args.get("minParams").and_then(|v| v.as_u64()).map(|v| v as u8);
     let min_returns = args.get("minReturns").and_then(|v| v.as_u64()).map(|v| v as u8);
     let min_calls = args.get("minCalls").and_then(|v| v.as_u64()).map(|v| v as u16);
+    let min_volume = args.get("minHalsteadVolume").and_then(|v| v.as_f64()).map(|v| v as f32);
+    let min_difficulty = args.get("minHalsteadDifficulty").and_then(|v| v.as_f64()).map(|v| v as f32);
+    let max_maintainability = args.get("maxMaintainabilityIndex").and_then(|v| v.as_u64()).map(|v| v.min(100) as u8);
+    let min_wmc = args.get("minWmc").and_then(|v| v.as_u64()).map(|v| v as u32);
+    let min_lcom = args.get("minLcom").and_then(|v| v.as_u64()).map(|v| v as u32);
 
-    let has_stats_filter = sort_by.is_some()
-        || min_complexity.is_some()
+    let has_member_filter = min_complexity.is_some()
         || min_cognitive.is_some()
         || min_nesting.is_some()
         || min_params.is_some()
         || min_returns.is_some()
-        || min_calls.is_some();
+        || min_calls.is_some()
+        || min_volume.is_some()
+        || min_difficulty.is_some()
+        || max_maintainability.is_some();
+    // WMC and LCOM are class-level: they select classes instead of methods
+    let class_level = matches!(sort_by, Some("wmc" | "lcom")) || min_wmc.is_some() || min_lcom.is_some();
+    let has_stats_filter = sort_by.is_some() || has_member_filter || class_level;
 
     // sortBy and min* imply includeCodeStats=true
     let include_code_stats = args.get("includeCodeStats").and_then(|v| v.as_bool()).unwrap_or(false)
@@ -95,14 +145,20 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
     // Validate sortBy value
     if let Some(sort_field) = sort_by {
         let valid = ["cyclomaticComplexity", "cognitiveComplexity", "maxNestingDepth",
-                     "paramCount", "returnCount", "callCount", "lambdaCount", "lines"];
+                     "paramCount", "returnCount", "callCount", "lambdaCount", "lines",
+                     "halsteadVolume", "halsteadDifficulty", "maintainabilityIndex", "wmc", "lcom"];
         if !valid.contains(&sort_field) {
-            return ToolCallResult::error(format!(
+            return Err(format!(
                 "Invalid sortBy value '{}'. Valid values: {}",
                 sort_field, valid.join(", ")
             ));
         }
     }
+    if class_level && (has_member_filter || matches!(sort_by, Some(f) if f != "wmc" && f != "lcom")) {
+        return Err(
+            "Class-level metrics (sortBy wmc/lcom, minWmc, minLcom) cannot be combined with method-level sortBy or min* filters.".to_string()
+        );
+    }
 
     // --- audit mode: return index coverage report ---
     if audit {
@@ -134,13 +190,13 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
             },
             "suspiciousFiles": suspicious,
         });
-        return ToolCallResult::success(serde_json::to_string(&output).unwrap());
+        return Ok(output);
     }
 
     // --- containsLine: find containing method/class by line number ---
     if let Some(line_num) = contains_line {
         if file_filter.is_none() {
-            return ToolCallResult::error(
+            return Err(
                 "containsLine requires 'file' parameter to identify the file.".to_string()
             );
         }
@@ -151,7 +207,8 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
         let mut file_cache: HashMap<String, Option<String>> = HashMap::new();
         let mut total_body_lines_emitted: usize = 0;
         for (file_id, file_path) in index.files.iter().enumerate() {
-            if !file_path.replace('\\', "/").to_lowercase().contains(&file_substr) {
+            if !file_path.replace('\\', "/").to_lowercase().contains(&file_substr)
+                || !owner_allows(owners, owner_filter, file_path) {
                 continue;
             }
             // Get all definitions in this file
@@ -164,7 +221,7 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
                     .collect();
 
                 // Sort by range size (smallest first = most specific)
-                matching.sort_by_key(|d| d.line_end - d.line_start);
+                matching.sort_by_key(|d| (d.line_end - d.line_start, d.line_start));
 
                 for def in &matching {
                     let mut obj = json!({
@@ -173,6 +230,7 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
                         "file": file_path,
                         "lines": format!("{}-{}", def.line_start, def.line_end),
                     });
+                    inject_owners(&mut obj, owners, file_path);
                     if let Some(ref parent) = def.parent {
                         obj["parent"] = json!(parent);
                     }
@@ -202,7 +260,7 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
         if include_body {
             summary["totalBodyLinesReturned"] = json!(total_body_lines_emitted);
         }
-        inject_branch_warning(&mut summary, ctx);
+        inject_owners_source(&mut summary, owners);
         let output = json!({
             "containingDefinitions": containing_defs,
             "query": {
@@ -211,148 +269,59 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
             },
             "summary": summary,
         });
-        return ToolCallResult::success(serde_json::to_string(&output).unwrap());
+        return Ok(output);
     }
 
-    // Start with candidate indices
-    let mut candidate_indices: Option<Vec<u32>> = None;
-
-    // Filter by kind first (most selective usually)
+    // Candidates: kind, attribute, base type, name, file, parent, namespace, excludeDir
+    let mut query = DefinitionQuery::new().regex(use_regex).exclude_dir(exclude_dir);
     if let Some(kind_str) = kind_filter {
-        match kind_str.parse::<DefinitionKind>() {
-            Ok(kind) => {
-                if let Some(indices) = index.kind_index.get(&kind) {
-                    candidate_indices = Some(indices.clone());
-                } else {
-                    candidate_indices = Some(Vec::new());
-                }
-            }
-            Err(e) => {
-                return ToolCallResult::error(e);
-            }
-        }
+        query = query.kind(kind_str.parse::<DefinitionKind>()?);
     }
-
-    // Filter by attribute
     if let Some(attr) = attribute_filter {
-        let attr_lower = attr.to_lowercase();
-        if let Some(indices) = index.attribute_index.get(&attr_lower) {
-            candidate_indices = Some(match candidate_indices {
-                Some(existing) => {
-                    let set: std::collections::HashSet<u32> = indices.iter().cloned().collect();
-                    existing.into_iter().filter(|i| set.contains(i)).collect()
-                }
-                None => indices.clone(),
-            });
-        } else {
-            candidate_indices = Some(Vec::new());
-        }
+        query = query.attribute(attr);
     }
-
-    // Filter by base type
     if let Some(bt) = base_type_filter {
-        let bt_lower = bt.to_lowercase();
-        if let Some(indices) = index.base_type_index.get(&bt_lower) {
-            candidate_indices = Some(match candidate_indices {
-                Some(existing) => {
-                    let set: std::collections::HashSet<u32> = indices.iter().cloned().collect();
-                    existing.into_iter().filter(|i| set.contains(i)).collect()
-                }
-                None => indices.clone(),
-            });
-        } else {
-            candidate_indices = Some(Vec::new());
-        }
+        query = query.base_type(bt);
     }
-
-    // Filter by name
     if let Some(name) = name_filter {
-        if use_regex {
-            // Regex match against all names in the index
-            let re = match regex::Regex::new(&format!("(?i){}", name)) {
-                Ok(r) => r,
-                Err(e) => return ToolCallResult::error(format!("Invalid regex '{}': {}", name, e)),
-            };
-            let mut matching_indices = Vec::new();
-            for (n, indices) in &index.name_index {
-                if re.is_match(n) {
-                    matching_indices.extend(indices);
-                }
-            }
-            candidate_indices = Some(match candidate_indices {
-                Some(existing) => {
-                    let set: std::collections::HashSet<u32> = matching_indices.into_iter().cloned().collect();
-                    existing.into_iter().filter(|i| set.contains(i)).collect()
-                }
-                None => matching_indices.into_iter().cloned().collect(),
-            });
-        } else {
-            // Comma-separated OR search with substring matching
-            let terms: Vec<String> = name.split(',')
-                .map(|s| s.trim().to_lowercase())
-                .filter(|s| !s.is_empty())
-                .collect();
-            let mut matching_indices = Vec::new();
-            for (n, indices) in &index.name_index {
-                if terms.iter().any(|t| n.contains(t)) {
-                    matching_indices.extend(indices);
-                }
-            }
-            candidate_indices = Some(match candidate_indices {
-                Some(existing) => {
-                    let set: std::collections::HashSet<u32> = matching_indices.into_iter().cloned().collect();
-                    existing.into_iter().filter(|i| set.contains(i)).collect()
-                }
-                None => matching_indices.into_iter().cloned().collect(),
-            });
-        }
+        query = query.name(name);
     }
+    if let Some(ff) = file_filter {
+        query = query.file(ff);
+    }
+    if let Some(pf) = parent_filter {
+        query = query.parent(pf);
+    }
+    if let Some(nf) = namespace_filter {
+        query = query.namespace(nf);
+    }
+    let candidates = index.find(&query)
+        .map_err(|e| format!("Invalid regex '{}': {}", name_filter.unwrap_or_default(), e))?;
 
-    // If no filters applied, return all definitions (up to max)
-    let mut candidates = candidate_indices.unwrap_or_else(|| {
-        (0..index.definitions.len() as u32).collect()
-    });
-
-    // Deduplicate candidate indices (a definition may appear multiple times
-    // if e.g. multiple attributes normalize to the same name)
-    candidates.sort_unstable();
-    candidates.dedup();
-
-    // Apply remaining filters (file, parent, excludeDir) on actual entries
     // Track (def_idx, &DefinitionEntry) for code_stats lookup
     let mut results: Vec<(u32, &DefinitionEntry)> = candidates.iter()
         .filter_map(|&idx| {
             let def = index.definitions.get(idx as usize)?;
             let file_path = index.files.get(def.file_id as usize)?;
 
-            // File filter (normalize separators for cross-platform matching)
-            if let Some(ff) = file_filter
-                && !file_path.replace('\\', "/").to_lowercase().contains(&ff.replace('\\', "/").to_lowercase()) {
+            // Docs: every term in the doc comment
+            if let Some(ref terms) = docs_terms {
+                let text = doc_comment(index, def).map(doc_text).unwrap_or_default();
+                if !terms.iter().all(|t| text.contains(t.as_str())) {
                     return None;
                 }
-
-            // Parent filter
-            if let Some(pf) = parent_filter {
-                match &def.parent {
-                    Some(parent) => {
-                        if !parent.to_lowercase().contains(&pf.to_lowercase()) {
-                            return None;
-                        }
-                    }
-                    None => return None,
-                }
             }
 
-            // Exclude dir
-            if exclude_dir.iter().any(|excl| {
-                file_path.to_lowercase().contains(&excl.to_lowercase())
-            }) {
+            if !owner_allows(owners, owner_filter, file_path) {
                 return None;
             }
 
             Some((idx, def))
         })
         .collect();
+    // A partial type is listed once, at its first matching part
+    let mut seen_partials = HashSet::new();
+    results.retain(|(idx, _)| index.partial_types.get(idx).is_none_or(|p| seen_partials.insert(p.parts[0])));
 
     // ── Stats error check & filtering ──
     let mut stats_filters_applied = false;
@@ -362,12 +331,19 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
         let needs_code_stats = sort_by != Some("lines");
 
         if needs_code_stats && index.code_stats.is_empty() {
-            return ToolCallResult::error(
+            return Err(
                 "Code stats not available for this index. Run search_reindex_definitions to compute metrics.".to_string()
             );
         }
 
-        if needs_code_stats {
+        if class_level {
+            // Classes with members that have code stats, WMC/LCOM thresholds
+            results.retain(|(def_idx, _def)| {
+                let Some(metrics) = class_metrics(index, *def_idx) else { return false };
+                min_wmc.is_none_or(|min| metrics.wmc >= min) && min_lcom.is_none_or(|min| metrics.lcom >= min)
+            });
+            stats_filters_applied = true;
+        } else if needs_code_stats {
             // Filter to only definitions with code_stats, apply min* thresholds
             results.retain(|(def_idx, _def)| {
                 let stats = match index.code_stats.get(def_idx) {
@@ -393,7 +369,9 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
                 if let Some(min) = min_calls {
                     if stats.call_count < min { return false; }
                 }
-                true
+                min_volume.is_none_or(|min| stats.halstead_volume >= min)
+                    && min_difficulty.is_none_or(|min| stats.halstead_difficulty >= min)
+                    && max_maintainability.is_none_or(|max| stats.maintainability_index <= max)
             });
             stats_filters_applied = true;
         }
@@ -403,16 +381,28 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
 
     // ── Sorting ──
     if let Some(sort_field) = sort_by {
-        // Sort by metric (descending — worst first)
+        // Sort by metric (worst first: descending, ascending for the maintainability index)
+        let class_values: HashMap<u32, u32> = if class_level {
+            results.iter()
+                .filter_map(|(idx, _)| class_metrics(index, *idx).map(|m| (*idx, if sort_field == "lcom" { m.lcom } else { m.wmc })))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let value = |idx: &u32, def: &DefinitionEntry| match class_values.get(idx) {
+            Some(&v) => v,
+            None => get_sort_value(index.code_stats.get(idx), def, sort_field),
+        };
         results.sort_by(|(idx_a, def_a), (idx_b, def_b)| {
-            let va = get_sort_value(index.code_stats.get(idx_a), def_a, sort_field);
-            let vb = get_sort_value(index.code_stats.get(idx_b), def_b, sort_field);
-            vb.cmp(&va) // descending — worst first
+            let (va, vb) = (value(idx_a, def_a), value(idx_b, def_b));
+            let order = if sort_field == "maintainabilityIndex" { va.cmp(&vb) } else { vb.cmp(&va) };
+            order.then_with(|| cmp_def_location(index, def_a, def_b))
         });
     } else if name_filter.is_some() && !use_regex {
-        // Relevance ranking (only when name filter is active and not regex)
+        // Relevance ranking (only when name filter is active and not regex);
+        // qualified terms rank by their last segment
         let terms: Vec<String> = name_filter.unwrap().split(',')
-            .map(|s| s.trim().to_lowercase())
+            .map(|s| s.trim().rsplit('.').next().unwrap_or_default().to_lowercase())
             .filter(|s| !s.is_empty())
             .collect();
 
@@ -423,11 +413,26 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
                 .then_with(|| kind_priority(&a.kind).cmp(&kind_priority(&b.kind)))
                 .then_with(|| a.name.len().cmp(&b.name.len()))
                 .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| cmp_def_location(index, a, b))
         });
     }
 
-    // Apply max results
-    if max_results > 0 && results.len() > max_results {
+    // ── Policy hook: re-score the built-in order before the cut ──
+    let hooked = match rank_hook {
+        Some(hook) if sort_by.is_none() => Some(hook.apply(RankTool::Definitions, &mut results, |rank, (_, def)| Candidate {
+            path: repo_path(&index.root, index.files.get(def.file_id as usize).map_or("", |s| s.as_str())),
+            name: &def.name,
+            kind: def.kind as u32,
+            line: def.line_start,
+            score: 1.0 / (1.0 + rank as f32),
+        })),
+        _ => None,
+    };
+    let total_results = total_results - hooked.as_ref().map_or(0, |h| h.dropped);
+
+    // Apply the page, or max results
+    let page_info = page.map(|p| p.apply(&mut results));
+    if page.is_none() && max_results > 0 && results.len() > max_results {
         results.truncate(max_results);
     }
 
@@ -447,15 +452,22 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
             "file": file_path,
             "lines": format!("{}-{}", def.line_start, def.line_end),
         });
+        inject_owners(&mut obj, owners, file_path);
 
         if !def.modifiers.is_empty() {
             obj["modifiers"] = json!(def.modifiers);
         }
-        if !def.attributes.is_empty() {
-            obj["attributes"] = json!(def.attributes);
+        let partial = index.partial_types.get(def_idx_value);
+        let attributes = partial.map_or(&def.attributes, |p| &p.attributes);
+        if !attributes.is_empty() {
+            obj["attributes"] = json!(attributes);
         }
-        if !def.base_types.is_empty() {
-            obj["baseTypes"] = json!(def.base_types);
+        let base_types = partial.map_or(&def.base_types, |p| &p.base_types);
+        if !base_types.is_empty() {
+            obj["baseTypes"] = json!(base_types);
+        }
+        if let Some(partial) = partial {
+            obj["partialParts"] = partial_parts_json(index, partial);
         }
         if let Some(ref sig) = def.signature {
             obj["signature"] = json!(sig);
@@ -463,6 +475,13 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
         if let Some(ref parent) = def.parent {
             obj["parent"] = json!(parent);
         }
+        if let Some(ns) = namespace_of(index, def) {
+            obj["namespace"] = json!(ns);
+        }
+        if include_docs
+            && let Some(doc) = doc_comment(index, def) {
+                obj["docs"] = doc_json(doc);
+            }
         // Add Angular template metadata
         if let Some(children) = index.template_children.get(&(*def_idx_value as u32)) {
             obj["templateChildren"] = json!(children);
@@ -495,6 +514,16 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
                     "returnCount": stats.return_count,
                     "callCount": stats.call_count,
                     "lambdaCount": stats.lambda_count,
+                    "halsteadVolume": round1(stats.halstead_volume),
+                    "halsteadDifficulty": round1(stats.halstead_difficulty),
+                    "maintainabilityIndex": stats.maintainability_index,
+                });
+            } else if let Some(metrics) = class_metrics(index, *def_idx_value) {
+                obj["codeStats"] = json!({
+                    "lines": def.line_end.saturating_sub(def.line_start) + 1,
+                    "members": metrics.members,
+                    "wmc": metrics.wmc,
+                    "lcom": metrics.lcom,
                 });
             }
         }
@@ -518,9 +547,15 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
     if include_body {
         summary["totalBodyLinesReturned"] = json!(total_body_lines_emitted);
     }
+    if let Some(info) = page_info {
+        info.inject(&mut summary);
+    }
     if let Some(sort_field) = sort_by {
         summary["sortedBy"] = json!(sort_field);
     }
+    if let Some(outcome) = &hooked {
+        outcome.inject(&mut summary);
+    }
     if stats_filters_applied {
         summary["statsFiltersApplied"] = json!(true);
         summary["afterStatsFilter"] = json!(total_results);
@@ -529,13 +564,173 @@ pub(crate) fn handle_search_definitions(ctx: &HandlerContext, args: &Value) -> T
     if include_code_stats && index.code_stats.is_empty() {
         summary["codeStatsAvailable"] = json!(false);
     }
-    inject_branch_warning(&mut summary, ctx);
-    let output = json!({
+    if include_docs && index.doc_comments.is_empty() {
+        summary["docsAvailable"] = json!(false);
+    }
+    inject_owners_source(&mut summary, owners);
+    Ok(json!({
         "definitions": defs_json,
         "summary": summary,
-    });
+    }))
+}
+
+// ─── CLI query grammar ───────────────────────────────────────────────
+
+/// Value type of a `key:value` filter in a definitions query.
+#[derive(Clone, Copy)]
+enum QueryValue {
+    Str,
+    Int,
+    Bool,
+    /// Repeatable; collected into a JSON array
+    List,
+}
+
+/// Query keys accepted by [`parse_def_query`]: aliases → MCP parameter name.
+const DEF_QUERY_KEYS: &[(&[&str], &str, QueryValue)] = &[
+    (&["name"], "name", QueryValue::Str),
+    (&["kind"], "kind", QueryValue::Str),
+    (&["base", "basetype"], "baseType", QueryValue::Str),
+    (&["attr", "attribute"], "attribute", QueryValue::Str),
+    (&["file"], "file", QueryValue::Str),
+    (&["parent", "in"], "parent", QueryValue::Str),
+    (&["ns", "namespace"], "namespace", QueryValue::Str),
+    (&["exclude", "excludedir"], "excludeDir", QueryValue::List),
+    (&["owner"], "owner", QueryValue::Str),
+    (&["line", "containsline"], "containsLine", QueryValue::Int),
+    (&["sort", "sortby"], "sortBy", QueryValue::Str),
+    (&["limit", "maxresults"], "maxResults", QueryValue::Int),
+    (&["regex"], "regex", QueryValue::Bool),
+    (&["body", "includebody"], "includeBody", QueryValue::Bool),
+    (&["stats", "includecodestats"], "includeCodeStats", QueryValue::Bool),
+    (&["doc", "docs"], "docs", QueryValue::Str),
+    (&["includedocs"], "includeDocs", QueryValue::Bool),
+    (&["complexity", "mincomplexity"], "minComplexity", QueryValue::Int),
+    (&["cognitive", "mincognitive"], "minCognitive", QueryValue::Int),
+    (&["nesting", "minnesting"], "minNesting", QueryValue::Int),
+    (&["params", "minparams"], "minParams", QueryValue::Int),
+    (&["returns", "minreturns"], "minReturns", QueryValue::Int),
+    (&["calls", "mincalls"], "minCalls", QueryValue::Int),
+    (&["volume", "minhalsteadvolume"], "minHalsteadVolume", QueryValue::Int),
+    (&["difficulty", "minhalsteaddifficulty"], "minHalsteadDifficulty", QueryValue::Int),
+    (&["mi", "maxmaintainabilityindex"], "maxMaintainabilityIndex", QueryValue::Int),
+    (&["wmc", "minwmc"], "minWmc", QueryValue::Int),
+    (&["lcom", "minlcom"], "minLcom", QueryValue::Int),
+];
+
+/// `{summary, params: [{name, description}], returns}` of a doc comment.
+fn doc_json(doc: &DocComment) -> Value {
+    let mut obj = json!({});
+    if let Some(ref summary) = doc.summary {
+        obj["summary"] = json!(summary);
+    }
+    if !doc.params.is_empty() {
+        obj["params"] = json!(doc.params.iter().map(|p| json!({ "name": p.name, "description": p.description })).collect::<Vec<_>>());
+    }
+    if let Some(ref returns) = doc.returns {
+        obj["returns"] = json!(returns);
+    }
+    obj
+}
+
+/// Tie-breaker for ranked definitions: file path, then position in the file.
+/// Definition indices follow parallel parse order, which differs between index builds.
+fn cmp_def_location(index: &DefinitionIndex, a: &DefinitionEntry, b: &DefinitionEntry) -> std::cmp::Ordering {
+    let path_a = index.files.get(a.file_id as usize);
+    let path_b = index.files.get(b.file_id as usize);
+    path_a.cmp(&path_b)
+        .then(a.line_start.cmp(&b.line_start))
+        .then(a.file_id.cmp(&b.file_id))
+}
+
+/// Parse a `key:value` definitions query into `search_definitions` arguments.
+///
+/// Example: `kind:class base:ControllerBase attr:Authorize file:Controllers`.
+/// Bare words are name terms (comma-joined, i.e. OR — same as the MCP `name` parameter).
+/// Values containing spaces can be quoted: `file:"My Project/src"`.
+/// `exclude:` may repeat; any other repeated key is an error.
+pub(crate) fn parse_def_query(query: &str) -> Result<Value, String> {
+    let mut args = serde_json::Map::new();
+    let mut name_terms: Vec<String> = Vec::new();
+
+    for token in tokenize_def_query(query)? {
+        let (key, value) = match token.split_once(':') {
+            Some((k, v)) if !token.starts_with('"') => (k.to_lowercase(), v.trim_matches('"').to_string()),
+            _ => {
+                name_terms.push(token.trim_matches('"').to_string());
+                continue;
+            }
+        };
+        let (_, param, kind) = DEF_QUERY_KEYS.iter()
+            .find(|(aliases, _, _)| aliases.contains(&key.as_str()))
+            .ok_or_else(|| {
+                let valid: Vec<&str> = DEF_QUERY_KEYS.iter().map(|(a, _, _)| a[0]).collect();
+                format!("Unknown query key '{}'. Valid keys: {}", key, valid.join(", "))
+            })?;
+        if value.is_empty() {
+            return Err(format!("Query key '{}' has no value.", key));
+        }
+        if *param == "name" {
+            name_terms.push(value);
+            continue;
+        }
+        let json_value = match kind {
+            QueryValue::Str => json!(value),
+            QueryValue::Int => json!(value.parse::<u64>()
+                .map_err(|_| format!("Query key '{}' expects a number, got '{}'.", key, value))?),
+            QueryValue::Bool => match value.to_lowercase().as_str() {
+                "true" | "yes" | "1" => json!(true),
+                "false" | "no" | "0" => json!(false),
+                _ => return Err(format!("Query key '{}' expects true/false, got '{}'.", key, value)),
+            },
+            QueryValue::List => {
+                let list = args.entry(param.to_string()).or_insert_with(|| json!([]));
+                list.as_array_mut().unwrap().push(json!(value));
+                continue;
+            }
+        };
+        if args.insert(param.to_string(), json_value).is_some() {
+            return Err(format!("Query key '{}' given more than once.", key));
+        }
+    }
+
+    if !name_terms.is_empty() {
+        args.insert("name".to_string(), json!(name_terms.join(",")));
+    }
+    Ok(Value::Object(args))
+}
 
-    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+/// Split a query on whitespace, keeping double-quoted spans together.
+fn tokenize_def_query(query: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("Unterminated quote in query.".to_string());
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// `value` rounded to one decimal, for JSON output.
+fn round1(value: f32) -> f64 {
+    (value as f64 * 10.0).round() / 10.0
 }
 
 /// Extract a numeric value from CodeStats for sorting.
@@ -555,6 +750,9 @@ fn get_sort_value(stats: Option<&CodeStats>, def: &DefinitionEntry, field: &str)
                 "returnCount" => s.return_count as u32,
                 "callCount" => s.call_count as u32,
                 "lambdaCount" => s.lambda_count as u32,
+                "halsteadVolume" => s.halstead_volume.round() as u32,
+                "halsteadDifficulty" => (s.halstead_difficulty * 10.0).round() as u32,
+                "maintainabilityIndex" => s.maintainability_index as u32,
                 _ => 0,
             }
         }
@@ -652,4 +850,81 @@ mod tests {
         assert!(kind_priority(&DefinitionKind::Struct) < kind_priority(&DefinitionKind::Function));
         assert!(kind_priority(&DefinitionKind::Record) < kind_priority(&DefinitionKind::Constructor));
     }
-}
\ No newline at end of file
+
+    // ─── parse_def_query tests ───────────────────────────────────────
+
+    #[test]
+    fn test_parse_def_query_maps_keys_to_mcp_params() {
+        let args = parse_def_query("kind:class base:ControllerBase attr:Authorize file:Controllers").unwrap();
+        assert_eq!(args, json!({
+            "kind": "class", "baseType": "ControllerBase", "attribute": "Authorize", "file": "Controllers"
+        }));
+    }
+
+    #[test]
+    fn test_parse_def_query_bare_words_are_name_or_terms() {
+        let args = parse_def_query("UserService name:OrderService kind:interface").unwrap();
+        assert_eq!(args["name"], "UserService,OrderService");
+        assert_eq!(args["kind"], "interface");
+    }
+
+    #[test]
+    fn test_parse_def_query_typed_values_and_lists() {
+        let args = parse_def_query("line:120 limit:0 regex:true exclude:test exclude:Mock in:Program").unwrap();
+        assert_eq!(args["containsLine"], 120);
+        assert_eq!(args["maxResults"], 0);
+        assert_eq!(args["regex"], true);
+        assert_eq!(args["excludeDir"], json!(["test", "Mock"]));
+        assert_eq!(args["parent"], "Program");
+    }
+
+    #[test]
+    fn test_parse_def_query_quoted_values() {
+        let args = parse_def_query(r#"file:"My Project/src" "Get User""#).unwrap();
+        assert_eq!(args["file"], "My Project/src");
+        assert_eq!(args["name"], "Get User");
+        // Only the first colon separates key from value
+        assert_eq!(parse_def_query(r"file:C:\Repo").unwrap()["file"], r"C:\Repo");
+    }
+
+    #[test]
+    fn test_parse_def_query_errors() {
+        assert!(parse_def_query("color:red").unwrap_err().contains("Unknown query key 'color'"));
+        assert!(parse_def_query("kind:class kind:method").unwrap_err().contains("more than once"));
+        assert!(parse_def_query("line:abc").unwrap_err().contains("expects a number"));
+        assert!(parse_def_query("regex:maybe").unwrap_err().contains("true/false"));
+        assert!(parse_def_query("kind:").unwrap_err().contains("no value"));
+        assert!(parse_def_query(r#"file:"open"#).unwrap_err().contains("Unterminated"));
+    }
+
+    #[test]
+    fn test_parse_def_query_empty_matches_everything() {
+        assert_eq!(parse_def_query("  ").unwrap(), json!({}));
+    }
+
+    #[test]
+    fn test_equal_rank_definitions_ordered_by_location() {
+        let mut index = DefinitionIndex {
+            files: vec!["src/Z.cs".to_string(), "src/A.cs".to_string()],
+            ..Default::default()
+        };
+        // Same name, kind and length everywhere: only location can order them
+        for (file_id, line) in [(0, 30), (1, 50), (0, 10), (1, 20)] {
+            let idx = index.definitions.len() as u32;
+            index.definitions.push(DefinitionEntry {
+                file_id, name: "Run".to_string(), kind: DefinitionKind::Method,
+                line_start: line, line_end: line + 4, parent: None, signature: None,
+                modifiers: Vec::new(), attributes: Vec::new(), base_types: Vec::new(),
+            });
+            index.name_index.entry("run".to_string()).or_default().push(idx);
+        }
+        let expected = vec!["src/A.cs:20", "src/A.cs:50", "src/Z.cs:10", "src/Z.cs:30"];
+        for args in [json!({ "name": "run" }), json!({ "name": "run", "sortBy": "lines" })] {
+            let output = search_definitions_json(&index, &args, None, None, None).unwrap();
+            let locations: Vec<String> = output["definitions"].as_array().unwrap().iter()
+                .map(|d| format!("{}:{}", d["file"].as_str().unwrap(), d["lines"].as_str().unwrap().split('-').next().unwrap()))
+                .collect();
+            assert_eq!(locations, expected, "{}", args);
+        }
+    }
+}
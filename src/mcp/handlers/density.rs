@@ -0,0 +1,143 @@
+//! search_density handler: where in a file the matches of a term cluster.
+//!
+//! Line hits come from the inverted index postings and are counted per fixed-size
+//! line bucket, so a term with hundreds of hits in one large file becomes a short
+//! histogram plus the densest few regions to read.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::mcp::protocol::ToolCallResult;
+use crate::ContentIndex;
+
+use super::utils::{inject_branch_warning, is_under_dir};
+use super::HandlerContext;
+
+const DEFAULT_BUCKET_SIZE: u32 = 100;
+const DEFAULT_MAX_FILES: usize = 10;
+const DEFAULT_TOP_BUCKETS: usize = 3;
+
+pub(crate) fn handle_search_density(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let index = ctx.index.snapshot();
+    match search_density_json(&index, args) {
+        Ok(mut output) => {
+            inject_branch_warning(&mut output["summary"], ctx);
+            ToolCallResult::success(serde_json::to_string(&output).unwrap())
+        }
+        Err(msg) => ToolCallResult::error(msg),
+    }
+}
+
+/// Per-file line histograms of the hits of `terms`.
+fn search_density_json(index: &ContentIndex, args: &Value) -> Result<Value, String> {
+    let start = Instant::now();
+
+    let terms: Vec<String> = args.get("terms").and_then(|v| v.as_str()).unwrap_or("")
+        .split(',').map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+    if terms.is_empty() {
+        return Err("Missing required parameter: terms".to_string());
+    }
+    let bucket_size = match args.get("bucketSize").and_then(|v| v.as_u64()) {
+        Some(0) => return Err("bucketSize must be >= 1".to_string()),
+        Some(n) => n.min(u32::MAX as u64) as u32,
+        None => DEFAULT_BUCKET_SIZE,
+    };
+    let file_filter = args.get("file").and_then(|v| v.as_str()).filter(|s| !s.is_empty())
+        .map(|s| s.replace('\\', "/").to_lowercase());
+    let dir = args.get("dir").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|d| {
+        if Path::new(d).is_absolute() { d.to_string() } else { format!("{}/{}", index.root, d.trim_start_matches("./")) }
+    });
+    let exclude_dir: Vec<String> = args.get("excludeDir").and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).map(|s| s.replace('\\', "/").to_lowercase()).collect())
+        .unwrap_or_default();
+    let max_files = match args.get("maxFiles").and_then(|v| v.as_u64()) {
+        Some(0) => usize::MAX,
+        Some(n) => n as usize,
+        None => DEFAULT_MAX_FILES,
+    };
+    let top_buckets = args.get("topBuckets").and_then(|v| v.as_u64()).map(|n| n as usize).unwrap_or(DEFAULT_TOP_BUCKETS);
+
+    // file_id -> hit lines of any term
+    let mut hits: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for term in &terms {
+        for posting in index.index.get(term.as_str()).into_iter().flatten() {
+            hits.entry(posting.file_id).or_default().extend(&posting.lines);
+        }
+    }
+    let mut files: Vec<(&str, Vec<u32>)> = hits.into_iter()
+        .filter_map(|(file_id, lines)| {
+            let file = index.files.get(file_id as usize)?;
+            let lower = file.replace('\\', "/").to_lowercase();
+            let keep = file_filter.as_ref().is_none_or(|f| lower.contains(f.as_str()))
+                && dir.as_ref().is_none_or(|d| is_under_dir(file, d))
+                && !exclude_dir.iter().any(|e| lower.contains(e.as_str()));
+            keep.then_some((file.as_str(), lines))
+        })
+        .collect();
+    // Most hits first; path breaks ties so repeated calls agree
+    files.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+    let total_files = files.len();
+    let total_hits: usize = files.iter().map(|(_, lines)| lines.len()).sum();
+
+    let results: Vec<Value> = files.into_iter().take(max_files).map(|(file, lines)| {
+        let buckets = bucket_lines(&lines, bucket_size);
+        let mut densest: Vec<&Bucket> = buckets.iter().collect();
+        // Densest first; earlier region breaks ties
+        densest.sort_by(|a, b| b.hits.cmp(&a.hits).then(a.start.cmp(&b.start)));
+        densest.truncate(top_buckets);
+        json!({
+            "path": file,
+            "hits": lines.len(),
+            // [startLine, hits] per non-empty bucket, in line order
+            "histogram": buckets.iter().map(|b| json!([b.start, b.hits])).collect::<Vec<_>>(),
+            "densest": densest.iter().map(|b| json!({
+                "startLine": b.start,
+                "endLine": b.start.saturating_add(bucket_size - 1),
+                "hits": b.hits,
+                "firstHit": b.first_hit,
+                "lastHit": b.last_hit,
+            })).collect::<Vec<_>>(),
+        })
+    }).collect();
+
+    let mut summary = json!({
+        "terms": terms,
+        "bucketSize": bucket_size,
+        "totalFiles": total_files,
+        "totalHits": total_hits,
+        "returnedFiles": results.len(),
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if results.is_empty() {
+        summary["hint"] = json!("No indexed token matches. Terms are exact tokens (use search_vocabulary to check spelling), and file/dir/excludeDir may exclude every match.");
+    }
+    Ok(json!({
+        "files": results,
+        "summary": summary,
+    }))
+}
+
+/// One non-empty bucket of `bucket_size` lines starting at `start` (1-based).
+struct Bucket {
+    start: u32,
+    hits: usize,
+    first_hit: u32,
+    last_hit: u32,
+}
+
+/// Count hit lines per bucket; buckets are `[1, size]`, `[size + 1, 2 * size]`, ...
+fn bucket_lines(lines: &[u32], bucket_size: u32) -> Vec<Bucket> {
+    let mut buckets: BTreeMap<u32, Bucket> = BTreeMap::new();
+    for &line in lines {
+        let start = (line.saturating_sub(1) / bucket_size) * bucket_size + 1;
+        let b = buckets.entry(start).or_insert(Bucket { start, hits: 0, first_hit: line, last_hit: line });
+        b.hits += 1;
+        b.first_hit = b.first_hit.min(line);
+        b.last_hit = b.last_hit.max(line);
+    }
+    buckets.into_values().collect()
+}
+
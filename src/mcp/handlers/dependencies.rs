@@ -0,0 +1,109 @@
+//! search_dependencies handler: file-level dependencies, dependents and import cycles.
+
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{resolve_file_id, DefinitionIndex, DependencyEdge, DependencyGraph};
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+fn file_path(index: &DefinitionIndex, file_id: u32) -> &str {
+    index.files.get(file_id as usize).map(|s| s.as_str()).unwrap_or("")
+}
+
+fn edges_to_json(index: &DefinitionIndex, edges: &[(u32, &DependencyEdge)]) -> Vec<Value> {
+    edges.iter().map(|(file_id, edge)| json!({
+        "file": file_path(index, *file_id),
+        "imports": edge.imports,
+        "calls": edge.calls,
+    })).collect()
+}
+
+pub(crate) fn handle_search_dependencies(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+    let direction = {
+        let raw = args.get("direction").and_then(|v| v.as_str()).unwrap_or("both");
+        match raw.to_lowercase().as_str() {
+            "dependson" => "dependsOn",
+            "dependents" => "dependents",
+            "both" => "both",
+            _ => return ToolCallResult::error(format!(
+                "Invalid direction '{}'. Must be 'dependsOn', 'dependents', or 'both'.", raw
+            )),
+        }
+    };
+    let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
+    let file = args.get("file").and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let start = Instant::now();
+    let file_id = match file.map(|f| resolve_file_id(&index, f)).transpose() {
+        Ok(id) => id,
+        Err(msg) => return ToolCallResult::error(msg),
+    };
+    let graph = DependencyGraph::build(&index);
+
+    let Some(file_id) = file_id else {
+        // No file: every group of files that depend on each other
+        let cycles = graph.cycles();
+        let files_in_cycles: usize = cycles.iter().map(Vec::len).sum();
+        let returned: Vec<Value> = cycles.iter()
+            .take(if max_results == 0 { usize::MAX } else { max_results })
+            .map(|group| json!({
+                "size": group.len(),
+                "files": group.iter().map(|&f| file_path(&index, f)).collect::<Vec<_>>(),
+            }))
+            .collect();
+        let mut summary = json!({
+            "totalCycles": cycles.len(),
+            "filesInCycles": files_in_cycles,
+            "returned": returned.len(),
+            "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+        });
+        if cycles.is_empty() {
+            summary["hint"] = json!("No dependency cycles. Pass file to list what a file depends on and what depends on it.");
+        }
+        inject_branch_warning(&mut summary, ctx);
+        let output = json!({ "cycles": returned, "summary": summary });
+        return ToolCallResult::success(serde_json::to_string(&output).unwrap());
+    };
+
+    let depends_on = graph.dependencies(file_id);
+    let dependents = graph.dependents(file_id);
+    let cycle = graph.cycle_through(file_id);
+
+    let mut output = json!({ "file": file_path(&index, file_id) });
+    if direction != "dependents" {
+        output["dependsOn"] = json!(edges_to_json(&index, &depends_on));
+        output["external"] = json!(graph.external.get(&file_id).cloned().unwrap_or_default());
+    }
+    if direction != "dependsOn" {
+        output["dependents"] = json!(edges_to_json(&index, &dependents));
+    }
+    if let Some(cycle) = &cycle {
+        output["cycle"] = json!(cycle.iter().map(|&f| file_path(&index, f)).collect::<Vec<_>>());
+    }
+    let mut summary = json!({
+        "dependsOn": depends_on.len(),
+        "dependents": dependents.len(),
+        "inCycle": cycle.is_some(),
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    inject_branch_warning(&mut summary, ctx);
+    output["summary"] = summary;
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
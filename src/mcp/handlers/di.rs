@@ -0,0 +1,81 @@
+//! search_di_registrations handler: C# DI container registrations from the definition index.
+
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+const LIFETIMES: &[&str] = &["scoped", "transient", "singleton"];
+
+pub(crate) fn handle_search_di_registrations(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+    let lower_arg = |key: &str| args.get(key).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty()).map(str::to_lowercase);
+    let service = lower_arg("service");
+    let implementation = lower_arg("implementation");
+    let file = lower_arg("file").map(|f| f.replace('\\', "/"));
+    let lifetime = lower_arg("lifetime");
+    if let Some(l) = &lifetime
+        && !LIFETIMES.contains(&l.as_str()) {
+            return ToolCallResult::error(format!(
+                "Invalid lifetime '{}'. Must be 'scoped', 'transient', or 'singleton'.", l
+            ));
+        }
+    let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
+
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let start = Instant::now();
+    let file_of = |file_id: u32| index.files.get(file_id as usize).map(|s| s.as_str()).unwrap_or("");
+    let mut matching: Vec<_> = index.di_registrations.iter()
+        .filter(|r| service.as_ref().is_none_or(|s| r.service.to_lowercase().contains(s.as_str())))
+        .filter(|r| implementation.as_ref().is_none_or(|i| r.implementation.as_ref().is_some_and(|ri| ri.to_lowercase().contains(i.as_str()))))
+        .filter(|r| lifetime.as_ref().is_none_or(|l| r.lifetime == *l))
+        .filter(|r| file.as_ref().is_none_or(|f| file_of(r.file_id).replace('\\', "/").to_lowercase().contains(f.as_str())))
+        .collect();
+    matching.sort_by(|a, b| file_of(a.file_id).cmp(file_of(b.file_id)).then(a.line.cmp(&b.line)));
+
+    let registrations: Vec<Value> = matching.iter()
+        .take(if max_results == 0 { usize::MAX } else { max_results })
+        .map(|r| json!({
+            "service": r.service,
+            "implementation": r.implementation,
+            "lifetime": r.lifetime,
+            "file": file_of(r.file_id),
+            "line": r.line,
+        }))
+        .collect();
+    let by_lifetime: serde_json::Map<String, Value> = LIFETIMES.iter()
+        .map(|l| (l.to_string(), json!(matching.iter().filter(|r| r.lifetime == *l).count())))
+        .collect();
+
+    let mut summary = json!({
+        "totalRegistrations": matching.len(),
+        "returned": registrations.len(),
+        "byLifetime": by_lifetime,
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if index.di_registrations.is_empty() {
+        summary["hint"] = json!("No DI registrations in the index. Only C# services.Add{Scoped,Transient,Singleton}(...) calls and their TryAdd variants are recorded.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "registrations": registrations,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
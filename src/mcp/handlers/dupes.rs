@@ -0,0 +1,79 @@
+//! search_dupes handler: near-duplicate method bodies (token shingle similarity).
+
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{clone_pair_to_json, find_duplicates, DupeOptions};
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+pub(crate) fn handle_search_dupes(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+
+    let defaults = DupeOptions::default();
+    let min_similarity = args.get("threshold").and_then(|v| v.as_f64()).unwrap_or(defaults.min_similarity);
+    if min_similarity <= 0.0 || min_similarity > 1.0 {
+        return ToolCallResult::error(format!(
+            "threshold must be in (0, 1], got {}", min_similarity
+        ));
+    }
+    let shingle_size = args.get("shingleSize").and_then(|v| v.as_u64()).unwrap_or(defaults.shingle_size as u64);
+    if shingle_size == 0 {
+        return ToolCallResult::error("shingleSize must be >= 1".to_string());
+    }
+    let opts = DupeOptions {
+        shingle_size: shingle_size as usize,
+        min_lines: args.get("minLines").and_then(|v| v.as_u64()).map(|n| n as u32).unwrap_or(defaults.min_lines),
+        min_similarity,
+        file_filter: args.get("file").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        exclude_dir: args.get("excludeDir").and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|s| s.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default(),
+        ..defaults
+    };
+    let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
+
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let start = Instant::now();
+    let report = find_duplicates(&index, &opts);
+    let shown = if max_results == 0 { report.pairs.len() } else { report.pairs.len().min(max_results) };
+    let pairs: Vec<Value> = report.pairs[..shown].iter()
+        .map(|p| clone_pair_to_json(&index, p))
+        .collect();
+
+    let mut summary = json!({
+        "totalPairs": report.pairs.len(),
+        "returned": shown,
+        "bodiesScanned": report.bodies_scanned,
+        "threshold": opts.min_similarity,
+        "shingleSize": opts.shingle_size,
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if report.files_unreadable > 0 {
+        summary["filesUnreadable"] = json!(report.files_unreadable);
+    }
+    if report.pairs.is_empty() {
+        summary["hint"] = json!("No clone pairs found. Lower threshold (e.g. 0.6) or minLines to widen the search.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "pairs": pairs,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
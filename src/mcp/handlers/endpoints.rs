@@ -0,0 +1,112 @@
+//! search_endpoints handler: ASP.NET routes and the methods that handle them.
+
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{route_matches, route_specificity, DefinitionIndex, DefinitionKind};
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+/// Definition of a `Class.Method` or bare `Method` handler name.
+fn resolve_handler(index: &DefinitionIndex, handler: &str) -> Option<u32> {
+    let (class, method) = match handler.rsplit_once('.') {
+        Some((class, method)) => (Some(class.rsplit('.').next().unwrap_or(class)), method),
+        None => (None, handler),
+    };
+    index.name_index.get(&method.to_lowercase())?.iter().copied().find(|&id| {
+        index.definitions.get(id as usize).is_some_and(|def| {
+            matches!(def.kind, DefinitionKind::Method | DefinitionKind::Function)
+                && class.is_none_or(|c| def.parent.as_deref().is_some_and(|p| p.eq_ignore_ascii_case(c)))
+        })
+    })
+}
+
+pub(crate) fn handle_search_endpoints(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+    let arg = |key: &str| args.get(key).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+    // A full URL works too: keep the path
+    let path = arg("path").map(|p| match p.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]).to_string(),
+        None => p.to_string(),
+    });
+    let method = arg("method").map(str::to_uppercase);
+    let handler = arg("handler").map(str::to_lowercase);
+    let file = arg("file").map(|f| f.replace('\\', "/").to_lowercase());
+    let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
+
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let start = Instant::now();
+    let file_of = |file_id: u32| index.files.get(file_id as usize).map(|s| s.as_str()).unwrap_or("");
+    // (served: the path matches the template, not just a fragment of it)
+    let mut matching: Vec<(bool, &crate::definitions::Endpoint)> = index.endpoints.iter()
+        .filter(|e| method.as_ref().is_none_or(|m| e.http_method == *m || e.http_method == "ANY"))
+        .filter(|e| handler.as_ref().is_none_or(|h| e.handler.as_ref().is_some_and(|eh| eh.to_lowercase().contains(h.as_str()))))
+        .filter(|e| file.as_ref().is_none_or(|f| file_of(e.file_id).replace('\\', "/").to_lowercase().contains(f.as_str())))
+        .filter_map(|e| match &path {
+            None => Some((false, e)),
+            Some(p) if route_matches(&e.route, p) => Some((true, e)),
+            Some(p) if e.route.to_lowercase().contains(&p.to_lowercase()) => Some((false, e)),
+            Some(_) => None,
+        })
+        .collect();
+    matching.sort_by(|(a_served, a), (b_served, b)| {
+        b_served.cmp(a_served)
+            .then(route_specificity(&b.route).cmp(&route_specificity(&a.route)))
+            .then(file_of(a.file_id).cmp(file_of(b.file_id)))
+            .then(a.line.cmp(&b.line))
+    });
+
+    let endpoints: Vec<Value> = matching.iter()
+        .take(if max_results == 0 { usize::MAX } else { max_results })
+        .map(|(_, e)| {
+            let mut obj = json!({
+                "method": e.http_method,
+                "route": e.route,
+                "handler": e.handler,
+                "file": file_of(e.file_id),
+                "line": e.line,
+            });
+            if let Some(def) = e.handler.as_deref().and_then(|h| resolve_handler(&index, h)).map(|id| &index.definitions[id as usize]) {
+                obj["handlerFile"] = json!(file_of(def.file_id));
+                obj["handlerLines"] = json!(format!("{}-{}", def.line_start, def.line_end));
+            }
+            obj
+        })
+        .collect();
+
+    let mut summary = json!({
+        "totalEndpoints": matching.len(),
+        "returned": endpoints.len(),
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if let Some(p) = &path {
+        summary["served"] = json!(matching.iter().filter(|(served, _)| *served).count());
+        if matching.is_empty() {
+            summary["hint"] = json!(format!("No route serves or contains '{}'. Conventional routes (MapControllerRoute) are not indexed; search for the controller name instead.", p));
+        }
+    }
+    if index.endpoints.is_empty() {
+        summary["hint"] = json!("No endpoints in the index. Only C# [Route]/[Http*] attributes and minimal-API Map{Get,Post,Put,Delete,Patch} calls are recorded.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "endpoints": endpoints,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
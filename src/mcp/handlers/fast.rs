@@ -7,6 +7,8 @@ use serde_json::{json, Value};
 use tracing::info;
 
 use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use crate::priority::BuildPriority;
 
 use super::HandlerContext;
 use super::utils::{best_match_tier, inject_branch_warning};
@@ -42,6 +44,9 @@ pub(crate) fn handle_search_fast(ctx: &HandlerContext, args: &Value) -> ToolCall
                 hidden: false,
                 no_ignore: false,
                 threads: 0,
+                dry_run: false,
+                build_priority: BuildPriority::Normal,
+                storage: None,
             });
             let _ = crate::save_index(&new_index, &ctx.index_base);
             new_index
@@ -67,7 +72,7 @@ pub(crate) fn handle_search_fast(ctx: &HandlerContext, args: &Value) -> ToolCall
             let pat = if ignore_case { format!("(?i){}", t) } else { t.clone() };
             match regex::Regex::new(&pat) {
                 Ok(r) => regexes.push(r),
-                Err(e) => return ToolCallResult::error(format!("Invalid regex '{}': {}", t, e)),
+                Err(e) => return SearchError::InvalidRegex { pattern: t.clone(), source: e }.into(),
             }
         }
         Some(regexes)
@@ -6,6 +6,7 @@ use std::time::Instant;
 use serde_json::{json, Value};
 
 use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
 
 use super::utils::validate_search_dir;
 use super::HandlerContext;
@@ -45,7 +46,7 @@ pub(crate) fn handle_search_find(ctx: &HandlerContext, args: &Value) -> ToolCall
             pattern.clone()
         }) {
             Ok(r) => Some(r),
-            Err(e) => return ToolCallResult::error(format!("Invalid regex: {}", e)),
+            Err(e) => return SearchError::InvalidRegex { pattern, source: e }.into(),
         }
     } else {
         None
@@ -53,7 +54,7 @@ pub(crate) fn handle_search_find(ctx: &HandlerContext, args: &Value) -> ToolCall
 
     let root = Path::new(&dir);
     if !root.exists() {
-        return ToolCallResult::error(format!("Directory does not exist: {}", dir));
+        return SearchError::DirNotFound(dir).into();
     }
 
     let mut results: Vec<Value> = Vec::new();
@@ -10,6 +10,8 @@
 //! fall back to the existing CLI-based `git log` calls.
 //!
 //! Exception: `search_git_diff` always uses CLI (cache has no patch data).
+//! `search_git_grep` uses its own commit content index
+//! ([`CommitIndex`](crate::git::commit_index::CommitIndex)), built on first call.
 
 use std::path::Path;
 use std::sync::atomic::Ordering;
@@ -19,7 +21,10 @@ use serde_json::{json, Value};
 
 use crate::git;
 use crate::git::cache::GitHistoryCache;
+use crate::git::commit_index::{self, CommitQuery, IndexRefresh, IN_ADDED, IN_MESSAGE, IN_REMOVED};
 use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
 
 use super::HandlerContext;
 
@@ -112,6 +117,45 @@ pub(crate) fn git_tool_definitions() -> Vec<crate::mcp::protocol::ToolDefinition
                 "required": ["repo"]
             }),
         },
+        crate::mcp::protocol::ToolDefinition {
+            name: "search_git_grep".to_string(),
+            description: "Find commits whose message or diff contains a string -- e.g. which commit introduced or removed an identifier. Backed by an inverted index of commit messages and patch lines (built on first call, cached on disk, then updated incrementally as HEAD moves), so repeat queries take milliseconds instead of a full `git log -S` scan. Results are oldest first: with scope='added' the first commit is the one that introduced the string. Terms match whole tokens (identifiers), not arbitrary substrings.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "repo": { "type": "string", "description": "Path to git repository" },
+                    "terms": { "type": "string", "description": "Comma-separated terms. A term with several tokens (e.g. 'Foo.Bar') matches commits containing all of them." },
+                    "mode": { "type": "string", "enum": ["or", "and"], "description": "Match any term (or, default) or every term (and)" },
+                    "scope": { "type": "string", "enum": ["all", "message", "diff", "added", "removed"], "description": "Where to look: all (default), message, diff (added or removed lines), added, removed" },
+                    "includePatches": { "type": "boolean", "description": "Index patch lines, not just messages (default: true). Message-only indexes build much faster on large repos but only support scope='message'." },
+                    "author": { "type": "string", "description": "Filter by author name/email (substring, case-insensitive)" },
+                    "from": { "type": "string", "description": "Start date (YYYY-MM-DD, inclusive)" },
+                    "to": { "type": "string", "description": "End date (YYYY-MM-DD, inclusive)" },
+                    "date": { "type": "string", "description": "Exact date (YYYY-MM-DD), overrides from/to" },
+                    "sortBy": { "type": "string", "enum": ["oldest", "newest"], "description": "Result order (default: oldest)" },
+                    "maxResults": { "type": "integer", "description": "Max commits (default: 50, 0=unlimited)" }
+                },
+                "required": ["repo", "terms"]
+            }),
+        },
+        crate::mcp::protocol::ToolDefinition {
+            name: "search_git_added_lines".to_string(),
+            description: "Find lines added since a ref that contain a term -- e.g. which lines using 'ObsoleteApi' were added since release/1.2. Diffs the ref against HEAD (or 'until') once and tokenizes only the added lines, so it answers release-audit questions without walking history per file. Returns file, line number (in the newer revision) and content. Terms match whole tokens (identifiers), not arbitrary substrings.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "repo": { "type": "string", "description": "Path to git repository" },
+                    "since": { "type": "string", "description": "Older revision: branch, tag or commit (e.g. 'release/1.2', 'v1.2.0', 'HEAD~20')" },
+                    "until": { "type": "string", "description": "Newer revision (default: HEAD)" },
+                    "terms": { "type": "string", "description": "Comma-separated terms. A term with several tokens (e.g. 'Foo.Bar') matches lines containing all of them." },
+                    "mode": { "type": "string", "enum": ["or", "and"], "description": "Match any term (or, default) or every term (and) on the same line" },
+                    "path": { "type": "string", "description": "Limit to a file or directory (relative to repo)" },
+                    "ext": { "type": "string", "description": "Limit to file extensions (comma-separated, e.g. 'cs,ts')" },
+                    "maxResults": { "type": "integer", "description": "Max lines (default: 100, 0=unlimited)" }
+                },
+                "required": ["repo", "since", "terms"]
+            }),
+        },
         crate::mcp::protocol::ToolDefinition {
             name: "search_branch_status".to_string(),
             description: "Shows the current git branch status: branch name, whether it's main/master, how far behind/ahead of remote, uncommitted changes, and how fresh the last fetch is. Call this before investigating production bugs to ensure you're looking at the right code.".to_string(),
@@ -138,6 +182,8 @@ pub(crate) fn dispatch_git_tool(
         "search_git_authors" => handle_git_authors(ctx, arguments),
         "search_git_activity" => handle_git_activity(ctx, arguments),
         "search_git_blame" => handle_git_blame(ctx, arguments),
+        "search_git_grep" => handle_git_grep(ctx, arguments),
+        "search_git_added_lines" => handle_git_added_lines(ctx, arguments),
         "search_branch_status" => handle_branch_status(ctx, arguments),
         _ => ToolCallResult::error(format!("Unknown git tool: {}", tool_name)),
     }
@@ -176,7 +222,7 @@ fn date_str_to_timestamp_end(date: &str) -> Result<i64, String> {
 /// Parse from/to/date strings into Option<i64> timestamps for cache queries.
 /// `date` overrides `from`/`to` (single-day filter).
 /// Returns error if `from` date is after `to` date (BUG-4).
-fn parse_cache_date_range(
+pub(super) fn parse_cache_date_range(
     from: Option<&str>,
     to: Option<&str>,
     date: Option<&str>,
@@ -210,7 +256,7 @@ fn parse_cache_date_range(
 /// Format a Unix timestamp as "YYYY-MM-DD HH:MM:SS +0000" (UTC).
 ///
 /// Matches git's `%ai` format for consistent output.
-fn format_timestamp(ts: i64) -> String {
+pub(crate) fn format_timestamp(ts: i64) -> String {
     let secs_per_day: i64 = 86400;
     let days = if ts >= 0 { ts / secs_per_day } else { (ts - secs_per_day + 1) / secs_per_day };
     let time_of_day = ts - days * secs_per_day;
@@ -256,7 +302,7 @@ fn handle_git_history(ctx: &HandlerContext, args: &Value, include_diff: bool) ->
 
     // ── Cache path (history only, not diff — cache has no patch data) ──
     if !include_diff && !no_cache && ctx.git_cache_ready.load(Ordering::Relaxed) {
-        if let Ok(cache_guard) = ctx.git_cache.read() {
+        if let Ok(cache_guard) = lock_stats::timed(Lock::Git, || ctx.git_cache.read()) {
             if let Some(cache) = cache_guard.as_ref() {
                 let start = Instant::now();
                 let normalized = GitHistoryCache::normalize_path(file);
@@ -386,7 +432,7 @@ fn handle_git_authors(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
 
     // ── Cache path ──
     if !no_cache && ctx.git_cache_ready.load(Ordering::Relaxed) {
-        if let Ok(cache_guard) = ctx.git_cache.read() {
+        if let Ok(cache_guard) = lock_stats::timed(Lock::Git, || ctx.git_cache.read()) {
             if let Some(cache) = cache_guard.as_ref() {
                 let start = Instant::now();
                 let normalized = GitHistoryCache::normalize_path(query_path);
@@ -505,7 +551,7 @@ fn handle_git_activity(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
 
     // ── Cache path ──
     if !no_cache && ctx.git_cache_ready.load(Ordering::Relaxed) {
-        if let Ok(cache_guard) = ctx.git_cache.read() {
+        if let Ok(cache_guard) = lock_stats::timed(Lock::Git, || ctx.git_cache.read()) {
             if let Some(cache) = cache_guard.as_ref() {
                 let start = Instant::now();
 
@@ -703,6 +749,241 @@ fn handle_git_blame(_ctx: &HandlerContext, args: &Value) -> ToolCallResult {
     }
 }
 
+// ─── Commit content search handler ──────────────────────────────────
+
+/// Handle search_git_grep — token search over commit messages and patch lines.
+fn handle_git_grep(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let repo = match args.get("repo").and_then(|v| v.as_str()) {
+        Some(r) => r,
+        None => return ToolCallResult::error("Missing required parameter: repo".to_string()),
+    };
+    let terms: Vec<String> = args.get("terms").and_then(|v| v.as_str()).unwrap_or("")
+        .split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+    if terms.is_empty() {
+        return ToolCallResult::error("Missing required parameter: terms".to_string());
+    }
+    let all_terms = match args.get("mode").and_then(|v| v.as_str()).unwrap_or("or") {
+        "or" => false,
+        "and" => true,
+        other => return ToolCallResult::error(format!("Invalid mode '{}'. Valid values: or, and", other)),
+    };
+    let scope = match args.get("scope").and_then(|v| v.as_str()).unwrap_or("all") {
+        "all" => IN_MESSAGE | IN_ADDED | IN_REMOVED,
+        "message" => IN_MESSAGE,
+        "diff" => IN_ADDED | IN_REMOVED,
+        "added" => IN_ADDED,
+        "removed" => IN_REMOVED,
+        other => return ToolCallResult::error(format!(
+            "Invalid scope '{}'. Valid values: all, message, diff, added, removed", other)),
+    };
+    let include_patches = args.get("includePatches").and_then(|v| v.as_bool()).unwrap_or(true);
+    if !include_patches && scope & IN_MESSAGE == 0 {
+        return ToolCallResult::error("scope requires patch lines; set includePatches=true or use scope='message'".to_string());
+    }
+    let newest_first = match args.get("sortBy").and_then(|v| v.as_str()).unwrap_or("oldest") {
+        "oldest" => false,
+        "newest" => true,
+        other => return ToolCallResult::error(format!("Invalid sortBy '{}'. Valid values: oldest, newest", other)),
+    };
+    let (from, to) = match parse_cache_date_range(
+        args.get("from").and_then(|v| v.as_str()),
+        args.get("to").and_then(|v| v.as_str()),
+        args.get("date").and_then(|v| v.as_str()),
+    ) {
+        Ok(range) => range,
+        Err(e) => return ToolCallResult::error(e),
+    };
+    let max_results = match args.get("maxResults").and_then(|v| v.as_u64()) {
+        Some(0) => usize::MAX,
+        Some(n) => n as usize,
+        None => 50,
+    };
+    let query = CommitQuery {
+        terms,
+        all_terms,
+        scope,
+        author: args.get("author").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|s| s.to_string()),
+        from,
+        to,
+    };
+
+    let start = Instant::now();
+    let mut slot = match ctx.commit_index.lock() {
+        Ok(guard) => guard,
+        Err(e) => return SearchError::LockPoisoned(format!("commit index: {}", e)).into(),
+    };
+    let refresh = match commit_index::load_or_update(&mut slot, Path::new(repo), include_patches, &ctx.index_base,
+        ctx.generation.clock().as_ref()) {
+        Ok(r) => r,
+        Err(e) => return ToolCallResult::error(e),
+    };
+    let index_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let Some(index) = slot.as_ref() else {
+        return ToolCallResult::error("Commit index is unavailable".to_string());
+    };
+
+    let search_start = Instant::now();
+    let mut matches = match index.search(&query) {
+        Ok(m) => m,
+        Err(e) => return ToolCallResult::error(e),
+    };
+    if newest_first {
+        matches.reverse();
+    }
+    let total_matching = matches.len();
+    matches.truncate(max_results);
+
+    let commits: Vec<Value> = matches.iter().map(|m| json!({
+        "hash": m.commit.hash,
+        "date": format_timestamp(m.commit.timestamp),
+        "author": m.commit.author_name,
+        "email": m.commit.author_email,
+        "message": m.commit.subject,
+        "matchedIn": commit_index::flag_names(m.flags),
+    })).collect();
+
+    let mut summary = json!({
+        "tool": "search_git_grep",
+        "totalMatching": total_matching,
+        "returned": commits.len(),
+        "indexedCommits": index.commits.len(),
+        "includesPatches": index.include_patches,
+        "indexStatus": refresh.as_str(),
+        "headHash": index.head_hash,
+        "indexMs": (index_ms * 100.0).round() / 100.0,
+        "searchTimeMs": (search_start.elapsed().as_secs_f64() * 1000.0 * 100.0).round() / 100.0,
+    });
+    if let IndexRefresh::Updated(n) = refresh {
+        summary["newCommitsIndexed"] = json!(n);
+    }
+    if commits.is_empty() {
+        summary["hint"] = json!("No commits match. Terms match whole tokens: search 'MaxRetries', not 'MaxRetr'. Widen scope or drop author/date filters.");
+    }
+
+    let output = json!({
+        "commits": commits,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+// ─── Added lines handler ────────────────────────────────────────────
+
+/// Added line content longer than this is cut in the response.
+const MAX_ADDED_LINE_CHARS: usize = 300;
+
+/// Handle search_git_added_lines — added lines since a ref that contain a term.
+fn handle_git_added_lines(_ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let repo = match args.get("repo").and_then(|v| v.as_str()) {
+        Some(r) => r,
+        None => return ToolCallResult::error("Missing required parameter: repo".to_string()),
+    };
+    let since = match args.get("since").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        Some(s) => s,
+        None => return ToolCallResult::error("Missing required parameter: since".to_string()),
+    };
+    let until = args.get("until").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).unwrap_or("HEAD");
+    let terms: Vec<&str> = args.get("terms").and_then(|v| v.as_str()).unwrap_or("")
+        .split(',').map(str::trim).filter(|t| !t.is_empty()).collect();
+    if terms.is_empty() {
+        return ToolCallResult::error("Missing required parameter: terms".to_string());
+    }
+    let all_terms = match args.get("mode").and_then(|v| v.as_str()).unwrap_or("or") {
+        "or" => false,
+        "and" => true,
+        other => return ToolCallResult::error(format!("Invalid mode '{}'. Valid values: or, and", other)),
+    };
+    // Each term as its tokens; a line matches a term when it contains all of them
+    let mut term_tokens: Vec<Vec<String>> = Vec::with_capacity(terms.len());
+    for term in &terms {
+        let tokens = crate::tokenize(term, 1);
+        if tokens.is_empty() {
+            return ToolCallResult::error(format!("Term '{}' contains no searchable tokens", term));
+        }
+        term_tokens.push(tokens);
+    }
+    let exts: Vec<String> = args.get("ext").and_then(|v| v.as_str()).unwrap_or("")
+        .split(',').map(|e| e.trim().trim_start_matches('.').to_lowercase()).filter(|e| !e.is_empty()).collect();
+    let pathspecs: Vec<String> = args.get("path").and_then(|v| v.as_str())
+        .filter(|p| !p.is_empty()).map(|p| vec![p.to_string()]).unwrap_or_default();
+    let max_results = match args.get("maxResults").and_then(|v| v.as_u64()) {
+        Some(0) => usize::MAX,
+        Some(n) => n as usize,
+        None => 100,
+    };
+
+    let start = Instant::now();
+    let (since_hash, until_hash) = match (git::resolve_commit(repo, since), git::resolve_commit(repo, until)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(e), _) | (_, Err(e)) => return ToolCallResult::error(e),
+    };
+    let mut matched_terms: Vec<Vec<&str>> = Vec::new();
+    let keep = |file: &str, content: &str| {
+        let ext_ok = exts.is_empty() || Path::new(file).extension()
+            .is_some_and(|e| exts.iter().any(|x| e.eq_ignore_ascii_case(x)));
+        // Cheap substring check before tokenizing
+        let lower = content.to_lowercase();
+        if !ext_ok || !term_tokens.iter().any(|tokens| tokens.iter().all(|t| lower.contains(t.as_str()))) {
+            return false;
+        }
+        let line_tokens: std::collections::HashSet<String> = crate::tokenize(content, 1).into_iter().collect();
+        let hits: Vec<&str> = terms.iter().zip(&term_tokens)
+            .filter(|(_, tokens)| tokens.iter().all(|t| line_tokens.contains(t)))
+            .map(|(term, _)| *term)
+            .collect();
+        let matched = if all_terms { hits.len() == terms.len() } else { !hits.is_empty() };
+        if matched {
+            matched_terms.push(hits);
+        }
+        matched
+    };
+    let scan = match git::added_lines_between(repo, &since_hash, &until_hash, &pathspecs, keep) {
+        Ok(s) => s,
+        Err(e) => return ToolCallResult::error(e),
+    };
+
+    let total_matching = scan.lines.len();
+    let mut files_matched: Vec<&str> = scan.lines.iter().map(|l| l.file.as_str()).collect();
+    files_matched.sort_unstable();
+    files_matched.dedup();
+    let lines: Vec<Value> = scan.lines.iter().zip(&matched_terms).take(max_results).map(|(l, hits)| {
+        let content = l.content.trim();
+        let content = match content.char_indices().nth(MAX_ADDED_LINE_CHARS) {
+            Some((end, _)) => format!("{}...", &content[..end]),
+            None => content.to_string(),
+        };
+        json!({
+            "file": l.file,
+            "line": l.line,
+            "content": content,
+            "matchedTerms": hits,
+        })
+    }).collect();
+
+    let mut summary = json!({
+        "tool": "search_git_added_lines",
+        "since": since,
+        "sinceHash": since_hash,
+        "until": until,
+        "untilHash": until_hash,
+        "totalMatching": total_matching,
+        "returned": lines.len(),
+        "filesMatched": files_matched.len(),
+        "filesChanged": scan.files_changed,
+        "addedLinesScanned": scan.added_lines,
+        "elapsedMs": (start.elapsed().as_secs_f64() * 1000.0 * 100.0).round() / 100.0,
+    });
+    if lines.is_empty() {
+        summary["hint"] = json!("No added lines match. Terms match whole tokens: search 'ObsoleteApi', not 'Obsolete'. Check that 'since' is older than 'until', or drop path/ext filters.");
+    }
+
+    let output = json!({
+        "lines": lines,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
 // ─── Branch status handler ──────────────────────────────────────────
 
 /// Handle search_branch_status — shows current branch, ahead/behind, dirty files, fetch age.
@@ -1401,4 +1682,102 @@ mod tests {
             "Should NOT have warning when no path filter is provided"
         );
     }
+
+    #[test]
+    fn test_git_grep_finds_introducing_commit() {
+        use std::process::Command;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git").args(args).current_dir(&repo)
+                .env("GIT_CONFIG_GLOBAL", "/dev/null").env("GIT_CONFIG_SYSTEM", "/dev/null")
+                .output().expect("git");
+            assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        };
+        let commit = |msg: &str| git(&["-c", "user.name=Ada", "-c", "user.email=ada@example.com", "commit", "-q", "-m", msg]);
+        git(&["init", "-q"]);
+        std::fs::write(repo.join("a.rs"), "fn main() {}\n").unwrap();
+        git(&["add", "."]);
+        commit("init");
+        std::fs::write(repo.join("a.rs"), "fn main() { retry_budget(); }\n").unwrap();
+        git(&["add", "."]);
+        commit("Call the budget helper");
+        std::fs::write(repo.join("a.rs"), "fn main() { retry_budget(); retry_budget(); }\n").unwrap();
+        git(&["add", "."]);
+        commit("Call it twice");
+
+        let ctx = super::super::HandlerContext { index_base: tmp.path().join("idx"), ..make_git_test_ctx() };
+        let repo_str = repo.to_string_lossy().to_string();
+        let result = handle_git_grep(&ctx, &json!({ "repo": repo_str, "terms": "retry_budget", "scope": "added" }));
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(output["summary"]["totalMatching"], 2);
+        assert_eq!(output["summary"]["indexStatus"], "built");
+        assert_eq!(output["commits"][0]["message"], "Call the budget helper");
+        assert_eq!(output["commits"][0]["matchedIn"], json!(["added"]));
+
+        // Second call reuses the in-memory index
+        let result = handle_git_grep(&ctx, &json!({ "repo": repo_str, "terms": "budget", "scope": "message", "sortBy": "newest" }));
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(output["summary"]["indexStatus"], "current");
+        assert_eq!(output["summary"]["returned"], 1);
+
+        let result = handle_git_grep(&ctx, &json!({ "repo": repo_str, "terms": "x", "scope": "everywhere" }));
+        assert!(result.is_error);
+        let result = handle_git_grep(&ctx, &json!({ "repo": repo_str, "terms": "x", "scope": "added", "includePatches": false }));
+        assert!(result.is_error);
+    }
+
+    #[test]
+    fn test_git_added_lines_finds_new_uses_since_ref() {
+        use std::process::Command;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git").args(args).current_dir(&repo)
+                .env("GIT_CONFIG_GLOBAL", "/dev/null").env("GIT_CONFIG_SYSTEM", "/dev/null")
+                .output().expect("git");
+            assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        };
+        let commit = |msg: &str| git(&["-c", "user.name=Ada", "-c", "user.email=ada@example.com", "commit", "-q", "-m", msg]);
+        git(&["init", "-q"]);
+        std::fs::write(repo.join("src/a.cs"), "class A {\n    void Run() { ObsoleteApi.Call(); }\n}\n").unwrap();
+        git(&["add", "."]);
+        commit("init");
+        git(&["tag", "v1"]);
+        std::fs::write(repo.join("src/a.cs"), "class A {\n    void Run() { ObsoleteApi.Call(); }\n    void Go() { ObsoleteApi.Other(); }\n}\n").unwrap();
+        std::fs::write(repo.join("src/b.ts"), "// ObsoleteApiWrapper is fine\nObsoleteApi.call();\n").unwrap();
+        git(&["add", "."]);
+        commit("more uses");
+
+        let ctx = make_git_test_ctx();
+        let repo_str = repo.to_string_lossy().to_string();
+        let result = handle_git_added_lines(&ctx, &json!({ "repo": repo_str, "since": "v1", "terms": "ObsoleteApi" }));
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        // The pre-existing use and the longer identifier are not reported
+        assert_eq!(output["summary"]["totalMatching"], 2);
+        assert_eq!(output["summary"]["filesChanged"], 2);
+        assert_eq!(output["summary"]["addedLinesScanned"], 3);
+        assert_eq!(output["lines"][0], json!({
+            "file": "src/a.cs", "line": 3, "content": "void Go() { ObsoleteApi.Other(); }", "matchedTerms": ["ObsoleteApi"],
+        }));
+        assert_eq!((output["lines"][1]["file"].as_str(), output["lines"][1]["line"].as_u64()), (Some("src/b.ts"), Some(2)));
+
+        // Filters: extension, multi-token term with mode=and
+        let result = handle_git_added_lines(&ctx, &json!({ "repo": repo_str, "since": "v1", "terms": "ObsoleteApi", "ext": "ts" }));
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(output["summary"]["totalMatching"], 1);
+        let result = handle_git_added_lines(&ctx, &json!({ "repo": repo_str, "since": "v1", "terms": "ObsoleteApi.Other,Go", "mode": "and" }));
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(output["summary"]["totalMatching"], 1);
+        assert_eq!(output["lines"][0]["matchedTerms"], json!(["ObsoleteApi.Other", "Go"]));
+
+        assert!(handle_git_added_lines(&ctx, &json!({ "repo": repo_str, "since": "nope", "terms": "x" })).is_error);
+        assert!(handle_git_added_lines(&ctx, &json!({ "repo": repo_str, "terms": "x" })).is_error);
+    }
 }
@@ -1,27 +1,71 @@
 //! search_grep handler: token search, substring search, phrase search.
 
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
 use std::time::Instant;
 
 use serde_json::{json, Value};
 
+use crate::mcp::progress;
 use crate::mcp::protocol::ToolCallResult;
-use crate::{tokenize, ContentIndex};
-use crate::index::build_trigram_index;
-use search::generate_trigrams;
+use crate::{ContentIndex, SearchError, Searcher, TrigramIndex};
+use crate::index::{build_trigram_index, load_or_build_content_index_at_rev};
+use crate::rank_hook::{repo_path, Candidate, HookOutcome, RankTool};
+use search::query::{token_regex, DefinitionBoost, ScoredFile};
+use search::lock_stats::{self, Lock};
 
+use super::read_file::resolve_symbol;
 use super::utils::{
-    build_line_content_from_matches, inject_branch_warning, is_under_dir, matches_ext_filter,
-    sorted_intersect, validate_search_dir,
+    apply_grep_sort, build_line_content_from_matches, cmp_ranked, map_indexed_files, MatchLocator, Page, PageInfo, inject_branch_warning,
+    inject_owners, inject_owners_source, is_under_dir, matches_ext_filter, normalize_path_sep,
+    owner_allows, parse_owner_filter, read_indexed_files, validate_search_dir, explain_json, GrepSort,
+    boosted, fuse_ranks, skip_stop_terms, group_by_dir, Boost, BoundaryFilter, CaseFilter, GrepGroup, Ranking, TermScore, EXPLAIN_FORMULA,
 };
 use super::HandlerContext;
 
+/// Index tokens a regex is matched against between progress reports.
+const PROGRESS_STRIDE: usize = 16_384;
+
 pub(crate) struct FileScoreEntry {
+    pub file_id: u32,
     pub file_path: String,
     pub lines: Vec<u32>,
+    /// TF-IDF sum, then multiplied by `boosts` before ranking
     pub tf_idf: f64,
+    /// The definition the [`Scorer`] boosted the file for
+    pub definition: Option<DefinitionBoost>,
     pub occurrences: usize,
     pub terms_matched: usize,
+    /// Per-token score breakdown, collected only for `explain`
+    pub explain: Vec<TermScore>,
+    pub boosts: Vec<Boost>,
+    /// Set by a `rerank`: where the file stood before and what it fused to
+    pub fusion: Option<Fusion>,
+}
+
+impl FileScoreEntry {
+    fn scored(index: &ContentIndex, file: ScoredFile) -> Self {
+        FileScoreEntry {
+            file_id: file.file_id,
+            file_path: index.files[file.file_id as usize].clone(),
+            lines: file.lines,
+            tf_idf: file.tf_idf,
+            definition: file.definition,
+            occurrences: file.occurrences,
+            terms_matched: file.terms_matched,
+            explain: file.explain,
+            boosts: Vec::new(),
+            fusion: None,
+        }
+    }
+}
+
+pub(crate) struct Fusion {
+    pub lexical_rank: usize,
+    pub semantic_rank: Option<usize>,
+    pub similarity: Option<f64>,
+    pub score: f64,
 }
 
 pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
@@ -29,6 +73,9 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
         Some(t) => t.to_string(),
         None => return ToolCallResult::error("Missing required parameter: terms".to_string()),
     };
+    if let Some(group) = args.get("groupBy").and_then(|v| v.as_str()) {
+        return handle_grouped_grep(ctx, args, group);
+    }
 
     // Check dir parameter -- must match server dir or be a subdirectory
     let dir_filter: Option<String> = if let Some(dir) = args.get("dir").and_then(|v| v.as_str()) {
@@ -57,12 +104,116 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
     } else {
         args.get("substring").and_then(|v| v.as_bool()).unwrap_or(true)
     };
+    let word_boundary = args.get("wordBoundary").and_then(|v| v.as_bool()).unwrap_or(false);
+    if word_boundary && (use_regex || use_phrase) {
+        return ToolCallResult::error("wordBoundary applies to substring search, not to regex or phrase".to_string());
+    }
     let context_lines = args.get("contextLines").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
     // Auto-enable showLines when contextLines > 0 (BUG-6: contextLines without showLines was silently ignored)
     let show_lines = args.get("showLines").and_then(|v| v.as_bool()).unwrap_or(false)
         || context_lines > 0;
+    let highlight = args.get("highlight").and_then(|v| v.as_bool()).unwrap_or(false);
     let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
+    let page = match Page::from_args(ctx, "search_grep", args) {
+        Ok(page) => page,
+        Err(msg) => return ToolCallResult::error(msg),
+    };
+    let page = page.as_ref();
     let count_only = args.get("countOnly").and_then(|v| v.as_bool()).unwrap_or(false);
+    let estimate = args.get("estimate").and_then(|v| v.as_bool()).unwrap_or(false);
+    if estimate && use_phrase {
+        return ToolCallResult::error("estimate is not supported with phrase. Use countOnly for an exact count".to_string());
+    }
+    let case_sensitive = args.get("caseSensitive").and_then(|v| v.as_bool()).unwrap_or(false);
+    if case_sensitive && use_regex {
+        return ToolCallResult::error("caseSensitive is not supported with regex: patterns match lowercased index tokens".to_string());
+    }
+    if (case_sensitive || word_boundary) && estimate {
+        return ToolCallResult::error(format!("estimate is not supported with {}: it is only known by reading the files. Use countOnly",
+            if case_sensitive { "caseSensitive" } else { "wordBoundary" }));
+    }
+    let explain = args.get("explain").and_then(|v| v.as_bool()).unwrap_or(false);
+    if explain && use_phrase {
+        return ToolCallResult::error("explain is not supported with phrase: phrase results are not ranked".to_string());
+    }
+    let ranking = if args.get("boosts").and_then(|v| v.as_bool()).unwrap_or(true) {
+        // Regex patterns are not names: only the path penalties apply to them
+        let terms: Vec<String> = if use_regex { Vec::new() } else { terms_str.split(',').map(|t| t.trim().to_string()).collect() };
+        let ranking = Ranking::new(ctx.ranking, &terms, explain);
+        // Phrase matches and bare counts are not ranked
+        if ranking.wants_definitions() && !use_phrase && !count_only {
+            let files = defining_files(ctx, ranking.terms());
+            ranking.with_defining_files(files)
+        } else {
+            ranking
+        }
+    } else {
+        Ranking::unboosted(explain)
+    };
+    // Resolved before the content lock is taken: the two indexes are never locked together
+    let within = match args.get("withinDefinition") {
+        Some(arg) => {
+            if estimate {
+                return ToolCallResult::error("estimate is not supported with withinDefinition".to_string());
+            }
+            if args.get("rev").and_then(|v| v.as_str()).is_some_and(|s| !s.is_empty()) {
+                return ToolCallResult::error(
+                    "withinDefinition is not supported with rev: definition lines describe the working tree".to_string());
+            }
+            match resolve_within_definition(ctx, arg) {
+                Ok(within) => Some(within),
+                Err(msg) => return ToolCallResult::error(msg),
+            }
+        }
+        None => None,
+    };
+    let related_limit = args.get("relatedTerms").and_then(|v| v.as_u64()).unwrap_or(0).min(50) as usize;
+    let sort = match args.get("sortBy").and_then(|v| v.as_str()) {
+        Some(s) => match s.parse::<GrepSort>() {
+            Ok(sort) => sort,
+            Err(e) => return ToolCallResult::error(e),
+        },
+        None => GrepSort::Score,
+    };
+    let ranking = if args.get("rerank").and_then(|v| v.as_bool()).unwrap_or(false) {
+        if use_phrase {
+            return ToolCallResult::error("rerank is not supported with phrase: phrase results are not ranked".to_string());
+        }
+        if sort != GrepSort::Score {
+            return ToolCallResult::error(format!("rerank reorders by relevance, it cannot be combined with sortBy={}", sort.as_str()));
+        }
+        if args.get("rev").and_then(|v| v.as_str()).is_some_and(|s| !s.is_empty()) {
+            return ToolCallResult::error(
+                "rerank is not supported with rev: the semantic index describes the working tree".to_string());
+        }
+        if count_only {
+            ranking
+        } else {
+            // Before the content lock: the semantic index takes its own snapshot
+            let query = terms_str.split(',').map(str::trim).collect::<Vec<_>>().join(" ");
+            match super::semantic::semantic_file_scores(ctx, &query) {
+                Ok((scores, refresh)) => ranking.with_semantic_scores(scores, refresh.as_str()),
+                Err(e) => return e,
+            }
+        }
+    } else {
+        ranking
+    };
+    // Phrase matches are not ranked, so there is nothing for a policy to reorder
+    let ranking = if args.get("rankHook").and_then(|v| v.as_bool()).unwrap_or(true) && !use_phrase {
+        ranking.with_rank_hook(ctx.rank_hook.clone())
+    } else {
+        ranking
+    };
+    // Historical revision: search a cached index of that commit instead of the live one
+    let rev_index = match args.get("rev").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        Some(rev) => match load_or_build_content_index_at_rev(&ctx.server_dir, &ctx.server_ext, rev, &ctx.index_base) {
+            Ok(idx) => Some(Searcher::new(idx)),
+            Err(e) => return e.into(),
+        },
+        None => None,
+    };
+    let searcher: &Searcher = rev_index.as_ref().unwrap_or(&ctx.index);
     let exclude_dir: Vec<String> = args.get("excludeDir")
         .and_then(|v| v.as_array())
         .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
@@ -72,6 +223,12 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
         .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
         .unwrap_or_default();
 
+    let owner_filter = match parse_owner_filter(ctx.owners.as_deref(), args) {
+        Ok(filter) => filter,
+        Err(msg) => return ToolCallResult::error(msg),
+    };
+    let owner_filter = owner_filter.as_deref();
+
     let search_start = Instant::now();
 
     // (Mutual exclusivity check is now handled above during use_substring init)
@@ -79,52 +236,86 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
     // --- Substring: check if trigram index needs rebuild -----
     if use_substring {
         let trigram_check_start = Instant::now();
-        let needs_rebuild = ctx.index.read().map(|idx| idx.trigram_dirty).unwrap_or(false);
-        if needs_rebuild {
+        let current = searcher.snapshot();
+        if current.trigram_dirty {
             eprintln!("[substring-trace] Trigram dirty, rebuilding...");
             let rebuild_start = Instant::now();
-            // Build trigram index under READ lock (doesn't block other readers)
-            let new_trigram = ctx.index.read().ok().and_then(|idx| {
-                if idx.trigram_dirty {
-                    Some(build_trigram_index(&idx.index))
-                } else {
-                    None
+            // Build from a snapshot (blocks nobody), then drop it: unless another query
+            // holds a snapshot, the update below edits the index in place without a copy
+            let trigram = build_trigram_index(&current.index);
+            drop(current);
+            searcher.update(|idx| {
+                if idx.trigram_dirty {  // a watcher batch may have rebuilt it meanwhile
+                    eprintln!("[substring] Rebuilt trigram index: {} tokens, {} trigrams",
+                        trigram.tokens.len(), trigram.trigram_map.len());
+                    idx.trigram = trigram;
+                    idx.trigram_dirty = false;
                 }
             });
-            // Swap in under brief WRITE lock (microseconds, not ~200ms)
-            if let Some(trigram) = new_trigram {
-                if let Ok(mut idx) = ctx.index.write() {
-                    if idx.trigram_dirty {  // double-check after acquiring write lock
-                        eprintln!("[substring] Rebuilt trigram index: {} tokens, {} trigrams",
-                            trigram.tokens.len(), trigram.trigram_map.len());
-                        idx.trigram = trigram;
-                        idx.trigram_dirty = false;
-                    }
-                }
-            }
             eprintln!("[substring-trace] Trigram rebuild: {:.3}ms", rebuild_start.elapsed().as_secs_f64() * 1000.0);
         } else {
             eprintln!("[substring-trace] Trigram dirty check: clean in {:.3}ms", trigram_check_start.elapsed().as_secs_f64() * 1000.0);
         }
     }
 
-    let index = match ctx.index.read() {
-        Ok(idx) => idx,
-        Err(e) => return ToolCallResult::error(format!("Failed to acquire index lock: {}", e)),
+    let index = searcher.snapshot();
+    let scope = match within.as_ref().map(|w| w.scope(&index)).transpose() {
+        Ok(scope) => scope,
+        Err(msg) => return ToolCallResult::error(msg),
+    };
+    let scope = scope.as_ref();
+    let opts = GrepOptions {
+        filters: PathFilters {
+            dir: &dir_filter, ext: &ext_filter, exclude_dir: &exclude_dir, exclude: &exclude, owner: owner_filter,
+        },
+        scope,
+        show_lines, context_lines, highlight, max_results, page, mode_and, count_only, sort, related_limit,
+        ranking: &ranking, case_sensitive, word_boundary, search_start,
     };
 
+    // --- Terms the index cannot answer ----------------------
+    // Tokens outside the index's length limits, or text spanning characters the
+    // tokenizer splits on, were never indexed. A query of one such term scans file
+    // contents instead; otherwise the summary warns which terms cannot match.
+    let mut term_warnings: Vec<String> = Vec::new();
+    if !use_regex && !use_phrase {
+        let raw_terms: Vec<String> = terms_str.split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+        term_warnings = raw_terms.iter().filter_map(|t| index.tokenizer.unanswerable(t, use_substring)).collect();
+        if raw_terms.len() == 1 && !term_warnings.is_empty() && !estimate {
+            term_warnings.push("Searched file contents for the term instead of the index".to_string());
+            let term = terms_str.split(',').map(str::trim).find(|t| !t.is_empty()).unwrap_or_default();
+            return handle_phrase_search(ctx, &index, term, &opts, "content-scan", term_warnings);
+        }
+    }
+
+    // --- Estimated count ------------------------------------
+    if estimate {
+        let mode = if use_regex { "regex" } else if use_substring { "substring" } else { "exact" };
+        return match estimate_count(ctx, &index, &terms_str, mode, mode_and, &opts.filters) {
+            Ok(mut summary) => {
+                summary["searchMode"] = json!(format!("{}-{}", mode, if mode_and { "and" } else { "or" }));
+                summary["searchTimeMs"] = json!(search_start.elapsed().as_secs_f64() * 1000.0);
+                if !term_warnings.is_empty() {
+                    summary["warnings"] = json!(term_warnings);
+                }
+                finish_summary(&mut summary, &index, ctx, None);
+                ToolCallResult::success(serde_json::to_string(&json!({ "summary": summary })).unwrap())
+            }
+            Err(msg) => ToolCallResult::error(msg),
+        };
+    }
+
     // --- Substring search mode ------------------------------
     if use_substring {
-        return handle_substring_search(ctx, &index, &terms_str, &ext_filter, &exclude_dir, &exclude,
-            show_lines, context_lines, max_results, mode_and, count_only, search_start, &dir_filter);
+        return handle_substring_search(ctx, &index, &terms_str, &opts, term_warnings);
     }
 
     // --- Phrase search mode ---------------------------------
     if use_phrase {
-        return handle_phrase_search(
-            ctx, &index, &terms_str, &ext_filter, &exclude_dir, &exclude,
-            show_lines, context_lines, max_results, count_only, search_start, &dir_filter,
-        );
+        return handle_phrase_search(ctx, &index, &terms_str, &opts, "phrase", Vec::new());
     }
 
     // --- Normal token search --------------------------------
@@ -140,37 +331,47 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
     }
 
     // If regex mode, expand each pattern
-    let terms: Vec<String> = if use_regex {
+    let mut terms: Vec<String> = if use_regex {
         let mut expanded = Vec::new();
-        for pat in &raw_terms {
-            match regex::Regex::new(&format!("(?i)^{}$", pat)) {
+        let total_tokens = index.index.len() * raw_terms.len();
+        for (p, pat) in raw_terms.iter().enumerate() {
+            match token_regex(pat) {
                 Ok(re) => {
-                    let matching: Vec<String> = index.index.keys()
-                        .filter(|k| re.is_match(k))
-                        .cloned()
-                        .collect();
-                    expanded.extend(matching);
+                    for (n, k) in index.index.keys().enumerate() {
+                        if n % PROGRESS_STRIDE == 0 {
+                            progress::report(p * index.index.len() + n, Some(total_tokens), "matching regex against index tokens");
+                        }
+                        if re.is_match(k) {
+                            expanded.push(k.to_string());
+                        }
+                    }
                 }
-                Err(e) => return ToolCallResult::error(format!("Invalid regex '{}': {}", pat, e)),
+                Err(e) => return SearchError::InvalidRegex { pattern: pat.to_string(), source: e }.into(),
             }
         }
         expanded
     } else {
         raw_terms.clone()
     };
+    if !use_regex {
+        term_warnings.extend(skip_stop_terms(&index, &mut terms, mode_and));
+    }
 
-    let total_docs = index.files.len() as f64;
     let search_mode = if use_regex { "regex" } else if mode_and { "and" } else { "or" };
     let term_count_for_all = if use_regex { raw_terms.len() } else { terms.len() };
 
     // Collect per-file scores
-    let mut file_scores: HashMap<u32, FileScoreEntry> = HashMap::new();
-
-    for term in &terms {
+    let mut scorer = ranking.scorer(&index);
+    let mut case_filter = case_sensitive.then(|| CaseFilter::new(&index, &terms_str));
+
+    for (term_no, term) in terms.iter().enumerate() {
+        // The directory's bloom filter answers "not here" without walking the postings
+        if let Some(ref prefix) = dir_filter
+            && !index.dir_may_contain(prefix, term)
+        {
+            continue;
+        }
         if let Some(postings) = index.index.get(term.as_str()) {
-            let doc_freq = postings.len() as f64;
-            let idf = (total_docs / doc_freq).ln();
-
             for posting in postings {
                 let file_path = match index.files.get(posting.file_id as usize) {
                     Some(p) => p,
@@ -197,52 +398,44 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
                     file_path.to_lowercase().contains(&excl.to_lowercase())
                 }) { continue; }
 
-                let occurrences = posting.lines.len();
-                let file_total = if (posting.file_id as usize) < index.file_token_counts.len() {
-                    index.file_token_counts[posting.file_id as usize] as f64
-                } else {
-                    1.0
+                let Some(lines) = scoped_lines(scope, posting) else { continue };
+                let lines = match case_filter.as_mut() {
+                    Some(filter) => match filter.retain(posting.file_id, &lines, &[term.as_str()], false) {
+                        kept if kept.is_empty() => continue,
+                        kept => Cow::Owned(kept),
+                    },
+                    None => lines,
                 };
-                let tf = occurrences as f64 / file_total;
-                let tf_idf = tf * idf;
-
-                let entry = file_scores.entry(posting.file_id).or_insert(FileScoreEntry {
-                    file_path: file_path.clone(),
-                    lines: Vec::new(),
-                    tf_idf: 0.0,
-                    occurrences: 0,
-                    terms_matched: 0,
-                });
-                entry.tf_idf += tf_idf;
-                entry.occurrences += occurrences;
-                entry.lines.extend_from_slice(&posting.lines);
-                entry.terms_matched += 1;
+                scorer.add(posting.file_id, term_no, term, term, postings.len(), &lines);
             }
         }
     }
 
     // Filter by AND mode
-    let mut results: Vec<FileScoreEntry> = file_scores
-        .into_values()
+    let mut results: Vec<FileScoreEntry> = scorer.finish()
+        .into_iter()
         .filter(|fs| !mode_and || fs.terms_matched >= term_count_for_all)
+        .map(|fs| FileScoreEntry::scored(&index, fs))
+        .filter(|fs| owner_allows(ctx.owners.as_deref(), owner_filter, &fs.file_path))
         .collect();
 
-    // Sort/dedup lines
-    for result in &mut results {
-        result.lines.sort();
-        result.lines.dedup();
-    }
-
-    // Sort by TF-IDF descending
-    results.sort_by(|a, b| b.tf_idf.partial_cmp(&a.tf_idf).unwrap_or(std::cmp::Ordering::Equal));
+    // Sort by boosted TF-IDF descending (ties by path for repeatable output)
+    apply_ranking(&mut results, &ranking);
+    results.sort_by(|a, b| cmp_ranked((a.tf_idf, &a.file_path, a.file_id), (b.tf_idf, &b.file_path, b.file_id)));
+    apply_rerank(&mut results, &ranking);
+    let hooked = apply_rank_hook(&mut results, &ranking, &index.root);
+    apply_grep_sort(&mut results, sort, |r| r.file_path.as_str(), |r| r.occurrences);
 
     let total_files = results.len();
     let total_occurrences: usize = results.iter().map(|r| r.occurrences).sum();
+    let related = if related_limit > 0 && !count_only {
+        let file_ids: Vec<u32> = results.iter().map(|r| r.file_id).collect();
+        Some(related_terms(&index, &file_ids, &terms, related_limit))
+    } else {
+        None
+    };
 
-    // Apply max_results
-    if max_results > 0 {
-        results.truncate(max_results);
-    }
+    let page_info = take_page(&mut results, page, max_results);
 
     let search_elapsed = search_start.elapsed();
 
@@ -257,7 +450,13 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
             "searchTimeMs": search_elapsed.as_secs_f64() * 1000.0,
             "indexLoadTimeMs": 0.0
         });
-        inject_branch_warning(&mut summary, ctx);
+        if !term_warnings.is_empty() {
+            summary["warnings"] = json!(term_warnings);
+        }
+        if let Some(outcome) = &hooked {
+            outcome.inject(&mut summary);
+        }
+        finish_summary(&mut summary, &index, ctx, scope);
         let output = json!({
             "summary": summary
         });
@@ -265,7 +464,13 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
     }
 
     // Build JSON output
-    let files_json: Vec<Value> = results.iter().map(|r| {
+    let locator = MatchLocator::Tokens(terms.iter().cloned().collect());
+    let contents = if show_lines {
+        read_indexed_files(&index, &results.iter().map(|r| r.file_path.as_str()).collect::<Vec<_>>())
+    } else {
+        Vec::new()
+    };
+    let files_json: Vec<Value> = results.iter().enumerate().map(|(i, r)| {
         let mut file_obj = json!({
             "path": r.file_path,
             "score": (r.tf_idf * 10000.0).round() / 10000.0,
@@ -273,11 +478,17 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
             "termsMatched": format!("{}/{}", r.terms_matched, terms.len()),
             "lines": r.lines,
         });
+        if explain {
+            file_obj["explain"] = explain_json(r.tf_idf, &r.explain, &r.boosts);
+        }
+        if let Some(fusion) = &r.fusion {
+            file_obj["rerank"] = fusion_json(fusion);
+        }
+        inject_owners(&mut file_obj, ctx.owners.as_deref(), &r.file_path);
 
-        if show_lines
-            && let Ok(content) = std::fs::read_to_string(&r.file_path) {
-                file_obj["lineContent"] = build_line_content_from_matches(&content, &r.lines, context_lines);
-            }
+        if let Some(Some(content)) = contents.get(i) {
+            file_obj["lineContent"] = build_line_content_from_matches(content, &r.lines, context_lines, Some(&locator), highlight);
+        }
 
         file_obj
     }).collect();
@@ -292,7 +503,28 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
         "searchTimeMs": search_elapsed.as_secs_f64() * 1000.0,
         "indexLoadTimeMs": 0.0
     });
-    inject_branch_warning(&mut summary, ctx);
+    if explain {
+        summary["scoring"] = json!(EXPLAIN_FORMULA);
+    }
+    if sort != GrepSort::Score {
+        summary["sortedBy"] = json!(sort.as_str());
+    }
+    if let Some(rerank) = ranking.rerank_summary() {
+        summary["rerank"] = rerank;
+    }
+    if let Some(related) = related {
+        summary["relatedTerms"] = json!(related);
+    }
+    if !term_warnings.is_empty() {
+        summary["warnings"] = json!(term_warnings);
+    }
+    if let Some(info) = &page_info {
+        info.inject(&mut summary);
+    }
+    if let Some(outcome) = &hooked {
+        outcome.inject(&mut summary);
+    }
+    finish_summary(&mut summary, &index, ctx, scope);
     let output = json!({
         "files": files_json,
         "summary": summary
@@ -301,23 +533,443 @@ pub(crate) fn handle_search_grep(ctx: &HandlerContext, args: &Value) -> ToolCall
     ToolCallResult::success(serde_json::to_string(&output).unwrap())
 }
 
-/// Substring search using the trigram index.
-fn handle_substring_search(
+/// `groupBy`: run the search for every matching file, without lines, and report the
+/// files per group instead. `maxResults` limits the groups.
+fn handle_grouped_grep(ctx: &HandlerContext, args: &Value, group: &str) -> ToolCallResult {
+    let group = match group.parse::<GrepGroup>() {
+        Ok(group) => group,
+        Err(e) => return ToolCallResult::error(e),
+    };
+    if let Some(arg) = ["countOnly", "estimate", "cursor", "pageSize"].into_iter()
+        .find(|key| args.get(*key).is_some_and(|v| v.as_bool() != Some(false))) {
+        return ToolCallResult::error(format!("groupBy is not supported with {}", arg));
+    }
+    let depth = args.get("groupDepth").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+    let max_groups = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
+
+    let mut file_args = args.clone();
+    if let Some(obj) = file_args.as_object_mut() {
+        for key in ["groupBy", "groupDepth", "showLines", "contextLines"] {
+            obj.remove(key);
+        }
+        obj.insert("maxResults".to_string(), json!(0));
+    }
+    let result = handle_search_grep(ctx, &file_args);
+    if result.is_error {
+        return result;
+    }
+    let Some(mut output) = result.content.first().and_then(|c| serde_json::from_str::<Value>(&c.text).ok()) else {
+        return result;
+    };
+    let files = output["files"].as_array().cloned().unwrap_or_default();
+    let mut groups = match group {
+        GrepGroup::Dir => group_by_dir(&ctx.server_dir, files.iter().map(|f| {
+            (f["path"].as_str().unwrap_or_default(), f["occurrences"].as_u64().unwrap_or(0) as usize)
+        }), depth),
+    };
+    let summary = &mut output["summary"];
+    summary["groupedBy"] = json!("dir");
+    summary["totalGroups"] = json!(groups.len());
+    if max_groups > 0 {
+        groups.truncate(max_groups);
+    }
+    ToolCallResult::success(serde_json::to_string(&json!({ "groups": groups, "summary": output["summary"] })).unwrap())
+}
+
+/// Cut the ranked `results` to the requested page, or to `max_results` (0 = all)
+/// without one.
+fn take_page<T>(results: &mut Vec<T>, page: Option<&Page>, max_results: usize) -> Option<PageInfo> {
+    match page {
+        Some(page) => Some(page.apply(results)),
+        None => {
+            if max_results > 0 {
+                results.truncate(max_results);
+            }
+            None
+        }
+    }
+}
+
+/// Revision searches report the commit instead of a branch warning: the working-tree
+/// branch says nothing about a historical snapshot.
+fn finish_summary(summary: &mut Value, index: &ContentIndex, ctx: &HandlerContext, scope: Option<&LineScope>) {
+    match index.revision {
+        Some(ref commit) => summary["revision"] = json!(commit),
+        None => inject_branch_warning(summary, ctx),
+    }
+    if let Some(scope) = scope {
+        summary["withinDefinition"] = scope.definition.clone();
+    }
+    inject_owners_source(summary, ctx.owners.as_deref());
+}
+
+/// A `withinDefinition` argument resolved against the definition index.
+struct WithinDefinition {
+    path: String,
+    line_start: u32,
+    line_end: u32,
+    /// Reported as `summary.withinDefinition`
+    definition: Value,
+}
+
+impl WithinDefinition {
+    /// The definition's file in the content index.
+    fn scope(&self, index: &ContentIndex) -> Result<LineScope, String> {
+        let wanted = normalize_path_sep(&self.path).to_lowercase();
+        let file_id = index.files.iter()
+            .position(|f| normalize_path_sep(f).to_lowercase() == wanted)
+            .ok_or_else(|| format!("'{}' is not in the content index.", self.path))?;
+        Ok(LineScope {
+            file_id: file_id as u32,
+            line_start: self.line_start,
+            line_end: self.line_end,
+            definition: self.definition.clone(),
+        })
+    }
+}
+
+/// Lines of one file that matches are limited to.
+struct LineScope {
+    file_id: u32,
+    line_start: u32,
+    line_end: u32,
+    definition: Value,
+}
+
+impl LineScope {
+    fn contains(&self, line: u32) -> bool {
+        (self.line_start..=self.line_end).contains(&line)
+    }
+}
+
+/// `withinDefinition: {file, name, parent?}`: the one definition named `name` in a
+/// file whose path contains `file`, as search_read_file resolves `symbol`.
+fn resolve_within_definition(ctx: &HandlerContext, arg: &Value) -> Result<WithinDefinition, String> {
+    let field = |key: &str| arg.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    let file = field("file").ok_or("withinDefinition.file is required")?;
+    let name = field("name").ok_or("withinDefinition.name is required")?;
+    if ctx.def_index.is_none() {
+        return Err("Definition index not available. Start server with --definitions flag to use withinDefinition.".to_string());
+    }
+    let (path, mut definition, line_start, line_end) = resolve_symbol(ctx, name, field("parent"), Some(file))?;
+    definition["file"] = json!(path);
+    Ok(WithinDefinition { path, line_start, line_end, definition })
+}
+
+/// Multiply each result's TF-IDF by the ranking boosts of its path and definitions.
+fn apply_ranking(results: &mut [FileScoreEntry], ranking: &Ranking) {
+    for result in results {
+        result.boosts = ranking.boosts(&result.file_path, result.definition.as_ref());
+        result.tf_idf = boosted(result.tf_idf, &result.boosts);
+    }
+}
+
+/// Reorder ranked results by reciprocal rank fusion with the semantic similarities of
+/// a `rerank` ([`fuse_ranks`]). No-op without one.
+fn apply_rerank(results: &mut [FileScoreEntry], ranking: &Ranking) {
+    if !ranking.reranks() {
+        return;
+    }
+    let similarities: Vec<Option<f64>> = results.iter().map(|r| ranking.semantic_score(&r.file_path)).collect();
+    for (i, (result, (score, semantic_rank))) in results.iter_mut().zip(fuse_ranks(&similarities)).enumerate() {
+        result.fusion = Some(Fusion { lexical_rank: i + 1, semantic_rank, similarity: similarities[i], score });
+    }
+    // Stable: equal fused scores keep their lexical order
+    results.sort_by(|a, b| fused(b).total_cmp(&fused(a)));
+}
+
+/// Re-score the ranked results with the `--rank-hook` policy, before the page or
+/// `maxResults` cut. `None` without a hook.
+pub(crate) fn apply_rank_hook(results: &mut Vec<FileScoreEntry>, ranking: &Ranking, root: &str) -> Option<HookOutcome> {
+    let hook = ranking.rank_hook()?;
+    Some(hook.apply(RankTool::Grep, results, |_, r| Candidate {
+        path: repo_path(root, &r.file_path),
+        name: "",
+        kind: u32::MAX,
+        line: r.lines.first().copied().unwrap_or(0),
+        score: r.fusion.as_ref().map_or(r.tf_idf, |f| f.score) as f32,
+    }))
+}
+
+fn fused(result: &FileScoreEntry) -> f64 {
+    result.fusion.as_ref().map_or(0.0, |f| f.score)
+}
+
+fn fusion_json(fusion: &Fusion) -> Value {
+    let round = |x: f64| (x * 10000.0).round() / 10000.0;
+    json!({
+        "lexicalRank": fusion.lexical_rank,
+        "semanticRank": fusion.semantic_rank,
+        "semanticScore": fusion.similarity.map(round),
+        "fusedScore": round(fusion.score),
+    })
+}
+
+/// [`search::query::defining_files`] of the server's definition index. Empty while
+/// the definition index is missing or still building.
+fn defining_files(ctx: &HandlerContext, terms: &[String]) -> HashMap<String, String> {
+    let Some(def_arc) = &ctx.def_index else { return HashMap::new() };
+    if !ctx.def_ready.load(Ordering::Acquire) {
+        return HashMap::new();
+    }
+    let Ok(index) = lock_stats::timed(Lock::Definitions, || def_arc.read()) else { return HashMap::new() };
+    search::query::defining_files(&index, terms)
+}
+
+/// The posting's lines inside `scope` (all of them without one); `None` when none are.
+fn scoped_lines<'a>(scope: Option<&LineScope>, posting: &'a crate::Posting) -> Option<Cow<'a, [u32]>> {
+    match scope {
+        None => Some(Cow::Borrowed(&posting.lines)),
+        Some(s) if s.file_id != posting.file_id => None,
+        Some(s) => {
+            let lines: Vec<u32> = posting.lines.iter().copied().filter(|&l| s.contains(l)).collect();
+            (!lines.is_empty()).then_some(Cow::Owned(lines))
+        }
+    }
+}
+
+/// Tokens that co-occur with the matched files more than their overall frequency predicts.
+///
+/// Scans every posting list once and counts, per token, how many of `file_ids` contain it;
+/// the score is that count weighted by IDF, so rare tokens (a `UserServiceFactory`,
+/// `UserServiceTests`) outrank ubiquitous ones (`public`, `return`). Tokens shorter than 3
+/// characters, numbers, and `skip` (the query terms) are ignored. A token must appear in at
+/// least two matched files unless only one file matched.
+pub(crate) fn related_terms(index: &ContentIndex, file_ids: &[u32], skip: &[String], limit: usize) -> Vec<Value> {
+    if file_ids.is_empty() || limit == 0 {
+        return Vec::new();
+    }
+    let mut matched = vec![false; index.files.len()];
+    for &id in file_ids {
+        if let Some(slot) = matched.get_mut(id as usize) {
+            *slot = true;
+        }
+    }
+    let min_co = if file_ids.len() > 1 { 2 } else { 1 };
+    let total_docs = index.files.len() as f64;
+
+    let mut scored: Vec<(f64, &str, usize, usize)> = Vec::new();
+    for (token, postings) in &index.index {
+        if token.len() < 3 || token.chars().all(|c| c.is_ascii_digit()) || skip.iter().any(|s| **s == **token) {
+            continue;
+        }
+        let co = postings.iter().filter(|p| matched.get(p.file_id as usize).copied().unwrap_or(false)).count();
+        if co < min_co {
+            continue;
+        }
+        let score = co as f64 * (total_docs / postings.len() as f64).ln();
+        if score > 0.0 {
+            scored.push((score, &**token, co, postings.len()));
+        }
+    }
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    scored.into_iter().take(limit)
+        .map(|(_, term, co, df)| json!({ "term": term, "files": co, "docFreq": df }))
+        .collect()
+}
+
+/// Files sampled to estimate the share that passes the path filters (±5% at 95%).
+const ESTIMATE_SAMPLE: usize = 400;
+/// z for the 95% confidence bounds of an estimate.
+const ESTIMATE_Z: f64 = 1.96;
+
+/// The path filters of one search_grep call.
+#[derive(Clone, Copy)]
+struct PathFilters<'a> {
+    dir: &'a Option<String>,
+    ext: &'a Option<String>,
+    exclude_dir: &'a [String],
+    exclude: &'a [String],
+    owner: Option<&'a str>,
+}
+
+impl PathFilters<'_> {
+    fn is_empty(&self) -> bool {
+        self.dir.is_none() && self.ext.is_none() && self.exclude_dir.is_empty() && self.exclude.is_empty() && self.owner.is_none()
+    }
+
+    fn accepts(&self, ctx: &HandlerContext, path: &str) -> bool {
+        let lower = path.to_lowercase();
+        self.dir.as_ref().is_none_or(|d| is_under_dir(path, d))
+            && self.ext.as_ref().is_none_or(|e| matches_ext_filter(path, e))
+            && !self.exclude_dir.iter().chain(self.exclude).any(|x| lower.contains(&x.to_lowercase()))
+            && owner_allows(ctx.owners.as_deref(), self.owner, path)
+    }
+}
+
+/// Approximate `countOnly` for `estimate=true`.
+///
+/// The candidate files (those containing the terms, before path filters) are counted
+/// exactly from the posting lists, which only touches integers. The path filters, the
+/// expensive part of a count, run on an evenly spaced sample of at most
+/// [`ESTIMATE_SAMPLE`] candidates. `totalFiles` is the candidate count scaled by the
+/// sample's acceptance rate, with a 95% Wilson interval (finite-population corrected) as
+/// `totalFilesLow`/`totalFilesHigh`. Without filters, or when every candidate fits in
+/// the sample, the count is exact and both bounds equal it.
+fn estimate_count(
     ctx: &HandlerContext,
     index: &ContentIndex,
     terms_str: &str,
-    ext_filter: &Option<String>,
-    exclude_dir: &[String],
-    exclude: &[String],
+    mode: &str,
+    mode_and: bool,
+    filters: &PathFilters,
+) -> Result<Value, String> {
+    let raw_terms: Vec<String> = terms_str.split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if raw_terms.is_empty() {
+        return Err("No search terms provided".to_string());
+    }
+
+    // One group of posting lists per term: a substring or regex term covers many tokens
+    let mut groups: Vec<Vec<&[crate::Posting]>> = Vec::with_capacity(raw_terms.len());
+    for term in &raw_terms {
+        let tokens: Vec<&str> = match mode {
+            "regex" => {
+                let re = token_regex(term)
+                    .map_err(|e| format!("Invalid regex '{}': {}", term, e))?;
+                index.index.keys().map(|k| &**k).filter(|k| re.is_match(k)).collect()
+            }
+            "substring" => substring_token_ids(&index.trigram, term).into_iter()
+                .filter_map(|i| index.trigram.tokens.get(i as usize).map(|t| &**t))
+                .collect(),
+            _ => vec![term.as_str()],
+        };
+        groups.push(tokens.iter().filter_map(|t| index.index.get(*t).map(|p| p.as_slice())).collect());
+    }
+
+    // Per file: occurrences, and how many groups matched (AND needs all of them)
+    let file_count = index.files.len();
+    let mut occurrences = vec![0u32; file_count];
+    let mut groups_hit = vec![0u32; file_count];
+    let mut last_group = vec![u32::MAX; file_count];
+    for (g, group) in groups.iter().enumerate() {
+        for posting in group.iter().flat_map(|p| p.iter()) {
+            let f = posting.file_id as usize;
+            if f >= file_count { continue; }
+            if last_group[f] != g as u32 {
+                last_group[f] = g as u32;
+                groups_hit[f] += 1;
+            }
+            occurrences[f] += posting.lines.len() as u32;
+        }
+    }
+    let needed = if mode_and { groups.len() as u32 } else { 1 };
+    let candidates: Vec<u32> = (0..file_count as u32).filter(|&f| groups_hit[f as usize] >= needed).collect();
+    let candidate_files = candidates.len();
+    let candidate_occurrences: u64 = candidates.iter().map(|&f| occurrences[f as usize] as u64).sum();
+
+    // Evenly spaced sample, so repeated calls return the same estimate
+    let sample: Vec<u32> = if filters.is_empty() {
+        Vec::new()
+    } else if candidate_files <= ESTIMATE_SAMPLE {
+        candidates.clone()
+    } else {
+        (0..ESTIMATE_SAMPLE).map(|k| candidates[k * candidate_files / ESTIMATE_SAMPLE]).collect()
+    };
+    let (mut accepted, mut sampled_occ, mut accepted_occ) = (0usize, 0u64, 0u64);
+    for &f in &sample {
+        let occ = occurrences[f as usize] as u64;
+        sampled_occ += occ;
+        if index.files.get(f as usize).is_some_and(|path| filters.accepts(ctx, path)) {
+            accepted += 1;
+            accepted_occ += occ;
+        }
+    }
+
+    let (files, low, high, occ) = if filters.is_empty() {
+        let c = candidate_files as f64;
+        (c, c, c, candidate_occurrences as f64)
+    } else if sample.len() == candidate_files {
+        let a = accepted as f64;
+        (a, a, a, accepted_occ as f64)
+    } else {
+        let (low_rate, high_rate) = wilson_bounds(accepted, sample.len(), candidate_files);
+        let c = candidate_files as f64;
+        let rate = accepted as f64 / sample.len() as f64;
+        let occ_rate = if sampled_occ == 0 { 0.0 } else { accepted_occ as f64 / sampled_occ as f64 };
+        // Sampled files that passed (or failed) bound the count from below (above)
+        (c * rate,
+         (c * low_rate).max(accepted as f64),
+         (c * high_rate).min((candidate_files - (sample.len() - accepted)) as f64),
+         candidate_occurrences as f64 * occ_rate)
+    };
+
+    Ok(json!({
+        "estimated": true,
+        "totalFiles": files.round() as u64,
+        "totalFilesLow": low.floor() as u64,
+        "totalFilesHigh": high.ceil() as u64,
+        "totalOccurrences": occ.round() as u64,
+        "confidence": 0.95,
+        "candidateFiles": candidate_files,
+        "sampledFiles": sample.len(),
+        "termsSearched": raw_terms,
+        "indexFiles": index.files.len(),
+        "indexTokens": index.index.len(),
+    }))
+}
+
+/// 95% Wilson score interval for `accepted` of `sampled` draws from `population`,
+/// shrunk toward the observed rate by the finite-population correction.
+fn wilson_bounds(accepted: usize, sampled: usize, population: usize) -> (f64, f64) {
+    let n = sampled as f64;
+    let p = accepted as f64 / n;
+    let z2 = ESTIMATE_Z * ESTIMATE_Z;
+    let denom = 1.0 + z2 / n;
+    let center = (p + z2 / (2.0 * n)) / denom;
+    let half = ESTIMATE_Z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denom;
+    let fpc = if population > 1 { ((population - sampled) as f64 / (population - 1) as f64).sqrt() } else { 0.0 };
+    let low = (center - half).max(0.0);
+    let high = (center + half).min(1.0);
+    (p - (p - low) * fpc, p + (high - p) * fpc)
+}
+
+/// Indices (into `trigram.tokens`) of the tokens containing `term`.
+fn substring_token_ids(trigram_idx: &TrigramIndex, term: &str) -> Vec<u32> {
+    let start = Instant::now();
+    let verified = search::query::substring_token_ids(trigram_idx, term);
+    eprintln!("[substring-trace] Token lookup for '{}': {} tokens in {:.3}ms",
+        term, verified.len(), start.elapsed().as_secs_f64() * 1000.0);
+    verified
+}
+
+/// Substring search using the trigram index.
+/// What a search_grep call filters on and how it returns its results, shared by the
+/// substring and phrase searches.
+#[derive(Clone, Copy)]
+struct GrepOptions<'a> {
+    filters: PathFilters<'a>,
+    scope: Option<&'a LineScope>,
     show_lines: bool,
     context_lines: usize,
-    max_results_param: usize,
+    highlight: bool,
+    max_results: usize,
+    page: Option<&'a Page>,
     mode_and: bool,
     count_only: bool,
+    sort: GrepSort,
+    related_limit: usize,
+    ranking: &'a Ranking,
+    case_sensitive: bool,
+    word_boundary: bool,
     search_start: Instant,
-    dir_filter: &Option<String>,
+}
+
+fn handle_substring_search(
+    ctx: &HandlerContext,
+    index: &ContentIndex,
+    terms_str: &str,
+    opts: &GrepOptions,
+    mut warnings: Vec<String>,
 ) -> ToolCallResult {
-    let max_results = if max_results_param == 0 { 0 } else { max_results_param };
+    let GrepOptions {
+        filters, scope, show_lines, context_lines, highlight, max_results, page, mode_and, count_only, sort,
+        related_limit, ranking, case_sensitive, word_boundary, search_start,
+    } = *opts;
+    let PathFilters { dir: dir_filter, ext: ext_filter, exclude_dir, exclude, owner: owner_filter } = filters;
+    let explain = ranking.explain;
 
     // Stage 1: Terms parsing
     let stage1 = Instant::now();
@@ -333,11 +985,8 @@ fn handle_substring_search(
     }
 
     let trigram_idx = &index.trigram;
-    let total_docs = index.files.len() as f64;
     let search_mode = if mode_and { "and" } else { "or" };
 
-    // Track warnings
-    let mut warnings: Vec<String> = Vec::new();
     let has_short_query = raw_terms.iter().any(|t| t.len() < 4);
     if has_short_query {
         warnings.push("Short substring query (<4 chars) may return broad results".to_string());
@@ -350,82 +999,42 @@ fn handle_substring_search(
     // BUG-7 fix: collect matchedTokens only from tokens that have at least one
     // file passing dir/ext/exclude filters, not from the global trigram index.
     let mut tokens_with_hits: HashSet<String> = HashSet::new();
-    let mut file_scores: HashMap<u32, FileScoreEntry> = HashMap::new();
+    let mut scorer = ranking.scorer(index);
     let term_count = raw_terms.len();
-    // Track which distinct term indices matched per file (for correct AND-mode filtering)
-    let mut file_matched_terms: HashMap<u32, HashSet<usize>> = HashMap::new();
+    let mut case_filter = case_sensitive.then(|| CaseFilter::new(index, terms_str));
+    let mut boundary_filter = word_boundary.then(|| BoundaryFilter::new(index));
 
     for (term_idx, term) in raw_terms.iter().enumerate() {
         // Stage 3: Trigram intersection (per term)
         let trigram_start = Instant::now();
 
         // Find tokens that contain this term as a substring
-        let matched_token_indices: Vec<u32> = if term.len() < 3 {
-            // Linear scan for very short terms (no trigrams possible)
-            trigram_idx.tokens.iter().enumerate()
-                .filter(|(_, tok)| tok.contains(term.as_str()))
-                .map(|(i, _)| i as u32)
-                .collect()
-        } else {
-            // Use trigram index: intersect posting lists for all trigrams of the term
-            let trigrams = generate_trigrams(term);
-            if trigrams.is_empty() {
-                Vec::new()
-            } else {
-                // Get candidate token indices by intersecting trigram posting lists
-                let mut candidates: Option<Vec<u32>> = None;
-                for tri in &trigrams {
-                    if let Some(posting_list) = trigram_idx.trigram_map.get(tri) {
-                        candidates = Some(match candidates {
-                            None => posting_list.clone(),
-                            Some(prev) => sorted_intersect(&prev, posting_list),
-                        });
-                    } else {
-                        // Trigram not found -> no candidates
-                        candidates = Some(Vec::new());
-                        break;
-                    }
-                }
-
-                let candidate_indices = candidates.unwrap_or_default();
-
-                // Stage 4: Token verification (.contains() check)
-                let verify_start = Instant::now();
-                let verified: Vec<u32> = candidate_indices.into_iter()
-                    .filter(|&idx| {
-                        if let Some(tok) = trigram_idx.tokens.get(idx as usize) {
-                            tok.contains(term.as_str())
-                        } else {
-                            false
-                        }
-                    })
-                    .collect();
-                eprintln!("[substring-trace] Token verification for '{}': {} verified from candidates in {:.3}ms",
-                    term, verified.len(), verify_start.elapsed().as_secs_f64() * 1000.0);
-                verified
-            }
-        };
+        let matched_token_indices = substring_token_ids(trigram_idx, term);
 
         eprintln!("[substring-trace] Trigram intersection for '{}': {} candidates in {:.3}ms",
             term, matched_token_indices.len(), trigram_start.elapsed().as_secs_f64() * 1000.0);
 
         // Collect matched token names (not yet filtered by dir/ext/exclude)
         let matched_tokens: Vec<String> = matched_token_indices.iter()
-            .filter_map(|&idx| trigram_idx.tokens.get(idx as usize).cloned())
+            .filter_map(|&idx| trigram_idx.tokens.get(idx as usize).map(|t| t.to_string()))
             .collect();
 
         // Stage 5: Main index lookups + Stage 6: File filter checks
         let lookup_start = Instant::now();
         let mut term_postings_checked: usize = 0;
         let mut term_files_passed: usize = 0;
+        let mut term_bloom_skipped: usize = 0;
 
         // For each matched token, look up in main inverted index to get file postings
         for token in &matched_tokens {
             let token_key: &str = token.as_str();
+            if let Some(prefix) = dir_filter
+                && !index.dir_may_contain(prefix, token_key)
+            {
+                term_bloom_skipped += 1;
+                continue;
+            }
             if let Some(postings) = index.index.get(token_key) {
-                let doc_freq = postings.len() as f64;
-                let idf = if doc_freq > 0.0 { (total_docs / doc_freq).ln() } else { 0.0 };
-
                 for posting in postings {
                     term_postings_checked += 1;
                     let file_path = match index.files.get(posting.file_id as usize) {
@@ -453,37 +1062,33 @@ fn handle_substring_search(
                         file_path.to_lowercase().contains(&excl.to_lowercase())
                     }) { continue; }
 
+                    let Some(lines) = scoped_lines(scope, posting) else { continue };
+                    let lines = match case_filter.as_mut() {
+                        Some(filter) => match filter.retain(posting.file_id, &lines, &[term.as_str()], true) {
+                            kept if kept.is_empty() => continue,
+                            kept => Cow::Owned(kept),
+                        },
+                        None => lines,
+                    };
+                    let lines = match boundary_filter.as_mut() {
+                        Some(filter) => match filter.retain(posting.file_id, &lines, token_key, &[term.as_str()]) {
+                            kept if kept.is_empty() => continue,
+                            kept => Cow::Owned(kept),
+                        },
+                        None => lines,
+                    };
                     term_files_passed += 1;
                     // BUG-7 fix: token passed all filters, record it
                     tokens_with_hits.insert(token.clone());
 
-                    let occurrences = posting.lines.len();
-                    let file_total = if (posting.file_id as usize) < index.file_token_counts.len() {
-                        index.file_token_counts[posting.file_id as usize] as f64
-                    } else {
-                        1.0
-                    };
-                    let tf = occurrences as f64 / file_total;
-                    let tf_idf = tf * idf;
-
-                    let entry = file_scores.entry(posting.file_id).or_insert(FileScoreEntry {
-                        file_path: file_path.clone(),
-                        lines: Vec::new(),
-                        tf_idf: 0.0,
-                        occurrences: 0,
-                        terms_matched: 0,
-                    });
-                    entry.tf_idf += tf_idf;
-                    entry.occurrences += occurrences;
-                    entry.lines.extend_from_slice(&posting.lines);
-                    // Track distinct term index (not per-token) for correct AND filtering
-                    file_matched_terms.entry(posting.file_id).or_default().insert(term_idx);
+                    // Terms are credited by their index, for correct AND-mode filtering
+                    scorer.add(posting.file_id, term_idx, term, token, postings.len(), &lines);
                 }
             }
         }
 
-        eprintln!("[substring-trace] Main index lookup for '{}': {} tokens, {} postings checked, {} files passed in {:.3}ms",
-            term, matched_tokens.len(), term_postings_checked, term_files_passed,
+        eprintln!("[substring-trace] Main index lookup for '{}': {} tokens ({} ruled out by dir bloom), {} postings checked, {} files passed in {:.3}ms",
+            term, matched_tokens.len(), term_bloom_skipped, term_postings_checked, term_files_passed,
             lookup_start.elapsed().as_secs_f64() * 1000.0);
     }
 
@@ -491,35 +1096,33 @@ fn handle_substring_search(
     let mut all_matched_tokens: Vec<String> = tokens_with_hits.into_iter().collect();
     all_matched_tokens.sort();
 
-    // Set terms_matched from the distinct matched term indices
-    for (file_id, entry) in &mut file_scores {
-        if let Some(matched) = file_matched_terms.get(file_id) {
-            entry.terms_matched = matched.len();
-        }
-    }
-
     // Filter by AND mode
-    let mut results: Vec<FileScoreEntry> = file_scores
-        .into_values()
+    let mut results: Vec<FileScoreEntry> = scorer.finish()
+        .into_iter()
         .filter(|fs| !mode_and || fs.terms_matched >= term_count)
+        .map(|fs| FileScoreEntry::scored(index, fs))
+        .filter(|fs| owner_allows(ctx.owners.as_deref(), owner_filter, &fs.file_path))
         .collect();
 
-    // Sort/dedup lines
-    for result in &mut results {
-        result.lines.sort();
-        result.lines.dedup();
-    }
-
-    // Sort by TF-IDF descending
-    results.sort_by(|a, b| b.tf_idf.partial_cmp(&a.tf_idf).unwrap_or(std::cmp::Ordering::Equal));
+    // Sort by boosted TF-IDF descending
+    apply_ranking(&mut results, ranking);
+    results.sort_by(|a, b| cmp_ranked((a.tf_idf, &a.file_path, a.file_id), (b.tf_idf, &b.file_path, b.file_id)));
+    apply_rerank(&mut results, ranking);
+    let hooked = apply_rank_hook(&mut results, ranking, &index.root);
+    apply_grep_sort(&mut results, sort, |r| r.file_path.as_str(), |r| r.occurrences);
 
     let total_files = results.len();
     let total_occurrences: usize = results.iter().map(|r| r.occurrences).sum();
+    let related = if related_limit > 0 && !count_only {
+        let file_ids: Vec<u32> = results.iter().map(|r| r.file_id).collect();
+        // Matched tokens contain the search term, so they are not "related"
+        let skip: Vec<String> = raw_terms.iter().chain(&all_matched_tokens).cloned().collect();
+        Some(related_terms(index, &file_ids, &skip, related_limit))
+    } else {
+        None
+    };
 
-    // Apply max_results
-    if max_results > 0 {
-        results.truncate(max_results);
-    }
+    let page_info = take_page(&mut results, page, max_results);
 
     if count_only {
         let mut summary = json!({
@@ -532,7 +1135,10 @@ fn handle_substring_search(
         if !warnings.is_empty() {
             summary["warnings"] = json!(warnings);
         }
-        inject_branch_warning(&mut summary, ctx);
+        if let Some(outcome) = &hooked {
+            outcome.inject(&mut summary);
+        }
+        finish_summary(&mut summary, index, ctx, scope);
         let output = json!({
             "summary": summary
         });
@@ -542,18 +1148,29 @@ fn handle_substring_search(
 
     // Stage 7: Response JSON building
     let json_start = Instant::now();
-    let files_json: Vec<Value> = results.iter().map(|r| {
+    let locator = MatchLocator::substrings(&raw_terms);
+    let contents = if show_lines {
+        read_indexed_files(index, &results.iter().map(|r| r.file_path.as_str()).collect::<Vec<_>>())
+    } else {
+        Vec::new()
+    };
+    let files_json: Vec<Value> = results.iter().enumerate().map(|(i, r)| {
         let mut file_obj = json!({
             "path": r.file_path,
             "score": (r.tf_idf * 10000.0).round() / 10000.0,
             "occurrences": r.occurrences,
             "lines": r.lines,
         });
+        if explain {
+            file_obj["explain"] = explain_json(r.tf_idf, &r.explain, &r.boosts);
+        }
+        if let Some(fusion) = &r.fusion {
+            file_obj["rerank"] = fusion_json(fusion);
+        }
+        inject_owners(&mut file_obj, ctx.owners.as_deref(), &r.file_path);
 
-        if show_lines {
-            if let Ok(content) = std::fs::read_to_string(&r.file_path) {
-                file_obj["lineContent"] = build_line_content_from_matches(&content, &r.lines, context_lines);
-            }
+        if let Some(Some(content)) = contents.get(i) {
+            file_obj["lineContent"] = build_line_content_from_matches(content, &r.lines, context_lines, locator.as_ref(), highlight);
         }
 
         file_obj
@@ -569,7 +1186,25 @@ fn handle_substring_search(
     if !warnings.is_empty() {
         summary["warnings"] = json!(warnings);
     }
-    inject_branch_warning(&mut summary, ctx);
+    if explain {
+        summary["scoring"] = json!(EXPLAIN_FORMULA);
+    }
+    if sort != GrepSort::Score {
+        summary["sortedBy"] = json!(sort.as_str());
+    }
+    if let Some(rerank) = ranking.rerank_summary() {
+        summary["rerank"] = rerank;
+    }
+    if let Some(related) = related {
+        summary["relatedTerms"] = json!(related);
+    }
+    if let Some(info) = &page_info {
+        info.inject(&mut summary);
+    }
+    if let Some(outcome) = &hooked {
+        outcome.inject(&mut summary);
+    }
+    finish_summary(&mut summary, index, ctx, scope);
     let output = json!({
         "files": files_json,
         "summary": summary
@@ -588,58 +1223,72 @@ fn handle_phrase_search(
     ctx: &HandlerContext,
     index: &ContentIndex,
     phrase: &str,
-    ext_filter: &Option<String>,
-    exclude_dir: &[String],
-    exclude: &[String],
-    show_lines: bool,
-    context_lines: usize,
-    max_results: usize,
-    count_only: bool,
-    search_start: Instant,
-    dir_filter: &Option<String>,
+    opts: &GrepOptions,
+    search_mode: &str,
+    warnings: Vec<String>,
 ) -> ToolCallResult {
+    let GrepOptions {
+        filters, scope, show_lines, context_lines, highlight, max_results, page, count_only, sort, case_sensitive, search_start, ..
+    } = *opts;
+    let PathFilters { dir: dir_filter, ext: ext_filter, exclude_dir, exclude, owner: owner_filter } = filters;
     let phrase_lower = phrase.to_lowercase();
-    let phrase_tokens = tokenize(&phrase_lower, 2);
-
-    if phrase_tokens.is_empty() {
-        return ToolCallResult::error(format!(
-            "Phrase '{}' has no indexable tokens (min length 2)", phrase
-        ));
-    }
-
-    let phrase_regex_pattern = phrase_tokens.iter()
+    let phrase_tokens = index.tokenizer.tokenize(&phrase_lower);
+    let mut warnings = warnings;
+
+    // Case-sensitive: the phrase's own words, as typed, matched without (?i)
+    let pattern_words: Vec<String> = if case_sensitive {
+        phrase.split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|word| !index.tokenizer.tokenize(word).is_empty())
+            .map(str::to_string)
+            .collect()
+    } else {
+        phrase_tokens.clone()
+    };
+    let phrase_regex_pattern = pattern_words.iter()
         .map(|t| regex::escape(t))
         .collect::<Vec<_>>()
         .join(r"\s+");
-    let phrase_re = match regex::Regex::new(&format!("(?i){}", phrase_regex_pattern)) {
+    let flags = if case_sensitive { "" } else { "(?i)" };
+    let phrase_re = match regex::Regex::new(&format!("{}{}", flags, phrase_regex_pattern)) {
         Ok(r) => r,
         Err(e) => return ToolCallResult::error(format!("Failed to build phrase regex: {}", e)),
     };
 
-    // Step 1: Find candidate files via AND search
+    let accepts = |file_id: u32| {
+        if scope.is_some_and(|s| s.file_id != file_id) {
+            return false;
+        }
+        let path = match index.files.get(file_id as usize) {
+            Some(p) => p,
+            None => return false,
+        };
+        if let Some(prefix) = dir_filter {
+            if !is_under_dir(path, prefix) { return false; }
+        }
+        if let Some(ext) = ext_filter {
+            if !matches_ext_filter(path, ext) { return false; }
+        }
+        if exclude_dir.iter().any(|excl| path.to_lowercase().contains(&excl.to_lowercase())) {
+            return false;
+        }
+        if exclude.iter().any(|excl| path.to_lowercase().contains(&excl.to_lowercase())) {
+            return false;
+        }
+        owner_allows(ctx.owners.as_deref(), owner_filter, path)
+    };
+
+    // Step 1: Find candidate files via AND search. A phrase with no token the index
+    // holds has nothing to look up: every file that passes the filters is a candidate.
     let mut candidate_file_ids: Option<std::collections::HashSet<u32>> = None;
+    if phrase_tokens.is_empty() {
+        warnings.push(format!("Phrase '{}' has no indexable tokens; scanned the content of every file", phrase));
+        candidate_file_ids = Some((0..index.files.len() as u32).filter(|&id| accepts(id)).collect());
+    }
     for token in &phrase_tokens {
-        if let Some(postings) = index.index.get(token.as_str()) {
+        let ruled_out = dir_filter.as_ref().is_some_and(|prefix| !index.dir_may_contain(prefix, token));
+        if !ruled_out && let Some(postings) = index.index.get(token.as_str()) {
             let file_ids: std::collections::HashSet<u32> = postings.iter()
-                .filter(|p| {
-                    let path = match index.files.get(p.file_id as usize) {
-                        Some(p) => p,
-                        None => return false,
-                    };
-                    if let Some(prefix) = dir_filter {
-                        if !is_under_dir(path, prefix) { return false; }
-                    }
-                    if let Some(ext) = ext_filter {
-                        if !matches_ext_filter(path, ext) { return false; }
-                    }
-                    if exclude_dir.iter().any(|excl| path.to_lowercase().contains(&excl.to_lowercase())) {
-                        return false;
-                    }
-                    if exclude.iter().any(|excl| path.to_lowercase().contains(&excl.to_lowercase())) {
-                        return false;
-                    }
-                    true
-                })
+                .filter(|p| accepts(p.file_id))
                 .map(|p| p.file_id)
                 .collect();
             candidate_file_ids = Some(match candidate_file_ids {
@@ -661,54 +1310,61 @@ fn handle_phrase_search(
     // In that case, we match using the original phrase as a case-insensitive
     // substring against raw file content instead of the tokenized regex.
     // This eliminates false positives from tokenization stripping punctuation.
-    let phrase_has_punctuation = phrase.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace());
+    let phrase_has_punctuation = phrase.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace())
+        || phrase_tokens.is_empty();
 
     struct PhraseMatch {
         file_path: String,
         lines: Vec<u32>,
         content: Option<String>, // cached for show_lines to avoid re-reading
     }
-    let mut results: Vec<PhraseMatch> = Vec::new();
-
-    for &file_id in &candidates {
-        let file_path = &index.files[file_id as usize];
-        if let Ok(content) = std::fs::read_to_string(file_path) {
-            let mut matching_lines = Vec::new();
-            if phrase_has_punctuation {
-                // Use raw phrase substring match (case-insensitive) to avoid
-                // false positives from tokenizer stripping punctuation
-                for (line_num, line) in content.lines().enumerate() {
-                    if line.to_lowercase().contains(&phrase_lower) {
-                        matching_lines.push((line_num + 1) as u32);
-                    }
-                }
-            } else if phrase_re.is_match(&content) {
-                // Use tokenized phrase regex (no punctuation → no false positives)
-                for (line_num, line) in content.lines().enumerate() {
-                    if phrase_re.is_match(line) {
-                        matching_lines.push((line_num + 1) as u32);
-                    }
+    let mut candidate_ids: Vec<u32> = candidates.into_iter().collect();
+    candidate_ids.sort_unstable();
+    let paths: Vec<&str> = candidate_ids.iter().map(|&id| index.files[id as usize].as_str()).collect();
+    // Candidate files are read and verified on worker threads, which report progress
+    // and stream each match as they go
+    let mut results: Vec<PhraseMatch> = map_indexed_files(index, &paths, "verifying phrase in candidate files", |file_path, content| {
+        let content = content?;
+        let mut matching_lines = Vec::new();
+        if phrase_has_punctuation {
+            // Use raw phrase substring match (case-insensitive) to avoid
+            // false positives from tokenizer stripping punctuation
+            for (line_num, line) in content.lines().enumerate() {
+                let found = if case_sensitive { line.contains(phrase) } else { line.to_lowercase().contains(&phrase_lower) };
+                if found {
+                    matching_lines.push((line_num + 1) as u32);
                 }
             }
-            if !matching_lines.is_empty() {
-                results.push(PhraseMatch {
-                    file_path: file_path.clone(),
-                    lines: matching_lines,
-                    content: if show_lines { Some(content) } else { None },
-                });
+        } else if phrase_re.is_match(&content) {
+            // Use tokenized phrase regex (no punctuation → no false positives)
+            for (line_num, line) in content.lines().enumerate() {
+                if phrase_re.is_match(line) {
+                    matching_lines.push((line_num + 1) as u32);
+                }
             }
         }
-    }
+        if let Some(s) = scope {
+            matching_lines.retain(|&l| s.contains(l));
+        }
+        if matching_lines.is_empty() {
+            return None;
+        }
+        progress::partial(|| json!({ "path": file_path, "lines": matching_lines }));
+        Some(PhraseMatch {
+            file_path: file_path.to_string(),
+            lines: matching_lines,
+            content: if show_lines { Some(content) } else { None },
+        })
+    }).into_iter().flatten().collect();
 
     let total_files = results.len();
     let total_occurrences: usize = results.iter().map(|r| r.lines.len()).sum();
 
-    // Sort by number of occurrences descending (most matches first)
-    results.sort_by(|a, b| b.lines.len().cmp(&a.lines.len()));
+    // Sort by number of occurrences descending (most matches first), ties by path
+    results.sort_by(|a, b| b.lines.len().cmp(&a.lines.len()).then_with(|| a.file_path.cmp(&b.file_path)));
+    apply_grep_sort(&mut results, sort, |r| r.file_path.as_str(), |r| r.lines.len());
 
-    if max_results > 0 {
-        results.truncate(max_results);
-    }
+    let page_info = take_page(&mut results, page, max_results);
 
     let search_elapsed = search_start.elapsed();
 
@@ -717,30 +1373,40 @@ fn handle_phrase_search(
             "totalFiles": total_files,
             "totalOccurrences": total_occurrences,
             "termsSearched": [phrase],
-            "searchMode": "phrase",
+            "searchMode": search_mode,
             "indexFiles": index.files.len(),
             "indexTokens": index.index.len(),
             "searchTimeMs": search_elapsed.as_secs_f64() * 1000.0,
             "indexLoadTimeMs": 0.0
         });
-        inject_branch_warning(&mut summary, ctx);
+        if !warnings.is_empty() {
+            summary["warnings"] = json!(warnings);
+        }
+        finish_summary(&mut summary, index, ctx, scope);
         let output = json!({
             "summary": summary
         });
         return ToolCallResult::success(serde_json::to_string(&output).unwrap());
     }
 
+    // Columns come from the same test that verified the line
+    let locator = if phrase_has_punctuation {
+        MatchLocator::substrings(std::slice::from_ref(&phrase_lower))
+    } else {
+        Some(MatchLocator::Regex(phrase_re.clone()))
+    };
     let files_json: Vec<Value> = results.iter().map(|r| {
         let mut file_obj = json!({
             "path": r.file_path,
             "occurrences": r.lines.len(),
             "lines": r.lines,
         });
+        inject_owners(&mut file_obj, ctx.owners.as_deref(), &r.file_path);
 
         if show_lines {
             // Use cached content from phrase verification (no second read)
             if let Some(ref content) = r.content {
-                file_obj["lineContent"] = build_line_content_from_matches(content, &r.lines, context_lines);
+                file_obj["lineContent"] = build_line_content_from_matches(content, &r.lines, context_lines, locator.as_ref(), highlight);
             }
         }
 
@@ -751,13 +1417,22 @@ fn handle_phrase_search(
         "totalFiles": total_files,
         "totalOccurrences": total_occurrences,
         "termsSearched": [phrase],
-        "searchMode": "phrase",
+        "searchMode": search_mode,
         "indexFiles": index.files.len(),
         "indexTokens": index.index.len(),
         "searchTimeMs": search_elapsed.as_secs_f64() * 1000.0,
         "indexLoadTimeMs": 0.0
     });
-    inject_branch_warning(&mut summary, ctx);
+    if sort != GrepSort::Score {
+        summary["sortedBy"] = json!(sort.as_str());
+    }
+    if !warnings.is_empty() {
+        summary["warnings"] = json!(warnings);
+    }
+    if let Some(info) = &page_info {
+        info.inject(&mut summary);
+    }
+    finish_summary(&mut summary, index, ctx, scope);
     let output = json!({
         "files": files_json,
         "summary": summary
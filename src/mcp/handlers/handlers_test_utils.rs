@@ -3,11 +3,12 @@
 
 use super::*;
 use crate::definitions::*;
-use crate::Posting;
+use crate::{ContentIndex, Posting};
+use crate::{SkippedFiles, TokenizerSettings};
 use crate::TrigramIndex;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 /// Remove a temporary directory used in tests.
 pub(crate) fn cleanup_tmp(tmp_dir: &std::path::Path) {
@@ -19,22 +20,22 @@ pub(crate) fn cleanup_tmp(tmp_dir: &std::path::Path) {
 pub(crate) fn make_ctx_with_defs() -> HandlerContext {
     // Content index: tokens -> files+lines
     let mut content_idx = HashMap::new();
-    content_idx.insert("executequeryasync".to_string(), vec![
+    content_idx.insert("executequeryasync".into(), vec![
         Posting { file_id: 0, lines: vec![242] },
         Posting { file_id: 1, lines: vec![88] },
         Posting { file_id: 2, lines: vec![391] },
     ]);
-    content_idx.insert("queryinternalasync".to_string(), vec![
+    content_idx.insert("queryinternalasync".into(), vec![
         Posting { file_id: 2, lines: vec![766] },
         Posting { file_id: 2, lines: vec![462] },
     ]);
-    content_idx.insert("proxyclient".to_string(), vec![
+    content_idx.insert("proxyclient".into(), vec![
         Posting { file_id: 1, lines: vec![1, 88] },
     ]);
-    content_idx.insert("resilientclient".to_string(), vec![
+    content_idx.insert("resilientclient".into(), vec![
         Posting { file_id: 0, lines: vec![1, 242] },
     ]);
-    content_idx.insert("queryservice".to_string(), vec![
+    content_idx.insert("queryservice".into(), vec![
         Posting { file_id: 2, lines: vec![1, 391, 462, 766] },
     ]);
 
@@ -55,6 +56,15 @@ pub(crate) fn make_ctx_with_defs() -> HandlerContext {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -138,10 +148,17 @@ pub(crate) fn make_ctx_with_defs() -> HandlerContext {
         file_index,
         path_to_id,
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -153,5 +170,14 @@ pub(crate) fn make_ctx_with_defs() -> HandlerContext {
         git_cache: Arc::new(RwLock::new(None)),
         git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     }
 }
\ No newline at end of file
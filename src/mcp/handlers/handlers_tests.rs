@@ -10,16 +10,18 @@ use super::fast::handle_search_fast;
 use super::utils::validate_search_dir;
 use super::handlers_test_utils::{cleanup_tmp, make_ctx_with_defs};
 use crate::index::build_trigram_index;
-use crate::Posting;
+use crate::{ContentIndex, Posting};
+use crate::{SkippedFiles, TokenizerSettings};
 use crate::TrigramIndex;
+use crate::Token;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 #[test]
 fn test_tool_definitions_count() {
     let tools = tool_definitions();
-    assert_eq!(tools.len(), 15);
+    assert_eq!(tools.len(), 39);
 }
 
 #[test]
@@ -31,6 +33,9 @@ fn test_tool_definitions_names() {
     assert!(names.contains(&"search_fast"));
     assert!(names.contains(&"search_info"));
     assert!(names.contains(&"search_reindex"));
+    assert!(names.contains(&"search_reindex_status"));
+    assert!(names.contains(&"search_metrics"));
+    assert!(names.contains(&"search_history"));
     assert!(names.contains(&"search_reindex_definitions"));
     assert!(names.contains(&"search_definitions"));
     assert!(names.contains(&"search_callers"));
@@ -97,9 +102,18 @@ fn make_empty_ctx() -> HandlerContext {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
     HandlerContext {
-        index: Arc::new(RwLock::new(index)),
+        index: Searcher::new(index),
         def_index: None,
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -108,6 +122,15 @@ fn make_empty_ctx() -> HandlerContext {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     }
 }
 
@@ -139,7 +162,7 @@ fn test_dispatch_grep_empty_index() {
 #[test]
 fn test_dispatch_grep_with_results() {
     let mut idx = HashMap::new();
-    idx.insert("httpclient".to_string(), vec![Posting {
+    idx.insert("httpclient".into(), vec![Posting {
         file_id: 0,
         lines: vec![5, 12],
     }]);
@@ -156,9 +179,18 @@ fn test_dispatch_grep_with_results() {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(index)),
+        index: Searcher::new(index),
         def_index: None,
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -167,6 +199,15 @@ fn test_dispatch_grep_with_results() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "HttpClient", "substring": false}));
     assert!(!result.is_error);
@@ -217,6 +258,25 @@ fn test_contains_line_requires_file() {
     assert!(result.content[0].text.contains("containsLine requires 'file' parameter"));
 }
 
+// --- search defs (CLI query grammar) shares the search_definitions implementation ---
+
+#[test]
+fn test_def_query_matches_mcp_search_definitions() {
+    let ctx = make_ctx_with_defs();
+    let query = super::parse_def_query("ExecuteQueryAsync kind:method in:ProxyClient").unwrap();
+    let index = ctx.def_index.as_ref().unwrap().read().unwrap();
+    let cli_output = super::search_definitions_json(&index, &query, None, None, None).unwrap();
+    drop(index);
+
+    let mcp_result = dispatch_tool(&ctx, "search_definitions", &json!({
+        "name": "ExecuteQueryAsync", "kind": "method", "parent": "ProxyClient"
+    }));
+    let mcp_output: Value = serde_json::from_str(&mcp_result.content[0].text).unwrap();
+    assert_eq!(cli_output["definitions"], mcp_output["definitions"]);
+    assert_eq!(cli_output["definitions"].as_array().unwrap().len(), 1);
+    assert_eq!(cli_output["definitions"][0]["parent"], "ProxyClient");
+}
+
 // --- search_callers schema tests ---
 
 #[test]
@@ -290,9 +350,9 @@ fn test_search_definitions_max_results_default_is_100() {
 // --- Substring search handler integration tests ---
 
 fn make_substring_ctx(tokens_to_files: Vec<(&str, u32, Vec<u32>)>, files: Vec<&str>) -> HandlerContext {
-    let mut index_map: HashMap<String, Vec<Posting>> = HashMap::new();
+    let mut index_map: HashMap<Token, Vec<Posting>> = HashMap::new();
     for (token, file_id, lines) in &tokens_to_files {
-        index_map.entry(token.to_string()).or_default().push(Posting { file_id: *file_id, lines: lines.clone() });
+        index_map.entry((*token).into()).or_default().push(Posting { file_id: *file_id, lines: lines.clone() });
     }
     let file_token_counts: Vec<u32> = {
         let mut counts = vec![0u32; files.len()];
@@ -308,14 +368,32 @@ fn make_substring_ctx(tokens_to_files: Vec<(&str, u32, Vec<u32>)>, files: Vec<&s
         files: files.iter().map(|s| s.to_string()).collect(), index: index_map,
         total_tokens, extensions: vec!["cs".to_string()], file_token_counts,
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
     HandlerContext {
-        index: Arc::new(RwLock::new(content_index)), def_index: None,
+        index: Searcher::new(content_index), def_index: None,
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)),
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     }
 }
 
@@ -327,6 +405,32 @@ fn make_substring_ctx(tokens_to_files: Vec<(&str, u32, Vec<u32>)>, files: Vec<&s
     assert_eq!(output["summary"]["totalFiles"], 1);
 }
 
+/// A `--rank-hook` policy sees repo-relative paths and re-scores grep files before
+/// the maxResults cut, in token and substring mode.
+#[test] fn test_grep_rank_hook_reorders_before_truncation() {
+    // Files under "gen" are dropped; the rest keep their built-in score
+    let drop_gen = r#"(select (f32.const -1) (f32.load offset=24 (local.get $rec))
+        (i32.eq (i32.load8_u (i32.add (local.get $pool) (i32.load (local.get $rec)))) (i32.const 103)))"#;
+    let files = vec!["C:/repo/gen/Client.cs", "C:/repo/src/Client.cs"];
+    let tokens = vec![("httpclient", 0, vec![1, 2, 3, 4]), ("httpclient", 1, vec![7])];
+    let mut ctx = make_substring_ctx(tokens, files);
+    ctx.rank_hook = Some(Arc::new(crate::rank_hook::test_policy(drop_gen)));
+    ctx.index.update(|index| index.root = "C:/repo".to_string());
+    for substring in [false, true] {
+        let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "httpclient", "substring": substring, "maxResults": 1}));
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(output["files"][0]["path"], "C:/repo/src/Client.cs", "substring={}", substring);
+        assert_eq!(output["summary"]["totalFiles"], 1);
+        assert_eq!(output["summary"]["rankHookDropped"], 1);
+
+        let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "httpclient", "substring": substring, "rankHook": false}));
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(output["files"][0]["path"], "C:/repo/gen/Client.cs", "substring={}", substring);
+        assert_eq!(output["summary"]["totalFiles"], 2);
+    }
+}
+
 #[test] fn test_substring_search_no_match() {
     let ctx = make_substring_ctx(vec![("httpclient", 0, vec![5])], vec!["C:\\test\\Program.cs"]);
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "xyznonexistent", "substring": true}));
@@ -439,30 +543,84 @@ fn make_substring_ctx(tokens_to_files: Vec<(&str, u32, Vec<u32>)>, files: Vec<&s
     assert!(output.get("files").is_none());
 }
 
+#[test] fn test_grep_estimate_matches_count_within_bounds() {
+    // 1000 files contain "widgetfactory", every other one under tests/; 40 of them also "gadget"
+    let files: Vec<String> = (0..1000).map(|i| format!("C:/repo/{}/W{}.cs", if i % 2 == 0 { "src" } else { "tests" }, i)).collect();
+    let mut tokens: Vec<(&str, u32, Vec<u32>)> = (0..1000).map(|i| ("widgetfactory", i, vec![1, 2])).collect();
+    tokens.extend((0..40).map(|i| ("gadget", i * 25, vec![9])));
+    let ctx = make_substring_ctx(tokens, files.iter().map(|s| s.as_str()).collect());
+
+    // No path filters: the candidate count is exact
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_grep",
+        &json!({ "terms": "widget", "estimate": true })).content[0].text).unwrap();
+    let summary = &output["summary"];
+    assert_eq!(summary["estimated"], true);
+    assert_eq!((summary["totalFiles"].as_u64(), summary["totalFilesLow"].as_u64(), summary["totalFilesHigh"].as_u64()),
+        (Some(1000), Some(1000), Some(1000)));
+    assert_eq!(summary["totalOccurrences"], 2000);
+    assert!(output.get("files").is_none());
+
+    // excludeDir runs on a 400-file sample; the true count (500) is inside the bounds
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_grep",
+        &json!({ "terms": "widget", "excludeDir": ["tests"], "estimate": true })).content[0].text).unwrap();
+    let summary = &output["summary"];
+    let (est, low, high) = (summary["totalFiles"].as_u64().unwrap(), summary["totalFilesLow"].as_u64().unwrap(), summary["totalFilesHigh"].as_u64().unwrap());
+    assert_eq!(summary["sampledFiles"], 400);
+    assert!(low <= 500 && 500 <= high && low <= est && est <= high, "{}", summary);
+    assert!(est.abs_diff(500) <= 50 && high - low < 150, "{}", summary);
+
+    // AND with a small candidate set: every candidate is checked, so the count is exact
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_grep",
+        &json!({ "terms": "widgetfactory,gadget", "substring": false, "mode": "and", "exclude": ["/tests/"], "estimate": true })).content[0].text).unwrap();
+    assert_eq!(output["summary"]["candidateFiles"], 40);
+    assert_eq!(output["summary"]["totalFiles"], 20);
+    assert_eq!(output["summary"]["totalFilesHigh"], 20);
+
+    assert!(dispatch_tool(&ctx, "search_grep", &json!({ "terms": "widget factory", "phrase": true, "estimate": true })).is_error);
+}
+
 #[test]
 fn test_substring_search_trigram_dirty_triggers_rebuild() {
-    let mut index_map: HashMap<String, Vec<Posting>> = HashMap::new();
-    index_map.insert("httpclient".to_string(), vec![Posting { file_id: 0, lines: vec![5] }]);
+    let mut index_map: HashMap<Token, Vec<Posting>> = HashMap::new();
+    index_map.insert("httpclient".into(), vec![Posting { file_id: 0, lines: vec![5] }]);
     let content_index = ContentIndex {
         root: ".".to_string(), created_at: 0, max_age_secs: 3600,
         files: vec!["C:\\test\\Program.cs".to_string()], index: index_map,
         total_tokens: 1, extensions: vec!["cs".to_string()], file_token_counts: vec![1],
         trigram: TrigramIndex::default(), trigram_dirty: true,
         forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)), def_index: None,
+        index: Searcher::new(content_index), def_index: None,
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)),
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "httpcli", "substring": true}));
     assert!(!result.is_error);
     let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
     assert_eq!(output["summary"]["totalFiles"], 1);
-    let idx = ctx.index.read().unwrap();
+    let idx = ctx.index.snapshot();
     assert!(!idx.trigram_dirty);
     assert!(!idx.trigram.tokens.is_empty());
 }
@@ -500,15 +658,38 @@ fn make_e2e_substring_ctx() -> (HandlerContext, std::path::PathBuf) {
     let content_index = crate::build_content_index(&crate::ContentIndexArgs {
         dir: tmp_dir.to_string_lossy().to_string(), ext: "cs".to_string(),
         max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: 0,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: 0,
+        stop_token_pct: 0,
+        checkpoint_secs: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
     });
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)), def_index: None,
+        index: Searcher::new(content_index), def_index: None,
         server_dir: tmp_dir.to_string_lossy().to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: tmp_dir.join(".index"),
         max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)),
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
     (ctx, tmp_dir)
 }
@@ -536,6 +717,108 @@ fn make_e2e_substring_ctx() -> (HandlerContext, std::path::PathBuf) {
     cleanup_tmp(&tmp_dir);
 }
 
+#[test] fn e2e_grep_match_columns_per_mode() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    let columns = |args: Value| -> (Value, Value) {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        let group = output["files"][0]["lineContent"][0].clone();
+        (group["matchColumns"].clone(), group["lines"][group["matchIndices"][0].as_u64().unwrap() as usize].clone())
+    };
+    let (cols, line) = columns(json!({"terms": "grpcservice", "showLines": true, "highlight": true}));
+    assert_eq!(cols, json!([[[31, 42]]]));
+    assert_eq!(line, "            var provider = new <mark>GrpcService</mark>Provider();");
+    let (cols, line) = columns(json!({"terms": "grpcserviceprovider", "substring": false, "showLines": true}));
+    assert_eq!(cols, json!([[[31, 50]]]));
+    assert_eq!(line, "            var provider = new GrpcServiceProvider();", "no marks without highlight");
+    let (cols, _) = columns(json!({"terms": "public void execute", "phrase": true, "showLines": true}));
+    assert_eq!(cols, json!([[[8, 27]]]));
+    cleanup_tmp(&tmp_dir);
+}
+
+#[test] fn e2e_grep_cursor_pages_through_ranking() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    let grep = |args: Value| -> Result<Value, String> {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        let text = result.content[0].text.clone();
+        if result.is_error { Err(text) } else { Ok(serde_json::from_str(&text).unwrap()) }
+    };
+    let paths = |output: &Value| -> Vec<String> {
+        output["files"].as_array().unwrap().iter().map(|f| f["path"].as_str().unwrap().to_string()).collect()
+    };
+    let all = paths(&grep(json!({"terms": "public"})).unwrap());
+    assert_eq!(all.len(), 3);
+
+    let first = grep(json!({"terms": "public", "pageSize": 2})).unwrap();
+    assert_eq!(paths(&first), all[..2]);
+    assert_eq!(first["summary"]["hasMore"], true);
+    let cursor = first["summary"]["nextCursor"].as_str().unwrap().to_string();
+    let second = grep(json!({"terms": "public", "cursor": cursor})).unwrap();
+    assert_eq!(paths(&second), all[2..]);
+    assert_eq!((second["summary"]["hasMore"].clone(), second["summary"]["pageOffset"].clone()), (json!(false), json!(2)));
+    assert!(second["summary"].get("nextCursor").is_none(), "the last page has no cursor");
+
+    assert!(grep(json!({"terms": "class", "cursor": cursor})).unwrap_err().contains("different query"));
+    ctx.generation.bump();
+    assert!(grep(json!({"terms": "public", "cursor": cursor})).unwrap_err().contains("expired"));
+    cleanup_tmp(&tmp_dir);
+}
+
+#[test] fn e2e_grep_cursor_walk_stops_at_the_last_page() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    let mut args = json!({"terms": "public", "pageSize": 1});
+    let mut pages = 0;
+    loop {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        pages += 1;
+        assert!(output["summary"].get("_pageEndCursor").is_none());
+        match output["summary"].get("nextCursor") {
+            Some(cursor) => {
+                assert_eq!(output["summary"]["hasMore"], true);
+                args = json!({"terms": "public", "cursor": cursor});
+            }
+            None => {
+                assert_eq!(output["summary"]["hasMore"], false);
+                assert_eq!(output["files"].as_array().unwrap().len(), 1);
+                break;
+            }
+        }
+        assert!(pages < 10, "paging did not stop");
+    }
+    assert_eq!(pages, 3);
+    cleanup_tmp(&tmp_dir);
+}
+
+#[test] fn e2e_grep_streams_progress_and_partial_results() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    let sent = Arc::new(Mutex::new(Vec::<Value>::new()));
+    let sink = Arc::clone(&sent);
+    let result = crate::mcp::progress::with_reporter(Some(json!("grep-1")), move |m| sink.lock().unwrap().push(m), || {
+        dispatch_tool(&ctx, "search_grep", &json!({"terms": "public class", "phrase": true}))
+    });
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let mut expected: Vec<&str> = output["files"].as_array().unwrap().iter().map(|f| f["path"].as_str().unwrap()).collect();
+    expected.sort();
+    assert_eq!(expected.len(), 2, "Util.cs has public and class, but not the phrase");
+
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent[0]["params"]["progressToken"], "grep-1");
+    assert_eq!(sent[0]["params"]["total"], 3.0);
+    let progress: Vec<f64> = sent.iter().map(|m| m["params"]["progress"].as_f64().unwrap()).collect();
+    assert!(progress.windows(2).all(|w| w[0] < w[1]), "progress must grow: {:?}", progress);
+    // Every matching file is streamed once before the response, with its lines
+    let mut streamed: Vec<&str> = sent.iter()
+        .flat_map(|m| m["params"]["_meta"]["partialResults"].as_array().into_iter().flatten())
+        .map(|f| f["path"].as_str().unwrap())
+        .collect();
+    streamed.sort();
+    assert_eq!(streamed, expected);
+    cleanup_tmp(&tmp_dir);
+}
+
 #[test] fn e2e_reindex_rebuilds_trigram() {
     use std::io::Write;
     let (ctx, tmp_dir) = make_e2e_substring_ctx();
@@ -544,7 +827,7 @@ fn make_e2e_substring_ctx() -> (HandlerContext, std::path::PathBuf) {
     assert!(o1["summary"]["totalFiles"].as_u64().unwrap() >= 1);
     std::fs::remove_file(tmp_dir.join("Util.cs")).unwrap();
     { let mut f = std::fs::File::create(tmp_dir.join("NewFile.cs")).unwrap(); writeln!(f, "public class DatabaseConnectionPoolManager {{}}").unwrap(); }
-    let _ = dispatch_tool(&ctx, "search_reindex", &json!({}));
+    let _ = dispatch_tool(&ctx, "search_reindex", &json!({"wait": true}));
     let r2 = dispatch_tool(&ctx, "search_grep", &json!({"terms": "cachemanager", "substring": true}));
     let o2: Value = serde_json::from_str(&r2.content[0].text).unwrap();
     assert_eq!(o2["summary"]["totalFiles"], 0);
@@ -554,30 +837,180 @@ fn make_e2e_substring_ctx() -> (HandlerContext, std::path::PathBuf) {
     cleanup_tmp(&tmp_dir);
 }
 
+#[test] fn e2e_reindex_keeps_the_token_length_limits() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    let limits = crate::TokenizerSettings { min_token_len: 3, max_token_len: 12 };
+    ctx.index.update(|idx| idx.tokenizer = limits);
+    let _ = dispatch_tool(&ctx, "search_reindex", &json!({"wait": true}));
+    let index = ctx.index.snapshot();
+    assert_eq!(index.tokenizer, limits);
+    assert!(index.index.keys().all(|t| (3..=12).contains(&t.len())), "rebuilt with the stored limits");
+    cleanup_tmp(&tmp_dir);
+}
+
+#[test] fn e2e_watcher_applies_file_changes_after_a_reindex() {
+    use crate::mcp::watcher::{build_watch_index_from, start_watcher, WatchMode, WatcherOptions};
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    ctx.index.replace(build_watch_index_from(ContentIndex::clone(&ctx.index.snapshot())));
+    let options = WatcherOptions {
+        debounce_ms: 20, bulk_threshold: 100, rebuild_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0, mode: WatchMode::Poll, poll_interval_ms: 20,
+    };
+    start_watcher(ctx.index.clone(), None, tmp_dir.clone(), vec!["cs".to_string()], ctx.index_base.clone(),
+        options, Arc::clone(&ctx.generation)).unwrap();
+    let _ = dispatch_tool(&ctx, "search_reindex", &json!({"wait": true}));
+    assert!(ctx.index.snapshot().path_to_id.is_some());
+
+    std::fs::write(tmp_dir.join("Util.cs"), "public static class EditedAfterReindex {}\n").unwrap();
+    let files = |term: &str| {
+        let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": term}));
+        serde_json::from_str::<Value>(&result.content[0].text).unwrap()["summary"]["totalFiles"].clone()
+    };
+    let deadline = Instant::now() + std::time::Duration::from_secs(30);
+    while files("editedafterreindex") != 1 && Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert_eq!(files("editedafterreindex"), 1, "the watcher updated the reindexed index");
+    assert_eq!(files("cachemanagerhelper"), 0);
+    cleanup_tmp(&tmp_dir);
+}
+
+#[test] fn e2e_background_reindex_swaps_index_and_reports_status() {
+    use std::io::Write;
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    let none = dispatch_tool(&ctx, "search_reindex_status", &json!({}));
+    assert!(none.is_error, "no job started yet");
+    { let mut f = std::fs::File::create(tmp_dir.join("Added.cs")).unwrap(); writeln!(f, "public class BackgroundSwapMarker {{}}").unwrap(); }
+    let started = dispatch_tool(&ctx, "search_reindex", &json!({}));
+    let started: Value = serde_json::from_str(&started.content[0].text).unwrap();
+    assert_eq!(started["status"], "started");
+    let job_id = started["jobId"].as_str().unwrap().to_string();
+
+    let deadline = Instant::now() + std::time::Duration::from_secs(30);
+    let status = loop {
+        let result = dispatch_tool(&ctx, "search_reindex_status", &json!({ "jobId": job_id }));
+        let status: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        if status["status"] != "running" || Instant::now() > deadline {
+            break status;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    assert_eq!(status["status"], "completed", "{}", status);
+    assert_eq!(status["files"], 4);
+    let found = dispatch_tool(&ctx, "search_grep", &json!({"terms": "backgroundswapmarker"}));
+    let found: Value = serde_json::from_str(&found.content[0].text).unwrap();
+    assert_eq!(found["summary"]["totalFiles"], 1);
+    assert_eq!(ctx.generation.get(), 1);
+    let unknown = dispatch_tool(&ctx, "search_reindex_status", &json!({ "jobId": "reindex-99" }));
+    assert!(unknown.is_error);
+    cleanup_tmp(&tmp_dir);
+}
+
+#[test] fn e2e_search_metrics_counts_calls() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    for _ in 0..2 {
+        dispatch_tool(&ctx, "search_grep", &json!({"terms": "class"}));
+    }
+    let result = dispatch_tool(&ctx, "search_metrics", &json!({}));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    // Counters are process-wide: other tests call search_grep too
+    let grep = &output["tools"]["search_grep"];
+    assert!(grep["calls"].as_u64().unwrap() >= 2, "{}", output);
+    assert_eq!(grep["latencyMs"]["buckets"]["+Inf"], grep["calls"]);
+    assert_eq!(output["index"]["files"], 3);
+    assert!(output["watcher"]["events"].is_u64());
+
+    let text = dispatch_tool(&ctx, "search_metrics", &json!({"format": "prometheus"})).content[0].text.clone();
+    assert!(text.contains("search_tool_latency_ms_bucket{tool=\"search_grep\",le=\"+Inf\"}"), "{}", text);
+    assert!(text.contains("search_index_files 3\n"));
+    assert!(dispatch_tool(&ctx, "search_metrics", &json!({"format": "xml"})).is_error);
+    cleanup_tmp(&tmp_dir);
+}
+
+#[test] fn e2e_search_history_records_calls_and_suggests_terms() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    dispatch_tool(&ctx, "search_grep", &json!({"terms": "httpclienthandler", "substring": false}));
+    let empty = dispatch_tool(&ctx, "search_grep", &json!({"terms": "httpclienthandlr", "substring": false}));
+    let output: Value = serde_json::from_str(&empty.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalFiles"], 0);
+    assert_eq!(output["summary"]["didYouMean"][0]["term"], "httpclienthandlr");
+    assert_eq!(output["summary"]["didYouMean"][0]["suggestions"][0], "httpclienthandler", "{}", output);
+    // Not searched before: the nearest token by trigram candidates and edit distance
+    let typo = dispatch_tool(&ctx, "search_grep", &json!({"terms": "cachemanagerhelpr"}));
+    let output: Value = serde_json::from_str(&typo.content[0].text).unwrap();
+    assert_eq!(output["summary"]["didYouMean"][0]["suggestions"][0], "cachemanagerhelper", "{}", output);
+
+    let result = dispatch_tool(&ctx, "search_history", &json!({}));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let queries = output["queries"].as_array().unwrap();
+    assert_eq!(queries.len(), 3, "search_history itself is not recorded");
+    assert_eq!((&queries[1]["arguments"]["terms"], &queries[1]["hits"]), (&json!("httpclienthandlr"), &json!(0)));
+    assert_eq!(queries[2]["hits"], 2);
+
+    let empty_only = dispatch_tool(&ctx, "search_history", &json!({"emptyOnly": true, "tool": "search_grep"}));
+    let output: Value = serde_json::from_str(&empty_only.content[0].text).unwrap();
+    assert_eq!(output["summary"]["returned"], 2);
+    cleanup_tmp(&tmp_dir);
+}
+
+#[test] fn e2e_search_audit_runs_suite_from_config() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    let listed: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_audit", &json!({})).content[0].text).unwrap();
+    assert!(listed["hint"].is_string());
+    std::fs::write(tmp_dir.join(crate::config::CONFIG_FILE_NAME), r#"
+[[audit.policy]]
+name = "No GrpcServiceProvider"
+tool = "grep"
+args = { terms = "grpcserviceprovider", substring = false }
+
+[[audit.policy]]
+name = "Handlers exist"
+any = [{ tool = "grep", args = { terms = "httpclienthandler", substring = false } },
+       { tool = "grep", args = { terms = "nosuchtoken", substring = false } }]
+expect = "nonzero"
+
+[[audit.policy]]
+name = "Recursive"
+tool = "audit"
+"#).unwrap();
+
+    let result = dispatch_tool(&ctx, "search_audit", &json!({"suite": "Policy"}));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let checks = output["checks"].as_array().unwrap();
+    assert_eq!((&checks[0]["status"], &checks[0]["count"]), (&json!("fail"), &json!(1)));
+    assert_eq!((&checks[1]["status"], &checks[1]["count"]), (&json!("pass"), &json!(2)), "counts of OR-ed queries add up");
+    assert_eq!(checks[2]["status"], "error");
+    assert_eq!(output["summary"]["result"], "fail");
+    assert_eq!((&output["summary"]["passed"], &output["summary"]["errors"]), (&json!(1), &json!(1)));
+
+    assert!(dispatch_tool(&ctx, "search_audit", &json!({"suite": "nope"})).content[0].text.contains("Configured suites: policy"));
+    cleanup_tmp(&tmp_dir);
+}
+
 #[test] fn e2e_watcher_trigram_dirty_lazy_rebuild() {
     use std::io::Write;
     let (ctx, tmp_dir) = make_e2e_substring_ctx();
-    { let mut idx = ctx.index.write().unwrap();
+    ctx.index.update(|idx| {
       let new_file_id = idx.files.len() as u32;
       let new_path = tmp_dir.join("Dynamic.cs");
       { let mut f = std::fs::File::create(&new_path).unwrap(); writeln!(f, "public class AsyncBlobStorageProcessor {{}}").unwrap(); }
       idx.files.push(clean_path(&new_path.to_string_lossy()));
       idx.file_token_counts.push(1);
-      idx.index.entry("asyncblobstorageprocessor".to_string()).or_default().push(Posting { file_id: new_file_id, lines: vec![1] });
+      idx.index.entry("asyncblobstorageprocessor".into()).or_default().push(Posting { file_id: new_file_id, lines: vec![1] });
       idx.total_tokens += 1;
       idx.trigram_dirty = true;
-    }
+    });
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "blobstorage", "substring": true}));
     assert!(!result.is_error);
     let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
     assert!(output["summary"]["totalFiles"].as_u64().unwrap() >= 1);
-    assert!(!ctx.index.read().unwrap().trigram_dirty);
+    assert!(!ctx.index.snapshot().trigram_dirty);
     cleanup_tmp(&tmp_dir);
 }
 
 #[test] fn e2e_index_serialization_roundtrip_with_trigram() {
     let (ctx, tmp_dir) = make_e2e_substring_ctx();
-    let original = ctx.index.read().unwrap();
+    let original = ctx.index.snapshot();
     let orig_files = original.files.len();
     let orig_tokens = original.index.len();
     let orig_trigrams = original.trigram.trigram_map.len();
@@ -589,7 +1022,7 @@ fn make_e2e_substring_ctx() -> (HandlerContext, std::path::PathBuf) {
     assert_eq!(loaded.files.len(), orig_files);
     assert_eq!(loaded.index.len(), orig_tokens);
     assert_eq!(loaded.trigram.trigram_map.len(), orig_trigrams);
-    let loaded_ctx = HandlerContext { index: Arc::new(RwLock::new(loaded)), def_index: None, server_dir: root, server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let loaded_ctx = HandlerContext { index: Searcher::new(loaded), def_index: None, server_dir: root, server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     let result = dispatch_tool(&loaded_ctx, "search_grep", &json!({"terms": "databaseconn", "substring": true}));
     assert!(!result.is_error);
     let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
@@ -768,9 +1201,23 @@ fn make_phrase_postfilter_ctx() -> (HandlerContext, std::path::PathBuf) {
     let content_index = crate::build_content_index(&crate::ContentIndexArgs {
         dir: tmp_dir.to_string_lossy().to_string(), ext: "xml".to_string(),
         max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: 0,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: 0,
+        stop_token_pct: 0,
+        checkpoint_secs: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
     });
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)), def_index: None,
+        index: Searcher::new(content_index), def_index: None,
         server_dir: tmp_dir.to_string_lossy().to_string(), server_ext: "xml".to_string(),
         metrics: false, index_base: tmp_dir.join(".index"),
         max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES,
@@ -778,6 +1225,15 @@ fn make_phrase_postfilter_ctx() -> (HandlerContext, std::path::PathBuf) {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
     (ctx, tmp_dir)
 }
@@ -830,6 +1286,101 @@ fn make_phrase_postfilter_ctx() -> (HandlerContext, std::path::PathBuf) {
     cleanup_tmp(&tmp);
 }
 
+#[test] fn test_grep_term_outside_tokenizer_falls_back_to_content_scan() {
+    let (ctx, tmp) = make_phrase_postfilter_ctx();
+    // '{}' is split away by the tokenizer: the index alone can never match it
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "{}", "showLines": true }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["searchMode"], "content-scan");
+    assert_eq!(output["summary"]["totalFiles"], 1);
+    assert!(output["files"][0]["path"].as_str().unwrap().contains("Code.xml"));
+    let warnings = output["summary"]["warnings"].to_string();
+    assert!(warnings.contains("characters the tokenizer splits on"), "{}", warnings);
+
+    // Exact 'fn' is fine; a 1-character exact term is below min_token_len and only warns
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "fn,x", "substring": false }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalFiles"], 1);
+    assert!(output["summary"]["warnings"].to_string().contains("shorter than the index's minimum token length (2)"));
+
+    let info: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_info", &json!({})).content[0].text).unwrap();
+    let content = info["indexes"].as_array().unwrap().iter().find(|i| i["type"] == "content").unwrap();
+    assert_eq!(content["tokenizer"], json!({ "minTokenLen": 2, "maxTokenLen": 0 }));
+    cleanup_tmp(&tmp);
+}
+
+#[test] fn test_grep_case_sensitive_checks_candidate_lines() {
+    let (ctx, tmp) = make_phrase_postfilter_ctx();
+    let total_files = |args: Value| -> u64 {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        output["summary"]["totalFiles"].as_u64().unwrap()
+    };
+    // Exact: the index finds 'property' in any case, the check keeps 'Property'
+    assert_eq!(total_files(json!({ "terms": "property", "substring": false })), 2);
+    assert_eq!(total_files(json!({ "terms": "property", "substring": false, "caseSensitive": true })), 0);
+    assert_eq!(total_files(json!({ "terms": "Property", "substring": false, "caseSensitive": true })), 2);
+    // Substring: 'Logger' inside ILogger
+    assert_eq!(total_files(json!({ "terms": "Logger", "caseSensitive": true })), 2);
+    assert_eq!(total_files(json!({ "terms": "logger", "caseSensitive": true })), 0);
+    // Phrase
+    assert_eq!(total_files(json!({ "terms": "pub fn", "phrase": true, "caseSensitive": true })), 1);
+    assert_eq!(total_files(json!({ "terms": "Pub Fn", "phrase": true, "caseSensitive": true })), 0);
+
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "Prop.*", "regex": true, "caseSensitive": true }));
+    assert!(result.is_error);
+    cleanup_tmp(&tmp);
+}
+
+#[test] fn test_grep_word_boundary_keeps_identifier_part_matches() {
+    let (ctx, tmp) = make_phrase_postfilter_ctx();
+    let grep = |args: Value| -> Value {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+    assert_eq!(grep(json!({ "terms": "ogger" }))["summary"]["totalFiles"], 2);
+    assert_eq!(grep(json!({ "terms": "ogger", "wordBoundary": true }))["summary"]["totalFiles"], 0);
+    assert_eq!(grep(json!({ "terms": "prop", "wordBoundary": true }))["summary"]["totalFiles"], 0);
+    // 'Logger' is a camelCase part of ILogger: found by reading the lines
+    let output = grep(json!({ "terms": "logger", "wordBoundary": true }));
+    assert_eq!(output["summary"]["totalFiles"], 2);
+    assert_eq!(output["summary"]["matchedTokens"], json!(["ilogger"]));
+
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "logger", "phrase": true, "wordBoundary": true }));
+    assert!(result.is_error);
+    cleanup_tmp(&tmp);
+}
+
+#[test] fn test_grep_group_by_dir_aggregates_files_per_directory() {
+    let ctx = make_substring_ctx(
+        vec![("userservice", 0, vec![1, 2]), ("userservice", 1, vec![3]), ("iuserservice", 2, vec![4, 5, 6]), ("userservice", 3, vec![7])],
+        vec!["./src/users/UserService.cs", "./src/users/UserController.cs", "./src/api/IUserService.cs", "./Program.cs"],
+    );
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice", "groupBy": "dir" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert!(output.get("files").is_none());
+    let groups = output["groups"].as_array().unwrap();
+    let dirs: Vec<&str> = groups.iter().map(|g| g["dir"].as_str().unwrap()).collect();
+    assert_eq!(dirs, ["src/api", "src/users", "."]);
+    assert_eq!(groups[1]["files"], 2);
+    assert_eq!(groups[1]["occurrences"], 3);
+    assert_eq!(groups[1]["topFiles"].as_array().unwrap().len(), 2);
+    assert_eq!(output["summary"]["totalFiles"], 4);
+    assert_eq!(output["summary"]["totalGroups"], 3);
+
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_grep",
+        &json!({ "terms": "userservice", "groupBy": "dir", "groupDepth": 1, "maxResults": 1 })).content[0].text).unwrap();
+    assert_eq!(output["groups"], json!([{ "dir": "src", "files": 3, "occurrences": 6, "topFiles": output["groups"][0]["topFiles"] }]));
+    assert_eq!(output["summary"]["totalGroups"], 2);
+
+    assert!(dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice", "groupBy": "dir", "countOnly": true })).is_error);
+    assert!(dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice", "groupBy": "file" })).is_error);
+}
+
 #[test] fn test_explicit_substring_true_with_regex_errors() {
     let ctx = make_substring_ctx(
         vec![("httpclient", 0, vec![5])],
@@ -844,9 +1395,9 @@ fn make_phrase_postfilter_ctx() -> (HandlerContext, std::path::PathBuf) {
 
 #[test] fn test_metrics_off_no_extra_fields() {
     let mut idx = HashMap::new();
-    idx.insert("httpclient".to_string(), vec![Posting { file_id: 0, lines: vec![5] }]);
-    let index = ContentIndex { root: ".".to_string(), created_at: 0, max_age_secs: 3600, files: vec!["C:\\test\\Program.cs".to_string()], index: idx, total_tokens: 100, extensions: vec!["cs".to_string()], file_token_counts: vec![50], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(index)), def_index: None, server_dir: ".".to_string(), server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    idx.insert("httpclient".into(), vec![Posting { file_id: 0, lines: vec![5] }]);
+    let index = ContentIndex { root: ".".to_string(), created_at: 0, max_age_secs: 3600, files: vec!["C:\\test\\Program.cs".to_string()], index: idx, total_tokens: 100, extensions: vec!["cs".to_string()], file_token_counts: vec![50], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(index), def_index: None, server_dir: ".".to_string(), server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "HttpClient"}));
     let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
     assert!(output["summary"].get("responseBytes").is_none());
@@ -855,9 +1406,9 @@ fn make_phrase_postfilter_ctx() -> (HandlerContext, std::path::PathBuf) {
 
 #[test] fn test_metrics_on_injects_fields() {
     let mut idx = HashMap::new();
-    idx.insert("httpclient".to_string(), vec![Posting { file_id: 0, lines: vec![5] }]);
-    let index = ContentIndex { root: ".".to_string(), created_at: 0, max_age_secs: 3600, files: vec!["C:\\test\\Program.cs".to_string()], index: idx, total_tokens: 100, extensions: vec!["cs".to_string()], file_token_counts: vec![50], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(index)), def_index: None, server_dir: ".".to_string(), server_ext: "cs".to_string(), metrics: true, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    idx.insert("httpclient".into(), vec![Posting { file_id: 0, lines: vec![5] }]);
+    let index = ContentIndex { root: ".".to_string(), created_at: 0, max_age_secs: 3600, files: vec!["C:\\test\\Program.cs".to_string()], index: idx, total_tokens: 100, extensions: vec!["cs".to_string()], file_token_counts: vec![50], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(index), def_index: None, server_dir: ".".to_string(), server_ext: "cs".to_string(), metrics: true, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "HttpClient"}));
     let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
     assert!(output["summary"]["searchTimeMs"].as_f64().is_some());
@@ -867,7 +1418,7 @@ fn make_phrase_postfilter_ctx() -> (HandlerContext, std::path::PathBuf) {
 
 #[test] fn test_metrics_not_injected_on_error() {
     let ctx = make_empty_ctx();
-    let ctx = HandlerContext { metrics: true, ..ctx };
+    let ctx = HandlerContext { metrics: true, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), ..ctx };
     let result = dispatch_tool(&ctx, "search_grep", &json!({}));
     assert!(result.is_error);
     assert!(!result.content[0].text.contains("searchTimeMs"));
@@ -875,9 +1426,9 @@ fn make_phrase_postfilter_ctx() -> (HandlerContext, std::path::PathBuf) {
 
 #[test] fn test_metrics_search_time_is_positive() {
     let mut idx = HashMap::new();
-    idx.insert("foo".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-    let index = ContentIndex { root: ".".to_string(), created_at: 0, max_age_secs: 3600, files: vec!["test.cs".to_string()], index: idx, total_tokens: 10, extensions: vec!["cs".to_string()], file_token_counts: vec![10], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(index)), def_index: None, server_dir: ".".to_string(), server_ext: "cs".to_string(), metrics: true, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    idx.insert("foo".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+    let index = ContentIndex { root: ".".to_string(), created_at: 0, max_age_secs: 3600, files: vec!["test.cs".to_string()], index: idx, total_tokens: 10, extensions: vec!["cs".to_string()], file_token_counts: vec![10], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(index), def_index: None, server_dir: ".".to_string(), server_ext: "cs".to_string(), metrics: true, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "foo"}));
     let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
     assert!(output["summary"]["searchTimeMs"].as_f64().unwrap() >= 0.0);
@@ -897,11 +1448,11 @@ fn make_search_fast_ctx() -> (HandlerContext, std::path::PathBuf) {
         writeln!(f, "// {}", name).unwrap();
     }
     let dir_str = tmp_dir.to_string_lossy().to_string();
-    let file_index = crate::build_index(&crate::IndexArgs { dir: dir_str.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 0 });
+    let file_index = crate::build_index(&crate::IndexArgs { dir: dir_str.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 0, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, storage: None });
     let idx_base = tmp_dir.join(".index");
     let _ = crate::save_index(&file_index, &idx_base);
-    let content_index = ContentIndex { root: dir_str.clone(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(content_index)), def_index: None, server_dir: dir_str, server_ext: "cs".to_string(), metrics: false, index_base: idx_base, max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let content_index = ContentIndex { root: dir_str.clone(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(content_index), def_index: None, server_dir: dir_str, server_ext: "cs".to_string(), metrics: false, index_base: idx_base, max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     (ctx, tmp_dir)
 }
 
@@ -991,8 +1542,8 @@ fn make_search_fast_ctx() -> (HandlerContext, std::path::PathBuf) {
     std::fs::create_dir_all(&sub_a).unwrap(); std::fs::create_dir_all(&sub_b).unwrap();
     std::fs::write(sub_a.join("hello.txt"), "ProductCatalog usage here").unwrap();
     std::fs::write(sub_b.join("other.txt"), "ProductCatalog other usage").unwrap();
-    let index = crate::build_content_index(&crate::ContentIndexArgs { dir: tmp.to_string_lossy().to_string(), ext: "txt".to_string(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2 });
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(index)), def_index: None, server_dir: tmp.to_string_lossy().to_string(), server_ext: "txt".to_string(), metrics: false, index_base: tmp.to_path_buf(), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let index = crate::build_content_index(&crate::ContentIndexArgs { dir: tmp.to_string_lossy().to_string(), ext: "txt".to_string(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, watch: false, save_interval_secs: 60, max_file_size: 0, spill_mb: 0, shards: 0, max_token_len: 0, stop_token_pct: 0, checkpoint_secs: 0, max_depth: 0, prune_dir: Vec::new(), storage: None });
+    let ctx = HandlerContext { index: Searcher::new(index), def_index: None, server_dir: tmp.to_string_lossy().to_string(), server_ext: "txt".to_string(), metrics: false, index_base: tmp.to_path_buf(), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     let r_all = handle_search_grep(&ctx, &json!({"terms": "productcatalog"}));
     let o_all: Value = serde_json::from_str(&r_all.content[0].text).unwrap();
     assert_eq!(o_all["summary"]["totalFiles"], 2);
@@ -1005,8 +1556,8 @@ fn make_search_fast_ctx() -> (HandlerContext, std::path::PathBuf) {
 #[test] fn test_grep_rejects_outside_dir() {
     let tmp_holder = tempfile::tempdir().unwrap();
     let tmp = tmp_holder.path();
-    let index = ContentIndex { root: tmp.to_string_lossy().to_string(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), file_token_counts: vec![], total_tokens: 0, extensions: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(index)), def_index: None, server_dir: tmp.to_string_lossy().to_string(), server_ext: "cs".to_string(), metrics: false, index_base: tmp.to_path_buf(), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let index = ContentIndex { root: tmp.to_string_lossy().to_string(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), file_token_counts: vec![], total_tokens: 0, extensions: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(index), def_index: None, server_dir: tmp.to_string_lossy().to_string(), server_ext: "cs".to_string(), metrics: false, index_base: tmp.to_path_buf(), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     let result = handle_search_grep(&ctx, &json!({"terms": "test", "dir": r"Z:\some\other\path"}));
     assert!(result.is_error);
 }
@@ -1028,7 +1579,7 @@ fn test_response_truncation_triggers_on_large_result() {
         file_token_counts.push(1000u32);
 
         let lines: Vec<u32> = (1..=100).collect();
-        idx.entry("common".to_string())
+        idx.entry("common".into())
             .or_insert_with(Vec::new)
             .push(Posting { file_id: i as u32, lines });
     }
@@ -1046,10 +1597,19 @@ fn test_response_truncation_triggers_on_large_result() {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(index)),
+        index: Searcher::new(index),
         def_index: None,
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -1058,6 +1618,15 @@ fn test_response_truncation_triggers_on_large_result() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_grep", &json!({
@@ -1090,7 +1659,7 @@ fn test_response_truncation_triggers_on_large_result() {
 #[test]
 fn test_response_truncation_does_not_trigger_on_small_result() {
     let mut idx = HashMap::new();
-    idx.insert("mytoken".to_string(), vec![Posting { file_id: 0, lines: vec![10, 20] }]);
+    idx.insert("mytoken".into(), vec![Posting { file_id: 0, lines: vec![10, 20] }]);
 
     let index = ContentIndex {
         root: ".".to_string(),
@@ -1105,10 +1674,19 @@ fn test_response_truncation_does_not_trigger_on_small_result() {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(index)),
+        index: Searcher::new(index),
         def_index: None,
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -1117,6 +1695,15 @@ fn test_response_truncation_does_not_trigger_on_small_result() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "mytoken", "substring": false}));
@@ -1135,12 +1722,23 @@ fn test_response_truncation_does_not_trigger_on_small_result() {
 fn test_dispatch_grep_while_content_index_building() {
     let ctx = HandlerContext {
         content_ready: Arc::new(AtomicBool::new(false)),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..make_empty_ctx()
     };
     let result = dispatch_tool(&ctx, "search_grep", &json!({"terms": "foo"}));
     assert!(result.is_error);
     assert!(result.content[0].text.contains("being built"),
         "Expected 'being built' message, got: {}", result.content[0].text);
+    let hint = &result.meta.as_ref().unwrap()["error"];
+    assert_eq!((&hint["retryable"], &hint["suggestedAction"]), (&json!(true), &json!("wait_for_build")));
 }
 
 #[test]
@@ -1164,7 +1762,23 @@ fn test_dispatch_definitions_while_def_index_building() {
             parse_errors: 0,
             lossy_file_count: 0,
             empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+            walk_limits: Default::default(),
+            file_imports: HashMap::new(),
+            di_registrations: Vec::new(),
+            endpoints: Vec::new(),
+            partial_types: HashMap::new(),
+            doc_comments: HashMap::new(),
+            imports: Vec::new(),
         }))),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..make_empty_ctx()
     };
     let result = dispatch_tool(&ctx, "search_definitions", &json!({"name": "Foo"}));
@@ -1194,7 +1808,23 @@ fn test_dispatch_callers_while_def_index_building() {
             parse_errors: 0,
             lossy_file_count: 0,
             empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+            walk_limits: Default::default(),
+            file_imports: HashMap::new(),
+            di_registrations: Vec::new(),
+            endpoints: Vec::new(),
+            partial_types: HashMap::new(),
+            doc_comments: HashMap::new(),
+            imports: Vec::new(),
         }))),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..make_empty_ctx()
     };
     let result = dispatch_tool(&ctx, "search_callers", &json!({"method": "Foo"}));
@@ -1207,6 +1837,15 @@ fn test_dispatch_callers_while_def_index_building() {
 fn test_dispatch_reindex_while_content_index_building() {
     let ctx = HandlerContext {
         content_ready: Arc::new(AtomicBool::new(false)),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..make_empty_ctx()
     };
     let result = dispatch_tool(&ctx, "search_reindex", &json!({}));
@@ -1219,6 +1858,15 @@ fn test_dispatch_reindex_while_content_index_building() {
 fn test_dispatch_fast_while_content_index_building() {
     let ctx = HandlerContext {
         content_ready: Arc::new(AtomicBool::new(false)),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..make_empty_ctx()
     };
     let result = dispatch_tool(&ctx, "search_fast", &json!({"pattern": "foo"}));
@@ -1232,6 +1880,15 @@ fn test_dispatch_help_works_while_index_building() {
     let ctx = HandlerContext {
         content_ready: Arc::new(AtomicBool::new(false)),
         def_ready: Arc::new(AtomicBool::new(false)),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..make_empty_ctx()
     };
     let result = dispatch_tool(&ctx, "search_help", &json!({}));
@@ -1245,6 +1902,15 @@ fn test_dispatch_help_works_while_index_building() {
 fn test_dispatch_find_works_while_index_building() {
     let ctx = HandlerContext {
         content_ready: Arc::new(AtomicBool::new(false)),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..make_empty_ctx()
     };
     let result = dispatch_tool(&ctx, "search_find", &json!({"pattern": "nonexistent_xyz"}));
@@ -1267,7 +1933,7 @@ fn test_search_grep_response_truncation_via_small_budget() {
         files.push(path);
         file_token_counts.push(100u32);
         let lines: Vec<u32> = (1..=20).collect();
-        idx.entry("targettoken".to_string())
+        idx.entry("targettoken".into())
             .or_insert_with(Vec::new)
             .push(Posting { file_id: i as u32, lines });
     }
@@ -1285,10 +1951,19 @@ fn test_search_grep_response_truncation_via_small_budget() {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(index)),
+        index: Searcher::new(index),
         def_index: None,
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -1300,6 +1975,15 @@ fn test_search_grep_response_truncation_via_small_budget() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_grep", &json!({
@@ -1374,11 +2058,11 @@ fn test_search_fast_dirs_only_and_files_only() {
     { let mut f = std::fs::File::create(&file_at_root).unwrap(); writeln!(f, "// helper").unwrap(); }
 
     let dir_str = tmp_dir.to_string_lossy().to_string();
-    let file_index = crate::build_index(&crate::IndexArgs { dir: dir_str.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 0 });
+    let file_index = crate::build_index(&crate::IndexArgs { dir: dir_str.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 0, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, storage: None });
     let idx_base = tmp_dir.join(".index");
     let _ = crate::save_index(&file_index, &idx_base);
-    let content_index = ContentIndex { root: dir_str.clone(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(content_index)), def_index: None, server_dir: dir_str, server_ext: "cs".to_string(), metrics: false, index_base: idx_base, max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let content_index = ContentIndex { root: dir_str.clone(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(content_index), def_index: None, server_dir: dir_str, server_ext: "cs".to_string(), metrics: false, index_base: idx_base, max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
 
     let result_dirs = handle_search_fast(&ctx, &json!({"pattern": "Models", "dirsOnly": true}));
     assert!(!result_dirs.is_error, "dirsOnly should not error: {}", result_dirs.content[0].text);
@@ -1434,7 +2118,7 @@ fn test_search_fast_regex_mode() {
 #[test]
 fn test_search_grep_sql_extension_filter() {
     let mut idx = HashMap::new();
-    idx.insert("createtable".to_string(), vec![
+    idx.insert("createtable".into(), vec![
         Posting { file_id: 0, lines: vec![5] },
         Posting { file_id: 1, lines: vec![10] },
         Posting { file_id: 2, lines: vec![3] },
@@ -1457,10 +2141,19 @@ fn test_search_grep_sql_extension_filter() {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(index)),
+        index: Searcher::new(index),
         def_index: None,
         server_dir: ".".to_string(),
         server_ext: "cs,sql".to_string(),
@@ -1472,6 +2165,15 @@ fn test_search_grep_sql_extension_filter() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_grep", &json!({
@@ -1531,10 +2233,24 @@ fn test_search_grep_phrase_search_with_show_lines() {
         dir: tmp_dir.to_string_lossy().to_string(),
         ext: "sql".to_string(),
         max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: 0,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: 0,
+        stop_token_pct: 0,
+        checkpoint_secs: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
     });
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: None,
         server_dir: tmp_dir.to_string_lossy().to_string(),
         server_ext: "sql".to_string(),
@@ -1546,6 +2262,15 @@ fn test_search_grep_phrase_search_with_show_lines() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_grep", &json!({
@@ -1604,6 +2329,15 @@ fn test_search_definitions_file_filter_slash_normalization() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![25, 25],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1644,10 +2378,17 @@ fn test_search_definitions_file_filter_slash_normalization() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1657,6 +2398,15 @@ fn test_search_definitions_file_filter_slash_normalization() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result_backslash = dispatch_tool(&ctx, "search_definitions", &json!({
@@ -1723,7 +2473,7 @@ fn test_search_grep_max_results_zero_means_unlimited() {
         let path = format!("C:\\src\\Module_{:02}\\Service.cs", i);
         files.push(path);
         file_token_counts.push(50u32);
-        idx.entry("commontoken".to_string())
+        idx.entry("commontoken".into())
             .or_insert_with(Vec::new)
             .push(Posting { file_id: i as u32, lines: vec![10] });
     }
@@ -1741,10 +2491,19 @@ fn test_search_grep_max_results_zero_means_unlimited() {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(index)),
+        index: Searcher::new(index),
         def_index: None,
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -1756,6 +2515,15 @@ fn test_search_grep_max_results_zero_means_unlimited() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result_unlimited = dispatch_tool(&ctx, "search_grep", &json!({
@@ -1816,9 +2584,18 @@ fn test_search_find_combined_parameters() {
         files: vec![], index: HashMap::new(), total_tokens: 0,
         extensions: vec!["cs".to_string()], file_token_counts: vec![],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: None,
         server_dir: dir_str.clone(),
         server_ext: "cs".to_string(),
@@ -1830,6 +2607,15 @@ fn test_search_find_combined_parameters() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result_count = dispatch_tool(&ctx, "search_find", &json!({
@@ -1975,9 +2761,18 @@ fn test_search_find_contents_mode() {
         files: vec![], index: HashMap::new(), total_tokens: 0,
         extensions: vec!["txt".to_string()], file_token_counts: vec![],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: None,
         server_dir: dir_str.clone(),
         server_ext: "txt".to_string(),
@@ -1989,6 +2784,15 @@ fn test_search_find_contents_mode() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Search file contents for "magic_searchable_token" in .txt files
@@ -2159,6 +2963,26 @@ fn test_search_info_response_structure() {
     }
 }
 
+#[test]
+fn test_search_info_staleness_follows_clock_and_generation() {
+    let clock = Arc::new(search::clock::ManualClock::new(1800));
+    let mut ctx = make_substring_ctx(vec![("httpclient", 0, vec![5])], vec!["C:\\test\\Program.cs"]);
+    ctx.generation = Arc::new(search::clock::Generation::with_clock(clock.clone()));
+    let content = |ctx: &HandlerContext| {
+        let info: Value = serde_json::from_str(&dispatch_tool(ctx, "search_info", &json!({})).content[0].text).unwrap();
+        info["indexes"].as_array().unwrap().iter().find(|i| i["type"] == "content").unwrap().clone()
+    };
+    // Built at 0 with a max age of 3600s
+    assert_eq!((content(&ctx)["ageHours"].clone(), content(&ctx)["stale"].clone()), (json!(0.5), json!(false)));
+    clock.set(7200);
+    assert_eq!((content(&ctx)["ageHours"].clone(), content(&ctx)["stale"].clone()), (json!(2.0), json!(true)));
+    // A watcher batch or reindex refreshes a live index, whatever its build time
+    ctx.generation.bump();
+    assert_eq!(content(&ctx)["stale"], false);
+    clock.advance(3601);
+    assert_eq!(content(&ctx)["stale"], true);
+}
+
 // ═══════════════════════════════════════════════════════════════════════
 // Relevance Ranking tests
 // ═══════════════════════════════════════════════════════════════════════
@@ -2179,6 +3003,15 @@ fn make_ranking_defs_ctx() -> HandlerContext {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50, 30, 20],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -2230,10 +3063,17 @@ fn make_ranking_defs_ctx() -> HandlerContext {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -2243,6 +3083,15 @@ fn make_ranking_defs_ctx() -> HandlerContext {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     }
 }
 
@@ -2341,6 +3190,56 @@ fn test_search_definitions_ranking_not_applied_with_regex() {
     // We don't assert specific order since regex mode uses default order (no ranking)
 }
 
+/// A `--rank-hook` policy re-scores search_definitions results after the relevance sort:
+/// here one that ranks the built-in order backwards and drops interfaces (kind 1).
+#[test]
+fn test_search_definitions_rank_hook_reorders_and_drops() {
+    let reverse_without_interfaces = r#"(select (f32.const -1) (f32.convert_i32_u (local.get $i))
+        (i32.eq (i32.load offset=16 (local.get $rec)) (i32.const 1)))"#;
+    let built_in = dispatch_tool(&make_ranking_defs_ctx(), "search_definitions", &json!({"name": "UserService"}));
+    let built_in: Value = serde_json::from_str(&built_in.content[0].text).unwrap();
+    let mut expected: Vec<&str> = built_in["definitions"].as_array().unwrap().iter()
+        .map(|d| d["name"].as_str().unwrap())
+        .filter(|n| *n != "IUserService")
+        .collect();
+    expected.reverse();
+
+    let ctx = HandlerContext {
+        rank_hook: Some(Arc::new(crate::rank_hook::test_policy(reverse_without_interfaces))),
+        ..make_ranking_defs_ctx()
+    };
+    let result = dispatch_tool(&ctx, "search_definitions", &json!({"name": "UserService"}));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let names: Vec<&str> = output["definitions"].as_array().unwrap().iter()
+        .map(|d| d["name"].as_str().unwrap()).collect();
+    assert_eq!(names, expected);
+    assert_eq!(output["summary"]["rankHookDropped"], 1);
+    assert_eq!(output["summary"]["totalResults"], expected.len());
+
+    // rankHook: false keeps the built-in order
+    let result = dispatch_tool(&ctx, "search_definitions", &json!({"name": "UserService", "rankHook": false}));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["definitions"], built_in["definitions"]);
+    assert!(output["summary"].get("rankHookDropped").is_none());
+}
+
+/// A failing policy keeps the built-in order and reports why in summary.rankHookError.
+#[test]
+fn test_search_definitions_rank_hook_trap_falls_back() {
+    let built_in = dispatch_tool(&make_ranking_defs_ctx(), "search_definitions", &json!({"name": "UserService"}));
+    let built_in: Value = serde_json::from_str(&built_in.content[0].text).unwrap();
+    let ctx = HandlerContext {
+        rank_hook: Some(Arc::new(crate::rank_hook::test_policy("(unreachable)"))),
+        ..make_ranking_defs_ctx()
+    };
+    let result = dispatch_tool(&ctx, "search_definitions", &json!({"name": "UserService"}));
+    assert!(!result.is_error);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["definitions"], built_in["definitions"]);
+    assert!(output["summary"]["rankHookError"].is_string());
+}
+
 /// search_fast ranking: exact stem match sorts first, then prefix, then contains.
 #[test]
 fn test_search_fast_ranking_exact_stem_first() {
@@ -2358,11 +3257,11 @@ fn test_search_fast_ranking_exact_stem_first() {
     }
 
     let dir_str = tmp_dir.to_string_lossy().to_string();
-    let file_index = crate::build_index(&crate::IndexArgs { dir: dir_str.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 0 });
+    let file_index = crate::build_index(&crate::IndexArgs { dir: dir_str.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 0, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, storage: None });
     let idx_base = tmp_dir.join(".index");
     let _ = crate::save_index(&file_index, &idx_base);
-    let content_index = ContentIndex { root: dir_str.clone(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(content_index)), def_index: None, server_dir: dir_str, server_ext: "cs".to_string(), metrics: false, index_base: idx_base, max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let content_index = ContentIndex { root: dir_str.clone(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(content_index), def_index: None, server_dir: dir_str, server_ext: "cs".to_string(), metrics: false, index_base: idx_base, max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
 
     let result = handle_search_fast(&ctx, &json!({"pattern": "UserService"}));
     assert!(!result.is_error, "search_fast should not error: {}", result.content[0].text);
@@ -2406,11 +3305,11 @@ fn test_search_fast_ranking_shorter_stem_first() {
     }
 
     let dir_str = tmp_dir.to_string_lossy().to_string();
-    let file_index = crate::build_index(&crate::IndexArgs { dir: dir_str.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 0 });
+    let file_index = crate::build_index(&crate::IndexArgs { dir: dir_str.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 0, dry_run: false, build_priority: crate::priority::BuildPriority::Normal, storage: None });
     let idx_base = tmp_dir.join(".index");
     let _ = crate::save_index(&file_index, &idx_base);
-    let content_index = ContentIndex { root: dir_str.clone(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(content_index)), def_index: None, server_dir: dir_str, server_ext: "cs".to_string(), metrics: false, index_base: idx_base, max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let content_index = ContentIndex { root: dir_str.clone(), created_at: 0, max_age_secs: 3600, files: vec![], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(content_index), def_index: None, server_dir: dir_str, server_ext: "cs".to_string(), metrics: false, index_base: idx_base, max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
 
     let result = handle_search_fast(&ctx, &json!({"pattern": "Order"}));
     assert!(!result.is_error);
@@ -2604,9 +3503,23 @@ fn test_search_grep_phrase_sort_by_occurrences() {
     let content_index = crate::build_content_index(&crate::ContentIndexArgs {
         dir: tmp_dir.to_string_lossy().to_string(), ext: "cs".to_string(),
         max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: 0,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: 0,
+        stop_token_pct: 0,
+        checkpoint_secs: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
     });
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)), def_index: None,
+        index: Searcher::new(content_index), def_index: None,
         server_dir: tmp_dir.to_string_lossy().to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: tmp_dir.join(".index"),
         max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES,
@@ -2615,6 +3528,15 @@ fn test_search_grep_phrase_sort_by_occurrences() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_grep", &json!({
@@ -2755,6 +3677,15 @@ fn test_substring_matched_tokens_filtered_by_dir() {
     // Override server_dir to match the file paths
     let ctx = HandlerContext {
         server_dir: "C:\\project".to_string(),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..ctx
     };
 
@@ -2870,6 +3801,15 @@ fn test_substring_matched_tokens_empty_when_no_files_match() {
     );
     let ctx = HandlerContext {
         server_dir: "C:\\project".to_string(),
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
         ..ctx
     };
 
@@ -2995,3 +3935,1322 @@ fn test_git_history_cached_reversed_dates_returns_error() {
     assert!(result.content[0].text.contains("after"),
         "Error should mention 'after', got: {}", result.content[0].text);
 }
+
+// ─── search_read_file tests ─────────────────────────────────────────
+
+fn make_read_file_ctx(max_response_bytes: usize) -> (HandlerContext, tempfile::TempDir) {
+    let tmp = tempfile::tempdir().unwrap();
+    let body: String = (1..=50).map(|i| format!("line {}\n", i)).collect();
+    std::fs::write(tmp.path().join("sample.txt"), body).unwrap();
+    std::fs::write(tmp.path().join("Service.cs"), "class Service\n{\n    void Run()\n    {\n        Go();\n    }\n}\n").unwrap();
+
+    let mut ctx = make_empty_ctx();
+    ctx.server_dir = tmp.path().to_string_lossy().to_string();
+    ctx.max_response_bytes = max_response_bytes;
+
+    let cs_path = clean_path(&std::fs::canonicalize(tmp.path().join("Service.cs")).unwrap().to_string_lossy());
+    let mut def_index = crate::definitions::DefinitionIndex {
+        files: vec![cs_path],
+        ..Default::default()
+    };
+    for (i, (name, kind, s, e, parent)) in [
+        ("Service", crate::definitions::DefinitionKind::Class, 1, 7, None),
+        ("Run", crate::definitions::DefinitionKind::Method, 3, 6, Some("Service")),
+    ].into_iter().enumerate() {
+        def_index.definitions.push(crate::definitions::DefinitionEntry {
+            file_id: 0, name: name.to_string(), kind, line_start: s, line_end: e,
+            parent: parent.map(|p: &str| p.to_string()), signature: None,
+            modifiers: vec![], attributes: vec![], base_types: vec![],
+        });
+        def_index.name_index.entry(name.to_lowercase()).or_default().push(i as u32);
+        def_index.file_index.entry(0).or_default().push(i as u32);
+    }
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    (ctx, tmp)
+}
+
+#[test]
+fn test_read_file_line_range() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({
+        "path": "sample.txt", "startLine": 10, "endLine": 12
+    }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["startLine"], 10);
+    assert_eq!(output["endLine"], 12);
+    assert_eq!(output["lines"], json!(["line 10", "line 11", "line 12"]));
+    assert_eq!(output["summary"]["totalLines"], 50);
+    assert!(output["summary"].get("responseTruncated").is_none());
+}
+
+#[test]
+fn test_read_file_max_lines_sets_next_start_line() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({
+        "path": "sample.txt", "maxLines": 5
+    }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["lines"].as_array().unwrap().len(), 5);
+    assert_eq!(output["summary"]["responseTruncated"], true);
+    assert_eq!(output["summary"]["nextStartLine"], 6);
+    assert_eq!(output["summary"]["truncationReason"], "capped to maxLines=5");
+
+    // A window that ends exactly at EOF is complete, not capped
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({
+        "path": "sample.txt", "startLine": 46, "maxLines": 5
+    }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["lines"].as_array().unwrap().len(), 5);
+    assert!(output["summary"].get("responseTruncated").is_none(), "{}", output["summary"]);
+    assert!(output["summary"].get("truncationReason").is_none(), "{}", output["summary"]);
+}
+
+#[test]
+fn test_read_file_response_budget_cuts_at_line_boundary() {
+    // 1024 bytes are reserved for the envelope, leaving ~100 bytes for lines
+    let (ctx, _tmp) = make_read_file_ctx(1124);
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({ "path": "sample.txt", "maxLines": 0 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let returned = output["lines"].as_array().unwrap().len();
+    assert!(returned > 0 && returned < 50, "expected partial read, got {} lines", returned);
+    assert_eq!(output["summary"]["nextStartLine"], returned + 1);
+    assert!(output["summary"]["truncationReason"].as_str().unwrap().contains("budget"));
+}
+
+#[test]
+fn test_read_file_byte_range() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({
+        "path": "sample.txt", "byteOffset": 7, "byteLength": 6
+    }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["text"], "line 2");
+    assert_eq!(output["startLine"], 2);
+}
+
+#[test]
+fn test_read_file_symbol_returns_definition_body() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({ "symbol": "Run" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["definition"]["kind"], "method");
+    assert_eq!(output["definition"]["parent"], "Service");
+    assert_eq!(output["startLine"], 3);
+    assert_eq!(output["lines"].as_array().unwrap().len(), 4);
+    assert_eq!(output["lines"][2], "        Go();");
+}
+
+#[test]
+fn test_read_file_symbol_not_found() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({ "symbol": "Missing" }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("No definition named"));
+}
+
+#[test]
+fn test_read_file_rejects_path_outside_server_dir() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let outside = tempfile::NamedTempFile::new().unwrap();
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({
+        "path": outside.path().to_string_lossy()
+    }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("outside the server directory"));
+}
+
+#[test]
+fn test_read_file_requires_path_or_symbol() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_read_file", &json!({}));
+    assert!(result.is_error);
+}
+
+// ─── search_outline tests ───────────────────────────────────────────
+
+#[test]
+fn test_outline_returns_nested_tree() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_outline", &json!({ "file": "Service.cs" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert!(output["file"].as_str().unwrap().ends_with("Service.cs"));
+    assert_eq!(output["outline"][0]["name"], "Service");
+    assert_eq!(output["outline"][0]["children"][0]["name"], "Run");
+    assert_eq!(output["outline"][0]["children"][0]["lines"], "3-6");
+    assert_eq!(output["summary"]["totalDefinitions"], 2);
+    assert_eq!(output["summary"]["topLevel"], 1);
+}
+
+#[test]
+fn test_outline_unknown_file_is_error() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_outline", &json!({ "file": "Nope.cs" }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("not in the definition index"));
+}
+
+#[test]
+fn test_outline_requires_file_param() {
+    let (ctx, _tmp) = make_read_file_ctx(0);
+    let result = dispatch_tool(&ctx, "search_outline", &json!({}));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("file"));
+}
+
+// ─── search_references tests ────────────────────────────────────────
+
+fn make_references_ctx(with_defs: bool) -> (HandlerContext, tempfile::TempDir) {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Widget.cs"), "\
+public class Widget
+{
+    public void Spin() { }
+}
+").unwrap();
+    std::fs::write(tmp.path().join("Gadget.cs"), "\
+public class Gadget : Widget
+{
+    private Widget _inner;
+    public Gadget(Widget inner)
+    {
+        _inner = new Widget();
+        var items = new List<Widget>();
+        _inner.Spin();
+    }
+}
+").unwrap();
+    let ctx = references_ctx_for(tmp.path(), with_defs);
+    (ctx, tmp)
+}
+
+/// Content index (and definition index, with `with_defs`) of the `.cs` files in `dir`.
+fn references_ctx_for(dir: &std::path::Path, with_defs: bool) -> HandlerContext {
+    let dir = dir.to_string_lossy().to_string();
+    let mut ctx = make_empty_ctx();
+    ctx.index.replace(crate::build_content_index(&crate::ContentIndexArgs {
+        dir: dir.clone(), ext: "cs".to_string(), max_age_hours: 24, hidden: false, no_ignore: false,
+        threads: 1, min_token_len: 2, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: 0,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: 0,
+        stop_token_pct: 0,
+        checkpoint_secs: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+    }));
+    if with_defs {
+        let def_index = crate::definitions::build_definition_index(&crate::definitions::DefIndexArgs {
+            dir: dir.clone(), ext: "cs".to_string(), threads: 1, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+            import: Vec::new(),
+        });
+        ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    }
+    ctx.server_dir = dir;
+    ctx
+}
+
+fn usages_by_line(output: &Value) -> Vec<(u64, String)> {
+    output["references"].as_array().unwrap().iter()
+        .filter(|r| r["file"].as_str().unwrap().ends_with("Gadget.cs"))
+        .map(|r| (r["line"].as_u64().unwrap(), r["usage"].as_str().unwrap().to_string()))
+        .collect()
+}
+
+#[test]
+fn test_references_classifies_usages_with_defs() {
+    let (ctx, _tmp) = make_references_ctx(true);
+    let result = dispatch_tool(&ctx, "search_references", &json!({ "name": "Widget" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(usages_by_line(&output), vec![
+        (1, "inheritance".to_string()),
+        (3, "typeReference".to_string()),
+        (4, "typeReference".to_string()),
+        (6, "instantiation".to_string()),
+        // `new List<Widget>()` instantiates List; Widget is a generic argument
+        (7, "typeReference".to_string()),
+    ]);
+    // `public class Widget` is a declaration, not a reference
+    assert_eq!(output["summary"]["declarationsSkipped"], 1);
+    assert_eq!(output["summary"]["astContext"], true);
+    assert_eq!(output["summary"]["byUsage"]["instantiation"], 1);
+    let ctor_ref = output["references"].as_array().unwrap().iter()
+        .find(|r| r["line"] == 6).unwrap();
+    assert_eq!(ctor_ref["containingMethod"], "Gadget");
+}
+
+#[test]
+fn test_references_usage_filter_and_lexical_fallback() {
+    let (ctx, _tmp) = make_references_ctx(false);
+    let result = dispatch_tool(&ctx, "search_references", &json!({ "name": "Widget", "usage": "inheritance,call" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(usages_by_line(&output), vec![(1, "inheritance".to_string())]);
+    assert_eq!(output["summary"]["astContext"], false);
+    assert!(output["summary"]["hint"].as_str().unwrap().contains("--definitions"));
+}
+
+#[test]
+fn test_references_rejects_invalid_input() {
+    let (ctx, _tmp) = make_references_ctx(false);
+    let result = dispatch_tool(&ctx, "search_references", &json!({ "name": "Ns.Widget" }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("single identifier"));
+    let result = dispatch_tool(&ctx, "search_references", &json!({ "name": "Widget", "usage": "read" }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("Invalid usage 'read'"));
+}
+
+#[test]
+fn test_references_report_a_line_with_two_occurrences_once() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Babe.cs"), "\
+public class Babe
+{
+    public static Babe Gobe(Babe left, Babe right) { return left; }
+}
+").unwrap();
+    std::fs::write(tmp.path().join("Bico.cs"), "\
+public class Bico
+{
+    public void Run(Babe babe, Babe bubo)
+    {
+        var bico = Babe.Gobe(babe, bubo);
+    }
+}
+").unwrap();
+    let ctx = references_ctx_for(tmp.path(), true);
+    let result = dispatch_tool(&ctx, "search_references", &json!({ "name": "Babe", "file": "Bico.cs" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let lines: Vec<u64> = output["references"].as_array().unwrap().iter().map(|r| r["line"].as_u64().unwrap()).collect();
+    assert_eq!(lines, vec![3, 5]);
+    let counted: u64 = output["summary"]["byUsage"].as_object().unwrap().values().map(|v| v.as_u64().unwrap()).sum();
+    assert_eq!(counted, 2);
+
+    // search_symbol_at lists the same references
+    let result = dispatch_tool(&ctx, "search_symbol_at", &json!({ "file": "Bico.cs", "line": 5, "column": 20 }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["symbol"], "Babe");
+    let lines: Vec<u64> = output["references"].as_array().unwrap().iter()
+        .filter(|r| r["file"].as_str().unwrap().ends_with("Bico.cs"))
+        .map(|r| r["line"].as_u64().unwrap()).collect();
+    assert_eq!(lines, vec![3, 5]);
+}
+
+// ─── search_symbol_at tests ─────────────────────────────────────────
+
+#[test]
+fn test_symbol_at_resolves_declaration_call_site_and_local() {
+    let (ctx, _tmp) = make_references_ctx(true);
+    let at = |file: &str, line: u64, column: u64| -> Value {
+        let result = dispatch_tool(&ctx, "search_symbol_at", &json!({ "file": file, "line": line, "column": column }));
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+
+    // `public class Widget` — the declaration itself
+    let output = at("Widget.cs", 1, 16);
+    assert_eq!(output["symbol"], "Widget");
+    assert_eq!(output["resolution"], "declaration");
+    assert_eq!(output["definition"]["kind"], "class");
+    assert_eq!(output["summary"]["totalReferences"], 5);
+
+    // `_inner.Spin()` — the call site's receiver type picks Widget.Spin
+    let output = at("Gadget.cs", 8, 17);
+    assert_eq!(output["symbol"], "Spin");
+    assert_eq!(output["qualifier"], "_inner");
+    assert_eq!(output["resolution"], "callSite");
+    assert_eq!(output["definition"]["parent"], "Widget");
+    assert_eq!(output["definition"]["lines"], "3-3");
+
+    // `items` is a local: no definition, references still listed
+    let output = at("Gadget.cs", 7, 14);
+    assert_eq!(output["symbol"], "items");
+    assert_eq!(output["resolution"], "unresolved");
+    assert!(output.get("definition").is_none());
+    assert!(output["summary"]["hint"].as_str().unwrap().contains("local"));
+
+    let result = dispatch_tool(&ctx, "search_symbol_at", &json!({ "file": "Gadget.cs", "line": 2, "column": 1 }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("No identifier"));
+}
+
+// ─── grep withinDefinition tests ────────────────────────────────────
+
+#[test]
+fn test_grep_within_definition_limits_lines_to_body() {
+    let (ctx, _tmp) = make_references_ctx(true);
+    let grep = |args: Value| -> Value {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+    // The Gadget constructor (lines 4-9), not the class or Widget.cs
+    let within = json!({ "file": "Gadget.cs", "name": "Gadget", "parent": "Gadget" });
+
+    for substring in [false, true] {
+        let output = grep(json!({ "terms": "widget", "substring": substring, "withinDefinition": within }));
+        let files = output["files"].as_array().unwrap();
+        assert_eq!(files.len(), 1, "substring={}", substring);
+        assert!(files[0]["path"].as_str().unwrap().ends_with("Gadget.cs"));
+        assert_eq!(files[0]["lines"], json!([4, 6, 7]), "substring={}", substring);
+        assert_eq!(files[0]["occurrences"], 3);
+        assert_eq!(output["summary"]["withinDefinition"]["lines"], "4-9");
+    }
+
+    let output = grep(json!({ "terms": "new Widget", "phrase": true, "withinDefinition": within }));
+    assert_eq!(output["files"][0]["lines"], json!([6]));
+
+    // `_inner` is declared on line 3, outside the constructor
+    let output = grep(json!({ "terms": "_inner", "withinDefinition": within, "countOnly": true }));
+    assert_eq!(output["summary"]["totalOccurrences"], 2);
+
+    // Errors: ambiguous name, unsupported combinations, no definition index
+    let error = |args: Value, expected: &str| {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(result.is_error, "{}", args);
+        assert!(result.content[0].text.contains(expected), "{}", result.content[0].text);
+    };
+    error(json!({ "terms": "widget", "withinDefinition": { "file": "Gadget.cs", "name": "Gadget" } }), "ambiguous");
+    error(json!({ "terms": "widget", "withinDefinition": { "file": "Gadget.cs" } }), "withinDefinition.name");
+    error(json!({ "terms": "widget", "estimate": true, "withinDefinition": within }), "estimate");
+    error(json!({ "terms": "widget", "rev": "HEAD", "withinDefinition": within }), "rev");
+    let (no_defs, _tmp2) = make_references_ctx(false);
+    let result = dispatch_tool(&no_defs, "search_grep", &json!({ "terms": "widget", "withinDefinition": within }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("Definition index not available"));
+}
+
+// ─── grep sortBy tests ──────────────────────────────────────────────
+
+#[test]
+fn test_apply_grep_sort_orders() {
+    use super::utils::{apply_grep_sort, GrepSort};
+    let tmp = tempfile::tempdir().unwrap();
+    let old = tmp.path().join("b_old.txt");
+    let new = tmp.path().join("a_new.txt");
+    std::fs::write(&old, "x").unwrap();
+    std::fs::write(&new, "x").unwrap();
+    let base = std::time::SystemTime::now();
+    std::fs::File::options().write(true).open(&old).unwrap()
+        .set_modified(base - std::time::Duration::from_secs(3600)).unwrap();
+    std::fs::File::options().write(true).open(&new).unwrap().set_modified(base).unwrap();
+
+    let old_s = old.to_string_lossy().to_string();
+    let new_s = new.to_string_lossy().to_string();
+    let missing = tmp.path().join("c_missing.txt").to_string_lossy().to_string();
+    let items = vec![(old_s.clone(), 5usize), (missing.clone(), 5), (new_s.clone(), 1)];
+
+    let mut by_path = items.clone();
+    apply_grep_sort(&mut by_path, GrepSort::Path, |r| r.0.as_str(), |r| r.1);
+    assert_eq!(by_path.iter().map(|r| r.0.as_str()).collect::<Vec<_>>(), vec![&new_s, &old_s, &missing]);
+
+    let mut by_matches = items.clone();
+    apply_grep_sort(&mut by_matches, GrepSort::Matches, |r| r.0.as_str(), |r| r.1);
+    // Equal counts fall back to path order
+    assert_eq!(by_matches.iter().map(|r| r.0.as_str()).collect::<Vec<_>>(), vec![&old_s, &missing, &new_s]);
+
+    let mut by_mtime = items.clone();
+    apply_grep_sort(&mut by_mtime, GrepSort::Mtime, |r| r.0.as_str(), |r| r.1);
+    assert_eq!(by_mtime.iter().map(|r| r.0.as_str()).collect::<Vec<_>>(), vec![&new_s, &old_s, &missing]);
+
+    let mut by_score = items.clone();
+    apply_grep_sort(&mut by_score, GrepSort::Score, |r| r.0.as_str(), |r| r.1);
+    assert_eq!(by_score, items);
+}
+
+#[test]
+fn test_grep_sort_by_path_in_all_modes() {
+    let (ctx, _tmp) = make_references_ctx(false);
+    for extra in [
+        json!({ "terms": "public,class" }),
+        json!({ "terms": "public,class", "substring": false }),
+        json!({ "terms": "public class", "phrase": true }),
+    ] {
+        let mut args = json!({ "sortBy": "path" });
+        for (k, v) in extra.as_object().unwrap() {
+            args[k] = v.clone();
+        }
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        let paths: Vec<&str> = output["files"].as_array().unwrap().iter()
+            .map(|f| f["path"].as_str().unwrap()).collect();
+        assert_eq!(paths.len(), 2, "{}", args);
+        assert!(paths[0].ends_with("Gadget.cs") && paths[1].ends_with("Widget.cs"), "{:?}", paths);
+        assert_eq!(output["summary"]["sortedBy"], "path");
+    }
+}
+
+#[test]
+fn test_grep_equal_scores_break_ties_by_path() {
+    let ctx = make_empty_ctx();
+    ctx.index.update(|idx| {
+        // file_ids deliberately not in path order; all files score identically
+        idx.files = vec!["src/z.cs".to_string(), "src/a.cs".to_string(), "src/m.cs".to_string()];
+        idx.file_token_counts = vec![10, 10, 10];
+        idx.total_tokens = 30;
+        idx.index.insert("widget".into(), (0..3).map(|file_id| Posting { file_id, lines: vec![1] }).collect());
+        idx.trigram_dirty = true;
+    });
+    for substring in [true, false] {
+        let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "widget", "substring": substring }));
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        let paths: Vec<&str> = output["files"].as_array().unwrap().iter()
+            .map(|f| f["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, vec!["src/a.cs", "src/m.cs", "src/z.cs"], "substring={}", substring);
+    }
+}
+
+#[test]
+fn test_cmp_ranked_orders_by_score_then_path_then_id() {
+    use super::utils::cmp_ranked;
+    let mut items = [(1.0, "b", 0u32), (2.0, "z", 5), (1.0, "a", 9), (1.0, "a", 3)];
+    items.sort_by(|x, y| cmp_ranked(*x, *y));
+    let order: Vec<(&str, u32)> = items.iter().map(|i| (i.1, i.2)).collect();
+    assert_eq!(order, vec![("z", 5), ("a", 3), ("a", 9), ("b", 0)]);
+}
+
+#[test]
+fn test_grep_sort_by_invalid_value() {
+    let (ctx, _tmp) = make_references_ctx(false);
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "widget", "sortBy": "size" }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("Invalid sortBy value 'size'"));
+}
+
+// ─── search_type_hierarchy tests ────────────────────────────────────
+
+#[test]
+fn test_search_type_hierarchy_both_directions() {
+    let (ctx, _tmp) = make_references_ctx(true);
+    let result = dispatch_tool(&ctx, "search_type_hierarchy", &json!({ "name": "Widget" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let root = &output["types"][0];
+    assert_eq!(root["kind"], "class");
+    assert!(root["file"].as_str().unwrap().ends_with("Widget.cs"));
+    assert_eq!(root["supertypes"].as_array().unwrap().len(), 0);
+    assert_eq!(root["subtypes"][0]["name"], "Gadget");
+    assert_eq!(output["summary"]["totalNodes"], 1);
+
+    let result = dispatch_tool(&ctx, "search_type_hierarchy", &json!({ "name": "gadget", "direction": "up" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["types"][0]["supertypes"][0]["name"], "Widget");
+    assert!(output["types"][0].get("subtypes").is_none());
+}
+
+#[test]
+fn test_search_type_hierarchy_unknown_type_is_external() {
+    let (ctx, _tmp) = make_references_ctx(true);
+    let result = dispatch_tool(&ctx, "search_type_hierarchy", &json!({ "name": "ControllerBase" }));
+    assert!(!result.is_error);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["types"][0]["external"], true);
+    assert_eq!(output["summary"]["totalNodes"], 0);
+    assert!(output["summary"]["hint"].as_str().unwrap().contains("ControllerBase"));
+}
+
+#[test]
+fn test_search_type_hierarchy_invalid_args() {
+    let (ctx, _tmp) = make_references_ctx(true);
+    assert!(dispatch_tool(&ctx, "search_type_hierarchy", &json!({})).is_error);
+    let result = dispatch_tool(&ctx, "search_type_hierarchy", &json!({ "name": "Widget", "depth": 0 }));
+    assert!(result.is_error && result.content[0].text.contains("depth must be >= 1"));
+    let result = dispatch_tool(&ctx, "search_type_hierarchy", &json!({ "name": "Widget", "direction": "sideways" }));
+    assert!(result.is_error && result.content[0].text.contains("Invalid direction"));
+}
+
+// ─── search_dependencies tests ──────────────────────────────────────
+
+#[test]
+fn test_search_dependencies_for_file_and_cycles() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Orders.cs"), "using Shop.Data;\nnamespace Shop.Orders;\npublic class OrderService { }\n").unwrap();
+    std::fs::write(tmp.path().join("Repo.cs"), "using Shop.Orders;\nnamespace Shop.Data;\npublic class OrderRepository { }\n").unwrap();
+    std::fs::write(tmp.path().join("Api.cs"), "using Shop.Orders;\nusing System.Linq;\nnamespace Shop.Api;\npublic class Api { }\n").unwrap();
+    let def_index = crate::definitions::build_definition_index(&crate::definitions::DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_empty_ctx();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+
+    let result = dispatch_tool(&ctx, "search_dependencies", &json!({ "file": "Orders.cs" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert!(output["dependsOn"][0]["file"].as_str().unwrap().ends_with("Repo.cs"));
+    assert_eq!(output["summary"]["dependents"], 2);
+    assert_eq!(output["summary"]["inCycle"], true);
+    assert_eq!(output["cycle"].as_array().unwrap().len(), 3);
+
+    let result = dispatch_tool(&ctx, "search_dependencies", &json!({ "file": "Api.cs", "direction": "dependsOn" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["external"], json!(["System.Linq"]));
+    assert!(output.get("dependents").is_none());
+    assert_eq!(output["summary"]["inCycle"], false);
+
+    let result = dispatch_tool(&ctx, "search_dependencies", &json!({}));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalCycles"], 1);
+    assert_eq!(output["cycles"][0]["size"], 2);
+
+    let result = dispatch_tool(&ctx, "search_dependencies", &json!({ "file": "Orders.cs", "direction": "sideways" }));
+    assert!(result.is_error && result.content[0].text.contains("Invalid direction"));
+}
+
+// ─── search_dupes tests ─────────────────────────────────────────────
+
+#[test]
+fn test_search_dupes_reports_pairs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let body = "    {\n        var sum = 0;\n        foreach (var item in items)\n            sum += item.Price * item.Quantity;\n        audit.Record(sum);\n        return sum;\n    }\n";
+    std::fs::write(tmp.path().join("Cart.cs"), format!("class Cart\n{{\n    int Total(List<Item> items)\n{}}}\n", body)).unwrap();
+    std::fs::write(tmp.path().join("Basket.cs"), format!("class Basket\n{{\n    int Total(List<Item> items)\n{}}}\n", body)).unwrap();
+    let def_index = crate::definitions::build_definition_index(&crate::definitions::DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_empty_ctx();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+
+    let result = dispatch_tool(&ctx, "search_dupes", &json!({}));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalPairs"], 1);
+    assert_eq!(output["summary"]["bodiesScanned"], 2);
+    let pair = &output["pairs"][0];
+    assert_eq!(pair["similarity"], 1.0);
+    assert!(pair["a"]["file"].as_str().unwrap().ends_with("Basket.cs"));
+    assert!(pair["b"]["file"].as_str().unwrap().ends_with("Cart.cs"));
+
+    let result = dispatch_tool(&ctx, "search_dupes", &json!({ "minLines": 100 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalPairs"], 0);
+    assert!(output["summary"]["hint"].is_string());
+}
+
+#[test]
+fn test_search_dupes_invalid_args() {
+    let mut ctx = make_empty_ctx();
+    ctx.def_index = Some(Arc::new(RwLock::new(crate::definitions::DefinitionIndex::default())));
+    let result = dispatch_tool(&ctx, "search_dupes", &json!({ "threshold": 1.5 }));
+    assert!(result.is_error && result.content[0].text.contains("threshold"));
+    let result = dispatch_tool(&ctx, "search_dupes", &json!({ "shingleSize": 0 }));
+    assert!(result.is_error && result.content[0].text.contains("shingleSize"));
+}
+
+// ─── search_semantic tests ──────────────────────────────────────────
+
+/// A context over a temp dir holding a payment service and a user store, with real
+/// content and definition indexes.
+fn make_semantic_ctx(tmp: &std::path::Path) -> HandlerContext {
+    std::fs::write(tmp.join("PaymentService.cs"), "class PaymentService\n{\n    async Task RetryFailedPaymentAsync(Payment payment)\n    {\n        for (var attempt = 0; attempt < MaxRetries; attempt++)\n            if (await gateway.Charge(payment)) return;\n    }\n\n    void Refund(Payment payment)\n    {\n        gateway.Refund(payment.Id);\n    }\n}\n").unwrap();
+    std::fs::write(tmp.join("UserStore.cs"), "class UserStore\n{\n    User Load(int id)\n    {\n        return db.Users.Find(id);\n    }\n}\n").unwrap();
+    let dir = tmp.to_string_lossy().to_string();
+    let content_index = crate::build_content_index(&crate::ContentIndexArgs {
+        dir: dir.clone(), ext: "cs".to_string(),
+        max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: 0,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: 0,
+        stop_token_pct: 0,
+        checkpoint_secs: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+    });
+    let def_index = crate::definitions::build_definition_index(&crate::definitions::DefIndexArgs {
+        dir: dir.clone(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_empty_ctx();
+    ctx.index = Searcher::new(content_index);
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    ctx.server_dir = dir;
+    ctx.index_base = tmp.join(".index");
+    ctx
+}
+
+#[test]
+fn test_search_semantic_ranks_definitions_and_refreshes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let ctx = make_semantic_ctx(tmp.path());
+
+    let result = dispatch_tool(&ctx, "search_semantic", &json!({ "query": "where do we retry failed payments" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["index"], "built");
+    assert_eq!(output["summary"]["chunks"], 5, "2 classes and 3 methods");
+    assert_eq!(output["summary"]["vectorSearch"], "exact", "too few chunks for IVF lists");
+    let top = &output["results"][0];
+    assert_eq!(top["name"], "PaymentService.RetryFailedPaymentAsync");
+    assert_eq!(top["lines"], "3-7");
+    assert!(top["semanticScore"].as_f64().unwrap() > 0.3);
+
+    let result = dispatch_tool(&ctx, "search_semantic", &json!({ "query": "load user", "file": "userstore" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["index"], "current");
+    assert!(output["results"].as_array().unwrap().iter().all(|r| r["file"].as_str().unwrap().ends_with("UserStore.cs")));
+
+    ctx.generation.bump();
+    let result = dispatch_tool(&ctx, "search_semantic", &json!({ "query": "refund" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!((&output["summary"]["index"], &output["summary"]["chunksEmbedded"]), (&json!("updated"), &json!(0)));
+    assert_eq!(output["results"][0]["name"], "PaymentService.Refund");
+
+    let result = dispatch_tool(&ctx, "search_semantic", &json!({ "query": "x", "keywordWeight": 2 }));
+    assert!(result.is_error && result.content[0].text.contains("keywordWeight"));
+}
+
+#[test]
+fn test_grep_rerank_fuses_lexical_and_semantic_ranks() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Invoice.cs"), "class Invoice\n{\n    decimal Total(Payment payment) => payment.Amount;\n}\n").unwrap();
+    let ctx = make_semantic_ctx(tmp.path());
+
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "payment,retry", "rerank": true }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["rerank"], json!({ "method": "rrf", "k": 60.0, "semanticIndex": "built" }));
+    let files = output["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    assert!(files[0]["path"].as_str().unwrap().ends_with("PaymentService.cs"));
+    let fused: Vec<f64> = files.iter().map(|f| f["rerank"]["fusedScore"].as_f64().unwrap()).collect();
+    assert!(fused[0] >= fused[1]);
+    let mut lexical: Vec<u64> = files.iter().map(|f| f["rerank"]["lexicalRank"].as_u64().unwrap()).collect();
+    lexical.sort();
+    assert_eq!(lexical, vec![1, 2]);
+    assert_eq!(files[0]["rerank"]["semanticRank"], 1);
+
+    for (args, error) in [
+        (json!({ "terms": "payment", "rerank": true, "phrase": true }), "phrase"),
+        (json!({ "terms": "payment", "rerank": true, "sortBy": "path" }), "sortBy=path"),
+    ] {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(result.is_error && result.content[0].text.contains(error), "{}", result.content[0].text);
+    }
+}
+
+// ─── relatedTerms tests ─────────────────────────────────────────────
+
+fn make_related_terms_ctx() -> HandlerContext {
+    let ctx = make_empty_ctx();
+    ctx.index.update(|idx| {
+        idx.files = (0..6).map(|i| format!("src/F{}.cs", i)).collect();
+        idx.file_token_counts = vec![10; 6];
+        idx.total_tokens = 60;
+        for (token, files) in [
+            ("userservice", vec![0u32, 1, 2]),
+            ("userservicefactory", vec![0, 1]),
+            ("logger", vec![0, 1, 4, 5]),
+            ("public", vec![0, 1, 2, 3, 4, 5]),
+            ("id", vec![0, 1]),
+            ("42", vec![0, 1]),
+            ("widget", vec![2, 3]),
+        ] {
+            idx.index.insert(token.into(), files.into_iter().map(|file_id| Posting { file_id, lines: vec![1] }).collect());
+        }
+        idx.trigram_dirty = true;
+    });
+    ctx
+}
+
+#[test]
+fn test_grep_related_terms() {
+    let ctx = make_related_terms_ctx();
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice", "substring": false, "relatedTerms": 5 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let related = output["summary"]["relatedTerms"].as_array().unwrap();
+    let terms: Vec<&str> = related.iter().map(|r| r["term"].as_str().unwrap()).collect();
+    // "public" is everywhere (IDF 0); "id"/"42" are too short / numeric; "widget" co-occurs only once
+    assert_eq!(terms, vec!["userservicefactory", "logger"]);
+    assert_eq!(related[0]["files"], 2);
+    assert_eq!(related[1]["docFreq"], 4);
+
+    // Substring mode: tokens containing the term are matches, not suggestions
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice", "relatedTerms": 5 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["relatedTerms"][0]["term"], "logger");
+    assert_eq!(output["summary"]["relatedTerms"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_grep_related_terms_off_by_default() {
+    let ctx = make_related_terms_ctx();
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert!(output["summary"].get("relatedTerms").is_none());
+
+    let index = ctx.index.snapshot();
+    // A single matched file lowers the co-occurrence floor to 1
+    let related = super::grep::related_terms(&index, &[3], &[], 10);
+    let terms: Vec<&str> = related.iter().map(|r| r["term"].as_str().unwrap()).collect();
+    assert_eq!(terms, vec!["widget"]);
+}
+
+#[test]
+fn test_grep_explain_breaks_down_scores() {
+    let ctx = make_related_terms_ctx();
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice,logger", "substring": false, "explain": true }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert!(output["summary"]["scoring"].as_str().unwrap().contains("idf"));
+    let top = &output["files"][0];
+    let explain = &top["explain"];
+    assert_eq!(explain["score"], top["score"]);
+    assert_eq!(explain["boosts"], json!([]));
+    let terms = explain["terms"].as_array().unwrap();
+    // logger is in 4 of 6 files, userservice in 3: the rarer token contributes more
+    assert_eq!(terms[0]["term"], "userservice");
+    assert_eq!(terms[0]["docFreq"], 3);
+    assert_eq!(terms[0]["tf"], 0.1);
+    assert_eq!(terms[0]["idf"], json!(((6.0f64 / 3.0).ln() * 10000.0).round() / 10000.0));
+    assert_eq!(terms[1]["term"], "logger");
+    assert!(terms[0].get("token").is_none());
+
+    // Substring mode names the matched token under its query term
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice", "explain": true }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let terms = output["files"][0]["explain"]["terms"].as_array().unwrap();
+    assert_eq!(terms.len(), 2);
+    assert!(terms.iter().any(|t| t["term"] == "userservice" && t["token"] == "userservicefactory"));
+
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice", "explain": false }));
+    assert!(!result.content[0].text.contains("\"explain\""));
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "user service", "phrase": true, "explain": true }));
+    assert!(result.is_error);
+}
+
+#[test]
+fn test_grep_boosts_file_name_and_definition_matches() {
+    let ctx = make_ranking_defs_ctx();
+    ctx.index.update(|idx| {
+        idx.files.push("C:\\src\\obj\\Helpers.g.cs".to_string());
+        idx.files.push("C:\\src\\Other.cs".to_string());
+        idx.file_token_counts = vec![10; 5];
+        idx.index.insert("userservice".into(), (0..4).map(|file_id| Posting { file_id, lines: vec![1] }).collect());
+        idx.index.insert("other".into(), vec![Posting { file_id: 4, lines: vec![1] }]);
+    });
+    let paths = |args: Value| -> Vec<String> {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        output["files"].as_array().unwrap().iter().map(|f| f["path"].as_str().unwrap().to_string()).collect()
+    };
+    // Equal TF-IDF: name (x2) and class UserService (x1.5) lead, generated code (x0.5) trails
+    assert_eq!(paths(json!({ "terms": "userservice", "substring": false })), vec![
+        "C:\\src\\UserService.cs", "C:\\src\\UserServiceFactory.cs", "C:\\src\\Helpers.cs", "C:\\src\\obj\\Helpers.g.cs",
+    ]);
+    assert_eq!(paths(json!({ "terms": "userservice", "substring": false, "boosts": false }))[0], "C:\\src\\Helpers.cs");
+
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "userservice", "substring": false, "explain": true }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let explain = &output["files"][0]["explain"];
+    let kinds: Vec<&str> = explain["boosts"].as_array().unwrap().iter().map(|b| b["kind"].as_str().unwrap()).collect();
+    assert_eq!(kinds, vec!["filename", "definition"]);
+    assert_eq!(explain["boosts"][1]["reason"], "defines UserService");
+    assert_eq!(explain["score"].as_f64().unwrap(), (explain["tfIdf"].as_f64().unwrap() * 3.0 * 10000.0).round() / 10000.0);
+}
+
+#[test]
+fn test_grep_skips_stop_tokens_in_multi_term_queries() {
+    let ctx = make_empty_ctx();
+    ctx.index.update(|idx| {
+        idx.files = (0..120).map(|i| format!("src/F{}.cs", i)).collect();
+        idx.file_token_counts = vec![10; 120];
+        idx.index.insert("public".into(), (0..120).map(|file_id| Posting { file_id, lines: vec![1] }).collect());
+        idx.index.insert("userservice".into(), vec![Posting { file_id: 3, lines: vec![2] }, Posting { file_id: 7, lines: vec![2] }]);
+        idx.stop_token_pct = 50;
+        idx.trigram_dirty = true;
+    });
+    let grep = |args: Value| -> Value {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+    let output = grep(json!({ "terms": "public,userservice", "substring": false }));
+    assert_eq!(output["summary"]["totalFiles"], 2);
+    assert_eq!(output["summary"]["termsSearched"], json!(["userservice"]));
+    assert!(output["summary"]["warnings"][0].as_str().unwrap().contains("'public' is in 100% of files"));
+
+    // AND mode keeps it: it still narrows the result, and ranks last by IDF
+    let output = grep(json!({ "terms": "public,userservice", "substring": false, "mode": "and" }));
+    assert_eq!(output["summary"]["totalFiles"], 2);
+    assert_eq!(output["summary"]["termsSearched"], json!(["public", "userservice"]));
+    assert!(output["summary"].get("warnings").is_none());
+
+    // Substring terms are never dropped: `public` also stands for longer tokens
+    let output = grep(json!({ "terms": "public,userservice", "countOnly": true }));
+    assert_eq!(output["summary"]["totalFiles"], 120);
+
+    // Alone, a stop token is still searched
+    let output = grep(json!({ "terms": "public", "substring": false, "countOnly": true }));
+    assert_eq!(output["summary"]["totalFiles"], 120);
+    assert!(output["summary"].get("warnings").is_none());
+
+    // Small indexes have no stop tokens
+    ctx.index.update(|idx| idx.files.truncate(99));
+    assert!(!ctx.index.snapshot().is_stop_token("public"));
+}
+
+// ─── search_vocabulary tests ────────────────────────────────────────
+
+#[test]
+fn test_search_vocabulary_orders_by_doc_freq() {
+    let ctx = make_related_terms_ctx();
+    let result = dispatch_tool(&ctx, "search_vocabulary", &json!({}));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    // "id" is too short, "42" is numeric; ties broken alphabetically
+    assert_eq!(output["vocabulary"], json!([
+        ["public", 6], ["logger", 4], ["userservice", 3], ["userservicefactory", 2], ["widget", 2]
+    ]));
+    assert_eq!(output["summary"]["totalMatching"], 5);
+
+    let result = dispatch_tool(&ctx, "search_vocabulary", &json!({ "prefix": "User", "limit": 1 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["vocabulary"], json!([["userservice", 3]]));
+    assert_eq!(output["summary"]["totalMatching"], 2);
+
+    let result = dispatch_tool(&ctx, "search_vocabulary", &json!({ "contains": "factory", "minLength": 20 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["vocabulary"], json!([]));
+    assert!(output["summary"]["hint"].is_string());
+}
+
+#[test]
+fn test_search_vocabulary_defined_only() {
+    let mut ctx = make_related_terms_ctx();
+    let result = dispatch_tool(&ctx, "search_vocabulary", &json!({ "definedOnly": true }));
+    assert!(result.is_error && result.content[0].text.contains("--definitions"));
+
+    let mut def_index = crate::definitions::DefinitionIndex::default();
+    def_index.name_index.insert("widget".to_string(), vec![0]);
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    let result = dispatch_tool(&ctx, "search_vocabulary", &json!({ "definedOnly": true }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["vocabulary"], json!([["widget", 2]]));
+
+    assert!(dispatch_tool(&ctx, "search_vocabulary", &json!({ "limit": 0 })).is_error);
+}
+
+// ─── search_todos tests ─────────────────────────────────────────────
+
+fn make_todos_ctx(root: &str, files: &[&str], markers: &[(u32, u32, &str)]) -> HandlerContext {
+    let ctx = make_empty_ctx();
+    ctx.index.update(|idx| {
+        idx.root = root.to_string();
+        idx.files = files.iter().map(|f| f.to_string()).collect();
+        idx.file_token_counts = vec![0; files.len()];
+        idx.comment_markers = markers.iter().map(|&(file_id, line, marker)| crate::CommentMarker {
+            file_id, line, marker: marker.to_string(), text: format!("{} at {}", marker.to_lowercase(), line),
+        }).collect();
+    });
+    ctx
+}
+
+#[test]
+fn test_search_todos_filters_by_marker_and_dir() {
+    let ctx = make_todos_ctx("/repo", &["/repo/src/b.rs", "/repo/src/a.rs", "/repo/tests/t.rs"],
+        &[(0, 9, "TODO"), (0, 3, "HACK"), (1, 40, "FIXME"), (2, 1, "TODO")]);
+    let result = dispatch_tool(&ctx, "search_todos", &json!({}));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let found: Vec<String> = output["todos"].as_array().unwrap().iter()
+        .map(|t| format!("{}:{} {}", t["file"].as_str().unwrap(), t["line"], t["marker"].as_str().unwrap()))
+        .collect();
+    // Sorted by path then line, not by file_id
+    assert_eq!(found, vec!["/repo/src/a.rs:40 FIXME", "/repo/src/b.rs:3 HACK", "/repo/src/b.rs:9 TODO", "/repo/tests/t.rs:1 TODO"]);
+    assert_eq!(output["summary"]["byMarker"], json!({ "FIXME": 1, "HACK": 1, "TODO": 2 }));
+    assert!(output["todos"][0].get("author").is_none());
+
+    let result = dispatch_tool(&ctx, "search_todos", &json!({ "marker": "todo,hack", "dir": "src", "maxResults": 1 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["todos"].as_array().unwrap().len(), 1);
+    assert_eq!(output["todos"][0]["line"], 3);
+    assert_eq!(output["summary"]["totalMatching"], 2);
+
+    let result = dispatch_tool(&ctx, "search_todos", &json!({ "excludeDir": ["src/"] }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalMatching"], 1);
+
+    assert!(dispatch_tool(&ctx, "search_todos", &json!({ "marker": "NOTE" })).is_error);
+    assert!(dispatch_tool(&ctx, "search_todos", &json!({ "sortBy": "age" })).is_error);
+    assert!(dispatch_tool(&ctx, "search_todos", &json!({ "minAgeDays": 10, "maxAgeDays": 5 })).is_error);
+
+    let empty = make_todos_ctx("/repo", &[], &[]);
+    let result = dispatch_tool(&empty, "search_todos", &json!({}));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert!(output["summary"]["hint"].as_str().unwrap().contains("rebuild"));
+}
+
+#[test]
+fn test_search_todos_blame_age_filters() {
+    use std::process::Command;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let root = crate::clean_path(&tmp.path().canonicalize().unwrap().to_string_lossy());
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).current_dir(&root)
+            .env("GIT_AUTHOR_DATE", "2020-01-15T12:00:00Z")
+            .env("GIT_COMMITTER_DATE", "2020-01-15T12:00:00Z")
+            .output().expect("git");
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    };
+    git(&["init", "-q"]);
+    std::fs::write(tmp.path().join("old.rs"), "fn a() {}\n// TODO: old debt\n").unwrap();
+    git(&["add", "."]);
+    git(&["-c", "user.name=Ada", "-c", "user.email=ada@example.com", "commit", "-q", "-m", "init"]);
+
+    let file = format!("{}/old.rs", root);
+    let ctx = make_todos_ctx(&root, &[&file], &[(0, 2, "TODO")]);
+    let result = dispatch_tool(&ctx, "search_todos", &json!({ "blame": true, "sortBy": "age" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let todo = &output["todos"][0];
+    assert_eq!(todo["author"], "Ada");
+    assert_eq!(todo["date"], "2020-01-15");
+    assert!(todo["ageDays"].as_u64().unwrap() > 365);
+    assert_eq!(output["summary"]["blamedMarkers"], 1);
+
+    for (args, expected) in [
+        (json!({ "minAgeDays": 365 }), 1),
+        (json!({ "maxAgeDays": 30 }), 0),
+        (json!({ "author": "ADA@example" }), 1),
+        (json!({ "author": "grace" }), 0),
+    ] {
+        let result = dispatch_tool(&ctx, "search_todos", &args);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(output["summary"]["totalMatching"], expected, "{}", args);
+    }
+}
+
+// ─── search_grep rev tests ──────────────────────────────────────────
+
+#[test]
+fn test_grep_rev_searches_historical_code() {
+    use std::process::Command;
+
+    let repo = tempfile::tempdir().unwrap();
+    let idx_base = tempfile::tempdir().unwrap();
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).current_dir(repo.path()).output().expect("git");
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    };
+    let commit = |msg: &str| git(&["-c", "user.name=Test", "-c", "user.email=t@example.com", "commit", "-qam", msg]);
+    git(&["init", "-q"]);
+    std::fs::write(repo.path().join("Client.cs"), "class Client {\n    LegacyHttpClient http;\n}\n").unwrap();
+    git(&["add", "."]);
+    commit("before");
+    std::fs::write(repo.path().join("Client.cs"), "class Client {\n    HttpClientFactory http;\n}\n").unwrap();
+    commit("after");
+
+    let mut ctx = make_empty_ctx();
+    ctx.server_dir = crate::clean_path(&repo.path().canonicalize().unwrap().to_string_lossy());
+    ctx.index_base = idx_base.path().to_path_buf();
+
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "LegacyHttpClient", "rev": "HEAD~1", "showLines": true }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalFiles"], 1);
+    assert_eq!(output["summary"]["revision"].as_str().unwrap().len(), 40);
+    assert!(output["summary"].get("branchWarning").is_none());
+    assert!(output["files"][0]["lineContent"].to_string().contains("LegacyHttpClient"));
+
+    // Same query on HEAD: the symbol is gone; token mode exercises the non-substring path
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "legacyhttpclient", "rev": "HEAD", "substring": false }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalFiles"], 0);
+
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "x", "rev": "no-such-rev" }));
+    assert!(result.is_error && result.content[0].text.contains("no-such-rev"));
+}
+
+// ─── Ownership annotations ──────────────────────────────────────────
+
+#[test]
+fn test_owner_annotations_and_filter() {
+    let output_source = |ctx: &HandlerContext| json!(ctx.owners.as_ref().unwrap().source);
+    let tmp = tempfile::tempdir().unwrap();
+    let codeowners = tmp.path().join("CODEOWNERS");
+    std::fs::write(&codeowners, "*  @org/platform\nProxy*.cs  @org/gateway @bob\n").unwrap();
+
+    let mut ctx = make_ctx_with_defs();
+    let result = dispatch_tool(&ctx, "search_grep", &json!({ "terms": "ExecuteQueryAsync", "owner": "gateway" }));
+    assert!(result.is_error && result.content[0].text.contains("ownership file"));
+
+    ctx.owners = Some(Arc::new(crate::owners::Owners::load(&codeowners, std::path::Path::new("C:/src")).unwrap()));
+    ctx.index.update(|idx| idx.trigram_dirty = true);
+    for substring in [true, false] {
+        let result = dispatch_tool(&ctx, "search_grep",
+            &json!({ "terms": "ExecuteQueryAsync", "owner": "@ORG/gateway", "substring": substring }));
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(output["summary"]["totalFiles"], 1, "substring={}", substring);
+        assert_eq!(output["files"][0]["owners"], json!(["@org/gateway", "@bob"]));
+        assert_eq!(output["summary"]["ownersSource"], output_source(&ctx));
+    }
+
+    let result = dispatch_tool(&ctx, "search_definitions", &json!({ "name": "ExecuteQueryAsync" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let defs = output["definitions"].as_array().unwrap();
+    assert!(defs.len() > 1);
+    assert!(defs.iter().all(|d| d["owners"].is_array()));
+    let result = dispatch_tool(&ctx, "search_definitions", &json!({ "name": "ExecuteQueryAsync", "owner": "platform" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert!(output["definitions"].as_array().unwrap().iter()
+        .all(|d| d["owners"] == json!(["@org/platform"]) && !d["file"].as_str().unwrap().contains("Proxy")));
+
+    let result = dispatch_tool(&ctx, "search_callers", &json!({ "method": "QueryInternalAsync", "class": "QueryService", "depth": 1 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["ownersSource"], output_source(&ctx));
+}
+
+// ─── search_hotspots ────────────────────────────────────────────────
+
+#[test]
+fn test_search_hotspots_ranks_churn_times_complexity() {
+    use crate::definitions::CodeStats;
+    use crate::git::cache::{parse_git_log_stream, GitHistoryCache};
+
+    let mut ctx = make_ctx_with_defs();
+    let result = dispatch_tool(&ctx, "search_hotspots", &json!({}));
+    assert!(result.is_error && result.content[0].text.contains("still being built"));
+
+    {
+        let mut idx = ctx.def_index.as_ref().unwrap().write().unwrap();
+        idx.root = "C:\\src".to_string();
+        for (def, cc) in [(1u32, 10u16), (3, 4), (6, 2), (7, 6)] {
+            idx.code_stats.insert(def, CodeStats { cyclomatic_complexity: cc, ..Default::default() });
+        }
+    }
+    let log = concat!(
+        "COMMIT:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa␞1700000000␞alice@example.com␞Alice␞Initial\n",
+        "ResilientClient.cs\nProxyClient.cs\n\n",
+        "COMMIT:bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb␞1700100000␞bob@example.com␞Bob␞Proxy retries\n",
+        "ProxyClient.cs\nQueryService.cs\n\n",
+        "COMMIT:cccccccccccccccccccccccccccccccccccccccc␞1700200000␞bob@example.com␞Bob␞Proxy fix\n",
+        "ProxyClient.cs\n\n",
+        "COMMIT:dddddddddddddddddddddddddddddddddddddddd␞1700300000␞alice@example.com␞Alice␞Batching\n",
+        "QueryService.cs\n\n",
+    );
+    let mut builder = GitHistoryCache::builder();
+    parse_git_log_stream(std::io::Cursor::new(log.as_bytes()), &mut builder).unwrap();
+    ctx.git_cache = Arc::new(RwLock::new(Some(GitHistoryCache::from_builder(builder, "d".repeat(40), "main".to_string()))));
+    ctx.git_cache_ready = Arc::new(AtomicBool::new(true));
+
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_hotspots", &json!({})).content[0].text).unwrap();
+    let ranked: Vec<(&str, u64)> = output["hotspots"].as_array().unwrap().iter()
+        .map(|h| (h["file"].as_str().unwrap(), h["score"].as_u64().unwrap())).collect();
+    assert_eq!(ranked, vec![("C:\\src\\QueryService.cs", 16), ("C:\\src\\ProxyClient.cs", 12), ("C:\\src\\ResilientClient.cs", 10)]);
+    assert_eq!(output["hotspots"][1]["topAuthors"][0], json!({ "name": "Bob", "email": "bob@example.com", "commits": 2 }));
+    assert_eq!(output["hotspots"][0]["methods"], 2);
+
+    // Method level: equal scores fall back to higher complexity
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_hotspots",
+        &json!({ "level": "method", "maxResults": 2, "topAuthors": 1 })).content[0].text).unwrap();
+    let names: Vec<&str> = output["hotspots"].as_array().unwrap().iter().map(|h| h["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["QueryInternalAsync", "ExecuteQueryAsync"]);
+    assert_eq!(output["hotspots"][1]["parent"], "ProxyClient");
+    assert_eq!(output["hotspots"][0]["topAuthors"].as_array().unwrap().len(), 1);
+    assert_eq!(output["summary"]["totalCandidates"], 4);
+
+    // Date window drops the initial commit, and with it ResilientClient
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_hotspots",
+        &json!({ "from": "2023-11-16" })).content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalCandidates"], 2);
+    assert_eq!(output["hotspots"][1]["churn"], 2);
+
+    assert!(dispatch_tool(&ctx, "search_hotspots", &json!({ "metric": "loc" })).is_error);
+}
+
+// ─── search_tickets tests ───────────────────────────────────────────
+
+#[test]
+fn test_search_tickets_maps_ticket_to_code_and_commits() {
+    use crate::git::cache::{parse_git_log_stream, GitHistoryCache};
+
+    let mut ctx = make_todos_ctx("/repo", &["/repo/src/pay.rs", "/repo/tests/pay_test.rs", "/repo/src/other.rs"], &[]);
+    ctx.index.update(|idx| {
+        idx.ticket_refs = [(0, 12, "PAY-812"), (0, 40, "PAY-812"), (1, 3, "PAY-812"), (2, 7, "#77")].iter()
+            .map(|&(file_id, line, ticket)| crate::TicketRef { file_id, line, ticket: ticket.to_string() })
+            .collect();
+    });
+    // Without a git cache the code side still answers
+    let result = dispatch_tool(&ctx, "search_tickets", &json!({ "ticket": "pay-812" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["ticket"], "PAY-812");
+    assert_eq!(output["code"], json!([
+        { "file": "/repo/src/pay.rs", "lines": [12, 40] },
+        { "file": "/repo/tests/pay_test.rs", "lines": [3] },
+    ]));
+    assert!(output["summary"]["commitsSkipped"].as_str().unwrap().contains("still being built"));
+
+    let log = concat!(
+        "COMMIT:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa␞1700000000␞alice@example.com␞Alice␞PAY-812: charge retries\n",
+        "src/pay.rs\n\n",
+        "COMMIT:bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb␞1700100000␞bob@example.com␞Bob␞Fix PAY-8120 rounding (#77)\n",
+        "src/other.rs\nsrc/pay.rs\n\n",
+        "COMMIT:cccccccccccccccccccccccccccccccccccccccc␞1700200000␞bob@example.com␞Bob␞Follow-up for PAY-812\n",
+        "tests/pay_test.rs\n\n",
+    );
+    let mut builder = GitHistoryCache::builder();
+    parse_git_log_stream(std::io::Cursor::new(log.as_bytes()), &mut builder).unwrap();
+    ctx.git_cache = Arc::new(RwLock::new(Some(GitHistoryCache::from_builder(builder, "c".repeat(40), "main".to_string()))));
+    ctx.git_cache_ready = Arc::new(AtomicBool::new(true));
+
+    // PAY-8120 is a different ticket; newest commit first
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_tickets",
+        &json!({ "ticket": "PAY-812", "dir": "src" })).content[0].text).unwrap();
+    assert_eq!(output["code"].as_array().unwrap().len(), 1);
+    let subjects: Vec<&str> = output["commits"].as_array().unwrap().iter().map(|c| c["subject"].as_str().unwrap()).collect();
+    assert_eq!(subjects, vec!["Follow-up for PAY-812", "PAY-812: charge retries"]);
+    assert_eq!(output["commits"][1]["files"], json!(["src/pay.rs"]));
+    assert!(output["summary"].get("commitsSkipped").is_none());
+
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_tickets",
+        &json!({ "ticket": "77" })).content[0].text).unwrap();
+    assert_eq!(output["ticket"], "#77");
+    assert_eq!(output["code"][0]["file"], "/repo/src/other.rs");
+    assert_eq!(output["commits"][0]["files"], json!(["src/other.rs", "src/pay.rs"]));
+
+    assert!(dispatch_tool(&ctx, "search_tickets", &json!({})).is_error);
+    assert!(dispatch_tool(&ctx, "search_tickets", &json!({ "ticket": "not a ticket" })).is_error);
+}
+
+#[test]
+fn test_search_density_buckets_hits_per_file() {
+    let ctx = make_todos_ctx("/repo", &["/repo/src/big.cs", "/repo/src/small.cs", "/repo/tests/t.cs"], &[]);
+    ctx.index.update(|idx| {
+        idx.index.insert("retry".into(), vec![
+            Posting { file_id: 0, lines: vec![5, 120, 130, 150, 199, 950] },
+            Posting { file_id: 1, lines: vec![3] },
+            Posting { file_id: 2, lines: vec![1, 2] },
+        ]);
+        idx.index.insert("backoff".into(), vec![Posting { file_id: 0, lines: vec![101] }]);
+    });
+    let result = dispatch_tool(&ctx, "search_density", &json!({ "terms": "Retry,backoff", "dir": "src", "topBuckets": 2 }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalFiles"], 2);
+    assert_eq!(output["summary"]["totalHits"], 8);
+    let big = &output["files"][0];
+    assert_eq!(big["path"], "/repo/src/big.cs");
+    assert_eq!(big["histogram"], json!([[1, 1], [101, 5], [901, 1]]));
+    assert_eq!(big["densest"][0], json!({ "startLine": 101, "endLine": 200, "hits": 5, "firstHit": 101, "lastHit": 199 }));
+    // Ties go to the earlier region
+    assert_eq!(big["densest"][1]["startLine"], 1);
+    assert_eq!(output["files"][1]["path"], "/repo/src/small.cs");
+
+    let output: Value = serde_json::from_str(&dispatch_tool(&ctx, "search_density",
+        &json!({ "terms": "retry", "file": "big.cs", "bucketSize": 50 })).content[0].text).unwrap();
+    assert_eq!(output["files"].as_array().unwrap().len(), 1);
+    assert_eq!(output["files"][0]["histogram"], json!([[1, 1], [101, 3], [151, 1], [901, 1]]));
+
+    assert!(dispatch_tool(&ctx, "search_density", &json!({ "terms": "retry", "bucketSize": 0 })).is_error);
+    assert!(dispatch_tool(&ctx, "search_density", &json!({})).is_error);
+}
+
+#[test]
+fn test_grep_dir_scope_uses_dir_bloom_filters() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(tmp.path()).unwrap();
+    std::fs::create_dir_all(root.join("Services")).unwrap();
+    std::fs::create_dir_all(root.join("Web")).unwrap();
+    std::fs::write(root.join("Services/Orders.cs"), "class OrderRepository {}\n").unwrap();
+    std::fs::write(root.join("Web/Page.cs"), "class UserRepository {}\n").unwrap();
+    let dir = crate::clean_path(&root.to_string_lossy());
+
+    let mut content_index = crate::build_content_index(&crate::ContentIndexArgs {
+        dir: dir.clone(), ext: "cs".to_string(),
+        max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, min_token_len: 2,
+        dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size: 0,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: 0,
+        stop_token_pct: 0,
+        checkpoint_secs: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+    });
+    assert!(!content_index.dir_may_contain(&format!("{}/Web", dir), "orderrepository"));
+    // An empty filter for Services: scoped queries must skip its postings entirely
+    content_index.dir_blooms.insert("services".to_string(), search::TokenBloom::with_capacity(1));
+    let mut ctx = make_empty_ctx();
+    ctx.index = Searcher::new(content_index);
+    ctx.server_dir = dir.clone();
+
+    let files = |args: Value| -> Vec<String> {
+        let result = dispatch_tool(&ctx, "search_grep", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        output["files"].as_array().unwrap().iter().map(|f| f["path"].as_str().unwrap().to_string()).collect()
+    };
+    let web = format!("{}/Web", dir);
+    let services = format!("{}/Services", dir);
+    assert_eq!(files(json!({ "terms": "userrepository", "substring": false, "dir": web })), vec![format!("{}/Page.cs", web)]);
+    assert_eq!(files(json!({ "terms": "repository", "dir": web })), vec![format!("{}/Page.cs", web)]);
+    assert!(files(json!({ "terms": "orderrepository", "substring": false, "dir": services })).is_empty());
+    assert!(files(json!({ "terms": "repository", "dir": services })).is_empty());
+    // Unscoped searches don't consult the filters
+    assert_eq!(files(json!({ "terms": "orderrepository", "substring": false })).len(), 1);
+}
+
+// ─── search_replace_preview tests ───────────────────────────────────
+
+#[test]
+fn test_search_replace_preview_diffs_without_writing() {
+    let (ctx, tmp_dir) = make_e2e_substring_ctx();
+    let before = std::fs::read_to_string(tmp_dir.join("Controller.cs")).unwrap();
+    let result = dispatch_tool(&ctx, "search_replace_preview",
+        &json!({ "pattern": "HttpClientHandler", "replacement": "SocketsHttpHandler", "contextLines": 0, "maxFiles": 1 }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalFiles"], 2);
+    assert_eq!(output["summary"]["candidateFiles"], 2, "Util.cs is ruled out by the index");
+    assert_eq!(output["summary"]["totalReplacements"], 2);
+    assert_eq!(output["files"].as_array().unwrap().len(), 1);
+    assert_eq!(output["files"][0]["diff"], "--- a/Controller.cs\n+++ b/Controller.cs\n@@ -4,1 +4,1 @@\n\
+        -        private readonly HttpClientHandler _client;\n+        private readonly SocketsHttpHandler _client;\n");
+    assert_eq!(std::fs::read_to_string(tmp_dir.join("Controller.cs")).unwrap(), before);
+
+    assert!(dispatch_tool(&ctx, "search_replace_preview", &json!({ "pattern": "(", "replacement": "", "regex": true })).is_error);
+    assert!(dispatch_tool(&ctx, "search_replace_preview", &json!({ "pattern": "x" })).is_error);
+    cleanup_tmp(&tmp_dir);
+}
@@ -4,12 +4,14 @@
 use super::*;
 use super::handlers_test_utils::{cleanup_tmp, make_ctx_with_defs};
 use crate::index::build_trigram_index;
-use crate::Posting;
+use crate::{ContentIndex, Posting};
+use crate::{SkippedFiles, TokenizerSettings};
 use crate::TrigramIndex;
+use crate::Token;
 use crate::definitions::*;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 // ─── Helpers ─────────────────────────────────────────────────────────
 
@@ -38,9 +40,9 @@ fn make_ctx_with_real_files() -> (HandlerContext, std::path::PathBuf) {
     let mut path_to_id: HashMap<PathBuf, u32> = HashMap::new();
     for (i, def) in definitions.iter().enumerate() { let idx = i as u32; name_index.entry(def.name.to_lowercase()).or_default().push(idx); kind_index.entry(def.kind).or_default().push(idx); file_index.entry(def.file_id).or_default().push(idx); }
     path_to_id.insert(file0_path, 0); path_to_id.insert(file1_path, 1);
-    let def_index = DefinitionIndex { root: tmp_dir.to_string_lossy().to_string(), created_at: 0, extensions: vec!["cs".to_string()], files: vec![file0_str.clone(), file1_str.clone()], definitions, name_index, kind_index, attribute_index: HashMap::new(), base_type_index: HashMap::new(), file_index, path_to_id, method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new() };
-    let content_index = ContentIndex { root: tmp_dir.to_string_lossy().to_string(), created_at: 0, max_age_secs: 3600, files: vec![file0_str, file1_str], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![0, 0], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(content_index)), def_index: Some(Arc::new(RwLock::new(def_index))), server_dir: tmp_dir.to_string_lossy().to_string(), server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let def_index = DefinitionIndex { root: tmp_dir.to_string_lossy().to_string(), created_at: 0, extensions: vec!["cs".to_string()], files: vec![file0_str.clone(), file1_str.clone()], definitions, name_index, kind_index, attribute_index: HashMap::new(), base_type_index: HashMap::new(), file_index, path_to_id, method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(), walk_limits: Default::default(), file_imports: HashMap::new(), di_registrations: Vec::new(), endpoints: Vec::new(), partial_types: HashMap::new(), doc_comments: HashMap::new(), imports: Vec::new() };
+    let content_index = ContentIndex { root: tmp_dir.to_string_lossy().to_string(), created_at: 0, max_age_secs: 3600, files: vec![file0_str, file1_str], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![0, 0], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(content_index), def_index: Some(Arc::new(RwLock::new(def_index))), server_dir: tmp_dir.to_string_lossy().to_string(), server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     (ctx, tmp_dir)
 }
 
@@ -69,6 +71,549 @@ fn test_search_callers_finds_callers() {
     assert!(output["summary"]["searchTimeMs"].as_f64().is_some());
 }
 
+#[test]
+fn test_search_callers_export_format_replaces_tree_with_graph() {
+    let ctx = make_ctx_with_defs();
+    let callers = |format: &str| -> Value {
+        let result = dispatch_tool(&ctx, "search_callers", &json!({
+            "method": "ExecuteQueryAsync", "depth": 2, "exportFormat": format
+        }));
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+    let output = callers("json");
+    assert!(output.get("callTree").is_none());
+    assert_eq!(output["query"]["exportFormat"], "json");
+    let edges = output["graph"]["edges"].as_array().unwrap();
+    assert!(!edges.is_empty());
+    // Up trees point from each caller to the method it calls
+    assert!(edges.iter().any(|e| e["to"] == "ExecuteQueryAsync"), "{:?}", edges);
+    assert!(output["graph"]["nodes"].as_array().unwrap().contains(&json!("ExecuteQueryAsync")));
+
+    let dot = callers("dot");
+    let dot = dot["graph"].as_str().unwrap();
+    assert!(dot.starts_with("digraph calls {") && dot.contains("-> \"ExecuteQueryAsync\""), "{}", dot);
+    assert!(callers("mermaid")["graph"].as_str().unwrap().starts_with("flowchart LR\n"));
+
+    let result = dispatch_tool(&ctx, "search_callers", &json!({ "method": "ExecuteQueryAsync", "exportFormat": "svg" }));
+    assert!(result.is_error);
+}
+
+#[test]
+fn test_search_di_registrations_lists_and_filters() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Program.cs"), "\
+var builder = WebApplication.CreateBuilder(args);
+builder.Services.AddScoped<IOrderService, OrderService>();
+builder.Services.AddSingleton<IClock, SystemClock>();
+builder.Services.AddScoped<IOrderStore>(sp => new SqlOrderStore());
+").unwrap();
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+
+    let result = dispatch_tool(&ctx, "search_di_registrations", &json!({}));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalRegistrations"], 3);
+    assert_eq!(output["summary"]["byLifetime"], json!({ "scoped": 2, "transient": 0, "singleton": 1 }));
+    let first = &output["registrations"][0];
+    assert_eq!((first["service"].as_str(), first["implementation"].as_str(), first["line"].as_u64()), (Some("IOrderService"), Some("OrderService"), Some(2)));
+    assert!(first["file"].as_str().unwrap().ends_with("Program.cs"));
+
+    let result = dispatch_tool(&ctx, "search_di_registrations", &json!({ "service": "order", "lifetime": "scoped" }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    assert_eq!(output["summary"]["totalRegistrations"], 2);
+    assert_eq!(output["registrations"][1]["implementation"], "SqlOrderStore");
+    let result = dispatch_tool(&ctx, "search_di_registrations", &json!({ "lifetime": "perRequest" }));
+    assert!(result.is_error && result.content[0].text.contains("Invalid lifetime"));
+}
+
+#[test]
+fn test_search_endpoints_resolves_path_to_handler() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("OrdersController.cs"), "\
+[Route(\"api/orders\")]
+public class OrdersController
+{
+    [HttpGet(\"{id}\")]
+    public Order Get(int id) => null;
+
+    [HttpGet(\"recent\")]
+    public Order[] Recent() => null;
+}
+").unwrap();
+    std::fs::write(tmp.path().join("Program.cs"), "\
+var app = builder.Build();
+app.MapPost(\"/api/orders/{id}/cancel\", OrderHandlers.Cancel);
+").unwrap();
+    std::fs::write(tmp.path().join("OrderHandlers.cs"), "\
+public static class OrderHandlers
+{
+    public static void Cancel(int id) { }
+}
+").unwrap();
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    let endpoints = |args: Value| -> Value {
+        let result = dispatch_tool(&ctx, "search_endpoints", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+
+    // The literal 'recent' is more specific than '{id}'
+    let output = endpoints(json!({ "path": "https://shop.example/api/orders/recent" }));
+    assert_eq!(output["summary"]["served"], 2);
+    assert_eq!(output["endpoints"][0]["handler"], "OrdersController.Recent");
+    assert_eq!(output["endpoints"][1]["route"], "/api/orders/{id}");
+
+    let output = endpoints(json!({ "path": "/api/orders/42/cancel", "method": "post" }));
+    assert_eq!(output["summary"]["totalEndpoints"], 1);
+    let cancel = &output["endpoints"][0];
+    assert!(cancel["file"].as_str().unwrap().ends_with("Program.cs"));
+    assert!(cancel["handlerFile"].as_str().unwrap().ends_with("OrderHandlers.cs"));
+    assert_eq!(cancel["handlerLines"], "3-3");
+
+    // A fragment of a template matches without serving the path
+    let output = endpoints(json!({ "path": "orders" }));
+    assert_eq!((output["summary"]["totalEndpoints"].as_u64(), output["summary"]["served"].as_u64()), (Some(3), Some(0)));
+}
+
+#[test]
+fn test_search_namespaces_and_qualified_definitions() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Invoice.cs"), "\
+namespace MyCompany.Billing
+{
+    public class InvoiceService
+    {
+        public void Create() { }
+    }
+
+    namespace Tax
+    {
+        public class TaxCalculator { }
+    }
+}
+").unwrap();
+    std::fs::write(tmp.path().join("Legacy.cs"), "\
+namespace MyCompany.Legacy;
+
+public class InvoiceService
+{
+    public void Create() { }
+}
+").unwrap();
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    let call = |tool: &str, args: Value| -> Value {
+        let result = dispatch_tool(&ctx, tool, &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+
+    let roots = call("search_namespaces", json!({ "depth": 3 }));
+    assert_eq!(roots["namespaces"][0]["name"], "MyCompany");
+    let children = &roots["namespaces"][0]["children"];
+    assert_eq!(children[0]["name"], "MyCompany.Billing");
+    assert_eq!(children[0]["children"][0]["name"], "MyCompany.Billing.Tax");
+    assert_eq!(children[1]["name"], "MyCompany.Legacy");
+    assert_eq!(roots["summary"]["totalNamespaces"], 4);
+
+    let billing = call("search_namespaces", json!({ "namespace": "mycompany.billing" }));
+    assert_eq!(billing["namespace"], "MyCompany.Billing");
+    assert_eq!(billing["types"][0]["name"], "InvoiceService");
+    assert_eq!(billing["summary"]["totalTypes"], 1);
+    let missing = dispatch_tool(&ctx, "search_namespaces", &json!({ "namespace": "Billing" }));
+    assert!(missing.is_error && missing.content[0].text.contains("MyCompany.Billing"));
+
+    // Qualified names pick one of the two InvoiceService classes
+    let defs = call("search_definitions", json!({ "name": "MyCompany.Billing.InvoiceService" }));
+    assert_eq!(defs["summary"]["totalResults"], 1);
+    assert_eq!(defs["definitions"][0]["kind"], "class");
+    assert_eq!(defs["definitions"][0]["namespace"], "MyCompany.Billing");
+    let defs = call("search_definitions", json!({ "name": "Legacy.InvoiceService.Create" }));
+    assert_eq!(defs["summary"]["totalResults"], 1);
+    assert!(defs["definitions"][0]["file"].as_str().unwrap().ends_with("Legacy.cs"));
+    let defs = call("search_definitions", json!({ "kind": "class", "namespace": "MyCompany.Billing" }));
+    let names: Vec<&str> = defs["definitions"].as_array().unwrap().iter().map(|d| d["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["InvoiceService", "TaxCalculator"]);
+}
+
+#[test]
+fn test_partial_class_is_one_type_in_definitions_and_hierarchy() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Form1.cs"), "\
+[DesignerCategory(\"Form\")]
+public partial class Form1 : Form
+{
+    public void Save() { }
+}
+").unwrap();
+    std::fs::write(tmp.path().join("Form1.Designer.cs"), "\
+public partial class Form1 : IValidatable
+{
+    private void InitializeComponent() { }
+}
+").unwrap();
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    let call = |tool: &str, args: Value| -> Value {
+        let result = dispatch_tool(&ctx, tool, &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+
+    let defs = call("search_definitions", json!({ "name": "Form1", "kind": "class" }));
+    assert_eq!(defs["summary"]["totalResults"], 1);
+    let form = &defs["definitions"][0];
+    assert_eq!(form["baseTypes"], json!(["IValidatable", "Form"]));
+    assert_eq!(form["attributes"], json!(["DesignerCategory(\"Form\")"]));
+    let parts: Vec<&str> = form["partialParts"].as_array().unwrap().iter().map(|p| p["file"].as_str().unwrap()).collect();
+    assert!(parts[0].ends_with("Form1.Designer.cs") && parts[1].ends_with("Form1.cs"));
+    // Members of every part
+    let members = call("search_definitions", json!({ "parent": "Form1" }));
+    assert_eq!(members["summary"]["totalResults"], 2);
+
+    let hierarchy = call("search_type_hierarchy", json!({ "name": "Form1", "direction": "up" }));
+    assert_eq!(hierarchy["types"].as_array().unwrap().len(), 1);
+    let supertypes: Vec<&str> = hierarchy["types"][0]["supertypes"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap()).collect();
+    assert_eq!(supertypes, ["IValidatable", "Form"]);
+    let subtypes = call("search_type_hierarchy", json!({ "name": "IValidatable", "direction": "down" }));
+    assert_eq!(subtypes["types"][0]["subtypes"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_search_callers_selects_overloads_by_arg_count_and_param_types() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("UserRepository.cs"), "\
+public class UserRepository<TKey>
+{
+    public User Get(TKey id) { return null; }
+    public User Get(string name, bool exact = false) { return null; }
+    public User Get(int id, string name, params string[] roles) { return null; }
+}
+").unwrap();
+    std::fs::write(tmp.path().join("UserController.cs"), "\
+public class UserController
+{
+    private readonly UserRepository<int> _repo;
+    public void ById() { _repo.Get(1); }
+    public void ByName() { _repo.Get(\"ann\", true); }
+    public void WithRoles() { _repo.Get(1, \"ann\", \"admin\", \"dev\"); }
+}
+").unwrap();
+    let dir = tmp.path().to_string_lossy().to_string();
+    let content_index = crate::build_content_index(&crate::ContentIndexArgs {
+        dir: dir.clone(), ext: "cs".to_string(), max_age_hours: 24, hidden: false, no_ignore: false,
+        threads: 1, min_token_len: 2, dry_run: false, build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0, watch: false, save_interval_secs: 60, max_file_size: 0, spill_mb: 0, shards: 0,
+        max_token_len: 0, stop_token_pct: 0, checkpoint_secs: 0, max_depth: 0, prune_dir: Vec::new(), storage: None,
+    });
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir, ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.index = Searcher::new(content_index);
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    let callers = |args: Value| -> Vec<String> {
+        let result = dispatch_tool(&ctx, "search_callers", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        output["callTree"].as_array().unwrap().iter().map(|n| n["method"].as_str().unwrap().to_string()).collect()
+    };
+
+    let mut all = callers(json!({ "method": "Get", "class": "UserRepository", "depth": 1 }));
+    all.sort();
+    assert_eq!(all, ["ById", "ByName", "WithRoles"]);
+    // Two arguments: the optional parameter of Get(string, bool)
+    assert_eq!(callers(json!({ "method": "Get", "class": "UserRepository", "depth": 1, "argCount": 2 })), ["ByName"]);
+    // The generic key parameter matches int; the params overload takes any number from two
+    assert_eq!(callers(json!({ "method": "Get", "class": "UserRepository", "depth": 1, "paramTypes": ["int"] })), ["ById"]);
+    assert_eq!(callers(json!({ "method": "Get", "class": "UserRepository", "depth": 1, "paramTypes": ["int", "string", "string[]"] })), ["WithRoles"]);
+
+    let result = dispatch_tool(&ctx, "search_callers", &json!({ "method": "Get", "class": "UserRepository", "paramTypes": ["Guid", "Guid"] }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("public User Get(string name, bool exact = false)"), "{}", result.content[0].text);
+
+    // Down: the call with one argument resolves to the one-parameter overload only
+    let result = dispatch_tool(&ctx, "search_callers", &json!({ "method": "ById", "class": "UserController", "direction": "down", "depth": 1 }));
+    let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+    let lines: Vec<u64> = output["callTree"].as_array().unwrap().iter().map(|n| n["line"].as_u64().unwrap()).collect();
+    assert_eq!(lines, [3]);
+}
+
+#[test]
+fn test_search_callers_matches_overloads_by_argument_types() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Lookup.cs"), "\
+public class Lookup
+{
+    public User Find(int id) { return null; }
+    public User Find(string name) { return null; }
+}
+").unwrap();
+    std::fs::write(tmp.path().join("LookupClient.cs"), "\
+public class LookupClient
+{
+    private readonly Lookup _lookup;
+    private readonly string _defaultName;
+    public void ByNumber() { _lookup.Find(42); }
+    public void ByText() { _lookup.Find(@\"ann\"); }
+    public void ByParameter(string name) { _lookup.Find(name); }
+    public void ByLocal() { int id = 7; _lookup.Find(id); }
+    public void ByVar() { var name = \"bob\"; _lookup.Find(name); }
+    public void ByField() { _lookup.Find(this._defaultName); }
+    public void ByCall() { _lookup.Find(Pick()); }
+    public void Wrapped()
+    {
+        _lookup.Find(
+            \"carol\");
+    }
+}
+").unwrap();
+    let dir = tmp.path().to_string_lossy().to_string();
+    let content_index = crate::build_content_index(&crate::ContentIndexArgs {
+        dir: dir.clone(), ext: "cs".to_string(), max_age_hours: 24, hidden: false, no_ignore: false,
+        threads: 1, min_token_len: 2, dry_run: false, build_priority: crate::priority::BuildPriority::Normal,
+        io_limit_mb: 0, watch: false, save_interval_secs: 60, max_file_size: 0, spill_mb: 0, shards: 0,
+        max_token_len: 0, stop_token_pct: 0, checkpoint_secs: 0, max_depth: 0, prune_dir: Vec::new(), storage: None,
+    });
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir, ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.index = Searcher::new(content_index);
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    let callers = |types: Value| -> (Vec<String>, Value) {
+        let result = dispatch_tool(&ctx, "search_callers", &json!({ "method": "Find", "class": "Lookup", "depth": 1, "paramTypes": types }));
+        assert!(!result.is_error, "{}", result.content[0].text);
+        let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
+        let mut methods: Vec<String> = output["callTree"].as_array().unwrap().iter().map(|n| n["method"].as_str().unwrap().to_string()).collect();
+        methods.sort();
+        (methods, output)
+    };
+
+    // A call whose argument type is not known fits either overload
+    let (methods, output) = callers(json!(["string"]));
+    assert_eq!(methods, ["ByCall", "ByField", "ByParameter", "ByText", "ByVar", "Wrapped"]);
+    assert!(output["query"]["overloadMatching"].as_str().unwrap().contains("argument type"));
+    assert_eq!(callers(json!(["int"])).0, ["ByCall", "ByLocal", "ByNumber"]);
+
+    for types in [json!("string"), json!(["string", 1])] {
+        let result = dispatch_tool(&ctx, "search_callers", &json!({ "method": "Find", "class": "Lookup", "paramTypes": types }));
+        assert!(result.is_error);
+        assert!(result.content[0].text.contains("paramTypes must be an array"), "{}", result.content[0].text);
+    }
+}
+
+#[test]
+fn test_search_definitions_docs_search_and_include_docs() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("RetryPolicy.cs"), "\
+public class RetryPolicy
+{
+    /// <summary>Waits longer after each failed attempt.</summary>
+    /// <param name=\"attempt\">Attempts so far.</param>
+    public TimeSpan Backoff(int attempt) { return TimeSpan.Zero; }
+
+    /// <summary>Gives up after the retry limit.</summary>
+    public bool ShouldRetry(int attempt) { return false; }
+}
+").unwrap();
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    let call = |args: Value| -> Value {
+        let result = dispatch_tool(&ctx, "search_definitions", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+
+    let found = call(json!({ "docs": "attempt LONGER" }));
+    assert_eq!(found["summary"]["totalResults"], 1);
+    assert_eq!(found["definitions"][0]["name"], "Backoff");
+    assert_eq!(found["definitions"][0]["docs"], json!({
+        "summary": "Waits longer after each failed attempt.",
+        "params": [{ "name": "attempt", "description": "Attempts so far." }],
+    }));
+
+    let methods = call(json!({ "parent": "RetryPolicy", "includeDocs": true }));
+    let docs: Vec<&Value> = methods["definitions"].as_array().unwrap().iter().map(|d| &d["docs"]["summary"]).collect();
+    assert_eq!(docs, [&json!("Waits longer after each failed attempt."), &json!("Gives up after the retry limit.")]);
+    assert!(call(json!({ "parent": "RetryPolicy" }))["definitions"][0].get("docs").is_none());
+}
+
+#[test]
+fn test_search_definitions_halstead_and_class_metrics() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("Billing.cs"), "\
+public class Invoice
+{
+    private decimal _total;
+    public void Add(decimal amount) { _total += amount; }
+    public decimal Total() { return _total; }
+}
+
+public class Ledger
+{
+    private int _entries;
+    private string _name;
+    public void Post() { _entries++; }
+    public string Rename(string name, bool trim) { if (trim) { name = name.Trim(); } _name = name.ToUpper() + \"!\"; return _name; }
+}
+").unwrap();
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+    let call = |args: Value| -> Value {
+        let result = dispatch_tool(&ctx, "search_definitions", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+    let names = |output: &Value| -> Vec<String> {
+        output["definitions"].as_array().unwrap().iter().map(|d| d["name"].as_str().unwrap().to_string()).collect()
+    };
+
+    // Least maintainable first
+    let by_mi = call(json!({ "sortBy": "maintainabilityIndex" }));
+    assert_eq!(names(&by_mi)[0], "Rename");
+    let stats = &by_mi["definitions"][0]["codeStats"];
+    assert!(stats["halsteadVolume"].as_f64().unwrap() > 0.0);
+    let worst = stats["maintainabilityIndex"].as_u64().unwrap();
+    assert_eq!(names(&call(json!({ "maxMaintainabilityIndex": worst }))), ["Rename"]);
+    assert_eq!(names(&call(json!({ "minHalsteadVolume": 1000 }))), Vec::<String>::new());
+
+    // Ledger's methods share nothing: LCOM 2, and the higher WMC
+    let classes = call(json!({ "sortBy": "lcom" }));
+    assert_eq!(names(&classes), ["Ledger", "Invoice"]);
+    assert_eq!(classes["definitions"][0]["codeStats"]["lcom"], 2);
+    assert_eq!(classes["definitions"][0]["codeStats"]["wmc"], 3);
+    assert_eq!(classes["definitions"][1]["codeStats"]["lcom"], 1);
+    assert_eq!(names(&call(json!({ "minLcom": 2 }))), ["Ledger"]);
+
+    let mixed = dispatch_tool(&ctx, "search_definitions", &json!({ "minWmc": 2, "minComplexity": 2 }));
+    assert!(mixed.is_error);
+}
+
+#[test]
+fn test_search_impact_walks_callers_up_to_tests() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::create_dir(tmp.path().join("src")).unwrap();
+    std::fs::create_dir(tmp.path().join("tests")).unwrap();
+    std::fs::write(tmp.path().join("src/OrderService.cs"), "\
+public interface IOrderService { void Place(int id); }
+public class OrderService : IOrderService
+{
+    public void Place(int id) { Validate(id); }
+    private void Validate(int id) { }
+}
+public class OrderController
+{
+    private readonly IOrderService _orders;
+    public void Create(int id) { _orders.Place(id); }
+}
+").unwrap();
+    std::fs::write(tmp.path().join("tests/OrderTests.cs"), "\
+public class OrderTests
+{
+    private readonly OrderController _controller;
+    private readonly OrderService _service;
+    [Fact]
+    public void Create_places_order() { _controller.Create(1); }
+    [Test]
+    public void Place_validates() { _service.Place(1); }
+}
+").unwrap();
+    let def_index = build_definition_index(&DefIndexArgs {
+        dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+        build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+        max_depth: 0,
+        prune_dir: Vec::new(),
+        storage: None,
+        import: Vec::new(),
+    });
+    let mut ctx = make_ctx_with_defs();
+    ctx.def_index = Some(Arc::new(RwLock::new(def_index)));
+
+    let impact = |args: Value| -> Value {
+        let result = dispatch_tool(&ctx, "search_impact", &args);
+        assert!(!result.is_error, "{}", result.content[0].text);
+        serde_json::from_str(&result.content[0].text).unwrap()
+    };
+    let output = impact(json!({ "method": "Validate", "class": "OrderService" }));
+    let tests: Vec<(&str, u64)> = output["tests"].as_array().unwrap().iter()
+        .map(|t| (t["name"].as_str().unwrap(), t["depth"].as_u64().unwrap()))
+        .collect();
+    // Nearest first; the controller is reached through IOrderService
+    assert_eq!(tests, [("OrderTests.Place_validates", 2), ("OrderTests.Create_places_order", 3)]);
+    assert_eq!(output["tests"][1]["via"], json!(["OrderController.Create", "OrderService.Place", "OrderService.Validate"]));
+    assert_eq!(output["summary"]["testFiles"], 1);
+
+    let output = impact(json!({ "method": "Validate", "class": "OrderService", "depth": 2 }));
+    assert_eq!(output["summary"]["totalTests"], 1);
+    assert_eq!(output["summary"]["truncated"], true);
+    // Neither the attributes nor the paths given match: no tests
+    let output = impact(json!({ "method": "Place", "testAttributes": ["Benchmark"], "testPaths": ["specs/"] }));
+    assert_eq!(output["summary"]["totalTests"], 0);
+    assert!(dispatch_tool(&ctx, "search_impact", &json!({})).is_error);
+}
+
 #[test]
 fn test_search_callers_nonexistent_method() {
     let ctx = make_ctx_with_defs();
@@ -129,17 +674,17 @@ fn test_search_callers_has_class_and_file() {
 #[test]
 fn test_search_callers_field_prefix_m_underscore() {
     let mut content_idx = HashMap::new();
-    content_idx.insert("submitasync".to_string(), vec![
+    content_idx.insert("submitasync".into(), vec![
         Posting { file_id: 0, lines: vec![45] },
         Posting { file_id: 1, lines: vec![30] },
     ]);
-    content_idx.insert("orderprocessor".to_string(), vec![
+    content_idx.insert("orderprocessor".into(), vec![
         Posting { file_id: 0, lines: vec![1, 45] },
     ]);
-    content_idx.insert("m_orderprocessor".to_string(), vec![
+    content_idx.insert("m_orderprocessor".into(), vec![
         Posting { file_id: 1, lines: vec![5, 30] },
     ]);
-    content_idx.insert("checkouthandler".to_string(), vec![
+    content_idx.insert("checkouthandler".into(), vec![
         Posting { file_id: 1, lines: vec![1] },
     ]);
 
@@ -155,6 +700,15 @@ fn test_search_callers_field_prefix_m_underscore() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![100, 100],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -201,6 +755,10 @@ fn test_search_callers_field_prefix_m_underscore() {
         receiver_type: Some("OrderProcessor".to_string()),
         line: 30,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
 
     let def_index = DefinitionIndex {
@@ -213,16 +771,32 @@ fn test_search_callers_field_prefix_m_underscore() {
         definitions, name_index, kind_index,
         attribute_index: HashMap::new(), base_type_index: HashMap::new(),
         file_index, path_to_id, method_calls, code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)),
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_callers", &json!({
@@ -243,14 +817,14 @@ fn test_search_callers_field_prefix_m_underscore() {
 #[test]
 fn test_search_callers_field_prefix_underscore() {
     let mut content_idx = HashMap::new();
-    content_idx.insert("getuserasync".to_string(), vec![
+    content_idx.insert("getuserasync".into(), vec![
         Posting { file_id: 0, lines: vec![15] },
         Posting { file_id: 1, lines: vec![15] },
     ]);
-    content_idx.insert("userservice".to_string(), vec![
+    content_idx.insert("userservice".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
     ]);
-    content_idx.insert("_userservice".to_string(), vec![
+    content_idx.insert("_userservice".into(), vec![
         Posting { file_id: 1, lines: vec![3, 15] },
     ]);
 
@@ -263,6 +837,15 @@ fn test_search_callers_field_prefix_underscore() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50, 50],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -309,6 +892,10 @@ fn test_search_callers_field_prefix_underscore() {
         receiver_type: Some("UserService".to_string()),
         line: 15,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
 
     let def_index = DefinitionIndex {
@@ -318,16 +905,32 @@ fn test_search_callers_field_prefix_underscore() {
         definitions, name_index, kind_index,
         attribute_index: HashMap::new(), base_type_index: HashMap::new(),
         file_index, path_to_id, method_calls, code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)),
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_callers", &json!({
@@ -369,6 +972,15 @@ fn test_search_callers_multi_ext_filter() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&multi_ext_ctx, "search_callers", &json!({
@@ -507,6 +1119,13 @@ fn test_resolve_call_site_with_class_scope() {
         file_index,
         path_to_id: HashMap::new(),
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let call_a = CallSite {
@@ -514,6 +1133,10 @@ fn test_resolve_call_site_with_class_scope() {
         receiver_type: Some("ServiceA".to_string()),
         line: 5,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             };
     let resolved_a = resolve_call_site(&call_a, &def_index, None);
     assert_eq!(resolved_a.len(), 1);
@@ -524,6 +1147,10 @@ fn test_resolve_call_site_with_class_scope() {
         receiver_type: Some("ServiceB".to_string()),
         line: 10,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             };
     let resolved_b = resolve_call_site(&call_b, &def_index, None);
     assert_eq!(resolved_b.len(), 1);
@@ -534,6 +1161,10 @@ fn test_resolve_call_site_with_class_scope() {
         receiver_type: None,
         line: 15,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             };
     let resolved_none = resolve_call_site(&call_no_recv, &def_index, None);
     assert_eq!(resolved_none.len(), 2);
@@ -543,6 +1174,10 @@ fn test_resolve_call_site_with_class_scope() {
         receiver_type: Some("IService".to_string()),
         line: 20,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             };
     let resolved_iface = resolve_call_site(&call_iface, &def_index, None);
     assert!(!resolved_iface.is_empty());
@@ -575,8 +1210,8 @@ fn test_search_callers_down_class_filter() {
     }
 
     let mut method_calls: HashMap<u32, Vec<CallSite>> = HashMap::new();
-    method_calls.insert(1, vec![CallSite { method_name: "ShouldIssueVectorSearch".to_string(), receiver_type: None, line: 780, receiver_is_generic: false }]);
-    method_calls.insert(4, vec![CallSite { method_name: "TraceInformation".to_string(), receiver_type: None, line: 333, receiver_is_generic: false }]);
+    method_calls.insert(1, vec![CallSite { method_name: "ShouldIssueVectorSearch".to_string(), receiver_type: None, line: 780, receiver_is_generic: false, column: 0, end_column: 0, receiver: None, arg_count: None }]);
+    method_calls.insert(4, vec![CallSite { method_name: "TraceInformation".to_string(), receiver_type: None, line: 333, receiver_is_generic: false, column: 0, end_column: 0, receiver: None, arg_count: None }]);
 
     let mut path_to_id: HashMap<PathBuf, u32> = HashMap::new();
     path_to_id.insert(PathBuf::from("C:\\src\\IndexSearchService.cs"), 0);
@@ -589,6 +1224,13 @@ fn test_search_callers_down_class_filter() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let content_index = ContentIndex {
@@ -597,16 +1239,34 @@ fn test_search_callers_down_class_filter() {
         index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()],
         file_token_counts: vec![100, 100], trigram: TrigramIndex::default(),
         trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)),
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_callers", &json!({ "method": "SearchInternalAsync", "class": "IndexSearchService", "direction": "down", "depth": 1 }));
@@ -639,7 +1299,7 @@ fn test_search_callers_down_class_filter() {
 fn test_search_callers_ambiguity_warning_truncated() {
     // Create 15 classes each with a method named "OnInit" — exceeds MAX_LISTED (10)
     let num_classes = 15;
-    let mut content_idx: HashMap<String, Vec<Posting>> = HashMap::new();
+    let mut content_idx: HashMap<Token, Vec<Posting>> = HashMap::new();
     let mut files: Vec<String> = Vec::new();
     let mut definitions: Vec<DefinitionEntry> = Vec::new();
 
@@ -662,7 +1322,7 @@ fn test_search_callers_ambiguity_warning_truncated() {
             modifiers: vec![], attributes: vec![], base_types: vec![],
         });
 
-        content_idx.entry("oninit".to_string()).or_default().push(
+        content_idx.entry("oninit".into()).or_default().push(
             Posting { file_id: i as u32, lines: vec![10] }
         );
     }
@@ -690,6 +1350,15 @@ fn test_search_callers_ambiguity_warning_truncated() {
         file_token_counts: vec![50; num_classes],
         trigram: TrigramIndex::default(), trigram_dirty: false,
         forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let def_index = DefinitionIndex {
@@ -702,10 +1371,17 @@ fn test_search_callers_ambiguity_warning_truncated() {
         base_type_index: HashMap::new(),
         file_index, path_to_id,
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(),
         server_ext: "ts".to_string(),
@@ -715,6 +1391,15 @@ fn test_search_callers_ambiguity_warning_truncated() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_callers", &json!({ "method": "OnInit" }));
@@ -734,7 +1419,7 @@ fn test_search_callers_ambiguity_warning_few_classes() {
     // Create 3 classes each with a method named "Initialize" — within MAX_LISTED (10)
     // When called without `class` param, should get a warning listing ALL 3 classes.
     let num_classes = 3;
-    let mut content_idx: HashMap<String, Vec<Posting>> = HashMap::new();
+    let mut content_idx: HashMap<Token, Vec<Posting>> = HashMap::new();
     let mut files: Vec<String> = Vec::new();
     let mut definitions: Vec<DefinitionEntry> = Vec::new();
 
@@ -755,7 +1440,7 @@ fn test_search_callers_ambiguity_warning_few_classes() {
             modifiers: vec![], attributes: vec![], base_types: vec![],
         });
 
-        content_idx.entry("initialize".to_string()).or_default().push(
+        content_idx.entry("initialize".into()).or_default().push(
             Posting { file_id: i as u32, lines: vec![10] }
         );
     }
@@ -782,6 +1467,15 @@ fn test_search_callers_ambiguity_warning_few_classes() {
         file_token_counts: vec![50; num_classes],
         trigram: TrigramIndex::default(), trigram_dirty: false,
         forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let def_index = DefinitionIndex {
@@ -794,10 +1488,17 @@ fn test_search_callers_ambiguity_warning_few_classes() {
         base_type_index: HashMap::new(),
         file_index, path_to_id,
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -809,6 +1510,15 @@ fn test_search_callers_ambiguity_warning_few_classes() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // No `class` param → should produce a warning listing all 3 classes
@@ -832,7 +1542,7 @@ fn test_search_callers_ambiguity_warning_few_classes() {
 #[test]
 fn test_search_callers_no_ambiguity_warning_with_class_param() {
     // Same setup as above (3 classes with "Initialize") but WITH `class` param → no warning.
-    let mut content_idx: HashMap<String, Vec<Posting>> = HashMap::new();
+    let mut content_idx: HashMap<Token, Vec<Posting>> = HashMap::new();
     let mut files: Vec<String> = Vec::new();
     let mut definitions: Vec<DefinitionEntry> = Vec::new();
 
@@ -853,10 +1563,10 @@ fn test_search_callers_no_ambiguity_warning_with_class_param() {
             modifiers: vec![], attributes: vec![], base_types: vec![],
         });
 
-        content_idx.entry("initialize".to_string()).or_default().push(
+        content_idx.entry("initialize".into()).or_default().push(
             Posting { file_id: i as u32, lines: vec![10] }
         );
-        content_idx.entry(class_name.to_lowercase()).or_default().push(
+        content_idx.entry(class_name.to_lowercase().into()).or_default().push(
             Posting { file_id: i as u32, lines: vec![1, 10] }
         );
     }
@@ -883,6 +1593,15 @@ fn test_search_callers_no_ambiguity_warning_with_class_param() {
         file_token_counts: vec![50; 3],
         trigram: TrigramIndex::default(), trigram_dirty: false,
         forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let def_index = DefinitionIndex {
@@ -895,10 +1614,17 @@ fn test_search_callers_no_ambiguity_warning_with_class_param() {
         base_type_index: HashMap::new(),
         file_index, path_to_id,
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(),
         server_ext: "cs".to_string(),
@@ -910,6 +1636,15 @@ fn test_search_callers_no_ambiguity_warning_with_class_param() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // WITH `class` param → should NOT produce a warning
@@ -944,12 +1679,12 @@ fn test_search_callers_exclude_dir_and_file() {
     // Set up: MethodA is defined in ServiceA (dir: src\services)
     // MethodA is called from ControllerB (dir: src\controllers) and from TestC (dir: src\tests)
     let mut content_idx = HashMap::new();
-    content_idx.insert("methoda".to_string(), vec![
+    content_idx.insert("methoda".into(), vec![
         Posting { file_id: 0, lines: vec![10] },
         Posting { file_id: 1, lines: vec![25] },
         Posting { file_id: 2, lines: vec![15] },
     ]);
-    content_idx.insert("servicea".to_string(), vec![
+    content_idx.insert("servicea".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
         Posting { file_id: 1, lines: vec![5] },
         Posting { file_id: 2, lines: vec![3] },
@@ -966,6 +1701,15 @@ fn test_search_callers_exclude_dir_and_file() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![100, 100, 100],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1031,10 +1775,17 @@ fn test_search_callers_exclude_dir_and_file() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1044,6 +1795,15 @@ fn test_search_callers_exclude_dir_and_file() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Test excludeDir: exclude "tests" directory
@@ -1131,12 +1891,20 @@ fn test_search_callers_cycle_detection_down() {
         receiver_type: Some("ClassB".to_string()),
         line: 20,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
     method_calls.insert(3, vec![CallSite {
         method_name: "MethodA".to_string(),
         receiver_type: Some("ClassA".to_string()),
         line: 20,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
 
     let def_index = DefinitionIndex {
@@ -1148,6 +1916,13 @@ fn test_search_callers_cycle_detection_down() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let content_index = ContentIndex {
@@ -1157,10 +1932,19 @@ fn test_search_callers_cycle_detection_down() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50, 50],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1170,6 +1954,15 @@ fn test_search_callers_cycle_detection_down() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // direction=down with depth=5 — cycle should be stopped by visited set
@@ -1270,6 +2063,15 @@ fn test_search_definitions_exclude_dir() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50, 50],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1322,10 +2124,17 @@ fn test_search_definitions_exclude_dir() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1335,6 +2144,15 @@ fn test_search_definitions_exclude_dir() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Exclude "tests" directory
@@ -1405,6 +2223,15 @@ fn test_search_definitions_struct_kind() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1452,10 +2279,17 @@ fn test_search_definitions_struct_kind() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1465,6 +2299,15 @@ fn test_search_definitions_struct_kind() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_definitions", &json!({
@@ -1494,6 +2337,15 @@ fn test_search_definitions_base_type_filter() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1548,10 +2400,17 @@ fn test_search_definitions_base_type_filter() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1561,6 +2420,15 @@ fn test_search_definitions_base_type_filter() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Filter by baseType=ControllerBase — should return UserController and AdminController
@@ -1604,6 +2472,15 @@ fn test_search_definitions_enum_member_kind() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1664,10 +2541,17 @@ fn test_search_definitions_enum_member_kind() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1677,6 +2561,15 @@ fn test_search_definitions_enum_member_kind() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Filter by kind=enumMember
@@ -1776,9 +2669,9 @@ fn test_search_definitions_enum_member_kind() {
     let definitions = vec![DefinitionEntry { file_id: 0, name: "StaleClass".to_string(), kind: DefinitionKind::Class, line_start: 5, line_end: 20, parent: None, signature: None, modifiers: vec![], attributes: vec![], base_types: vec![] }];
     let mut ni: HashMap<String, Vec<u32>> = HashMap::new(); let mut ki: HashMap<DefinitionKind, Vec<u32>> = HashMap::new(); let mut fi: HashMap<u32, Vec<u32>> = HashMap::new();
     for (i, def) in definitions.iter().enumerate() { ni.entry(def.name.to_lowercase()).or_default().push(i as u32); ki.entry(def.kind).or_default().push(i as u32); fi.entry(def.file_id).or_default().push(i as u32); }
-    let di = DefinitionIndex { root: tmp.to_string_lossy().to_string(), created_at: 0, extensions: vec!["cs".to_string()], files: vec![fs.clone()], definitions, name_index: ni, kind_index: ki, attribute_index: HashMap::new(), base_type_index: HashMap::new(), file_index: fi, path_to_id: HashMap::new(), method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new() };
-    let ci = ContentIndex { root: tmp.to_string_lossy().to_string(), created_at: 0, max_age_secs: 3600, files: vec![fs], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![0], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(ci)), def_index: Some(Arc::new(RwLock::new(di))), server_dir: tmp.to_string_lossy().to_string(), server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let di = DefinitionIndex { root: tmp.to_string_lossy().to_string(), created_at: 0, extensions: vec!["cs".to_string()], files: vec![fs.clone()], definitions, name_index: ni, kind_index: ki, attribute_index: HashMap::new(), base_type_index: HashMap::new(), file_index: fi, path_to_id: HashMap::new(), method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(), walk_limits: Default::default(), file_imports: HashMap::new(), di_registrations: Vec::new(), endpoints: Vec::new(), partial_types: HashMap::new(), doc_comments: HashMap::new(), imports: Vec::new() };
+    let ci = ContentIndex { root: tmp.to_string_lossy().to_string(), created_at: 0, max_age_secs: 3600, files: vec![fs], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![0], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(ci), def_index: Some(Arc::new(RwLock::new(di))), server_dir: tmp.to_string_lossy().to_string(), server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     let result = dispatch_tool(&ctx, "search_definitions", &json!({"name": "StaleClass", "includeBody": true}));
     let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
     assert!(output["definitions"].as_array().unwrap()[0].get("bodyWarning").is_some());
@@ -1790,9 +2683,9 @@ fn test_search_definitions_enum_member_kind() {
     let mut ni: HashMap<String, Vec<u32>> = HashMap::new(); let mut ki: HashMap<DefinitionKind, Vec<u32>> = HashMap::new(); let mut fi: HashMap<u32, Vec<u32>> = HashMap::new();
     for (i, def) in definitions.iter().enumerate() { ni.entry(def.name.to_lowercase()).or_default().push(i as u32); ki.entry(def.kind).or_default().push(i as u32); fi.entry(def.file_id).or_default().push(i as u32); }
     let ne = "C:\\nonexistent\\path\\Ghost.cs".to_string();
-    let di = DefinitionIndex { root: ".".to_string(), created_at: 0, extensions: vec!["cs".to_string()], files: vec![ne.clone()], definitions, name_index: ni, kind_index: ki, attribute_index: HashMap::new(), base_type_index: HashMap::new(), file_index: fi, path_to_id: HashMap::new(), method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new() };
-    let ci = ContentIndex { root: ".".to_string(), created_at: 0, max_age_secs: 3600, files: vec![ne], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![0], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None };
-    let ctx = HandlerContext { index: Arc::new(RwLock::new(ci)), def_index: Some(Arc::new(RwLock::new(di))), server_dir: ".".to_string(), server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None };
+    let di = DefinitionIndex { root: ".".to_string(), created_at: 0, extensions: vec!["cs".to_string()], files: vec![ne.clone()], definitions, name_index: ni, kind_index: ki, attribute_index: HashMap::new(), base_type_index: HashMap::new(), file_index: fi, path_to_id: HashMap::new(), method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(), walk_limits: Default::default(), file_imports: HashMap::new(), di_registrations: Vec::new(), endpoints: Vec::new(), partial_types: HashMap::new(), doc_comments: HashMap::new(), imports: Vec::new() };
+    let ci = ContentIndex { root: ".".to_string(), created_at: 0, max_age_secs: 3600, files: vec![ne], index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()], file_token_counts: vec![0], trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None, comment_markers: Vec::new(), revision: None, ticket_refs: Vec::new(), dir_blooms: HashMap::new(), skipped: SkippedFiles::default(), tokenizer: TokenizerSettings::default(), walk_limits: Default::default(), stop_token_pct: 0, max_file_size: 0 };
+    let ctx = HandlerContext { index: Searcher::new(ci), def_index: Some(Arc::new(RwLock::new(di))), server_dir: ".".to_string(), server_ext: "cs".to_string(), metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)), git_cache: Arc::new(RwLock::new(None)), git_cache_ready: Arc::new(AtomicBool::new(false)), current_branch: None, commit_index: Arc::new(Mutex::new(None)), semantic_index: Arc::new(Mutex::new(None)), owners: None, generation: Default::default(), index_subscribed: Arc::new(AtomicBool::new(false)), reindex_jobs: Default::default(), query_history: Default::default(), ranking: Default::default(), rank_hook: None };
     let result = dispatch_tool(&ctx, "search_definitions", &json!({"name": "GhostClass", "includeBody": true}));
     let output: Value = serde_json::from_str(&result.content[0].text).unwrap();
     assert_eq!(output["definitions"].as_array().unwrap()[0]["bodyError"], "failed to read file");
@@ -1829,6 +2722,13 @@ fn test_reindex_definitions_success() {
         file_index: HashMap::new(), path_to_id: HashMap::new(),
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0,
         empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let content_index = ContentIndex {
@@ -1836,10 +2736,19 @@ fn test_reindex_definitions_success() {
         files: vec![], index: HashMap::new(), total_tokens: 0,
         extensions: vec!["cs".to_string()], file_token_counts: vec![],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: dir_str.clone(),
         server_ext: "cs".to_string(),
@@ -1851,6 +2760,15 @@ fn test_reindex_definitions_success() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_reindex_definitions", &json!({}));
@@ -1860,6 +2778,7 @@ fn test_reindex_definitions_success() {
     assert!(output["files"].as_u64().unwrap() >= 1, "Should have parsed at least 1 file");
     assert!(output["definitions"].as_u64().unwrap() >= 1, "Should have found at least 1 definition");
     assert!(output["rebuildTimeMs"].as_f64().is_some(), "Should report rebuild time");
+    assert_eq!(ctx.generation.get(), 1, "Reindex should bump the index generation");
 
     cleanup_tmp(&tmp_dir);
 }
@@ -1920,6 +2839,13 @@ fn make_ctx_with_backslash_paths() -> HandlerContext {
         file_index, path_to_id,
         method_calls: HashMap::new(), code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0,
         empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let content_index = ContentIndex {
@@ -1931,10 +2857,19 @@ fn make_ctx_with_backslash_paths() -> HandlerContext {
         index: HashMap::new(), total_tokens: 0, extensions: vec!["cs".to_string()],
         file_token_counts: vec![100, 100], trigram: TrigramIndex::default(),
         trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1944,6 +2879,15 @@ fn make_ctx_with_backslash_paths() -> HandlerContext {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     }
 }
 
@@ -2061,21 +3005,21 @@ fn test_search_callers_cycle_detection() {
 
     let mut content_idx = HashMap::new();
     // MethodA token appears in file 0 (definition) and file 1 (call site in MethodB)
-    content_idx.insert("methoda".to_string(), vec![
+    content_idx.insert("methoda".into(), vec![
         Posting { file_id: 0, lines: vec![10] },  // definition in ServiceA
         Posting { file_id: 1, lines: vec![20] },  // call site in ServiceB.MethodB
     ]);
     // MethodB token appears in file 1 (definition) and file 0 (call site in MethodA)
-    content_idx.insert("methodb".to_string(), vec![
+    content_idx.insert("methodb".into(), vec![
         Posting { file_id: 1, lines: vec![10] },  // definition in ServiceB
         Posting { file_id: 0, lines: vec![20] },  // call site in ServiceA.MethodA
     ]);
     // Class tokens for parent filtering
-    content_idx.insert("servicea".to_string(), vec![
+    content_idx.insert("servicea".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
         Posting { file_id: 1, lines: vec![20] },  // ServiceB references ServiceA
     ]);
-    content_idx.insert("serviceb".to_string(), vec![
+    content_idx.insert("serviceb".into(), vec![
         Posting { file_id: 1, lines: vec![1] },
         Posting { file_id: 0, lines: vec![20] },  // ServiceA references ServiceB
     ]);
@@ -2090,6 +3034,15 @@ fn test_search_callers_cycle_detection() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![100, 100],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -2137,6 +3090,10 @@ fn test_search_callers_cycle_detection() {
         receiver_type: Some("ServiceA".to_string()),
         line: 20,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
     // MethodA (di=1) calls MethodB at line 20
     method_calls.insert(1, vec![CallSite {
@@ -2144,6 +3101,10 @@ fn test_search_callers_cycle_detection() {
         receiver_type: Some("ServiceB".to_string()),
         line: 20,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
 
     let def_index = DefinitionIndex {
@@ -2155,10 +3116,17 @@ fn test_search_callers_cycle_detection() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -2168,6 +3136,15 @@ fn test_search_callers_cycle_detection() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // direction=up (default) with depth=5 — cycle should be stopped by visited set
@@ -2208,20 +3185,20 @@ fn test_search_callers_ext_filter_comma_split() {
     // Setup: DataService.cs defines ProcessData; callers exist in both .cs and .txt files.
     // The ext parameter should filter caller files by extension.
     let mut content_idx = HashMap::new();
-    content_idx.insert("processdata".to_string(), vec![
+    content_idx.insert("processdata".into(), vec![
         Posting { file_id: 0, lines: vec![20] },   // definition site
         Posting { file_id: 1, lines: vec![15] },   // caller in .cs file
         Posting { file_id: 2, lines: vec![10] },   // caller in .txt file
     ]);
-    content_idx.insert("dataservice".to_string(), vec![
+    content_idx.insert("dataservice".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
         Posting { file_id: 1, lines: vec![5, 15] },
         Posting { file_id: 2, lines: vec![3, 10] },
     ]);
-    content_idx.insert("cscontroller".to_string(), vec![
+    content_idx.insert("cscontroller".into(), vec![
         Posting { file_id: 1, lines: vec![1] },
     ]);
-    content_idx.insert("scriptrunner".to_string(), vec![
+    content_idx.insert("scriptrunner".into(), vec![
         Posting { file_id: 2, lines: vec![1] },
     ]);
 
@@ -2238,6 +3215,15 @@ fn test_search_callers_ext_filter_comma_split() {
         extensions: vec!["cs".to_string(), "txt".to_string()],
         file_token_counts: vec![80, 60, 60],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -2297,6 +3283,10 @@ fn test_search_callers_ext_filter_comma_split() {
         receiver_type: Some("DataService".to_string()),
         line: 15,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
     // RunScript (di=5) calls ProcessData at line 10
     method_calls.insert(5, vec![CallSite {
@@ -2304,6 +3294,10 @@ fn test_search_callers_ext_filter_comma_split() {
         receiver_type: Some("DataService".to_string()),
         line: 10,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
 
     let def_index = DefinitionIndex {
@@ -2317,16 +3311,32 @@ fn test_search_callers_ext_filter_comma_split() {
         definitions, name_index, kind_index,
         attribute_index: HashMap::new(), base_type_index: HashMap::new(),
         file_index, path_to_id, method_calls, code_stats: HashMap::new(), parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs,txt".to_string(),
         metrics: false, index_base: PathBuf::from("."), max_response_bytes: crate::mcp::handlers::utils::DEFAULT_MAX_RESPONSE_BYTES, content_ready: Arc::new(AtomicBool::new(true)), def_ready: Arc::new(AtomicBool::new(true)),
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // ── Case 1: ext="cs" → only .cs callers ──────────────────────────
@@ -2376,15 +3386,15 @@ fn test_search_callers_overloads_not_collapsed_up() {
     // Two overloads of Process (same class, different lines) both call Validate.
     // Both should appear as callers (direction=up) — they must NOT be collapsed.
     let mut content_idx = HashMap::new();
-    content_idx.insert("validate".to_string(), vec![
+    content_idx.insert("validate".into(), vec![
         Posting { file_id: 0, lines: vec![10] },       // definition in Validator
         Posting { file_id: 1, lines: vec![25, 45] },    // calls in both Process overloads
     ]);
-    content_idx.insert("validator".to_string(), vec![
+    content_idx.insert("validator".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
         Posting { file_id: 1, lines: vec![25, 45] },
     ]);
-    content_idx.insert("processor".to_string(), vec![
+    content_idx.insert("processor".into(), vec![
         Posting { file_id: 1, lines: vec![1] },
     ]);
 
@@ -2398,6 +3408,15 @@ fn test_search_callers_overloads_not_collapsed_up() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50, 80],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -2453,12 +3472,20 @@ fn test_search_callers_overloads_not_collapsed_up() {
         receiver_type: Some("Validator".to_string()),
         line: 25,
         receiver_is_generic: false,
+        column: 0,
+        end_column: 0,
+        receiver: None,
+        arg_count: None,
     }]);
     method_calls.insert(4, vec![CallSite {
         method_name: "Validate".to_string(),
         receiver_type: Some("Validator".to_string()),
         line: 45,
         receiver_is_generic: false,
+        column: 0,
+        end_column: 0,
+        receiver: None,
+        arg_count: None,
     }]);
 
     let def_index = DefinitionIndex {
@@ -2470,10 +3497,17 @@ fn test_search_callers_overloads_not_collapsed_up() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -2483,6 +3517,15 @@ fn test_search_callers_overloads_not_collapsed_up() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_callers", &json!({
@@ -2569,12 +3612,20 @@ fn test_search_callers_overloads_not_collapsed_down() {
             receiver_type: Some("Executor".to_string()),
             line: 15,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         },
         CallSite {
             method_name: "Execute".to_string(),
             receiver_type: Some("Executor".to_string()),
             line: 20,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         },
     ]);
 
@@ -2587,6 +3638,13 @@ fn test_search_callers_overloads_not_collapsed_down() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let content_index = ContentIndex {
@@ -2596,10 +3654,19 @@ fn test_search_callers_overloads_not_collapsed_down() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50, 80],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -2609,6 +3676,15 @@ fn test_search_callers_overloads_not_collapsed_down() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     let result = dispatch_tool(&ctx, "search_callers", &json!({
@@ -2661,7 +3737,7 @@ fn test_search_callers_same_name_different_receiver_interface_resolution() {
 
     let mut content_idx = HashMap::new();
     // "execute" token appears in all files
-    content_idx.insert("execute".to_string(), vec![
+    content_idx.insert("execute".into(), vec![
         Posting { file_id: 0, lines: vec![10] },  // IServiceA.Execute definition
         Posting { file_id: 1, lines: vec![10] },  // IServiceB.Execute definition
         Posting { file_id: 2, lines: vec![10] },  // ServiceA.Execute definition
@@ -2669,22 +3745,22 @@ fn test_search_callers_same_name_different_receiver_interface_resolution() {
         Posting { file_id: 4, lines: vec![20] },  // Consumer.DoWork calls Execute
     ]);
     // Class/interface name tokens for parent_file_ids pre-filter
-    content_idx.insert("iservicea".to_string(), vec![
+    content_idx.insert("iservicea".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
         Posting { file_id: 2, lines: vec![1] },   // ServiceA implements IServiceA
     ]);
-    content_idx.insert("iserviceb".to_string(), vec![
+    content_idx.insert("iserviceb".into(), vec![
         Posting { file_id: 1, lines: vec![1] },
         Posting { file_id: 3, lines: vec![1] },   // ServiceB implements IServiceB
         Posting { file_id: 4, lines: vec![5, 20] }, // Consumer has _serviceB field of type IServiceB
     ]);
-    content_idx.insert("servicea".to_string(), vec![
+    content_idx.insert("servicea".into(), vec![
         Posting { file_id: 2, lines: vec![1] },
     ]);
-    content_idx.insert("serviceb".to_string(), vec![
+    content_idx.insert("serviceb".into(), vec![
         Posting { file_id: 3, lines: vec![1] },
     ]);
-    content_idx.insert("consumer".to_string(), vec![
+    content_idx.insert("consumer".into(), vec![
         Posting { file_id: 4, lines: vec![1] },
     ]);
 
@@ -2701,6 +3777,15 @@ fn test_search_callers_same_name_different_receiver_interface_resolution() {
         extensions: vec!["cs".to_string()],
         file_token_counts: vec![50, 50, 50, 50, 50],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -2800,6 +3885,10 @@ fn test_search_callers_same_name_different_receiver_interface_resolution() {
         receiver_type: Some("IServiceB".to_string()),
         line: 20,
         receiver_is_generic: false,
+        column: 0,
+        end_column: 0,
+        receiver: None,
+        arg_count: None,
     }]);
 
     let def_index = DefinitionIndex {
@@ -2817,10 +3906,17 @@ fn test_search_callers_same_name_different_receiver_interface_resolution() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -2830,6 +3926,15 @@ fn test_search_callers_same_name_different_receiver_interface_resolution() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // ── Test 1: callers of ServiceA.Execute() should NOT find Consumer.DoWork()
@@ -4,13 +4,14 @@
 use super::*;
 use super::handlers_test_utils::cleanup_tmp;
 use crate::index::build_trigram_index;
-use crate::Posting;
+use crate::{ContentIndex, Posting};
+use crate::{SkippedFiles, TokenizerSettings};
 use crate::TrigramIndex;
 use crate::definitions::DefinitionEntry;
 use crate::definitions::*;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 // ─── Helpers ─────────────────────────────────────────────────────────
 
@@ -18,18 +19,18 @@ use std::sync::{Arc, RwLock};
 fn make_ts_ctx_with_defs() -> HandlerContext {
     // Content index: tokens -> files+lines (all lowercase)
     let mut content_idx = HashMap::new();
-    content_idx.insert("getuser".to_string(), vec![
+    content_idx.insert("getuser".into(), vec![
         Posting { file_id: 0, lines: vec![15] },
         Posting { file_id: 1, lines: vec![20] },
     ]);
-    content_idx.insert("userservice".to_string(), vec![
+    content_idx.insert("userservice".into(), vec![
         Posting { file_id: 0, lines: vec![1, 15] },
         Posting { file_id: 1, lines: vec![5] },
     ]);
-    content_idx.insert("orderprocessor".to_string(), vec![
+    content_idx.insert("orderprocessor".into(), vec![
         Posting { file_id: 1, lines: vec![1] },
     ]);
-    content_idx.insert("handleorder".to_string(), vec![
+    content_idx.insert("handleorder".into(), vec![
         Posting { file_id: 1, lines: vec![18] },
     ]);
 
@@ -52,6 +53,15 @@ fn make_ts_ctx_with_defs() -> HandlerContext {
         trigram_dirty: false,
         forward: None,
         path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     // Definitions: all TS definition kinds
@@ -212,6 +222,10 @@ fn make_ts_ctx_with_defs() -> HandlerContext {
         receiver_type: Some("UserService".to_string()),
         line: 20,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
 
     let def_index = DefinitionIndex {
@@ -240,10 +254,17 @@ fn make_ts_ctx_with_defs() -> HandlerContext {
         parse_errors: 0,
         lossy_file_count: 0,
         empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(),
         server_ext: "ts".to_string(),
@@ -255,6 +276,15 @@ fn make_ts_ctx_with_defs() -> HandlerContext {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     }
 }
 
@@ -344,6 +374,13 @@ fn make_ts_ctx_with_real_files() -> (HandlerContext, std::path::PathBuf) {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let content_index = ContentIndex {
@@ -353,10 +390,19 @@ fn make_ts_ctx_with_real_files() -> (HandlerContext, std::path::PathBuf) {
         extensions: vec!["ts".to_string()],
         file_token_counts: vec![0, 0],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: tmp_dir.to_string_lossy().to_string(),
         server_ext: "ts".to_string(),
@@ -368,6 +414,15 @@ fn make_ts_ctx_with_real_files() -> (HandlerContext, std::path::PathBuf) {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
     (ctx, tmp_dir)
 }
@@ -744,10 +799,10 @@ fn test_ts_search_callers_inject_support() {
     // Create a context where a service is injected via Angular inject()
     // and the caller uses it through the injected field.
     let mut content_idx = HashMap::new();
-    content_idx.insert("processorder".to_string(), vec![
+    content_idx.insert("processorder".into(), vec![
         Posting { file_id: 0, lines: vec![10] },
     ]);
-    content_idx.insert("getuser".to_string(), vec![
+    content_idx.insert("getuser".into(), vec![
         Posting { file_id: 0, lines: vec![12] },
         Posting { file_id: 1, lines: vec![5] },
     ]);
@@ -764,6 +819,15 @@ fn test_ts_search_callers_inject_support() {
         extensions: vec!["ts".to_string()],
         file_token_counts: vec![50, 50],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -819,6 +883,10 @@ fn test_ts_search_callers_inject_support() {
         receiver_type: Some("UserService".to_string()),
         line: 12,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
 
     let def_index = DefinitionIndex {
@@ -830,10 +898,17 @@ fn test_ts_search_callers_inject_support() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "ts".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -843,6 +918,15 @@ fn test_ts_search_callers_inject_support() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // search_callers up: who calls getUser in UserService?
@@ -888,7 +972,7 @@ fn test_ts_search_callers_arrow_fn_property() {
 fn test_mixed_cs_ts_definitions_query() {
     // Create a context with both .cs and .ts files
     let mut content_idx = HashMap::new();
-    content_idx.insert("userservice".to_string(), vec![
+    content_idx.insert("userservice".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
         Posting { file_id: 1, lines: vec![1] },
     ]);
@@ -905,6 +989,15 @@ fn test_mixed_cs_ts_definitions_query() {
         extensions: vec!["cs".to_string(), "ts".to_string()],
         file_token_counts: vec![50, 50],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -954,10 +1047,17 @@ fn test_mixed_cs_ts_definitions_query() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs,ts".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -967,6 +1067,15 @@ fn test_mixed_cs_ts_definitions_query() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Query by name — should find both C# and TS versions
@@ -1002,7 +1111,7 @@ fn test_mixed_cs_ts_definitions_query() {
 fn test_mixed_cs_ts_callers_ext_filter() {
     // Create mixed-language context with calls in both .cs and .ts files
     let mut content_idx = HashMap::new();
-    content_idx.insert("getuser".to_string(), vec![
+    content_idx.insert("getuser".into(), vec![
         Posting { file_id: 0, lines: vec![5] },
         Posting { file_id: 1, lines: vec![10] },
     ]);
@@ -1019,6 +1128,15 @@ fn test_mixed_cs_ts_callers_ext_filter() {
         extensions: vec!["cs".to_string(), "ts".to_string()],
         file_token_counts: vec![50, 50],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1079,12 +1197,20 @@ fn test_mixed_cs_ts_callers_ext_filter() {
         receiver_type: Some("CsService".to_string()),
         line: 5,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
     method_calls.insert(3, vec![CallSite {
         method_name: "getUser".to_string(),
         receiver_type: Some("CsService".to_string()),
         line: 10,
                 receiver_is_generic: false,
+                column: 0,
+                end_column: 0,
+                receiver: None,
+                arg_count: None,
             }]);
 
     let def_index = DefinitionIndex {
@@ -1096,10 +1222,17 @@ fn test_mixed_cs_ts_callers_ext_filter() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "cs,ts".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1109,6 +1242,15 @@ fn test_mixed_cs_ts_callers_ext_filter() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Without ext filter — should find callers from both languages
@@ -1146,7 +1288,7 @@ fn test_mixed_cs_ts_callers_ext_filter() {
 fn test_tsx_file_support_through_handler() {
     // Create a context with a .tsx file
     let mut content_idx = HashMap::new();
-    content_idx.insert("appcomponent".to_string(), vec![
+    content_idx.insert("appcomponent".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
     ]);
 
@@ -1159,6 +1301,15 @@ fn test_tsx_file_support_through_handler() {
         extensions: vec!["ts".to_string(), "tsx".to_string()],
         file_token_counts: vec![50],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1202,10 +1353,17 @@ fn test_tsx_file_support_through_handler() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "ts,tsx".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1215,6 +1373,15 @@ fn test_tsx_file_support_through_handler() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Find class in .tsx file
@@ -1282,6 +1449,13 @@ fn test_ts_incremental_update_through_handler() {
         path_to_id: HashMap::new(), method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let clean_path = PathBuf::from(&file_str);
@@ -1295,10 +1469,19 @@ fn test_ts_incremental_update_through_handler() {
         extensions: vec!["ts".to_string()],
         file_token_counts: vec![0],
         trigram: TrigramIndex::default(), trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: tmp_dir.to_string_lossy().to_string(),
         server_ext: "ts".to_string(),
@@ -1309,6 +1492,15 @@ fn test_ts_incremental_update_through_handler() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Verify OldService is found
@@ -1370,7 +1562,7 @@ fn test_ts_incremental_update_through_handler() {
 #[test]
 fn test_ts_search_definitions_exclude_dir() {
     let mut content_idx = HashMap::new();
-    content_idx.insert("userservice".to_string(), vec![
+    content_idx.insert("userservice".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
         Posting { file_id: 1, lines: vec![1] },
     ]);
@@ -1386,6 +1578,15 @@ fn test_ts_search_definitions_exclude_dir() {
         extensions: vec!["ts".to_string()],
         file_token_counts: vec![50, 50],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1429,10 +1630,17 @@ fn test_ts_search_definitions_exclude_dir() {
         file_index, path_to_id, method_calls: HashMap::new(),
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "ts".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1442,6 +1650,15 @@ fn test_ts_search_definitions_exclude_dir() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // Exclude __tests__ directory
@@ -1514,15 +1731,15 @@ fn test_ts_direction_down_with_typed_local_variable() {
     // so direction=down from Orchestrator.run() should find DataProcessor.transform().
 
     let mut content_idx = HashMap::new();
-    content_idx.insert("transform".to_string(), vec![
+    content_idx.insert("transform".into(), vec![
         Posting { file_id: 0, lines: vec![3] },
         Posting { file_id: 1, lines: vec![10] },
     ]);
-    content_idx.insert("dataprocessor".to_string(), vec![
+    content_idx.insert("dataprocessor".into(), vec![
         Posting { file_id: 0, lines: vec![1] },
         Posting { file_id: 1, lines: vec![9] },
     ]);
-    content_idx.insert("orchestrator".to_string(), vec![
+    content_idx.insert("orchestrator".into(), vec![
         Posting { file_id: 1, lines: vec![7] },
     ]);
 
@@ -1538,6 +1755,15 @@ fn test_ts_direction_down_with_typed_local_variable() {
         extensions: vec!["ts".to_string()],
         file_token_counts: vec![50, 50],
         trigram, trigram_dirty: false, forward: None, path_to_id: None,
+        comment_markers: Vec::new(),
+        revision: None,
+        ticket_refs: Vec::new(),
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
     };
 
     let definitions = vec![
@@ -1603,12 +1829,20 @@ fn test_ts_direction_down_with_typed_local_variable() {
             receiver_type: Some("Orchestrator".to_string()),
             line: 9,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         },
         CallSite {
             method_name: "transform".to_string(),
             receiver_type: Some("DataProcessor".to_string()),
             line: 10,
             receiver_is_generic: false,
+            column: 0,
+            end_column: 0,
+            receiver: None,
+            arg_count: None,
         },
     ]);
 
@@ -1621,10 +1855,17 @@ fn test_ts_direction_down_with_typed_local_variable() {
         file_index, path_to_id, method_calls,
         code_stats: HashMap::new(),
         parse_errors: 0, lossy_file_count: 0, empty_file_ids: Vec::new(), extension_methods: HashMap::new(), selector_index: HashMap::new(), template_children: HashMap::new(),
+        walk_limits: Default::default(),
+        file_imports: HashMap::new(),
+        di_registrations: Vec::new(),
+        endpoints: Vec::new(),
+        partial_types: HashMap::new(),
+        doc_comments: HashMap::new(),
+        imports: Vec::new(),
     };
 
     let ctx = HandlerContext {
-        index: Arc::new(RwLock::new(content_index)),
+        index: Searcher::new(content_index),
         def_index: Some(Arc::new(RwLock::new(def_index))),
         server_dir: ".".to_string(), server_ext: "ts".to_string(),
         metrics: false, index_base: PathBuf::from("."),
@@ -1634,6 +1875,15 @@ fn test_ts_direction_down_with_typed_local_variable() {
     git_cache: Arc::new(RwLock::new(None)),
     git_cache_ready: Arc::new(AtomicBool::new(false)),
         current_branch: None,
+        commit_index: Arc::new(Mutex::new(None)),
+        semantic_index: Arc::new(Mutex::new(None)),
+        owners: None,
+        generation: Default::default(),
+        index_subscribed: Arc::new(AtomicBool::new(false)),
+        reindex_jobs: Default::default(),
+        query_history: Default::default(),
+        ranking: Default::default(),
+        rank_hook: None,
     };
 
     // direction=down from Orchestrator.run() should find DataProcessor.transform()
@@ -0,0 +1,126 @@
+//! search_type_hierarchy handler: supertypes and subtypes/implementations of a type.
+
+use std::collections::HashSet;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{find_type_definitions, hierarchy_len, hierarchy_to_json, partial_parts_json, primary_part, HierarchyWalker};
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+pub(crate) fn handle_search_type_hierarchy(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+
+    let name = match args.get("name").and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty()) {
+        Some(n) => n.to_string(),
+        None => return ToolCallResult::error("Missing required parameter: name".to_string()),
+    };
+    let max_depth = {
+        let raw = args.get("depth").and_then(|v| v.as_u64()).unwrap_or(3);
+        if raw == 0 {
+            return ToolCallResult::error(
+                "depth must be >= 1. Use depth=1 for direct base types and subtypes only.".to_string()
+            );
+        }
+        raw.min(10) as usize
+    };
+    let direction = {
+        let raw = args.get("direction").and_then(|v| v.as_str()).unwrap_or("both");
+        let d = raw.to_lowercase();
+        if d != "up" && d != "down" && d != "both" {
+            return ToolCallResult::error(format!(
+                "Invalid direction '{}'. Must be 'up', 'down', or 'both'.", raw
+            ));
+        }
+        d
+    };
+    let max_total_nodes = args.get("maxTotalNodes").and_then(|v| v.as_u64()).unwrap_or(200) as usize;
+    let file_filter = args.get("file").and_then(|v| v.as_str()).map(|s| s.replace('\\', "/").to_lowercase());
+
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let start = Instant::now();
+    let mut roots: Vec<u32> = find_type_definitions(&index, &name).into_iter()
+        .filter(|&id| file_filter.as_ref().is_none_or(|f| {
+            let file_id = index.definitions[id as usize].file_id as usize;
+            index.files.get(file_id).is_some_and(|p| p.replace('\\', "/").to_lowercase().contains(f.as_str()))
+        }))
+        .map(|id| primary_part(&index, id))
+        .collect();
+    // The parts of a partial type are one root
+    let mut seen = HashSet::new();
+    roots.retain(|&id| seen.insert(id));
+
+    let mut walker = HierarchyWalker::new(&index, max_depth, max_total_nodes);
+    let mut types = Vec::new();
+    let mut total_nodes = 0;
+    let root_ids: Vec<Option<u32>> = if roots.is_empty() { vec![None] } else { roots.into_iter().map(Some).collect() };
+    for root in root_ids {
+        let mut obj = match root {
+            Some(id) => {
+                let def = &index.definitions[id as usize];
+                let mut obj = json!({
+                    "name": def.name,
+                    "kind": def.kind.as_str(),
+                    "file": index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or(""),
+                    "lines": format!("{}-{}", def.line_start, def.line_end),
+                });
+                if let Some(partial) = index.partial_types.get(&id) {
+                    obj["partialParts"] = partial_parts_json(&index, partial);
+                }
+                obj
+            }
+            // Not defined in the indexed code (e.g. a framework base class): subtypes only
+            None => json!({ "name": name, "external": true }),
+        };
+        if direction != "down"
+            && let Some(id) = root
+        {
+            let supertypes = walker.supertypes(id);
+            total_nodes += hierarchy_len(&supertypes);
+            obj["supertypes"] = hierarchy_to_json(&index, &supertypes);
+        }
+        if direction != "up" {
+            let subtypes = walker.subtypes(&name, root);
+            total_nodes += hierarchy_len(&subtypes);
+            obj["subtypes"] = hierarchy_to_json(&index, &subtypes);
+        }
+        types.push(obj);
+    }
+
+    let mut summary = json!({
+        "totalNodes": total_nodes,
+        "depth": max_depth,
+        "direction": direction,
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if walker.truncated {
+        summary["truncated"] = json!(true);
+        summary["hint"] = json!("Node limit reached. Increase maxTotalNodes or reduce depth.");
+    } else if total_nodes == 0 {
+        summary["hint"] = json!(format!(
+            "No base types or subtypes found for '{}'. Type names are matched exactly (case-insensitive), without namespace or generic arguments.",
+            name
+        ));
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "types": types,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
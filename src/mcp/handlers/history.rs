@@ -0,0 +1,225 @@
+//! search_history handler and "did you mean" suggestions: the recent tool calls of
+//! this server's client session.
+//!
+//! Each finished call is recorded with its arguments and result count. When
+//! `search_grep` or `search_definitions` finds nothing, the response gets a
+//! `didYouMean` list per unknown term: earlier queries of the session that found
+//! something, then the most similar tokens by trigram (for grep) or the closest
+//! definition names by edit distance.
+
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::mcp::protocol::ToolCallResult;
+use search::edit_distance_within;
+use search::lock_stats::{self, Lock};
+
+use super::HandlerContext;
+
+/// Calls kept per session; older ones are dropped first
+const MAX_ENTRIES: usize = 200;
+
+/// Default and maximum `limit` of search_history
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = MAX_ENTRIES;
+
+/// Suggestions returned per unknown term
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Summary keys holding a tool's result count, in order of preference
+const COUNT_KEYS: &[&str] = &["totalResults", "totalFiles", "totalMatches", "totalNodes"];
+
+/// Recent tool calls of one server, oldest first.
+#[derive(Default)]
+pub struct QueryHistory {
+    recorded: u64,
+    entries: VecDeque<QueryRecord>,
+}
+
+struct QueryRecord {
+    id: u64,
+    tool: String,
+    arguments: Value,
+    /// Result count from the response summary; `None` for tools that report none
+    hits: Option<u64>,
+    is_error: bool,
+    elapsed: Duration,
+    at: Instant,
+}
+
+impl QueryHistory {
+    /// Record a finished call. search_history itself is not recorded.
+    pub(crate) fn record(&mut self, tool: &str, arguments: &Value, result: &ToolCallResult, elapsed: Duration) {
+        if tool == "search_history" {
+            return;
+        }
+        self.recorded += 1;
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(QueryRecord {
+            id: self.recorded,
+            tool: tool.to_string(),
+            arguments: arguments.clone(),
+            hits: if result.is_error { None } else { result_count(result) },
+            is_error: result.is_error,
+            elapsed,
+            at: Instant::now(),
+        });
+    }
+
+    /// Terms of earlier `tool` calls that found something, newest first, without repeats.
+    fn successful_terms(&self, tool: &str, field: &str) -> Vec<String> {
+        let mut terms: Vec<String> = Vec::new();
+        for entry in self.entries.iter().rev().filter(|e| e.tool == tool && e.hits.is_some_and(|n| n > 0)) {
+            for term in query_terms(&entry.arguments, field) {
+                if !terms.contains(&term) {
+                    terms.push(term);
+                }
+            }
+        }
+        terms
+    }
+}
+
+pub(crate) fn handle_search_history(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let limit = match args.get("limit").and_then(|v| v.as_u64()) {
+        Some(0) => return ToolCallResult::error("limit must be >= 1".to_string()),
+        Some(n) => (n as usize).min(MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+    let tool = args.get("tool").and_then(|v| v.as_str());
+    let empty_only = args.get("emptyOnly").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let history = ctx.query_history.lock().unwrap_or_else(|e| e.into_inner());
+    let matching: Vec<&QueryRecord> = history.entries.iter().rev()
+        .filter(|e| tool.is_none_or(|t| e.tool == t))
+        .filter(|e| !empty_only || e.hits == Some(0))
+        .collect();
+    let queries: Vec<Value> = matching.iter().take(limit).map(|e| {
+        let mut query = json!({
+            "id": e.id,
+            "tool": e.tool,
+            "arguments": e.arguments,
+            "elapsedMs": (e.elapsed.as_secs_f64() * 1000.0 * 100.0).round() / 100.0,
+            "secondsAgo": e.at.elapsed().as_secs(),
+        });
+        if let Some(hits) = e.hits {
+            query["hits"] = json!(hits);
+        }
+        if e.is_error {
+            query["error"] = json!(true);
+        }
+        query
+    }).collect();
+    let output = json!({
+        "queries": queries,
+        "summary": {
+            "totalRecorded": history.recorded,
+            "kept": history.entries.len(),
+            "matching": matching.len(),
+            "returned": queries.len(),
+            "emptyResults": history.entries.iter().filter(|e| e.hits == Some(0)).count(),
+        }
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+/// Add `summary.didYouMean` to an empty search_grep or search_definitions result:
+/// `[{term, suggestions}]` for each term the index does not know.
+pub(crate) fn suggest_if_empty(ctx: &HandlerContext, tool: &str, args: &Value, result: ToolCallResult) -> ToolCallResult {
+    let field = match tool {
+        "search_grep" if !args.get("regex").and_then(|v| v.as_bool()).unwrap_or(false) => "terms",
+        "search_definitions" => "name",
+        _ => return result,
+    };
+    if result_count(&result) != Some(0) {
+        return result;
+    }
+    let Ok(mut output) = serde_json::from_str::<Value>(&result.content[0].text) else {
+        return result;
+    };
+    let previous = ctx.query_history.lock().unwrap_or_else(|e| e.into_inner()).successful_terms(tool, field);
+    let mut did_you_mean = Vec::new();
+    for term in query_terms(args, field) {
+        let suggestions = if tool == "search_grep" {
+            let index = ctx.index.snapshot();
+            if index.index.contains_key(term.as_str()) {
+                continue;
+            }
+            let similar = index.spelling_suggestions(&term, MAX_SUGGESTIONS);
+            suggestions_for(&term, &previous, similar.iter().map(|token| &***token))
+        } else {
+            let Some(def_index) = &ctx.def_index else { continue };
+            if !ctx.def_ready.load(Ordering::Acquire) {
+                continue;
+            }
+            let Ok(def_index) = lock_stats::timed(Lock::Definitions, || def_index.read()) else { continue };
+            if def_index.name_index.contains_key(term.as_str()) {
+                continue;
+            }
+            let closest = closest_names(&term, def_index.name_index.iter().map(|(name, ids)| (name.as_str(), ids.len())));
+            suggestions_for(&term, &previous, closest.into_iter())
+        };
+        if !suggestions.is_empty() {
+            did_you_mean.push(json!({ "term": term, "suggestions": suggestions }));
+        }
+    }
+    if did_you_mean.is_empty() || !output["summary"].is_object() {
+        return result;
+    }
+    output["summary"]["didYouMean"] = json!(did_you_mean);
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+/// Earlier successful terms within reach of `term`, then `ranked` dictionary entries.
+fn suggestions_for<'a>(term: &str, previous: &[String], ranked: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let max_distance = max_distance(term);
+    let mut suggestions: Vec<String> = previous.iter()
+        .filter(|p| p.as_str() != term && (p.contains(term) || edit_distance_within(term, p, max_distance).is_some()))
+        .take(MAX_SUGGESTIONS)
+        .cloned()
+        .collect();
+    for entry in ranked {
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+        if !suggestions.iter().any(|s| s == entry) {
+            suggestions.push(entry.to_string());
+        }
+    }
+    suggestions
+}
+
+/// Dictionary entries within reach of `term`, by distance and then frequency.
+fn closest_names<'a>(term: &str, dictionary: impl Iterator<Item = (&'a str, usize)>) -> Vec<&'a str> {
+    let max_distance = max_distance(term);
+    let mut close: Vec<(usize, usize, &str)> = dictionary
+        .filter_map(|(name, count)| edit_distance_within(term, name, max_distance).map(|d| (d, count, name)))
+        .collect();
+    close.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(b.2)));
+    close.into_iter().take(MAX_SUGGESTIONS).map(|(_, _, name)| name).collect()
+}
+
+/// Edits allowed for a term: one for short terms, two from eight characters
+fn max_distance(term: &str) -> usize {
+    if term.chars().count() >= 8 { 2 } else { 1 }
+}
+
+/// Lowercased comma-separated terms of `args[field]`.
+fn query_terms(args: &Value, field: &str) -> Vec<String> {
+    args.get(field).and_then(|v| v.as_str()).unwrap_or_default()
+        .split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Result count from the first of [`COUNT_KEYS`] in the response summary.
+pub(crate) fn result_count(result: &ToolCallResult) -> Option<u64> {
+    let output: Value = serde_json::from_str(&result.content.first()?.text).ok()?;
+    COUNT_KEYS.iter().find_map(|key| output["summary"][key].as_u64())
+}
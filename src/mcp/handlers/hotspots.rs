@@ -0,0 +1,265 @@
+//! search_hotspots handler: refactoring hotspots ranked by churn × complexity.
+//!
+//! Churn is the number of commits touching a file in the git history cache;
+//! complexity comes from the definition index's `code_stats`. The cache records
+//! history per file, so a method's churn is the churn of the file containing it.
+
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{CodeStats, DefinitionIndex};
+use crate::git::cache::GitHistoryCache;
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use crate::owners::Owners;
+use search::lock_stats::{self, Lock};
+
+use super::git::{format_timestamp, parse_cache_date_range};
+use super::utils::{
+    inject_branch_warning, inject_owners, inject_owners_source, is_under_dir, owner_allows, parse_owner_filter,
+};
+use super::HandlerContext;
+
+const DEFAULT_MAX_RESULTS: usize = 20;
+const DEFAULT_TOP_AUTHORS: usize = 3;
+
+pub(crate) fn handle_search_hotspots(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+    if !ctx.git_cache_ready.load(Ordering::Acquire) {
+        return ToolCallResult::error(
+            "Git history cache is still being built in the background. Please retry in a few seconds.".to_string()
+        );
+    }
+    let cache_guard = match lock_stats::timed(Lock::Git, || ctx.git_cache.read()) {
+        Ok(guard) => guard,
+        Err(e) => return SearchError::LockPoisoned(format!("git cache: {}", e)).into(),
+    };
+    let Some(cache) = cache_guard.as_ref() else {
+        return ToolCallResult::error(
+            "Git history not available: the server directory is not a git repository root, or the cache build failed.".to_string()
+        );
+    };
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+    match search_hotspots_json(&index, cache, args, ctx.owners.as_deref()) {
+        Ok(mut output) => {
+            inject_branch_warning(&mut output["summary"], ctx);
+            ToolCallResult::success(serde_json::to_string(&output).unwrap())
+        }
+        Err(msg) => ToolCallResult::error(msg),
+    }
+}
+
+/// Complexity metric used for the score.
+#[derive(Clone, Copy)]
+enum Metric {
+    Cyclomatic,
+    Cognitive,
+}
+
+impl Metric {
+    fn of(self, stats: &CodeStats) -> u64 {
+        match self {
+            Metric::Cyclomatic => stats.cyclomatic_complexity as u64,
+            Metric::Cognitive => stats.cognitive_complexity as u64,
+        }
+    }
+}
+
+/// Commits touching one file inside the date window.
+struct FileChurn {
+    commits: usize,
+    last_change: i64,
+    /// author index -> commit count
+    authors: HashMap<u16, usize>,
+}
+
+/// Rank files (or methods) by churn × complexity and build the JSON response.
+/// Shared by the MCP handler and the `search hotspots` CLI command; `args` uses the
+/// MCP parameter names. Definition paths are matched to cache paths relative to the
+/// index root, which must be the repository root.
+pub(crate) fn search_hotspots_json(
+    index: &DefinitionIndex,
+    cache: &GitHistoryCache,
+    args: &Value,
+    owners: Option<&Owners>,
+) -> Result<Value, String> {
+    let start = Instant::now();
+
+    let by_method = match args.get("level").and_then(|v| v.as_str()).unwrap_or("file") {
+        "file" => false,
+        "method" => true,
+        other => return Err(format!("Invalid level '{}'. Valid values: file, method", other)),
+    };
+    let metric = match args.get("metric").and_then(|v| v.as_str()).unwrap_or("cyclomatic") {
+        "cyclomatic" => Metric::Cyclomatic,
+        "cognitive" => Metric::Cognitive,
+        other => return Err(format!("Invalid metric '{}'. Valid values: cyclomatic, cognitive", other)),
+    };
+    let (from, to) = parse_cache_date_range(
+        args.get("from").and_then(|v| v.as_str()),
+        args.get("to").and_then(|v| v.as_str()),
+        args.get("date").and_then(|v| v.as_str()),
+    )?;
+    let dir = args.get("dir").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|d| {
+        if std::path::Path::new(d).is_absolute() { d.to_string() } else { format!("{}/{}", index.root, d.trim_start_matches("./")) }
+    });
+    let exclude_dir: Vec<String> = args.get("excludeDir").and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).map(|s| s.replace('\\', "/").to_lowercase()).collect())
+        .unwrap_or_default();
+    let max_results = match args.get("maxResults").and_then(|v| v.as_u64()) {
+        Some(0) => usize::MAX,
+        Some(n) => n as usize,
+        None => DEFAULT_MAX_RESULTS,
+    };
+    let owner_filter = parse_owner_filter(owners, args)?;
+    let top_authors = args.get("topAuthors").and_then(|v| v.as_u64()).map(|n| n as usize).unwrap_or(DEFAULT_TOP_AUTHORS);
+
+    if index.code_stats.is_empty() {
+        return Err("Code stats not available for this index. Run search_reindex_definitions to compute metrics.".to_string());
+    }
+
+    let root = index.root.replace('\\', "/");
+    let root = root.trim_end_matches('/');
+    let included = |path: &str| {
+        let lower = path.replace('\\', "/").to_lowercase();
+        dir.as_ref().is_none_or(|d| is_under_dir(path, d))
+            && !exclude_dir.iter().any(|e| lower.contains(e.as_str()))
+            && owner_allows(owners, owner_filter.as_deref(), path)
+    };
+
+    // Churn per file id, computed once per file that has scored definitions
+    let mut churn: HashMap<u32, Option<FileChurn>> = HashMap::new();
+    let mut churn_of = |file_id: u32| -> Option<usize> {
+        let entry = churn.entry(file_id).or_insert_with(|| {
+            let path = index.files.get(file_id as usize)?.replace('\\', "/");
+            let rel = path.strip_prefix(root).unwrap_or(&path).trim_start_matches('/');
+            let commit_ids = cache.file_commits.get(rel)?;
+            let mut stats = FileChurn { commits: 0, last_change: i64::MIN, authors: HashMap::new() };
+            for meta in commit_ids.iter().filter_map(|&id| cache.commits.get(id as usize)) {
+                if from.is_some_and(|f| meta.timestamp < f) || to.is_some_and(|t| meta.timestamp > t) {
+                    continue;
+                }
+                stats.commits += 1;
+                stats.last_change = stats.last_change.max(meta.timestamp);
+                *stats.authors.entry(meta.author_idx).or_default() += 1;
+            }
+            (stats.commits > 0).then_some(stats)
+        });
+        entry.as_ref().map(|c| c.commits)
+    };
+
+    // (def index, complexity, churn) for every scored definition that passes the filters
+    let mut scored_defs: Vec<(u32, u64, usize)> = Vec::new();
+    for (&def_idx, stats) in &index.code_stats {
+        let Some(def) = index.definitions.get(def_idx as usize) else { continue };
+        let Some(path) = index.files.get(def.file_id as usize) else { continue };
+        if !included(path) {
+            continue;
+        }
+        if let Some(commits) = churn_of(def.file_id) {
+            scored_defs.push((def_idx, metric.of(stats), commits));
+        }
+    }
+
+    let authors_json = |file_id: u32| -> Value {
+        let Some(Some(stats)) = churn.get(&file_id) else { return json!([]) };
+        let mut authors: Vec<(&u16, &usize)> = stats.authors.iter().collect();
+        authors.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        json!(authors.iter().take(top_authors).filter_map(|(idx, count)| {
+            cache.authors.get(**idx as usize).map(|a| json!({ "name": a.name, "email": a.email, "commits": count }))
+        }).collect::<Vec<_>>())
+    };
+    let last_change = |file_id: u32| churn.get(&file_id).and_then(|c| c.as_ref())
+        .map(|c| format_timestamp(c.last_change)).unwrap_or_default();
+
+    let hotspots: Vec<Value>;
+    let total_candidates;
+    if by_method {
+        scored_defs.sort_by(|a, b| (b.1 * b.2 as u64).cmp(&(a.1 * a.2 as u64))
+            .then_with(|| b.1.cmp(&a.1))
+            .then_with(|| cmp_def(index, a.0, b.0)));
+        total_candidates = scored_defs.len();
+        hotspots = scored_defs.iter().take(max_results).map(|&(def_idx, complexity, commits)| {
+            let def = &index.definitions[def_idx as usize];
+            let file = index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or("");
+            let mut obj = json!({
+                "name": def.name,
+                "kind": def.kind.as_str(),
+                "file": file,
+                "lines": format!("{}-{}", def.line_start, def.line_end),
+                "score": complexity * commits as u64,
+                "churn": commits,
+                "complexity": complexity,
+                "lastChange": last_change(def.file_id),
+                "topAuthors": authors_json(def.file_id),
+            });
+            if let Some(ref parent) = def.parent {
+                obj["parent"] = json!(parent);
+            }
+            inject_owners(&mut obj, owners, file);
+            obj
+        }).collect();
+    } else {
+        // file id -> (summed complexity, churn, scored methods)
+        let mut files: HashMap<u32, (u64, usize, usize)> = HashMap::new();
+        for &(def_idx, complexity, commits) in &scored_defs {
+            let entry = files.entry(index.definitions[def_idx as usize].file_id).or_insert((0, commits, 0));
+            entry.0 += complexity;
+            entry.2 += 1;
+        }
+        let file_path = |id: u32| index.files.get(id as usize).map(|s| s.as_str()).unwrap_or("");
+        let mut ranked: Vec<(u32, u64, usize, usize)> = files.into_iter().map(|(id, (c, n, m))| (id, c, n, m)).collect();
+        ranked.sort_by(|a, b| (b.1 * b.2 as u64).cmp(&(a.1 * a.2 as u64))
+            .then_with(|| b.1.cmp(&a.1))
+            .then_with(|| file_path(a.0).cmp(file_path(b.0))));
+        total_candidates = ranked.len();
+        hotspots = ranked.iter().take(max_results).map(|&(file_id, complexity, commits, methods)| {
+            let file = file_path(file_id);
+            let mut obj = json!({
+                "file": file,
+                "score": complexity * commits as u64,
+                "churn": commits,
+                "complexity": complexity,
+                "methods": methods,
+                "lastChange": last_change(file_id),
+                "topAuthors": authors_json(file_id),
+            });
+            inject_owners(&mut obj, owners, file);
+            obj
+        }).collect();
+    }
+
+    let mut summary = json!({
+        "level": if by_method { "method" } else { "file" },
+        "metric": match metric { Metric::Cyclomatic => "cyclomatic", Metric::Cognitive => "cognitive" },
+        "returned": hotspots.len(),
+        "totalCandidates": total_candidates,
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    inject_owners_source(&mut summary, owners);
+    if hotspots.is_empty() {
+        summary["hint"] = json!("No changed files with complexity data match. Widen from/to, drop dir/excludeDir, or check that the server --dir is the repository root.");
+    }
+    Ok(json!({
+        "hotspots": hotspots,
+        "summary": summary,
+    }))
+}
+
+/// Tie-breaker for methods with equal scores: file path, then position.
+fn cmp_def(index: &DefinitionIndex, a: u32, b: u32) -> std::cmp::Ordering {
+    let (da, db) = (&index.definitions[a as usize], &index.definitions[b as usize]);
+    index.files.get(da.file_id as usize).cmp(&index.files.get(db.file_id as usize))
+        .then(da.line_start.cmp(&db.line_start))
+}
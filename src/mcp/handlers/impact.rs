@@ -0,0 +1,116 @@
+//! search_impact handler: test methods that reach a method or class through its callers.
+
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{affected_tests, is_callable, qualified_name, DefinitionEntry, DefinitionIndex, TestMatcher};
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+fn string_array(args: &Value, key: &str) -> Option<Vec<String>> {
+    args.get(key).and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+}
+
+/// Methods, constructors and functions named `method` (in `class`, when given), or all
+/// of those in `class`.
+fn find_targets(index: &DefinitionIndex, method: Option<&str>, class: Option<&str>) -> Vec<u32> {
+    let in_class = |def: &DefinitionEntry| class.is_none_or(|c| def.parent.as_deref().is_some_and(|p| p.eq_ignore_ascii_case(c)));
+    let mut targets: Vec<u32> = match method {
+        Some(m) => index.name_index.get(&m.to_lowercase()).cloned().unwrap_or_default(),
+        None => index.file_index.values().flatten().copied().collect(),
+    };
+    targets.retain(|&id| index.definitions.get(id as usize).is_some_and(|def| is_callable(&def.kind) && in_class(def)));
+    targets.sort_unstable();
+    targets
+}
+
+pub(crate) fn handle_search_impact(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+    let method = args.get("method").and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+    let class = args.get("class").and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+    if method.is_none() && class.is_none() {
+        return ToolCallResult::error("Missing required parameter: method or class".to_string());
+    }
+    let max_depth = {
+        let raw = args.get("depth").and_then(|v| v.as_u64()).unwrap_or(5);
+        if raw == 0 {
+            return ToolCallResult::error(
+                "depth must be >= 1. Use depth=1 for tests that call the method directly.".to_string()
+            );
+        }
+        raw.min(10) as usize
+    };
+    let mut matcher = TestMatcher::default();
+    if let Some(attributes) = string_array(args, "testAttributes") {
+        matcher.attributes = attributes;
+    }
+    if let Some(paths) = string_array(args, "testPaths") {
+        matcher.paths = paths;
+    }
+    let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
+
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let start = Instant::now();
+    let targets = find_targets(&index, method, class);
+    let report = affected_tests(&index, &targets, &matcher, max_depth);
+
+    let file_of = |id: u32| {
+        let def = &index.definitions[id as usize];
+        index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or("")
+    };
+    let test_files: BTreeSet<&str> = report.tests.iter().map(|t| file_of(t.def_id)).collect();
+    let tests: Vec<Value> = report.tests.iter()
+        .take(if max_results == 0 { usize::MAX } else { max_results })
+        .map(|test| {
+            let def = &index.definitions[test.def_id as usize];
+            json!({
+                "name": qualified_name(def),
+                "file": file_of(test.def_id),
+                "line": def.line_start,
+                "depth": test.depth,
+                "via": test.via.iter().map(|&id| qualified_name(&index.definitions[id as usize])).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+
+    let mut summary = json!({
+        "totalTests": report.tests.len(),
+        "returned": tests.len(),
+        "testFiles": test_files.len(),
+        "targets": targets.len(),
+        "methodsVisited": report.visited,
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if report.truncated {
+        summary["truncated"] = json!(true);
+        summary["hint"] = json!(format!("Callers remain above depth {}. Raise depth (max 10) to find more tests.", max_depth));
+    } else if targets.is_empty() {
+        summary["hint"] = json!("No method, constructor or function matches. Check the name with search_definitions.");
+    } else if report.tests.is_empty() {
+        summary["hint"] = json!("No test reaches these methods. Calls through local variables or reflection are not in the call graph; testPaths and testAttributes change what counts as a test.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "tests": tests,
+        "testFiles": test_files,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
@@ -0,0 +1,56 @@
+//! search_metrics handler: the server metrics registry (`search::metrics`) plus the
+//! current index sizes, as JSON or in the Prometheus text format.
+
+use std::sync::atomic::Ordering;
+
+use serde_json::{json, Value};
+
+use crate::mcp::protocol::ToolCallResult;
+use search::lock_stats::{self, Lock};
+use search::metrics;
+
+use super::HandlerContext;
+
+pub(crate) fn handle_search_metrics(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let gauges = index_gauges(ctx);
+    match args.get("format").and_then(|v| v.as_str()).unwrap_or("json") {
+        "json" => {
+            let mut output = metrics::summary();
+            output["index"] = gauges.iter().map(|g| (g.key.to_string(), json!(g.value))).collect();
+            ToolCallResult::success(serde_json::to_string(&output).unwrap())
+        }
+        "prometheus" => {
+            let gauges: Vec<(&str, &str, f64)> = gauges.iter().map(|g| (g.name, g.help, g.value as f64)).collect();
+            ToolCallResult::success(metrics::render_prometheus(&gauges))
+        }
+        other => ToolCallResult::error(format!("Invalid format '{}'. Valid values: json, prometheus", other)),
+    }
+}
+
+struct Gauge {
+    /// Key in the JSON `index` object
+    key: &'static str,
+    /// Prometheus name, without the `search_` prefix
+    name: &'static str,
+    help: &'static str,
+    value: u64,
+}
+
+/// Sizes of the in-memory indexes and the content index generation.
+fn index_gauges(ctx: &HandlerContext) -> Vec<Gauge> {
+    let content = ctx.index.snapshot();
+    let mut gauges = vec![
+        Gauge { key: "files", name: "index_files", help: "Files in the content index", value: content.files.len() as u64 },
+        Gauge { key: "uniqueTokens", name: "index_unique_tokens", help: "Distinct tokens in the content index", value: content.index.len() as u64 },
+        Gauge { key: "totalTokens", name: "index_tokens", help: "Token occurrences in the content index", value: content.total_tokens },
+        Gauge { key: "generation", name: "index_generation", help: "Content index updates since the server started", value: ctx.generation.get() },
+    ];
+    if let Some(def) = &ctx.def_index
+        && ctx.def_ready.load(Ordering::Acquire)
+        && let Ok(def) = lock_stats::timed(Lock::Definitions, || def.read())
+    {
+        gauges.push(Gauge { key: "definitionFiles", name: "definition_files", help: "Files in the definition index", value: def.files.len() as u64 });
+        gauges.push(Gauge { key: "definitions", name: "definitions", help: "Definitions in the definition index", value: def.definitions.len() as u64 });
+    }
+    gauges
+}
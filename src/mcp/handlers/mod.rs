@@ -1,41 +1,84 @@
 //! MCP tool handlers — dispatches tool calls to specialized handler modules.
 
+mod audit;
 mod callers;
 mod definitions;
+mod dependencies;
+mod di;
+mod density;
+mod dupes;
+mod endpoints;
 mod fast;
 mod find;
 mod git;
 mod grep;
+mod hierarchy;
+mod history;
+mod hotspots;
+mod impact;
+mod metrics;
+mod namespaces;
+mod outline;
+mod read_file;
+mod references;
+mod reindex;
+mod replace;
+mod semantic;
+mod symbol_at;
+mod tickets;
+mod todos;
+mod vocabulary;
 pub(crate) mod utils;
 
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
 
 use serde_json::{json, Value};
 use tracing::{info, warn};
 
 use crate::mcp::protocol::{ToolCallResult, ToolDefinition};
-use crate::{
-    build_content_index, clean_path,
-    save_content_index, ContentIndex, ContentIndexArgs,
-};
+use crate::{clean_path, SearchError, Searcher};
+use crate::config::RankingBoosts;
+use search::clock::Generation;
 use crate::definitions::DefinitionIndex;
+use crate::priority::BuildPriority;
 use crate::git::cache::GitHistoryCache;
+use crate::git::commit_index::CommitIndex;
+use crate::owners::Owners;
+use crate::rank_hook::RankHook;
+use crate::semantic::SemanticIndex;
+use search::lock_stats::{self, Lock};
+
+// Shared with the `search defs` CLI command
+pub(crate) use self::definitions::{parse_def_query, search_definitions_json};
+// Shared with the `search todos` CLI command
+pub(crate) use self::todos::search_todos_json;
+// Shared with the `search replace` CLI command
+pub(crate) use self::replace::{plan_replacements, replace_plan_json};
+// Shared with the `search hotspots` CLI command
+pub(crate) use self::hotspots::search_hotspots_json;
+// Shared with the `search tickets` CLI command
+pub(crate) use self::tickets::search_tickets_json;
+// Shared with the `search brief` CLI command
+pub(crate) use self::git::format_timestamp;
+// Shared with the `search audit` CLI command
+pub(crate) use self::audit::{list_suites, run_suite, unknown_suite};
+pub use self::history::QueryHistory;
+pub use self::reindex::ReindexJobs;
+pub(crate) use self::callers::resolve_call_site;
 
 // Re-export for use by tests (crate-internal only)
 #[cfg(test)]
 pub(crate) use self::callers::find_containing_method;
-#[cfg(test)]
-pub(crate) use self::callers::resolve_call_site;
 
 /// Return all tool definitions for tools/list
 pub fn tool_definitions() -> Vec<ToolDefinition> {
     let mut tools = vec![
         ToolDefinition {
             name: "search_grep".to_string(),
-            description: "Search file contents using an inverted index with TF-IDF ranking. LANGUAGE-AGNOSTIC: works with any text file (C#, Rust, Python, JS/TS, XML, JSON, config, etc.). Supports exact tokens, multi-term OR/AND, regex, phrase search, substring search, and exclusion filters. Results ranked by relevance. Index stays in memory for instant subsequent queries (~0.001s). Substring search is ON by default. Large results are auto-truncated to ~16KB (~4K tokens). Use countOnly=true or narrow with dir/ext/excludeDir for focused results.".to_string(),
+            description: "Search file contents using an inverted index with TF-IDF ranking. Works with any text file. Supports exact tokens, multi-term OR/AND, regex, phrase search, substring search, and exclusion filters. Results ranked by relevance. Substring search is ON by default. Large results are auto-truncated to ~16KB (~4K tokens). Use countOnly=true or narrow with dir/ext/excludeDir for focused results.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -72,15 +115,31 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                         "type": "integer",
                         "description": "Context lines before/after each match, requires showLines (default: 0)"
                     },
+                    "highlight": {
+                        "type": "boolean",
+                        "description": "Wrap matches in <mark>...</mark> in showLines output; matchColumns still refer to the unmarked line (default: false)"
+                    },
                     "maxResults": {
                         "type": "integer",
                         "description": "Max results (0=unlimited, default: 50)"
                     },
+                    "pageSize": {
+                        "type": "integer",
+                        "description": "Return ranked files in pages of this size, replacing maxResults. The summary carries nextCursor and hasMore"
+                    },
+                    "cursor": {
+                        "type": "string",
+                        "description": "nextCursor of the previous page, with otherwise identical parameters. Expires when the index changes"
+                    },
                     "excludeDir": {
                         "type": "array",
                         "items": { "type": "string" },
                         "description": "Directory names to exclude"
                     },
+                    "owner": {
+                        "type": "string",
+                        "description": "Only files owned by this team/user per CODEOWNERS (case-insensitive substring, '@' optional, e.g. 'payments'). Every result carries an 'owners' array whenever an ownership file is loaded."
+                    },
                     "exclude": {
                         "type": "array",
                         "items": { "type": "string" },
@@ -90,9 +149,69 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                         "type": "boolean",
                         "description": "Return counts only (default: false)"
                     },
+                    "estimate": {
+                        "type": "boolean",
+                        "description": "Approximate counts only, in microseconds: candidate files are counted exactly from the index, path filters are checked on a sample. Returns totalFiles with 95% bounds totalFilesLow/High. For 'roughly how widespread is X'. Not with phrase (default: false)"
+                    },
                     "substring": {
                         "type": "boolean",
                         "description": "Match within tokens (default: true). Auto-disabled for regex/phrase."
+                    },
+                    "wordBoundary": {
+                        "type": "boolean",
+                        "description": "Substring mode: match only from one identifier-part boundary to another (camelCase hump, '_', digit, start/end), so 'cat' finds CatName and cat_id but not concatenate. Not with regex or phrase (default: false)"
+                    },
+                    "caseSensitive": {
+                        "type": "boolean",
+                        "description": "Match terms in the case typed, e.g. 'ID' but not 'id', 'Map' but not 'map'. The index finds candidates regardless of case, then the candidate lines are read and checked, so this is slower on common terms. Exact, substring and phrase modes; not with regex or estimate (default: false)"
+                    },
+                    "sortBy": {
+                        "type": "string",
+                        "enum": ["score", "path", "mtime", "matches"],
+                        "description": "Result order (default: score = TF-IDF). path for stable output, mtime for newest first"
+                    },
+                    "groupBy": {
+                        "type": "string",
+                        "enum": ["dir"],
+                        "description": "Aggregate matches per directory instead of listing files: groups[] of {dir, files, occurrences, topFiles} with the most occurrences first, a heat map of where a term lives. maxResults limits the groups. Not with countOnly, estimate or pagination"
+                    },
+                    "groupDepth": {
+                        "type": "integer",
+                        "description": "With groupBy=dir: group by the first N directory levels under the server dir (default: 0 = each file's own directory)"
+                    },
+                    "relatedTerms": {
+                        "type": "integer",
+                        "description": "Return up to N rare tokens that co-occur in the matched files as summary.relatedTerms, e.g. factory/interface/test names around a symbol (default: 0 = off, max: 50). Token and substring modes only."
+                    },
+                    "explain": {
+                        "type": "boolean",
+                        "description": "Add an 'explain' object to each file: per-token tf, docFreq, idf and contribution to the score, plus boosts applied, and summary.scoring with the formula. Not with phrase (default: false)"
+                    },
+                    "rerank": {
+                        "type": "boolean",
+                        "description": "Rerank by reciprocal rank fusion with search_semantic similarity; adds a per-file 'rerank' object. Not with phrase, sortBy or rev (default: false)"
+                    },
+                    "rankHook": {
+                        "type": "boolean",
+                        "description": "false = ignore --rank-hook (default: true)"
+                    },
+                    "boosts": {
+                        "type": "boolean",
+                        "description": "Apply the [ranking] factors of .search-index.toml to scores: files named after a term (x2) or defining a type/method of that name (x1.5) rank higher, test and generated files (x0.5) lower. false ranks by plain TF-IDF (default: true)"
+                    },
+                    "rev": {
+                        "type": "string",
+                        "description": "Search the code as of this git revision (commit, branch, tag, HEAD~N) instead of the working tree. The first query per commit builds an index from git blobs (seconds on large repos); later queries reuse the cached index. Response summary includes the resolved commit hash."
+                    },
+                    "withinDefinition": {
+                        "type": "object",
+                        "properties": {
+                            "file": { "type": "string", "description": "File path or path substring" },
+                            "name": { "type": "string", "description": "Method/class/property name" },
+                            "parent": { "type": "string", "description": "Containing class, to pick one of several same-named definitions" }
+                        },
+                        "required": ["file", "name"],
+                        "description": "Only match lines inside this definition's body, e.g. {file: 'UserService.cs', name: 'GetUserAsync'} to ask whether one method references the cache without reading the file. Requires the definition index. Not with rev or estimate."
                     }
                 },
                 "required": ["terms"]
@@ -152,9 +271,33 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                 "required": []
             }),
         },
+        ToolDefinition {
+            name: "search_metrics".to_string(),
+            description: "Server metrics for monitoring: calls, errors and a latency histogram per tool, truncated responses, file watcher events and batches, and the current index sizes. Counters only grow; compare two reads for rates. format=prometheus returns the Prometheus text exposition format instead of JSON.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "format": { "type": "string", "enum": ["json", "prometheus"], "description": "Output format (default: json)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_history".to_string(),
+            description: "Recent tool calls of this session, newest first: tool, arguments, hits (result count), elapsedMs and secondsAgo. Use it to recall what was already searched and which queries came back empty. When search_grep or search_definitions finds nothing, its summary carries didYouMean: for each unknown term, earlier successful terms and close entries of the token or definition name dictionary.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "tool": { "type": "string", "description": "Only calls of this tool, e.g. search_grep" },
+                    "emptyOnly": { "type": "boolean", "description": "Only calls that found nothing (default: false)" },
+                    "limit": { "type": "integer", "description": "Maximum calls to return (default: 20, max: 200)" }
+                },
+                "required": []
+            }),
+        },
         ToolDefinition {
             name: "search_reindex".to_string(),
-            description: "Force rebuild the content index and reload it into the server's in-memory cache. Useful after many file changes or when --watch is not enabled. The rebuilt index replaces the current in-memory index immediately.".to_string(),
+            description: "Force rebuild the content index and reload it into the server's in-memory cache. Useful after many file changes or when --watch is not enabled. The rebuild runs in the background: the call returns a jobId at once, queries keep using the current index, and the new one is swapped in atomically when done. Poll search_reindex_status for progress, or pass wait=true to block until the rebuild finishes.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -162,11 +305,26 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                     "ext": {
                         "type": "string",
                         "description": "File extensions (comma-separated)"
+                    },
+                    "wait": {
+                        "type": "boolean",
+                        "description": "Block until the rebuild finishes and return its result (default: false)"
                     }
                 },
                 "required": []
             }),
         },
+        ToolDefinition {
+            name: "search_reindex_status".to_string(),
+            description: "Status of a background search_reindex job: running (with build phase and files done), completed (files, uniqueTokens, rebuildTimeMs) or failed (error). Defaults to the latest job.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "jobId": { "type": "string", "description": "Job id returned by search_reindex (default: the latest job)" }
+                },
+                "required": []
+            }),
+        },
         ToolDefinition {
             name: "search_reindex_definitions".to_string(),
             description: "Force rebuild the AST definition index (tree-sitter) and reload it into the server's in-memory cache. Returns build metrics: files parsed, definitions extracted, call sites, codeStatsEntries (methods with complexity metrics), parse errors, build time, and index size. After rebuild, code stats are available for includeCodeStats/sortBy/min* queries. Requires server started with --definitions flag.".to_string(),
@@ -184,13 +342,13 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
         },
         ToolDefinition {
             name: "search_definitions".to_string(),
-            description: "Search code definitions -- classes, interfaces, methods, properties, enums. Uses pre-built tree-sitter AST index for instant results (~0.001s). LANGUAGE-SPECIFIC: Supports C# and TypeScript/TSX (tree-sitter grammars). SQL parser retained but disabled. Requires server started with --definitions flag. Supports 'containsLine' to find which method/class contains a given line number (no more manual read_file!). Supports 'includeBody' to return actual source code inline, eliminating read_file calls.".to_string(),
+            description: "Search code definitions -- classes, interfaces, methods, properties, enums. Pre-built tree-sitter AST index (~0.001s). Supports C# and TypeScript/TSX (tree-sitter grammars). SQL parser retained but disabled. Requires server started with --definitions flag. Supports 'containsLine' to find which method/class contains a given line number. Supports 'includeBody' to return actual source code inline, eliminating read_file calls.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
                     "name": {
                         "type": "string",
-                        "description": "Name to search (substring). Comma-separated for multi-term OR."
+                        "description": "Name to search (substring). Comma-separated for multi-term OR. A dotted name also matches the qualified name: 'Billing.InvoiceService', 'MyCompany.Billing.InvoiceService.Create'."
                     },
                     "kind": {
                         "type": "string",
@@ -213,6 +371,10 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                         "type": "string",
                         "description": "Filter by parent/containing class name."
                     },
+                    "namespace": {
+                        "type": "string",
+                        "description": "Filter by namespace, including nested namespaces (case-insensitive). Example: 'MyCompany.Billing'."
+                    },
                     "containsLine": {
                         "type": "integer",
                         "description": "Find definition(s) containing this line number. Returns innermost method + parent class. Requires 'file' parameter."
@@ -225,11 +387,23 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                         "type": "integer",
                         "description": "Max results (default: 100, 0=unlimited)"
                     },
+                    "pageSize": {
+                        "type": "integer",
+                        "description": "Return definitions in pages of this size, replacing maxResults. The summary carries nextCursor and hasMore"
+                    },
+                    "cursor": {
+                        "type": "string",
+                        "description": "nextCursor of the previous page, with otherwise identical parameters. Expires when the index changes"
+                    },
                     "excludeDir": {
                         "type": "array",
                         "items": { "type": "string" },
                         "description": "Directory names to exclude"
                     },
+                    "owner": {
+                        "type": "string",
+                        "description": "Only definitions in files owned by this team/user per CODEOWNERS (case-insensitive substring, '@' optional). Results carry an 'owners' array whenever an ownership file is loaded."
+                    },
                     "includeBody": {
                         "type": "boolean",
                         "description": "Include source code body in results. Use maxBodyLines to control size. (default: false)"
@@ -246,18 +420,30 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                         "type": "boolean",
                         "description": "Return index coverage report instead of search results. (default: false)"
                     },
+                    "rankHook": {
+                        "type": "boolean",
+                        "description": "false = ignore --rank-hook (default: true)"
+                    },
                     "auditMinBytes": {
                         "type": "integer",
                         "description": "Min file size to flag as suspicious in audit (default: 500)"
                     },
+                    "docs": {
+                        "type": "string",
+                        "description": "Search C# XML doc comments: only definitions whose /// summary, param or returns text contains every word (case-insensitive). Implies includeDocs"
+                    },
+                    "includeDocs": {
+                        "type": "boolean",
+                        "description": "Include the XML doc comment of each C# definition as docs {summary, params [{name, description}], returns}. (default: false)"
+                    },
                     "includeCodeStats": {
                         "type": "boolean",
-                        "description": "Include complexity metrics (cyclomatic, cognitive, nesting, params, returns, calls, lambdas). Auto-enabled by sortBy/min*. (default: false)"
+                        "description": "Include complexity metrics (cyclomatic, cognitive, nesting, params, returns, calls, lambdas, Halstead, maintainability index; classes: wmc, lcom). Auto-enabled by sortBy/min*. (default: false)"
                     },
                     "sortBy": {
                         "type": "string",
-                        "enum": ["cyclomaticComplexity", "cognitiveComplexity", "maxNestingDepth", "paramCount", "returnCount", "callCount", "lambdaCount", "lines"],
-                        "description": "Sort by metric descending (worst first). Auto-enables includeCodeStats."
+                        "enum": ["cyclomaticComplexity", "cognitiveComplexity", "maxNestingDepth", "paramCount", "returnCount", "callCount", "lambdaCount", "lines", "halsteadVolume", "halsteadDifficulty", "maintainabilityIndex", "wmc", "lcom"],
+                        "description": "Sort by metric, worst first. wmc/lcom sort classes. Auto-enables includeCodeStats."
                     },
                     "minComplexity": {
                         "type": "integer",
@@ -282,6 +468,26 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                     "minCalls": {
                         "type": "integer",
                         "description": "Min call count (fan-out). Auto-enables includeCodeStats."
+                    },
+                    "minHalsteadVolume": {
+                        "type": "number",
+                        "description": "Min Halstead volume. Auto-enables includeCodeStats."
+                    },
+                    "minHalsteadDifficulty": {
+                        "type": "number",
+                        "description": "Min Halstead difficulty. Auto-enables includeCodeStats."
+                    },
+                    "maxMaintainabilityIndex": {
+                        "type": "integer",
+                        "description": "Max maintainability index (0-100, lower is worse). Auto-enables includeCodeStats."
+                    },
+                    "minWmc": {
+                        "type": "integer",
+                        "description": "Classes: min weighted methods per class (sum of member cyclomatic complexity)."
+                    },
+                    "minLcom": {
+                        "type": "integer",
+                        "description": "Classes: min lack of cohesion (LCOM4; 1 = cohesive)."
                     }
                 },
                 "required": []
@@ -289,7 +495,7 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
         },
         ToolDefinition {
             name: "search_callers".to_string(),
-            description: "RECOMMENDED for call chain analysis -- find all callers of a method and build a call tree (up or down) in a SINGLE sub-millisecond request. Supports C# and TypeScript/TSX. DI-aware. Returns a hierarchical call tree with method signatures, file paths, and line numbers. Always specify the 'class' parameter to avoid mixing callers from unrelated classes. Requires server started with --definitions flag. Limitation: calls through local variables (e.g., `var x = service.GetFoo(); x.Bar()`) may not be detected because the tool uses AST parsing without type inference. DI-injected fields, `this`/`base` calls, and direct receiver calls are fully supported.".to_string(),
+            description: "RECOMMENDED for call chain analysis -- find all callers of a method and build a call tree (up or down) in a SINGLE sub-millisecond request. Supports C# and TypeScript/TSX. DI-aware. Returns a hierarchical call tree with method signatures, file paths, and line numbers (plus owning teams when a CODEOWNERS file is loaded). Always specify the 'class' parameter to avoid mixing callers from unrelated classes. Requires server started with --definitions flag. Limitation: calls through local variables (e.g., `var x = service.GetFoo(); x.Bar()`) may not be detected because the tool uses AST parsing without type inference. DI-injected fields, `this`/`base` calls, and direct receiver calls are fully supported.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -310,6 +516,15 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                         "enum": ["up", "down"],
                         "description": "'up' = callers (default), 'down' = callees."
                     },
+                    "argCount": {
+                        "type": "integer",
+                        "description": "Only the overloads taking this many arguments (optional and params/rest parameters counted), and only call sites passing exactly this many. Up: callers of those overloads; down: their callees"
+                    },
+                    "paramTypes": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only the overload with these parameter types, in order, e.g. [\"int\", \"string\"]. Case-insensitive, namespace qualifiers ignored; a generic parameter T matches any type and List matches List<T>. Call sites are matched by argument count and known argument types; query.overloads lists the selected overloads"
+                    },
                     "ext": {
                         "type": "string",
                         "description": "File extension filter (default: server's --ext)"
@@ -332,6 +547,19 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                         "type": "integer",
                         "description": "Max total nodes in call tree (default: 200)"
                     },
+                    "exportFormat": {
+                        "type": "string",
+                        "enum": ["dot", "mermaid", "json"],
+                        "description": "Return the tree as a graph instead of callTree: dot (Graphviz) or mermaid text in graph, or json {nodes, edges} with one edge per caller/callee pair. For whole classes or namespaces use the search callgraph CLI"
+                    },
+                    "pageSize": {
+                        "type": "integer",
+                        "description": "Return the root-level nodes of the tree in pages of this size, replacing maxResults. The summary carries nextCursor and hasMore"
+                    },
+                    "cursor": {
+                        "type": "string",
+                        "description": "nextCursor of the previous page, with otherwise identical parameters. Expires when the index changes"
+                    },
                     "resolveInterfaces": {
                         "type": "boolean",
                         "description": "Auto-resolve interface methods to implementations (default: true)"
@@ -340,6 +568,298 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
                 "required": ["method"]
             }),
         },
+        ToolDefinition {
+            name: "search_read_file".to_string(),
+            description: "Read part of a file: a line range, a byte range, or the body of a named definition (resolved via the definition index). Use after search_grep/search_definitions instead of reading whole files. Output is capped to the response budget at a line boundary; summary.nextStartLine tells where to continue.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "File path (absolute or relative to server --dir). With symbol: optional path substring filter." },
+                    "startLine": { "type": "integer", "description": "First line, 1-based (default: 1)" },
+                    "endLine": { "type": "integer", "description": "Last line, inclusive (default: startLine + maxLines)" },
+                    "byteOffset": { "type": "integer", "description": "Read from this byte offset instead of a line range" },
+                    "byteLength": { "type": "integer", "description": "Number of bytes to read with byteOffset" },
+                    "symbol": { "type": "string", "description": "Definition name (exact, case-insensitive). Returns its body. Requires --definitions" },
+                    "parent": { "type": "string", "description": "Containing class/type to disambiguate symbol" },
+                    "maxLines": { "type": "integer", "description": "Max lines to return (0=unlimited, default: 200)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_outline".to_string(),
+            description: "Show the nested definition structure of one file (classes, members, line ranges, signatures) from the definition index, without reading the file. Use before search_read_file to pick the exact lines you need. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": { "type": "string", "description": "File path: absolute, or a unique suffix such as 'Services/UserService.cs'" }
+                },
+                "required": ["file"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_references".to_string(),
+            description: "Find all usages of a symbol, classified as call / instantiation / inheritance / typeReference / reference (field reads etc.). Covers what search_callers misses: type usages in signatures, generic arguments, base lists. Declarations are excluded. Uses the content index; --definitions adds AST-based classification and containing method.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Symbol name (single identifier, case-sensitive)" },
+                    "usage": { "type": "string", "description": "Comma-separated usage filter: call, instantiation, inheritance, typeReference, reference" },
+                    "file": { "type": "string", "description": "File path substring filter" },
+                    "excludeDir": { "type": "array", "items": { "type": "string" }, "description": "Exclude paths containing these substrings" },
+                    "maxResults": { "type": "integer", "description": "Max references (0=unlimited, default: 100)" }
+                },
+                "required": ["name"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_symbol_at".to_string(),
+            description: "Resolve the identifier at a file position (line + column, as an editor cursor) to its definition and references in one call. Tells a declaration apart from a call (using the parsed call site's receiver type to pick the right class) and ranks same-name definitions by the enclosing class. Returns the identifier, its column range, the definition (file, lines, signature), other candidates when ambiguous, and classified references. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": { "type": "string", "description": "File path: absolute, or a unique suffix such as 'Services/UserService.cs'" },
+                    "line": { "type": "integer", "description": "Line number (1-based)" },
+                    "column": { "type": "integer", "description": "Column (1-based, in characters). A column just past the end of an identifier also selects it." },
+                    "includeReferences": { "type": "boolean", "description": "Also list usages of the identifier (default: true)" },
+                    "maxReferences": { "type": "integer", "description": "Max references (0=unlimited, default: 20)" }
+                },
+                "required": ["file", "line", "column"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_type_hierarchy".to_string(),
+            description: "Show the inheritance tree of a class or interface: base classes and implemented interfaces (up) and subclasses/implementations (down), recursively, in one request. Use for 'who implements IFoo' or 'what does this class derive from'. Types not defined in the indexed code (framework base classes) appear as external nodes; their subtypes can still be listed. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Class, interface, struct, or record name (no namespace or generic arguments)" },
+                    "direction": { "type": "string", "enum": ["up", "down", "both"], "description": "'up' = base types, 'down' = subtypes/implementations, 'both' (default)" },
+                    "depth": { "type": "integer", "description": "Max levels in each direction (default: 3, max: 10)" },
+                    "file": { "type": "string", "description": "File path substring to pick one definition when several types share the name" },
+                    "maxTotalNodes": { "type": "integer", "description": "Max total nodes across all trees (default: 200)" }
+                },
+                "required": ["name"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_dependencies".to_string(),
+            description: "File-level dependency graph from using/import statements and call sites. With file: the files it depends on (dependsOn), the files that depend on it (dependents), unresolved package imports, and the shortest dependency cycle through it. Without file: every group of files that depend on each other (import cycles), largest first. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": { "type": "string", "description": "File path: absolute, or a unique suffix such as 'Services/UserService.cs'. Omit to list all cycles" },
+                    "direction": { "type": "string", "enum": ["dependsOn", "dependents", "both"], "description": "'dependsOn' = what the file uses, 'dependents' = what uses the file, 'both' (default)" },
+                    "maxResults": { "type": "integer", "description": "Max cycles listed without file (0=unlimited, default: 50)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_impact".to_string(),
+            description: "Test selection: the test methods likely affected by a change to a method or class. Walks the caller graph upward until it reaches tests (methods with [Test]/[Fact]/[TestMethod]-style attributes, or any method in a test file) and returns each test with the call path to the changed code, plus the distinct test files. Tests are not walked past. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "method": { "type": "string", "description": "Changed method name. With class: that class's method only" },
+                    "class": { "type": "string", "description": "Class of the method, or alone: every method of the class" },
+                    "depth": { "type": "integer", "description": "Max calls between a test and the changed code (default: 5, max: 10)" },
+                    "testPaths": { "type": "array", "items": { "type": "string" }, "description": "Path substrings of test files (default: test directories and *Tests / *.spec / *.test files)" },
+                    "testAttributes": { "type": "array", "items": { "type": "string" }, "description": "Attributes marking a test method (default: Test, TestCase, TestCaseSource, Fact, Theory, TestMethod, DataTestMethod)" },
+                    "maxResults": { "type": "integer", "description": "Max tests listed (0=unlimited, default: 100)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_di_registrations".to_string(),
+            description: "List C# dependency injection registrations (services.AddScoped<IFoo, Foo>(), AddTransient, AddSingleton, TryAdd*, typeof and factory forms) with lifetime, service interface, implementation, and file/line. Use for 'what implements IFoo at runtime' or 'which services are singletons'. search_callers uses the same registrations to resolve interface calls exactly. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "service": { "type": "string", "description": "Service type substring, case-insensitive (e.g. 'IOrderService')" },
+                    "implementation": { "type": "string", "description": "Implementation type substring, case-insensitive" },
+                    "lifetime": { "type": "string", "enum": ["scoped", "transient", "singleton"], "description": "Only registrations with this lifetime" },
+                    "file": { "type": "string", "description": "Only registrations in files whose path contains this substring" },
+                    "maxResults": { "type": "integer", "description": "Max registrations (0=unlimited, default: 100)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_endpoints".to_string(),
+            description: "Find ASP.NET HTTP endpoints: attribute-routed controller actions ([Route] + [HttpGet(\"{id}\")] etc., with [controller]/[action] replaced) and minimal-API app.MapGet/MapPost/... calls, including MapGroup prefixes. Pass a concrete path such as '/api/orders/42' to jump straight to the handling method: routes that serve it come first, most specific first. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "HTTP path or URL ('/api/orders/42'), matched against route templates; a fragment of a template ('orders') also matches" },
+                    "method": { "type": "string", "description": "HTTP method (GET, POST, ...). Actions with only [Route] match every method" },
+                    "handler": { "type": "string", "description": "Handler name substring, case-insensitive ('OrdersController', 'GetOrder')" },
+                    "file": { "type": "string", "description": "Only endpoints in files whose path contains this substring" },
+                    "maxResults": { "type": "integer", "description": "Max endpoints (0=unlimited, default: 50)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_namespaces".to_string(),
+            description: "Browse the namespace tree: C# namespaces and TypeScript namespace/module blocks, with file and type counts per namespace. Without namespace, lists the root namespaces; with one, lists its sub-namespaces and the types declared directly in it. Use search_definitions with namespace or a qualified name ('MyCompany.Billing.InvoiceService') to search inside one. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "namespace": { "type": "string", "description": "Full namespace to browse, case-insensitive ('MyCompany.Billing'). Default: the roots" },
+                    "depth": { "type": "integer", "description": "Levels of sub-namespaces to nest (default: 1, max: 10)" },
+                    "maxResults": { "type": "integer", "description": "Max types listed (0=unlimited, default: 100)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_dupes".to_string(),
+            description: "Find near-duplicate (copy-pasted) methods, constructors, and functions. Compares token shingles of every body from the definition index and reports clone pairs with Jaccard similarity at or above threshold, with file/line ranges for both sides. Reads the files of all scanned bodies, so narrow with file/excludeDir on very large repos. Requires --definitions.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "threshold": { "type": "number", "description": "Minimum similarity, 0-1 (default: 0.8)" },
+                    "minLines": { "type": "integer", "description": "Ignore bodies shorter than this many lines (default: 5)" },
+                    "shingleSize": { "type": "integer", "description": "Tokens per shingle (default: 5). Larger = stricter" },
+                    "file": { "type": "string", "description": "Only scan files whose path contains this substring" },
+                    "excludeDir": { "type": "array", "items": { "type": "string" }, "description": "Skip paths containing these substrings" },
+                    "maxResults": { "type": "integer", "description": "Max pairs returned (0=unlimited, default: 50)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_semantic".to_string(),
+            description: "Natural-language code search ('where do we retry payments'): ranks definitions by embedding similarity plus keyword TF-IDF. The default hash backend is keyword overlap only; semantic results need a real embedder ([semantic] in .search-index.toml).".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Natural-language question" },
+                    "keywordWeight": { "type": "number", "description": "Keyword score share, 0-1 (default: 0.3)" },
+                    "file": { "type": "string", "description": "Only paths containing this" },
+                    "maxResults": { "type": "integer", "description": "Default: 10" }
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_vocabulary".to_string(),
+            description: "List identifiers that actually exist in the indexed code, most widespread first, as compact [term, fileCount] pairs (lowercased). Call before guessing symbol names: filter with prefix/contains to check spellings and naming conventions instead of retrying greps that return zero results.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "prefix": { "type": "string", "description": "Only tokens starting with this (case-insensitive)" },
+                    "contains": { "type": "string", "description": "Only tokens containing this substring (case-insensitive)" },
+                    "minLength": { "type": "integer", "description": "Minimum token length (default: 3)" },
+                    "definedOnly": { "type": "boolean", "description": "Only names of definitions (classes, methods, ...). Requires --definitions (default: false)" },
+                    "limit": { "type": "integer", "description": "Max entries (default: 500, max: 10000)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_todos".to_string(),
+            description: "List TODO/FIXME/HACK/BUG comments recorded during content indexing, sorted by file and line. Set blame=true to add author, date, and age in days from git blame; age and author filters imply blame. Use for tech-debt triage ('FIXMEs older than a year under src/Payments').".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "marker": { "type": "string", "description": "Comma-separated markers to include: TODO, FIXME, HACK, BUG (default: all)" },
+                    "dir": { "type": "string", "description": "Only files under this directory (absolute, or relative to the indexed root)" },
+                    "excludeDir": { "type": "array", "items": { "type": "string" }, "description": "Skip paths containing these substrings" },
+                    "blame": { "type": "boolean", "description": "Add author/date/ageDays via git blame, up to 200 files per call (default: false)" },
+                    "author": { "type": "string", "description": "Only markers whose blamed author name or email contains this (implies blame)" },
+                    "minAgeDays": { "type": "integer", "description": "Only markers at least this many days old (implies blame)" },
+                    "maxAgeDays": { "type": "integer", "description": "Only markers at most this many days old (implies blame)" },
+                    "sortBy": { "type": "string", "enum": ["path", "age"], "description": "path (default) or age (oldest first, requires blame)" },
+                    "maxResults": { "type": "integer", "description": "Max markers returned (0=unlimited, default: 100)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_replace_preview".to_string(),
+            description: "Preview a search-and-replace across the indexed files: a unified diff per file that would change, with the number of replacements. Nothing is written; apply the same replacement with the `search replace --yes` CLI command, which keeps a backup journal for `--undo`. The index selects candidate files and every match is verified against the current file text.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string", "description": "Text to replace (literal unless regex=true)" },
+                    "replacement": { "type": "string", "description": "Replacement text. With regex=true, $1 / ${name} insert capture groups" },
+                    "regex": { "type": "boolean", "description": "Treat pattern as a regular expression; scans every indexed file (default: false)" },
+                    "ignoreCase": { "type": "boolean", "description": "Case-insensitive match (default: false)" },
+                    "wordBoundary": { "type": "boolean", "description": "Only whole-word matches (default: false)" },
+                    "ext": { "type": "string", "description": "Comma-separated extensions to include, e.g. 'cs,ts'" },
+                    "dir": { "type": "string", "description": "Only files under this directory (absolute, or relative to the indexed root)" },
+                    "contextLines": { "type": "integer", "description": "Unchanged lines shown around each change (default: 3)" },
+                    "maxFiles": { "type": "integer", "description": "Max file diffs returned (0=unlimited, default: 50); the summary counts all files" }
+                },
+                "required": ["pattern", "replacement"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_hotspots".to_string(),
+            description: "Refactoring hotspots: files (or methods) ranked by churn x complexity, where churn is the number of commits touching the file (git history cache) and complexity comes from code stats. Each hotspot lists its top authors, who are the people to ask before refactoring it. Requires --definitions and a git repository as the server --dir. Method churn is the churn of the containing file.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "level": { "type": "string", "enum": ["file", "method"], "description": "Rank files (default; complexity summed over their methods) or individual methods" },
+                    "metric": { "type": "string", "enum": ["cyclomatic", "cognitive"], "description": "Complexity metric (default: cyclomatic)" },
+                    "from": { "type": "string", "description": "Count commits from this date (YYYY-MM-DD, inclusive)" },
+                    "to": { "type": "string", "description": "Count commits up to this date (YYYY-MM-DD, inclusive)" },
+                    "date": { "type": "string", "description": "Count commits of one day only (YYYY-MM-DD); overrides from/to" },
+                    "dir": { "type": "string", "description": "Only files under this directory (absolute, or relative to the indexed root)" },
+                    "excludeDir": { "type": "array", "items": { "type": "string" }, "description": "Skip paths containing these substrings" },
+                    "owner": { "type": "string", "description": "Only files owned by this team/user per CODEOWNERS (case-insensitive substring)" },
+                    "topAuthors": { "type": "integer", "description": "Authors listed per hotspot, by commit count (default: 3)" },
+                    "maxResults": { "type": "integer", "description": "Max hotspots returned (0=unlimited, default: 20)" }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "search_tickets".to_string(),
+            description: "Traceability for an issue-tracker ID: the code comments that mention it (JIRA-style PAY-812 or GitHub-style #77) and the commits whose subject mentions it, with the files each commit changed. Code references are recorded when the content index is built; commits come from the git history cache and are skipped while it is still building.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "ticket": { "type": "string", "description": "Ticket ID: a tracker key like PAY-812 (case-insensitive) or an issue number like #77 or 77" },
+                    "dir": { "type": "string", "description": "Only code under this directory (absolute, or relative to the indexed root)" },
+                    "excludeDir": { "type": "array", "items": { "type": "string" }, "description": "Skip paths containing these substrings" },
+                    "owner": { "type": "string", "description": "Only code owned by this team/user per CODEOWNERS (case-insensitive substring)" },
+                    "maxResults": { "type": "integer", "description": "Max files and max commits returned (0=unlimited, default: 50)" }
+                },
+                "required": ["ticket"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_density".to_string(),
+            description: "Where in a file the hits of a term cluster: line hits per fixed-size region (default 100 lines) for each matching file, plus the densest regions with their first and last hit line. Use on very large files to read the one region that matters instead of paging through hundreds of grep line hits. Terms are exact indexed tokens (case-insensitive).".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "terms": { "type": "string", "description": "Comma-separated tokens; hits of all terms are counted together" },
+                    "file": { "type": "string", "description": "Only files whose path contains this substring" },
+                    "dir": { "type": "string", "description": "Only files under this directory (absolute, or relative to the indexed root)" },
+                    "excludeDir": { "type": "array", "items": { "type": "string" }, "description": "Skip paths containing these substrings" },
+                    "bucketSize": { "type": "integer", "description": "Lines per region (default: 100)" },
+                    "topBuckets": { "type": "integer", "description": "Densest regions listed per file (default: 3)" },
+                    "maxFiles": { "type": "integer", "description": "Max files returned, most hits first (0=unlimited, default: 10)" }
+                },
+                "required": ["terms"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_audit".to_string(),
+            description: "Run a saved audit suite from the [audit] section of .search-index.toml and report pass/fail per check. Each check is one or more search tool calls (grep, definitions, find, ...) whose result counts are summed and asserted to be zero (forbidden pattern) or non-zero (required pattern). Index-speed policy checks, e.g. 'no Thread.Sleep in services'. Without suite, lists the configured suites.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "suite": { "type": "string", "description": "Suite name (case-insensitive). Omit to list suites and their checks" }
+                },
+                "required": []
+            }),
+        },
         ToolDefinition {
             name: "search_help".to_string(),
             description: "Show best practices and usage tips for search-index tools. Call this when unsure which tool to use or how to optimize queries. Returns a concise guide with tool selection priorities, performance tiers, and common pitfalls.".to_string(),
@@ -359,7 +879,8 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
 
 /// Context for tool handlers -- shared state
 pub struct HandlerContext {
-    pub index: Arc<RwLock<ContentIndex>>,
+    /// Live content index; handlers query a [`Searcher::snapshot`] of it.
+    pub index: Searcher,
     pub def_index: Option<Arc<RwLock<DefinitionIndex>>>,
     pub server_dir: String,
     pub server_ext: String,
@@ -381,9 +902,33 @@ pub struct HandlerContext {
     pub git_cache: Arc<RwLock<Option<GitHistoryCache>>>,
     /// Fast readiness check for git cache (avoids RwLock contention).
     pub git_cache_ready: Arc<AtomicBool>,
+    /// Commit content index for `search_git_grep` — built on first use, then
+    /// updated incrementally as HEAD moves. Holds one repository at a time.
+    pub commit_index: Arc<Mutex<Option<CommitIndex>>>,
+    /// Chunk embeddings for `search_semantic` — built on first use, then refreshed on the
+    /// next call after the index generation moves. Taken out of the lock to refresh, so
+    /// a slow embeddings server never blocks other calls on it.
+    pub semantic_index: Arc<Mutex<Option<Arc<SemanticIndex>>>>,
     /// Current checked-out branch name (detected at server startup).
     /// Used to inject branchWarning into index-based tool responses.
     pub current_branch: Option<String>,
+    /// Ownership rules (CODEOWNERS / owners.yaml) used to annotate grep, definitions
+    /// and callers results and to apply the `owner` filter. `None` when no file is loaded.
+    pub owners: Option<Arc<Owners>>,
+    /// Index generation: bumped on every background build, watcher batch and reindex,
+    /// returned as `_meta.indexGeneration` on tool results so clients can invalidate caches.
+    pub generation: Arc<Generation>,
+    /// Whether the client subscribed to the index resource and wants
+    /// `notifications/resources/updated` when the generation changes.
+    pub index_subscribed: Arc<AtomicBool>,
+    /// Background `search_reindex` jobs, polled with `search_reindex_status`.
+    pub reindex_jobs: Arc<Mutex<ReindexJobs>>,
+    /// Recent tool calls of the client session, for `search_history` and "did you mean" suggestions.
+    pub query_history: Arc<Mutex<QueryHistory>>,
+    /// `[ranking]` factors of `.search-index.toml`, applied to search_grep scores.
+    pub ranking: RankingBoosts,
+    /// `--rank-hook` policy module, re-scoring grep and definitions results before truncation.
+    pub rank_hook: Option<Arc<RankHook>>,
 }
 
 /// Message returned when the content index is still building in background.
@@ -406,15 +951,15 @@ const SEARCH_HELP_MIN_RESPONSE_BYTES: usize = 20_480;
 
 /// Returns true when a tool requires the content index to be ready.
 fn requires_content_index(tool_name: &str) -> bool {
-    matches!(tool_name, "search_grep" | "search_fast" | "search_reindex")
+    matches!(tool_name, "search_grep" | "search_fast" | "search_references" | "search_symbol_at" | "search_vocabulary" | "search_todos" | "search_tickets" | "search_replace_preview" | "search_density" | "search_semantic" | "search_reindex")
 }
 
 /// Returns true when a tool requires the definition index to be ready.
 fn requires_def_index(tool_name: &str) -> bool {
-    matches!(tool_name, "search_definitions" | "search_callers" | "search_reindex_definitions")
+    matches!(tool_name, "search_definitions" | "search_callers" | "search_outline" | "search_symbol_at" | "search_type_hierarchy" | "search_dependencies" | "search_impact" | "search_di_registrations" | "search_endpoints" | "search_namespaces" | "search_dupes" | "search_hotspots" | "search_reindex_definitions")
 }
 
-/// Dispatch a tool call to the right handler.
+/// Dispatch a tool call to the right handler, and count it in the server metrics.
 /// When `ctx.metrics` is true, injects performance metrics into the response summary.
 pub fn dispatch_tool(
     ctx: &HandlerContext,
@@ -422,41 +967,77 @@ pub fn dispatch_tool(
     arguments: &Value,
 ) -> ToolCallResult {
     let dispatch_start = Instant::now();
+    let result = dispatch_tool_from(ctx, tool_name, arguments, dispatch_start);
+    search::metrics::record_call(tool_name, dispatch_start.elapsed(), result.is_error);
+    ctx.query_history.lock().unwrap_or_else(|e| e.into_inner())
+        .record(tool_name, arguments, &result, dispatch_start.elapsed());
+    result
+}
+
+fn dispatch_tool_from(
+    ctx: &HandlerContext,
+    tool_name: &str,
+    arguments: &Value,
+    dispatch_start: Instant,
+) -> ToolCallResult {
 
     // Check readiness: if the required index is still building, return early
     if requires_content_index(tool_name) && !ctx.content_ready.load(Ordering::Acquire) {
         if tool_name == "search_reindex" {
-            return ToolCallResult::error(ALREADY_BUILDING_MSG.to_string());
+            return SearchError::IndexBuilding(ALREADY_BUILDING_MSG.to_string()).into();
         }
-        return ToolCallResult::error(INDEX_BUILDING_MSG.to_string());
+        return SearchError::IndexBuilding(INDEX_BUILDING_MSG.to_string()).into();
     }
     if requires_def_index(tool_name) && !ctx.def_ready.load(Ordering::Acquire) {
         if tool_name == "search_reindex_definitions" {
-            return ToolCallResult::error(ALREADY_BUILDING_MSG.to_string());
+            return SearchError::IndexBuilding(ALREADY_BUILDING_MSG.to_string()).into();
         }
-        return ToolCallResult::error(DEF_INDEX_BUILDING_MSG.to_string());
+        return SearchError::IndexBuilding(DEF_INDEX_BUILDING_MSG.to_string()).into();
     }
 
-    let result = match tool_name {
+    let result = lock_stats::record_query(|| match tool_name {
         "search_grep" => grep::handle_search_grep(ctx, arguments),
         "search_find" => find::handle_search_find(ctx, arguments),
         "search_fast" => fast::handle_search_fast(ctx, arguments),
         "search_info" => handle_search_info(ctx),
-        "search_reindex" => handle_search_reindex(ctx, arguments),
+        "search_metrics" => metrics::handle_search_metrics(ctx, arguments),
+        "search_history" => history::handle_search_history(ctx, arguments),
+        "search_reindex" => reindex::handle_search_reindex(ctx, arguments),
+        "search_reindex_status" => reindex::handle_search_reindex_status(ctx, arguments),
         "search_reindex_definitions" => handle_search_reindex_definitions(ctx, arguments),
         "search_definitions" => definitions::handle_search_definitions(ctx, arguments),
         "search_callers" => callers::handle_search_callers(ctx, arguments),
+        "search_read_file" => read_file::handle_search_read_file(ctx, arguments),
+        "search_outline" => outline::handle_search_outline(ctx, arguments),
+        "search_references" => references::handle_search_references(ctx, arguments),
+        "search_symbol_at" => symbol_at::handle_search_symbol_at(ctx, arguments),
+        "search_type_hierarchy" => hierarchy::handle_search_type_hierarchy(ctx, arguments),
+        "search_dependencies" => dependencies::handle_search_dependencies(ctx, arguments),
+        "search_impact" => impact::handle_search_impact(ctx, arguments),
+        "search_di_registrations" => di::handle_search_di_registrations(ctx, arguments),
+        "search_endpoints" => endpoints::handle_search_endpoints(ctx, arguments),
+        "search_namespaces" => namespaces::handle_search_namespaces(ctx, arguments),
+        "search_dupes" => dupes::handle_search_dupes(ctx, arguments),
+        "search_semantic" => semantic::handle_search_semantic(ctx, arguments),
+        "search_vocabulary" => vocabulary::handle_search_vocabulary(ctx, arguments),
+        "search_todos" => todos::handle_search_todos(ctx, arguments),
+        "search_hotspots" => hotspots::handle_search_hotspots(ctx, arguments),
+        "search_replace_preview" => replace::handle_search_replace_preview(ctx, arguments),
+        "search_tickets" => tickets::handle_search_tickets(ctx, arguments),
+        "search_density" => density::handle_search_density(ctx, arguments),
+        "search_audit" => audit::handle_search_audit(ctx, arguments),
         "search_help" => handle_search_help(),
         // Git history tools
-        "search_git_history" | "search_git_diff" | "search_git_authors" | "search_git_activity" | "search_git_blame" | "search_branch_status" => {
+        "search_git_history" | "search_git_diff" | "search_git_authors" | "search_git_activity" | "search_git_blame" | "search_git_grep" | "search_git_added_lines" | "search_branch_status" => {
             git::dispatch_git_tool(ctx, tool_name, arguments)
         }
-        _ => return ToolCallResult::error(format!("Unknown tool: {}", tool_name)),
-    };
+        _ => ToolCallResult::error(format!("Unknown tool: {}", tool_name)),
+    });
 
     if result.is_error {
         return result;
     }
+    let result = history::suggest_if_empty(ctx, tool_name, arguments, result);
 
     // search_help is reference content (best practices, strategies, examples).
     // Use a larger response budget (32KB) to avoid truncating static help text.
@@ -498,35 +1079,39 @@ fn handle_search_info(ctx: &HandlerContext) -> ToolCallResult {
 
     // ── Content index (in-memory) ──
     if ctx.content_ready.load(Ordering::Acquire) {
-        if let Ok(idx) = ctx.index.read() {
-            if !idx.files.is_empty() {
-                // Get disk file size without loading
-                let exts_str = idx.extensions.join(",");
-                let disk_path = crate::index::content_index_path_for(&idx.root, &exts_str, &ctx.index_base);
-                let size_mb = std::fs::metadata(&disk_path)
-                    .map(|m| (m.len() as f64 / 1_048_576.0 * 10.0).round() / 10.0)
-                    .unwrap_or(0.0);
-
-                let age_secs = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or(std::time::Duration::ZERO)
-                    .as_secs()
-                    .saturating_sub(idx.created_at);
-
-                indexes.push(json!({
-                    "type": "content",
-                    "root": idx.root,
-                    "files": idx.files.len(),
-                    "uniqueTokens": idx.index.len(),
-                    "totalTokens": idx.total_tokens,
-                    "extensions": idx.extensions,
-                    "sizeMb": size_mb,
-                    "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
-                    "inMemory": true,
-                }));
+        let idx = ctx.index.snapshot();
+        if !idx.files.is_empty() {
+            // Get disk file size without loading
+            let exts_str = idx.extensions.join(",");
+            let disk_path = crate::sqlite::stored_path(&crate::index::content_index_path_for(&idx.root, &exts_str, &ctx.index_base));
+            let size_mb = std::fs::metadata(&disk_path)
+                .map(|m| (m.len() as f64 / 1_048_576.0 * 10.0).round() / 10.0)
+                .unwrap_or(0.0);
+
+            let age_secs = ctx.generation.clock().now_secs().saturating_sub(idx.created_at);
+
+            let mut content_info = json!({
+                "type": "content",
+                "root": idx.root,
+                "files": idx.files.len(),
+                "uniqueTokens": idx.index.len(),
+                "totalTokens": idx.total_tokens,
+                "extensions": idx.extensions,
+                "sizeMb": size_mb,
+                "ageHours": (age_secs as f64 / 3600.0 * 10.0).round() / 10.0,
+                "stale": ctx.generation.is_stale(idx.created_at, idx.max_age_secs),
+                "tokenizer": {
+                    "minTokenLen": idx.tokenizer.min_token_len,
+                    "maxTokenLen": idx.tokenizer.max_token_len,
+                },
+                "inMemory": true,
+            });
+            if !idx.walk_limits.is_unlimited() {
+                content_info["walkLimits"] = json!({ "maxDepth": idx.walk_limits.max_depth, "pruneDirs": idx.walk_limits.prune_dirs });
             }
-            memory_estimate["contentIndex"] = crate::index::estimate_content_index_memory(&idx);
+            indexes.push(content_info);
         }
+        memory_estimate["contentIndex"] = crate::index::estimate_content_index_memory(&idx);
     } else {
         indexes.push(json!({
             "type": "content",
@@ -537,20 +1122,16 @@ fn handle_search_info(ctx: &HandlerContext) -> ToolCallResult {
     // ── Definition index (in-memory) ──
     if let Some(ref def_arc) = ctx.def_index {
         if ctx.def_ready.load(Ordering::Acquire) {
-            if let Ok(idx) = def_arc.read() {
+            if let Ok(idx) = lock_stats::timed(Lock::Definitions, || def_arc.read()) {
                 if !idx.files.is_empty() {
-                    let disk_path = crate::definitions::definition_index_path_for(
+                    let disk_path = crate::sqlite::stored_path(&crate::definitions::definition_index_path_for(
                         &idx.root, &idx.extensions.join(","), &ctx.index_base,
-                    );
+                    ));
                     let size_mb = std::fs::metadata(&disk_path)
                         .map(|m| (m.len() as f64 / 1_048_576.0 * 10.0).round() / 10.0)
                         .unwrap_or(0.0);
 
-                    let age_secs = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or(std::time::Duration::ZERO)
-                        .as_secs()
-                        .saturating_sub(idx.created_at);
+                    let age_secs = ctx.generation.clock().now_secs().saturating_sub(idx.created_at);
 
                     let call_sites: usize = idx.method_calls.values().map(|v| v.len()).sum();
                     let mut def_info = json!({
@@ -584,7 +1165,7 @@ fn handle_search_info(ctx: &HandlerContext) -> ToolCallResult {
 
     // ── File list index (disk metadata only — small file, no full deserialization) ──
     {
-        let file_index_path = crate::index::index_path_for(&ctx.server_dir, &ctx.index_base);
+        let file_index_path = crate::sqlite::stored_path(&crate::index::index_path_for(&ctx.server_dir, &ctx.index_base));
         if file_index_path.exists() {
             if let Some(root) = crate::index::read_root_from_index_file_pub(&file_index_path) {
                 let size_mb = std::fs::metadata(&file_index_path)
@@ -601,7 +1182,7 @@ fn handle_search_info(ctx: &HandlerContext) -> ToolCallResult {
 
     // ── Git cache (in-memory) ──
     if ctx.git_cache_ready.load(Ordering::Acquire) {
-        if let Ok(guard) = ctx.git_cache.read() {
+        if let Ok(guard) = lock_stats::timed(Lock::Git, || ctx.git_cache.read()) {
             if let Some(ref cache) = *guard {
                 let cache_path = crate::git::cache::GitHistoryCache::cache_path_for(&ctx.server_dir, &ctx.index_base);
                 let size_mb = std::fs::metadata(&cache_path)
@@ -637,69 +1218,12 @@ fn handle_search_info(ctx: &HandlerContext) -> ToolCallResult {
     if !memory_estimate.as_object().map_or(true, |m| m.is_empty()) {
         info["memoryEstimate"] = memory_estimate;
     }
-
-    ToolCallResult::success(serde_json::to_string(&info).unwrap())
-}
-
-fn handle_search_reindex(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
-    let dir = args.get("dir").and_then(|v| v.as_str()).unwrap_or(&ctx.server_dir);
-    let ext = args.get("ext").and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| ctx.server_ext.clone());
-
-    // Check dir matches server dir
-    let requested = std::fs::canonicalize(dir)
-        .map(|p| clean_path(&p.to_string_lossy()))
-        .unwrap_or_else(|_| dir.to_string());
-    let server = std::fs::canonicalize(&ctx.server_dir)
-        .map(|p| clean_path(&p.to_string_lossy()))
-        .unwrap_or_else(|_| ctx.server_dir.clone());
-    if !requested.eq_ignore_ascii_case(&server) {
-        return ToolCallResult::error(format!(
-            "Server started with --dir {}. For other directories, start another server instance or use CLI.",
-            ctx.server_dir
-        ));
-    }
-
-    info!(dir = %dir, ext = %ext, "Rebuilding content index");
-    let start = Instant::now();
-
-    let new_index = build_content_index(&ContentIndexArgs {
-        dir: dir.to_string(),
-        ext: ext.clone(),
-        max_age_hours: 24,
-        hidden: false,
-        no_ignore: false,
-        threads: 0,
-        min_token_len: 2,
-    });
-
-    // Save to disk
-    if let Err(e) = save_content_index(&new_index, &ctx.index_base) {
-        warn!(error = %e, "Failed to save reindexed content to disk");
-    }
-
-    let file_count = new_index.files.len();
-    let token_count = new_index.index.len();
-
-    // Update in-memory cache
-    match ctx.index.write() {
-        Ok(mut idx) => {
-            *idx = new_index;
-        }
-        Err(e) => return ToolCallResult::error(format!("Failed to update in-memory index: {}", e)),
+    // Only with `serve --lock-metrics`
+    if let Some(lock_wait) = lock_stats::summary() {
+        info["lockWait"] = lock_wait;
     }
 
-    let elapsed = start.elapsed();
-
-    let output = json!({
-        "status": "ok",
-        "files": file_count,
-        "uniqueTokens": token_count,
-        "rebuildTimeMs": elapsed.as_secs_f64() * 1000.0,
-    });
-
-    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+    ToolCallResult::success(serde_json::to_string(&info).unwrap())
 }
 
 fn handle_search_reindex_definitions(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
@@ -731,11 +1255,23 @@ fn handle_search_reindex_definitions(ctx: &HandlerContext, args: &Value) -> Tool
 
     info!(dir = %dir, ext = %ext, "Rebuilding definition index");
     let start = Instant::now();
+    // The rebuild walks the same part of the tree, and imports the same files, as the
+    // build it replaces
+    let (walk_limits, imports) = lock_stats::timed(Lock::Definitions, || def_index_arc.read())
+        .map(|idx| (idx.walk_limits.clone(), idx.imports.clone()))
+        .unwrap_or_default();
 
     let new_index = crate::definitions::build_definition_index(&crate::definitions::DefIndexArgs {
         dir: dir.to_string(),
         ext: ext.clone(),
         threads: 0,
+        dry_run: false,
+        build_priority: BuildPriority::Normal,
+        io_limit_mb: 0,
+        max_depth: walk_limits.max_depth,
+        prune_dir: walk_limits.prune_dirs,
+        storage: None,
+        import: imports,
     });
 
     // Save to disk
@@ -760,6 +1296,7 @@ fn handle_search_reindex_definitions(ctx: &HandlerContext, args: &Value) -> Tool
         }
         Err(e) => return ToolCallResult::error(format!("Failed to update in-memory definition index: {}", e)),
     }
+    ctx.generation.bump();
 
     let elapsed = start.elapsed();
 
@@ -0,0 +1,113 @@
+//! search_namespaces handler: browse the namespace tree of the definition index.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{namespace_tree, DefinitionIndex, NamespaceNode};
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+/// Full names of the namespaces directly under `parent` (the roots for `None`).
+fn children<'a>(tree: &'a BTreeMap<String, NamespaceNode>, parent: Option<&str>) -> Vec<&'a str> {
+    tree.keys()
+        .filter(|name| match parent {
+            Some(p) => name.len() > p.len() + 1 && name.starts_with(p) && name.as_bytes()[p.len()] == b'.' && !name[p.len() + 1..].contains('.'),
+            None => !name.contains('.'),
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+/// `name` and its sub-namespaces down to `depth` levels.
+fn node_json(tree: &BTreeMap<String, NamespaceNode>, name: &str, depth: usize) -> Value {
+    let node = &tree[name];
+    let subs = children(tree, Some(name));
+    let mut obj = json!({
+        "name": name,
+        "files": node.files.len(),
+        "types": node.types.len(),
+        "namespaces": subs.len(),
+    });
+    if depth > 1 && !subs.is_empty() {
+        obj["children"] = json!(subs.iter().map(|s| node_json(tree, s, depth - 1)).collect::<Vec<_>>());
+    }
+    obj
+}
+
+fn type_json(index: &DefinitionIndex, def_id: u32) -> Value {
+    let def = &index.definitions[def_id as usize];
+    json!({
+        "name": def.name,
+        "kind": def.kind.as_str(),
+        "file": index.files.get(def.file_id as usize).map(|s| s.as_str()).unwrap_or(""),
+        "lines": format!("{}-{}", def.line_start, def.line_end),
+    })
+}
+
+pub(crate) fn handle_search_namespaces(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+    let namespace = args.get("namespace").and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+    let depth = args.get("depth").and_then(|v| v.as_u64()).unwrap_or(1).clamp(1, 10) as usize;
+    let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
+
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let start = Instant::now();
+    let tree = namespace_tree(&index);
+    let name = match namespace {
+        Some(ns) => match tree.keys().find(|k| k.eq_ignore_ascii_case(ns)) {
+            Some(name) => Some(name.as_str()),
+            None => {
+                let lower = ns.to_lowercase();
+                let similar: Vec<&String> = tree.keys().filter(|k| k.to_lowercase().contains(&lower)).take(10).collect();
+                return ToolCallResult::error(format!(
+                    "Namespace '{}' not found.{}", ns,
+                    if similar.is_empty() { String::new() } else { format!(" Similar: {}", similar.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")) }
+                ));
+            }
+        },
+        None => None,
+    };
+
+    let subs = children(&tree, name);
+    let namespaces: Vec<Value> = subs.iter().map(|s| node_json(&tree, s, depth)).collect();
+    let mut output = json!({
+        "namespace": name,
+        "namespaces": namespaces,
+    });
+    let mut summary = json!({
+        "totalNamespaces": tree.len(),
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if let Some(name) = name {
+        let node = &tree[name];
+        let types: Vec<Value> = node.types.iter()
+            .take(if max_results == 0 { usize::MAX } else { max_results })
+            .map(|&id| type_json(&index, id))
+            .collect();
+        summary["totalTypes"] = json!(node.types.len());
+        summary["returnedTypes"] = json!(types.len());
+        output["files"] = json!(node.files.len());
+        output["types"] = json!(types);
+    }
+    if tree.is_empty() {
+        summary["hint"] = json!("No namespaces in the index. Only C# namespace declarations and TypeScript namespace/module blocks are recorded; ES modules have none.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+    output["summary"] = summary;
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
@@ -0,0 +1,56 @@
+//! search_outline handler: hierarchical symbol tree for a single file.
+
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{build_outline, outline_len, outline_to_json, resolve_file_id};
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+pub(crate) fn handle_search_outline(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let file = match args.get("file").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        Some(f) => f,
+        None => return ToolCallResult::error("Missing required parameter: file".to_string()),
+    };
+
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+    let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+        Ok(idx) => idx,
+        Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+    };
+
+    let start = Instant::now();
+    let file_id = match resolve_file_id(&index, file) {
+        Ok(id) => id,
+        Err(msg) => return ToolCallResult::error(msg),
+    };
+    let nodes = build_outline(&index, file_id);
+    let path = index.files.get(file_id as usize).cloned().unwrap_or_default();
+
+    let mut summary = json!({
+        "totalDefinitions": outline_len(&nodes),
+        "topLevel": nodes.len(),
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if nodes.is_empty() {
+        summary["hint"] = json!("No definitions found in this file. Use search_read_file to view its contents.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "file": path,
+        "outline": outline_to_json(&nodes),
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
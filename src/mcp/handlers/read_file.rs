@@ -0,0 +1,326 @@
+//! search_read_file handler: read a line range, byte range, or definition body.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use serde_json::{json, Value};
+
+use crate::clean_path;
+use crate::definitions::DefinitionEntry;
+use crate::mcp::protocol::ToolCallResult;
+use search::lock_stats::{self, Lock};
+
+use super::utils::{inject_branch_warning, is_under_dir, normalize_path_sep};
+use super::HandlerContext;
+
+/// Default maximum number of lines returned when no explicit range is given.
+const DEFAULT_MAX_LINES: usize = 200;
+
+/// Bytes reserved for the JSON envelope (file, summary, definition metadata)
+/// when fitting lines into the response budget.
+const ENVELOPE_RESERVE_BYTES: usize = 1024;
+
+/// Maximum number of candidates listed when a symbol name is ambiguous.
+const MAX_AMBIGUOUS_CANDIDATES: usize = 10;
+
+pub(crate) fn handle_search_read_file(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let path_arg = args.get("path").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    let symbol = args.get("symbol").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    let parent = args.get("parent").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    let max_lines = args.get("maxLines").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_MAX_LINES as u64) as usize;
+    let start_line = args.get("startLine").and_then(|v| v.as_u64());
+    let end_line = args.get("endLine").and_then(|v| v.as_u64());
+    let byte_offset = args.get("byteOffset").and_then(|v| v.as_u64());
+    let byte_length = args.get("byteLength").and_then(|v| v.as_u64());
+
+    if path_arg.is_none() && symbol.is_none() {
+        return ToolCallResult::error("Either 'path' or 'symbol' is required.".to_string());
+    }
+    if symbol.is_some() && (start_line.is_some() || byte_offset.is_some()) {
+        return ToolCallResult::error(
+            "'symbol' cannot be combined with startLine/endLine or byteOffset/byteLength.".to_string()
+        );
+    }
+    if byte_offset.is_some() && (start_line.is_some() || end_line.is_some()) {
+        return ToolCallResult::error(
+            "Use either startLine/endLine or byteOffset/byteLength, not both.".to_string()
+        );
+    }
+    if start_line == Some(0) {
+        return ToolCallResult::error("startLine must be >= 1 (1-based).".to_string());
+    }
+    if let (Some(s), Some(e)) = (start_line, end_line)
+        && e < s {
+            return ToolCallResult::error(format!("endLine ({}) must be >= startLine ({}).", e, s));
+        }
+
+    // ── Resolve what to read ──
+    let (file_path, definition, range) = if let Some(name) = symbol {
+        match resolve_symbol(ctx, name, parent, path_arg) {
+            Ok((path, def_json, s, e)) => (path, Some(def_json), (s, Some(e))),
+            Err(msg) => return ToolCallResult::error(msg),
+        }
+    } else {
+        let path = match resolve_path(path_arg.unwrap(), &ctx.server_dir) {
+            Ok(p) => p,
+            Err(msg) => return ToolCallResult::error(msg),
+        };
+        (path, None, (start_line.unwrap_or(1) as u32, end_line.map(|e| e as u32)))
+    };
+
+    let content = match crate::read_file_lossy(Path::new(&file_path)) {
+        Ok((c, _lossy)) => c,
+        Err(e) => return ToolCallResult::error(format!("Failed to read '{}': {}", file_path, e)),
+    };
+
+    if let Some(offset) = byte_offset {
+        return read_byte_range(ctx, &file_path, &content, offset as usize, byte_length.map(|l| l as usize));
+    }
+
+    let all_lines: Vec<&str> = content.lines().collect();
+    let total_lines = all_lines.len();
+    let (start, requested_end) = range;
+    let start_idx = (start as usize).saturating_sub(1);
+
+    let mut summary = json!({
+        "totalLines": total_lines,
+        "fileBytes": content.len(),
+    });
+
+    if start_idx >= total_lines && total_lines > 0 {
+        return ToolCallResult::error(format!(
+            "startLine {} is past end of file ({} lines).", start, total_lines
+        ));
+    }
+
+    // Explicit endLine is honored up to EOF; otherwise maxLines caps the window.
+    let mut end_idx = match requested_end {
+        Some(e) => (e as usize).min(total_lines),
+        None if max_lines == 0 => total_lines,
+        None => start_idx.saturating_add(max_lines).min(total_lines),
+    };
+    if let Some(e) = requested_end
+        && e as usize > total_lines {
+            summary["warning"] = json!(format!(
+                "requested endLine={} but file has only {} lines{}",
+                e, total_lines,
+                if definition.is_some() { " (stale index?)" } else { "" }
+            ));
+        }
+    let mut truncation_reasons: Vec<String> = Vec::new();
+    if max_lines > 0 && end_idx.saturating_sub(start_idx) > max_lines {
+        end_idx = start_idx + max_lines;
+    }
+
+    // Fit into the response budget the same way truncate_response_if_needed would,
+    // but cut at a line boundary so the caller can resume with nextStartLine.
+    let budget = if ctx.max_response_bytes > 0 {
+        ctx.max_response_bytes.saturating_sub(ENVELOPE_RESERVE_BYTES).max(1)
+    } else {
+        usize::MAX
+    };
+    let mut used = 0usize;
+    let mut fitted_end = start_idx;
+    for line in &all_lines[start_idx.min(total_lines)..end_idx] {
+        // JSON-escaped length plus the separating comma
+        let cost = serde_json::to_string(line).map(|s| s.len()).unwrap_or(line.len()) + 1;
+        if used + cost > budget && fitted_end > start_idx {
+            break;
+        }
+        used += cost;
+        fitted_end += 1;
+    }
+    let wanted_end = requested_end.map(|e| (e as usize).min(total_lines)).unwrap_or(total_lines);
+    if fitted_end < end_idx {
+        truncation_reasons.push(format!("response budget {} bytes reached", ctx.max_response_bytes));
+    } else if max_lines > 0 && fitted_end == start_idx + max_lines && fitted_end < wanted_end {
+        // Only a cap when lines remain: a window ending exactly at EOF is complete
+        truncation_reasons.push(format!("capped to maxLines={}", max_lines));
+    }
+
+    let lines: Vec<&str> = all_lines[start_idx.min(total_lines)..fitted_end].to_vec();
+    let returned = lines.len();
+
+    summary["returnedLines"] = json!(returned);
+    if fitted_end < wanted_end {
+        summary["responseTruncated"] = json!(true);
+        summary["truncationReason"] = json!(truncation_reasons.join("; "));
+        summary["nextStartLine"] = json!(fitted_end + 1);
+        summary["hint"] = json!(format!(
+            "Call again with startLine={} to continue reading.", fitted_end + 1
+        ));
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let mut output = json!({
+        "file": file_path,
+        "startLine": start_idx + 1,
+        "endLine": start_idx + returned,
+        "lines": lines,
+    });
+    if let Some(def) = definition {
+        output["definition"] = def;
+    }
+    output["summary"] = summary;
+
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+/// Return `len` bytes starting at `offset`, snapped outward to UTF-8 char boundaries.
+fn read_byte_range(
+    ctx: &HandlerContext,
+    file_path: &str,
+    content: &str,
+    offset: usize,
+    len: Option<usize>,
+) -> ToolCallResult {
+    if offset > content.len() {
+        return ToolCallResult::error(format!(
+            "byteOffset {} is past end of file ({} bytes).", offset, content.len()
+        ));
+    }
+    let budget = if ctx.max_response_bytes > 0 {
+        ctx.max_response_bytes.saturating_sub(ENVELOPE_RESERVE_BYTES).max(1)
+    } else {
+        usize::MAX
+    };
+    let wanted = len.unwrap_or(content.len() - offset);
+    let capped = wanted.min(budget);
+
+    let mut start = offset;
+    while !content.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = start.saturating_add(capped).min(content.len());
+    while !content.is_char_boundary(end) {
+        end += 1;
+    }
+    let text = &content[start..end];
+    let start_line = content[..start].matches('\n').count() + 1;
+
+    let mut summary = json!({
+        "fileBytes": content.len(),
+        "returnedBytes": text.len(),
+    });
+    if capped < wanted && end < content.len() {
+        summary["responseTruncated"] = json!(true);
+        summary["truncationReason"] = json!(format!(
+            "response budget {} bytes reached", ctx.max_response_bytes
+        ));
+        summary["nextByteOffset"] = json!(end);
+        summary["hint"] = json!(format!("Call again with byteOffset={} to continue reading.", end));
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "file": file_path,
+        "byteOffset": start,
+        "byteLength": text.len(),
+        "startLine": start_line,
+        "text": text,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+/// Resolve a user-supplied path against the server directory and ensure it does not
+/// escape it (`..` traversal, absolute paths elsewhere on disk).
+pub(crate) fn resolve_path(path: &str, server_dir: &str) -> Result<String, String> {
+    let candidate = if Path::new(path).is_absolute() {
+        PathBuf::from(path)
+    } else {
+        Path::new(server_dir).join(path)
+    };
+    let canonical = std::fs::canonicalize(&candidate)
+        .map_err(|e| format!("File not found: '{}' ({})", path, e))?;
+    let server = std::fs::canonicalize(server_dir)
+        .map(|p| clean_path(&p.to_string_lossy()))
+        .unwrap_or_else(|_| server_dir.to_string());
+    let file = clean_path(&canonical.to_string_lossy());
+    if !is_under_dir(&file, &server) {
+        return Err(format!(
+            "Path '{}' is outside the server directory {}.", path, server_dir
+        ));
+    }
+    if !canonical.is_file() {
+        return Err(format!("'{}' is not a file.", path));
+    }
+    Ok(file)
+}
+
+/// Look up a definition by exact (case-insensitive) name, optionally narrowed by
+/// parent and file path substring. Returns (file, definition JSON, line_start, line_end).
+pub(crate) fn resolve_symbol(
+    ctx: &HandlerContext,
+    name: &str,
+    parent: Option<&str>,
+    file_filter: Option<&str>,
+) -> Result<(String, Value, u32, u32), String> {
+    let def_arc = ctx.def_index.as_ref().ok_or_else(|| {
+        "Definition index not available. Start server with --definitions flag, or pass 'path' with startLine/endLine.".to_string()
+    })?;
+    if !ctx.def_ready.load(Ordering::Acquire) {
+        return Err(super::DEF_INDEX_BUILDING_MSG.to_string());
+    }
+    let index = lock_stats::timed(Lock::Definitions, || def_arc.read())
+        .map_err(|e| format!("Failed to acquire definition index lock: {}", e))?;
+
+    let file_norm = file_filter.map(|f| normalize_path_sep(f).to_lowercase());
+    let parent_lower = parent.map(|p| p.to_lowercase());
+
+    let mut matches: Vec<&DefinitionEntry> = index.name_index
+        .get(&name.to_lowercase())
+        .map(|ids| ids.iter().filter_map(|&i| index.definitions.get(i as usize)).collect())
+        .unwrap_or_default();
+    matches.retain(|d| {
+        if let Some(ref p) = parent_lower {
+            match &d.parent {
+                Some(dp) if dp.to_lowercase() == *p => {}
+                _ => return false,
+            }
+        }
+        if let Some(ref f) = file_norm {
+            let path = index.files.get(d.file_id as usize).map(|s| s.as_str()).unwrap_or("");
+            if !normalize_path_sep(path).to_lowercase().contains(f.as_str()) {
+                return false;
+            }
+        }
+        true
+    });
+    // Stable order so "ambiguous" listings are reproducible.
+    matches.sort_by(|a, b| a.file_id.cmp(&b.file_id).then(a.line_start.cmp(&b.line_start)));
+
+    match matches.len() {
+        0 => Err(format!("No definition named '{}' found{}.", name,
+            if parent.is_some() || file_filter.is_some() { " with the given parent/path filters" } else { "" })),
+        1 => {
+            let d = matches[0];
+            let path = index.files.get(d.file_id as usize).cloned().unwrap_or_default();
+            let mut obj = json!({
+                "name": d.name,
+                "kind": d.kind.as_str(),
+                "lines": format!("{}-{}", d.line_start, d.line_end),
+            });
+            if let Some(ref p) = d.parent {
+                obj["parent"] = json!(p);
+            }
+            if let Some(ref s) = d.signature {
+                obj["signature"] = json!(s);
+            }
+            Ok((path, obj, d.line_start, d.line_end))
+        }
+        n => {
+            let candidates: Vec<String> = matches.iter().take(MAX_AMBIGUOUS_CANDIDATES).map(|d| {
+                let path = index.files.get(d.file_id as usize).map(|s| s.as_str()).unwrap_or("?");
+                match &d.parent {
+                    Some(p) => format!("{}.{} ({}) {}:{}", p, d.name, d.kind.as_str(), path, d.line_start),
+                    None => format!("{} ({}) {}:{}", d.name, d.kind.as_str(), path, d.line_start),
+                }
+            }).collect();
+            Err(format!(
+                "Symbol '{}' is ambiguous ({} matches). Narrow with 'parent' or 'path'. Candidates: {}",
+                name, n, candidates.join("; ")
+            ))
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! search_references handler: all usages of a symbol, classified by AST context.
+//!
+//! Candidate lines come from the content index (exact token lookup). Each line is
+//! classified using the definition index where possible — declarations, base-type
+//! lists, and pre-computed call sites — with lexical fallbacks (`new X`, `X(`) for
+//! files or servers without definitions.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{DefinitionIndex, DefinitionKind};
+use crate::mcp::protocol::ToolCallResult;
+use search::lock_stats::{self, Lock};
+
+use super::callers::find_containing_method;
+use super::utils::{inject_branch_warning, normalize_path_sep};
+use super::HandlerContext;
+
+/// Usage categories, strongest first. A line with several occurrences of the
+/// symbol is reported once, under its strongest category.
+pub(crate) const USAGE_KINDS: &[&str] = &["inheritance", "instantiation", "call", "typeReference", "reference"];
+
+pub(crate) fn handle_search_references(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let name = match args.get("name").and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty()) {
+        Some(n) => n.to_string(),
+        None => return ToolCallResult::error("Missing required parameter: name".to_string()),
+    };
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return ToolCallResult::error(format!(
+            "'{}' is not a single identifier. Pass the bare symbol name (e.g. 'UserService', not 'Ns.UserService').", name
+        ));
+    }
+    let file_filter = args.get("file").and_then(|v| v.as_str()).map(|s| normalize_path_sep(s).to_lowercase());
+    let exclude_dir: Vec<String> = args.get("excludeDir")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_lowercase())).collect())
+        .unwrap_or_default();
+    let usage_filter: Option<Vec<String>> = match args.get("usage").and_then(|v| v.as_str()) {
+        Some(u) => {
+            let kinds: Vec<String> = u.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+            if let Some(bad) = kinds.iter().find(|k| !USAGE_KINDS.contains(&k.as_str())) {
+                return ToolCallResult::error(format!(
+                    "Invalid usage '{}'. Valid values: {}", bad, USAGE_KINDS.join(", ")
+                ));
+            }
+            Some(kinds)
+        }
+        None => None,
+    };
+    let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
+
+    let search_start = Instant::now();
+    let scan = match collect_references(ctx, &name, file_filter.as_deref(), &exclude_dir, usage_filter.as_deref()) {
+        Ok(scan) => scan,
+        Err(e) => return ToolCallResult::error(e),
+    };
+    let ReferenceScan { mut references, by_usage, declarations_skipped, files: files_with_refs, ast_context } = scan;
+
+    let total_results = references.len();
+    if max_results > 0 && references.len() > max_results {
+        references.truncate(max_results);
+    }
+
+    let mut summary = json!({
+        "totalResults": total_results,
+        "returned": references.len(),
+        "files": files_with_refs,
+        "byUsage": by_usage,
+        "declarationsSkipped": declarations_skipped,
+        "astContext": ast_context,
+        "searchTimeMs": search_start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if !ast_context {
+        summary["hint"] = json!("Definition index not available: classification is lexical only and declarations are not excluded. Start server with --definitions for AST-based results.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "references": references,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+/// Usages of `name` found by [`collect_references`], in file then line order.
+pub(crate) struct ReferenceScan {
+    /// `{file, line, usage, text, containingMethod?, containingClass?}` objects
+    pub references: Vec<Value>,
+    pub by_usage: BTreeMap<&'static str, usize>,
+    pub declarations_skipped: usize,
+    /// Files with at least one reference
+    pub files: usize,
+    /// Whether the definition index was used for classification
+    pub ast_context: bool,
+}
+
+/// Every classified usage of identifier `name`, declarations excluded. `file_filter`
+/// and `exclude_dir` are lowercase path substrings.
+pub(crate) fn collect_references(
+    ctx: &HandlerContext,
+    name: &str,
+    file_filter: Option<&str>,
+    exclude_dir: &[String],
+    usage_filter: Option<&[String]>,
+) -> Result<ReferenceScan, String> {
+    // ── Candidate lines from the content index ──
+    let candidates: Vec<(String, Vec<u32>)> = {
+        let index = ctx.index.snapshot();
+        index.index.get(name.to_lowercase().as_str())
+            .map(|postings| postings.iter().filter_map(|p| {
+                let path = index.files.get(p.file_id as usize)?;
+                let norm = normalize_path_sep(path).to_lowercase();
+                if let Some(f) = file_filter
+                    && !norm.contains(f) {
+                        return None;
+                    }
+                if exclude_dir.iter().any(|d| norm.contains(d.as_str())) {
+                    return None;
+                }
+                // One line entry per occurrence: a line is classified once
+                let mut lines = p.lines.clone();
+                lines.sort_unstable();
+                lines.dedup();
+                Some((path.clone(), lines))
+            }).collect())
+            .unwrap_or_default()
+    };
+
+    // ── AST context (optional) ──
+    let def_guard = match &ctx.def_index {
+        Some(arc) if ctx.def_ready.load(Ordering::Acquire) => lock_stats::timed(Lock::Definitions, || arc.read()).ok(),
+        _ => None,
+    };
+    let def_index: Option<&DefinitionIndex> = def_guard.as_deref();
+    let def_file_ids: HashMap<&str, u32> = def_index
+        .map(|d| d.files.iter().enumerate().map(|(i, f)| (f.as_str(), i as u32)).collect())
+        .unwrap_or_default();
+    let is_known_type = def_index.is_some_and(|d| {
+        d.name_index.get(&name.to_lowercase()).is_some_and(|ids| ids.iter()
+            .filter_map(|&i| d.definitions.get(i as usize))
+            .any(|def| def.name == name && is_type_kind(def.kind)))
+    });
+
+    let mut references: Vec<Value> = Vec::new();
+    let mut by_usage: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut declarations_skipped = 0usize;
+    let mut files_with_refs: HashSet<String> = HashSet::new();
+
+    for (path, lines) in &candidates {
+        let content = match crate::read_file_lossy(Path::new(path)) {
+            Ok((c, _)) => c,
+            Err(_) => continue,
+        };
+        let file_lines: Vec<&str> = content.lines().collect();
+        let def_file_id = def_file_ids.get(path.as_str()).copied();
+        let ast = def_index.zip(def_file_id)
+            .map(|(d, fid)| FileAst::new(d, fid, name, &file_lines));
+
+        for &line in lines {
+            let text = match file_lines.get((line as usize).saturating_sub(1)) {
+                Some(t) => *t,
+                None => continue,
+            };
+            let occurrences = find_word_occurrences(text, name);
+            if occurrences.is_empty() {
+                // Token matched with different casing (e.g. `userService` for `UserService`)
+                continue;
+            }
+            if ast.as_ref().is_some_and(|a| a.declaration_lines.contains(&line)) {
+                declarations_skipped += 1;
+                continue;
+            }
+            let usage = occurrences.iter()
+                .map(|&pos| classify_occurrence(text, pos, name, line, ast.as_ref(), is_known_type))
+                .min_by_key(|u| USAGE_KINDS.iter().position(|k| k == u).unwrap_or(usize::MAX))
+                .unwrap();
+            if let Some(allowed) = usage_filter
+                && !allowed.iter().any(|a| a == usage) {
+                    continue;
+                }
+
+            *by_usage.entry(usage).or_default() += 1;
+            files_with_refs.insert(path.clone());
+            let mut obj = json!({
+                "file": path,
+                "line": line,
+                "usage": usage,
+                "text": text.trim(),
+            });
+            if let (Some(d), Some(fid)) = (def_index, def_file_id)
+                && let Some((m, parent, _, _)) = find_containing_method(d, fid, line) {
+                    obj["containingMethod"] = json!(m);
+                    if let Some(p) = parent {
+                        obj["containingClass"] = json!(p);
+                    }
+                }
+            references.push(obj);
+        }
+    }
+
+    // Deterministic order: by file, then line
+    references.sort_by(|a, b| a["file"].as_str().cmp(&b["file"].as_str())
+        .then(a["line"].as_u64().cmp(&b["line"].as_u64())));
+    Ok(ReferenceScan {
+        references,
+        by_usage,
+        declarations_skipped,
+        files: files_with_refs.len(),
+        ast_context: def_index.is_some(),
+    })
+}
+
+/// Per-file AST facts relevant to one symbol.
+struct FileAst {
+    /// Lines where a definition named after the symbol is declared
+    declaration_lines: HashSet<u32>,
+    /// Declaration lines of types that list the symbol as a base type
+    inheritance_lines: HashSet<u32>,
+    /// Lines with a parsed call site targeting the symbol
+    call_lines: HashSet<u32>,
+}
+
+impl FileAst {
+    fn new(index: &DefinitionIndex, file_id: u32, name: &str, file_lines: &[&str]) -> Self {
+        let mut ast = FileAst {
+            declaration_lines: HashSet::new(),
+            inheritance_lines: HashSet::new(),
+            call_lines: HashSet::new(),
+        };
+        let Some(def_ids) = index.file_index.get(&file_id) else { return ast };
+        for &di in def_ids {
+            let Some(def) = index.definitions.get(di as usize) else { continue };
+            if def.name == name {
+                // Declaration line = first line of the definition that mentions its name
+                // (line_start may point at leading attributes)
+                if let Some(l) = first_line_with_word(file_lines, def.line_start, def.line_end, name) {
+                    ast.declaration_lines.insert(l);
+                }
+            }
+            if def.base_types.iter().any(|bt| strip_generics(bt) == name) {
+                // Base list may wrap onto following lines
+                let mut l = def.line_start;
+                while l <= def.line_end {
+                    let text = file_lines.get((l as usize).saturating_sub(1)).copied().unwrap_or("");
+                    if !find_word_occurrences(text, name).is_empty() {
+                        ast.inheritance_lines.insert(l);
+                        break;
+                    }
+                    if text.contains('{') {
+                        break;
+                    }
+                    l += 1;
+                }
+            }
+            if let Some(calls) = index.method_calls.get(&di) {
+                ast.call_lines.extend(calls.iter().filter(|c| c.method_name == name).map(|c| c.line));
+            }
+        }
+        ast
+    }
+}
+
+pub(crate) fn first_line_with_word(file_lines: &[&str], start: u32, end: u32, word: &str) -> Option<u32> {
+    (start..=end).find(|&l| file_lines.get((l as usize).saturating_sub(1))
+        .is_some_and(|t| !find_word_occurrences(t, word).is_empty()))
+}
+
+pub(crate) fn is_type_kind(kind: DefinitionKind) -> bool {
+    matches!(kind,
+        DefinitionKind::Class | DefinitionKind::Interface | DefinitionKind::Struct
+        | DefinitionKind::Record | DefinitionKind::Enum | DefinitionKind::Delegate
+        | DefinitionKind::TypeAlias)
+}
+
+/// `List<T>` → `List`
+pub(crate) fn strip_generics(s: &str) -> &str {
+    s.split('<').next().unwrap_or(s).trim()
+}
+
+pub(crate) fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte offsets of whole-word, case-sensitive occurrences of `word` in `text`.
+pub(crate) fn find_word_occurrences(text: &str, word: &str) -> Vec<usize> {
+    let mut result = Vec::new();
+    let mut from = 0;
+    while let Some(rel) = text[from..].find(word) {
+        let pos = from + rel;
+        let end = pos + word.len();
+        let before_ok = text[..pos].chars().next_back().is_none_or(|c| !is_ident_char(c));
+        let after_ok = text[end..].chars().next().is_none_or(|c| !is_ident_char(c));
+        if before_ok && after_ok {
+            result.push(pos);
+        }
+        from = end;
+    }
+    result
+}
+
+/// Skip a balanced `<...>` generic argument list at the start of `s`.
+fn skip_generic_args(s: &str) -> &str {
+    if !s.starts_with('<') {
+        return s;
+    }
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return s[i + 1..].trim_start();
+                }
+            }
+            _ => {}
+        }
+    }
+    s
+}
+
+/// Classify one occurrence of `name` at byte `pos` of a line.
+fn classify_occurrence(
+    text: &str,
+    pos: usize,
+    name: &str,
+    line: u32,
+    ast: Option<&FileAst>,
+    is_known_type: bool,
+) -> &'static str {
+    let before = text[..pos].trim_end();
+    let after = skip_generic_args(text[pos + name.len()..].trim_start());
+
+    if ast.is_some_and(|a| a.inheritance_lines.contains(&line)) || is_base_list(before) {
+        return "inheritance";
+    }
+    if before.ends_with("new") && before[..before.len() - 3].chars().next_back().is_none_or(|c| !is_ident_char(c)) {
+        return "instantiation";
+    }
+    if after.starts_with('(') || ast.is_some_and(|a| a.call_lines.contains(&line) && !before.ends_with('<')) {
+        return "call";
+    }
+    if is_known_type
+        || after.starts_with(|c: char| is_ident_char(c) || c == '?' || c == '[')
+        || before.ends_with('<')
+        || before.ends_with("typeof(")
+        || before.ends_with(" is")
+        || before.ends_with(" as")
+    {
+        return "typeReference";
+    }
+    "reference"
+}
+
+/// Lexical fallback: `class Foo : Bar, Baz` / `class Foo extends Bar implements Baz`.
+fn is_base_list(before: &str) -> bool {
+    let has_type_keyword = ["class ", "interface ", "struct ", "record "].iter().any(|k| before.contains(k));
+    has_type_keyword
+        && (before.contains(':') || before.contains(" extends ") || before.ends_with(" extends")
+            || before.contains(" implements ") || before.ends_with(" implements"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_word_occurrences_whole_word_case_sensitive() {
+        assert_eq!(find_word_occurrences("Foo FooBar _Foo Foo()", "Foo"), vec![0, 16]);
+        assert!(find_word_occurrences("foo", "Foo").is_empty());
+    }
+
+    #[test]
+    fn test_classify_lexical_categories() {
+        let c = |text: &str| classify_occurrence(text, text.find("Widget").unwrap(), "Widget", 1, None, false);
+        assert_eq!(c("var w = new Widget(1);"), "instantiation");
+        assert_eq!(c("var w = new Widget<int>();"), "instantiation");
+        assert_eq!(c("Widget(3);"), "call");
+        assert_eq!(c("x = Make<Widget>();"), "typeReference");
+        assert_eq!(c("private Widget _w;"), "typeReference");
+        assert_eq!(c("public class Gadget : Widget, IDisposable"), "inheritance");
+        assert_eq!(c("class Gadget extends Widget {"), "inheritance");
+        assert_eq!(c("return obj.Widget;"), "reference");
+        assert_eq!(c("if (x is Widget)"), "typeReference");
+    }
+
+    #[test]
+    fn test_classify_known_type_without_syntax_hint() {
+        assert_eq!(classify_occurrence("Log(Widget);", 4, "Widget", 1, None, true), "typeReference");
+    }
+
+    #[test]
+    fn test_skip_generic_args_nested() {
+        assert_eq!(skip_generic_args("<Dictionary<string, int>>(x)"), "(x)");
+        assert_eq!(skip_generic_args("(x)"), "(x)");
+    }
+}
@@ -0,0 +1,212 @@
+//! search_reindex and search_reindex_status handlers: content index rebuilds on a
+//! background thread.
+//!
+//! A reindex builds a fresh `ContentIndex` while queries keep using the current one,
+//! then swaps it in with `Searcher::replace`, so no query waits for the build or sees
+//! a partial index. Under `serve --watch` the new index gets the path map the watcher
+//! updates it through. The call returns a job id at once; `search_reindex_status` reports
+//! the build phase and progress, then the result. One job runs at a time per server.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::mcp::protocol::ToolCallResult;
+use crate::mcp::watcher::build_watch_index_from;
+use crate::priority::BuildPriority;
+use crate::progress::Tracker;
+use crate::{build_content_index, clean_path, save_content_index, ContentIndexArgs};
+
+use super::HandlerContext;
+
+/// Finished jobs kept for `search_reindex_status`
+const MAX_JOBS: usize = 10;
+
+/// Reindex jobs of one server, oldest first.
+#[derive(Default)]
+pub struct ReindexJobs {
+    started: u64,
+    jobs: Vec<Arc<ReindexJob>>,
+}
+
+struct ReindexJob {
+    id: String,
+    started: Instant,
+    tracker: Tracker,
+    outcome: Mutex<Option<Outcome>>,
+}
+
+enum Outcome {
+    Completed { files: usize, tokens: usize, elapsed: Duration },
+    Failed(String),
+}
+
+impl ReindexJobs {
+    fn running(&self) -> Option<&Arc<ReindexJob>> {
+        self.jobs.iter().find(|job| job.outcome.lock().unwrap_or_else(|e| e.into_inner()).is_none())
+    }
+
+    fn start(&mut self) -> Arc<ReindexJob> {
+        self.started += 1;
+        let job = Arc::new(ReindexJob {
+            id: format!("reindex-{}", self.started),
+            started: Instant::now(),
+            tracker: Tracker::default(),
+            outcome: Mutex::new(None),
+        });
+        if self.jobs.len() == MAX_JOBS {
+            self.jobs.remove(0);
+        }
+        self.jobs.push(Arc::clone(&job));
+        job
+    }
+}
+
+impl ReindexJob {
+    fn status_json(&self) -> Value {
+        let mut status = json!({ "jobId": self.id });
+        match &*self.outcome.lock().unwrap_or_else(|e| e.into_inner()) {
+            None => {
+                status["status"] = json!("running");
+                status["elapsedMs"] = json!(self.started.elapsed().as_millis() as u64);
+                if let Some(progress) = self.tracker.snapshot() {
+                    status["progress"] = json!({
+                        "phase": progress.phase,
+                        "unit": progress.unit,
+                        "done": progress.done,
+                        "total": progress.total,
+                    });
+                }
+            }
+            Some(Outcome::Completed { files, tokens, elapsed }) => {
+                status["status"] = json!("completed");
+                status["files"] = json!(files);
+                status["uniqueTokens"] = json!(tokens);
+                status["rebuildTimeMs"] = json!(elapsed.as_secs_f64() * 1000.0);
+            }
+            Some(Outcome::Failed(error)) => {
+                status["status"] = json!("failed");
+                status["error"] = json!(error);
+            }
+        }
+        status
+    }
+}
+
+pub(crate) fn handle_search_reindex(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let dir = args.get("dir").and_then(|v| v.as_str()).unwrap_or(&ctx.server_dir);
+    let ext = args.get("ext").and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| ctx.server_ext.clone());
+    let wait = args.get("wait").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    // Check dir matches server dir
+    let requested = std::fs::canonicalize(dir)
+        .map(|p| clean_path(&p.to_string_lossy()))
+        .unwrap_or_else(|_| dir.to_string());
+    let server = std::fs::canonicalize(&ctx.server_dir)
+        .map(|p| clean_path(&p.to_string_lossy()))
+        .unwrap_or_else(|_| ctx.server_dir.clone());
+    if !requested.eq_ignore_ascii_case(&server) {
+        return ToolCallResult::error(format!(
+            "Server started with --dir {}. For other directories, start another server instance or use CLI.",
+            ctx.server_dir
+        ));
+    }
+
+    let job = {
+        let mut jobs = ctx.reindex_jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(running) = jobs.running() {
+            let mut output = running.status_json();
+            output["hint"] = json!("A reindex is already running. Poll search_reindex_status for its progress.");
+            return ToolCallResult::success(serde_json::to_string(&output).unwrap());
+        }
+        jobs.start()
+    };
+    info!(dir = %dir, ext = %ext, job = %job.id, "Rebuilding content index in the background");
+
+    // Under `serve --watch` the current index maps paths to file ids for the watcher,
+    // which goes on updating whichever index is current: the new one needs the map too
+    let (tokenizer, walk_limits, stop_token_pct, max_file_size, watched) = {
+        let current = ctx.index.snapshot();
+        (current.tokenizer, current.walk_limits.clone(), current.stop_token_pct, current.max_file_size, current.path_to_id.is_some())
+    };
+    let build_args = ContentIndexArgs {
+        dir: dir.to_string(),
+        ext,
+        max_age_hours: 24,
+        hidden: false,
+        no_ignore: false,
+        threads: 0,
+        min_token_len: tokenizer.min_token_len,
+        dry_run: false,
+        build_priority: BuildPriority::Normal,
+        io_limit_mb: 0,
+        watch: false,
+        save_interval_secs: 60,
+        max_file_size,
+        spill_mb: 0,
+        shards: 0,
+        max_token_len: tokenizer.max_token_len,
+        stop_token_pct,
+        checkpoint_secs: 0,
+        max_depth: walk_limits.max_depth,
+        prune_dir: walk_limits.prune_dirs,
+        storage: None,
+    };
+    let searcher = ctx.index.clone();
+    let generation = Arc::clone(&ctx.generation);
+    let index_base = ctx.index_base.clone();
+    let bg_job = Arc::clone(&job);
+    let builder = std::thread::spawn(move || {
+        let built = catch_unwind(AssertUnwindSafe(|| bg_job.tracker.track(|| build_content_index(&build_args))));
+        let outcome = match built {
+            Ok(new_index) => {
+                if let Err(e) = save_content_index(&new_index, &index_base) {
+                    warn!(error = %e, "Failed to save reindexed content to disk");
+                }
+                let (files, tokens) = (new_index.files.len(), new_index.index.len());
+                searcher.replace(if watched { build_watch_index_from(new_index) } else { new_index });
+                generation.bump();
+                info!(job = %bg_job.id, files, tokens, "Content index rebuilt and swapped in");
+                Outcome::Completed { files, tokens, elapsed: bg_job.started.elapsed() }
+            }
+            Err(_) => Outcome::Failed("The index build panicked; the previous index is still in use.".to_string()),
+        };
+        *bg_job.outcome.lock().unwrap_or_else(|e| e.into_inner()) = Some(outcome);
+    });
+
+    if wait {
+        let _ = builder.join();
+        let mut output = job.status_json();
+        if output["status"] == "failed" {
+            return ToolCallResult::error(output["error"].as_str().unwrap_or_default().to_string());
+        }
+        output["status"] = json!("ok");
+        return ToolCallResult::success(serde_json::to_string(&output).unwrap());
+    }
+    let output = json!({
+        "jobId": job.id,
+        "status": "started",
+        "hint": "The current index keeps serving queries until the new one is swapped in. Poll search_reindex_status for progress.",
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+pub(crate) fn handle_search_reindex_status(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let jobs = ctx.reindex_jobs.lock().unwrap_or_else(|e| e.into_inner());
+    let job = match args.get("jobId").and_then(|v| v.as_str()) {
+        Some(id) => jobs.jobs.iter().find(|job| job.id == id),
+        None => jobs.jobs.last(),
+    };
+    match job {
+        Some(job) => ToolCallResult::success(serde_json::to_string(&job.status_json()).unwrap()),
+        None => ToolCallResult::error(match args.get("jobId").and_then(|v| v.as_str()) {
+            Some(id) => format!("Unknown reindex job '{}'. Only the last {} jobs are kept.", id, MAX_JOBS),
+            None => "No reindex has been started on this server.".to_string(),
+        }),
+    }
+}
@@ -0,0 +1,248 @@
+//! search_replace_preview handler: search-and-replace planned against the content index.
+//!
+//! The index only narrows the candidates: a file is read when every token of the pattern
+//! occurs (as a substring of an indexed token) somewhere in it. Matches are then found in
+//! the file text itself, so a stale index can miss files but never reports a wrong match.
+//! The plan is shared with `search replace`, which applies it; the MCP tool only previews.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Instant;
+
+use regex::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+
+use crate::mcp::protocol::ToolCallResult;
+use crate::ContentIndex;
+
+use super::utils::{inject_branch_warning, is_under_dir, matches_ext_filter, read_indexed_files};
+use super::HandlerContext;
+
+const DEFAULT_CONTEXT_LINES: usize = 3;
+const DEFAULT_MAX_FILES: usize = 50;
+
+/// Planned edit of one file.
+pub(crate) struct FileReplacement {
+    pub path: String,
+    /// Content the plan was made from; an apply must find the file unchanged.
+    pub original: String,
+    pub updated: String,
+    pub replacements: usize,
+    /// Unified diff from `original` to `updated`
+    pub diff: String,
+}
+
+pub(crate) struct ReplacePlan {
+    pub files: Vec<FileReplacement>,
+    /// Files the index selected for verification
+    pub candidate_files: usize,
+}
+
+pub(crate) fn handle_search_replace_preview(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let index = ctx.index.snapshot();
+    let start = Instant::now();
+    let plan = match plan_replacements(&index, args) {
+        Ok(plan) => plan,
+        Err(msg) => return ToolCallResult::error(msg),
+    };
+    let max_files = match args.get("maxFiles").and_then(|v| v.as_u64()) {
+        Some(0) => usize::MAX,
+        Some(n) => n as usize,
+        None => DEFAULT_MAX_FILES,
+    };
+    let mut output = replace_plan_json(&plan, max_files);
+    output["summary"]["searchTimeMs"] = json!(start.elapsed().as_secs_f64() * 1000.0);
+    inject_branch_warning(&mut output["summary"], ctx);
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+/// `{files: [{path, replacements, diff}], summary}` for the first `max_files` files.
+pub(crate) fn replace_plan_json(plan: &ReplacePlan, max_files: usize) -> Value {
+    let files: Vec<Value> = plan.files.iter().take(max_files)
+        .map(|f| json!({ "path": f.path, "replacements": f.replacements, "diff": f.diff }))
+        .collect();
+    let total_replacements: usize = plan.files.iter().map(|f| f.replacements).sum();
+    json!({
+        "files": files,
+        "summary": {
+            "totalFiles": plan.files.len(),
+            "shownFiles": files.len(),
+            "totalReplacements": total_replacements,
+            "candidateFiles": plan.candidate_files,
+        }
+    })
+}
+
+/// Find every replacement of `pattern` by `replacement` in the indexed files. `args` uses
+/// the MCP parameter names: pattern, replacement, regex, ignoreCase, wordBoundary, ext,
+/// dir and contextLines. Files come back sorted by path.
+pub(crate) fn plan_replacements(index: &ContentIndex, args: &Value) -> Result<ReplacePlan, String> {
+    let pattern = args.get("pattern").and_then(|v| v.as_str()).filter(|s| !s.is_empty())
+        .ok_or("Missing required parameter: pattern")?;
+    let replacement = args.get("replacement").and_then(|v| v.as_str())
+        .ok_or("Missing required parameter: replacement")?;
+    let use_regex = args.get("regex").and_then(|v| v.as_bool()).unwrap_or(false);
+    let ignore_case = args.get("ignoreCase").and_then(|v| v.as_bool()).unwrap_or(false);
+    let word = args.get("wordBoundary").and_then(|v| v.as_bool()).unwrap_or(false);
+    let context = args.get("contextLines").and_then(|v| v.as_u64())
+        .map_or(DEFAULT_CONTEXT_LINES, |n| n as usize);
+    let ext = args.get("ext").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    let dir = args.get("dir").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|d| {
+        if Path::new(d).is_absolute() { d.to_string() } else { format!("{}/{}", index.root, d.trim_start_matches("./")) }
+    });
+
+    let source = if use_regex { pattern.to_string() } else { regex::escape(pattern) };
+    let source = if word { format!(r"\b(?:{})\b", source) } else { source };
+    let re = RegexBuilder::new(&source).case_insensitive(ignore_case).build()
+        .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+
+    let mut candidates: Vec<&str> = candidate_files(index, pattern, use_regex).into_iter()
+        .map(|id| index.files[id as usize].as_str())
+        .filter(|path| ext.is_none_or(|e| matches_ext_filter(path, e)))
+        .filter(|path| dir.as_deref().is_none_or(|d| is_under_dir(path, d)))
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let contents = read_indexed_files(index, &candidates);
+    let mut files = Vec::new();
+    for (path, content) in candidates.iter().zip(contents) {
+        let Some(original) = content else { continue };
+        let (updated, regions) = apply_pattern(&original, &re, replacement, use_regex);
+        let replacements = regions.iter().map(|r| r.matches).sum();
+        if updated == original {
+            continue;
+        }
+        let relative = path.strip_prefix(index.root.as_str()).unwrap_or(path).trim_start_matches('/');
+        let diff = unified_diff(relative, &original, &regions, context);
+        files.push(FileReplacement { path: path.to_string(), original, updated, replacements, diff });
+    }
+    Ok(ReplacePlan { files, candidate_files: candidates.len() })
+}
+
+/// File ids that may contain `pattern`: those holding, for every token of the pattern,
+/// some indexed token containing it. A regex, or a pattern without indexable tokens,
+/// selects every file.
+fn candidate_files(index: &ContentIndex, pattern: &str, use_regex: bool) -> Vec<u32> {
+    let tokens = if use_regex { Vec::new() } else { index.tokenizer.tokenize(&pattern.to_lowercase()) };
+    if tokens.is_empty() {
+        return (0..index.files.len() as u32).collect();
+    }
+    let mut selected: Option<HashSet<u32>> = None;
+    for token in &tokens {
+        let files: HashSet<u32> = index.index.iter()
+            .filter(|(key, _)| key.contains(token.as_str()))
+            .flat_map(|(_, postings)| postings.iter().map(|p| p.file_id))
+            .collect();
+        selected = Some(match selected {
+            Some(prev) => prev.intersection(&files).copied().collect(),
+            None => files,
+        });
+    }
+    selected.unwrap_or_default().into_iter().collect()
+}
+
+/// Whole lines `first_line..first_line + old_lines` of the original, rewritten as `new_text`.
+struct Region {
+    first_line: usize,
+    old_lines: usize,
+    new_text: String,
+    matches: usize,
+}
+
+/// Replace every non-empty match in `content`, and return the result together with the
+/// changed line regions. Matches sharing a line end up in one region.
+fn apply_pattern(content: &str, re: &Regex, replacement: &str, expand: bool) -> (String, Vec<Region>) {
+    let mut line_starts: Vec<usize> = content.split_inclusive('\n')
+        .scan(0, |offset, line| { let start = *offset; *offset += line.len(); Some(start) })
+        .collect();
+    line_starts.push(content.len());
+    let line_of = |byte: usize| line_starts.partition_point(|&s| s <= byte) - 1;
+
+    // (region start byte, region end byte, cursor into the original, region)
+    let mut open: Vec<(usize, usize, usize, Region)> = Vec::new();
+    for caps in re.captures_iter(content) {
+        let m = caps.get(0).unwrap();
+        if m.is_empty() {
+            continue;
+        }
+        let mut with = String::new();
+        if expand { caps.expand(replacement, &mut with) } else { with.push_str(replacement) }
+        let (lo, hi) = (line_of(m.start()), line_of(m.end() - 1));
+        match open.last_mut() {
+            Some((_, end, cursor, region)) if line_starts[lo] < *end => {
+                region.new_text.push_str(&content[*cursor..m.start()]);
+                region.new_text.push_str(&with);
+                region.old_lines = hi + 1 - region.first_line;
+                region.matches += 1;
+                *end = line_starts[hi + 1];
+                *cursor = m.end();
+            }
+            _ => {
+                let start = line_starts[lo];
+                let new_text = format!("{}{}", &content[start..m.start()], with);
+                let region = Region { first_line: lo, old_lines: hi + 1 - lo, new_text, matches: 1 };
+                open.push((start, line_starts[hi + 1], m.end(), region));
+            }
+        }
+    }
+
+    let mut updated = String::with_capacity(content.len());
+    let mut copied = 0;
+    let regions = open.into_iter().map(|(start, end, cursor, mut region)| {
+        region.new_text.push_str(&content[cursor..end]);
+        updated.push_str(&content[copied..start]);
+        updated.push_str(&region.new_text);
+        copied = end;
+        region
+    }).collect();
+    updated.push_str(&content[copied..]);
+    (updated, regions)
+}
+
+/// Unified diff of the regions, with `context` unchanged lines around each hunk.
+fn unified_diff(path: &str, original: &str, regions: &[Region], context: usize) -> String {
+    let lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let strip = |l: &str| l.trim_end_matches('\n').trim_end_matches('\r').to_string();
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let mut shift: isize = 0;
+    let mut i = 0;
+    while i < regions.len() {
+        // Regions whose context overlaps share a hunk
+        let mut j = i;
+        while j + 1 < regions.len()
+            && regions[j + 1].first_line <= regions[j].first_line + regions[j].old_lines + 2 * context {
+            j += 1;
+        }
+        let from = regions[i].first_line.saturating_sub(context);
+        let to = (regions[j].first_line + regions[j].old_lines + context).min(lines.len());
+        let mut body = String::new();
+        let mut line = from;
+        let mut new_count = 0;
+        for region in &regions[i..=j] {
+            for l in &lines[line..region.first_line] {
+                body.push_str(&format!(" {}\n", strip(l)));
+                new_count += 1;
+            }
+            for l in &lines[region.first_line..region.first_line + region.old_lines] {
+                body.push_str(&format!("-{}\n", strip(l)));
+            }
+            for l in region.new_text.split_inclusive('\n') {
+                body.push_str(&format!("+{}\n", strip(l)));
+                new_count += 1;
+            }
+            line = region.first_line + region.old_lines;
+        }
+        for l in &lines[line..to] {
+            body.push_str(&format!(" {}\n", strip(l)));
+            new_count += 1;
+        }
+        let old_count = to - from;
+        out.push_str(&format!("@@ -{},{} +{},{} @@\n", from + 1, old_count,
+            (from as isize + shift + 1).max(0), new_count));
+        out.push_str(&body);
+        shift += new_count as isize - old_count as isize;
+        i = j + 1;
+    }
+    out
+}
@@ -0,0 +1,159 @@
+//! search_semantic handler: natural-language queries over chunk embeddings, blended
+//! with keyword TF-IDF (see [`crate::semantic`]).
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, MutexGuard};
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::config::RepoConfig;
+use crate::mcp::protocol::ToolCallResult;
+use crate::semantic::{self, Embedder, IndexRefresh, SemanticIndex, SemanticQuery};
+use crate::{ContentIndex, SearchError};
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+/// Chunks scored per query when `search_grep` reranks: the nearest ones, by the IVF index.
+const RERANK_CHUNKS: usize = 1000;
+
+pub(crate) fn handle_search_semantic(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let query = match args.get("query").and_then(|v| v.as_str()).map(str::trim) {
+        Some(q) if !q.is_empty() => q,
+        _ => return ToolCallResult::error("Missing required parameter: query".to_string()),
+    };
+    let keyword_weight = args.get("keywordWeight").and_then(|v| v.as_f64()).unwrap_or(0.3);
+    if !(0.0..=1.0).contains(&keyword_weight) {
+        return ToolCallResult::error(format!("keywordWeight must be in [0, 1], got {}", keyword_weight));
+    }
+    let max_results = args.get("maxResults").and_then(|v| v.as_u64()).unwrap_or(10).max(1) as usize;
+    let file_filter = args.get("file").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+
+    let start = Instant::now();
+    let content = ctx.index.snapshot();
+    let refreshed = match refresh_semantic_index(ctx, &content) {
+        Ok(r) => r,
+        Err(e) => return e,
+    };
+    let (index, refresh) = (&refreshed.index, refreshed.refresh);
+    let index_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let search_start = Instant::now();
+    let query_vector = match embed_query(&refreshed, query) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let hits = index.search(&content, &query_vector, &SemanticQuery {
+        text: query,
+        max_results,
+        keyword_weight,
+        file_filter,
+    });
+    let round = |x: f64| (x * 1000.0).round() / 1000.0;
+    let results: Vec<Value> = hits.iter().map(|hit| {
+        let chunk = &index.chunks[hit.chunk];
+        let mut obj = json!({
+            "file": chunk.file,
+            "lines": format!("{}-{}", chunk.line_start, chunk.line_end),
+            "kind": chunk.kind,
+            "score": round(hit.score),
+            "semanticScore": round(hit.semantic),
+            "keywordScore": round(hit.keyword),
+        });
+        if let Some(name) = &chunk.name {
+            obj["name"] = json!(name);
+        }
+        obj
+    }).collect();
+
+    let mut summary = json!({
+        "returned": results.len(),
+        "chunks": index.chunks.len(),
+        "embedder": index.embedder,
+        "vectorSearch": index.vector_search(),
+        "index": refresh.as_str(),
+        "indexMs": index_ms,
+        "searchTimeMs": search_start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if let IndexRefresh::Updated(n) = refresh {
+        summary["chunksEmbedded"] = json!(n);
+    }
+    if results.is_empty() {
+        summary["hint"] = json!("No chunk matched. Rephrase with words the code would use, or try search_grep.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    let output = json!({
+        "results": results,
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+/// The semantic index, up to date with the server's indexes, and the embedder that
+/// computed it.
+struct RefreshedIndex {
+    index: Arc<SemanticIndex>,
+    embedder: Box<dyn Embedder>,
+    refresh: IndexRefresh,
+}
+
+fn lock_slot(ctx: &HandlerContext) -> Result<MutexGuard<'_, Option<Arc<SemanticIndex>>>, ToolCallResult> {
+    ctx.semantic_index.lock()
+        .map_err(|e| ToolCallResult::from(SearchError::LockPoisoned(format!("semantic index: {}", e))))
+}
+
+/// Load, build or update the semantic index with the embedder `[semantic]` selects.
+/// The index lock is only held to take and store the index, never while embedding.
+fn refresh_semantic_index(ctx: &HandlerContext, content: &ContentIndex) -> Result<RefreshedIndex, ToolCallResult> {
+    let config = RepoConfig::load_or_default(Path::new(&ctx.server_dir)).semantic;
+    let embedder = semantic::embedder::from_config(&config).map_err(ToolCallResult::error)?;
+    let current = lock_slot(ctx)?.clone();
+    let sources = || {
+        let defs = match &ctx.def_index {
+            Some(idx) if ctx.def_ready.load(Ordering::Acquire) => Some(lock_stats::timed(Lock::Definitions, || idx.read())
+                .map_err(|e| format!("definition index lock poisoned: {}", e))?),
+            _ => None,
+        };
+        Ok(semantic::chunk_files(content, defs.as_deref()))
+    };
+    let (index, refresh) = semantic::load_or_update(current, &ctx.server_dir, ctx.generation.get(), &ctx.index_base,
+        embedder.as_ref(), ctx.generation.clock().as_ref(), sources).map_err(ToolCallResult::error)?;
+    if refresh != IndexRefresh::Current {
+        let mut slot = lock_slot(ctx)?;
+        // A concurrent call may have stored an index of a later generation meanwhile
+        if slot.as_ref().is_none_or(|stored| stored.root != index.root || stored.generation <= index.generation) {
+            *slot = Some(Arc::clone(&index));
+        }
+    }
+    Ok(RefreshedIndex { index, embedder, refresh })
+}
+
+/// The query's vector, checked against the index's dimensions.
+fn embed_query(refreshed: &RefreshedIndex, query: &str) -> Result<Vec<f32>, ToolCallResult> {
+    let vector = refreshed.embedder.embed(&[query.to_string()])
+        .map(|mut v| v.pop().unwrap_or_default())
+        .map_err(ToolCallResult::error)?;
+    refreshed.index.check_dimensions(&vector).map_err(ToolCallResult::error)?;
+    Ok(vector)
+}
+
+/// Best chunk similarity to `query` per file, for `search_grep` `rerank`. Files none of
+/// whose chunks is among the [`RERANK_CHUNKS`] nearest are left out.
+pub(crate) fn semantic_file_scores(ctx: &HandlerContext, query: &str)
+    -> Result<(HashMap<String, f64>, IndexRefresh), ToolCallResult> {
+    let content = ctx.index.snapshot();
+    let refreshed = refresh_semantic_index(ctx, &content)?;
+    let query_vector = embed_query(&refreshed, query)?;
+    let index = &refreshed.index;
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    for (chunk, similarity) in index.nearest(&query_vector, RERANK_CHUNKS, |_| true) {
+        let best = scores.entry(index.chunks[chunk].file.clone()).or_insert(0.0);
+        *best = best.max(similarity as f64);
+    }
+    Ok((scores, refreshed.refresh))
+}
@@ -0,0 +1,260 @@
+//! search_symbol_at handler: what identifier is at file:line:column, where it is
+//! defined, and where it is used — the lookup behind editor "go to definition".
+//!
+//! The identifier is read from the file on disk. It is resolved, in order, as the
+//! name of a definition declared on that line, as a parsed call site on that line
+//! (whose receiver type picks the right overload owner), or by name, preferring the
+//! enclosing class and then types. Identifiers with no definition (locals,
+//! parameters, framework types) are reported as unresolved, with references still
+//! listed.
+
+use std::path::Path;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::definitions::{resolve_file_id, DefinitionEntry, DefinitionIndex};
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::callers::find_containing_method;
+use super::references::{collect_references, first_line_with_word, is_ident_char, is_type_kind, strip_generics};
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+/// Other definitions listed when the name is ambiguous.
+const MAX_CANDIDATES: usize = 10;
+
+pub(crate) fn handle_search_symbol_at(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let file = match args.get("file").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        Some(f) => f,
+        None => return ToolCallResult::error("Missing required parameter: file".to_string()),
+    };
+    let line = match args.get("line").and_then(|v| v.as_u64()) {
+        Some(n) if n >= 1 => n as u32,
+        Some(_) => return ToolCallResult::error("line must be >= 1".to_string()),
+        None => return ToolCallResult::error("Missing required parameter: line".to_string()),
+    };
+    let column = match args.get("column").and_then(|v| v.as_u64()) {
+        Some(n) if n >= 1 => n as usize,
+        Some(_) => return ToolCallResult::error("column must be >= 1".to_string()),
+        None => return ToolCallResult::error("Missing required parameter: column".to_string()),
+    };
+    let include_references = args.get("includeReferences").and_then(|v| v.as_bool()).unwrap_or(true);
+    let max_references = args.get("maxReferences").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+
+    let def_index = match &ctx.def_index {
+        Some(idx) => idx,
+        None => return ToolCallResult::error(
+            "Definition index not available. Start server with --definitions flag.".to_string()
+        ),
+    };
+    let start = Instant::now();
+
+    // Resolve under the definition lock, then release it before the reference scan
+    let (path, symbol, resolution, definitions) = {
+        let index = match lock_stats::timed(Lock::Definitions, || def_index.read()) {
+            Ok(idx) => idx,
+            Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+        };
+        let file_id = match resolve_file_id(&index, file) {
+            Ok(id) => id,
+            Err(msg) => return ToolCallResult::error(msg),
+        };
+        let path = index.files.get(file_id as usize).cloned().unwrap_or_default();
+        let content = match crate::read_file_lossy(Path::new(&path)) {
+            Ok((c, _)) => c,
+            Err(e) => return ToolCallResult::error(format!("Failed to read {}: {}", path, e)),
+        };
+        let file_lines: Vec<&str> = content.lines().collect();
+        let Some(text) = file_lines.get(line as usize - 1) else {
+            return ToolCallResult::error(format!("{} has {} lines; line {} is out of range", path, file_lines.len(), line));
+        };
+        let Some(symbol) = identifier_at(text, column) else {
+            return ToolCallResult::error(format!(
+                "No identifier at {}:{}:{}. Line text: {}", path, line, column, text.trim()));
+        };
+        let (resolution, defs) = resolve(&index, file_id, line, &symbol, &file_lines);
+        let definitions: Vec<Value> = defs.iter().take(MAX_CANDIDATES + 1).map(|d| definition_json(&index, d)).collect();
+        (path, symbol, resolution, definitions)
+    };
+
+    let mut output = json!({
+        "symbol": symbol.name,
+        "file": path,
+        "line": line,
+        "column": column,
+        "range": { "startColumn": symbol.start_column, "endColumn": symbol.end_column },
+        "resolution": resolution,
+    });
+    if let Some(ref qualifier) = symbol.qualifier {
+        output["qualifier"] = json!(qualifier);
+    }
+    if let Some((definition, others)) = definitions.split_first() {
+        output["definition"] = definition.clone();
+        if !others.is_empty() {
+            output["candidates"] = json!(others);
+        }
+    }
+
+    let mut summary = json!({
+        "tool": "search_symbol_at",
+        "definitionsFound": definitions.len(),
+    });
+    if include_references {
+        match collect_references(ctx, &symbol.name, None, &[], None) {
+            Ok(scan) => {
+                summary["totalReferences"] = json!(scan.references.len());
+                summary["referenceFiles"] = json!(scan.files);
+                summary["byUsage"] = json!(scan.by_usage);
+                let mut references = scan.references;
+                if max_references > 0 {
+                    references.truncate(max_references);
+                }
+                output["references"] = json!(references);
+            }
+            Err(e) => return ToolCallResult::error(e),
+        }
+    }
+    if resolution == "unresolved" {
+        summary["hint"] = json!("No definition with this name is indexed: it is a local, a parameter, or defined outside the indexed code.");
+    }
+    summary["searchTimeMs"] = json!(start.elapsed().as_secs_f64() * 1000.0);
+    inject_branch_warning(&mut summary, ctx);
+    output["summary"] = summary;
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
+
+/// The identifier under the cursor.
+#[derive(Debug, PartialEq)]
+struct Symbol {
+    name: String,
+    /// 1-based character columns, end exclusive
+    start_column: usize,
+    end_column: usize,
+    /// `x` in `x.Name` / `x?.Name`
+    qualifier: Option<String>,
+}
+
+/// Identifier covering 1-based character `column` of `text`. A column just past the
+/// end of an identifier (a cursor after the last letter) also selects it.
+fn identifier_at(text: &str, column: usize) -> Option<Symbol> {
+    let chars: Vec<char> = text.chars().collect();
+    let at = column - 1;
+    let pos = if chars.get(at).is_some_and(|&c| is_ident_char(c)) {
+        at
+    } else if at > 0 && chars.get(at - 1).is_some_and(|&c| is_ident_char(c)) {
+        at - 1
+    } else {
+        return None;
+    };
+    let start = (0..=pos).rev().take_while(|&i| is_ident_char(chars[i])).last()?;
+    let end = (pos..chars.len()).take_while(|&i| is_ident_char(chars[i])).last()? + 1;
+    if chars[start].is_ascii_digit() {
+        return None;
+    }
+
+    let before: String = chars[..start].iter().collect();
+    let qualifier = before.trim_end().strip_suffix('.')
+        .map(|b| b.strip_suffix('?').unwrap_or(b))
+        .and_then(|b| {
+            let word: String = b.chars().rev().take_while(|&c| is_ident_char(c)).collect();
+            (!word.is_empty()).then(|| word.chars().rev().collect())
+        });
+    Some(Symbol {
+        name: chars[start..end].iter().collect(),
+        start_column: start + 1,
+        end_column: end + 1,
+        qualifier,
+    })
+}
+
+/// How the symbol was resolved, and its definitions, best first.
+fn resolve<'a>(
+    index: &'a DefinitionIndex,
+    file_id: u32,
+    line: u32,
+    symbol: &Symbol,
+    file_lines: &[&str],
+) -> (&'static str, Vec<&'a DefinitionEntry>) {
+    let name = symbol.name.as_str();
+    let named: Vec<&DefinitionEntry> = index.name_index.get(&name.to_lowercase())
+        .map(|ids| ids.iter().filter_map(|&i| index.definitions.get(i as usize)).filter(|d| d.name == name).collect())
+        .unwrap_or_default();
+
+    // Declaration: a definition in this file whose name appears first on this line
+    if let Some(def) = named.iter().find(|d| d.file_id == file_id
+        && first_line_with_word(file_lines, d.line_start, d.line_end, name) == Some(line)) {
+        return ("declaration", vec![def]);
+    }
+
+    let enclosing = find_containing_method(index, file_id, line);
+    let enclosing_class = enclosing.as_ref().and_then(|(_, parent, _, _)| parent.clone());
+
+    // Call site parsed on this line: its receiver type names the owner
+    let receiver = enclosing.as_ref()
+        .and_then(|(_, _, _, di)| index.method_calls.get(di))
+        .and_then(|calls| calls.iter().find(|c| c.line == line && c.method_name == name
+            && (c.column == 0 || c.column as usize == symbol.start_column)))
+        .map(|call| call.receiver_type.as_deref().map(strip_generics).map(str::to_string));
+    if let Some(receiver) = receiver {
+        let owner = receiver.or(enclosing_class.clone());
+        let owned: Vec<&DefinitionEntry> = named.iter().copied()
+            .filter(|d| owner.is_some() && d.parent == owner)
+            .collect();
+        if !owned.is_empty() {
+            return ("callSite", owned);
+        }
+    }
+
+    if named.is_empty() {
+        return ("unresolved", Vec::new());
+    }
+    // By name: same class, then qualifier type, then same file, then types
+    let mut ranked = named;
+    let qualifier = symbol.qualifier.as_deref();
+    ranked.sort_by_key(|d| (
+        !(enclosing_class.is_some() && d.parent == enclosing_class),
+        !(qualifier.is_some() && d.parent.as_deref() == qualifier),
+        d.file_id != file_id,
+        !is_type_kind(d.kind),
+        index.files.get(d.file_id as usize).cloned(),
+        d.line_start,
+    ));
+    ("name", ranked)
+}
+
+fn definition_json(index: &DefinitionIndex, def: &DefinitionEntry) -> Value {
+    let mut obj = json!({
+        "name": def.name,
+        "kind": def.kind.as_str(),
+        "file": index.files.get(def.file_id as usize).cloned().unwrap_or_default(),
+        "lines": format!("{}-{}", def.line_start, def.line_end),
+    });
+    if let Some(ref parent) = def.parent {
+        obj["parent"] = json!(parent);
+    }
+    if let Some(ref sig) = def.signature {
+        obj["signature"] = json!(sig);
+    }
+    obj
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_at_columns_and_qualifier() {
+        let text = "    var user = _userService?.GetUser(42);";
+        let sym = identifier_at(text, 32).unwrap();
+        assert_eq!(sym, Symbol { name: "GetUser".to_string(), start_column: 30, end_column: 37, qualifier: Some("_userService".to_string()) });
+        // Cursor right after the last letter
+        assert_eq!(identifier_at(text, 37).unwrap().name, "GetUser");
+        assert_eq!(identifier_at(text, 10).unwrap().qualifier, None);
+        assert!(identifier_at(text, 1).is_none(), "whitespace");
+        assert!(identifier_at(text, 41).is_none(), "after ')'");
+        assert!(identifier_at(text, 38).is_none(), "number literal");
+    }
+}
@@ -0,0 +1,140 @@
+//! search_tickets handler: maps an issue-tracker ID (`PAY-812`, `#77`) to the code
+//! comments and commits that mention it.
+//!
+//! Code references come from the content index's `ticket_refs` (comments only);
+//! commits come from subjects in the git history cache.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::git::cache::GitHistoryCache;
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use crate::owners::Owners;
+use crate::{normalize_ticket, ContentIndex};
+use search::lock_stats::{self, Lock};
+
+use super::git::format_timestamp;
+use super::utils::{inject_branch_warning, inject_owners, inject_owners_source, is_under_dir, owner_allows, parse_owner_filter};
+use super::HandlerContext;
+
+const DEFAULT_MAX_RESULTS: usize = 50;
+/// Changed files listed per commit; the rest are counted in `filesTotal`.
+const MAX_FILES_PER_COMMIT: usize = 20;
+
+pub(crate) fn handle_search_tickets(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let index = ctx.index.snapshot();
+    let cache_guard = match lock_stats::timed(Lock::Git, || ctx.git_cache.read()) {
+        Ok(guard) => guard,
+        Err(e) => return SearchError::LockPoisoned(format!("git cache: {}", e)).into(),
+    };
+    let cache = cache_guard.as_ref().filter(|_| ctx.git_cache_ready.load(Ordering::Acquire));
+    match search_tickets_json(&index, cache, args, ctx.owners.as_deref()) {
+        Ok(mut output) => {
+            if cache.is_none() {
+                output["summary"]["commitsSkipped"] = json!(if ctx.git_cache_ready.load(Ordering::Acquire) {
+                    "Git history not available: the server directory is not a git repository root."
+                } else {
+                    "Git history cache is still being built. Retry in a few seconds to include commits."
+                });
+            }
+            inject_branch_warning(&mut output["summary"], ctx);
+            ToolCallResult::success(serde_json::to_string(&output).unwrap())
+        }
+        Err(msg) => ToolCallResult::error(msg),
+    }
+}
+
+/// Look up one ticket in the index's comment references and, when a cache is given,
+/// in commit subjects. Shared by the MCP handler and the `search tickets` CLI command;
+/// `args` uses the MCP parameter names.
+pub(crate) fn search_tickets_json(
+    index: &ContentIndex,
+    cache: Option<&GitHistoryCache>,
+    args: &Value,
+    owners: Option<&Owners>,
+) -> Result<Value, String> {
+    let start = Instant::now();
+
+    let raw = args.get("ticket").and_then(|v| v.as_str()).unwrap_or("");
+    if raw.trim().is_empty() {
+        return Err("Missing required parameter: ticket".to_string());
+    }
+    let ticket = normalize_ticket(raw).ok_or_else(|| format!(
+        "Invalid ticket '{}'. Use a tracker key like PAY-812 or an issue number like #77", raw))?;
+    let dir = args.get("dir").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|d| {
+        if Path::new(d).is_absolute() { d.to_string() } else { format!("{}/{}", index.root, d.trim_start_matches("./")) }
+    });
+    let exclude_dir: Vec<String> = args.get("excludeDir").and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).map(|s| s.replace('\\', "/").to_lowercase()).collect())
+        .unwrap_or_default();
+    let owner_filter = parse_owner_filter(owners, args)?;
+    let max_results = match args.get("maxResults").and_then(|v| v.as_u64()) {
+        Some(0) => usize::MAX,
+        Some(n) => n as usize,
+        None => DEFAULT_MAX_RESULTS,
+    };
+
+    // file path -> lines, sorted by path
+    let mut by_file: BTreeMap<&str, Vec<u32>> = BTreeMap::new();
+    for r in index.ticket_refs.iter().filter(|r| r.ticket == ticket) {
+        let Some(file) = index.files.get(r.file_id as usize) else { continue };
+        let lower = file.replace('\\', "/").to_lowercase();
+        if dir.as_ref().is_none_or(|d| is_under_dir(file, d))
+            && !exclude_dir.iter().any(|e| lower.contains(e.as_str()))
+            && owner_allows(owners, owner_filter.as_deref(), file)
+        {
+            by_file.entry(file.as_str()).or_default().push(r.line);
+        }
+    }
+    let code_refs: usize = by_file.values().map(Vec::len).sum();
+    let code_files = by_file.len();
+    let code: Vec<Value> = by_file.into_iter().take(max_results).map(|(file, lines)| {
+        let mut obj = json!({ "file": file, "lines": lines });
+        inject_owners(&mut obj, owners, file);
+        obj
+    }).collect();
+
+    let matched_commits = cache.map(|c| c.query_ticket(&ticket)).unwrap_or_default();
+    let total_commits = matched_commits.len();
+    let commits: Vec<Value> = matched_commits.into_iter().take(max_results).map(|(info, files)| {
+        let files_total = files.len();
+        let mut obj = json!({
+            "hash": info.hash,
+            "date": format_timestamp(info.timestamp),
+            "author": info.author_name,
+            "email": info.author_email,
+            "subject": info.subject,
+            "files": files.into_iter().take(MAX_FILES_PER_COMMIT).collect::<Vec<_>>(),
+        });
+        if files_total > MAX_FILES_PER_COMMIT {
+            obj["filesTotal"] = json!(files_total);
+        }
+        obj
+    }).collect();
+
+    let mut summary = json!({
+        "ticket": ticket,
+        "codeFiles": code_files,
+        "codeRefs": code_refs,
+        "commits": total_commits,
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    inject_owners_source(&mut summary, owners);
+    if index.ticket_refs.is_empty() {
+        summary["hint"] = json!("No ticket references recorded. Indexes built before ticket tracking have none; rebuild the content index.");
+    } else if code.is_empty() && commits.is_empty() {
+        summary["hint"] = json!("No comment or commit subject mentions this ticket. Check the key's spelling, or drop dir/excludeDir/owner.");
+    }
+
+    Ok(json!({
+        "ticket": ticket,
+        "code": code,
+        "commits": commits,
+        "summary": summary,
+    }))
+}
@@ -0,0 +1,181 @@
+//! search_todos handler: TODO/FIXME/HACK/BUG comments recorded in the content index,
+//! optionally enriched with author and age from `git blame`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::git::{self, BlameLine};
+use crate::mcp::protocol::ToolCallResult;
+use crate::{CommentMarker, ContentIndex, COMMENT_MARKERS};
+
+use super::utils::{inject_branch_warning, is_under_dir};
+use super::HandlerContext;
+
+const DEFAULT_MAX_RESULTS: usize = 100;
+/// Blame runs one `git blame` per file; beyond this many files the rest stay unblamed.
+const MAX_BLAME_FILES: usize = 200;
+const SECS_PER_DAY: i64 = 86_400;
+
+pub(crate) fn handle_search_todos(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let index = ctx.index.snapshot();
+    match search_todos_json(&index, args) {
+        Ok(mut output) => {
+            inject_branch_warning(&mut output["summary"], ctx);
+            ToolCallResult::success(serde_json::to_string(&output).unwrap())
+        }
+        Err(msg) => ToolCallResult::error(msg),
+    }
+}
+
+/// Filter the index's comment markers and build the JSON response.
+/// Shared by the MCP handler and the `search todos` CLI command; `args` uses the
+/// MCP parameter names. Blame runs against the index root.
+pub(crate) fn search_todos_json(index: &ContentIndex, args: &Value) -> Result<Value, String> {
+    let start = Instant::now();
+
+    let markers: Vec<String> = match args.get("marker").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        Some(list) => {
+            let mut wanted = Vec::new();
+            for m in list.split(',').map(|m| m.trim().to_uppercase()).filter(|m| !m.is_empty()) {
+                if !COMMENT_MARKERS.contains(&m.as_str()) {
+                    return Err(format!("Unknown marker '{}'. Valid markers: {}", m, COMMENT_MARKERS.join(", ")));
+                }
+                wanted.push(m);
+            }
+            wanted
+        }
+        None => Vec::new(),
+    };
+    let dir = args.get("dir").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|d| {
+        if Path::new(d).is_absolute() { d.to_string() } else { format!("{}/{}", index.root, d.trim_start_matches("./")) }
+    });
+    let exclude_dir: Vec<String> = args.get("excludeDir").and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).map(|s| s.replace('\\', "/").to_lowercase()).collect())
+        .unwrap_or_default();
+    let min_age = args.get("minAgeDays").and_then(|v| v.as_u64());
+    let max_age = args.get("maxAgeDays").and_then(|v| v.as_u64());
+    if let (Some(min), Some(max)) = (min_age, max_age)
+        && min > max {
+            return Err(format!("minAgeDays ({}) must be <= maxAgeDays ({})", min, max));
+        }
+    let author = args.get("author").and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|s| s.to_lowercase());
+    let needs_blame = min_age.is_some() || max_age.is_some() || author.is_some();
+    let blame = needs_blame || args.get("blame").and_then(|v| v.as_bool()).unwrap_or(false);
+    let sort_by_age = match args.get("sortBy").and_then(|v| v.as_str()).unwrap_or("path") {
+        "path" => false,
+        "age" if blame => true,
+        "age" => return Err("sortBy=age requires blame=true".to_string()),
+        other => return Err(format!("Invalid sortBy '{}'. Valid values: path, age", other)),
+    };
+    let max_results = match args.get("maxResults").and_then(|v| v.as_u64()) {
+        Some(0) => usize::MAX,
+        Some(n) => n as usize,
+        None => DEFAULT_MAX_RESULTS,
+    };
+
+    let file_of = |m: &CommentMarker| index.files.get(m.file_id as usize).map(|s| s.as_str()).unwrap_or("");
+    let mut matched: Vec<&CommentMarker> = index.comment_markers.iter()
+        .filter(|m| {
+            let file = file_of(m);
+            let lower = file.replace('\\', "/").to_lowercase();
+            (markers.is_empty() || markers.contains(&m.marker))
+                && dir.as_ref().is_none_or(|d| is_under_dir(file, d))
+                && !exclude_dir.iter().any(|e| lower.contains(e.as_str()))
+        })
+        .collect();
+    matched.sort_by(|a, b| file_of(a).cmp(file_of(b)).then(a.line.cmp(&b.line)));
+
+    // (file_id, line) -> blame for that line
+    let mut blamed: HashMap<(u32, u32), BlameLine> = HashMap::new();
+    let mut blame_failed_files = 0usize;
+    let mut blame_skipped_files = 0usize;
+    if blame {
+        let mut ranges: BTreeMap<u32, (u32, u32)> = BTreeMap::new();
+        for m in &matched {
+            let range = ranges.entry(m.file_id).or_insert((m.line, m.line));
+            range.0 = range.0.min(m.line);
+            range.1 = range.1.max(m.line);
+        }
+        blame_skipped_files = ranges.len().saturating_sub(MAX_BLAME_FILES);
+        for (&file_id, &(first, last)) in ranges.iter().take(MAX_BLAME_FILES) {
+            let path = index.files.get(file_id as usize).map(|s| s.as_str()).unwrap_or("");
+            match git::blame_lines(&index.root, path, first as usize, Some(last as usize)) {
+                Ok(lines) => blamed.extend(lines.into_iter().map(|b| ((file_id, b.line as u32), b))),
+                Err(_) => blame_failed_files += 1,
+            }
+        }
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    let age_days = |b: &BlameLine| (now - b.timestamp).max(0) / SECS_PER_DAY;
+    if needs_blame {
+        matched.retain(|m| blamed.get(&(m.file_id, m.line)).is_some_and(|b| {
+            let age = age_days(b) as u64;
+            min_age.is_none_or(|min| age >= min)
+                && max_age.is_none_or(|max| age <= max)
+                && author.as_ref().is_none_or(|a| {
+                    b.author_name.to_lowercase().contains(a.as_str()) || b.author_email.to_lowercase().contains(a.as_str())
+                })
+        }));
+    }
+    if sort_by_age {
+        // Oldest first; unblamed markers last. Stable, so path order breaks ties.
+        matched.sort_by_key(|m| blamed.get(&(m.file_id, m.line)).map(|b| b.timestamp).unwrap_or(i64::MAX));
+    }
+
+    let mut by_marker: BTreeMap<&str, usize> = BTreeMap::new();
+    for m in &matched {
+        *by_marker.entry(m.marker.as_str()).or_default() += 1;
+    }
+    let total_matching = matched.len();
+    let blamed_markers = matched.iter().filter(|m| blamed.contains_key(&(m.file_id, m.line))).count();
+    matched.truncate(max_results);
+
+    let todos: Vec<Value> = matched.iter().map(|m| {
+        let mut obj = json!({
+            "file": file_of(m),
+            "line": m.line,
+            "marker": m.marker,
+            "text": m.text,
+        });
+        if let Some(b) = blamed.get(&(m.file_id, m.line)) {
+            obj["author"] = json!(b.author_name);
+            obj["date"] = json!(b.date.split(' ').next().unwrap_or(""));
+            obj["ageDays"] = json!(age_days(b));
+            obj["commit"] = json!(b.hash);
+        }
+        obj
+    }).collect();
+
+    let mut summary = json!({
+        "returned": todos.len(),
+        "totalMatching": total_matching,
+        "totalMarkers": index.comment_markers.len(),
+        "byMarker": by_marker,
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if blame {
+        summary["blamedMarkers"] = json!(blamed_markers);
+        if blame_failed_files > 0 {
+            summary["blameFailedFiles"] = json!(blame_failed_files);
+        }
+        if blame_skipped_files > 0 {
+            summary["blameSkippedFiles"] = json!(blame_skipped_files);
+            summary["hint"] = json!(format!(
+                "Blame is limited to {} files per call. Narrow with dir or marker to blame the rest.", MAX_BLAME_FILES));
+        }
+    }
+    if index.comment_markers.is_empty() {
+        summary["hint"] = json!("No comment markers recorded. Indexes built before TODO tracking have none; rebuild the content index.");
+    } else if todos.is_empty() && summary.get("hint").is_none() {
+        summary["hint"] = json!("No markers match. Drop marker/dir/age filters or check excludeDir.");
+    }
+
+    Ok(json!({
+        "todos": todos,
+        "summary": summary,
+    }))
+}
@@ -1,12 +1,19 @@
 //! Shared utility functions for MCP tool handlers.
 
 use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
 use serde_json::{json, Value};
 
+use crate::mcp::progress;
 use crate::mcp::protocol::ToolCallResult;
 use crate::clean_path;
+use crate::config::RankingBoosts;
+use crate::owners::Owners;
+use crate::rank_hook::RankHook;
+use search::query::{path_key, DefinitionBoost, Scorer};
 
 use super::HandlerContext;
 
@@ -35,6 +42,43 @@ pub(crate) fn inject_branch_warning(summary: &mut Value, ctx: &HandlerContext) {
     }
 }
 
+// ─── Ownership ──────────────────────────────────────────────────────
+
+/// Parse the `owner` filter argument. Errors when a filter is given but no ownership
+/// file is loaded, so an empty result is never mistaken for "no matches".
+pub(crate) fn parse_owner_filter(owners: Option<&Owners>, args: &Value) -> Result<Option<String>, String> {
+    match args.get("owner").and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty()) {
+        Some(_) if owners.is_none() => Err(
+            "owner filter requires an ownership file: add a CODEOWNERS file under the indexed directory or pass --owners <FILE>.".to_string()
+        ),
+        Some(owner) => Ok(Some(owner.to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Whether `path` passes the `owner` filter (always true without a filter).
+pub(crate) fn owner_allows(owners: Option<&Owners>, filter: Option<&str>, path: &str) -> bool {
+    match (owners, filter) {
+        (Some(owners), Some(filter)) => owners.matches(path, filter),
+        _ => true,
+    }
+}
+
+/// Add an `owners` array to a result object when ownership rules are loaded.
+/// An empty array means no rule assigns the file an owner.
+pub(crate) fn inject_owners(obj: &mut Value, owners: Option<&Owners>, path: &str) {
+    if let Some(owners) = owners {
+        obj["owners"] = json!(owners.owners_of(path));
+    }
+}
+
+/// Report which ownership file annotated the results.
+pub(crate) fn inject_owners_source(summary: &mut Value, owners: Option<&Owners>) {
+    if let Some(owners) = owners {
+        summary["ownersSource"] = json!(owners.source);
+    }
+}
+
 // ─── Dir validation ─────────────────────────────────────────────────
 
 /// Normalize path separators to forward slashes for cross-platform comparison.
@@ -105,31 +149,636 @@ pub(crate) fn matches_ext_filter(file_path: &str, ext_filter: &str) -> bool {
         })
 }
 
-// ─── Set operations ─────────────────────────────────────────────────
+// ─── Result ordering ────────────────────────────────────────────────
+
+/// Result ordering for grep (`--sort` on the CLI, `sortBy` in MCP).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum GrepSort {
+    /// TF-IDF relevance, most relevant first (phrase search: most matching lines first)
+    #[default]
+    Score,
+    /// File path, ascending — stable output for diffs in CI
+    Path,
+    /// File modification time, newest first
+    Mtime,
+    /// Number of matches in the file, most first
+    Matches,
+}
+
+impl GrepSort {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Score => "score",
+            Self::Path => "path",
+            Self::Mtime => "mtime",
+            Self::Matches => "matches",
+        }
+    }
+}
+
+impl std::str::FromStr for GrepSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "score" => Ok(Self::Score),
+            "path" => Ok(Self::Path),
+            "mtime" => Ok(Self::Mtime),
+            "matches" => Ok(Self::Matches),
+            other => Err(format!("Invalid sortBy value '{}'. Valid values: score, path, mtime, matches", other)),
+        }
+    }
+}
+
+/// Order for TF-IDF ranked results: score descending, then path, then file_id.
+/// Equal scores are common (same occurrence count in same-sized files); without the
+/// secondary keys ties come back in HashMap iteration order, which changes between runs.
+pub(crate) fn cmp_ranked(a: (f64, &str, u32), b: (f64, &str, u32)) -> std::cmp::Ordering {
+    b.0.total_cmp(&a.0)
+        .then_with(|| a.1.cmp(b.1))
+        .then_with(|| a.2.cmp(&b.2))
+}
+
+/// Reorder grep results for a non-default sort. `Score` keeps the caller's relevance order.
+/// Ties (same mtime / match count) are broken by path so output is deterministic.
+/// Files whose mtime cannot be read sort last.
+pub(crate) fn apply_grep_sort<T>(
+    results: &mut [T],
+    sort: GrepSort,
+    path: impl Fn(&T) -> &str,
+    matches: impl Fn(&T) -> usize,
+) {
+    use std::cmp::Reverse;
+    match sort {
+        GrepSort::Score => {}
+        GrepSort::Path => results.sort_by(|a, b| path(a).cmp(path(b))),
+        GrepSort::Matches => results.sort_by(|a, b| matches(b).cmp(&matches(a)).then_with(|| path(a).cmp(path(b)))),
+        GrepSort::Mtime => results.sort_by_cached_key(|r| {
+            let mtime = std::fs::metadata(path(r)).and_then(|m| m.modified()).ok();
+            (Reverse(mtime), path(r).to_string())
+        }),
+    }
+}
+
+// ─── Directory grouping ─────────────────────────────────────────────
+
+/// Files listed per directory by grep `--group-by dir` / `groupBy: "dir"`.
+pub(crate) const GROUP_TOP_FILES: usize = 3;
+
+/// Grep result grouping (`--group-by` on the CLI, `groupBy` in MCP).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum GrepGroup {
+    /// Hit counts per directory, with the top files of each
+    Dir,
+}
+
+impl std::str::FromStr for GrepGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dir" => Ok(Self::Dir),
+            other => Err(format!("Invalid groupBy value '{}'. Valid values: dir", other)),
+        }
+    }
+}
+
+/// Directory of `path` relative to `root`, cut to its first `depth` components
+/// (0 = the file's own directory). Files directly under the root are in `.`.
+fn group_dir(root: &str, path: &str, depth: usize) -> String {
+    let path = normalize_path_sep(path);
+    let root = normalize_path_sep(root);
+    let rel = path.strip_prefix(root.trim_end_matches('/'))
+        .filter(|rest| rest.starts_with('/'))
+        .map_or(path.as_str(), |rest| rest.trim_start_matches('/'))
+        .trim_start_matches("./");
+    let mut parts: Vec<&str> = rel.split('/').collect();
+    parts.pop();
+    if depth > 0 {
+        parts.truncate(depth);
+    }
+    if parts.is_empty() { ".".to_string() } else { parts.join("/") }
+}
+
+/// Grep results grouped by directory: files, occurrences and the first
+/// [`GROUP_TOP_FILES`] files of each, in the order of `results` (path, occurrences).
+/// Directories with the most occurrences come first, ties by name.
+pub(crate) fn group_by_dir<'a>(root: &str, results: impl IntoIterator<Item = (&'a str, usize)>, depth: usize) -> Vec<Value> {
+    let mut groups: Vec<(String, usize, usize, Vec<Value>)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (path, occurrences) in results {
+        let dir = group_dir(root, path, depth);
+        let at = *positions.entry(dir.clone()).or_insert_with(|| {
+            groups.push((dir, 0, 0, Vec::new()));
+            groups.len() - 1
+        });
+        let group = &mut groups[at];
+        group.1 += 1;
+        group.2 += occurrences;
+        if group.3.len() < GROUP_TOP_FILES {
+            group.3.push(json!({ "path": path, "occurrences": occurrences }));
+        }
+    }
+    groups.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    groups.into_iter()
+        .map(|(dir, files, occurrences, top)| json!({ "dir": dir, "files": files, "occurrences": occurrences, "topFiles": top }))
+        .collect()
+}
+
+// ─── Ranking boosts ─────────────────────────────────────────────────
+
+/// Directory names whose files are tests
+const TEST_DIRS: &[&str] = &["test", "tests", "spec", "specs", "__tests__", "__mocks__", "testing"];
+
+/// Directory names whose files are generated or build output
+const GENERATED_DIRS: &[&str] = &["obj", "bin", "generated", "__generated__", "dist"];
+
+/// File name endings of generated code (lowercase)
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".g.cs", ".g.i.cs", ".designer.cs", ".generated.cs", ".generated.ts", ".pb.go", "_pb2.py", ".min.js",
+];
+
+/// A factor applied to a file's TF-IDF score, reported by explain mode.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Boost {
+    /// `filename`, `definition`, `test` or `generated`, as in `[ranking]`
+    pub kind: &'static str,
+    pub factor: f64,
+    pub reason: String,
+}
+
+/// How grep ranks the files of one search: the `[ranking]` factors for file names,
+/// definitions, tests and generated code on top of TF-IDF, the semantic similarities
+/// a `rerank` fuses in, and whether to explain the scores.
+pub(crate) struct Ranking {
+    factors: RankingBoosts,
+    /// Lowercased query terms looked for in file names
+    terms: Vec<String>,
+    /// Path key -> name of a symbol it defines that matches a query term, handed to
+    /// the [`Scorer`]
+    defining_files: HashMap<String, String>,
+    /// Lowercased path -> best chunk similarity to the query, when reranking
+    semantic: Option<HashMap<String, f64>>,
+    /// How the semantic index was brought up to date for the rerank (`IndexRefresh::as_str`)
+    semantic_index: &'static str,
+    /// `--rank-hook` policy, applied after the built-in ranking
+    rank_hook: Option<Arc<RankHook>>,
+    pub explain: bool,
+}
+
+impl Ranking {
+    pub fn new(factors: RankingBoosts, terms: &[String], explain: bool) -> Self {
+        Self {
+            factors,
+            terms: terms.iter().map(|t| t.to_lowercase()).filter(|t| !t.is_empty()).collect(),
+            defining_files: HashMap::new(),
+            semantic: None,
+            semantic_index: "",
+            rank_hook: None,
+            explain,
+        }
+    }
+
+    /// Plain TF-IDF: every factor 1.0.
+    pub fn unboosted(explain: bool) -> Self {
+        let off = RankingBoosts { filename: 1.0, definition: 1.0, test: 1.0, generated: 1.0 };
+        Self::new(off, &[], explain)
+    }
+
+    /// Whether the definition factor can change a score, so the caller should look up
+    /// [`search::query::defining_files`] for [`Ranking::with_defining_files`].
+    pub fn wants_definitions(&self) -> bool {
+        self.factors.definition != 1.0 && !self.terms.is_empty()
+    }
+
+    pub fn terms(&self) -> &[String] {
+        &self.terms
+    }
 
-/// Merge-intersect two sorted u32 slices. Returns sorted intersection.
-pub(crate) fn sorted_intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
-    let mut result = Vec::new();
-    let (mut i, mut j) = (0, 0);
-    while i < a.len() && j < b.len() {
-        match a[i].cmp(&b[j]) {
-            std::cmp::Ordering::Equal => { result.push(a[i]); i += 1; j += 1; }
-            std::cmp::Ordering::Less => i += 1,
-            std::cmp::Ordering::Greater => j += 1,
+    /// Files that define a symbol named like a query term, as [`search::query::defining_files`]
+    /// returns them.
+    pub fn with_defining_files(mut self, files: HashMap<String, String>) -> Self {
+        self.defining_files = files;
+        self
+    }
+
+    /// A TF-IDF scorer over `index` that applies the definition factor to the files of
+    /// [`Ranking::with_defining_files`], so its scores already carry that boost.
+    pub fn scorer<'a>(&self, index: &'a crate::ContentIndex) -> Scorer<'a> {
+        let scorer = Scorer::new(index, self.explain);
+        if self.factors.definition == 1.0 || self.defining_files.is_empty() {
+            return scorer;
+        }
+        scorer.boost_definitions(self.defining_files.clone(), self.factors.definition)
+    }
+
+    /// Rerank with these per-file semantic similarities (see [`fuse_ranks`]), read from
+    /// a semantic index that was `index` (`current`, `updated`, `built`).
+    pub fn with_semantic_scores(mut self, files: impl IntoIterator<Item = (String, f64)>, index: &'static str) -> Self {
+        self.semantic = Some(files.into_iter().map(|(path, score)| (path_key(&path), score)).collect());
+        self.semantic_index = index;
+        self
+    }
+
+    /// Re-score the ranked files with this policy module before truncation.
+    pub fn with_rank_hook(mut self, hook: Option<Arc<RankHook>>) -> Self {
+        self.rank_hook = hook;
+        self
+    }
+
+    pub fn rank_hook(&self) -> Option<&RankHook> {
+        self.rank_hook.as_deref()
+    }
+
+    /// The semantic similarity of `path`, when reranking and the file has one.
+    pub fn semantic_score(&self, path: &str) -> Option<f64> {
+        self.semantic.as_ref()?.get(&path_key(path)).copied()
+    }
+
+    pub fn reranks(&self) -> bool {
+        self.semantic.is_some()
+    }
+
+    /// The `rerank` entry of a grep summary, when reranking.
+    pub fn rerank_summary(&self) -> Option<Value> {
+        self.semantic.as_ref()?;
+        Some(json!({ "method": "rrf", "k": RRF_K, "semanticIndex": self.semantic_index }))
+    }
+
+    /// The factors that apply to `path`; `definition` is what the [`Scorer`] found it defines.
+    pub fn boosts(&self, path: &str, definition: Option<&DefinitionBoost>) -> Vec<Boost> {
+        let mut boosts = Vec::new();
+        let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        let lower_name = name.to_lowercase();
+        if self.factors.filename != 1.0
+            && let Some(term) = self.terms.iter().find(|t| lower_name.contains(t.as_str()))
+        {
+            boosts.push(Boost { kind: "filename", factor: self.factors.filename, reason: format!("file name contains '{}'", term) });
+        }
+        if let Some(definition) = definition {
+            boosts.push(Boost { kind: "definition", factor: definition.factor, reason: format!("defines {}", definition.symbol) });
+        }
+        if self.factors.test != 1.0 && is_test_path(path) {
+            boosts.push(Boost { kind: "test", factor: self.factors.test, reason: "test file".to_string() });
+        }
+        if self.factors.generated != 1.0 && is_generated_path(path) {
+            boosts.push(Boost { kind: "generated", factor: self.factors.generated, reason: "generated code".to_string() });
         }
+        boosts
     }
-    result
 }
 
+/// Rank constant of reciprocal rank fusion: larger values flatten the gap between
+/// the first ranks. 60 is the value of the original RRF paper.
+pub(crate) const RRF_K: f64 = 60.0;
+
+/// Reciprocal rank fusion of a lexical order with semantic similarities: each item
+/// scores `1/(k + lexical rank) + 1/(k + semantic rank)`, ranks 1-based, and items
+/// without a similarity only get the lexical term. Returns, per item of the lexical
+/// order, its fused score and semantic rank; the caller sorts by the score.
+pub(crate) fn fuse_ranks(semantic: &[Option<f64>]) -> Vec<(f64, Option<usize>)> {
+    let mut by_similarity: Vec<(usize, f64)> = semantic.iter().enumerate()
+        .filter_map(|(i, s)| s.map(|s| (i, s))).collect();
+    by_similarity.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut semantic_rank = vec![None; semantic.len()];
+    for (rank, &(i, _)) in by_similarity.iter().enumerate() {
+        semantic_rank[i] = Some(rank + 1);
+    }
+    semantic_rank.into_iter().enumerate().map(|(i, rank)| {
+        let lexical = 1.0 / (RRF_K + (i + 1) as f64);
+        (lexical + rank.map_or(0.0, |r| 1.0 / (RRF_K + r as f64)), rank)
+    }).collect()
+}
+
+/// `score` with every factor of `boosts` applied.
+pub(crate) fn boosted(score: f64, boosts: &[Boost]) -> f64 {
+    boosts.iter().fold(score, |score, boost| score * boost.factor)
+}
+
+/// A test directory on the path, or a test file name: `*Test(s).*`, `*_test.*`,
+/// `test_*`, `*.test.*`, `*.spec.*`.
+pub(crate) fn is_test_path(path: &str) -> bool {
+    let mut parts: Vec<&str> = path.split(['/', '\\']).collect();
+    let name = parts.pop().unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or(name);
+    let lower = name.to_lowercase();
+    parts.iter().any(|dir| {
+        let dir = dir.to_lowercase();
+        TEST_DIRS.contains(&dir.as_str()) || dir.ends_with(".tests") || dir.ends_with(".test")
+    }) || stem.ends_with("Test") || stem.ends_with("Tests")
+        || lower.contains("_test.") || lower.starts_with("test_")
+        || lower.contains(".test.") || lower.contains(".spec.")
+}
+
+/// A build output or generated-code directory on the path, or a generated file name
+/// such as `*.g.cs` or `*.designer.cs`.
+pub(crate) fn is_generated_path(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    let mut parts: Vec<&str> = lower.split(['/', '\\']).collect();
+    let name = parts.pop().unwrap_or_default();
+    parts.iter().any(|dir| GENERATED_DIRS.contains(dir)) || GENERATED_SUFFIXES.iter().any(|s| name.ends_with(s))
+}
+
+/// How grep's explain mode describes the ranking score.
+pub(crate) const EXPLAIN_FORMULA: &str =
+    "score = tfIdf x boost factors, tfIdf = sum over matched tokens of tf x idf, tf = occurrences / fileTokens, idf = ln(indexed files / docFreq)";
+
+pub(crate) use search::query::TermScore;
+
+/// Round a score component to the 4 decimals grep reports.
+fn round4(value: f64) -> f64 {
+    (value * 10000.0).round() / 10000.0
+}
+
+/// The `explain` object of a grep result: the final score, each token's TF, IDF and
+/// contribution (largest first), and the boosts applied on top of the TF-IDF sum.
+pub(crate) fn explain_json(score: f64, terms: &[TermScore], boosts: &[Boost]) -> Value {
+    let mut terms: Vec<&TermScore> = terms.iter().collect();
+    terms.sort_by(|a, b| b.contribution().total_cmp(&a.contribution()).then_with(|| a.token.cmp(&b.token)));
+    let terms: Vec<Value> = terms.into_iter().map(|t| {
+        let mut term = json!({
+            "term": t.term,
+            "occurrences": t.occurrences,
+            "fileTokens": t.file_tokens,
+            "tf": round4(t.tf),
+            "docFreq": t.doc_freq,
+            "idf": round4(t.idf),
+            "contribution": round4(t.contribution()),
+        });
+        if t.token != t.term {
+            term["token"] = json!(t.token);
+        }
+        term
+    }).collect();
+    let tf_idf: f64 = terms.iter().map(|t| t["contribution"].as_f64().unwrap_or_default()).sum();
+    let boosts: Vec<Value> = boosts.iter().map(|b| json!({ "kind": b.kind, "factor": b.factor, "reason": b.reason })).collect();
+    json!({
+        "score": round4(score),
+        "tfIdf": round4(tf_idf),
+        "terms": terms,
+        "boosts": boosts,
+    })
+}
+
+/// Text form of [`explain_json`]: one indented line per token, then one per boost.
+/// `total_files` is the IDF numerator, the files in the index.
+pub(crate) fn explain_lines(terms: &[TermScore], boosts: &[Boost], total_files: usize) -> Vec<String> {
+    let mut terms: Vec<&TermScore> = terms.iter().collect();
+    terms.sort_by(|a, b| b.contribution().total_cmp(&a.contribution()).then_with(|| a.token.cmp(&b.token)));
+    let mut lines: Vec<String> = terms.into_iter().map(|t| {
+        let name = if t.token != t.term { format!("{} ({})", t.token, t.term) } else { t.token.clone() };
+        format!("    {:.4}  {}: tf {} / {} = {:.4} x idf ln({} / {}) = {:.4}",
+            t.contribution(), name, t.occurrences, t.file_tokens, t.tf, total_files, t.doc_freq, t.idf)
+    }).collect();
+    lines.extend(boosts.iter().map(|b| format!("    x{}  {}: {}", b.factor, b.kind, b.reason)));
+    lines
+}
+
+/// Leave the index's stop tokens (see [`crate::ContentIndex::is_stop_token`]) out of a
+/// multi-term OR query of whole tokens, with a warning for each. In AND mode they are
+/// kept: they still narrow the result, and their low IDF already ranks them last.
+/// Substring terms are never passed here, since `public` also stands for `publicapi`.
+/// A query of nothing but stop tokens is searched as given.
+pub(crate) fn skip_stop_terms(index: &crate::ContentIndex, terms: &mut Vec<String>, mode_and: bool) -> Vec<String> {
+    if mode_and || terms.iter().all(|t| index.is_stop_token(t)) {
+        return Vec::new();
+    }
+    let mut warnings = Vec::new();
+    terms.retain(|term| {
+        if !index.is_stop_token(term) {
+            return true;
+        }
+        let files = index.index.get(term.as_str()).map_or(0, |postings| postings.len());
+        warnings.push(format!(
+            "'{}' is in {}% of files, above the index's stop-token cutoff of {}%: left out of the query. Search it alone to match it",
+            term, files * 100 / index.files.len(), index.stop_token_pct));
+        false
+    });
+    warnings
+}
+
+// ─── Line checks: case and word boundaries ──────────────────────────
+
+/// Lines of indexed files, read on first use, for checks the lowercased index
+/// cannot answer.
+struct FileLines<'a> {
+    index: &'a crate::ContentIndex,
+    files: HashMap<u32, Option<Vec<String>>>,
+}
+
+impl<'a> FileLines<'a> {
+    fn new(index: &'a crate::ContentIndex) -> Self {
+        FileLines { index, files: HashMap::new() }
+    }
+
+    /// The `lines` of file `file_id` for which `keep` holds.
+    fn retain(&mut self, file_id: u32, lines: &[u32], mut keep: impl FnMut(&str) -> bool) -> Vec<u32> {
+        let index = self.index;
+        let content = self.files.entry(file_id).or_insert_with(|| {
+            let path = index.files.get(file_id as usize)?;
+            crate::read_indexed_file(index, path).map(|c| c.lines().map(str::to_string).collect())
+        });
+        let Some(content) = content else { return Vec::new() };
+        lines.iter().copied()
+            .filter(|&n| (n as usize).checked_sub(1).and_then(|i| content.get(i)).is_some_and(|line| keep(line)))
+            .collect()
+    }
+}
+
+/// Identifiers of a line, split as the tokenizer splits.
+fn identifiers(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|t| !t.is_empty())
+}
+
+/// Case-sensitive check of the lines the index matched. The index holds lowercased
+/// tokens, so it finds `Map`, `map` and `MAP` alike; this keeps the lines that hold
+/// a query term as it was typed. Each file is read once, on its first candidate.
+pub(crate) struct CaseFilter<'a> {
+    lines: FileLines<'a>,
+    /// Query terms as typed, by lowercased term
+    variants: HashMap<String, Vec<String>>,
+}
+
+impl<'a> CaseFilter<'a> {
+    /// Filter for the comma-separated query `terms_str`.
+    pub(crate) fn new(index: &'a crate::ContentIndex, terms_str: &str) -> Self {
+        let mut variants: HashMap<String, Vec<String>> = HashMap::new();
+        for term in terms_str.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let typed = variants.entry(term.to_lowercase()).or_default();
+            if !typed.iter().any(|t| t == term) {
+                typed.push(term.to_string());
+            }
+        }
+        CaseFilter { lines: FileLines::new(index), variants }
+    }
+
+    /// The `lines` of file `file_id` that hold one of `terms` (lowercased query terms)
+    /// as typed: as a whole token, or anywhere in the line with `substring`.
+    pub(crate) fn retain(&mut self, file_id: u32, lines: &[u32], terms: &[&str], substring: bool) -> Vec<u32> {
+        let typed: Vec<&str> = terms.iter()
+            .filter_map(|t| self.variants.get(*t))
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        self.lines.retain(file_id, lines, |line| typed.iter().any(|t| if substring {
+            line.contains(t)
+        } else {
+            identifiers(line).any(|token| token == *t)
+        }))
+    }
+}
+
+/// `wordBoundary` check of substring matches: the term must start and end at
+/// identifier-part boundaries, so `cat` matches `cat_name` and `CatName` but not
+/// `concatenate`. Start and end of the token, `_` and letter/digit changes are
+/// visible in the lowercased token itself; camelCase humps are only visible in the
+/// source, so those lines are read.
+pub(crate) struct BoundaryFilter<'a> {
+    lines: FileLines<'a>,
+}
+
+impl<'a> BoundaryFilter<'a> {
+    pub(crate) fn new(index: &'a crate::ContentIndex) -> Self {
+        BoundaryFilter { lines: FileLines::new(index) }
+    }
+
+    /// The `lines` of file `file_id` where index token `token` holds one of `terms`
+    /// (lowercased substrings) at part boundaries.
+    pub(crate) fn retain(&mut self, file_id: u32, lines: &[u32], token: &str, terms: &[&str]) -> Vec<u32> {
+        if terms.iter().any(|term| at_part_boundaries(token, term)) {
+            return lines.to_vec();
+        }
+        self.lines.retain(file_id, lines, |line| {
+            identifiers(line)
+                .filter(|ident| ident.to_lowercase() == token)
+                .any(|ident| terms.iter().any(|term| at_part_boundaries(ident, term)))
+        })
+    }
+}
+
+/// Whether `term` (lowercase) occurs in `ident` from one identifier-part boundary to
+/// another. Case only marks boundaries when `ident` has its source case.
+pub(crate) fn at_part_boundaries(ident: &str, term: &str) -> bool {
+    let chars: Vec<char> = ident.chars().collect();
+    let lower: Vec<char> = ident.chars().flat_map(char::to_lowercase).collect();
+    let term: Vec<char> = term.chars().collect();
+    // Lowercasing that changes the length would shift the positions
+    if term.is_empty() || lower.len() != chars.len() || term.len() > chars.len() {
+        return false;
+    }
+    (0..=chars.len() - term.len()).any(|start| {
+        lower[start..start + term.len()] == term[..]
+            && is_part_boundary(&chars, start)
+            && is_part_boundary(&chars, start + term.len())
+    })
+}
+
+/// Whether an identifier part starts at `at` in `chars`: at either end, next to `_`,
+/// between letters and digits, at a lower-to-upper hump (`user|Service`) or before
+/// the last capital of an acronym followed by lowercase (`HTTP|Server`).
+fn is_part_boundary(chars: &[char], at: usize) -> bool {
+    if at == 0 || at == chars.len() {
+        return true;
+    }
+    let (prev, next) = (chars[at - 1], chars[at]);
+    prev == '_' || next == '_'
+        || prev.is_ascii_digit() != next.is_ascii_digit()
+        || (prev.is_lowercase() && next.is_uppercase())
+        || (prev.is_uppercase() && next.is_uppercase() && chars.get(at + 1).is_some_and(|c| c.is_lowercase()))
+}
+
+// ─── Set operations ─────────────────────────────────────────────────
+
+pub(crate) use search::query::sorted_intersect;
+
 // ─── Line content helpers ───────────────────────────────────────────
 
+/// Where the matches of a grep fall inside a line, for `matchColumns` and highlighting.
+pub(crate) enum MatchLocator {
+    /// Whole tokens in the set (lowercased), as exact and regex grep match them
+    Tokens(HashSet<String>),
+    /// Every non-empty match of a case-insensitive regex: substring terms and phrases
+    Regex(regex::Regex),
+}
+
+impl MatchLocator {
+    /// Locator for case-insensitive occurrences of any of `terms`.
+    pub(crate) fn substrings(terms: &[String]) -> Option<Self> {
+        let alternation = terms.iter().filter(|t| !t.is_empty())
+            .map(|t| regex::escape(t)).collect::<Vec<_>>().join("|");
+        if alternation.is_empty() {
+            return None;
+        }
+        regex::Regex::new(&format!("(?i){}", alternation)).ok().map(MatchLocator::Regex)
+    }
+
+    /// `(start, end)` byte offsets of the matches in `line`, 0-based and end-exclusive.
+    pub(crate) fn columns(&self, line: &str) -> Vec<(usize, usize)> {
+        match self {
+            MatchLocator::Tokens(tokens) => {
+                let is_word = |c: char| c.is_alphanumeric() || c == '_';
+                let mut found = Vec::new();
+                let mut start = None;
+                for (i, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+                    match (start, is_word(c)) {
+                        (None, true) => start = Some(i),
+                        (Some(s), false) => {
+                            if tokens.contains(&line[s..i].to_lowercase()) {
+                                found.push((s, i));
+                            }
+                            start = None;
+                        }
+                        _ => {}
+                    }
+                }
+                found
+            }
+            MatchLocator::Regex(re) => re.find_iter(line)
+                .filter(|m| !m.is_empty())
+                .map(|m| (m.start(), m.end()))
+                .collect(),
+        }
+    }
+}
+
+/// `line` with every column range wrapped in `<mark>`…`</mark>`.
+pub(crate) fn highlight_columns(line: &str, columns: &[(usize, usize)]) -> String {
+    let mut out = String::with_capacity(line.len() + columns.len() * 13);
+    let mut copied = 0;
+    for &(start, end) in columns {
+        out.push_str(&line[copied..start]);
+        out.push_str("<mark>");
+        out.push_str(&line[start..end]);
+        out.push_str("</mark>");
+        copied = end;
+    }
+    out.push_str(&line[copied..]);
+    out
+}
+
+/// The match at `start..end` in `line` with up to `context_chars` characters on each side.
+pub(crate) fn match_fragment(line: &str, start: usize, end: usize, context_chars: usize) -> &str {
+    if context_chars == 0 {
+        return &line[start..end];
+    }
+    let from = line[..start].char_indices().rev().nth(context_chars - 1).map_or(0, |(i, _)| i);
+    let to = line[end..].char_indices().nth(context_chars).map_or(line.len(), |(i, _)| end + i);
+    &line[from..to]
+}
+
 /// Build compact grouped lineContent for search_grep from raw file content.
 /// Computes context windows around match lines, then groups consecutive lines
 /// into `[{startLine, lines[], matchIndices[]}]`.
+///
+/// With a `locator`, each group also gets `matchColumns`: per entry of `matchIndices`,
+/// the `[start, end]` byte offsets of the matches in that line. `highlight` wraps the
+/// matches in `<mark>` tags; the columns still refer to the unmarked line.
 pub(crate) fn build_line_content_from_matches(
     content: &str,
     match_lines: &[u32],
     context_lines: usize,
+    locator: Option<&MatchLocator>,
+    highlight: bool,
 ) -> Value {
     let lines_vec: Vec<&str> = content.lines().collect();
     let total_lines = lines_vec.len();
@@ -147,7 +796,23 @@ pub(crate) fn build_line_content_from_matches(
         }
     }
 
-    build_grouped_line_content(&lines_to_show, &lines_vec, &match_lines_set)
+    let mut groups = build_grouped_line_content(&lines_to_show, &lines_vec, &match_lines_set);
+    let Some(locator) = locator else { return groups };
+    for group in groups.as_array_mut().into_iter().flatten() {
+        let Some(indices) = group.get("matchIndices").and_then(|v| v.as_array()).cloned() else { continue };
+        let start = group["startLine"].as_u64().unwrap_or(1) as usize - 1;
+        let mut columns = Vec::with_capacity(indices.len());
+        for i in indices.iter().filter_map(|v| v.as_u64()).map(|i| i as usize) {
+            let line = lines_vec[start + i];
+            let found = locator.columns(line);
+            if highlight {
+                group["lines"][i] = json!(highlight_columns(line, &found));
+            }
+            columns.push(found.into_iter().map(|(s, e)| [s, e]).collect::<Vec<_>>());
+        }
+        group["matchColumns"] = json!(columns);
+    }
+    groups
 }
 
 /// Groups consecutive lines into compact chunks: `[{startLine, lines[], matchIndices[]}]`.
@@ -203,6 +868,193 @@ pub(crate) fn build_grouped_line_content(
     json!(groups)
 }
 
+// ─── Concurrent file reads ──────────────────────────────────────────
+
+/// Concurrent reads of local files. SSDs serve many requests at once, so snippets
+/// for a page of results cost about one read instead of fifty.
+const LOCAL_READ_CONCURRENCY: usize = 16;
+
+/// Concurrent reads of files on network shares, kept low so one query does not
+/// flood the file server.
+const NETWORK_READ_CONCURRENCY: usize = 4;
+
+/// Whether `path` (as stored in the index) is on a network share: a UNC path,
+/// `//server/share/...` after [`clean_path`].
+pub(crate) fn is_network_path(path: &str) -> bool {
+    path.starts_with("//")
+}
+
+/// Read `paths` with [`read_indexed_file`](crate::read_indexed_file), several at a time,
+/// and return the contents in the order of `paths`. Local and network files have
+/// separate concurrency limits, so a slow share does not hold up local reads.
+pub(crate) fn read_indexed_files(index: &crate::ContentIndex, paths: &[&str]) -> Vec<Option<String>> {
+    map_indexed_files(index, paths, "reading matched files", |_, content| content)
+}
+
+/// Read `paths` as [`read_indexed_files`] does and run `f` on each path and content in
+/// the reading thread. Returns the results in the order of `paths`. Workers report
+/// their reads as the progress phase `message` (see [`crate::mcp::progress`]).
+pub(crate) fn map_indexed_files<T: Send>(
+    index: &crate::ContentIndex,
+    paths: &[&str],
+    message: &str,
+    f: impl Fn(&str, Option<String>) -> T + Sync,
+) -> Vec<T> {
+    if paths.len() <= 1 {
+        return paths.iter().map(|p| f(p, crate::read_indexed_file(index, p))).collect();
+    }
+    let (network, local): (Vec<usize>, Vec<usize>) = (0..paths.len()).partition(|&i| is_network_path(paths[i]));
+    let cursors = [AtomicUsize::new(0), AtomicUsize::new(0)];
+    let done = AtomicUsize::new(0);
+    let reporter = progress::handle();
+    progress::report(0, Some(paths.len()), message);
+    let mut read: Vec<(usize, T)> = std::thread::scope(|s| {
+        let mut workers = Vec::new();
+        for ((queue, limit), next) in [(&local, LOCAL_READ_CONCURRENCY), (&network, NETWORK_READ_CONCURRENCY)].into_iter().zip(&cursors) {
+            for _ in 0..limit.min(queue.len()) {
+                let (f, done, reporter) = (&f, &done, reporter.clone());
+                workers.push(s.spawn(move || {
+                    let work = || {
+                        let mut read = Vec::new();
+                        loop {
+                            let slot = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(&i) = queue.get(slot) else { break };
+                            read.push((i, f(paths[i], crate::read_indexed_file(index, paths[i]))));
+                            progress::report(done.fetch_add(1, Ordering::Relaxed) + 1, Some(paths.len()), message);
+                        }
+                        read
+                    };
+                    match reporter {
+                        Some(reporter) => reporter.enter(work),
+                        None => work(),
+                    }
+                }));
+            }
+        }
+        workers.into_iter().flat_map(|w| w.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect()
+    });
+    read.sort_unstable_by_key(|(i, _)| *i);
+    read.into_iter().map(|(_, value)| value).collect()
+}
+
+// ─── Cursor pagination ──────────────────────────────────────────────
+
+/// One page of a ranked result list, from the `cursor`/`pageSize` parameters.
+///
+/// A cursor is opaque to clients: `<generation>-<offset>-<size>-<query hash>` in hex.
+/// It is only valid against the index generation it was issued for, so the next page
+/// continues the same ordering; after a reindex or watcher update the search has to be
+/// repeated from the start. The query hash covers every argument except `cursor`,
+/// `pageSize` and `maxResults`, so a cursor cannot be replayed against another query.
+pub(crate) struct Page {
+    pub offset: usize,
+    pub size: usize,
+    generation: u64,
+    query: u64,
+}
+
+impl Page {
+    /// The page requested by `args` of `tool`, or `None` when the request has neither
+    /// `cursor` nor `pageSize`. Without `pageSize`, a cursor keeps the size of its page.
+    pub(crate) fn from_args(ctx: &HandlerContext, tool: &str, args: &Value) -> Result<Option<Page>, String> {
+        let cursor = args.get("cursor").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+        let page_size = args.get("pageSize").and_then(|v| v.as_u64());
+        if cursor.is_none() && page_size.is_none() {
+            return Ok(None);
+        }
+        if page_size == Some(0) {
+            return Err("pageSize must be at least 1".to_string());
+        }
+        let generation = ctx.generation.get();
+        let query = query_hash(tool, args);
+        let (offset, size) = match cursor {
+            None => (0, 0),
+            Some(cursor) => {
+                let fields: Vec<u64> = cursor.split('-').filter_map(|f| u64::from_str_radix(f, 16).ok()).collect();
+                let [cursor_generation, offset, size, cursor_query] = fields[..] else {
+                    return Err(format!("Invalid cursor '{}'", cursor));
+                };
+                if cursor_query != query {
+                    return Err("cursor belongs to a different query; pass the same parameters as the first page".to_string());
+                }
+                if cursor_generation != generation {
+                    return Err("cursor expired: the index changed since the first page. Repeat the search without cursor".to_string());
+                }
+                (offset as usize, size as usize)
+            }
+        };
+        let size = page_size.map_or(size, |n| n as usize);
+        Ok(Some(Page { offset, size, generation, query }))
+    }
+
+    /// Keep this page of the ranked `items`. The cursor past the page is kept even on the
+    /// last page, so a truncated response can rewind it (see [`truncate_large_response`]).
+    pub(crate) fn apply<T>(&self, items: &mut Vec<T>) -> PageInfo {
+        let total = items.len();
+        items.drain(..self.offset.min(total));
+        items.truncate(self.size);
+        let end = self.offset + items.len();
+        PageInfo { next_cursor: self.cursor_at(end), has_more: end < total, offset: self.offset }
+    }
+
+    fn cursor_at(&self, offset: usize) -> String {
+        format!("{:x}-{:x}-{:x}-{:016x}", self.generation, offset, self.size, self.query)
+    }
+}
+
+/// Where a page ended, for the response summary.
+pub(crate) struct PageInfo {
+    next_cursor: String,
+    has_more: bool,
+    offset: usize,
+}
+
+impl PageInfo {
+    /// Write `nextCursor`, `hasMore` and `pageOffset` to `summary`. The last page has no
+    /// `nextCursor`; its cursor goes to [`PAGE_END_CURSOR`] until the response is sent.
+    pub(crate) fn inject(&self, summary: &mut Value) {
+        let key = if self.has_more { "nextCursor" } else { PAGE_END_CURSOR };
+        summary[key] = json!(self.next_cursor);
+        summary["hasMore"] = json!(self.has_more);
+        summary["pageOffset"] = json!(self.offset);
+    }
+}
+
+fn query_hash(tool: &str, args: &Value) -> u64 {
+    let mut query = args.clone();
+    if let Some(obj) = query.as_object_mut() {
+        for key in ["cursor", "pageSize", "maxResults"] {
+            obj.remove(key);
+        }
+    }
+    search::stable_hash(&[tool.as_bytes(), query.to_string().as_bytes()])
+}
+
+/// Summary key holding the cursor past the last page. It is not part of the response:
+/// [`remove_page_end_cursor`] drops it once truncation had its chance to use it.
+const PAGE_END_CURSOR: &str = "_pageEndCursor";
+
+/// Move the `nextCursor` in `summary` back by `removed` entries that truncation dropped
+/// from the page, so the next page starts with them. A last page gets a `nextCursor`
+/// again, from its [`PAGE_END_CURSOR`].
+fn rewind_cursor(summary: &mut Value, removed: usize) {
+    let Some(cursor) = summary.get("nextCursor").or_else(|| summary.get(PAGE_END_CURSOR)).and_then(|v| v.as_str()) else { return };
+    let mut fields: Vec<&str> = cursor.split('-').collect();
+    let Some(offset) = fields.get(1).and_then(|f| usize::from_str_radix(f, 16).ok()) else { return };
+    let rewound = format!("{:x}", offset.saturating_sub(removed));
+    fields[1] = &rewound;
+    summary["nextCursor"] = json!(fields.join("-"));
+    summary["hasMore"] = json!(true);
+    remove_page_end_cursor(summary);
+}
+
+/// Drop the [`PAGE_END_CURSOR`] from a response summary.
+fn remove_page_end_cursor(summary: &mut Value) {
+    if let Some(obj) = summary.as_object_mut() {
+        obj.remove(PAGE_END_CURSOR);
+    }
+}
+
 // ─── Response size truncation ───────────────────────────────────────
 
 /// Default maximum response size in bytes before truncation kicks in.
@@ -321,6 +1173,9 @@ pub(crate) fn truncate_large_response(mut output: Value, max_bytes: usize) -> Va
             let removed = original_count - files.len();
             if removed > 0 {
                 reasons.push(format!("reduced files from {} to {}", original_count, files.len()));
+                if let Some(summary) = output.get_mut("summary") {
+                    rewind_cursor(summary, removed);
+                }
             }
         }
     }
@@ -367,6 +1222,7 @@ pub(crate) fn truncate_large_response(mut output: Value, max_bytes: usize) -> Va
                             // Update 'returned' in summary to reflect actual array length
                             if let Some(summary) = obj.get_mut("summary") {
                                 summary["returned"] = json!(actual_kept);
+                                rewind_cursor(summary, removed);
                             }
                         }
                     }
@@ -385,6 +1241,7 @@ fn inject_truncation_metadata(output: &mut Value, reasons: &[String], original_b
     if reasons.is_empty() {
         return;
     }
+    search::metrics::record_truncation();
 
     // Detect response type to provide a relevant hint
     let has_files = output.get("files").is_some();
@@ -408,21 +1265,20 @@ fn inject_truncation_metadata(output: &mut Value, reasons: &[String], original_b
 /// Apply response size truncation to a ToolCallResult (no metrics injection).
 /// Used when metrics are disabled but we still need to cap response size.
 pub(crate) fn truncate_response_if_needed(result: ToolCallResult, max_bytes: usize) -> ToolCallResult {
-    if max_bytes == 0 {
-        return result;
-    }
-
     let text = match result.content.first() {
         Some(c) => &c.text,
         None => return result,
     };
 
-    if text.len() <= max_bytes {
+    if (max_bytes == 0 || text.len() <= max_bytes) && !text.contains(PAGE_END_CURSOR) {
         return result;
     }
 
     if let Ok(output) = serde_json::from_str::<Value>(text) {
-        let truncated = truncate_large_response(output, max_bytes);
+        let mut truncated = truncate_large_response(output, max_bytes);
+        if let Some(summary) = truncated.get_mut("summary") {
+            remove_page_end_cursor(summary);
+        }
         ToolCallResult::success(serde_json::to_string(&truncated).unwrap())
     } else {
         result
@@ -449,15 +1305,17 @@ pub(crate) fn inject_metrics(result: ToolCallResult, ctx: &HandlerContext, start
         if let Some(summary) = output.get_mut("summary") {
             summary["searchTimeMs"] = json!((elapsed_ms * 100.0).round() / 100.0);
 
-            if let Ok(idx) = ctx.index.read() {
-                summary["indexFiles"] = json!(idx.files.len());
-                summary["indexTokens"] = json!(idx.index.len());
-            }
+            let idx = ctx.index.snapshot();
+            summary["indexFiles"] = json!(idx.files.len());
+            summary["indexTokens"] = json!(idx.index.len());
         }
 
         // Apply response size truncation BEFORE measuring final bytes
         let max_bytes = if ctx.max_response_bytes > 0 { ctx.max_response_bytes } else { 0 };
         output = truncate_large_response(output, max_bytes);
+        if let Some(summary) = output.get_mut("summary") {
+            remove_page_end_cursor(summary);
+        }
 
         // Measure response size after truncation
         let json_str = serde_json::to_string(&output).unwrap();
@@ -587,6 +1445,7 @@ pub(crate) fn best_match_tier(name: &str, terms: &[String]) -> u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{SkippedFiles, TokenizerSettings};
 
     #[test]
     fn test_sorted_intersect_empty_left() {
@@ -726,7 +1585,7 @@ mod tests {
     fn test_context_lines_calculation() {
         let content = (0..20).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
         let match_lines = vec![10u32]; // line 10 (1-based)
-        let result = build_line_content_from_matches(&content, &match_lines, 2);
+        let result = build_line_content_from_matches(&content, &match_lines, 2, None, false);
         let groups = result.as_array().unwrap();
         assert_eq!(groups.len(), 1);
         // Should show lines 8-12 (5 lines: 2 before + match + 2 after)
@@ -738,18 +1597,69 @@ mod tests {
     fn test_context_lines_at_file_boundaries() {
         let content = "line1\nline2\nline3";
         let match_lines = vec![1u32];
-        let result = build_line_content_from_matches(&content, &match_lines, 5);
+        let result = build_line_content_from_matches(&content, &match_lines, 5, None, false);
         let groups = result.as_array().unwrap();
         assert_eq!(groups.len(), 1);
         let lines = groups[0]["lines"].as_array().unwrap();
         assert_eq!(lines.len(), 3); // can't go before line 1
     }
 
+    #[test]
+    fn test_line_content_match_columns_and_highlight() {
+        let content = "int count = Count(counter);\nnone here\nCOUNT";
+        let tokens = MatchLocator::Tokens(HashSet::from(["count".to_string()]));
+        let result = build_line_content_from_matches(content, &[1, 3], 1, Some(&tokens), false);
+        assert_eq!(result[0]["matchIndices"], json!([0, 2]));
+        assert_eq!(result[0]["matchColumns"], json!([[[4, 9], [12, 17]], [[0, 5]]]));
+        assert_eq!(result[0]["lines"][0], "int count = Count(counter);");
+
+        let substring = MatchLocator::substrings(&["count".to_string()]).unwrap();
+        let result = build_line_content_from_matches(content, &[1], 0, Some(&substring), true);
+        assert_eq!(result[0]["matchColumns"], json!([[[4, 9], [12, 17], [18, 23]]]));
+        assert_eq!(result[0]["lines"][0], "int <mark>count</mark> = <mark>Count</mark>(<mark>count</mark>er);");
+    }
+
+    #[test]
+    fn test_match_fragment_keeps_context_chars_on_each_side() {
+        let line = "let café = order.total();";
+        let start = line.find("order").unwrap();
+        let end = start + "order".len();
+        assert_eq!(match_fragment(line, start, end, 0), "order");
+        assert_eq!(match_fragment(line, start, end, 4), "é = order.tot");
+        assert_eq!(match_fragment(line, start, end, 100), line);
+    }
+
+    #[test]
+    fn test_read_indexed_files_keeps_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for i in 0..40 {
+            let path = tmp.path().join(format!("f{}.cs", i));
+            std::fs::write(&path, format!("file {}", i)).unwrap();
+            paths.push(clean_path(&path.to_string_lossy()));
+        }
+        // `//tmp/...` is a network path to the classifier and the same file to Unix
+        let network = if cfg!(unix) { format!("/{}", paths[7]) } else { paths[7].clone() };
+        paths.insert(5, network);
+        paths.push(clean_path(&tmp.path().join("missing.cs").to_string_lossy()));
+        let ctx = make_ctx_with_branch(None);
+        let index = ctx.index.snapshot();
+        let refs: Vec<&str> = paths.iter().map(String::as_str).collect();
+        let contents = read_indexed_files(&index, &refs);
+        assert_eq!(contents.len(), 42);
+        assert_eq!(contents[0].as_deref(), Some("file 0"));
+        assert_eq!(contents[5].as_deref(), Some("file 7"));
+        assert_eq!(contents[6].as_deref(), Some("file 5"));
+        assert_eq!(contents[40].as_deref(), Some("file 39"));
+        assert_eq!(contents[41], None);
+        assert!(is_network_path("//server/share/a.cs") && !is_network_path("C:/repo/a.cs"));
+    }
+
     #[test]
     fn test_context_merges_overlapping_ranges() {
         let content = (0..20).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
         let match_lines = vec![5u32, 7u32]; // lines 5 and 7 with context 2 overlap
-        let result = build_line_content_from_matches(&content, &match_lines, 2);
+        let result = build_line_content_from_matches(&content, &match_lines, 2, None, false);
         let groups = result.as_array().unwrap();
         assert_eq!(groups.len(), 1); // should merge into single group
     }
@@ -899,6 +1809,23 @@ mod tests {
         assert!(summary.get("hint").is_some());
     }
 
+    #[test]
+    fn test_truncate_rewinds_page_cursor() {
+        let files: Vec<Value> = (0..1000)
+            .map(|i| json!({ "path": format!("/some/long/path/to/deeply/nested/file_number_{}.cs", i) }))
+            .collect();
+        let output = json!({
+            "files": files,
+            "summary": { "_pageEndCursor": "7-bb8-3e8-00000000000000ff", "hasMore": false }
+        });
+        let result = truncate_large_response(output, DEFAULT_MAX_RESPONSE_BYTES);
+        let kept = result["files"].as_array().unwrap().len();
+        // The last page ended at 3000 (0xbb8); the next one starts at the first dropped file
+        assert_eq!(result["summary"]["nextCursor"], format!("7-{:x}-3e8-00000000000000ff", 2000 + kept));
+        assert_eq!(result["summary"]["hasMore"], true);
+        assert!(result["summary"].get("_pageEndCursor").is_none());
+    }
+
     #[test]
     fn test_truncate_response_if_needed_small() {
         let small = ToolCallResult::success(r#"{"files":[],"summary":{"totalFiles":0}}"#.to_string());
@@ -1119,9 +2046,18 @@ mod tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
         HandlerContext {
-            index: std::sync::Arc::new(std::sync::RwLock::new(index)),
+            index: crate::Searcher::new(index),
             def_index: None,
             server_dir: ".".to_string(),
             server_ext: "cs".to_string(),
@@ -1133,6 +2069,15 @@ mod tests {
             git_cache: std::sync::Arc::new(std::sync::RwLock::new(None)),
             git_cache_ready: std::sync::Arc::new(AtomicBool::new(false)),
             current_branch: branch.map(|s| s.to_string()),
+            commit_index: std::sync::Arc::new(std::sync::Mutex::new(None)),
+            semantic_index: std::sync::Arc::new(std::sync::Mutex::new(None)),
+            owners: None,
+            generation: Default::default(),
+            index_subscribed: std::sync::Arc::new(AtomicBool::new(false)),
+            reindex_jobs: Default::default(),
+            query_history: Default::default(),
+            ranking: Default::default(),
+            rank_hook: None,
         }
     }
 
@@ -1189,4 +2134,72 @@ mod tests {
         inject_branch_warning(&mut summary, &ctx);
         assert!(summary.get("branchWarning").is_none());
     }
+
+    #[test]
+    fn test_ranking_classifies_test_and_generated_paths() {
+        for path in ["tests/A.cs", "src/App.Tests/A.cs", "src/UserServiceTests.cs", "pkg/user_test.go",
+            "test_user.py", "web/user.spec.ts", "web/__tests__/user.ts"] {
+            assert!(is_test_path(path), "{}", path);
+        }
+        for path in ["src/Latest.cs", "src/Contest/Rules.cs", "src/Attestation.cs", "docs/testing.md"] {
+            assert!(!is_test_path(path), "{}", path);
+        }
+        assert!(is_generated_path("src/obj/Debug/App.AssemblyInfo.cs"));
+        assert!(is_generated_path("src/Forms/Main.Designer.cs"));
+        assert!(is_generated_path("api/user.pb.go"));
+        assert!(!is_generated_path("src/Objects/Binder.cs"));
+
+        let ranking = Ranking::new(RankingBoosts::default(), &["UserService".to_string()], false);
+        let definition = DefinitionBoost { symbol: "UserService".to_string(), factor: 3.0 };
+        let kinds: Vec<&str> = ranking.boosts("src\\UserService.cs", Some(&definition)).iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, vec!["filename", "definition"]);
+        let boosts = ranking.boosts("tests/UserServiceTests.cs", None);
+        assert_eq!(boosted(1.0, &boosts), 2.0 * 0.5);
+        assert!(Ranking::unboosted(false).boosts("tests/UserServiceTests.cs", None).is_empty());
+    }
+
+    #[test]
+    fn test_fuse_ranks_lifts_semantically_close_items() {
+        // Lexical order a, b, c; a has no similarity, c is the most similar
+        let fused = fuse_ranks(&[None, Some(0.2), Some(0.9)]);
+        assert_eq!(fused.iter().map(|f| f.1).collect::<Vec<_>>(), vec![None, Some(2), Some(1)]);
+        assert_eq!(fused[0].0, 1.0 / 61.0);
+        assert_eq!(fused[1].0, 1.0 / 62.0 + 1.0 / 62.0);
+        assert!(fused[2].0 > fused[1].0 && fused[1].0 > fused[0].0, "semantic ranks reverse the lexical order");
+        assert!(fuse_ranks(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_part_boundaries_follow_humps_underscores_and_digits() {
+        for (ident, term) in [("IUserService", "userservice"), ("IUserServiceFactory", "service"), ("cat_name", "cat"),
+            ("HTTPServer", "server"), ("HTTPServer", "http"), ("log2file", "file"), ("CatName", "cat")] {
+            assert!(at_part_boundaries(ident, term), "{} in {}", term, ident);
+        }
+        for (ident, term) in [("concatenate", "cat"), ("Catalog", "cat"), ("UserService", "serv"), ("HTTPServer", "tps")] {
+            assert!(!at_part_boundaries(ident, term), "{} in {}", term, ident);
+        }
+        // The lowercased token shows no humps
+        assert!(!at_part_boundaries("iuserservicefactory", "service"));
+        assert!(at_part_boundaries("user_service", "service"));
+    }
+
+    #[test]
+    fn test_group_by_dir_counts_per_directory_in_result_order() {
+        let results = [
+            ("C:/repo/src/a/x.rs", 2), ("C:/repo/src/b/y.rs", 5), ("C:/repo/src/a/z.rs", 4),
+            ("C:/repo/README.md", 1), ("C:\\repo\\src\\a\\w.rs", 1), ("C:/repo/src/a/v.rs", 1),
+        ];
+        let groups = group_by_dir("C:/repo", results, 0);
+        let dirs: Vec<&str> = groups.iter().map(|g| g["dir"].as_str().unwrap()).collect();
+        assert_eq!(dirs, ["src/a", "src/b", "."]);
+        assert_eq!(groups[0]["files"], 4);
+        assert_eq!(groups[0]["occurrences"], 8);
+        let top: Vec<&str> = groups[0]["topFiles"].as_array().unwrap().iter().map(|f| f["path"].as_str().unwrap()).collect();
+        assert_eq!(top, ["C:/repo/src/a/x.rs", "C:/repo/src/a/z.rs", "C:\\repo\\src\\a\\w.rs"]);
+
+        let groups = group_by_dir("C:/repo", results, 1);
+        assert_eq!(groups[0]["dir"], "src");
+        assert_eq!(groups[0]["occurrences"], 13);
+        assert_eq!(groups[1]["dir"], ".");
+    }
 }
\ No newline at end of file
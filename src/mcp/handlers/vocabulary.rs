@@ -0,0 +1,77 @@
+//! search_vocabulary handler: the most widespread identifiers in the content index.
+
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::mcp::protocol::ToolCallResult;
+use crate::SearchError;
+use search::lock_stats::{self, Lock};
+
+use super::utils::inject_branch_warning;
+use super::HandlerContext;
+
+const DEFAULT_LIMIT: usize = 500;
+const MAX_LIMIT: usize = 10_000;
+
+pub(crate) fn handle_search_vocabulary(ctx: &HandlerContext, args: &Value) -> ToolCallResult {
+    let limit = match args.get("limit").and_then(|v| v.as_u64()) {
+        Some(0) => return ToolCallResult::error("limit must be >= 1".to_string()),
+        Some(n) => (n as usize).min(MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+    let prefix = args.get("prefix").and_then(|v| v.as_str()).map(|s| s.to_lowercase());
+    let contains = args.get("contains").and_then(|v| v.as_str()).map(|s| s.to_lowercase());
+    let min_length = args.get("minLength").and_then(|v| v.as_u64()).unwrap_or(3) as usize;
+    let defined_only = args.get("definedOnly").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let def_index = if defined_only {
+        match &ctx.def_index {
+            Some(idx) => match lock_stats::timed(Lock::Definitions, || idx.read()) {
+                Ok(idx) => Some(idx),
+                Err(e) => return SearchError::LockPoisoned(format!("definition index: {}", e)).into(),
+            },
+            None => return ToolCallResult::error(
+                "definedOnly requires the definition index. Start server with --definitions flag.".to_string()
+            ),
+        }
+    } else {
+        None
+    };
+    let index = ctx.index.snapshot();
+
+    let start = Instant::now();
+    let mut entries: Vec<(&str, usize)> = index.index.iter()
+        .filter(|(token, _)| {
+            token.len() >= min_length
+                // Identifiers only: numbers and hex-like literals are noise for query grounding
+                && !token.starts_with(|c: char| c.is_ascii_digit())
+                && prefix.as_ref().is_none_or(|p| token.starts_with(p.as_str()))
+                && contains.as_ref().is_none_or(|c| token.contains(c.as_str()))
+                && def_index.as_ref().is_none_or(|d| d.name_index.contains_key(&token[..]))
+        })
+        .map(|(token, postings)| (&**token, postings.len()))
+        .collect();
+    let total_matching = entries.len();
+    entries.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    entries.truncate(limit);
+
+    let mut summary = json!({
+        "returned": entries.len(),
+        "totalMatching": total_matching,
+        "indexTokens": index.index.len(),
+        "indexFiles": index.files.len(),
+        "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    if entries.is_empty() {
+        summary["hint"] = json!("No identifiers match. Drop prefix/contains, lower minLength, or check the server --ext.");
+    }
+    inject_branch_warning(&mut summary, ctx);
+
+    // [term, documentFrequency] pairs keep the payload compact
+    let output = json!({
+        "vocabulary": entries.iter().map(|(t, df)| json!([t, df])).collect::<Vec<_>>(),
+        "summary": summary,
+    });
+    ToolCallResult::success(serde_json::to_string(&output).unwrap())
+}
@@ -0,0 +1,187 @@
+//! Ignore rules for watcher events, matching what the index walk skips.
+//!
+//! The index is built with `ignore::WalkBuilder`, which honors `.gitignore` (inside a
+//! git repository only, including the files of parent directories up to the repo
+//! root), `.ignore`, `.git/info/exclude`, the global git excludes file, and skips
+//! hidden paths, plus the `exclude` globs and `max-file-size-kb` of `.search-index.toml`.
+//! Native file events carry none of that, so the watcher checks each created or
+//! modified path against the same rules. [`IgnoreRules::is_ignore_file`] tells the
+//! watcher when the rules themselves changed and need reloading.
+
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use tracing::warn;
+
+use crate::config::{RepoConfig, CONFIG_FILE_NAME};
+
+/// File names whose contents change which paths are indexed.
+const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".ignore"];
+
+pub struct IgnoreRules {
+    root: PathBuf,
+    /// Matchers in increasing precedence: global excludes, `info/exclude`, then per
+    /// directory from the shallowest, `.gitignore` before `.ignore`.
+    matchers: Vec<Gitignore>,
+    /// `exclude` globs from `.search-index.toml`; applied on top of the ignore files,
+    /// which cannot re-include what they match.
+    excludes: Option<Gitignore>,
+    max_file_size: Option<u64>,
+}
+
+impl IgnoreRules {
+    /// Read every ignore file that applies to `root`. Walks the tree once, skipping
+    /// directories that are already ignored, like the index walk does.
+    pub fn load(root: &Path) -> Self {
+        let git_root = root.ancestors().find(|a| a.join(".git").exists()).map(Path::to_path_buf);
+        let mut files: Vec<PathBuf> = Vec::new();
+        if let Some(ref git_root) = git_root {
+            // Parent directories between the repo root and `root`, shallowest first
+            let mut parents: Vec<&Path> = root.ancestors().skip(1).take_while(|a| a.starts_with(git_root)).collect();
+            parents.reverse();
+            files.extend(parents.iter().flat_map(|dir| ignore_files_in(dir)));
+        }
+        let mut walker = WalkBuilder::new(root);
+        walker.hidden(false).filter_entry(|e| e.file_name() != ".git");
+        let mut nested: Vec<PathBuf> = walker.build().flatten()
+            .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+            .map(|e| e.into_path())
+            .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| IGNORE_FILE_NAMES.contains(&n)))
+            .filter(|p| git_root.is_some() || p.file_name().is_some_and(|n| n != ".gitignore"))
+            .collect();
+        // Shallowest directory first; within a directory `.gitignore` sorts before `.ignore`
+        nested.sort_by_key(|p| (p.components().count(), p.clone()));
+        files.extend(nested);
+
+        let mut matchers = Vec::new();
+        if let Some(ref git_root) = git_root {
+            let (global, err) = GitignoreBuilder::new(git_root).build_global();
+            if let Some(e) = err {
+                warn!(error = %e, "Failed to read global git excludes file");
+            }
+            matchers.push(global);
+            let exclude = git_root.join(".git").join("info").join("exclude");
+            if exclude.is_file() {
+                matchers.extend(build_matcher(git_root, &exclude));
+            }
+        }
+        for file in &files {
+            if let Some(dir) = file.parent() {
+                matchers.extend(build_matcher(dir, file));
+            }
+        }
+        let config = RepoConfig::load_or_default(root);
+        IgnoreRules {
+            root: root.to_path_buf(),
+            matchers,
+            excludes: config.exclude_matcher(root),
+            max_file_size: config.max_file_size(),
+        }
+    }
+
+    /// Whether the index walk would skip `path`: a hidden component below the root, a
+    /// config exclude or size limit, or an ignore rule that matches the path or one of
+    /// its parents. The most specific ignore rule wins, so a nested `!keep.cs`
+    /// re-includes a file.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else { return false };
+        if rel.components().any(|c| c.as_os_str().to_str().is_some_and(|s| s.starts_with('.'))) {
+            return true;
+        }
+        if self.excludes.as_ref().is_some_and(|ex| ex.matched_path_or_any_parents(rel, is_dir).is_ignore()) {
+            return true;
+        }
+        if !is_dir && let Some(max) = self.max_file_size
+            && std::fs::metadata(path).is_ok_and(|m| m.len() > max) {
+            return true;
+        }
+        let mut ignored = false;
+        for matcher in &self.matchers {
+            let Ok(rel) = path.strip_prefix(matcher.path()) else { continue };
+            let m = matcher.matched_path_or_any_parents(rel, is_dir);
+            if m.is_ignore() {
+                ignored = true;
+            } else if m.is_whitelist() {
+                ignored = false;
+            }
+        }
+        ignored
+    }
+
+    /// Whether a change to `path` can change the rules: an ignore file anywhere in the
+    /// tree, the repository's `.git/info/exclude`, or `.search-index.toml`.
+    pub fn is_ignore_file(path: &Path) -> bool {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        IGNORE_FILE_NAMES.contains(&name) || name == CONFIG_FILE_NAME || path.ends_with(".git/info/exclude")
+    }
+}
+
+/// The ignore files directly in `dir` that exist, in precedence order.
+fn ignore_files_in(dir: &Path) -> Vec<PathBuf> {
+    IGNORE_FILE_NAMES.iter()
+        .map(|name| dir.join(name))
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+fn build_matcher(dir: &Path, file: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(e) = builder.add(file) {
+        warn!(file = %file.display(), error = %e, "Failed to parse ignore file");
+    }
+    match builder.build() {
+        Ok(gi) => Some(gi),
+        Err(e) => {
+            warn!(file = %file.display(), error = %e, "Failed to build ignore matcher");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules_follow_nested_gitignores() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::create_dir_all(root.join(".git/info")).unwrap();
+        std::fs::create_dir_all(root.join("src/gen")).unwrap();
+        std::fs::write(root.join(".gitignore"), "bin/\n*.g.cs\n").unwrap();
+        std::fs::write(root.join("src/gen/.gitignore"), "!keep.g.cs\n").unwrap();
+        std::fs::write(root.join(".git/info/exclude"), "scratch.cs\n").unwrap();
+
+        let rules = IgnoreRules::load(root);
+        assert!(rules.is_ignored(&root.join("bin/Debug/a.cs"), false));
+        assert!(rules.is_ignored(&root.join("src/a.g.cs"), false));
+        assert!(!rules.is_ignored(&root.join("src/gen/keep.g.cs"), false), "nested whitelist wins");
+        assert!(rules.is_ignored(&root.join("src/scratch.cs"), false));
+        assert!(rules.is_ignored(&root.join(".vscode/a.cs"), false), "hidden paths are skipped");
+        assert!(!rules.is_ignored(&root.join("src/a.cs"), false));
+        assert!(!rules.is_ignored(Path::new("/elsewhere/bin/a.cs"), false));
+
+        // .search-index.toml excludes and size limit apply on top, and can't be re-included
+        std::fs::write(root.join(CONFIG_FILE_NAME), "[index]\nexclude = [\"src/gen/\"]\nmax-file-size-kb = 1\n").unwrap();
+        std::fs::write(root.join("src/big.cs"), "x".repeat(2048)).unwrap();
+        std::fs::write(root.join("src/small.cs"), "x").unwrap();
+        let rules = IgnoreRules::load(root);
+        assert!(rules.is_ignored(&root.join("src/gen/keep.g.cs"), false));
+        assert!(rules.is_ignored(&root.join("src/big.cs"), false));
+        assert!(!rules.is_ignored(&root.join("src/small.cs"), false));
+        assert!(IgnoreRules::is_ignore_file(&root.join(CONFIG_FILE_NAME)));
+
+        assert!(IgnoreRules::is_ignore_file(&root.join("src/.gitignore")));
+        assert!(IgnoreRules::is_ignore_file(&root.join(".git/info/exclude")));
+        assert!(!IgnoreRules::is_ignore_file(&root.join("src/a.cs")));
+
+        // Outside a git repository `.gitignore` does not apply, `.ignore` does
+        let plain = tempfile::tempdir().unwrap();
+        std::fs::write(plain.path().join(".gitignore"), "*.cs\n").unwrap();
+        std::fs::write(plain.path().join(".ignore"), "*.ts\n").unwrap();
+        let rules = IgnoreRules::load(plain.path());
+        assert!(!rules.is_ignored(&plain.path().join("a.cs"), false));
+        assert!(rules.is_ignored(&plain.path().join("a.ts"), false));
+    }
+}
@@ -1,4 +1,8 @@
+pub mod audit_log;
 pub mod handlers;
+pub mod ignore_rules;
+pub mod progress;
+pub mod prompts;
 pub mod protocol;
 pub mod server;
 pub mod watcher;
\ No newline at end of file
@@ -0,0 +1,229 @@
+//! Progress notifications and partial results for long `tools/call` requests.
+//!
+//! A client that sends `params._meta.progressToken` with `tools/call` receives
+//! `notifications/progress` while the tool runs, so a regex over every index token or
+//! a deep call tree shows life before the response arrives. Tools report through
+//! [`report`] and [`partial`]; both do nothing unless the server installed a reporter
+//! for the request with [`with_reporter`]. The reporter is a shared [`Handle`]: a tool
+//! that hands work to other threads passes [`handle`] along, and each worker reports
+//! under [`Handle::enter`].
+//!
+//! `progress` counts work units across the phases of a call (tokens matched, files
+//! read, call-tree nodes) and only grows, as MCP requires: each phase starts where the
+//! previous one ended. Results a tool already knows are final are streamed in
+//! `params._meta.partialResults` of the next notification sent. They are a preview:
+//! the response still carries the complete, ranked, deduplicated result.
+
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+/// Minimum gap between notifications of one phase. The first notification of each
+/// phase, and the first carrying partial results, go out at once.
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Reporter {
+    token: Value,
+    send: Box<dyn FnMut(Value) + Send>,
+    /// Message of the current phase, the progress it started from, and its end
+    phase: String,
+    phase_base: f64,
+    total: Option<f64>,
+    /// Progress last reported by the tool, and last sent to the client
+    latest: f64,
+    progress: f64,
+    last_sent: Option<Instant>,
+    /// Partial results waiting for the next notification
+    pending: Vec<Value>,
+    streamed: bool,
+}
+
+impl Reporter {
+    /// Send `latest` (with any pending results) unless the client already has it.
+    fn send(&mut self) {
+        if self.last_sent.is_some() && self.latest <= self.progress {
+            return;
+        }
+        self.progress = self.latest;
+        self.last_sent = Some(Instant::now());
+        let mut params = json!({ "progressToken": self.token, "progress": self.progress, "message": self.phase });
+        if let Some(total) = self.total {
+            params["total"] = json!(total);
+        }
+        if !self.pending.is_empty() {
+            params["_meta"] = json!({ "partialResults": std::mem::take(&mut self.pending) });
+            self.streamed = true;
+        }
+        (self.send)(json!({ "jsonrpc": "2.0", "method": "notifications/progress", "params": params }));
+    }
+
+    /// Send the partial results still pending when the tool is done. The client may
+    /// already have the latest progress, and progress has to grow, so this last
+    /// notification then counts one more unit.
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        if self.last_sent.is_some() && self.latest <= self.progress {
+            self.latest = self.progress + 1.0;
+            self.total = self.total.map(|t| t.max(self.latest));
+        }
+        self.send();
+    }
+}
+
+/// The reporter of one request, shared by the threads working on it.
+#[derive(Clone)]
+pub(crate) struct Handle(Arc<Mutex<Reporter>>);
+
+impl Handle {
+    /// Run `f` with this reporter as the current thread's, as in the thread that
+    /// installed it.
+    pub(crate) fn enter<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|c| c.borrow_mut().replace(self.clone()));
+        let result = f();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+        result
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut Reporter) -> T) -> T {
+        let mut reporter = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut reporter)
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Handle>> = const { RefCell::new(None) };
+}
+
+/// The reporter of the current thread, for passing to worker threads.
+pub(crate) fn handle() -> Option<Handle> {
+    CURRENT.with(|c| c.borrow().clone())
+}
+
+/// Run `f` with progress reported to `send` as `notifications/progress` messages for
+/// `token`. Without a token `f` just runs. Partial results still pending when `f`
+/// returns, from any thread, are sent before it returns, ahead of the response.
+pub(crate) fn with_reporter<T>(token: Option<Value>, send: impl FnMut(Value) + Send + 'static, f: impl FnOnce() -> T) -> T {
+    let Some(token) = token else { return f() };
+    let handle = Handle(Arc::new(Mutex::new(Reporter {
+        token,
+        send: Box::new(send),
+        phase: String::new(),
+        phase_base: 0.0,
+        total: None,
+        latest: 0.0,
+        progress: 0.0,
+        last_sent: None,
+        pending: Vec::new(),
+        streamed: false,
+    })));
+    let result = handle.enter(f);
+    handle.with(Reporter::flush);
+    result
+}
+
+/// `done` of `total` units of the phase described by `message`.
+pub(crate) fn report(done: usize, total: Option<usize>, message: &str) {
+    let Some(handle) = handle() else { return };
+    handle.with(|r| {
+        let new_phase = r.phase != message;
+        if new_phase {
+            r.phase = message.to_string();
+            r.phase_base = r.latest;
+        }
+        // Workers of one phase report out of order: keep the furthest
+        r.latest = r.latest.max(r.phase_base + done as f64);
+        r.total = total.map(|t| r.phase_base + t as f64);
+        let first_results = !r.streamed && !r.pending.is_empty();
+        if new_phase || first_results || r.last_sent.is_none_or(|t| t.elapsed() >= MIN_INTERVAL) {
+            r.send();
+        }
+    });
+}
+
+/// Queue a final result for the next notification. `item` only runs while progress is on.
+pub(crate) fn partial(item: impl FnOnce() -> Value) {
+    if let Some(handle) = handle() {
+        let item = item();
+        handle.with(|r| r.pending.push(item));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_progress_phases_throttle_and_partial_results() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&sent);
+        report(1, None, "ignored without a reporter");
+        let answer = with_reporter(Some(json!("tok")), move |m| sink.lock().unwrap().push(m), || {
+            report(0, Some(100), "matching");
+            report(50, Some(100), "matching"); // throttled
+            partial(|| json!({ "path": "a.cs" }));
+            report(51, Some(100), "matching"); // first results go out at once
+            report(2, Some(10), "reading"); // and so does a new phase
+            partial(|| json!({ "path": "b.cs" }));
+            report(3, Some(10), "reading"); // throttled, flushed on return
+            42
+        });
+        assert_eq!(answer, 42);
+        report(3, None, "reading");
+        partial(|| panic!("no reporter, nothing to build"));
+
+        let sent = sent.lock().unwrap();
+        let params: Vec<&Value> = sent.iter().map(|m| &m["params"]).collect();
+        assert_eq!(params.len(), 4, "{:?}", params);
+        assert_eq!(sent[0]["method"], "notifications/progress");
+        assert_eq!(*params[0], json!({ "progressToken": "tok", "progress": 0.0, "total": 100.0, "message": "matching" }));
+        assert_eq!((&params[1]["progress"], &params[1]["_meta"]["partialResults"]), (&json!(51.0), &json!([{ "path": "a.cs" }])));
+        // The second phase counts on from where the first got to
+        assert_eq!((&params[2]["progress"], &params[2]["total"]), (&json!(53.0), &json!(61.0)));
+        assert!(params[2].get("_meta").is_none());
+        assert_eq!((&params[3]["progress"], &params[3]["_meta"]["partialResults"]), (&json!(54.0), &json!([{ "path": "b.cs" }])));
+
+        with_reporter(None, |_| panic!("no token, no notifications"), || report(1, None, "x"));
+    }
+
+    #[test]
+    fn test_worker_threads_report_and_pending_results_are_flushed() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&sent);
+        with_reporter(Some(json!(7)), move |m| sink.lock().unwrap().push(m), || {
+            report(0, Some(4), "reading");
+            let reporter = handle().unwrap();
+            let done = std::sync::atomic::AtomicUsize::new(0);
+            std::thread::scope(|s| {
+                for i in 0..4 {
+                    let (reporter, done) = (reporter.clone(), &done);
+                    s.spawn(move || reporter.enter(|| {
+                        partial(|| json!({ "path": format!("{}.cs", i) }));
+                        report(done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1, Some(4), "reading");
+                    }));
+                }
+            });
+            assert!(std::thread::spawn(|| handle().is_none()).join().unwrap(), "a thread reports only under enter");
+            report(1, Some(1), "ranking");
+            // Queued after the last progress went out: flushed on return all the same
+            partial(|| json!({ "path": "late.cs" }));
+        });
+
+        let sent = sent.lock().unwrap();
+        let progress: Vec<f64> = sent.iter().map(|m| m["params"]["progress"].as_f64().unwrap()).collect();
+        assert!(progress.windows(2).all(|w| w[0] < w[1]), "progress must grow: {:?}", progress);
+        let mut streamed: Vec<String> = sent.iter()
+            .flat_map(|m| m["params"]["_meta"]["partialResults"].as_array().cloned().unwrap_or_default())
+            .map(|r| r["path"].as_str().unwrap().to_string())
+            .collect();
+        streamed.sort();
+        assert_eq!(streamed, vec!["0.cs", "1.cs", "2.cs", "3.cs", "late.cs"]);
+        let last = &sent.last().unwrap()["params"];
+        assert_eq!((&last["progress"], &last["total"]), (&json!(6.0), &json!(6.0)));
+        assert_eq!(last["_meta"]["partialResults"], json!([{ "path": "late.cs" }]));
+    }
+}
@@ -0,0 +1,221 @@
+//! MCP prompts: parameterized workflows that chain the server's own tools.
+//!
+//! Each prompt turns one of the `search_help` strategy recipes into a ready-to-run
+//! plan with the user's arguments filled in, so a client can offer it from a menu
+//! instead of the agent having to discover the recipe first. The anti-patterns come
+//! from [`crate::tips::strategies`], which stays the single source of the advice.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::mcp::protocol::{PromptArgument, PromptDefinition};
+
+struct PromptArg {
+    name: &'static str,
+    description: &'static str,
+    /// `None` = required
+    default: Option<&'static str>,
+}
+
+struct Prompt {
+    name: &'static str,
+    description: &'static str,
+    args: &'static [PromptArg],
+    /// First line of the message; `{arg}` placeholders are filled in.
+    goal: &'static str,
+    steps: &'static [&'static str],
+    /// Strategy recipes (by name) whose anti-patterns are appended.
+    strategies: &'static [&'static str],
+}
+
+const PROMPTS: &[Prompt] = &[
+    Prompt {
+        name: "trace_feature",
+        description: "Trace a feature end-to-end: its types, key implementations, entry points and callers",
+        args: &[
+            PromptArg { name: "feature", description: "Feature or type name, e.g. 'Payment' or 'OrderService'", default: None },
+            PromptArg { name: "depth", description: "Call tree depth (default: 3)", default: Some("3") },
+        ],
+        goal: "Trace the feature '{feature}' end-to-end and explain how it works, from entry points to the code that does the work.",
+        steps: &[
+            "search_definitions name='{feature}' maxResults=50 -> every class, interface and method whose name contains it, with file paths",
+            "search_definitions name='<3-5 central types from step 1>' includeBody=true maxBodyLines=30 -> read the key implementations",
+            "search_callers method='<main entry method>' class='<its class>' direction='up' depth={depth} -> who triggers the feature (controllers, jobs, handlers)",
+            "search_callers method='<same method>' class='<its class>' direction='down' depth={depth} -> what it calls (repositories, clients, other services)",
+            "(optional) search_grep terms='{feature}' countOnly=true -> how widespread the name is, to check nothing was missed",
+        ],
+        strategies: &["Architecture Exploration", "Call Chain Investigation"],
+    },
+    Prompt {
+        name: "complexity_review",
+        description: "Review code complexity of a directory: most complex methods, churn hotspots, duplicates and open TODOs",
+        args: &[
+            PromptArg { name: "dir", description: "Directory or path substring to review, e.g. 'src/Services'", default: None },
+            PromptArg { name: "top", description: "How many methods to rank (default: 20)", default: Some("20") },
+        ],
+        goal: "Review the code in '{dir}' for complexity and maintainability, and recommend the most valuable refactorings.",
+        steps: &[
+            "search_definitions file='{dir}' sortBy='cognitiveComplexity' maxResults={top} -> the most complex methods with their metrics",
+            "search_hotspots dir='{dir}' level='method' -> methods that are both complex and frequently changed (the riskiest)",
+            "search_dupes file='{dir}' -> copy-pasted method bodies worth extracting",
+            "search_todos dir='{dir}' -> known debt already flagged in comments",
+            "search_definitions name='<2-3 worst offenders>' includeBody=true maxBodyLines=60 -> read them before recommending changes",
+        ],
+        strategies: &["Code Health Scan"],
+    },
+    Prompt {
+        name: "investigate_stack_trace",
+        description: "Find the method at a file and line from a stack trace, who calls it, and who last changed it",
+        args: &[
+            PromptArg { name: "file", description: "File name or path from the stack trace, e.g. 'OrderService.cs'", default: None },
+            PromptArg { name: "line", description: "Line number from the stack trace", default: None },
+        ],
+        goal: "Investigate the failure at {file}:{line}: identify the code, how it is reached, and when it last changed.",
+        steps: &[
+            "search_definitions file='{file}' containsLine={line} includeBody=true -> the method and class containing the line, with source",
+            "search_callers method='<method from step 1>' class='<class from step 1>' depth=2 -> the code paths that reach it",
+            "search_git_blame repo='.' file='<full path from step 1>' startLine={line} -> the commit, author and date of the failing line",
+        ],
+        strategies: &["Stack Trace / Bug Investigation", "Code History Investigation"],
+    },
+    Prompt {
+        name: "trace_endpoint",
+        description: "Trace an HTTP endpoint from its route to the handler and everything the handler calls",
+        args: &[
+            PromptArg { name: "route", description: "Route or part of it, e.g. 'api/orders' or 'orders/{id}'", default: None },
+            PromptArg { name: "depth", description: "Call tree depth (default: 3)", default: Some("3") },
+        ],
+        goal: "Trace the endpoint '{route}': find the handler that serves it and explain what happens when it is called.",
+        steps: &[
+            "search_grep terms='{route}' substring=true showLines=true -> the route attribute or mapping that declares the endpoint, with file and line",
+            "search_definitions file='<file from step 1>' containsLine=<line from step 1> includeBody=true -> the handler method and its controller",
+            "search_callers method='<handler method>' class='<its class>' direction='down' depth={depth} -> the services, repositories and clients the request reaches",
+            "(optional) search_references name='<request or response type from the handler signature>' usage='typeReference' -> other code sharing the endpoint's contract",
+        ],
+        strategies: &["Call Chain Investigation"],
+    },
+    Prompt {
+        name: "find_usages_and_tests",
+        description: "Find every usage of a symbol and the tests that cover it",
+        args: &[
+            PromptArg { name: "symbol", description: "Class, method or property name, e.g. 'OrderValidator'", default: None },
+            PromptArg { name: "tests", description: "Path substring that marks test code (default: 'test')", default: Some("test") },
+        ],
+        goal: "Find all usages of '{symbol}' and the tests that exercise it, and point out usages no test covers.",
+        steps: &[
+            "search_definitions name='{symbol}' -> where it is declared, with its kind and file",
+            "search_references name='{symbol}' excludeDir=['{tests}'] -> every production usage, classified as call, instantiation, inheritance or type reference",
+            "search_references name='{symbol}' file='{tests}' -> usages in test code",
+            "(optional) search_callers method='{symbol}' class='<class from step 1>' direction='up' depth=2 -> indirect usages through its callers, for methods",
+        ],
+        strategies: &["Call Chain Investigation"],
+    },
+    Prompt {
+        name: "summarize_recent_changes",
+        description: "Summarize recent changes to a module: commits, authors and the code they touched",
+        args: &[
+            PromptArg { name: "module", description: "Directory or file relative to the repo root, e.g. 'src/Orders'", default: None },
+            PromptArg { name: "from", description: "Start date (YYYY-MM-DD)", default: None },
+        ],
+        goal: "Summarize what changed in '{module}' since {from}: the main themes, who made the changes, and anything risky.",
+        steps: &[
+            "search_branch_status repo='.' -> confirm the branch and that the history is fresh",
+            "search_git_history repo='.' file='{module}' from='{from}' -> the commits that touched the module, with messages and authors",
+            "search_git_authors repo='.' path='{module}' from='{from}' -> who drove the changes",
+            "search_git_diff repo='.' file='<1-3 files the commits touch most>' from='{from}' -> what actually changed in the key files",
+            "(optional) search_hotspots dir='{module}' -> whether the churn lands on already complex code",
+        ],
+        strategies: &["Code History Investigation"],
+    },
+];
+
+/// Prompt list for `prompts/list`.
+pub fn prompt_definitions() -> Vec<PromptDefinition> {
+    PROMPTS.iter().map(|p| PromptDefinition {
+        name: p.name.to_string(),
+        description: p.description.to_string(),
+        arguments: p.args.iter().map(|a| PromptArgument {
+            name: a.name.to_string(),
+            description: a.description.to_string(),
+            required: a.default.is_none(),
+        }).collect(),
+    }).collect()
+}
+
+/// Render prompt `name` with `arguments` (a JSON object of strings or numbers).
+/// Returns the prompt description and the message text, or an error for an unknown
+/// prompt or a missing required argument.
+pub fn render_prompt(name: &str, arguments: &Value) -> Result<(String, String), String> {
+    let prompt = PROMPTS.iter().find(|p| p.name == name)
+        .ok_or_else(|| format!("Unknown prompt: {}", name))?;
+    let mut values: HashMap<&str, String> = HashMap::new();
+    for arg in prompt.args {
+        let given = arguments.get(arg.name).and_then(|v| match v {
+            Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        });
+        let value = given.or_else(|| arg.default.map(str::to_string))
+            .ok_or_else(|| format!("Missing required argument '{}' for prompt '{}'", arg.name, name))?;
+        values.insert(arg.name, value);
+    }
+    let fill = |template: &str| {
+        values.iter().fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+    };
+
+    let mut text = fill(prompt.goal);
+    text.push_str(&format!("\n\nUse the search-index tools in this order ({} calls at most; skip optional steps when earlier results already answer the question):\n", prompt.steps.len()));
+    for (i, step) in prompt.steps.iter().enumerate() {
+        text.push_str(&format!("{}. {}\n", i + 1, fill(step)));
+    }
+    let avoid: Vec<&str> = crate::tips::strategies().into_iter()
+        .filter(|s| prompt.strategies.contains(&s.name))
+        .flat_map(|s| s.anti_patterns.iter().copied())
+        .collect();
+    if !avoid.is_empty() {
+        text.push_str("\nAvoid:\n");
+        for line in avoid {
+            text.push_str(&format!("- {}\n", line));
+        }
+    }
+    Ok((prompt.description.to_string(), text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_prompts_render_arguments_and_strategy_advice() {
+        let defs = prompt_definitions();
+        let names: Vec<&str> = defs.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["trace_feature", "complexity_review", "investigate_stack_trace",
+            "trace_endpoint", "find_usages_and_tests", "summarize_recent_changes"]);
+        assert!(defs[0].arguments[0].required && !defs[0].arguments[1].required);
+
+        // Every prompt names real strategy recipes, so the advice can't silently vanish
+        let strategies: Vec<&str> = crate::tips::strategies().iter().map(|s| s.name).collect();
+        for prompt in PROMPTS {
+            assert!(prompt.strategies.iter().all(|s| strategies.contains(s)), "{}", prompt.name);
+        }
+
+        let (_, text) = render_prompt("complexity_review", &json!({ "dir": "src/Services" })).unwrap();
+        assert!(text.contains("search_definitions file='src/Services' sortBy='cognitiveComplexity' maxResults=20"));
+        assert!(text.contains("search_hotspots dir='src/Services'"));
+        assert!(text.contains("Avoid:\n- Don't read every file"));
+        assert!(!text.contains('{'), "all placeholders filled: {}", text);
+
+        let (_, text) = render_prompt("investigate_stack_trace", &json!({ "file": "Order.cs", "line": 42 })).unwrap();
+        assert!(text.starts_with("Investigate the failure at Order.cs:42"));
+
+        let (_, text) = render_prompt("find_usages_and_tests", &json!({ "symbol": "OrderValidator" })).unwrap();
+        assert!(text.contains("search_references name='OrderValidator' file='test'"));
+        let (_, text) = render_prompt("summarize_recent_changes", &json!({ "module": "src/Orders", "from": "2024-01-01" })).unwrap();
+        assert!(text.contains("search_git_history repo='.' file='src/Orders' from='2024-01-01'"));
+
+        assert!(render_prompt("trace_feature", &json!({})).unwrap_err().contains("'feature'"));
+        assert!(render_prompt("nope", &json!({})).unwrap_err().contains("Unknown prompt"));
+    }
+}
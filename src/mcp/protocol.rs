@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+
+use crate::error::{SearchError, SuggestedAction};
 
 // ─── JSON-RPC 2.0 base types ────────────────────────────────────────
 
@@ -54,6 +56,8 @@ pub struct InitializeResult {
 #[derive(Serialize, Debug)]
 pub struct ServerCapabilities {
     pub tools: ToolsCapability,
+    pub resources: ResourcesCapability,
+    pub prompts: PromptsCapability,
 }
 
 #[derive(Serialize, Debug)]
@@ -62,6 +66,19 @@ pub struct ToolsCapability {
     pub list_changed: bool,
 }
 
+#[derive(Serialize, Debug)]
+pub struct ResourcesCapability {
+    pub subscribe: bool,
+    #[serde(rename = "listChanged")]
+    pub list_changed: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PromptsCapability {
+    #[serde(rename = "listChanged")]
+    pub list_changed: bool,
+}
+
 #[derive(Serialize, Debug)]
 pub struct ServerInfo {
     pub name: String,
@@ -83,12 +100,57 @@ pub struct ToolDefinition {
     pub input_schema: Value,
 }
 
+// ─── MCP Resources types ────────────────────────────────────────────
+
+/// URI of the single resource the server exposes: the index state. Subscribing to it
+/// delivers `notifications/resources/updated` whenever the index generation changes.
+pub const INDEX_RESOURCE_URI: &str = "search-index://index";
+
+#[derive(Serialize, Debug)]
+pub struct ResourceDefinition {
+    pub uri: String,
+    pub name: String,
+    pub description: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResourcesListResult {
+    pub resources: Vec<ResourceDefinition>,
+}
+
+// ─── MCP Prompts types ──────────────────────────────────────────────
+
+#[derive(Serialize, Debug)]
+pub struct PromptsListResult {
+    pub prompts: Vec<PromptDefinition>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PromptDefinition {
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PromptArgument {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+}
+
 /// MCP tool call result content
 #[derive(Serialize, Debug)]
 pub struct ToolCallResult {
     pub content: Vec<ToolContent>,
     #[serde(rename = "isError", skip_serializing_if = "std::ops::Not::not")]
     pub is_error: bool,
+    /// Errors only: `{error: {code, message, retryable, suggestedAction}}` for clients
+    /// that recover automatically
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
 }
 
 #[derive(Serialize, Debug)]
@@ -128,20 +190,40 @@ impl ToolCallResult {
                 text,
             }],
             is_error: false,
+            meta: None,
         }
     }
 
+    /// Error the caller can only fix by changing the arguments.
     pub fn error(text: String) -> Self {
+        Self::error_with_hint(text, "invalid_arguments", false, SuggestedAction::FixArguments)
+    }
+
+    /// Error text plus its `code`, `retryable` and `suggestedAction` in `_meta.error`.
+    pub fn error_with_hint(text: String, code: &str, retryable: bool, action: SuggestedAction) -> Self {
+        let meta = json!({ "error": {
+            "code": code,
+            "message": text,
+            "retryable": retryable,
+            "suggestedAction": action.as_str(),
+        }});
         Self {
             content: vec![ToolContent {
                 content_type: "text".to_string(),
                 text,
             }],
             is_error: true,
+            meta: Some(meta),
         }
     }
 }
 
+impl From<SearchError> for ToolCallResult {
+    fn from(e: SearchError) -> Self {
+        Self::error_with_hint(e.to_string(), e.code(), e.retryable(), e.suggested_action())
+    }
+}
+
 impl InitializeResult {
     pub fn new() -> Self {
         Self {
@@ -150,6 +232,13 @@ impl InitializeResult {
                 tools: ToolsCapability {
                     list_changed: false,
                 },
+                resources: ResourcesCapability {
+                    subscribe: true,
+                    list_changed: false,
+                },
+                prompts: PromptsCapability {
+                    list_changed: false,
+                },
             },
             server_info: ServerInfo {
                 name: "search-index".to_string(),
@@ -256,6 +345,17 @@ mod tests {
         let json = serde_json::to_value(&result).unwrap();
         assert_eq!(json["content"][0]["text"], "something failed");
         assert_eq!(json["isError"], true);
+        assert_eq!(json["_meta"]["error"]["suggestedAction"], "fix_arguments");
+
+        let building = SearchError::IndexBuilding("Content index is being built".to_string());
+        let json = serde_json::to_value(ToolCallResult::from(building)).unwrap();
+        assert_eq!(json["_meta"]["error"], serde_json::json!({
+            "code": "index_building",
+            "message": "Content index is being built",
+            "retryable": true,
+            "suggestedAction": "wait_for_build",
+        }));
+        assert!(serde_json::to_value(ToolCallResult::success("ok".to_string())).unwrap().get("_meta").is_none());
     }
 
     #[test]
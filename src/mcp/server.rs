@@ -1,51 +1,30 @@
 use std::io::{self, BufRead, Write};
-use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use serde_json::{json, Value};
 use tracing::{debug, error, info, warn};
 
+use crate::mcp::audit_log::AuditLog;
 use crate::mcp::handlers::{self, HandlerContext};
+use crate::mcp::{progress, prompts};
 use crate::mcp::protocol::*;
-use crate::{save_content_index, ContentIndex};
-use crate::definitions::{self, DefinitionIndex};
-use crate::git::cache::GitHistoryCache;
-
-/// Run the MCP server event loop over stdio
-pub fn run_server(
-    index: Arc<RwLock<ContentIndex>>,
-    def_index: Option<Arc<RwLock<DefinitionIndex>>>,
-    server_dir: String,
-    server_ext: String,
-    metrics: bool,
-    index_base: PathBuf,
-    max_response_bytes: usize,
-    content_ready: Arc<AtomicBool>,
-    def_ready: Arc<AtomicBool>,
-    git_cache: Arc<RwLock<Option<GitHistoryCache>>>,
-    git_cache_ready: Arc<AtomicBool>,
-    current_branch: Option<String>,
-) {
-    let ctx = HandlerContext {
-        index,
-        def_index,
-        server_dir,
-        server_ext,
-        metrics,
-        index_base,
-        max_response_bytes,
-        content_ready,
-        def_ready,
-        git_cache,
-        git_cache_ready,
-        current_branch,
-    };
+use crate::save_content_index;
+use crate::definitions;
+use search::clock::Generation;
+
+/// How often the change notifier checks the index generation.
+const NOTIFY_POLL_INTERVAL: Duration = Duration::from_millis(200);
 
+/// Run the MCP server event loop over stdio, answering with `ctx`. `ascii_json` is the
+/// default of the per-request `asciiOutput`; answers are appended to `audit_log` once
+/// both indexes are ready.
+pub fn run_server(ctx: HandlerContext, ascii_json: bool, mut audit_log: Option<AuditLog>) {
     let stdin = io::stdin();
     let mut reader = stdin.lock();
-    let stdout = io::stdout();
-    let mut writer = stdout.lock();
+    // Shared with the change notifier thread, so a notification never interleaves a response
+    let writer = Arc::new(Mutex::new(io::stdout()));
 
     const MAX_REQUEST_SIZE: usize = 10 * 1024 * 1024; // 10 MB
 
@@ -59,12 +38,21 @@ pub fn run_server(
         warn!("Failed to set Ctrl+C handler: {}", e);
     }
 
+    spawn_change_notifier(
+        Arc::clone(&ctx.generation),
+        Arc::clone(&ctx.index_subscribed),
+        Arc::clone(&writer),
+        Arc::clone(&shutdown_flag),
+    );
+
     info!("MCP server ready, waiting for JSON-RPC requests on stdin");
 
-    let mut line = String::new();
+    // Raw bytes, so a request that is not valid UTF-8 gets a parse error instead of
+    // ending the read loop
+    let mut raw: Vec<u8> = Vec::new();
     loop {
-        line.clear();
-        match reader.read_line(&mut line) {
+        raw.clear();
+        match reader.read_until(b'\n', &mut raw) {
             Ok(0) => break, // EOF
             Ok(_) => {
                 // Check if shutdown was signaled (belt-and-suspenders for signal between reads)
@@ -72,18 +60,22 @@ pub fn run_server(
                     info!("Shutdown flag set, exiting event loop");
                     break;
                 }
-                if line.len() > MAX_REQUEST_SIZE {
-                    error!(size = line.len(), "Request too large, skipping");
+                if raw.len() > MAX_REQUEST_SIZE {
+                    error!(size = raw.len(), "Request too large, skipping");
                     continue;
                 }
-                let line = line.trim().to_string();
+                let line = String::from_utf8_lossy(&raw).trim().to_string();
                 if line.is_empty() {
                     continue;
                 }
 
                 debug!(request = %line, "Incoming JSON-RPC");
 
-                let request: JsonRpcRequest = match serde_json::from_str(&line) {
+                let parsed = match std::str::from_utf8(&raw) {
+                    Ok(_) => serde_json::from_str::<JsonRpcRequest>(&line).map_err(|e| e.to_string()),
+                    Err(e) => Err(format!("request is not valid UTF-8 ({})", e)),
+                };
+                let request: JsonRpcRequest = match parsed {
                     Ok(r) => r,
                     Err(e) => {
                         warn!(error = %e, "Failed to parse JSON-RPC request");
@@ -94,14 +86,10 @@ pub fn run_server(
                         );
                         let resp = serde_json::to_string(&err).unwrap();
                         debug!(response = %resp, "Error response");
-                        if let Err(e) = writeln!(writer, "{}", resp) {
+                        if let Err(e) = write_message(&writer, &resp) {
                             error!(error = %e, "Failed to write error response to stdout, shutting down");
                             break;
                         }
-                        if let Err(e) = writer.flush() {
-                            error!(error = %e, "Failed to flush stdout, shutting down");
-                            break;
-                        }
                         continue;
                     }
                 };
@@ -113,18 +101,36 @@ pub fn run_server(
                 }
 
                 let id = request.id.unwrap();
-                let response = handle_request(&ctx, &request.method, &request.params, id.clone());
+                // Per request: params._meta.asciiOutput overrides --ascii-json
+                let ascii = request.params.as_ref()
+                    .and_then(|p| p.get("_meta")).and_then(|m| m.get("asciiOutput")).and_then(|v| v.as_bool())
+                    .unwrap_or(ascii_json);
+                let response = with_progress(&writer, &request.method, &request.params, ascii, || {
+                    handle_request(&ctx, &request.method, &request.params, id.clone())
+                });
+                // Answers given while an index was still building would never replay the same
+                if let Some(log) = audit_log.as_mut()
+                    && request.method == "tools/call"
+                    && ctx.content_ready.load(Ordering::Acquire) && ctx.def_ready.load(Ordering::Acquire)
+                    && let Some(result) = response.get("result") {
+                    let params = request.params.as_ref();
+                    let tool = params.and_then(|p| p.get("name")).and_then(|v| v.as_str()).unwrap_or("");
+                    let arguments = params.and_then(|p| p.get("arguments")).cloned().unwrap_or(json!({}));
+                    if let Err(e) = log.append(tool, &arguments, result) {
+                        warn!(error = %e, "Failed to write audit log, disabling it");
+                        audit_log = None;
+                    }
+                }
 
-                let resp_str = serde_json::to_string(&response).unwrap();
+                let mut resp_str = serde_json::to_string(&response).unwrap();
+                if ascii {
+                    resp_str = crate::escape_non_ascii_json(&resp_str);
+                }
                 debug!(response = %resp_str, "Outgoing JSON-RPC");
-                if let Err(e) = writeln!(writer, "{}", resp_str) {
+                if let Err(e) = write_message(&writer, &resp_str) {
                     error!(error = %e, "Failed to write response to stdout, shutting down");
                     break;
                 }
-                if let Err(e) = writer.flush() {
-                    error!(error = %e, "Failed to flush stdout, shutting down");
-                    break;
-                }
             }
             Err(e) => {
                 error!(error = %e, "Error reading stdin");
@@ -138,21 +144,94 @@ pub fn run_server(
     info!("Shutdown complete");
 }
 
+/// Write one JSON-RPC message as a line and flush it.
+fn write_message<W: Write>(writer: &Mutex<W>, message: &str) -> io::Result<()> {
+    let mut w = writer.lock().unwrap_or_else(|e| e.into_inner());
+    writeln!(w, "{}", message)?;
+    w.flush()
+}
+
+/// Run `f`, the handling of a request, sending `notifications/progress` to `writer`
+/// when it is a `tools/call` whose `params._meta.progressToken` asks for them.
+fn with_progress<W: Write + Send + 'static, T>(
+    writer: &Arc<Mutex<W>>,
+    method: &str,
+    params: &Option<Value>,
+    ascii: bool,
+    f: impl FnOnce() -> T,
+) -> T {
+    let token = params.as_ref()
+        .filter(|_| method == "tools/call")
+        .and_then(|p| p.get("_meta")).and_then(|m| m.get("progressToken"))
+        .filter(|t| t.is_string() || t.is_number())
+        .cloned();
+    let writer = Arc::clone(writer);
+    progress::with_reporter(token, move |notification| {
+        let mut msg = notification.to_string();
+        if ascii {
+            msg = crate::escape_non_ascii_json(&msg);
+        }
+        debug!(notification = %msg, "Outgoing JSON-RPC notification");
+        if let Err(e) = write_message(&writer, &msg) {
+            warn!(error = %e, "Failed to write progress notification");
+        }
+    }, f)
+}
+
+/// `notifications/resources/updated` for the index resource at `generation`.
+fn index_updated_notification(generation: u64) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/resources/updated",
+        "params": {
+            "uri": INDEX_RESOURCE_URI,
+            "_meta": { "indexGeneration": generation },
+        },
+    })
+}
+
+/// Watch the index generation and, while the client is subscribed to the index
+/// resource, send `notifications/resources/updated` each time it moves. Several
+/// changes within one poll interval produce a single notification.
+fn spawn_change_notifier<W: Write + Send + 'static>(
+    generation: Arc<Generation>,
+    subscribed: Arc<AtomicBool>,
+    writer: Arc<Mutex<W>>,
+    shutdown: Arc<AtomicBool>,
+) {
+    std::thread::spawn(move || {
+        let mut last = generation.get();
+        while !shutdown.load(Ordering::SeqCst) {
+            std::thread::sleep(NOTIFY_POLL_INTERVAL);
+            let current = generation.get();
+            if current == last {
+                continue;
+            }
+            last = current;
+            if !subscribed.load(Ordering::Acquire) {
+                continue;
+            }
+            let msg = index_updated_notification(current).to_string();
+            debug!(notification = %msg, "Outgoing JSON-RPC notification");
+            if let Err(e) = write_message(&writer, &msg) {
+                warn!(error = %e, "Failed to write index change notification, stopping notifier");
+                break;
+            }
+        }
+    });
+}
+
 /// Save in-memory indexes to disk on graceful shutdown.
 /// This preserves incremental watcher updates that were only held in memory.
 fn save_indexes_on_shutdown(ctx: &HandlerContext) {
     // Save content index
-    match ctx.index.read() {
-        Ok(idx) => {
-            if idx.files.is_empty() {
-                info!("Content index is empty, skipping save");
-            } else if let Err(e) = save_content_index(&idx, &ctx.index_base) {
-                warn!(error = %e, "Failed to save content index on shutdown");
-            } else {
-                info!(files = idx.files.len(), "Content index saved on shutdown");
-            }
-        }
-        Err(e) => warn!(error = %e, "Failed to read content index for shutdown save"),
+    let idx = ctx.index.snapshot();
+    if idx.files.is_empty() {
+        info!("Content index is empty, skipping save");
+    } else if let Err(e) = save_content_index(&idx, &ctx.index_base) {
+        warn!(error = %e, "Failed to save content index on shutdown");
+    } else {
+        info!(files = idx.files.len(), "Content index saved on shutdown");
     }
 
     // Save definition index
@@ -218,14 +297,74 @@ fn handle_request(
                 .cloned()
                 .unwrap_or(Value::Object(serde_json::Map::new()));
 
+            // Read before dispatch: the results reflect at least this generation
+            let generation = ctx.generation.get();
             let result = handlers::dispatch_tool(ctx, tool_name, &arguments);
+            let mut result = serde_json::to_value(result).unwrap();
+            result["_meta"] = json!({ "indexGeneration": generation });
 
+            serde_json::to_value(JsonRpcResponse::new(id, result)).unwrap()
+        }
+        "prompts/list" => {
+            let result = PromptsListResult { prompts: prompts::prompt_definitions() };
+            serde_json::to_value(JsonRpcResponse::new(
+                id,
+                serde_json::to_value(result).unwrap(),
+            ))
+            .unwrap()
+        }
+        "prompts/get" => {
+            let name = params.as_ref().and_then(|p| p.get("name")).and_then(|v| v.as_str()).unwrap_or("");
+            let arguments = params.as_ref().and_then(|p| p.get("arguments")).cloned().unwrap_or(Value::Null);
+            match prompts::render_prompt(name, &arguments) {
+                Ok((description, text)) => serde_json::to_value(JsonRpcResponse::new(id, json!({
+                    "description": description,
+                    "messages": [{ "role": "user", "content": { "type": "text", "text": text } }],
+                })))
+                .unwrap(),
+                Err(message) => serde_json::to_value(JsonRpcErrorResponse::new(id, -32602, message)).unwrap(),
+            }
+        }
+        "resources/list" => {
+            let result = ResourcesListResult {
+                resources: vec![ResourceDefinition {
+                    uri: INDEX_RESOURCE_URI.to_string(),
+                    name: "Index state".to_string(),
+                    description: "Index generation and readiness. Subscribe to be notified when the index changes.".to_string(),
+                    mime_type: "application/json".to_string(),
+                }],
+            };
             serde_json::to_value(JsonRpcResponse::new(
                 id,
                 serde_json::to_value(result).unwrap(),
             ))
             .unwrap()
         }
+        "resources/read" | "resources/subscribe" | "resources/unsubscribe" => {
+            let uri = params.as_ref().and_then(|p| p.get("uri")).and_then(|v| v.as_str()).unwrap_or("");
+            if uri != INDEX_RESOURCE_URI {
+                return serde_json::to_value(JsonRpcErrorResponse::new(
+                    id,
+                    -32602,
+                    format!("Unknown resource: {}", uri),
+                ))
+                .unwrap();
+            }
+            let result = match method {
+                "resources/read" => json!({
+                    "contents": [{
+                        "uri": INDEX_RESOURCE_URI,
+                        "mimeType": "application/json",
+                        "text": index_state(ctx).to_string(),
+                    }],
+                }),
+                _ => {
+                    ctx.index_subscribed.store(method == "resources/subscribe", Ordering::Release);
+                    json!({})
+                }
+            };
+            serde_json::to_value(JsonRpcResponse::new(id, result)).unwrap()
+        }
         "ping" => {
             serde_json::to_value(JsonRpcResponse::new(id, json!({}))).unwrap()
         }
@@ -240,11 +379,28 @@ fn handle_request(
     }
 }
 
+/// Contents of the index resource.
+fn index_state(ctx: &HandlerContext) -> Value {
+    let files = ctx.index.snapshot().files.len();
+    let mut state = json!({
+        "generation": ctx.generation.get(),
+        "contentReady": ctx.content_ready.load(Ordering::Acquire),
+        "defReady": ctx.def_ready.load(Ordering::Acquire),
+        "files": files,
+    });
+    if let Some(ref def) = ctx.def_index {
+        state["definitions"] = json!(def.read().map(|d| d.definitions.len()).unwrap_or(0));
+    }
+    state
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{ContentIndex, Searcher, SkippedFiles, TokenizerSettings};
     use std::collections::HashMap;
+    use std::sync::RwLock;
     use crate::TrigramIndex;
 
     fn make_ctx() -> HandlerContext {
@@ -261,9 +417,18 @@ mod tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
         HandlerContext {
-            index: Arc::new(RwLock::new(index)),
+            index: Searcher::new(index),
             def_index: None,
             server_dir: ".".to_string(),
             server_ext: "cs".to_string(),
@@ -275,6 +440,15 @@ mod tests {
             git_cache: Arc::new(RwLock::new(None)),
             git_cache_ready: Arc::new(AtomicBool::new(false)),
             current_branch: None,
+            commit_index: Arc::new(Mutex::new(None)),
+            semantic_index: Arc::new(Mutex::new(None)),
+            owners: None,
+            generation: Default::default(),
+            index_subscribed: Arc::new(AtomicBool::new(false)),
+            reindex_jobs: Default::default(),
+            query_history: Default::default(),
+            ranking: Default::default(),
+            rank_hook: None,
         }
     }
 
@@ -295,13 +469,15 @@ mod tests {
         assert_eq!(result["jsonrpc"], "2.0");
         assert_eq!(result["id"], 2);
         let tools = result["result"]["tools"].as_array().unwrap();
-        assert_eq!(tools.len(), 15);
+        assert_eq!(tools.len(), 39);
         let names: Vec<&str> = tools.iter().map(|t| t["name"].as_str().unwrap()).collect();
         assert!(names.contains(&"search_grep"));
         assert!(names.contains(&"search_find"));
         assert!(names.contains(&"search_fast"));
         assert!(names.contains(&"search_info"));
         assert!(names.contains(&"search_reindex"));
+        assert!(names.contains(&"search_reindex_status"));
+        assert!(names.contains(&"search_metrics"));
         assert!(names.contains(&"search_reindex_definitions"));
         assert!(names.contains(&"search_definitions"));
     }
@@ -349,6 +525,90 @@ mod tests {
         assert!(result["result"]["content"][0]["text"].as_str().unwrap().contains("Missing params"));
     }
 
+    #[test]
+    fn test_tools_call_reports_index_generation() {
+        let ctx = make_ctx();
+        for _ in 0..3 {
+            ctx.generation.bump();
+        }
+        let params = json!({ "name": "search_grep", "arguments": { "terms": "HttpClient" } });
+        let result = handle_request(&ctx, "tools/call", &Some(params), json!(6));
+        assert_eq!(result["result"]["_meta"]["indexGeneration"], 3);
+    }
+
+    #[test]
+    fn test_index_resource_subscribe_and_read() {
+        let ctx = make_ctx();
+        let init = handle_request(&ctx, "initialize", &None, json!(1));
+        assert_eq!(init["result"]["capabilities"]["resources"]["subscribe"], true);
+
+        let list = handle_request(&ctx, "resources/list", &None, json!(2));
+        assert_eq!(list["result"]["resources"][0]["uri"], INDEX_RESOURCE_URI);
+
+        let uri = Some(json!({ "uri": INDEX_RESOURCE_URI }));
+        handle_request(&ctx, "resources/subscribe", &uri, json!(3));
+        assert!(ctx.index_subscribed.load(Ordering::Acquire));
+
+        for _ in 0..5 {
+            ctx.generation.bump();
+        }
+        let read = handle_request(&ctx, "resources/read", &uri, json!(4));
+        let state: Value = serde_json::from_str(read["result"]["contents"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(state["generation"], 5);
+        assert_eq!(state["contentReady"], true);
+
+        handle_request(&ctx, "resources/unsubscribe", &uri, json!(5));
+        assert!(!ctx.index_subscribed.load(Ordering::Acquire));
+
+        let unknown = handle_request(&ctx, "resources/read", &Some(json!({ "uri": "file:///x" })), json!(6));
+        assert_eq!(unknown["error"]["code"], -32602);
+    }
+
+    #[test]
+    fn test_change_notifier_only_notifies_subscribed_clients() {
+        let generation = Arc::new(Generation::default());
+        let subscribed = Arc::new(AtomicBool::new(false));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let out: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+        spawn_change_notifier(Arc::clone(&generation), Arc::clone(&subscribed), Arc::clone(&out), Arc::clone(&shutdown));
+        let wait = || std::thread::sleep(NOTIFY_POLL_INTERVAL * 3);
+
+        generation.bump();
+        wait();
+        assert!(out.lock().unwrap().is_empty(), "no notification without a subscription");
+
+        subscribed.store(true, Ordering::Release);
+        generation.bump();
+        generation.bump();
+        wait();
+        shutdown.store(true, Ordering::SeqCst);
+        let text = String::from_utf8(out.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 1, "changes within one interval coalesce: {}", text);
+        assert_eq!(lines[0]["method"], "notifications/resources/updated");
+        assert_eq!(lines[0]["params"]["uri"], INDEX_RESOURCE_URI);
+        assert_eq!(lines[0]["params"]["_meta"]["indexGeneration"], 3);
+    }
+
+    #[test]
+    fn test_handle_prompts_list_and_get() {
+        let ctx = make_ctx();
+        let init = handle_request(&ctx, "initialize", &None, json!(1));
+        assert!(init["result"]["capabilities"]["prompts"].is_object());
+
+        let list = handle_request(&ctx, "prompts/list", &None, json!(2));
+        let prompts = list["result"]["prompts"].as_array().unwrap();
+        assert!(prompts.iter().any(|p| p["name"] == "trace_feature" && p["arguments"][0]["required"] == true));
+
+        let params = Some(json!({ "name": "trace_feature", "arguments": { "feature": "Payment" } }));
+        let get = handle_request(&ctx, "prompts/get", &params, json!(3));
+        assert_eq!(get["result"]["messages"][0]["role"], "user");
+        assert!(get["result"]["messages"][0]["content"]["text"].as_str().unwrap().contains("search_definitions name='Payment'"));
+
+        let missing = handle_request(&ctx, "prompts/get", &Some(json!({ "name": "trace_feature" })), json!(4));
+        assert_eq!(missing["error"]["code"], -32602);
+    }
+
     #[test]
     fn test_shutdown_flag_initially_false_and_can_be_set() {
         let flag = Arc::new(AtomicBool::new(false));
@@ -1,41 +1,180 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
+use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind, RenameMode};
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use tracing::{error, info, warn};
 
-use crate::{build_content_index, clean_path, load_content_index, save_content_index, tokenize, ContentIndex, ContentIndexArgs, Posting, DEFAULT_MIN_TOKEN_LEN};
+use crate::{build_content_index, clean_path, path_from_string, path_to_string, top_level_dir, extract_comment_markers, extract_ticket_refs, load_content_index, looks_binary, save_content_index, CommentMarker, ContentIndex, ContentIndexArgs, Posting, Searcher, TicketRef, Token};
 use crate::definitions::{self, DefinitionIndex};
+use crate::mcp::ignore_rules::IgnoreRules;
+use crate::priority::{resolve_threads, BuildPriority};
+use search::clock::{Clock, Generation};
+use search::metrics;
+
+/// How the watcher learns about file changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WatchMode {
+    /// Poll on network and container file systems (SMB, NFS, 9p, Docker Desktop
+    /// volumes, WSL drives), OS notifications elsewhere (default)
+    #[default]
+    Auto,
+    /// OS notifications: inotify, ReadDirectoryChangesW, FSEvents
+    Native,
+    /// Rescan the tree every --poll-interval-ms and compare file sizes and mtimes
+    Poll,
+}
+
+impl WatchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Native => "native",
+            Self::Poll => "poll",
+        }
+    }
+}
 
-/// Start a file watcher thread that incrementally updates the in-memory index
+impl std::fmt::Display for WatchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Tuning knobs for the file watcher.
+#[derive(Debug, Clone, Copy)]
+pub struct WatcherOptions {
+    /// Debounce window for batching file change events
+    pub debounce_ms: u64,
+    /// Number of changed files that triggers a full reindex instead of incremental updates
+    pub bulk_threshold: usize,
+    /// Priority for full reindexes triggered by bulk changes
+    pub rebuild_priority: BuildPriority,
+    /// Read throughput limit (MB/s) for full reindexes; 0 = unlimited
+    pub io_limit_mb: u64,
+    /// Notification backend
+    pub mode: WatchMode,
+    /// Rescan interval when polling
+    pub poll_interval_ms: u64,
+}
+
+/// Start a file watcher thread that incrementally updates the in-memory index.
+///
+/// Every applied batch (incremental or full reindex) bumps `batches`, so a caller
+/// that persists the index can tell whether anything changed since its last save,
+/// and the MCP server can use it as the index generation. The same counter is returned.
 pub fn start_watcher(
-    index: Arc<RwLock<ContentIndex>>,
+    index: Searcher,
     def_index: Option<Arc<RwLock<DefinitionIndex>>>,
     dir: PathBuf,
     extensions: Vec<String>,
-    debounce_ms: u64,
-    bulk_threshold: usize,
     index_base: PathBuf,
-) -> notify::Result<()> {
+    options: WatcherOptions,
+    batches: Arc<Generation>,
+) -> notify::Result<Arc<Generation>> {
+    let WatcherOptions { debounce_ms, bulk_threshold, rebuild_priority, io_limit_mb, mode, poll_interval_ms } = options;
     let (tx, rx) = std::sync::mpsc::channel::<notify::Result<Event>>();
 
-    let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
-    watcher.watch(&dir, RecursiveMode::Recursive)?;
-
     let dir_str = clean_path(&dir.to_string_lossy());
 
-    info!(dir = %dir_str, debounce_ms, bulk_threshold, "File watcher started");
+    // The poller feeds synthetic events into the same channel, so batching below is shared
+    let poll = match mode {
+        WatchMode::Poll => true,
+        WatchMode::Native => false,
+        WatchMode::Auto => match remote_fs_type(&dir) {
+            Some(fs_type) => {
+                info!(dir = %dir_str, fs_type = %fs_type, "Network or container file system, polling for changes");
+                true
+            }
+            None => false,
+        },
+    };
+    let watcher = if poll {
+        None
+    } else {
+        let native = RecommendedWatcher::new(tx.clone(), Config::default())
+            .and_then(|mut w| w.watch(&dir, RecursiveMode::Recursive).map(|_| w));
+        match native {
+            Ok(w) => Some(w),
+            Err(e) if mode == WatchMode::Auto => {
+                warn!(error = %e, "Native file watcher unavailable, falling back to polling");
+                None
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    if watcher.is_none() {
+        spawn_poller(dir.clone(), extensions.clone(), Duration::from_millis(poll_interval_ms.max(100)),
+            Arc::clone(batches.clock()), tx);
+    }
+    let backend = if watcher.is_some() { "native" } else { "poll" };
+
+    info!(dir = %dir_str, backend, debounce_ms, bulk_threshold, %rebuild_priority, "File watcher started");
 
+    let batches_out = Arc::clone(&batches);
     std::thread::spawn(move || {
-        let _watcher = watcher; // move watcher into thread to keep it alive
+        let _watcher = watcher; // move watcher into thread to keep it alive (None when polling)
+        let mut retained = RetainedFiles::default();
         let mut dirty_files: HashSet<PathBuf> = HashSet::new();
         let mut removed_files: HashSet<PathBuf> = HashSet::new();
+        // (old path, new path or None if moved out of the tree), in event order
+        let mut renamed: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+        // Source half of a rename reported as two events, with its tracker (cookie)
+        let mut pending_from: Option<(PathBuf, Option<usize>)> = None;
+        // Same rules the index walk applies; reloaded when an ignore file changes
+        let mut ignore_rules = IgnoreRules::load(&dir);
+        let mut ignore_changed = false;
+        // HEAD at the last processed batch; a change means a checkout/pull happened
+        let mut last_head = crate::git::resolve_commit(&dir_str, "HEAD").ok();
 
         loop {
             match rx.recv_timeout(Duration::from_millis(debounce_ms)) {
                 Ok(Ok(event)) => {
+                    metrics::record_watcher_event();
+                    if event.paths.iter().any(|p| IgnoreRules::is_ignore_file(p)) {
+                        ignore_changed = true;
+                    }
+                    // Renames: inotify reports From, To, then Both; Windows reports From, then To
+                    if let EventKind::Modify(ModifyKind::Name(mode)) = event.kind {
+                        match (mode, event.paths.as_slice()) {
+                            (RenameMode::Both, [from, to]) => {
+                                queue_rename(&mut renamed, &mut dirty_files, &mut removed_files, &extensions, from.clone(), Some(to.clone()));
+                                continue;
+                            }
+                            (RenameMode::From, [from]) => {
+                                if let Some((orphan, _)) = pending_from.replace((from.clone(), event.tracker())) {
+                                    queue_rename(&mut renamed, &mut dirty_files, &mut removed_files, &extensions, orphan, None);
+                                }
+                                continue;
+                            }
+                            (RenameMode::To, [to]) => {
+                                match pending_from.take() {
+                                    Some((from, tracker)) if tracker == event.tracker() => {
+                                        queue_rename(&mut renamed, &mut dirty_files, &mut removed_files, &extensions, from, Some(to.clone()));
+                                    }
+                                    other => {
+                                        if let Some((orphan, _)) = other {
+                                            queue_rename(&mut renamed, &mut dirty_files, &mut removed_files, &extensions, orphan, None);
+                                        }
+                                        queue_moved_in(&mut dirty_files, &mut removed_files, to, &extensions);
+                                    }
+                                }
+                                continue;
+                            }
+                            // FSEvents reports each side of a rename as `Any` with no pairing
+                            (RenameMode::Any | RenameMode::Other, [path]) if !path.exists() => {
+                                queue_rename(&mut renamed, &mut dirty_files, &mut removed_files, &extensions, path.clone(), None);
+                                continue;
+                            }
+                            (RenameMode::Any | RenameMode::Other, [path]) if path.is_dir() => {
+                                queue_moved_in(&mut dirty_files, &mut removed_files, path, &extensions);
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
                     // Collect changed files
                     for path in &event.paths {
                         // Skip .git directory — git operations generate massive event floods
@@ -43,7 +182,10 @@ pub fn start_watcher(
                         if is_inside_git_dir(path) {
                             continue;
                         }
-                        if !matches_extensions(path, &extensions) {
+                        // A deleted extensionless file can no longer be detected; purging
+                        // a path that was never indexed is a no-op
+                        let removed_unknown = matches!(event.kind, EventKind::Remove(_)) && path.extension().is_none();
+                        if !removed_unknown && !matches_extensions(path, &extensions) {
                             continue;
                         }
                         match event.kind {
@@ -64,16 +206,76 @@ pub fn start_watcher(
                 }
                 Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
                     // Debounce window expired — process batch
-                    if dirty_files.is_empty() && removed_files.is_empty() {
+                    // A source with no destination by now was moved out of the tree
+                    if let Some((from, _)) = pending_from.take() {
+                        queue_rename(&mut renamed, &mut dirty_files, &mut removed_files, &extensions, from, None);
+                    }
+                    if ignore_changed {
+                        ignore_changed = false;
+                        ignore_rules = IgnoreRules::load(&dir);
+                        let mut wanted = poll_snapshot(&dir, &extensions);
+                        wanted.retain(|p, _| !ignore_rules.is_ignored(p, false));
+                        let (now_ignored, now_visible) = ignore_rule_changes(index.snapshot().path_to_id.as_ref(), &wanted);
+                        info!(removed = now_ignored.len(), added = now_visible.len(), "Ignore rules changed, syncing indexed files");
+                        for path in now_ignored {
+                            dirty_files.remove(&path);
+                            removed_files.insert(path);
+                        }
+                        for path in now_visible {
+                            removed_files.remove(&path);
+                            dirty_files.insert(path);
+                        }
+                    }
+                    // Build output, other ignored paths and paths outside the build's
+                    // --max-depth / --prune-dir never reach the index
+                    let walk_limits = index.snapshot().walk_limits.clone();
+                    dirty_files.retain(|p| !ignore_rules.is_ignored(p, false) && walk_limits.covers(&dir, p));
+                    for (_, to) in renamed.iter_mut() {
+                        if to.as_ref().is_some_and(|t| ignore_rules.is_ignored(t, t.is_dir()) || !walk_limits.covers(&dir, t)) {
+                            *to = None;
+                        }
+                    }
+                    if dirty_files.is_empty() && removed_files.is_empty() && renamed.is_empty() {
                         continue;
                     }
 
-                    let total_changes = dirty_files.len() + removed_files.len();
+                    let total_changes = dirty_files.len() + removed_files.len() + renamed.len();
+                    let head = crate::git::resolve_commit(&dir_str, "HEAD").ok();
+                    let switched_from = last_head.take().filter(|old| head.as_ref().is_some_and(|new| new != old));
+                    last_head = head.clone();
 
                     if total_changes > bulk_threshold {
+                        // Branch switch: git knows exactly which files changed, so update only
+                        // those unless they are a large share of the index anyway.
+                        let rename_paths: Vec<PathBuf> = renamed.iter()
+                            .flat_map(|(from, to)| std::iter::once(from.clone()).chain(to.clone()))
+                            .collect();
+                        if let (Some(old), Some(new)) = (&switched_from, &head)
+                            && let Some((removed, dirty)) = branch_switch_batch(
+                                &dir_str, old, new, &extensions, &ignore_rules, dirty_files.iter().chain(&removed_files).chain(&rename_paths))
+                        {
+                            let indexed = index.snapshot().files.len();
+                            if (removed.len() + dirty.len()) * 2 <= indexed {
+                                info!(changes = total_changes, updated = dirty.len(), removed = removed.len(),
+                                    old_head = %&old[..old.len().min(8)], new_head = %&new[..new.len().min(8)],
+                                    "HEAD moved, updating files changed between commits instead of full reindex");
+                                apply_incremental_batch(&index, def_index.as_ref(), &[], &removed, &dirty, &extensions, &mut retained);
+                                batches.bump();
+                                metrics::record_watcher_batch();
+                                dirty_files.clear();
+                                removed_files.clear();
+                                renamed.clear();
+                                continue;
+                            }
+                        }
+
                         // Too many changes — full reindex
                         info!(changes = total_changes, "Bulk threshold exceeded, triggering full reindex");
                         let ext_str = extensions.join(",");
+                        let (tokenizer, walk_limits, stop_token_pct, max_file_size) = {
+                            let current = index.snapshot();
+                            (current.tokenizer, current.walk_limits.clone(), current.stop_token_pct, current.max_file_size)
+                        };
                         let new_index = build_content_index(&ContentIndexArgs {
                             dir: dir_str.clone(),
                             ext: ext_str,
@@ -81,7 +283,21 @@ pub fn start_watcher(
                             hidden: false,
                             no_ignore: false,
                             threads: 0,
-                            min_token_len: DEFAULT_MIN_TOKEN_LEN,
+                            min_token_len: tokenizer.min_token_len,
+                            dry_run: false,
+                            build_priority: rebuild_priority,
+                            io_limit_mb,
+                            watch: false,
+                            save_interval_secs: 60,
+                            max_file_size,
+                            spill_mb: 0,
+                            shards: 0,
+                            max_token_len: tokenizer.max_token_len,
+                            stop_token_pct,
+                            checkpoint_secs: 0,
+                            max_depth: walk_limits.max_depth,
+                            prune_dir: walk_limits.prune_dirs.clone(),
+                            storage: None,
                         });
                         if let Err(e) = save_content_index(&new_index, &index_base) {
                             warn!(error = %e, "Failed to save reindexed content to disk");
@@ -106,87 +322,60 @@ pub fn start_watcher(
                                     hidden: false,
                                     no_ignore: false,
                                     threads: 0,
-                                    min_token_len: DEFAULT_MIN_TOKEN_LEN,
+                                    min_token_len: tokenizer.min_token_len,
+                                    dry_run: false,
+                                    build_priority: rebuild_priority,
+                                    io_limit_mb,
+                                    watch: false,
+                                    save_interval_secs: 60,
+                                    max_file_size,
+                                    spill_mb: 0,
+                                    shards: 0,
+                                    max_token_len: tokenizer.max_token_len,
+                                    stop_token_pct,
+                                    checkpoint_secs: 0,
+                                    max_depth: walk_limits.max_depth,
+                                    prune_dir: walk_limits.prune_dirs,
+                                    storage: None,
                                 })
                             }
                         };
 
                         // Build path_to_id for watch mode (no forward index — saves ~1.5 GB RAM)
                         let new_index = build_watch_index_from(new_index);
-                        match index.write() {
-                            Ok(mut idx) => *idx = new_index,
-                            Err(e) => error!(error = %e, "Failed to acquire content index write lock"),
-                        }
+                        index.replace(new_index);
+                        retained.forget_removed();
+                        batches.bump();
+                        metrics::record_watcher_batch();
                         dirty_files.clear();
                         removed_files.clear();
+                        renamed.clear();
                         continue;
                     }
 
                     // Incremental update — single write lock for entire batch
                     let update_count = dirty_files.len();
                     let remove_count = removed_files.len();
+                    let rename_count = renamed.len();
 
                     // Collect cleaned paths once for both indexes
                     let removed_clean: Vec<PathBuf> = removed_files.drain()
-                        .map(|p| PathBuf::from(clean_path(&p.to_string_lossy())))
+                        .map(|p| PathBuf::from(clean_path(&path_to_string(&p))))
                         .collect();
                     let dirty_clean: Vec<PathBuf> = dirty_files.drain()
-                        .map(|p| PathBuf::from(clean_path(&p.to_string_lossy())))
+                        .map(|p| PathBuf::from(clean_path(&path_to_string(&p))))
                         .collect();
+                    let renamed_clean: Vec<(PathBuf, Option<PathBuf>)> = renamed.drain(..)
+                        .map(|(from, to)| (
+                            PathBuf::from(clean_path(&path_to_string(&from))),
+                            to.map(|t| PathBuf::from(clean_path(&path_to_string(&t)))),
+                        ))
+                        .collect();
+                    apply_incremental_batch(&index, def_index.as_ref(), &renamed_clean, &removed_clean, &dirty_clean, &extensions, &mut retained);
+                    batches.bump();
+                    metrics::record_watcher_batch();
 
-                    // Update content index
-                    match index.write() {
-                        Ok(mut idx) => {
-                            for path in &removed_clean {
-                                remove_file_from_index(&mut idx, path);
-                            }
-                            for path in &dirty_clean {
-                                update_file_in_index(&mut idx, path);
-                            }
-                            // Mark trigram index as dirty — will be rebuilt lazily on next substring search
-                            idx.trigram_dirty = true;
-
-                            // Conditionally shrink collections after retain() to release excess capacity.
-                            // Only shrink when capacity > 2 × len to avoid unnecessary realloc storms.
-                            // retain() reduces len but not capacity — shrink_to_fit() reclaims
-                            // the dead allocations, which mimalloc/system allocator can return to OS.
-                            for postings in idx.index.values_mut() {
-                                if postings.capacity() > postings.len() * 2 {
-                                    postings.shrink_to_fit();
-                                }
-                            }
-                            if idx.index.capacity() > idx.index.len() * 2 {
-                                idx.index.shrink_to_fit();
-                            }
-                            if let Some(ref mut p2id) = idx.path_to_id {
-                                if p2id.capacity() > p2id.len() * 2 {
-                                    p2id.shrink_to_fit();
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!(error = %e, "Failed to acquire content index write lock");
-                        }
-                    }
-
-                    // Update definition index (if available)
-                    if let Some(ref def_idx) = def_index {
-                        match def_idx.write() {
-                            Ok(mut idx) => {
-                                for path in &removed_clean {
-                                    definitions::remove_file_from_def_index(&mut idx, path);
-                                }
-                                for path in &dirty_clean {
-                                    definitions::update_file_definitions(&mut idx, path);
-                                }
-                            }
-                            Err(e) => {
-                                error!(error = %e, "Failed to acquire definition index write lock");
-                            }
-                        }
-                    }
-
-                    info!(updated = update_count, removed = remove_count, "Incremental index update complete");
+                    info!(updated = update_count, removed = remove_count, renamed = rename_count, "Incremental index update complete");
                 }
                 Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
                     info!("Watcher channel disconnected, stopping");
@@ -196,7 +385,408 @@ pub fn start_watcher(
         }
     });
 
-    Ok(())
+    Ok(batches_out)
+}
+
+/// Size and mtime (seconds) of every watched file, keyed by path.
+type PollSnapshot = HashMap<PathBuf, (u64, u64)>;
+
+/// Start a thread that rescans `dir` every `interval` and sends the differences to
+/// `tx` as create/modify/remove events. `clock` dates each scan for [`diff_snapshots`].
+///
+/// Each scan is a [`crate::index::walk_file_entries`] walk (the same walk `search index`
+/// uses, honoring `.gitignore`), so the previous scan's entries are the baseline.
+/// The thread exits once the receiving side is gone.
+fn spawn_poller(dir: PathBuf, extensions: Vec<String>, interval: Duration, clock: Arc<dyn Clock>,
+    tx: std::sync::mpsc::Sender<notify::Result<Event>>) {
+    std::thread::spawn(move || {
+        let mut baseline = poll_snapshot(&dir, &extensions);
+        let mut baseline_secs = clock.now_secs();
+        loop {
+            std::thread::sleep(interval);
+            let current = poll_snapshot(&dir, &extensions);
+            let events = diff_snapshots(&baseline, &current, baseline_secs);
+            baseline = current;
+            baseline_secs = clock.now_secs();
+            for event in events {
+                if tx.send(Ok(event)).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+fn poll_snapshot(dir: &Path, extensions: &[String]) -> PollSnapshot {
+    let threads = resolve_threads(0, BuildPriority::Low);
+    crate::index::walk_file_entries(dir, false, false, threads, BuildPriority::Low, None)
+        .into_iter()
+        .filter(|e| !e.is_dir)
+        .map(|e| (path_from_string(&e.path), (e.size, e.modified)))
+        .filter(|(p, _)| !is_inside_git_dir(p) && matches_extensions(p, extensions))
+        .collect()
+}
+
+/// Events that turn `old` into `new`, at most one per kind.
+///
+/// Mtimes have one-second resolution, so a file whose mtime is not older than the
+/// second `old` was taken in (`old_secs`) is reported as modified again: a
+/// same-size edit later in that second would otherwise go unnoticed.
+fn diff_snapshots(old: &PollSnapshot, new: &PollSnapshot, old_secs: u64) -> Vec<Event> {
+    let mut created = Vec::new();
+    let mut modified = Vec::new();
+    for (path, &(size, mtime)) in new {
+        match old.get(path) {
+            None => created.push(path.clone()),
+            Some(&prev) if prev != (size, mtime) || mtime >= old_secs => modified.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    let removed: Vec<PathBuf> = old.keys().filter(|p| !new.contains_key(*p)).cloned().collect();
+
+    [
+        (EventKind::Create(CreateKind::File), created),
+        (EventKind::Modify(ModifyKind::Data(DataChange::Any)), modified),
+        (EventKind::Remove(RemoveKind::File), removed),
+    ].into_iter()
+        .filter(|(_, paths)| !paths.is_empty())
+        .map(|(kind, paths)| Event { kind, paths, attrs: Default::default() })
+        .collect()
+}
+
+/// File system type of `dir` when OS change notifications are unreliable on it:
+/// network shares and the bind mounts Docker Desktop, WSL and VMs use.
+fn remote_fs_type(dir: &Path) -> Option<String> {
+    let dir_str = dir.to_string_lossy();
+    // UNC paths (\\server\share, or \\?\UNC\ after canonicalize)
+    if (dir_str.starts_with("\\\\") && !dir_str.starts_with("\\\\?\\")) || dir_str.starts_with("\\\\?\\UNC\\") {
+        return Some("unc".to_string());
+    }
+    if cfg!(target_os = "linux") {
+        let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+        return mount_fs_type(&mounts, &dir_str).filter(|t| is_remote_fs_type(t));
+    }
+    None
+}
+
+/// File system type of the longest mount point containing `path`, from
+/// `/proc/self/mounts` content.
+fn mount_fs_type(mounts: &str, path: &str) -> Option<String> {
+    mounts.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            // Spaces and tabs in mount points are octal-escaped
+            let mount_point = fields.next()?.replace("\\040", " ").replace("\\011", "\t");
+            let fs_type = fields.next()?;
+            let inside = path == mount_point
+                || mount_point == "/"
+                || path.strip_prefix(mount_point.as_str()).is_some_and(|rest| rest.starts_with('/'));
+            inside.then(|| (mount_point.len(), fs_type.to_string()))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, fs_type)| fs_type)
+}
+
+fn is_remote_fs_type(fs_type: &str) -> bool {
+    matches!(fs_type,
+        "nfs" | "nfs4" | "cifs" | "smb3" | "smbfs" | "9p" | "drvfs" | "vboxsf" | "vmhgfs" | "fakeowner"
+        | "fuse.sshfs" | "fuse.grpcfuse" | "fuse.vmhgfs-fuse" | "fuse.osxfs")
+}
+
+/// Paths to remove and to update after HEAD moved from `old` to `new`.
+///
+/// Covers every file git reports as changed between the two commits plus any other
+/// path the watcher saw (local edits in the same debounce window, or files outside git).
+/// Each path is classified by whether it exists now and isn't ignored. Returns `None`
+/// if git fails.
+fn branch_switch_batch<'a>(
+    dir: &str,
+    old: &str,
+    new: &str,
+    extensions: &[String],
+    ignore_rules: &IgnoreRules,
+    seen: impl Iterator<Item = &'a PathBuf>,
+) -> Option<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let changed = match crate::git::changed_files_between(dir, old, new) {
+        Ok(files) => files,
+        Err(e) => {
+            warn!(error = %e, "git diff failed after HEAD change, falling back to full reindex");
+            return None;
+        }
+    };
+    let paths: HashSet<PathBuf> = changed.iter()
+        .map(|rel| Path::new(dir).join(rel))
+        .chain(seen.cloned())
+        .filter(|p| !is_inside_git_dir(p) && matches_extensions(p, extensions))
+        .map(|p| PathBuf::from(clean_path(&path_to_string(&p))))
+        .collect();
+    let (dirty, removed): (Vec<PathBuf>, Vec<PathBuf>) = paths.into_iter()
+        .partition(|p| {
+            let path = p.to_str().map_or_else(|| p.clone(), path_from_string);
+            path.exists() && !ignore_rules.is_ignored(&path, false)
+        });
+    Some((removed, dirty))
+}
+
+/// Indexed files the walk now skips, and walked files not indexed yet, after the
+/// ignore rules changed. `wanted` is a fresh walk with the new rules.
+fn ignore_rule_changes(indexed: Option<&HashMap<PathBuf, u32>>, wanted: &PollSnapshot) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let Some(indexed) = indexed else { return (Vec::new(), Vec::new()) };
+    let now_ignored = indexed.keys().filter(|p| !wanted.contains_key(*p)).cloned().collect();
+    let now_visible = wanted.keys().filter(|p| !indexed.contains_key(*p)).cloned().collect();
+    (now_ignored, now_visible)
+}
+
+/// Queue a rename of `from` (a file or a directory) to `to`, or out of the watched tree
+/// when `to` is `None`. Edits already queued under `from` follow it to `to`, and a
+/// queued removal under `to` is dropped because the rename recreated those paths.
+///
+/// Renames between two unwatched file names (build temp files) are skipped so they
+/// don't count toward the bulk threshold. A move out of the tree is always queued:
+/// the source is gone, so there is no telling whether it was a directory.
+fn queue_rename(
+    renamed: &mut Vec<(PathBuf, Option<PathBuf>)>,
+    dirty: &mut HashSet<PathBuf>,
+    removed: &mut HashSet<PathBuf>,
+    extensions: &[String],
+    from: PathBuf,
+    to: Option<PathBuf>,
+) {
+    if is_inside_git_dir(&from) || to.as_deref().is_some_and(is_inside_git_dir) {
+        return;
+    }
+    if let Some(ref to) = to
+        && !matches_extensions(&from, extensions) && !matches_extensions(to, extensions) && !to.is_dir()
+    {
+        return;
+    }
+    // inotify reports the same rename as From + To and again as Both
+    if renamed.last().is_some_and(|(f, t)| *f == from && *t == to) {
+        return;
+    }
+    let edited: Vec<PathBuf> = dirty.iter().filter(|p| p.starts_with(&from)).cloned().collect();
+    for path in edited {
+        dirty.remove(&path);
+        if let Some(ref to) = to {
+            dirty.insert(moved_path(&from, to, &path));
+        }
+    }
+    if let Some(ref to) = to {
+        removed.retain(|p| !p.starts_with(to));
+    }
+    renamed.push((from, to));
+}
+
+/// Queue a file or directory that appeared by being moved in from outside the tree.
+/// A directory's files are indexed as new files.
+fn queue_moved_in(dirty: &mut HashSet<PathBuf>, removed: &mut HashSet<PathBuf>, path: &Path, extensions: &[String]) {
+    if is_inside_git_dir(path) {
+        return;
+    }
+    let files: Vec<PathBuf> = if path.is_dir() {
+        poll_snapshot(path, extensions).into_keys().collect()
+    } else if matches_extensions(path, extensions) {
+        vec![path.to_path_buf()]
+    } else {
+        Vec::new()
+    };
+    for file in files {
+        removed.remove(&file);
+        dirty.insert(file);
+    }
+}
+
+/// Where `path`, at or under `from`, ends up after `from` is renamed to `to`.
+fn moved_path(from: &Path, to: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(from) {
+        Ok(rest) if !rest.as_os_str().is_empty() => PathBuf::from(clean_path(&path_to_string(&to.join(rest)))),
+        _ => to.to_path_buf(),
+    }
+}
+
+/// Apply renames to the content index without re-tokenizing: each indexed file at or
+/// under the old path keeps its file_id, postings and token count, and only its path
+/// changes. Returns the applied (old, new) file paths for the definition index.
+///
+/// Renames that can't be remapped are added to `removed` / `dirty` instead. An indexed
+/// file moved out of the tree or to an unwatched extension is removed. A source path
+/// the index doesn't know (e.g. `foo.cs.tmp` saved over `foo.cs`) is indexed as a change.
+fn remap_renamed_files(
+    index: &mut ContentIndex,
+    renamed: &[(PathBuf, Option<PathBuf>)],
+    extensions: &[String],
+    removed: &mut Vec<PathBuf>,
+    dirty: &mut Vec<PathBuf>,
+) -> Vec<(PathBuf, PathBuf)> {
+    let mut moves = Vec::new();
+    for (from, to) in renamed {
+        let Some(ref path_to_id) = index.path_to_id else { break };
+        let indexed: Vec<PathBuf> = path_to_id.keys().filter(|p| p.starts_with(from)).cloned().collect();
+        if indexed.is_empty() {
+            if let Some(to) = to {
+                if to.is_dir() {
+                    dirty.extend(poll_snapshot(to, extensions).into_keys());
+                } else if matches_extensions(to, extensions) {
+                    dirty.push(to.clone());
+                }
+            }
+            continue;
+        }
+        for old in indexed {
+            match to.as_ref().map(|to| moved_path(from, to, &old)).filter(|new| matches_extensions(new, extensions)) {
+                Some(new) => {
+                    rename_file_in_index(index, &old, &new);
+                    moves.push((old, new));
+                }
+                None => removed.push(old),
+            }
+        }
+    }
+    add_moved_files_to_dir_blooms(index, &moves);
+    moves
+}
+
+/// Add the tokens of files moved into another top-level directory to that directory's
+/// bloom filter, so scoped searches don't skip them. One scan of the inverted index.
+fn add_moved_files_to_dir_blooms(index: &mut ContentIndex, moves: &[(PathBuf, PathBuf)]) {
+    if index.dir_blooms.is_empty() {
+        return;
+    }
+    let Some(ref path_to_id) = index.path_to_id else { return };
+    let crossed: HashMap<u32, &PathBuf> = moves.iter()
+        .filter(|(old, new)| {
+            top_level_dir(&index.root, &path_to_string(old), false) != top_level_dir(&index.root, &path_to_string(new), false)
+        })
+        .filter_map(|(_, new)| path_to_id.get(new).map(|&id| (id, new)))
+        .collect();
+    if crossed.is_empty() {
+        return;
+    }
+    let mut tokens: HashMap<u32, Vec<Token>> = HashMap::new();
+    for (token, postings) in &index.index {
+        for p in postings.iter().filter(|p| crossed.contains_key(&p.file_id)) {
+            tokens.entry(p.file_id).or_default().push(Token::clone(token));
+        }
+    }
+    let paths: Vec<(String, Vec<Token>)> = tokens.into_iter()
+        .map(|(id, tokens)| (path_to_string(crossed[&id]), tokens))
+        .collect();
+    for (path, tokens) in &paths {
+        index.add_to_dir_bloom(path, tokens.iter().map(|t| &**t));
+    }
+}
+
+/// Move an indexed file from `old` to `new`, keeping its file_id. A file previously
+/// indexed at `new` is removed first (the rename replaced it).
+fn rename_file_in_index(index: &mut ContentIndex, old: &Path, new: &Path) {
+    if old != new {
+        remove_file_from_index(index, new, true);
+    }
+    let Some(ref mut path_to_id) = index.path_to_id else { return };
+    let Some(file_id) = path_to_id.remove(old) else { return };
+    path_to_id.insert(new.to_path_buf(), file_id);
+    if let Some(slot) = index.files.get_mut(file_id as usize) {
+        *slot = path_to_string(new);
+    }
+}
+
+/// Apply one batch of renames, removals and updates to the content index and, if
+/// present, the definition index, each in one step: content index snapshots see the
+/// whole batch or none of it. Renames go first, so later edits in the same batch find
+/// the file under its new path. Removed files go into `retained`, and files it holds
+/// come back from there when they reappear unchanged.
+fn apply_incremental_batch(
+    index: &Searcher,
+    def_index: Option<&Arc<RwLock<DefinitionIndex>>>,
+    renamed: &[(PathBuf, Option<PathBuf>)],
+    removed: &[PathBuf],
+    dirty: &[PathBuf],
+    extensions: &[String],
+    retained: &mut RetainedFiles,
+) {
+    let mut removed = removed.to_vec();
+    let mut dirty = dirty.to_vec();
+    let mut moves = Vec::new();
+
+    // Update content index
+    index.update(|idx| {
+        if !renamed.is_empty() {
+            moves = remap_renamed_files(idx, renamed, extensions, &mut removed, &mut dirty);
+        }
+        // One purge pass for the whole batch (a branch switch can touch thousands of files)
+        let (known, keep): (HashSet<u32>, HashSet<u32>) = match idx.path_to_id {
+            Some(ref p2id) => (
+                removed.iter().chain(&dirty).filter_map(|p| p2id.get(p).copied()).collect(),
+                removed.iter().filter(|p| retained.can_retain(p)).filter_map(|p| p2id.get(p).copied()).collect(),
+            ),
+            None => (HashSet::new(), HashSet::new()),
+        };
+        let mut kept = if known.is_empty() {
+            HashMap::new()
+        } else {
+            purge_files_from_inverted_index(&mut idx.index, &known, &keep)
+        };
+        for path in &removed {
+            if let Some(&file_id) = idx.path_to_id.as_ref().and_then(|p2id| p2id.get(path))
+                && let Some(postings) = kept.remove(&file_id)
+            {
+                let token_count = idx.file_token_counts.get(file_id as usize).copied().unwrap_or(0);
+                retained.retain(path, file_id, token_count, postings);
+            }
+            remove_file_from_index(idx, path, false);
+        }
+        for path in &dirty {
+            if restore_file(idx, path, retained) {
+                continue;
+            }
+            if let Some(hash) = update_file_in_index(idx, path, false) {
+                retained.remember(path, hash);
+            }
+        }
+        // Mark trigram index as dirty — will be rebuilt lazily on next substring search
+        idx.trigram_dirty = true;
+
+        // Conditionally shrink collections after retain() to release excess capacity.
+        // Only shrink when capacity > 2 × len to avoid unnecessary realloc storms.
+        // retain() reduces len but not capacity — shrink_to_fit() reclaims
+        // the dead allocations, which mimalloc/system allocator can return to OS.
+        for postings in idx.index.values_mut() {
+            if postings.capacity() > postings.len() * 2 {
+                postings.shrink_to_fit();
+            }
+        }
+        if idx.index.capacity() > idx.index.len() * 2 {
+            idx.index.shrink_to_fit();
+        }
+        if let Some(ref mut p2id) = idx.path_to_id {
+            if p2id.capacity() > p2id.len() * 2 {
+                p2id.shrink_to_fit();
+            }
+        }
+    });
+
+    // Update definition index (if available)
+    if let Some(def_idx) = def_index {
+        match def_idx.write() {
+            Ok(mut idx) => {
+                for (old, new) in &moves {
+                    if !definitions::rename_file_in_def_index(&mut idx, old, new) {
+                        definitions::update_file_definitions(&mut idx, new);
+                    }
+                }
+                for path in &removed {
+                    definitions::remove_file_from_def_index(&mut idx, path);
+                }
+                for path in &dirty {
+                    definitions::update_file_definitions(&mut idx, path);
+                }
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to acquire definition index write lock");
+            }
+        }
+    }
 }
 
 /// Check if a path is inside a `.git` directory.
@@ -206,10 +796,11 @@ fn is_inside_git_dir(path: &Path) -> bool {
     path.components().any(|c| c.as_os_str() == ".git")
 }
 
+/// Whether `path` is indexed under `extensions`. Extensionless files are matched by
+/// their detected language; `[languages]` overrides apply on the next full rebuild.
 fn matches_extensions(path: &Path, extensions: &[String]) -> bool {
-    path.extension()
-        .and_then(|e| e.to_str())
-        .is_some_and(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+    crate::language::file_language(path)
+        .is_some_and(|lang| extensions.iter().any(|x| x.eq_ignore_ascii_case(&lang)))
 }
 
 /// Build a ContentIndex with path_to_id populated (for watch mode).
@@ -235,14 +826,28 @@ pub fn build_watch_index_from(mut index: ContentIndex) -> ContentIndex {
 ///
 /// Uses brute-force scan of the inverted index to remove old postings for the file,
 /// avoiding the need for a forward index (which consumed ~1.5 GB of RAM).
-fn update_file_in_index(index: &mut ContentIndex, path: &Path) {
-    let path_str = path.to_string_lossy().to_string();
+/// With `purge == false` the caller has already removed the file's old postings
+/// (see [`apply_incremental_batch`]). Returns the hash of the content indexed.
+fn update_file_in_index(index: &mut ContentIndex, path: &Path, purge: bool) -> Option<u64> {
+    let path_str = path_to_string(path);
+
+    // A file that grew past the build's size limit leaves the index, as does one that
+    // turned binary below
+    if index.max_file_size > 0 && std::fs::metadata(path).is_ok_and(|m| m.len() > index.max_file_size) {
+        remove_file_from_index(index, path, purge);
+        return None;
+    }
 
     // Read the file (lossy UTF-8 for non-UTF8 files like Windows-1252)
     let (content, _was_lossy) = match crate::read_file_lossy(path) {
         Ok(r) => r,
-        Err(_) => return, // File might have been deleted between event and processing
+        Err(_) => return None, // File might have been deleted between event and processing
     };
+    // A file that turned binary leaves the index, like the build would have skipped it
+    if looks_binary(&content) {
+        remove_file_from_index(index, path, purge);
+        return None;
+    }
 
     if let Some(ref mut path_to_id) = index.path_to_id {
         if let Some(&file_id) = path_to_id.get(path) {
@@ -257,13 +862,15 @@ fn update_file_in_index(index: &mut ContentIndex, path: &Path) {
 
             // Remove all postings for this file_id from the inverted index (brute-force scan).
             // This replaces the forward index lookup — O(total_tokens) but saves ~1.5 GB RAM.
-            purge_file_from_inverted_index(&mut index.index, file_id);
+            if purge {
+                purge_file_from_inverted_index(&mut index.index, file_id);
+            }
 
             // Re-tokenize file
             let mut file_tokens: std::collections::HashMap<String, Vec<u32>> = std::collections::HashMap::new();
             let mut file_total: u32 = 0;
             for (line_num, line) in content.lines().enumerate() {
-                for token in tokenize(line, DEFAULT_MIN_TOKEN_LEN) {
+                for token in index.tokenizer.tokenize(line) {
                     index.total_tokens += 1;
                     file_total += 1;
                     file_tokens.entry(token).or_default().push((line_num + 1) as u32);
@@ -272,9 +879,7 @@ fn update_file_in_index(index: &mut ContentIndex, path: &Path) {
 
             // Add new tokens to inverted index
             for (token, lines) in &file_tokens {
-                index.index.entry(token.clone())
-                    .or_default()
-                    .push(Posting { file_id, lines: lines.clone() });
+                add_posting(&mut index.index, token, Posting { file_id, lines: lines.clone() });
             }
 
             // Update file token count
@@ -283,6 +888,10 @@ fn update_file_in_index(index: &mut ContentIndex, path: &Path) {
             } else {
                 warn!(file_id, len = index.file_token_counts.len(), "file_token_counts out of bounds, TF-IDF scores may be stale");
             }
+            replace_comment_markers(&mut index.comment_markers, file_id, Some(&content));
+            replace_ticket_refs(&mut index.ticket_refs, file_id, Some(&content));
+            // Blooms only gain tokens; ones the file no longer has just cost false positives
+            index.add_to_dir_bloom(&path_str, file_tokens.keys().map(String::as_str));
         } else {
             // NEW FILE — assign new file_id
             let file_id = index.files.len() as u32;
@@ -292,7 +901,7 @@ fn update_file_in_index(index: &mut ContentIndex, path: &Path) {
             let mut file_tokens: std::collections::HashMap<String, Vec<u32>> = std::collections::HashMap::new();
             let mut file_total: u32 = 0;
             for (line_num, line) in content.lines().enumerate() {
-                for token in tokenize(line, DEFAULT_MIN_TOKEN_LEN) {
+                for token in index.tokenizer.tokenize(line) {
                     index.total_tokens += 1;
                     file_total += 1;
                     file_tokens.entry(token).or_default().push((line_num + 1) as u32);
@@ -300,12 +909,146 @@ fn update_file_in_index(index: &mut ContentIndex, path: &Path) {
             }
 
             for (token, lines) in &file_tokens {
-                index.index.entry(token.clone())
-                    .or_default()
-                    .push(Posting { file_id, lines: lines.clone() });
+                add_posting(&mut index.index, token, Posting { file_id, lines: lines.clone() });
             }
 
             index.file_token_counts.push(file_total);
+            replace_comment_markers(&mut index.comment_markers, file_id, Some(&content));
+            replace_ticket_refs(&mut index.ticket_refs, file_id, Some(&content));
+            // Blooms only gain tokens; ones the file no longer has just cost false positives
+            index.add_to_dir_bloom(&path_str, file_tokens.keys().map(String::as_str));
+        }
+    }
+    Some(content_hash(&content))
+}
+
+/// Put a removed file back from `retained` if it reappeared with the content it was
+/// removed with: its postings, token count and file id return without tokenizing.
+/// Returns false when the file has to be indexed from its content.
+fn restore_file(index: &mut ContentIndex, path: &Path, retained: &mut RetainedFiles) -> bool {
+    if index.path_to_id.as_ref().is_none_or(|p2id| p2id.contains_key(path)) {
+        return false;
+    }
+    let Some(file) = retained.take(path) else { return false };
+    // A full rebuild since the removal may have given the file id to another file
+    let id = file.file_id as usize;
+    if index.files.get(id).is_none_or(|f| *f != path_to_string(path)) || index.file_token_counts.get(id) != Some(&0) {
+        return false;
+    }
+    let Ok((content, _)) = crate::read_file_lossy(path) else { return false };
+    if content_hash(&content) != file.hash {
+        return false;
+    }
+    for (token, posting) in file.postings {
+        add_posting(&mut index.index, &token, posting);
+    }
+    index.file_token_counts[id] = file.token_count;
+    index.total_tokens += file.token_count as u64;
+    replace_comment_markers(&mut index.comment_markers, file.file_id, Some(&content));
+    replace_ticket_refs(&mut index.ticket_refs, file.file_id, Some(&content));
+    if let Some(ref mut p2id) = index.path_to_id {
+        p2id.insert(path.to_path_buf(), file.file_id);
+    }
+    retained.remember(path, file.hash);
+    true
+}
+
+fn content_hash(content: &str) -> u64 {
+    search::stable_hash(&[content.as_bytes()])
+}
+
+/// Content hashes of files the watcher has indexed
+const MAX_KNOWN_HASHES: usize = 4096;
+/// Removed files whose postings are kept for a restore
+const MAX_RETAINED_FILES: usize = 32;
+
+/// Postings of recently removed files, so that an undo in the editor (delete, then
+/// restore) puts the file back without tokenizing it again. Only files the watcher
+/// indexed itself can be kept: their content hash tells whether the restored file
+/// is the one removed. Least recently removed files are dropped first.
+#[derive(Default)]
+struct RetainedFiles {
+    hashes: HashMap<PathBuf, u64>,
+    /// Paths in `hashes`, oldest first
+    hash_order: VecDeque<PathBuf>,
+    removed: VecDeque<RemovedFile>,
+}
+
+struct RemovedFile {
+    path: PathBuf,
+    hash: u64,
+    file_id: u32,
+    token_count: u32,
+    postings: Vec<(Token, Posting)>,
+}
+
+impl RetainedFiles {
+    /// Note the hash of the content just indexed for `path`.
+    fn remember(&mut self, path: &Path, hash: u64) {
+        if self.hashes.insert(path.to_path_buf(), hash).is_none() {
+            self.hash_order.push_back(path.to_path_buf());
+        }
+        while self.hashes.len() > MAX_KNOWN_HASHES
+            && let Some(oldest) = self.hash_order.pop_front()
+        {
+            self.hashes.remove(&oldest);
+        }
+    }
+
+    fn can_retain(&self, path: &Path) -> bool {
+        self.hashes.contains_key(path)
+    }
+
+    /// Keep the postings of a file being removed.
+    fn retain(&mut self, path: &Path, file_id: u32, token_count: u32, postings: Vec<(Token, Posting)>) {
+        let Some(&hash) = self.hashes.get(path) else { return };
+        self.removed.retain(|f| f.path != path);
+        if self.removed.len() == MAX_RETAINED_FILES {
+            self.removed.pop_front();
+        }
+        self.removed.push_back(RemovedFile { path: path.to_path_buf(), hash, file_id, token_count, postings });
+    }
+
+    fn take(&mut self, path: &Path) -> Option<RemovedFile> {
+        let i = self.removed.iter().position(|f| f.path == path)?;
+        self.removed.remove(i)
+    }
+
+    /// Drop kept postings, after a full reindex gave out new file ids.
+    fn forget_removed(&mut self) {
+        self.removed.clear();
+        self.hashes.clear();
+        self.hash_order.clear();
+    }
+}
+
+/// Drop a file's TODO/FIXME/HACK/BUG markers and, if `content` is given, re-extract them.
+/// Keeps `markers` ordered by file_id so output order matches a fresh build.
+fn replace_comment_markers(markers: &mut Vec<CommentMarker>, file_id: u32, content: Option<&str>) {
+    markers.retain(|m| m.file_id != file_id);
+    let Some(content) = content else { return };
+    let at = markers.partition_point(|m| m.file_id < file_id);
+    let fresh = extract_comment_markers(content).into_iter()
+        .map(|(line, marker, text)| CommentMarker { file_id, line, marker: marker.to_string(), text });
+    markers.splice(at..at, fresh);
+}
+
+/// Same as [`replace_comment_markers`] for ticket references.
+fn replace_ticket_refs(refs: &mut Vec<TicketRef>, file_id: u32, content: Option<&str>) {
+    refs.retain(|r| r.file_id != file_id);
+    let Some(content) = content else { return };
+    let at = refs.partition_point(|r| r.file_id < file_id);
+    let fresh = extract_ticket_refs(content).into_iter().map(|(line, ticket)| TicketRef { file_id, line, ticket });
+    refs.splice(at..at, fresh);
+}
+
+/// Append a posting to `token`'s list. The key is only allocated for a token the
+/// index has not seen yet.
+fn add_posting(inverted: &mut HashMap<Token, Vec<Posting>>, token: &str, posting: Posting) {
+    match inverted.get_mut(token) {
+        Some(postings) => postings.push(posting),
+        None => {
+            inverted.insert(Token::from(token), vec![posting]);
         }
     }
 }
@@ -314,7 +1057,7 @@ fn update_file_in_index(index: &mut ContentIndex, path: &Path) {
 /// This is a brute-force O(total_tokens) scan that replaces the forward index lookup.
 /// Typically takes ~50-100ms for 400K tokens, which is acceptable for watcher events.
 fn purge_file_from_inverted_index(
-    inverted: &mut std::collections::HashMap<String, Vec<Posting>>,
+    inverted: &mut HashMap<Token, Vec<Posting>>,
     file_id: u32,
 ) {
     inverted.retain(|_token, postings| {
@@ -323,11 +1066,37 @@ fn purge_file_from_inverted_index(
     });
 }
 
+/// Remove all postings for any of `file_ids` in one scan of the inverted index.
+/// Lets a batch of N changed files cost one O(total_tokens) pass instead of N.
+/// The removed postings of files in `keep` are returned, with their tokens.
+fn purge_files_from_inverted_index(
+    inverted: &mut HashMap<Token, Vec<Posting>>,
+    file_ids: &HashSet<u32>,
+    keep: &HashSet<u32>,
+) -> HashMap<u32, Vec<(Token, Posting)>> {
+    let mut kept: HashMap<u32, Vec<(Token, Posting)>> = HashMap::new();
+    inverted.retain(|token, postings| {
+        postings.retain(|p| {
+            if !file_ids.contains(&p.file_id) {
+                return true;
+            }
+            if keep.contains(&p.file_id) {
+                kept.entry(p.file_id).or_default().push((token.clone(), p.clone()));
+            }
+            false
+        });
+        !postings.is_empty()
+    });
+    kept
+}
+
 /// Remove a file from the index.
 ///
 /// Uses brute-force scan of the inverted index instead of forward index lookup,
 /// saving ~1.5 GB of RAM at the cost of ~50-100ms per file removal.
-fn remove_file_from_index(index: &mut ContentIndex, path: &Path) {
+/// With `purge == false` the caller has already removed the file's postings
+/// (see [`apply_incremental_batch`]).
+fn remove_file_from_index(index: &mut ContentIndex, path: &Path, purge: bool) {
     if let Some(ref mut path_to_id) = index.path_to_id
         && let Some(&file_id) = path_to_id.get(path) {
             // Subtract this file's token count from total
@@ -343,7 +1112,11 @@ fn remove_file_from_index(index: &mut ContentIndex, path: &Path) {
             }
 
             // Remove all postings for this file from inverted index (brute-force scan)
-            purge_file_from_inverted_index(&mut index.index, file_id);
+            if purge {
+                purge_file_from_inverted_index(&mut index.index, file_id);
+            }
+            replace_comment_markers(&mut index.comment_markers, file_id, None);
+            replace_ticket_refs(&mut index.ticket_refs, file_id, None);
 
             path_to_id.remove(path);
             // Don't remove from files vec to preserve file_id stability
@@ -353,16 +1126,17 @@ fn remove_file_from_index(index: &mut ContentIndex, path: &Path) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{SkippedFiles, TokenizerSettings};
     use std::collections::HashMap;
     use crate::TrigramIndex;
 
     fn make_test_index() -> ContentIndex {
         let mut idx = HashMap::new();
-        idx.insert("httpclient".to_string(), vec![Posting {
+        idx.insert("httpclient".into(), vec![Posting {
             file_id: 0,
             lines: vec![5, 12],
         }]);
-        idx.insert("ilogger".to_string(), vec![Posting {
+        idx.insert("ilogger".into(), vec![Posting {
             file_id: 0,
             lines: vec![3],
         }, Posting {
@@ -383,6 +1157,15 @@ mod tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: None,
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         }
     }
 
@@ -422,7 +1205,7 @@ mod tests {
         }
 
         let clean_path = PathBuf::from(crate::clean_path(&new_file.to_string_lossy()));
-        update_file_in_index(&mut index, &clean_path);
+        update_file_in_index(&mut index, &clean_path, true);
 
         // New file should be added
         assert_eq!(index.files.len(), 3);
@@ -446,8 +1229,8 @@ mod tests {
             files: vec![clean.clone()],
             index: {
                 let mut m = HashMap::new();
-                m.insert("original".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-                m.insert("oldtoken".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
+                m.insert("original".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+                m.insert("oldtoken".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
                 m
             },
             total_tokens: 10,
@@ -461,11 +1244,20 @@ mod tests {
                 m.insert(PathBuf::from(&clean), 0u32);
                 m
             }),
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
 
         // Now update the file content
         std::fs::write(&test_file, "class Updated { NewToken stuff; }").unwrap();
-        update_file_in_index(&mut index, &PathBuf::from(&clean));
+        update_file_in_index(&mut index, &PathBuf::from(&clean), true);
 
         // Old tokens should be gone, new tokens should be present
         assert!(!index.index.contains_key("original"), "old token 'original' should be removed");
@@ -474,6 +1266,24 @@ mod tests {
         assert!(index.index.contains_key("newtoken"), "new token 'newtoken' should be present");
     }
 
+    #[test]
+    fn test_incremental_update_skips_file_over_size_limit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let big_file = dir.join("big.cs");
+        std::fs::write(&big_file, format!("class BigClass {{ {} }}", "x".repeat(200))).unwrap();
+
+        let mut index = build_watch_index_from(make_test_index());
+        index.max_file_size = 64;
+
+        let clean_path = PathBuf::from(crate::clean_path(&big_file.to_string_lossy()));
+        update_file_in_index(&mut index, &clean_path, true);
+
+        assert!(!index.index.contains_key("bigclass"), "file over the size limit should not be indexed");
+        assert!(!index.path_to_id.as_ref().unwrap().contains_key(&clean_path));
+    }
+
     #[test]
     fn test_remove_file() {
         let mut index = make_test_index();
@@ -481,7 +1291,7 @@ mod tests {
         index = build_watch_index_from(index);
 
         // Remove file0.cs
-        remove_file_from_index(&mut index, &PathBuf::from("file0.cs"));
+        remove_file_from_index(&mut index, &PathBuf::from("file0.cs"), true);
 
         // httpclient was only in file0 — should be gone from index
         assert!(!index.index.contains_key("httpclient"), "httpclient should be removed with file0");
@@ -507,6 +1317,43 @@ mod tests {
         assert!(!matches_extensions(Path::new("no_ext"), &exts));
     }
 
+    #[test]
+    fn test_diff_snapshots_reports_create_modify_remove() {
+        let snap = |items: &[(&str, u64, u64)]| -> PollSnapshot {
+            items.iter().map(|&(p, size, mtime)| (PathBuf::from(p), (size, mtime))).collect()
+        };
+        let old = snap(&[("/r/a.cs", 10, 100), ("/r/b.cs", 20, 100), ("/r/c.cs", 30, 100), ("/r/d.cs", 5, 150)]);
+        let new = snap(&[("/r/a.cs", 10, 100), ("/r/b.cs", 21, 160), ("/r/d.cs", 5, 150), ("/r/e.cs", 1, 160)]);
+        let events = diff_snapshots(&old, &new, 150);
+        let paths = |kind: fn(&EventKind) -> bool| -> Vec<PathBuf> {
+            let mut p: Vec<PathBuf> = events.iter().filter(|e| kind(&e.kind)).flat_map(|e| e.paths.clone()).collect();
+            p.sort();
+            p
+        };
+        assert_eq!(paths(|k| matches!(k, EventKind::Create(_))), vec![PathBuf::from("/r/e.cs")]);
+        // d.cs is unchanged but its mtime second was still open when `old` was taken
+        assert_eq!(paths(|k| matches!(k, EventKind::Modify(_))), vec![PathBuf::from("/r/b.cs"), PathBuf::from("/r/d.cs")]);
+        assert_eq!(paths(|k| matches!(k, EventKind::Remove(_))), vec![PathBuf::from("/r/c.cs")]);
+
+        assert!(diff_snapshots(&new, &new, 200).is_empty());
+    }
+
+    #[test]
+    fn test_mount_fs_type_picks_longest_mount() {
+        let mounts = "\
+overlay / overlay rw,relatime 0 0
+proc /proc proc rw 0 0
+//nas/share /mnt/share cifs rw,vers=3.0 0 0
+grpcfuse /workspace/my\\040repo fuse.grpcfuse rw 0 0
+";
+        assert_eq!(mount_fs_type(mounts, "/mnt/share/src").as_deref(), Some("cifs"));
+        assert_eq!(mount_fs_type(mounts, "/mnt/share").as_deref(), Some("cifs"));
+        assert_eq!(mount_fs_type(mounts, "/mnt/shared").as_deref(), Some("overlay"));
+        assert_eq!(mount_fs_type(mounts, "/workspace/my repo/src").as_deref(), Some("fuse.grpcfuse"));
+        assert!(is_remote_fs_type("cifs") && is_remote_fs_type("fuse.grpcfuse"));
+        assert!(!is_remote_fs_type("overlay") && !is_remote_fs_type("ext4"));
+    }
+
     #[test]
     fn test_is_inside_git_dir() {
         // Should detect .git directory in various positions
@@ -527,14 +1374,14 @@ mod tests {
     #[test]
     fn test_purge_file_from_inverted_index_removes_single_file() {
         let mut inverted = HashMap::new();
-        inverted.insert("token_a".to_string(), vec![
+        inverted.insert("token_a".into(), vec![
             Posting { file_id: 0, lines: vec![1, 5] },
             Posting { file_id: 1, lines: vec![3] },
         ]);
-        inverted.insert("token_b".to_string(), vec![
+        inverted.insert("token_b".into(), vec![
             Posting { file_id: 0, lines: vec![2] },
         ]);
-        inverted.insert("token_c".to_string(), vec![
+        inverted.insert("token_c".into(), vec![
             Posting { file_id: 1, lines: vec![10] },
         ]);
 
@@ -556,7 +1403,7 @@ mod tests {
     #[test]
     fn test_purge_file_from_inverted_index_nonexistent_file() {
         let mut inverted = HashMap::new();
-        inverted.insert("token".to_string(), vec![
+        inverted.insert("token".into(), vec![
             Posting { file_id: 0, lines: vec![1] },
         ]);
 
@@ -569,7 +1416,7 @@ mod tests {
 
     #[test]
     fn test_purge_file_from_inverted_index_empty_index() {
-        let mut inverted: HashMap<String, Vec<Posting>> = HashMap::new();
+        let mut inverted: HashMap<Token, Vec<Posting>> = HashMap::new();
         purge_file_from_inverted_index(&mut inverted, 0);
         assert!(inverted.is_empty());
     }
@@ -606,7 +1453,7 @@ mod tests {
             m
         });
 
-        remove_file_from_index(&mut index, &PathBuf::from("file0.cs"));
+        remove_file_from_index(&mut index, &PathBuf::from("file0.cs"), true);
 
         // httpclient was only in file0 — should be gone
         assert!(!index.index.contains_key("httpclient"));
@@ -632,8 +1479,8 @@ mod tests {
             files: vec![clean.clone()],
             index: {
                 let mut m = HashMap::new();
-                m.insert("original".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-                m.insert("oldtoken".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
+                m.insert("original".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+                m.insert("oldtoken".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
                 m
             },
             total_tokens: 10,
@@ -647,11 +1494,20 @@ mod tests {
                 m.insert(PathBuf::from(&clean), 0u32);
                 m
             }),
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
 
         // Update file content
         std::fs::write(&test_file, "class Updated { NewToken stuff; }").unwrap();
-        update_file_in_index(&mut index, &PathBuf::from(&clean));
+        update_file_in_index(&mut index, &PathBuf::from(&clean), true);
 
         // Old tokens removed via brute-force scan, new tokens added
         assert!(!index.index.contains_key("original"), "old token should be removed");
@@ -660,6 +1516,92 @@ mod tests {
         assert!(index.index.contains_key("newtoken"), "new token should be present");
     }
 
+    #[test]
+    fn test_watcher_keeps_comment_markers_in_sync() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_a = tmp.path().join("a.rs");
+        let file_b = tmp.path().join("b.rs");
+        std::fs::write(&file_a, "// TODO: first\nfn a() {}\n").unwrap();
+        std::fs::write(&file_b, "fn b() {} // FIXME: later\n").unwrap();
+
+        let mut index = make_test_index();
+        index.files.clear();
+        index.file_token_counts.clear();
+        index.path_to_id = Some(HashMap::new());
+        update_file_in_index(&mut index, &file_a, true);
+        update_file_in_index(&mut index, &file_b, true);
+        let summary = |idx: &ContentIndex| idx.comment_markers.iter()
+            .map(|m| (m.file_id, m.line, m.marker.clone())).collect::<Vec<_>>();
+        assert_eq!(summary(&index), vec![(0, 1, "TODO".to_string()), (1, 1, "FIXME".to_string())]);
+
+        // Re-extracted in place, so order stays by file_id
+        std::fs::write(&file_a, "fn a() {}\n// HACK: one\n// BUG: two\n").unwrap();
+        update_file_in_index(&mut index, &file_a, true);
+        assert_eq!(summary(&index), vec![
+            (0, 2, "HACK".to_string()), (0, 3, "BUG".to_string()), (1, 1, "FIXME".to_string()),
+        ]);
+
+        remove_file_from_index(&mut index, &file_a, true);
+        assert_eq!(summary(&index), vec![(1, 1, "FIXME".to_string())]);
+    }
+
+    #[test]
+    fn test_branch_switch_updates_only_changed_files() {
+        use std::process::Command;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = clean_path(&tmp.path().to_string_lossy());
+        let git = |args: &[&str]| {
+            let output = Command::new("git").args(args).current_dir(tmp.path())
+                .env("GIT_CONFIG_GLOBAL", "/dev/null").env("GIT_CONFIG_SYSTEM", "/dev/null")
+                .output().expect("git");
+            assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        let commit = |msg: &str| git(&["-c", "user.name=Ada", "-c", "user.email=ada@example.com", "commit", "-q", "-m", msg]);
+        git(&["init", "-q", "-b", "main"]);
+        std::fs::write(tmp.path().join("a.rs"), "fn mainline_a() {}\n").unwrap();
+        std::fs::write(tmp.path().join("b.rs"), "fn mainline_b() {}\n").unwrap();
+        std::fs::write(tmp.path().join("c.rs"), "fn untouched_c() {}\n").unwrap();
+        git(&["add", "."]);
+        commit("main");
+        let old = git(&["rev-parse", "HEAD"]);
+        git(&["checkout", "-q", "-b", "feature"]);
+        std::fs::write(tmp.path().join("a.rs"), "fn feature_a() {}\n").unwrap();
+        std::fs::remove_file(tmp.path().join("b.rs")).unwrap();
+        std::fs::write(tmp.path().join("d.rs"), "fn feature_d() {}\n").unwrap();
+        std::fs::write(tmp.path().join("notes.md"), "ignored\n").unwrap();
+        git(&["add", "-A"]);
+        commit("feature");
+        let new = git(&["rev-parse", "HEAD"]);
+        git(&["checkout", "-q", "main"]);
+
+        let mut index = make_test_index();
+        index.files.clear();
+        index.file_token_counts.clear();
+        index.index.clear();
+        index.path_to_id = Some(HashMap::new());
+        for f in ["a.rs", "b.rs", "c.rs"] {
+            update_file_in_index(&mut index, &PathBuf::from(format!("{}/{}", dir, f)), true);
+        }
+
+        git(&["checkout", "-q", "feature"]);
+        let (removed, mut dirty) = branch_switch_batch(&dir, &old, &new, &["rs".to_string()], &IgnoreRules::load(tmp.path()), std::iter::empty()).unwrap();
+        dirty.sort();
+        assert_eq!(removed, vec![PathBuf::from(format!("{}/b.rs", dir))]);
+        assert_eq!(dirty, vec![PathBuf::from(format!("{}/a.rs", dir)), PathBuf::from(format!("{}/d.rs", dir))]);
+
+        let index = Searcher::new(index);
+        apply_incremental_batch(&index, None, &[], &removed, &dirty, &["rs".to_string()], &mut RetainedFiles::default());
+        let idx = index.snapshot();
+        let files_with = |token: &str| idx.index.get(token).map(|p| p.len()).unwrap_or(0);
+        assert_eq!(files_with("feature_a"), 1);
+        assert_eq!(files_with("feature_d"), 1);
+        assert_eq!(files_with("untouched_c"), 1);
+        assert_eq!(files_with("mainline_a"), 0);
+        assert_eq!(files_with("mainline_b"), 0);
+    }
+
     #[test]
     fn test_bulk_threshold_concept() {
         // Verify the threshold logic: if changes > threshold, we'd do full reindex
@@ -687,10 +1629,10 @@ mod tests {
             files: vec![clean.clone()],
             index: {
                 let mut m = HashMap::new();
-                m.insert("original".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-                m.insert("oldtoken".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-                m.insert("stuff".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
-                m.insert("class".to_string(), vec![Posting { file_id: 0, lines: vec![1] }]);
+                m.insert("original".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+                m.insert("oldtoken".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+                m.insert("stuff".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
+                m.insert("class".into(), vec![Posting { file_id: 0, lines: vec![1] }]);
                 m
             },
             total_tokens: 4,
@@ -704,11 +1646,20 @@ mod tests {
                 m.insert(PathBuf::from(&clean), 0u32);
                 m
             }),
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
 
         // Update file with different content
         std::fs::write(&test_file, "class Updated { NewToken stuff; }").unwrap();
-        update_file_in_index(&mut index, &PathBuf::from(&clean));
+        update_file_in_index(&mut index, &PathBuf::from(&clean), true);
 
         // total_tokens should equal sum of file_token_counts
         let sum: u64 = index.file_token_counts.iter().map(|&c| c as u64).sum();
@@ -725,7 +1676,7 @@ mod tests {
         let initial_total = index.total_tokens;
         let file0_tokens = index.file_token_counts[0] as u64;
 
-        remove_file_from_index(&mut index, &PathBuf::from("file0.cs"));
+        remove_file_from_index(&mut index, &PathBuf::from("file0.cs"), true);
 
         assert_eq!(index.total_tokens, initial_total - file0_tokens,
             "total_tokens should decrease by file0's token count");
@@ -754,22 +1705,31 @@ mod tests {
             trigram_dirty: false,
             forward: None,
             path_to_id: Some(HashMap::new()),
+            comment_markers: Vec::new(),
+            revision: None,
+            ticket_refs: Vec::new(),
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
         };
 
         // Add file1
         let clean1 = PathBuf::from(crate::clean_path(&file1.to_string_lossy()));
-        update_file_in_index(&mut index, &clean1);
+        update_file_in_index(&mut index, &clean1, true);
 
         // Add file2
         let clean2 = PathBuf::from(crate::clean_path(&file2.to_string_lossy()));
-        update_file_in_index(&mut index, &clean2);
+        update_file_in_index(&mut index, &clean2, true);
 
         // Update file1 with new content
         std::fs::write(&file1, "class AlphaUpdated { NewMethod(); }").unwrap();
-        update_file_in_index(&mut index, &clean1);
+        update_file_in_index(&mut index, &clean1, true);
 
         // Remove file2
-        remove_file_from_index(&mut index, &clean2);
+        remove_file_from_index(&mut index, &clean2, true);
 
         // Verify consistency: total_tokens == sum(file_token_counts) for non-removed files
         let sum: u64 = index.file_token_counts.iter().map(|&c| c as u64).sum();
@@ -822,4 +1782,175 @@ mod tests {
         assert_eq!(loaded.path_to_id.as_ref().unwrap().len(), orig_path_to_id_len,
             "path_to_id entry count mismatch after roundtrip");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_renames_remap_paths_without_retokenizing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = clean_path(&tmp.path().to_string_lossy());
+        let path = |rel: &str| PathBuf::from(format!("{}/{}", dir, rel));
+        std::fs::create_dir(path("src")).unwrap();
+        std::fs::write(path("src/a.cs"), "class Alpha {}\n").unwrap();
+        std::fs::write(path("src/b.cs"), "class Beta {}\n").unwrap();
+        std::fs::write(path("c.cs"), "class Gamma {}\n").unwrap();
+        std::fs::write(path("d.cs"), "class Delta {}\n").unwrap();
+        std::fs::write(path("e.cs"), "class Epsilon {}\n").unwrap();
+
+        let mut content = make_test_index();
+        content.files.clear();
+        content.file_token_counts.clear();
+        content.index.clear();
+        content.path_to_id = Some(HashMap::new());
+        let mut defs = DefinitionIndex::default();
+        for f in ["src/a.cs", "src/b.cs", "c.cs", "d.cs", "e.cs"] {
+            update_file_in_index(&mut content, &path(f), true);
+            definitions::update_file_definitions(&mut defs, &path(f));
+        }
+        content.root = dir.clone();
+        content.dir_blooms = search::builder::build_dir_blooms(&content.index, &content.files, &dir);
+        let alpha: Vec<(u32, Vec<u32>)> = content.index["alpha"].iter().map(|p| (p.file_id, p.lines.clone())).collect();
+        let tokens = content.total_tokens;
+
+        // Directory move, file rename, and a rename over another indexed file
+        std::fs::rename(path("src"), path("lib")).unwrap();
+        std::fs::rename(path("c.cs"), path("main.cs")).unwrap();
+        std::fs::rename(path("d.cs"), path("e.cs")).unwrap();
+        let renamed = vec![
+            (path("src"), Some(path("lib"))),
+            (path("c.cs"), Some(path("main.cs"))),
+            (path("d.cs"), Some(path("e.cs"))),
+        ];
+        // Content on disk changes after the rename: a remap must not read it
+        std::fs::write(path("lib/a.cs"), "class Changed {}\n").unwrap();
+        let content = Searcher::new(content);
+        let defs = Arc::new(RwLock::new(defs));
+        apply_incremental_batch(&content, Some(&defs), &renamed, &[], &[], &["cs".to_string()], &mut RetainedFiles::default());
+
+        let content = content.snapshot();
+        let p2id = content.path_to_id.as_ref().unwrap();
+        assert_eq!(content.files.len(), 5, "no new file ids for renamed files");
+        assert_eq!(p2id.get(&path("lib/a.cs")), Some(&0));
+        assert_eq!(p2id.get(&path("lib/b.cs")), Some(&1));
+        assert_eq!(p2id.get(&path("main.cs")), Some(&2));
+        assert_eq!(p2id.get(&path("e.cs")), Some(&3), "d.cs replaced e.cs");
+        assert_eq!(p2id.len(), 4);
+        assert_eq!(content.files[0], format!("{}/lib/a.cs", dir));
+        assert_eq!(content.index["alpha"].iter().map(|p| (p.file_id, p.lines.clone())).collect::<Vec<_>>(), alpha);
+        assert!(!content.index.contains_key("changed"));
+        assert!(!content.index.contains_key("epsilon"), "postings of the replaced file are purged");
+        assert_eq!(content.total_tokens, tokens - 2);
+        assert!(content.dir_may_contain(&format!("{}/lib", dir), "alpha"), "moved tokens reach the new directory's bloom");
+
+        let defs = defs.read().unwrap();
+        assert_eq!(defs.path_to_id.get(&path("lib/b.cs")), Some(&1));
+        assert_eq!(defs.path_to_id.get(&path("e.cs")), Some(&3));
+        assert_eq!(defs.files[2], format!("{}/main.cs", dir));
+        assert!(!defs.path_to_id.contains_key(&path("src/a.cs")));
+        assert!(defs.file_index.get(&4).is_none_or(|d| d.is_empty()), "definitions of the replaced file are dropped");
+    }
+
+    #[test]
+    fn test_queue_rename_dedupes_and_carries_pending_edits() {
+        let exts = vec!["cs".to_string()];
+        let mut renamed = Vec::new();
+        let mut dirty: HashSet<PathBuf> = [PathBuf::from("/r/old/a.cs"), PathBuf::from("/r/x.cs")].into();
+        let mut removed: HashSet<PathBuf> = [PathBuf::from("/r/new.cs")].into();
+
+        // inotify: From + To paired, then the same rename again as Both
+        queue_rename(&mut renamed, &mut dirty, &mut removed, &exts, PathBuf::from("/r/a.cs"), Some(PathBuf::from("/r/new.cs")));
+        queue_rename(&mut renamed, &mut dirty, &mut removed, &exts, PathBuf::from("/r/a.cs"), Some(PathBuf::from("/r/new.cs")));
+        assert_eq!(renamed.len(), 1);
+        assert!(removed.is_empty(), "the rename recreated /r/new.cs");
+
+        // Directory moved out of the tree: its queued edits are dropped
+        queue_rename(&mut renamed, &mut dirty, &mut removed, &exts, PathBuf::from("/r/old"), None);
+        assert_eq!(dirty, [PathBuf::from("/r/x.cs")].into());
+
+        // Edited, then renamed in the same window: the edit follows the file
+        queue_rename(&mut renamed, &mut dirty, &mut removed, &exts, PathBuf::from("/r/x.cs"), Some(PathBuf::from("/r/y.cs")));
+        assert_eq!(dirty, [PathBuf::from("/r/y.cs")].into());
+
+        // Temp files outside the watched extensions are not queued
+        queue_rename(&mut renamed, &mut dirty, &mut removed, &exts, PathBuf::from("/r/a.tmp"), Some(PathBuf::from("/r/b.tmp")));
+        assert_eq!(renamed.len(), 3);
+    }
+
+    #[test]
+    fn test_gitignore_change_prunes_and_restores_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = clean_path(&tmp.path().to_string_lossy());
+        let path = |rel: &str| PathBuf::from(format!("{}/{}", dir, rel));
+        std::fs::create_dir_all(path(".git")).unwrap();
+        std::fs::create_dir_all(path("gen")).unwrap();
+        std::fs::write(path("a.cs"), "class Kept {}\n").unwrap();
+        std::fs::write(path("gen/b.cs"), "class Generated {}\n").unwrap();
+        let exts = vec!["cs".to_string()];
+
+        let mut content = make_test_index();
+        content.files.clear();
+        content.file_token_counts.clear();
+        content.index.clear();
+        content.path_to_id = Some(HashMap::new());
+        update_file_in_index(&mut content, &path("a.cs"), true);
+        update_file_in_index(&mut content, &path("gen/b.cs"), true);
+        let content = Searcher::new(content);
+        let sync = || {
+            let wanted = poll_snapshot(tmp.path(), &exts);
+            let (gone, new) = ignore_rule_changes(content.snapshot().path_to_id.as_ref(), &wanted);
+            apply_incremental_batch(&content, None, &[], &gone, &new, &exts, &mut RetainedFiles::default());
+            (gone.len(), new.len())
+        };
+
+        std::fs::write(path(".gitignore"), "gen/\n").unwrap();
+        assert_eq!(sync(), (1, 0));
+        assert!(!content.snapshot().index.contains_key("generated"));
+        assert!(IgnoreRules::load(tmp.path()).is_ignored(&path("gen/c.cs"), false), "new files there are skipped too");
+
+        std::fs::remove_file(path(".gitignore")).unwrap();
+        assert_eq!(sync(), (0, 1));
+        assert!(content.snapshot().index.contains_key("generated"));
+        assert!(content.snapshot().index.contains_key("kept"));
+    }
+
+    #[test]
+    fn test_undo_of_delete_restores_retained_postings() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = crate::clean_path(&tmp.path().to_string_lossy());
+        let path = |rel: &str| PathBuf::from(format!("{}/{}", dir, rel));
+        std::fs::write(path("a.rs"), "fn kept_first() {}\n").unwrap();
+        std::fs::write(path("b.rs"), "fn other_file() {}\n").unwrap();
+        let exts = vec!["rs".to_string()];
+
+        let mut content = make_test_index();
+        content.files.clear();
+        content.file_token_counts.clear();
+        content.index.clear();
+        content.path_to_id = Some(HashMap::new());
+        let content = Searcher::new(content);
+        let mut retained = RetainedFiles::default();
+        apply_incremental_batch(&content, None, &[], &[], &[path("a.rs"), path("b.rs")], &exts, &mut retained);
+        let before = content.snapshot();
+
+        std::fs::remove_file(path("a.rs")).unwrap();
+        apply_incremental_batch(&content, None, &[], &[path("a.rs")], &[], &exts, &mut retained);
+        assert!(!content.snapshot().index.contains_key("kept_first"));
+        assert_eq!(retained.removed.len(), 1);
+
+        std::fs::write(path("a.rs"), "fn kept_first() {}\n").unwrap();
+        apply_incremental_batch(&content, None, &[], &[], &[path("a.rs")], &exts, &mut retained);
+        let after = content.snapshot();
+        assert!(retained.removed.is_empty());
+        assert_eq!(after.files.len(), 2, "the file id is reused, not a new one");
+        assert_eq!(after.path_to_id.as_ref().unwrap()[&path("a.rs")], 0);
+        assert_eq!(after.index["kept_first"][0].file_id, 0);
+        assert_eq!((after.total_tokens, &after.file_token_counts), (before.total_tokens, &before.file_token_counts));
+
+        // Restored with other content: indexed from the file as usual
+        std::fs::remove_file(path("b.rs")).unwrap();
+        apply_incremental_batch(&content, None, &[], &[path("b.rs")], &[], &exts, &mut retained);
+        std::fs::write(path("b.rs"), "fn edited_file() {}\n").unwrap();
+        apply_incremental_batch(&content, None, &[], &[], &[path("b.rs")], &exts, &mut retained);
+        let after = content.snapshot();
+        assert!(after.index.contains_key("edited_file"));
+        assert!(!after.index.contains_key("other_file"));
+    }
+}
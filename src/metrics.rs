@@ -0,0 +1,173 @@
+//! Server metrics registry: tool calls, latency histograms, truncated responses and
+//! file watcher activity, for operators of long-lived servers.
+//!
+//! Counters are process-wide and only grow, as in Prometheus: rates come from two
+//! reads. [`summary`] gives them as JSON (the `search_metrics` tool), and
+//! [`render_prometheus`] in the text exposition format, together with gauges the
+//! caller knows about (index sizes), for a scraper.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+/// Upper bounds of the latency buckets, in ms; the last bucket is `+Inf`
+pub const LATENCY_BUCKETS_MS: [f64; 13] = [1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0];
+
+/// Distinct tool names tracked; calls to further names count as `other`
+const MAX_TOOLS: usize = 64;
+
+#[derive(Default, Clone)]
+struct ToolStats {
+    calls: u64,
+    errors: u64,
+    latency_sum_ms: f64,
+    /// Calls per bucket of [`LATENCY_BUCKETS_MS`], plus one for `+Inf`; not cumulative
+    buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+static TOOLS: Mutex<BTreeMap<String, ToolStats>> = Mutex::new(BTreeMap::new());
+static TRUNCATED: AtomicU64 = AtomicU64::new(0);
+static WATCHER_EVENTS: AtomicU64 = AtomicU64::new(0);
+static WATCHER_BATCHES: AtomicU64 = AtomicU64::new(0);
+
+/// Start the uptime clock, if nothing was recorded yet.
+pub fn start() {
+    LazyLock::force(&STARTED);
+}
+
+/// Count a finished tool call.
+pub fn record_call(tool: &str, elapsed: Duration, is_error: bool) {
+    start();
+    let ms = elapsed.as_secs_f64() * 1000.0;
+    let mut tools = TOOLS.lock().unwrap_or_else(|e| e.into_inner());
+    let name = if tools.contains_key(tool) || tools.len() < MAX_TOOLS { tool } else { "other" };
+    let stats = tools.entry(name.to_string()).or_default();
+    stats.calls += 1;
+    stats.errors += is_error as u64;
+    stats.latency_sum_ms += ms;
+    stats.buckets[LATENCY_BUCKETS_MS.partition_point(|&le| le < ms)] += 1;
+}
+
+/// Count a response cut down to the size limit.
+pub fn record_truncation() {
+    TRUNCATED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a file system event received by the watcher.
+pub fn record_watcher_event() {
+    WATCHER_EVENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a watcher batch applied to the index.
+pub fn record_watcher_batch() {
+    WATCHER_BATCHES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// `{uptimeSecs, tools: {name: {calls, errors, latencyMs: {sum, buckets}}}, responses, watcher}`.
+/// Buckets are cumulative, keyed by upper bound, as in Prometheus.
+pub fn summary() -> Value {
+    let uptime = STARTED.elapsed().as_secs_f64();
+    let tools: serde_json::Map<String, Value> = tools_snapshot().into_iter().map(|(name, s)| {
+        let mut cumulative = 0;
+        let buckets: serde_json::Map<String, Value> = s.buckets.iter().enumerate().map(|(i, n)| {
+            cumulative += n;
+            (bucket_label(i), json!(cumulative))
+        }).collect();
+        (name, json!({
+            "calls": s.calls,
+            "errors": s.errors,
+            "latencyMs": { "sum": (s.latency_sum_ms * 100.0).round() / 100.0, "buckets": buckets },
+        }))
+    }).collect();
+    let events = WATCHER_EVENTS.load(Ordering::Relaxed);
+    json!({
+        "uptimeSecs": uptime.round(),
+        "tools": tools,
+        "responses": { "truncated": TRUNCATED.load(Ordering::Relaxed) },
+        "watcher": {
+            "events": events,
+            "batches": WATCHER_BATCHES.load(Ordering::Relaxed),
+            "eventsPerMinute": ((events as f64 * 60.0 / uptime.max(1.0)) * 10.0).round() / 10.0,
+        },
+    })
+}
+
+/// Everything in [`summary`] plus `gauges` (`(name, help, value)`), in the Prometheus
+/// text exposition format. Gauge names get the `search_` prefix.
+pub fn render_prometheus(gauges: &[(&str, &str, f64)]) -> String {
+    let mut out = String::new();
+    let tools = tools_snapshot();
+    let counter = |out: &mut String, name: &str, help: &str| {
+        let _ = writeln!(out, "# HELP search_{} {}\n# TYPE search_{} counter", name, help, name);
+    };
+    counter(&mut out, "tool_calls_total", "Tool calls, by tool");
+    for (tool, s) in &tools {
+        let _ = writeln!(out, "search_tool_calls_total{{tool=\"{}\"}} {}", tool, s.calls);
+    }
+    counter(&mut out, "tool_errors_total", "Tool calls that returned an error, by tool");
+    for (tool, s) in &tools {
+        let _ = writeln!(out, "search_tool_errors_total{{tool=\"{}\"}} {}", tool, s.errors);
+    }
+    let _ = writeln!(out, "# HELP search_tool_latency_ms Tool call latency in milliseconds\n# TYPE search_tool_latency_ms histogram");
+    for (tool, s) in &tools {
+        let mut cumulative = 0;
+        for (i, n) in s.buckets.iter().enumerate() {
+            cumulative += n;
+            let _ = writeln!(out, "search_tool_latency_ms_bucket{{tool=\"{}\",le=\"{}\"}} {}", tool, bucket_label(i), cumulative);
+        }
+        let _ = writeln!(out, "search_tool_latency_ms_sum{{tool=\"{}\"}} {}", tool, s.latency_sum_ms);
+        let _ = writeln!(out, "search_tool_latency_ms_count{{tool=\"{}\"}} {}", tool, s.calls);
+    }
+    for (name, help, value) in [
+        ("responses_truncated_total", "Responses cut down to the size limit", &TRUNCATED),
+        ("watcher_events_total", "File system events received by the watcher", &WATCHER_EVENTS),
+        ("watcher_batches_total", "Watcher batches applied to the index", &WATCHER_BATCHES),
+    ] {
+        counter(&mut out, name, help);
+        let _ = writeln!(out, "search_{} {}", name, value.load(Ordering::Relaxed));
+    }
+    for (name, help, value) in gauges {
+        let _ = writeln!(out, "# HELP search_{} {}\n# TYPE search_{} gauge\nsearch_{} {}", name, help, name, name, value);
+    }
+    let _ = writeln!(out, "# HELP search_uptime_seconds Seconds since the server started\n# TYPE search_uptime_seconds gauge\nsearch_uptime_seconds {:.0}",
+        STARTED.elapsed().as_secs_f64());
+    out
+}
+
+fn tools_snapshot() -> BTreeMap<String, ToolStats> {
+    TOOLS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn bucket_label(i: usize) -> String {
+    LATENCY_BUCKETS_MS.get(i).map_or_else(|| "+Inf".to_string(), |le| le.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calls_land_in_cumulative_latency_buckets() {
+        // Counters are process-wide: use a tool name no other test calls
+        record_call("metrics_test_tool", Duration::from_micros(500), false);
+        record_call("metrics_test_tool", Duration::from_millis(30), true);
+        record_call("metrics_test_tool", Duration::from_secs(20), false);
+
+        let summary = summary();
+        let tool = &summary["tools"]["metrics_test_tool"];
+        assert_eq!((&tool["calls"], &tool["errors"]), (&json!(3), &json!(1)));
+        let buckets = &tool["latencyMs"]["buckets"];
+        assert_eq!((&buckets["1"], &buckets["25"], &buckets["50"], &buckets["10000"], &buckets["+Inf"]),
+            (&json!(1), &json!(1), &json!(2), &json!(2), &json!(3)));
+
+        let text = render_prometheus(&[("index_files", "Files in the content index", 42.0)]);
+        assert!(text.contains("search_tool_latency_ms_bucket{tool=\"metrics_test_tool\",le=\"50\"} 2\n"), "{}", text);
+        assert!(text.contains("search_tool_latency_ms_count{tool=\"metrics_test_tool\"} 3\n"));
+        assert!(text.contains("# TYPE search_index_files gauge\nsearch_index_files 42\n"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Code ownership: maps file paths to owning teams from a CODEOWNERS file or a flat
+//! `owners.yaml`, so search results can be annotated with owners and filtered by them.
+//!
+//! Patterns use gitignore syntax (the same syntax GitHub/GitLab use for CODEOWNERS) and
+//! the last matching rule wins. A rule with no owners marks its paths as unowned.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Where CODEOWNERS is looked for, relative to the indexed directory, in GitHub's order.
+const CODEOWNERS_LOCATIONS: [&str; 4] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS", ".gitlab/CODEOWNERS"];
+
+struct OwnerRule {
+    matcher: Gitignore,
+    owners: Vec<String>,
+    /// `dir/*` owns direct children only; gitignore would also match everything
+    /// below a matched child directory.
+    direct_children_only: bool,
+}
+
+/// Parsed ownership rules for one directory tree.
+pub struct Owners {
+    /// Forward-slash root that rule patterns are relative to.
+    root: String,
+    /// File the rules came from (for `search_info` and summaries).
+    pub source: String,
+    rules: Vec<OwnerRule>,
+    /// path -> index of the last matching rule (None = no rule matches)
+    cache: Mutex<HashMap<String, Option<usize>>>,
+}
+
+impl std::fmt::Debug for Owners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Owners").field("source", &self.source).field("rules", &self.rules.len()).finish()
+    }
+}
+
+impl Owners {
+    /// First CODEOWNERS file found under `root`, if any.
+    pub fn discover(root: &Path) -> Option<PathBuf> {
+        CODEOWNERS_LOCATIONS.iter().map(|loc| root.join(loc)).find(|p| p.is_file())
+    }
+
+    /// Load rules from `path`. Files ending in `.yaml`/`.yml` use the flat owners.yaml
+    /// format; anything else is read as CODEOWNERS.
+    pub fn load(path: &Path, root: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read owners file {}: {}", path.display(), e))?;
+        let is_yaml = path.extension().and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"));
+        let entries = if is_yaml { parse_owners_yaml(&text) } else { parse_codeowners(&text) };
+        Self::from_entries(root, &path.to_string_lossy(), entries)
+    }
+
+    /// Load `explicit` if given, else a discovered CODEOWNERS under `root`. Load errors
+    /// are returned for an explicit file and only logged for a discovered one.
+    pub fn load_for(root: &Path, explicit: Option<&Path>) -> Result<Option<Self>, String> {
+        match explicit {
+            Some(path) => Self::load(path, root).map(Some),
+            None => Ok(Self::discover(root).and_then(|path| match Self::load(&path, root) {
+                Ok(owners) => Some(owners),
+                Err(e) => {
+                    eprintln!("[owners] Warning: {}", e);
+                    None
+                }
+            })),
+        }
+    }
+
+    fn from_entries(root: &Path, source: &str, entries: Vec<(String, Vec<String>)>) -> Result<Self, String> {
+        let mut rules = Vec::with_capacity(entries.len());
+        for (pattern, owners) in entries {
+            let mut builder = GitignoreBuilder::new(root);
+            builder.add_line(None, &pattern)
+                .map_err(|e| format!("Invalid owners pattern '{}' in {}: {}", pattern, source, e))?;
+            let matcher = builder.build()
+                .map_err(|e| format!("Invalid owners pattern '{}' in {}: {}", pattern, source, e))?;
+            let direct_children_only = pattern.ends_with("/*");
+            rules.push(OwnerRule { matcher, owners, direct_children_only });
+        }
+        Ok(Self {
+            root: crate::clean_path(&root.to_string_lossy()).trim_end_matches('/').to_string(),
+            source: crate::clean_path(source),
+            rules,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Number of ownership rules.
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Owners of `path` (absolute under the root, or root-relative). Empty when no rule
+    /// matches, the matching rule lists no owners, or the path is outside the root.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        let normalized = path.replace('\\', "/");
+        let rel = match normalized.strip_prefix(&self.root) {
+            Some(rest) if rest.starts_with('/') => rest.trim_start_matches('/'),
+            Some(_) => return &[],
+            None if Path::new(&normalized).is_absolute() || normalized.starts_with('/') => return &[],
+            None => normalized.trim_start_matches("./"),
+        };
+        if rel.is_empty() {
+            return &[];
+        }
+        let cached = self.cache.lock().ok().and_then(|c| c.get(rel).copied());
+        let rule = match cached {
+            Some(rule) => rule,
+            None => {
+                let rule = self.rules.iter().rposition(|r| if r.direct_children_only {
+                    r.matcher.matched(rel, false).is_ignore()
+                } else {
+                    r.matcher.matched_path_or_any_parents(rel, false).is_ignore()
+                });
+                if let Ok(mut c) = self.cache.lock() {
+                    c.insert(rel.to_string(), rule);
+                }
+                rule
+            }
+        };
+        rule.map(|i| self.rules[i].owners.as_slice()).unwrap_or(&[])
+    }
+
+    /// Whether any owner of `path` contains `filter` (case-insensitive, `@` optional).
+    pub fn matches(&self, path: &str, filter: &str) -> bool {
+        let wanted = filter.trim_start_matches('@').to_lowercase();
+        self.owners_of(path).iter().any(|o| o.to_lowercase().contains(&wanted))
+    }
+}
+
+/// CODEOWNERS: `pattern owner1 owner2 ...` per line; `#` starts a comment.
+/// Negated patterns are not part of the format and are skipped.
+pub fn parse_codeowners(text: &str) -> Vec<(String, Vec<String>)> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') || line.starts_with('[') {
+                return None;
+            }
+            let line = match line.find(" #") {
+                Some(i) => &line[..i],
+                None => line,
+            };
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?.replace("\\#", "#");
+            Some((pattern, parts.map(|s| s.to_string()).collect()))
+        })
+        .collect()
+}
+
+/// Flat owners.yaml: one `pattern: owner` or `pattern: [owner1, owner2]` mapping per
+/// line. Keys and values may be quoted; `#` starts a comment.
+pub fn parse_owners_yaml(text: &str) -> Vec<(String, Vec<String>)> {
+    let unquote = |s: &str| s.trim().trim_matches('"').trim_matches('\'').to_string();
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == "---" {
+                return None;
+            }
+            let quote = line.chars().next().filter(|c| *c == '"' || *c == '\'');
+            let (key, value) = if let Some(q) = quote {
+                let rest = &line[1..];
+                let end = rest.find(q)?;
+                (rest[..end].to_string(), rest[end + 1..].trim_start().strip_prefix(':')?)
+            } else {
+                let (k, v) = line.split_once(": ").or_else(|| line.strip_suffix(':').map(|k| (k, "")))?;
+                (k.trim().to_string(), v)
+            };
+            let value = match value.find(" #") {
+                Some(i) => &value[..i],
+                None => value,
+            };
+            let value = value.trim().trim_start_matches('[').trim_end_matches(']');
+            let owners = value.split(',').map(unquote).filter(|o| !o.is_empty()).collect();
+            Some((key, owners))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owners(text: &str) -> Owners {
+        Owners::from_entries(Path::new("/repo"), "CODEOWNERS", parse_codeowners(text)).unwrap()
+    }
+
+    #[test]
+    fn test_codeowners_last_match_wins() {
+        let o = owners(concat!(
+            "# comment\n",
+            "*       @org/everyone\n",
+            "*.sql   @org/dba\n",
+            "/src/Payments/  @org/payments @alice  # inline comment\n",
+            "docs/*  @org/docs\n",
+            "/src/Payments/generated/\n",
+        ));
+        assert_eq!(o.rule_count(), 5);
+        assert_eq!(o.owners_of("/repo/README.md"), ["@org/everyone"]);
+        assert_eq!(o.owners_of("/repo/db/schema.sql"), ["@org/dba"]);
+        assert_eq!(o.owners_of("/repo/src/Payments/Api/Charge.cs"), ["@org/payments", "@alice"]);
+        assert_eq!(o.owners_of("src/Payments/Charge.cs"), ["@org/payments", "@alice"]);
+        // docs/* matches direct children only, so nested files fall back to `*`
+        assert_eq!(o.owners_of("/repo/docs/guide.md"), ["@org/docs"]);
+        assert_eq!(o.owners_of("/repo/docs/api/ref.md"), ["@org/everyone"]);
+        // A rule without owners un-owns its paths
+        assert!(o.owners_of("/repo/src/Payments/generated/Model.cs").is_empty());
+        assert!(o.owners_of("/elsewhere/file.cs").is_empty());
+
+        assert!(o.matches("/repo/src/Payments/Charge.cs", "payments"));
+        assert!(o.matches("/repo/src/Payments/Charge.cs", "@ALICE"));
+        assert!(!o.matches("/repo/README.md", "payments"));
+    }
+
+    #[test]
+    fn test_owners_yaml_and_load() {
+        let entries = parse_owners_yaml(concat!(
+            "---\n",
+            "# team map\n",
+            "\"src/Payments/**\": \"@payments\"\n",
+            "'*.sql': [dba, data-team]\n",
+            "docs/: docs-team  # trailing\n",
+        ));
+        assert_eq!(entries, vec![
+            ("src/Payments/**".to_string(), vec!["@payments".to_string()]),
+            ("*.sql".to_string(), vec!["dba".to_string(), "data-team".to_string()]),
+            ("docs/".to_string(), vec!["docs-team".to_string()]),
+        ]);
+
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join(".github")).unwrap();
+        std::fs::write(tmp.path().join(".github/CODEOWNERS"), "*.rs @rustaceans\n").unwrap();
+        let found = Owners::load_for(tmp.path(), None).unwrap().unwrap();
+        assert!(found.source.ends_with(".github/CODEOWNERS"));
+        assert_eq!(found.owners_of(&format!("{}/src/main.rs", crate::clean_path(&tmp.path().to_string_lossy()))), ["@rustaceans"]);
+
+        let yaml = tmp.path().join("owners.yaml");
+        std::fs::write(&yaml, "docs/: docs-team\n").unwrap();
+        let explicit = Owners::load_for(tmp.path(), Some(&yaml)).unwrap().unwrap();
+        assert_eq!(explicit.owners_of("docs/a.md"), ["docs-team"]);
+        assert!(Owners::load_for(tmp.path(), Some(&tmp.path().join("missing.yaml"))).is_err());
+    }
+}
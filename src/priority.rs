@@ -0,0 +1,200 @@
+//! Resource limits for index builds: worker thread count, OS thread priority, IO throttling.
+//!
+//! `--build-priority low` is meant for rebuilds that run while the user keeps working:
+//! it caps worker threads to a quarter of the cores, drops each worker thread to
+//! background/low priority via platform APIs, and can be combined with `--io-limit-mb`
+//! to throttle file reads.
+
+use std::cell::Cell;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Scheduling priority for index build worker threads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BuildPriority {
+    /// Use all cores at normal priority (default).
+    #[default]
+    Normal,
+    /// Use ~25% of cores at background priority.
+    Low,
+}
+
+impl BuildPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Low => "low",
+        }
+    }
+}
+
+impl std::fmt::Display for BuildPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Resolve the worker thread count for a build.
+/// An explicit `--threads N` always wins; otherwise `Normal` uses every core and
+/// `Low` uses a quarter of them (at least one).
+pub fn resolve_threads(requested: usize, priority: BuildPriority) -> usize {
+    if requested > 0 {
+        return requested;
+    }
+    let cores = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4);
+    match priority {
+        BuildPriority::Normal => cores,
+        BuildPriority::Low => (cores / 4).max(1),
+    }
+}
+
+thread_local! {
+    static PRIORITY_APPLIED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Lower the calling thread's scheduling priority when `priority` is `Low`.
+/// Idempotent per thread, so it is cheap to call from per-file callbacks
+/// (e.g. `WalkBuilder` visitors, which are created on the caller's thread).
+///
+/// - Linux: `setpriority(PRIO_PROCESS, 0, 10)` — Linux applies nice values per thread.
+/// - macOS: `setpriority(PRIO_DARWIN_THREAD, 0, PRIO_DARWIN_BG)` — CPU and IO background.
+/// - Windows: `SetThreadPriority(THREAD_MODE_BACKGROUND_BEGIN)` — CPU and IO background.
+/// - Other platforms: no-op.
+pub fn apply_to_current_thread(priority: BuildPriority) {
+    if priority == BuildPriority::Normal {
+        return;
+    }
+    if PRIORITY_APPLIED.with(|applied| applied.replace(true)) {
+        return;
+    }
+    lower_current_thread_priority();
+}
+
+#[cfg(target_os = "linux")]
+fn lower_current_thread_priority() {
+    unsafe extern "C" {
+        fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+    }
+    const PRIO_PROCESS: i32 = 0;
+    // who = 0 targets the calling thread (Linux nice values are per-thread)
+    let _ = unsafe { setpriority(PRIO_PROCESS, 0, 10) };
+}
+
+#[cfg(target_os = "macos")]
+fn lower_current_thread_priority() {
+    unsafe extern "C" {
+        fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+    }
+    const PRIO_DARWIN_THREAD: i32 = 3;
+    const PRIO_DARWIN_BG: i32 = 0x1000;
+    let _ = unsafe { setpriority(PRIO_DARWIN_THREAD, 0, PRIO_DARWIN_BG) };
+}
+
+#[cfg(target_os = "windows")]
+fn lower_current_thread_priority() {
+    unsafe extern "system" {
+        fn GetCurrentThread() -> isize;
+        fn SetThreadPriority(thread: isize, priority: i32) -> i32;
+    }
+    const THREAD_MODE_BACKGROUND_BEGIN: i32 = 0x0001_0000;
+    let _ = unsafe { SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN) };
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn lower_current_thread_priority() {}
+
+/// Shared read-rate limiter. Worker threads report bytes read via [`IoThrottle::consume`];
+/// when the aggregate rate exceeds the limit, the reporting thread sleeps until the
+/// build is back on schedule. A limit of 0 disables throttling.
+pub struct IoThrottle {
+    bytes_per_sec: u64,
+    state: Mutex<(Instant, u64)>,
+}
+
+impl IoThrottle {
+    pub fn new(limit_mb_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: limit_mb_per_sec.saturating_mul(1_048_576),
+            state: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.bytes_per_sec > 0
+    }
+
+    /// Account for `bytes` just read and sleep if the build is ahead of the allowed rate.
+    pub fn consume(&self, bytes: u64) {
+        if !self.is_enabled() {
+            return;
+        }
+        let wait = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.1 += bytes;
+            let due = Duration::from_secs_f64(state.1 as f64 / self.bytes_per_sec as f64);
+            due.saturating_sub(state.0.elapsed())
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_threads_explicit_wins() {
+        assert_eq!(resolve_threads(3, BuildPriority::Normal), 3);
+        assert_eq!(resolve_threads(3, BuildPriority::Low), 3);
+    }
+
+    #[test]
+    fn test_resolve_threads_low_uses_quarter_of_cores() {
+        let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+        assert_eq!(resolve_threads(0, BuildPriority::Normal), cores);
+        assert_eq!(resolve_threads(0, BuildPriority::Low), (cores / 4).max(1));
+    }
+
+    #[test]
+    fn test_io_throttle_disabled_is_noop() {
+        let t = IoThrottle::new(0);
+        assert!(!t.is_enabled());
+        let start = Instant::now();
+        t.consume(u64::MAX / 2);
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_io_throttle_sleeps_when_ahead_of_rate() {
+        let t = IoThrottle::new(1); // 1 MB/s
+        let start = Instant::now();
+        t.consume(104_858); // ~0.1 MB → ~100ms
+        assert!(start.elapsed() >= Duration::from_millis(80), "elapsed {:?}", start.elapsed());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_low_priority_sets_thread_nice() {
+        unsafe extern "C" {
+            fn getpriority(which: i32, who: u32) -> i32;
+        }
+        let nice = std::thread::spawn(|| {
+            apply_to_current_thread(BuildPriority::Low);
+            apply_to_current_thread(BuildPriority::Low); // idempotent
+            unsafe { getpriority(0, 0) }
+        }).join().unwrap();
+        assert!(nice >= 10, "expected nice >= 10, got {}", nice);
+    }
+
+    #[test]
+    fn test_apply_normal_priority_is_noop() {
+        std::thread::spawn(|| {
+            apply_to_current_thread(BuildPriority::Normal);
+            assert!(!PRIORITY_APPLIED.with(|a| a.get()));
+        }).join().unwrap();
+    }
+}
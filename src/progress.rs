@@ -0,0 +1,245 @@
+//! Live progress line for index builds: current phase, items done, items/sec and, when
+//! the phase knows its total, an ETA.
+//!
+//! Off unless the CLI turns it on with [`enable`]; it does so for commands run on a
+//! terminal without `--quiet`. The MCP server never does, so background builds keep
+//! their logs clean. A [`Progress`] that is off costs one atomic add per file.
+//!
+//! A build run inside [`Tracker::track`] can also be polled from other threads, which
+//! is how `search_reindex_status` reports a background reindex.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Whether a command shows progress: not with `--quiet`, not for `serve` (the server
+/// logs to stderr; a progress line would garble it), and only when stderr is a terminal.
+pub fn wanted(quiet: bool, serve: bool, stderr_is_terminal: bool) -> bool {
+    !quiet && !serve && stderr_is_terminal
+}
+
+/// Show progress for the builds that follow.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+struct State {
+    label: &'static str,
+    /// Phase name, what it counts, and its start
+    phase: Mutex<(&'static str, &'static str, Instant)>,
+    done: AtomicUsize,
+    /// Items in the phase; 0 when unknown
+    total: AtomicUsize,
+    stop: AtomicBool,
+}
+
+/// Progress of one build, redrawn on stderr by a ticker thread until dropped.
+pub struct Progress {
+    state: Arc<State>,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl Progress {
+    /// Start reporting a build under `label` (e.g. `content-index`), in `phase`, which
+    /// counts `unit`s (e.g. `files`).
+    pub fn start(label: &'static str, phase: &'static str, unit: &'static str, total: Option<usize>) -> Progress {
+        let state = Arc::new(State {
+            label,
+            phase: Mutex::new((phase, unit, Instant::now())),
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(total.unwrap_or(0)),
+            stop: AtomicBool::new(false),
+        });
+        TRACKER.with(|t| {
+            if let Some(tracker) = t.borrow().as_ref() {
+                *tracker.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::clone(&state));
+            }
+        });
+        let ticker = ENABLED.load(Ordering::Relaxed).then(|| {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                while !state.stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(REDRAW_INTERVAL);
+                    let (phase, unit, started) = *state.phase.lock().unwrap_or_else(|e| e.into_inner());
+                    let total = state.total.load(Ordering::Relaxed);
+                    let line = render(state.label, phase, unit, state.done.load(Ordering::Relaxed),
+                        (total > 0).then_some(total), started.elapsed());
+                    let _ = write!(std::io::stderr(), "\r\x1b[2K{}", line);
+                }
+            })
+        });
+        Progress { state, ticker }
+    }
+
+    /// Count `n` more items done in the current phase.
+    pub fn inc(&self, n: usize) {
+        self.state.done.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Move on to `phase`, counting `unit`s from zero towards `total`. Clears the line
+    /// until the next redraw, so the build can log between phases.
+    pub fn phase(&self, phase: &'static str, unit: &'static str, total: Option<usize>) {
+        let mut current = self.state.phase.lock().unwrap_or_else(|e| e.into_inner());
+        *current = (phase, unit, Instant::now());
+        self.state.done.store(0, Ordering::Relaxed);
+        self.state.total.store(total.unwrap_or(0), Ordering::Relaxed);
+        if self.ticker.is_some() {
+            let _ = write!(std::io::stderr(), "\r\x1b[2K");
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+        if let Some(ticker) = self.ticker.take() {
+            let _ = ticker.join();
+            let _ = write!(std::io::stderr(), "\r\x1b[2K");
+        }
+    }
+}
+
+/// State of the latest build a [`Tracker`] saw start
+type Latest = Arc<Mutex<Option<Arc<State>>>>;
+
+thread_local! {
+    static TRACKER: RefCell<Option<Latest>> = const { RefCell::new(None) };
+}
+
+/// Where a build stands: its label, phase, and items done of the phase.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub label: &'static str,
+    pub phase: &'static str,
+    pub unit: &'static str,
+    pub done: usize,
+    pub total: Option<usize>,
+}
+
+/// Shared view of the latest [`Progress`] started inside [`Tracker::track`].
+#[derive(Clone, Default)]
+pub struct Tracker {
+    latest: Latest,
+}
+
+impl Tracker {
+    /// Run `f`, letting this tracker see the progress of builds it starts on this thread.
+    pub fn track<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = TRACKER.with(|t| t.borrow_mut().replace(Arc::clone(&self.latest)));
+        let result = f();
+        TRACKER.with(|t| *t.borrow_mut() = previous);
+        result
+    }
+
+    /// Progress of the latest build, or `None` before one started.
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        let state = self.latest.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+        let (phase, unit, _) = *state.phase.lock().unwrap_or_else(|e| e.into_inner());
+        let total = state.total.load(Ordering::Relaxed);
+        Some(Snapshot { label: state.label, phase, unit, done: state.done.load(Ordering::Relaxed), total: (total > 0).then_some(total) })
+    }
+}
+
+/// `[label] phase: done/total unit (pct%) | rate unit/s | ETA`, or the elapsed time in
+/// place of the ETA when the total is unknown.
+fn render(label: &str, phase: &str, unit: &str, done: usize, total: Option<usize>, elapsed: Duration) -> String {
+    let (rate, eta) = rate_and_eta(done, total, elapsed);
+    match total {
+        Some(total) => {
+            let pct = (done * 100).checked_div(total).unwrap_or(100).min(100);
+            let eta = eta.map_or_else(|| "?".to_string(), format_duration);
+            format!("[{}] {}: {}/{} {} ({}%) | {:.0} {}/s | ETA {}", label, phase, done, total, unit, pct, rate, unit, eta)
+        }
+        None => format!("[{}] {}: {} {} | {:.0} {}/s | {}", label, phase, done, unit, rate, unit, format_duration(elapsed)),
+    }
+}
+
+/// Items per second so far, and the time left at that rate: `None` without a total or
+/// before the first item, zero once `done` reaches the total.
+fn rate_and_eta(done: usize, total: Option<usize>, elapsed: Duration) -> (f64, Option<Duration>) {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 { done as f64 / secs } else { 0.0 };
+    let eta = total.filter(|_| rate > 0.0)
+        .map(|total| Duration::from_secs_f64(total.saturating_sub(done) as f64 / rate));
+    (rate, eta)
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_with_and_without_total() {
+        let line = render("def-index", "parsing", "files", 250, Some(1000), Duration::from_secs(5));
+        assert_eq!(line, "[def-index] parsing: 250/1000 files (25%) | 50 files/s | ETA 15s");
+        let line = render("content-index", "tokenizing", "files", 9000, None, Duration::from_secs(90));
+        assert_eq!(line, "[content-index] tokenizing: 9000 files | 100 files/s | 1m30s");
+        let line = render("def-index", "parsing", "files", 0, Some(10), Duration::ZERO);
+        assert!(line.ends_with("(0%) | 0 files/s | ETA ?"), "{}", line);
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1h02m");
+    }
+
+    #[test]
+    fn test_tracker_sees_builds_started_inside_track() {
+        let tracker = Tracker::default();
+        let outside = Progress::start("index", "scanning", "entries", None);
+        assert_eq!(tracker.snapshot(), None);
+        let progress = tracker.track(|| Progress::start("content-index", "tokenizing", "files", None));
+        progress.inc(3);
+        progress.phase("merging", "shards", Some(4));
+        outside.inc(1);
+        let snapshot = tracker.snapshot().unwrap();
+        assert_eq!((snapshot.label, snapshot.phase, snapshot.unit), ("content-index", "merging", "shards"));
+        assert_eq!((snapshot.done, snapshot.total), (0, Some(4)));
+    }
+
+    #[test]
+    fn test_rate_and_eta() {
+        assert_eq!(rate_and_eta(300, Some(900), Duration::from_secs(3)), (100.0, Some(Duration::from_secs(6))));
+        // Unknown total: a rate but no ETA
+        assert_eq!(rate_and_eta(300, None, Duration::from_secs(3)), (100.0, None));
+        // Nothing done yet, or no time elapsed: no rate to extrapolate from
+        assert_eq!(rate_and_eta(0, Some(10), Duration::from_secs(3)), (0.0, None));
+        assert_eq!(rate_and_eta(5, Some(10), Duration::ZERO), (0.0, None));
+        // Done, or past a total that was an estimate: nothing left
+        assert_eq!(rate_and_eta(10, Some(10), Duration::from_secs(2)).1, Some(Duration::ZERO));
+        assert_eq!(rate_and_eta(12, Some(10), Duration::from_secs(2)).1, Some(Duration::ZERO));
+        let line = render("content-index", "tokenizing", "files", 12, Some(10), Duration::from_secs(2));
+        assert!(line.ends_with("(100%) | 6 files/s | ETA 0s"), "{}", line);
+    }
+
+    #[test]
+    fn test_progress_wanted_only_on_a_terminal_without_quiet_outside_serve() {
+        assert!(wanted(false, false, true));
+        assert!(!wanted(true, false, true), "--quiet");
+        assert!(!wanted(false, true, true), "serve");
+        assert!(!wanted(false, false, false), "stderr redirected");
+    }
+
+    #[test]
+    fn test_progress_while_disabled_counts_without_a_ticker() {
+        let tracker = Tracker::default();
+        let progress = tracker.track(|| Progress::start("content-index", "tokenizing", "files", Some(2)));
+        assert!(progress.ticker.is_none());
+        progress.inc(2);
+        assert_eq!(tracker.snapshot().map(|s| (s.done, s.total)), Some((2, Some(2))));
+    }
+}
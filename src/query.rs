@@ -0,0 +1,554 @@
+//! Content search over a [`ContentIndex`], for programs that embed the engine: the
+//! exact, substring, regex and phrase modes of `search grep`, ranked by TF-IDF.
+//!
+//! The CLI and the MCP server add filters, paging, ranking boosts and output formats
+//! on top; the lookups ([`substring_token_ids`], [`token_regex`]), the TF-IDF scoring
+//! and the definition boost ([`Scorer`], [`defining_files`]) are shared with them, so
+//! all three rank files alike.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::definition_index::{DefinitionIndex, DefinitionKind};
+use crate::{generate_trigrams, path_from_string, read_file_lossy, ContentIndex, Searcher, TrigramIndex};
+
+/// How the terms of a [`GrepQuery`] match index tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Tokens containing a term: `userservice` finds `IUserServiceFactory`
+    #[default]
+    Substring,
+    /// Tokens equal to a term
+    Exact,
+    /// Tokens matching a term as a whole-token, case-insensitive regex
+    Regex,
+    /// Lines containing the terms as one phrase; files are ranked by matching lines
+    Phrase,
+}
+
+impl std::str::FromStr for MatchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "substring" => Ok(Self::Substring),
+            "exact" => Ok(Self::Exact),
+            "regex" => Ok(Self::Regex),
+            "phrase" => Ok(Self::Phrase),
+            other => Err(format!("Invalid mode '{}'. Valid values: substring, exact, regex, phrase", other)),
+        }
+    }
+}
+
+/// A content search: what [`grep`] and [`Searcher::grep`] look for.
+#[derive(Debug, Clone)]
+pub struct GrepQuery {
+    /// Terms, patterns, or the phrase (a single term) for [`MatchMode::Phrase`]
+    pub terms: Vec<String>,
+    pub mode: MatchMode,
+    /// Keep only files matching every term (default: any term)
+    pub all_terms: bool,
+    /// Files returned, best first; 0 = all
+    pub max_results: usize,
+}
+
+impl GrepQuery {
+    /// Comma-separated `terms`, as `search grep` takes them, in substring mode.
+    pub fn new(terms: &str) -> Self {
+        GrepQuery {
+            terms: terms.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect(),
+            mode: MatchMode::default(),
+            all_terms: false,
+            max_results: 50,
+        }
+    }
+
+    /// A phrase search for `phrase`.
+    pub fn phrase(phrase: &str) -> Self {
+        GrepQuery { terms: vec![phrase.trim().to_string()], mode: MatchMode::Phrase, ..GrepQuery::new("") }
+    }
+
+    pub fn mode(mut self, mode: MatchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn all_terms(mut self, all_terms: bool) -> Self {
+        self.all_terms = all_terms;
+        self
+    }
+
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+}
+
+/// One file found by [`grep`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMatch {
+    pub path: String,
+    /// Sum of TF-IDF over the matched tokens; matching lines for a phrase
+    pub score: f64,
+    pub occurrences: usize,
+    /// Query terms found in the file
+    pub terms_matched: usize,
+    /// Matching lines, 1-based, ascending
+    pub lines: Vec<u32>,
+}
+
+/// How one index token added to a file's TF-IDF score, for grep's explain mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermScore {
+    /// Query term the token matched (the token itself outside substring mode)
+    pub term: String,
+    pub token: String,
+    pub occurrences: usize,
+    /// Tokens in the file, the TF denominator
+    pub file_tokens: f64,
+    /// Files containing the token, the IDF denominator
+    pub doc_freq: usize,
+    pub tf: f64,
+    pub idf: f64,
+}
+
+impl TermScore {
+    pub fn contribution(&self) -> f64 {
+        self.tf * self.idf
+    }
+}
+
+/// A type, method or function named like a query term that a scored file defines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefinitionBoost {
+    pub symbol: String,
+    /// What the file's score was multiplied by
+    pub factor: f64,
+}
+
+/// A file's TF-IDF score over the tokens credited to it by a [`Scorer`].
+#[derive(Debug, Clone)]
+pub struct ScoredFile {
+    pub file_id: u32,
+    /// `tf_idf`, times the definition factor when the file defines a query term
+    pub score: f64,
+    /// Sum of `tf x idf` over the credited tokens
+    pub tf_idf: f64,
+    /// Set when the file defines a symbol named like a query term
+    pub definition: Option<DefinitionBoost>,
+    pub occurrences: usize,
+    /// Credited lines, 1-based, ascending
+    pub lines: Vec<u32>,
+    /// Distinct query terms credited
+    pub terms_matched: usize,
+    /// Per-token breakdown, kept when the scorer explains
+    pub explain: Vec<TermScore>,
+}
+
+/// Accumulates TF-IDF per file over the postings a search keeps, and boosts the files
+/// that define a query term. [`grep`], `search grep` and the `search_grep` tool all
+/// score through it; they differ only in which postings and lines they credit.
+pub struct Scorer<'a> {
+    index: &'a ContentIndex,
+    total_docs: f64,
+    explain: bool,
+    files: HashMap<u32, (ScoredFile, HashSet<usize>)>,
+    /// Path key (see [`defining_files`]) -> symbol, and the factor for those files
+    definitions: HashMap<String, String>,
+    definition_factor: f64,
+}
+
+impl<'a> Scorer<'a> {
+    /// A scorer for matches in `index`; `explain` keeps a [`TermScore`] per credit.
+    pub fn new(index: &'a ContentIndex, explain: bool) -> Self {
+        Scorer {
+            index, total_docs: index.files.len() as f64, explain, files: HashMap::new(),
+            definitions: HashMap::new(), definition_factor: 1.0,
+        }
+    }
+
+    /// Multiply the score of the files in `definitions` (from [`defining_files`]) by
+    /// `factor`, and record the symbol each defines.
+    pub fn boost_definitions(mut self, definitions: HashMap<String, String>, factor: f64) -> Self {
+        self.definitions = definitions;
+        self.definition_factor = factor;
+        self
+    }
+
+    /// Credit `lines` of `token`, found in `doc_freq` files, in file `file_id` to query
+    /// term number `term_no` (`term` in the explain breakdown). Returns the credit.
+    pub fn add(&mut self, file_id: u32, term_no: usize, term: &str, token: &str, doc_freq: usize, lines: &[u32]) -> f64 {
+        let occurrences = lines.len();
+        let file_tokens = self.index.file_token_counts.get(file_id as usize).map_or(1, |&c| c.max(1)) as f64;
+        let idf = idf(self.total_docs, doc_freq as f64);
+        let tf = occurrences as f64 / file_tokens;
+        let (file, terms) = self.files.entry(file_id).or_insert_with(|| (ScoredFile {
+            file_id, score: 0.0, tf_idf: 0.0, definition: None, occurrences: 0, lines: Vec::new(), terms_matched: 0, explain: Vec::new(),
+        }, HashSet::new()));
+        file.tf_idf += tf * idf;
+        file.occurrences += occurrences;
+        file.lines.extend_from_slice(lines);
+        terms.insert(term_no);
+        if self.explain {
+            file.explain.push(TermScore {
+                term: term.to_string(), token: token.to_string(), occurrences, file_tokens, doc_freq, tf, idf,
+            });
+        }
+        tf * idf
+    }
+
+    /// The scored files, in no particular order, with their lines sorted and deduplicated.
+    pub fn finish(self) -> Vec<ScoredFile> {
+        let (index, definitions, factor) = (self.index, self.definitions, self.definition_factor);
+        self.files.into_values().map(|(mut file, terms)| {
+            file.terms_matched = terms.len();
+            file.lines.sort_unstable();
+            file.lines.dedup();
+            file.definition = index.files.get(file.file_id as usize)
+                .and_then(|path| definitions.get(&path_key(path)))
+                .map(|symbol| DefinitionBoost { symbol: symbol.clone(), factor });
+            file.score = file.tf_idf * file.definition.as_ref().map_or(1.0, |d| d.factor);
+            file
+        }).collect()
+    }
+}
+
+/// Files of `defs` that define a type, method or function named like one of `terms`
+/// (lowercased), keyed by [`path_key`], with the symbol's name. Members such as fields
+/// and properties don't count: their names say little about what a file is for.
+pub fn defining_files(defs: &DefinitionIndex, terms: &[String]) -> HashMap<String, String> {
+    terms.iter()
+        .filter_map(|term| defs.name_index.get(term))
+        .flatten()
+        .filter_map(|&i| defs.definitions.get(i as usize))
+        .filter(|d| !matches!(d.kind, DefinitionKind::Property | DefinitionKind::Field | DefinitionKind::Variable
+            | DefinitionKind::EnumMember | DefinitionKind::Event | DefinitionKind::Column | DefinitionKind::SqlIndex))
+        .filter_map(|d| defs.files.get(d.file_id as usize).map(|path| (path_key(path), d.name.clone())))
+        .collect()
+}
+
+/// Lowercased path with forward slashes: the content and definition indexes may
+/// spell one file differently.
+pub fn path_key(path: &str) -> String {
+    path.replace('\\', "/").to_lowercase()
+}
+
+/// Inverse document frequency of a token found in `doc_freq` of `total_docs` files.
+pub fn idf(total_docs: f64, doc_freq: f64) -> f64 {
+    if doc_freq > 0.0 { (total_docs / doc_freq).ln() } else { 0.0 }
+}
+
+/// The whole-token, case-insensitive regex a [`MatchMode::Regex`] term stands for.
+pub fn token_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("(?i)^{}$", pattern))
+}
+
+/// Indices (into `trigram.tokens`) of the tokens containing `term` (lowercased).
+pub fn substring_token_ids(trigram: &TrigramIndex, term: &str) -> Vec<u32> {
+    if term.len() < 3 {
+        // No trigrams in the term: scan the tokens
+        return trigram.tokens.iter().enumerate()
+            .filter(|(_, tok)| tok.contains(term))
+            .map(|(i, _)| i as u32)
+            .collect();
+    }
+    // Intersect the trigram lists, then drop tokens that have the trigrams out of order
+    let mut candidates: Option<Vec<u32>> = None;
+    for tri in generate_trigrams(term) {
+        let Some(list) = trigram.trigram_map.get(&tri) else { return Vec::new() };
+        candidates = Some(match candidates {
+            None => list.clone(),
+            Some(prev) => sorted_intersect(&prev, list),
+        });
+    }
+    candidates.unwrap_or_default().into_iter()
+        .filter(|&i| trigram.tokens.get(i as usize).is_some_and(|tok| tok.contains(term)))
+        .collect()
+}
+
+/// Intersection of two ascending lists.
+pub fn sorted_intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j, mut out) = (0, 0, Vec::new());
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Run `query` against `index`. Fails only on an invalid regex.
+pub fn grep(index: &ContentIndex, query: &GrepQuery) -> Result<Vec<FileMatch>, regex::Error> {
+    let mut results = ranked(index, query)?;
+    if query.max_results > 0 {
+        results.truncate(query.max_results);
+    }
+    Ok(results)
+}
+
+/// [`grep`] as the MCP `search_grep` tool reports it: `files` (path, score,
+/// occurrences, termsMatched, lines) and a `summary` with the totals before
+/// `max_results`, the terms, the search mode and the index size.
+pub fn grep_response(index: &ContentIndex, query: &GrepQuery) -> Result<Value, regex::Error> {
+    let start = Instant::now();
+    let mut results = ranked(index, query)?;
+    let total_files = results.len();
+    let total_occurrences: usize = results.iter().map(|f| f.occurrences).sum();
+    if query.max_results > 0 {
+        results.truncate(query.max_results);
+    }
+    let terms: Vec<String> = match query.mode {
+        MatchMode::Phrase => query.terms.clone(),
+        _ => query.terms.iter().map(|t| t.to_lowercase()).collect(),
+    };
+    let files: Vec<Value> = results.iter().map(|f| match query.mode {
+        MatchMode::Phrase => json!({ "path": f.path, "occurrences": f.occurrences, "lines": f.lines }),
+        _ => json!({
+            "path": f.path,
+            "score": (f.score * 10000.0).round() / 10000.0,
+            "occurrences": f.occurrences,
+            "termsMatched": format!("{}/{}", f.terms_matched, terms.len()),
+            "lines": f.lines,
+        }),
+    }).collect();
+    let any_or_all = if query.all_terms { "and" } else { "or" };
+    Ok(json!({
+        "files": files,
+        "summary": {
+            "totalFiles": total_files,
+            "totalOccurrences": total_occurrences,
+            "termsSearched": terms,
+            "searchMode": match query.mode {
+                MatchMode::Substring => format!("substring-{}", any_or_all),
+                MatchMode::Exact => any_or_all.to_string(),
+                MatchMode::Regex => "regex".to_string(),
+                MatchMode::Phrase => "phrase".to_string(),
+            },
+            "indexFiles": index.files.len(),
+            "indexTokens": index.index.len(),
+            "searchTimeMs": start.elapsed().as_secs_f64() * 1000.0,
+        },
+    }))
+}
+
+/// Every match of `query`, best first, ties by path.
+fn ranked(index: &ContentIndex, query: &GrepQuery) -> Result<Vec<FileMatch>, regex::Error> {
+    let mut results = if query.mode == MatchMode::Phrase {
+        phrase_matches(index, query.terms.first().map_or("", String::as_str))
+    } else {
+        token_matches(index, query)?
+    };
+    best_first(&mut results);
+    Ok(results)
+}
+
+/// Sort matches best first, ties by path.
+pub(crate) fn best_first(results: &mut [FileMatch]) {
+    results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+}
+
+fn token_matches(index: &ContentIndex, query: &GrepQuery) -> Result<Vec<FileMatch>, regex::Error> {
+    let mut scorer = Scorer::new(index, false);
+    for (term_no, term) in query.terms.iter().enumerate() {
+        let term = term.to_lowercase();
+        let tokens: Vec<&str> = match query.mode {
+            MatchMode::Exact => vec![term.as_str()],
+            MatchMode::Substring => substring_token_ids(&index.trigram, &term).into_iter()
+                .filter_map(|i| index.trigram.tokens.get(i as usize).map(|t| &**t))
+                .collect(),
+            MatchMode::Regex => {
+                let re = token_regex(&term)?;
+                index.index.keys().filter(|k| re.is_match(k)).map(|k| &**k).collect()
+            }
+            MatchMode::Phrase => unreachable!("phrases are matched by lines"),
+        };
+        for token in tokens {
+            let Some(postings) = index.index.get(token) else { continue };
+            for posting in postings.iter().filter(|p| (p.file_id as usize) < index.files.len()) {
+                scorer.add(posting.file_id, term_no, &term, token, postings.len(), &posting.lines);
+            }
+        }
+    }
+    Ok(scorer.finish().into_iter()
+        .filter(|file| !query.all_terms || file.terms_matched == query.terms.len())
+        .map(|file| FileMatch {
+            path: index.files[file.file_id as usize].clone(),
+            score: file.score,
+            occurrences: file.occurrences,
+            terms_matched: file.terms_matched,
+            lines: file.lines,
+        })
+        .collect())
+}
+
+/// Files with lines containing `phrase`: the files holding all its tokens, read to
+/// find the lines (see [`PhraseSearch`]).
+fn phrase_matches(index: &ContentIndex, phrase: &str) -> Vec<FileMatch> {
+    let search = PhraseSearch::new(index, phrase);
+    search.paths.iter().filter_map(|path| search.verify(path)).collect()
+}
+
+/// A phrase search split into its index lookup and its file reads, so that
+/// [`Searcher::grep_async`] can spread the reads over the worker pool. Words match
+/// across any whitespace, ignoring case; a phrase with punctuation must appear as
+/// typed, ignoring case.
+pub(crate) struct PhraseSearch {
+    /// Files holding every token of the phrase
+    pub paths: Vec<String>,
+    phrase_lower: String,
+    has_punctuation: bool,
+    words: Regex,
+}
+
+impl PhraseSearch {
+    pub(crate) fn new(index: &ContentIndex, phrase: &str) -> Self {
+        let phrase_lower = phrase.to_lowercase();
+        let tokens = index.tokenizer.tokenize(&phrase_lower);
+        let has_punctuation = phrase.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace());
+        let words = Regex::new(&format!("(?i){}", tokens.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join(r"\s+")))
+            .expect("escaped words form a valid regex");
+        let paths = if tokens.is_empty() {
+            Vec::new()
+        } else {
+            phrase_candidates(index, &tokens).into_iter().filter_map(|id| index.files.get(id as usize).cloned()).collect()
+        };
+        PhraseSearch { paths, phrase_lower, has_punctuation, words }
+    }
+
+    /// Read `path` for the lines holding the phrase; `None` when it has none or can't be read.
+    pub(crate) fn verify(&self, path: &str) -> Option<FileMatch> {
+        let (content, _) = read_file_lossy(&path_from_string(path)).ok()?;
+        let lines: Vec<u32> = content.lines().enumerate()
+            .filter(|(_, line)| if self.has_punctuation { line.to_lowercase().contains(&self.phrase_lower) } else { self.words.is_match(line) })
+            .map(|(n, _)| n as u32 + 1)
+            .collect();
+        (!lines.is_empty()).then(|| FileMatch {
+            path: path.to_string(),
+            score: lines.len() as f64,
+            occurrences: lines.len(),
+            terms_matched: 1,
+            lines,
+        })
+    }
+}
+
+/// file_ids holding every one of `tokens`, sorted.
+fn phrase_candidates(index: &ContentIndex, tokens: &[String]) -> Vec<u32> {
+    let mut candidates: Option<Vec<u32>> = None;
+    for token in tokens {
+        let mut ids: Vec<u32> = index.index.get(token.as_str()).map_or_else(Vec::new, |p| p.iter().map(|p| p.file_id).collect());
+        ids.sort_unstable();
+        candidates = Some(match candidates {
+            None => ids,
+            Some(prev) => sorted_intersect(&prev, &ids),
+        });
+    }
+    candidates.unwrap_or_default()
+}
+
+impl Searcher {
+    /// Run `query` against the current index (see [`grep`]).
+    pub fn grep(&self, query: &GrepQuery) -> Result<Vec<FileMatch>, regex::Error> {
+        grep(&self.snapshot(), query)
+    }
+
+    /// [`grep_response`] against the current index.
+    pub fn grep_response(&self, query: &GrepQuery) -> Result<Value, regex::Error> {
+        grep_response(&self.snapshot(), query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexBuilder;
+
+    #[test]
+    fn test_grep_modes_rank_by_tf_idf() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("a.cs"), "class UserService {}\nIUserService service;\nvar user = new UserService();\n").unwrap();
+        std::fs::write(tmp.path().join("b.cs"), "class Order { UserService owner; }\nstring note = \"user   service\";\n").unwrap();
+        std::fs::write(tmp.path().join("c.cs"), "class Invoice { int total; }\n").unwrap();
+        let searcher = IndexBuilder::new(tmp.path()).build_searcher().unwrap();
+        let paths = |query: GrepQuery| -> Vec<String> {
+            searcher.grep(&query).unwrap().into_iter().map(|f| f.path.rsplit('/').next().unwrap().to_string()).collect()
+        };
+
+        assert_eq!(paths(GrepQuery::new("userservice").mode(MatchMode::Exact)), vec!["a.cs", "b.cs"]);
+        let top = &searcher.grep(&GrepQuery::new("userservice")).unwrap()[0];
+        assert_eq!((top.lines.clone(), top.occurrences), (vec![1, 2, 3], 3), "substring adds iuserservice");
+        assert_eq!(paths(GrepQuery::new("invoice,order").all_terms(true)), Vec::<String>::new());
+        assert_eq!(paths(GrepQuery::new("invoice,order")), vec!["c.cs", "b.cs"], "order is 1 of 8 tokens in b.cs, invoice 1 of 4 in c.cs");
+        assert_eq!(paths(GrepQuery::new("i.*service").mode(MatchMode::Regex)), vec!["a.cs"]);
+        assert_eq!(paths(GrepQuery::phrase("User Service")), vec!["b.cs"]);
+        assert!(searcher.grep(&GrepQuery::new("(").mode(MatchMode::Regex)).is_err());
+    }
+
+    #[test]
+    fn test_grep_response_has_the_search_grep_shape() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in ["a.cs", "b.cs", "c.cs"] {
+            std::fs::write(tmp.path().join(name), "class Order {}\nOrder order;\n").unwrap();
+        }
+        let searcher = IndexBuilder::new(tmp.path()).build_searcher().unwrap();
+
+        let query = GrepQuery::new("Order,missing").mode("exact".parse().unwrap()).max_results(2);
+        let response = searcher.grep_response(&query).unwrap();
+        let summary = &response["summary"];
+        assert_eq!((&summary["totalFiles"], &summary["totalOccurrences"]), (&json!(3), &json!(9)), "totals before maxResults");
+        assert_eq!((&summary["termsSearched"], &summary["searchMode"]), (&json!(["order", "missing"]), &json!("or")));
+        let files = response["files"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!((&files[0]["termsMatched"], &files[0]["lines"]), (&json!("1/2"), &json!([1, 2])));
+        assert_eq!(files[0]["score"], 0.0, "a token in every file has no weight");
+
+        let response = searcher.grep_response(&GrepQuery::phrase("order order")).unwrap();
+        assert_eq!(response["summary"]["searchMode"], "phrase");
+        assert!(response["files"][0].get("score").is_none());
+        assert!("fuzzy".parse::<MatchMode>().unwrap_err().contains("substring, exact, regex, phrase"));
+    }
+    #[test]
+    fn test_scorer_boosts_files_that_define_a_term() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("a.cs"), "class UserService {}\n").unwrap();
+        std::fs::write(tmp.path().join("b.cs"), "class Order { UserService userService; }\n").unwrap();
+        std::fs::write(tmp.path().join("c.cs"), "class Invoice {}\n").unwrap();
+        let index = IndexBuilder::new(tmp.path()).build_searcher().unwrap().snapshot();
+        let entry = |file_id: u32, name: &str, kind: DefinitionKind| crate::definition_index::DefinitionEntry {
+            file_id, name: name.to_string(), kind, line_start: 1, line_end: 1, parent: None, signature: None,
+            modifiers: Vec::new(), attributes: Vec::new(), base_types: Vec::new(),
+        };
+        // The definition index spells the paths with backslashes; b.cs only declares a field
+        let id = |name: &str| index.files.iter().position(|f| f.ends_with(name)).unwrap() as u32;
+        let mut defs = DefinitionIndex {
+            files: index.files.iter().map(|f| f.replace('/', "\\").to_uppercase()).collect(),
+            definitions: vec![entry(id("a.cs"), "UserService", DefinitionKind::Class), entry(id("b.cs"), "userService", DefinitionKind::Field)],
+            ..Default::default()
+        };
+        defs.name_index.insert("userservice".to_string(), vec![0, 1]);
+        let files = defining_files(&defs, &["userservice".to_string()]);
+        assert_eq!(files.values().collect::<Vec<_>>(), vec!["UserService"]);
+
+        let postings = &index.index["userservice"];
+        let mut scorer = Scorer::new(&index, false).boost_definitions(files, 3.0);
+        for posting in postings {
+            scorer.add(posting.file_id, 0, "userservice", "userservice", postings.len(), &posting.lines);
+        }
+        let scored = scorer.finish();
+        let file = |name: &str| scored.iter().find(|f| f.file_id == id(name)).unwrap();
+        let (a, b) = (file("a.cs"), file("b.cs"));
+        assert_eq!(a.definition, Some(DefinitionBoost { symbol: "UserService".to_string(), factor: 3.0 }));
+        assert_eq!(a.score, a.tf_idf * 3.0);
+        assert!(a.tf_idf > 0.0 && a.score > b.score, "b.cs mentions the term twice but does not define it");
+        assert_eq!((b.definition.as_ref(), b.score), (None, b.tf_idf));
+    }
+}
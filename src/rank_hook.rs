@@ -0,0 +1,365 @@
+//! WASM ranking hook: a sandboxed policy module that re-scores `search_grep` and
+//! `search_definitions` results after the built-in ranking, before truncation.
+//! ABI and limits: `user-stories/wasm-ranking-hook-design.md`.
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+/// The only ABI version this host speaks (`rank_abi_version()` must return it).
+const ABI_VERSION: i32 = 1;
+/// Fuel units per call; a module that runs out falls back to the built-in order.
+const FUEL_PER_CALL: u64 = 10_000_000;
+/// Linear memory limit of one instance.
+const MAX_MEMORY: usize = 16 << 20;
+/// Records passed per call; the rest keep the built-in order after the hooked prefix.
+const MAX_RECORDS: usize = 10_000;
+/// `path_off, path_len, name_off, name_len, kind, line, score`
+const RECORD_SIZE: usize = 28;
+const HEADER_SIZE: usize = 12;
+
+/// Which tool the results come from (the `tool` field of the input header).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankTool {
+    Grep = 0,
+    Definitions = 1,
+}
+
+/// One ranked result as the module sees it.
+pub struct Candidate<'a> {
+    /// Repo-relative, forward slashes ([`repo_path`])
+    pub path: String,
+    /// Definition name; empty for grep
+    pub name: &'a str,
+    /// `DefinitionKind` discriminant; `u32::MAX` for grep
+    pub kind: u32,
+    pub line: u32,
+    /// Built-in score, higher = better
+    pub score: f32,
+}
+
+/// What a hook call did to one result list, for `summary.rankHookDropped` / `rankHookError`.
+#[derive(Debug, Default, PartialEq)]
+pub struct HookOutcome {
+    pub dropped: usize,
+    pub error: Option<String>,
+}
+
+impl HookOutcome {
+    pub fn inject(&self, summary: &mut Value) {
+        match &self.error {
+            Some(error) => summary["rankHookError"] = json!(error),
+            None => summary["rankHookDropped"] = json!(self.dropped),
+        }
+    }
+}
+
+/// A compiled policy module. Compiled once at startup; every call gets a fresh
+/// instance, so no state survives between calls.
+pub struct RankHook {
+    engine: Engine,
+    module: Module,
+}
+
+/// One instance of the module with its exports resolved.
+struct Session {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    score: TypedFunc<(i32, i32, i32), i32>,
+    version: TypedFunc<(), i32>,
+}
+
+impl RankHook {
+    /// Load and validate the module at `path` (`--rank-hook`).
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Failed to read rank hook {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+            .map_err(|e| format!("Invalid rank hook {}: {}", path.display(), e))
+    }
+
+    /// Compile a module and check its exports and ABI version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes).map_err(|e| e.to_string())?;
+        if let Some(import) = module.imports().next() {
+            return Err(format!(
+                "the module imports {}::{}; rank hooks must not import anything",
+                import.module(), import.name()
+            ));
+        }
+        let hook = RankHook { engine, module };
+        let mut session = hook.session()?;
+        let version = session.version.call(&mut session.store, ()).map_err(|e| e.to_string())?;
+        if version != ABI_VERSION {
+            return Err(format!("rank_abi_version() returned {}, expected {}", version, ABI_VERSION));
+        }
+        Ok(hook)
+    }
+
+    fn session(&self) -> Result<Session, String> {
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| e.to_string())?;
+        let memory = instance.get_memory(&store, "memory")
+            .ok_or("missing export 'memory'")?;
+        let export = |name: &str, e: wasmi::Error| format!("export '{}': {}", name, e);
+        Ok(Session {
+            alloc: instance.get_typed_func(&store, "alloc").map_err(|e| export("alloc", e))?,
+            score: instance.get_typed_func(&store, "score").map_err(|e| export("score", e))?,
+            version: instance.get_typed_func(&store, "rank_abi_version")
+                .map_err(|e| export("rank_abi_version", e))?,
+            store,
+            memory,
+        })
+    }
+
+    /// Scores of `candidates`, one per record, from a fresh instance.
+    pub fn score(&self, tool: RankTool, candidates: &[Candidate]) -> Result<Vec<f32>, String> {
+        let input = encode(tool, candidates);
+        let mut session = self.session()?;
+        let Session { store, memory, alloc, score, .. } = &mut session;
+
+        let mut alloc_bytes = |len: usize| -> Result<i32, String> {
+            let len = i32::try_from(len).map_err(|_| "input too large".to_string())?;
+            alloc.call(&mut *store, len).map_err(|e| format!("alloc: {}", e))
+        };
+        let in_ptr = alloc_bytes(input.len())?;
+        let out_ptr = alloc_bytes(candidates.len() * 4)?;
+        memory.write(&mut *store, in_ptr as u32 as usize, &input)
+            .map_err(|e| format!("alloc returned an invalid pointer: {}", e))?;
+        let status = score.call(&mut *store, (in_ptr, input.len() as i32, out_ptr))
+            .map_err(|e| format!("score: {}", e))?;
+        if status != 0 {
+            return Err(format!("score returned {}", status));
+        }
+        let mut out = vec![0u8; candidates.len() * 4];
+        memory.read(&*store, out_ptr as u32 as usize, &mut out)
+            .map_err(|e| format!("alloc returned an invalid pointer: {}", e))?;
+        Ok(out.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+    }
+
+    /// Re-rank `results` (already in built-in order) by the module's scores: stable sort
+    /// descending, negative or NaN scores drop the result. Only the first 10,000 results
+    /// go to the module. When the call fails the order is left untouched.
+    pub fn apply<T>(&self, tool: RankTool, results: &mut Vec<T>, candidate: impl Fn(usize, &T) -> Candidate) -> HookOutcome {
+        let hooked = results.len().min(MAX_RECORDS);
+        let scores = {
+            let candidates: Vec<Candidate> = results[..hooked].iter().enumerate()
+                .map(|(rank, r)| candidate(rank, r))
+                .collect();
+            match self.score(tool, &candidates) {
+                Ok(scores) => scores,
+                Err(error) => return HookOutcome { dropped: 0, error: Some(error) },
+            }
+        };
+        let rest = results.split_off(hooked);
+        let mut scored: Vec<(f32, T)> = scores.into_iter().zip(results.drain(..))
+            .filter(|(score, _)| *score >= 0.0)
+            .collect();
+        let dropped = hooked - scored.len();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        results.extend(scored.into_iter().map(|(_, r)| r));
+        results.extend(rest);
+        HookOutcome { dropped, error: None }
+    }
+}
+
+/// `path` relative to the index `root`, with forward slashes, as the module sees it.
+pub fn repo_path(root: &str, path: &str) -> String {
+    let path = crate::clean_path(path);
+    crate::relative_path(&crate::clean_path(root), &path).to_string()
+}
+
+/// The input buffer of §2.2: header, fixed-size records, then the string pool.
+fn encode(tool: RankTool, candidates: &[Candidate]) -> Vec<u8> {
+    let pool_len: usize = candidates.iter().map(|c| c.path.len() + c.name.len()).sum();
+    let mut records = Vec::with_capacity(HEADER_SIZE + candidates.len() * RECORD_SIZE + pool_len);
+    let mut pool = Vec::with_capacity(pool_len);
+    for value in [tool as u32, candidates.len() as u32, pool_len as u32] {
+        records.extend_from_slice(&value.to_le_bytes());
+    }
+    let mut intern = |s: &str| {
+        let range = [pool.len() as u32, s.len() as u32];
+        pool.extend_from_slice(s.as_bytes());
+        range
+    };
+    for c in candidates {
+        let [path_off, path_len] = intern(&c.path);
+        let [name_off, name_len] = if c.name.is_empty() { [0, 0] } else { intern(c.name) };
+        for value in [path_off, path_len, name_off, name_len, c.kind, c.line] {
+            records.extend_from_slice(&value.to_le_bytes());
+        }
+        records.extend_from_slice(&c.score.to_le_bytes());
+    }
+    records.extend_from_slice(&pool);
+    records
+}
+
+/// A test policy: `body` computes the score of record `$i` (at `$rec`, strings at
+/// `$pool`) and leaves an f32 on the stack.
+#[cfg(test)]
+pub(crate) fn test_policy(body: &str) -> RankHook {
+    let wat = format!(r#"(module
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 1024))
+        (func (export "rank_abi_version") (result i32) i32.const 1)
+        (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (local.get $ptr) (local.get $len)))
+            (if (i32.gt_u (global.get $next) (i32.shl (memory.size) (i32.const 16)))
+                (then (drop (memory.grow (i32.sub
+                    (i32.add (i32.shr_u (global.get $next) (i32.const 16)) (i32.const 1))
+                    (memory.size))))))
+            (local.get $ptr))
+        (func (export "score") (param $ptr i32) (param $len i32) (param $out i32) (result i32)
+            (local $i i32) (local $rec i32) (local $count i32) (local $pool i32)
+            (local.set $count (i32.load offset=4 (local.get $ptr)))
+            (local.set $pool (i32.add (i32.add (local.get $ptr) (i32.const 12))
+                (i32.mul (local.get $count) (i32.const 28))))
+            (block $done (loop $next
+                (br_if $done (i32.ge_u (local.get $i) (local.get $count)))
+                (local.set $rec (i32.add (i32.add (local.get $ptr) (i32.const 12))
+                    (i32.mul (local.get $i) (i32.const 28))))
+                (f32.store (i32.add (local.get $out) (i32.mul (local.get $i) (i32.const 4)))
+                    {body})
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next)))
+            i32.const 0))"#);
+    RankHook::from_bytes(&wat::parse_str(wat).unwrap()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rerank(hook: &RankHook, paths: &[&str]) -> (Vec<String>, HookOutcome) {
+        let mut results: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+        let outcome = hook.apply(RankTool::Grep, &mut results, |rank, path| {
+            Candidate { path: path.to_string(), name: "", kind: u32::MAX, line: 1, score: 1.0 / (1.0 + rank as f32) }
+        });
+        (results, outcome)
+    }
+
+    #[test]
+    fn test_hook_can_reverse_the_built_in_order() {
+        // score = record index: the last result ranks first
+        let hook = test_policy("(f32.convert_i32_u (local.get $i))");
+        let (results, outcome) = rerank(&hook, &["a.cs", "b.cs", "c.cs"]);
+        assert_eq!(results, ["c.cs", "b.cs", "a.cs"]);
+        assert_eq!(outcome, HookOutcome { dropped: 0, error: None });
+    }
+
+    #[test]
+    fn test_negative_scores_drop_results_and_ties_keep_the_built_in_order() {
+        // Drop paths starting with 'g' (generated/), keep the rest at 1.0
+        let hook = test_policy(r#"(select (f32.const -1) (f32.const 1)
+            (i32.eq (i32.load8_u (i32.add (local.get $pool) (i32.load (local.get $rec)))) (i32.const 103)))"#);
+        let (results, outcome) = rerank(&hook, &["src/a.cs", "generated/b.cs", "src/c.cs", "gen.cs"]);
+        assert_eq!(results, ["src/a.cs", "src/c.cs"]);
+        assert_eq!(outcome.dropped, 2);
+        let mut summary = json!({});
+        outcome.inject(&mut summary);
+        assert_eq!(summary["rankHookDropped"], 2);
+    }
+
+    #[test]
+    fn test_hook_reads_the_built_in_score() {
+        // score = -built-in score + 1: reverses the order without dropping anything
+        let hook = test_policy("(f32.sub (f32.const 1) (f32.load offset=24 (local.get $rec)))");
+        let (results, _) = rerank(&hook, &["a", "b", "c"]);
+        assert_eq!(results, ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_trap_keeps_the_built_in_order() {
+        let hook = test_policy("(unreachable)");
+        let (results, outcome) = rerank(&hook, &["a", "b"]);
+        assert_eq!(results, ["a", "b"]);
+        assert!(outcome.error.as_deref().unwrap().starts_with("score:"), "{:?}", outcome.error);
+        let mut summary = json!({});
+        outcome.inject(&mut summary);
+        assert!(summary["rankHookError"].is_string());
+        assert!(summary.get("rankHookDropped").is_none());
+    }
+
+    #[test]
+    fn test_fuel_exhaustion_keeps_the_built_in_order() {
+        let hook = test_policy("(loop $forever (br $forever)) (f32.const 0)");
+        let (results, outcome) = rerank(&hook, &["a", "b"]);
+        assert_eq!(results, ["a", "b"]);
+        assert!(outcome.error.is_some());
+    }
+
+    #[test]
+    fn test_records_past_the_limit_keep_their_order_after_the_hooked_prefix() {
+        let hook = test_policy("(f32.convert_i32_u (local.get $i))");
+        let paths: Vec<String> = (0..MAX_RECORDS + 2).map(|i| i.to_string()).collect();
+        let mut results = paths.clone();
+        let outcome = hook.apply(RankTool::Grep, &mut results, |_, path| {
+            Candidate { path: path.to_string(), name: "", kind: u32::MAX, line: 1, score: 1.0 }
+        });
+        assert_eq!(outcome.error, None);
+        assert_eq!(results[0], (MAX_RECORDS - 1).to_string());
+        assert_eq!(results[MAX_RECORDS - 1], "0");
+        assert_eq!(&results[MAX_RECORDS..], &paths[MAX_RECORDS..]);
+    }
+
+    #[test]
+    fn test_paths_are_relative_to_the_root_with_forward_slashes() {
+        assert_eq!(repo_path("/repo", "/repo/src/a.cs"), "src/a.cs");
+        assert_eq!(repo_path("C:\\repo", "C:\\repo\\src\\a.cs"), "src/a.cs");
+        assert_eq!(repo_path("/other", "/repo/a.cs"), "/repo/a.cs");
+    }
+
+    #[test]
+    fn test_input_layout() {
+        let mut input = encode(RankTool::Definitions, &[
+            Candidate { path: "src/a.cs".to_string(), name: "Foo", kind: 3, line: 7, score: 0.5 },
+        ]);
+        assert_eq!(input.len(), HEADER_SIZE + RECORD_SIZE + "src/a.csFoo".len());
+        let words: Vec<u32> = input.drain(..HEADER_SIZE + RECORD_SIZE - 4).collect::<Vec<_>>()
+            .chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+        assert_eq!(words, [1, 1, 11, 0, 8, 8, 3, 3, 7]);
+        assert_eq!(f32::from_le_bytes([input[0], input[1], input[2], input[3]]), 0.5);
+        assert_eq!(&input[4..], b"src/a.csFoo");
+    }
+
+    #[test]
+    fn test_modules_with_imports_or_another_abi_are_rejected() {
+        let import = r#"(module (import "env" "log" (func)) (memory (export "memory") 1))"#;
+        let err = RankHook::from_bytes(&wat::parse_str(import).unwrap()).err().unwrap();
+        assert!(err.contains("env::log"), "{}", err);
+
+        let v2 = r#"(module (memory (export "memory") 1)
+            (func (export "rank_abi_version") (result i32) i32.const 2)
+            (func (export "alloc") (param i32) (result i32) i32.const 0)
+            (func (export "score") (param i32 i32 i32) (result i32) i32.const 0))"#;
+        let err = RankHook::from_bytes(&wat::parse_str(v2).unwrap()).err().unwrap();
+        assert!(err.contains("returned 2"), "{}", err);
+
+        let no_score = r#"(module (memory (export "memory") 1)
+            (func (export "rank_abi_version") (result i32) i32.const 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 0))"#;
+        let err = RankHook::from_bytes(&wat::parse_str(no_score).unwrap()).err().unwrap();
+        assert!(err.contains("'score'"), "{}", err);
+    }
+
+    #[test]
+    fn test_memory_over_the_limit_is_rejected() {
+        // 300 pages = 18.75 MiB > 16 MiB
+        let big = r#"(module (memory (export "memory") 300)
+            (func (export "rank_abi_version") (result i32) i32.const 1))"#;
+        assert!(RankHook::from_bytes(&wat::parse_str(big).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Layout of serialized types, traced from their `Deserialize` impls.
+//!
+//! [`layout`] runs `T::deserialize` against a deserializer that makes up every value
+//! asked of it (one element per sequence and map, `Some` for options, the first variant
+//! of each enum) and records each struct and enum it meets on the way: field names in
+//! declaration order with their Rust types, and variant names. Bincode writes struct
+//! fields in that order without names, so for the index files this is the on-disk
+//! layout, taken from the code that reads them rather than from hand-written docs.
+
+use std::any::type_name;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde_json::{json, Value};
+
+/// Nesting below which options, sequences and maps are traced as empty, so recursive
+/// types terminate
+const MAX_DEPTH: usize = 32;
+
+enum TypeLayout {
+    /// `(field, type)` in declaration order; the type is `?` for fields never read
+    Struct(Vec<(&'static str, String)>),
+    Enum(&'static [&'static str]),
+}
+
+type Registry = RefCell<BTreeMap<&'static str, TypeLayout>>;
+
+/// `{root, types: {Name: {fields: [{name, type}]} | {variants: [..]}}}` for `T` and every
+/// struct and enum reachable from it. Fails for types that need a self-describing format
+/// (`#[serde(flatten)]`, untagged enums) or that reject the made-up values.
+pub fn layout<T: DeserializeOwned>() -> Result<Value, String> {
+    let registry = Registry::default();
+    T::deserialize(Tracer { registry: &registry, depth: 0 }).map_err(|e| e.0)?;
+    let types: serde_json::Map<String, Value> = registry.into_inner().into_iter().map(|(name, layout)| {
+        let layout = match layout {
+            TypeLayout::Struct(fields) => json!({
+                "fields": fields.into_iter().map(|(name, ty)| json!({ "name": name, "type": ty })).collect::<Vec<_>>(),
+            }),
+            TypeLayout::Enum(variants) => json!({ "variants": variants }),
+        };
+        (name.to_string(), layout)
+    }).collect();
+    Ok(json!({ "root": short_type_name(type_name::<T>()), "types": types }))
+}
+
+/// `alloc::vec::Vec<search::Posting>` → `Vec<Posting>`.
+fn short_type_name(full: &str) -> String {
+    static PATH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*::").unwrap());
+    PATH.replace_all(full, "").into_owned()
+}
+
+#[derive(Debug)]
+struct TraceError(String);
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl de::Error for TraceError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        TraceError(msg.to_string())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Tracer<'a> {
+    registry: &'a Registry,
+    depth: usize,
+}
+
+impl<'a> Tracer<'a> {
+    fn deeper(self) -> Self {
+        Tracer { depth: self.depth + 1, ..self }
+    }
+
+    /// Elements to make up for a sequence or map at this depth
+    fn items(self) -> usize {
+        usize::from(self.depth < MAX_DEPTH)
+    }
+}
+
+macro_rules! trace_default {
+    ($($method:ident => $visit:ident($value:expr)),* $(,)?) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+            visitor.$visit($value)
+        })*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Tracer<'a> {
+    type Error = TraceError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        Err(TraceError("types that need a self-describing format cannot be traced".to_string()))
+    }
+
+    trace_default! {
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_i128 => visit_i128(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_u128 => visit_u128(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char('a'),
+        deserialize_str => visit_str(""),
+        deserialize_string => visit_str(""),
+        deserialize_bytes => visit_bytes(&[]),
+        deserialize_byte_buf => visit_bytes(&[]),
+        deserialize_identifier => visit_u32(0),
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        if self.items() == 0 { visitor.visit_none() } else { visitor.visit_some(self.deeper()) }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_newtype_struct(self.deeper())
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_seq(Elements { tracer: self.deeper(), remaining: self.items(), fields: None })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_seq(Elements { tracer: self.deeper(), remaining: len, fields: None })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_map(Entries { tracer: self.deeper(), remaining: self.items() })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, TraceError> {
+        // Field types are filled in as the visitor reads them; a type seen before is
+        // only made up again, not re-recorded
+        let record = !self.registry.borrow().contains_key(name);
+        if record {
+            let layout = TypeLayout::Struct(fields.iter().map(|&f| (f, "?".to_string())).collect());
+            self.registry.borrow_mut().insert(name, layout);
+        }
+        visitor.visit_seq(Elements { tracer: self.deeper(), remaining: fields.len(), fields: record.then_some((name, 0)) })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, TraceError> {
+        self.registry.borrow_mut().entry(name).or_insert(TypeLayout::Enum(variants));
+        visitor.visit_enum(self.deeper())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+}
+
+/// Made-up elements of a sequence, tuple or struct. For a struct being recorded,
+/// `fields` is its name and the position of the next field.
+struct Elements<'a> {
+    tracer: Tracer<'a>,
+    remaining: usize,
+    fields: Option<(&'static str, usize)>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for Elements<'a> {
+    type Error = TraceError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, TraceError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        if let Some((name, position)) = &mut self.fields {
+            if let Some(TypeLayout::Struct(fields)) = self.tracer.registry.borrow_mut().get_mut(name)
+                && let Some(field) = fields.get_mut(*position)
+            {
+                field.1 = short_type_name(type_name::<T::Value>());
+            }
+            *position += 1;
+        }
+        seed.deserialize(self.tracer).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+struct Entries<'a> {
+    tracer: Tracer<'a>,
+    remaining: usize,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for Entries<'a> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, TraceError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(self.tracer).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, TraceError> {
+        seed.deserialize(self.tracer)
+    }
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for Tracer<'a> {
+    type Error = TraceError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), TraceError> {
+        let first: de::value::U32Deserializer<TraceError> = 0u32.into_deserializer();
+        Ok((seed.deserialize(first)?, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for Tracer<'a> {
+    type Error = TraceError;
+
+    fn unit_variant(self) -> Result<(), TraceError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, TraceError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, TraceError> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, TraceError> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Outer {
+        name: Arc<str>,
+        postings: HashMap<String, Vec<Inner>>,
+        parent: Option<Box<Outer>>,
+        #[serde(default)]
+        kind: Kind,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Inner {
+        file_id: u32,
+        lines: Vec<u32>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, Default)]
+    enum Kind {
+        #[default]
+        Class,
+        Method { arity: u8 },
+    }
+
+    #[test]
+    fn test_layout_traces_nested_types_in_field_order() {
+        let layout = layout::<Outer>().unwrap();
+        assert_eq!(layout["root"], "Outer");
+        assert_eq!(layout["types"]["Outer"]["fields"], json!([
+            { "name": "name", "type": "Arc<str>" },
+            { "name": "postings", "type": "HashMap<String, Vec<Inner>>" },
+            { "name": "parent", "type": "Option<Box<Outer>>" },
+            { "name": "kind", "type": "Kind" },
+        ]));
+        // Only reachable through the map value and the vector element
+        assert_eq!(layout["types"]["Inner"]["fields"][1], json!({ "name": "lines", "type": "Vec<u32>" }));
+        assert_eq!(layout["types"]["Kind"]["variants"], json!(["Class", "Method"]));
+    }
+}
@@ -0,0 +1,133 @@
+//! Shared handle to a live content index, read through immutable snapshots.
+//!
+//! A [`Searcher`] holds the current index behind an `Arc`. [`Searcher::snapshot`]
+//! clones that `Arc` under a lock held for a pointer copy, so a query keeps one
+//! consistent generation for as long as it runs, without holding any lock. Writers
+//! go through [`Searcher::update`]. When no query holds a snapshot, it edits the index
+//! in place under the write lock: nobody can see the edit half done, and a snapshot
+//! taken meanwhile waits for the edit, not for a copy. Otherwise it is copy-on-write:
+//! it copies the current index and edits the copy without holding the lock, then takes
+//! the lock only to swap the copy in, so a running query never sees half a watcher
+//! batch. Only batches that overlap a query pay for a copy of the index.
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::lock_stats::{self, Lock};
+use crate::ContentIndex;
+
+/// Cheaply cloneable handle to the current [`ContentIndex`]. Clones share the index.
+#[derive(Clone, Debug)]
+pub struct Searcher {
+    current: Arc<RwLock<Arc<ContentIndex>>>,
+    /// Held by writers from reading the current index to swapping in its successor,
+    /// so concurrent updates apply one after another instead of overwriting each other
+    writer: Arc<Mutex<()>>,
+}
+
+impl Searcher {
+    pub fn new(index: ContentIndex) -> Self {
+        Searcher { current: Arc::new(RwLock::new(Arc::new(index))), writer: Arc::new(Mutex::new(())) }
+    }
+
+    /// Immutable view of the current index. Later updates don't affect it.
+    pub fn snapshot(&self) -> Arc<ContentIndex> {
+        Arc::clone(&lock_stats::timed(Lock::Content, || self.current.read()).unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Make `index` the current index. Snapshots taken before keep the old one.
+    pub fn replace(&self, index: ContentIndex) {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        *lock_stats::timed(Lock::Content, || self.current.write()).unwrap_or_else(|e| e.into_inner()) = Arc::new(index);
+    }
+
+    /// Apply `edit` to the current index: snapshots see the index either before or
+    /// after it. Without outstanding snapshots the index is edited in place, and
+    /// snapshots taken meanwhile wait for the edit; otherwise `edit` gets a copy, which
+    /// is made current once edited, and taking a snapshot never waits. Concurrent
+    /// updates run one at a time.
+    pub fn update<T>(&self, edit: impl FnOnce(&mut ContentIndex) -> T) -> T {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        {
+            let mut current = lock_stats::timed(Lock::Content, || self.current.write()).unwrap_or_else(|e| e.into_inner());
+            // The lock holds the only reference, and no snapshot can be taken while we write
+            if let Some(index) = Arc::get_mut(&mut current) {
+                return edit(index);
+            }
+        }
+        let base = self.snapshot();
+        let mut next = ContentIndex::clone(&base);
+        let result = edit(&mut next);
+        let mut current = lock_stats::timed(Lock::Content, || self.current.write()).unwrap_or_else(|e| e.into_inner());
+        // Writers hold `writer`, so nothing can have replaced `base` meanwhile
+        debug_assert!(Arc::ptr_eq(&current, &base), "index replaced during an update");
+        *current = Arc::new(next);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SkippedFiles, TokenizerSettings, TrigramIndex};
+    use std::collections::HashMap;
+
+    fn index_with_files(files: &[&str]) -> ContentIndex {
+        ContentIndex {
+            root: ".".to_string(),
+            created_at: 0,
+            max_age_secs: 3600,
+            files: files.iter().map(|f| f.to_string()).collect(),
+            index: HashMap::new(),
+            total_tokens: 0,
+            extensions: vec!["cs".to_string()],
+            file_token_counts: vec![0; files.len()],
+            trigram: TrigramIndex::default(),
+            trigram_dirty: false,
+            forward: None,
+            path_to_id: None,
+            comment_markers: Vec::new(),
+            ticket_refs: Vec::new(),
+            revision: None,
+            dir_blooms: HashMap::new(),
+            skipped: SkippedFiles::default(),
+            tokenizer: TokenizerSettings::default(),
+            walk_limits: Default::default(),
+            stop_token_pct: 0,
+            max_file_size: 0,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_is_isolated_from_updates() {
+        let searcher = Searcher::new(index_with_files(&["a.cs"]));
+        let before = searcher.snapshot();
+
+        // A live snapshot makes the update work on a copy
+        searcher.update(|idx| idx.files.push("b.cs".to_string()));
+        assert_eq!(before.files, vec!["a.cs"]);
+        assert_eq!(searcher.snapshot().files, vec!["a.cs", "b.cs"]);
+
+        // While a snapshot is out, snapshots taken during an update don't wait for it
+        // and see the old index
+        let during = searcher.snapshot();
+        searcher.update(|idx| {
+            assert_eq!(searcher.snapshot().files, vec!["a.cs", "b.cs"]);
+            idx.files.push("c.cs".to_string());
+        });
+        assert_eq!(searcher.snapshot().files, vec!["a.cs", "b.cs", "c.cs"]);
+        assert_eq!(before.files, vec!["a.cs"]);
+
+        // Without snapshots the index is edited where it is
+        drop((before, during));
+        let address = Arc::as_ptr(&searcher.snapshot());
+        searcher.update(|idx| idx.files.push("d.cs".to_string()));
+        let after = searcher.snapshot();
+        assert_eq!(Arc::as_ptr(&after), address, "edited in place");
+        assert_eq!(after.files, vec!["a.cs", "b.cs", "c.cs", "d.cs"]);
+        drop(after);
+
+        let clone = searcher.clone();
+        clone.replace(index_with_files(&[]));
+        assert!(searcher.snapshot().files.is_empty(), "clones share the current index");
+    }
+}
@@ -0,0 +1,90 @@
+//! Inverted-file (IVF) approximate nearest neighbour index over unit vectors.
+//!
+//! Vectors are clustered around `√n` centroids with spherical k-means; a query scans
+//! the lists of its `nprobe` closest centroids instead of every vector. Below
+//! [`MIN_VECTORS`] there are no lists and every query is an exact scan.
+
+use serde::{Deserialize, Serialize};
+
+/// Fewer vectors than this are scanned exhaustively.
+pub const MIN_VECTORS: usize = 2000;
+
+/// Upper bound on the number of lists.
+const MAX_LISTS: usize = 256;
+
+/// Vectors k-means trains on; the rest are only assigned to the trained centroids.
+const TRAINING_SAMPLE: usize = 16_384;
+
+const KMEANS_ITERATIONS: usize = 6;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IvfIndex {
+    pub dimensions: usize,
+    /// Unit centroids, `dimensions` floats each. Empty = exact scan.
+    pub centroids: Vec<f32>,
+    /// Vector ids per centroid.
+    pub lists: Vec<Vec<u32>>,
+}
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+impl IvfIndex {
+    /// Cluster `vectors` (`dimensions` floats each).
+    pub fn build(vectors: &[f32], dimensions: usize) -> Self {
+        let n = vectors.len().checked_div(dimensions).unwrap_or(0);
+        if n < MIN_VECTORS {
+            return Self { dimensions, ..Self::default() };
+        }
+        let k = ((n as f64).sqrt() as usize).min(MAX_LISTS);
+        let row = |i: usize| &vectors[i * dimensions..(i + 1) * dimensions];
+
+        // Evenly spaced samples: deterministic, and spread over the files chunks come from
+        let step = (n / TRAINING_SAMPLE).max(1);
+        let sample: Vec<usize> = (0..n).step_by(step).collect();
+        let mut centroids: Vec<f32> = (0..k).flat_map(|c| row(sample[c * sample.len() / k]).to_vec()).collect();
+        for _ in 0..KMEANS_ITERATIONS {
+            let mut sums = vec![0.0f32; k * dimensions];
+            for &i in &sample {
+                let c = nearest(&centroids, dimensions, row(i));
+                sums[c * dimensions..(c + 1) * dimensions].iter_mut().zip(row(i)).for_each(|(s, x)| *s += x);
+            }
+            for c in 0..k {
+                let sum = &mut sums[c * dimensions..(c + 1) * dimensions];
+                if sum.iter().any(|&x| x != 0.0) {
+                    super::embedder::normalize(sum);
+                    centroids[c * dimensions..(c + 1) * dimensions].copy_from_slice(sum);
+                }
+            }
+        }
+
+        let mut lists = vec![Vec::new(); k];
+        for i in 0..n {
+            lists[nearest(&centroids, dimensions, row(i))].push(i as u32);
+        }
+        Self { dimensions, centroids, lists }
+    }
+
+    /// Ids of the vectors to score for `query`: the lists of its `nprobe` closest
+    /// centroids, or `None` for an exact scan.
+    pub fn candidates(&self, query: &[f32], nprobe: usize) -> Option<Vec<u32>> {
+        if self.lists.is_empty() {
+            return None;
+        }
+        let mut order: Vec<(usize, f32)> = self.centroids.chunks(self.dimensions)
+            .map(|c| dot(c, query)).enumerate().collect();
+        order.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Some(order.iter().take(nprobe.max(1)).flat_map(|&(c, _)| self.lists[c].iter().copied()).collect())
+    }
+
+    /// Lists probed by default: an eighth of them, at least 4.
+    pub fn default_nprobe(&self) -> usize {
+        (self.lists.len() / 8).max(4)
+    }
+}
+
+fn nearest(centroids: &[f32], dimensions: usize, v: &[f32]) -> usize {
+    centroids.chunks(dimensions).map(|c| dot(c, v)).enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1)).map_or(0, |(i, _)| i)
+}
@@ -0,0 +1,388 @@
+//! Embedding backends for the semantic index.
+//!
+//! - [`HashingEmbedder`] (`embeddings = "hash"`, the default) needs no model: it hashes
+//!   the stemmed words and identifier parts of a text into a fixed-size vector. Queries
+//!   find code that uses the same vocabulary in any casing or word form ("retry failed
+//!   payments" finds `RetryFailedPaymentAsync`), but not synonyms.
+//! - [`HttpEmbedder`] (`embeddings = "http://host:port/v1/embeddings"`) posts batches to
+//!   an OpenAI-compatible embeddings endpoint: a local model server (Ollama, llama.cpp,
+//!   text-embeddings-inference), or a hosted API at an `https://` URL. HTTPS checks the
+//!   server certificate against the Mozilla root certificates built into the binary and
+//!   those of the operating system (which a TLS-inspecting proxy's CA is added to).
+//!
+//! Every backend returns unit vectors, so a dot product is the cosine similarity.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::pki_types::ServerName;
+
+use serde_json::{json, Value};
+
+/// Computes embedding vectors for texts.
+pub trait Embedder: Send {
+    /// Backend and model. An index built by another embedder is rebuilt, not reused.
+    fn id(&self) -> String;
+    /// One unit vector per text, all of the same length.
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// `[semantic]` settings of `.search-index.toml`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SemanticConfig {
+    /// `"hash"` or the URL of an OpenAI-compatible embeddings endpoint. `None` = hash.
+    pub embeddings: Option<String>,
+    /// Model name sent to the endpoint.
+    pub model: Option<String>,
+    /// Environment variable holding the API key, sent as a bearer token.
+    pub api_key_env: Option<String>,
+}
+
+/// The embedder `config` selects.
+pub fn from_config(config: &SemanticConfig) -> Result<Box<dyn Embedder>, String> {
+    match config.embeddings.as_deref() {
+        None | Some("hash") => Ok(Box::new(HashingEmbedder::default())),
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            let api_key = match &config.api_key_env {
+                Some(var) => Some(std::env::var(var)
+                    .map_err(|_| format!("[semantic] api-key-env: environment variable {} is not set", var))?),
+                None => None,
+            };
+            Ok(Box::new(HttpEmbedder::new(url, config.model.clone(), api_key)?))
+        }
+        Some(other) => Err(format!(
+            "[semantic] embeddings = \"{}\": expected \"hash\" or an http:// or https:// embeddings URL", other)),
+    }
+}
+
+// ─── Hashing ────────────────────────────────────────────────────────
+
+/// Words too common in questions and code to say anything about a chunk.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "get",
+    "how", "i", "if", "in", "is", "it", "of", "on", "or", "set", "the", "this", "that", "to",
+    "we", "what", "when", "where", "which", "who", "why", "with", "var", "let", "const", "new",
+    "return", "public", "private", "protected", "internal", "static", "void", "async", "await",
+    "string", "int", "bool", "true", "false", "null", "else", "using", "import", "export",
+];
+
+/// Feature hashing of stemmed words and identifier parts, with sublinear term frequency.
+pub struct HashingEmbedder {
+    pub dimensions: usize,
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self { dimensions: 256 }
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn id(&self) -> String {
+        format!("hash-{}", self.dimensions)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Ok(texts.iter().map(|t| self.embed_one(t)).collect())
+    }
+}
+
+impl HashingEmbedder {
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut counts: std::collections::HashMap<String, u32> = std::collections::HashMap::new();
+        for word in words(text) {
+            if !STOP_WORDS.contains(&word.as_str()) {
+                *counts.entry(word).or_insert(0) += 1;
+            }
+        }
+        let mut vector = vec![0.0f32; self.dimensions];
+        for (word, count) in counts {
+            let hash = search::stable_hash(&[word.as_bytes()]);
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dimensions as u64) as usize] += sign * (1.0 + (count as f32).ln());
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+/// Lowercased, stemmed words of `text`, splitting identifiers at `_` and case changes:
+/// `retryFailedPayments` → `retry`, `fail`, `payment`.
+pub fn words(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for token in text.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<char> = token.chars().collect();
+        let mut start = 0;
+        for i in 1..=chars.len() {
+            let boundary = i == chars.len()
+                || (chars[i].is_uppercase() && chars[i - 1].is_lowercase())
+                || (chars[i].is_uppercase() && chars.get(i + 1).is_some_and(|c| c.is_lowercase()) && chars[i - 1].is_uppercase())
+                || (chars[i].is_alphabetic() != chars[i - 1].is_alphabetic());
+            if boundary {
+                let part: String = chars[start..i].iter().collect::<String>().to_lowercase();
+                if part.len() > 1 && !part.chars().all(|c| c.is_ascii_digit()) {
+                    out.push(stem(&part));
+                }
+                start = i;
+            }
+        }
+    }
+    out
+}
+
+/// Strip a plural or verb ending, so "payments", "failed" and "retrying" meet
+/// "payment", "fail" and "retry".
+fn stem(word: &str) -> String {
+    for (suffix, replacement) in [("ies", "y"), ("ing", ""), ("ed", ""), ("es", ""), ("s", "")] {
+        if let Some(base) = word.strip_suffix(suffix)
+            && base.len() >= 3
+            && !(suffix == "s" && base.ends_with('s'))
+        {
+            return format!("{}{}", base, replacement);
+        }
+    }
+    word.to_string()
+}
+
+/// Scale `v` to unit length (a zero vector stays zero).
+pub fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+// ─── HTTP ───────────────────────────────────────────────────────────
+
+/// Texts per request.
+const HTTP_BATCH: usize = 64;
+
+/// Characters of a text sent to the endpoint; longer chunks are cut (models cap tokens).
+const HTTP_MAX_TEXT_CHARS: usize = 6000;
+
+/// Time to connect, and to send one request; an unreachable server fails fast.
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const HTTP_WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time to wait for a response: a local model on CPU can take long for a full batch.
+const HTTP_READ_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A connection to the embeddings server: plain TCP, or TLS over it.
+trait Connection: Read + Write {}
+
+impl<T: Read + Write> Connection for T {}
+
+/// An OpenAI-compatible `POST /v1/embeddings` endpoint over HTTP or HTTPS.
+pub struct HttpEmbedder {
+    /// `host:port`
+    host: String,
+    path: String,
+    model: Option<String>,
+    api_key: Option<String>,
+    /// Set for an `https://` URL: the client settings and the name the certificate must carry
+    tls: Option<(Arc<rustls::ClientConfig>, ServerName<'static>)>,
+}
+
+impl HttpEmbedder {
+    pub fn new(url: &str, model: Option<String>, api_key: Option<String>) -> Result<Self, String> {
+        let (rest, https) = match (url.strip_prefix("http://"), url.strip_prefix("https://")) {
+            (Some(rest), _) => (rest, false),
+            (_, Some(rest)) => (rest, true),
+            _ => return Err(format!("Not an http:// or https:// URL: {}", url)),
+        };
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/v1/embeddings"),
+        };
+        if host.is_empty() {
+            return Err(format!("No host in embeddings URL: {}", url));
+        }
+        let tls = if https {
+            let name = match host.strip_prefix('[') {
+                Some(ipv6) => ipv6.split(']').next().unwrap_or(ipv6),
+                None => host.split(':').next().unwrap_or(host),
+            };
+            let name = ServerName::try_from(name.to_string())
+                .map_err(|e| format!("Bad host in embeddings URL {}: {}", url, e))?;
+            Some((tls_config()?, name))
+        } else {
+            None
+        };
+        let host = if host.contains(':') { host.to_string() } else { format!("{}:{}", host, if https { 443 } else { 80 }) };
+        Ok(Self { host, path: path.to_string(), model, api_key, tls })
+    }
+
+    /// A connection to the server, with the TLS handshake done for HTTPS.
+    fn connect(&self) -> Result<Box<dyn Connection>, String> {
+        let tcp = self.connect_tcp()?;
+        let _ = tcp.set_write_timeout(Some(HTTP_WRITE_TIMEOUT));
+        let _ = tcp.set_read_timeout(Some(HTTP_READ_TIMEOUT));
+        let Some((config, name)) = &self.tls else { return Ok(Box::new(tcp)) };
+        let mut stream = rustls::StreamOwned::new(
+            rustls::ClientConnection::new(Arc::clone(config), name.clone())
+                .map_err(|e| format!("Cannot start TLS with {}: {}", self.host, e))?,
+            tcp);
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock)
+                .map_err(|e| format!("TLS handshake with embeddings server {} failed: {}", self.host, e))?;
+        }
+        Ok(Box::new(stream))
+    }
+
+    fn connect_tcp(&self) -> Result<TcpStream, String> {
+        let addrs = self.host.to_socket_addrs()
+            .map_err(|e| format!("Cannot resolve embeddings server {}: {}", self.host, e))?;
+        let mut last_error = None;
+        for addr in addrs {
+            match TcpStream::connect_timeout(&addr, HTTP_CONNECT_TIMEOUT) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(match last_error {
+            Some(e) => format!("Cannot connect to embeddings server {}: {}", self.host, e),
+            None => format!("Cannot resolve embeddings server {}: no addresses", self.host),
+        })
+    }
+
+    fn post(&self, body: &str) -> Result<Value, String> {
+        let mut stream = self.connect()?;
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.path, self.host, body.len());
+        if let Some(key) = &self.api_key {
+            request.push_str(&format!("Authorization: Bearer {}\r\n", key));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream.write_all(request.as_bytes()).and_then(|_| stream.flush())
+            .map_err(|e| format!("Embeddings request failed: {}", e))?;
+
+        let mut reader = BufReader::new(stream);
+        let (status, payload) = read_response(&mut reader).map_err(|e| format!("Embeddings response: {}", e))?;
+        if !(200..300).contains(&status) {
+            return Err(format!("Embeddings server returned HTTP {}: {}", status,
+                String::from_utf8_lossy(&payload).chars().take(300).collect::<String>()));
+        }
+        serde_json::from_slice(&payload).map_err(|e| format!("Embeddings response is not JSON: {}", e))
+    }
+}
+
+/// TLS client settings trusting the Mozilla and the operating system's root certificates,
+/// shared by every HTTPS embedder. System certificates that fail to load are skipped.
+fn tls_config() -> Result<Arc<rustls::ClientConfig>, String> {
+    static CONFIG: std::sync::OnceLock<Result<Arc<rustls::ClientConfig>, String>> = std::sync::OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let mut roots = rustls::RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+        roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("Cannot set up TLS: {}", e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Arc::new(config))
+    }).clone()
+}
+
+impl Embedder for HttpEmbedder {
+    fn id(&self) -> String {
+        format!("http-{}{}-{}", self.host, self.path, self.model.as_deref().unwrap_or("default"))
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut out: Vec<Vec<f32>> = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(HTTP_BATCH) {
+            let input: Vec<String> = batch.iter().map(|t| t.chars().take(HTTP_MAX_TEXT_CHARS).collect()).collect();
+            let mut body = json!({ "input": input });
+            if let Some(model) = &self.model {
+                body["model"] = json!(model);
+            }
+            let response = self.post(&body.to_string())?;
+            // Every batch must match the first one's dimensions
+            let vectors = parse_embeddings(&response, batch.len(), out.first().map(Vec::len))?;
+            out.extend(vectors);
+        }
+        Ok(out)
+    }
+}
+
+/// `data[].embedding` of an embeddings response, in `index` order, normalized. Every
+/// vector must have `dimensions` floats, or as many as the first one when `None`.
+pub fn parse_embeddings(response: &Value, expected: usize, dimensions: Option<usize>) -> Result<Vec<Vec<f32>>, String> {
+    let data = response.get("data").and_then(Value::as_array)
+        .ok_or_else(|| "Embeddings response has no 'data' array".to_string())?;
+    let mut items: Vec<(u64, Vec<f32>)> = data.iter().enumerate().map(|(i, item)| {
+        let vector = item.get("embedding").and_then(Value::as_array)
+            .ok_or_else(|| "Embeddings response item has no 'embedding' array".to_string())?
+            .iter().map(|x| x.as_f64().unwrap_or(0.0) as f32).collect();
+        Ok((item.get("index").and_then(Value::as_u64).unwrap_or(i as u64), vector))
+    }).collect::<Result<_, String>>()?;
+    if items.len() != expected {
+        return Err(format!("Embeddings server returned {} vectors for {} texts", items.len(), expected));
+    }
+    let dimensions = dimensions.or_else(|| items.first().map(|(_, v)| v.len())).unwrap_or(0);
+    if dimensions == 0 && !items.is_empty() {
+        return Err("Embeddings server returned an empty vector".to_string());
+    }
+    if let Some((_, v)) = items.iter().find(|(_, v)| v.len() != dimensions) {
+        return Err(format!("Embeddings server returned a vector of {} dimensions, expected {}", v.len(), dimensions));
+    }
+    items.sort_by_key(|(i, _)| *i);
+    Ok(items.into_iter().map(|(_, mut v)| { normalize(&mut v); v }).collect())
+}
+
+/// Status code and body of an HTTP/1.1 response, decoding chunked transfer encoding.
+pub(crate) fn read_response(reader: &mut impl BufRead) -> Result<(u16, Vec<u8>), String> {
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| e.to_string())?;
+    let status = line.split_whitespace().nth(1).and_then(|s| s.parse().ok())
+        .ok_or_else(|| format!("bad status line '{}'", line.trim()))?;
+    let mut chunked = false;
+    let mut length = None;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        let header = line.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked") {
+                chunked = true;
+            } else if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            line.clear();
+            reader.read_line(&mut line).map_err(|e| e.to_string())?;
+            let size = usize::from_str_radix(line.trim().split(';').next().unwrap_or(""), 16)
+                .map_err(|_| format!("bad chunk size '{}'", line.trim()))?;
+            if size == 0 {
+                break;
+            }
+            let start = body.len();
+            body.resize(start + size, 0);
+            reader.read_exact(&mut body[start..]).map_err(|e| e.to_string())?;
+            line.clear();
+            reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        }
+    } else if let Some(n) = length {
+        body.resize(n, 0);
+        reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+    } else {
+        match reader.read_to_end(&mut body) {
+            // A TLS server may close without close_notify after a body of unknown length
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && !body.is_empty() => {}
+            result => { result.map_err(|e| e.to_string())?; }
+        }
+    }
+    Ok((status, body))
+}
+
@@ -0,0 +1,458 @@
+//! Semantic index — embedding vectors of code chunks, for natural-language queries
+//! such as "where do we retry failed payments" (`search_semantic`).
+//!
+//! A chunk is a definition (method, function, constructor, type) where the definition
+//! index covers the file, and a window of [`WINDOW_LINES`] lines elsewhere. Chunk texts
+//! are embedded by the backend `[semantic]` in `.search-index.toml` selects (see
+//! [`embedder`]) and the vectors are clustered into an [`IvfIndex`]. Queries blend the
+//! cosine similarity with a TF-IDF keyword score from the content index, so exact
+//! identifiers still rank where embeddings alone would miss them.
+//!
+//! The index is built on first use and saved as `<prefix>_<hash>.semantic`. A rebuild
+//! reuses the vector of every chunk whose text is unchanged, so after an edit only the
+//! edited chunks are embedded again.
+
+pub mod ann;
+pub mod embedder;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::definitions::{DefinitionIndex, DefinitionKind};
+use crate::ContentIndex;
+use search::Clock;
+pub use ann::IvfIndex;
+pub use embedder::{Embedder, SemanticConfig};
+
+// ─── Constants ──────────────────────────────────────────────────────
+
+/// Index format version. Bump when struct layout changes incompatibly.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Lines per chunk of a file the definition index has no definitions for.
+pub const WINDOW_LINES: u32 = 40;
+
+/// Lines of a chunk that are embedded; the rest of a long body is left out.
+const MAX_EMBEDDED_LINES: usize = 150;
+
+/// Lines of a type definition that are embedded: its header and first members.
+/// The members have chunks of their own.
+const TYPE_HEADER_LINES: usize = 15;
+
+// ─── Core types ─────────────────────────────────────────────────────
+
+/// A span of a file with one vector.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Chunk {
+    pub file: String,
+    /// 1-based, inclusive.
+    pub line_start: u32,
+    pub line_end: u32,
+    /// `Parent.Name` of a definition chunk; `None` for a window.
+    pub name: Option<String>,
+    /// Definition kind, or `"lines"` for a window.
+    pub kind: String,
+    /// Hash of the embedded text; equal hashes reuse the vector on rebuild.
+    pub hash: u64,
+}
+
+/// A chunk and the text its vector is computed from.
+pub struct ChunkText {
+    pub chunk: Chunk,
+    pub text: String,
+}
+
+/// Chunk vectors of one directory, from one embedder.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SemanticIndex {
+    /// Index format version. Mismatch → full rebuild.
+    pub format_version: u32,
+    /// Canonical directory the chunks come from.
+    pub root: String,
+    /// [`Embedder::id`] of the backend that computed the vectors.
+    pub embedder: String,
+    pub dimensions: usize,
+    /// Grouped by file, in content index order.
+    pub chunks: Vec<Chunk>,
+    /// Unit vectors, `dimensions` floats per chunk, in chunk order.
+    pub vectors: Vec<f32>,
+    pub ann: IvfIndex,
+    /// Timestamp when the index was last built (seconds since epoch).
+    pub built_at: u64,
+    /// Server index generation the chunks were taken at. Not saved: an index loaded
+    /// from disk is checked against the files before it is used.
+    #[serde(skip)]
+    pub generation: Option<u64>,
+}
+
+/// One ranked chunk of [`SemanticIndex::search`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SemanticHit {
+    pub chunk: usize,
+    /// Blended score, 0..1.
+    pub score: f64,
+    /// Cosine similarity to the query, clamped to 0..1.
+    pub semantic: f64,
+    /// TF-IDF keyword score relative to the best keyword match, 0..1.
+    pub keyword: f64,
+}
+
+/// Options of [`SemanticIndex::search`].
+pub struct SemanticQuery<'a> {
+    pub text: &'a str,
+    pub max_results: usize,
+    /// Share of the keyword score in the blended score, 0..1.
+    pub keyword_weight: f64,
+    /// Case-insensitive substring of the file path.
+    pub file_filter: Option<&'a str>,
+}
+
+// ─── Chunking ───────────────────────────────────────────────────────
+
+fn is_chunk_kind(kind: DefinitionKind) -> bool {
+    matches!(kind, DefinitionKind::Method | DefinitionKind::Constructor | DefinitionKind::Function
+        | DefinitionKind::StoredProcedure | DefinitionKind::SqlFunction)
+        || is_type(kind)
+}
+
+fn is_type(kind: DefinitionKind) -> bool {
+    matches!(kind, DefinitionKind::Class | DefinitionKind::Interface | DefinitionKind::Enum
+        | DefinitionKind::Struct | DefinitionKind::Record | DefinitionKind::TypeAlias
+        | DefinitionKind::Table | DefinitionKind::View)
+}
+
+/// Chunks of the live files of `content`: definitions where `defs` has any for the file,
+/// windows of [`WINDOW_LINES`] lines otherwise. Unreadable files are skipped.
+pub fn chunk_files(content: &ContentIndex, defs: Option<&DefinitionIndex>) -> Vec<ChunkText> {
+    let mut out = Vec::new();
+    for (file_id, path) in content.files.iter().enumerate() {
+        // Files removed by the watcher stay in the list with a zero token count
+        if content.file_token_counts.get(file_id).is_none_or(|&n| n == 0) {
+            continue;
+        }
+        let Ok((text, _)) = crate::read_file_lossy(Path::new(path)) else { continue };
+        let lines: Vec<&str> = text.lines().collect();
+        let file_name = Path::new(path).file_name().map_or(path.as_str(), |n| n.to_str().unwrap_or(path));
+        let before = out.len();
+
+        if let Some(d) = defs
+            && let Some(ids) = d.path_to_id.get(&PathBuf::from(path)).and_then(|id| d.file_index.get(id))
+        {
+            let mut defs_in_file: Vec<_> = ids.iter().map(|&i| &d.definitions[i as usize])
+                .filter(|def| is_chunk_kind(def.kind)).collect();
+            defs_in_file.sort_by_key(|def| (def.line_start, std::cmp::Reverse(def.line_end)));
+            for def in defs_in_file {
+                let name = match &def.parent {
+                    Some(parent) => format!("{}.{}", parent, def.name),
+                    None => def.name.clone(),
+                };
+                let limit = if is_type(def.kind) { TYPE_HEADER_LINES } else { MAX_EMBEDDED_LINES };
+                let header = format!("{} {} in {}", def.kind.as_str(), name, file_name);
+                out.push(chunk_text(path, &lines, def.line_start, def.line_end, Some(name), def.kind.as_str(), header, limit));
+            }
+        }
+        if out.len() == before {
+            let mut start = 1;
+            while start as usize <= lines.len() {
+                let end = (start + WINDOW_LINES - 1).min(lines.len() as u32);
+                out.push(chunk_text(path, &lines, start, end, None, "lines", file_name.to_string(), MAX_EMBEDDED_LINES));
+                start = end + 1;
+            }
+        }
+    }
+    out
+}
+
+#[allow(clippy::too_many_arguments)]
+fn chunk_text(file: &str, lines: &[&str], line_start: u32, line_end: u32, name: Option<String>, kind: &str,
+    header: String, limit: usize) -> ChunkText {
+    let from = (line_start.max(1) - 1) as usize;
+    let to = (line_end as usize).min(lines.len()).min(from + limit);
+    let mut text = header;
+    for line in lines.get(from..to).unwrap_or_default() {
+        text.push('\n');
+        text.push_str(line.trim());
+    }
+    let hash = search::stable_hash(&[text.as_bytes()]);
+    ChunkText {
+        chunk: Chunk { file: file.to_string(), line_start, line_end, name, kind: kind.to_string(), hash },
+        text,
+    }
+}
+
+// ─── Build and search ───────────────────────────────────────────────
+
+impl SemanticIndex {
+    /// Embed `sources`, reusing the vectors `previous` has for unchanged chunk texts
+    /// when it was built by the same embedder. Returns the index, stamped with `clock`'s
+    /// time, and the number of chunks embedded.
+    pub fn build(root: &str, sources: Vec<ChunkText>, embedder: &dyn Embedder, previous: Option<&SemanticIndex>,
+        clock: &dyn Clock) -> Result<(Self, usize), String> {
+        let embedder_id = embedder.id();
+        let reusable: HashMap<u64, &[f32]> = previous
+            .filter(|p| p.embedder == embedder_id && p.dimensions > 0)
+            .map(|p| p.chunks.iter().zip(p.vectors.chunks(p.dimensions)).map(|(c, v)| (c.hash, v)).collect())
+            .unwrap_or_default();
+
+        let pending: Vec<usize> = (0..sources.len()).filter(|&i| !reusable.contains_key(&sources[i].chunk.hash)).collect();
+        let texts: Vec<String> = pending.iter().map(|&i| sources[i].text.clone()).collect();
+        let embedded = if texts.is_empty() { Vec::new() } else { embedder.embed(&texts)? };
+        if embedded.len() != texts.len() {
+            return Err(format!("Embedder returned {} vectors for {} chunks", embedded.len(), texts.len()));
+        }
+        let dimensions = embedded.first().map(Vec::len)
+            .or_else(|| reusable.values().next().map(|v| v.len()))
+            .unwrap_or(0);
+        if embedded.iter().any(|v| v.len() != dimensions) {
+            return Err("Embedder returned vectors of different lengths".to_string());
+        }
+        // The model behind the endpoint changed: the old vectors are not comparable
+        if let Some(old) = reusable.values().next()
+            && old.len() != dimensions {
+                return Self::build(root, sources, embedder, None, clock);
+            }
+
+        let mut fresh: HashMap<usize, Vec<f32>> = pending.into_iter().zip(embedded).collect();
+        let mut vectors = Vec::with_capacity(sources.len() * dimensions);
+        let mut chunks = Vec::with_capacity(sources.len());
+        for (i, source) in sources.into_iter().enumerate() {
+            match fresh.remove(&i) {
+                Some(v) => vectors.extend(v),
+                None => vectors.extend_from_slice(reusable[&source.chunk.hash]),
+            }
+            chunks.push(source.chunk);
+        }
+        let embedded_count = texts.len();
+        let ann = IvfIndex::build(&vectors, dimensions);
+        Ok((Self {
+            format_version: FORMAT_VERSION,
+            root: root.to_string(),
+            embedder: embedder_id,
+            dimensions,
+            chunks,
+            vectors,
+            ann,
+            built_at: clock.now_secs(),
+            generation: None,
+        }, embedded_count))
+    }
+
+    /// Err when `query_vector` cannot be compared with the chunk vectors.
+    pub fn check_dimensions(&self, query_vector: &[f32]) -> Result<(), String> {
+        if self.dimensions > 0 && query_vector.len() != self.dimensions {
+            return Err(format!(
+                "The embedder returned a {}-dimensional query vector, but the semantic index has {} dimensions. \
+                 The model behind it changed; the index is rebuilt after the next file change.",
+                query_vector.len(), self.dimensions));
+        }
+        Ok(())
+    }
+
+    fn vector(&self, chunk: usize) -> &[f32] {
+        &self.vectors[chunk * self.dimensions..(chunk + 1) * self.dimensions]
+    }
+
+    /// How [`SemanticIndex::nearest`] finds vectors: `ivf` probes the closest lists,
+    /// `exact` scores every vector.
+    pub fn vector_search(&self) -> &'static str {
+        if self.ann.lists.is_empty() { "exact" } else { "ivf" }
+    }
+
+    /// Chunks closest to `query_vector` that `accept` lets through, best first, with
+    /// their cosine similarity. Without IVF lists (fewer than [`ann::MIN_VECTORS`]
+    /// chunks) every vector is scored; a larger index without them is logged once.
+    pub fn nearest(&self, query_vector: &[f32], k: usize, accept: impl Fn(&Chunk) -> bool) -> Vec<(usize, f32)> {
+        if self.dimensions == 0 || query_vector.len() != self.dimensions {
+            return Vec::new();
+        }
+        let candidates = self.ann.candidates(query_vector, self.ann.default_nprobe()).unwrap_or_else(|| {
+            static LOGGED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+            if self.chunks.len() >= ann::MIN_VECTORS && !LOGGED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                eprintln!("[semantic] Warning: no IVF lists for {} chunks; scoring every vector", self.chunks.len());
+            }
+            (0..self.chunks.len() as u32).collect()
+        });
+        let mut scored: Vec<(usize, f32)> = candidates.into_iter().map(|i| i as usize)
+            .filter(|&i| accept(&self.chunks[i]))
+            .map(|i| (i, ann::dot(self.vector(i), query_vector)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+        scored
+    }
+
+    /// TF-IDF of the query's tokens within each chunk, from the content index postings.
+    pub fn keyword_scores(&self, content: &ContentIndex, query: &str, accept: impl Fn(&Chunk) -> bool) -> HashMap<usize, f64> {
+        let mut by_file: HashMap<&str, (usize, usize)> = HashMap::new();
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            by_file.entry(chunk.file.as_str()).and_modify(|r| r.1 = i + 1).or_insert((i, i + 1));
+        }
+        let live_files = content.file_token_counts.iter().filter(|&&n| n > 0).count().max(1) as f64;
+        let mut tokens = content.tokenizer.tokenize(query);
+        tokens.sort();
+        tokens.dedup();
+
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        for token in &tokens {
+            let Some(postings) = content.index.get(token.as_str()) else { continue };
+            let idf = (1.0 + live_files / postings.len().max(1) as f64).ln();
+            for posting in postings {
+                let Some(path) = content.files.get(posting.file_id as usize) else { continue };
+                let Some(&(from, to)) = by_file.get(path.as_str()) else { continue };
+                for i in from..to {
+                    let chunk = &self.chunks[i];
+                    if !accept(chunk) {
+                        continue;
+                    }
+                    let hits = posting.lines.iter().filter(|&&l| l >= chunk.line_start && l <= chunk.line_end).count();
+                    if hits > 0 {
+                        *scores.entry(i).or_insert(0.0) += idf * (1.0 + (hits as f64).ln());
+                    }
+                }
+            }
+        }
+        scores
+    }
+
+    /// The chunks that best match `query`: the union of the nearest vectors and the best
+    /// keyword matches, ranked by `(1 - w) · cosine + w · keyword`.
+    pub fn search(&self, content: &ContentIndex, query_vector: &[f32], query: &SemanticQuery) -> Vec<SemanticHit> {
+        let filter = query.file_filter.map(str::to_lowercase);
+        let accept = |c: &Chunk| filter.as_ref().is_none_or(|f| c.file.to_lowercase().contains(f.as_str()));
+        let pool = (query.max_results * 5).max(50);
+
+        let nearest = self.nearest(query_vector, pool, accept);
+        let keyword = self.keyword_scores(content, query.text, accept);
+        let best_keyword = keyword.values().copied().fold(0.0, f64::max);
+        let mut by_keyword: Vec<(usize, f64)> = keyword.iter().map(|(&i, &s)| (i, s)).collect();
+        by_keyword.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut candidates: Vec<usize> = nearest.iter().map(|&(i, _)| i).collect();
+        candidates.extend(by_keyword.iter().take(pool).map(|&(i, _)| i));
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let w = query.keyword_weight.clamp(0.0, 1.0);
+        let mut hits: Vec<SemanticHit> = candidates.into_iter().map(|i| {
+            let semantic = if query_vector.len() == self.dimensions {
+                (ann::dot(self.vector(i), query_vector) as f64).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let keyword = if best_keyword > 0.0 { keyword.get(&i).copied().unwrap_or(0.0) / best_keyword } else { 0.0 };
+            SemanticHit { chunk: i, score: (1.0 - w) * semantic + w * keyword, semantic, keyword }
+        }).filter(|h| h.score > 0.0).collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.chunk.cmp(&b.chunk)));
+        hits.truncate(query.max_results);
+        hits
+    }
+}
+
+// ─── Persistence ────────────────────────────────────────────────────
+
+impl SemanticIndex {
+    /// Save index to disk using bincode + LZ4 compression (atomic: temp file, then rename).
+    pub fn save_to_disk(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create index directory: {}", e))?;
+        }
+        crate::index::save_compressed(path, self, "semantic")
+            .map_err(|e| format!("Failed to save semantic index: {}", e))
+    }
+
+    /// Load index from disk. Returns Err on any error (corrupt file, wrong version) —
+    /// caller does full rebuild.
+    pub fn load_from_disk(path: &Path) -> Result<Self, String> {
+        let index: Self = crate::index::load_compressed(path, "semantic")
+            .map_err(|e| format!("Failed to load semantic index: {}", e))?;
+        if index.format_version != FORMAT_VERSION {
+            return Err(format!(
+                "Semantic index format version mismatch: file has {}, expected {}",
+                index.format_version, FORMAT_VERSION
+            ));
+        }
+        Ok(index)
+    }
+
+    /// Index file path for a directory: `<semantic_prefix>_<hash>.semantic`.
+    pub fn index_path_for(dir: &str, index_base: &Path) -> PathBuf {
+        let canonical = std::fs::canonicalize(dir)
+            .unwrap_or_else(|_| PathBuf::from(dir));
+        let hash = search::stable_hash(&[
+            canonical.to_string_lossy().as_bytes(),
+            b"semantic",
+        ]);
+        let prefix = search::extract_semantic_prefix(&canonical);
+        index_base.join(format!("{}_{:08x}.semantic", prefix, hash as u32))
+    }
+}
+
+/// How [`load_or_update`] obtained the index it returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexRefresh {
+    /// Already taken at the current generation.
+    Current,
+    /// Re-chunked; this many chunks were new or changed and embedded again.
+    Updated(usize),
+    /// Built from scratch (no index, or another embedder).
+    Built,
+}
+
+impl IndexRefresh {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexRefresh::Current => "current",
+            IndexRefresh::Updated(_) => "updated",
+            IndexRefresh::Built => "built",
+        }
+    }
+}
+
+/// Bring `current` up to date with the indexes at `generation`, reusing it or the
+/// on-disk index and embedding only the chunks of `sources` it has no vector for.
+/// `sources` is only called when the index is not current. Saves to disk whenever the
+/// index changed.
+///
+/// Embedding can take minutes with a remote model, so this runs without the server's
+/// lock on the index: the caller stores the returned index when it is done.
+pub fn load_or_update(
+    current: Option<Arc<SemanticIndex>>,
+    dir: &str,
+    generation: u64,
+    index_base: &Path,
+    embedder: &dyn Embedder,
+    clock: &dyn Clock,
+    sources: impl FnOnce() -> Result<Vec<ChunkText>, String>,
+) -> Result<(Arc<SemanticIndex>, IndexRefresh), String> {
+    let canonical = std::fs::canonicalize(dir)
+        .map(|p| crate::clean_path(&p.to_string_lossy()))
+        .unwrap_or_else(|_| dir.to_string());
+    let path = SemanticIndex::index_path_for(dir, index_base);
+    let embedder_id = embedder.id();
+
+    let current = current.filter(|idx| idx.root == canonical).or_else(|| {
+        SemanticIndex::load_from_disk(&path).ok().filter(|idx| idx.root == canonical).map(Arc::new)
+    });
+    if let Some(idx) = &current
+        && idx.generation == Some(generation) && idx.embedder == embedder_id {
+            return Ok((Arc::clone(idx), IndexRefresh::Current));
+        }
+
+    let previous = current.filter(|idx| idx.embedder == embedder_id);
+    let (mut index, embedded) = SemanticIndex::build(&canonical, sources()?, embedder, previous.as_deref(), clock)?;
+    index.generation = Some(generation);
+    let refresh = if previous.is_some() { IndexRefresh::Updated(embedded) } else { IndexRefresh::Built };
+    if previous.as_ref().is_none_or(|p| p.chunks != index.chunks)
+        && let Err(e) = index.save_to_disk(&path) {
+            eprintln!("[semantic] Warning: {}", e);
+        }
+    Ok((Arc::new(index), refresh))
+}
+
+#[cfg(test)]
+#[path = "semantic_tests.rs"]
+mod tests;
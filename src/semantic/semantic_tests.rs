@@ -0,0 +1,178 @@
+//! Unit tests for the semantic index: the hashing embedder, the HTTP response reader,
+//! the IVF index, and vector reuse across rebuilds.
+
+use std::io::BufReader;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::json;
+
+use super::ann::{dot, IvfIndex, MIN_VECTORS};
+use super::embedder::{self, words, Embedder, HashingEmbedder};
+use super::*;
+use search::SystemClock;
+
+#[test]
+fn test_hashing_embedder_matches_identifier_parts_and_word_forms() {
+    assert_eq!(words("RetryFailedPaymentsAsync(HTTPClient http_client)"),
+        vec!["retry", "fail", "payment", "async", "http", "client", "http", "client"]);
+    assert_eq!(words("retries processing classes"), vec!["retry", "process", "class"]);
+
+    let e = HashingEmbedder::default();
+    let v = e.embed(&[
+        "where do we retry failed payments".to_string(),
+        "method RetryFailedPaymentAsync\nawait gateway.Charge(payment)".to_string(),
+        "method LoadUser\nreturn db.Users.Find(id)".to_string(),
+    ]).unwrap();
+    assert!((dot(&v[1], &v[1]) - 1.0).abs() < 1e-5, "unit vectors");
+    assert!(dot(&v[0], &v[1]) > 0.4);
+    assert!(dot(&v[0], &v[1]) > dot(&v[0], &v[2]) + 0.3);
+}
+
+#[test]
+fn test_http_response_reader_and_embeddings_payload() {
+    let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6;x=1\r\n world\r\n0\r\n\r\n";
+    assert_eq!(embedder::read_response(&mut BufReader::new(&raw[..])).unwrap(), (200, b"hello world".to_vec()));
+    let raw = b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 2\r\n\r\nno";
+    assert_eq!(embedder::read_response(&mut BufReader::new(&raw[..])).unwrap(), (401, b"no".to_vec()));
+
+    let response = json!({ "data": [
+        { "index": 1, "embedding": [0.0, 2.0] },
+        { "index": 0, "embedding": [3.0, 4.0] },
+    ]});
+    assert_eq!(embedder::parse_embeddings(&response, 2, None).unwrap(), vec![vec![0.6, 0.8], vec![0.0, 1.0]]);
+    assert!(embedder::parse_embeddings(&response, 3, None).unwrap_err().contains("2 vectors for 3 texts"));
+    assert!(embedder::parse_embeddings(&response, 2, Some(3)).unwrap_err().contains("2 dimensions, expected 3"));
+    let ragged = json!({ "data": [{ "embedding": [1.0, 0.0] }, { "embedding": [1.0, 0.0, 0.0] }] });
+    assert!(embedder::parse_embeddings(&ragged, 2, None).unwrap_err().contains("3 dimensions, expected 2"));
+    assert!(embedder::from_config(&SemanticConfig { embeddings: Some("ftp://api.example.com".to_string()), ..Default::default() })
+        .err().unwrap().contains("http:// or https://"));
+}
+
+#[test]
+fn test_http_embedder_posts_a_batch_and_reads_the_vectors() {
+    use std::io::{BufRead, Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/v1/embeddings", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut length = 0;
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            if let Some(n) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                length = n.trim().parse().unwrap();
+            }
+            line.clear();
+        }
+        let mut request = vec![0; length];
+        reader.read_exact(&mut request).unwrap();
+        let body = json!({ "data": [{ "index": 0, "embedding": [0.0, 3.0] }] }).to_string();
+        write!(reader.get_mut(), "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        serde_json::from_slice::<serde_json::Value>(&request).unwrap()
+    });
+    let http = embedder::HttpEmbedder::new(&url, Some("m".to_string()), None).unwrap();
+    assert_eq!(http.embed(&["retry".to_string()]).unwrap(), vec![vec![0.0, 1.0]]);
+    assert_eq!(server.join().unwrap(), json!({ "input": ["retry"], "model": "m" }));
+}
+
+#[test]
+fn test_https_embedder_starts_a_tls_handshake_for_the_host() {
+    use std::io::Read;
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("https://localhost:{}/v1/embeddings", listener.local_addr().unwrap().port());
+    // Reads the ClientHello and hangs up instead of answering it
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut hello = vec![0; 512];
+        let n = stream.read(&mut hello).unwrap();
+        hello.truncate(n);
+        hello
+    });
+    let https = embedder::HttpEmbedder::new(&url, None, None).unwrap();
+    let err = https.embed(&["retry".to_string()]).unwrap_err();
+    assert!(err.contains("TLS handshake with embeddings server localhost:"), "{err}");
+    let hello = server.join().unwrap();
+    assert_eq!(hello[0], 0x16, "a TLS handshake record");
+    assert!(hello.windows(9).any(|w| w == b"localhost"), "the host name is sent for SNI");
+}
+
+#[test]
+fn test_ivf_probes_the_list_of_the_nearest_vector() {
+    let dims = 8;
+    let mut vectors = Vec::new();
+    for i in 0..MIN_VECTORS + 500 {
+        let mut v: Vec<f32> = (0..dims).map(|d| (((i * 31 + d * 17) % 97) as f32) - 48.0).collect();
+        embedder::normalize(&mut v);
+        vectors.extend(v);
+    }
+    let ivf = IvfIndex::build(&vectors, dims);
+    assert!(!ivf.lists.is_empty());
+    assert_eq!(ivf.lists.iter().map(Vec::len).sum::<usize>(), MIN_VECTORS + 500);
+    let query = vectors[1234 * dims..1235 * dims].to_vec();
+    assert!(ivf.candidates(&query, 1).unwrap().contains(&1234));
+    assert!(IvfIndex::build(&vectors[..100 * dims], dims).candidates(&query, 1).is_none(), "small sets are scanned exactly");
+}
+
+/// Counts the texts it is asked to embed.
+struct CountingEmbedder {
+    inner: HashingEmbedder,
+    embedded: AtomicUsize,
+}
+
+impl Embedder for CountingEmbedder {
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        self.embedded.fetch_add(texts.len(), Ordering::Relaxed);
+        self.inner.embed(texts)
+    }
+}
+
+fn chunk(file: &str, text: &str) -> ChunkText {
+    ChunkText {
+        chunk: Chunk { file: file.to_string(), line_start: 1, line_end: 1, name: None, kind: "lines".to_string(),
+            hash: search::stable_hash(&[text.as_bytes()]) },
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn test_rebuild_embeds_only_changed_chunks() {
+    let embedder = CountingEmbedder { inner: HashingEmbedder::default(), embedded: AtomicUsize::new(0) };
+    let clock = search::clock::ManualClock::new(500);
+    let (first, n) = SemanticIndex::build("/repo", vec![chunk("a.cs", "charge card"), chunk("b.cs", "send invoice")], &embedder, None, &clock).unwrap();
+    assert_eq!((n, first.dimensions, first.vectors.len(), first.built_at), (2, 256, 512, 500));
+
+    clock.advance(30);
+    let (second, n) = SemanticIndex::build("/repo", vec![chunk("a.cs", "charge card"), chunk("b.cs", "send reminder")], &embedder, Some(&first), &clock).unwrap();
+    assert_eq!((n, embedder.embedded.load(Ordering::Relaxed), second.built_at), (1, 3, 530));
+    assert_eq!(second.vectors[..256], first.vectors[..256]);
+
+    let query = HashingEmbedder::default().embed(&["reminder".to_string()]).unwrap().remove(0);
+    assert_eq!(second.nearest(&query, 1, |_| true)[0].0, 1);
+    assert!(second.nearest(&query, 5, |c| c.file == "a.cs").iter().all(|&(i, _)| i == 0));
+    assert!(second.check_dimensions(&query).is_ok());
+    assert!(second.check_dimensions(&query[..128]).unwrap_err().contains("128-dimensional"));
+}
+
+#[test]
+fn test_rebuild_with_other_dimensions_embeds_every_chunk_again() {
+    let wide = CountingEmbedder { inner: HashingEmbedder::default(), embedded: AtomicUsize::new(0) };
+    let (first, _) = SemanticIndex::build("/repo", vec![chunk("a.cs", "charge card"), chunk("b.cs", "send invoice")], &wide, None, &SystemClock).unwrap();
+
+    // Same embedder id, but the model behind it now returns 64 floats
+    struct Narrow(CountingEmbedder);
+    impl Embedder for Narrow {
+        fn id(&self) -> String {
+            "hash-256".to_string()
+        }
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+            self.0.embed(texts)
+        }
+    }
+    let narrow = Narrow(CountingEmbedder { inner: HashingEmbedder { dimensions: 64 }, embedded: AtomicUsize::new(0) });
+    let (second, n) = SemanticIndex::build("/repo", vec![chunk("a.cs", "charge card"), chunk("b.cs", "send reminder")], &narrow, Some(&first), &SystemClock).unwrap();
+    assert_eq!((n, second.dimensions, second.vectors.len()), (2, 64, 128));
+}
@@ -0,0 +1,545 @@
+//! Sharded content indexes for very large repositories.
+//!
+//! `content-index --shards N` splits a content index into N files by top-level
+//! directory: every file under one top-level directory lands in the same shard, picked
+//! by a stable hash of the directory name, and files directly in the root share one
+//! shard. The shards build in parallel, each walking only its own directories.
+//!
+//! On disk, a manifest (`.word-shards`) sits next to the shard files (`.word-shard`).
+//! It records the root, the extensions and a fingerprint of each shard's postings.
+//! Saving a sharded index, including the periodic saves of the watcher, splits the
+//! in-memory index and rewrites only the shards whose fingerprint changed.
+//!
+//! A [`ShardSet`] reads each shard the first time it is needed. Exact and substring
+//! searches of `search grep` fan out: every shard looks up its own matching tokens, in
+//! parallel, and the postings are merged with the files of all shards, so document
+//! frequencies and file counts, and with them the scores, are corpus-wide exactly as
+//! for an unsharded index. Everything else loads the shards in parallel and merges them
+//! into one [`ContentIndex`].
+//!
+//! Shard files carry no trigram table; it is rebuilt once for the merged index, over
+//! the looked-up tokens only after a fan-out.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SearchError;
+use crate::index::{build_content_index_shard, build_trigram_index, content_index_path_for, load_compressed, load_content_index_file, save_compressed};
+use crate::priority::resolve_threads;
+use crate::sqlite::TokenFilter;
+use crate::{ContentIndex, ContentIndexArgs, Posting, SkippedFiles, TokenizerSettings};
+use search::{stable_hash, top_level_dir, TrigramIndex};
+
+/// Index file extension of the manifest.
+pub const MANIFEST_EXTENSION: &str = "word-shards";
+
+/// Index file extension of one shard.
+pub const SHARD_EXTENSION: &str = "word-shard";
+
+/// Lists the shards of a content index. `root` comes first, like in the index types,
+/// so the manifest's root can be read from the file header.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShardManifest {
+    pub root: String,
+    pub extensions: Vec<String>,
+    pub created_at: u64,
+    /// Shard number → [`shard_fingerprint`] of its content when last written
+    pub fingerprints: Vec<u64>,
+}
+
+/// Shard of the files under top-level directory `dir` (lowercased; empty for files
+/// directly in the root).
+pub fn shard_of_dir(dir: &str, count: usize) -> usize {
+    (stable_hash(&[dir.as_bytes()]) % count.max(1) as u64) as usize
+}
+
+/// Shard of the file at `path` under `root`.
+pub fn shard_of(root: &str, path: &str, count: usize) -> usize {
+    shard_of_dir(&top_level_dir(root, path, false).unwrap_or_default(), count)
+}
+
+pub fn manifest_path_for(dir: &str, exts: &str, index_base: &Path) -> PathBuf {
+    content_index_path_for(dir, exts, index_base).with_extension(MANIFEST_EXTENSION)
+}
+
+pub fn shard_path_for(dir: &str, exts: &str, shard: usize, count: usize, index_base: &Path) -> PathBuf {
+    let manifest = manifest_path_for(dir, exts, index_base);
+    let stem = manifest.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    manifest.with_file_name(format!("{}.{:02}of{:02}.{}", stem, shard + 1, count, SHARD_EXTENSION))
+}
+
+/// Number of shards of the index saved for `dir` and `exts`; `None` when it is not
+/// sharded.
+pub fn shard_count_for(dir: &str, exts: &str, index_base: &Path) -> Option<usize> {
+    let path = manifest_path_for(dir, exts, index_base);
+    if !path.exists() {
+        return None;
+    }
+    load_compressed::<ShardManifest>(&path, "content-shards").ok().map(|m| m.fingerprints.len())
+}
+
+/// Build the content index of `args.dir` as `count` shards, in parallel. Threads, the
+/// spill budget and the IO limit are divided between the shard builds.
+pub fn build_content_index_sharded(args: &ContentIndexArgs, count: usize) -> Vec<ContentIndex> {
+    let count = count.max(1);
+    let shard_args = ContentIndexArgs {
+        threads: (resolve_threads(args.threads, args.build_priority) / count).max(1),
+        spill_mb: args.spill_mb.div_ceil(count as u64),
+        io_limit_mb: args.io_limit_mb.div_ceil(count as u64),
+        ..args.clone()
+    };
+    std::thread::scope(|s| {
+        let handles: Vec<_> = (0..count)
+            .map(|shard| {
+                let shard_args = &shard_args;
+                s.spawn(move || build_content_index_shard(shard_args, Some((shard, count))))
+            })
+            .collect();
+        handles.into_iter()
+            .map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .map(|shard| ContentIndex { trigram: TrigramIndex::default(), trigram_dirty: true, ..shard })
+            .collect()
+    })
+}
+
+/// Split `index` into `count` shards by [`shard_of`]. File ids are renumbered within
+/// each shard; token strings are shared with `index`. Shards have no trigram table.
+pub fn split_content_index(index: &ContentIndex, count: usize) -> Vec<ContentIndex> {
+    let count = count.max(1);
+    let mut shards: Vec<ContentIndex> = (0..count)
+        .map(|_| ContentIndex { trigram_dirty: true, ..empty_like(index) })
+        .collect();
+    // Global file id → (shard, id within the shard)
+    let mut placement: Vec<(usize, u32)> = Vec::with_capacity(index.files.len());
+    for (id, path) in index.files.iter().enumerate() {
+        let shard_id = shard_of(&index.root, path, count);
+        let shard = &mut shards[shard_id];
+        let token_count = index.file_token_counts.get(id).copied().unwrap_or(0);
+        placement.push((shard_id, shard.files.len() as u32));
+        shard.files.push(path.clone());
+        shard.file_token_counts.push(token_count);
+        shard.total_tokens += token_count as u64;
+    }
+    for (token, postings) in &index.index {
+        for posting in postings {
+            let Some(&(shard, file_id)) = placement.get(posting.file_id as usize) else { continue };
+            shards[shard].index.entry(token.clone()).or_default()
+                .push(Posting { file_id, lines: posting.lines.clone() });
+        }
+    }
+    for marker in &index.comment_markers {
+        if let Some(&(shard, file_id)) = placement.get(marker.file_id as usize) {
+            shards[shard].comment_markers.push(crate::CommentMarker { file_id, ..marker.clone() });
+        }
+    }
+    for ticket in &index.ticket_refs {
+        if let Some(&(shard, file_id)) = placement.get(ticket.file_id as usize) {
+            shards[shard].ticket_refs.push(crate::TicketRef { file_id, ..ticket.clone() });
+        }
+    }
+    for (dir, bloom) in &index.dir_blooms {
+        shards[shard_of_dir(dir, count)].dir_blooms.insert(dir.clone(), bloom.clone());
+    }
+    shards[0].skipped = index.skipped;
+    shards
+}
+
+/// Merge shards into one index: files are renumbered in shard order and the trigram
+/// table is rebuilt. The result is as old as its oldest shard.
+pub fn merge_content_shards(shards: Vec<ContentIndex>) -> ContentIndex {
+    let Some(first) = shards.first() else {
+        return empty_like_root("");
+    };
+    let mut merged = empty_like(first);
+    for shard in shards {
+        let offset = merged.files.len() as u32;
+        merged.created_at = merged.created_at.min(shard.created_at);
+        merged.files.extend(shard.files);
+        merged.file_token_counts.extend(shard.file_token_counts);
+        merged.total_tokens += shard.total_tokens;
+        for (token, postings) in shard.index {
+            merged.index.entry(token).or_default()
+                .extend(postings.into_iter().map(|p| Posting { file_id: p.file_id + offset, lines: p.lines }));
+        }
+        merged.comment_markers.extend(shard.comment_markers.into_iter()
+            .map(|m| crate::CommentMarker { file_id: m.file_id + offset, ..m }));
+        merged.ticket_refs.extend(shard.ticket_refs.into_iter()
+            .map(|t| crate::TicketRef { file_id: t.file_id + offset, ..t }));
+        merged.dir_blooms.extend(shard.dir_blooms);
+        merged.skipped.too_large += shard.skipped.too_large;
+        merged.skipped.binary += shard.skipped.binary;
+    }
+    merged.trigram = build_trigram_index(&merged.index);
+    merged.trigram_dirty = false;
+    merged
+}
+
+/// Order-independent hash of a shard's files, postings and comment annotations. Equal
+/// fingerprints mean the shard file need not be rewritten; `created_at` and the
+/// trigram table are not included.
+pub fn shard_fingerprint(index: &ContentIndex) -> u64 {
+    let path = |file_id: u32| index.files.get(file_id as usize).map_or("", String::as_str);
+    let mut fingerprint = 0u64;
+    for (id, file) in index.files.iter().enumerate() {
+        let count = index.file_token_counts.get(id).copied().unwrap_or(0);
+        fingerprint = fingerprint.wrapping_add(stable_hash(&[b"file", file.as_bytes(), &count.to_le_bytes()]));
+    }
+    for (token, postings) in &index.index {
+        for posting in postings {
+            let lines: Vec<u8> = posting.lines.iter().flat_map(|l| l.to_le_bytes()).collect();
+            fingerprint = fingerprint.wrapping_add(stable_hash(&[token.as_bytes(), path(posting.file_id).as_bytes(), &lines]));
+        }
+    }
+    for m in &index.comment_markers {
+        fingerprint = fingerprint.wrapping_add(stable_hash(&[b"marker", path(m.file_id).as_bytes(), &m.line.to_le_bytes(), m.marker.as_bytes(), m.text.as_bytes()]));
+    }
+    for t in &index.ticket_refs {
+        fingerprint = fingerprint.wrapping_add(stable_hash(&[b"ticket", path(t.file_id).as_bytes(), &t.line.to_le_bytes(), t.ticket.as_bytes()]));
+    }
+    fingerprint
+}
+
+/// Save `shards` of one index and its manifest. A shard whose fingerprint matches the
+/// manifest on disk is not rewritten. Shard files of a different shard count and an
+/// unsharded index file or database for the same directory and extensions are
+/// removed. Returns the number of shard files written.
+pub fn save_content_shards(shards: &[ContentIndex], index_base: &Path) -> Result<usize, SearchError> {
+    let Some(first) = shards.first() else { return Ok(0) };
+    fs::create_dir_all(index_base)?;
+    let (root, exts) = (first.root.as_str(), first.extensions.join(","));
+    // The lock of the unsharded file: both layouts of one index have a single writer
+    let _lock = crate::lock::IndexLock::acquire(&content_index_path_for(root, &exts, index_base))?;
+    let count = shards.len();
+    let manifest_path = manifest_path_for(root, &exts, index_base);
+    let previous = load_compressed::<ShardManifest>(&manifest_path, "content-shards").ok()
+        .filter(|m| m.fingerprints.len() == count);
+
+    let mut fingerprints = Vec::with_capacity(count);
+    let mut written = 0;
+    for (i, shard) in shards.iter().enumerate() {
+        let fingerprint = shard_fingerprint(shard);
+        let path = shard_path_for(root, &exts, i, count, index_base);
+        let unchanged = previous.as_ref().is_some_and(|m| m.fingerprints[i] == fingerprint) && path.exists();
+        if !unchanged {
+            save_compressed(&path, shard, "content-shard")?;
+            written += 1;
+        }
+        fingerprints.push(fingerprint);
+    }
+    let created_at = shards.iter().map(|s| s.created_at).min().unwrap_or(first.created_at);
+    let manifest = ShardManifest { root: root.to_string(), extensions: first.extensions.clone(), created_at, fingerprints };
+    save_compressed(&manifest_path, &manifest, "content-shards")?;
+
+    let keep: Vec<PathBuf> = (0..count).map(|i| shard_path_for(root, &exts, i, count, index_base)).collect();
+    remove_shard_files(&manifest_path, |path| !keep.contains(&path.to_path_buf()));
+    let _ = fs::remove_file(content_index_path_for(root, &exts, index_base));
+    crate::sqlite::remove_database(&crate::sqlite::database_path(&content_index_path_for(root, &exts, index_base)));
+    Ok(written)
+}
+
+/// Load the shards listed in the manifest at `manifest_path`, in parallel, and merge
+/// them. Fails if any shard is missing or unreadable.
+pub fn load_content_shards(manifest_path: &Path) -> Result<ContentIndex, SearchError> {
+    ShardSet::open(manifest_path)?.into_index()
+}
+
+/// The shards of a sharded content index, each read from disk on first use.
+pub struct ShardSet {
+    paths: Vec<PathBuf>,
+    shards: Vec<OnceLock<Result<ContentIndex, SearchError>>>,
+}
+
+impl ShardSet {
+    /// Open the shards listed in the manifest at `manifest_path`; none is read yet.
+    pub fn open(manifest_path: &Path) -> Result<Self, SearchError> {
+        let manifest: ShardManifest = load_compressed(manifest_path, "content-shards")?;
+        let count = manifest.fingerprints.len();
+        let exts = manifest.extensions.join(",");
+        let index_base = manifest_path.parent().unwrap_or(Path::new("."));
+        Ok(ShardSet {
+            paths: (0..count).map(|i| shard_path_for(&manifest.root, &exts, i, count, index_base)).collect(),
+            shards: (0..count).map(|_| OnceLock::new()).collect(),
+        })
+    }
+
+    /// The shards of the index saved for `dir` and `exts`; `None` when that index is
+    /// not sharded.
+    pub fn open_for(dir: &str, exts: &str, index_base: &Path) -> Option<Self> {
+        let manifest = manifest_path_for(dir, exts, index_base);
+        if !manifest.exists() || content_index_path_for(dir, exts, index_base).exists() {
+            return None;
+        }
+        Self::open(&manifest).ok()
+    }
+
+    /// Number of shards read so far.
+    pub fn loaded(&self) -> usize {
+        self.shards.iter().filter(|s| s.get().is_some()).count()
+    }
+
+    /// Shard `i`, read on first use. A shard that failed to load is not read again.
+    pub fn shard(&self, i: usize) -> Result<&ContentIndex, SearchError> {
+        let path = &self.paths[i];
+        self.shards[i].get_or_init(|| load_content_index_file(path, "content-shard"))
+            .as_ref()
+            .map_err(|e| SearchError::IndexLoad { path: path.display().to_string(), message: e.to_string() })
+    }
+
+    /// Run `f` on every shard in parallel, reading the shards not yet loaded. Results
+    /// are in shard order.
+    fn fan_out<T: Send>(&self, f: impl Fn(&ContentIndex) -> T + Sync) -> Result<Vec<T>, SearchError> {
+        std::thread::scope(|s| {
+            let handles: Vec<_> = (0..self.shards.len())
+                .map(|i| {
+                    let f = &f;
+                    s.spawn(move || self.shard(i).map(f))
+                })
+                .collect();
+            handles.into_iter()
+                .map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+
+    /// The index with the postings of only the tokens `filter` selects, like
+    /// [`crate::sqlite::load_content_tokens`]. Each shard looks up its own tokens, in
+    /// parallel; the postings are merged with the files of every shard, so a token's
+    /// document frequency and the file count cover the whole corpus.
+    pub fn load_tokens(&self, filter: &TokenFilter) -> Result<ContentIndex, SearchError> {
+        let start = Instant::now();
+        let picked = self.fan_out(|shard| {
+            let index: HashMap<_, _> = match filter {
+                TokenFilter::Exact(terms) => terms.iter()
+                    .filter_map(|t| shard.index.get_key_value(t.as_str()))
+                    .map(|(t, p)| (t.clone(), p.clone()))
+                    .collect(),
+                TokenFilter::Containing(_) => shard.index.iter()
+                    .filter(|(t, _)| filter.matches(t))
+                    .map(|(t, p)| (t.clone(), p.clone()))
+                    .collect(),
+            };
+            ContentIndex { index, ..files_of(shard) }
+        })?;
+        let index = merge_content_shards(picked);
+        eprintln!("[content-shards] Looked up {} tokens in {} of {} shards in {:.3}s",
+            index.index.len(), self.loaded(), self.shards.len(), start.elapsed().as_secs_f64());
+        Ok(index)
+    }
+
+    /// Read the shards not yet loaded, in parallel, and merge all of them.
+    pub fn into_index(self) -> Result<ContentIndex, SearchError> {
+        self.fan_out(|_| ())?;
+        let shards = self.shards.into_iter()
+            .filter_map(OnceLock::into_inner)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(merge_content_shards(shards))
+    }
+}
+
+/// Remove the manifest and shard files of the index for `dir` and `exts`. Returns the
+/// number of files removed.
+pub fn remove_content_shards(dir: &str, exts: &str, index_base: &Path) -> usize {
+    let manifest_path = manifest_path_for(dir, exts, index_base);
+    let removed = remove_shard_files(&manifest_path, |_| true);
+    removed + usize::from(fs::remove_file(&manifest_path).is_ok())
+}
+
+/// Remove the shard files that belong to `manifest_path` and match `filter`.
+fn remove_shard_files(manifest_path: &Path, filter: impl Fn(&Path) -> bool) -> usize {
+    let Some(dir) = manifest_path.parent() else { return 0 };
+    let prefix = format!("{}.", manifest_path.file_stem().unwrap_or_default().to_string_lossy());
+    let Ok(entries) = fs::read_dir(dir) else { return 0 };
+    entries.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == SHARD_EXTENSION))
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with(&prefix)))
+        .filter(|p| filter(p))
+        .filter(|p| fs::remove_file(p).is_ok())
+        .inspect(|p| crate::lock::lock_files_for(p).iter().for_each(|l| { let _ = fs::remove_file(l); }))
+        .count()
+}
+
+/// `index` without postings: its identity, files, token counts and comment annotations.
+fn files_of(index: &ContentIndex) -> ContentIndex {
+    ContentIndex {
+        files: index.files.clone(),
+        file_token_counts: index.file_token_counts.clone(),
+        total_tokens: index.total_tokens,
+        comment_markers: index.comment_markers.clone(),
+        ticket_refs: index.ticket_refs.clone(),
+        dir_blooms: index.dir_blooms.clone(),
+        skipped: index.skipped,
+        ..empty_like(index)
+    }
+}
+
+/// An index with the identity of `index` (root, extensions, age, tokenizer, walk limits,
+/// stop-token cutoff, size limit)
+/// and no files.
+fn empty_like(index: &ContentIndex) -> ContentIndex {
+    ContentIndex {
+        created_at: index.created_at,
+        max_age_secs: index.max_age_secs,
+        extensions: index.extensions.clone(),
+        revision: index.revision.clone(),
+        tokenizer: index.tokenizer,
+        walk_limits: index.walk_limits.clone(),
+        stop_token_pct: index.stop_token_pct,
+        max_file_size: index.max_file_size,
+        ..empty_like_root(&index.root)
+    }
+}
+
+fn empty_like_root(root: &str) -> ContentIndex {
+    ContentIndex {
+        root: root.to_string(),
+        created_at: 0,
+        max_age_secs: 0,
+        files: Vec::new(),
+        index: HashMap::new(),
+        total_tokens: 0,
+        extensions: Vec::new(),
+        file_token_counts: Vec::new(),
+        trigram: TrigramIndex::default(),
+        trigram_dirty: false,
+        forward: None,
+        path_to_id: None,
+        comment_markers: Vec::new(),
+        ticket_refs: Vec::new(),
+        revision: None,
+        dir_blooms: HashMap::new(),
+        skipped: SkippedFiles::default(),
+        tokenizer: TokenizerSettings::default(),
+        walk_limits: Default::default(),
+        stop_token_pct: 0,
+        max_file_size: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// token → sorted (path, lines), independent of file numbering
+    fn postings_by_path(index: &ContentIndex) -> BTreeMap<String, Vec<(String, Vec<u32>)>> {
+        index.index.iter().map(|(token, postings)| {
+            let mut by_path: Vec<(String, Vec<u32>)> = postings.iter()
+                .map(|p| (index.files[p.file_id as usize].clone(), p.lines.clone()))
+                .collect();
+            by_path.sort();
+            (token.to_string(), by_path)
+        }).collect()
+    }
+
+    /// `content-index --shards 3 --ext cs` of `dir`
+    fn sharded_args(dir: &Path) -> ContentIndexArgs {
+        ContentIndexArgs {
+            dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 2, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 3,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: None,
+        }
+    }
+
+    #[test]
+    fn test_sharded_build_matches_single_index_and_saves_only_changed_shards() {
+        let tmp = tempfile::tempdir().unwrap();
+        let idx_base = tempfile::tempdir().unwrap();
+        for dir in ["api", "core", "data", "web", "tools"] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+            std::fs::write(tmp.path().join(dir).join("a.cs"), format!("class {}Service {{ // TODO: split\n}}\n", dir)).unwrap();
+        }
+        std::fs::write(tmp.path().join("Program.cs"), "class Program { ApiService api; }\n").unwrap();
+        let args = sharded_args(tmp.path());
+        let single = crate::build_content_index(&args);
+        let built = build_content_index_sharded(&args, 3);
+        assert!(built.iter().all(|s| s.trigram.tokens.is_empty()));
+        assert_eq!(built.iter().map(|s| s.files.len()).sum::<usize>(), 6);
+        for shard in &built {
+            for path in &shard.files {
+                assert_eq!(shard_of(&shard.root, path, 3), built.iter().position(|s| std::ptr::eq(s, shard)).unwrap());
+            }
+        }
+
+        assert_eq!(save_content_shards(&built, idx_base.path()).unwrap(), 3);
+        let loaded = crate::load_content_index(&args.dir, "cs", idx_base.path()).unwrap();
+        assert_eq!(postings_by_path(&loaded), postings_by_path(&single));
+        assert_eq!(loaded.comment_markers.len(), 5);
+        assert_eq!(loaded.trigram.tokens.len(), single.trigram.tokens.len());
+        assert_eq!(loaded.total_tokens, single.total_tokens);
+        assert!(crate::index::find_content_index_for_dir(&args.dir, idx_base.path()).is_some());
+
+        // Saving the loaded index unchanged rewrites nothing; a change rewrites one shard
+        assert_eq!(save_content_shards(&split_content_index(&loaded, 3), idx_base.path()).unwrap(), 0);
+        let mut changed = loaded;
+        changed.index.get_mut("program").unwrap()[0].lines.push(2);
+        assert_eq!(save_content_shards(&split_content_index(&changed, 3), idx_base.path()).unwrap(), 1);
+        crate::save_content_index(&changed, idx_base.path()).unwrap();
+        assert_eq!(shard_count_for(&args.dir, "cs", idx_base.path()), Some(3));
+
+        // Two shards: the three-shard files are replaced
+        assert_eq!(save_content_shards(&split_content_index(&changed, 2), idx_base.path()).unwrap(), 2);
+        let shard_files = std::fs::read_dir(idx_base.path()).unwrap().flatten()
+            .filter(|e| e.path().extension().is_some_and(|x| x == SHARD_EXTENSION)).count();
+        assert_eq!(shard_files, 2);
+        assert_eq!(remove_content_shards(&args.dir, "cs", idx_base.path()), 3);
+        assert!(crate::load_content_index(&args.dir, "cs", idx_base.path()).is_err());
+    }
+
+    #[test]
+    fn test_shard_set_reads_shards_on_first_use_and_scores_lookups_corpus_wide() {
+        let tmp = tempfile::tempdir().unwrap();
+        let idx_base = tempfile::tempdir().unwrap();
+        for (dir, body) in [("api", "class OrderService { Order order; }"), ("core", "class Invoice { Order order; int total; }"),
+            ("web", "class UserService { }"), ("data", "class Report { Order last; }")] {
+            std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+            std::fs::write(tmp.path().join(dir).join("a.cs"), format!("{}\n", body)).unwrap();
+        }
+        std::fs::write(tmp.path().join("Program.cs"), "class Program { UserService users; }\n").unwrap();
+        let args = sharded_args(tmp.path());
+        let single = crate::build_content_index(&args);
+        save_content_shards(&build_content_index_sharded(&args, 3), idx_base.path()).unwrap();
+        assert!(ShardSet::open_for(&args.dir, "rs", idx_base.path()).is_none());
+
+        let set = ShardSet::open_for(&args.dir, "cs", idx_base.path()).unwrap();
+        assert_eq!(set.loaded(), 0);
+        assert!(set.shard(1).is_ok());
+        assert_eq!(set.loaded(), 1);
+
+        let terms = vec!["order".to_string()];
+        let exact = set.load_tokens(&TokenFilter::Exact(&terms)).unwrap();
+        assert_eq!(set.loaded(), 3);
+        assert_eq!(exact.index.keys().map(|t| t.to_string()).collect::<Vec<_>>(), vec!["order"]);
+        assert_eq!(exact.files.len(), single.files.len());
+        // Document frequencies and file counts span the shards: scores match the whole index
+        let query = search::GrepQuery::new("order").mode(search::MatchMode::Exact).max_results(0);
+        let found = search::query::grep(&exact, &query).unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found, search::query::grep(&single, &query).unwrap());
+
+        let terms = vec!["service".to_string()];
+        let containing = set.load_tokens(&TokenFilter::Containing(&terms)).unwrap();
+        let mut tokens: Vec<String> = containing.index.keys().map(|t| t.to_string()).collect();
+        tokens.sort();
+        assert_eq!(tokens, vec!["orderservice", "userservice"]);
+        let query = search::GrepQuery::new("service").max_results(0);
+        assert_eq!(search::query::grep(&containing, &query).unwrap(), search::query::grep(&single, &query).unwrap());
+        assert_eq!(postings_by_path(&set.into_index().unwrap()), postings_by_path(&single));
+    }
+}
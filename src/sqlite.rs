@@ -0,0 +1,1008 @@
+//! SQLite storage for the file, content and definition indexes (`--storage sqlite`).
+//!
+//! An index stored in SQLite is one database next to where its bincode file would be,
+//! named after it with `.sqlite` appended (`<name>_<hash>.word-search.sqlite`). The
+//! database runs in WAL mode, so any number of readers (`search grep`, a second
+//! `serve`, the `sqlite3` shell) read it while one process saves.
+//!
+//! Rows are kept per file, each with a fingerprint of what the file contributes to the
+//! index. A save compares the fingerprints and rewrites only the rows of the files
+//! that changed, so the periodic saves of `serve` and `--watch` write a few rows
+//! instead of the whole index. What is not per file (root, extensions, limits, Angular
+//! selectors, ...) is a bincode blob in the `meta` table.
+//!
+//! Postings are stored by token (`postings`, keyed on token and file id), so a query
+//! can load only the tokens it needs: [`load_content_tokens`] reads the files table
+//! and the postings of the query's tokens, and nothing else. Definitions, call sites
+//! and code stats are plain tables with lookup indexes, for ad-hoc SQL analysis.
+//!
+//! Tables of a content index:
+//!
+//! ```text
+//! files(id, path, token_count, mapped, annotations, fingerprint)
+//! postings(token, file_id, lines)      -- lines: little-endian u32s
+//! tokens(token)                        -- every token with postings
+//! ```
+//!
+//! Tables of a definition index:
+//!
+//! ```text
+//! files(id, path, mapped, extras, fingerprint)
+//! definitions(id, file_id, name, kind, line_start, line_end, parent, signature,
+//!             modifiers, attributes, base_types, live)
+//! call_sites(caller_id, seq, method_name, receiver_type, receiver, line, column,
+//!            end_column, receiver_is_generic, arg_count)
+//! code_stats(def_id, cyclomatic, cognitive, ..., members_used)
+//! ```
+//!
+//! Definition ids are the index's def_idx; definitions removed by incremental updates
+//! keep their rows with `live = 0`, so the ids of the others do not change.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Transaction};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::definitions::{CallSite, CodeStats, DefinitionEntry, DefinitionIndex, DocComment, FileImports};
+use crate::error::SearchError;
+use crate::index::build_trigram_index;
+use crate::{CommentMarker, ContentIndex, FileEntry, FileIndex, Posting, TicketRef, Token, TrigramIndex};
+use search::stable_hash;
+
+/// Extension appended to the index file name.
+pub const EXTENSION: &str = "sqlite";
+
+/// Layout of the tables; a database of a newer layout is not read.
+const SCHEMA_VERSION: i64 = 1;
+
+/// How long a save waits for another writer before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where an index is stored, chosen with `--storage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Storage {
+    /// One LZ4-compressed bincode file, rewritten on every save
+    Bincode,
+    /// A SQLite database, updated per changed file
+    Sqlite,
+}
+
+/// The tokens a query needs from [`load_content_tokens`] or
+/// [`crate::shards::ShardSet::load_tokens`].
+pub enum TokenFilter<'a> {
+    /// These tokens
+    Exact(&'a [String]),
+    /// Every token containing one of these substrings
+    Containing(&'a [String]),
+}
+
+impl TokenFilter<'_> {
+    /// Whether the filter selects `token`.
+    pub fn matches(&self, token: &str) -> bool {
+        match self {
+            TokenFilter::Exact(terms) => terms.iter().any(|t| t == token),
+            TokenFilter::Containing(terms) => terms.iter().any(|t| token.contains(t.as_str())),
+        }
+    }
+}
+
+/// Database of the index whose bincode file is `index_path`.
+pub fn database_path(index_path: &Path) -> PathBuf {
+    let mut name = index_path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Format of the index whose bincode file is `index_path`: SQLite if its database
+/// exists.
+pub fn storage_of(index_path: &Path) -> Storage {
+    if database_path(index_path).exists() { Storage::Sqlite } else { Storage::Bincode }
+}
+
+/// File the index whose bincode file is `index_path` is stored in.
+pub fn stored_path(index_path: &Path) -> PathBuf {
+    match storage_of(index_path) {
+        Storage::Sqlite => database_path(index_path),
+        Storage::Bincode => index_path.to_path_buf(),
+    }
+}
+
+/// Whether `path` is an index database of the given bincode index extension
+/// (`word-search`, `file-list`, `code-structure`).
+pub fn is_database_of(path: &Path, index_extension: &str) -> bool {
+    path.extension().is_some_and(|e| e == EXTENSION)
+        && path.file_stem().and_then(|s| Path::new(s).extension()).is_some_and(|e| e == index_extension)
+}
+
+/// Remove the database at `path` and its WAL files. Returns whether the database
+/// existed.
+pub fn remove_database(path: &Path) -> bool {
+    let removed = std::fs::remove_file(path).is_ok();
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = path.as_os_str().to_os_string();
+        sidecar.push(suffix);
+        let _ = std::fs::remove_file(sidecar);
+    }
+    removed
+}
+
+/// Root, extensions and age of the index in a database, read from its `meta` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseSummary {
+    /// `files`, `content` or `definitions`
+    pub kind: String,
+    pub root: String,
+    pub extensions: Vec<String>,
+    pub created_at: u64,
+    /// Rows of the files table (entries of a file index)
+    pub files: usize,
+}
+
+/// [`DatabaseSummary`] of the database at `path`; `None` if it is not an index database.
+pub fn read_summary(path: &Path) -> Option<DatabaseSummary> {
+    let conn = open_read(path).ok()?;
+    let kind: String = meta(&conn, "kind").ok()?;
+    let table = if kind == "files" { "entries" } else { "files" };
+    let files: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0)).ok()?;
+    let extensions: String = meta(&conn, "extensions").ok()?;
+    Some(DatabaseSummary {
+        kind,
+        root: meta(&conn, "root").ok()?,
+        extensions: extensions.split(',').filter(|e| !e.is_empty()).map(str::to_string).collect(),
+        created_at: meta::<i64>(&conn, "created_at").ok()? as u64,
+        files: files as usize,
+    })
+}
+
+// ─── Connections ─────────────────────────────────────────────────────
+
+fn open_write(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(conn)
+}
+
+fn open_read(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+fn save_error(path: &Path) -> impl Fn(rusqlite::Error) -> SearchError + '_ {
+    move |e| SearchError::SaveFailed(format!("{}: {}", path.display(), e))
+}
+
+fn load_error(path: &Path) -> impl Fn(rusqlite::Error) -> SearchError + '_ {
+    move |e| SearchError::IndexLoad { path: path.display().to_string(), message: e.to_string() }
+}
+
+fn encode<T: Serialize>(value: &T) -> rusqlite::Result<Vec<u8>> {
+    bincode::serialize(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> rusqlite::Result<T> {
+    bincode::deserialize(bytes).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e)))
+}
+
+fn json_list(items: &[String]) -> String {
+    serde_json::to_string(items).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn parse_json<T: DeserializeOwned>(text: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(text).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+}
+
+fn lines_blob(lines: &[u32]) -> Vec<u8> {
+    lines.iter().flat_map(|l| l.to_le_bytes()).collect()
+}
+
+fn blob_lines(blob: &[u8]) -> Vec<u32> {
+    blob.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+}
+
+// ─── Meta ────────────────────────────────────────────────────────────
+
+const META_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value) WITHOUT ROWID;";
+
+fn meta<T: rusqlite::types::FromSql>(conn: &Connection, key: &str) -> rusqlite::Result<T> {
+    conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |r| r.get(0))
+}
+
+fn set_meta(tx: &Transaction, key: &str, value: impl rusqlite::ToSql) -> rusqlite::Result<()> {
+    tx.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![key, value])?;
+    Ok(())
+}
+
+/// Create the tables of `kind` and check that the database holds that kind of index.
+fn init(tx: &Transaction, kind: &str, schema: &str) -> rusqlite::Result<()> {
+    tx.execute_batch(META_SCHEMA)?;
+    let existing: Option<String> = tx.query_row("SELECT value FROM meta WHERE key = 'kind'", [], |r| r.get(0)).optional()?;
+    if let Some(existing) = existing.filter(|k| k != kind) {
+        return Err(rusqlite::Error::InvalidParameterName(format!("database holds a {} index, not {}", existing, kind)));
+    }
+    tx.execute_batch(schema)?;
+    set_meta(tx, "kind", kind)?;
+    set_meta(tx, "schema", SCHEMA_VERSION)
+}
+
+/// Write the identity of the index and the rest of it (`header`) to `meta`.
+fn write_header<T: Serialize>(tx: &Transaction, root: &str, extensions: &[String], created_at: u64, header: &T) -> rusqlite::Result<()> {
+    set_meta(tx, "root", root)?;
+    set_meta(tx, "extensions", extensions.join(","))?;
+    set_meta(tx, "created_at", created_at as i64)?;
+    set_meta(tx, "header", encode(header)?)
+}
+
+/// Check the kind and layout of a database and read its header.
+fn read_header<T: DeserializeOwned>(conn: &Connection, kind: &str) -> rusqlite::Result<T> {
+    let stored: String = meta(conn, "kind")?;
+    let schema: i64 = meta(conn, "schema")?;
+    if stored != kind || schema > SCHEMA_VERSION {
+        return Err(rusqlite::Error::InvalidParameterName(format!(
+            "database holds a {} index of schema {}, expected {} of schema {} or older", stored, schema, kind, SCHEMA_VERSION)));
+    }
+    decode(&meta::<Vec<u8>>(conn, "header")?)
+}
+
+/// Fingerprints of the rows of a files table, by id.
+fn fingerprints(tx: &Transaction, table: &str, id: &str) -> rusqlite::Result<HashMap<u32, i64>> {
+    let mut stmt = tx.prepare(&format!("SELECT {}, fingerprint FROM {}", id, table))?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, u32>(0)?, r.get::<_, i64>(1)?)))?;
+    rows.collect()
+}
+
+fn finish_save(path: &Path, label: &str, start: Instant, written: usize, total: usize) {
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    eprintln!("[{}] Saved to SQLite in {:.2}s: {} of {} files rewritten, {:.1} MB ({})",
+        label, start.elapsed().as_secs_f64(), written, total, size as f64 / 1_048_576.0, path.display());
+}
+
+// ─── File index ──────────────────────────────────────────────────────
+
+const FILE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS entries (path TEXT PRIMARY KEY, size INTEGER NOT NULL, modified INTEGER NOT NULL, is_dir INTEGER NOT NULL) WITHOUT ROWID;
+";
+
+/// Save a file index to the database at `path`: entries that changed are written and
+/// entries no longer in the index deleted. Returns the number of entries written.
+pub fn save_file_index(path: &Path, index: &FileIndex) -> Result<usize, SearchError> {
+    let start = Instant::now();
+    let mut conn = open_write(path).map_err(save_error(path))?;
+    let written = (|| {
+        let tx = conn.transaction()?;
+        init(&tx, "files", FILE_SCHEMA)?;
+        let mut stored: HashMap<String, (i64, i64, bool)> = HashMap::new();
+        {
+            let mut stmt = tx.prepare("SELECT path, size, modified, is_dir FROM entries")?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, (r.get(1)?, r.get(2)?, r.get(3)?))))?;
+            for row in rows {
+                let (path, entry) = row?;
+                stored.insert(path, entry);
+            }
+        }
+        let mut written = 0;
+        {
+            let mut upsert = tx.prepare("INSERT OR REPLACE INTO entries (path, size, modified, is_dir) VALUES (?1, ?2, ?3, ?4)")?;
+            for entry in &index.entries {
+                let row = (entry.size as i64, entry.modified as i64, entry.is_dir);
+                if stored.remove(&entry.path) != Some(row) {
+                    upsert.execute(params![entry.path, row.0, row.1, row.2])?;
+                    written += 1;
+                }
+            }
+            let mut delete = tx.prepare("DELETE FROM entries WHERE path = ?1")?;
+            for path in stored.keys() {
+                delete.execute([path])?;
+            }
+        }
+        let header = FileIndex { root: index.root.clone(), created_at: index.created_at, max_age_secs: index.max_age_secs, entries: Vec::new() };
+        write_header(&tx, &index.root, &[], index.created_at, &header)?;
+        tx.commit()?;
+        Ok(written)
+    })().map_err(save_error(path))?;
+    finish_save(path, "file-index", start, written, index.entries.len());
+    Ok(written)
+}
+
+/// Load the file index in the database at `path`. Entries are ordered by path.
+pub fn load_file_index(path: &Path) -> Result<FileIndex, SearchError> {
+    let conn = open_read(path).map_err(load_error(path))?;
+    (|| {
+        let mut index: FileIndex = read_header(&conn, "files")?;
+        let mut stmt = conn.prepare("SELECT path, size, modified, is_dir FROM entries ORDER BY path")?;
+        let rows = stmt.query_map([], |r| Ok(FileEntry {
+            path: r.get(0)?,
+            size: r.get::<_, i64>(1)? as u64,
+            modified: r.get::<_, i64>(2)? as u64,
+            is_dir: r.get(3)?,
+        }))?;
+        index.entries = rows.collect::<rusqlite::Result<_>>()?;
+        Ok(index)
+    })().map_err(load_error(path))
+}
+
+// ─── Content index ───────────────────────────────────────────────────
+
+const CONTENT_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS files (id INTEGER PRIMARY KEY, path TEXT NOT NULL, token_count INTEGER NOT NULL, mapped INTEGER NOT NULL, annotations BLOB NOT NULL, fingerprint INTEGER NOT NULL);
+CREATE TABLE IF NOT EXISTS postings (token TEXT NOT NULL, file_id INTEGER NOT NULL, lines BLOB NOT NULL, PRIMARY KEY (token, file_id)) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS postings_file ON postings(file_id);
+CREATE TABLE IF NOT EXISTS tokens (token TEXT PRIMARY KEY) WITHOUT ROWID;
+";
+
+/// Comment markers and ticket references of one file, as stored in `files.annotations`.
+type Annotations = (Vec<CommentMarker>, Vec<TicketRef>);
+
+/// Save a content index to the database at `path`, rewriting the rows of the files
+/// whose fingerprint changed. Returns the number of files rewritten.
+pub fn save_content_index(path: &Path, index: &ContentIndex) -> Result<usize, SearchError> {
+    let start = Instant::now();
+    let mut conn = open_write(path).map_err(save_error(path))?;
+    let written = (|| {
+        let tx = conn.transaction()?;
+        init(&tx, "content", CONTENT_SCHEMA)?;
+        let written = write_content(&tx, index)?;
+        write_header(&tx, &index.root, &index.extensions, index.created_at, &content_header(index))?;
+        tx.commit()?;
+        Ok(written)
+    })().map_err(save_error(path))?;
+    finish_save(path, "content-index", start, written, index.files.len());
+    Ok(written)
+}
+
+fn write_content(tx: &Transaction, index: &ContentIndex) -> rusqlite::Result<usize> {
+    let file_count = index.files.len();
+    let mut annotations: Vec<Annotations> = vec![(Vec::new(), Vec::new()); file_count];
+    for marker in &index.comment_markers {
+        if let Some(a) = annotations.get_mut(marker.file_id as usize) { a.0.push(marker.clone()); }
+    }
+    for ticket in &index.ticket_refs {
+        if let Some(a) = annotations.get_mut(ticket.file_id as usize) { a.1.push(ticket.clone()); }
+    }
+    let mut posting_hashes = vec![0u64; file_count];
+    for (token, postings) in &index.index {
+        for posting in postings {
+            if let Some(hash) = posting_hashes.get_mut(posting.file_id as usize) {
+                *hash = hash.wrapping_add(stable_hash(&[token.as_bytes(), &lines_blob(&posting.lines)]));
+            }
+        }
+    }
+
+    let stored = fingerprints(tx, "files", "id")?;
+    let mut changed: HashMap<u32, (bool, Vec<u8>, i64)> = HashMap::new();
+    for (id, file) in index.files.iter().enumerate() {
+        let token_count = index.file_token_counts.get(id).copied().unwrap_or(0);
+        let mapped = index.path_to_id.as_ref().is_some_and(|m| m.get(Path::new(file)) == Some(&(id as u32)));
+        let blob = encode(&annotations[id])?;
+        let fingerprint = stable_hash(&[file.as_bytes(), &token_count.to_le_bytes(), &[mapped as u8], &blob])
+            .wrapping_add(posting_hashes[id]) as i64;
+        if stored.get(&(id as u32)) != Some(&fingerprint) {
+            changed.insert(id as u32, (mapped, blob, fingerprint));
+        }
+    }
+
+    let mut postings: HashMap<u32, Vec<(&Token, &[u32])>> = HashMap::new();
+    for (token, list) in &index.index {
+        for posting in list.iter().filter(|p| changed.contains_key(&p.file_id)) {
+            postings.entry(posting.file_id).or_default().push((token, &posting.lines));
+        }
+    }
+
+    // Tokens that lost postings; those left with none leave the tokens table
+    let mut stale: HashSet<String> = HashSet::new();
+    {
+        let mut old_tokens = tx.prepare("SELECT token FROM postings WHERE file_id = ?1")?;
+        let mut delete = tx.prepare("DELETE FROM postings WHERE file_id = ?1")?;
+        let mut insert = tx.prepare("INSERT OR REPLACE INTO postings (token, file_id, lines) VALUES (?1, ?2, ?3)")?;
+        let mut insert_token = tx.prepare("INSERT OR IGNORE INTO tokens (token) VALUES (?1)")?;
+        let mut upsert_file = tx.prepare("INSERT OR REPLACE INTO files (id, path, token_count, mapped, annotations, fingerprint) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+        let mut ids: Vec<&u32> = changed.keys().collect();
+        ids.sort_unstable();
+        for &id in ids {
+            if stored.contains_key(&id) {
+                for token in old_tokens.query_map([id], |r| r.get::<_, String>(0))? {
+                    stale.insert(token?);
+                }
+                delete.execute([id])?;
+            }
+            for (token, lines) in postings.remove(&id).unwrap_or_default() {
+                insert.execute(params![&**token, id, lines_blob(lines)])?;
+                insert_token.execute([&**token])?;
+            }
+            let (mapped, blob, fingerprint) = &changed[&id];
+            let token_count = index.file_token_counts.get(id as usize).copied().unwrap_or(0);
+            upsert_file.execute(params![id, index.files[id as usize], token_count, mapped, blob, fingerprint])?;
+        }
+    }
+    if stored.keys().any(|&id| id as usize >= file_count) {
+        let mut old_tokens = tx.prepare("SELECT DISTINCT token FROM postings WHERE file_id >= ?1")?;
+        for token in old_tokens.query_map([file_count as i64], |r| r.get::<_, String>(0))? {
+            stale.insert(token?);
+        }
+        tx.execute("DELETE FROM postings WHERE file_id >= ?1", [file_count as i64])?;
+        tx.execute("DELETE FROM files WHERE id >= ?1", [file_count as i64])?;
+    }
+    let mut prune = tx.prepare("DELETE FROM tokens WHERE token = ?1 AND NOT EXISTS (SELECT 1 FROM postings WHERE token = ?1)")?;
+    for token in &stale {
+        prune.execute([token])?;
+    }
+    Ok(changed.len())
+}
+
+/// `index` without its per-file data, for the `meta` header.
+fn content_header(index: &ContentIndex) -> ContentIndex {
+    ContentIndex {
+        root: index.root.clone(),
+        created_at: index.created_at,
+        max_age_secs: index.max_age_secs,
+        files: Vec::new(),
+        index: HashMap::new(),
+        total_tokens: index.total_tokens,
+        extensions: index.extensions.clone(),
+        file_token_counts: Vec::new(),
+        trigram: TrigramIndex::default(),
+        trigram_dirty: false,
+        forward: None,
+        path_to_id: index.path_to_id.as_ref().map(|_| HashMap::new()),
+        comment_markers: Vec::new(),
+        ticket_refs: Vec::new(),
+        revision: index.revision.clone(),
+        dir_blooms: index.dir_blooms.clone(),
+        skipped: index.skipped,
+        tokenizer: index.tokenizer,
+        walk_limits: index.walk_limits.clone(),
+        stop_token_pct: index.stop_token_pct,
+        max_file_size: index.max_file_size,
+    }
+}
+
+/// Load the content index in the database at `path`, with its trigram table rebuilt.
+pub fn load_content_index(path: &Path) -> Result<ContentIndex, SearchError> {
+    load_content(path, None)
+}
+
+/// Load the content index in the database at `path` with only the postings of the
+/// tokens `filter` selects. Files, token counts and the other fields are complete, so
+/// scores match those over the whole index; the trigram table holds the loaded tokens.
+pub fn load_content_tokens(path: &Path, filter: &TokenFilter) -> Result<ContentIndex, SearchError> {
+    load_content(path, Some(filter))
+}
+
+fn load_content(path: &Path, filter: Option<&TokenFilter>) -> Result<ContentIndex, SearchError> {
+    let start = Instant::now();
+    let conn = open_read(path).map_err(load_error(path))?;
+    let mut index = read_content(&conn, filter).map_err(load_error(path))?;
+    index.trigram = build_trigram_index(&index.index);
+    index.trigram_dirty = false;
+    eprintln!("[content-index] Loaded {} files, {} tokens from SQLite in {:.3}s",
+        index.files.len(), index.index.len(), start.elapsed().as_secs_f64());
+    Ok(index)
+}
+
+fn read_content(conn: &Connection, filter: Option<&TokenFilter>) -> rusqlite::Result<ContentIndex> {
+    let mut index: ContentIndex = read_header(conn, "content")?;
+    let mut path_to_id = HashMap::new();
+    {
+        let mut stmt = conn.prepare("SELECT id, path, token_count, mapped, annotations FROM files ORDER BY id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: u32 = row.get(0)?;
+            if id as usize != index.files.len() {
+                return Err(rusqlite::Error::InvalidParameterName(format!("file id {} is missing", index.files.len())));
+            }
+            let path: String = row.get(1)?;
+            if row.get::<_, bool>(3)? {
+                path_to_id.insert(PathBuf::from(&path), id);
+            }
+            let (markers, tickets): Annotations = decode(&row.get::<_, Vec<u8>>(4)?)?;
+            index.files.push(path);
+            index.file_token_counts.push(row.get(2)?);
+            index.comment_markers.extend(markers);
+            index.ticket_refs.extend(tickets);
+        }
+    }
+    if index.path_to_id.is_some() {
+        index.path_to_id = Some(path_to_id);
+    }
+
+    match filter {
+        None => {
+            let mut stmt = conn.prepare("SELECT token, file_id, lines FROM postings ORDER BY token, file_id")?;
+            let mut rows = stmt.query([])?;
+            let mut current: Option<(String, Vec<Posting>)> = None;
+            while let Some(row) = rows.next()? {
+                let token = row.get_ref(0)?.as_str()?;
+                let posting = Posting { file_id: row.get(1)?, lines: blob_lines(row.get_ref(2)?.as_blob()?) };
+                match current.as_mut() {
+                    Some((t, postings)) if t == token => postings.push(posting),
+                    _ => {
+                        if let Some((t, postings)) = current.replace((token.to_string(), vec![posting])) {
+                            index.index.insert(Arc::from(t), postings);
+                        }
+                    }
+                }
+            }
+            if let Some((t, postings)) = current {
+                index.index.insert(Arc::from(t), postings);
+            }
+        }
+        Some(filter) => {
+            let tokens: Vec<String> = match filter {
+                TokenFilter::Exact(terms) => terms.to_vec(),
+                TokenFilter::Containing(terms) => {
+                    let mut stmt = conn.prepare("SELECT token FROM tokens WHERE instr(token, ?1) > 0")?;
+                    let mut found = HashSet::new();
+                    for term in terms.iter() {
+                        for token in stmt.query_map([term], |r| r.get::<_, String>(0))? {
+                            found.insert(token?);
+                        }
+                    }
+                    found.into_iter().collect()
+                }
+            };
+            let mut stmt = conn.prepare("SELECT file_id, lines FROM postings WHERE token = ?1 ORDER BY file_id")?;
+            for token in tokens {
+                let postings: Vec<Posting> = stmt.query_map([&token], |r| Ok(Posting {
+                    file_id: r.get(0)?,
+                    lines: blob_lines(r.get_ref(1)?.as_blob()?),
+                }))?.collect::<rusqlite::Result<_>>()?;
+                if !postings.is_empty() {
+                    index.index.insert(Arc::from(token), postings);
+                }
+            }
+        }
+    }
+    Ok(index)
+}
+
+// ─── Definition index ────────────────────────────────────────────────
+
+const DEFINITION_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS files (id INTEGER PRIMARY KEY, path TEXT NOT NULL, mapped INTEGER NOT NULL, extras BLOB NOT NULL, fingerprint INTEGER NOT NULL);
+CREATE TABLE IF NOT EXISTS definitions (id INTEGER PRIMARY KEY, file_id INTEGER NOT NULL, name TEXT NOT NULL, kind TEXT NOT NULL, line_start INTEGER NOT NULL, line_end INTEGER NOT NULL, parent TEXT, signature TEXT, modifiers TEXT NOT NULL, attributes TEXT NOT NULL, base_types TEXT NOT NULL, live INTEGER NOT NULL);
+CREATE INDEX IF NOT EXISTS definitions_name ON definitions(name COLLATE NOCASE);
+CREATE INDEX IF NOT EXISTS definitions_kind ON definitions(kind);
+CREATE INDEX IF NOT EXISTS definitions_file ON definitions(file_id);
+CREATE INDEX IF NOT EXISTS definitions_parent ON definitions(parent COLLATE NOCASE);
+CREATE TABLE IF NOT EXISTS call_sites (caller_id INTEGER NOT NULL, seq INTEGER NOT NULL, method_name TEXT NOT NULL, receiver_type TEXT, receiver TEXT, line INTEGER NOT NULL, column INTEGER NOT NULL, end_column INTEGER NOT NULL, receiver_is_generic INTEGER NOT NULL, arg_count INTEGER, PRIMARY KEY (caller_id, seq)) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS call_sites_method ON call_sites(method_name COLLATE NOCASE);
+CREATE TABLE IF NOT EXISTS code_stats (def_id INTEGER PRIMARY KEY, cyclomatic INTEGER NOT NULL, cognitive INTEGER NOT NULL, max_nesting INTEGER NOT NULL, params INTEGER NOT NULL, returns INTEGER NOT NULL, calls INTEGER NOT NULL, lambdas INTEGER NOT NULL, halstead_volume REAL NOT NULL, halstead_difficulty REAL NOT NULL, maintainability_index INTEGER NOT NULL, members_used TEXT NOT NULL);
+";
+
+/// Imports and doc comments of one file, as stored in `files.extras`.
+type FileExtras = (Option<FileImports>, Vec<DocComment>);
+
+/// Save a definition index to the database at `path`, rewriting the definitions, call
+/// sites and code stats of the files whose fingerprint changed. Returns the number of
+/// files rewritten.
+pub fn save_definition_index(path: &Path, index: &DefinitionIndex) -> Result<usize, SearchError> {
+    let start = Instant::now();
+    let mut conn = open_write(path).map_err(save_error(path))?;
+    let written = (|| {
+        let tx = conn.transaction()?;
+        init(&tx, "definitions", DEFINITION_SCHEMA)?;
+        let written = write_definitions(&tx, index)?;
+        write_header(&tx, &index.root, &index.extensions, index.created_at, &definition_header(index))?;
+        tx.commit()?;
+        Ok(written)
+    })().map_err(save_error(path))?;
+    finish_save(path, "definition-index", start, written, index.files.len());
+    Ok(written)
+}
+
+fn write_definitions(tx: &Transaction, index: &DefinitionIndex) -> rusqlite::Result<usize> {
+    let file_count = index.files.len();
+    let def_count = index.definitions.len();
+    let stored = fingerprints(tx, "files", "id")?;
+    let stored_defs: i64 = tx.query_row("SELECT COUNT(*) FROM definitions", [], |r| r.get(0))?;
+
+    let mut changed: Vec<(u32, bool, Vec<u8>, i64)> = Vec::new();
+    for (id, file) in index.files.iter().enumerate() {
+        let id = id as u32;
+        let mapped = index.path_to_id.get(Path::new(file)) == Some(&id);
+        let extras: FileExtras = (index.file_imports.get(&id).cloned(), index.doc_comments.get(&id).cloned().unwrap_or_default());
+        let blob = encode(&extras)?;
+        let mut fingerprint = stable_hash(&[file.as_bytes(), &[mapped as u8], &blob]);
+        for &def_idx in index.file_index.get(&id).into_iter().flatten() {
+            let rows = (
+                &index.definitions[def_idx as usize],
+                index.method_calls.get(&def_idx),
+                index.code_stats.get(&def_idx),
+            );
+            fingerprint = fingerprint.wrapping_add(stable_hash(&[&def_idx.to_le_bytes(), &encode(&rows)?]));
+        }
+        if stored.get(&id) != Some(&(fingerprint as i64)) {
+            changed.push((id, mapped, blob, fingerprint as i64));
+        }
+    }
+
+    // Definitions to write: those of the changed files, those the changed files had
+    // before, and those added since the last save
+    let mut dirty: HashSet<u32> = (stored_defs.max(0) as u32..def_count as u32).collect();
+    {
+        let mut previous = tx.prepare("SELECT id FROM definitions WHERE file_id = ?1 AND live = 1")?;
+        for (id, ..) in &changed {
+            dirty.extend(index.file_index.get(id).into_iter().flatten().copied());
+            for def_idx in previous.query_map([id], |r| r.get::<_, u32>(0))? {
+                dirty.insert(def_idx?);
+            }
+        }
+    }
+    dirty.retain(|&d| (d as usize) < def_count);
+    let live: HashSet<u32> = index.file_index.values().flatten().copied().collect();
+
+    tx.execute("DELETE FROM definitions WHERE id >= ?1", [def_count as i64])?;
+    tx.execute("DELETE FROM call_sites WHERE caller_id >= ?1", [def_count as i64])?;
+    tx.execute("DELETE FROM code_stats WHERE def_id >= ?1", [def_count as i64])?;
+    tx.execute("DELETE FROM files WHERE id >= ?1", [file_count as i64])?;
+    {
+        let mut upsert_def = tx.prepare("INSERT OR REPLACE INTO definitions (id, file_id, name, kind, line_start, line_end, parent, signature, modifiers, attributes, base_types, live) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)")?;
+        let mut delete_calls = tx.prepare("DELETE FROM call_sites WHERE caller_id = ?1")?;
+        let mut insert_call = tx.prepare("INSERT INTO call_sites (caller_id, seq, method_name, receiver_type, receiver, line, column, end_column, receiver_is_generic, arg_count) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)")?;
+        let mut delete_stats = tx.prepare("DELETE FROM code_stats WHERE def_id = ?1")?;
+        let mut insert_stats = tx.prepare("INSERT INTO code_stats (def_id, cyclomatic, cognitive, max_nesting, params, returns, calls, lambdas, halstead_volume, halstead_difficulty, maintainability_index, members_used) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)")?;
+        let mut dirty: Vec<u32> = dirty.into_iter().collect();
+        dirty.sort_unstable();
+        for def_idx in dirty {
+            let d = &index.definitions[def_idx as usize];
+            upsert_def.execute(params![
+                def_idx, d.file_id, d.name, d.kind.as_str(), d.line_start, d.line_end, d.parent, d.signature,
+                json_list(&d.modifiers), json_list(&d.attributes), json_list(&d.base_types), live.contains(&def_idx),
+            ])?;
+            delete_calls.execute([def_idx])?;
+            for (seq, c) in index.method_calls.get(&def_idx).into_iter().flatten().enumerate() {
+                insert_call.execute(params![
+                    def_idx, seq as i64, c.method_name, c.receiver_type, c.receiver, c.line, c.column, c.end_column,
+                    c.receiver_is_generic, c.arg_count,
+                ])?;
+            }
+            delete_stats.execute([def_idx])?;
+            if let Some(s) = index.code_stats.get(&def_idx) {
+                insert_stats.execute(params![
+                    def_idx, s.cyclomatic_complexity, s.cognitive_complexity, s.max_nesting_depth, s.param_count,
+                    s.return_count, s.call_count, s.lambda_count, s.halstead_volume, s.halstead_difficulty,
+                    s.maintainability_index, json_list(&s.members_used),
+                ])?;
+            }
+        }
+        let mut upsert_file = tx.prepare("INSERT OR REPLACE INTO files (id, path, mapped, extras, fingerprint) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+        for (id, mapped, blob, fingerprint) in &changed {
+            upsert_file.execute(params![id, index.files[*id as usize], mapped, blob, fingerprint])?;
+        }
+    }
+    Ok(changed.len())
+}
+
+/// `index` without its per-file and per-definition data, for the `meta` header.
+fn definition_header(index: &DefinitionIndex) -> DefinitionIndex {
+    DefinitionIndex {
+        root: index.root.clone(),
+        created_at: index.created_at,
+        extensions: index.extensions.clone(),
+        files: Vec::new(),
+        definitions: Vec::new(),
+        name_index: HashMap::new(),
+        kind_index: HashMap::new(),
+        attribute_index: HashMap::new(),
+        base_type_index: HashMap::new(),
+        file_index: HashMap::new(),
+        path_to_id: HashMap::new(),
+        method_calls: HashMap::new(),
+        parse_errors: index.parse_errors,
+        lossy_file_count: index.lossy_file_count,
+        empty_file_ids: index.empty_file_ids.clone(),
+        code_stats: HashMap::new(),
+        extension_methods: index.extension_methods.clone(),
+        selector_index: index.selector_index.clone(),
+        template_children: index.template_children.clone(),
+        walk_limits: index.walk_limits.clone(),
+        file_imports: HashMap::new(),
+        di_registrations: index.di_registrations.clone(),
+        endpoints: index.endpoints.clone(),
+        partial_types: index.partial_types.clone(),
+        doc_comments: HashMap::new(),
+        imports: index.imports.clone(),
+    }
+}
+
+/// Load the definition index in the database at `path`. The name, kind, attribute,
+/// base-type and file lookups are rebuilt from the live definitions.
+pub fn load_definition_index(path: &Path) -> Result<DefinitionIndex, SearchError> {
+    let start = Instant::now();
+    let conn = open_read(path).map_err(load_error(path))?;
+    let index = read_definitions(&conn).map_err(load_error(path))?;
+    eprintln!("[definition-index] Loaded {} definitions from SQLite in {:.3}s",
+        index.definitions.len(), start.elapsed().as_secs_f64());
+    Ok(index)
+}
+
+fn read_definitions(conn: &Connection) -> rusqlite::Result<DefinitionIndex> {
+    let mut index: DefinitionIndex = read_header(conn, "definitions")?;
+    {
+        let mut stmt = conn.prepare("SELECT id, path, mapped, extras FROM files ORDER BY id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: u32 = row.get(0)?;
+            if id as usize != index.files.len() {
+                return Err(rusqlite::Error::InvalidParameterName(format!("file id {} is missing", index.files.len())));
+            }
+            let path: String = row.get(1)?;
+            if row.get::<_, bool>(2)? {
+                index.path_to_id.insert(PathBuf::from(&path), id);
+            }
+            let (imports, docs): FileExtras = decode(&row.get::<_, Vec<u8>>(3)?)?;
+            if let Some(imports) = imports {
+                index.file_imports.insert(id, imports);
+            }
+            if !docs.is_empty() {
+                index.doc_comments.insert(id, docs);
+            }
+            index.files.push(path);
+        }
+    }
+    {
+        let mut stmt = conn.prepare("SELECT id, file_id, name, kind, line_start, line_end, parent, signature, modifiers, attributes, base_types, live FROM definitions ORDER BY id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: u32 = row.get(0)?;
+            if id as usize != index.definitions.len() {
+                return Err(rusqlite::Error::InvalidParameterName(format!("definition id {} is missing", index.definitions.len())));
+            }
+            let kind: String = row.get(3)?;
+            let def = DefinitionEntry {
+                file_id: row.get(1)?,
+                name: row.get(2)?,
+                kind: kind.parse().map_err(|e: String| rusqlite::Error::InvalidParameterName(e))?,
+                line_start: row.get(4)?,
+                line_end: row.get(5)?,
+                parent: row.get(6)?,
+                signature: row.get(7)?,
+                modifiers: parse_json(row.get_ref(8)?.as_str()?)?,
+                attributes: parse_json(row.get_ref(9)?.as_str()?)?,
+                base_types: parse_json(row.get_ref(10)?.as_str()?)?,
+            };
+            if row.get::<_, bool>(11)? {
+                crate::definitions::index_definition(&mut index, def.file_id, id, &def);
+            }
+            index.definitions.push(def);
+        }
+    }
+    // Angular selectors are looked up by name too (see `build_definition_index`)
+    for (selector, ids) in &index.selector_index {
+        index.name_index.entry(selector.to_lowercase()).or_default().extend(ids);
+    }
+    {
+        let mut stmt = conn.prepare("SELECT caller_id, method_name, receiver_type, receiver, line, column, end_column, receiver_is_generic, arg_count FROM call_sites ORDER BY caller_id, seq")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let call = CallSite {
+                method_name: row.get(1)?,
+                receiver_type: row.get(2)?,
+                receiver: row.get(3)?,
+                line: row.get(4)?,
+                column: row.get(5)?,
+                end_column: row.get(6)?,
+                receiver_is_generic: row.get(7)?,
+                arg_count: row.get(8)?,
+            };
+            index.method_calls.entry(row.get(0)?).or_default().push(call);
+        }
+    }
+    {
+        let mut stmt = conn.prepare("SELECT def_id, cyclomatic, cognitive, max_nesting, params, returns, calls, lambdas, halstead_volume, halstead_difficulty, maintainability_index, members_used FROM code_stats")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let stats = CodeStats {
+                cyclomatic_complexity: row.get(1)?,
+                cognitive_complexity: row.get(2)?,
+                max_nesting_depth: row.get(3)?,
+                param_count: row.get(4)?,
+                return_count: row.get(5)?,
+                call_count: row.get(6)?,
+                lambda_count: row.get(7)?,
+                halstead_volume: row.get::<_, f64>(8)? as f32,
+                halstead_difficulty: row.get::<_, f64>(9)? as f32,
+                maintainability_index: row.get(10)?,
+                members_used: parse_json(row.get_ref(11)?.as_str()?)?,
+            };
+            index.code_stats.insert(row.get(0)?, stats);
+        }
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::definitions::{build_definition_index, update_file_definitions, DefIndexArgs};
+    use crate::ContentIndexArgs;
+
+    /// token → sorted (path, lines), independent of file numbering
+    fn postings_by_path(index: &ContentIndex) -> BTreeMap<String, Vec<(String, Vec<u32>)>> {
+        index.index.iter().map(|(token, postings)| {
+            let mut by_path: Vec<(String, Vec<u32>)> = postings.iter()
+                .map(|p| (index.files[p.file_id as usize].clone(), p.lines.clone()))
+                .collect();
+            by_path.sort();
+            (token.to_string(), by_path)
+        }).collect()
+    }
+
+    fn content_args(dir: &Path) -> ContentIndexArgs {
+        ContentIndexArgs {
+            dir: dir.to_string_lossy().to_string(), ext: "cs".to_string(), max_age_hours: 24,
+            hidden: false, no_ignore: false, threads: 1, min_token_len: 2, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0,
+            watch: false,
+            save_interval_secs: 60,
+            max_file_size: 0,
+            spill_mb: 0,
+            shards: 0,
+            max_token_len: 0,
+            stop_token_pct: 0,
+            checkpoint_secs: 0,
+            max_depth: 0,
+            prune_dir: Vec::new(),
+            storage: Some(Storage::Sqlite),
+        }
+    }
+
+    fn write_sources(dir: &Path) {
+        std::fs::write(dir.join("Orders.cs"), "class OrderService { // TODO: cache orders\n    void Save() { Validate(); }\n    void Validate() { }\n}\n").unwrap();
+        std::fs::write(dir.join("Users.cs"), "class UserService {\n    void Load() { }\n}\n").unwrap();
+        std::fs::write(dir.join("Program.cs"), "class Program { OrderService orders; UserService users; }\n").unwrap();
+    }
+
+    #[test]
+    fn test_content_index_round_trip_and_saves_only_changed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_dir = tempfile::tempdir().unwrap();
+        write_sources(tmp.path());
+        let index = crate::build_content_index(&content_args(tmp.path()));
+        let path = db_dir.path().join("orders.word-search.sqlite");
+
+        assert_eq!(save_content_index(&path, &index).unwrap(), 3);
+        let loaded = load_content_index(&path).unwrap();
+        assert_eq!(loaded.files, index.files);
+        assert_eq!(postings_by_path(&loaded), postings_by_path(&index));
+        assert_eq!(loaded.file_token_counts, index.file_token_counts);
+        assert_eq!(loaded.comment_markers.len(), 1);
+        assert_eq!(loaded.total_tokens, index.total_tokens);
+        assert_eq!(loaded.trigram.tokens.len(), index.trigram.tokens.len());
+
+        // Unchanged: nothing rewritten. One posting changed: one file
+        assert_eq!(save_content_index(&path, &loaded).unwrap(), 0);
+        let mut changed = loaded;
+        changed.index.get_mut("program").unwrap()[0].lines.push(2);
+        assert_eq!(save_content_index(&path, &changed).unwrap(), 1);
+        assert_eq!(postings_by_path(&load_content_index(&path).unwrap()), postings_by_path(&changed));
+
+        // A token left without postings leaves the tokens table
+        changed.index.remove("program");
+        assert_eq!(save_content_index(&path, &changed).unwrap(), 1);
+        let conn = open_read(&path).unwrap();
+        let stale: i64 = conn.query_row("SELECT COUNT(*) FROM tokens WHERE token = 'program'", [], |r| r.get(0)).unwrap();
+        assert_eq!(stale, 0);
+
+        let summary = read_summary(&path).unwrap();
+        assert_eq!((summary.kind.as_str(), summary.files), ("content", 3));
+        assert_eq!(summary.root, index.root);
+        assert_eq!(summary.extensions, vec!["cs".to_string()]);
+    }
+
+    #[test]
+    fn test_load_content_tokens_reads_only_the_selected_postings() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_dir = tempfile::tempdir().unwrap();
+        write_sources(tmp.path());
+        let index = crate::build_content_index(&content_args(tmp.path()));
+        let path = db_dir.path().join("orders.word-search.sqlite");
+        save_content_index(&path, &index).unwrap();
+
+        let terms = vec!["orderservice".to_string(), "missing".to_string()];
+        let exact = load_content_tokens(&path, &TokenFilter::Exact(&terms)).unwrap();
+        assert_eq!(exact.index.keys().map(|t| t.to_string()).collect::<Vec<_>>(), vec!["orderservice"]);
+        assert_eq!(postings_by_path(&exact)["orderservice"], postings_by_path(&index)["orderservice"]);
+        assert_eq!(exact.files, index.files);
+        assert_eq!(exact.file_token_counts, index.file_token_counts);
+
+        let terms = vec!["service".to_string()];
+        let containing = load_content_tokens(&path, &TokenFilter::Containing(&terms)).unwrap();
+        let mut tokens: Vec<String> = containing.index.keys().map(|t| t.to_string()).collect();
+        tokens.sort();
+        assert_eq!(tokens, vec!["orderservice", "userservice"]);
+        assert!(containing.trigram.tokens.iter().all(|t| t.contains("service")));
+    }
+
+    #[test]
+    fn test_definition_index_keeps_ids_across_incremental_updates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_dir = tempfile::tempdir().unwrap();
+        write_sources(tmp.path());
+        let mut index = build_definition_index(&DefIndexArgs {
+            dir: tmp.path().to_string_lossy().to_string(), ext: "cs".to_string(), threads: 1, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, io_limit_mb: 0, max_depth: 0, prune_dir: Vec::new(),
+            storage: Some(Storage::Sqlite), import: Vec::new(),
+        });
+        let path = db_dir.path().join("orders.code-structure.sqlite");
+        assert_eq!(save_definition_index(&path, &index).unwrap(), 3);
+        let loaded = load_definition_index(&path).unwrap();
+        assert_eq!(loaded.definitions.len(), index.definitions.len());
+        assert_eq!(loaded.name_index["validate"], index.name_index["validate"]);
+        assert_eq!(loaded.method_calls.values().map(Vec::len).sum::<usize>(), index.method_calls.values().map(Vec::len).sum::<usize>());
+        assert_eq!(loaded.code_stats.len(), index.code_stats.len());
+        assert_eq!(loaded.path_to_id, index.path_to_id);
+
+        // Validate() is removed: its row stays, no longer live, and the others keep their ids
+        let orders = tmp.path().join("Orders.cs");
+        std::fs::write(&orders, "class OrderService {\n    void Save() { }\n}\n").unwrap();
+        let orders = std::fs::canonicalize(&orders).unwrap();
+        let (user_service, users_def) = (index.name_index["userservice"].clone(), index.name_index["load"].clone());
+        update_file_definitions(&mut index, &orders);
+        assert_eq!(save_definition_index(&path, &index).unwrap(), 1);
+        let updated = load_definition_index(&path).unwrap();
+        assert!(!updated.name_index.contains_key("validate"));
+        assert_eq!(updated.name_index["userservice"], user_service);
+        assert_eq!(updated.name_index["load"], users_def);
+        assert_eq!(updated.definitions.len(), index.definitions.len());
+        let mut saves = updated.name_index["save"].clone();
+        saves.sort_unstable();
+        let mut expected = index.name_index["save"].clone();
+        expected.sort_unstable();
+        assert_eq!(saves, expected);
+        let conn = open_read(&path).unwrap();
+        let dead: i64 = conn.query_row("SELECT COUNT(*) FROM definitions WHERE live = 0 AND name = 'Validate'", [], |r| r.get(0)).unwrap();
+        assert_eq!(dead, 1);
+    }
+
+    #[test]
+    fn test_storage_follows_the_format_on_disk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let idx_base = tempfile::tempdir().unwrap();
+        write_sources(tmp.path());
+        let args = content_args(tmp.path());
+        let index = crate::build_content_index(&args);
+        let file = crate::index::content_index_path_for(&index.root, "cs", idx_base.path());
+
+        crate::index::save_content_index_as(&index, idx_base.path(), Some(Storage::Sqlite)).unwrap();
+        assert!(!file.exists());
+        assert_eq!(storage_of(&file), Storage::Sqlite);
+        assert_eq!(stored_path(&file), database_path(&file));
+        assert!(is_database_of(&database_path(&file), "word-search"));
+        // Later saves keep the format; loads and the directory scan find the database
+        crate::save_content_index(&index, idx_base.path()).unwrap();
+        assert!(!file.exists());
+        let loaded = crate::load_content_index(&args.dir, "cs", idx_base.path()).unwrap();
+        assert_eq!(postings_by_path(&loaded), postings_by_path(&index));
+        assert!(crate::index::find_content_index_for_dir(&args.dir, idx_base.path()).is_some());
+        assert_eq!(crate::index::content_database_for(&args.dir, "", idx_base.path()), Some(database_path(&file)));
+
+        crate::index::save_content_index_as(&index, idx_base.path(), Some(Storage::Bincode)).unwrap();
+        assert!(file.exists());
+        assert_eq!(storage_of(&file), Storage::Bincode);
+        assert_eq!(crate::index::content_database_for(&args.dir, "cs", idx_base.path()), None);
+
+        // File index
+        let files = crate::build_index(&crate::IndexArgs {
+            dir: args.dir.clone(), max_age_hours: 24, hidden: false, no_ignore: false, threads: 1, dry_run: false,
+            build_priority: crate::priority::BuildPriority::Normal, storage: Some(Storage::Sqlite),
+        });
+        crate::index::save_index_as(&files, idx_base.path(), Some(Storage::Sqlite)).unwrap();
+        let mut expected: Vec<String> = files.entries.iter().map(|e| e.path.clone()).collect();
+        expected.sort();
+        let loaded = crate::index::load_index(&args.dir, idx_base.path()).unwrap();
+        assert_eq!(loaded.entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>(), expected);
+        assert_eq!(loaded.root, files.root);
+        assert_eq!(crate::index::cleanup_indexes_for_dir(&args.dir, idx_base.path()), 2);
+        assert!(!database_path(&crate::index::index_path_for(&args.dir, idx_base.path())).exists());
+    }
+}
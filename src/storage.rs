@@ -0,0 +1,175 @@
+//! On-disk container of index files.
+//!
+//! An index file is a 24-byte header followed by the LZ4-compressed bincode payload.
+//! The header holds magic bytes, a format version, the payload length and its XXH64
+//! checksum, so a truncated or corrupt file, or one written by a newer build, is
+//! reported as such instead of misread. Files of the two earlier formats (a headerless
+//! LZ4 frame, and plain bincode) are still read.
+//!
+//! This module only reads and writes one file. The CLI adds the write lock, the atomic
+//! rename over the previous file and the timing logs around it.
+
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Magic bytes of the previous index format: LZ4 frame with no header or checksum.
+/// Still read; no longer written.
+pub const LZ4_MAGIC: &[u8; 4] = b"LZ4S";
+
+/// Magic bytes identifying index files with a header.
+pub const INDEX_MAGIC: &[u8; 4] = b"SIXF";
+
+/// Version of the header and container layout. A file with a higher version was
+/// written by a newer build and is refused instead of misread. Index types whose
+/// payload layout changed write their own, higher version and read older ones with
+/// [`read_index_file`].
+pub const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// Header: magic, format version (u32 LE), payload length (u64 LE), and the XXH64
+/// checksum of the payload (u64 LE). The payload is the LZ4-compressed bincode data.
+pub const INDEX_HEADER_LEN: u64 = 24;
+
+/// Counts and checksums the bytes passing through to the inner writer or reader.
+struct Checksummed<T> {
+    inner: T,
+    hasher: twox_hash::XxHash64,
+    len: u64,
+}
+
+impl<T> Checksummed<T> {
+    fn new(inner: T) -> Self {
+        Self { inner, hasher: twox_hash::XxHash64::with_seed(0), len: 0 }
+    }
+
+    fn checksum(&self) -> u64 {
+        std::hash::Hasher::finish(&self.hasher)
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        std::hash::Hasher::write(&mut self.hasher, &buf[..n]);
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        std::hash::Hasher::write(&mut self.hasher, &buf[..n]);
+        self.len += n as u64;
+        Ok(n)
+    }
+}
+
+/// Write header and payload to `path` and fsync it. The header's length and checksum
+/// are filled in once the payload is written. Replaces `path` in place: write to a
+/// temp file and rename it for an atomic save.
+pub fn write_index_file<T: serde::Serialize>(path: &Path, data: &T, version: u32) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(&[0u8; INDEX_HEADER_LEN as usize])?;
+    let mut encoder = lz4_flex::frame::FrameEncoder::new(Checksummed::new(BufWriter::new(file)));
+    bincode::serialize_into(&mut encoder, data).map_err(io::Error::other)?;
+    let payload = encoder.finish().map_err(io::Error::other)?;
+    let (len, checksum) = (payload.len, payload.checksum());
+    let mut file = payload.inner.into_inner().map_err(|e| e.into_error())?;
+
+    let mut header = Vec::with_capacity(INDEX_HEADER_LEN as usize);
+    header.extend_from_slice(INDEX_MAGIC);
+    header.extend_from_slice(&version.to_le_bytes());
+    header.extend_from_slice(&len.to_le_bytes());
+    header.extend_from_slice(&checksum.to_le_bytes());
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&header)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Uncompressed bincode payload handed to a [`read_index_file`] decoder.
+pub struct Payload<'a> {
+    reader: &'a mut dyn Read,
+    /// Size of an uncompressed legacy file: lengths past it are corrupt and are
+    /// refused before allocating
+    limit: Option<u64>,
+}
+
+impl Payload<'_> {
+    pub fn deserialize<T: serde::de::DeserializeOwned>(self) -> bincode::Result<T> {
+        use bincode::Options;
+        let options = bincode::DefaultOptions::new().with_fixint_encoding().allow_trailing_bytes();
+        match self.limit {
+            Some(limit) => options.with_limit(limit).deserialize_from(self.reader),
+            None => options.deserialize_from(self.reader),
+        }
+    }
+}
+
+/// Read an index file of any format: files up to `max_version` are accepted and
+/// `decode` is given the file's version to pick the payload layout. Headerless legacy
+/// files have version 0. Errors carry a message saying what is wrong with the file
+/// (truncated, corrupt, too new) and that the index should be rebuilt.
+pub fn read_index_file<T>(
+    path: &Path,
+    max_version: u32,
+    decode: impl FnOnce(u32, Payload) -> bincode::Result<T>,
+) -> io::Result<T> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let compressed_size = fs::metadata(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("file not found or inaccessible: {}", e)))?
+        .len();
+
+    let file = fs::File::open(path).map_err(|e| io::Error::new(e.kind(), format!("cannot open file: {}", e)))?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic).map_err(|e| io::Error::new(e.kind(), format!("read error (magic bytes): {}", e)))?;
+
+    if &magic == INDEX_MAGIC {
+        let mut header = [0u8; INDEX_HEADER_LEN as usize - 4];
+        reader.read_exact(&mut header)
+            .map_err(|_| invalid("truncated header (interrupted write?); rebuild the index".to_string()))?;
+        let version = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        let len = u64::from_le_bytes(header[4..12].try_into().unwrap());
+        let checksum = u64::from_le_bytes(header[12..20].try_into().unwrap());
+        if version > max_version {
+            return Err(invalid(format!(
+                "format version {} is newer than this build reads ({}); upgrade search or rebuild the index",
+                version, max_version)));
+        }
+        let actual = compressed_size.saturating_sub(INDEX_HEADER_LEN);
+        if actual != len {
+            return Err(invalid(format!(
+                "truncated or corrupt: header says {} payload bytes, file has {} (interrupted write?); rebuild the index",
+                len, actual)));
+        }
+        let mut decoder = lz4_flex::frame::FrameDecoder::new(Checksummed::new(reader));
+        let decoded = decode(version, Payload { reader: &mut decoder, limit: None });
+        // Checksum the whole payload, including what the decoder did not need
+        let mut payload = decoder.into_inner();
+        io::copy(&mut payload, &mut io::sink())
+            .map_err(|e| io::Error::new(e.kind(), format!("read error: {}", e)))?;
+        if payload.checksum() != checksum {
+            return Err(invalid("checksum mismatch: the file is corrupt; rebuild the index".to_string()));
+        }
+        decoded.map_err(|e| invalid(format!("deserialization failed: {}", e)))
+    } else if &magic == LZ4_MAGIC {
+        // Headerless compressed format
+        let mut decoder = lz4_flex::frame::FrameDecoder::new(reader);
+        decode(0, Payload { reader: &mut decoder, limit: None })
+            .map_err(|e| invalid(format!("LZ4 deserialization failed: {}", e)))
+    } else {
+        // Legacy uncompressed format
+        reader.seek(SeekFrom::Start(0)).map_err(|e| io::Error::new(e.kind(), format!("seek error: {}", e)))?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(|e| io::Error::new(e.kind(), format!("read error: {}", e)))?;
+        decode(0, Payload { reader: &mut data.as_slice(), limit: Some(data.len() as u64) })
+            .map_err(|e| invalid(format!("deserialization failed: {}", e)))
+    }
+}
@@ -442,7 +442,7 @@ pub fn render_instructions() -> String {
     }
 
     // --- Git tools (brief mention) ---
-    out.push_str("\nGit tools: search_git_history, search_git_authors, search_git_activity, search_git_blame, search_branch_status -- use for code history/blame/authorship investigations. Call search_help for details.\n");
+    out.push_str("\nGit tools: search_git_history, search_git_authors, search_git_activity, search_git_blame, search_git_grep, search_git_added_lines, search_hotspots, search_tickets, search_branch_status -- use for code history/blame/authorship investigations. Call search_help for details.\n");
 
     // --- Soft reference to search_help (Phase 4: no urgency) ---
     out.push_str("\nCall search_help for detailed best practices with examples.\n");